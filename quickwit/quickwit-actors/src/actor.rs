@@ -550,6 +550,30 @@ impl<A: Actor> ActorContext<A> {
             .send_message(&self.inner.scheduler_mailbox, scheduler_msg)
             .await;
     }
+
+    /// Similar to `schedule_self_msg`, except that if a message of the same type scheduled with
+    /// this method is still pending in the mailbox when the timer fires, it is replaced by this
+    /// message rather than queued a second time.
+    ///
+    /// This is meant for idempotent "refresh" messages, such as observe or supervise loops, so
+    /// that they do not pile up when the actor falls behind.
+    pub async fn schedule_self_msg_coalesced<M>(&self, after_duration: Duration, message: M)
+    where
+        A: Handler<M>,
+        M: Sync + Send + std::fmt::Debug + 'static,
+    {
+        let self_mailbox = self.inner.self_mailbox.clone();
+        let callback = Callback(Box::pin(async move {
+            let _ = self_mailbox.send_coalesced_message_with_high_priority(message);
+        }));
+        let scheduler_msg = ScheduleEvent {
+            timeout: after_duration,
+            callback,
+        };
+        let _ = self
+            .send_message(&self.inner.scheduler_mailbox, scheduler_msg)
+            .await;
+    }
 }
 
 #[async_trait::async_trait]
@@ -17,11 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::any::Any;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
 use std::convert::Infallible;
 use std::fmt;
+use std::marker::PhantomData;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Weak};
+use std::sync::{Arc, Mutex, Weak};
 
 use async_trait::async_trait;
 use tokio::sync::oneshot;
@@ -129,6 +131,8 @@ impl<A: Actor> Mailbox<A> {
 pub(crate) struct Inner<A: Actor> {
     pub(crate) tx: Sender<Envelope<A>>,
     instance_id: String,
+    /// Latest value of the coalesced messages pending in the queue, by message type.
+    coalesced_messages: Mutex<HashMap<TypeId, Box<dyn Any + Send>>>,
 }
 
 impl<A: Actor> fmt::Debug for Mailbox<A> {
@@ -175,6 +179,38 @@ impl<A: Actor> Mailbox<A> {
         self.inner.tx.send_high_priority(envelope)
     }
 
+    /// Sends a message with high priority, unless a message of the same type sent with this method
+    /// is still pending. In that case, the pending message is replaced by this one, so that at
+    /// most one message of this type is ever queued and only the latest one is processed.
+    pub(crate) fn send_coalesced_message_with_high_priority<M>(
+        &self,
+        message: M,
+    ) -> Result<(), SendError>
+    where
+        A: Handler<M>,
+        M: 'static + Send + Sync + fmt::Debug,
+    {
+        let mut coalesced_messages = self.inner.coalesced_messages.lock().unwrap();
+        let type_id = TypeId::of::<M>();
+        if let Some(pending_message) = coalesced_messages.get_mut(&type_id) {
+            *pending_message = Box::new(message);
+            return Ok(());
+        }
+        self.send_message_with_high_priority(Coalesced::<M>(PhantomData))?;
+        coalesced_messages.insert(type_id, Box::new(message));
+        Ok(())
+    }
+
+    fn take_coalesced_message<M: 'static>(&self) -> Option<M> {
+        let pending_message = self
+            .inner
+            .coalesced_messages
+            .lock()
+            .unwrap()
+            .remove(&TypeId::of::<M>())?;
+        pending_message.downcast::<M>().ok().map(|message| *message)
+    }
+
     /// Similar to `send_message`, except this method
     /// waits asynchronously for the actor reply.
     ///
@@ -209,6 +245,36 @@ impl<A: Actor> Mailbox<A> {
     }
 }
 
+/// Placeholder queued for a coalesced message of type `M`. The message itself is held by the
+/// mailbox until the placeholder is processed, so that it can be replaced in the meantime.
+struct Coalesced<M>(PhantomData<fn() -> M>);
+
+impl<M> fmt::Debug for Coalesced<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Coalesced<{}>", std::any::type_name::<M>())
+    }
+}
+
+#[async_trait]
+impl<A, M> Handler<Coalesced<M>> for A
+where
+    A: Handler<M>,
+    M: 'static + Send + Sync + fmt::Debug,
+{
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Coalesced<M>,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(message) = ctx.mailbox().take_coalesced_message::<M>() {
+            Handler::<M>::handle(self, message, ctx).await?;
+        }
+        Ok(())
+    }
+}
+
 pub struct Inbox<A: Actor> {
     rx: Arc<Receiver<Envelope<A>>>,
 }
@@ -277,6 +343,7 @@ pub fn create_mailbox<A: Actor>(
         inner: Arc::new(Inner {
             tx,
             instance_id: quickwit_common::new_coolid(&actor_name),
+            coalesced_messages: Mutex::default(),
         }),
         ref_count,
     };
@@ -304,7 +371,7 @@ impl<A: Actor> WeakMailbox<A> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests::PingReceiverActor;
+    use crate::tests::{Ping, PingReceiverActor};
 
     #[test]
     fn test_weak_mailbox_downgrade_upgrade() {
@@ -313,6 +380,27 @@ mod tests {
         assert!(weak_mailbox.upgrade().is_some());
     }
 
+    #[test]
+    fn test_mailbox_coalesced_messages() {
+        let (mailbox, inbox) = create_test_mailbox::<PingReceiverActor>();
+        mailbox
+            .send_coalesced_message_with_high_priority(Ping)
+            .unwrap();
+        mailbox
+            .send_coalesced_message_with_high_priority(Ping)
+            .unwrap();
+        assert!(inbox.try_recv().is_ok());
+        assert_eq!(inbox.try_recv().unwrap_err(), RecvError::NoMessageAvailable);
+        assert!(mailbox.take_coalesced_message::<Ping>().is_some());
+        assert!(mailbox.take_coalesced_message::<Ping>().is_none());
+
+        // Once the pending message is consumed, the next one is queued again.
+        mailbox
+            .send_coalesced_message_with_high_priority(Ping)
+            .unwrap();
+        assert!(inbox.try_recv().is_ok());
+    }
+
     #[test]
     fn test_weak_mailbox_failing_upgrade() {
         let (mailbox, _inbox) = create_test_mailbox::<PingReceiverActor>();
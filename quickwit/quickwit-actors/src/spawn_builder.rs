@@ -253,8 +253,14 @@ impl<A: Actor> ActorExecutionEnv<A> {
         match &exit_status {
             ActorExitStatus::Success
             | ActorExitStatus::Quit
-            | ActorExitStatus::DownstreamClosed
-            | ActorExitStatus::Killed => {}
+            | ActorExitStatus::DownstreamClosed => {}
+            ActorExitStatus::Killed => {
+                info!(
+                    actor_id=%self.ctx.actor_instance_id(),
+                    killed_by=?self.ctx.kill_switch().killed_by(),
+                    "actor-killed"
+                );
+            }
             ActorExitStatus::Failure(err) => {
                 error!(cause=?err, exit_status=?exit_status, "actor-failure");
             }
@@ -61,7 +61,8 @@ impl<A: Actor> Actor for Supervisor<A> {
     }
 
     async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
-        ctx.schedule_self_msg(crate::HEARTBEAT, SuperviseLoop).await;
+        ctx.schedule_self_msg_coalesced(crate::HEARTBEAT, SuperviseLoop)
+            .await;
         Ok(())
     }
 
@@ -174,7 +175,8 @@ impl<A: Actor> Handler<SuperviseLoop> for Supervisor<A> {
         ctx: &ActorContext<Self>,
     ) -> Result<Self::Reply, ActorExitStatus> {
         self.supervise(ctx).await?;
-        ctx.schedule_self_msg(crate::HEARTBEAT, SuperviseLoop).await;
+        ctx.schedule_self_msg_coalesced(crate::HEARTBEAT, SuperviseLoop)
+            .await;
         Ok(())
     }
 }
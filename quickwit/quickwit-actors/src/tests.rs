@@ -583,6 +583,20 @@ async fn test_actor_simple_sleep() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_actor_coalesced_message() -> anyhow::Result<()> {
+    let universe = Universe::new();
+    let adder = Adder::default();
+    let (mailbox, handle) = universe.spawn_builder().spawn(adder);
+    mailbox.send_coalesced_message_with_high_priority(AddOperand(2))?;
+    let total = *handle.process_pending_and_observe().await;
+    assert_eq!(total, 2);
+    mailbox.send_coalesced_message_with_high_priority(AddOperand(3))?;
+    let total = *handle.process_pending_and_observe().await;
+    assert_eq!(total, 5);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_actor_sleep_and_resume() -> anyhow::Result<()> {
     quickwit_common::setup_logging_for_tests();
@@ -42,7 +42,7 @@ impl Universe {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Universe {
         let scheduler = Scheduler::default();
-        let kill_switch = KillSwitch::default();
+        let kill_switch = KillSwitch::named("universe");
         let (fake_mailbox, _inbox) =
             crate::create_mailbox("fake-mailbox".to_string(), QueueCapacity::Unbounded);
         let registry = ActorRegistry::default();
@@ -71,6 +71,13 @@ impl Universe {
         self.kill_switch.kill();
     }
 
+    /// Kills the actors of the kill switch domain `domain` only, for instance
+    /// `universe/indexing-pipeline:my-index:my-source:0`, and returns the number of kill switches
+    /// activated.
+    pub fn kill_domain(&self, domain: &str) -> usize {
+        self.kill_switch.kill_domain(domain)
+    }
+
     /// Simulate advancing the time for unit tests.
     ///
     /// It is not just about jumping the clock and triggering one round of messages:
@@ -171,4 +178,25 @@ mod tests {
         // See comment on `universe.simulate_advance_time`.
         assert_eq!(count_after_advance_time, 4);
     }
+
+    #[tokio::test]
+    async fn test_universe_kill_domain() {
+        let universe = Universe::new();
+        let (_mailbox_a, handle_a) = universe
+            .spawn_builder()
+            .set_kill_switch(universe.kill_switch.named_child("a"))
+            .spawn(ActorWithSchedule::default());
+        let (_mailbox_b, handle_b) = universe
+            .spawn_builder()
+            .set_kill_switch(universe.kill_switch.named_child("b"))
+            .spawn(ActorWithSchedule::default());
+        handle_a.process_pending_and_observe().await;
+        handle_b.process_pending_and_observe().await;
+
+        assert_eq!(universe.kill_domain("universe/a"), 1);
+        universe.simulate_time_shift(Duration::from_secs(60)).await;
+        let (exit_status, _) = handle_a.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Killed));
+        assert_eq!(handle_b.process_pending_and_observe().await.state, 2);
+    }
 }
@@ -30,9 +30,11 @@ use rusoto_kinesis::{
 };
 use rusoto_s3::{
     AbortMultipartUploadError, CompleteMultipartUploadError, CreateMultipartUploadError,
-    DeleteObjectError, DeleteObjectsError, GetObjectError, HeadObjectError, PutObjectError,
-    UploadPartError,
+    DeleteObjectError, DeleteObjectsError, GetObjectError, HeadObjectError, ListObjectsV2Error,
+    PutObjectError, UploadPartError,
 };
+#[cfg(feature = "sqs")]
+use rusoto_sqs::{DeleteMessageBatchError, GetQueueAttributesError, ReceiveMessageError};
 
 use crate::retry::Retryable;
 
@@ -133,6 +135,12 @@ impl Retryable for HeadObjectError {
     }
 }
 
+impl Retryable for ListObjectsV2Error {
+    fn is_retryable(&self) -> bool {
+        false
+    }
+}
+
 #[cfg(feature = "kinesis")]
 impl Retryable for GetRecordsError {
     fn is_retryable(&self) -> bool {
@@ -202,3 +210,24 @@ impl Retryable for SplitShardError {
         false
     }
 }
+
+#[cfg(feature = "sqs")]
+impl Retryable for ReceiveMessageError {
+    fn is_retryable(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "sqs")]
+impl Retryable for DeleteMessageBatchError {
+    fn is_retryable(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "sqs")]
+impl Retryable for GetQueueAttributesError {
+    fn is_retryable(&self) -> bool {
+        false
+    }
+}
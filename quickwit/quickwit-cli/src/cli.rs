@@ -22,10 +22,13 @@ use clap::{Arg, ArgMatches, Command};
 use quickwit_config::DEFAULT_QW_CONFIG_PATH;
 use tracing::Level;
 
+use crate::doctor::{build_doctor_command, DoctorCliCommand};
 use crate::index::{build_index_command, IndexCliCommand};
+use crate::metastore::{build_metastore_command, MetastoreCliCommand};
 use crate::service::{build_run_command, RunCliCommand};
 use crate::source::{build_source_command, SourceCliCommand};
 use crate::split::{build_split_command, SplitCliCommand};
+use crate::tool::{build_tool_command, ToolCliCommand};
 
 pub fn build_cli<'a>() -> Command<'a> {
     Command::new("Quickwit")
@@ -42,6 +45,9 @@ pub fn build_cli<'a>() -> Command<'a> {
         .subcommand(build_index_command().display_order(2))
         .subcommand(build_source_command().display_order(3))
         .subcommand(build_split_command().display_order(4))
+        .subcommand(build_tool_command().display_order(5))
+        .subcommand(build_doctor_command().display_order(6))
+        .subcommand(build_metastore_command().display_order(7))
         .arg_required_else_help(true)
         .disable_help_subcommand(true)
         .subcommand_required(true)
@@ -53,6 +59,9 @@ pub enum CliCommand {
     Index(IndexCliCommand),
     Split(SplitCliCommand),
     Source(SourceCliCommand),
+    Tool(ToolCliCommand),
+    Doctor(DoctorCliCommand),
+    Metastore(MetastoreCliCommand),
 }
 
 impl CliCommand {
@@ -62,6 +71,9 @@ impl CliCommand {
             CliCommand::Index(subcommand) => subcommand.default_log_level(),
             CliCommand::Source(_) => Level::ERROR,
             CliCommand::Split(_) => Level::ERROR,
+            CliCommand::Tool(_) => Level::ERROR,
+            CliCommand::Doctor(_) => Level::ERROR,
+            CliCommand::Metastore(_) => Level::ERROR,
         }
     }
 
@@ -74,6 +86,11 @@ impl CliCommand {
             "run" => RunCliCommand::parse_cli_args(submatches).map(CliCommand::Run),
             "source" => SourceCliCommand::parse_cli_args(submatches).map(CliCommand::Source),
             "split" => SplitCliCommand::parse_cli_args(submatches).map(CliCommand::Split),
+            "tool" => ToolCliCommand::parse_cli_args(submatches).map(CliCommand::Tool),
+            "doctor" => DoctorCliCommand::parse_cli_args(submatches).map(CliCommand::Doctor),
+            "metastore" => {
+                MetastoreCliCommand::parse_cli_args(submatches).map(CliCommand::Metastore)
+            }
             _ => bail!("Subcommand `{}` is not implemented.", subcommand),
         }
     }
@@ -84,6 +101,9 @@ impl CliCommand {
             CliCommand::Run(subcommand) => subcommand.execute().await,
             CliCommand::Source(subcommand) => subcommand.execute().await,
             CliCommand::Split(subcommand) => subcommand.execute().await,
+            CliCommand::Tool(subcommand) => subcommand.execute().await,
+            CliCommand::Doctor(subcommand) => subcommand.execute().await,
+            CliCommand::Metastore(subcommand) => subcommand.execute().await,
         }
     }
 }
@@ -0,0 +1,295 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use clap::{arg, ArgMatches, Command};
+use quickwit_common::rand::append_random_suffix;
+use quickwit_common::run_checklist;
+use quickwit_common::uri::Uri;
+use quickwit_config::{IndexConfig, QuickwitConfig, SourceConfig};
+use quickwit_metastore::quickwit_metastore_uri_resolver;
+use quickwit_storage::{load_file, quickwit_storage_uri_resolver, Storage};
+use tracing::debug;
+
+/// Prefix of the file written, read, and deleted by the storage probe.
+const STORAGE_PROBE_FILE_PREFIX: &str = ".quickwit-doctor-probe";
+
+pub fn build_doctor_command<'a>() -> Command<'a> {
+    Command::new("doctor")
+        .about(
+            "Validates the node config and, optionally, index and source configs, then checks the \
+             metastore connectivity, the storage permissions, and the availability of the listen \
+             ports.",
+        )
+        .args(&[
+            arg!(--"index-config" <INDEX_CONFIG> "Location of an index config file to validate.")
+                .multiple_occurrences(true)
+                .required(false),
+            arg!(--"source-config" <SOURCE_CONFIG> "Location of a source config file to validate.")
+                .multiple_occurrences(true)
+                .required(false),
+        ])
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DoctorCliCommand {
+    pub config_uri: Uri,
+    pub index_config_uris: Vec<Uri>,
+    pub source_config_uris: Vec<Uri>,
+}
+
+impl DoctorCliCommand {
+    pub fn parse_cli_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_config_uris = matches
+            .values_of("index-config")
+            .map(|values| values.map(Uri::from_str).collect::<Result<Vec<_>, _>>())
+            .transpose()?
+            .unwrap_or_default();
+        let source_config_uris = matches
+            .values_of("source-config")
+            .map(|values| values.map(Uri::from_str).collect::<Result<Vec<_>, _>>())
+            .transpose()?
+            .unwrap_or_default();
+        Ok(DoctorCliCommand {
+            config_uri,
+            index_config_uris,
+            source_config_uris,
+        })
+    }
+
+    pub async fn execute(self) -> anyhow::Result<()> {
+        debug!(args = ?self, "doctor");
+        let mut checks: Vec<(String, anyhow::Result<()>)> = Vec::new();
+
+        let config_res = load_node_config(&self.config_uri).await;
+        let config_opt = config_res.as_ref().ok().cloned();
+        checks.push((
+            format!("node config `{}`", self.config_uri),
+            config_res.map(|_| ()),
+        ));
+        for index_config_uri in &self.index_config_uris {
+            checks.push((
+                format!("index config `{}`", index_config_uri),
+                validate_index_config(index_config_uri).await,
+            ));
+        }
+        for source_config_uri in &self.source_config_uris {
+            checks.push((
+                format!("source config `{}`", source_config_uri),
+                validate_source_config(source_config_uri).await,
+            ));
+        }
+        // The remaining checks require a valid node config.
+        if let Some(config) = config_opt {
+            checks.push((
+                format!("metastore `{}`", config.metastore_uri),
+                check_metastore(&config.metastore_uri).await,
+            ));
+            checks.push((
+                format!("storage `{}`", config.default_index_root_uri),
+                check_storage(&config.default_index_root_uri).await,
+            ));
+            checks.push((
+                format!("REST port `{}`", config.rest_listen_addr),
+                check_tcp_port(config.rest_listen_addr),
+            ));
+            checks.push((
+                format!("gRPC port `{}`", config.grpc_listen_addr),
+                check_tcp_port(config.grpc_listen_addr),
+            ));
+            checks.push((
+                format!("gossip port `{}`", config.gossip_listen_addr),
+                check_udp_port(config.gossip_listen_addr),
+            ));
+        }
+        let (check_names, check_results): (Vec<String>, Vec<anyhow::Result<()>>) =
+            checks.into_iter().unzip();
+        run_checklist(
+            check_names
+                .iter()
+                .map(String::as_str)
+                .zip(check_results)
+                .collect(),
+        )?;
+        Ok(())
+    }
+}
+
+async fn read_config_file(uri: &Uri) -> anyhow::Result<Vec<u8>> {
+    let file_content = load_file(uri)
+        .await
+        .with_context(|| format!("Failed to read config file `{}`.", uri))?;
+    Ok(file_content.as_slice().to_vec())
+}
+
+// The errors returned by the config parsers embed the line and column of parse errors. They are
+// printed along with their chain of causes, so the config file location is attached as context.
+
+async fn load_node_config(uri: &Uri) -> anyhow::Result<QuickwitConfig> {
+    let file_content = read_config_file(uri).await?;
+    QuickwitConfig::load(uri, &file_content)
+        .await
+        .with_context(|| format!("Node config file `{}` is invalid.", uri))
+}
+
+async fn validate_index_config(uri: &Uri) -> anyhow::Result<()> {
+    let file_content = read_config_file(uri).await?;
+    IndexConfig::load(uri, &file_content)
+        .await
+        .with_context(|| format!("Index config file `{}` is invalid.", uri))?;
+    Ok(())
+}
+
+async fn validate_source_config(uri: &Uri) -> anyhow::Result<()> {
+    let file_content = read_config_file(uri).await?;
+    SourceConfig::load(uri, &file_content)
+        .await
+        .with_context(|| format!("Source config file `{}` is invalid.", uri))?;
+    Ok(())
+}
+
+async fn check_metastore(metastore_uri: &Uri) -> anyhow::Result<()> {
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(metastore_uri)
+        .await?;
+    metastore.check_connectivity().await
+}
+
+/// Writes, reads back, and deletes a probe file to check that the node has the permissions
+/// required on the storage.
+async fn check_storage(storage_uri: &Uri) -> anyhow::Result<()> {
+    let storage = quickwit_storage_uri_resolver().resolve(storage_uri)?;
+    let probe_file_name = append_random_suffix(STORAGE_PROBE_FILE_PREFIX);
+    let probe_path = Path::new(&probe_file_name);
+    let probe_payload = probe_file_name.as_bytes().to_vec();
+    storage
+        .put(probe_path, Box::new(probe_payload.clone()))
+        .await
+        .context("PUT probe failed.")?;
+    let get_res = check_storage_get(&*storage, probe_path, &probe_payload).await;
+    // Attempt to clean up the probe file even if the GET probe failed.
+    let delete_res = storage
+        .delete(probe_path)
+        .await
+        .context("DELETE probe failed.");
+    get_res?;
+    delete_res?;
+    Ok(())
+}
+
+async fn check_storage_get(
+    storage: &dyn Storage,
+    probe_path: &Path,
+    expected_payload: &[u8],
+) -> anyhow::Result<()> {
+    let payload = storage
+        .get_all(probe_path)
+        .await
+        .context("GET probe failed.")?;
+    if payload.as_slice() != expected_payload {
+        bail!("GET probe failed: the file read back differs from the file written.");
+    }
+    Ok(())
+}
+
+fn check_tcp_port(listen_addr: SocketAddr) -> anyhow::Result<()> {
+    TcpListener::bind(listen_addr)
+        .with_context(|| format!("Failed to bind TCP socket on `{}`.", listen_addr))?;
+    Ok(())
+}
+
+fn check_udp_port(listen_addr: SocketAddr) -> anyhow::Result<()> {
+    UdpSocket::bind(listen_addr)
+        .with_context(|| format!("Failed to bind UDP socket on `{}`.", listen_addr))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{build_cli, CliCommand};
+
+    #[test]
+    fn test_parse_doctor_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "doctor",
+            "--config",
+            "/config.yaml",
+            "--index-config",
+            "/index-1.yaml",
+            "--index-config",
+            "/index-2.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Doctor(DoctorCliCommand {
+            config_uri: Uri::from_str("file:///config.yaml")?,
+            index_config_uris: vec![
+                Uri::from_str("file:///index-1.yaml")?,
+                Uri::from_str("file:///index-2.yaml")?,
+            ],
+            source_config_uris: Vec::new(),
+        });
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_storage() {
+        let storage_uri = Uri::from_str("ram:///indexes").unwrap();
+        check_storage(&storage_uri).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_validate_index_config_reports_location() {
+        let mut index_config_file = tempfile::Builder::new().suffix(".yaml").tempfile().unwrap();
+        std::io::Write::write_all(
+            &mut index_config_file,
+            b"version: 0\nindex_id: hdfs-logs\ndoc_mapping: {\n",
+        )
+        .unwrap();
+        let index_config_uri = Uri::from_str(index_config_file.path().to_str().unwrap()).unwrap();
+        let error = validate_index_config(&index_config_uri).await.unwrap_err();
+        let error_message = format!("{:#}", error);
+        assert!(error_message.contains("is invalid"));
+        assert!(error_message.contains("line"));
+    }
+
+    #[test]
+    fn test_check_ports() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let listen_addr = listener.local_addr().unwrap();
+        let error = check_tcp_port(listen_addr).unwrap_err();
+        assert!(error.to_string().contains("Failed to bind TCP socket"));
+        drop(listener);
+        check_tcp_port(listen_addr).unwrap();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let listen_addr = socket.local_addr().unwrap();
+        check_udp_port(listen_addr).unwrap_err();
+    }
+}
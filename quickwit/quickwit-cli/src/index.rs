@@ -22,36 +22,53 @@ use std::fmt::Display;
 use std::io::{stdout, Stdout, Write};
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::{env, fmt, io};
 
 use anyhow::{bail, Context};
+use bytes::Bytes;
 use clap::{arg, ArgMatches, Command};
 use colored::{ColoredString, Colorize};
+use futures::TryStreamExt;
+use humansize::{format_size, DECIMAL};
 use humantime::format_duration;
 use itertools::Itertools;
+use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::Int64Type;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type as ParquetType;
 use quickwit_actors::{ActorHandle, ObservationType, Universe};
 use quickwit_common::uri::Uri;
 use quickwit_common::GREEN_COLOR;
 use quickwit_config::service::QuickwitService;
 use quickwit_config::{
-    IndexConfig, IndexerConfig, SourceConfig, SourceParams, CLI_INGEST_SOURCE_ID,
+    build_doc_mapper, IndexConfig, IndexerConfig, SourceConfig, SourceParams, CLI_INGEST_SOURCE_ID,
     INGEST_API_SOURCE_ID,
 };
 use quickwit_core::{
-    clear_cache_directory, remove_indexing_directory, validate_storage_uri, IndexService,
+    clear_cache_directory, remove_indexing_directory, validate_storage_uri, CopyProgress,
+    FileEntry, IndexService,
 };
 use quickwit_indexing::actors::{IndexingPipeline, IndexingService};
 use quickwit_indexing::models::{
     DetachPipeline, IndexingPipelineId, IndexingStatistics, SpawnMergePipeline, SpawnPipeline,
 };
-use quickwit_metastore::{quickwit_metastore_uri_resolver, IndexMetadata, Split, SplitState};
-use quickwit_proto::{SearchRequest, SearchResponse};
-use quickwit_search::{single_node_search, SearchResponseRest};
-use quickwit_storage::{load_file, quickwit_storage_uri_resolver};
+use quickwit_metastore::{
+    quickwit_metastore_uri_resolver, IndexMetadata, Metastore, Split, SplitMetadata, SplitState,
+};
+use quickwit_proto::{OutputFormat, SearchRequest, SearchResponse, SearchStreamRequest};
+use quickwit_search::{
+    single_node_search, single_node_search_splits, single_node_search_stream_fields,
+    SearchResponseRest,
+};
+use quickwit_storage::{load_file, quickwit_storage_uri_resolver, FilePayload};
 use quickwit_telemetry::payload::TelemetryEvent;
+use serde_json::Value as JsonValue;
 use tabled::object::{Columns, Segment};
 use tabled::{Alignment, Concat, Format, Modify, Panel, Rotate, Style, Table, Tabled};
+use tantivy::schema::{Schema, Type};
 use thousands::Separable;
 use tracing::{debug, warn, Level};
 
@@ -142,6 +159,9 @@ pub fn build_index_command<'a>() -> Command<'a> {
                     arg!(--"snippet-fields" <FIELD_NAME> "List of fields that Quickwit will return snippet highlight on. Space-separated list, e.g. \"field1 field2\". ")
                         .multiple_values(true)
                         .required(false),
+                    arg!(--"fetch-fields" <FIELD_NAME> "List of stored fields returned in the hits. All the stored fields are returned by default. Space-separated list, e.g. \"field1 field2\". ")
+                        .multiple_values(true)
+                        .required(false),
                     arg!(--"start-timestamp" <TIMESTAMP> "Filters out documents before that timestamp (time-series indexes only).")
                         .required(false),
                     arg!(--"end-timestamp" <TIMESTAMP> "Filters out documents after that timestamp (time-series indexes only).")
@@ -150,6 +170,55 @@ pub fn build_index_command<'a>() -> Command<'a> {
                         .required(false),
                 ])
             )
+        .subcommand(
+            Command::new("tail")
+                .about("Continuously prints the newly published documents matching a query, like `tail -f` for an index.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                    arg!(--query <QUERY> "Query expressed in natural query language. By default, all the new documents are printed.")
+                        .default_value("*")
+                        .required(false),
+                    arg!(--"search-fields" <FIELD_NAME> "List of fields that Quickwit will search into if the user query does not explicitly target a field in the query. It overrides the default search fields defined in the index config. Space-separated list, e.g. \"field1 field2\". ")
+                        .multiple_values(true)
+                        .required(false),
+                    arg!(--fields <FIELD_NAME> "List of fields to print. Space-separated list, e.g. \"field1 field2\". By default, entire documents are printed.")
+                        .multiple_values(true)
+                        .required(false),
+                    arg!(--"poll-interval" <POLL_INTERVAL> "Interval at which the metastore is polled for newly published splits.")
+                        .default_value("2s")
+                        .required(false),
+                    arg!(--"max-hits" <MAX_HITS> "Maximum number of documents printed per poll.")
+                        .default_value("100")
+                        .required(false),
+                    arg!(--"no-color" "Disables color formatting. Colors are also disabled when the output is not a terminal.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("export")
+                .about("Exports the values of fast fields for the documents matching a query to a CSV, NDJSON, or Parquet file.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                    arg!(--fields <FAST_FIELD> "Names of the fast fields to export, one column per field. Only `i64`, `u64`, and `datetime` fast fields are supported. Space-separated list, e.g. \"field1 field2\".")
+                        .multiple_values(true),
+                    arg!(--"output-uri" <OUTPUT_URI> "Location of the output file, on the local file system or on an object storage."),
+                    arg!(--format <FORMAT> "Output format (csv|ndjson|parquet).")
+                        .default_value("csv")
+                        .required(false),
+                    arg!(--query <QUERY> "Query expressed in natural query language. By default, all the documents are exported.")
+                        .default_value("*")
+                        .required(false),
+                    arg!(--"search-fields" <FIELD_NAME> "List of fields that Quickwit will search into if the user query does not explicitly target a field in the query. It overrides the default search fields defined in the index config. Space-separated list, e.g. \"field1 field2\". ")
+                        .multiple_values(true)
+                        .required(false),
+                    arg!(--"start-timestamp" <TIMESTAMP> "Filters out documents before that timestamp (time-series indexes only).")
+                        .required(false),
+                    arg!(--"end-timestamp" <TIMESTAMP> "Filters out documents after that timestamp (time-series indexes only).")
+                        .required(false),
+                ])
+            )
         .subcommand(
             Command::new("merge")
                 .about("Merges all the splits of the index pipeline defined by the tuple (index ID, source ID, pipeline ordinal). The pipeline ordinal is 0 by default. If you have a source with `num_pipelines > 0`, you may want to merge splits on ordinals > 0.")
@@ -170,6 +239,54 @@ pub fn build_index_command<'a>() -> Command<'a> {
                         .required(false),
                     arg!(--"dry-run" "Executes the command in dry run mode and only displays the list of splits candidates for garbage collection.")
                         .required(false),
+                    arg!(--"scan-orphans" "Also scans the index storage for split files not referenced by the metastore, such as files left behind by a crash, and reports the reclaimable storage. Files are only deleted when `--yes` is passed.")
+                        .required(false),
+                    arg!(--yes "Deletes the files found by the orphan scan.")
+                        .requires("scan-orphans")
+                        .conflicts_with("dry-run")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("backup")
+                .about("Backs up an index: writes its metadata, source checkpoints, and published splits metadata to a target URI, and optionally copies its split files. Interrupted backups can be resumed by running the same command again.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                    arg!(--"target-uri" <TARGET_URI> "URI where the backup is written, e.g. `s3://my-bucket/backups/my-index`."),
+                    arg!(--"metadata-only" "Only backs up the metadata. The restored index will read the split files from the original index URI.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("restore")
+                .about("Restores an index from a backup created with `quickwit index backup`. Interrupted restores can be resumed by running the same command again.")
+                .args(&[
+                    arg!(--"backup-uri" <BACKUP_URI> "URI of the backup to restore.")
+                        .display_order(1),
+                    arg!(--index <INDEX> "ID of the restored index. Defaults to the ID of the backed up index.")
+                        .required(false),
+                    arg!(--"index-uri" <INDEX_URI> "URI where the split files are restored. Defaults to `{default_index_root_uri}/{index}`.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("snapshot")
+                .about("Takes a self-contained and immutable snapshot of an index: copies its published split files and writes a manifest holding its metadata to a target URI. The snapshot can be attached read-only to any cluster with `quickwit index attach`. Interrupted snapshots can be resumed by running the same command again.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                    arg!(--"target-uri" <TARGET_URI> "URI where the snapshot is written, e.g. `s3://my-bucket/snapshots/my-index`."),
+                ])
+            )
+        .subcommand(
+            Command::new("attach")
+                .about("Attaches a manifest of splits, such as a snapshot created with `quickwit index snapshot`, as a read-only index. No indexing pipeline runs on the index, and its split files are read in place and are never modified nor deleted.")
+                .args(&[
+                    arg!(--"manifest-uri" <MANIFEST_URI> "URI of the snapshot or of the manifest file (`.json`) to attach. The split files must sit next to the manifest.")
+                        .display_order(1),
+                    arg!(--index <INDEX> "ID of the attached index. Defaults to the ID of the snapshotted index.")
+                        .required(false),
                 ])
             )
         .subcommand(
@@ -196,6 +313,36 @@ pub fn build_index_command<'a>() -> Command<'a> {
         .arg_required_else_help(true)
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct BackupIndexArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub target_uri: Uri,
+    pub include_split_files: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct RestoreIndexArgs {
+    pub config_uri: Uri,
+    pub backup_uri: Uri,
+    pub index_id_opt: Option<String>,
+    pub index_uri_opt: Option<Uri>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct SnapshotIndexArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub target_uri: Uri,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct AttachIndexArgs {
+    pub config_uri: Uri,
+    pub manifest_uri: Uri,
+    pub index_id_opt: Option<String>,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct ClearIndexArgs {
     pub config_uri: Uri,
@@ -233,6 +380,55 @@ pub struct ToggleIngestApiArgs {
     pub enable: bool,
 }
 
+/// Output format of `index export`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> anyhow::Result<Self> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            "parquet" => Ok(Self::Parquet),
+            _ => bail!(
+                "Unknown export format `{}`. Supported formats are `csv`, `ndjson`, and `parquet`.",
+                format
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ExportIndexArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub fast_fields: Vec<String>,
+    pub output_uri: Uri,
+    pub format: ExportFormat,
+    pub query: String,
+    pub search_fields: Option<Vec<String>>,
+    pub start_timestamp: Option<i64>,
+    pub end_timestamp: Option<i64>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct TailIndexArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub query: String,
+    pub search_fields: Option<Vec<String>>,
+    pub fields: Vec<String>,
+    pub poll_interval: Duration,
+    pub max_hits: usize,
+    pub color: bool,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct SearchIndexArgs {
     pub config_uri: Uri,
@@ -243,6 +439,7 @@ pub struct SearchIndexArgs {
     pub start_offset: usize,
     pub search_fields: Option<Vec<String>>,
     pub snippet_fields: Option<Vec<String>>,
+    pub fetch_fields: Option<Vec<String>>,
     pub start_timestamp: Option<i64>,
     pub end_timestamp: Option<i64>,
     pub sort_by_score: bool,
@@ -261,6 +458,8 @@ pub struct GarbageCollectIndexArgs {
     pub index_id: String,
     pub grace_period: Duration,
     pub dry_run: bool,
+    pub scan_orphans: bool,
+    pub yes: bool,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -277,22 +476,28 @@ pub struct ListIndexesArgs {
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum IndexCliCommand {
+    Attach(AttachIndexArgs),
+    Backup(BackupIndexArgs),
     Clear(ClearIndexArgs),
     Create(CreateIndexArgs),
     Delete(DeleteIndexArgs),
     Describe(DescribeIndexArgs),
+    Export(ExportIndexArgs),
     GarbageCollect(GarbageCollectIndexArgs),
     Ingest(IngestDocsArgs),
     ToggleIngestApi(ToggleIngestApiArgs),
     List(ListIndexesArgs),
     Merge(MergeArgs),
+    Restore(RestoreIndexArgs),
     Search(SearchIndexArgs),
+    Snapshot(SnapshotIndexArgs),
+    Tail(TailIndexArgs),
 }
 
 impl IndexCliCommand {
     pub fn default_log_level(&self) -> Level {
         match self {
-            Self::Search(_) => Level::ERROR,
+            Self::Search(_) | Self::Tail(_) => Level::ERROR,
             _ => Level::INFO,
         }
     }
@@ -302,6 +507,8 @@ impl IndexCliCommand {
             .subcommand()
             .ok_or_else(|| anyhow::anyhow!("Failed to parse sub-matches."))?;
         match subcommand {
+            "attach" => Self::parse_attach_args(submatches),
+            "backup" => Self::parse_backup_args(submatches),
             "clear" => Self::parse_clear_args(submatches),
             "create" => Self::parse_create_args(submatches),
             "delete" => Self::parse_delete_args(submatches),
@@ -311,11 +518,100 @@ impl IndexCliCommand {
             "ingest-api" => Self::parse_toggle_ingest_api_args(submatches),
             "list" => Self::parse_list_args(submatches),
             "merge" => Self::parse_merge_args(submatches),
+            "restore" => Self::parse_restore_args(submatches),
+            "export" => Self::parse_export_args(submatches),
             "search" => Self::parse_search_args(submatches),
+            "snapshot" => Self::parse_snapshot_args(submatches),
+            "tail" => Self::parse_tail_args(submatches),
             _ => bail!("Index subcommand `{}` is not implemented.", subcommand),
         }
     }
 
+    fn parse_backup_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let target_uri = matches
+            .value_of("target-uri")
+            .map(Uri::from_str)
+            .expect("`target-uri` is a required arg.")?;
+        let include_split_files = !matches.is_present("metadata-only");
+        Ok(Self::Backup(BackupIndexArgs {
+            config_uri,
+            index_id,
+            target_uri,
+            include_split_files,
+        }))
+    }
+
+    fn parse_restore_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let backup_uri = matches
+            .value_of("backup-uri")
+            .map(Uri::from_str)
+            .expect("`backup-uri` is a required arg.")?;
+        let index_id_opt = matches
+            .value_of("index")
+            .map(|index_id| index_id.to_string());
+        let index_uri_opt = matches
+            .value_of("index-uri")
+            .map(Uri::from_str)
+            .transpose()?;
+        Ok(Self::Restore(RestoreIndexArgs {
+            config_uri,
+            backup_uri,
+            index_id_opt,
+            index_uri_opt,
+        }))
+    }
+
+    fn parse_snapshot_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let target_uri = matches
+            .value_of("target-uri")
+            .map(Uri::from_str)
+            .expect("`target-uri` is a required arg.")?;
+        Ok(Self::Snapshot(SnapshotIndexArgs {
+            config_uri,
+            index_id,
+            target_uri,
+        }))
+    }
+
+    fn parse_attach_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let manifest_uri = matches
+            .value_of("manifest-uri")
+            .map(Uri::from_str)
+            .expect("`manifest-uri` is a required arg.")?;
+        let index_id_opt = matches
+            .value_of("index")
+            .map(|index_id| index_id.to_string());
+        Ok(Self::Attach(AttachIndexArgs {
+            config_uri,
+            manifest_uri,
+            index_id_opt,
+        }))
+    }
+
     fn parse_clear_args(matches: &ArgMatches) -> anyhow::Result<Self> {
         let config_uri = matches
             .value_of("config")
@@ -420,6 +716,58 @@ impl IndexCliCommand {
             enable,
         }))
     }
+    fn parse_export_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let fast_fields = matches
+            .values_of("fields")
+            .expect("`fields` is a required arg.")
+            .map(|value| value.to_string())
+            .collect();
+        let output_uri = matches
+            .value_of("output-uri")
+            .map(Uri::from_str)
+            .expect("`output-uri` is a required arg.")?;
+        let format = matches
+            .value_of("format")
+            .map(ExportFormat::from_str)
+            .expect("`format` should have a default value.")?;
+        let query = matches
+            .value_of("query")
+            .expect("`query` should have a default value.")
+            .to_string();
+        let search_fields = matches
+            .values_of("search-fields")
+            .map(|values| values.map(|value| value.to_string()).collect());
+        let start_timestamp = if matches.is_present("start-timestamp") {
+            Some(matches.value_of_t::<i64>("start-timestamp")?)
+        } else {
+            None
+        };
+        let end_timestamp = if matches.is_present("end-timestamp") {
+            Some(matches.value_of_t::<i64>("end-timestamp")?)
+        } else {
+            None
+        };
+        Ok(Self::Export(ExportIndexArgs {
+            config_uri,
+            index_id,
+            fast_fields,
+            output_uri,
+            format,
+            query,
+            search_fields,
+            start_timestamp,
+            end_timestamp,
+        }))
+    }
+
     fn parse_search_args(matches: &ArgMatches) -> anyhow::Result<Self> {
         let index_id = matches
             .value_of("index")
@@ -439,6 +787,9 @@ impl IndexCliCommand {
         let snippet_fields = matches
             .values_of("snippet-fields")
             .map(|values| values.map(|value| value.to_string()).collect());
+        let fetch_fields = matches
+            .values_of("fetch-fields")
+            .map(|values| values.map(|value| value.to_string()).collect());
         let sort_by_score = matches.is_present("sort-by-score");
         let start_timestamp = if matches.is_present("start-timestamp") {
             Some(matches.value_of_t::<i64>("start-timestamp")?)
@@ -462,6 +813,7 @@ impl IndexCliCommand {
             start_offset,
             search_fields,
             snippet_fields,
+            fetch_fields,
             start_timestamp,
             end_timestamp,
             config_uri,
@@ -469,6 +821,44 @@ impl IndexCliCommand {
         }))
     }
 
+    fn parse_tail_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let query = matches
+            .value_of("query")
+            .expect("`query` should have a default value.")
+            .to_string();
+        let search_fields = matches
+            .values_of("search-fields")
+            .map(|values| values.map(|value| value.to_string()).collect());
+        let fields = matches
+            .values_of("fields")
+            .map(|values| values.map(|value| value.to_string()).collect())
+            .unwrap_or_default();
+        let poll_interval = matches
+            .value_of("poll-interval")
+            .map(parse_duration_with_unit)
+            .expect("`poll-interval` should have a default value.")?;
+        let max_hits = matches.value_of_t::<usize>("max-hits")?;
+        let color = !matches.is_present("no-color");
+        Ok(Self::Tail(TailIndexArgs {
+            config_uri,
+            index_id,
+            query,
+            search_fields,
+            fields,
+            poll_interval,
+            max_hits,
+            color,
+        }))
+    }
+
     fn parse_merge_args(matches: &ArgMatches) -> anyhow::Result<Self> {
         let config_uri = matches
             .value_of("config")
@@ -503,11 +893,15 @@ impl IndexCliCommand {
             .map(parse_duration_with_unit)
             .expect("`grace-period` should have a default value.")?;
         let dry_run = matches.is_present("dry-run");
+        let scan_orphans = matches.is_present("scan-orphans");
+        let yes = matches.is_present("yes");
         Ok(Self::GarbageCollect(GarbageCollectIndexArgs {
             index_id,
             grace_period,
             dry_run,
             config_uri,
+            scan_orphans,
+            yes,
         }))
     }
 
@@ -530,6 +924,8 @@ impl IndexCliCommand {
 
     pub async fn execute(self) -> anyhow::Result<()> {
         match self {
+            Self::Attach(args) => attach_index_cli(args).await,
+            Self::Backup(args) => backup_index_cli(args).await,
             Self::Clear(args) => clear_index_cli(args).await,
             Self::Create(args) => create_index_cli(args).await,
             Self::Delete(args) => delete_index_cli(args).await,
@@ -539,11 +935,105 @@ impl IndexCliCommand {
             Self::ToggleIngestApi(args) => toggle_ingest_api_index_cli(args).await,
             Self::List(args) => list_index_cli(args).await,
             Self::Merge(args) => merge_cli(args).await,
+            Self::Restore(args) => restore_index_cli(args).await,
+            Self::Export(args) => export_index_cli(args).await,
             Self::Search(args) => search_index_cli(args).await,
+            Self::Snapshot(args) => snapshot_index_cli(args).await,
+            Self::Tail(args) => tail_index_cli(args).await,
         }
     }
 }
 
+pub async fn backup_index_cli(args: BackupIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "backup-index");
+    let config = load_quickwit_config(&args.config_uri).await?;
+    let index_service = IndexService::from_config(config).await?;
+    let progress = index_service
+        .backup_index(
+            &args.index_id,
+            &args.target_uri,
+            args.include_split_files,
+            display_copy_progress,
+        )
+        .await?;
+    println!(
+        "Index `{}` successfully backed up to `{}` ({} split(s), {} copied).",
+        args.index_id,
+        args.target_uri,
+        progress.num_splits_total,
+        format_size(progress.num_bytes_copied, DECIMAL),
+    );
+    Ok(())
+}
+
+pub async fn restore_index_cli(args: RestoreIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "restore-index");
+    let config = load_quickwit_config(&args.config_uri).await?;
+    let index_service = IndexService::from_config(config).await?;
+    let progress = index_service
+        .restore_index(
+            &args.backup_uri,
+            args.index_id_opt,
+            args.index_uri_opt,
+            display_copy_progress,
+        )
+        .await?;
+    println!(
+        "Index successfully restored from `{}` ({} split(s), {} copied).",
+        args.backup_uri,
+        progress.num_splits_total,
+        format_size(progress.num_bytes_copied, DECIMAL),
+    );
+    Ok(())
+}
+
+pub async fn snapshot_index_cli(args: SnapshotIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "snapshot-index");
+    let config = load_quickwit_config(&args.config_uri).await?;
+    let index_service = IndexService::from_config(config).await?;
+    let progress = index_service
+        .snapshot_index(&args.index_id, &args.target_uri, display_copy_progress)
+        .await?;
+    println!(
+        "Snapshot of index `{}` successfully written to `{}` ({} split(s), {} copied).",
+        args.index_id,
+        args.target_uri,
+        progress.num_splits_total,
+        format_size(progress.num_bytes_copied, DECIMAL),
+    );
+    Ok(())
+}
+
+pub async fn attach_index_cli(args: AttachIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "attach-index");
+    let config = load_quickwit_config(&args.config_uri).await?;
+    let index_service = IndexService::from_config(config).await?;
+    let index_metadata = index_service
+        .attach_index(&args.manifest_uri, args.index_id_opt)
+        .await?;
+    println!(
+        "Manifest `{}` successfully attached as read-only index `{}`.",
+        args.manifest_uri, index_metadata.index_id,
+    );
+    Ok(())
+}
+
+fn display_copy_progress(progress: &CopyProgress) {
+    let skipped = if progress.num_splits_skipped > 0 {
+        format!(" ({} already present)", progress.num_splits_skipped)
+    } else {
+        String::new()
+    };
+    println!(
+        "{} Split {}/{}{} - {} copied",
+        "→".bright_blue(),
+        progress.num_splits_processed,
+        progress.num_splits_total,
+        skipped,
+        format_size(progress.num_bytes_copied, DECIMAL),
+    );
+}
+
 pub async fn clear_index_cli(args: ClearIndexArgs) -> anyhow::Result<()> {
     debug!(args=?args, "clear-index");
     if !args.yes {
@@ -887,6 +1377,11 @@ pub async fn ingest_docs_cli(args: IngestDocsArgs) -> anyhow::Result<()> {
     let source_config = SourceConfig {
         source_id: CLI_INGEST_SOURCE_ID.to_string(),
         num_pipelines: 1,
+        expected_throughput: None,
+        rate_limit_per_sec: None,
+        transform: Vec::new(),
+        input_format: None,
+        csv_options: None,
         enabled: true,
         source_params,
     };
@@ -990,6 +1485,7 @@ pub async fn search_index(args: SearchIndexArgs) -> anyhow::Result<SearchRespons
         query: args.query.clone(),
         search_fields: args.search_fields.unwrap_or_default(),
         snippet_fields: args.snippet_fields.unwrap_or_default(),
+        fetch_fields: args.fetch_fields.unwrap_or_default(),
         start_timestamp: args.start_timestamp,
         end_timestamp: args.end_timestamp,
         max_hits: args.max_hits as u64,
@@ -997,6 +1493,9 @@ pub async fn search_index(args: SearchIndexArgs) -> anyhow::Result<SearchRespons
         sort_order: None,
         sort_by_field: args.sort_by_score.then_some("_score".to_string()),
         aggregation_request: args.aggregation,
+        debug: false,
+        role: None,
+        query_ast: None,
     };
     let search_response: SearchResponse =
         single_node_search(&search_request, &*metastore, storage_uri_resolver.clone()).await?;
@@ -1011,6 +1510,407 @@ pub async fn search_index_cli(args: SearchIndexArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn tail_index_cli(args: TailIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "tail-index");
+    let quickwit_config = load_quickwit_config(&args.config_uri).await?;
+    let storage_uri_resolver = quickwit_storage_uri_resolver();
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&quickwit_config.metastore_uri)
+        .await?;
+    let search_request = SearchRequest {
+        index_id: args.index_id.clone(),
+        query: args.query.clone(),
+        search_fields: args.search_fields.clone().unwrap_or_default(),
+        max_hits: args.max_hits as u64,
+        ..Default::default()
+    };
+    let color = args.color && atty::is(atty::Stream::Stdout);
+    let mut split_tracker = PublishedSplitTracker::default();
+    // Only the documents published from now on are printed.
+    split_tracker.update(list_published_splits(&*metastore, &args.index_id).await?);
+    let mut poll_interval = tokio::time::interval(args.poll_interval);
+    // The first tick completes immediately.
+    poll_interval.tick().await;
+
+    loop {
+        poll_interval.tick().await;
+        let new_splits =
+            split_tracker.update(list_published_splits(&*metastore, &args.index_id).await?);
+        if new_splits.is_empty() {
+            continue;
+        }
+        let search_response = single_node_search_splits(
+            &search_request,
+            &*metastore,
+            storage_uri_resolver.clone(),
+            &new_splits,
+        )
+        .await?;
+        for hit in &search_response.hits {
+            let doc: JsonValue = serde_json::from_str(&hit.json)?;
+            println!("{}", format_tail_doc(&doc, &args.fields, color));
+        }
+        let num_omitted_hits = search_response
+            .num_hits
+            .saturating_sub(search_response.hits.len() as u64);
+        if num_omitted_hits > 0 {
+            println!(
+                "... {} more matching document(s) omitted. Increase `--max-hits` to print them.",
+                num_omitted_hits
+            );
+        }
+    }
+}
+
+async fn list_published_splits(
+    metastore: &dyn Metastore,
+    index_id: &str,
+) -> anyhow::Result<Vec<SplitMetadata>> {
+    let splits = metastore
+        .list_splits(index_id, SplitState::Published, None, None)
+        .await?
+        .into_iter()
+        .map(|split| split.split_metadata)
+        .collect();
+    Ok(splits)
+}
+
+/// Keeps track of the published splits of an index to detect the newly published ones.
+#[derive(Default)]
+struct PublishedSplitTracker {
+    known_split_ids: HashSet<String>,
+}
+
+impl PublishedSplitTracker {
+    /// Records the currently published splits and returns the ones published since the previous
+    /// call. The splits produced by merges are ignored since their documents were already
+    /// published in the merged splits.
+    fn update(&mut self, published_splits: Vec<SplitMetadata>) -> Vec<SplitMetadata> {
+        let mut published_split_ids = HashSet::with_capacity(published_splits.len());
+        let mut new_splits = Vec::new();
+
+        for split in published_splits {
+            published_split_ids.insert(split.split_id.clone());
+
+            if split.num_merge_ops == 0 && !self.known_split_ids.contains(&split.split_id) {
+                new_splits.push(split);
+            }
+        }
+        self.known_split_ids = published_split_ids;
+        new_splits
+    }
+}
+
+/// Formats a document as a single JSON line, optionally restricted to `fields`. Field names and
+/// values are colorized if `color` is set.
+fn format_tail_doc(doc: &JsonValue, fields: &[String], color: bool) -> String {
+    let doc_object = match doc.as_object() {
+        Some(doc_object) => doc_object,
+        None => return doc.to_string(),
+    };
+    let entries: Vec<(&String, &JsonValue)> = if fields.is_empty() {
+        doc_object.iter().collect()
+    } else {
+        fields
+            .iter()
+            .filter_map(|field| doc_object.get(field).map(|value| (field, value)))
+            .collect()
+    };
+    let formatted_entries = entries
+        .into_iter()
+        .map(|(field, value)| {
+            let field_json = JsonValue::from(field.as_str()).to_string();
+            if color {
+                format!("{}:{}", field_json.cyan(), colorize_json_value(value))
+            } else {
+                format!("{}:{}", field_json, value)
+            }
+        })
+        .join(",");
+    format!("{{{}}}", formatted_entries)
+}
+
+fn colorize_json_value(value: &JsonValue) -> ColoredString {
+    let value_json = value.to_string();
+    match value {
+        JsonValue::String(_) => value_json.green(),
+        JsonValue::Number(_) => value_json.yellow(),
+        JsonValue::Bool(_) | JsonValue::Null => value_json.magenta(),
+        JsonValue::Array(_) | JsonValue::Object(_) => value_json.normal(),
+    }
+}
+
+pub async fn export_index_cli(args: ExportIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "export-index");
+    let quickwit_config = load_quickwit_config(&args.config_uri).await?;
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&quickwit_config.metastore_uri)
+        .await?;
+    let index_metadata = metastore.index_metadata(&args.index_id).await?;
+    let doc_mapper = build_doc_mapper(
+        &index_metadata.doc_mapping,
+        &index_metadata.search_settings,
+        &index_metadata.indexing_settings,
+    )?;
+    let schema = doc_mapper.schema();
+    let columns = args
+        .fast_fields
+        .iter()
+        .map(|field_name| {
+            let value_type = ExportValueType::for_field(&schema, field_name)?;
+            Ok((field_name.clone(), value_type))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let search_stream_request = SearchStreamRequest {
+        index_id: args.index_id,
+        query: args.query,
+        search_fields: args.search_fields.unwrap_or_default(),
+        snippet_fields: Vec::new(),
+        start_timestamp: args.start_timestamp,
+        end_timestamp: args.end_timestamp,
+        fast_field: String::new(),
+        output_format: OutputFormat::Csv as i32,
+        partition_by_field: None,
+        role: None,
+    };
+    let stream = single_node_search_stream_fields(
+        search_stream_request,
+        args.fast_fields,
+        &*metastore,
+        quickwit_storage_uri_resolver().clone(),
+    )
+    .await?;
+    // The values are written to the output file as they are streamed. Other destinations than
+    // the local file system get a temporary file, uploaded once the export completes.
+    let num_values = if args.output_uri.protocol().is_file() {
+        let output_path = args
+            .output_uri
+            .filepath()
+            .expect("A `file://` URI should have a file path.");
+        let output_file = std::fs::File::create(output_path).with_context(|| {
+            format!("Failed to create output file `{}`.", output_path.display())
+        })?;
+        let export_writer =
+            ExportWriter::new(args.format, &columns, io::BufWriter::new(output_file))?;
+        write_export(stream, export_writer).await?
+    } else {
+        let (output_dir_uri, output_file_name) = args
+            .output_uri
+            .parent()
+            .zip(args.output_uri.file_name())
+            .with_context(|| format!("Invalid output URI `{}`.", args.output_uri))?;
+        let temp_dir = tempfile::tempdir()?;
+        let temp_file_path = temp_dir.path().join("export");
+        let temp_file = std::fs::File::create(&temp_file_path)?;
+        let export_writer =
+            ExportWriter::new(args.format, &columns, io::BufWriter::new(temp_file))?;
+        let num_values = write_export(stream, export_writer).await?;
+        let payload = FilePayload::open(&temp_file_path).await?;
+        let storage = quickwit_storage_uri_resolver().resolve(&output_dir_uri)?;
+        storage.put(output_file_name, Box::new(payload)).await?;
+        num_values
+    };
+    println!(
+        "Exported {} rows to `{}`.",
+        num_values.separate_with_commas(),
+        args.output_uri
+    );
+    Ok(())
+}
+
+async fn write_export<W: Write + Send>(
+    stream: impl futures::Stream<Item = quickwit_search::Result<Vec<Bytes>>>,
+    mut export_writer: ExportWriter<W>,
+) -> anyhow::Result<usize> {
+    let mut stream = Box::pin(stream);
+    let mut num_rows = 0;
+    while let Some(field_chunks) = stream.try_next().await? {
+        num_rows += export_writer.write_chunks(&field_chunks)?;
+    }
+    export_writer.finish()?;
+    Ok(num_rows)
+}
+
+/// Type of the values of an exported fast field. Datetime values are exported as `i64`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ExportValueType {
+    I64,
+    U64,
+}
+
+impl ExportValueType {
+    fn for_field(schema: &Schema, field_name: &str) -> anyhow::Result<Self> {
+        let field = schema
+            .get_field(field_name)
+            .with_context(|| format!("Field `{}` does not exist.", field_name))?;
+        let field_entry = schema.get_field_entry(field);
+        if !field_entry.is_fast() {
+            bail!("Field `{}` is not a fast field.", field_name);
+        }
+        match field_entry.field_type().value_type() {
+            Type::I64 | Type::Date => Ok(Self::I64),
+            Type::U64 => Ok(Self::U64),
+            value_type => bail!(
+                "Field `{}` of type `{:?}` cannot be exported. Only `i64`, `u64`, and `datetime` \
+                 fast fields are supported.",
+                field_name,
+                value_type
+            ),
+        }
+    }
+
+    /// Parses a value of the CSV output of search stream. Parquet stores unsigned integers in
+    /// signed physical columns.
+    fn parse_value(&self, value: &str) -> anyhow::Result<i64> {
+        let value = match self {
+            Self::I64 => value.parse::<i64>()?,
+            Self::U64 => value.parse::<u64>()? as i64,
+        };
+        Ok(value)
+    }
+}
+
+/// Converts the CSV output of search stream, one value per line and one chunk per field, into
+/// the rows of the export format.
+enum ExportWriter<W: Write + Send> {
+    Csv(W),
+    Ndjson {
+        writer: W,
+        /// Field names serialized as JSON strings.
+        json_field_names: Vec<String>,
+    },
+    Parquet {
+        writer: SerializedFileWriter<W>,
+        value_types: Vec<ExportValueType>,
+    },
+}
+
+impl<W: Write + Send> ExportWriter<W> {
+    fn new(
+        format: ExportFormat,
+        columns: &[(String, ExportValueType)],
+        mut writer: W,
+    ) -> anyhow::Result<Self> {
+        let export_writer = match format {
+            ExportFormat::Csv => {
+                writeln!(
+                    writer,
+                    "{}",
+                    columns.iter().map(|(field_name, _)| field_name).join(",")
+                )?;
+                Self::Csv(writer)
+            }
+            ExportFormat::Ndjson => Self::Ndjson {
+                writer,
+                json_field_names: columns
+                    .iter()
+                    .map(|(field_name, _)| serde_json::to_string(field_name))
+                    .collect::<Result<_, _>>()?,
+            },
+            ExportFormat::Parquet => {
+                let mut fields = columns
+                    .iter()
+                    .map(|(field_name, value_type)| {
+                        let logical_type_opt = match value_type {
+                            ExportValueType::I64 => None,
+                            ExportValueType::U64 => Some(LogicalType::Integer {
+                                bit_width: 64,
+                                is_signed: false,
+                            }),
+                        };
+                        let field =
+                            ParquetType::primitive_type_builder(field_name, PhysicalType::INT64)
+                                .with_repetition(Repetition::REQUIRED)
+                                .with_logical_type(logical_type_opt)
+                                .build()?;
+                        Ok(Arc::new(field))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let schema = ParquetType::group_type_builder("export")
+                    .with_fields(&mut fields)
+                    .build()?;
+                let properties = WriterProperties::builder().build();
+                let writer =
+                    SerializedFileWriter::new(writer, Arc::new(schema), Arc::new(properties))?;
+                Self::Parquet {
+                    writer,
+                    value_types: columns.iter().map(|(_, value_type)| *value_type).collect(),
+                }
+            }
+        };
+        Ok(export_writer)
+    }
+
+    /// Writes the values of a split, one chunk per field, and returns the number of rows written.
+    fn write_chunks(&mut self, field_chunks: &[Bytes]) -> anyhow::Result<usize> {
+        let columns: Vec<Vec<&str>> = field_chunks
+            .iter()
+            .map(|chunk| Ok(std::str::from_utf8(chunk)?.lines().collect()))
+            .collect::<anyhow::Result<_>>()?;
+        let num_rows = columns.first().map(Vec::len).unwrap_or(0);
+        if columns.iter().any(|column| column.len() != num_rows) {
+            bail!("The fast fields returned a different number of values for the same split.");
+        }
+        if num_rows == 0 {
+            return Ok(0);
+        }
+        match self {
+            Self::Csv(writer) => {
+                for row_idx in 0..num_rows {
+                    let row = columns.iter().map(|column| column[row_idx]).join(",");
+                    writeln!(writer, "{}", row)?;
+                }
+            }
+            Self::Ndjson {
+                writer,
+                json_field_names,
+            } => {
+                for row_idx in 0..num_rows {
+                    let row = json_field_names
+                        .iter()
+                        .zip(&columns)
+                        .map(|(json_field_name, column)| {
+                            format!("{}:{}", json_field_name, column[row_idx])
+                        })
+                        .join(",");
+                    writeln!(writer, "{{{}}}", row)?;
+                }
+            }
+            Self::Parquet {
+                writer,
+                value_types,
+            } => {
+                // The values of each split become one row group.
+                let mut row_group_writer = writer.next_row_group()?;
+                for (column, value_type) in columns.iter().zip(value_types.iter()) {
+                    let values: Vec<i64> = column
+                        .iter()
+                        .map(|value| value_type.parse_value(value))
+                        .collect::<anyhow::Result<_>>()?;
+                    if let Some(mut column_writer) = row_group_writer.next_column()? {
+                        column_writer
+                            .typed::<Int64Type>()
+                            .write_batch(&values, None, None)?;
+                        column_writer.close()?;
+                    }
+                }
+                row_group_writer.close()?;
+            }
+        }
+        Ok(num_rows)
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Self::Csv(mut writer) | Self::Ndjson { mut writer, .. } => writer.flush()?,
+            Self::Parquet { writer, .. } => {
+                writer.close()?;
+            }
+        }
+        Ok(())
+    }
+}
+
 pub async fn merge_cli(args: MergeArgs) -> anyhow::Result<()> {
     debug!(args=?args, "run-merge-operations");
     let config = load_quickwit_config(&args.config_uri).await?;
@@ -1110,6 +2010,9 @@ pub async fn garbage_collect_index_cli(args: GarbageCollectIndexArgs) -> anyhow:
         quickwit_storage_uri_resolver().clone(),
         quickwit_config.default_index_root_uri,
     );
+    if args.scan_orphans {
+        return garbage_collect_orphans_cli(&index_service, args).await;
+    }
     let deleted_files = index_service
         .garbage_collect_index(&args.index_id, args.grace_period, args.dry_run)
         .await?;
@@ -1138,6 +2041,79 @@ pub async fn garbage_collect_index_cli(args: GarbageCollectIndexArgs) -> anyhow:
     Ok(())
 }
 
+/// Reports the orphan split files along with the splits candidates for garbage collection, and
+/// deletes them only when `--yes` is passed.
+async fn garbage_collect_orphans_cli(
+    index_service: &IndexService,
+    args: GarbageCollectIndexArgs,
+) -> anyhow::Result<()> {
+    let orphan_files = index_service.list_orphan_files(&args.index_id).await?;
+    let candidate_files = index_service
+        .garbage_collect_index(&args.index_id, args.grace_period, true)
+        .await?;
+    if orphan_files.is_empty() && candidate_files.is_empty() {
+        println!("No dangling files to garbage collect.");
+        return Ok(());
+    }
+    if !args.yes {
+        display_file_entries(
+            "Orphan split files not referenced by the metastore:",
+            &orphan_files,
+        );
+        display_file_entries(
+            "Stale staged splits and splits marked for deletion:",
+            &candidate_files,
+        );
+        println!(
+            "{} of storage can be reclaimed. Run the command again with `--yes` to delete these \
+             files.",
+            format_size(
+                sum_file_sizes(&orphan_files) + sum_file_sizes(&candidate_files),
+                DECIMAL
+            )
+        );
+        return Ok(());
+    }
+    let deleted_orphan_files = index_service
+        .delete_orphan_files(&args.index_id, orphan_files)
+        .await?;
+    let deleted_files = index_service
+        .garbage_collect_index(&args.index_id, args.grace_period, false)
+        .await?;
+    println!(
+        "{} of storage garbage collected ({} orphan file(s), {} split file(s)).",
+        format_size(
+            sum_file_sizes(&deleted_orphan_files) + sum_file_sizes(&deleted_files),
+            DECIMAL
+        ),
+        deleted_orphan_files.len(),
+        deleted_files.len()
+    );
+    println!("Index `{}` successfully garbage collected.", args.index_id);
+    Ok(())
+}
+
+fn display_file_entries(header: &str, file_entries: &[FileEntry]) {
+    if file_entries.is_empty() {
+        return;
+    }
+    println!("{}", header);
+    for file_entry in file_entries {
+        println!(
+            " - {} ({})",
+            file_entry.file_name,
+            format_size(file_entry.file_size_in_bytes, DECIMAL)
+        );
+    }
+}
+
+fn sum_file_sizes(file_entries: &[FileEntry]) -> u64 {
+    file_entries
+        .iter()
+        .map(|file_entry| file_entry.file_size_in_bytes)
+        .sum()
+}
+
 /// Starts a tokio task that displays the indexing statistics
 /// every once in awhile.
 pub async fn start_statistics_reporting_loop(
@@ -1328,6 +2304,7 @@ mod test {
     use std::str::FromStr;
 
     use quickwit_metastore::SplitMetadata;
+    use serde_json::json;
 
     use super::*;
     use crate::cli::{build_cli, CliCommand};
@@ -1416,6 +2393,314 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_backup_and_restore_args() -> anyhow::Result<()> {
+        {
+            let app = build_cli().no_binary_name(true);
+            let matches = app.try_get_matches_from(vec![
+                "index",
+                "backup",
+                "--config",
+                "/config.yaml",
+                "--index",
+                "foo",
+                "--target-uri",
+                "s3://backups/foo",
+                "--metadata-only",
+            ])?;
+            let command = CliCommand::parse_cli_args(&matches)?;
+            let expected_command = CliCommand::Index(IndexCliCommand::Backup(BackupIndexArgs {
+                config_uri: Uri::from_str("file:///config.yaml").unwrap(),
+                index_id: "foo".to_string(),
+                target_uri: Uri::from_str("s3://backups/foo").unwrap(),
+                include_split_files: false,
+            }));
+            assert_eq!(command, expected_command);
+        }
+        {
+            let app = build_cli().no_binary_name(true);
+            let matches = app.try_get_matches_from(vec![
+                "index",
+                "restore",
+                "--config",
+                "/config.yaml",
+                "--backup-uri",
+                "s3://backups/foo",
+                "--index",
+                "bar",
+            ])?;
+            let command = CliCommand::parse_cli_args(&matches)?;
+            let expected_command = CliCommand::Index(IndexCliCommand::Restore(RestoreIndexArgs {
+                config_uri: Uri::from_str("file:///config.yaml").unwrap(),
+                backup_uri: Uri::from_str("s3://backups/foo").unwrap(),
+                index_id_opt: Some("bar".to_string()),
+                index_uri_opt: None,
+            }));
+            assert_eq!(command, expected_command);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_snapshot_and_attach_args() -> anyhow::Result<()> {
+        {
+            let app = build_cli().no_binary_name(true);
+            let matches = app.try_get_matches_from(vec![
+                "index",
+                "snapshot",
+                "--config",
+                "/config.yaml",
+                "--index",
+                "foo",
+                "--target-uri",
+                "s3://snapshots/foo",
+            ])?;
+            let command = CliCommand::parse_cli_args(&matches)?;
+            let expected_command =
+                CliCommand::Index(IndexCliCommand::Snapshot(SnapshotIndexArgs {
+                    config_uri: Uri::from_str("file:///config.yaml").unwrap(),
+                    index_id: "foo".to_string(),
+                    target_uri: Uri::from_str("s3://snapshots/foo").unwrap(),
+                }));
+            assert_eq!(command, expected_command);
+        }
+        {
+            let app = build_cli().no_binary_name(true);
+            let matches = app.try_get_matches_from(vec![
+                "index",
+                "attach",
+                "--config",
+                "/config.yaml",
+                "--manifest-uri",
+                "s3://snapshots/foo",
+                "--index",
+                "bar",
+            ])?;
+            let command = CliCommand::parse_cli_args(&matches)?;
+            let expected_command = CliCommand::Index(IndexCliCommand::Attach(AttachIndexArgs {
+                config_uri: Uri::from_str("file:///config.yaml").unwrap(),
+                manifest_uri: Uri::from_str("s3://snapshots/foo").unwrap(),
+                index_id_opt: Some("bar".to_string()),
+            }));
+            assert_eq!(command, expected_command);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_export_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "index",
+            "export",
+            "--config",
+            "/config.yaml",
+            "--index",
+            "hdfs-logs",
+            "--fields",
+            "timestamp",
+            "response_time",
+            "--output-uri",
+            "s3://exports/timestamps.parquet",
+            "--format",
+            "parquet",
+            "--query",
+            "severity_text:ERROR",
+            "--start-timestamp",
+            "1000",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Index(IndexCliCommand::Export(ExportIndexArgs {
+            config_uri: Uri::from_str("file:///config.yaml").unwrap(),
+            index_id: "hdfs-logs".to_string(),
+            fast_fields: vec!["timestamp".to_string(), "response_time".to_string()],
+            output_uri: Uri::from_str("s3://exports/timestamps.parquet").unwrap(),
+            format: ExportFormat::Parquet,
+            query: "severity_text:ERROR".to_string(),
+            search_fields: None,
+            start_timestamp: Some(1000),
+            end_timestamp: None,
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tail_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "index",
+            "tail",
+            "--config",
+            "/config.yaml",
+            "--index",
+            "hdfs-logs",
+            "--query",
+            "severity_text:ERROR",
+            "--fields",
+            "timestamp",
+            "body",
+            "--poll-interval",
+            "5s",
+            "--no-color",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Index(IndexCliCommand::Tail(TailIndexArgs {
+            config_uri: Uri::from_str("file:///config.yaml").unwrap(),
+            index_id: "hdfs-logs".to_string(),
+            query: "severity_text:ERROR".to_string(),
+            search_fields: None,
+            fields: vec!["timestamp".to_string(), "body".to_string()],
+            poll_interval: Duration::from_secs(5),
+            max_hits: 100,
+            color: false,
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[test]
+    fn test_published_split_tracker() {
+        let split = |split_id: &str, num_merge_ops: usize| SplitMetadata {
+            split_id: split_id.to_string(),
+            num_merge_ops,
+            ..Default::default()
+        };
+        let split_ids = |splits: Vec<SplitMetadata>| -> Vec<String> {
+            splits.into_iter().map(|split| split.split_id).collect()
+        };
+        let mut split_tracker = PublishedSplitTracker::default();
+        assert_eq!(
+            split_ids(split_tracker.update(vec![split("split-1", 0), split("split-2", 0)])),
+            vec!["split-1", "split-2"]
+        );
+        assert!(split_tracker
+            .update(vec![split("split-1", 0), split("split-2", 0)])
+            .is_empty());
+        // The merged split is ignored.
+        assert_eq!(
+            split_ids(split_tracker.update(vec![split("split-3", 1), split("split-4", 0)])),
+            vec!["split-4"]
+        );
+        assert!(split_tracker.update(vec![split("split-3", 1)]).is_empty());
+    }
+
+    #[test]
+    fn test_format_tail_doc() {
+        let doc = json!({"timestamp": 1, "body": "foo", "severity_text": "INFO"});
+        assert_eq!(
+            format_tail_doc(&doc, &[], false),
+            r#"{"body":"foo","severity_text":"INFO","timestamp":1}"#
+        );
+        let fields = vec![
+            "timestamp".to_string(),
+            "body".to_string(),
+            "missing".to_string(),
+        ];
+        assert_eq!(
+            format_tail_doc(&doc, &fields, false),
+            r#"{"timestamp":1,"body":"foo"}"#
+        );
+    }
+
+    #[test]
+    fn test_export_writer_csv_and_ndjson() -> anyhow::Result<()> {
+        let columns = vec![
+            ("response_time".to_string(), ExportValueType::I64),
+            ("status_code".to_string(), ExportValueType::U64),
+        ];
+        {
+            let mut buffer = Vec::new();
+            let mut export_writer = ExportWriter::new(ExportFormat::Csv, &columns, &mut buffer)?;
+            assert_eq!(
+                export_writer.write_chunks(&[
+                    Bytes::from_static(b"12\n-7\n"),
+                    Bytes::from_static(b"200\n500\n")
+                ])?,
+                2
+            );
+            assert_eq!(
+                export_writer.write_chunks(&[Bytes::new(), Bytes::new()])?,
+                0
+            );
+            export_writer.finish()?;
+            assert_eq!(buffer, b"response_time,status_code\n12,200\n-7,500\n");
+        }
+        {
+            let mut buffer = Vec::new();
+            let mut export_writer = ExportWriter::new(ExportFormat::Ndjson, &columns, &mut buffer)?;
+            assert_eq!(
+                export_writer.write_chunks(&[
+                    Bytes::from_static(b"12\n-7\n"),
+                    Bytes::from_static(b"200\n500\n")
+                ])?,
+                2
+            );
+            export_writer.finish()?;
+            assert_eq!(
+                buffer,
+                b"{\"response_time\":12,\"status_code\":200}\n{\"response_time\":-7,\"status_code\":500}\n"
+            );
+        }
+        {
+            let mut buffer = Vec::new();
+            let mut export_writer = ExportWriter::new(ExportFormat::Csv, &columns, &mut buffer)?;
+            assert!(export_writer
+                .write_chunks(&[
+                    Bytes::from_static(b"12\n-7\n"),
+                    Bytes::from_static(b"200\n")
+                ])
+                .is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_writer_parquet() -> anyhow::Result<()> {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::Field;
+
+        let columns = vec![
+            ("response_time".to_string(), ExportValueType::I64),
+            ("status_code".to_string(), ExportValueType::U64),
+        ];
+        let mut output_file = tempfile::tempfile()?;
+        let mut export_writer =
+            ExportWriter::new(ExportFormat::Parquet, &columns, output_file.try_clone()?)?;
+        assert_eq!(
+            export_writer.write_chunks(&[
+                Bytes::from_static(b"12\n-7\n"),
+                Bytes::from_static(b"200\n500\n")
+            ])?,
+            2
+        );
+        assert_eq!(
+            export_writer
+                .write_chunks(&[Bytes::from_static(b"3\n"), Bytes::from_static(b"204\n")])?,
+            1
+        );
+        export_writer.finish()?;
+
+        std::io::Seek::rewind(&mut output_file)?;
+        let reader = SerializedFileReader::new(output_file)?;
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        let rows: Vec<(i64, u64)> = reader
+            .get_row_iter(None)?
+            .map(|row| {
+                let mut fields = row.get_column_iter();
+                match (fields.next(), fields.next()) {
+                    (
+                        Some((_, Field::Long(response_time))),
+                        Some((_, Field::ULong(status_code))),
+                    ) => (*response_time, *status_code),
+                    fields => panic!("Expected an `i64` and a `u64` value, got `{:?}`.", fields),
+                }
+            })
+            .collect();
+        assert_eq!(rows, vec![(12, 200), (-7, 500), (3, 204)]);
+        Ok(())
+    }
+
     #[test]
     fn test_index_stats() -> anyhow::Result<()> {
         let index_id = "index-stats-env".to_string();
@@ -18,6 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::HashSet;
+use std::net::Ipv4Addr;
 use std::time::Duration;
 
 use anyhow::{bail, Context};
@@ -38,13 +39,17 @@ use tabled::{Alignment, Header, Modify, Style, Table, Tabled};
 use tracing::info;
 
 pub mod cli;
+pub mod doctor;
 pub mod index;
 #[cfg(feature = "jemalloc")]
 pub mod jemalloc;
+pub mod metastore;
+pub mod metrics_push;
 pub mod service;
 pub mod source;
 pub mod split;
 pub mod stats;
+pub mod tool;
 
 /// Throughput calculation window size.
 const THROUGHPUT_WINDOW_SIZE: usize = 5;
@@ -56,6 +61,9 @@ pub const QW_ENABLE_TOKIO_CONSOLE_ENV_KEY: &str = "QW_ENABLE_TOKIO_CONSOLE";
 pub const QW_ENABLE_OPENTELEMETRY_OTLP_EXPORTER_ENV_KEY: &str =
     "QW_ENABLE_OPENTELEMETRY_OTLP_EXPORTER";
 
+/// Format of the logs: `text` (default) or `json`.
+pub const QW_LOG_FORMAT_ENV_KEY: &str = "QW_LOG_FORMAT";
+
 /// Regular expression representing a valid duration with unit.
 pub const DURATION_WITH_UNIT_PATTERN: &str = r#"^(\d{1,3})(s|m|h|d)$"#;
 
@@ -78,10 +86,32 @@ pub fn parse_duration_with_unit(duration_with_unit_str: &str) -> anyhow::Result<
     }
 }
 
-pub fn start_actor_runtimes(services: &HashSet<QuickwitService>) -> anyhow::Result<()> {
+/// Sizes the actor runtimes according to the services running on the node. Returns `None` if
+/// none of the services runs actors.
+fn actor_runtimes_config(
+    services: &HashSet<QuickwitService>,
+    num_cpus: usize,
+) -> Option<RuntimesConfiguration> {
     if services.contains(&QuickwitService::Indexer) || services.contains(&QuickwitService::Janitor)
     {
-        let runtime_configuration = RuntimesConfiguration::default();
+        // Indexing and merging are CPU intensive. On nodes also running the searcher, half of
+        // the cores are left to the search thread pool.
+        let num_actor_cpus = if services.contains(&QuickwitService::Searcher) {
+            (num_cpus / 2).max(1)
+        } else {
+            num_cpus
+        };
+        return Some(RuntimesConfiguration::with_num_cpus(num_actor_cpus));
+    }
+    if services.contains(&QuickwitService::ControlPlane) {
+        // The control plane only runs lightweight actors.
+        return Some(RuntimesConfiguration::with_num_cpus(1));
+    }
+    None
+}
+
+pub fn start_actor_runtimes(services: &HashSet<QuickwitService>) -> anyhow::Result<()> {
+    if let Some(runtime_configuration) = actor_runtimes_config(services, num_cpus::get()) {
         quickwit_common::runtimes::initialize_runtimes(runtime_configuration)
             .context("Failed to start actor runtimes.")?;
     }
@@ -95,6 +125,16 @@ async fn load_quickwit_config(config_uri: &Uri) -> anyhow::Result<QuickwitConfig
     Ok(config)
 }
 
+/// Returns the base URL of the REST API of the node configured by `config`. Unspecified listen
+/// addresses are replaced with localhost.
+fn local_rest_endpoint(config: &QuickwitConfig) -> String {
+    let mut rest_addr = config.rest_listen_addr;
+    if rest_addr.ip().is_unspecified() {
+        rest_addr.set_ip(Ipv4Addr::LOCALHOST.into());
+    }
+    format!("http://{}", rest_addr)
+}
+
 /// Runs connectivity checks for a given `metastore_uri` and `index_id`.
 /// Optionaly, it takes a `SourceConfig` that will be checked instead
 /// of the index's sources.
@@ -169,9 +209,7 @@ fn prompt_confirmation(prompt: &str, default: bool) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
-
-    use super::parse_duration_with_unit;
+    use super::*;
 
     #[test]
     fn test_parse_duration_with_unit() -> anyhow::Result<()> {
@@ -193,4 +231,33 @@ mod tests {
         assert!(parse_duration_with_unit("1h30").is_err());
         Ok(())
     }
+
+    #[test]
+    fn test_actor_runtimes_config() {
+        let services = |services: &[QuickwitService]| -> HashSet<QuickwitService> {
+            services.iter().copied().collect()
+        };
+        assert_eq!(
+            actor_runtimes_config(&services(&[QuickwitService::Indexer]), 16),
+            Some(RuntimesConfiguration::with_num_cpus(16))
+        );
+        assert_eq!(
+            actor_runtimes_config(
+                &services(&[QuickwitService::Indexer, QuickwitService::Searcher]),
+                16
+            ),
+            Some(RuntimesConfiguration::with_num_cpus(8))
+        );
+        assert_eq!(
+            actor_runtimes_config(&services(&[QuickwitService::ControlPlane]), 16),
+            Some(RuntimesConfiguration::with_num_cpus(1))
+        );
+        assert_eq!(
+            actor_runtimes_config(
+                &services(&[QuickwitService::Searcher, QuickwitService::Metastore]),
+                16
+            ),
+            None
+        );
+    }
 }
@@ -19,7 +19,7 @@
 
 use std::env;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use opentelemetry::sdk::propagation::TraceContextPropagator;
 use opentelemetry::sdk::{trace, Resource};
 use opentelemetry::{global, KeyValue};
@@ -27,16 +27,18 @@ use opentelemetry_otlp::WithExportConfig;
 use quickwit_cli::cli::{build_cli, CliCommand};
 #[cfg(feature = "jemalloc")]
 use quickwit_cli::jemalloc::start_jemalloc_metrics_loop;
+use quickwit_cli::metrics_push::start_metrics_push_loop;
 use quickwit_cli::{
     QW_ENABLE_JAEGER_EXPORTER_ENV_KEY, QW_ENABLE_OPENTELEMETRY_OTLP_EXPORTER_ENV_KEY,
+    QW_LOG_FORMAT_ENV_KEY,
 };
-use quickwit_serve::{build_quickwit_build_info, QuickwitBuildInfo};
+use quickwit_serve::{build_quickwit_build_info, set_log_filter_handle, QuickwitBuildInfo};
 use quickwit_telemetry::payload::TelemetryEvent;
 use tonic::metadata::MetadataMap;
 use tracing::{info, Level};
 use tracing_subscriber::fmt::time::UtcTime;
 use tracing_subscriber::prelude::*;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{reload, EnvFilter};
 
 fn setup_logging_and_tracing(level: Level, build_info: &QuickwitBuildInfo) -> anyhow::Result<()> {
     #[cfg(feature = "tokio-console")]
@@ -50,30 +52,17 @@ fn setup_logging_and_tracing(level: Level, build_info: &QuickwitBuildInfo) -> an
         .map(|_| EnvFilter::from_default_env())
         .or_else(|_| EnvFilter::try_new(format!("quickwit={}", level)))
         .context("Failed to set up tracing env filter.")?;
+    // The filter can be changed at runtime through the log filter endpoint.
+    let (env_filter, log_filter_handle) = reload::Layer::new(env_filter);
+    set_log_filter_handle(log_filter_handle);
     global::set_text_map_propagator(TraceContextPropagator::new());
-    let registry = tracing_subscriber::registry().with(env_filter);
-    let event_format = tracing_subscriber::fmt::format()
-        .with_target(true)
-        .with_timer(
-            // We do not rely on the Rfc3339 implementation, because it has a nanosecond precision.
-            // See discussion here: https://github.com/time-rs/time/discussions/418
-            UtcTime::new(
-                time::format_description::parse(
-                    "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z",
-                )
-                .expect("Time format invalid."),
-            ),
-        );
-    if std::env::var_os(QW_ENABLE_JAEGER_EXPORTER_ENV_KEY).is_some() {
+
+    let tracer_opt = if std::env::var_os(QW_ENABLE_JAEGER_EXPORTER_ENV_KEY).is_some() {
         let tracer = opentelemetry_jaeger::new_agent_pipeline()
             .with_service_name("quickwit")
             .install_batch(opentelemetry::runtime::Tokio)
             .context("Failed to initialize Jaeger exporter.")?;
-        registry
-            .with(tracing_opentelemetry::layer().with_tracer(tracer))
-            .with(tracing_subscriber::fmt::layer().event_format(event_format))
-            .try_init()
-            .context("Failed to set up tracing.")?;
+        Some(tracer)
     } else if std::env::var_os(QW_ENABLE_OPENTELEMETRY_OTLP_EXPORTER_ENV_KEY).is_some() {
         let mut metadata_map = MetadataMap::with_capacity(2);
         metadata_map.insert("version", build_info.version.parse()?);
@@ -91,17 +80,43 @@ fn setup_logging_and_tracing(level: Level, build_info: &QuickwitBuildInfo) -> an
             .with_trace_config(trace_config)
             .install_batch(opentelemetry::runtime::Tokio)
             .context("Failed to initialize OpenTelemetry OTLP exporter.")?;
-        registry
-            .with(tracing_opentelemetry::layer().with_tracer(tracer))
-            .with(tracing_subscriber::fmt::layer().event_format(event_format))
-            .try_init()
-            .context("Failed to set up tracing.")?;
+        Some(tracer)
     } else {
-        registry
-            .with(tracing_subscriber::fmt::layer().event_format(event_format))
-            .try_init()
-            .context("Failed to set up tracing.")?;
-    }
+        None
+    };
+    let opentelemetry_layer_opt =
+        tracer_opt.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
+
+    // We do not rely on the Rfc3339 implementation, because it has a nanosecond precision.
+    // See discussion here: https://github.com/time-rs/time/discussions/418
+    let timer = UtcTime::new(
+        time::format_description::parse(
+            "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z",
+        )
+        .expect("Time format invalid."),
+    );
+    let log_format = env::var(QW_LOG_FORMAT_ENV_KEY).unwrap_or_else(|_| "text".to_string());
+    let fmt_layer = match log_format.as_str() {
+        "json" => tracing_subscriber::fmt::layer()
+            .json()
+            .with_target(true)
+            .with_timer(timer)
+            .boxed(),
+        "text" => tracing_subscriber::fmt::layer()
+            .with_target(true)
+            .with_timer(timer)
+            .boxed(),
+        _ => bail!(
+            "Failed to set up tracing: log format `{log_format}` read from environment variable \
+             `{QW_LOG_FORMAT_ENV_KEY}` is not supported. Supported formats are `text` and `json`."
+        ),
+    };
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(opentelemetry_layer_opt)
+        .with(fmt_layer)
+        .try_init()
+        .context("Failed to set up tracing.")?;
     Ok(())
 }
 
@@ -135,12 +150,16 @@ async fn main() -> anyhow::Result<()> {
         version = build_info.version,
         commit = build_info.commit_short_hash,
     );
+    let metrics_push_handle_opt = start_metrics_push_loop()?;
     let return_code: i32 = if let Err(err) = command.execute().await {
         eprintln!("Command failed: {:?}", err);
         1
     } else {
         0
     };
+    if let Some(metrics_push_handle) = metrics_push_handle_opt {
+        metrics_push_handle.terminate().await;
+    }
     quickwit_telemetry::send_telemetry_event(TelemetryEvent::EndCommand { return_code }).await;
     telemetry_handle.terminate_telemetry().await;
     global::shutdown_tracer_provider();
@@ -341,6 +360,7 @@ mod tests {
                 start_offset: 0,
                 search_fields: None,
                 snippet_fields: None,
+                fetch_fields: None,
                 start_timestamp: None,
                 end_timestamp: None,
                 aggregation: None,
@@ -369,6 +389,8 @@ mod tests {
             "url",
             "--snippet-fields",
             "body",
+            "--fetch-fields",
+            "title",
             "--config",
             "/config.yaml",
         ])?;
@@ -384,6 +406,7 @@ mod tests {
                 start_offset: 100,
                 search_fields: Some(search_field_names),
                 snippet_fields: Some(snippet_field_names),
+                fetch_fields: Some(fetch_field_names),
                 start_timestamp: Some(0),
                 end_timestamp: Some(1),
                 config_uri: _config_uri,
@@ -392,6 +415,7 @@ mod tests {
                   && query == "Barack Obama"
                   && search_field_names == vec!["title".to_string(), "url".to_string()]
                   && snippet_field_names == vec!["body".to_string()]
+                  && fetch_field_names == vec!["title".to_string()]
         ));
         Ok(())
     }
@@ -457,6 +481,8 @@ mod tests {
                 index_id,
                 grace_period,
                 dry_run: false,
+                scan_orphans: false,
+                yes: false,
                 ..
             })) if &index_id == "wikipedia" && grace_period == Duration::from_secs(60 * 60)
         ));
@@ -482,8 +508,45 @@ mod tests {
                 grace_period,
                 config_uri,
                 dry_run: true,
+                scan_orphans: false,
+                yes: false,
             })) if &index_id == "wikipedia" && grace_period == Duration::from_secs(5 * 60) && config_uri == expected_config_uri
         ));
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(&[
+            "index",
+            "gc",
+            "--index",
+            "wikipedia",
+            "--config",
+            "/config.yaml",
+            "--scan-orphans",
+            "--yes",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::GarbageCollect(GarbageCollectIndexArgs {
+                dry_run: false,
+                scan_orphans: true,
+                yes: true,
+                ..
+            }))
+        ));
+
+        // `--yes` requires `--scan-orphans`.
+        let app = build_cli().no_binary_name(true);
+        let matches_res = app.try_get_matches_from(&[
+            "index",
+            "gc",
+            "--index",
+            "wikipedia",
+            "--config",
+            "/config.yaml",
+            "--yes",
+        ]);
+        assert!(matches_res.is_err());
         Ok(())
     }
 
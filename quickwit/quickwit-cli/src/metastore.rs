@@ -0,0 +1,367 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::bail;
+use clap::{arg, ArgMatches, Command};
+use itertools::Itertools;
+use quickwit_common::run_checklist;
+use quickwit_common::uri::Uri;
+use quickwit_config::{CLI_INGEST_SOURCE_ID, INGEST_API_SOURCE_ID};
+use quickwit_janitor::list_orphan_files;
+use quickwit_metastore::{quickwit_metastore_uri_resolver, IndexMetadata, Metastore, SplitState};
+use quickwit_storage::quickwit_storage_uri_resolver;
+use tracing::debug;
+
+use crate::load_quickwit_config;
+
+pub fn build_metastore_command<'a>() -> Command<'a> {
+    Command::new("metastore")
+        .about("Performs operations on the metastore (check).")
+        .subcommand(
+            Command::new("check")
+                .about("Cross-validates the split records and checkpoints of the metastore against the content of the index storages and reports dangling references in both directions.")
+                .args(&[
+                    arg!(--index <INDEX_ID> "ID of the index to check. Defaults to all the indexes.")
+                        .display_order(1)
+                        .required(false),
+                ])
+            )
+        .arg_required_else_help(true)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct CheckMetastoreArgs {
+    pub config_uri: Uri,
+    pub index_id_opt: Option<String>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum MetastoreCliCommand {
+    Check(CheckMetastoreArgs),
+}
+
+impl MetastoreCliCommand {
+    pub fn parse_cli_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let (subcommand, submatches) = matches
+            .subcommand()
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse sub-matches."))?;
+        match subcommand {
+            "check" => Self::parse_check_args(submatches),
+            _ => bail!("Subcommand `{}` is not implemented.", subcommand),
+        }
+    }
+
+    fn parse_check_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id_opt = matches.value_of("index").map(String::from);
+        Ok(Self::Check(CheckMetastoreArgs {
+            config_uri,
+            index_id_opt,
+        }))
+    }
+
+    pub async fn execute(self) -> anyhow::Result<()> {
+        match self {
+            Self::Check(args) => check_metastore_cli(args).await,
+        }
+    }
+}
+
+async fn check_metastore_cli(args: CheckMetastoreArgs) -> anyhow::Result<()> {
+    debug!(args = ?args, "check-metastore");
+
+    let quickwit_config = load_quickwit_config(&args.config_uri).await?;
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&quickwit_config.metastore_uri)
+        .await?;
+    let index_metadatas = if let Some(index_id) = &args.index_id_opt {
+        vec![metastore.index_metadata(index_id).await?]
+    } else {
+        metastore.list_indexes_metadatas().await?
+    };
+    let mut checks: Vec<(String, anyhow::Result<()>)> = Vec::new();
+
+    for index_metadata in &index_metadatas {
+        let check_res = check_index_consistency(&*metastore, index_metadata)
+            .await
+            .and_then(|report| {
+                if report.is_consistent() {
+                    Ok(())
+                } else {
+                    bail!("{report}")
+                }
+            });
+        checks.push((format!("index `{}`", index_metadata.index_id), check_res));
+    }
+    let (check_names, check_results): (Vec<String>, Vec<anyhow::Result<()>>) =
+        checks.into_iter().unzip();
+    run_checklist(
+        check_names
+            .iter()
+            .map(String::as_str)
+            .zip(check_results)
+            .collect(),
+    )?;
+    Ok(())
+}
+
+/// Inconsistencies between the metastore records of an index, its checkpoints, and the content of
+/// its storage.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct IndexConsistencyReport {
+    /// Published splits whose file is missing from storage.
+    pub missing_split_files: Vec<String>,
+    /// Split, bloom filter, and Parquet sidecar files of the index storage that no split record
+    /// references.
+    pub orphan_split_files: Vec<PathBuf>,
+    /// Sources of the index without a checkpoint.
+    pub sources_without_checkpoint: Vec<String>,
+    /// Checkpoints of sources that do not exist.
+    pub orphan_checkpoints: Vec<String>,
+}
+
+impl IndexConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_split_files.is_empty()
+            && self.orphan_split_files.is_empty()
+            && self.sources_without_checkpoint.is_empty()
+            && self.orphan_checkpoints.is_empty()
+    }
+}
+
+impl fmt::Display for IndexConsistencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut issues = Vec::new();
+        if !self.missing_split_files.is_empty() {
+            issues.push(format!(
+                "published splits with a missing file: `{}`",
+                self.missing_split_files.join("`, `")
+            ));
+        }
+        if !self.orphan_split_files.is_empty() {
+            issues.push(format!(
+                "files without a split record: `{}`",
+                self.orphan_split_files
+                    .iter()
+                    .map(|path| path.display())
+                    .join("`, `")
+            ));
+        }
+        if !self.sources_without_checkpoint.is_empty() {
+            issues.push(format!(
+                "sources without a checkpoint: `{}`",
+                self.sources_without_checkpoint.join("`, `")
+            ));
+        }
+        if !self.orphan_checkpoints.is_empty() {
+            issues.push(format!(
+                "checkpoints of missing sources: `{}`",
+                self.orphan_checkpoints.join("`, `")
+            ));
+        }
+        write!(f, "{}.", issues.join("; "))
+    }
+}
+
+/// Cross-validates the split records and the checkpoints of an index against each other and
+/// against the content of the index storage.
+///
+/// Staged splits and splits marked for deletion may legitimately miss their file, since they are
+/// being uploaded or deleted, so only published splits are reported.
+pub async fn check_index_consistency(
+    metastore: &dyn Metastore,
+    index_metadata: &IndexMetadata,
+) -> anyhow::Result<IndexConsistencyReport> {
+    let storage_uri_resolver = quickwit_storage_uri_resolver();
+    let index_storage = storage_uri_resolver.resolve(&index_metadata.index_uri)?;
+    let splits = metastore.list_all_splits(&index_metadata.index_id).await?;
+    let split_files: HashSet<PathBuf> = index_storage
+        .list_files()
+        .await?
+        .into_iter()
+        .map(|(path, _)| path)
+        .filter(|path| {
+            path.parent() == Some(Path::new(""))
+                && path.extension().map(|ext| ext == "split").unwrap_or(false)
+        })
+        .collect();
+    let mut report = IndexConsistencyReport::default();
+
+    for split in &splits {
+        if split.split_state != SplitState::Published {
+            continue;
+        }
+        let split_file = PathBuf::from(quickwit_common::split_file(split.split_id()));
+        let is_present = match split.split_metadata.storage_tier.storage_uri() {
+            Some(storage_uri) => {
+                storage_uri_resolver
+                    .resolve(storage_uri)?
+                    .exists(&split_file)
+                    .await?
+            }
+            None => split_files.contains(&split_file),
+        };
+        if !is_present {
+            report
+                .missing_split_files
+                .push(split.split_id().to_string());
+        }
+    }
+    report.orphan_split_files =
+        list_orphan_files(&index_metadata.index_id, &*index_storage, metastore)
+            .await?
+            .into_iter()
+            .map(|file_entry| PathBuf::from(file_entry.file_name))
+            .sorted()
+            .collect();
+    report.sources_without_checkpoint = index_metadata
+        .sources
+        .keys()
+        .filter(|source_id| {
+            index_metadata
+                .checkpoint
+                .source_checkpoint(source_id)
+                .is_none()
+        })
+        .cloned()
+        .sorted()
+        .collect();
+    // The ingest command and the ingest API checkpoint their positions without a source config.
+    report.orphan_checkpoints = index_metadata
+        .checkpoint
+        .source_ids()
+        .filter(|source_id| {
+            !index_metadata.sources.contains_key(*source_id)
+                && *source_id != CLI_INGEST_SOURCE_ID
+                && *source_id != INGEST_API_SOURCE_ID
+        })
+        .map(String::from)
+        .collect();
+    report.missing_split_files.sort();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_metastore::checkpoint::IndexCheckpointDelta;
+    use quickwit_metastore::{metastore_for_test, SplitMetadata};
+
+    use super::*;
+    use crate::cli::{build_cli, CliCommand};
+
+    #[test]
+    fn test_parse_metastore_check_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "metastore",
+            "check",
+            "--index",
+            "wikipedia",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command =
+            CliCommand::Metastore(MetastoreCliCommand::Check(CheckMetastoreArgs {
+                config_uri: Uri::from_str("file:///config.yaml")?,
+                index_id_opt: Some("wikipedia".to_string()),
+            }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_index_consistency() -> anyhow::Result<()> {
+        let index_id = "test-check-index-consistency";
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let metastore = metastore_for_test();
+        let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+        metastore.create_index(index_metadata).await?;
+        metastore
+            .add_source(
+                index_id,
+                SourceConfig {
+                    source_id: "my-source".to_string(),
+                    num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
+                    enabled: true,
+                    source_params: SourceParams::void(),
+                },
+            )
+            .await?;
+        // Publishing a checkpoint delta does not check that the source exists.
+        metastore
+            .publish_splits(
+                index_id,
+                &[],
+                &[],
+                Some(IndexCheckpointDelta::for_test("deleted-source", 0..1)),
+            )
+            .await?;
+
+        for split_id in ["split-present", "split-missing"] {
+            let split_metadata = SplitMetadata {
+                split_id: split_id.to_string(),
+                ..Default::default()
+            };
+            metastore.stage_split(index_id, split_metadata).await?;
+        }
+        metastore
+            .publish_splits(index_id, &["split-present", "split-missing"], &[], None)
+            .await?;
+        let index_storage = quickwit_storage_uri_resolver().resolve(&Uri::from_str(&index_uri)?)?;
+        for split_id in ["split-present", "split-orphan"] {
+            index_storage
+                .put(
+                    Path::new(&quickwit_common::split_file(split_id)),
+                    Box::new(b"split".to_vec()),
+                )
+                .await?;
+        }
+        let index_metadata = metastore.index_metadata(index_id).await?;
+        let report = check_index_consistency(&*metastore, &index_metadata).await?;
+        let expected_report = IndexConsistencyReport {
+            missing_split_files: vec!["split-missing".to_string()],
+            orphan_split_files: vec![PathBuf::from("split-orphan.split")],
+            sources_without_checkpoint: Vec::new(),
+            orphan_checkpoints: vec!["deleted-source".to_string()],
+        };
+        assert_eq!(report, expected_report);
+        assert!(!report.is_consistent());
+        assert_eq!(
+            report.to_string(),
+            "published splits with a missing file: `split-missing`; files without a split record: \
+             `split-orphan.split`; checkpoints of missing sources: `deleted-source`."
+        );
+        Ok(())
+    }
+}
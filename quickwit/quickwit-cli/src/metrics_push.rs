@@ -0,0 +1,217 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use quickwit_common::metrics::{metrics_text_payload, METRICS_TEXT_CONTENT_TYPE};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::parse_duration_with_unit;
+
+/// URL of the Prometheus push gateway metrics are pushed to, for instance
+/// `http://pushgateway:9091`. Metrics are not pushed if the variable is not set.
+pub const QW_METRICS_PUSH_GATEWAY_URL_ENV_KEY: &str = "QW_METRICS_PUSH_GATEWAY_URL";
+
+/// Interval between two pushes, for instance `15s` or `1m`.
+pub const QW_METRICS_PUSH_INTERVAL_ENV_KEY: &str = "QW_METRICS_PUSH_INTERVAL";
+
+/// Name of the job the pushed metrics are grouped under.
+pub const QW_METRICS_PUSH_JOB_ENV_KEY: &str = "QW_METRICS_PUSH_JOB";
+
+const DEFAULT_METRICS_PUSH_INTERVAL: Duration = Duration::from_secs(15);
+
+const DEFAULT_METRICS_PUSH_JOB: &str = "quickwit";
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct MetricsPushConfig {
+    /// Push gateway endpoint of the grouping key, i.e. `<url>/metrics/job/<job>/instance/<host>`.
+    push_url: String,
+    push_interval: Duration,
+}
+
+impl MetricsPushConfig {
+    fn new(
+        gateway_url: &str,
+        job: &str,
+        instance: &str,
+        push_interval: Duration,
+    ) -> anyhow::Result<Self> {
+        if !gateway_url.starts_with("http://") && !gateway_url.starts_with("https://") {
+            bail!(
+                "Push gateway URL `{}` is invalid: the URL must start with `http://` or \
+                 `https://`.",
+                gateway_url
+            );
+        }
+        if job.is_empty() || job.contains('/') || instance.is_empty() || instance.contains('/') {
+            bail!(
+                "Push gateway job `{}` or instance `{}` is invalid: grouping labels must be \
+                 non-empty and may not contain `/`.",
+                job,
+                instance
+            );
+        }
+        let push_url = format!(
+            "{}/metrics/job/{}/instance/{}",
+            gateway_url.trim_end_matches('/'),
+            job,
+            instance
+        );
+        Ok(Self {
+            push_url,
+            push_interval,
+        })
+    }
+
+    fn from_env() -> anyhow::Result<Option<Self>> {
+        let gateway_url = match std::env::var(QW_METRICS_PUSH_GATEWAY_URL_ENV_KEY) {
+            Ok(gateway_url) if !gateway_url.is_empty() => gateway_url,
+            _ => return Ok(None),
+        };
+        let push_interval = match std::env::var(QW_METRICS_PUSH_INTERVAL_ENV_KEY) {
+            Ok(push_interval_str) => {
+                parse_duration_with_unit(&push_interval_str).with_context(|| {
+                    format!(
+                        "Failed to parse environment variable `{}`.",
+                        QW_METRICS_PUSH_INTERVAL_ENV_KEY
+                    )
+                })?
+            }
+            Err(_) => DEFAULT_METRICS_PUSH_INTERVAL,
+        };
+        let job = std::env::var(QW_METRICS_PUSH_JOB_ENV_KEY)
+            .unwrap_or_else(|_| DEFAULT_METRICS_PUSH_JOB.to_string());
+        let instance = hostname::get()
+            .ok()
+            .and_then(|hostname| hostname.into_string().ok())
+            .unwrap_or_else(|| "localhost".to_string());
+        Self::new(&gateway_url, &job, &instance, push_interval).map(Some)
+    }
+}
+
+/// Handle of the loop periodically pushing the metrics of the process to a Prometheus push
+/// gateway.
+pub struct MetricsPushHandle {
+    terminate_tx: oneshot::Sender<()>,
+    join_handle: JoinHandle<()>,
+}
+
+impl MetricsPushHandle {
+    /// Stops the push loop and pushes the metrics one last time, so that the final values of
+    /// short-lived commands are not lost.
+    pub async fn terminate(self) {
+        let _ = self.terminate_tx.send(());
+        let _ = self.join_handle.await;
+    }
+}
+
+/// Starts pushing the metrics of the process to the Prometheus push gateway configured with the
+/// `QW_METRICS_PUSH_GATEWAY_URL` environment variable. Batch commands, such as a local ingest,
+/// usually terminate before a Prometheus server gets a chance to scrape them. Returns `None` if no
+/// push gateway is configured.
+pub fn start_metrics_push_loop() -> anyhow::Result<Option<MetricsPushHandle>> {
+    let metrics_push_config = match MetricsPushConfig::from_env()? {
+        Some(metrics_push_config) => metrics_push_config,
+        None => return Ok(None),
+    };
+    let (terminate_tx, terminate_rx) = oneshot::channel();
+    let join_handle = tokio::spawn(metrics_push_loop(metrics_push_config, terminate_rx));
+    Ok(Some(MetricsPushHandle {
+        terminate_tx,
+        join_handle,
+    }))
+}
+
+async fn metrics_push_loop(
+    metrics_push_config: MetricsPushConfig,
+    mut terminate_rx: oneshot::Receiver<()>,
+) {
+    let client = reqwest::Client::new();
+    let mut push_interval = tokio::time::interval(metrics_push_config.push_interval);
+    loop {
+        tokio::select! {
+            _ = push_interval.tick() => {}
+            _ = &mut terminate_rx => break,
+        }
+        if let Err(error) = push_metrics(&client, &metrics_push_config.push_url).await {
+            warn!(error = ?error, "Failed to push metrics to the push gateway.");
+        }
+    }
+    if let Err(error) = push_metrics(&client, &metrics_push_config.push_url).await {
+        warn!(error = ?error, "Failed to push metrics to the push gateway.");
+    }
+}
+
+/// Replaces the metrics of the grouping key with the current metrics of the process.
+async fn push_metrics(client: &reqwest::Client, push_url: &str) -> anyhow::Result<()> {
+    let response = client
+        .put(push_url)
+        .header(reqwest::header::CONTENT_TYPE, METRICS_TEXT_CONTENT_TYPE)
+        .body(metrics_text_payload())
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        bail!(
+            "Push gateway responded with status code `{}`.",
+            response.status()
+        );
+    }
+    debug!(push_url = %push_url, "Pushed metrics to the push gateway.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_push_config() {
+        let metrics_push_config = MetricsPushConfig::new(
+            "http://pushgateway:9091/",
+            "backfill",
+            "host-1",
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert_eq!(
+            metrics_push_config.push_url,
+            "http://pushgateway:9091/metrics/job/backfill/instance/host-1"
+        );
+        assert_eq!(metrics_push_config.push_interval, Duration::from_secs(5));
+
+        MetricsPushConfig::new(
+            "pushgateway:9091",
+            "quickwit",
+            "host-1",
+            Duration::from_secs(5),
+        )
+        .unwrap_err();
+        MetricsPushConfig::new(
+            "http://pushgateway:9091",
+            "quickwit/job",
+            "host-1",
+            Duration::from_secs(5),
+        )
+        .unwrap_err();
+    }
+}
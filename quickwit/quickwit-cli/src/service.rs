@@ -34,7 +34,7 @@ pub fn build_run_command<'a>() -> Command<'a> {
     Command::new("run")
         .about("Runs quickwit services. By default, `metastore`, `indexer` and `searcher` are started.")
         .args(&[
-            arg!(--"service" <SERVICE> "Services (indexer|searcher|janitor|metastore) to run. If unspecified, all the supported services are started.")
+            arg!(--"service" <SERVICE> "Services (indexer|searcher|janitor|metastore|control_plane) to run. If unspecified, all the supported services are started.")
                 .multiple_occurrences(true)
                 .required(false),
         ])
@@ -17,21 +17,44 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::cmp::Ordering;
 use std::str::FromStr;
 
 use anyhow::{bail, Context};
-use clap::{arg, ArgMatches, Command};
+use clap::{arg, Arg, ArgMatches, Command};
 use itertools::Itertools;
 use quickwit_common::uri::Uri;
-use quickwit_config::{validate_identifier, SourceConfig, SourceParams, INGEST_API_SOURCE_ID};
+use quickwit_config::{
+    validate_identifier, QuickwitConfig, SourceConfig, SourceParams, INGEST_API_SOURCE_ID,
+};
 use quickwit_indexing::check_source_connectivity;
-use quickwit_metastore::checkpoint::SourceCheckpoint;
+use quickwit_indexing::models::ReloadSourcePipelinesResponse;
+use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
 use quickwit_metastore::{quickwit_metastore_uri_resolver, IndexMetadata};
 use quickwit_storage::load_file;
 use serde_json::Value;
 use tabled::{Table, Tabled};
 
-use crate::{load_quickwit_config, make_table};
+use crate::{load_quickwit_config, local_rest_endpoint, make_table};
+
+fn endpoint_arg<'a>() -> Arg<'a> {
+    arg!(--endpoint <ENDPOINT> "REST endpoint of an indexer to notify of the change, e.g. `http://indexer-1:7280`. Can be repeated. Defaults to the REST listen address of the node config.")
+        .multiple_occurrences(true)
+        .required(false)
+}
+
+fn reset_checkpoint_args<'a>() -> Vec<Arg<'a>> {
+    vec![
+        arg!(--index <INDEX_ID> "Index ID").display_order(1),
+        arg!(--source <SOURCE_ID> "Source ID").display_order(2),
+        arg!(--partition <PARTITION_ID> "ID of a partition to reset. Can be repeated. By default, all the partitions are reset.")
+            .multiple_occurrences(true)
+            .required(false),
+        arg!(--"dry-run" "Displays the data that would be re-ingested without modifying the checkpoint.")
+            .required(false),
+        endpoint_arg(),
+    ]
+}
 
 pub fn build_source_command<'a>() -> Command<'a> {
     Command::new("source")
@@ -45,20 +68,32 @@ pub fn build_source_command<'a>() -> Command<'a> {
                     arg!(--"source-config" <SOURCE_CONFIG> "Path to source config file. Please, refer to the documentation for more details."),
                 ])
             )
+        .subcommand(
+            Command::new("update")
+                .about("Updates the config of a source and restarts its indexing pipelines.")
+                .args(&[
+                    arg!(--index <INDEX_ID> "ID of the target index")
+                        .display_order(1),
+                    arg!(--"source-config" <SOURCE_CONFIG> "Path to source config file. The source ID and type cannot be changed."),
+                    endpoint_arg(),
+                ])
+            )
         .subcommand(
             Command::new("enable")
-                .about("Enables a source for an index.")
+                .about("Enables a source for an index and starts its indexing pipelines.")
                 .args(&[
                     arg!(--index <INDEX_ID> "ID of the target index"),
                     arg!(--source <SOURCE_ID> "ID of the source."),
+                    endpoint_arg(),
                 ])
             )
         .subcommand(
             Command::new("disable")
-                .about("Disables a source for an index.")
+                .about("Disables a source for an index and stops its indexing pipelines.")
                 .args(&[
                     arg!(--index <INDEX_ID> "ID of the target index"),
                     arg!(--source <SOURCE_ID> "ID of the source."),
+                    endpoint_arg(),
                 ])
             )
         .subcommand(
@@ -96,12 +131,42 @@ pub fn build_source_command<'a>() -> Command<'a> {
             Command::new("reset-checkpoint")
                 .about("Resets a source checkpoint. This operation is destructive and cannot be undone. Proceed with caution.")
                 .alias("reset")
-                .args(&[
-                    arg!(--index <INDEX_ID> "Index ID")
-                        .display_order(1),
-                    arg!(--source <SOURCE_ID> "Source ID")
-                        .display_order(2),
-                ])
+                .args(reset_checkpoint_args())
+            )
+        .subcommand(
+            Command::new("checkpoint")
+                .about("Inspects and modifies source checkpoints.")
+                .subcommand(
+                    Command::new("show")
+                        .about("Displays the position reached in each partition of a source.")
+                        .args(&[
+                            arg!(--index <INDEX_ID> "Index ID")
+                                .display_order(1),
+                            arg!(--source <SOURCE_ID> "Source ID")
+                                .display_order(2),
+                        ])
+                    )
+                .subcommand(
+                    Command::new("reset")
+                        .about("Resets the positions of a source, or only some of its partitions, so that their data is re-ingested from the beginning.")
+                        .args(reset_checkpoint_args())
+                    )
+                .subcommand(
+                    Command::new("seek")
+                        .about("Moves the position of a partition of a source. Moving backward re-ingests data, moving forward skips data.")
+                        .args(&[
+                            arg!(--index <INDEX_ID> "Index ID")
+                                .display_order(1),
+                            arg!(--source <SOURCE_ID> "Source ID")
+                                .display_order(2),
+                            arg!(--partition <PARTITION_ID> "ID of the partition."),
+                            arg!(--position <POSITION> "New position of the partition. Use `beginning` to re-ingest the whole partition."),
+                            arg!(--"dry-run" "Displays the data that would be re-ingested or skipped without modifying the checkpoint.")
+                                .required(false),
+                            endpoint_arg(),
+                        ])
+                    )
+                .arg_required_else_help(true)
             )
         .arg_required_else_help(true)
 }
@@ -113,12 +178,21 @@ pub struct CreateSourceArgs {
     pub source_config_uri: Uri,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct UpdateSourceArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub source_config_uri: Uri,
+    pub endpoints: Vec<String>,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct ToggleSourceArgs {
     pub config_uri: Uri,
     pub index_id: String,
     pub source_id: String,
     pub enable: bool,
+    pub endpoints: Vec<String>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -141,32 +215,60 @@ pub struct ListSourcesArgs {
     pub index_id: String,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct ShowCheckpointArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub source_id: String,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct ResetCheckpointArgs {
     pub config_uri: Uri,
     pub index_id: String,
     pub source_id: String,
+    /// Partitions to reset. All the partitions are reset if empty.
+    pub partition_ids: Vec<String>,
+    pub dry_run: bool,
+    pub endpoints: Vec<String>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct SeekCheckpointArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub source_id: String,
+    pub partition_id: String,
+    pub position: String,
+    pub dry_run: bool,
+    pub endpoints: Vec<String>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum SourceCliCommand {
     CreateSource(CreateSourceArgs),
+    UpdateSource(UpdateSourceArgs),
     ToggleSource(ToggleSourceArgs),
     DeleteSource(DeleteSourceArgs),
     DescribeSource(DescribeSourceArgs),
     ListSources(ListSourcesArgs),
+    ShowCheckpoint(ShowCheckpointArgs),
     ResetCheckpoint(ResetCheckpointArgs),
+    SeekCheckpoint(SeekCheckpointArgs),
 }
 
 impl SourceCliCommand {
     pub async fn execute(self) -> anyhow::Result<()> {
         match self {
             Self::CreateSource(args) => create_source_cli(args).await,
+            Self::UpdateSource(args) => update_source_cli(args).await,
             Self::ToggleSource(args) => toggle_source_cli(args).await,
             Self::DeleteSource(args) => delete_source_cli(args).await,
             Self::DescribeSource(args) => describe_source_cli(args).await,
             Self::ListSources(args) => list_sources_cli(args).await,
+            Self::ShowCheckpoint(args) => show_checkpoint_cli(args).await,
             Self::ResetCheckpoint(args) => reset_checkpoint_cli(args).await,
+            Self::SeekCheckpoint(args) => seek_checkpoint_cli(args).await,
         }
     }
 
@@ -176,6 +278,7 @@ impl SourceCliCommand {
             .ok_or_else(|| anyhow::anyhow!("Failed to parse source subcommand arguments."))?;
         match subcommand {
             "create" => Self::parse_create_args(submatches).map(Self::CreateSource),
+            "update" => Self::parse_update_args(submatches).map(Self::UpdateSource),
             "enable" => {
                 Self::parse_toggle_source_args(subcommand, submatches).map(Self::ToggleSource)
             }
@@ -188,10 +291,77 @@ impl SourceCliCommand {
             "reset-checkpoint" => {
                 Self::parse_reset_checkpoint_args(submatches).map(Self::ResetCheckpoint)
             }
+            "checkpoint" => Self::parse_checkpoint_args(submatches),
             _ => bail!("Source subcommand `{}` is not implemented.", subcommand),
         }
     }
 
+    fn parse_checkpoint_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let (subcommand, submatches) = matches
+            .subcommand()
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse checkpoint subcommand arguments."))?;
+        match subcommand {
+            "show" => Self::parse_show_checkpoint_args(submatches).map(Self::ShowCheckpoint),
+            "reset" => Self::parse_reset_checkpoint_args(submatches).map(Self::ResetCheckpoint),
+            "seek" => Self::parse_seek_checkpoint_args(submatches).map(Self::SeekCheckpoint),
+            _ => bail!("Checkpoint subcommand `{}` is not implemented.", subcommand),
+        }
+    }
+
+    fn parse_show_checkpoint_args(matches: &ArgMatches) -> anyhow::Result<ShowCheckpointArgs> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .map(String::from)
+            .expect("`index` is a required arg.");
+        let source_id = matches
+            .value_of("source")
+            .map(String::from)
+            .expect("`source` is a required arg.");
+        Ok(ShowCheckpointArgs {
+            config_uri,
+            index_id,
+            source_id,
+        })
+    }
+
+    fn parse_seek_checkpoint_args(matches: &ArgMatches) -> anyhow::Result<SeekCheckpointArgs> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .map(String::from)
+            .expect("`index` is a required arg.");
+        let source_id = matches
+            .value_of("source")
+            .map(String::from)
+            .expect("`source` is a required arg.");
+        let partition_id = matches
+            .value_of("partition")
+            .map(String::from)
+            .expect("`partition` is a required arg.");
+        let position = matches
+            .value_of("position")
+            .map(String::from)
+            .expect("`position` is a required arg.");
+        let dry_run = matches.is_present("dry-run");
+        let endpoints = parse_endpoints(matches);
+        Ok(SeekCheckpointArgs {
+            config_uri,
+            index_id,
+            source_id,
+            partition_id,
+            position,
+            dry_run,
+            endpoints,
+        })
+    }
+
     fn parse_create_args(matches: &ArgMatches) -> anyhow::Result<CreateSourceArgs> {
         let config_uri = matches
             .value_of("config")
@@ -212,6 +382,28 @@ impl SourceCliCommand {
         })
     }
 
+    fn parse_update_args(matches: &ArgMatches) -> anyhow::Result<UpdateSourceArgs> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .map(String::from)
+            .expect("`index` is a required arg.");
+        let source_config_uri = matches
+            .value_of("source-config")
+            .map(Uri::from_str)
+            .expect("`source-config` is a required arg.")?;
+        let endpoints = parse_endpoints(matches);
+        Ok(UpdateSourceArgs {
+            config_uri,
+            index_id,
+            source_config_uri,
+            endpoints,
+        })
+    }
+
     fn parse_toggle_source_args(
         subcommand: &str,
         matches: &ArgMatches,
@@ -229,11 +421,13 @@ impl SourceCliCommand {
             .map(String::from)
             .expect("`source` is a required arg.");
         let enable = matches!(subcommand, "enable");
+        let endpoints = parse_endpoints(matches);
         Ok(ToggleSourceArgs {
             config_uri,
             index_id,
             source_id,
             enable,
+            endpoints,
         })
     }
 
@@ -305,14 +499,34 @@ impl SourceCliCommand {
             .value_of("source")
             .map(String::from)
             .expect("`source` is a required arg.");
+        let partition_ids = matches
+            .values_of("partition")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default();
+        let dry_run = matches.is_present("dry-run");
+        let endpoints = parse_endpoints(matches);
         Ok(ResetCheckpointArgs {
             config_uri,
             index_id,
             source_id,
+            partition_ids,
+            dry_run,
+            endpoints,
         })
     }
 }
 
+fn parse_endpoints(matches: &ArgMatches) -> Vec<String> {
+    matches
+        .values_of("endpoint")
+        .map(|endpoints| {
+            endpoints
+                .map(|endpoint| endpoint.trim_end_matches('/').to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 async fn create_source_cli(args: CreateSourceArgs) -> anyhow::Result<()> {
     let qw_config = load_quickwit_config(&args.config_uri).await?;
     let metastore = quickwit_metastore_uri_resolver()
@@ -342,6 +556,54 @@ async fn create_source_cli(args: CreateSourceArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn update_source_cli(args: UpdateSourceArgs) -> anyhow::Result<()> {
+    let config = load_quickwit_config(&args.config_uri).await?;
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&config.metastore_uri)
+        .await?;
+    let source_config_content = load_file(&args.source_config_uri).await?;
+    let source_config =
+        SourceConfig::load(&args.source_config_uri, source_config_content.as_slice()).await?;
+    if let SourceParams::IngestApi = source_config.source_params {
+        bail!(
+            "Source `{}` is managed by Quickwit, you cannot update a source managed by Quickwit.",
+            source_config.source_id
+        );
+    }
+    let index_metadata = metastore.index_metadata(&args.index_id).await?;
+    let current_source_config = index_metadata
+        .sources
+        .get(&source_config.source_id)
+        .with_context(|| {
+            format!(
+                "Source `{}` does not exist for index `{}`.",
+                source_config.source_id, args.index_id
+            )
+        })?;
+    // The checkpoint of the source is kept, so it must keep pointing to the same kind of data.
+    if current_source_config.source_type() != source_config.source_type() {
+        bail!(
+            "Failed to update source `{}`: the source type cannot be changed from `{}` to `{}`. \
+             Delete the source and create a new one instead.",
+            source_config.source_id,
+            current_source_config.source_type(),
+            source_config.source_type()
+        );
+    }
+    let source_id = source_config.source_id.clone();
+    check_source_connectivity(&source_config).await?;
+
+    metastore
+        .update_source(&args.index_id, source_config)
+        .await?;
+    println!(
+        "Source `{}` successfully updated for index `{}`.",
+        source_id, args.index_id
+    );
+    reload_source_pipelines(&config, args.endpoints, &args.index_id, &source_id).await;
+    Ok(())
+}
+
 async fn toggle_source_cli(args: ToggleSourceArgs) -> anyhow::Result<()> {
     let config = load_quickwit_config(&args.config_uri).await?;
     let metastore = quickwit_metastore_uri_resolver()
@@ -364,9 +626,62 @@ async fn toggle_source_cli(args: ToggleSourceArgs) -> anyhow::Result<()> {
         "Source `{}` successfully {} for index `{}`.",
         args.source_id, toggled_state_name, args.index_id
     );
+    reload_source_pipelines(&config, args.endpoints, &args.index_id, &args.source_id).await;
     Ok(())
 }
 
+/// Asks the indexers to restart the pipelines of a source so that the changes made to the source
+/// in the metastore take effect immediately. Indexers that cannot be reached pick up the changes
+/// on their next restart, so failures are reported but do not fail the command.
+async fn reload_source_pipelines(
+    config: &QuickwitConfig,
+    endpoints: Vec<String>,
+    index_id: &str,
+    source_id: &str,
+) {
+    let endpoints = if endpoints.is_empty() {
+        vec![local_rest_endpoint(config)]
+    } else {
+        endpoints
+    };
+    let client = reqwest::Client::new();
+    for endpoint in endpoints {
+        match reload_source_pipelines_on_indexer(&client, &endpoint, index_id, source_id).await {
+            Ok(response) => println!(
+                "Indexer `{}`: {} pipeline(s) stopped, {} pipeline(s) started.",
+                endpoint, response.num_shutdown_pipelines, response.num_spawned_pipelines
+            ),
+            Err(error) => println!(
+                "Failed to reload the pipelines of source `{}` on indexer `{}`: {:#}. The change \
+                 will take effect when the indexer restarts.",
+                source_id, endpoint, error
+            ),
+        }
+    }
+}
+
+async fn reload_source_pipelines_on_indexer(
+    client: &reqwest::Client,
+    endpoint: &str,
+    index_id: &str,
+    source_id: &str,
+) -> anyhow::Result<ReloadSourcePipelinesResponse> {
+    let url = format!(
+        "{}/api/v1/{}/sources/{}/reload",
+        endpoint, index_id, source_id
+    );
+    let response = client
+        .post(&url)
+        .send()
+        .await
+        .context("Indexer is unreachable")?;
+    if !response.status().is_success() {
+        bail!("{}: {}", response.status(), response.text().await?);
+    }
+    let reload_response = response.json().await?;
+    Ok(reload_response)
+}
+
 async fn delete_source_cli(args: DeleteSourceArgs) -> anyhow::Result<()> {
     let config = load_quickwit_config(&args.config_uri).await?;
     let metastore = quickwit_metastore_uri_resolver()
@@ -526,21 +841,239 @@ fn flatten_json(value: Value) -> Vec<(String, Value)> {
     acc
 }
 
+async fn show_checkpoint_cli(args: ShowCheckpointArgs) -> anyhow::Result<()> {
+    let config = load_quickwit_config(&args.config_uri).await?;
+    let index_metadata = resolve_index(&config.metastore_uri, &args.index_id).await?;
+    let source_checkpoint = find_source_checkpoint(&index_metadata, &args.source_id)?;
+    let checkpoint_rows = source_checkpoint
+        .iter()
+        .map(|(partition_id, position)| CheckpointRow {
+            partition_id: display_offset(&partition_id.0),
+            offset: display_position(&position),
+        });
+    println!(
+        "{}",
+        make_table(
+            &format!("Checkpoint of source `{}`", args.source_id),
+            checkpoint_rows,
+            false
+        )
+    );
+    Ok(())
+}
+
 async fn reset_checkpoint_cli(args: ResetCheckpointArgs) -> anyhow::Result<()> {
     let config = load_quickwit_config(&args.config_uri).await?;
     let metastore = quickwit_metastore_uri_resolver()
         .resolve(&config.metastore_uri)
         .await?;
+    let index_metadata = metastore.index_metadata(&args.index_id).await?;
+    let current_checkpoint = find_source_checkpoint(&index_metadata, &args.source_id)?;
+    let partition_ids: Vec<PartitionId> = args
+        .partition_ids
+        .iter()
+        .map(|partition_id| resolve_partition_id(&current_checkpoint, partition_id))
+        .collect();
+    let new_checkpoint: SourceCheckpoint = current_checkpoint
+        .iter()
+        .filter(|(partition_id, _)| {
+            !partition_ids.is_empty() && !partition_ids.contains(partition_id)
+        })
+        .collect();
+    display_checkpoint_changes(&current_checkpoint, &new_checkpoint);
+    if args.dry_run {
+        println!("Dry run: the checkpoint was not modified.");
+        return Ok(());
+    }
+    if partition_ids.is_empty() {
+        metastore
+            .reset_source_checkpoint(&args.index_id, &args.source_id)
+            .await?;
+    } else {
+        metastore
+            .update_source_checkpoint(&args.index_id, &args.source_id, new_checkpoint)
+            .await?;
+    }
+    println!(
+        "Checkpoint successfully reset for index `{}` and source `{}`.",
+        args.index_id, args.source_id
+    );
+    reload_source_pipelines(&config, args.endpoints, &args.index_id, &args.source_id).await;
+    Ok(())
+}
+
+async fn seek_checkpoint_cli(args: SeekCheckpointArgs) -> anyhow::Result<()> {
+    let config = load_quickwit_config(&args.config_uri).await?;
+    let metastore = quickwit_metastore_uri_resolver()
+        .resolve(&config.metastore_uri)
+        .await?;
+    let index_metadata = metastore.index_metadata(&args.index_id).await?;
+    let current_checkpoint = find_source_checkpoint(&index_metadata, &args.source_id)?;
+    let partition_id = resolve_partition_id(&current_checkpoint, &args.partition_id);
+    let position = parse_position(&args.position);
+    let new_checkpoint: SourceCheckpoint = current_checkpoint
+        .iter()
+        .filter(|(current_partition_id, _)| *current_partition_id != partition_id)
+        .chain(std::iter::once((partition_id.clone(), position)))
+        .collect();
+    display_checkpoint_changes(&current_checkpoint, &new_checkpoint);
+    if args.dry_run {
+        println!("Dry run: the checkpoint was not modified.");
+        return Ok(());
+    }
     metastore
-        .reset_source_checkpoint(&args.index_id, &args.source_id)
+        .update_source_checkpoint(&args.index_id, &args.source_id, new_checkpoint)
         .await?;
     println!(
-        "Checkpoint successfully deleted for index `{}` and source `{}`.",
+        "Checkpoint successfully updated for index `{}` and source `{}`.",
         args.index_id, args.source_id
     );
+    reload_source_pipelines(&config, args.endpoints, &args.index_id, &args.source_id).await;
     Ok(())
 }
 
+fn find_source_checkpoint(
+    index_metadata: &IndexMetadata,
+    source_id: &str,
+) -> anyhow::Result<SourceCheckpoint> {
+    // Sources that are not registered, such as the one of `index ingest`, may have a checkpoint.
+    match index_metadata.checkpoint.source_checkpoint(source_id) {
+        Some(source_checkpoint) => Ok(source_checkpoint.clone()),
+        None if index_metadata.sources.contains_key(source_id) => Ok(SourceCheckpoint::default()),
+        None => bail!(
+            "Source `{}` does not exist for index `{}`.",
+            source_id,
+            index_metadata.index_id
+        ),
+    }
+}
+
+/// Numeric partition IDs and positions are stored as 0-left-padded 20-digit strings so that
+/// their lexicographical order matches their natural order.
+const NUM_OFFSET_DIGITS: usize = 20;
+
+fn is_padded_offset(offset: &str) -> bool {
+    offset.len() == NUM_OFFSET_DIGITS && offset.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Strips the padding of numeric partition IDs and positions.
+fn display_offset(offset: &str) -> String {
+    if is_padded_offset(offset) {
+        let trimmed_offset = offset.trim_start_matches('0');
+        if trimmed_offset.is_empty() {
+            return "0".to_string();
+        }
+        return trimmed_offset.to_string();
+    }
+    offset.to_string()
+}
+
+fn display_position(position: &Position) -> String {
+    match position {
+        Position::Beginning => "beginning".to_string(),
+        Position::Offset(offset) => display_offset(offset),
+    }
+}
+
+/// Resolves a partition ID passed on the command line, such as `0`, to the partition ID stored in
+/// the checkpoint, such as `00000000000000000000`.
+fn resolve_partition_id(checkpoint: &SourceCheckpoint, partition_id: &str) -> PartitionId {
+    checkpoint
+        .iter()
+        .map(|(checkpoint_partition_id, _)| checkpoint_partition_id)
+        .find(|checkpoint_partition_id| {
+            *checkpoint_partition_id.0 == partition_id
+                || (is_padded_offset(&checkpoint_partition_id.0)
+                    && display_offset(&checkpoint_partition_id.0) == partition_id)
+        })
+        .unwrap_or_else(|| match partition_id.parse::<u64>() {
+            Ok(numeric_partition_id) => PartitionId::from(numeric_partition_id),
+            Err(_) => PartitionId::from(partition_id),
+        })
+}
+
+fn parse_position(position: &str) -> Position {
+    if position.is_empty() || position.eq_ignore_ascii_case("beginning") {
+        return Position::Beginning;
+    }
+    match position.parse::<u64>() {
+        Ok(offset) => Position::from(offset),
+        Err(_) => Position::from(position),
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Tabled)]
+struct CheckpointChangeRow {
+    #[tabled(rename = "Partition ID")]
+    partition_id: String,
+    #[tabled(rename = "Current Position")]
+    current_position: String,
+    #[tabled(rename = "New Position")]
+    new_position: String,
+    #[tabled(rename = "Effect")]
+    effect: String,
+}
+
+/// Describes, for each partition, the data that is re-ingested or skipped when the checkpoint of
+/// a source goes from `current_checkpoint` to `new_checkpoint`. Partitions missing from a
+/// checkpoint are at the beginning.
+fn make_checkpoint_change_rows(
+    current_checkpoint: &SourceCheckpoint,
+    new_checkpoint: &SourceCheckpoint,
+) -> Vec<CheckpointChangeRow> {
+    current_checkpoint
+        .iter()
+        .chain(new_checkpoint.iter())
+        .map(|(partition_id, _)| partition_id)
+        .sorted()
+        .dedup()
+        .map(|partition_id| {
+            let current_position = current_checkpoint
+                .position_for_partition(&partition_id)
+                .cloned()
+                .unwrap_or(Position::Beginning);
+            let new_position = new_checkpoint
+                .position_for_partition(&partition_id)
+                .cloned()
+                .unwrap_or(Position::Beginning);
+            let effect = match new_position.cmp(&current_position) {
+                Ordering::Equal => "Unchanged".to_string(),
+                Ordering::Less => format!(
+                    "Re-ingests data after `{}` up to `{}`",
+                    display_position(&new_position),
+                    display_position(&current_position)
+                ),
+                Ordering::Greater => format!(
+                    "Skips data after `{}` up to `{}`",
+                    display_position(&current_position),
+                    display_position(&new_position)
+                ),
+            };
+            CheckpointChangeRow {
+                partition_id: display_offset(&partition_id.0),
+                current_position: display_position(&current_position),
+                new_position: display_position(&new_position),
+                effect,
+            }
+        })
+        .collect()
+}
+
+fn display_checkpoint_changes(
+    current_checkpoint: &SourceCheckpoint,
+    new_checkpoint: &SourceCheckpoint,
+) {
+    let checkpoint_change_rows = make_checkpoint_change_rows(current_checkpoint, new_checkpoint);
+    if checkpoint_change_rows.is_empty() {
+        println!("The checkpoint is empty: no data would be re-ingested.");
+        return;
+    }
+    println!(
+        "{}",
+        make_table("Checkpoint Changes", checkpoint_change_rows, false)
+    );
+}
+
 async fn resolve_index(metastore_uri: &Uri, index_id: &str) -> anyhow::Result<IndexMetadata> {
     let metastore_uri_resolver = quickwit_metastore_uri_resolver();
     let metastore = metastore_uri_resolver.resolve(metastore_uri).await?;
@@ -601,6 +1134,32 @@ mod tests {
         assert_eq!(command, expected_command);
     }
 
+    #[test]
+    fn test_parse_update_source_args() {
+        let app = build_cli().no_binary_name(true);
+        let matches = app
+            .try_get_matches_from(vec![
+                "source",
+                "update",
+                "--index",
+                "hdfs-logs",
+                "--source-config",
+                "/source-conf.yaml",
+                "--config",
+                "/conf.yaml",
+            ])
+            .unwrap();
+        let command = CliCommand::parse_cli_args(&matches).unwrap();
+        let expected_command =
+            CliCommand::Source(SourceCliCommand::UpdateSource(UpdateSourceArgs {
+                config_uri: Uri::from_str("file:///conf.yaml").unwrap(),
+                index_id: "hdfs-logs".to_string(),
+                source_config_uri: Uri::from_str("file:///source-conf.yaml").unwrap(),
+                endpoints: Vec::new(),
+            }));
+        assert_eq!(command, expected_command);
+    }
+
     #[test]
     fn test_parse_toggle_source_args() {
         {
@@ -624,6 +1183,7 @@ mod tests {
                     index_id: "hdfs-logs".to_string(),
                     source_id: "kafka-foo".to_string(),
                     enable: true,
+                    endpoints: Vec::new(),
                 }));
             assert_eq!(command, expected_command);
         }
@@ -639,6 +1199,10 @@ mod tests {
                     "kafka-foo",
                     "--config",
                     "/conf.yaml",
+                    "--endpoint",
+                    "http://indexer-1:7280/",
+                    "--endpoint",
+                    "http://indexer-2:7280",
                 ])
                 .unwrap();
             let command = CliCommand::parse_cli_args(&matches).unwrap();
@@ -648,6 +1212,10 @@ mod tests {
                     index_id: "hdfs-logs".to_string(),
                     source_id: "kafka-foo".to_string(),
                     enable: false,
+                    endpoints: vec![
+                        "http://indexer-1:7280".to_string(),
+                        "http://indexer-2:7280".to_string(),
+                    ],
                 }));
             assert_eq!(command, expected_command);
         }
@@ -674,10 +1242,186 @@ mod tests {
                 config_uri: Uri::from_str("file:///conf.yaml").unwrap(),
                 index_id: "hdfs-logs".to_string(),
                 source_id: "hdfs-logs-source".to_string(),
+                partition_ids: Vec::new(),
+                dry_run: false,
+                endpoints: Vec::new(),
             }));
         assert_eq!(command, expected_command);
     }
 
+    #[test]
+    fn test_parse_checkpoint_args() {
+        {
+            let app = build_cli().no_binary_name(true);
+            let matches = app
+                .try_get_matches_from(vec![
+                    "source",
+                    "checkpoint",
+                    "show",
+                    "--index",
+                    "hdfs-logs",
+                    "--source",
+                    "kafka-foo",
+                    "--config",
+                    "/conf.yaml",
+                ])
+                .unwrap();
+            let command = CliCommand::parse_cli_args(&matches).unwrap();
+            let expected_command =
+                CliCommand::Source(SourceCliCommand::ShowCheckpoint(ShowCheckpointArgs {
+                    config_uri: Uri::from_str("file:///conf.yaml").unwrap(),
+                    index_id: "hdfs-logs".to_string(),
+                    source_id: "kafka-foo".to_string(),
+                }));
+            assert_eq!(command, expected_command);
+        }
+        {
+            let app = build_cli().no_binary_name(true);
+            let matches = app
+                .try_get_matches_from(vec![
+                    "source",
+                    "checkpoint",
+                    "reset",
+                    "--index",
+                    "hdfs-logs",
+                    "--source",
+                    "kafka-foo",
+                    "--partition",
+                    "0",
+                    "--partition",
+                    "2",
+                    "--dry-run",
+                    "--config",
+                    "/conf.yaml",
+                ])
+                .unwrap();
+            let command = CliCommand::parse_cli_args(&matches).unwrap();
+            let expected_command =
+                CliCommand::Source(SourceCliCommand::ResetCheckpoint(ResetCheckpointArgs {
+                    config_uri: Uri::from_str("file:///conf.yaml").unwrap(),
+                    index_id: "hdfs-logs".to_string(),
+                    source_id: "kafka-foo".to_string(),
+                    partition_ids: vec!["0".to_string(), "2".to_string()],
+                    dry_run: true,
+                    endpoints: Vec::new(),
+                }));
+            assert_eq!(command, expected_command);
+        }
+        {
+            let app = build_cli().no_binary_name(true);
+            let matches = app
+                .try_get_matches_from(vec![
+                    "source",
+                    "checkpoint",
+                    "seek",
+                    "--index",
+                    "hdfs-logs",
+                    "--source",
+                    "kafka-foo",
+                    "--partition",
+                    "1",
+                    "--position",
+                    "1234",
+                    "--endpoint",
+                    "http://indexer:7280",
+                    "--config",
+                    "/conf.yaml",
+                ])
+                .unwrap();
+            let command = CliCommand::parse_cli_args(&matches).unwrap();
+            let expected_command =
+                CliCommand::Source(SourceCliCommand::SeekCheckpoint(SeekCheckpointArgs {
+                    config_uri: Uri::from_str("file:///conf.yaml").unwrap(),
+                    index_id: "hdfs-logs".to_string(),
+                    source_id: "kafka-foo".to_string(),
+                    partition_id: "1".to_string(),
+                    position: "1234".to_string(),
+                    dry_run: false,
+                    endpoints: vec!["http://indexer:7280".to_string()],
+                }));
+            assert_eq!(command, expected_command);
+        }
+    }
+
+    #[test]
+    fn test_resolve_partition_id_and_parse_position() {
+        let checkpoint: SourceCheckpoint = [
+            (PartitionId::from(0u64), Position::from(42u64)),
+            (PartitionId::from("shard-a"), Position::from("abc")),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            resolve_partition_id(&checkpoint, "0"),
+            PartitionId::from(0u64)
+        );
+        assert_eq!(
+            resolve_partition_id(&checkpoint, "shard-a"),
+            PartitionId::from("shard-a")
+        );
+        assert_eq!(
+            resolve_partition_id(&checkpoint, "3"),
+            PartitionId::from(3u64)
+        );
+        assert_eq!(
+            resolve_partition_id(&checkpoint, "shard-b"),
+            PartitionId::from("shard-b")
+        );
+        assert_eq!(parse_position("beginning"), Position::Beginning);
+        assert_eq!(parse_position("42"), Position::from(42u64));
+        assert_eq!(parse_position("abc"), Position::from("abc"));
+        assert_eq!(display_position(&Position::from(42u64)), "42");
+        assert_eq!(display_position(&Position::from(0u64)), "0");
+        assert_eq!(display_position(&Position::Beginning), "beginning");
+    }
+
+    #[test]
+    fn test_make_checkpoint_change_rows() {
+        let current_checkpoint: SourceCheckpoint = [
+            (PartitionId::from(0u64), Position::from(10u64)),
+            (PartitionId::from(1u64), Position::from(20u64)),
+            (PartitionId::from(2u64), Position::from(30u64)),
+        ]
+        .into_iter()
+        .collect();
+        let new_checkpoint: SourceCheckpoint = [
+            (PartitionId::from(1u64), Position::from(15u64)),
+            (PartitionId::from(2u64), Position::from(30u64)),
+            (PartitionId::from(3u64), Position::from(5u64)),
+        ]
+        .into_iter()
+        .collect();
+        let checkpoint_change_rows =
+            make_checkpoint_change_rows(&current_checkpoint, &new_checkpoint);
+        let expected_rows = vec![
+            CheckpointChangeRow {
+                partition_id: "0".to_string(),
+                current_position: "10".to_string(),
+                new_position: "beginning".to_string(),
+                effect: "Re-ingests data after `beginning` up to `10`".to_string(),
+            },
+            CheckpointChangeRow {
+                partition_id: "1".to_string(),
+                current_position: "20".to_string(),
+                new_position: "15".to_string(),
+                effect: "Re-ingests data after `15` up to `20`".to_string(),
+            },
+            CheckpointChangeRow {
+                partition_id: "2".to_string(),
+                current_position: "30".to_string(),
+                new_position: "30".to_string(),
+                effect: "Unchanged".to_string(),
+            },
+            CheckpointChangeRow {
+                partition_id: "3".to_string(),
+                current_position: "beginning".to_string(),
+                new_position: "5".to_string(),
+                effect: "Skips data after `beginning` up to `5`".to_string(),
+            },
+        ];
+        assert_eq!(checkpoint_change_rows, expected_rows);
+    }
+
     #[test]
     fn test_parse_describe_source_args() {
         let app = build_cli().no_binary_name(true);
@@ -744,6 +1488,11 @@ mod tests {
         let sources = vec![SourceConfig {
             source_id: "foo-source".to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::file("path/to/file"),
         }];
@@ -809,12 +1558,22 @@ mod tests {
             SourceConfig {
                 source_id: "foo-source".to_string(),
                 num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
                 enabled: true,
                 source_params: SourceParams::stdin(),
             },
             SourceConfig {
                 source_id: "bar-source".to_string(),
                 num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
                 enabled: true,
                 source_params: SourceParams::stdin(),
             },
@@ -17,29 +17,43 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use anyhow::{bail, Context};
 use clap::{arg, Arg, ArgMatches, Command};
 use humansize::{format_size, DECIMAL};
 use itertools::Itertools;
 use quickwit_common::uri::Uri;
+use quickwit_config::service::QuickwitService;
+use quickwit_config::{build_doc_mapper, IndexConfig};
+use quickwit_core::IndexService;
 use quickwit_directories::{
     get_hotcache_from_split, read_split_footer, BundleDirectory, HotDirectory,
 };
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+use quickwit_doc_mapper::DYNAMIC_ALL_FIELD_NAME;
+use quickwit_indexing::models::MergeSplitsResponse;
 use quickwit_metastore::{quickwit_metastore_uri_resolver, Split, SplitState};
-use quickwit_storage::{quickwit_storage_uri_resolver, BundleStorage, Storage};
+use quickwit_proto::SearchRequest;
+use quickwit_search::{offline_search, SearchResponseRest};
+use quickwit_storage::{
+    load_file, quickwit_storage_uri_resolver, BundleStorage, OwnedBytes, Storage,
+};
 use tabled::{Table, Tabled};
+use tantivy::directory::FileSlice;
+use tantivy::schema::{Field, FieldType};
+use tantivy::{Index, IndexReader, ReloadPolicy, Searcher};
 use time::{format_description, Date, OffsetDateTime, PrimitiveDateTime};
 use tracing::debug;
 
-use crate::{load_quickwit_config, make_table};
+use crate::{load_quickwit_config, local_rest_endpoint, make_table, start_actor_runtimes};
 
 pub fn build_split_command<'a>() -> Command<'a> {
     Command::new("split")
-        .about("Performs operations on splits (list, describe, mark for deletion, extract).")
+        .about("Performs operations on splits (list, describe, mark for deletion, merge, upgrade, extract, search).")
         .subcommand(
             Command::new("list")
                 .about("Lists the splits of an index.")
@@ -93,6 +107,39 @@ pub fn build_split_command<'a>() -> Command<'a> {
                     arg!(--split <SPLIT> "ID of the target split")
                         .display_order(2),
                     arg!(--verbose "Displays additional metadata about the hotcache."),
+                    arg!(--fields "Downloads the whole split and displays the footprint of each field along with its most frequent terms.")
+                        .required(false),
+                    arg!(--"num-top-terms" <NUM_TOP_TERMS> "Number of most frequent terms displayed per field when `--fields` is set.")
+                        .default_value("5")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("search")
+                .about("Searches split files directly, without any running cluster or metastore. Useful for debugging splits copied out of a cluster.")
+                .args(&[
+                    arg!(--"index-config" <INDEX_CONFIG> "Location of the config file of the index the splits belong to.")
+                        .display_order(1),
+                    arg!(--"splits-uri" <SPLITS_URI> "URI of the directory holding the split files. Local directory or storage URI, e.g. `s3://my-bucket/my-index`.")
+                        .display_order(2),
+                    arg!(--splits <SPLIT_IDS> "Comma-separated list of split IDs to search. Required unless `splits-uri` is a local directory, in which case all its split files are searched.")
+                        .display_order(3)
+                        .required(false)
+                        .use_value_delimiter(true),
+                    arg!(--query <QUERY> "Query expressed in natural query language ((barack AND obama) OR \"president of united states\"). Learn more on https://quickwit.io/docs/reference/search-language."),
+                    arg!(--"max-hits" <MAX_HITS> "Maximum number of hits returned.")
+                        .default_value("20")
+                        .required(false),
+                    arg!(--"start-offset" <OFFSET> "Offset in the global result set of the first hit returned.")
+                        .default_value("0")
+                        .required(false),
+                    arg!(--"search-fields" <FIELD_NAME> "List of fields that Quickwit will search into if the user query does not explicitly target a field in the query. Space-separated list, e.g. \"field1 field2\". ")
+                        .multiple_values(true)
+                        .required(false),
+                    arg!(--"start-timestamp" <TIMESTAMP> "Filters out documents before that timestamp (time-series indexes only).")
+                        .required(false),
+                    arg!(--"end-timestamp" <TIMESTAMP> "Filters out documents after that timestamp (time-series indexes only).")
+                        .required(false),
                 ])
             )
         .subcommand(
@@ -109,6 +156,38 @@ pub fn build_split_command<'a>() -> Command<'a> {
                         .use_value_delimiter(true),
                 ])
             )
+        .subcommand(
+            Command::new("upgrade")
+                .about("Rewrites the splits of an index written with an outdated split or tantivy index format. Runs offline, without a running cluster. Stop the indexers of the index beforehand.")
+                .args(&[
+                    arg!(--index <INDEX_ID> "Target index ID")
+                        .display_order(1)
+                        .required(true),
+                    arg!(--"dry-run" "Executes the command in dry run mode and only displays the list of splits to upgrade.")
+                        .display_order(2)
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("merge")
+                .about("Asks a running indexer to merge splits of an index right away, regardless of the merge policy.")
+                .args(&[
+                    arg!(--index <INDEX_ID> "Target index ID")
+                        .display_order(1)
+                        .required(true),
+                    arg!(--splits <SPLIT_IDS> "Comma-separated list of split IDs to merge. Splits are merged partition by partition.")
+                        .display_order(2)
+                        .required_unless_present("all")
+                        .conflicts_with("all")
+                        .use_value_delimiter(true),
+                    arg!(--all "Merges all the splits that can be merged, partition by partition.")
+                        .display_order(3)
+                        .required(false),
+                    arg!(--endpoint <ENDPOINT> "REST endpoint of the indexer, e.g. `http://localhost:7280`. Defaults to the REST listen address of the node config.")
+                        .display_order(4)
+                        .required(false),
+                ])
+            )
         .arg_required_else_help(true)
 }
 
@@ -131,12 +210,30 @@ pub struct MarkForDeletionArgs {
     pub split_ids: Vec<String>,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct MergeSplitsArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    /// `None` merges all the splits that can be merged.
+    pub split_ids_opt: Option<Vec<String>>,
+    pub endpoint_opt: Option<String>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct UpgradeSplitsArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub dry_run: bool,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct DescribeSplitArgs {
     pub config_uri: Uri,
     pub index_id: String,
     pub split_id: String,
     pub verbose: bool,
+    pub fields: bool,
+    pub num_top_terms: usize,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -147,12 +244,28 @@ pub struct ExtractSplitArgs {
     pub target_dir: PathBuf,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct SearchSplitsArgs {
+    pub index_config_uri: Uri,
+    pub splits_uri: Uri,
+    pub split_ids_opt: Option<Vec<String>>,
+    pub query: String,
+    pub max_hits: usize,
+    pub start_offset: usize,
+    pub search_fields: Option<Vec<String>>,
+    pub start_timestamp: Option<i64>,
+    pub end_timestamp: Option<i64>,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum SplitCliCommand {
     List(ListSplitArgs),
     MarkForDeletion(MarkForDeletionArgs),
+    Merge(MergeSplitsArgs),
+    Upgrade(UpgradeSplitsArgs),
     Describe(DescribeSplitArgs),
     Extract(ExtractSplitArgs),
+    Search(SearchSplitsArgs),
 }
 
 impl SplitCliCommand {
@@ -165,6 +278,9 @@ impl SplitCliCommand {
             "extract" => Self::parse_extract_split_args(submatches),
             "list" => Self::parse_list_args(submatches),
             "mark-for-deletion" => Self::parse_mark_for_deletion_args(submatches),
+            "merge" => Self::parse_merge_args(submatches),
+            "search" => Self::parse_search_args(submatches),
+            "upgrade" => Self::parse_upgrade_args(submatches),
             _ => bail!("Subcommand `{}` is not implemented.", subcommand),
         }
     }
@@ -247,6 +363,46 @@ impl SplitCliCommand {
         }))
     }
 
+    fn parse_merge_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .map(String::from)
+            .expect("`index` is a required arg.");
+        let split_ids_opt = matches
+            .values_of("splits")
+            .map(|values| values.map(String::from).collect());
+        let endpoint_opt = matches
+            .value_of("endpoint")
+            .map(|endpoint| endpoint.trim_end_matches('/').to_string());
+        Ok(Self::Merge(MergeSplitsArgs {
+            config_uri,
+            index_id,
+            split_ids_opt,
+            endpoint_opt,
+        }))
+    }
+
+    fn parse_upgrade_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .map(String::from)
+            .expect("`index` is a required arg.");
+        let dry_run = matches.is_present("dry-run");
+        Ok(Self::Upgrade(UpgradeSplitsArgs {
+            config_uri,
+            index_id,
+            dry_run,
+        }))
+    }
+
     fn parse_describe_args(matches: &ArgMatches) -> anyhow::Result<Self> {
         let index_id = matches
             .value_of("index")
@@ -261,12 +417,16 @@ impl SplitCliCommand {
             .map(Uri::from_str)
             .expect("`config` is a required arg.")?;
         let verbose = matches.is_present("verbose");
+        let fields = matches.is_present("fields");
+        let num_top_terms = matches.value_of_t::<usize>("num-top-terms")?;
 
         Ok(Self::Describe(DescribeSplitArgs {
             config_uri,
             index_id,
             split_id,
             verbose,
+            fields,
+            num_top_terms,
         }))
     }
 
@@ -295,12 +455,59 @@ impl SplitCliCommand {
         }))
     }
 
+    fn parse_search_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let index_config_uri = matches
+            .value_of("index-config")
+            .map(Uri::from_str)
+            .expect("`index-config` is a required arg.")?;
+        let splits_uri = matches
+            .value_of("splits-uri")
+            .map(Uri::from_str)
+            .expect("`splits-uri` is a required arg.")?;
+        let split_ids_opt = matches
+            .values_of("splits")
+            .map(|values| values.map(String::from).collect());
+        let query = matches
+            .value_of("query")
+            .context("`query` is a required arg.")?
+            .to_string();
+        let max_hits = matches.value_of_t::<usize>("max-hits")?;
+        let start_offset = matches.value_of_t::<usize>("start-offset")?;
+        let search_fields = matches
+            .values_of("search-fields")
+            .map(|values| values.map(String::from).collect());
+        let start_timestamp = if matches.is_present("start-timestamp") {
+            Some(matches.value_of_t::<i64>("start-timestamp")?)
+        } else {
+            None
+        };
+        let end_timestamp = if matches.is_present("end-timestamp") {
+            Some(matches.value_of_t::<i64>("end-timestamp")?)
+        } else {
+            None
+        };
+        Ok(Self::Search(SearchSplitsArgs {
+            index_config_uri,
+            splits_uri,
+            split_ids_opt,
+            query,
+            max_hits,
+            start_offset,
+            search_fields,
+            start_timestamp,
+            end_timestamp,
+        }))
+    }
+
     pub async fn execute(self) -> anyhow::Result<()> {
         match self {
             Self::List(args) => list_split_cli(args).await,
             Self::MarkForDeletion(args) => mark_splits_for_deletion_cli(args).await,
+            Self::Merge(args) => merge_splits_cli(args).await,
+            Self::Upgrade(args) => upgrade_splits_cli(args).await,
             Self::Describe(args) => describe_split_cli(args).await,
             Self::Extract(args) => extract_split_cli(args).await,
+            Self::Search(args) => search_splits_cli(args).await,
         }
     }
 }
@@ -361,6 +568,110 @@ async fn mark_splits_for_deletion_cli(args: MarkForDeletionArgs) -> anyhow::Resu
     Ok(())
 }
 
+#[derive(Tabled)]
+struct MergeOperationRow {
+    #[tabled(rename = "Merged Split ID")]
+    merge_split_id: String,
+    #[tabled(rename = "Num Input Splits")]
+    num_input_splits: usize,
+    #[tabled(rename = "Input Split IDs")]
+    input_split_ids: String,
+}
+
+async fn merge_splits_cli(args: MergeSplitsArgs) -> anyhow::Result<()> {
+    debug!(args = ?args, "merge-splits");
+
+    let endpoint = match args.endpoint_opt {
+        Some(endpoint) => endpoint,
+        None => {
+            let quickwit_config = load_quickwit_config(&args.config_uri).await?;
+            local_rest_endpoint(&quickwit_config)
+        }
+    };
+    let url = format!("{}/api/v1/{}/merge", endpoint, args.index_id);
+    let response = reqwest::Client::new()
+        .post(&url)
+        .json(&serde_json::json!({ "split_ids": args.split_ids_opt }))
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach indexer at `{}`.", endpoint))?;
+    if !response.status().is_success() {
+        bail!(
+            "Failed to merge splits of index `{}` ({}): {}",
+            args.index_id,
+            response.status(),
+            response.text().await?
+        );
+    }
+    let merge_splits_response: MergeSplitsResponse = response.json().await?;
+    if merge_splits_response.merge_operations.is_empty() {
+        println!("No splits to merge.");
+    } else {
+        let merge_operation_rows = merge_splits_response.merge_operations.into_iter().map(
+            |(merge_split_id, input_split_ids)| MergeOperationRow {
+                merge_split_id,
+                num_input_splits: input_split_ids.len(),
+                input_split_ids: input_split_ids.join(", "),
+            },
+        );
+        println!(
+            "{}",
+            make_table("Scheduled Merge Operations", merge_operation_rows, false)
+        );
+    }
+    if !merge_splits_response.skipped_split_ids.is_empty() {
+        println!(
+            "The following splits were not merged because they are unknown to the indexer, \
+             mature, already being merged, or alone in their partition: `{}`.",
+            merge_splits_response.skipped_split_ids.join(", ")
+        );
+    }
+    Ok(())
+}
+
+async fn upgrade_splits_cli(args: UpgradeSplitsArgs) -> anyhow::Result<()> {
+    debug!(args = ?args, "upgrade-splits");
+
+    let quickwit_config = load_quickwit_config(&args.config_uri).await?;
+    if !args.dry_run {
+        start_actor_runtimes(&HashSet::from_iter([QuickwitService::Indexer]))?;
+    }
+    let data_dir_path = quickwit_config.data_dir_path.clone();
+    let index_service = IndexService::from_config(quickwit_config).await?;
+    let report = index_service
+        .upgrade_splits(&args.index_id, &data_dir_path, args.dry_run)
+        .await?;
+    if report.outdated_split_ids.is_empty() {
+        println!(
+            "All the splits of index `{}` are up to date.",
+            args.index_id
+        );
+        return Ok(());
+    }
+    if args.dry_run {
+        println!("The following splits will be upgraded.");
+        for split_id in &report.outdated_split_ids {
+            println!(" - {}", split_id);
+        }
+        return Ok(());
+    }
+    if !report.failed_split_ids.is_empty() {
+        bail!(
+            "Failed to upgrade {} out of {} splits of index `{}`: `{}`.",
+            report.failed_split_ids.len(),
+            report.outdated_split_ids.len(),
+            args.index_id,
+            report.failed_split_ids.join(", ")
+        );
+    }
+    println!(
+        "{} splits of index `{}` successfully upgraded.",
+        report.outdated_split_ids.len(),
+        args.index_id
+    );
+    Ok(())
+}
+
 #[derive(Tabled)]
 struct FileRow {
     #[tabled(rename = "File Name")]
@@ -394,12 +705,76 @@ async fn describe_split_cli(args: DescribeSplitArgs) -> anyhow::Result<()> {
             )
         })?;
 
-    println!("{}", make_split_table(&[split_metadata], "Split"));
+    println!("{}", make_split_table(&[split_metadata.clone()], "Split"));
 
     let split_file = PathBuf::from(format!("{}.split", args.split_id));
-    let (split_footer, _) = read_split_footer(index_storage, &split_file).await?;
+    let (split_footer, _) = read_split_footer(index_storage.clone(), &split_file).await?;
     let stats = BundleDirectory::get_stats_split(split_footer.clone())?;
     let hotcache_bytes = get_hotcache_from_split(split_footer)?;
+    let field_footprints_opt = if args.fields {
+        let split_data = index_storage.get_all(&split_file).await?;
+        Some(compute_field_footprints(split_data, args.num_top_terms)?)
+    } else {
+        None
+    };
+
+    let mut split_details_rows = vec![
+        SplitDetailsRow {
+            property: "Num docs".to_string(),
+            value: split_metadata.split_metadata.num_docs.to_string(),
+        },
+        SplitDetailsRow {
+            property: "Split size".to_string(),
+            value: format_size(split_metadata.split_metadata.footer_offsets.end, DECIMAL),
+        },
+        SplitDetailsRow {
+            property: "Hotcache size".to_string(),
+            value: format_size(hotcache_bytes.len(), DECIMAL),
+        },
+        SplitDetailsRow {
+            property: "Time range".to_string(),
+            value: split_metadata
+                .split_metadata
+                .time_range
+                .as_ref()
+                .map(|time_range| format!("{:?}", time_range))
+                .unwrap_or_else(|| "[*]".to_string()),
+        },
+        SplitDetailsRow {
+            property: "Tags".to_string(),
+            value: split_metadata.split_metadata.tags.iter().join(", "),
+        },
+        SplitDetailsRow {
+            property: "Split format version".to_string(),
+            value: split_metadata
+                .split_metadata
+                .split_format_version
+                .to_string(),
+        },
+        SplitDetailsRow {
+            property: "Tantivy index format version".to_string(),
+            value: split_metadata
+                .split_metadata
+                .tantivy_index_format_version
+                .to_string(),
+        },
+    ];
+    if let Some(dynamic_all_footprint) = field_footprints_opt
+        .iter()
+        .flatten()
+        .find(|field_footprint| field_footprint.field_name == DYNAMIC_ALL_FIELD_NAME)
+    {
+        let split_size = split_metadata.split_metadata.footer_offsets.end.max(1);
+        split_details_rows.push(SplitDetailsRow {
+            property: "Dynamic all field size".to_string(),
+            value: format!(
+                "{} ({:.1}% of split size)",
+                format_size(dynamic_all_footprint.num_bytes, DECIMAL),
+                dynamic_all_footprint.num_bytes as f64 * 100.0 / split_size as f64
+            ),
+        });
+    }
+    println!("{}", make_table("Split details", split_details_rows, false));
 
     let mut file_rows = Vec::new();
 
@@ -425,9 +800,171 @@ async fn describe_split_cli(args: DescribeSplitArgs) -> anyhow::Result<()> {
         let hotcache_table = make_table("Files in Hotcache", hotcache_files.into_iter(), false);
         println!("{hotcache_table}");
     }
+    if let Some(field_footprints) = field_footprints_opt {
+        let field_rows = field_footprints
+            .into_iter()
+            .map(|field_footprint| FieldRow {
+                field_name: field_footprint.field_name,
+                field_type: field_footprint.field_type,
+                size: format_size(field_footprint.num_bytes, DECIMAL),
+                top_terms: field_footprint
+                    .top_terms
+                    .iter()
+                    .map(|(term, doc_freq)| format!("{term} ({doc_freq})"))
+                    .join(", "),
+            });
+        println!("{}", make_table("Fields", field_rows, false));
+    }
+    Ok(())
+}
+
+#[derive(Tabled)]
+struct SplitDetailsRow {
+    #[tabled(rename = "Property")]
+    property: String,
+    #[tabled(rename = "Value")]
+    value: String,
+}
+
+#[derive(Tabled)]
+struct FieldRow {
+    #[tabled(rename = "Field")]
+    field_name: String,
+    #[tabled(rename = "Type")]
+    field_type: String,
+    #[tabled(rename = "Size")]
+    size: String,
+    #[tabled(rename = "Top terms (doc freq)")]
+    top_terms: String,
+}
+
+/// Footprint of a field in a split.
+#[derive(Debug)]
+struct FieldFootprint {
+    field_name: String,
+    field_type: String,
+    /// Bytes used by the term dictionary, postings, positions, fast field, and fieldnorms of the
+    /// field. The doc store is not broken down per field and is not included.
+    num_bytes: u64,
+    /// Most frequent terms of the field along with their document frequency, for indexed text
+    /// fields only.
+    top_terms: Vec<(String, u32)>,
+}
+
+/// Opens the split and computes the footprint of each field of its schema, sorted by decreasing
+/// size.
+fn compute_field_footprints(
+    split_data: OwnedBytes,
+    num_top_terms: usize,
+) -> anyhow::Result<Vec<FieldFootprint>> {
+    let bundle_directory = BundleDirectory::open_split(FileSlice::new(Arc::new(split_data)))?;
+    let index = Index::open(bundle_directory)?;
+    let index_reader: IndexReader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+    let searcher = index_reader.searcher();
+    let schema = index.schema();
+    let space_usage = searcher.space_usage()?;
+
+    let mut field_num_bytes: HashMap<Field, u64> = HashMap::new();
+    for segment_space_usage in space_usage.segments() {
+        for per_field_space_usage in [
+            segment_space_usage.termdict(),
+            segment_space_usage.postings(),
+            segment_space_usage.positions(),
+            segment_space_usage.fast_fields(),
+            segment_space_usage.fieldnorms(),
+        ] {
+            for (field, field_usage) in per_field_space_usage.fields() {
+                *field_num_bytes.entry(*field).or_default() += field_usage.total() as u64;
+            }
+        }
+    }
+    let mut field_footprints = Vec::new();
+    for (field, field_entry) in schema.fields() {
+        let top_terms = match field_entry.field_type() {
+            FieldType::Str(_) if field_entry.is_indexed() => {
+                let mut top_terms: Vec<(String, u32)> = Vec::new();
+                for_each_term_doc_freq(&searcher, field, |term_data, doc_freq| {
+                    push_top_term(&mut top_terms, term_data, doc_freq, num_top_terms)
+                })?;
+                top_terms
+            }
+            _ => Vec::new(),
+        };
+        field_footprints.push(FieldFootprint {
+            field_name: field_entry.name().to_string(),
+            field_type: format!("{:?}", field_entry.field_type().value_type()),
+            num_bytes: field_num_bytes.get(&field).copied().unwrap_or_default(),
+            top_terms,
+        });
+    }
+    field_footprints.sort_by(|left, right| right.num_bytes.cmp(&left.num_bytes));
+    Ok(field_footprints)
+}
+
+/// Calls `term_doc_freq_fn` with each term of the field and its document frequency summed over
+/// the segments of the split. The term dictionaries of the segments are sorted, so they are merged
+/// on the fly and each term is visited once.
+fn for_each_term_doc_freq(
+    searcher: &Searcher,
+    field: Field,
+    mut term_doc_freq_fn: impl FnMut(&[u8], u32),
+) -> anyhow::Result<()> {
+    let inverted_indexes = searcher
+        .segment_readers()
+        .iter()
+        .map(|segment_reader| segment_reader.inverted_index(field))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut terms_streamers = Vec::with_capacity(inverted_indexes.len());
+    for inverted_index in &inverted_indexes {
+        let mut terms_streamer = inverted_index.terms().stream()?;
+        if terms_streamer.advance() {
+            terms_streamers.push(terms_streamer);
+        }
+    }
+    let mut term_data: Vec<u8> = Vec::new();
+    while let Some(min_term_data) = terms_streamers
+        .iter()
+        .map(|terms_streamer| terms_streamer.key())
+        .min()
+    {
+        term_data.clear();
+        term_data.extend_from_slice(min_term_data);
+        let mut doc_freq = 0;
+        terms_streamers.retain_mut(|terms_streamer| {
+            if terms_streamer.key() != term_data {
+                return true;
+            }
+            doc_freq += terms_streamer.value().doc_freq;
+            terms_streamer.advance()
+        });
+        term_doc_freq_fn(&term_data, doc_freq);
+    }
     Ok(())
 }
 
+/// Inserts the term into `top_terms`, which is kept sorted by decreasing document frequency and
+/// holds at most `num_top_terms` terms.
+fn push_top_term(
+    top_terms: &mut Vec<(String, u32)>,
+    term_data: &[u8],
+    doc_freq: u32,
+    num_top_terms: usize,
+) {
+    if num_top_terms == 0 {
+        return;
+    }
+    if top_terms.len() == num_top_terms && top_terms[num_top_terms - 1].1 >= doc_freq {
+        return;
+    }
+    let term = String::from_utf8_lossy(term_data).to_string();
+    let position = top_terms.partition_point(|(_, top_doc_freq)| *top_doc_freq >= doc_freq);
+    top_terms.insert(position, (term, doc_freq));
+    top_terms.truncate(num_top_terms);
+}
+
 async fn extract_split_cli(args: ExtractSplitArgs) -> anyhow::Result<()> {
     debug!(args = ?args, "extract-split");
 
@@ -457,6 +994,65 @@ async fn extract_split_cli(args: ExtractSplitArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn search_splits_cli(args: SearchSplitsArgs) -> anyhow::Result<()> {
+    debug!(args = ?args, "search-splits");
+
+    let index_config_content = load_file(&args.index_config_uri).await?;
+    let index_config =
+        IndexConfig::load(&args.index_config_uri, index_config_content.as_slice()).await?;
+    let doc_mapper = build_doc_mapper(
+        &index_config.doc_mapping,
+        &index_config.search_settings,
+        &index_config.indexing_settings,
+    )?;
+    let split_ids = match args.split_ids_opt {
+        Some(split_ids) => split_ids,
+        None => list_local_split_ids(&args.splits_uri).await?,
+    };
+    if split_ids.is_empty() {
+        bail!("No split files found at `{}`.", args.splits_uri);
+    }
+    let storage = quickwit_storage_uri_resolver().resolve(&args.splits_uri)?;
+    let search_request = SearchRequest {
+        index_id: index_config.index_id,
+        query: args.query,
+        search_fields: args.search_fields.unwrap_or_default(),
+        start_timestamp: args.start_timestamp,
+        end_timestamp: args.end_timestamp,
+        max_hits: args.max_hits as u64,
+        start_offset: args.start_offset as u64,
+        ..Default::default()
+    };
+    let search_response = offline_search(&search_request, storage, &split_ids, doc_mapper).await?;
+    let search_response_rest = SearchResponseRest::try_from(search_response)?;
+    let search_response_json = serde_json::to_string_pretty(&search_response_rest)?;
+    println!("{}", search_response_json);
+    Ok(())
+}
+
+/// Lists the IDs of the split files stored in the local directory `splits_uri`.
+async fn list_local_split_ids(splits_uri: &Uri) -> anyhow::Result<Vec<String>> {
+    let splits_dir_path = splits_uri.filepath().with_context(|| {
+        format!(
+            "Split IDs must be provided with `--splits` for non-local URI `{}`.",
+            splits_uri
+        )
+    })?;
+    let mut split_ids = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(splits_dir_path).await?;
+    while let Some(dir_entry) = read_dir.next_entry().await? {
+        let path = dir_entry.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("split") {
+            continue;
+        }
+        if let Some(split_id) = path.file_stem().and_then(|file_stem| file_stem.to_str()) {
+            split_ids.push(split_id.to_string());
+        }
+    }
+    split_ids.sort();
+    Ok(split_ids)
+}
+
 fn filter_splits(
     splits: Vec<Split>,
     split_states_opt: Option<Vec<SplitState>>,
@@ -700,6 +1296,77 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_split_merge_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "split",
+            "merge",
+            "--index",
+            "wikipedia",
+            "--splits",
+            "split1,split2",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Split(SplitCliCommand::Merge(MergeSplitsArgs {
+            config_uri: Uri::from_str("file:///config.yaml")?,
+            index_id: "wikipedia".to_string(),
+            split_ids_opt: Some(vec!["split1".to_string(), "split2".to_string()]),
+            endpoint_opt: None,
+        }));
+        assert_eq!(command, expected_command);
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "split",
+            "merge",
+            "--index",
+            "wikipedia",
+            "--all",
+            "--endpoint",
+            "http://indexer:7280/",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Split(SplitCliCommand::Merge(MergeSplitsArgs {
+            config_uri: Uri::from_str("file:///config.yaml")?,
+            index_id: "wikipedia".to_string(),
+            split_ids_opt: None,
+            endpoint_opt: Some("http://indexer:7280".to_string()),
+        }));
+        assert_eq!(command, expected_command);
+
+        let app = build_cli().no_binary_name(true);
+        app.try_get_matches_from(vec!["split", "merge", "--index", "wikipedia"])
+            .unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_split_upgrade_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "split",
+            "upgrade",
+            "--index",
+            "wikipedia",
+            "--dry-run",
+            "--config",
+            "/config.yaml",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Split(SplitCliCommand::Upgrade(UpgradeSplitsArgs {
+            config_uri: Uri::from_str("file:///config.yaml")?,
+            index_id: "wikipedia".to_string(),
+            dry_run: true,
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
     #[test]
     fn test_parse_split_describe_args() -> anyhow::Result<()> {
         let app = build_cli().no_binary_name(true);
@@ -720,12 +1387,113 @@ mod tests {
                 index_id,
                 split_id,
                 verbose: false,
+                fields: false,
+                num_top_terms: 5,
                 ..
             })) if &index_id == "wikipedia" && &split_id == "ABC"
         ));
         Ok(())
     }
 
+    #[test]
+    fn test_push_top_term() {
+        let mut top_terms = Vec::new();
+        for (term, doc_freq) in [("a", 3), ("b", 10), ("c", 1), ("d", 7), ("e", 10)] {
+            push_top_term(&mut top_terms, term.as_bytes(), doc_freq, 3);
+        }
+        assert_eq!(
+            top_terms,
+            vec![
+                ("b".to_string(), 10),
+                ("e".to_string(), 10),
+                ("d".to_string(), 7)
+            ]
+        );
+        let mut top_terms = Vec::new();
+        push_top_term(&mut top_terms, b"a", 3, 0);
+        assert!(top_terms.is_empty());
+    }
+
+    #[test]
+    fn test_for_each_term_doc_freq_merges_segments() -> anyhow::Result<()> {
+        let mut schema_builder = tantivy::schema::Schema::builder();
+        let text_field = schema_builder.add_text_field("text", tantivy::schema::STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000)?;
+        index_writer.set_merge_policy(Box::new(tantivy::merge_policy::NoMergePolicy));
+        for segment_terms in [&["a", "b", "b"][..], &["b", "c"], &["a", "c", "c", "c"]] {
+            for term in segment_terms {
+                index_writer.add_document(tantivy::doc!(text_field => *term))?;
+            }
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+
+        let mut term_doc_freqs = Vec::new();
+        for_each_term_doc_freq(&searcher, text_field, |term_data, doc_freq| {
+            term_doc_freqs.push((String::from_utf8_lossy(term_data).to_string(), doc_freq))
+        })?;
+        assert_eq!(
+            term_doc_freqs,
+            vec![
+                ("a".to_string(), 2),
+                ("b".to_string(), 3),
+                ("c".to_string(), 4)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_split_search_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "split",
+            "search",
+            "--index-config",
+            "/index-config.yaml",
+            "--splits-uri",
+            "/splits",
+            "--splits",
+            "split-1,split-2",
+            "--query",
+            "foo",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Split(SplitCliCommand::Search(SearchSplitsArgs {
+            index_config_uri: Uri::from_str("file:///index-config.yaml")?,
+            splits_uri: Uri::from_str("file:///splits")?,
+            split_ids_opt: Some(vec!["split-1".to_string(), "split-2".to_string()]),
+            query: "foo".to_string(),
+            max_hits: 20,
+            start_offset: 0,
+            search_fields: None,
+            start_timestamp: None,
+            end_timestamp: None,
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_local_split_ids() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        for file_name in ["split-2.split", "split-1.split", "hotcache", "split-3.json"] {
+            tokio::fs::write(temp_dir.path().join(file_name), b"").await?;
+        }
+        let splits_uri = Uri::from_str(temp_dir.path().to_str().unwrap())?;
+        let split_ids = list_local_split_ids(&splits_uri).await?;
+        assert_eq!(
+            split_ids,
+            vec!["split-1".to_string(), "split-2".to_string()]
+        );
+
+        let s3_uri = Uri::from_str("s3://bucket/splits")?;
+        assert!(list_local_split_ids(&s3_uri).await.is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_parse_split_extract_args() -> anyhow::Result<()> {
         let app = build_cli().no_binary_name(true);
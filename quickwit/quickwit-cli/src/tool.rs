@@ -0,0 +1,614 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use clap::{arg, ArgMatches, Command};
+use humansize::{format_size, DECIMAL};
+use humantime::format_duration;
+use quickwit_actors::{Mailbox, Universe};
+use quickwit_common::uri::Uri;
+use quickwit_config::service::QuickwitService;
+use quickwit_indexing::actors::IndexingService;
+use quickwit_indexing::models::SpawnPipelines;
+use quickwit_ingest_api::{start_ingest_api_service, DocBatchBuilder, IngestApiService};
+use quickwit_metastore::{quickwit_metastore_uri_resolver, Metastore, Split, SplitState};
+use quickwit_proto::ingest_api::{CreateQueueIfNotExistsRequest, IngestRequest};
+use quickwit_storage::quickwit_storage_uri_resolver;
+use serde_json::json;
+use tabled::Tabled;
+use thousands::Separable;
+use tracing::debug;
+
+use crate::{load_quickwit_config, make_table, parse_duration_with_unit, start_actor_runtimes};
+
+/// Number of batches sent per second by `bench-ingest`.
+const BENCH_INGEST_BATCHES_PER_SEC: u64 = 10;
+
+/// Interval at which `bench-ingest` polls the number of searchable documents.
+const SEARCHABLE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn build_tool_command<'a>() -> Command<'a> {
+    Command::new("tool")
+        .about("Performs utility operations (benchmarks...).")
+        .subcommand(
+            Command::new("bench-ingest")
+                .about("Ingests generated or replayed documents at a target rate and reports the ingestion throughput, the latency until the documents are searchable, and the CPU and memory usage.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index.")
+                        .display_order(1),
+                    arg!(--"input-path" <INPUT_PATH> "Location of a newline-delimited JSON file replayed in a loop. If omitted, synthetic log documents with the fields `timestamp`, `level`, `service`, and `body` are generated.")
+                        .required(false),
+                    arg!(--"num-docs" <NUM_DOCS> "Total number of documents to ingest.")
+                        .default_value("100000")
+                        .required(false),
+                    arg!(--rate <DOCS_PER_SEC> "Target ingestion rate in documents per second.")
+                        .default_value("10000")
+                        .required(false),
+                    arg!(--endpoint <ENDPOINT> "Endpoint of a running Quickwit cluster, e.g. `http://localhost:7280`. If omitted, documents are ingested into an indexing pipeline started in this process.")
+                        .required(false),
+                    arg!(--timeout <TIMEOUT> "Maximum time waited for the documents to become searchable.")
+                        .default_value("5m")
+                        .required(false),
+                ])
+            )
+        .arg_required_else_help(true)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct BenchIngestArgs {
+    pub config_uri: Uri,
+    pub index_id: String,
+    pub input_path_opt: Option<PathBuf>,
+    pub num_docs: usize,
+    pub docs_per_sec: usize,
+    pub endpoint_opt: Option<String>,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ToolCliCommand {
+    BenchIngest(BenchIngestArgs),
+}
+
+impl ToolCliCommand {
+    pub fn parse_cli_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let (subcommand, submatches) = matches
+            .subcommand()
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse sub-matches."))?;
+        match subcommand {
+            "bench-ingest" => Self::parse_bench_ingest_args(submatches),
+            _ => bail!("Tool subcommand `{}` is not implemented.", subcommand),
+        }
+    }
+
+    fn parse_bench_ingest_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let input_path_opt = matches.value_of("input-path").map(PathBuf::from);
+        let num_docs = matches.value_of_t::<usize>("num-docs")?;
+        let docs_per_sec = matches.value_of_t::<usize>("rate")?;
+        if docs_per_sec == 0 {
+            bail!("`rate` must be strictly positive.");
+        }
+        let endpoint_opt = matches
+            .value_of("endpoint")
+            .map(|endpoint| endpoint.trim_end_matches('/').to_string());
+        let timeout = matches
+            .value_of("timeout")
+            .map(parse_duration_with_unit)
+            .expect("`timeout` should have a default value.")?;
+        Ok(Self::BenchIngest(BenchIngestArgs {
+            config_uri,
+            index_id,
+            input_path_opt,
+            num_docs,
+            docs_per_sec,
+            endpoint_opt,
+            timeout,
+        }))
+    }
+
+    pub async fn execute(self) -> anyhow::Result<()> {
+        match self {
+            Self::BenchIngest(args) => bench_ingest_cli(args).await,
+        }
+    }
+}
+
+/// Destination of the documents ingested by `bench-ingest`.
+#[async_trait]
+trait BenchIngestTarget: Send + Sync {
+    /// Ingests a batch of JSON documents.
+    async fn ingest(&self, docs: &[Vec<u8>]) -> anyhow::Result<()>;
+
+    /// Returns the number of documents in the published splits of the index.
+    async fn num_searchable_docs(&self) -> anyhow::Result<usize>;
+}
+
+/// Ingests documents into an ingest API queue consumed by indexing pipelines running in the
+/// current process.
+struct LocalTarget {
+    index_id: String,
+    metastore: Arc<dyn Metastore>,
+    ingest_api_service: Mailbox<IngestApiService>,
+    // Keeps the actors alive for the duration of the benchmark.
+    _universe: Universe,
+    _indexing_service: Mailbox<IndexingService>,
+}
+
+impl LocalTarget {
+    async fn start(config_uri: &Uri, index_id: &str) -> anyhow::Result<Self> {
+        let config = load_quickwit_config(config_uri).await?;
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&config.metastore_uri)
+            .await?;
+        let index_metadata = metastore.index_metadata(index_id).await?;
+        if !index_metadata.sources.values().any(|source_config| {
+            source_config.enabled && source_config.source_type() == "ingest-api"
+        }) {
+            bail!(
+                "The ingest API of index `{}` is disabled. Enable it with `quickwit index \
+                 ingest-api --index {} --enable`.",
+                index_id,
+                index_id
+            );
+        }
+        start_actor_runtimes(&HashSet::from_iter([QuickwitService::Indexer]))?;
+        let universe = Universe::new();
+        let ingest_api_service = start_ingest_api_service(&universe, &config.data_dir_path).await?;
+        ingest_api_service
+            .ask_for_res(CreateQueueIfNotExistsRequest {
+                queue_id: index_id.to_string(),
+            })
+            .await?;
+        let indexing_service = IndexingService::new(
+            config.node_id.clone(),
+            config.data_dir_path.clone(),
+            config.indexer_config.clone(),
+            metastore.clone(),
+            quickwit_storage_uri_resolver().clone(),
+        )
+        .await?;
+        let (indexing_service, _) = universe.spawn_builder().spawn(indexing_service);
+        indexing_service
+            .ask_for_res(SpawnPipelines {
+                index_id: index_id.to_string(),
+            })
+            .await?;
+        Ok(Self {
+            index_id: index_id.to_string(),
+            metastore,
+            ingest_api_service,
+            _universe: universe,
+            _indexing_service: indexing_service,
+        })
+    }
+}
+
+#[async_trait]
+impl BenchIngestTarget for LocalTarget {
+    async fn ingest(&self, docs: &[Vec<u8>]) -> anyhow::Result<()> {
+        let num_bytes = docs.iter().map(Vec::len).sum();
+        let mut doc_batch_builder =
+            DocBatchBuilder::with_capacity(self.index_id.clone(), num_bytes);
+        for doc in docs {
+            doc_batch_builder.add_doc(doc);
+        }
+        self.ingest_api_service
+            .ask_for_res(IngestRequest {
+                doc_batches: vec![doc_batch_builder.build()],
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn num_searchable_docs(&self) -> anyhow::Result<usize> {
+        let splits = self
+            .metastore
+            .list_splits(&self.index_id, SplitState::Published, None, None)
+            .await?;
+        Ok(count_docs(&splits))
+    }
+}
+
+/// Ingests documents into a running cluster through the REST API.
+struct RemoteTarget {
+    endpoint: String,
+    index_id: String,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl BenchIngestTarget for RemoteTarget {
+    async fn ingest(&self, docs: &[Vec<u8>]) -> anyhow::Result<()> {
+        let mut body = Vec::with_capacity(docs.iter().map(|doc| doc.len() + 1).sum());
+        for doc in docs {
+            body.extend_from_slice(doc);
+            body.push(b'\n');
+        }
+        let url = format!("{}/api/v1/{}/ingest", self.endpoint, self.index_id);
+        self.client
+            .post(url)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn num_searchable_docs(&self) -> anyhow::Result<usize> {
+        let url = format!("{}/api/v1/indexes/{}/splits", self.endpoint, self.index_id);
+        let splits: Vec<Split> = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(count_docs(&splits))
+    }
+}
+
+fn count_docs(splits: &[Split]) -> usize {
+    splits
+        .iter()
+        .filter(|split| split.split_state == SplitState::Published)
+        .map(|split| split.split_metadata.num_docs)
+        .sum()
+}
+
+/// Produces the documents ingested by `bench-ingest`.
+enum DocGenerator {
+    /// Replays the lines of a file in a loop.
+    Replay {
+        lines: Vec<Vec<u8>>,
+        position: usize,
+    },
+    /// Generates synthetic log documents.
+    Synthetic { num_generated_docs: usize },
+}
+
+impl DocGenerator {
+    async fn from_input_path(input_path_opt: Option<&PathBuf>) -> anyhow::Result<Self> {
+        let input_path = match input_path_opt {
+            Some(input_path) => input_path,
+            None => {
+                return Ok(Self::Synthetic {
+                    num_generated_docs: 0,
+                })
+            }
+        };
+        let content = tokio::fs::read(input_path)
+            .await
+            .with_context(|| format!("Failed to read `{}`.", input_path.display()))?;
+        let lines: Vec<Vec<u8>> = content
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+            .map(|line| line.to_vec())
+            .collect();
+        if lines.is_empty() {
+            bail!("Input file `{}` is empty.", input_path.display());
+        }
+        Ok(Self::Replay { lines, position: 0 })
+    }
+
+    fn next_doc(&mut self) -> Vec<u8> {
+        match self {
+            Self::Replay { lines, position } => {
+                let doc = lines[*position].clone();
+                *position = (*position + 1) % lines.len();
+                doc
+            }
+            Self::Synthetic { num_generated_docs } => {
+                const LEVELS: [&str; 4] = ["DEBUG", "INFO", "WARN", "ERROR"];
+                const SERVICES: [&str; 3] = ["api", "billing", "search"];
+                let doc_ord = *num_generated_docs;
+                *num_generated_docs += 1;
+                let doc = json!({
+                    "timestamp": time::OffsetDateTime::now_utc().unix_timestamp(),
+                    "level": LEVELS[doc_ord % LEVELS.len()],
+                    "service": SERVICES[doc_ord % SERVICES.len()],
+                    "body": format!("Request {} served in {}ms.", doc_ord, doc_ord % 1_000),
+                });
+                serde_json::to_vec(&doc).expect("Serializing a JSON value should never fail.")
+            }
+        }
+    }
+}
+
+/// CPU time and resident memory of the current process.
+#[derive(Debug, Clone, Copy)]
+struct ProcessResourceUsage {
+    cpu_time: Duration,
+    resident_memory_bytes: u64,
+}
+
+impl ProcessResourceUsage {
+    /// Samples the resource usage of the current process. Only supported on Linux.
+    fn sample() -> Option<Self> {
+        // SAFETY: `sysconf` has no precondition and only reads a system configuration value.
+        let clock_ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if clock_ticks_per_sec <= 0 {
+            return None;
+        }
+        let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+        // The process name may contain spaces, so fields are counted after the closing
+        // parenthesis. `utime` and `stime` are the 14th and 15th fields.
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        let utime: u64 = fields.get(11)?.parse().ok()?;
+        let stime: u64 = fields.get(12)?.parse().ok()?;
+        let cpu_time = Duration::from_millis((utime + stime) * 1_000 / clock_ticks_per_sec as u64);
+
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let resident_memory_kib: u64 = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .ok()?;
+        Some(Self {
+            cpu_time,
+            // Despite its `kB` unit, `VmRSS` is expressed in KiB.
+            resident_memory_bytes: resident_memory_kib * 1_024,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct BenchIngestReport {
+    num_docs: usize,
+    num_bytes: u64,
+    ingest_duration: Duration,
+    time_to_searchable_opt: Option<Duration>,
+    cpu_time_opt: Option<Duration>,
+    peak_resident_memory_bytes_opt: Option<u64>,
+}
+
+#[derive(Tabled)]
+struct ReportRow {
+    #[tabled(rename = "Metric")]
+    metric: &'static str,
+    #[tabled(rename = "Value")]
+    value: String,
+}
+
+impl BenchIngestReport {
+    fn rows(&self) -> Vec<ReportRow> {
+        let ingest_secs = self.ingest_duration.as_secs_f64().max(f64::EPSILON);
+        let not_available = || "n/a".to_string();
+        vec![
+            ReportRow {
+                metric: "Documents ingested",
+                value: self.num_docs.separate_with_commas(),
+            },
+            ReportRow {
+                metric: "Bytes ingested",
+                value: format_size(self.num_bytes, DECIMAL),
+            },
+            ReportRow {
+                metric: "Ingestion duration",
+                value: format!("{:.2?}", self.ingest_duration),
+            },
+            ReportRow {
+                metric: "Throughput",
+                value: format!(
+                    "{:.0} docs/s, {}/s",
+                    self.num_docs as f64 / ingest_secs,
+                    format_size((self.num_bytes as f64 / ingest_secs) as u64, DECIMAL)
+                ),
+            },
+            ReportRow {
+                metric: "Time to searchable",
+                value: self
+                    .time_to_searchable_opt
+                    .map(|duration| format!("{:.2?}", duration))
+                    .unwrap_or_else(|| "timed out".to_string()),
+            },
+            ReportRow {
+                metric: "CPU time",
+                value: self
+                    .cpu_time_opt
+                    .map(|cpu_time| format!("{:.2?}", cpu_time))
+                    .unwrap_or_else(not_available),
+            },
+            ReportRow {
+                metric: "Peak resident memory",
+                value: self
+                    .peak_resident_memory_bytes_opt
+                    .map(|num_bytes| format_size(num_bytes, DECIMAL))
+                    .unwrap_or_else(not_available),
+            },
+        ]
+    }
+}
+
+pub async fn bench_ingest_cli(args: BenchIngestArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "bench-ingest");
+    let target: Box<dyn BenchIngestTarget> = match &args.endpoint_opt {
+        Some(endpoint) => Box::new(RemoteTarget {
+            endpoint: endpoint.clone(),
+            index_id: args.index_id.clone(),
+            client: reqwest::Client::new(),
+        }),
+        None => Box::new(LocalTarget::start(&args.config_uri, &args.index_id).await?),
+    };
+    // Resource usage is only meaningful when the pipeline runs in this process.
+    let measure_resources = args.endpoint_opt.is_none();
+    let initial_resource_usage_opt = measure_resources
+        .then(ProcessResourceUsage::sample)
+        .flatten();
+    let mut peak_resident_memory_bytes_opt =
+        initial_resource_usage_opt.map(|usage| usage.resident_memory_bytes);
+    let mut sample_peak_memory = || {
+        if let Some(usage) = measure_resources
+            .then(ProcessResourceUsage::sample)
+            .flatten()
+        {
+            let peak = peak_resident_memory_bytes_opt.get_or_insert(0);
+            *peak = (*peak).max(usage.resident_memory_bytes);
+        }
+    };
+    let mut doc_generator = DocGenerator::from_input_path(args.input_path_opt.as_ref()).await?;
+    let initial_num_searchable_docs = target.num_searchable_docs().await?;
+
+    println!(
+        "Ingesting {} documents into `{}` at {} docs/s...",
+        args.num_docs.separate_with_commas(),
+        args.index_id,
+        args.docs_per_sec.separate_with_commas()
+    );
+    let batch_num_docs = (args.docs_per_sec / BENCH_INGEST_BATCHES_PER_SEC as usize).max(1);
+    let mut batch_interval = tokio::time::interval(Duration::from_secs_f64(
+        batch_num_docs as f64 / args.docs_per_sec as f64,
+    ));
+    let start_instant = Instant::now();
+    let mut num_docs_sent = 0;
+    let mut num_bytes_sent = 0;
+    while num_docs_sent < args.num_docs {
+        batch_interval.tick().await;
+        let num_docs = batch_num_docs.min(args.num_docs - num_docs_sent);
+        let docs: Vec<Vec<u8>> = (0..num_docs).map(|_| doc_generator.next_doc()).collect();
+        num_bytes_sent += docs.iter().map(|doc| doc.len() as u64).sum::<u64>();
+        target.ingest(&docs).await?;
+        num_docs_sent += num_docs;
+        sample_peak_memory();
+    }
+    let ingest_duration = start_instant.elapsed();
+
+    println!("Waiting for the documents to become searchable...");
+    let ingest_end_instant = Instant::now();
+    let mut time_to_searchable_opt = None;
+    while ingest_end_instant.elapsed() < args.timeout {
+        let num_searchable_docs = target.num_searchable_docs().await?;
+        if num_searchable_docs >= initial_num_searchable_docs + args.num_docs {
+            time_to_searchable_opt = Some(ingest_end_instant.elapsed());
+            break;
+        }
+        sample_peak_memory();
+        tokio::time::sleep(SEARCHABLE_POLL_INTERVAL).await;
+    }
+    let cpu_time_opt = match (initial_resource_usage_opt, ProcessResourceUsage::sample()) {
+        (Some(initial_usage), Some(final_usage)) if measure_resources => {
+            Some(final_usage.cpu_time.saturating_sub(initial_usage.cpu_time))
+        }
+        _ => None,
+    };
+    let report = BenchIngestReport {
+        num_docs: num_docs_sent,
+        num_bytes: num_bytes_sent,
+        ingest_duration,
+        time_to_searchable_opt,
+        cpu_time_opt,
+        peak_resident_memory_bytes_opt,
+    };
+    println!("{}", make_table("Ingest benchmark", report.rows(), false));
+    if time_to_searchable_opt.is_none() {
+        bail!(
+            "Documents were not all searchable after {}.",
+            format_duration(args.timeout)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::cli::{build_cli, CliCommand};
+
+    #[test]
+    fn test_parse_bench_ingest_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "tool",
+            "bench-ingest",
+            "--config",
+            "/config.yaml",
+            "--index",
+            "hdfs-logs",
+            "--rate",
+            "500",
+            "--endpoint",
+            "http://localhost:7280/",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Tool(ToolCliCommand::BenchIngest(BenchIngestArgs {
+            config_uri: Uri::from_str("file:///config.yaml")?,
+            index_id: "hdfs-logs".to_string(),
+            input_path_opt: None,
+            num_docs: 100_000,
+            docs_per_sec: 500,
+            endpoint_opt: Some("http://localhost:7280".to_string()),
+            timeout: Duration::from_secs(5 * 60),
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_doc_generator_replay() -> anyhow::Result<()> {
+        let mut input_file = tempfile::NamedTempFile::new()?;
+        input_file.write_all(b"{\"body\": \"foo\"}\n\n{\"body\": \"bar\"}\n")?;
+        let input_path = input_file.path().to_path_buf();
+        let mut doc_generator = DocGenerator::from_input_path(Some(&input_path)).await?;
+        assert_eq!(doc_generator.next_doc(), b"{\"body\": \"foo\"}");
+        assert_eq!(doc_generator.next_doc(), b"{\"body\": \"bar\"}");
+        assert_eq!(doc_generator.next_doc(), b"{\"body\": \"foo\"}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_doc_generator_synthetic() -> anyhow::Result<()> {
+        let mut doc_generator = DocGenerator::from_input_path(None).await?;
+        let doc: serde_json::Value = serde_json::from_slice(&doc_generator.next_doc())?;
+        assert_eq!(doc["level"], "DEBUG");
+        assert_eq!(doc["service"], "api");
+        let doc: serde_json::Value = serde_json::from_slice(&doc_generator.next_doc())?;
+        assert_eq!(doc["level"], "INFO");
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_process_resource_usage() {
+        let usage = ProcessResourceUsage::sample().unwrap();
+        assert!(usage.resident_memory_bytes > 0);
+        assert_eq!(usage.resident_memory_bytes % 1_024, 0);
+        let later_usage = ProcessResourceUsage::sample().unwrap();
+        assert!(later_usage.cpu_time >= usage.cpu_time);
+    }
+}
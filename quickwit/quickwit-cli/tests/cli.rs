@@ -302,6 +302,7 @@ async fn test_cmd_search_aggregation() -> Result<()> {
         start_offset: 0,
         search_fields: Some(vec!["city".to_string()]),
         snippet_fields: None,
+        fetch_fields: None,
         start_timestamp: None,
         end_timestamp: None,
         config_uri: Uri::from_str(&test_env.resource_files["config"].display().to_string())
@@ -377,6 +378,7 @@ async fn test_cmd_search_with_snippets() -> Result<()> {
         start_offset: 0,
         search_fields: None,
         snippet_fields: Some(vec!["event".to_string()]),
+        fetch_fields: None,
         start_timestamp: None,
         end_timestamp: None,
         config_uri: Uri::from_str(&test_env.resource_files["config"].display().to_string())
@@ -414,6 +416,7 @@ async fn test_search_index_cli() {
         start_offset: 0,
         search_fields: None,
         snippet_fields: None,
+        fetch_fields: None,
         start_timestamp: None,
         end_timestamp: None,
         sort_by_score: false,
@@ -554,6 +557,8 @@ async fn test_garbage_collect_cli_no_grace() {
         index_id: index_id.clone(),
         grace_period: Duration::from_secs(3600),
         dry_run,
+        scan_orphans: false,
+        yes: false,
     };
 
     let splits = metastore.list_all_splits(&test_env.index_id).await.unwrap();
@@ -714,6 +719,56 @@ async fn test_cmd_garbage_collect_spares_files_within_grace_period() -> Result<(
     Ok(())
 }
 
+#[tokio::test]
+async fn test_cmd_garbage_collect_scan_orphans() -> Result<()> {
+    let index_id = append_random_suffix("test-gc-cmd--scan-orphans");
+    let test_env = create_test_env(index_id, TestStorageType::LocalFileSystem)?;
+    create_logs_index(&test_env);
+    ingest_docs(test_env.resource_files["logs"].as_path(), &test_env);
+
+    let metastore = test_env.metastore().await?;
+    let splits = metastore.list_all_splits(&test_env.index_id).await?;
+    assert_eq!(splits.len(), 1);
+
+    let index_path = test_env.indexes_dir_path.join(&test_env.index_id);
+    let split_path = index_path.join(quickwit_common::split_file(splits[0].split_id()));
+    let orphan_split_path = index_path.join("orphan-split.split");
+    std::fs::write(&orphan_split_path, b"orphan")?;
+
+    make_command(
+        format!(
+            "index gc --index {} --config {} --scan-orphans",
+            test_env.index_id,
+            test_env.resource_files["config"].display(),
+        )
+        .as_str(),
+    )
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("orphan-split.split"))
+    .stdout(predicate::str::contains("6 B of storage can be reclaimed"));
+    assert_eq!(orphan_split_path.exists(), true);
+
+    make_command(
+        format!(
+            "index gc --index {} --config {} --scan-orphans --yes",
+            test_env.index_id,
+            test_env.resource_files["config"].display(),
+        )
+        .as_str(),
+    )
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(format!(
+        "Index `{}` successfully garbage collected",
+        test_env.index_id
+    )));
+    assert_eq!(orphan_split_path.exists(), false);
+    assert_eq!(split_path.exists(), true);
+
+    Ok(())
+}
+
 #[tokio::test]
 #[cfg_attr(not(feature = "ci-test"), ignore)]
 async fn test_cmd_dry_run_delete_on_s3_localstack() -> Result<()> {
@@ -877,6 +932,7 @@ async fn test_all_with_s3_localstack_cli() {
         start_offset: 0,
         search_fields: None,
         snippet_fields: None,
+        fetch_fields: None,
         start_timestamp: None,
         end_timestamp: None,
         sort_by_score: false,
@@ -263,6 +263,30 @@ impl Cluster {
         build_cluster_members(ready_nodes, &cluster_snapshot.chitchat_state_snapshot)
     }
 
+    /// Returns the ready members that have set `key` in their node state, along with the
+    /// associated value.
+    pub async fn ready_members_key_value(&self, key: &str) -> Vec<(ClusterMember, String)> {
+        let cluster_snapshot = self.snapshot().await;
+        let node_states = &cluster_snapshot.chitchat_state_snapshot.node_states;
+        let mut ready_nodes = cluster_snapshot.ready_nodes.clone();
+        if node_states
+            .get(&cluster_snapshot.self_node_id.id)
+            .map(is_ready_predicate)
+            .unwrap_or(false)
+        {
+            ready_nodes.insert(cluster_snapshot.self_node_id.clone());
+        }
+        ready_nodes
+            .iter()
+            .filter_map(|node_id| {
+                let node_state = node_states.get(&node_id.id)?;
+                let value = node_state.get(key)?.to_string();
+                let member = build_cluster_member(node_id, node_state).ok()?;
+                Some((member, value))
+            })
+            .collect()
+    }
+
     /// Returns the gRPC addresses of the members providing the specified service.
     pub fn members_grpc_advertise_addr_for_service(
         &self,
@@ -1023,4 +1047,44 @@ mod tests {
 
         Ok(())
     }
+    #[tokio::test]
+    async fn test_cluster_ready_members_key_value() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let transport = ChannelTransport::default();
+        let cluster1 = create_cluster_for_test(Vec::new(), &["janitor"], &transport, true).await?;
+        let node_1 = cluster1.gossip_listen_addr.to_string();
+        let cluster2 =
+            create_cluster_for_test(vec![node_1], &["indexer"], &transport, true).await?;
+        let wait_secs = Duration::from_secs(30);
+
+        for cluster in [&cluster1, &cluster2] {
+            cluster
+                .wait_for_members(|members| members.len() == 2, wait_secs)
+                .await
+                .unwrap();
+        }
+        assert!(cluster1
+            .ready_members_key_value("test-key")
+            .await
+            .is_empty());
+
+        cluster2.set_key_value("test-key", "test-value").await;
+        timeout(wait_secs, async {
+            while cluster1
+                .ready_members_key_value("test-key")
+                .await
+                .is_empty()
+            {
+                tokio::time::sleep(Duration::from_millis(25)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let members_key_value = cluster1.ready_members_key_value("test-key").await;
+        assert_eq!(members_key_value.len(), 1);
+        let (member, value) = &members_key_value[0];
+        assert_eq!(member.gossip_advertise_addr, cluster2.gossip_listen_addr);
+        assert_eq!(value, "test-value");
+        Ok(())
+    }
 }
@@ -29,8 +29,8 @@ use quickwit_config::service::QuickwitService;
 use quickwit_config::QuickwitConfig;
 
 pub use crate::cluster::{
-    create_cluster_for_test, grpc_addr_from_listen_addr_for_test, Cluster, ClusterMember,
-    ClusterSnapshot,
+    create_cluster_for_test, create_cluster_for_test_with_id, grpc_addr_from_listen_addr_for_test,
+    Cluster, ClusterMember, ClusterSnapshot,
 };
 pub use crate::error::{ClusterError, ClusterResult};
 
@@ -0,0 +1,145 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Monitors the free space of the disks holding the data of the node. When the free space drops
+//! below a threshold, sources stop emitting documents and the ingest API rejects new documents
+//! until enough space is freed, rather than letting writes fail midway with `ENOSPC`.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use tracing::{error, info, warn};
+
+use crate::metrics::{new_gauge_vec, IntGaugeVec};
+
+const DISK_SPACE_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Writes resume once the free space exceeds the threshold by this ratio, so that they do not
+/// flap around the threshold.
+const RESUME_THRESHOLD_RATIO: f64 = 1.1;
+
+static IS_DISK_SPACE_LOW: AtomicBool = AtomicBool::new(false);
+
+static AVAILABLE_DISK_SPACE_BYTES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    new_gauge_vec(
+        "available_disk_space_bytes",
+        "Free space available on the disk holding the directory, in bytes.",
+        "quickwit",
+        &["directory"],
+    )
+});
+
+/// Returns whether the free space of a monitored directory is below the threshold. Writers
+/// should hold off until it returns `false` again.
+pub fn is_disk_space_low() -> bool {
+    IS_DISK_SPACE_LOW.load(Ordering::Relaxed)
+}
+
+/// Returns the free space available to the process on the disk holding `path`.
+pub fn available_disk_space(path: &Path) -> std::io::Result<u64> {
+    fs2::available_space(path)
+}
+
+/// Starts monitoring the free space of the disks holding `directories`, each identified by a
+/// name used as metric label. A `min_free_space` of zero disables the safeguards, but the free
+/// space is still reported.
+pub fn start_disk_space_monitor(directories: Vec<(&'static str, PathBuf)>, min_free_space: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DISK_SPACE_POLLING_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut min_available_space_opt: Option<u64> = None;
+            for (directory_name, directory_path) in &directories {
+                match available_disk_space(directory_path) {
+                    Ok(available_space) => {
+                        AVAILABLE_DISK_SPACE_BYTES
+                            .with_label_values(&[directory_name])
+                            .set(available_space as i64);
+                        min_available_space_opt = Some(
+                            min_available_space_opt
+                                .map_or(available_space, |min| min.min(available_space)),
+                        );
+                    }
+                    Err(error) => {
+                        error!(directory = %directory_path.display(), error = ?error, "Failed to read the available disk space.");
+                    }
+                }
+            }
+            if let Some(min_available_space) = min_available_space_opt {
+                update_disk_space_low(min_available_space, min_free_space);
+            }
+        }
+    });
+}
+
+fn update_disk_space_low(available_space: u64, min_free_space: u64) {
+    let was_low = is_disk_space_low();
+    let is_low = is_disk_space_low_with_hysteresis(was_low, available_space, min_free_space);
+    if is_low == was_low {
+        return;
+    }
+    IS_DISK_SPACE_LOW.store(is_low, Ordering::Relaxed);
+    if is_low {
+        warn!(
+            available_space = available_space,
+            min_free_space = min_free_space,
+            "Disk space is low, pausing indexing and ingestion."
+        );
+    } else {
+        info!(
+            available_space = available_space,
+            "Disk space is available again, resuming indexing and ingestion."
+        );
+    }
+}
+
+fn is_disk_space_low_with_hysteresis(
+    was_low: bool,
+    available_space: u64,
+    min_free_space: u64,
+) -> bool {
+    if was_low {
+        (available_space as f64) < min_free_space as f64 * RESUME_THRESHOLD_RATIO
+    } else {
+        available_space < min_free_space
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_disk_space_low_with_hysteresis() {
+        assert!(!is_disk_space_low_with_hysteresis(false, 1_000, 0));
+        assert!(!is_disk_space_low_with_hysteresis(false, 1_000, 1_000));
+        assert!(is_disk_space_low_with_hysteresis(false, 999, 1_000));
+        assert!(is_disk_space_low_with_hysteresis(true, 1_050, 1_000));
+        assert!(!is_disk_space_low_with_hysteresis(true, 1_100, 1_000));
+        assert!(!is_disk_space_low_with_hysteresis(true, 1_000, 0));
+    }
+
+    #[test]
+    fn test_available_disk_space() {
+        let tempdir = tempfile::tempdir().unwrap();
+        assert!(available_disk_space(tempdir.path()).unwrap() > 0);
+    }
+}
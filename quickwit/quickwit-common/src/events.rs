@@ -0,0 +1,125 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Operational events, such as pipeline restarts or slow queries, recorded by the node in the
+//! internal events index. Events are dropped unless a sink is installed, which the node only does
+//! when the events index is enabled.
+
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+/// ID of the built-in index storing the operational events of the cluster.
+pub const EVENTS_INDEX_ID: &str = "_quickwit_events";
+
+/// Number of events buffered before new events are dropped.
+const EVENT_SINK_CAPACITY: usize = 10_000;
+
+static EVENT_SINK: OnceCell<mpsc::Sender<Event>> = OnceCell::new();
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    PipelineRestart,
+    PublishFailure,
+    GarbageCollection,
+    SlowQuery,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Event {
+    /// Unix timestamp of the event in seconds.
+    pub timestamp: i64,
+    pub event_type: EventType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_id: Option<String>,
+    pub message: String,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Event {
+    pub fn new(event_type: EventType, message: impl Into<String>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default();
+        Self {
+            timestamp,
+            event_type,
+            index_id: None,
+            message: message.into(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_index_id(mut self, index_id: impl Into<String>) -> Self {
+        self.index_id = Some(index_id.into());
+        self
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: impl ToString) -> Self {
+        self.attributes.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Installs the sink receiving the events recorded by the process. Returns `None` if a sink is
+/// already installed.
+pub fn install_event_sink() -> Option<mpsc::Receiver<Event>> {
+    let (event_tx, event_rx) = mpsc::channel(EVENT_SINK_CAPACITY);
+    EVENT_SINK.set(event_tx).ok()?;
+    Some(event_rx)
+}
+
+/// Records an event. This never blocks: the event is dropped if no sink is installed or if the
+/// sink is lagging behind.
+pub fn record_event(event: Event) {
+    if let Some(event_tx) = EVENT_SINK.get() {
+        let _ = event_tx.try_send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_serialization() {
+        let event = Event {
+            timestamp: 1_660_000_000,
+            ..Event::new(EventType::SlowQuery, "Slow search request.")
+        }
+        .with_index_id("hdfs-logs")
+        .with_attribute("elapsed_millis", 5_250);
+        let event_json = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            event_json,
+            serde_json::json!({
+                "timestamp": 1_660_000_000,
+                "event_type": "slow_query",
+                "index_id": "hdfs-logs",
+                "message": "Slow search request.",
+                "attributes": {"elapsed_millis": "5250"},
+            })
+        );
+    }
+}
@@ -143,6 +143,12 @@ impl IoControls {
         self
     }
 
+    /// Updates the throughput limit of the limiter in place. The new limit applies to all the
+    /// clones of this `IoControls` sharing the same limiter.
+    pub fn update_throughput_limit(&self, throughput: f64) {
+        self.throughput_limiter.set_speed_limit(throughput);
+    }
+
     pub fn set_bytes_counter(mut self, bytes_counter: IntCounter) -> Self {
         self.bytes_counter = bytes_counter;
         self
@@ -371,6 +377,24 @@ mod tests {
         assert_eq!(io_controls.num_bytes(), 2_000_000u64);
     }
 
+    #[tokio::test]
+    async fn test_controlled_writer_updated_limit_async() {
+        let io_controls = IoControls::default().set_throughput_limit(f64::INFINITY);
+        let mut controlled_write = io_controls.clone().wrap_write(sink());
+        io_controls.update_throughput_limit(2_000_000f64);
+        let buf = vec![44u8; 1_000];
+        let start = Instant::now();
+        // We write 200 KB
+        for _ in 0..200 {
+            controlled_write.write_all(&buf).await.unwrap();
+        }
+        controlled_write.flush().await.unwrap();
+        // The limiter runs on the wall clock, so only the lower bound, which shows that the
+        // updated limit applies, is checked.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50));
+    }
+
     #[test]
     fn test_controlled_writer_limited_sync() {
         let io_controls = IoControls::default().set_throughput_limit(2_000_000f64);
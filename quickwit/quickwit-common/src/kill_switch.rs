@@ -20,22 +20,33 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, Weak};
 
+use once_cell::sync::OnceCell;
 use tracing::debug;
 
+/// Kill switches form a tree: killing a kill switch kills all its descendants.
+///
+/// Each kill switch belongs to a domain. Named children open a new domain nested in the domain of
+/// their parent, for instance `indexing-pipeline:my-index:my-source:0`, and a whole domain can be
+/// killed from any of its ancestors with [`KillSwitch::kill_domain`]. Once dead, a kill switch
+/// remembers the domain of the kill switch whose activation killed it.
 #[derive(Clone, Default)]
 pub struct KillSwitch {
     inner: Arc<Inner>,
 }
 
 struct Inner {
+    domain: String,
     alive: AtomicBool,
+    killed_by: OnceCell<String>,
     children: Mutex<Vec<Weak<Inner>>>,
 }
 
 impl Default for Inner {
     fn default() -> Self {
         Self {
+            domain: String::new(),
             alive: AtomicBool::new(true),
+            killed_by: OnceCell::new(),
             children: Mutex::default(),
         }
     }
@@ -53,6 +64,21 @@ fn garbage_collect(children: &mut Vec<Weak<Inner>>) {
 }
 
 impl KillSwitch {
+    /// Creates a root kill switch opening the domain `domain`.
+    pub fn named(domain: impl Into<String>) -> KillSwitch {
+        KillSwitch {
+            inner: Arc::new(Inner {
+                domain: domain.into(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Returns the domain of the kill switch. Domains are nested with `/`.
+    pub fn domain(&self) -> &str {
+        &self.inner.domain
+    }
+
     pub fn is_alive(&self) -> bool {
         self.inner.alive.load(Ordering::Relaxed)
     }
@@ -61,17 +87,48 @@ impl KillSwitch {
         !self.is_alive()
     }
 
+    /// Returns the domain of the kill switch that triggered the death of this kill switch, or
+    /// `None` if it is still alive.
+    pub fn killed_by(&self) -> Option<&str> {
+        self.inner.killed_by.get().map(String::as_str)
+    }
+
     pub fn kill(&self) {
-        self.inner.kill();
+        self.inner.kill(&self.inner.domain);
+    }
+
+    /// Kills the kill switches of the sub-tree opening the domain `domain`, along with their
+    /// descendants, and returns how many of them were alive. The other kill switches are left
+    /// untouched.
+    pub fn kill_domain(&self, domain: &str) -> usize {
+        self.inner.kill_domain(domain)
     }
 
     // Creates a child killswitch.
     //
     // If the parent kill switch is dead to begin with, the child will be dead too.
     pub fn child(&self) -> KillSwitch {
+        self.create_child(self.inner.domain.clone())
+    }
+
+    /// Creates a child kill switch opening the domain `name`, nested in the domain of its parent.
+    ///
+    /// If the parent kill switch is dead to begin with, the child will be dead too.
+    pub fn named_child(&self, name: &str) -> KillSwitch {
+        let domain = if self.inner.domain.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.inner.domain, name)
+        };
+        self.create_child(domain)
+    }
+
+    fn create_child(&self, domain: String) -> KillSwitch {
         let mut lock = self.inner.children.lock().unwrap();
         let child_inner = Inner {
+            domain,
             alive: AtomicBool::new(self.is_alive()),
+            killed_by: self.inner.killed_by.clone(),
             ..Default::default()
         };
         garbage_collect(&mut *lock);
@@ -84,17 +141,39 @@ impl KillSwitch {
 }
 
 impl Inner {
-    pub fn kill(&self) {
-        debug!("kill-switch-activated");
+    pub fn kill(&self, killed_by: &str) {
+        debug!(domain=%self.domain, killed_by=%killed_by, "kill-switch-activated");
+        // The first kill wins. The cause is recorded before the switch dies so that whoever
+        // observes a dead kill switch can tell who killed it.
+        let _ = self.killed_by.set(killed_by.to_string());
         self.alive.store(false, Ordering::Relaxed);
         let mut lock = self.children.lock().unwrap();
         for weak in lock.drain(..) {
             if let Some(inner) = weak.upgrade() {
-                inner.kill();
+                inner.kill(killed_by);
             }
         }
     }
+
+    fn kill_domain(&self, domain: &str) -> usize {
+        if self.domain == domain {
+            let was_alive = self.alive.load(Ordering::Relaxed);
+            self.kill(domain);
+            return usize::from(was_alive);
+        }
+        // Only the descendants of the kill switch can open the domain.
+        if !self.domain.is_empty() && !domain.starts_with(&format!("{}/", self.domain)) {
+            return 0;
+        }
+        let children: Vec<Arc<Inner>> = {
+            let mut lock = self.children.lock().unwrap();
+            garbage_collect(&mut *lock);
+            lock.iter().filter_map(Weak::upgrade).collect()
+        };
+        children.iter().map(|child| child.kill_domain(domain)).sum()
+    }
 }
+
 #[cfg(test)]
 mod tests {
     use super::KillSwitch;
@@ -148,4 +227,53 @@ mod tests {
         assert!(kill_switch.is_alive());
         assert!(child_kill_switch.is_dead());
     }
+
+    #[test]
+    fn test_kill_switch_domains() {
+        let kill_switch = KillSwitch::named("universe");
+        let pipeline_kill_switch = kill_switch.named_child("pipeline-0");
+        let actor_kill_switch = pipeline_kill_switch.child();
+        let sub_kill_switch = pipeline_kill_switch.named_child("sub");
+        assert_eq!(kill_switch.domain(), "universe");
+        assert_eq!(pipeline_kill_switch.domain(), "universe/pipeline-0");
+        assert_eq!(actor_kill_switch.domain(), "universe/pipeline-0");
+        assert_eq!(sub_kill_switch.domain(), "universe/pipeline-0/sub");
+        assert_eq!(KillSwitch::default().named_child("foo").domain(), "foo");
+    }
+
+    #[test]
+    fn test_kill_switch_kill_domain() {
+        let kill_switch = KillSwitch::named("universe");
+        let pipeline_0_kill_switch = kill_switch.named_child("pipeline-0");
+        let actor_0_kill_switch = pipeline_0_kill_switch.child();
+        let pipeline_1_kill_switch = kill_switch.named_child("pipeline-1");
+        let actor_1_kill_switch = pipeline_1_kill_switch.child();
+
+        assert_eq!(kill_switch.kill_domain("universe/pipeline-2"), 0);
+        assert_eq!(kill_switch.kill_domain("universe/pipeline-0"), 1);
+        assert!(kill_switch.is_alive());
+        assert!(pipeline_0_kill_switch.is_dead());
+        assert!(actor_0_kill_switch.is_dead());
+        assert!(pipeline_1_kill_switch.is_alive());
+        assert!(actor_1_kill_switch.is_alive());
+        assert_eq!(kill_switch.kill_domain("universe/pipeline-0"), 0);
+    }
+
+    #[test]
+    fn test_kill_switch_killed_by() {
+        let kill_switch = KillSwitch::named("universe");
+        let pipeline_kill_switch = kill_switch.named_child("pipeline");
+        let actor_kill_switch = pipeline_kill_switch.child();
+        assert!(actor_kill_switch.killed_by().is_none());
+
+        actor_kill_switch.kill();
+        assert_eq!(actor_kill_switch.killed_by(), Some("universe/pipeline"));
+        assert!(pipeline_kill_switch.killed_by().is_none());
+
+        kill_switch.kill();
+        assert_eq!(pipeline_kill_switch.killed_by(), Some("universe"));
+        // The first kill wins.
+        assert_eq!(actor_kill_switch.killed_by(), Some("universe/pipeline"));
+        assert_eq!(kill_switch.child().killed_by(), Some("universe"));
+    }
 }
@@ -20,6 +20,8 @@
 mod checklist;
 mod coolid;
 
+pub mod disk_space;
+pub mod events;
 pub mod fs;
 pub mod io;
 mod kill_switch;
@@ -61,6 +63,19 @@ pub fn split_file(split_id: &str) -> String {
     format!("{}.split", split_id)
 }
 
+/// Returns the name of the file holding the bloom filters of a split.
+pub fn bloom_filter_file(split_id: &str) -> String {
+    format!("{}.bloom", split_id)
+}
+
+/// Name of the directory, within an index directory, holding the Parquet sidecar files of the
+/// splits.
+pub const PARQUET_SIDECAR_DIR_NAME: &str = "parquet";
+
+pub fn parquet_sidecar_file(split_id: &str) -> String {
+    format!("{}/{}.parquet", PARQUET_SIDECAR_DIR_NAME, split_id)
+}
+
 pub fn get_from_env<T: FromStr + Debug>(key: &str, default_value: T) -> T {
     if let Ok(value_str) = std::env::var(key) {
         if let Ok(value) = T::from_str(&value_str) {
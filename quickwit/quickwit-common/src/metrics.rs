@@ -62,6 +62,28 @@ pub fn new_histogram_vec(
     histogram_vec
 }
 
+/// Creates a histogram vec whose buckets are `num_buckets` powers of `factor` starting at `start`.
+/// The default buckets of the histograms are meant for durations in seconds.
+pub fn new_exponential_histogram_vec(
+    name: &str,
+    description: &str,
+    namespace: &str,
+    labels: &[&str],
+    start: f64,
+    factor: f64,
+    num_buckets: usize,
+) -> HistogramVec {
+    let buckets = prometheus::exponential_buckets(start, factor, num_buckets)
+        .expect("Failed to create exponential buckets");
+    let histogram_opts = HistogramOpts::new(name, description)
+        .namespace(namespace)
+        .buckets(buckets);
+    let histogram_vec =
+        HistogramVec::new(histogram_opts, labels).expect("Failed to create histogram vec");
+    prometheus::register(Box::new(histogram_vec.clone())).expect("Failed to register counter");
+    histogram_vec
+}
+
 pub fn new_gauge(name: &str, description: &str, namespace: &str) -> IntGauge {
     let gauge_opts = Opts::new(name, description).namespace(namespace);
     let gauge = IntGauge::with_opts(gauge_opts).expect("Failed to create gauge");
@@ -81,7 +103,11 @@ pub fn new_gauge_vec(
     gauge_vec
 }
 
-pub fn metrics_handler() -> impl warp::Reply {
+/// Content type of the payload returned by [`metrics_text_payload`].
+pub const METRICS_TEXT_CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+/// Encodes the registered metrics in the Prometheus text exposition format.
+pub fn metrics_text_payload() -> String {
     let metric_families = prometheus::gather();
     let mut buffer = Vec::new();
     let encoder = TextEncoder::new();
@@ -89,15 +115,88 @@ pub fn metrics_handler() -> impl warp::Reply {
     String::from_utf8_lossy(&buffer).to_string()
 }
 
-pub fn create_gauge_guard(gauge: &'static IntGauge) -> GaugeGuard {
-    gauge.inc();
-    GaugeGuard(gauge)
+pub fn metrics_handler() -> impl warp::Reply {
+    metrics_text_payload()
 }
 
-pub struct GaugeGuard(&'static IntGauge);
+pub fn create_gauge_guard(gauge: &IntGauge) -> GaugeGuard {
+    GaugeGuard::from_gauge(gauge)
+}
+
+/// Increments a gauge when created and decrements it when dropped, which makes in-flight
+/// tracking robust to early returns and errors.
+#[must_use = "The gauge is decremented as soon as the guard is dropped."]
+pub struct GaugeGuard(IntGauge);
+
+impl GaugeGuard {
+    pub fn from_gauge(gauge: &IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge.clone())
+    }
+
+    /// Creates a guard for the gauge of `gauge_vec` identified by `label_values`.
+    pub fn from_gauge_vec(gauge_vec: &IntGaugeVec, label_values: &[&str]) -> Self {
+        Self::from_gauge(&gauge_vec.with_label_values(label_values))
+    }
+}
 
 impl Drop for GaugeGuard {
     fn drop(&mut self) {
         self.0.dec();
     }
 }
+
+/// Starts a timer observing the elapsed time in the histogram of `histogram_vec` identified by
+/// `label_values` when dropped. Call [`HistogramTimer::stop_and_discard`] to skip the
+/// observation, for instance on errors.
+#[must_use = "The duration is observed as soon as the timer is dropped."]
+pub fn start_histogram_timer(
+    histogram_vec: &HistogramVec,
+    label_values: &[&str],
+) -> HistogramTimer {
+    histogram_vec.with_label_values(label_values).start_timer()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gauge_guard() {
+        let gauge_vec =
+            IntGaugeVec::new(Opts::new("test_gauge_guard", "Test gauge."), &["label"]).unwrap();
+        {
+            let _guard_foo = GaugeGuard::from_gauge_vec(&gauge_vec, &["foo"]);
+            let _guard_bar = GaugeGuard::from_gauge_vec(&gauge_vec, &["bar"]);
+            {
+                let _guard_foo = GaugeGuard::from_gauge_vec(&gauge_vec, &["foo"]);
+                assert_eq!(gauge_vec.with_label_values(&["foo"]).get(), 2);
+            }
+            assert_eq!(gauge_vec.with_label_values(&["foo"]).get(), 1);
+            assert_eq!(gauge_vec.with_label_values(&["bar"]).get(), 1);
+        }
+        assert_eq!(gauge_vec.with_label_values(&["foo"]).get(), 0);
+        assert_eq!(gauge_vec.with_label_values(&["bar"]).get(), 0);
+    }
+
+    #[test]
+    fn test_histogram_timer() {
+        let histogram_vec = HistogramVec::new(
+            HistogramOpts::new("test_histogram_timer", "Test histogram."),
+            &["label"],
+        )
+        .unwrap();
+        {
+            let _timer = start_histogram_timer(&histogram_vec, &["foo"]);
+        }
+        start_histogram_timer(&histogram_vec, &["bar"]).stop_and_discard();
+        assert_eq!(
+            histogram_vec.with_label_values(&["foo"]).get_sample_count(),
+            1
+        );
+        assert_eq!(
+            histogram_vec.with_label_values(&["bar"]).get_sample_count(),
+            0
+        );
+    }
+}
@@ -50,7 +50,7 @@ pub enum RuntimeType {
     IngestApi,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Eq, PartialEq)]
 pub struct RuntimesConfiguration {
     num_threads_non_blocking: usize,
     num_threads_blocking: usize,
@@ -0,0 +1,211 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use humantime::parse_duration;
+use serde::{Deserialize, Serialize};
+
+fn default_alert_enabled() -> bool {
+    true
+}
+
+/// Turns a stored query into an alert evaluated periodically by the janitor.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertConfig {
+    /// Defines how often the alert is evaluated, expressed in a human-friendly way (`1 minute`,
+    /// `5m`, ...). Each evaluation searches the documents of the time range elapsed since the
+    /// previous evaluation.
+    pub evaluation_interval: String,
+    /// Condition on the number of hits of an evaluation for the alert to fire.
+    pub condition: AlertCondition,
+    /// Notification channels of the alert, notified when the alert fires and resolves.
+    pub channels: Vec<AlertChannel>,
+    #[serde(default = "default_alert_enabled")]
+    pub enabled: bool,
+    /// Role the alert searches are issued with. Required on the indexes defining field access
+    /// policies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+}
+
+impl AlertConfig {
+    pub fn evaluation_interval(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.evaluation_interval).with_context(|| {
+            format!(
+                "Failed to parse alert evaluation interval `{}`.",
+                self.evaluation_interval
+            )
+        })
+    }
+
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        if self.evaluation_interval()?.as_secs() == 0 {
+            bail!("Alert evaluation interval must be at least one second.");
+        }
+        if self.channels.is_empty() {
+            bail!("Alert must have at least one notification channel.");
+        }
+        Ok(())
+    }
+}
+
+/// Compares the number of hits of an evaluation to a threshold.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertCondition {
+    pub operator: ThresholdOperator,
+    pub threshold: u64,
+}
+
+impl AlertCondition {
+    /// Returns whether the condition is met by `num_hits`.
+    pub fn is_met(&self, num_hits: u64) -> bool {
+        match self.operator {
+            ThresholdOperator::GreaterThan => num_hits > self.threshold,
+            ThresholdOperator::GreaterThanOrEqual => num_hits >= self.threshold,
+            ThresholdOperator::LessThan => num_hits < self.threshold,
+            ThresholdOperator::LessThanOrEqual => num_hits <= self.threshold,
+        }
+    }
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let operator = match self.operator {
+            ThresholdOperator::GreaterThan => ">",
+            ThresholdOperator::GreaterThanOrEqual => ">=",
+            ThresholdOperator::LessThan => "<",
+            ThresholdOperator::LessThanOrEqual => "<=",
+        };
+        write!(formatter, "num_hits {} {}", operator, self.threshold)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdOperator {
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+}
+
+/// A channel notified when an alert fires and resolves.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AlertChannel {
+    /// Posts a JSON payload describing the alert to `url`.
+    Webhook { url: String },
+    /// Triggers and resolves incidents through the PagerDuty Events API v2.
+    #[serde(rename = "pagerduty")]
+    PagerDuty { routing_key: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_config_serde() {
+        let alert_config_yaml = r#"
+            evaluation_interval: 5m
+            condition:
+                operator: greater_than
+                threshold: 100
+            channels:
+                - type: webhook
+                  url: https://hooks.example.com/alerts
+                - type: pagerduty
+                  routing_key: my-routing-key
+        "#;
+        let alert_config: AlertConfig = serde_yaml::from_str(alert_config_yaml).unwrap();
+        assert_eq!(
+            alert_config,
+            AlertConfig {
+                evaluation_interval: "5m".to_string(),
+                condition: AlertCondition {
+                    operator: ThresholdOperator::GreaterThan,
+                    threshold: 100,
+                },
+                channels: vec![
+                    AlertChannel::Webhook {
+                        url: "https://hooks.example.com/alerts".to_string()
+                    },
+                    AlertChannel::PagerDuty {
+                        routing_key: "my-routing-key".to_string()
+                    },
+                ],
+                enabled: true,
+                role: None,
+            }
+        );
+        alert_config.validate().unwrap();
+        assert_eq!(
+            alert_config.evaluation_interval().unwrap(),
+            Duration::from_secs(300)
+        );
+    }
+
+    #[test]
+    fn test_alert_config_validate() {
+        let alert_config = AlertConfig {
+            evaluation_interval: "forever".to_string(),
+            condition: AlertCondition {
+                operator: ThresholdOperator::GreaterThan,
+                threshold: 0,
+            },
+            channels: vec![AlertChannel::Webhook {
+                url: "https://hooks.example.com/alerts".to_string(),
+            }],
+            enabled: true,
+            role: None,
+        };
+        alert_config.validate().unwrap_err();
+
+        let alert_config = AlertConfig {
+            evaluation_interval: "1m".to_string(),
+            channels: Vec::new(),
+            ..alert_config
+        };
+        let error = alert_config.validate().unwrap_err();
+        assert!(error.to_string().contains("notification channel"));
+    }
+
+    #[test]
+    fn test_alert_condition() {
+        let condition = AlertCondition {
+            operator: ThresholdOperator::GreaterThanOrEqual,
+            threshold: 10,
+        };
+        assert!(!condition.is_met(9));
+        assert!(condition.is_met(10));
+        assert_eq!(condition.to_string(), "num_hits >= 10");
+
+        let condition = AlertCondition {
+            operator: ThresholdOperator::LessThan,
+            threshold: 1,
+        };
+        assert!(condition.is_met(0));
+        assert!(!condition.is_met(1));
+    }
+}
@@ -17,8 +17,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeSet, HashMap};
-use std::num::NonZeroU64;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::num::{NonZeroU64, NonZeroUsize};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -36,10 +36,11 @@ use quickwit_doc_mapper::{
 };
 use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
+use tantivy::schema::{FieldType, Schema, Type};
 
 use crate::merge_policy_config::{MergePolicyConfig, StableLogMergePolicyConfig};
 use crate::source_config::SourceConfig;
-use crate::validate_identifier;
+use crate::{is_false, validate_identifier};
 
 // Note(fmassot): `DocMapping` is a struct only used for
 // serialization/deserialization of `DocMapper` parameters.
@@ -54,6 +55,15 @@ pub struct DocMapping {
     #[serde(default)]
     pub tag_fields: BTreeSet<String>,
     #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub tag_keyword_fields: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub bloom_filter_fields: BTreeSet<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub dynamic_all: bool,
+    #[serde(default)]
     pub store_source: bool,
     #[serde(default)]
     pub mode: ModeType,
@@ -65,6 +75,12 @@ pub struct DocMapping {
     pub max_num_partitions: NonZeroU64,
 }
 
+const DEFAULT_SEGMENT_MEMORY_BUDGET: u64 = 10_000_000; // 10MB
+
+// Bounds of the memory arena of a tantivy segment writer.
+const MIN_SEGMENT_MEMORY_BUDGET: u64 = 3_000_000; // 3MB
+const MAX_SEGMENT_MEMORY_BUDGET: u64 = 4_000_000_000; // 4GB
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct IndexingResources {
@@ -84,11 +100,25 @@ pub struct IndexingResources {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_janitor_write_throughput: Option<Byte>,
+    /// Number of doc processors parsing and mapping the documents of the pipeline in parallel.
+    /// Batches are dispatched to the doc processors by source partition so that the documents of
+    /// a given partition are still indexed in order. Defaults to 1.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_doc_processors: Option<NonZeroUsize>,
+    /// Memory budget of the writer of each split, used to size its term hash table up front.
+    /// Documents with many distinct terms, such as large JSON documents, benefit from a larger
+    /// budget, which saves the rehashing of the table. Defaults to 10MB.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment_memory_budget: Option<Byte>,
 }
 
 impl PartialEq for IndexingResources {
     fn eq(&self, other: &Self) -> bool {
         self.heap_size == other.heap_size
+            && self.num_doc_processors == other.num_doc_processors
+            && self.segment_memory_budget == other.segment_memory_budget
     }
 }
 
@@ -97,6 +127,28 @@ impl IndexingResources {
         Byte::from_bytes(2_000_000_000) // 2GB
     }
 
+    pub fn num_doc_processors(&self) -> usize {
+        self.num_doc_processors.map(NonZeroUsize::get).unwrap_or(1)
+    }
+
+    pub fn segment_memory_budget(&self) -> Byte {
+        self.segment_memory_budget
+            .unwrap_or_else(|| Byte::from_bytes(DEFAULT_SEGMENT_MEMORY_BUDGET))
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(segment_memory_budget) = self.segment_memory_budget {
+            let num_bytes = segment_memory_budget.get_bytes();
+            if !(MIN_SEGMENT_MEMORY_BUDGET..=MAX_SEGMENT_MEMORY_BUDGET).contains(&num_bytes) {
+                bail!(
+                    "Indexing resource `segment_memory_budget` must be between 3MB and 4GB, got \
+                     {num_bytes} bytes."
+                );
+            }
+        }
+        Ok(())
+    }
+
     #[cfg(any(test, feature = "testsuite"))]
     pub fn for_test() -> Self {
         Self {
@@ -112,6 +164,8 @@ impl Default for IndexingResources {
             heap_size: Self::default_heap_size(),
             max_merge_write_throughput: None,
             max_janitor_write_throughput: None,
+            num_doc_processors: None,
+            segment_memory_budget: None,
             __num_threads_deprecated: IgnoredAny,
         }
     }
@@ -163,6 +217,11 @@ pub struct IndexingSettings {
     pub docstore_compression_level: i32,
     #[serde(default = "IndexingSettings::default_docstore_blocksize")]
     pub docstore_blocksize: usize,
+    /// Compressor of the blocks of the doc store. `docstore_compression_level` only applies to
+    /// `zstd`. Defaults to `zstd`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docstore_compressor: Option<DocstoreCompressor>,
     /// The merge policy aims to eventually produce mature splits that have a larger size but
     /// are within close range of `split_num_docs_target`.
     ///
@@ -175,6 +234,335 @@ pub struct IndexingSettings {
     pub merge_policy: MergePolicyConfig,
     #[serde(default)]
     pub resources: IndexingResources,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parquet_sidecar: Option<ParquetSidecarSettings>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<DedupSettings>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adaptive_commit: Option<AdaptiveCommitSettings>,
+    /// Node running the merge pipelines of the index instead of the nodes indexing it. The node
+    /// must have remote merge enabled.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_node_id: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingest_queue: Option<IngestQueueSettings>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hotcache: Option<HotcacheSettings>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_slicing: Option<TimeSlicingSettings>,
+}
+
+/// Compressors available for the blocks of the doc store.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocstoreCompressor {
+    /// Zstandard, at the level set by `docstore_compression_level`. Slower, but compresses
+    /// best.
+    #[default]
+    Zstd,
+    /// LZ4. Compresses less than `zstd`, but is much faster to compress and decompress.
+    Lz4,
+    /// No compression.
+    None,
+}
+
+/// Settings of the Parquet sidecar files: columnar copies of some fields of the documents of each
+/// published split, written next to the split files so that analytical engines such as Trino or
+/// Spark can scan the data without going through the search API.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParquetSidecarSettings {
+    /// Fields copied to the sidecar files. They must be stored fields of type `text`, `i64`,
+    /// `u64`, `f64`, `bool`, or `datetime`.
+    pub fields: Vec<String>,
+}
+
+impl ParquetSidecarSettings {
+    fn validate(&self, schema: &Schema) -> anyhow::Result<()> {
+        if self.fields.is_empty() {
+            bail!("Parquet sidecar settings must define at least one field.");
+        }
+        let mut field_names = HashSet::new();
+        for field_name in &self.fields {
+            if !field_names.insert(field_name) {
+                bail!(
+                    "Parquet sidecar field `{}` is declared more than once.",
+                    field_name
+                );
+            }
+            let field = schema.get_field(field_name).with_context(|| {
+                format!(
+                    "Parquet sidecar field `{}` does not exist in the doc mapping.",
+                    field_name
+                )
+            })?;
+            let field_entry = schema.get_field_entry(field);
+            if !field_entry.is_stored() {
+                bail!("Parquet sidecar field `{}` must be stored.", field_name);
+            }
+            match field_entry.field_type().value_type() {
+                Type::Str | Type::I64 | Type::U64 | Type::F64 | Type::Bool | Type::Date => {}
+                value_type => bail!(
+                    "Parquet sidecar field `{}` of type `{:?}` is not supported. Supported types \
+                     are `text`, `i64`, `u64`, `f64`, `bool`, and `datetime`.",
+                    field_name,
+                    value_type
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings of the best-effort deduplication of the documents sharing the same id, aimed at
+/// sources with at-least-once delivery semantics.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DedupSettings {
+    /// Field holding the unique id of the documents. It must be an indexed `text` field using
+    /// the `raw` tokenizer, or an indexed `u64` or `i64` field.
+    pub doc_id_field: String,
+    /// Number of recently seen ids remembered by each indexing pipeline, per partition. A
+    /// document is dropped if its id is among them.
+    #[serde(default = "DedupSettings::default_cache_capacity")]
+    pub cache_capacity: NonZeroUsize,
+    /// Whether merges also drop the documents whose id appears in another document of the
+    /// merged splits.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub enforce_on_merge: bool,
+}
+
+impl DedupSettings {
+    fn default_cache_capacity() -> NonZeroUsize {
+        NonZeroUsize::new(100_000).unwrap()
+    }
+
+    fn validate(&self, schema: &Schema) -> anyhow::Result<()> {
+        let field = schema.get_field(&self.doc_id_field).with_context(|| {
+            format!(
+                "Dedup doc id field `{}` does not exist in the doc mapping.",
+                self.doc_id_field
+            )
+        })?;
+        let is_indexed_id_field = match schema.get_field_entry(field).field_type() {
+            FieldType::Str(text_options) => text_options
+                .get_indexing_options()
+                .map(|indexing_options| indexing_options.tokenizer() == "raw")
+                .unwrap_or(false),
+            FieldType::U64(numeric_options) | FieldType::I64(numeric_options) => {
+                numeric_options.is_indexed()
+            }
+            _ => false,
+        };
+        if !is_indexed_id_field {
+            bail!(
+                "Dedup doc id field `{}` must be an indexed `text` field using the `raw` \
+                 tokenizer, or an indexed `u64` or `i64` field.",
+                self.doc_id_field
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Settings of the adaptive commit policy. When set, the indexer derives the commit timeout of
+/// each split batch from the recent arrival rate of the documents: the timeout is extended when
+/// the traffic is low, so that low-traffic indexes do not produce a tiny split every commit
+/// period, and shortened when the traffic is high, so that documents become searchable sooner.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdaptiveCommitSettings {
+    /// Lower bound of the commit timeout.
+    #[serde(default = "AdaptiveCommitSettings::default_min_commit_timeout_secs")]
+    pub min_commit_timeout_secs: usize,
+    /// Upper bound of the commit timeout.
+    #[serde(default = "AdaptiveCommitSettings::default_max_commit_timeout_secs")]
+    pub max_commit_timeout_secs: usize,
+    /// Number of documents each commit aims at. The commit timeout is the time expected to
+    /// receive that many documents at the recent arrival rate, within the bounds above.
+    #[serde(default = "AdaptiveCommitSettings::default_commit_num_docs_target")]
+    pub commit_num_docs_target: usize,
+}
+
+impl AdaptiveCommitSettings {
+    fn default_min_commit_timeout_secs() -> usize {
+        10
+    }
+
+    fn default_max_commit_timeout_secs() -> usize {
+        600
+    }
+
+    fn default_commit_num_docs_target() -> usize {
+        1_000_000
+    }
+
+    pub fn min_commit_timeout(&self) -> Duration {
+        Duration::from_secs(self.min_commit_timeout_secs as u64)
+    }
+
+    pub fn max_commit_timeout(&self) -> Duration {
+        Duration::from_secs(self.max_commit_timeout_secs as u64)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.min_commit_timeout_secs == 0 {
+            bail!("Adaptive commit `min_commit_timeout_secs` must be strictly positive.");
+        }
+        if self.min_commit_timeout_secs > self.max_commit_timeout_secs {
+            bail!(
+                "Adaptive commit `min_commit_timeout_secs` ({}) must be lower than or equal to \
+                 `max_commit_timeout_secs` ({}).",
+                self.min_commit_timeout_secs,
+                self.max_commit_timeout_secs
+            );
+        }
+        if self.commit_num_docs_target == 0 {
+            bail!("Adaptive commit `commit_num_docs_target` must be strictly positive.");
+        }
+        Ok(())
+    }
+}
+
+impl Default for AdaptiveCommitSettings {
+    fn default() -> Self {
+        Self {
+            min_commit_timeout_secs: Self::default_min_commit_timeout_secs(),
+            max_commit_timeout_secs: Self::default_max_commit_timeout_secs(),
+            commit_num_docs_target: Self::default_commit_num_docs_target(),
+        }
+    }
+}
+
+/// Settings of the ingest API queue of the index. By default, the records of the queue are deleted
+/// as soon as they are indexed. A retention period keeps the indexed records in the queue for
+/// some time so that they can be replayed, for instance to reindex them after a bad deployment.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IngestQueueSettings {
+    /// Period during which the indexed records are kept in the queue, expressed in a
+    /// human-friendly way (`6 hours`, `2 days`, ...).
+    retention_period: String,
+}
+
+impl IngestQueueSettings {
+    pub fn new(retention_period: String) -> Self {
+        Self { retention_period }
+    }
+
+    pub fn retention_period(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.retention_period).with_context(|| {
+            format!(
+                "Failed to parse ingest queue retention period `{}`.",
+                self.retention_period
+            )
+        })
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        self.retention_period()?;
+        Ok(())
+    }
+}
+
+/// Settings of the time slicing of the merged splits: instead of producing a single split spanning
+/// the time range of its input splits, a merge produces one split per time slice, so that
+/// retention and time pruning remain effective after merging backfilled data.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeSlicingSettings {
+    /// Duration of the time slices, expressed in a human-friendly way (`1 hour`, `1 day`, ...).
+    /// Slices are aligned on multiples of their duration since the Unix epoch.
+    slice_duration: String,
+}
+
+impl TimeSlicingSettings {
+    pub fn new(slice_duration: String) -> Self {
+        Self { slice_duration }
+    }
+
+    pub fn slice_duration(&self) -> anyhow::Result<Duration> {
+        let slice_duration = parse_duration(&self.slice_duration).with_context(|| {
+            format!(
+                "Failed to parse time slicing slice duration `{}`.",
+                self.slice_duration
+            )
+        })?;
+        if slice_duration.as_secs() == 0 {
+            bail!(
+                "Time slicing slice duration `{}` must be at least one second.",
+                self.slice_duration
+            );
+        }
+        Ok(slice_duration)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        self.slice_duration()?;
+        Ok(())
+    }
+}
+
+/// Settings of the hotcache of the splits, i.e. the parts of a split loaded in memory when a
+/// searcher opens it. With wide schemas, restricting the hotcache to the fields that are searched
+/// the most keeps it small, at the cost of extra storage requests when searching other fields.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HotcacheSettings {
+    /// Indexed fields whose term dictionary is included in the hotcache. All the indexed fields
+    /// are included if empty.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+    /// Byte ranges larger than this size are read on demand rather than stored in the hotcache,
+    /// except for the term dictionaries and the doc store index.
+    #[serde(default = "HotcacheSettings::default_max_slice_size")]
+    pub max_slice_size: Byte,
+}
+
+impl HotcacheSettings {
+    fn default_max_slice_size() -> Byte {
+        Byte::from_bytes(10_000_000) // 10MB
+    }
+
+    fn validate(&self, schema: &Schema) -> anyhow::Result<()> {
+        let mut field_names = HashSet::new();
+        for field_name in &self.fields {
+            if !field_names.insert(field_name) {
+                bail!(
+                    "Hotcache field `{}` is declared more than once.",
+                    field_name
+                );
+            }
+            let field = schema.get_field(field_name).with_context(|| {
+                format!(
+                    "Hotcache field `{}` does not exist in the doc mapping.",
+                    field_name
+                )
+            })?;
+            if !schema.get_field_entry(field).is_indexed() {
+                bail!("Hotcache field `{}` must be indexed.", field_name);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for HotcacheSettings {
+    fn default() -> Self {
+        Self {
+            fields: Vec::new(),
+            max_slice_size: Self::default_max_slice_size(),
+        }
+    }
 }
 
 /// The IndexingSettingsLegacy struct is just here to deserialize version 0 / version 1
@@ -229,9 +617,17 @@ impl From<IndexingSettingsLegacy> for IndexingSettings {
             commit_timeout_secs: settings.commit_timeout_secs,
             docstore_compression_level: settings.docstore_compression_level,
             docstore_blocksize: settings.docstore_blocksize,
+            docstore_compressor: None,
             split_num_docs_target: settings.split_num_docs_target,
             merge_policy,
             resources: settings.resources,
+            parquet_sidecar: None,
+            dedup: None,
+            adaptive_commit: None,
+            merge_node_id: None,
+            ingest_queue: None,
+            hotcache: None,
+            time_slicing: None,
         }
     }
 }
@@ -274,6 +670,15 @@ impl IndexingSettings {
         Duration::from_secs(self.commit_timeout_secs as u64)
     }
 
+    /// Returns the longest time a document can wait in the indexer before being committed,
+    /// accounting for the adaptive commit policy.
+    pub fn max_commit_timeout(&self) -> Duration {
+        self.adaptive_commit
+            .as_ref()
+            .map(AdaptiveCommitSettings::max_commit_timeout)
+            .unwrap_or_else(|| self.commit_timeout())
+    }
+
     fn default_commit_timeout_secs() -> usize {
         60
     }
@@ -290,6 +695,10 @@ impl IndexingSettings {
         10_000_000
     }
 
+    pub fn docstore_compressor(&self) -> DocstoreCompressor {
+        self.docstore_compressor.unwrap_or_default()
+    }
+
     fn default_merge_enabled() -> bool {
         true
     }
@@ -320,9 +729,17 @@ impl Default for IndexingSettings {
             commit_timeout_secs: Self::default_commit_timeout_secs(),
             docstore_blocksize: Self::default_docstore_blocksize(),
             docstore_compression_level: Self::default_docstore_compression_level(),
+            docstore_compressor: None,
             split_num_docs_target: Self::default_split_num_docs_target(),
             merge_policy: MergePolicyConfig::default(),
             resources: IndexingResources::default(),
+            parquet_sidecar: None,
+            dedup: None,
+            adaptive_commit: None,
+            merge_node_id: None,
+            ingest_queue: None,
+            hotcache: None,
+            time_slicing: None,
         }
     }
 }
@@ -332,6 +749,75 @@ impl Default for IndexingSettings {
 pub struct SearchSettings {
     #[serde(default)]
     pub default_search_fields: Vec<String>,
+    #[serde(default)]
+    pub warmup_fields: Vec<String>,
+    /// Maximum number of searches on the index a root searcher runs concurrently. Searches
+    /// beyond this limit are rejected.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_searches: Option<usize>,
+    /// Maximum number of bytes of split files a root searcher scans for the index per minute.
+    /// Searches are rejected once the budget of the current minute is exhausted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_scanned_bytes_per_minute: Option<Byte>,
+    /// Fields hidden from the searches issued with a given role.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub field_access_policies: Vec<FieldAccessPolicy>,
+}
+
+/// Denies a role access to some fields of the index. Denied fields are stripped from the hits
+/// and cannot be queried, sorted on, or aggregated by searches issued with the role.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldAccessPolicy {
+    pub role: String,
+    /// Denied field names. Denying an object field, or a JSON field, denies all the fields nested
+    /// under it.
+    pub denied_fields: Vec<String>,
+}
+
+impl FieldAccessPolicy {
+    /// Returns whether the field `field_name`, a possibly nested field path such as
+    /// `user.email`, is denied by the policy.
+    pub fn is_denied(&self, field_name: &str) -> bool {
+        self.denied_fields.iter().any(|denied_field| {
+            field_name == denied_field
+                || field_name
+                    .strip_prefix(denied_field.as_str())
+                    .map(|suffix| suffix.starts_with('.'))
+                    .unwrap_or(false)
+        })
+    }
+}
+
+impl SearchSettings {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.max_concurrent_searches == Some(0) {
+            bail!("Search setting `max_concurrent_searches` must be strictly positive.");
+        }
+        if self
+            .max_scanned_bytes_per_minute
+            .map(|num_bytes| num_bytes.get_bytes() == 0)
+            .unwrap_or(false)
+        {
+            bail!("Search setting `max_scanned_bytes_per_minute` must be strictly positive.");
+        }
+        let mut roles = HashSet::new();
+        for field_access_policy in &self.field_access_policies {
+            if field_access_policy.role.is_empty() {
+                bail!("Field access policy role must not be empty.");
+            }
+            if !roles.insert(field_access_policy.role.as_str()) {
+                bail!(
+                    "Role `{}` has more than one field access policy.",
+                    field_access_policy.role
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Defines on which split attribute the retention policy is applied relatively.
@@ -350,8 +836,17 @@ pub enum RetentionPolicyCutoffReference {
 pub struct RetentionPolicy {
     /// Duration of time for which the splits should be retained, expressed in a human-friendly way
     /// (`1 hour`, `3 days`, `a week`, ...).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "period")]
-    retention_period: String,
+    retention_period: Option<String>,
+    /// Maximum total size of the published splits of the index, expressed in a human-friendly way
+    /// (`500 MB`, `100 GiB`, `1 TB`, ...). When the index exceeds this size, its oldest splits are
+    /// deleted until it fits in.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "max_size")]
+    max_index_size: Option<String>,
     /// Determines on which split attribute the retention policy is applied relatively. See
     /// [`RetentionPolicyCutoffReference`] for more details.
     pub cutoff_reference: RetentionPolicyCutoffReference,
@@ -371,7 +866,23 @@ impl RetentionPolicy {
         evaluation_schedule: String,
     ) -> Self {
         Self {
-            retention_period,
+            retention_period: Some(retention_period),
+            max_index_size: None,
+            cutoff_reference,
+            evaluation_schedule,
+        }
+    }
+
+    /// Creates a size-based retention policy: the oldest splits of the index, as defined by the
+    /// cutoff reference, are deleted as soon as the index exceeds `max_index_size`.
+    pub fn with_max_index_size(
+        max_index_size: String,
+        cutoff_reference: RetentionPolicyCutoffReference,
+        evaluation_schedule: String,
+    ) -> Self {
+        Self {
+            retention_period: None,
+            max_index_size: Some(max_index_size),
             cutoff_reference,
             evaluation_schedule,
         }
@@ -381,13 +892,30 @@ impl RetentionPolicy {
         "hourly".to_string()
     }
 
-    pub fn retention_period(&self) -> anyhow::Result<Duration> {
-        parse_duration(&self.retention_period).with_context(|| {
-            format!(
-                "Failed to parse retention period `{}`.",
-                self.retention_period
-            )
-        })
+    pub fn retention_period(&self) -> anyhow::Result<Option<Duration>> {
+        self.retention_period
+            .as_ref()
+            .map(|retention_period| {
+                parse_duration(retention_period).with_context(|| {
+                    format!("Failed to parse retention period `{}`.", retention_period)
+                })
+            })
+            .transpose()
+    }
+
+    /// Returns the maximum size of the index in bytes, if any.
+    pub fn max_index_num_bytes(&self) -> anyhow::Result<Option<u64>> {
+        self.max_index_size
+            .as_ref()
+            .map(|max_index_size| {
+                Byte::from_str(max_index_size)
+                    .map(|max_index_size| max_index_size.get_bytes() as u64)
+                    .map_err(|error| anyhow::anyhow!("{}", error))
+                    .with_context(|| {
+                        format!("Failed to parse retention max size `{}`.", max_index_size)
+                    })
+            })
+            .transpose()
     }
 
     pub fn evaluation_schedule(&self) -> anyhow::Result<Schedule> {
@@ -421,7 +949,11 @@ impl RetentionPolicy {
     }
 
     fn validate(&self) -> anyhow::Result<()> {
+        if self.retention_period.is_none() && self.max_index_size.is_none() {
+            bail!("Retention policy must define a `period`, a `max_size`, or both.");
+        }
         self.retention_period()?;
+        self.max_index_num_bytes()?;
         self.evaluation_schedule()?;
         Ok(())
     }
@@ -429,7 +961,7 @@ impl RetentionPolicy {
 
 /// Prepends an `@` char at the start of the cron expression if necessary:
 /// `hourly` -> `@hourly`
-fn prepend_at_char(schedule: &str) -> String {
+pub(crate) fn prepend_at_char(schedule: &str) -> String {
     let trimmed_schedule = schedule.trim();
 
     if !trimmed_schedule.is_empty()
@@ -441,6 +973,56 @@ fn prepend_at_char(schedule: &str) -> String {
     trimmed_schedule.to_string()
 }
 
+/// Moves the splits of an index that are no longer searched to a cheaper warm storage, and the
+/// warm splits searched often again back to the index storage.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TieringPolicy {
+    /// URI of the storage receiving the warm splits of the index.
+    pub warm_storage_uri: Uri,
+    /// Period without any search after which a split is moved to the warm storage, expressed in a
+    /// human-friendly way (`1 day`, `2 weeks`, ...).
+    #[serde(rename = "demote_after")]
+    demotion_period: String,
+    /// Number of searches after which a warm split is moved back to the index storage. Warm
+    /// splits are never moved back if not set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub promote_after_num_searches: Option<NonZeroU64>,
+}
+
+impl TieringPolicy {
+    pub fn new(
+        warm_storage_uri: Uri,
+        demotion_period: String,
+        promote_after_num_searches: Option<NonZeroU64>,
+    ) -> Self {
+        Self {
+            warm_storage_uri,
+            demotion_period,
+            promote_after_num_searches,
+        }
+    }
+
+    pub fn demotion_period(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.demotion_period).with_context(|| {
+            format!(
+                "Failed to parse tiering demotion period `{}`.",
+                self.demotion_period
+            )
+        })
+    }
+
+    fn validate(&self, index_uri_opt: Option<&Uri>) -> anyhow::Result<()> {
+        self.demotion_period()?;
+
+        if index_uri_opt == Some(&self.warm_storage_uri) {
+            bail!("The tiering warm storage URI must differ from the index URI.");
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct IndexConfig {
@@ -458,6 +1040,9 @@ pub struct IndexConfig {
     #[serde(rename = "retention")]
     #[serde(default)]
     pub retention_policy: Option<RetentionPolicy>,
+    #[serde(rename = "tiering")]
+    #[serde(default)]
+    pub tiering_policy: Option<TieringPolicy>,
 }
 
 impl IndexConfig {
@@ -524,6 +1109,9 @@ impl IndexConfig {
                 );
             }
         }
+        if let Some(tiering_policy) = &self.tiering_policy {
+            tiering_policy.validate(self.index_uri.as_ref())?;
+        }
         if self.sources.len() > self.sources().len() {
             bail!("Index config contains duplicate sources.")
         }
@@ -533,13 +1121,37 @@ impl IndexConfig {
         // Validation is made by building the doc mapper.
         // Note: this needs a deep refactoring to separate the doc mapping configuration,
         // and doc mapper implementations.
-        build_doc_mapper(
+        let doc_mapper = build_doc_mapper(
             &self.doc_mapping,
             &self.search_settings,
             &self.indexing_settings,
         )?;
 
         self.indexing_settings.merge_policy.validate()?;
+        self.indexing_settings.resources.validate()?;
+        self.search_settings.validate()?;
+
+        if let Some(parquet_sidecar) = &self.indexing_settings.parquet_sidecar {
+            parquet_sidecar.validate(&doc_mapper.schema())?;
+        }
+        if let Some(dedup) = &self.indexing_settings.dedup {
+            dedup.validate(&doc_mapper.schema())?;
+        }
+        if let Some(adaptive_commit) = &self.indexing_settings.adaptive_commit {
+            adaptive_commit.validate()?;
+        }
+        if let Some(ingest_queue) = &self.indexing_settings.ingest_queue {
+            ingest_queue.validate()?;
+        }
+        if let Some(hotcache) = &self.indexing_settings.hotcache {
+            hotcache.validate(&doc_mapper.schema())?;
+        }
+        if let Some(time_slicing) = &self.indexing_settings.time_slicing {
+            time_slicing.validate()?;
+            if self.indexing_settings.timestamp_field.is_none() {
+                bail!("Time slicing requires the index to define a `timestamp_field`.");
+            }
+        }
 
         Ok(())
     }
@@ -562,10 +1174,14 @@ pub fn build_doc_mapper(
     let builder = DefaultDocMapperBuilder {
         store_source: doc_mapping.store_source,
         default_search_fields: search_settings.default_search_fields.clone(),
+        warmup_fields: search_settings.warmup_fields.clone(),
         timestamp_field: indexing_settings.timestamp_field.clone(),
         sort_by,
         field_mappings: doc_mapping.field_mappings.clone(),
         tag_fields: doc_mapping.tag_fields.iter().cloned().collect(),
+        tag_keyword_fields: doc_mapping.tag_keyword_fields,
+        bloom_filter_fields: doc_mapping.bloom_filter_fields.iter().cloned().collect(),
+        dynamic_all: doc_mapping.dynamic_all,
         mode: doc_mapping.mode,
         dynamic_mapping: doc_mapping.dynamic_mapping.clone(),
         partition_key: doc_mapping.partition_key.clone(),
@@ -626,7 +1242,8 @@ mod tests {
                     vec!["tenant_id".to_string()]
                 );
                 let expected_retention_policy = RetentionPolicy {
-                    retention_period: "90 days".to_string(),
+                    retention_period: Some("90 days".to_string()),
+                    max_index_size: None,
                     cutoff_reference: RetentionPolicyCutoffReference::SplitTimestampField,
                     evaluation_schedule: "daily".to_string(),
                 };
@@ -661,6 +1278,8 @@ mod tests {
                     index_config.indexing_settings.resources,
                     IndexingResources {
                         heap_size: Byte::from_bytes(3_000_000_000),
+                        num_doc_processors: NonZeroUsize::new(2),
+                        segment_memory_budget: Some(Byte::from_bytes(50_000_000)),
                         ..Default::default()
                     }
                 );
@@ -671,6 +1290,9 @@ mod tests {
                             "severity_text".to_string(),
                             "body".to_string()
                         ],
+                        warmup_fields: Vec::new(),
+                        max_concurrent_searches: Some(16),
+                        max_scanned_bytes_per_minute: Some(Byte::from_bytes(100_000_000_000)),
                     }
                 );
                 assert_eq!(index_config.sources.len(), 2);
@@ -718,6 +1340,7 @@ mod tests {
                 index_config.search_settings,
                 SearchSettings {
                     default_search_fields: vec!["body".to_string()],
+                    ..Default::default()
                 }
             );
             assert!(index_config.sources.is_empty());
@@ -759,6 +1382,7 @@ mod tests {
                 index_config.search_settings,
                 SearchSettings {
                     default_search_fields: vec!["body".to_string()],
+                    ..Default::default()
                 }
             );
             assert!(index_config.sources.is_empty());
@@ -789,6 +1413,136 @@ mod tests {
                  `merge_factor`."
             );
         }
+        {
+            let mut index_config = index_config.clone();
+            index_config.indexing_settings.parquet_sidecar = Some(ParquetSidecarSettings {
+                fields: vec!["body".to_string()],
+            });
+            index_config.validate().unwrap();
+
+            index_config.indexing_settings.parquet_sidecar = Some(ParquetSidecarSettings {
+                fields: vec!["body".to_string(), "severity".to_string()],
+            });
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(
+                validation_err,
+                "Parquet sidecar field `severity` does not exist in the doc mapping."
+            );
+        }
+        {
+            let mut index_config = index_config.clone();
+            index_config.indexing_settings.hotcache = Some(HotcacheSettings {
+                fields: vec!["body".to_string()],
+                ..Default::default()
+            });
+            index_config.validate().unwrap();
+
+            index_config.indexing_settings.hotcache = Some(HotcacheSettings {
+                fields: vec!["body".to_string(), "body".to_string()],
+                ..Default::default()
+            });
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(
+                validation_err,
+                "Hotcache field `body` is declared more than once."
+            );
+
+            index_config.indexing_settings.hotcache = Some(HotcacheSettings {
+                fields: vec!["severity".to_string()],
+                ..Default::default()
+            });
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(
+                validation_err,
+                "Hotcache field `severity` does not exist in the doc mapping."
+            );
+        }
+        {
+            let mut index_config = index_config.clone();
+            index_config
+                .indexing_settings
+                .resources
+                .segment_memory_budget = Some(Byte::from_bytes(50_000_000));
+            index_config.validate().unwrap();
+
+            index_config
+                .indexing_settings
+                .resources
+                .segment_memory_budget = Some(Byte::from_bytes(1_000_000));
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(
+                validation_err,
+                "Indexing resource `segment_memory_budget` must be between 3MB and 4GB, got \
+                 1000000 bytes."
+            );
+        }
+        {
+            let mut index_config = index_config.clone();
+            index_config.indexing_settings.time_slicing =
+                Some(TimeSlicingSettings::new("1 day".to_string()));
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(
+                validation_err,
+                "Time slicing requires the index to define a `timestamp_field`."
+            );
+        }
+        {
+            let mut index_config = index_config.clone();
+            index_config.search_settings.max_concurrent_searches = Some(0);
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(
+                validation_err,
+                "Search setting `max_concurrent_searches` must be strictly positive."
+            );
+        }
+        {
+            let mut index_config = index_config.clone();
+            let field_access_policy = FieldAccessPolicy {
+                role: "analyst".to_string(),
+                denied_fields: vec!["body".to_string()],
+            };
+            index_config.search_settings.field_access_policies =
+                vec![field_access_policy.clone(), field_access_policy];
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(
+                validation_err,
+                "Role `analyst` has more than one field access policy."
+            );
+        }
+        {
+            let mut index_config = index_config.clone();
+            index_config.indexing_settings.dedup = Some(DedupSettings {
+                doc_id_field: "body".to_string(),
+                cache_capacity: NonZeroUsize::new(10).unwrap(),
+                enforce_on_merge: true,
+            });
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert!(validation_err.contains("must be an indexed `text` field using the `raw`"));
+
+            index_config.indexing_settings.dedup = Some(DedupSettings {
+                doc_id_field: "event_id".to_string(),
+                cache_capacity: NonZeroUsize::new(10).unwrap(),
+                enforce_on_merge: true,
+            });
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(
+                validation_err,
+                "Dedup doc id field `event_id` does not exist in the doc mapping."
+            );
+        }
+        {
+            let mut index_config = index_config.clone();
+            index_config.search_settings.warmup_fields = vec!["body".to_string()];
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(
+                validation_err,
+                "Warmup fields must be fast fields (`body`)."
+            );
+
+            index_config.search_settings.warmup_fields = vec!["severity".to_string()];
+            let validation_err = index_config.validate().unwrap_err().to_string();
+            assert_eq!(validation_err, "Unknown warmup field: `severity`");
+        }
         {
             // Add two sources with same id.
             let mut invalid_index_config = index_config.clone();
@@ -796,12 +1550,22 @@ mod tests {
                 SourceConfig {
                     source_id: "void_1".to_string(),
                     num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
                     enabled: true,
                     source_params: SourceParams::void(),
                 },
                 SourceConfig {
                     source_id: "void_1".to_string(),
                     num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
                     enabled: true,
                     source_params: SourceParams::void(),
                 },
@@ -819,6 +1583,11 @@ mod tests {
             invalid_index_config.sources = vec![SourceConfig {
                 source_id: "file_params_1".to_string(),
                 num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
                 enabled: true,
                 source_params: SourceParams::stdin(),
             }];
@@ -854,10 +1623,169 @@ mod tests {
         assert_eq!(minimal_config.doc_mapping.mode, ModeType::Lenient);
     }
 
+    #[test]
+    fn test_dedup_settings_deserialization() {
+        let dedup_settings_yaml = r#"
+            doc_id_field: event_id
+        "#;
+        let dedup_settings = serde_yaml::from_str::<DedupSettings>(dedup_settings_yaml).unwrap();
+        assert_eq!(
+            dedup_settings,
+            DedupSettings {
+                doc_id_field: "event_id".to_string(),
+                cache_capacity: NonZeroUsize::new(100_000).unwrap(),
+                enforce_on_merge: false,
+            }
+        );
+        let dedup_settings_yaml = r#"
+            doc_id_field: event_id
+            cache_capacity: 0
+        "#;
+        serde_yaml::from_str::<DedupSettings>(dedup_settings_yaml).unwrap_err();
+    }
+
+    #[test]
+    fn test_adaptive_commit_settings_deserialization() {
+        let adaptive_commit_settings_yaml = r#"
+            max_commit_timeout_secs: 300
+        "#;
+        let adaptive_commit_settings =
+            serde_yaml::from_str::<AdaptiveCommitSettings>(adaptive_commit_settings_yaml).unwrap();
+        assert_eq!(
+            adaptive_commit_settings,
+            AdaptiveCommitSettings {
+                min_commit_timeout_secs: 10,
+                max_commit_timeout_secs: 300,
+                commit_num_docs_target: 1_000_000,
+            }
+        );
+        adaptive_commit_settings.validate().unwrap();
+
+        let adaptive_commit_settings = AdaptiveCommitSettings {
+            min_commit_timeout_secs: 60,
+            max_commit_timeout_secs: 30,
+            ..Default::default()
+        };
+        assert_eq!(
+            adaptive_commit_settings.validate().unwrap_err().to_string(),
+            "Adaptive commit `min_commit_timeout_secs` (60) must be lower than or equal to \
+             `max_commit_timeout_secs` (30)."
+        );
+        let adaptive_commit_settings = AdaptiveCommitSettings {
+            min_commit_timeout_secs: 0,
+            ..Default::default()
+        };
+        adaptive_commit_settings.validate().unwrap_err();
+    }
+
+    #[test]
+    fn test_ingest_queue_settings_deserialization() {
+        let ingest_queue_settings_yaml = r#"
+            retention_period: 6 hours
+        "#;
+        let ingest_queue_settings =
+            serde_yaml::from_str::<IngestQueueSettings>(ingest_queue_settings_yaml).unwrap();
+        assert_eq!(
+            ingest_queue_settings,
+            IngestQueueSettings::new("6 hours".to_string())
+        );
+        assert_eq!(
+            ingest_queue_settings.retention_period().unwrap(),
+            Duration::from_secs(6 * 3600)
+        );
+        ingest_queue_settings.validate().unwrap();
+
+        let ingest_queue_settings = IngestQueueSettings::new("a while".to_string());
+        assert_eq!(
+            ingest_queue_settings.validate().unwrap_err().to_string(),
+            "Failed to parse ingest queue retention period `a while`."
+        );
+    }
+
+    #[test]
+    fn test_time_slicing_settings_deserialization() {
+        let time_slicing_settings_yaml = r#"
+            slice_duration: 1 day
+        "#;
+        let time_slicing_settings =
+            serde_yaml::from_str::<TimeSlicingSettings>(time_slicing_settings_yaml).unwrap();
+        assert_eq!(
+            time_slicing_settings,
+            TimeSlicingSettings::new("1 day".to_string())
+        );
+        assert_eq!(
+            time_slicing_settings.slice_duration().unwrap(),
+            Duration::from_secs(24 * 3600)
+        );
+        time_slicing_settings.validate().unwrap();
+
+        let time_slicing_settings = TimeSlicingSettings::new("a while".to_string());
+        assert_eq!(
+            time_slicing_settings.validate().unwrap_err().to_string(),
+            "Failed to parse time slicing slice duration `a while`."
+        );
+        let time_slicing_settings = TimeSlicingSettings::new("500ms".to_string());
+        assert_eq!(
+            time_slicing_settings.validate().unwrap_err().to_string(),
+            "Time slicing slice duration `500ms` must be at least one second."
+        );
+    }
+
+    #[test]
+    fn test_docstore_compressor_deserialization() {
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>("docstore_compressor: lz4").unwrap();
+        assert_eq!(
+            indexing_settings.docstore_compressor,
+            Some(DocstoreCompressor::Lz4)
+        );
+        assert_eq!(
+            indexing_settings.docstore_compressor(),
+            DocstoreCompressor::Lz4
+        );
+        let indexing_settings =
+            serde_yaml::from_str::<IndexingSettings>("docstore_compressor: none").unwrap();
+        assert_eq!(
+            indexing_settings.docstore_compressor(),
+            DocstoreCompressor::None
+        );
+        let indexing_settings = serde_yaml::from_str::<IndexingSettings>("{}").unwrap();
+        assert_eq!(indexing_settings.docstore_compressor, None);
+        assert_eq!(
+            indexing_settings.docstore_compressor(),
+            DocstoreCompressor::Zstd
+        );
+        // The compressor is left out of the serialized settings when not set.
+        let indexing_settings_json = serde_json::to_string(&indexing_settings).unwrap();
+        assert!(!indexing_settings_json.contains("docstore_compressor"));
+
+        serde_yaml::from_str::<IndexingSettings>("docstore_compressor: brotli").unwrap_err();
+    }
+
+    #[test]
+    fn test_hotcache_settings_deserialization() {
+        let hotcache_settings_yaml = r#"
+            fields: [title, body]
+            max_slice_size: 1MB
+        "#;
+        let hotcache_settings =
+            serde_yaml::from_str::<HotcacheSettings>(hotcache_settings_yaml).unwrap();
+        assert_eq!(
+            hotcache_settings,
+            HotcacheSettings {
+                fields: vec!["title".to_string(), "body".to_string()],
+                max_slice_size: Byte::from_bytes(1_000_000),
+            }
+        );
+        let hotcache_settings = serde_yaml::from_str::<HotcacheSettings>("{}").unwrap();
+        assert_eq!(hotcache_settings, HotcacheSettings::default());
+    }
+
     #[test]
     fn test_retention_policy_serialization() {
         let retention_policy = RetentionPolicy {
-            retention_period: "90 days".to_string(),
+            retention_period: Some("90 days".to_string()),
+            max_index_size: None,
             cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
             evaluation_schedule: "hourly".to_string(),
         };
@@ -880,7 +1808,8 @@ mod tests {
                 serde_yaml::from_str::<RetentionPolicy>(retention_policy_yaml).unwrap();
 
             let expected_retention_policy = RetentionPolicy {
-                retention_period: "90 days".to_string(),
+                retention_period: Some("90 days".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: "hourly".to_string(),
             };
@@ -896,7 +1825,8 @@ mod tests {
                 serde_yaml::from_str::<RetentionPolicy>(retention_policy_yaml).unwrap();
 
             let expected_retention_policy = RetentionPolicy {
-                retention_period: "90 days".to_string(),
+                retention_period: Some("90 days".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: "daily".to_string(),
             };
@@ -908,17 +1838,19 @@ mod tests {
     fn test_parse_retention_policy_period() {
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: "hourly".to_string(),
             };
             assert_eq!(
                 retention_policy.retention_period().unwrap(),
-                Duration::from_secs(3600)
+                Some(Duration::from_secs(3600))
             );
             {
                 let retention_policy = RetentionPolicy {
-                    retention_period: "foo".to_string(),
+                    retention_period: Some("foo".to_string()),
+                    max_index_size: None,
                     cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                     evaluation_schedule: "hourly".to_string(),
                 };
@@ -943,7 +1875,8 @@ mod tests {
         let hourly_schedule = Schedule::from_str("@hourly").unwrap();
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: "@hourly".to_string(),
             };
@@ -954,7 +1887,8 @@ mod tests {
         }
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: "hourly".to_string(),
             };
@@ -965,7 +1899,8 @@ mod tests {
         }
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: "0 * * * * *".to_string(),
             };
@@ -979,7 +1914,8 @@ mod tests {
     fn test_retention_policy_validate() {
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: "hourly".to_string(),
             };
@@ -987,7 +1923,8 @@ mod tests {
         }
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "foo".to_string(),
+                retention_period: Some("foo".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: "hourly".to_string(),
             };
@@ -995,12 +1932,79 @@ mod tests {
         }
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: "foo".to_string(),
             };
             retention_policy.validate().unwrap_err();
         }
+        {
+            let retention_policy = RetentionPolicy {
+                retention_period: None,
+                max_index_size: Some("10 GB".to_string()),
+                cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
+                evaluation_schedule: "hourly".to_string(),
+            };
+            retention_policy.validate().unwrap();
+        }
+        {
+            let retention_policy = RetentionPolicy {
+                retention_period: None,
+                max_index_size: Some("foo".to_string()),
+                cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
+                evaluation_schedule: "hourly".to_string(),
+            };
+            retention_policy.validate().unwrap_err();
+        }
+        {
+            let retention_policy = RetentionPolicy {
+                retention_period: None,
+                max_index_size: None,
+                cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
+                evaluation_schedule: "hourly".to_string(),
+            };
+            retention_policy.validate().unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_parse_retention_policy_max_index_size() {
+        {
+            let retention_policy_yaml = r#"
+            max_size: 10 GB
+            cutoff_reference: split_timestamp_field
+        "#;
+            let retention_policy =
+                serde_yaml::from_str::<RetentionPolicy>(retention_policy_yaml).unwrap();
+            assert_eq!(retention_policy.retention_period().unwrap(), None);
+            assert_eq!(
+                retention_policy.max_index_num_bytes().unwrap(),
+                Some(10_000_000_000)
+            );
+        }
+        {
+            let retention_policy = RetentionPolicy::with_max_index_size(
+                "1 KiB".to_string(),
+                RetentionPolicyCutoffReference::PublishTimestamp,
+                "hourly".to_string(),
+            );
+            assert_eq!(retention_policy.max_index_num_bytes().unwrap(), Some(1024));
+        }
+        {
+            let retention_policy = RetentionPolicy::with_max_index_size(
+                "foo".to_string(),
+                RetentionPolicyCutoffReference::PublishTimestamp,
+                "hourly".to_string(),
+            );
+            assert_eq!(
+                retention_policy
+                    .max_index_num_bytes()
+                    .unwrap_err()
+                    .to_string(),
+                "Failed to parse retention max size `foo`."
+            );
+        }
     }
 
     #[test]
@@ -1008,7 +2012,8 @@ mod tests {
         let schedule_test_helper_fn = |schedule_str: &str| {
             let hourly_schedule = Schedule::from_str(&prepend_at_char(schedule_str)).unwrap();
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_index_size: None,
                 cutoff_reference: RetentionPolicyCutoffReference::PublishTimestamp,
                 evaluation_schedule: schedule_str.to_string(),
             };
@@ -1030,4 +2035,78 @@ mod tests {
         schedule_test_helper_fn("monthly");
         schedule_test_helper_fn("* * * ? * ?");
     }
+
+    #[test]
+    fn test_tiering_policy_deserialization() {
+        {
+            let tiering_policy_yaml = r#"
+                warm_storage_uri: s3://warm-bucket/indexes/my-index
+                demote_after: 7 days
+            "#;
+            let tiering_policy =
+                serde_yaml::from_str::<TieringPolicy>(tiering_policy_yaml).unwrap();
+            let expected_tiering_policy = TieringPolicy::new(
+                Uri::from_well_formed("s3://warm-bucket/indexes/my-index".to_string()),
+                "7 days".to_string(),
+                None,
+            );
+            assert_eq!(tiering_policy, expected_tiering_policy);
+            assert_eq!(
+                tiering_policy.demotion_period().unwrap(),
+                Duration::from_secs(7 * 24 * 3600)
+            );
+        }
+        {
+            let tiering_policy_yaml = r#"
+                warm_storage_uri: s3://warm-bucket/indexes/my-index
+                demote_after: 1 day
+                promote_after_num_searches: 100
+            "#;
+            let tiering_policy =
+                serde_yaml::from_str::<TieringPolicy>(tiering_policy_yaml).unwrap();
+            assert_eq!(
+                tiering_policy.promote_after_num_searches,
+                Some(NonZeroU64::new(100).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn test_tiering_policy_validate() {
+        let warm_storage_uri = Uri::from_well_formed("s3://warm-bucket/my-index".to_string());
+        let tiering_policy =
+            TieringPolicy::new(warm_storage_uri.clone(), "2 weeks".to_string(), None);
+        tiering_policy.validate(None).unwrap();
+        tiering_policy
+            .validate(Some(&Uri::from_well_formed(
+                "s3://hot-bucket/my-index".to_string(),
+            )))
+            .unwrap();
+        tiering_policy
+            .validate(Some(&warm_storage_uri))
+            .unwrap_err();
+
+        let tiering_policy = TieringPolicy::new(warm_storage_uri, "foo".to_string(), None);
+        tiering_policy.validate(None).unwrap_err();
+    }
+
+    #[test]
+    fn test_field_access_policy_serde() {
+        let search_settings_yaml = r#"
+            default_search_fields: [body]
+            field_access_policies:
+              - role: analyst
+                denied_fields: [user.email, attributes]
+        "#;
+        let search_settings = serde_yaml::from_str::<SearchSettings>(search_settings_yaml).unwrap();
+        let field_access_policy = &search_settings.field_access_policies[0];
+        assert_eq!(field_access_policy.role, "analyst");
+
+        assert!(field_access_policy.is_denied("user.email"));
+        assert!(field_access_policy.is_denied("attributes"));
+        assert!(field_access_policy.is_denied("attributes.client.ip"));
+        assert!(!field_access_policy.is_denied("user"));
+        assert!(!field_access_policy.is_denied("user.emails"));
+        assert!(!field_access_policy.is_denied("body"));
+    }
 }
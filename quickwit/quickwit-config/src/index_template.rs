@@ -0,0 +1,138 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::bail;
+use quickwit_common::uri::Uri;
+use serde::{Deserialize, Serialize};
+
+use crate::validate_identifier;
+
+/// Template from which the ingest API creates the indexes missing on first ingest, when
+/// `auto_create_indexes` is enabled.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IndexTemplateConfig {
+    pub template_id: String,
+    /// Patterns of the IDs of the indexes created from the template, in which `*` matches any
+    /// sequence of characters, for instance `logs-*`.
+    pub index_id_patterns: Vec<String>,
+    /// URI of the index config file of the template: doc mapping, settings, retention policy, and
+    /// sources. Its `index_id` and `index_uri` are replaced by the ID of the created index and
+    /// the default URI derived from it.
+    pub index_config_uri: Uri,
+}
+
+impl IndexTemplateConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        validate_identifier("Index template ID", &self.template_id)?;
+        if self.index_id_patterns.is_empty() {
+            bail!(
+                "Index template `{}` must have at least one index ID pattern.",
+                self.template_id
+            );
+        }
+        for index_id_pattern in &self.index_id_patterns {
+            let is_valid_pattern = !index_id_pattern.is_empty()
+                && index_id_pattern
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '*'));
+            if !is_valid_pattern {
+                bail!(
+                    "Index template `{}` has an invalid index ID pattern `{}`. Patterns may only \
+                     contain alphanumeric characters, `-`, `_`, and the wildcard `*`.",
+                    self.template_id,
+                    index_id_pattern
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the ID of an index matches one of the patterns of the template.
+    pub fn matches(&self, index_id: &str) -> bool {
+        self.index_id_patterns
+            .iter()
+            .any(|index_id_pattern| matches_pattern(index_id_pattern, index_id))
+    }
+}
+
+/// Returns whether `value` matches `pattern`, in which `*` matches any sequence of characters.
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let prefix = parts.next().unwrap_or_default();
+    let mut remaining = match value.strip_prefix(prefix) {
+        Some(remaining) => remaining,
+        None => return false,
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let suffix = match parts.pop() {
+        Some(suffix) => suffix,
+        // No wildcard: the pattern must match the whole value.
+        None => return remaining.is_empty(),
+    };
+    for part in parts {
+        match remaining.find(part) {
+            Some(position) => remaining = &remaining[position + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.ends_with(suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("logs", "logs"));
+        assert!(!matches_pattern("logs", "logs-app"));
+        assert!(matches_pattern("logs-*", "logs-app"));
+        assert!(matches_pattern("logs-*", "logs-"));
+        assert!(!matches_pattern("logs-*", "metrics-app"));
+        assert!(matches_pattern("*-logs", "app-logs"));
+        assert!(!matches_pattern("*-logs", "app-logs-2022"));
+        assert!(matches_pattern("logs-*-*", "logs-app-2022"));
+        assert!(matches_pattern("logs-*-2022", "logs-app-2022"));
+        assert!(!matches_pattern("logs-*-2022", "logs-2022"));
+        assert!(matches_pattern("*", "logs"));
+    }
+
+    #[test]
+    fn test_index_template_config_validate() {
+        let index_template_config = IndexTemplateConfig {
+            template_id: "logs-template".to_string(),
+            index_id_patterns: vec!["logs-*".to_string()],
+            index_config_uri: Uri::from_well_formed("s3://config/logs.yaml".to_string()),
+        };
+        index_template_config.validate().unwrap();
+        assert!(index_template_config.matches("logs-app"));
+        assert!(!index_template_config.matches("app-logs"));
+        {
+            let mut index_template_config = index_template_config.clone();
+            index_template_config.index_id_patterns.clear();
+            index_template_config.validate().unwrap_err();
+        }
+        {
+            let mut index_template_config = index_template_config;
+            index_template_config.index_id_patterns = vec!["logs/*".to_string()];
+            index_template_config.validate().unwrap_err();
+        }
+    }
+}
@@ -21,24 +21,42 @@ use anyhow::bail;
 use once_cell::sync::OnceCell;
 use regex::Regex;
 
+mod alert_config;
 mod config;
 mod config_value;
 mod index_config;
+mod index_template;
+mod maintenance_window;
 pub mod merge_policy_config;
 mod qw_env_vars;
 pub mod service;
 mod source_config;
+mod stored_query;
 mod templating;
 
-pub use config::{IndexerConfig, QuickwitConfig, SearcherConfig, DEFAULT_QW_CONFIG_PATH};
+pub use alert_config::{AlertChannel, AlertCondition, AlertConfig, ThresholdOperator};
+pub use config::{
+    IndexerConfig, JanitorConfig, QuickwitConfig, SearcherConfig, SplitCacheConfig,
+    DEFAULT_QW_CONFIG_PATH,
+};
 pub use index_config::{
-    build_doc_mapper, DocMapping, IndexConfig, IndexingResources, IndexingSettings,
-    IndexingSettingsLegacy, RetentionPolicy, RetentionPolicyCutoffReference, SearchSettings,
+    build_doc_mapper, AdaptiveCommitSettings, DedupSettings, DocMapping, DocstoreCompressor,
+    FieldAccessPolicy, HotcacheSettings, IndexConfig, IndexingResources, IndexingSettings,
+    IndexingSettingsLegacy, IngestQueueSettings, ParquetSidecarSettings, RetentionPolicy,
+    RetentionPolicyCutoffReference, SearchSettings, TieringPolicy, TimeSlicingSettings,
 };
+pub use index_template::IndexTemplateConfig;
+pub use maintenance_window::{MaintenanceWindow, MaintenanceWindows};
 pub use source_config::{
-    FileSourceParams, KafkaSourceParams, KinesisSourceParams, RegionOrEndpoint, SourceConfig,
-    SourceParams, VecSourceParams, VoidSourceParams, CLI_INGEST_SOURCE_ID, INGEST_API_SOURCE_ID,
+    BackfillSourceParams, CsvOptions, FileCleanupPolicy, FileSourceParams,
+    GcsNotificationsSourceParams, KafkaClientFeatures, KafkaFlavor, KafkaPartitionAssignment,
+    KafkaSourceParams, KinesisSourceParams, MqttSourceParams, PostgresCdcSourceParams,
+    PostgresDecodingPlugin, PubSubSourceParams, PulsarSourceParams, PulsarSubscriptionType,
+    PushSourceParams, RegionOrEndpoint, SourceConfig, SourceInputFormat, SourceParams,
+    SourceRateLimit, SqsSourceParams, SyslogProtocol, SyslogSourceParams, TransformOperation,
+    VecSourceParams, VoidSourceParams, CLI_INGEST_SOURCE_ID, INGEST_API_SOURCE_ID,
 };
+pub use stored_query::{StoredQuery, StoredQueryParam};
 
 fn is_false(val: &bool) -> bool {
     !*val
@@ -0,0 +1,192 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use humantime::parse_duration;
+use serde::{Deserialize, Serialize};
+
+use crate::index_config::prepend_at_char;
+
+/// A recurring time window during which background maintenance tasks (merges, garbage
+/// collection) are allowed to run at full speed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaintenanceWindow {
+    /// Defines when the window opens, expressed in a human-friendly way (`hourly`, `daily`, ...)
+    /// or as a cron expression (`0 0 20 * * *`). Cron expressions are evaluated in UTC.
+    pub start: String,
+    /// Defines how long the window remains open, expressed in a human-friendly way (`1 hour`,
+    /// `10h 30min`, ...).
+    pub duration: String,
+}
+
+impl MaintenanceWindow {
+    pub fn start_schedule(&self) -> anyhow::Result<Schedule> {
+        Schedule::from_str(&prepend_at_char(&self.start))
+            .with_context(|| format!("Failed to parse maintenance window start `{}`.", self.start))
+    }
+
+    pub fn duration(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.duration).with_context(|| {
+            format!(
+                "Failed to parse maintenance window duration `{}`.",
+                self.duration
+            )
+        })
+    }
+
+    /// Returns whether the window is open at `date_time`, i.e. whether the window opened less than
+    /// `duration` before `date_time`.
+    pub fn is_open_at(&self, date_time: DateTime<Utc>) -> anyhow::Result<bool> {
+        let schedule = self.start_schedule()?;
+        let duration = chrono::Duration::from_std(self.duration()?)?;
+        let is_open = schedule
+            .after(&(date_time - duration))
+            .next()
+            .map(|window_start| window_start <= date_time)
+            .unwrap_or(false);
+        Ok(is_open)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        self.start_schedule()?;
+        self.duration()?;
+        Ok(())
+    }
+}
+
+/// A set of maintenance windows. An empty set does not restrict the execution of maintenance
+/// tasks.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MaintenanceWindows(Vec<MaintenanceWindow>);
+
+impl MaintenanceWindows {
+    pub fn new(windows: Vec<MaintenanceWindow>) -> Self {
+        Self(windows)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn windows(&self) -> &[MaintenanceWindow] {
+        &self.0
+    }
+
+    /// Returns whether maintenance tasks are allowed to run at `date_time`, i.e. whether no window
+    /// is configured or one of the windows is open. Windows that fail to parse are ignored: they
+    /// are rejected when the config is validated.
+    pub fn is_open_at(&self, date_time: DateTime<Utc>) -> bool {
+        self.is_empty()
+            || self
+                .0
+                .iter()
+                .any(|window| window.is_open_at(date_time).unwrap_or(false))
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open_at(Utc::now())
+    }
+
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        for window in &self.0 {
+            window.validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn make_window(start: &str, duration: &str) -> MaintenanceWindow {
+        MaintenanceWindow {
+            start: start.to_string(),
+            duration: duration.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_maintenance_window_deserialization() {
+        let windows_yaml = r#"
+            - start: 0 0 20 * * *
+              duration: 10 hours
+            - start: weekly
+              duration: 1 day
+        "#;
+        let windows = serde_yaml::from_str::<MaintenanceWindows>(windows_yaml).unwrap();
+        assert_eq!(
+            windows,
+            MaintenanceWindows::new(vec![
+                make_window("0 0 20 * * *", "10 hours"),
+                make_window("weekly", "1 day"),
+            ])
+        );
+        windows.validate().unwrap();
+    }
+
+    #[test]
+    fn test_maintenance_window_validate() {
+        make_window("daily", "1 hour").validate().unwrap();
+        make_window("foo", "1 hour").validate().unwrap_err();
+        make_window("daily", "foo").validate().unwrap_err();
+    }
+
+    #[test]
+    fn test_maintenance_window_is_open_at() {
+        // Opens every day at 20:00 UTC for 10 hours.
+        let window = make_window("0 0 20 * * *", "10 hours");
+        let is_open_at = |hour: u32, minute: u32| {
+            window
+                .is_open_at(Utc.ymd(2022, 10, 12).and_hms(hour, minute, 0))
+                .unwrap()
+        };
+        assert!(is_open_at(20, 0));
+        assert!(is_open_at(23, 59));
+        assert!(is_open_at(0, 0));
+        assert!(is_open_at(5, 59));
+        assert!(!is_open_at(6, 0));
+        assert!(!is_open_at(12, 0));
+        assert!(!is_open_at(19, 59));
+    }
+
+    #[test]
+    fn test_maintenance_windows_is_open_at() {
+        let date_time = Utc.ymd(2022, 10, 12).and_hms(12, 0, 0);
+        assert!(MaintenanceWindows::default().is_open_at(date_time));
+
+        let windows = MaintenanceWindows::new(vec![
+            make_window("0 0 20 * * *", "10 hours"),
+            make_window("0 0 11 * * *", "2 hours"),
+        ]);
+        assert!(windows.is_open_at(date_time));
+
+        let windows = MaintenanceWindows::new(vec![make_window("0 0 20 * * *", "10 hours")]);
+        assert!(!windows.is_open_at(date_time));
+    }
+}
@@ -59,7 +59,8 @@ qw_env_vars!(
     QW_PEER_SEEDS,
     QW_DATA_DIR,
     QW_METASTORE_URI,
-    QW_DEFAULT_INDEX_ROOT_URI
+    QW_DEFAULT_INDEX_ROOT_URI,
+    QW_ADMIN_TOKEN
 );
 
 #[cfg(test)]
@@ -32,6 +32,7 @@ pub enum QuickwitService {
     Searcher,
     Janitor,
     Metastore,
+    ControlPlane,
 }
 
 impl QuickwitService {
@@ -41,6 +42,7 @@ impl QuickwitService {
             QuickwitService::Searcher => "searcher",
             QuickwitService::Janitor => "janitor",
             QuickwitService::Metastore => "metastore",
+            QuickwitService::ControlPlane => "control_plane",
         }
     }
 
@@ -64,6 +66,7 @@ impl FromStr for QuickwitService {
             "searcher" => Ok(QuickwitService::Searcher),
             "janitor" => Ok(QuickwitService::Janitor),
             "metastore" => Ok(QuickwitService::Metastore),
+            "control_plane" => Ok(QuickwitService::ControlPlane),
             _ => {
                 bail!(
                     "Failed to parse service `{service_str}`. Supported services are: `{}`.",
@@ -0,0 +1,298 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{bail, Context};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{validate_identifier, AlertConfig};
+
+// Matches `{{param}}`, ignoring whitespaces in curly braces.
+static STORED_QUERY_PARAM_CAPTURE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("Failed to compile regular expression. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.")
+});
+
+// Parameter values are substituted verbatim into the query text, so they are restricted to
+// characters that cannot alter the structure of the query. A leading `-` would negate the clause
+// the value is substituted into.
+static STORED_QUERY_PARAM_VALUE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[A-Za-z0-9_.@/][A-Za-z0-9_.@/-]*$").expect("Failed to compile regular expression. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.")
+});
+
+/// A parameter of a stored query.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StoredQueryParam {
+    pub name: String,
+    /// Value used when the parameter is not provided. Parameters without a default value are
+    /// required.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// A named, parameterized search query persisted in the metastore along with its index.
+/// Parameters are referenced in the query text as `{{param}}`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StoredQuery {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<StoredQueryParam>,
+    /// Fields to search on if no field name is specified in the query. Defaults to the default
+    /// search fields of the index.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search_fields: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_hits: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_by_field: Option<String>,
+    /// The aggregation request, serialized as a JSON string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregation_request: Option<String>,
+    /// Evaluates the stored query periodically and notifies channels when the number of hits
+    /// meets a condition.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert: Option<AlertConfig>,
+}
+
+impl StoredQuery {
+    /// Checks that the name of the stored query is a valid identifier, that every parameter
+    /// referenced in the query is declared exactly once, and that the alert, if any, is valid.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        validate_identifier("Stored query", &self.name)?;
+        let mut param_names = BTreeSet::new();
+        for param in &self.params {
+            if !param_names.insert(param.name.as_str()) {
+                bail!(
+                    "Parameter `{}` of stored query `{}` is declared more than once.",
+                    param.name,
+                    self.name
+                );
+            }
+            if let Some(default_value) = &param.default {
+                validate_param_value(&param.name, default_value)?;
+            }
+        }
+        for captures in STORED_QUERY_PARAM_CAPTURE.captures_iter(&self.query) {
+            let param_name = &captures[1];
+            if !param_names.contains(param_name) {
+                bail!(
+                    "Parameter `{}` referenced in stored query `{}` is not declared.",
+                    param_name,
+                    self.name
+                );
+            }
+        }
+        if let Some(aggregation_request) = &self.aggregation_request {
+            if let Err(error) = serde_json::from_str::<serde_json::Value>(aggregation_request) {
+                bail!(
+                    "Aggregation request of stored query `{}` is not valid JSON: {}.",
+                    self.name,
+                    error
+                );
+            }
+        }
+        if let Some(alert) = &self.alert {
+            alert
+                .validate()
+                .with_context(|| format!("Alert of stored query `{}` is invalid.", self.name))?;
+            // Alerts are evaluated with the default values of the parameters.
+            if let Some(param) = self.params.iter().find(|param| param.default.is_none()) {
+                bail!(
+                    "Parameter `{}` of stored query `{}` must have a default value for the stored \
+                     query to be evaluated as an alert.",
+                    param.name,
+                    self.name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the query text, substituting the parameters with the values provided or, if
+    /// missing, with their default value.
+    pub fn render(&self, param_values: &HashMap<String, String>) -> anyhow::Result<String> {
+        for param_name in param_values.keys() {
+            if !self.params.iter().any(|param| &param.name == param_name) {
+                bail!(
+                    "Stored query `{}` has no parameter `{}`.",
+                    self.name,
+                    param_name
+                );
+            }
+        }
+        let mut rendered_values = HashMap::with_capacity(self.params.len());
+        for param in &self.params {
+            let value = match param_values.get(&param.name).or(param.default.as_ref()) {
+                Some(value) => value,
+                None => bail!(
+                    "Parameter `{}` of stored query `{}` is required.",
+                    param.name,
+                    self.name
+                ),
+            };
+            validate_param_value(&param.name, value)?;
+            rendered_values.insert(param.name.as_str(), value.as_str());
+        }
+        let rendered =
+            STORED_QUERY_PARAM_CAPTURE.replace_all(&self.query, |captures: &regex::Captures| {
+                // Parameters are validated to be declared, hence to have a value.
+                rendered_values
+                    .get(&captures[1])
+                    .copied()
+                    .unwrap_or_default()
+                    .to_string()
+            });
+        Ok(rendered.into_owned())
+    }
+}
+
+fn validate_param_value(param_name: &str, value: &str) -> anyhow::Result<()> {
+    if !STORED_QUERY_PARAM_VALUE.is_match(value) {
+        bail!(
+            "Value `{}` of parameter `{}` is invalid. Parameter values must match the following \
+             regular expression: `^[A-Za-z0-9_.@/][A-Za-z0-9_.@/-]*$`.",
+            value,
+            param_name
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlertChannel, AlertCondition, ThresholdOperator};
+
+    fn stored_query_for_test() -> StoredQuery {
+        StoredQuery {
+            name: "errors-by-service".to_string(),
+            description: None,
+            query: "service:{{service}} AND level:{{ level }}".to_string(),
+            params: vec![
+                StoredQueryParam {
+                    name: "service".to_string(),
+                    default: None,
+                },
+                StoredQueryParam {
+                    name: "level".to_string(),
+                    default: Some("error".to_string()),
+                },
+            ],
+            search_fields: Vec::new(),
+            max_hits: Some(10),
+            sort_by_field: None,
+            aggregation_request: None,
+            alert: None,
+        }
+    }
+
+    #[test]
+    fn test_stored_query_serde() {
+        let stored_query_json = r#"{
+            "name": "errors-by-service",
+            "query": "service:{{service}} AND level:{{ level }}",
+            "params": [{"name": "service"}, {"name": "level", "default": "error"}],
+            "max_hits": 10
+        }"#;
+        let stored_query: StoredQuery = serde_json::from_str(stored_query_json).unwrap();
+        assert_eq!(stored_query, stored_query_for_test());
+        stored_query.validate().unwrap();
+    }
+
+    #[test]
+    fn test_stored_query_validate() {
+        let mut stored_query = stored_query_for_test();
+        stored_query.params.pop();
+        let error = stored_query.validate().unwrap_err();
+        assert!(error.to_string().contains("`level`"));
+
+        let mut stored_query = stored_query_for_test();
+        stored_query.params.push(StoredQueryParam {
+            name: "service".to_string(),
+            default: None,
+        });
+        let error = stored_query.validate().unwrap_err();
+        assert!(error.to_string().contains("more than once"));
+
+        let mut stored_query = stored_query_for_test();
+        stored_query.name = "_".to_string();
+        stored_query.validate().unwrap_err();
+
+        let mut stored_query = stored_query_for_test();
+        stored_query.aggregation_request = Some("{".to_string());
+        stored_query.validate().unwrap_err();
+
+        let mut stored_query = stored_query_for_test();
+        stored_query.alert = Some(AlertConfig {
+            evaluation_interval: "1m".to_string(),
+            condition: AlertCondition {
+                operator: ThresholdOperator::GreaterThan,
+                threshold: 0,
+            },
+            channels: vec![AlertChannel::Webhook {
+                url: "https://hooks.example.com/alerts".to_string(),
+            }],
+            enabled: true,
+            role: None,
+        });
+        let error = stored_query.validate().unwrap_err();
+        assert!(error.to_string().contains("must have a default value"));
+        stored_query.params[0].default = Some("payments".to_string());
+        stored_query.validate().unwrap();
+    }
+
+    #[test]
+    fn test_stored_query_render() {
+        let stored_query = stored_query_for_test();
+        let mut param_values = HashMap::new();
+        stored_query.render(&param_values).unwrap_err();
+
+        param_values.insert("service".to_string(), "payments".to_string());
+        assert_eq!(
+            stored_query.render(&param_values).unwrap(),
+            "service:payments AND level:error"
+        );
+        param_values.insert("level".to_string(), "warn".to_string());
+        assert_eq!(
+            stored_query.render(&param_values).unwrap(),
+            "service:payments AND level:warn"
+        );
+        param_values.insert("level".to_string(), "warn OR *".to_string());
+        stored_query.render(&param_values).unwrap_err();
+        param_values.insert("level".to_string(), "-error".to_string());
+        stored_query.render(&param_values).unwrap_err();
+        param_values.insert("level".to_string(), "non-error".to_string());
+        assert_eq!(
+            stored_query.render(&param_values).unwrap(),
+            "service:payments AND level:non-error"
+        );
+
+        param_values.remove("level");
+        param_values.insert("tenant".to_string(), "acme".to_string());
+        let error = stored_query.render(&param_values).unwrap_err();
+        assert!(error.to_string().contains("no parameter `tenant`"));
+    }
+}
@@ -0,0 +1,398 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use quickwit_common::split_file;
+use quickwit_common::uri::Uri;
+use quickwit_metastore::{
+    IndexMetadata, Metastore, Split, SplitMetadata, SplitState, SplitStorageTier,
+};
+use quickwit_storage::{FilePayload, Storage, StorageErrorKind, StorageUriResolver};
+use serde::{Deserialize, Serialize};
+use tantivy::time::OffsetDateTime;
+use tracing::info;
+
+/// Name of the file holding the backup manifest at the root of the backup URI.
+pub const BACKUP_MANIFEST_FILE_NAME: &str = "backup-manifest.json";
+
+/// A backup manifest holds everything needed to recreate an index in another metastore: the
+/// index metadata (including the source checkpoints) and the metadata of its published splits.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Metadata of the index at the time of the backup.
+    pub index_metadata: IndexMetadata,
+    /// Published splits at the time of the backup.
+    pub splits: Vec<Split>,
+    /// Whether the split files were copied alongside the manifest. When `false`, the restored
+    /// index keeps pointing to the original index URI.
+    pub includes_split_files: bool,
+    /// Time at which the backup was started.
+    pub create_timestamp: i64,
+}
+
+/// Progress of a backup or restore operation, reported after each split is processed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CopyProgress {
+    pub num_splits_total: usize,
+    pub num_splits_processed: usize,
+    /// Number of splits left untouched because they were already present at the destination,
+    /// typically because a previous run was interrupted.
+    pub num_splits_skipped: usize,
+    pub num_bytes_copied: u64,
+}
+
+/// Backs up the index `index_id` to `backup_uri`.
+///
+/// The manifest is written first, so that an interrupted backup can be resumed by running the same
+/// command again: split files already present at the destination with the expected size are not
+/// copied twice.
+pub async fn backup_index(
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: &StorageUriResolver,
+    index_id: &str,
+    backup_uri: &Uri,
+    include_split_files: bool,
+    report_progress: impl Fn(&CopyProgress),
+) -> anyhow::Result<CopyProgress> {
+    let index_metadata = metastore.index_metadata(index_id).await?;
+    let splits = metastore
+        .list_splits(index_id, SplitState::Published, None, None)
+        .await?;
+    let index_storage = storage_resolver.resolve(&index_metadata.index_uri)?;
+    let backup_storage = storage_resolver.resolve(backup_uri)?;
+
+    let manifest = BackupManifest {
+        index_metadata,
+        splits,
+        includes_split_files: include_split_files,
+        create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    backup_storage
+        .put(
+            Path::new(BACKUP_MANIFEST_FILE_NAME),
+            Box::new(manifest_json),
+        )
+        .await
+        .with_context(|| format!("Failed to write backup manifest to `{}`.", backup_uri))?;
+    info!(index_id = %index_id, backup_uri = %backup_uri, num_splits = manifest.splits.len(), "Wrote backup manifest.");
+
+    let mut progress = CopyProgress {
+        num_splits_total: manifest.splits.len(),
+        ..Default::default()
+    };
+    if include_split_files {
+        for split in &manifest.splits {
+            let split_file_name = split_file(split.split_id());
+            let split_path = Path::new(&split_file_name);
+            let split_storage =
+                split_storage(storage_resolver, &index_storage, &split.split_metadata)?;
+            let num_bytes_copied_opt =
+                copy_split_file(&*split_storage, &*backup_storage, split_path).await?;
+            progress.record(num_bytes_copied_opt);
+            report_progress(&progress);
+        }
+    }
+    Ok(progress)
+}
+
+/// Restores the index backed up at `backup_uri`.
+///
+/// * `target_index_id_opt` - Restores the index under a different ID. Defaults to the ID of the
+///   backed up index.
+/// * `target_index_uri_opt` - Restores the split files at a different location. Defaults to
+///   `<default index root URI>/<index ID>` when the backup contains the split files, and to the
+///   original index URI otherwise.
+///
+/// Restoring is resumable: if the index already exists in the metastore with the same index URI,
+/// splits already published are skipped.
+pub async fn restore_index(
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: &StorageUriResolver,
+    default_index_root_uri: &Uri,
+    backup_uri: &Uri,
+    target_index_id_opt: Option<String>,
+    target_index_uri_opt: Option<Uri>,
+    report_progress: impl Fn(&CopyProgress),
+) -> anyhow::Result<CopyProgress> {
+    let backup_storage = storage_resolver.resolve(backup_uri)?;
+    let manifest = read_backup_manifest(&*backup_storage).await?;
+    let mut index_metadata = manifest.index_metadata;
+
+    if let Some(target_index_id) = target_index_id_opt {
+        index_metadata.index_id = target_index_id;
+    }
+    let index_id = index_metadata.index_id.clone();
+    index_metadata.index_uri = match target_index_uri_opt {
+        Some(target_index_uri) => target_index_uri,
+        None if manifest.includes_split_files => default_index_root_uri.join(&index_id)?,
+        None => index_metadata.index_uri,
+    };
+    let index_uri = index_metadata.index_uri.clone();
+
+    let (published_split_ids, staged_split_ids): (HashSet<String>, HashSet<String>) =
+        if metastore.index_exists(&index_id).await? {
+            let existing_index_metadata = metastore.index_metadata(&index_id).await?;
+            if existing_index_metadata.index_uri != index_uri {
+                bail!(
+                    "Index `{}` already exists with a different index URI `{}`.",
+                    index_id,
+                    existing_index_metadata.index_uri
+                );
+            }
+            info!(index_id = %index_id, "Index already exists, resuming restore.");
+            let published_split_ids = metastore
+                .list_splits(&index_id, SplitState::Published, None, None)
+                .await?
+                .into_iter()
+                .map(|split| split.split_id().to_string())
+                .collect();
+            // A previous interrupted run may have staged some splits already.
+            let staged_split_ids = metastore
+                .list_splits(&index_id, SplitState::Staged, None, None)
+                .await?
+                .into_iter()
+                .map(|split| split.split_id().to_string())
+                .collect();
+            (published_split_ids, staged_split_ids)
+        } else {
+            metastore.create_index(index_metadata).await?;
+            (HashSet::new(), HashSet::new())
+        };
+    let index_storage = storage_resolver.resolve(&index_uri)?;
+
+    let mut progress = CopyProgress {
+        num_splits_total: manifest.splits.len(),
+        ..Default::default()
+    };
+    for split in manifest.splits {
+        let split_id = split.split_id().to_string();
+        if published_split_ids.contains(&split_id) {
+            progress.record(None);
+            report_progress(&progress);
+            continue;
+        }
+        let mut num_bytes_copied = 0;
+        if manifest.includes_split_files {
+            let split_file_name = split_file(&split_id);
+            // The split file may have been copied by a previous run. The split is not published
+            // yet though, so it is not counted as skipped.
+            num_bytes_copied = copy_split_file(
+                &*backup_storage,
+                &*index_storage,
+                Path::new(&split_file_name),
+            )
+            .await?
+            .unwrap_or(0);
+        }
+        let mut split_metadata = split.split_metadata;
+        split_metadata.index_id = index_id.clone();
+        if manifest.includes_split_files {
+            // The split file now lives in the index storage, whatever its original tier.
+            split_metadata.storage_tier = SplitStorageTier::Hot;
+        }
+        if !staged_split_ids.contains(&split_id) {
+            metastore.stage_split(&index_id, split_metadata).await?;
+        }
+        metastore
+            .publish_splits(&index_id, &[&split_id], &[], None)
+            .await?;
+        progress.record(Some(num_bytes_copied));
+        report_progress(&progress);
+    }
+    Ok(progress)
+}
+
+/// Reads and deserializes the backup manifest stored in `backup_storage`.
+pub async fn read_backup_manifest(backup_storage: &dyn Storage) -> anyhow::Result<BackupManifest> {
+    let manifest_bytes = backup_storage
+        .get_all(Path::new(BACKUP_MANIFEST_FILE_NAME))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to read backup manifest from `{}`.",
+                backup_storage.uri()
+            )
+        })?;
+    let manifest = serde_json::from_slice(manifest_bytes.as_slice())?;
+    Ok(manifest)
+}
+
+impl CopyProgress {
+    /// Records a processed split. `None` means the split was skipped.
+    pub(crate) fn record(&mut self, num_bytes_copied_opt: Option<u64>) {
+        self.num_splits_processed += 1;
+        match num_bytes_copied_opt {
+            Some(num_bytes_copied) => self.num_bytes_copied += num_bytes_copied,
+            None => self.num_splits_skipped += 1,
+        }
+    }
+}
+
+/// Returns the storage holding the file of a split: the index storage, unless the split has been
+/// moved to a warm storage tier.
+pub(crate) fn split_storage(
+    storage_resolver: &StorageUriResolver,
+    index_storage: &Arc<dyn Storage>,
+    split_metadata: &SplitMetadata,
+) -> anyhow::Result<Arc<dyn Storage>> {
+    match split_metadata.storage_tier.storage_uri() {
+        Some(warm_storage_uri) => Ok(storage_resolver.resolve(warm_storage_uri)?),
+        None => Ok(index_storage.clone()),
+    }
+}
+
+/// Copies the file at `path` from `source_storage` to `target_storage`, unless a file with the
+/// same size already exists at the destination. Returns the number of bytes copied, or `None` if
+/// the copy was skipped.
+///
+/// Split files can be large: the file is downloaded to a temporary directory, then uploaded from
+/// there, rather than held in memory.
+pub(crate) async fn copy_split_file(
+    source_storage: &dyn Storage,
+    target_storage: &dyn Storage,
+    path: &Path,
+) -> anyhow::Result<Option<u64>> {
+    let num_bytes = source_storage.file_num_bytes(path).await?;
+    match target_storage.file_num_bytes(path).await {
+        Ok(target_num_bytes) if target_num_bytes == num_bytes => {
+            return Ok(None);
+        }
+        Ok(_) => {}
+        Err(error) if error.kind() == StorageErrorKind::DoesNotExist => {}
+        Err(error) => return Err(error.into()),
+    }
+    let temp_dir = tempfile::tempdir()?;
+    let download_path = temp_dir.path().join("split");
+    source_storage.copy_to_file(path, &download_path).await?;
+    let payload = FilePayload::open(&download_path).await?;
+    target_storage.put(path, Box::new(payload)).await?;
+    Ok(Some(num_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{metastore_for_test, SplitMetadata};
+    use quickwit_storage::StorageUriResolver;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_backup_and_restore_index() -> anyhow::Result<()> {
+        let storage_resolver = StorageUriResolver::for_test();
+        let source_metastore = metastore_for_test();
+        let index_id = "test-backup-index";
+        let index_uri = "ram:///indexes/test-backup-index";
+        let index_metadata = IndexMetadata::for_test(index_id, index_uri);
+        source_metastore.create_index(index_metadata).await?;
+
+        let split_id = "test-backup-split";
+        let split_metadata = SplitMetadata {
+            split_id: split_id.to_string(),
+            index_id: index_id.to_string(),
+            ..Default::default()
+        };
+        source_metastore
+            .stage_split(index_id, split_metadata)
+            .await?;
+        source_metastore
+            .publish_splits(index_id, &[split_id], &[], None)
+            .await?;
+        let index_storage =
+            storage_resolver.resolve(&Uri::from_well_formed(index_uri.to_string()))?;
+        index_storage
+            .put(
+                Path::new(&split_file(split_id)),
+                Box::new(b"split".to_vec()),
+            )
+            .await?;
+
+        let backup_uri = Uri::from_well_formed("ram:///backups/test-backup-index".to_string());
+        let backup_progress = backup_index(
+            source_metastore.clone(),
+            &storage_resolver,
+            index_id,
+            &backup_uri,
+            true,
+            |_| {},
+        )
+        .await?;
+        assert_eq!(backup_progress.num_splits_total, 1);
+        assert_eq!(backup_progress.num_bytes_copied, 5);
+
+        // Running the backup again is a no-op for the split files.
+        let backup_progress = backup_index(
+            source_metastore,
+            &storage_resolver,
+            index_id,
+            &backup_uri,
+            true,
+            |_| {},
+        )
+        .await?;
+        assert_eq!(backup_progress.num_splits_skipped, 1);
+        assert_eq!(backup_progress.num_bytes_copied, 0);
+
+        let target_metastore = metastore_for_test();
+        let default_index_root_uri = Uri::from_well_formed("ram:///restored".to_string());
+        let restore_progress = restore_index(
+            target_metastore.clone(),
+            &storage_resolver,
+            &default_index_root_uri,
+            &backup_uri,
+            Some("test-restored-index".to_string()),
+            None,
+            |_| {},
+        )
+        .await?;
+        assert_eq!(restore_progress.num_splits_processed, 1);
+        assert_eq!(restore_progress.num_bytes_copied, 5);
+
+        let restored_index_metadata = target_metastore
+            .index_metadata("test-restored-index")
+            .await?;
+        assert_eq!(
+            restored_index_metadata.index_uri,
+            "ram:///restored/test-restored-index"
+        );
+        let restored_splits = target_metastore
+            .list_splits("test-restored-index", SplitState::Published, None, None)
+            .await?;
+        assert_eq!(restored_splits.len(), 1);
+        assert_eq!(restored_splits[0].split_id(), split_id);
+
+        // Restoring again resumes and skips the published split.
+        let restore_progress = restore_index(
+            target_metastore,
+            &storage_resolver,
+            &default_index_root_uri,
+            &backup_uri,
+            Some("test-restored-index".to_string()),
+            None,
+            |_| {},
+        )
+        .await?;
+        assert_eq!(restore_progress.num_splits_skipped, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use quickwit_metastore::Split;
+use serde::Serialize;
+
+/// Statistics of a field aggregated across the published splits of an index.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct FieldStats {
+    /// Number of splits holding the field.
+    pub num_splits: usize,
+    /// Number of bytes of the term dictionaries of the field.
+    pub termdict_num_bytes: u64,
+    /// Number of bytes of the postings lists of the field.
+    pub postings_num_bytes: u64,
+    /// Number of bytes of the term positions of the field.
+    pub positions_num_bytes: u64,
+    /// Number of bytes of the fast field columns of the field.
+    pub fast_field_num_bytes: u64,
+    /// Number of bytes of the fieldnorms of the field.
+    pub fieldnorms_num_bytes: u64,
+    /// Number of bytes taken by the field, excluding the doc store.
+    pub total_num_bytes: u64,
+    /// Lower bound of the number of distinct terms of the field, i.e. the largest number of terms
+    /// of the field in a split.
+    pub min_cardinality: u64,
+    /// Upper bound of the number of distinct terms of the field, i.e. the sum of the number of
+    /// terms of the field over the splits.
+    pub max_cardinality: u64,
+    /// Number of splits in which the field is a fast field.
+    pub num_fast_splits: usize,
+}
+
+/// Statistics of the fields of an index, aggregated from the statistics recorded in the metadata
+/// of its splits when they were packaged.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct IndexFieldStats {
+    /// Number of splits the statistics are aggregated from.
+    pub num_splits: usize,
+    /// Number of splits packaged before field statistics were recorded, which are not accounted
+    /// for. They disappear as splits get merged.
+    pub num_splits_without_stats: usize,
+    /// Statistics of each field, keyed by field name.
+    pub fields: BTreeMap<String, FieldStats>,
+}
+
+/// Aggregates the field statistics of `splits`.
+pub fn aggregate_field_stats(splits: &[Split]) -> IndexFieldStats {
+    let mut index_field_stats = IndexFieldStats::default();
+    for split in splits {
+        if split.split_metadata.field_stats.is_empty() {
+            index_field_stats.num_splits_without_stats += 1;
+            continue;
+        }
+        index_field_stats.num_splits += 1;
+        for (field_name, split_field_stats) in &split.split_metadata.field_stats {
+            let field_stats = index_field_stats
+                .fields
+                .entry(field_name.clone())
+                .or_default();
+            field_stats.num_splits += 1;
+            field_stats.termdict_num_bytes += split_field_stats.termdict_num_bytes;
+            field_stats.postings_num_bytes += split_field_stats.postings_num_bytes;
+            field_stats.positions_num_bytes += split_field_stats.positions_num_bytes;
+            field_stats.fast_field_num_bytes += split_field_stats.fast_field_num_bytes;
+            field_stats.fieldnorms_num_bytes += split_field_stats.fieldnorms_num_bytes;
+            field_stats.total_num_bytes += split_field_stats.total_num_bytes();
+            field_stats.min_cardinality =
+                field_stats.min_cardinality.max(split_field_stats.num_terms);
+            field_stats.max_cardinality += split_field_stats.num_terms;
+            if split_field_stats.is_fast {
+                field_stats.num_fast_splits += 1;
+            }
+        }
+    }
+    index_field_stats
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{SplitFieldStats, SplitMetadata, SplitState};
+
+    use super::*;
+
+    fn make_split(split_id: &str, field_stats: &[(&str, SplitFieldStats)]) -> Split {
+        Split {
+            split_state: SplitState::Published,
+            update_timestamp: 0,
+            publish_timestamp: None,
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                field_stats: field_stats
+                    .iter()
+                    .map(|(field_name, stats)| (field_name.to_string(), *stats))
+                    .collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_aggregate_field_stats() {
+        let body_stats = SplitFieldStats {
+            termdict_num_bytes: 10,
+            postings_num_bytes: 20,
+            positions_num_bytes: 30,
+            num_terms: 5,
+            ..Default::default()
+        };
+        let timestamp_stats = SplitFieldStats {
+            fast_field_num_bytes: 8,
+            is_fast: true,
+            ..Default::default()
+        };
+        let splits = vec![
+            make_split("split-1", &[("body", body_stats), ("ts", timestamp_stats)]),
+            make_split(
+                "split-2",
+                &[(
+                    "body",
+                    SplitFieldStats {
+                        num_terms: 7,
+                        ..body_stats
+                    },
+                )],
+            ),
+            make_split("split-3", &[]),
+        ];
+        let index_field_stats = aggregate_field_stats(&splits);
+        assert_eq!(index_field_stats.num_splits, 2);
+        assert_eq!(index_field_stats.num_splits_without_stats, 1);
+        assert_eq!(
+            index_field_stats.fields["body"],
+            FieldStats {
+                num_splits: 2,
+                termdict_num_bytes: 20,
+                postings_num_bytes: 40,
+                positions_num_bytes: 60,
+                fast_field_num_bytes: 0,
+                fieldnorms_num_bytes: 0,
+                total_num_bytes: 120,
+                min_cardinality: 7,
+                max_cardinality: 12,
+                num_fast_splits: 0,
+            }
+        );
+        let ts_field_stats = &index_field_stats.fields["ts"];
+        assert_eq!(ts_field_stats.num_splits, 1);
+        assert_eq!(ts_field_stats.total_num_bytes, 8);
+        assert_eq!(ts_field_stats.num_fast_splits, 1);
+    }
+}
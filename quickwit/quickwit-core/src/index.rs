@@ -22,12 +22,14 @@ use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
+use anyhow::bail;
 use quickwit_common::fs::{empty_dir, get_cache_directory_path};
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexConfig, QuickwitConfig, SourceConfig};
-use quickwit_indexing::actors::INDEXING_DIR_NAME;
+use quickwit_config::{build_doc_mapper, IndexConfig, QuickwitConfig, SourceConfig, StoredQuery};
+use quickwit_indexing::actors::{prepare_document, INDEXING_DIR_NAME};
 use quickwit_janitor::{
-    delete_splits_with_files, run_garbage_collect, FileEntry, SplitDeletionError,
+    delete_orphan_files, delete_splits_with_files, list_orphan_files, run_garbage_collect,
+    FileEntry, SplitDeletionError,
 };
 use quickwit_metastore::{
     quickwit_metastore_uri_resolver, IndexMetadata, Metastore, MetastoreError, Split,
@@ -35,10 +37,18 @@ use quickwit_metastore::{
 };
 use quickwit_proto::{ServiceError, ServiceErrorCode};
 use quickwit_storage::{quickwit_storage_uri_resolver, StorageResolverError, StorageUriResolver};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use tantivy::schema::NamedFieldDocument;
 use tantivy::time::OffsetDateTime;
 use thiserror::Error;
 use tracing::{error, info};
 
+use crate::backup::{backup_index, restore_index, CopyProgress};
+use crate::field_stats::{aggregate_field_stats, IndexFieldStats};
+use crate::snapshot::{attach_index, snapshot_index};
+use crate::split_upgrade::{upgrade_splits, SplitUpgradeReport};
+
 #[derive(Error, Debug)]
 pub enum IndexServiceError {
     #[error("Failed to resolve the storage `{0}`.")]
@@ -49,6 +59,8 @@ pub enum IndexServiceError {
     SplitDeletionError(#[from] SplitDeletionError),
     #[error("Invalid index config: {0}.")]
     InvalidIndexConfig(String),
+    #[error("Invalid stored query: {0}")]
+    InvalidStoredQuery(String),
 }
 
 impl ServiceError for IndexServiceError {
@@ -58,10 +70,25 @@ impl ServiceError for IndexServiceError {
             Self::MetastoreError(_) => ServiceErrorCode::Internal,
             Self::SplitDeletionError(_) => ServiceErrorCode::Internal,
             Self::InvalidIndexConfig(_) => ServiceErrorCode::BadRequest,
+            Self::InvalidStoredQuery(_) => ServiceErrorCode::BadRequest,
         }
     }
 }
 
+/// Outcome of running a sample document through the doc mapper of an index.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum SimulatedDoc {
+    /// The document would be indexed as `doc`.
+    Accepted {
+        doc: NamedFieldDocument,
+        timestamp: Option<i64>,
+        partition: u64,
+    },
+    /// The document would be rejected for the reason `error`.
+    Rejected { error: String },
+}
+
 /// Index service responsible for creating, updating and deleting indexes.
 pub struct IndexService {
     metastore: Arc<dyn Metastore>,
@@ -104,6 +131,111 @@ impl IndexService {
         Ok(splits)
     }
 
+    /// Aggregates the field statistics of the published splits of index `index_id`.
+    pub async fn get_field_stats(
+        &self,
+        index_id: &str,
+    ) -> Result<IndexFieldStats, IndexServiceError> {
+        let splits = self
+            .metastore
+            .list_splits(index_id, SplitState::Published, None, None)
+            .await?;
+        Ok(aggregate_field_stats(&splits))
+    }
+
+    /// Get the stored queries of index `index_id`, sorted by name.
+    pub async fn list_stored_queries(
+        &self,
+        index_id: &str,
+    ) -> Result<Vec<StoredQuery>, IndexServiceError> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        Ok(index_metadata.stored_queries.into_values().collect())
+    }
+
+    /// Validates a stored query and adds it to index `index_id`, replacing the stored query with
+    /// the same name if any.
+    pub async fn put_stored_query(
+        &self,
+        index_id: &str,
+        stored_query: StoredQuery,
+    ) -> Result<StoredQuery, IndexServiceError> {
+        stored_query
+            .validate()
+            .map_err(|error| IndexServiceError::InvalidStoredQuery(error.to_string()))?;
+        if let Some(alert) = &stored_query.alert {
+            let index_metadata = self.metastore.index_metadata(index_id).await?;
+            if index_metadata.indexing_settings.timestamp_field.is_none() {
+                return Err(IndexServiceError::InvalidStoredQuery(format!(
+                    "Index `{index_id}` has no timestamp field: stored query `{}` cannot define \
+                     an alert.",
+                    stored_query.name
+                )));
+            }
+            let field_access_policies = &index_metadata.search_settings.field_access_policies;
+            let has_known_role = alert.role.as_ref().map_or(false, |role| {
+                field_access_policies
+                    .iter()
+                    .any(|field_access_policy| &field_access_policy.role == role)
+            });
+            if !field_access_policies.is_empty() && !has_known_role {
+                return Err(IndexServiceError::InvalidStoredQuery(format!(
+                    "Index `{index_id}` defines field access policies: the alert of stored query \
+                     `{}` must set the `role` of one of them.",
+                    stored_query.name
+                )));
+            }
+        }
+        self.metastore
+            .put_stored_query(index_id, stored_query.clone())
+            .await?;
+        Ok(stored_query)
+    }
+
+    /// Deletes the stored query `name` of index `index_id`.
+    pub async fn delete_stored_query(
+        &self,
+        index_id: &str,
+        name: &str,
+    ) -> Result<(), IndexServiceError> {
+        self.metastore.delete_stored_query(index_id, name).await?;
+        Ok(())
+    }
+
+    /// Runs sample documents through the doc mapper of index `index_id`, the way the doc processor
+    /// does before indexing, and returns the outcome for each of them. Nothing gets indexed.
+    pub async fn simulate_ingest(
+        &self,
+        index_id: &str,
+        docs: Vec<JsonValue>,
+    ) -> Result<Vec<SimulatedDoc>, IndexServiceError> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        let doc_mapper = build_doc_mapper(
+            &index_metadata.doc_mapping,
+            &index_metadata.search_settings,
+            &index_metadata.indexing_settings,
+        )
+        .map_err(|error| IndexServiceError::InvalidIndexConfig(error.to_string()))?;
+        let schema = doc_mapper.schema();
+        let timestamp_field_opt = doc_mapper.timestamp_field(&schema);
+        let simulated_docs = docs
+            .iter()
+            .map(|doc| {
+                let doc_json = serde_json::to_vec(doc).expect("JSON values should serialize.");
+                match prepare_document(&*doc_mapper, timestamp_field_opt, &doc_json) {
+                    Ok(prepared_doc) => SimulatedDoc::Accepted {
+                        doc: schema.to_named_doc(&prepared_doc.doc),
+                        timestamp: prepared_doc.timestamp_opt,
+                        partition: prepared_doc.partition,
+                    },
+                    Err(error) => SimulatedDoc::Rejected {
+                        error: error.to_string(),
+                    },
+                }
+            })
+            .collect();
+        Ok(simulated_docs)
+    }
+
     /// Get all indexes.
     pub async fn list_indexes(&self) -> anyhow::Result<Vec<IndexMetadata>> {
         let indexes_metadatas = self.metastore.list_indexes_metadatas().await?;
@@ -158,11 +290,16 @@ impl IndexService {
             index_id,
             index_uri,
             checkpoint: Default::default(),
+            publish_tokens: Default::default(),
             sources,
+            stored_queries: Default::default(),
+            alert_states: Default::default(),
             doc_mapping: index_config.doc_mapping,
             indexing_settings: index_config.indexing_settings,
             search_settings: index_config.search_settings,
             retention_policy: index_config.retention_policy,
+            tiering_policy: index_config.tiering_policy,
+            read_only: false,
             create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
             update_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
         };
@@ -186,8 +323,18 @@ impl IndexService {
         index_id: &str,
         dry_run: bool,
     ) -> Result<Vec<FileEntry>, IndexServiceError> {
-        let index_uri = self.metastore.index_metadata(index_id).await?.index_uri;
-        let storage = self.storage_resolver.resolve(&index_uri)?;
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        // The split files of a read-only index, such as an index attached from a snapshot, are
+        // left untouched: only its metadata is deleted.
+        if index_metadata.read_only {
+            if !dry_run {
+                // The metastore deletes the splits of a read-only index along with the index.
+                self.metastore.delete_index(index_id).await?;
+                info!(index_id = %index_id, "Deleted read-only index, its split files are left untouched.");
+            }
+            return Ok(Vec::new());
+        }
+        let storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
 
         if dry_run {
             let all_splits = self
@@ -253,8 +400,12 @@ impl IndexService {
         grace_period: Duration,
         dry_run: bool,
     ) -> anyhow::Result<Vec<FileEntry>> {
-        let index_uri = self.metastore.index_metadata(index_id).await?.index_uri;
-        let storage = self.storage_resolver.resolve(&index_uri)?;
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        // The split files of a read-only index are never deleted.
+        if index_metadata.read_only {
+            return Ok(Vec::new());
+        }
+        let storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
 
         let deleted_entries = run_garbage_collect(
             index_id,
@@ -272,6 +423,145 @@ impl IndexService {
         Ok(deleted_entries)
     }
 
+    /// Lists the split files of the index present on the storage but not referenced by the
+    /// metastore. See [`list_orphan_files`].
+    ///
+    /// * `index_id` - The target index Id.
+    pub async fn list_orphan_files(&self, index_id: &str) -> anyhow::Result<Vec<FileEntry>> {
+        let index_uri = self.metastore.index_metadata(index_id).await?.index_uri;
+        let storage = self.storage_resolver.resolve(&index_uri)?;
+        list_orphan_files(index_id, &*storage, &*self.metastore).await
+    }
+
+    /// Deletes orphan split files previously returned by [`IndexService::list_orphan_files`].
+    ///
+    /// * `index_id` - The target index Id.
+    /// * `orphan_files` - The list of orphan files to delete.
+    pub async fn delete_orphan_files(
+        &self,
+        index_id: &str,
+        orphan_files: Vec<FileEntry>,
+    ) -> anyhow::Result<Vec<FileEntry>> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        if index_metadata.read_only {
+            bail!(
+                "Index `{}` is read-only, its files cannot be deleted.",
+                index_id
+            );
+        }
+        let storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
+        delete_orphan_files(&*storage, orphan_files).await
+    }
+
+    /// Backs up the index `index_id` to `backup_uri`. See [`backup_index`].
+    ///
+    /// * `index_id` - The target index Id.
+    /// * `backup_uri` - The URI where the backup manifest and split files are written.
+    /// * `include_split_files` - Should the split files be copied alongside the manifest.
+    pub async fn backup_index(
+        &self,
+        index_id: &str,
+        backup_uri: &Uri,
+        include_split_files: bool,
+        report_progress: impl Fn(&CopyProgress),
+    ) -> anyhow::Result<CopyProgress> {
+        backup_index(
+            self.metastore.clone(),
+            &self.storage_resolver,
+            index_id,
+            backup_uri,
+            include_split_files,
+            report_progress,
+        )
+        .await
+    }
+
+    /// Restores the index backed up at `backup_uri`. See [`restore_index`].
+    ///
+    /// * `backup_uri` - The URI of a backup created with [`IndexService::backup_index`].
+    /// * `target_index_id_opt` - Restores the index under a different ID.
+    /// * `target_index_uri_opt` - Restores the split files at a different location.
+    pub async fn restore_index(
+        &self,
+        backup_uri: &Uri,
+        target_index_id_opt: Option<String>,
+        target_index_uri_opt: Option<Uri>,
+        report_progress: impl Fn(&CopyProgress),
+    ) -> anyhow::Result<CopyProgress> {
+        restore_index(
+            self.metastore.clone(),
+            &self.storage_resolver,
+            &self.default_index_root_uri,
+            backup_uri,
+            target_index_id_opt,
+            target_index_uri_opt,
+            report_progress,
+        )
+        .await
+    }
+
+    /// Takes an immutable snapshot of the index `index_id` at `snapshot_uri`. See
+    /// [`snapshot_index`].
+    ///
+    /// * `index_id` - The target index Id.
+    /// * `snapshot_uri` - The URI where the split files and the snapshot manifest are written.
+    pub async fn snapshot_index(
+        &self,
+        index_id: &str,
+        snapshot_uri: &Uri,
+        report_progress: impl Fn(&CopyProgress),
+    ) -> anyhow::Result<CopyProgress> {
+        snapshot_index(
+            self.metastore.clone(),
+            &self.storage_resolver,
+            index_id,
+            snapshot_uri,
+            report_progress,
+        )
+        .await
+    }
+
+    /// Attaches the manifest located at `manifest_uri` as a read-only index. See
+    /// [`attach_index`].
+    ///
+    /// * `manifest_uri` - The URI of a snapshot created with [`IndexService::snapshot_index`], or
+    ///   of a manifest file.
+    /// * `target_index_id_opt` - Attaches the manifest under a different index ID.
+    pub async fn attach_index(
+        &self,
+        manifest_uri: &Uri,
+        target_index_id_opt: Option<String>,
+    ) -> anyhow::Result<IndexMetadata> {
+        attach_index(
+            self.metastore.clone(),
+            &self.storage_resolver,
+            manifest_uri,
+            target_index_id_opt,
+        )
+        .await
+    }
+
+    /// Rewrites the splits of the index written with an outdated format. See [`upgrade_splits`].
+    ///
+    /// * `index_id` - The target index Id.
+    /// * `data_dir_path` - The directory where the splits are downloaded and rewritten.
+    /// * `dry_run` - Should this only return the list of outdated splits.
+    pub async fn upgrade_splits(
+        &self,
+        index_id: &str,
+        data_dir_path: &Path,
+        dry_run: bool,
+    ) -> anyhow::Result<SplitUpgradeReport> {
+        upgrade_splits(
+            self.metastore.clone(),
+            &self.storage_resolver,
+            index_id,
+            data_dir_path,
+            dry_run,
+        )
+        .await
+    }
+
     /// Clears the index by applying the following actions:
     /// - mark all splits for deletion in the metastore.
     /// - delete the files of all splits marked for deletion using garbage collection.
@@ -283,6 +573,9 @@ impl IndexService {
     /// * `storage_resolver` - A storage resolver object to access the storage.
     pub async fn clear_index(&self, index_id: &str) -> anyhow::Result<()> {
         let index_metadata = self.metastore.index_metadata(index_id).await?;
+        if index_metadata.read_only {
+            bail!("Index `{}` is read-only and cannot be cleared.", index_id);
+        }
         let storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
         let splits = self.metastore.list_all_splits(index_id).await?;
         let split_ids: Vec<&str> = splits.iter().map(|split| split.split_id()).collect();
@@ -17,25 +17,43 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod backup;
+mod field_stats;
 mod index;
+mod snapshot;
+mod split_upgrade;
 
+pub use backup::{
+    backup_index, read_backup_manifest, restore_index, BackupManifest, CopyProgress,
+    BACKUP_MANIFEST_FILE_NAME,
+};
+pub use field_stats::{aggregate_field_stats, FieldStats, IndexFieldStats};
 pub use index::{
     clear_cache_directory, remove_indexing_directory, validate_storage_uri, IndexService,
-    IndexServiceError,
+    IndexServiceError, SimulatedDoc,
+};
+pub use quickwit_janitor::FileEntry;
+pub use snapshot::{
+    attach_index, is_snapshot, read_snapshot_manifest, snapshot_index, SnapshotManifest,
+    SnapshotSplit, SNAPSHOT_MANIFEST_FILE_NAME,
 };
+pub use split_upgrade::{upgrade_splits, SplitUpgradeReport, SPLIT_UPGRADE_DIR_NAME};
 
 #[cfg(test)]
 mod tests {
     use std::path::Path;
 
     use quickwit_common::uri::Uri;
-    use quickwit_config::{IndexConfig, IndexingSettings, SearchSettings};
+    use quickwit_config::{
+        AlertChannel, AlertCondition, AlertConfig, IndexConfig, IndexingSettings, SearchSettings,
+        StoredQuery, ThresholdOperator,
+    };
     use quickwit_indexing::TestSandbox;
     use quickwit_janitor::FileEntry;
     use quickwit_metastore::quickwit_metastore_uri_resolver;
     use quickwit_storage::StorageUriResolver;
 
-    use crate::IndexService;
+    use crate::{IndexService, IndexServiceError};
 
     #[tokio::test]
     async fn test_file_entry_from_split_and_index_delete() -> anyhow::Result<()> {
@@ -95,6 +113,7 @@ mod tests {
             index_uri: None,
             doc_mapping: serde_yaml::from_str(doc_mapping_yaml)?,
             retention_policy: None,
+            tiering_policy: None,
             indexing_settings: IndexingSettings::default(),
             search_settings: SearchSettings::default(),
             sources: Vec::new(),
@@ -118,4 +137,66 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_put_stored_query_rejects_alert_without_timestamp_field() -> anyhow::Result<()> {
+        let index_id = "test-index--alert-without-timestamp-field";
+        let index_config = IndexConfig {
+            version: 0,
+            index_id: index_id.to_string(),
+            index_uri: None,
+            doc_mapping: serde_yaml::from_str("field_mappings: [{name: body, type: text}]")?,
+            retention_policy: None,
+            tiering_policy: None,
+            indexing_settings: IndexingSettings::default(),
+            search_settings: SearchSettings::default(),
+            sources: Vec::new(),
+        };
+        let metastore_uri = Uri::from_well_formed("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+        let index_service = IndexService::new(
+            metastore,
+            StorageUriResolver::for_test(),
+            Uri::from_well_formed("ram:///indexes".to_string()),
+        );
+        index_service.create_index(index_config, false).await?;
+
+        let mut stored_query = StoredQuery {
+            name: "errors".to_string(),
+            description: None,
+            query: "body:error".to_string(),
+            params: Vec::new(),
+            search_fields: Vec::new(),
+            max_hits: None,
+            sort_by_field: None,
+            aggregation_request: None,
+            alert: Some(AlertConfig {
+                evaluation_interval: "1m".to_string(),
+                condition: AlertCondition {
+                    operator: ThresholdOperator::GreaterThan,
+                    threshold: 10,
+                },
+                channels: vec![AlertChannel::Webhook {
+                    url: "https://hooks.example.com/alerts".to_string(),
+                }],
+                enabled: true,
+                role: None,
+            }),
+        };
+        let error = index_service
+            .put_stored_query(index_id, stored_query.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IndexServiceError::InvalidStoredQuery(_)));
+
+        // Stored queries without an alert do not need a timestamp field.
+        stored_query.alert = None;
+        index_service
+            .put_stored_query(index_id, stored_query)
+            .await?;
+        Ok(())
+    }
 }
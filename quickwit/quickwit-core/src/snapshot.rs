@@ -0,0 +1,394 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use quickwit_common::split_file;
+use quickwit_common::uri::Uri;
+use quickwit_metastore::{IndexMetadata, Metastore, SplitMetadata, SplitState, SplitStorageTier};
+use quickwit_storage::{Storage, StorageUriResolver};
+use serde::{Deserialize, Serialize};
+use tantivy::time::OffsetDateTime;
+use tracing::info;
+
+use crate::backup::{copy_split_file, split_storage, CopyProgress};
+
+/// Name of the file holding the snapshot manifest at the root of the snapshot URI. It is written
+/// last: a snapshot without a manifest is incomplete.
+pub const SNAPSHOT_MANIFEST_FILE_NAME: &str = "snapshot-manifest.json";
+
+/// A snapshot manifest describes a self-contained and immutable copy of an index: the split files
+/// sit next to the manifest, so the snapshot can be attached to any cluster.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// Metadata of the index at the time of the snapshot.
+    pub index_metadata: IndexMetadata,
+    /// Published splits at the time of the snapshot.
+    pub splits: Vec<SnapshotSplit>,
+    /// Time at which the snapshot was started.
+    pub create_timestamp: i64,
+}
+
+/// A split of a snapshot, along with the size of its file, checked when the snapshot is attached.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SnapshotSplit {
+    pub split_metadata: SplitMetadata,
+    pub file_num_bytes: u64,
+}
+
+/// Takes a snapshot of the published splits of the index `index_id` at `snapshot_uri`.
+///
+/// The split files are copied first and the manifest last, so an interrupted snapshot can be
+/// resumed by running the same command again. Once the manifest is written, the snapshot is
+/// immutable: taking another snapshot at the same URI fails.
+pub async fn snapshot_index(
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: &StorageUriResolver,
+    index_id: &str,
+    snapshot_uri: &Uri,
+    report_progress: impl Fn(&CopyProgress),
+) -> anyhow::Result<CopyProgress> {
+    let snapshot_storage = storage_resolver.resolve(snapshot_uri)?;
+    if is_snapshot(&*snapshot_storage).await? {
+        bail!(
+            "A snapshot already exists at `{}`. Snapshots are immutable.",
+            snapshot_uri
+        );
+    }
+    let index_metadata = metastore.index_metadata(index_id).await?;
+    let splits = metastore
+        .list_splits(index_id, SplitState::Published, None, None)
+        .await?;
+    let index_storage = storage_resolver.resolve(&index_metadata.index_uri)?;
+
+    let mut progress = CopyProgress {
+        num_splits_total: splits.len(),
+        ..Default::default()
+    };
+    let mut snapshot_splits = Vec::with_capacity(splits.len());
+    for split in splits {
+        let split_file_name = split_file(split.split_id());
+        let split_path = Path::new(&split_file_name);
+        let split_storage = split_storage(storage_resolver, &index_storage, &split.split_metadata)?;
+        let num_bytes_copied_opt =
+            copy_split_file(&*split_storage, &*snapshot_storage, split_path).await?;
+        let file_num_bytes = snapshot_storage.file_num_bytes(split_path).await?;
+        progress.record(num_bytes_copied_opt);
+        report_progress(&progress);
+        let mut split_metadata = split.split_metadata;
+        // The split files of a snapshot all sit next to its manifest.
+        split_metadata.storage_tier = SplitStorageTier::Hot;
+        snapshot_splits.push(SnapshotSplit {
+            split_metadata,
+            file_num_bytes,
+        });
+    }
+    let manifest = SnapshotManifest {
+        index_metadata,
+        splits: snapshot_splits,
+        create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    snapshot_storage
+        .put(
+            Path::new(SNAPSHOT_MANIFEST_FILE_NAME),
+            Box::new(manifest_json),
+        )
+        .await
+        .with_context(|| format!("Failed to write snapshot manifest to `{}`.", snapshot_uri))?;
+    info!(index_id = %index_id, snapshot_uri = %snapshot_uri, num_splits = manifest.splits.len(), "Wrote snapshot manifest.");
+    Ok(progress)
+}
+
+/// Attaches the manifest located at `manifest_uri` as a new read-only index, which reads the
+/// split files in place.
+///
+/// * `manifest_uri` - Either the URI of a snapshot, or the URI of a manifest file (`.json`). The
+///   split files must sit next to the manifest.
+/// * `target_index_id_opt` - Attaches the manifest under a different index ID. Defaults to the ID
+///   of the snapshotted index.
+///
+/// The attached index has no sources and no retention policy, so no pipeline ever runs on it, and
+/// deleting it leaves its split files untouched.
+pub async fn attach_index(
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: &StorageUriResolver,
+    manifest_uri: &Uri,
+    target_index_id_opt: Option<String>,
+) -> anyhow::Result<IndexMetadata> {
+    let (split_root_uri, manifest_file_name) = split_manifest_uri(manifest_uri)?;
+    let split_root_storage = storage_resolver.resolve(&split_root_uri)?;
+    let manifest = read_manifest(&*split_root_storage, &manifest_file_name).await?;
+    for snapshot_split in &manifest.splits {
+        let split_file_name = split_file(&snapshot_split.split_metadata.split_id);
+        let file_num_bytes = split_root_storage
+            .file_num_bytes(Path::new(&split_file_name))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to read split file `{}` of manifest `{}`.",
+                    split_file_name, manifest_uri
+                )
+            })?;
+        if file_num_bytes != snapshot_split.file_num_bytes {
+            bail!(
+                "Split file `{}` of manifest `{}` is corrupted: expected {} bytes, found {}.",
+                split_file_name,
+                manifest_uri,
+                snapshot_split.file_num_bytes,
+                file_num_bytes
+            );
+        }
+    }
+    let mut index_metadata = manifest.index_metadata;
+    if let Some(target_index_id) = target_index_id_opt {
+        index_metadata.index_id = target_index_id;
+    }
+    let index_id = index_metadata.index_id.clone();
+    index_metadata.index_uri = split_root_uri.clone();
+    index_metadata.read_only = true;
+    index_metadata.sources.clear();
+    index_metadata.checkpoint = Default::default();
+    index_metadata.publish_tokens.clear();
+    index_metadata.retention_policy = None;
+    index_metadata.tiering_policy = None;
+    let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+    index_metadata.create_timestamp = now_timestamp;
+    index_metadata.update_timestamp = now_timestamp;
+    metastore.create_index(index_metadata).await?;
+
+    let split_ids: Vec<String> = manifest
+        .splits
+        .iter()
+        .map(|snapshot_split| snapshot_split.split_metadata.split_id.clone())
+        .collect();
+    for snapshot_split in manifest.splits {
+        let mut split_metadata = snapshot_split.split_metadata;
+        split_metadata.index_id = index_id.clone();
+        metastore.stage_split(&index_id, split_metadata).await?;
+    }
+    if !split_ids.is_empty() {
+        let split_id_refs: Vec<&str> = split_ids.iter().map(String::as_str).collect();
+        metastore
+            .publish_splits(&index_id, &split_id_refs, &[], None)
+            .await?;
+    }
+    info!(index_id = %index_id, manifest_uri = %manifest_uri, num_splits = split_ids.len(), "Attached read-only index.");
+    let index_metadata = metastore.index_metadata(&index_id).await?;
+    Ok(index_metadata)
+}
+
+/// Returns whether `storage` holds a snapshot.
+pub async fn is_snapshot(storage: &dyn Storage) -> anyhow::Result<bool> {
+    let exists = storage
+        .exists(Path::new(SNAPSHOT_MANIFEST_FILE_NAME))
+        .await?;
+    Ok(exists)
+}
+
+/// Reads and deserializes the snapshot manifest stored in `snapshot_storage`.
+pub async fn read_snapshot_manifest(
+    snapshot_storage: &dyn Storage,
+) -> anyhow::Result<SnapshotManifest> {
+    read_manifest(snapshot_storage, SNAPSHOT_MANIFEST_FILE_NAME).await
+}
+
+async fn read_manifest(
+    storage: &dyn Storage,
+    manifest_file_name: &str,
+) -> anyhow::Result<SnapshotManifest> {
+    let manifest_bytes = storage
+        .get_all(Path::new(manifest_file_name))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to read manifest `{}` from `{}`.",
+                manifest_file_name,
+                storage.uri()
+            )
+        })?;
+    let manifest = serde_json::from_slice(manifest_bytes.as_slice())
+        .with_context(|| format!("Manifest `{}` is invalid.", manifest_file_name))?;
+    Ok(manifest)
+}
+
+/// Splits a manifest URI into the URI of the directory holding the split files and the name of
+/// the manifest file.
+fn split_manifest_uri(manifest_uri: &Uri) -> anyhow::Result<(Uri, String)> {
+    let file_name_opt = manifest_uri
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .filter(|file_name| file_name.ends_with(".json"));
+    if let Some(file_name) = file_name_opt {
+        let split_root_uri = manifest_uri
+            .parent()
+            .with_context(|| format!("Manifest URI `{}` has no parent.", manifest_uri))?;
+        return Ok((split_root_uri, file_name.to_string()));
+    }
+    Ok((
+        manifest_uri.clone(),
+        SNAPSHOT_MANIFEST_FILE_NAME.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use quickwit_metastore::metastore_for_test;
+    use quickwit_storage::StorageUriResolver;
+
+    use super::*;
+    use crate::IndexService;
+
+    #[test]
+    fn test_split_manifest_uri() {
+        let (split_root_uri, manifest_file_name) = split_manifest_uri(&Uri::from_well_formed(
+            "s3://bucket/snapshots/my-index".to_string(),
+        ))
+        .unwrap();
+        assert_eq!(split_root_uri, "s3://bucket/snapshots/my-index");
+        assert_eq!(manifest_file_name, SNAPSHOT_MANIFEST_FILE_NAME);
+
+        let (split_root_uri, manifest_file_name) = split_manifest_uri(&Uri::from_well_formed(
+            "s3://bucket/archive/manifest-2022.json".to_string(),
+        ))
+        .unwrap();
+        assert_eq!(split_root_uri, "s3://bucket/archive");
+        assert_eq!(manifest_file_name, "manifest-2022.json");
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_and_attach_index() -> anyhow::Result<()> {
+        let storage_resolver = StorageUriResolver::for_test();
+        let source_metastore = metastore_for_test();
+        let index_id = "test-snapshot-index";
+        let index_uri = "ram:///indexes/test-snapshot-index";
+        let index_metadata = IndexMetadata::for_test(index_id, index_uri);
+        source_metastore.create_index(index_metadata).await?;
+
+        let split_id = "test-snapshot-split";
+        let split_metadata = SplitMetadata {
+            split_id: split_id.to_string(),
+            index_id: index_id.to_string(),
+            ..Default::default()
+        };
+        source_metastore
+            .stage_split(index_id, split_metadata)
+            .await?;
+        source_metastore
+            .publish_splits(index_id, &[split_id], &[], None)
+            .await?;
+        let index_storage =
+            storage_resolver.resolve(&Uri::from_well_formed(index_uri.to_string()))?;
+        index_storage
+            .put(
+                Path::new(&split_file(split_id)),
+                Box::new(b"split".to_vec()),
+            )
+            .await?;
+
+        let snapshot_uri =
+            Uri::from_well_formed("ram:///snapshots/test-snapshot-index".to_string());
+        let snapshot_progress = snapshot_index(
+            source_metastore.clone(),
+            &storage_resolver,
+            index_id,
+            &snapshot_uri,
+            |_| {},
+        )
+        .await?;
+        assert_eq!(snapshot_progress.num_splits_total, 1);
+        assert_eq!(snapshot_progress.num_bytes_copied, 5);
+
+        // Snapshots are immutable.
+        snapshot_index(
+            source_metastore,
+            &storage_resolver,
+            index_id,
+            &snapshot_uri,
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+
+        let target_metastore = metastore_for_test();
+        let attached_index_metadata = attach_index(
+            target_metastore.clone(),
+            &storage_resolver,
+            &snapshot_uri,
+            Some("test-attached-index".to_string()),
+        )
+        .await?;
+        assert_eq!(attached_index_metadata.index_id, "test-attached-index");
+        assert_eq!(attached_index_metadata.index_uri, snapshot_uri);
+        assert!(attached_index_metadata.read_only);
+        assert!(attached_index_metadata.sources.is_empty());
+        assert!(attached_index_metadata.retention_policy.is_none());
+
+        let attached_splits = target_metastore
+            .list_splits("test-attached-index", SplitState::Published, None, None)
+            .await?;
+        assert_eq!(attached_splits.len(), 1);
+        assert_eq!(attached_splits[0].split_id(), split_id);
+
+        // The garbage collection leaves the splits of the attached index untouched.
+        let index_service = IndexService::new(
+            target_metastore.clone(),
+            storage_resolver.clone(),
+            Uri::from_well_formed("ram:///indexes".to_string()),
+        );
+        let deleted_file_entries = index_service
+            .garbage_collect_index("test-attached-index", Duration::ZERO, false)
+            .await?;
+        assert!(deleted_file_entries.is_empty());
+
+        // Deleting the attached index leaves the snapshot untouched.
+        let deleted_file_entries = index_service
+            .delete_index("test-attached-index", false)
+            .await?;
+        assert!(deleted_file_entries.is_empty());
+        let snapshot_storage = storage_resolver.resolve(&snapshot_uri)?;
+        assert!(
+            snapshot_storage
+                .exists(Path::new(&split_file(split_id)))
+                .await?
+        );
+
+        // A corrupted split file is detected when the snapshot is attached.
+        snapshot_storage
+            .put(
+                Path::new(&split_file(split_id)),
+                Box::new(b"truncated-split".to_vec()),
+            )
+            .await?;
+        let error = attach_index(
+            target_metastore,
+            &storage_resolver,
+            &snapshot_uri,
+            Some("test-attached-index-2".to_string()),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("is corrupted"));
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::bail;
+use quickwit_actors::Universe;
+use quickwit_indexing::merge_policy::MergeOperation;
+use quickwit_janitor::actors::CompactionPipeline;
+use quickwit_metastore::{Metastore, SplitMetadata, SplitState};
+use quickwit_storage::StorageUriResolver;
+use tantivy::Inventory;
+use tracing::info;
+
+/// Name of the directory, under the data directory, holding the splits being upgraded.
+pub const SPLIT_UPGRADE_DIR_NAME: &str = "split-upgrade";
+
+/// Maximum number of upgraded splits uploaded concurrently.
+const MAX_CONCURRENT_SPLIT_UPLOADS: usize = 4;
+
+/// Interval at which the completion of the upgrade operations is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Outcome of [`upgrade_splits`].
+#[derive(Debug, Default)]
+pub struct SplitUpgradeReport {
+    /// IDs of the published splits written with an outdated split or tantivy index format.
+    pub outdated_split_ids: Vec<String>,
+    /// IDs of the outdated splits that could not be rewritten.
+    pub failed_split_ids: Vec<String>,
+}
+
+/// Rewrites the published splits of the index `index_id` written with an outdated split or tantivy
+/// index format. Each outdated split is downloaded, rewritten with the formats of the running
+/// version of Quickwit, uploaded, and replaced in the metastore by the rewritten split.
+///
+/// The upgrade runs in the current process and does not require a running cluster. Splits moved to
+/// the warm storage tier are skipped until they are promoted back to the index storage.
+///
+/// * `index_id` - The target index Id.
+/// * `data_dir_path` - The directory where the splits are downloaded and rewritten.
+/// * `dry_run` - Should this only return the list of outdated splits without rewriting them.
+pub async fn upgrade_splits(
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: &StorageUriResolver,
+    index_id: &str,
+    data_dir_path: &Path,
+    dry_run: bool,
+) -> anyhow::Result<SplitUpgradeReport> {
+    let index_metadata = metastore.index_metadata(index_id).await?;
+    if index_metadata.read_only {
+        bail!(
+            "Index `{}` is read-only and its splits cannot be upgraded.",
+            index_id
+        );
+    }
+    let outdated_splits: Vec<SplitMetadata> = metastore
+        .list_splits(index_id, SplitState::Published, None, None)
+        .await?
+        .into_iter()
+        .map(|split| split.split_metadata)
+        .filter(|split| split.is_format_outdated() && split.storage_tier.is_hot())
+        .collect();
+    let outdated_split_ids: Vec<String> = outdated_splits
+        .iter()
+        .map(|split| split.split_id().to_string())
+        .collect();
+    if dry_run || outdated_splits.is_empty() {
+        return Ok(SplitUpgradeReport {
+            outdated_split_ids,
+            failed_split_ids: Vec::new(),
+        });
+    }
+    for split in &outdated_splits {
+        split.check_format_compatibility()?;
+    }
+    info!(index_id=%index_id, num_splits=%outdated_splits.len(), "Upgrading splits.");
+
+    let index_storage = storage_resolver.resolve(&index_metadata.index_uri)?;
+    let universe = Universe::new();
+    let pipeline = CompactionPipeline::new(
+        index_id.to_string(),
+        metastore.clone(),
+        index_storage,
+        data_dir_path.join(SPLIT_UPGRADE_DIR_NAME),
+        MAX_CONCURRENT_SPLIT_UPLOADS,
+    );
+    let (pipeline_mailbox, pipeline_handle) = universe.spawn_builder().spawn(pipeline);
+    // An operation is dropped once the rewritten split is published, or if it fails.
+    let ongoing_operations_inventory = Inventory::default();
+
+    for split in outdated_splits {
+        let upgrade_operation =
+            ongoing_operations_inventory.track(MergeOperation::new_upgrade_operation(split));
+        pipeline_mailbox.send_message(upgrade_operation).await?;
+    }
+    while !ongoing_operations_inventory.list().is_empty() && !pipeline_handle.state().is_exit() {
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    pipeline_handle.quit().await;
+
+    let published_split_ids: HashSet<String> = metastore
+        .list_splits(index_id, SplitState::Published, None, None)
+        .await?
+        .into_iter()
+        .map(|split| split.split_metadata.split_id)
+        .collect();
+    let failed_split_ids = outdated_split_ids
+        .iter()
+        .filter(|split_id| published_split_ids.contains(*split_id))
+        .cloned()
+        .collect();
+    Ok(SplitUpgradeReport {
+        outdated_split_ids,
+        failed_split_ids,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use quickwit_common::split_file;
+    use quickwit_indexing::TestSandbox;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upgrade_splits() -> anyhow::Result<()> {
+        let index_id = "test-upgrade-splits";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox =
+            TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"], None).await?;
+        test_sandbox
+            .add_documents(vec![
+                serde_json::json!({"body": "one"}),
+                serde_json::json!({"body": "two"}),
+            ])
+            .await?;
+        let metastore = test_sandbox.metastore();
+        let split = metastore.list_all_splits(index_id).await?.pop().unwrap();
+
+        // Simulates a split written by a previous version of Quickwit.
+        let mut outdated_split_metadata = split.split_metadata.clone();
+        outdated_split_metadata.split_id = "outdated-split".to_string();
+        outdated_split_metadata.split_format_version = 0;
+        outdated_split_metadata.tantivy_index_format_version = 0;
+        let split_bytes = test_sandbox
+            .storage()
+            .get_all(Path::new(&split_file(split.split_id())))
+            .await?;
+        test_sandbox
+            .storage()
+            .put(
+                Path::new(&split_file("outdated-split")),
+                Box::new(split_bytes.as_slice().to_vec()),
+            )
+            .await?;
+        metastore
+            .stage_split(index_id, outdated_split_metadata)
+            .await?;
+        metastore
+            .publish_splits(index_id, &["outdated-split"], &[split.split_id()], None)
+            .await?;
+
+        let temp_dir = tempfile::tempdir()?;
+        let storage_resolver = test_sandbox.storage_uri_resolver();
+        let report = upgrade_splits(
+            metastore.clone(),
+            &storage_resolver,
+            index_id,
+            temp_dir.path(),
+            true,
+        )
+        .await?;
+        assert_eq!(
+            report.outdated_split_ids,
+            vec!["outdated-split".to_string()]
+        );
+        assert!(report.failed_split_ids.is_empty());
+
+        let report = upgrade_splits(
+            metastore.clone(),
+            &storage_resolver,
+            index_id,
+            temp_dir.path(),
+            false,
+        )
+        .await?;
+        assert_eq!(
+            report.outdated_split_ids,
+            vec!["outdated-split".to_string()]
+        );
+        assert!(report.failed_split_ids.is_empty());
+
+        let published_splits = metastore
+            .list_splits(index_id, SplitState::Published, None, None)
+            .await?;
+        assert_eq!(published_splits.len(), 1);
+        let upgraded_split_metadata = &published_splits[0].split_metadata;
+        assert_ne!(upgraded_split_metadata.split_id(), "outdated-split");
+        assert!(!upgraded_split_metadata.is_format_outdated());
+        assert_eq!(upgraded_split_metadata.num_docs, 2);
+        Ok(())
+    }
+}
@@ -453,12 +453,37 @@ fn list_index_files(index: &Index) -> tantivy::Result<HashSet<PathBuf>> {
     Ok(files)
 }
 
+/// Default size above which the byte ranges read when opening a split are left out of the
+/// hotcache.
+pub const DEFAULT_HOTCACHE_MAX_SLICE_NUM_BYTES: usize = 10_000_000;
+
+/// Controls which parts of a split [`write_hotcache`] includes in the hotcache.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HotcacheOptions {
+    /// Names of the fields whose inverted index is warmed up. All the indexed fields are warmed
+    /// up if `None`. Unknown fields are ignored.
+    pub field_names_opt: Option<Vec<String>>,
+    /// Byte ranges larger than this are read on demand rather than stored in the hotcache, except
+    /// for the term dictionaries and the doc store index of the warmed up fields.
+    pub max_slice_num_bytes: usize,
+}
+
+impl Default for HotcacheOptions {
+    fn default() -> Self {
+        Self {
+            field_names_opt: None,
+            max_slice_num_bytes: DEFAULT_HOTCACHE_MAX_SLICE_NUM_BYTES,
+        }
+    }
+}
+
 /// Given a tantivy directory, automatically identify the parts that should be loaded on startup
 /// and writes a static cache file called hotcache in the `output`.
 ///
 /// See [`HotDirectory`] for more information.
 pub fn write_hotcache<D: Directory>(
     directory: D,
+    options: &HotcacheOptions,
     output: &mut dyn io::Write,
 ) -> tantivy::Result<()> {
     // We use the caching directory here in order to defensively ensure that
@@ -477,6 +502,14 @@ pub fn write_hotcache<D: Directory>(
         if !field_entry.is_indexed() {
             continue;
         }
+        if let Some(field_names) = &options.field_names_opt {
+            if !field_names
+                .iter()
+                .any(|field_name| field_name == field_entry.name())
+            {
+                continue;
+            }
+        }
         for reader in searcher.segment_readers() {
             let _inv_idx = reader.inverted_index(field)?;
         }
@@ -510,7 +543,7 @@ pub fn write_hotcache<D: Directory>(
                 let file_path_str = file_path.to_string_lossy();
                 if file_path_str.ends_with("store")
                     || file_path_str.ends_with("term")
-                    || len < 10_000_000
+                    || len < options.max_slice_num_bytes
                 {
                     let bytes = file_slice.read_bytes_slice(byte_range.clone())?;
                     file_cache_builder.add_bytes(bytes.as_slice(), byte_range.start);
@@ -708,4 +741,41 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_write_hotcache_with_field_names() -> anyhow::Result<()> {
+        let mut schema_builder = tantivy::schema::Schema::builder();
+        let title_field = schema_builder.add_text_field("title", tantivy::schema::TEXT);
+        let body_field = schema_builder.add_text_field("body", tantivy::schema::TEXT);
+        let schema = schema_builder.build();
+        let directory = tantivy::directory::RamDirectory::default();
+        let index = Index::create(directory.clone(), schema, Default::default())?;
+        let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+        for doc_id in 0..1_000 {
+            index_writer.add_document(tantivy::doc!(
+                title_field => format!("title{doc_id}"),
+                body_field => format!("body{doc_id} body{}", doc_id * 7),
+            ))?;
+        }
+        index_writer.commit()?;
+
+        let mut hotcache_bytes = Vec::new();
+        write_hotcache(
+            directory.clone(),
+            &HotcacheOptions::default(),
+            &mut hotcache_bytes,
+        )?;
+        let options = HotcacheOptions {
+            field_names_opt: Some(vec!["title".to_string()]),
+            ..Default::default()
+        };
+        let mut title_hotcache_bytes = Vec::new();
+        write_hotcache(directory, &options, &mut title_hotcache_bytes)?;
+        assert!(title_hotcache_bytes.len() < hotcache_bytes.len());
+
+        // The files of the split are all listed, even if some of their byte ranges are left out.
+        let static_dir_cache = StaticDirectoryCache::open(OwnedBytes::new(title_hotcache_bytes))?;
+        assert!(!static_dir_cache.file_lengths.is_empty());
+        Ok(())
+    }
 }
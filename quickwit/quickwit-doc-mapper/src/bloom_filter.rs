@@ -0,0 +1,283 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+
+use anyhow::{bail, Context};
+use siphasher::sip::SipHasher;
+
+use crate::tag_pruning::TagFilterAst;
+
+/// Version of the binary format of [`SplitBloomFilters`].
+const SPLIT_BLOOM_FILTERS_FORMAT_VERSION: u8 = 1;
+
+/// Target false positive rate of the bloom filters built at indexing.
+pub const BLOOM_FILTER_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// A bloom filter over the values of a field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BloomFilter {
+    num_hashes: u32,
+    words: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Creates an empty bloom filter sized to hold `num_values` values with the given false
+    /// positive rate.
+    pub fn with_num_values(num_values: usize, false_positive_rate: f64) -> Self {
+        let num_values = num_values.max(1) as f64;
+        let ln_2 = std::f64::consts::LN_2;
+        let num_bits = (-num_values * false_positive_rate.ln() / (ln_2 * ln_2)).ceil();
+        let num_words = ((num_bits / 64.0).ceil() as usize).max(1);
+        let num_hashes = ((num_words * 64) as f64 / num_values * ln_2)
+            .round()
+            .clamp(1.0, 16.0) as u32;
+        BloomFilter {
+            num_hashes,
+            words: vec![0; num_words],
+        }
+    }
+
+    /// Adds a value to the filter.
+    pub fn insert(&mut self, value: &str) {
+        for bit in self.bit_positions(value) {
+            self.words[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Returns false if the value was definitely not inserted in the filter.
+    pub fn contains(&self, value: &str) -> bool {
+        self.bit_positions(value)
+            .all(|bit| self.words[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Returns the size of the filter in bytes.
+    pub fn num_bytes(&self) -> usize {
+        self.words.len() * 8
+    }
+
+    // Uses double hashing to derive `num_hashes` bit positions from two hashes of the value. The
+    // hashers use fixed keys so that the positions remain stable across releases.
+    fn bit_positions(&self, value: &str) -> impl Iterator<Item = usize> {
+        let mut hasher = SipHasher::new_with_keys(0, 0);
+        hasher.write(value.as_bytes());
+        let hash_1 = hasher.finish();
+        let mut hasher = SipHasher::new_with_keys(0, 1);
+        hasher.write(value.as_bytes());
+        let hash_2 = hasher.finish();
+        let num_bits = self.words.len() as u64 * 64;
+        (0..self.num_hashes as u64)
+            .map(move |i| (hash_1.wrapping_add(i.wrapping_mul(hash_2)) % num_bits) as usize)
+    }
+}
+
+/// The bloom filters of the fields of a split, keyed by field name.
+///
+/// They are stored next to the split file in a file named after the split, see
+/// `quickwit_common::bloom_filter_file`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SplitBloomFilters {
+    filters: BTreeMap<String, BloomFilter>,
+}
+
+impl SplitBloomFilters {
+    /// Adds the bloom filter of a field.
+    pub fn insert(&mut self, field_name: String, bloom_filter: BloomFilter) {
+        self.filters.insert(field_name, bloom_filter);
+    }
+
+    /// Returns the bloom filter of a field, if any.
+    pub fn get(&self, field_name: &str) -> Option<&BloomFilter> {
+        self.filters.get(field_name)
+    }
+
+    /// Returns true if no bloom filter was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Returns the size of the bloom filters in bytes.
+    pub fn num_bytes(&self) -> usize {
+        self.filters.values().map(BloomFilter::num_bytes).sum()
+    }
+
+    /// Evaluates a tag filter (see [`extract_tags_from_query`]) against the bloom filters.
+    ///
+    /// Returns false only if the split definitely contains no document matching the filter. Terms
+    /// on fields without a bloom filter, as well as negated terms, cannot prune the split.
+    ///
+    /// [`extract_tags_from_query`]: crate::tag_pruning::extract_tags_from_query
+    pub fn might_match(&self, tag_filter_ast: &TagFilterAst) -> bool {
+        match tag_filter_ast {
+            TagFilterAst::And(children) => children.iter().all(|child| self.might_match(child)),
+            TagFilterAst::Or(children) => children.iter().any(|child| self.might_match(child)),
+            TagFilterAst::Tag { is_present, tag } => {
+                if let Some((field_name, value)) = tag.split_once(':') {
+                    if !is_present {
+                        return true;
+                    }
+                    return self
+                        .filters
+                        .get(field_name)
+                        .map(|bloom_filter| bloom_filter.contains(value))
+                        .unwrap_or(true);
+                }
+                if let Some(field_name) = tag.strip_suffix('!') {
+                    return self.filters.contains_key(field_name) == *is_present;
+                }
+                true
+            }
+        }
+    }
+
+    /// Serializes the bloom filters.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.num_bytes() + 64);
+        bytes.push(SPLIT_BLOOM_FILTERS_FORMAT_VERSION);
+        bytes.extend_from_slice(&(self.filters.len() as u32).to_le_bytes());
+        for (field_name, bloom_filter) in &self.filters {
+            bytes.extend_from_slice(&(field_name.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(field_name.as_bytes());
+            bytes.extend_from_slice(&bloom_filter.num_hashes.to_le_bytes());
+            bytes.extend_from_slice(&(bloom_filter.words.len() as u32).to_le_bytes());
+            for word in &bloom_filter.words {
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Deserializes bloom filters serialized with [`SplitBloomFilters::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = ByteReader { bytes };
+        let version = reader.read_bytes(1)?[0];
+        if version != SPLIT_BLOOM_FILTERS_FORMAT_VERSION {
+            bail!("Unsupported bloom filters format version `{}`.", version);
+        }
+        let num_filters = reader.read_u32()?;
+        let mut filters = BTreeMap::new();
+        for _ in 0..num_filters {
+            let field_name_len = reader.read_u32()? as usize;
+            let field_name = std::str::from_utf8(reader.read_bytes(field_name_len)?)
+                .context("Bloom filter field name is not valid UTF-8.")?
+                .to_string();
+            let num_hashes = reader.read_u32()?;
+            let num_words = reader.read_u32()? as usize;
+            if num_hashes == 0 || num_words == 0 {
+                bail!("Bloom filter of field `{}` is empty.", field_name);
+            }
+            let words = reader
+                .read_bytes(num_words * 8)?
+                .chunks_exact(8)
+                .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+                .collect();
+            filters.insert(field_name, BloomFilter { num_hashes, words });
+        }
+        if !reader.bytes.is_empty() {
+            bail!("Bloom filters have trailing bytes.");
+        }
+        Ok(SplitBloomFilters { filters })
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn read_bytes(&mut self, num_bytes: usize) -> anyhow::Result<&'a [u8]> {
+        if self.bytes.len() < num_bytes {
+            bail!("Bloom filters are truncated.");
+        }
+        let (head, tail) = self.bytes.split_at(num_bytes);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn read_u32(&mut self) -> anyhow::Result<u32> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_pruning::extract_tags_from_query;
+
+    fn split_bloom_filters_for_test() -> SplitBloomFilters {
+        let mut trace_id_filter = BloomFilter::with_num_values(1_000, 0.01);
+        for i in 0..1_000 {
+            trace_id_filter.insert(&format!("trace{}", i));
+        }
+        let mut split_bloom_filters = SplitBloomFilters::default();
+        split_bloom_filters.insert("trace_id".to_string(), trace_id_filter);
+        split_bloom_filters
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut bloom_filter = BloomFilter::with_num_values(10_000, 0.01);
+        for i in 0..10_000 {
+            bloom_filter.insert(&format!("value-{}", i));
+        }
+        for i in 0..10_000 {
+            assert!(bloom_filter.contains(&format!("value-{}", i)));
+        }
+        let num_false_positives = (0..10_000)
+            .filter(|i| bloom_filter.contains(&format!("other-value-{}", i)))
+            .count();
+        assert!(num_false_positives < 200, "{}", num_false_positives);
+
+        let empty_bloom_filter = BloomFilter::with_num_values(0, 0.01);
+        assert!(!empty_bloom_filter.contains("value-0"));
+    }
+
+    #[test]
+    fn test_split_bloom_filters_serialization() {
+        let split_bloom_filters = split_bloom_filters_for_test();
+        let bytes = split_bloom_filters.to_bytes();
+        assert_eq!(
+            SplitBloomFilters::from_bytes(&bytes).unwrap(),
+            split_bloom_filters
+        );
+        assert!(SplitBloomFilters::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(SplitBloomFilters::from_bytes(&[2]).is_err());
+    }
+
+    #[test]
+    fn test_split_bloom_filters_might_match() {
+        let split_bloom_filters = split_bloom_filters_for_test();
+        let might_match = |query: &str| {
+            extract_tags_from_query(query)
+                .unwrap()
+                .map(|tag_filter_ast| split_bloom_filters.might_match(&tag_filter_ast))
+                .unwrap_or(true)
+        };
+        assert!(might_match("trace_id:trace1"));
+        assert!(!might_match("trace_id:trace1000000"));
+        assert!(might_match("trace_id:trace1000000 OR trace_id:trace1"));
+        assert!(!might_match("trace_id:trace1000000 AND body:error"));
+        assert!(might_match("trace_id:trace1000000 OR body:error"));
+        assert!(might_match("body:error AND NOT trace_id:trace1000000"));
+        assert!(might_match("span_id:span1000000"));
+        assert!(might_match("error"));
+    }
+}
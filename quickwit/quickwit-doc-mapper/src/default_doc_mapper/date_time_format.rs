@@ -54,6 +54,7 @@ impl FromStr for StrptimeParser {
 }
 
 impl StrptimeParser {
+    /// Parses the datetime string, assuming UTC if the format does not contain a timezone.
     pub fn parse_date_time(&self, date_time_str: &str) -> Result<OffsetDateTime, String> {
         if *self.borrow_with_timezone() {
             OffsetDateTime::parse(date_time_str, self.borrow_items()).map_err(|err| err.to_string())
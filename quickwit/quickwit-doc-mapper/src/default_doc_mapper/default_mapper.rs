@@ -25,19 +25,25 @@ use quickwit_proto::SearchRequest;
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value as JsonValue};
 use tantivy::query::Query;
-use tantivy::schema::{Cardinality, Field, FieldType, Schema, STORED};
+use tantivy::schema::{
+    Cardinality, Field, FieldEntry, FieldType, IndexRecordOption, Schema, TextFieldIndexing,
+    TextOptions, Value, STORED,
+};
 use tantivy::Document;
 
 use super::field_mapping_entry::QuickwitTextTokenizer;
 use super::DefaultDocMapperBuilder;
-use crate::default_doc_mapper::mapping_tree::{build_mapping_tree, MappingNode, MappingTree};
+use crate::default_doc_mapper::mapping_tree::{
+    build_mapping_tree, LeafType, MappingNode, MappingTree,
+};
 pub use crate::default_doc_mapper::QuickwitJsonOptions;
 use crate::doc_mapper::Partition;
 use crate::query_builder::build_query;
 use crate::routing_expression::RoutingExpr;
 use crate::sort_by::{validate_sort_by_field_name, SortBy, SortOrder};
 use crate::{
-    DocMapper, DocParsingError, ModeType, QueryParserError, DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME,
+    DocMapper, DocParsingError, ModeType, QueryParserError, DYNAMIC_ALL_FIELD_NAME,
+    DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME,
 };
 
 /// Specifies the name of the sort field and the sort order for an index.
@@ -103,6 +109,10 @@ pub struct DefaultDocMapper {
     /// This field is only valid when using the schema associated with the default
     /// doc mapper, and therefore cannot be used in the `query` method.
     dynamic_field: Option<Field>,
+    /// Field in which all the string values of the document are indexed.
+    dynamic_all_field: Option<Field>,
+    /// Fields whose string values are copied in the `_dynamic_all` field.
+    dynamic_all_source_fields: Vec<Field>,
     /// Default list of field names used for search.
     default_search_field_names: Vec<String>,
     /// Timestamp field name.
@@ -116,6 +126,13 @@ pub struct DefaultDocMapper {
     schema: Schema,
     /// List of field names used for tagging.
     tag_field_names: BTreeSet<String>,
+    /// Whether all the keyword fields, i.e. the text fields using the `raw` tokenizer,
+    /// are used for tagging as well.
+    tag_keyword_fields: bool,
+    /// List of field names for which a bloom filter is built for each split.
+    bloom_filter_field_names: BTreeSet<String>,
+    /// List of field names warmed up when a split is first opened by a searcher.
+    warmup_field_names: BTreeSet<String>,
     /// The partition key is a DSL used to route documents
     /// into specific splits.
     partition_key: RoutingExpr,
@@ -171,6 +188,118 @@ fn validate_tag_fields(tag_fields: &[String], schema: &Schema) -> anyhow::Result
     Ok(())
 }
 
+fn validate_bloom_filter_fields(
+    bloom_filter_fields: &[String],
+    schema: &Schema,
+) -> anyhow::Result<BTreeSet<String>> {
+    let mut bloom_filter_field_names = BTreeSet::new();
+    for bloom_filter_field in bloom_filter_fields {
+        if bloom_filter_field_names.contains(bloom_filter_field) {
+            bail!("Duplicated bloom filter field: `{}`", bloom_filter_field)
+        }
+        let field = schema
+            .get_field(bloom_filter_field)
+            .with_context(|| format!("Unknown bloom filter field: `{}`", bloom_filter_field))?;
+        if !is_raw_text_field(schema.get_field_entry(field)) {
+            bail!(
+                "Bloom filters are only allowed on indexed text fields with the `raw` tokenizer \
+                 (`{}`).",
+                bloom_filter_field
+            );
+        }
+        bloom_filter_field_names.insert(bloom_filter_field.clone());
+    }
+    Ok(bloom_filter_field_names)
+}
+
+fn validate_warmup_fields(
+    warmup_fields: &[String],
+    schema: &Schema,
+) -> anyhow::Result<BTreeSet<String>> {
+    let mut warmup_field_names = BTreeSet::new();
+    for warmup_field in warmup_fields {
+        if warmup_field_names.contains(warmup_field) {
+            bail!("Duplicated warmup field: `{}`", warmup_field)
+        }
+        let field = schema
+            .get_field(warmup_field)
+            .with_context(|| format!("Unknown warmup field: `{}`", warmup_field))?;
+        // Only the fast field data is kept in a searcher cache outliving the searches.
+        if !schema.get_field_entry(field).is_fast() {
+            bail!("Warmup fields must be fast fields (`{}`).", warmup_field);
+        }
+        warmup_field_names.insert(warmup_field.clone());
+    }
+    Ok(warmup_field_names)
+}
+
+/// Returns whether the field is an indexed text field using the `raw` tokenizer.
+fn is_raw_text_field(field_entry: &FieldEntry) -> bool {
+    match field_entry.field_type() {
+        FieldType::Str(options) => options
+            .get_indexing_options()
+            .map(|text_options| text_options.tokenizer() == QuickwitTextTokenizer::Raw.get_name())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Lists the indexed text fields of the schema using the `raw` tokenizer.
+fn list_keyword_field_names(schema: &Schema) -> impl Iterator<Item = String> + '_ {
+    schema
+        .fields()
+        .filter(|(_, field_entry)| is_raw_text_field(field_entry))
+        .map(|(_, field_entry)| field_entry.name().to_string())
+}
+
+fn list_dynamic_all_source_fields_for_node(node: &MappingNode) -> Vec<Field> {
+    node.children()
+        .flat_map(list_dynamic_all_source_fields)
+        .collect()
+}
+
+fn list_dynamic_all_source_fields(field_mappings: &MappingTree) -> Vec<Field> {
+    match field_mappings {
+        MappingTree::Leaf(leaf) => match leaf.get_type() {
+            LeafType::Text(options) if options.include_in_all => vec![leaf.field()],
+            LeafType::Json(options) if options.include_in_all => vec![leaf.field()],
+            _ => Vec::new(),
+        },
+        MappingTree::Node(node) => list_dynamic_all_source_fields_for_node(node),
+    }
+}
+
+/// Appends the string values held by `value`, including the ones nested in JSON objects, to
+/// `string_values`.
+fn collect_string_values(value: &Value, string_values: &mut Vec<String>) {
+    match value {
+        Value::Str(text) => string_values.push(text.clone()),
+        Value::JsonObject(json_obj) => {
+            for json_value in json_obj.values() {
+                collect_json_string_values(json_value, string_values);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_json_string_values(json_value: &JsonValue, string_values: &mut Vec<String>) {
+    match json_value {
+        JsonValue::String(text) => string_values.push(text.clone()),
+        JsonValue::Array(json_values) => {
+            for json_value in json_values {
+                collect_json_string_values(json_value, string_values);
+            }
+        }
+        JsonValue::Object(json_obj) => {
+            for json_value in json_obj.values() {
+                collect_json_string_values(json_value, string_values);
+            }
+        }
+        _ => {}
+    }
+}
+
 fn list_required_fields_for_node(node: &MappingNode) -> Vec<Field> {
     node.children().flat_map(list_required_fields).collect()
 }
@@ -269,6 +398,25 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             None
         };
 
+        let dynamic_all_field = if builder.dynamic_all {
+            let text_field_indexing = TextFieldIndexing::default()
+                .set_tokenizer(QuickwitTextTokenizer::Default.get_name())
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+            let text_options = TextOptions::default().set_indexing_options(text_field_indexing);
+            Some(schema_builder.add_text_field(DYNAMIC_ALL_FIELD_NAME, text_options))
+        } else {
+            None
+        };
+        let mut dynamic_all_source_fields = Vec::new();
+        if dynamic_all_field.is_some() {
+            dynamic_all_source_fields = list_dynamic_all_source_fields_for_node(&field_mappings);
+            if let (Mode::Dynamic(json_options), Some(dynamic_field)) = (&mode, dynamic_field) {
+                if json_options.include_in_all {
+                    dynamic_all_source_fields.push(dynamic_field);
+                }
+            }
+        }
+
         let schema = schema_builder.build();
 
         // validate fast fields
@@ -301,6 +449,10 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             tag_field_names.insert(tag_field_name.clone());
         }
 
+        let bloom_filter_field_names =
+            validate_bloom_filter_fields(&builder.bloom_filter_fields, &schema)?;
+        let warmup_field_names = validate_warmup_fields(&builder.warmup_fields, &schema)?;
+
         let required_fields = list_required_fields_for_node(&field_mappings);
         let partition_key = RoutingExpr::new(&builder.partition_key, builder.max_num_partitions)
             .context("Failed to interpret the partition key.")?;
@@ -308,11 +460,16 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             schema,
             source_field,
             dynamic_field,
+            dynamic_all_field,
+            dynamic_all_source_fields,
             default_search_field_names,
             timestamp_field_name: builder.timestamp_field,
             sort_by,
             field_mappings,
             tag_field_names,
+            tag_keyword_fields: builder.tag_keyword_fields,
+            bloom_filter_field_names,
+            warmup_field_names,
             required_fields,
             partition_key,
             mode,
@@ -344,6 +501,13 @@ impl From<DefaultDocMapper> for DefaultDocMapperBuilder {
             field_mappings: default_doc_mapper.field_mappings.into(),
             sort_by: sort_by_config,
             tag_fields: default_doc_mapper.tag_field_names.into_iter().collect(),
+            tag_keyword_fields: default_doc_mapper.tag_keyword_fields,
+            bloom_filter_fields: default_doc_mapper
+                .bloom_filter_field_names
+                .into_iter()
+                .collect(),
+            warmup_fields: default_doc_mapper.warmup_field_names.into_iter().collect(),
+            dynamic_all: default_doc_mapper.dynamic_all_field.is_some(),
             default_search_fields: default_doc_mapper.default_search_field_names,
             mode,
             dynamic_mapping,
@@ -363,6 +527,7 @@ impl std::fmt::Debug for DefaultDocMapper {
                 &self.default_search_field_names,
             )
             .field("timestamp_field_name", &self.timestamp_field_name())
+            .field("tag_field_names", &self.tag_field_names())
             // TODO: complete it.
             .finish()
     }
@@ -394,9 +559,16 @@ fn extract_single_obj(
 #[typetag::serde(name = "default")]
 impl DocMapper for DefaultDocMapper {
     fn doc_from_json(&self, doc_json: String) -> Result<(Partition, Document), DocParsingError> {
-        let json_obj: serde_json::Map<String, JsonValue> = serde_json::from_str(&doc_json)
+        self.doc_from_json_bytes(doc_json.as_bytes())
+    }
+
+    fn doc_from_json_bytes(
+        &self,
+        doc_json: &[u8],
+    ) -> Result<(Partition, Document), DocParsingError> {
+        let json_obj: serde_json::Map<String, JsonValue> = serde_json::from_slice(doc_json)
             .map_err(|_| {
-                let doc_json_sample = doc_json.chars().take(20).collect();
+                let doc_json_sample = String::from_utf8_lossy(doc_json).chars().take(20).collect();
                 DocParsingError::NotJsonObject(doc_json_sample)
             })?;
 
@@ -425,6 +597,21 @@ impl DocMapper for DefaultDocMapper {
             }
         }
 
+        if let Some(dynamic_all_field) = self.dynamic_all_field {
+            let mut string_values = Vec::new();
+            for field_value in document.field_values() {
+                if self
+                    .dynamic_all_source_fields
+                    .contains(&field_value.field())
+                {
+                    collect_string_values(field_value.value(), &mut string_values);
+                }
+            }
+            for string_value in string_values {
+                document.add_text(dynamic_all_field, string_value);
+            }
+        }
+
         self.check_missing_required_fields(&document)?;
         Ok((partition, document))
     }
@@ -460,6 +647,11 @@ impl DocMapper for DefaultDocMapper {
                 tantivy_default_search_field_names.push(DYNAMIC_FIELD_NAME.to_string());
             }
         }
+        // The `_dynamic_all` field only stands in for the default search fields when none are
+        // configured.
+        if self.dynamic_all_field.is_some() && self.default_search_field_names.is_empty() {
+            tantivy_default_search_field_names.push(DYNAMIC_ALL_FIELD_NAME.to_string());
+        }
         build_query(split_schema, request, &tantivy_default_search_field_names)
     }
 
@@ -475,8 +667,24 @@ impl DocMapper for DefaultDocMapper {
         self.sort_by.clone()
     }
 
+    fn max_num_partitions(&self) -> NonZeroU64 {
+        self.partition_key.max_num_partitions()
+    }
+
     fn tag_field_names(&self) -> BTreeSet<String> {
-        self.tag_field_names.clone()
+        let mut tag_field_names = self.tag_field_names.clone();
+        if self.tag_keyword_fields {
+            tag_field_names.extend(list_keyword_field_names(&self.schema));
+        }
+        tag_field_names
+    }
+
+    fn bloom_filter_field_names(&self) -> BTreeSet<String> {
+        self.bloom_filter_field_names.clone()
+    }
+
+    fn warmup_field_names(&self) -> BTreeSet<String> {
+        self.warmup_field_names.clone()
     }
 }
 
@@ -490,8 +698,8 @@ mod tests {
 
     use super::DefaultDocMapper;
     use crate::{
-        DefaultDocMapperBuilder, DocMapper, DocParsingError, SortBy, SortOrder, DYNAMIC_FIELD_NAME,
-        SOURCE_FIELD_NAME,
+        DefaultDocMapperBuilder, DocMapper, DocParsingError, SortBy, SortOrder,
+        DYNAMIC_ALL_FIELD_NAME, DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME,
     };
 
     fn example_json_doc_value() -> serde_json::Value {
@@ -582,6 +790,21 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_parsing_document_from_bytes() {
+        let doc_mapper = crate::default_doc_mapper_for_test();
+        let json_doc = example_json_doc_value().to_string();
+        let (partition, document) = doc_mapper.doc_from_json_bytes(json_doc.as_bytes()).unwrap();
+        let (expected_partition, expected_document) = doc_mapper.doc_from_json(json_doc).unwrap();
+        assert_eq!(partition, expected_partition);
+        assert_eq!(document, expected_document);
+
+        let error = doc_mapper
+            .doc_from_json_bytes(b"\xFF{\"body\": \"invalid utf-8\"}")
+            .unwrap_err();
+        assert!(matches!(error, DocParsingError::NotJsonObject(_)));
+    }
+
     #[test]
     fn test_accept_parsing_document_with_unknown_fields_and_missing_fields() {
         let doc_mapper = crate::default_doc_mapper_for_test();
@@ -814,6 +1037,199 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_build_doc_mapper_with_tag_keyword_fields() {
+        let doc_mapper = r#"{
+            "tag_fields": ["severity"],
+            "tag_keyword_fields": true,
+            "field_mappings": [
+                {
+                    "name": "tenant_id",
+                    "type": "text",
+                    "tokenizer": "raw"
+                },
+                {
+                    "name": "severity",
+                    "type": "u64"
+                },
+                {
+                    "name": "body",
+                    "type": "text"
+                },
+                {
+                    "name": "resource",
+                    "type": "object",
+                    "field_mappings": [
+                        {
+                            "name": "service",
+                            "type": "text",
+                            "tokenizer": "raw"
+                        },
+                        {
+                            "name": "version",
+                            "type": "text",
+                            "indexed": false
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper).unwrap();
+        assert!(builder.tag_keyword_fields);
+        let doc_mapper = builder.try_build().unwrap();
+        assert_eq!(
+            doc_mapper.tag_field_names().into_iter().collect::<Vec<_>>(),
+            ["resource.service", "severity", "tenant_id"]
+        );
+        let tag_named_fields = doc_mapper.tag_named_fields().unwrap();
+        assert_eq!(tag_named_fields.len(), 3);
+
+        let builder = DefaultDocMapperBuilder::from(doc_mapper);
+        assert!(builder.tag_keyword_fields);
+        assert_eq!(builder.tag_fields, ["severity"]);
+    }
+
+    #[test]
+    fn test_build_doc_mapper_with_bloom_filter_fields() {
+        let doc_mapper_json = |bloom_filter_fields: &str| {
+            format!(
+                r#"{{
+                    "bloom_filter_fields": {},
+                    "field_mappings": [
+                        {{
+                            "name": "trace_id",
+                            "type": "text",
+                            "tokenizer": "raw"
+                        }},
+                        {{
+                            "name": "body",
+                            "type": "text"
+                        }},
+                        {{
+                            "name": "status",
+                            "type": "u64"
+                        }}
+                    ]
+                }}"#,
+                bloom_filter_fields
+            )
+        };
+        let doc_mapper =
+            serde_json::from_str::<DefaultDocMapperBuilder>(&doc_mapper_json(r#"["trace_id"]"#))
+                .unwrap()
+                .try_build()
+                .unwrap();
+        assert_eq!(
+            doc_mapper
+                .bloom_filter_field_names()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            ["trace_id"]
+        );
+        assert_eq!(doc_mapper.bloom_filter_named_fields().unwrap().len(), 1);
+        assert_eq!(
+            DefaultDocMapperBuilder::from(doc_mapper).bloom_filter_fields,
+            ["trace_id"]
+        );
+
+        for (bloom_filter_fields, expected_error) in [
+            (
+                r#"["trace_id", "trace_id"]"#,
+                "Duplicated bloom filter field: `trace_id`",
+            ),
+            (r#"["span_id"]"#, "Unknown bloom filter field: `span_id`"),
+            (
+                r#"["body"]"#,
+                "Bloom filters are only allowed on indexed text fields with the `raw` tokenizer \
+                 (`body`).",
+            ),
+            (
+                r#"["status"]"#,
+                "Bloom filters are only allowed on indexed text fields with the `raw` tokenizer \
+                 (`status`).",
+            ),
+        ] {
+            let error = serde_json::from_str::<DefaultDocMapperBuilder>(&doc_mapper_json(
+                bloom_filter_fields,
+            ))
+            .unwrap()
+            .try_build()
+            .unwrap_err();
+            assert_eq!(error.to_string(), expected_error);
+        }
+    }
+
+    #[test]
+    fn test_build_doc_mapper_with_warmup_fields() {
+        let doc_mapper_json = |warmup_fields: &str| {
+            format!(
+                r#"{{
+                    "warmup_fields": {},
+                    "field_mappings": [
+                        {{
+                            "name": "body",
+                            "type": "text"
+                        }},
+                        {{
+                            "name": "status",
+                            "type": "u64",
+                            "indexed": false,
+                            "fast": true
+                        }},
+                        {{
+                            "name": "timestamp",
+                            "type": "i64",
+                            "fast": true
+                        }},
+                        {{
+                            "name": "payload",
+                            "type": "text",
+                            "indexed": false
+                        }}
+                    ]
+                }}"#,
+                warmup_fields
+            )
+        };
+        let doc_mapper = serde_json::from_str::<DefaultDocMapperBuilder>(&doc_mapper_json(
+            r#"["timestamp", "status"]"#,
+        ))
+        .unwrap()
+        .try_build()
+        .unwrap();
+        assert_eq!(
+            doc_mapper
+                .warmup_field_names()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            ["status", "timestamp"]
+        );
+        assert_eq!(
+            DefaultDocMapperBuilder::from(doc_mapper).warmup_fields,
+            ["status", "timestamp"]
+        );
+
+        for (warmup_fields, expected_error) in [
+            (
+                r#"["status", "status"]"#,
+                "Duplicated warmup field: `status`",
+            ),
+            (r#"["severity"]"#, "Unknown warmup field: `severity`"),
+            (r#"["body"]"#, "Warmup fields must be fast fields (`body`)."),
+            (
+                r#"["payload"]"#,
+                "Warmup fields must be fast fields (`payload`).",
+            ),
+        ] {
+            let error =
+                serde_json::from_str::<DefaultDocMapperBuilder>(&doc_mapper_json(warmup_fields))
+                    .unwrap()
+                    .try_build()
+                    .unwrap_err();
+            assert_eq!(error.to_string(), expected_error);
+        }
+    }
+
     #[test]
     fn test_fail_to_build_doc_mapper_with_wrong_tag_fields_types() -> anyhow::Result<()> {
         let doc_mapper_one = r#"{
@@ -1278,4 +1694,117 @@ mod tests {
             r#"TermQuery(Term(type=Str, field=1, "toto"))"#
         );
     }
+
+    #[test]
+    fn test_dynamic_all_field() {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {"name": "body", "type": "text"},
+                {"name": "level", "type": "text", "tokenizer": "raw", "include_in_all": false},
+                {"name": "status", "type": "u64"},
+                {"name": "attributes", "type": "json"}
+            ],
+            "mode": "dynamic",
+            "dynamic_all": true
+        }"#,
+        )
+        .unwrap();
+        let (_, doc) = doc_mapper
+            .doc_from_json(
+                r#"{
+                    "body": "hello world",
+                    "level": "INFO",
+                    "status": 200,
+                    "attributes": {"host": "server-1", "ports": [80, 443], "tags": ["a", "b"]},
+                    "unmapped": {"user": "alice"}
+                }"#
+                .to_string(),
+            )
+            .unwrap();
+        let dynamic_all_field = doc_mapper
+            .schema()
+            .get_field(DYNAMIC_ALL_FIELD_NAME)
+            .unwrap();
+        let mut string_values: Vec<&str> = doc
+            .get_all(dynamic_all_field)
+            .map(|value| value.as_text().unwrap())
+            .collect();
+        string_values.sort_unstable();
+        assert_eq!(
+            string_values,
+            ["a", "alice", "b", "hello world", "server-1"]
+        );
+        let expected_term_query = format!(
+            r#"TermQuery(Term(type=Str, field={}, "alice"))"#,
+            dynamic_all_field.field_id()
+        );
+        assert!(default_doc_mapper_query_aux(&doc_mapper, "alice")
+            .unwrap()
+            .contains(&expected_term_query));
+        let builder: DefaultDocMapperBuilder = doc_mapper.into();
+        assert!(builder.dynamic_all);
+    }
+
+    #[test]
+    fn test_dynamic_all_field_with_default_search_fields() {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {"name": "body", "type": "text"},
+                {"name": "title", "type": "text"}
+            ],
+            "default_search_fields": ["body"],
+            "dynamic_all": true
+        }"#,
+        )
+        .unwrap();
+        let schema = doc_mapper.schema();
+        let body_field = schema.get_field("body").unwrap();
+        let dynamic_all_field = schema.get_field(DYNAMIC_ALL_FIELD_NAME).unwrap();
+        let query = default_doc_mapper_query_aux(&doc_mapper, "alice").unwrap();
+        assert!(query.contains(&format!(
+            r#"TermQuery(Term(type=Str, field={}, "alice"))"#,
+            body_field.field_id()
+        )));
+        assert!(!query.contains(&format!("field={},", dynamic_all_field.field_id())));
+
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {"name": "body", "type": "text"},
+                {"name": "title", "type": "text"}
+            ],
+            "default_search_fields": ["body", "_dynamic_all"],
+            "dynamic_all": true
+        }"#,
+        )
+        .unwrap();
+        let dynamic_all_field = doc_mapper
+            .schema()
+            .get_field(DYNAMIC_ALL_FIELD_NAME)
+            .unwrap();
+        let query = default_doc_mapper_query_aux(&doc_mapper, "alice").unwrap();
+        assert!(query.contains(&format!(
+            r#"TermQuery(Term(type=Str, field={}, "alice"))"#,
+            dynamic_all_field.field_id()
+        )));
+    }
+
+    #[test]
+    fn test_dynamic_all_field_disabled_by_default() {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [{"name": "body", "type": "text"}]
+        }"#,
+        )
+        .unwrap();
+        assert!(doc_mapper
+            .schema()
+            .get_field(DYNAMIC_ALL_FIELD_NAME)
+            .is_none());
+        assert!(default_doc_mapper_query_aux(&doc_mapper, "hello")
+            .unwrap_err()
+            .contains("No default field declared"));
+    }
 }
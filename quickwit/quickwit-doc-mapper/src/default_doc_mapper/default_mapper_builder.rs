@@ -56,6 +56,21 @@ pub struct DefaultDocMapperBuilder {
     /// Name of the fields that are tagged.
     #[serde(default)]
     pub tag_fields: Vec<String>,
+    /// Whether all the text fields using the `raw` tokenizer are tagged, in addition to
+    /// the fields listed in `tag_fields`.
+    #[serde(default)]
+    pub tag_keyword_fields: bool,
+    /// Name of the fields for which a bloom filter is stored alongside each split.
+    #[serde(default)]
+    pub bloom_filter_fields: Vec<String>,
+    /// Name of the fields whose fast field data and term dictionary are warmed up when a split
+    /// is first opened by a searcher.
+    #[serde(default)]
+    pub warmup_fields: Vec<String>,
+    /// Whether all the string values of the documents are indexed in the `_dynamic_all` field,
+    /// which is then searched by default.
+    #[serde(default)]
+    pub dynamic_all: bool,
     /// The partition key is a DSL used to route documents
     /// into specific splits.
     #[serde(default)]
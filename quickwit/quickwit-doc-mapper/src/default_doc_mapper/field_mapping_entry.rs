@@ -26,7 +26,7 @@ use tantivy::schema::{
 };
 
 use super::date_time_type::QuickwitDateTimeOptions;
-use super::{default_as_true, FieldMappingType};
+use super::{default_as_true, is_true, FieldMappingType};
 use crate::default_doc_mapper::field_mapping_type::QuickwitFieldType;
 use crate::default_doc_mapper::validate_field_mapping_name;
 
@@ -160,6 +160,9 @@ pub struct QuickwitTextOptions {
     pub stored: bool,
     #[serde(default)]
     pub fast: bool,
+    #[serde(default = "default_as_true")]
+    #[serde(skip_serializing_if = "is_true")]
+    pub include_in_all: bool,
 }
 
 impl Default for QuickwitTextOptions {
@@ -172,6 +175,7 @@ impl Default for QuickwitTextOptions {
             fieldnorms: false,
             stored: true,
             fast: false,
+            include_in_all: true,
         }
     }
 }
@@ -232,6 +236,11 @@ pub struct QuickwitJsonOptions {
     /// If true, the field will be stored in the doc store.
     #[serde(default = "default_as_true")]
     pub stored: bool,
+    /// If true, the string values of the json object are copied in the `_dynamic_all` field
+    /// when `dynamic_all` is enabled.
+    #[serde(default = "default_as_true")]
+    #[serde(skip_serializing_if = "is_true")]
+    pub include_in_all: bool,
 }
 
 impl Default for QuickwitJsonOptions {
@@ -242,6 +251,7 @@ impl Default for QuickwitJsonOptions {
             tokenizer: None,
             record: None,
             stored: true,
+            include_in_all: true,
         }
     }
 }
@@ -1038,6 +1048,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_text_mapping_excluded_from_all() {
+        let entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "my_field_name",
+                "type": "text",
+                "include_in_all": false
+            }
+            "#,
+        )
+        .unwrap();
+        match &entry.mapping_type {
+            FieldMappingType::Text(options, _) => assert!(!options.include_in_all),
+            _ => panic!("Expected a text mapping."),
+        }
+        let entry_deserser = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            entry_deserser,
+            json!({
+                "name": "my_field_name",
+                "type": "text",
+                "fast": false,
+                "stored": true,
+                "indexed": true,
+                "fieldnorms": false,
+                "include_in_all": false,
+            })
+        );
+    }
+
     #[test]
     fn test_parse_text_mapping_multivalued() {
         let entry = serde_json::from_str::<FieldMappingEntry>(
@@ -1202,6 +1243,7 @@ mod tests {
             tokenizer: None,
             record: None,
             stored: true,
+            include_in_all: true,
         };
         assert_eq!(&field_mapping_entry.name, "my_json_field");
         assert!(
@@ -1241,6 +1283,7 @@ mod tests {
             tokenizer: Some(QuickwitTextTokenizer::Raw),
             record: None,
             stored: false,
+            include_in_all: true,
         };
         assert_eq!(&field_mapping_entry.name, "my_json_field_multi");
         assert!(
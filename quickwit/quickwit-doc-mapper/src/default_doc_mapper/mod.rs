@@ -30,6 +30,7 @@ use anyhow::bail;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+pub use self::date_time_format::StrptimeParser;
 pub use self::default_mapper::{DefaultDocMapper, SortByConfig};
 pub use self::default_mapper_builder::{DefaultDocMapperBuilder, ModeType};
 pub use self::field_mapping_entry::{
@@ -89,6 +90,11 @@ fn default_as_true() -> bool {
     true
 }
 
+/// Function used with serde to skip serializing boolean values that are true by default.
+fn is_true(val: &bool) -> bool {
+    *val
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -19,6 +19,7 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
+use std::num::NonZeroU64;
 
 use anyhow::Context;
 use dyn_clone::{clone_trait_object, DynClone};
@@ -47,6 +48,13 @@ pub trait DocMapper: Send + Sync + Debug + DynClone + 'static {
     /// (we pass by value here, as the value can be used as is in the _source field.)
     fn doc_from_json(&self, doc_json: String) -> Result<(Partition, Document), DocParsingError>;
 
+    /// Returns the document built from a JSON payload given as raw bytes, which spares the
+    /// allocation of a string when the payload is shared by the ingest pipeline.
+    fn doc_from_json_bytes(
+        &self,
+        doc_json: &[u8],
+    ) -> Result<(Partition, Document), DocParsingError>;
+
     /// Converts a tantivy named Document to the json format.
     ///
     /// Tantivy does not have any notion of cardinality nor object.
@@ -96,6 +104,12 @@ pub trait DocMapper: Send + Sync + Debug + DynClone + 'static {
         None
     }
 
+    /// Returns the maximum number of partitions the documents are routed to. It is one if the
+    /// documents are not partitioned.
+    fn max_num_partitions(&self) -> NonZeroU64 {
+        NonZeroU64::new(1).unwrap()
+    }
+
     /// Returns the tag field names
     fn tag_field_names(&self) -> BTreeSet<String> {
         Default::default()
@@ -104,21 +118,43 @@ pub trait DocMapper: Send + Sync + Debug + DynClone + 'static {
     /// Returns the tag `NameField`s on the current schema.
     /// Returns an error if a tag field is not found in this schema.
     fn tag_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
-        let index_schema = self.schema();
-        self.tag_field_names()
-            .iter()
-            .map(|field_name| {
-                index_schema
-                    .get_field(field_name)
-                    .context(format!("Field `{}` must exist in the schema.", field_name))
-                    .map(|field| NamedField {
-                        name: field_name.clone(),
-                        field,
-                        field_type: index_schema.get_field_entry(field).field_type().clone(),
-                    })
-            })
-            .collect::<Result<Vec<_>, _>>()
+        resolve_named_fields(&self.schema(), &self.tag_field_names())
+    }
+
+    /// Returns the names of the fields for which a bloom filter is built for each split.
+    fn bloom_filter_field_names(&self) -> BTreeSet<String> {
+        Default::default()
     }
+
+    /// Returns the names of the fields warmed up when a split is first opened by a searcher.
+    fn warmup_field_names(&self) -> BTreeSet<String> {
+        Default::default()
+    }
+
+    /// Returns the bloom filter `NameField`s on the current schema.
+    /// Returns an error if a bloom filter field is not found in this schema.
+    fn bloom_filter_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
+        resolve_named_fields(&self.schema(), &self.bloom_filter_field_names())
+    }
+}
+
+fn resolve_named_fields(
+    index_schema: &Schema,
+    field_names: &BTreeSet<String>,
+) -> anyhow::Result<Vec<NamedField>> {
+    field_names
+        .iter()
+        .map(|field_name| {
+            index_schema
+                .get_field(field_name)
+                .context(format!("Field `{}` must exist in the schema.", field_name))
+                .map(|field| NamedField {
+                    name: field_name.clone(),
+                    field,
+                    field_type: index_schema.get_field_entry(field).field_type().clone(),
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()
 }
 
 /// A struct to wrap a tantivy field with its name.
@@ -228,6 +264,7 @@ mod tests {
             query: "json_field.toto.titi:hello".to_string(),
             search_fields: vec![],
             snippet_fields: vec![],
+            fetch_fields: Vec::new(),
             start_timestamp: None,
             end_timestamp: None,
             max_hits: 10,
@@ -235,6 +272,9 @@ mod tests {
             sort_order: None,
             sort_by_field: None,
             aggregation_request: None,
+            debug: false,
+            role: None,
+            query_ast: None,
         };
         let query = doc_mapper.query(schema, &search_request).unwrap();
         assert_eq!(
@@ -265,6 +305,7 @@ mod tests {
             query: "text_field:hello".to_string(),
             search_fields: vec![],
             snippet_fields: vec![],
+            fetch_fields: Vec::new(),
             start_timestamp: None,
             end_timestamp: None,
             max_hits: 10,
@@ -272,6 +313,9 @@ mod tests {
             sort_order: None,
             sort_by_field: Some("text_field".to_string()),
             aggregation_request: None,
+            debug: false,
+            role: None,
+            query_ast: None,
         };
         let query = doc_mapper.query(schema, &search_request).unwrap_err();
         assert_eq!(
@@ -300,6 +344,7 @@ mod tests {
             query: "toto.titi:hello".to_string(),
             search_fields: vec![],
             snippet_fields: vec![],
+            fetch_fields: Vec::new(),
             start_timestamp: None,
             end_timestamp: None,
             max_hits: 10,
@@ -307,6 +352,9 @@ mod tests {
             sort_order: None,
             sort_by_field: None,
             aggregation_request: None,
+            debug: false,
+            role: None,
+            query_ast: None,
         };
         let query = doc_mapper.query(schema, &search_request).unwrap();
         assert_eq!(
@@ -335,6 +383,7 @@ mod tests {
             query: "toto:5".to_string(),
             search_fields: vec![],
             snippet_fields: vec![],
+            fetch_fields: Vec::new(),
             start_timestamp: None,
             end_timestamp: None,
             max_hits: 10,
@@ -342,6 +391,9 @@ mod tests {
             sort_order: None,
             sort_by_field: None,
             aggregation_request: None,
+            debug: false,
+            role: None,
+            query_ast: None,
         };
         let query = doc_mapper.query(schema, &search_request).unwrap();
         assert_eq!(
@@ -0,0 +1,336 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Context};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde_json::Value as JsonValue;
+
+/// Maximum nesting depth of pattern references, which guards against cyclic definitions.
+const MAX_PATTERN_DEPTH: usize = 16;
+
+/// Library of named patterns available in grok patterns, adapted from the Logstash patterns to
+/// the syntax of the `regex` crate.
+static BUILTIN_PATTERNS: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
+    HashMap::from_iter([
+        // Base patterns.
+        ("USERNAME", r"[a-zA-Z0-9._-]+"),
+        ("USER", r"%{USERNAME}"),
+        ("INT", r"[+-]?[0-9]+"),
+        ("BASE10NUM", r"[+-]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)"),
+        ("NUMBER", r"%{BASE10NUM}"),
+        ("POSINT", r"[1-9][0-9]*"),
+        ("NONNEGINT", r"[0-9]+"),
+        ("WORD", r"\b\w+\b"),
+        ("NOTSPACE", r"\S+"),
+        ("SPACE", r"\s*"),
+        ("DATA", r".*?"),
+        ("GREEDYDATA", r".*"),
+        ("QUOTEDSTRING", r#""(?:[^"\\]|\\.)*""#),
+        ("QS", r"%{QUOTEDSTRING}"),
+        (
+            "UUID",
+            r"[A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}",
+        ),
+        (
+            "LOGLEVEL",
+            r"(?i:trace|debug|info|notice|warn(?:ing)?|err(?:or)?|crit(?:ical)?|fatal|severe|emerg(?:ency)?|alert)",
+        ),
+        // Networking.
+        (
+            "IPV4",
+            r"(?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)",
+        ),
+        (
+            "IPV6",
+            r"[0-9A-Fa-f]{0,4}(?::[0-9A-Fa-f]{0,4}){2,7}(?:%\S+)?",
+        ),
+        ("IP", r"(?:%{IPV4}|%{IPV6})"),
+        (
+            "HOSTNAME",
+            r"\b[0-9A-Za-z][0-9A-Za-z-]{0,62}(?:\.[0-9A-Za-z][0-9A-Za-z-]{0,62})*\.?\b",
+        ),
+        ("IPORHOST", r"(?:%{IP}|%{HOSTNAME})"),
+        ("HOSTPORT", r"%{IPORHOST}:%{POSINT}"),
+        ("URIPATH", r"(?:/[^\s?#]*)+"),
+        ("URIPARAM", r"\?\S*"),
+        ("URIPATHPARAM", r"%{URIPATH}(?:%{URIPARAM})?"),
+        // Dates and times.
+        (
+            "MONTH",
+            r"\b(?:[Jj]an(?:uary)?|[Ff]eb(?:ruary)?|[Mm]ar(?:ch)?|[Aa]pr(?:il)?|[Mm]ay|[Jj]un(?:e)?|[Jj]ul(?:y)?|[Aa]ug(?:ust)?|[Ss]ep(?:tember)?|[Oo]ct(?:ober)?|[Nn]ov(?:ember)?|[Dd]ec(?:ember)?)\b",
+        ),
+        ("MONTHNUM", r"(?:0?[1-9]|1[0-2])"),
+        ("MONTHDAY", r"(?:0[1-9]|[12][0-9]|3[01]|[1-9])"),
+        ("YEAR", r"(?:\d{4}|\d{2})"),
+        ("HOUR", r"(?:2[0123]|[01]?[0-9])"),
+        ("MINUTE", r"(?:[0-5][0-9])"),
+        ("SECOND", r"(?:(?:[0-5]?[0-9]|60)(?:[:.,][0-9]+)?)"),
+        ("TIME", r"%{HOUR}:%{MINUTE}(?::%{SECOND})?"),
+        ("ISO8601_TIMEZONE", r"(?:Z|[+-]%{HOUR}(?::?%{MINUTE}))"),
+        (
+            "TIMESTAMP_ISO8601",
+            r"%{YEAR}-%{MONTHNUM}-%{MONTHDAY}[T ]%{HOUR}:?%{MINUTE}(?::?%{SECOND})?%{ISO8601_TIMEZONE}?",
+        ),
+        ("HTTPDATE", r"%{MONTHDAY}/%{MONTH}/%{YEAR}:%{TIME} %{INT}"),
+        ("SYSLOGTIMESTAMP", r"%{MONTH} +%{MONTHDAY} %{TIME}"),
+        // Syslog.
+        ("PROG", r"[\x21-\x5a\x5c\x5e-\x7e]+"),
+        ("SYSLOGPROG", r"%{PROG:program}(?:\[%{POSINT:pid:int}\])?"),
+        ("SYSLOGHOST", r"%{IPORHOST}"),
+        (
+            "SYSLOGBASE",
+            r"%{SYSLOGTIMESTAMP:timestamp} (?:%{SYSLOGHOST:logsource} )?%{SYSLOGPROG}:",
+        ),
+        ("SYSLOGLINE", r"%{SYSLOGBASE} %{GREEDYDATA:message}"),
+        // Apache.
+        (
+            "COMMONAPACHELOG",
+            r#"%{IPORHOST:clientip} %{USER:ident} %{USER:auth} \[%{HTTPDATE:timestamp}\] "(?:%{WORD:verb} %{NOTSPACE:request}(?: HTTP/%{NUMBER:httpversion})?|%{DATA:rawrequest})" %{NUMBER:response:int} (?:%{NUMBER:bytes:int}|-)"#,
+        ),
+        (
+            "COMBINEDAPACHELOG",
+            r"%{COMMONAPACHELOG} %{QS:referrer} %{QS:agent}",
+        ),
+        // Nginx.
+        (
+            "NGINXACCESS",
+            r#"%{IPORHOST:remote_addr} - %{USER:remote_user} \[%{HTTPDATE:time_local}\] "%{WORD:method} %{NOTSPACE:request} HTTP/%{NUMBER:http_version}" %{INT:status:int} %{INT:body_bytes_sent:int} %{QS:http_referer} %{QS:http_user_agent}"#,
+        ),
+    ])
+});
+
+/// Matches the pattern references `%{NAME}`, `%{NAME:field}`, and `%{NAME:field:type}`.
+static PATTERN_REFERENCE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"%\{(\w+)(?::([\w.@-]+))?(?::(int|float))?\}")
+        .expect("The pattern reference regex should compile.")
+});
+
+#[derive(Clone, Copy, Debug)]
+enum Conversion {
+    String,
+    Int,
+    Float,
+}
+
+#[derive(Clone, Debug)]
+struct FieldCapture {
+    group_name: String,
+    field_name: String,
+    conversion: Conversion,
+}
+
+/// A compiled grok pattern: a regular expression in which `%{NAME:field:type}` references a
+/// named pattern and captures the text it matches into `field`, optionally converted to an
+/// `int` or a `float`.
+#[derive(Clone, Debug)]
+pub struct GrokPattern {
+    regex: Regex,
+    field_captures: Vec<FieldCapture>,
+}
+
+impl GrokPattern {
+    /// Compiles the grok pattern. The named patterns are looked up in `pattern_definitions`
+    /// first, then in the library of built-in patterns.
+    pub fn new(
+        pattern: &str,
+        pattern_definitions: &BTreeMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        let mut field_captures = Vec::new();
+        let regex_str = expand_pattern(pattern, pattern_definitions, &mut field_captures, 0)?;
+        let regex = Regex::new(&regex_str)
+            .with_context(|| format!("Failed to compile grok pattern `{pattern}`."))?;
+        Ok(Self {
+            regex,
+            field_captures,
+        })
+    }
+
+    /// Returns the fields captured from the text, or `None` if the text does not match the
+    /// pattern. Values that cannot be converted to the requested type are kept as strings.
+    pub fn captures(&self, text: &str) -> Option<Vec<(&str, JsonValue)>> {
+        let captures: Captures = self.regex.captures(text)?;
+        let fields = self
+            .field_captures
+            .iter()
+            .filter_map(|field_capture| {
+                let value = captures.name(&field_capture.group_name)?.as_str();
+                let json_value = match field_capture.conversion {
+                    Conversion::String => None,
+                    Conversion::Int => value.parse::<i64>().ok().map(JsonValue::from),
+                    Conversion::Float => value.parse::<f64>().ok().map(JsonValue::from),
+                }
+                .unwrap_or_else(|| JsonValue::from(value));
+                Some((field_capture.field_name.as_str(), json_value))
+            })
+            .collect();
+        Some(fields)
+    }
+}
+
+fn expand_pattern(
+    pattern: &str,
+    pattern_definitions: &BTreeMap<String, String>,
+    field_captures: &mut Vec<FieldCapture>,
+    depth: usize,
+) -> anyhow::Result<String> {
+    if depth > MAX_PATTERN_DEPTH {
+        bail!("Grok pattern references are nested more than {MAX_PATTERN_DEPTH} levels deep.");
+    }
+    let mut expanded_pattern = String::with_capacity(pattern.len());
+    let mut last_end = 0;
+    for reference in PATTERN_REFERENCE_REGEX.captures_iter(pattern) {
+        let reference_match = reference.get(0).expect("Group 0 always exists.");
+        let pattern_name = &reference[1];
+        let referenced_pattern = pattern_definitions
+            .get(pattern_name)
+            .map(String::as_str)
+            .or_else(|| BUILTIN_PATTERNS.get(pattern_name).copied())
+            .with_context(|| format!("Grok pattern `{pattern_name}` does not exist."))?;
+        let expanded_reference = expand_pattern(
+            referenced_pattern,
+            pattern_definitions,
+            field_captures,
+            depth + 1,
+        )?;
+        expanded_pattern.push_str(&pattern[last_end..reference_match.start()]);
+        if let Some(field_name) = reference.get(2) {
+            let conversion = match reference.get(3).map(|conversion| conversion.as_str()) {
+                Some("int") => Conversion::Int,
+                Some("float") => Conversion::Float,
+                _ => Conversion::String,
+            };
+            // Field names may contain dots, which are not allowed in group names.
+            let group_name = format!("grok{}", field_captures.len());
+            expanded_pattern.push_str(&format!("(?P<{group_name}>{expanded_reference})"));
+            field_captures.push(FieldCapture {
+                group_name,
+                field_name: field_name.as_str().to_string(),
+                conversion,
+            });
+        } else {
+            expanded_pattern.push_str(&format!("(?:{expanded_reference})"));
+        }
+        last_end = reference_match.end();
+    }
+    expanded_pattern.push_str(&pattern[last_end..]);
+    Ok(expanded_pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn captures(pattern: &str, text: &str) -> Option<JsonValue> {
+        let grok_pattern = GrokPattern::new(pattern, &BTreeMap::new()).unwrap();
+        grok_pattern.captures(text).map(|fields| {
+            JsonValue::Object(
+                fields
+                    .into_iter()
+                    .map(|(field_name, value)| (field_name.to_string(), value))
+                    .collect(),
+            )
+        })
+    }
+
+    #[test]
+    fn test_grok_pattern_builtin_patterns() {
+        assert_eq!(
+            captures(
+                "%{IP:client.ip} %{WORD:method} %{URIPATHPARAM:request} %{NUMBER:duration:float}",
+                "55.3.244.1 GET /index.html?lang=en 15.824"
+            ),
+            Some(json!({
+                "client.ip": "55.3.244.1",
+                "method": "GET",
+                "request": "/index.html?lang=en",
+                "duration": 15.824
+            }))
+        );
+        assert!(captures("%{IP:client} %{INT:status}", "localhost OK").is_none());
+    }
+
+    #[test]
+    fn test_grok_pattern_apache_and_nginx() {
+        let line = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 "http://www.example.com/start.html" "Mozilla/4.08""#;
+        assert_eq!(
+            captures("%{COMBINEDAPACHELOG}", line),
+            Some(json!({
+                "clientip": "127.0.0.1",
+                "ident": "-",
+                "auth": "frank",
+                "timestamp": "10/Oct/2000:13:55:36 -0700",
+                "verb": "GET",
+                "request": "/apache_pb.gif",
+                "httpversion": "1.0",
+                "response": 200,
+                "bytes": 2326,
+                "referrer": "\"http://www.example.com/start.html\"",
+                "agent": "\"Mozilla/4.08\""
+            }))
+        );
+        let fields = captures("%{NGINXACCESS}", line).unwrap();
+        assert_eq!(fields["remote_addr"], json!("127.0.0.1"));
+        assert_eq!(fields["status"], json!(200));
+        assert_eq!(fields["body_bytes_sent"], json!(2326));
+    }
+
+    #[test]
+    fn test_grok_pattern_syslog() {
+        assert_eq!(
+            captures(
+                "%{SYSLOGLINE}",
+                "Oct  5 13:12:11 my-host sshd[4242]: Accepted publickey for root"
+            ),
+            Some(json!({
+                "timestamp": "Oct  5 13:12:11",
+                "logsource": "my-host",
+                "program": "sshd",
+                "pid": 4242,
+                "message": "Accepted publickey for root"
+            }))
+        );
+    }
+
+    #[test]
+    fn test_grok_pattern_definitions() {
+        let pattern_definitions =
+            BTreeMap::from_iter([("TRACE_ID".to_string(), "[0-9a-f]{32}".to_string())]);
+        let grok_pattern =
+            GrokPattern::new("trace=%{TRACE_ID:trace_id}", &pattern_definitions).unwrap();
+        let fields = grok_pattern
+            .captures("trace=4bf92f3577b34da6a3ce929d0e0e4736")
+            .unwrap();
+        assert_eq!(
+            fields,
+            vec![("trace_id", json!("4bf92f3577b34da6a3ce929d0e0e4736"))]
+        );
+
+        let error = GrokPattern::new("%{UNKNOWN:field}", &BTreeMap::new()).unwrap_err();
+        assert_eq!(error.to_string(), "Grok pattern `UNKNOWN` does not exist.");
+
+        let pattern_definitions =
+            BTreeMap::from_iter([("CYCLE".to_string(), "a%{CYCLE}".to_string())]);
+        GrokPattern::new("%{CYCLE}", &pattern_definitions).unwrap_err();
+    }
+}
@@ -24,12 +24,16 @@
 //! to convert a json like documents to a document indexable by tantivy
 //! engine, aka tantivy::Document.
 
+/// Bloom filters used for split pruning.
+pub mod bloom_filter;
 mod default_doc_mapper;
 mod doc_mapper;
 mod error;
 
 #[allow(missing_docs)]
 pub mod fast_field_reader;
+mod grok;
+mod query_ast;
 mod query_builder;
 mod routing_expression;
 mod sort_by;
@@ -40,10 +44,13 @@ pub mod tag_pruning;
 
 pub use default_doc_mapper::{
     DefaultDocMapper, DefaultDocMapperBuilder, FieldMappingEntry, ModeType, QuickwitJsonOptions,
-    SortByConfig,
+    SortByConfig, StrptimeParser,
 };
 pub use doc_mapper::{DocMapper, NamedField};
 pub use error::{DocParsingError, QueryParserError};
+pub use grok::GrokPattern;
+pub use query_ast::{BoolQuery, QueryAst};
+pub use query_builder::{extract_query_fields, extract_search_request_query_fields, QueryFields};
 pub use sort_by::{SortBy, SortByField, SortOrder};
 pub use tokenizers::QUICKWIT_TOKENIZER_MANAGER;
 
@@ -53,6 +60,9 @@ pub const SOURCE_FIELD_NAME: &str = "_source";
 /// Field name reserved for storing the dynamically indexed fields.
 pub const DYNAMIC_FIELD_NAME: &str = "_dynamic";
 
+/// Field name reserved for indexing all the string values of a document.
+pub const DYNAMIC_ALL_FIELD_NAME: &str = "_dynamic_all";
+
 /// Returns a default `DefaultIndexConfig` for unit tests.
 #[cfg(any(test, feature = "testsuite"))]
 pub fn default_doc_mapper_for_test() -> DefaultDocMapper {
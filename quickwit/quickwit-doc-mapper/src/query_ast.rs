@@ -0,0 +1,626 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, Occur as TantivyOccur, Query, QueryParser,
+    QueryParserError as TantivyQueryParserError, TermQuery,
+};
+use tantivy::schema::{Field, FieldType, IndexRecordOption, Schema};
+use tantivy::Term;
+use tantivy_query_grammar::{Occur, UserInputAst, UserInputLeaf, UserInputLiteral};
+
+use crate::{QueryParserError, QUICKWIT_TOKENIZER_MANAGER};
+
+/// Characters that cannot appear in the field names of a query.
+const FORBIDDEN_FIELD_NAME_CHARS: [char; 8] = [':', '"', '(', ')', '[', ']', '{', '}'];
+
+/// Characters that cannot appear in the quoted phrases of the query language.
+const UNQUOTABLE_TEXT_CHARS: [char; 2] = ['"', '\\'];
+
+/// Abstract syntax tree of a search query.
+///
+/// Queries can be built programmatically, or parsed from the query language with
+/// [`QueryAst::parse`]. The tree is serialized to JSON with a `type` tag, e.g.
+/// `{"type": "term", "field": "severity", "text": "ERROR"}`, and this serialization is searched
+/// by setting it as the `query_ast` of a `SearchRequest`: the tantivy query is then built from
+/// the tree, so any text can be searched.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QueryAst {
+    /// Matches all the documents.
+    MatchAll,
+    /// Matches the documents containing the text in the field. If the text is made of several
+    /// tokens, they must appear in sequence.
+    Term {
+        /// Targeted field. If `None`, the default search fields of the index or of the request
+        /// are searched.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        field: Option<String>,
+        /// Searched text.
+        text: String,
+    },
+    /// Combines queries with boolean clauses.
+    Bool(BoolQuery),
+    /// Multiplies the score of the query by `boost`.
+    Boost {
+        /// Boosted query.
+        query: Box<QueryAst>,
+        /// Strictly positive boost factor.
+        boost: f64,
+    },
+}
+
+/// Boolean combination of queries: the matching documents match all the `must` queries, none of
+/// the `must_not` queries, and, if there are `should` queries, at least one of them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoolQuery {
+    /// Queries the documents must match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub must: Vec<QueryAst>,
+    /// Queries the documents must match at least one of.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub should: Vec<QueryAst>,
+    /// Queries the documents must not match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub must_not: Vec<QueryAst>,
+}
+
+impl BoolQuery {
+    /// Adds a query the documents must match.
+    pub fn must(mut self, query: QueryAst) -> Self {
+        self.must.push(query);
+        self
+    }
+
+    /// Adds a query the documents must match unless they match another `should` query.
+    pub fn should(mut self, query: QueryAst) -> Self {
+        self.should.push(query);
+        self
+    }
+
+    /// Adds a query the documents must not match.
+    pub fn must_not(mut self, query: QueryAst) -> Self {
+        self.must_not.push(query);
+        self
+    }
+}
+
+impl From<BoolQuery> for QueryAst {
+    fn from(bool_query: BoolQuery) -> Self {
+        QueryAst::Bool(bool_query)
+    }
+}
+
+impl QueryAst {
+    /// Builds a query matching all the documents.
+    pub fn match_all() -> Self {
+        QueryAst::MatchAll
+    }
+
+    /// Builds a query searching the text in the default search fields.
+    pub fn text(text: impl Into<String>) -> Self {
+        QueryAst::Term {
+            field: None,
+            text: text.into(),
+        }
+    }
+
+    /// Builds a query searching the text in the field.
+    pub fn term(field: impl Into<String>, text: impl Into<String>) -> Self {
+        QueryAst::Term {
+            field: Some(field.into()),
+            text: text.into(),
+        }
+    }
+
+    /// Starts building a boolean query.
+    pub fn bool() -> BoolQuery {
+        BoolQuery::default()
+    }
+
+    /// Builds a query matching the documents matched by both queries.
+    pub fn and(self, other: QueryAst) -> Self {
+        QueryAst::bool().must(self).must(other).into()
+    }
+
+    /// Builds a query matching the documents matched by either query.
+    pub fn or(self, other: QueryAst) -> Self {
+        QueryAst::bool().should(self).should(other).into()
+    }
+
+    /// Builds a query matching the documents not matched by the query.
+    pub fn negate(self) -> Self {
+        QueryAst::bool()
+            .must(QueryAst::MatchAll)
+            .must_not(self)
+            .into()
+    }
+
+    /// Multiplies the score of the query by `boost`.
+    pub fn boost(self, boost: f64) -> Self {
+        QueryAst::Boost {
+            query: Box::new(self),
+            boost,
+        }
+    }
+
+    /// Parses a query expressed in the query language.
+    pub fn parse(query: &str) -> Result<Self, QueryParserError> {
+        let user_input_ast = tantivy_query_grammar::parse_query(query)
+            .map_err(|_| TantivyQueryParserError::SyntaxError(query.to_string()))?;
+        Self::from_user_input_ast(user_input_ast)
+    }
+
+    /// Deserializes a query from its JSON serialization.
+    pub fn from_json(query_ast_json: &str) -> Result<Self, QueryParserError> {
+        serde_json::from_str(query_ast_json)
+            .map_err(|error| anyhow::anyhow!("Invalid query AST: {error}.").into())
+    }
+
+    fn from_user_input_ast(user_input_ast: UserInputAst) -> Result<Self, QueryParserError> {
+        match user_input_ast {
+            UserInputAst::Clause(clauses) => {
+                let mut bool_query = BoolQuery::default();
+                for (occur_opt, clause) in clauses {
+                    let query = Self::from_user_input_ast(clause)?;
+                    // Queries are parsed with conjunction by default.
+                    bool_query = match occur_opt.unwrap_or(Occur::Must) {
+                        Occur::Must => bool_query.must(query),
+                        Occur::Should => bool_query.should(query),
+                        Occur::MustNot => bool_query.must_not(query),
+                    };
+                }
+                // Clauses made of a single query are equivalent to that query.
+                let num_clauses =
+                    bool_query.must.len() + bool_query.should.len() + bool_query.must_not.len();
+                if num_clauses == 1 && bool_query.must_not.is_empty() {
+                    return Ok(bool_query
+                        .must
+                        .pop()
+                        .or_else(|| bool_query.should.pop())
+                        .expect("The clause should contain one query."));
+                }
+                Ok(bool_query.into())
+            }
+            UserInputAst::Leaf(leaf) => match *leaf {
+                UserInputLeaf::Literal(UserInputLiteral {
+                    field_name, phrase, ..
+                }) => Ok(QueryAst::Term {
+                    field: field_name,
+                    text: phrase,
+                }),
+                UserInputLeaf::All => Ok(QueryAst::MatchAll),
+                UserInputLeaf::Range { .. } => {
+                    Err(anyhow::anyhow!("Range queries are not currently allowed.").into())
+                }
+            },
+            UserInputAst::Boost(user_input_ast, boost) => {
+                Ok(Self::from_user_input_ast(*user_input_ast)?.boost(boost))
+            }
+        }
+    }
+
+    /// Renders the query into the query language, e.g. for display purposes.
+    ///
+    /// Texts containing double quotes or backslashes cannot be expressed in the query language:
+    /// such queries are rejected, and must be searched through the `query_ast` of the search
+    /// request instead.
+    pub fn to_query_string(&self) -> Result<String, QueryParserError> {
+        let mut query_string = String::new();
+        self.write_query_string(&mut query_string)?;
+        Ok(query_string)
+    }
+
+    fn write_query_string(&self, query_string: &mut String) -> Result<(), QueryParserError> {
+        match self {
+            QueryAst::MatchAll => query_string.push('*'),
+            QueryAst::Term { field, text } => {
+                if text.contains(&UNQUOTABLE_TEXT_CHARS[..]) {
+                    return Err(anyhow::anyhow!(
+                        "Text `{text}` cannot be expressed in the query language: it contains \
+                         double quotes or backslashes."
+                    )
+                    .into());
+                }
+                write_term_query_string(query_string, field.as_deref(), text)?;
+            }
+            QueryAst::Bool(bool_query) => {
+                let should_clause_opt = match bool_query.should.as_slice() {
+                    [] => None,
+                    [query] => Some(query.to_query_string()?),
+                    queries => {
+                        let should_clauses = queries
+                            .iter()
+                            .map(|query| query.to_query_string())
+                            .collect::<Result<Vec<_>, QueryParserError>>()?;
+                        Some(format!("({})", should_clauses.join(" OR ")))
+                    }
+                };
+                if bool_query.must.is_empty() && bool_query.must_not.is_empty() {
+                    query_string.push_str(should_clause_opt.as_deref().unwrap_or("*"));
+                    return Ok(());
+                }
+                let mut clauses = Vec::new();
+                for query in &bool_query.must {
+                    clauses.push(format!("+{}", query.to_query_string()?));
+                }
+                if let Some(should_clause) = should_clause_opt {
+                    clauses.push(format!("+{should_clause}"));
+                }
+                for query in &bool_query.must_not {
+                    clauses.push(format!("-{}", query.to_query_string()?));
+                }
+                write!(query_string, "({})", clauses.join(" "))
+                    .expect("Writing to a string should never fail.");
+            }
+            QueryAst::Boost { query, boost } => {
+                validate_boost(*boost)?;
+                let boosted_query = query.to_query_string()?;
+                write!(query_string, "({boosted_query})^{boost}")
+                    .expect("Writing to a string should never fail.");
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts the query into the AST of the query language, from which the fields and tags of
+    /// the query are extracted.
+    pub(crate) fn to_user_input_ast(&self) -> UserInputAst {
+        match self {
+            QueryAst::MatchAll => UserInputAst::Leaf(Box::new(UserInputLeaf::All)),
+            QueryAst::Term { field, text } => {
+                UserInputAst::Leaf(Box::new(UserInputLeaf::Literal(UserInputLiteral {
+                    field_name: field.clone(),
+                    phrase: text.clone(),
+                    slop: 0,
+                })))
+            }
+            QueryAst::Bool(bool_query) => {
+                let mut clauses: Vec<(Option<Occur>, UserInputAst)> = bool_query
+                    .must
+                    .iter()
+                    .map(|query| (Some(Occur::Must), query.to_user_input_ast()))
+                    .collect();
+                let should_clauses: Vec<(Option<Occur>, UserInputAst)> = bool_query
+                    .should
+                    .iter()
+                    .map(|query| (Some(Occur::Should), query.to_user_input_ast()))
+                    .collect();
+                clauses.extend(
+                    bool_query
+                        .must_not
+                        .iter()
+                        .map(|query| (Some(Occur::MustNot), query.to_user_input_ast())),
+                );
+                if clauses.is_empty() {
+                    return UserInputAst::Clause(should_clauses);
+                }
+                // At least one of the `should` queries must match.
+                if !should_clauses.is_empty() {
+                    clauses.push((Some(Occur::Must), UserInputAst::Clause(should_clauses)));
+                }
+                UserInputAst::Clause(clauses)
+            }
+            QueryAst::Boost { query, boost } => {
+                UserInputAst::Boost(Box::new(query.to_user_input_ast()), *boost)
+            }
+        }
+    }
+
+    /// Builds the tantivy query searching the tree on a split. The texts are searched in the
+    /// `search_fields` when the term queries do not target a field.
+    pub(crate) fn build_tantivy_query(
+        &self,
+        schema: Schema,
+        search_fields: Vec<Field>,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        let mut query_parser = QueryParser::new(
+            schema.clone(),
+            search_fields.clone(),
+            QUICKWIT_TOKENIZER_MANAGER.clone(),
+        );
+        query_parser.set_conjunction_by_default();
+        let term_query_builder = TermQueryBuilder {
+            schema,
+            search_fields,
+            query_parser,
+        };
+        self.build_tantivy_query_aux(&term_query_builder)
+    }
+
+    fn build_tantivy_query_aux(
+        &self,
+        term_query_builder: &TermQueryBuilder,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        match self {
+            QueryAst::MatchAll => Ok(Box::new(AllQuery)),
+            QueryAst::Term { field, text } => term_query_builder.build(field.as_deref(), text),
+            QueryAst::Bool(bool_query) => {
+                let mut clauses: Vec<(TantivyOccur, Box<dyn Query>)> = Vec::new();
+                for query in &bool_query.must {
+                    let must_query = query.build_tantivy_query_aux(term_query_builder)?;
+                    clauses.push((TantivyOccur::Must, must_query));
+                }
+                let mut should_clauses: Vec<(TantivyOccur, Box<dyn Query>)> = Vec::new();
+                for query in &bool_query.should {
+                    let should_query = query.build_tantivy_query_aux(term_query_builder)?;
+                    should_clauses.push((TantivyOccur::Should, should_query));
+                }
+                if clauses.is_empty() && bool_query.must_not.is_empty() {
+                    if should_clauses.is_empty() {
+                        return Ok(Box::new(AllQuery));
+                    }
+                    return Ok(Box::new(BooleanQuery::new(should_clauses)));
+                }
+                if clauses.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "Boolean queries must contain at least one `must` or `should` query."
+                    )
+                    .into());
+                }
+                // At least one of the `should` queries must match.
+                if !should_clauses.is_empty() {
+                    clauses.push((
+                        TantivyOccur::Must,
+                        Box::new(BooleanQuery::new(should_clauses)),
+                    ));
+                }
+                for query in &bool_query.must_not {
+                    let must_not_query = query.build_tantivy_query_aux(term_query_builder)?;
+                    clauses.push((TantivyOccur::MustNot, must_not_query));
+                }
+                Ok(Box::new(BooleanQuery::new(clauses)))
+            }
+            QueryAst::Boost { query, boost } => {
+                validate_boost(*boost)?;
+                let boosted_query = query.build_tantivy_query_aux(term_query_builder)?;
+                Ok(Box::new(BoostQuery::new(boosted_query, *boost as f32)))
+            }
+        }
+    }
+}
+
+/// Builds the tantivy queries of the term queries of the tree.
+///
+/// Texts are searched with the query parser, which resolves the fields and tokenizes the texts
+/// exactly like in the query language. Texts that cannot be quoted in the query language are
+/// searched field by field: raw fields are matched on the whole text, and the other fields are
+/// searched after replacing the unquotable characters, which their tokenizers split on anyway,
+/// with spaces.
+struct TermQueryBuilder {
+    schema: Schema,
+    search_fields: Vec<Field>,
+    query_parser: QueryParser,
+}
+
+impl TermQueryBuilder {
+    fn build(
+        &self,
+        field_opt: Option<&str>,
+        text: &str,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        if !text.contains(&UNQUOTABLE_TEXT_CHARS[..]) {
+            return self.parse_term_query(field_opt, text);
+        }
+        let target_fields: Vec<(Field, String)> = if let Some(field_name) = field_opt {
+            let field = resolve_field(&self.schema, field_name).ok_or_else(|| {
+                TantivyQueryParserError::FieldDoesNotExist(field_name.to_string())
+            })?;
+            vec![(field, field_name.to_string())]
+        } else {
+            self.search_fields
+                .iter()
+                .map(|field| (*field, self.schema.get_field_name(*field).to_string()))
+                .collect()
+        };
+        let sanitized_text = text.replace(&UNQUOTABLE_TEXT_CHARS[..], " ");
+        let mut field_queries: Vec<(TantivyOccur, Box<dyn Query>)> = Vec::new();
+
+        for (field, field_name) in target_fields {
+            let field_query: Box<dyn Query> = match self.schema.get_field_entry(field).field_type()
+            {
+                FieldType::Str(text_options)
+                    if text_options
+                        .get_indexing_options()
+                        .map(|indexing_options| indexing_options.tokenizer() == "raw")
+                        .unwrap_or(false) =>
+                {
+                    let term = Term::from_field_text(field, text);
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+                }
+                FieldType::JsonObject(json_options)
+                    if json_options
+                        .get_text_indexing_options()
+                        .map(|indexing_options| indexing_options.tokenizer() == "raw")
+                        .unwrap_or(false) =>
+                {
+                    return Err(anyhow::anyhow!(
+                        "Text `{text}` cannot be searched in the raw JSON field `{field_name}`: \
+                         it contains double quotes or backslashes."
+                    )
+                    .into());
+                }
+                _ => self.parse_term_query(Some(field_name.as_str()), &sanitized_text)?,
+            };
+            field_queries.push((TantivyOccur::Should, field_query));
+        }
+        if field_queries.len() == 1 {
+            let (_, field_query) = field_queries
+                .pop()
+                .expect("There should be one field query.");
+            return Ok(field_query);
+        }
+        Ok(Box::new(BooleanQuery::new(field_queries)))
+    }
+
+    fn parse_term_query(
+        &self,
+        field_opt: Option<&str>,
+        text: &str,
+    ) -> Result<Box<dyn Query>, QueryParserError> {
+        let mut query_string = String::new();
+        write_term_query_string(&mut query_string, field_opt, text)?;
+        let query = self.query_parser.parse_query(&query_string)?;
+        Ok(query)
+    }
+}
+
+/// Returns the field holding the values of `field_name`, which may be a path in a JSON field.
+fn resolve_field(schema: &Schema, field_name: &str) -> Option<Field> {
+    if let Some(field) = schema.get_field(field_name) {
+        return Some(field);
+    }
+    // Escaped dots are part of the field names.
+    field_name
+        .match_indices('.')
+        .rev()
+        .filter(|(dot_pos, _)| !field_name[..*dot_pos].ends_with('\\'))
+        .find_map(|(dot_pos, _)| schema.get_field(&field_name[..dot_pos]))
+}
+
+/// Writes a term query quoting its text, which must not contain unquotable characters.
+fn write_term_query_string(
+    query_string: &mut String,
+    field_opt: Option<&str>,
+    text: &str,
+) -> Result<(), QueryParserError> {
+    if let Some(field) = field_opt {
+        if field.is_empty()
+            || field.starts_with('-')
+            || field
+                .contains(|c: char| c.is_whitespace() || FORBIDDEN_FIELD_NAME_CHARS.contains(&c))
+        {
+            return Err(anyhow::anyhow!("Field name `{field}` cannot be used in a query.").into());
+        }
+        query_string.push_str(field);
+        query_string.push(':');
+    }
+    if text.trim().is_empty() {
+        return Err(anyhow::anyhow!("The text of a term query must not be empty.").into());
+    }
+    // Quoting does not change the meaning of single tokens and keeps the special characters of
+    // the text from being interpreted.
+    query_string.push('"');
+    query_string.push_str(text);
+    query_string.push('"');
+    Ok(())
+}
+
+fn validate_boost(boost: f64) -> Result<(), QueryParserError> {
+    if !boost.is_finite() || boost <= 0.0 {
+        return Err(anyhow::anyhow!("Boost `{boost}` must be strictly positive.").into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_query_ast_to_query_string() {
+        assert_eq!(QueryAst::match_all().to_query_string().unwrap(), "*");
+        assert_eq!(
+            QueryAst::term("severity", "ERROR")
+                .to_query_string()
+                .unwrap(),
+            r#"severity:"ERROR""#
+        );
+        let query = QueryAst::bool()
+            .must(QueryAst::text("connection refused"))
+            .should(QueryAst::term("service", "api"))
+            .should(QueryAst::term("service", "db").boost(2.5))
+            .must_not(QueryAst::term("env", "staging"));
+        assert_eq!(
+            QueryAst::from(query).to_query_string().unwrap(),
+            r#"(+"connection refused" +(service:"api" OR (service:"db")^2.5) -env:"staging")"#
+        );
+        assert_eq!(
+            QueryAst::term("severity", "ERROR")
+                .negate()
+                .to_query_string()
+                .unwrap(),
+            r#"(+* -severity:"ERROR")"#
+        );
+        assert!(QueryAst::text(r#"say "hi""#).to_query_string().is_err());
+        assert!(QueryAst::text(r#"C:\Windows"#).to_query_string().is_err());
+        assert!(QueryAst::term("a:b", "c").to_query_string().is_err());
+        assert!(QueryAst::text("hi").boost(0.0).to_query_string().is_err());
+    }
+
+    #[test]
+    fn test_query_ast_parse() {
+        assert_eq!(
+            QueryAst::parse("severity:ERROR AND service:api").unwrap(),
+            QueryAst::term("severity", "ERROR").and(QueryAst::term("service", "api"))
+        );
+        assert_eq!(
+            QueryAst::parse("severity:ERROR OR severity:WARN").unwrap(),
+            QueryAst::term("severity", "ERROR").or(QueryAst::term("severity", "WARN"))
+        );
+        assert!(QueryAst::parse("timestamp:[1 TO 2]").is_err());
+        assert!(QueryAst::parse("(severity:ERROR").is_err());
+    }
+
+    #[test]
+    fn test_query_ast_round_trip() {
+        let query: QueryAst = QueryAst::bool()
+            .must(QueryAst::term("service", "api"))
+            .should(QueryAst::text("timeout"))
+            .should(QueryAst::text("refused"))
+            .must_not(QueryAst::term("env", "staging"))
+            .into();
+        let query_string = query.to_query_string().unwrap();
+        let parsed_query = QueryAst::parse(&query_string).unwrap();
+        assert_eq!(parsed_query.to_query_string().unwrap(), query_string);
+    }
+
+    #[test]
+    fn test_query_ast_serde() {
+        let query = QueryAst::term("severity", "ERROR")
+            .or(QueryAst::text("panic").boost(2.0))
+            .negate();
+        let query_json = serde_json::to_value(&query).unwrap();
+        assert_eq!(
+            query_json,
+            json!({
+                "type": "bool",
+                "must": [{"type": "match_all"}],
+                "must_not": [{
+                    "type": "bool",
+                    "should": [
+                        {"type": "term", "field": "severity", "text": "ERROR"},
+                        {
+                            "type": "boost",
+                            "query": {"type": "term", "text": "panic"},
+                            "boost": 2.0
+                        }
+                    ]
+                }]
+            })
+        );
+        let deserialized_query: QueryAst = serde_json::from_value(query_json).unwrap();
+        assert_eq!(deserialized_query, query);
+    }
+}
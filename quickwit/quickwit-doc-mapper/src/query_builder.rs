@@ -24,17 +24,37 @@ use tantivy::query::{Query, QueryParser, QueryParserError as TantivyQueryParserE
 use tantivy::schema::{Field, FieldType, Schema};
 use tantivy_query_grammar::{UserInputAst, UserInputLeaf, UserInputLiteral};
 
+use crate::query_ast::QueryAst;
 use crate::sort_by::validate_sort_by_field_name;
 use crate::{QueryParserError, DYNAMIC_FIELD_NAME, QUICKWIT_TOKENIZER_MANAGER};
 
+/// Parses the query of a search request, expressed either in the query language or as a JSON
+/// serialized query AST. The query AST is returned when the request holds one.
+pub(crate) fn parse_search_request_query(
+    request: &SearchRequest,
+) -> Result<(UserInputAst, Option<QueryAst>), QueryParserError> {
+    if let Some(query_ast_json) = &request.query_ast {
+        if !request.query.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The `query` and `query_ast` of a search request cannot both be set."
+            )
+            .into());
+        }
+        let query_ast = QueryAst::from_json(query_ast_json)?;
+        return Ok((query_ast.to_user_input_ast(), Some(query_ast)));
+    }
+    let user_input_ast = tantivy_query_grammar::parse_query(&request.query)
+        .map_err(|_| TantivyQueryParserError::SyntaxError(request.query.to_string()))?;
+    Ok((user_input_ast, None))
+}
+
 /// Build a `Query` with field resolution & forbidding range clauses.
 pub(crate) fn build_query(
     schema: Schema,
     request: &SearchRequest,
     default_field_names: &[String],
 ) -> Result<Box<dyn Query>, QueryParserError> {
-    let user_input_ast = tantivy_query_grammar::parse_query(&request.query)
-        .map_err(|_| TantivyQueryParserError::SyntaxError(request.query.to_string()))?;
+    let (user_input_ast, query_ast_opt) = parse_search_request_query(request)?;
 
     if has_range_clause(&user_input_ast) {
         return Err(anyhow::anyhow!("Range queries are not currently allowed.").into());
@@ -61,6 +81,10 @@ pub(crate) fn build_query(
         validate_sort_by_field_name(sort_by_field, &schema, Some(&search_fields))?;
     }
 
+    if let Some(query_ast) = query_ast_opt {
+        return query_ast.build_tantivy_query(schema, search_fields);
+    }
+
     let mut query_parser =
         QueryParser::new(schema, search_fields, QUICKWIT_TOKENIZER_MANAGER.clone());
     query_parser.set_conjunction_by_default();
@@ -125,6 +149,40 @@ fn field_names(user_input_ast: &UserInputAst) -> HashSet<&str> {
         .collect()
 }
 
+/// Fields targeted by a user query.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct QueryFields {
+    /// Names of the fields targeted explicitly, e.g. `severity` in `severity:ERROR`.
+    pub field_names: HashSet<String>,
+    /// Whether some terms of the query target the default search fields.
+    pub uses_default_search_fields: bool,
+}
+
+/// Extracts the fields targeted by a user query.
+pub fn extract_query_fields(user_query: &str) -> Result<QueryFields, QueryParserError> {
+    let user_input_ast = tantivy_query_grammar::parse_query(user_query)
+        .map_err(|_| TantivyQueryParserError::SyntaxError(user_query.to_string()))?;
+    Ok(query_fields(&user_input_ast))
+}
+
+/// Extracts the fields targeted by the query of a search request, which may be a query AST.
+pub fn extract_search_request_query_fields(
+    request: &SearchRequest,
+) -> Result<QueryFields, QueryParserError> {
+    let (user_input_ast, _) = parse_search_request_query(request)?;
+    Ok(query_fields(&user_input_ast))
+}
+
+fn query_fields(user_input_ast: &UserInputAst) -> QueryFields {
+    QueryFields {
+        field_names: field_names(user_input_ast)
+            .into_iter()
+            .map(|field_name| field_name.to_string())
+            .collect(),
+        uses_default_search_fields: needs_default_search_field(user_input_ast),
+    }
+}
+
 fn validate_requested_snippet_fields(
     schema: &Schema,
     request: &SearchRequest,
@@ -173,10 +231,10 @@ fn validate_requested_snippet_fields(
 mod test {
     use quickwit_proto::SearchRequest;
     use tantivy::query::QueryParserError;
-    use tantivy::schema::{Schema, FAST, INDEXED, STORED, TEXT};
+    use tantivy::schema::{Schema, FAST, INDEXED, STORED, STRING, TEXT};
 
-    use super::{build_query, validate_requested_snippet_fields};
-    use crate::{DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME};
+    use super::{build_query, extract_query_fields, validate_requested_snippet_fields};
+    use crate::{QueryAst, DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME};
 
     enum TestExpectation {
         Err(&'static str),
@@ -192,6 +250,7 @@ mod test {
         schema_builder.add_bool_field("server.running", FAST | STORED | INDEXED);
         schema_builder.add_text_field(SOURCE_FIELD_NAME, TEXT);
         schema_builder.add_json_field(DYNAMIC_FIELD_NAME, TEXT);
+        schema_builder.add_text_field("trace_id", STRING);
         schema_builder.build()
     }
 
@@ -208,12 +267,16 @@ mod test {
             query: query_str.to_string(),
             search_fields,
             snippet_fields: vec![],
+            fetch_fields: Vec::new(),
             start_timestamp: None,
             end_timestamp: None,
             max_hits: 20,
             start_offset: 0,
             sort_order: None,
             sort_by_field: None,
+            debug: false,
+            role: None,
+            query_ast: None,
         };
 
         let default_field_names =
@@ -367,6 +430,10 @@ mod test {
             start_offset: 0,
             sort_order: None,
             sort_by_field: None,
+            fetch_fields: Vec::new(),
+            debug: false,
+            role: None,
+            query_ast: None,
         };
         let user_input_ast = tantivy_query_grammar::parse_query(&request.query)
             .map_err(|_| QueryParserError::SyntaxError(request.query.clone()))
@@ -462,4 +529,90 @@ mod test {
             "The snippet field `server.running` must be of type `Str`, got `Bool`."
         );
     }
+
+    fn build_query_ast(query_ast: &QueryAst) -> Result<String, String> {
+        let request = SearchRequest {
+            index_id: "test_index".to_string(),
+            query_ast: Some(serde_json::to_string(query_ast).unwrap()),
+            max_hits: 20,
+            ..Default::default()
+        };
+        let default_field_names = ["title".to_string(), "desc".to_string()];
+        build_query(make_schema(), &request, &default_field_names)
+            .map(|query| format!("{query:?}"))
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn test_build_query_from_query_ast() {
+        assert_eq!(
+            build_query_ast(&QueryAst::term("title", "hello")).unwrap(),
+            r#"TermQuery(Term(type=Str, field=0, "hello"))"#
+        );
+        assert_eq!(
+            build_query_ast(&QueryAst::term("title", "a").and(QueryAst::term("desc", "b")))
+                .unwrap(),
+            r#"BooleanQuery { subqueries: [(Must, TermQuery(Term(type=Str, field=0, "a"))), (Must, TermQuery(Term(type=Str, field=1, "b")))] }"#
+        );
+        // At least one of the `should` queries must match.
+        let query_ast = QueryAst::bool()
+            .must(QueryAst::term("title", "a"))
+            .should(QueryAst::term("desc", "b"))
+            .into();
+        assert_eq!(
+            build_query_ast(&query_ast).unwrap(),
+            r#"BooleanQuery { subqueries: [(Must, TermQuery(Term(type=Str, field=0, "a"))), (Must, BooleanQuery { subqueries: [(Should, TermQuery(Term(type=Str, field=1, "b")))] })] }"#
+        );
+        assert!(build_query_ast(&QueryAst::bool().must_not(QueryAst::text("a")).into()).is_err());
+        assert!(build_query_ast(&QueryAst::term("unknown", "a")).is_err());
+        assert!(build_query_ast(&QueryAst::term("title", "[1 TO 2]")).is_ok());
+    }
+
+    #[test]
+    fn test_build_query_from_query_ast_with_unquotable_text() {
+        // Raw fields are matched on the whole text.
+        let query_debug = build_query_ast(&QueryAst::term("trace_id", r#"a"b\c"#)).unwrap();
+        assert!(query_debug.starts_with("TermQuery"));
+        assert!(query_debug.contains("field=7"));
+        // The other fields are tokenized.
+        assert_eq!(
+            build_query_ast(&QueryAst::term("title", r#""hello""#)).unwrap(),
+            r#"TermQuery(Term(type=Str, field=0, "hello"))"#
+        );
+        assert_eq!(
+            build_query_ast(&QueryAst::text(r#""hello""#)).unwrap(),
+            r#"BooleanQuery { subqueries: [(Should, TermQuery(Term(type=Str, field=0, "hello"))), (Should, TermQuery(Term(type=Str, field=1, "hello")))] }"#
+        );
+        assert!(build_query_ast(&QueryAst::term("title", r#"say "hi""#))
+            .unwrap()
+            .starts_with("PhraseQuery"));
+    }
+
+    #[test]
+    fn test_build_query_with_query_and_query_ast() {
+        let request = SearchRequest {
+            index_id: "test_index".to_string(),
+            query: "title:hello".to_string(),
+            query_ast: Some(serde_json::to_string(&QueryAst::term("title", "hello")).unwrap()),
+            max_hits: 20,
+            ..Default::default()
+        };
+        let error = build_query(make_schema(), &request, &[]).unwrap_err();
+        assert!(error.to_string().contains("cannot both be set"));
+    }
+
+    #[test]
+    fn test_extract_query_fields() {
+        let query_fields =
+            extract_query_fields("severity:ERROR AND (user.email:foo OR bar)").unwrap();
+        assert_eq!(query_fields.field_names.len(), 2);
+        assert!(query_fields.field_names.contains("severity"));
+        assert!(query_fields.field_names.contains("user.email"));
+        assert!(query_fields.uses_default_search_fields);
+
+        let query_fields = extract_query_fields("severity:ERROR").unwrap();
+        assert!(!query_fields.uses_default_search_fields);
+
+        assert!(extract_query_fields("severity:(").is_err());
+    }
 }
@@ -20,10 +20,12 @@
 use std::collections::BTreeSet;
 use std::fmt::Display;
 
+use quickwit_proto::SearchRequest;
 use serde::{Deserialize, Serialize};
 use tantivy::query::QueryParserError as TantivyQueryParserError;
 use tantivy_query_grammar::{Occur, UserInputAst, UserInputLeaf, UserInputLiteral};
 
+use crate::query_builder::parse_search_request_query;
 use crate::QueryParserError;
 
 fn user_input_ast_to_tags_filter_ast(user_input_ast: UserInputAst) -> Option<TagFilterAst> {
@@ -51,6 +53,15 @@ pub fn extract_tags_from_query(user_query: &str) -> Result<Option<TagFilterAst>,
     Ok(user_input_ast_to_tags_filter_ast(user_input_ast))
 }
 
+/// Same as [`extract_tags_from_query`] for the query of a search request, which may be a query
+/// AST.
+pub fn extract_tags_from_search_request(
+    search_request: &SearchRequest,
+) -> Result<Option<TagFilterAst>, QueryParserError> {
+    let (user_input_ast, _) = parse_search_request_query(search_request)?;
+    Ok(user_input_ast_to_tags_filter_ast(user_input_ast))
+}
+
 /// Intermediary AST that may contain leaf that are
 /// equivalent to the "Uninformative" predicate.
 #[derive(Clone, Debug, Eq, PartialEq)]
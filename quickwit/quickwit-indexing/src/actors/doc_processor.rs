@@ -17,23 +17,91 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
+use anyhow::Context;
 use async_trait::async_trait;
+use bytes::Bytes;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
+use quickwit_config::{CsvOptions, DedupSettings, SourceInputFormat, TransformOperation};
 use quickwit_doc_mapper::{DocMapper, DocParsingError};
+use quickwit_metastore::checkpoint::{PartitionId, SourceCheckpointDelta};
 use serde::Serialize;
 use tantivy::schema::{Field, Value};
 use tokio::runtime::Handle;
 use tracing::warn;
 
 use crate::actors::Indexer;
-use crate::models::{NewPublishLock, PreparedDoc, PreparedDocBatch, PublishLock, RawDocBatch};
+use crate::csv_doc_parser::CsvDocParser;
+use crate::dedup::DocIdDeduplicator;
+use crate::doc_transform::DocTransform;
+use crate::models::{
+    CsvHeader, NewPublishLock, PreparedDoc, PreparedDocBatch, PublishLock, RawDocBatch,
+};
+use crate::source::SourceRateLimiter;
 
-enum PrepareDocumentError {
-    ParsingError,
-    MissingField,
+/// Reason why a document was rejected by the doc processor.
+#[derive(Debug, thiserror::Error)]
+pub enum PrepareDocumentError {
+    #[error("Failed to parse document: {0}")]
+    ParsingError(DocParsingError),
+    #[error("{0}")]
+    MissingField(String),
+    #[error("Failed to parse CSV record: {0}")]
+    CsvError(String),
+}
+
+/// Parses a JSON document with the doc mapper and extracts its partition and timestamp, exactly
+/// as the doc processor does before handing documents over to the indexer.
+pub fn prepare_document(
+    doc_mapper: &dyn DocMapper,
+    timestamp_field_opt: Option<Field>,
+    doc_json: &[u8],
+) -> Result<PreparedDoc, PrepareDocumentError> {
+    let num_bytes = doc_json.len();
+    let (partition, doc) =
+        doc_mapper
+            .doc_from_json_bytes(doc_json)
+            .map_err(|doc_parsing_error| match doc_parsing_error {
+                DocParsingError::RequiredFastField(_) => {
+                    PrepareDocumentError::MissingField(doc_parsing_error.to_string())
+                }
+                _ => PrepareDocumentError::ParsingError(doc_parsing_error),
+            })?;
+    // Extract timestamp if necessary
+    let timestamp_field = if let Some(timestamp_field) = timestamp_field_opt {
+        timestamp_field
+    } else {
+        // No need to check the timestamp, there are no timestamp.
+        return Ok(PreparedDoc {
+            doc,
+            timestamp_opt: None,
+            partition,
+            num_bytes,
+        });
+    };
+    let timestamp = doc
+        .get_first(timestamp_field)
+        .and_then(|value| match value {
+            Value::Date(date_time) => Some(date_time.into_timestamp_secs()),
+            value => value.as_i64(),
+        })
+        .ok_or_else(|| {
+            PrepareDocumentError::MissingField(format!(
+                "The document must contain the timestamp field {:?}.",
+                doc_mapper.schema().get_field_name(timestamp_field)
+            ))
+        })?;
+    Ok(PreparedDoc {
+        doc,
+        timestamp_opt: Some(timestamp),
+        partition,
+        num_bytes,
+    })
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
@@ -41,13 +109,19 @@ pub struct DocProcessorCounters {
     index_id: String,
     source_id: String,
     /// Overall number of documents received, partitioned
-    /// into 3 categories:
+    /// into 5 categories:
     /// - number docs that did not parse correctly.
     /// - number docs missing a timestamp (if the index has no timestamp,
     /// then this counter is 0)
+    /// - number of valid docs dropped because their id was recently seen (if
+    /// deduplication is disabled, then this counter is 0)
+    /// - number of docs discarded by the transform of the source (if the source
+    /// has no transform, then this counter is 0)
     /// - number of valid docs.
     pub num_parse_errors: u64,
     pub num_docs_with_missing_fields: u64,
+    pub num_duplicate_docs: u64,
+    pub num_discarded_docs: u64,
     pub num_valid_docs: u64,
 
     /// Number of bytes that went through the indexer
@@ -64,6 +138,8 @@ impl DocProcessorCounters {
             source_id,
             num_parse_errors: 0,
             num_docs_with_missing_fields: 0,
+            num_duplicate_docs: 0,
+            num_discarded_docs: 0,
             num_valid_docs: 0,
             overall_num_bytes: 0,
         }
@@ -71,7 +147,11 @@ impl DocProcessorCounters {
 
     /// Returns the overall number of docs that went through the indexer (valid or not).
     pub fn num_processed_docs(&self) -> u64 {
-        self.num_valid_docs + self.num_parse_errors + self.num_docs_with_missing_fields
+        self.num_valid_docs
+            + self.num_parse_errors
+            + self.num_docs_with_missing_fields
+            + self.num_duplicate_docs
+            + self.num_discarded_docs
     }
 
     /// Returns the overall number of docs that were sent to the indexer but were invalid.
@@ -123,6 +203,32 @@ impl DocProcessorCounters {
             .inc_by(num_bytes);
     }
 
+    pub fn record_duplicate(&mut self, num_bytes: u64) {
+        self.num_duplicate_docs += 1;
+        self.overall_num_bytes += num_bytes;
+        crate::metrics::INDEXER_METRICS
+            .processed_docs_total
+            .with_label_values(&[self.index_id.as_str(), self.source_id.as_str(), "duplicate"])
+            .inc();
+        crate::metrics::INDEXER_METRICS
+            .processed_bytes
+            .with_label_values(&[self.index_id.as_str(), self.source_id.as_str(), "duplicate"])
+            .inc_by(num_bytes);
+    }
+
+    pub fn record_discarded(&mut self, num_bytes: u64) {
+        self.num_discarded_docs += 1;
+        self.overall_num_bytes += num_bytes;
+        crate::metrics::INDEXER_METRICS
+            .processed_docs_total
+            .with_label_values(&[self.index_id.as_str(), self.source_id.as_str(), "discarded"])
+            .inc();
+        crate::metrics::INDEXER_METRICS
+            .processed_bytes
+            .with_label_values(&[self.index_id.as_str(), self.source_id.as_str(), "discarded"])
+            .inc_by(num_bytes);
+    }
+
     pub fn record_valid(&mut self, num_bytes: u64) {
         self.num_valid_docs += 1;
         self.overall_num_bytes += num_bytes;
@@ -137,12 +243,71 @@ impl DocProcessorCounters {
     }
 }
 
+/// Replies once all the batches received before it have been forwarded to the indexer.
+#[derive(Debug)]
+struct FlushPendingBatches;
+
+/// Dispatches the batches emitted by the source between the routing doc processor and its peers.
+///
+/// The batches of a given partition are always dispatched to the same doc processor so that they
+/// reach the indexer in order. Batches spanning several partitions are dispatched to the doc
+/// processor of their first partition, after the doc processors the other partitions were last
+/// dispatched to have flushed their pending batches.
+struct DocProcessorRouter {
+    peer_mailboxes: Vec<Mailbox<DocProcessor>>,
+    /// Doc processor each partition was last dispatched to. `0` designates the routing doc
+    /// processor itself, `i > 0` its `i - 1`-th peer.
+    last_processor_per_partition: BTreeMap<PartitionId, usize>,
+}
+
+impl DocProcessorRouter {
+    fn num_processors(&self) -> usize {
+        self.peer_mailboxes.len() + 1
+    }
+
+    fn processor_for_partition(&self, partition_id: &PartitionId) -> usize {
+        let mut hasher = DefaultHasher::new();
+        partition_id.0.hash(&mut hasher);
+        (hasher.finish() % self.num_processors() as u64) as usize
+    }
+
+    /// Picks the doc processor of the batch and returns the peers that must flush their pending
+    /// batches beforehand.
+    fn route(&mut self, checkpoint_delta: &SourceCheckpointDelta) -> (usize, BTreeSet<usize>) {
+        let processor = checkpoint_delta
+            .partitions()
+            .next()
+            .map(|partition_id| self.processor_for_partition(partition_id))
+            .unwrap_or(0);
+        let mut peers_to_flush = BTreeSet::new();
+        for partition_id in checkpoint_delta.partitions() {
+            if let Some(last_processor) = self
+                .last_processor_per_partition
+                .insert(partition_id.clone(), processor)
+            {
+                // The routing doc processor handles its batches sequentially, so they were
+                // already forwarded.
+                if last_processor != processor && last_processor != 0 {
+                    peers_to_flush.insert(last_processor);
+                }
+            }
+        }
+        (processor, peers_to_flush)
+    }
+}
+
 pub struct DocProcessor {
     doc_mapper: Arc<dyn DocMapper>,
     indexer_mailbox: Mailbox<Indexer>,
     timestamp_field_opt: Option<Field>,
     counters: DocProcessorCounters,
     publish_lock: PublishLock,
+    deduplicator_opt: Option<DocIdDeduplicator>,
+    router_opt: Option<DocProcessorRouter>,
+    rate_limiter_opt: Option<SourceRateLimiter>,
+    transform_opt: Option<DocTransform>,
+    csv_parser_opt: Option<CsvDocParser>,
+    is_peer: bool,
 }
 
 impl DocProcessor {
@@ -160,50 +325,189 @@ impl DocProcessor {
             timestamp_field_opt,
             counters: DocProcessorCounters::new(index_id, source_id),
             publish_lock: PublishLock::default(),
+            deduplicator_opt: None,
+            router_opt: None,
+            rate_limiter_opt: None,
+            transform_opt: None,
+            csv_parser_opt: None,
+            is_peer: false,
+        }
+    }
+
+    /// Turns the doc processor into the peer of a routing doc processor. Peers leave forwarding
+    /// publish locks and exiting the indexer to the routing doc processor.
+    pub fn into_peer(mut self) -> Self {
+        self.is_peer = true;
+        self
+    }
+
+    /// Shares the processing of the batches with the given peers. The routing doc processor
+    /// processes its share of the batches and dispatches the others to its peers.
+    pub fn set_peers(mut self, peer_mailboxes: Vec<Mailbox<DocProcessor>>) -> Self {
+        if !peer_mailboxes.is_empty() {
+            self.router_opt = Some(DocProcessorRouter {
+                peer_mailboxes,
+                last_processor_per_partition: BTreeMap::new(),
+            });
+        }
+        self
+    }
+
+    /// Records the batches received from the source in the source's rate limiter.
+    pub fn set_rate_limiter(mut self, rate_limiter_opt: Option<SourceRateLimiter>) -> Self {
+        self.rate_limiter_opt = rate_limiter_opt;
+        self
+    }
+
+    /// Applies the transform operations of the source to the documents before parsing them.
+    pub fn set_transform(mut self, operations: &[TransformOperation]) -> anyhow::Result<Self> {
+        if !operations.is_empty() {
+            self.transform_opt = Some(DocTransform::try_new(operations)?);
+        }
+        Ok(self)
+    }
+
+    /// Converts the CSV or TSV records emitted by the source into JSON documents.
+    pub fn set_input_format(
+        mut self,
+        input_format: SourceInputFormat,
+        csv_options_opt: Option<&CsvOptions>,
+    ) -> Self {
+        if input_format != SourceInputFormat::Json {
+            let csv_options = csv_options_opt.cloned().unwrap_or_default();
+            self.csv_parser_opt = Some(CsvDocParser::new(
+                input_format,
+                &csv_options,
+                self.doc_mapper.schema(),
+            ));
+        }
+        self
+    }
+
+    async fn flush_peers(
+        &self,
+        peers: BTreeSet<usize>,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(router) = &self.router_opt {
+            for peer in peers {
+                ctx.ask(&router.peer_mailboxes[peer - 1], FlushPendingBatches)
+                    .await
+                    .map_err(|_| ActorExitStatus::DownstreamClosed)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn process_batch(
+        &mut self,
+        raw_doc_batch: RawDocBatch,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let mut prepared_docs: Vec<PreparedDoc> = Vec::with_capacity(raw_doc_batch.docs.len());
+        // The header of CSV files is tracked per partition.
+        let partition_id = raw_doc_batch
+            .checkpoint_delta
+            .partitions()
+            .next()
+            .cloned()
+            .unwrap_or_default();
+        for doc_json in raw_doc_batch.docs {
+            let doc_json_num_bytes = doc_json.len() as u64;
+            match self.prepare_document(doc_json, &partition_id, ctx) {
+                Ok(None) => {
+                    self.counters.record_discarded(doc_json_num_bytes);
+                }
+                Ok(Some(document)) => {
+                    let is_duplicate = self
+                        .deduplicator_opt
+                        .as_mut()
+                        .map(|deduplicator| {
+                            deduplicator.is_duplicate(document.partition, &document.doc)
+                        })
+                        .unwrap_or(false);
+                    if is_duplicate {
+                        self.counters.record_duplicate(doc_json_num_bytes);
+                    } else {
+                        self.counters.record_valid(doc_json_num_bytes);
+                        prepared_docs.push(document);
+                    }
+                }
+                Err(PrepareDocumentError::ParsingError(doc_parsing_error)) => {
+                    warn!(err=?doc_parsing_error);
+                    self.counters.record_parsing_error(doc_json_num_bytes);
+                }
+                Err(PrepareDocumentError::MissingField(error)) => {
+                    warn!(err=?error);
+                    self.counters.record_missing_field(doc_json_num_bytes);
+                }
+                Err(PrepareDocumentError::CsvError(error)) => {
+                    warn!(err=?error);
+                    self.counters.record_parsing_error(doc_json_num_bytes);
+                }
+            }
+            ctx.record_progress();
         }
+        let prepared_doc_batch = PreparedDocBatch {
+            docs: prepared_docs,
+            checkpoint_delta: raw_doc_batch.checkpoint_delta,
+        };
+        ctx.send_message(&self.indexer_mailbox, prepared_doc_batch)
+            .await?;
+        Ok(())
+    }
+
+    /// Drops the documents whose id was recently seen in the same partition.
+    pub fn set_dedup_settings(mut self, dedup_settings: &DedupSettings) -> anyhow::Result<Self> {
+        let doc_id_field = self
+            .doc_mapper
+            .schema()
+            .get_field(&dedup_settings.doc_id_field)
+            .with_context(|| {
+                format!(
+                    "Dedup doc id field `{}` does not exist in the doc mapping.",
+                    dedup_settings.doc_id_field
+                )
+            })?;
+        self.deduplicator_opt = Some(DocIdDeduplicator::new(
+            doc_id_field,
+            dedup_settings.cache_capacity,
+        ));
+        Ok(self)
     }
 
     fn prepare_document(
         &self,
-        doc_json: String,
+        doc_json: Bytes,
+        partition_id: &PartitionId,
         ctx: &ActorContext<Self>,
-    ) -> Result<PreparedDoc, PrepareDocumentError> {
-        // Parse the document
+    ) -> Result<Option<PreparedDoc>, PrepareDocumentError> {
         let _protect_guard = ctx.protect_zone();
-        let num_bytes = doc_json.len();
-        let doc_parsing_result = self.doc_mapper.doc_from_json(doc_json);
-        let (partition, doc) = doc_parsing_result.map_err(|doc_parsing_error| {
-            warn!(err=?doc_parsing_error);
-            match doc_parsing_error {
-                DocParsingError::RequiredFastField(_) => PrepareDocumentError::MissingField,
-                _ => PrepareDocumentError::ParsingError,
+        let doc_json = if let Some(csv_parser) = &self.csv_parser_opt {
+            csv_parser
+                .to_json(partition_id, &doc_json)
+                .map(Bytes::from)
+                .map_err(PrepareDocumentError::CsvError)?
+        } else {
+            doc_json
+        };
+        let transformed_doc_json = if let Some(transform) = &self.transform_opt {
+            match transform
+                .apply(&doc_json)
+                .map_err(PrepareDocumentError::ParsingError)?
+            {
+                Some(transformed_doc_json) => Bytes::from(transformed_doc_json),
+                None => return Ok(None),
             }
-        })?;
-        // Extract timestamp if necessary
-        let timestamp_field = if let Some(timestamp_field) = self.timestamp_field_opt {
-            timestamp_field
         } else {
-            // No need to check the timestamp, there are no timestamp.
-            return Ok(PreparedDoc {
-                doc,
-                timestamp_opt: None,
-                partition,
-                num_bytes,
-            });
+            doc_json
         };
-        let timestamp = doc
-            .get_first(timestamp_field)
-            .and_then(|value| match value {
-                Value::Date(date_time) => Some(date_time.into_timestamp_secs()),
-                value => value.as_i64(),
-            })
-            .ok_or(PrepareDocumentError::MissingField)?;
-        Ok(PreparedDoc {
-            doc,
-            timestamp_opt: Some(timestamp),
-            partition,
-            num_bytes,
-        })
+        prepare_document(
+            &*self.doc_mapper,
+            self.timestamp_field_opt,
+            &transformed_doc_json,
+        )
+        .map(Some)
     }
 }
 
@@ -239,6 +543,15 @@ impl Actor for DocProcessor {
             | ActorExitStatus::Failure(_)
             | ActorExitStatus::Panicked => return Ok(()),
             ActorExitStatus::Quit | ActorExitStatus::Success => {
+                if self.is_peer {
+                    return Ok(());
+                }
+                if let Some(router) = &self.router_opt {
+                    for peer_mailbox in &router.peer_mailboxes {
+                        ctx.ask(peer_mailbox, FlushPendingBatches).await?;
+                        ctx.send_exit_with_success(peer_mailbox).await?;
+                    }
+                }
                 ctx.send_exit_with_success(&self.indexer_mailbox).await?;
             }
         }
@@ -255,36 +568,72 @@ impl Handler<RawDocBatch> for DocProcessor {
         raw_doc_batch: RawDocBatch,
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
+        if let Some(rate_limiter) = &self.rate_limiter_opt {
+            let num_bytes: usize = raw_doc_batch.docs.iter().map(|doc| doc.len()).sum();
+            rate_limiter.record(raw_doc_batch.docs.len() as u64, num_bytes as u64);
+        }
         if self.publish_lock.is_dead() {
             return Ok(());
         }
-        let mut prepared_docs: Vec<PreparedDoc> = Vec::with_capacity(raw_doc_batch.docs.len());
-        for doc_json in raw_doc_batch.docs {
-            let doc_json_num_bytes = doc_json.len() as u64;
-            match self.prepare_document(doc_json, ctx) {
-                Ok(document) => {
-                    self.counters.record_valid(doc_json_num_bytes);
-                    prepared_docs.push(document);
-                }
-                Err(PrepareDocumentError::ParsingError) => {
-                    self.counters.record_parsing_error(doc_json_num_bytes);
-                }
-                Err(PrepareDocumentError::MissingField) => {
-                    self.counters.record_missing_field(doc_json_num_bytes);
-                }
-            }
-            ctx.record_progress();
-        }
-        let prepared_doc_batch = PreparedDocBatch {
-            docs: prepared_docs,
-            checkpoint_delta: raw_doc_batch.checkpoint_delta,
+        let (processor, peers_to_flush) = if let Some(router) = self.router_opt.as_mut() {
+            router.route(&raw_doc_batch.checkpoint_delta)
+        } else {
+            (0, BTreeSet::new())
         };
-        ctx.send_message(&self.indexer_mailbox, prepared_doc_batch)
+        self.flush_peers(peers_to_flush, ctx).await?;
+        if processor == 0 {
+            return self.process_batch(raw_doc_batch, ctx).await;
+        }
+        let router = self
+            .router_opt
+            .as_ref()
+            .expect("Batches can only be dispatched to peers by a routing doc processor.");
+        ctx.send_message(&router.peer_mailboxes[processor - 1], raw_doc_batch)
             .await?;
         Ok(())
     }
 }
 
+#[async_trait]
+impl Handler<FlushPendingBatches> for DocProcessor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: FlushPendingBatches,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<CsvHeader> for DocProcessor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        csv_header: CsvHeader,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        // The header must reach the doc processor the batches of its partition are dispatched to.
+        if let Some(router) = &self.router_opt {
+            let processor = router.processor_for_partition(&csv_header.partition_id);
+            if processor != 0 {
+                ctx.send_message(&router.peer_mailboxes[processor - 1], csv_header)
+                    .await?;
+                return Ok(());
+            }
+        }
+        if let Some(csv_parser) = self.csv_parser_opt.as_mut() {
+            if let Err(error) = csv_parser.set_header(csv_header.partition_id, &csv_header.header) {
+                warn!(err=?error, "Failed to parse CSV header.");
+            }
+        }
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Handler<NewPublishLock> for DocProcessor {
     type Reply = ();
@@ -295,6 +644,20 @@ impl Handler<NewPublishLock> for DocProcessor {
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
         self.publish_lock = new_publish_lock.0.clone();
+        if self.is_peer {
+            return Ok(());
+        }
+        if let Some(router) = &self.router_opt {
+            // The batches of the previous publish lock must reach the indexer before the new
+            // publish lock does.
+            for peer_mailbox in &router.peer_mailboxes {
+                ctx.ask(peer_mailbox, FlushPendingBatches)
+                    .await
+                    .map_err(|_| ActorExitStatus::DownstreamClosed)?;
+                ctx.send_message(peer_mailbox, NewPublishLock(self.publish_lock.clone()))
+                    .await?;
+            }
+        }
         ctx.send_message(&self.indexer_mailbox, new_publish_lock)
             .await?;
         Ok(())
@@ -303,11 +666,12 @@ impl Handler<NewPublishLock> for DocProcessor {
 
 #[cfg(test)]
 mod tests {
+    use std::num::NonZeroUsize;
     use std::sync::Arc;
 
     use quickwit_actors::{create_test_mailbox, Universe};
     use quickwit_doc_mapper::{default_doc_mapper_for_test, DefaultDocMapper};
-    use quickwit_metastore::checkpoint::SourceCheckpointDelta;
+    use quickwit_metastore::checkpoint::{Position, SourceCheckpointDelta};
 
     use super::*;
     use crate::models::{PublishLock, RawDocBatch};
@@ -331,10 +695,10 @@ mod tests {
         doc_processor_mailbox
             .send_message(RawDocBatch {
                 docs: vec![
-                        r#"{"body": "happy", "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#.to_string(), // missing timestamp
-                        r#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#.to_string(), // ok
-                        r#"{"body": "happy2", "timestamp": 1628837062, "response_date": "2021-12-19T16:40:57+00:00", "response_time": 13, "response_payload": "YWJj"}"#.to_string(), // ok
-                        "{".to_string(),                    // invalid json
+                        Bytes::from_static(br#"{"body": "happy", "response_date": "2021-12-19T16:39:57+00:00", "response_time": 12, "response_payload": "YWJj"}"#), // missing timestamp
+                        Bytes::from_static(br#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#), // ok
+                        Bytes::from_static(br#"{"body": "happy2", "timestamp": 1628837062, "response_date": "2021-12-19T16:40:57+00:00", "response_time": 13, "response_payload": "YWJj"}"#), // ok
+                        Bytes::from_static(b"{"),                 // invalid json
                     ],
                 checkpoint_delta: checkpoint_delta.clone(),
             })
@@ -350,6 +714,8 @@ mod tests {
                 source_id: source_id.to_string(),
                 num_parse_errors: 1,
                 num_docs_with_missing_fields: 1,
+                num_duplicate_docs: 0,
+                num_discarded_docs: 0,
                 num_valid_docs: 2,
                 overall_num_bytes: 387,
             }
@@ -388,6 +754,48 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_doc_processor_csv_input_format() -> anyhow::Result<()> {
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let (indexer_mailbox, indexer_inbox) = create_test_mailbox();
+        let doc_processor = DocProcessor::new(
+            "my-index".to_string(),
+            "my-source".to_string(),
+            doc_mapper,
+            indexer_mailbox,
+        )
+        .set_input_format(SourceInputFormat::Csv, None);
+        let universe = Universe::new();
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        doc_processor_mailbox
+            .send_message(CsvHeader {
+                partition_id: PartitionId::default(),
+                header: Bytes::from_static(b"body,timestamp,response_time\n"),
+            })
+            .await?;
+        doc_processor_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                    Bytes::from_static(b"happy,1628837062,12\n"),
+                    Bytes::from_static(b"happy,1628837062,12,too many fields\n"),
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from(0..2),
+            })
+            .await?;
+        let counters = doc_processor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(counters.num_valid_docs, 1);
+        assert_eq!(counters.num_parse_errors, 1);
+        let batches: Vec<PreparedDocBatch> = indexer_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].docs.len(), 1);
+        assert_eq!(batches[0].docs[0].timestamp_opt, Some(1628837062));
+        Ok(())
+    }
+
     const DOCMAPPER_WITH_PARTITION_JSON: &str = r#"
         {
             "tag_fields": ["tenant"],
@@ -416,10 +824,18 @@ mod tests {
         doc_processor_mailbox
             .send_message(RawDocBatch {
                 docs: vec![
-                    r#"{"tenant": "tenant_1", "body": "first doc for tenant 1"}"#.to_string(),
-                    r#"{"tenant": "tenant_2", "body": "first doc for tenant 2"}"#.to_string(),
-                    r#"{"tenant": "tenant_1", "body": "second doc for tenant 1"}"#.to_string(),
-                    r#"{"tenant": "tenant_2", "body": "second doc for tenant 2"}"#.to_string(),
+                    Bytes::from_static(
+                        br#"{"tenant": "tenant_1", "body": "first doc for tenant 1"}"#,
+                    ),
+                    Bytes::from_static(
+                        br#"{"tenant": "tenant_2", "body": "first doc for tenant 2"}"#,
+                    ),
+                    Bytes::from_static(
+                        br#"{"tenant": "tenant_1", "body": "second doc for tenant 1"}"#,
+                    ),
+                    Bytes::from_static(
+                        br#"{"tenant": "tenant_2", "body": "second doc for tenant 2"}"#,
+                    ),
                 ],
                 checkpoint_delta: SourceCheckpointDelta::from(0..2),
             })
@@ -443,6 +859,56 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_doc_processor_drops_duplicate_docs() -> anyhow::Result<()> {
+        let doc_mapper: Arc<dyn DocMapper> = Arc::new(
+            serde_json::from_str::<DefaultDocMapper>(DOCMAPPER_WITH_PARTITION_JSON).unwrap(),
+        );
+        let (indexer_mailbox, indexer_inbox) = create_test_mailbox();
+        let dedup_settings = DedupSettings {
+            doc_id_field: "body".to_string(),
+            cache_capacity: NonZeroUsize::new(10).unwrap(),
+            enforce_on_merge: false,
+        };
+        let doc_processor = DocProcessor::new(
+            "my-index".to_string(),
+            "my-source".to_string(),
+            doc_mapper,
+            indexer_mailbox,
+        )
+        .set_dedup_settings(&dedup_settings)?;
+        let universe = Universe::new();
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        doc_processor_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                    Bytes::from_static(br#"{"tenant": "tenant_1", "body": "doc-1"}"#),
+                    Bytes::from_static(br#"{"tenant": "tenant_1", "body": "doc-1"}"#), // duplicate
+                    Bytes::from_static(br#"{"tenant": "tenant_2", "body": "doc-1"}"#), // other partition
+                    Bytes::from_static(br#"{"tenant": "tenant_1", "body": "doc-2"}"#),
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from(0..4),
+            })
+            .await?;
+        let counters = doc_processor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(counters.num_valid_docs, 3);
+        assert_eq!(counters.num_duplicate_docs, 1);
+        assert_eq!(counters.num_processed_docs(), 4);
+        assert_eq!(counters.num_invalid_docs(), 0);
+        let prepared_doc_batches: Vec<PreparedDocBatch> = indexer_inbox.drain_for_test_typed();
+        assert_eq!(prepared_doc_batches.len(), 1);
+        assert_eq!(prepared_doc_batches[0].docs.len(), 3);
+        assert_eq!(
+            prepared_doc_batches[0].checkpoint_delta,
+            SourceCheckpointDelta::from(0..4)
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_doc_processor_forward_publish_lock() {
         let doc_mapper = Arc::new(default_doc_mapper_for_test());
@@ -494,7 +960,7 @@ mod tests {
         doc_processor_mailbox
             .send_message(RawDocBatch {
                 docs: vec![
-                        r#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#.to_string(),
+                        Bytes::from_static(br#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#),
                     ],
                 checkpoint_delta: SourceCheckpointDelta::from(0..1),
             })
@@ -508,4 +974,89 @@ mod tests {
         let indexer_messages: Vec<PreparedDocBatch> = indexer_inbox.drain_for_test_typed();
         assert!(indexer_messages.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_doc_processor_dispatches_batches_to_peers() {
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let (indexer_mailbox, indexer_inbox) = create_test_mailbox();
+        let universe = Universe::new();
+        let mut peer_mailboxes = Vec::new();
+        let mut peer_handles = Vec::new();
+        for _ in 0..2 {
+            let peer = DocProcessor::new(
+                "my-index".to_string(),
+                "my-source".to_string(),
+                doc_mapper.clone(),
+                indexer_mailbox.clone(),
+            )
+            .into_peer();
+            let (peer_mailbox, peer_handle) = universe.spawn_builder().spawn(peer);
+            peer_mailboxes.push(peer_mailbox);
+            peer_handles.push(peer_handle);
+        }
+        let doc_processor = DocProcessor::new(
+            "my-index".to_string(),
+            "my-source".to_string(),
+            doc_mapper,
+            indexer_mailbox,
+        )
+        .set_peers(peer_mailboxes);
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        let doc = Bytes::from_static(
+            br#"{"body": "happy", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#,
+        );
+        for position in 0u64..3 {
+            for partition in 0..8 {
+                let checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+                    PartitionId::from(format!("partition-{}", partition)),
+                    Position::from(position),
+                    Position::from(position + 1),
+                );
+                doc_processor_mailbox
+                    .send_message(RawDocBatch::new(vec![doc.clone()], checkpoint_delta))
+                    .await
+                    .unwrap();
+            }
+        }
+        // A batch spanning several partitions must not overtake the previous batches of these
+        // partitions.
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        for partition in 0..8 {
+            checkpoint_delta
+                .record_partition_delta(
+                    PartitionId::from(format!("partition-{}", partition)),
+                    Position::from(3u64),
+                    Position::from(4u64),
+                )
+                .unwrap();
+        }
+        doc_processor_mailbox
+            .send_message(RawDocBatch::new(vec![doc.clone()], checkpoint_delta))
+            .await
+            .unwrap();
+        universe
+            .send_exit_with_success(&doc_processor_mailbox)
+            .await
+            .unwrap();
+        let (exit_status, doc_processor_counters) = doc_processor_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        let mut num_valid_docs = doc_processor_counters.num_valid_docs;
+        for peer_handle in peer_handles {
+            let (exit_status, peer_counters) = peer_handle.join().await;
+            assert!(matches!(exit_status, ActorExitStatus::Success));
+            num_valid_docs += peer_counters.num_valid_docs;
+        }
+        assert_eq!(num_valid_docs, 25);
+
+        let prepared_doc_batches: Vec<PreparedDocBatch> = indexer_inbox.drain_for_test_typed();
+        assert_eq!(prepared_doc_batches.len(), 25);
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        for prepared_doc_batch in prepared_doc_batches {
+            checkpoint_delta
+                .extend(prepared_doc_batch.checkpoint_delta)
+                .unwrap();
+        }
+        assert_eq!(checkpoint_delta.num_partitions(), 8);
+    }
 }
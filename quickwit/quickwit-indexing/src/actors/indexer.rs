@@ -20,6 +20,7 @@
 use std::collections::hash_map::Entry;
 use std::ops::RangeInclusive;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -30,7 +31,7 @@ use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::io::IoControls;
 use quickwit_common::runtimes::RuntimeType;
-use quickwit_config::IndexingSettings;
+use quickwit_config::{AdaptiveCommitSettings, DocstoreCompressor, IndexingSettings};
 use quickwit_doc_mapper::{DocMapper, SortBy, QUICKWIT_TOKENIZER_MANAGER};
 use quickwit_metastore::checkpoint::{IndexCheckpointDelta, SourceCheckpointDelta};
 use quickwit_metastore::Metastore;
@@ -43,14 +44,62 @@ use tracing::{info, info_span, Instrument, Span};
 use ulid::Ulid;
 
 use crate::actors::IndexSerializer;
+use crate::metrics::INDEXER_METRICS;
 use crate::models::{
     CommitTrigger, IndexedSplitBatchBuilder, IndexedSplitBuilder, IndexingDirectory,
     IndexingPipelineId, NewPublishLock, PreparedDoc, PreparedDocBatch, PublishLock,
 };
 
+/// Weight given to the most recent observation in the moving average of the arrival rate.
+const ARRIVAL_RATE_SMOOTHING_FACTOR: f64 = 0.5;
+
+/// Adaptive commit timeouts are not extended by less than this duration.
+const MIN_COMMIT_TIMEOUT_EXTENSION: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
 struct CommitTimeout {
     workbench_id: Ulid,
+    timeout: Duration,
+}
+
+/// Exponentially weighted moving average of the number of documents received per second,
+/// updated every time a workbench is committed.
+#[derive(Debug, Default)]
+struct ArrivalRate {
+    docs_per_sec_opt: Option<f64>,
+}
+
+impl ArrivalRate {
+    /// Returns the arrival rate as it would be after observing `num_docs` in `elapsed`.
+    fn blend(&self, num_docs: u64, elapsed: Duration) -> f64 {
+        let observed_docs_per_sec = num_docs as f64 / elapsed.as_secs_f64().max(1.0);
+        match self.docs_per_sec_opt {
+            Some(docs_per_sec) => {
+                ARRIVAL_RATE_SMOOTHING_FACTOR * observed_docs_per_sec
+                    + (1.0 - ARRIVAL_RATE_SMOOTHING_FACTOR) * docs_per_sec
+            }
+            None => observed_docs_per_sec,
+        }
+    }
+
+    fn record(&mut self, num_docs: u64, elapsed: Duration) {
+        self.docs_per_sec_opt = Some(self.blend(num_docs, elapsed));
+    }
+}
+
+/// Returns the time expected to receive `commit_num_docs_target` documents at the given arrival
+/// rate, bounded by the min and max commit timeouts.
+fn adaptive_commit_timeout(
+    adaptive_commit: &AdaptiveCommitSettings,
+    docs_per_sec: f64,
+) -> Duration {
+    let max_commit_timeout = adaptive_commit.max_commit_timeout();
+    let commit_timeout_secs = adaptive_commit.commit_num_docs_target as f64 / docs_per_sec;
+    // This also covers a null arrival rate, for which the division yields +inf.
+    if commit_timeout_secs >= max_commit_timeout.as_secs_f64() {
+        return max_commit_timeout;
+    }
+    Duration::from_secs_f64(commit_timeout_secs).max(adaptive_commit.min_commit_timeout())
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
@@ -74,9 +123,32 @@ struct IndexerState {
     publish_lock: PublishLock,
     schema: Schema,
     index_settings: IndexSettings,
+    arrival_rate: ArrivalRate,
+    /// Whether the documents are routed to several partitions, in which case the throughput and
+    /// the splits of each partition are recorded in the metrics.
+    is_partitioned: bool,
 }
 
 impl IndexerState {
+    /// Returns the timeout after which a newly created workbench is committed.
+    fn commit_timeout(&self) -> Duration {
+        let commit_timeout = self.indexing_settings.commit_timeout();
+        let adaptive_commit = if let Some(adaptive_commit) = &self.indexing_settings.adaptive_commit
+        {
+            adaptive_commit
+        } else {
+            return commit_timeout;
+        };
+        if let Some(docs_per_sec) = self.arrival_rate.docs_per_sec_opt {
+            adaptive_commit_timeout(adaptive_commit, docs_per_sec)
+        } else {
+            commit_timeout.clamp(
+                adaptive_commit.min_commit_timeout(),
+                adaptive_commit.max_commit_timeout(),
+            )
+        }
+    }
+
     fn create_indexed_split_builder(
         &self,
         partition_id: u64,
@@ -99,6 +171,7 @@ impl IndexerState {
             last_delete_opstamp,
             self.indexing_directory.scratch_directory().clone(),
             index_builder,
+            self.indexing_settings.resources.segment_memory_budget(),
             io_controls,
         )?;
         info!(
@@ -145,10 +218,13 @@ impl IndexerState {
             checkpoint_delta: IndexCheckpointDelta {
                 source_id: self.pipeline_id.source_id.clone(),
                 source_delta: SourceCheckpointDelta::default(),
+                publish_token_opt: None,
             },
             publish_lock: self.publish_lock.clone(),
             last_delete_opstamp,
             memory_usage: Byte::from_bytes(0),
+            created_at: Instant::now(),
+            elapsed_timeouts: Duration::default(),
         };
         Ok(workbench)
     }
@@ -164,14 +240,13 @@ impl IndexerState {
     ) -> anyhow::Result<&'a mut IndexingWorkbench> {
         if indexing_workbench_opt.is_none() {
             let indexing_workbench = self.create_workbench().await?;
+            let commit_timeout = self.commit_timeout();
             let commit_timeout_message = CommitTimeout {
                 workbench_id: indexing_workbench.workbench_id,
+                timeout: commit_timeout,
             };
-            ctx.schedule_self_msg(
-                self.indexing_settings.commit_timeout(),
-                commit_timeout_message,
-            )
-            .await;
+            ctx.schedule_self_msg(commit_timeout, commit_timeout_message)
+                .await;
             *indexing_workbench_opt = Some(indexing_workbench);
         }
         let current_indexing_workbench = indexing_workbench_opt.as_mut().context(
@@ -255,6 +330,16 @@ struct IndexingWorkbench {
     last_delete_opstamp: u64,
     // Number of bytes declared as used by tantivy.
     memory_usage: Byte,
+    created_at: Instant,
+    // Sum of the commit timeouts of this workbench that have expired. Unlike `created_at`, it
+    // follows the simulated time of the actor universe in tests.
+    elapsed_timeouts: Duration,
+}
+
+impl IndexingWorkbench {
+    fn elapsed(&self) -> Duration {
+        self.created_at.elapsed().max(self.elapsed_timeouts)
+    }
 }
 
 pub struct Indexer {
@@ -328,11 +413,33 @@ impl Handler<CommitTimeout> for Indexer {
         commit_timeout: CommitTimeout,
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
-        if let Some(indexing_workbench) = &self.indexing_workbench_opt {
+        if let Some(indexing_workbench) = &mut self.indexing_workbench_opt {
             // If this is a timeout for a different workbench, we must ignore it.
             if indexing_workbench.workbench_id != commit_timeout.workbench_id {
                 return Ok(());
             }
+            indexing_workbench.elapsed_timeouts += commit_timeout.timeout;
+
+            if let Some(adaptive_commit) = &self.indexer_state.indexing_settings.adaptive_commit {
+                // The arrival rate may have dropped since the timeout was scheduled, in which case
+                // we wait for more documents rather than emitting a tiny split.
+                let elapsed = indexing_workbench.elapsed();
+                let docs_per_sec = self
+                    .indexer_state
+                    .arrival_rate
+                    .blend(self.counters.num_docs_in_workbench, elapsed);
+                let extension =
+                    adaptive_commit_timeout(adaptive_commit, docs_per_sec).saturating_sub(elapsed);
+                if extension >= MIN_COMMIT_TIMEOUT_EXTENSION {
+                    let commit_timeout_message = CommitTimeout {
+                        workbench_id: indexing_workbench.workbench_id,
+                        timeout: extension,
+                    };
+                    ctx.schedule_self_msg(extension, commit_timeout_message)
+                        .await;
+                    return Ok(());
+                }
+            }
         }
         self.send_to_serializer(CommitTrigger::Timeout, ctx).await?;
         Ok(())
@@ -368,6 +475,16 @@ impl Handler<NewPublishLock> for Indexer {
     }
 }
 
+fn docstore_compression(indexing_settings: &IndexingSettings) -> Compressor {
+    match indexing_settings.docstore_compressor() {
+        DocstoreCompressor::Zstd => Compressor::Zstd(ZstdCompressor {
+            compression_level: Some(indexing_settings.docstore_compression_level),
+        }),
+        DocstoreCompressor::Lz4 => Compressor::Lz4,
+        DocstoreCompressor::None => Compressor::None,
+    }
+}
+
 impl Indexer {
     pub fn new(
         pipeline_id: IndexingPipelineId,
@@ -378,6 +495,7 @@ impl Indexer {
         index_serializer_mailbox: Mailbox<IndexSerializer>,
     ) -> Self {
         let schema = doc_mapper.schema();
+        let is_partitioned = doc_mapper.max_num_partitions().get() > 1;
         let sort_by_field_opt = match indexing_settings.sort_by() {
             SortBy::DocId | SortBy::Score { .. } => None,
             SortBy::FastField { field_name, order } => Some(IndexSortByField {
@@ -388,9 +506,7 @@ impl Indexer {
         let index_settings = IndexSettings {
             sort_by_field: sort_by_field_opt,
             docstore_blocksize: indexing_settings.docstore_blocksize,
-            docstore_compression: Compressor::Zstd(ZstdCompressor {
-                compression_level: Some(indexing_settings.docstore_compression_level),
-            }),
+            docstore_compression: docstore_compression(&indexing_settings),
             docstore_compress_dedicated_thread: true,
         };
         let publish_lock = PublishLock::default();
@@ -403,6 +519,8 @@ impl Indexer {
                 publish_lock,
                 schema,
                 index_settings,
+                arrival_rate: ArrivalRate::default(),
+                is_partitioned,
             },
             index_serializer_mailbox,
             indexing_workbench_opt: None,
@@ -443,6 +561,19 @@ impl Indexer {
             self.send_to_serializer(CommitTrigger::NumDocsLimit, ctx)
                 .await?;
         }
+        if let Some(adaptive_commit) = &self.indexer_state.indexing_settings.adaptive_commit {
+            let reached_commit_num_docs_target = self.counters.num_docs_in_workbench
+                >= adaptive_commit.commit_num_docs_target as u64;
+            let reached_min_commit_timeout = self
+                .indexing_workbench_opt
+                .as_ref()
+                .map(|workbench| workbench.elapsed() >= adaptive_commit.min_commit_timeout())
+                .unwrap_or(false);
+            if reached_commit_num_docs_target && reached_min_commit_timeout {
+                self.send_to_serializer(CommitTrigger::NumDocsLimit, ctx)
+                    .await?;
+            }
+        }
         fail_point!("indexer:batch:after");
         Ok(())
     }
@@ -453,17 +584,23 @@ impl Indexer {
         commit_trigger: CommitTrigger,
         ctx: &ActorContext<Self>,
     ) -> anyhow::Result<()> {
+        let indexing_workbench =
+            if let Some(indexing_workbench) = self.indexing_workbench_opt.take() {
+                indexing_workbench
+            } else {
+                return Ok(());
+            };
+        self.indexer_state.arrival_rate.record(
+            self.counters.num_docs_in_workbench,
+            indexing_workbench.elapsed(),
+        );
         let IndexingWorkbench {
             indexed_splits,
             checkpoint_delta,
             publish_lock,
             batch_parent_span,
             ..
-        } = if let Some(indexing_workbench) = self.indexing_workbench_opt.take() {
-            indexing_workbench
-        } else {
-            return Ok(());
-        };
+        } = indexing_workbench;
 
         let splits: Vec<IndexedSplitBuilder> = indexed_splits.into_values().collect();
 
@@ -497,6 +634,11 @@ impl Indexer {
         }
         let num_splits = splits.len() as u64;
         let split_ids = splits.iter().map(|split| split.split_id()).join(",");
+        if self.indexer_state.is_partitioned {
+            for split in &splits {
+                record_partition_metrics(&self.indexer_state.pipeline_id, split);
+            }
+        }
 
         info!(commit_trigger=?commit_trigger, split_ids=%split_ids, num_docs=self.counters.num_docs_in_workbench, "send-to-index-serializer");
         let span_id = batch_parent_span.id();
@@ -519,6 +661,38 @@ impl Indexer {
     }
 }
 
+/// Records the documents and the split indexed for a partition.
+fn record_partition_metrics(pipeline_id: &IndexingPipelineId, split: &IndexedSplitBuilder) {
+    let partition_id = split.split_attrs.partition_id.to_string();
+    let label_values = [
+        pipeline_id.index_id.as_str(),
+        pipeline_id.source_id.as_str(),
+        partition_id.as_str(),
+    ];
+    let num_docs = split.split_attrs.num_docs;
+    let num_bytes = split.split_attrs.uncompressed_docs_size_in_bytes;
+    INDEXER_METRICS
+        .partition_docs_total
+        .with_label_values(&label_values)
+        .inc_by(num_docs);
+    INDEXER_METRICS
+        .partition_bytes_total
+        .with_label_values(&label_values)
+        .inc_by(num_bytes);
+    INDEXER_METRICS
+        .partition_splits_total
+        .with_label_values(&label_values)
+        .inc();
+    INDEXER_METRICS
+        .partition_split_num_docs
+        .with_label_values(&label_values)
+        .observe(num_docs as f64);
+    INDEXER_METRICS
+        .partition_split_num_bytes
+        .with_label_values(&label_values)
+        .observe(num_bytes as f64);
+}
+
 #[cfg(test)]
 mod tests {
     use std::fmt::Write;
@@ -546,6 +720,30 @@ mod tests {
         assert_eq!(time_range, Some(1628664112..=1628665112))
     }
 
+    #[test]
+    fn test_docstore_compression() {
+        let mut indexing_settings = IndexingSettings {
+            docstore_compression_level: 3,
+            ..Default::default()
+        };
+        assert!(matches!(
+            docstore_compression(&indexing_settings),
+            Compressor::Zstd(ZstdCompressor {
+                compression_level: Some(3)
+            })
+        ));
+        indexing_settings.docstore_compressor = Some(DocstoreCompressor::Lz4);
+        assert!(matches!(
+            docstore_compression(&indexing_settings),
+            Compressor::Lz4
+        ));
+        indexing_settings.docstore_compressor = Some(DocstoreCompressor::None);
+        assert!(matches!(
+            docstore_compression(&indexing_settings),
+            Compressor::None
+        ));
+    }
+
     #[tokio::test]
     async fn test_indexer_trigger_on_target_num_docs() -> anyhow::Result<()> {
         let pipeline_id = IndexingPipelineId {
@@ -852,6 +1050,115 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_adaptive_commit_timeout() {
+        let adaptive_commit = AdaptiveCommitSettings {
+            min_commit_timeout_secs: 10,
+            max_commit_timeout_secs: 600,
+            commit_num_docs_target: 1_000,
+        };
+        assert_eq!(
+            adaptive_commit_timeout(&adaptive_commit, 0.0),
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            adaptive_commit_timeout(&adaptive_commit, 1.0),
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            adaptive_commit_timeout(&adaptive_commit, 10.0),
+            Duration::from_secs(100)
+        );
+        assert_eq!(
+            adaptive_commit_timeout(&adaptive_commit, 1_000.0),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn test_arrival_rate() {
+        let mut arrival_rate = ArrivalRate::default();
+        assert_eq!(arrival_rate.blend(100, Duration::from_secs(10)), 10.0);
+        // Durations shorter than a second are rounded up to avoid overestimating the rate.
+        assert_eq!(arrival_rate.blend(100, Duration::from_millis(10)), 100.0);
+        arrival_rate.record(100, Duration::from_secs(10));
+        assert_eq!(arrival_rate.docs_per_sec_opt, Some(10.0));
+        arrival_rate.record(0, Duration::from_secs(10));
+        assert_eq!(arrival_rate.docs_per_sec_opt, Some(5.0));
+    }
+
+    #[tokio::test]
+    async fn test_indexer_extends_commit_timeout_on_low_traffic() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let schema = doc_mapper.schema();
+        let body_field = schema.get_field("body").unwrap();
+        let indexing_directory = IndexingDirectory::for_test().await;
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.adaptive_commit = Some(AdaptiveCommitSettings {
+            min_commit_timeout_secs: 10,
+            max_commit_timeout_secs: 300,
+            commit_num_docs_target: 1_000,
+        });
+        let (index_serializer_mailbox, index_serializer_inbox) = create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_last_delete_opstamp()
+            .returning(move |_| Ok(10));
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            index_serializer_mailbox,
+        );
+        let universe = Universe::new();
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+        indexer_mailbox
+            .send_message(PreparedDocBatch {
+                docs: vec![PreparedDoc {
+                    doc: doc!(body_field=>"this is a test document"),
+                    timestamp_opt: None,
+                    partition: 1,
+                    num_bytes: 30,
+                }],
+                checkpoint_delta: SourceCheckpointDelta::from(0..1),
+            })
+            .await
+            .unwrap();
+        // The first commit timeout is the regular one, but a single document over 60 seconds
+        // is far from the commit target so the timeout is extended up to the max.
+        universe.simulate_time_shift(Duration::from_secs(61)).await;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_split_batches_emitted, 0);
+        assert_eq!(indexer_counters.num_docs_in_workbench, 1);
+
+        universe.simulate_time_shift(Duration::from_secs(240)).await;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(
+            indexer_counters,
+            IndexerCounters {
+                num_splits_emitted: 1,
+                num_split_batches_emitted: 1,
+                num_docs_in_workbench: 0,
+            }
+        );
+        let indexed_split_batches: Vec<IndexedSplitBatchBuilder> =
+            index_serializer_inbox.drain_for_test_typed();
+        assert_eq!(indexed_split_batches.len(), 1);
+        assert_eq!(
+            indexed_split_batches[0].commit_trigger,
+            CommitTrigger::Timeout
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_indexer_eof() -> anyhow::Result<()> {
         let pipeline_id = IndexingPipelineId {
@@ -964,6 +1271,14 @@ mod tests {
                 assert_eq!("test-index", index_id);
                 Ok(10)
             });
+        let partition_docs_total_before = INDEXER_METRICS
+            .partition_docs_total
+            .with_label_values(&["test-index", "test-source", "3"])
+            .get();
+        let partition_split_count_before = INDEXER_METRICS
+            .partition_split_num_docs
+            .with_label_values(&["test-index", "test-source", "3"])
+            .get_sample_count();
         let indexer = Indexer::new(
             pipeline_id,
             doc_mapper,
@@ -1023,6 +1338,20 @@ mod tests {
         let split_batches: Vec<IndexedSplitBatchBuilder> = packager_inbox.drain_for_test_typed();
         assert_eq!(split_batches.len(), 1);
         assert_eq!(split_batches[0].splits.len(), 2);
+
+        let partition_docs_total = INDEXER_METRICS.partition_docs_total.with_label_values(&[
+            "test-index",
+            "test-source",
+            "3",
+        ]);
+        assert_eq!(partition_docs_total.get() - partition_docs_total_before, 1);
+        let partition_split_num_docs = INDEXER_METRICS
+            .partition_split_num_docs
+            .with_label_values(&["test-index", "test-source", "3"]);
+        assert_eq!(
+            partition_split_num_docs.get_sample_count() - partition_split_count_before,
+            1
+        );
         Ok(())
     }
 
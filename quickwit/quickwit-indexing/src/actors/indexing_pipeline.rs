@@ -22,13 +22,18 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use itertools::Itertools;
 use quickwit_actors::{
     create_mailbox, Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Health, Mailbox,
     QueueCapacity, Supervisable,
 };
+use quickwit_common::events::{record_event, Event, EventType};
 use quickwit_common::KillSwitch;
-use quickwit_config::{IndexingSettings, SourceConfig};
+use quickwit_config::{IndexingSettings, SourceConfig, SourceParams};
 use quickwit_doc_mapper::DocMapper;
+use quickwit_metastore::checkpoint::{
+    IndexCheckpointDelta, PublishToken, SourceCheckpoint, SourceCheckpointDelta,
+};
 use quickwit_metastore::{Metastore, MetastoreError};
 use quickwit_storage::Storage;
 use tokio::join;
@@ -41,9 +46,14 @@ use crate::actors::index_serializer::IndexSerializer;
 use crate::actors::publisher::PublisherType;
 use crate::actors::sequencer::Sequencer;
 use crate::actors::uploader::UploaderType;
-use crate::actors::{Indexer, Packager, Publisher, Uploader};
+use crate::actors::{Indexer, Packager, ParquetSidecarWriter, Publisher, Uploader};
+use crate::metrics::INDEXER_METRICS;
 use crate::models::{IndexingDirectory, IndexingPipelineId, IndexingStatistics, Observe};
-use crate::source::{quickwit_supported_sources, SourceActor, SourceExecutionContext};
+use crate::source::{
+    backfill_completion_delta, backfill_phase_source_config, fetch_backfill_start_delta,
+    quickwit_supported_sources, BackfillPhase, SourceActor, SourceExecutionContext,
+    SourceRateLimiter,
+};
 use crate::split_store::IndexingSplitStore;
 use crate::SplitsUpdateMailbox;
 
@@ -72,12 +82,14 @@ static SPAWN_PIPELINE_SEMAPHORE: Semaphore = Semaphore::const_new(10);
 pub struct IndexingPipelineHandles {
     pub source: ActorHandle<SourceActor>,
     pub doc_processor: ActorHandle<DocProcessor>,
+    pub doc_processor_peers: Vec<ActorHandle<DocProcessor>>,
     pub indexer: ActorHandle<Indexer>,
     pub index_serializer: ActorHandle<IndexSerializer>,
     pub packager: ActorHandle<Packager>,
     pub uploader: ActorHandle<Uploader>,
     pub sequencer: ActorHandle<Sequencer<Publisher>>,
     pub publisher: ActorHandle<Publisher>,
+    pub parquet_sidecar_writer_opt: Option<ActorHandle<ParquetSidecarWriter>>,
 }
 
 // Messages
@@ -90,6 +102,10 @@ pub struct Spawn {
     retry_count: usize,
 }
 
+/// Terminates the actors of the pipeline and spawns a new generation of the pipeline.
+#[derive(Debug)]
+pub struct Restart;
+
 pub struct IndexingPipeline {
     params: IndexingPipelineParams,
     previous_generations_statistics: IndexingStatistics,
@@ -117,6 +133,15 @@ impl Actor for IndexingPipeline {
         self.handle(Supervise, ctx).await?;
         Ok(())
     }
+
+    async fn finalize(
+        &mut self,
+        _exit_status: &ActorExitStatus,
+        _ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        self.remove_statistics_metrics();
+        Ok(())
+    }
 }
 
 impl IndexingPipeline {
@@ -130,9 +155,62 @@ impl IndexingPipeline {
         }
     }
 
+    fn metric_labels(&self) -> [String; 3] {
+        let pipeline_id = &self.params.pipeline_id;
+        [
+            pipeline_id.index_id.clone(),
+            pipeline_id.source_id.clone(),
+            pipeline_id.pipeline_ord.to_string(),
+        ]
+    }
+
+    /// Exports the pipeline statistics as Prometheus metrics labeled by index, source, and
+    /// pipeline ordinal.
+    fn export_statistics_metrics(&self) {
+        let labels = self.metric_labels();
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        let gauge_values = [
+            (&INDEXER_METRICS.pipeline_num_docs, self.statistics.num_docs),
+            (
+                &INDEXER_METRICS.pipeline_num_invalid_docs,
+                self.statistics.num_invalid_docs,
+            ),
+            (
+                &INDEXER_METRICS.pipeline_num_published_splits,
+                self.statistics.num_published_splits,
+            ),
+            (
+                &INDEXER_METRICS.pipeline_uploaded_num_bytes,
+                self.statistics.total_size_splits,
+            ),
+            (
+                &INDEXER_METRICS.pipeline_pending_splits,
+                self.statistics.num_pending_splits(),
+            ),
+        ];
+        for (gauge_vec, value) in gauge_values {
+            gauge_vec.with_label_values(&labels).set(value as i64);
+        }
+    }
+
+    fn remove_statistics_metrics(&self) {
+        let labels = self.metric_labels();
+        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+        for gauge_vec in [
+            &INDEXER_METRICS.pipeline_num_docs,
+            &INDEXER_METRICS.pipeline_num_invalid_docs,
+            &INDEXER_METRICS.pipeline_num_published_splits,
+            &INDEXER_METRICS.pipeline_uploaded_num_bytes,
+            &INDEXER_METRICS.pipeline_pending_splits,
+        ] {
+            // The metrics are not registered if the pipeline never got observed.
+            let _ = gauge_vec.remove_label_values(&labels);
+        }
+    }
+
     fn supervisables(&self) -> Vec<&dyn Supervisable> {
         if let Some(handles) = &self.handles {
-            let supervisables: Vec<&dyn Supervisable> = vec![
+            let mut supervisables: Vec<&dyn Supervisable> = vec![
                 &handles.source,
                 &handles.doc_processor,
                 &handles.indexer,
@@ -142,6 +220,12 @@ impl IndexingPipeline {
                 &handles.sequencer,
                 &handles.publisher,
             ];
+            for doc_processor_peer in &handles.doc_processor_peers {
+                supervisables.push(doc_processor_peer);
+            }
+            if let Some(parquet_sidecar_writer) = &handles.parquet_sidecar_writer_opt {
+                supervisables.push(parquet_sidecar_writer);
+            }
             supervisables
         } else {
             Vec::new()
@@ -176,6 +260,7 @@ impl IndexingPipeline {
                 healthy_actors=?healthy_actors,
                 failed_or_unhealthy_actors=?failure_or_unhealthy_actors,
                 success_actors=?success_actors,
+                killed_by=?self.kill_switch.killed_by(),
                 "Indexing pipeline failure."
             );
             return Health::FailureOrUnhealthy;
@@ -217,7 +302,12 @@ impl IndexingPipeline {
     async fn spawn_pipeline(&mut self, ctx: &ActorContext<Self>) -> anyhow::Result<()> {
         let _spawn_pipeline_permit = SPAWN_PIPELINE_SEMAPHORE.acquire().await.expect("Failed to acquire spawn pipeline permit. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.");
         self.statistics.num_spawn_attempts += 1;
-        self.kill_switch = ctx.kill_switch().child();
+        self.kill_switch = ctx.kill_switch().named_child(&format!(
+            "indexing-pipeline:{}:{}:{}",
+            self.params.pipeline_id.index_id,
+            self.params.pipeline_id.source_id,
+            self.params.pipeline_id.pipeline_ord
+        ));
         info!(
             index_id=%self.params.pipeline_id.index_id,
             source_id=%self.params.pipeline_id.source_id,
@@ -225,16 +315,37 @@ impl IndexingPipeline {
             root_dir=%self.params.indexing_directory.path().display(),
             "Spawning indexing pipeline.",
         );
+        let publish_token = self.publish_token().await?;
         let (source_mailbox, source_inbox) =
             create_mailbox::<SourceActor>("SourceActor".to_string(), QueueCapacity::Unbounded);
 
         // Publisher
-        let publisher = Publisher::new(
+        let mut publisher = Publisher::new(
             PublisherType::MainPublisher,
             self.params.metastore.clone(),
-            Some(self.params.merge_planner_mailbox.clone()),
+            self.params.merge_planner_mailbox_opt.clone(),
             Some(source_mailbox.clone()),
         );
+        publisher.set_publish_token(publish_token.clone());
+        publisher.set_max_publish_batch_size(self.params.max_publish_batch_size);
+        let parquet_sidecar_writer_handler_opt =
+            if let Some(parquet_sidecar) = &self.params.indexing_settings.parquet_sidecar {
+                let parquet_sidecar_writer = ParquetSidecarWriter::new(
+                    self.params.pipeline_id.index_id.clone(),
+                    parquet_sidecar.fields.clone(),
+                    self.params.storage.clone(),
+                    self.params.split_store.clone(),
+                    self.params.indexing_directory.scratch_directory().clone(),
+                );
+                let (parquet_sidecar_writer_mailbox, parquet_sidecar_writer_handler) = ctx
+                    .spawn_actor()
+                    .set_kill_switch(self.kill_switch.clone())
+                    .spawn(parquet_sidecar_writer);
+                publisher.set_parquet_sidecar_writer_mailbox(parquet_sidecar_writer_mailbox);
+                Some(parquet_sidecar_writer_handler)
+            } else {
+                None
+            };
         let (publisher_mailbox, publisher_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -261,7 +372,14 @@ impl IndexingPipeline {
 
         // Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let packager = Packager::new("Packager", tag_fields, uploader_mailbox);
+        let bloom_filter_fields = self.params.doc_mapper.bloom_filter_named_fields()?;
+        let packager = Packager::new(
+            "Packager",
+            tag_fields,
+            bloom_filter_fields,
+            self.params.indexing_settings.hotcache.clone(),
+            uploader_mailbox,
+        );
         let (packager_mailbox, packager_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -288,12 +406,31 @@ impl IndexingPipeline {
             .set_kill_switch(self.kill_switch.clone())
             .spawn(indexer);
 
-        let doc_processor = DocProcessor::new(
-            self.params.pipeline_id.index_id.clone(),
-            self.params.pipeline_id.source_id.clone(),
-            self.params.doc_mapper.clone(),
-            indexer_mailbox,
-        );
+        // Doc processors
+        let num_doc_processors = self.params.indexing_settings.resources.num_doc_processors();
+        let mut doc_processor_peer_mailboxes = Vec::with_capacity(num_doc_processors - 1);
+        let mut doc_processor_peer_handlers = Vec::with_capacity(num_doc_processors - 1);
+        for _ in 1..num_doc_processors {
+            let doc_processor_peer = self
+                .make_doc_processor(indexer_mailbox.clone())?
+                .into_peer();
+            let (doc_processor_peer_mailbox, doc_processor_peer_handler) = ctx
+                .spawn_actor()
+                .set_kill_switch(self.kill_switch.clone())
+                .spawn(doc_processor_peer);
+            doc_processor_peer_mailboxes.push(doc_processor_peer_mailbox);
+            doc_processor_peer_handlers.push(doc_processor_peer_handler);
+        }
+        let rate_limiter_opt = self
+            .params
+            .source_config
+            .rate_limit_per_sec
+            .as_ref()
+            .map(SourceRateLimiter::new);
+        let doc_processor = self
+            .make_doc_processor(indexer_mailbox)?
+            .set_peers(doc_processor_peer_mailboxes)
+            .set_rate_limiter(rate_limiter_opt.clone());
         let (doc_processor_mailbox, doc_processor_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -305,18 +442,23 @@ impl IndexingPipeline {
             .metastore
             .index_metadata(&self.params.pipeline_id.index_id)
             .await?;
-        let source_checkpoint = index_metadata
+        let mut source_checkpoint = index_metadata
             .checkpoint
             .source_checkpoint(&self.params.pipeline_id.source_id)
             .cloned()
             .unwrap_or_default(); // TODO Have a stricter check.
+        let source_config = self
+            .resolve_source_config(&mut source_checkpoint, publish_token)
+            .await?;
         let source = quickwit_supported_sources()
             .load_source(
                 Arc::new(SourceExecutionContext {
                     metastore: self.params.metastore.clone(),
                     index_id: self.params.pipeline_id.index_id.clone(),
                     queues_dir_path: self.params.queues_dir_path.clone(),
-                    source_config: self.params.source_config.clone(),
+                    source_config,
+                    node_id: self.params.pipeline_id.node_id.clone(),
+                    pipeline_ord: self.params.pipeline_id.pipeline_ord,
                 }),
                 source_checkpoint,
             )
@@ -324,6 +466,7 @@ impl IndexingPipeline {
         let actor_source = SourceActor {
             source,
             doc_processor_mailbox,
+            rate_limiter_opt,
         };
         let (_source_mailbox, source_handler) = ctx
             .spawn_actor()
@@ -337,16 +480,155 @@ impl IndexingPipeline {
         self.handles = Some(IndexingPipelineHandles {
             source: source_handler,
             doc_processor: doc_processor_handler,
+            doc_processor_peers: doc_processor_peer_handlers,
             indexer: indexer_handler,
             index_serializer: index_serializer_handler,
             packager: packager_handler,
             uploader: uploader_handler,
             sequencer: sequencer_handler,
             publisher: publisher_handler,
+            parquet_sidecar_writer_opt: parquet_sidecar_writer_handler_opt,
         });
         Ok(())
     }
 
+    /// Returns the config of the source to load. For a backfill source, this is the config of the
+    /// historical or live source, depending on the phase of the backfill recorded in the
+    /// checkpoint. Starting a backfill records the boundary between the historical and live data.
+    async fn resolve_source_config(
+        &self,
+        source_checkpoint: &mut SourceCheckpoint,
+        publish_token: PublishToken,
+    ) -> anyhow::Result<SourceConfig> {
+        let source_config = &self.params.source_config;
+        let backfill_params =
+            if let SourceParams::Backfill(backfill_params) = &source_config.source_params {
+                backfill_params
+            } else {
+                return Ok(source_config.clone());
+            };
+        let mut phase = BackfillPhase::from_checkpoint(source_checkpoint);
+
+        if phase == BackfillPhase::NotStarted {
+            let source_delta = fetch_backfill_start_delta(backfill_params).await?;
+            self.publish_backfill_delta(source_delta.clone(), publish_token)
+                .await?;
+            source_checkpoint.try_apply_delta(source_delta)?;
+            phase = BackfillPhase::Historical;
+            info!(
+                index_id=%self.params.pipeline_id.index_id,
+                source_id=%self.params.pipeline_id.source_id,
+                checkpoint=?source_checkpoint,
+                "Recorded backfill boundary."
+            );
+        }
+        Ok(backfill_phase_source_config(
+            source_config,
+            backfill_params,
+            phase,
+        ))
+    }
+
+    /// Ends the historical phase of a backfill source once the historical source is drained.
+    /// Returns whether the pipeline must be respawned to consume the live source.
+    async fn complete_backfill(&self) -> anyhow::Result<bool> {
+        if !matches!(
+            self.params.source_config.source_params,
+            SourceParams::Backfill(_)
+        ) {
+            return Ok(false);
+        }
+        let pipeline_id = &self.params.pipeline_id;
+        let index_metadata = self
+            .params
+            .metastore
+            .index_metadata(&pipeline_id.index_id)
+            .await?;
+        let phase = index_metadata
+            .checkpoint
+            .source_checkpoint(&pipeline_id.source_id)
+            .map(BackfillPhase::from_checkpoint)
+            .unwrap_or(BackfillPhase::NotStarted);
+
+        if phase != BackfillPhase::Historical {
+            return Ok(false);
+        }
+        let publish_token = self.publish_token().await?;
+        self.publish_backfill_delta(backfill_completion_delta(), publish_token)
+            .await?;
+        info!(
+            index_id=%pipeline_id.index_id,
+            source_id=%pipeline_id.source_id,
+            "Backfill historical source drained, switching to the live source."
+        );
+        Ok(true)
+    }
+
+    /// Returns the publish token of the pipeline: the token handed out by the indexing scheduler
+    /// with the assignment of the pipeline if any, a new token acquired from the metastore
+    /// otherwise. Acquiring a new token before reading the checkpoint ensures that the pipelines of
+    /// previous generations, possibly running on other nodes, can no longer publish.
+    async fn publish_token(&self) -> anyhow::Result<PublishToken> {
+        if let Some(publish_token) = &self.params.publish_token_opt {
+            return Ok(publish_token.clone());
+        }
+        let pipeline_id = &self.params.pipeline_id;
+        let publish_token = self
+            .params
+            .metastore
+            .acquire_publish_token(
+                &pipeline_id.index_id,
+                &pipeline_id.source_id,
+                &pipeline_id.pipeline_ord.to_string(),
+            )
+            .await?;
+        Ok(publish_token)
+    }
+
+    async fn publish_backfill_delta(
+        &self,
+        source_delta: SourceCheckpointDelta,
+        publish_token: PublishToken,
+    ) -> anyhow::Result<()> {
+        let checkpoint_delta = IndexCheckpointDelta {
+            source_id: self.params.pipeline_id.source_id.clone(),
+            source_delta,
+            publish_token_opt: Some(publish_token),
+        };
+        self.params
+            .metastore
+            .publish_splits(
+                &self.params.pipeline_id.index_id,
+                &[],
+                &[],
+                Some(checkpoint_delta),
+            )
+            .await?;
+        Ok(())
+    }
+
+    fn make_doc_processor(
+        &self,
+        indexer_mailbox: Mailbox<Indexer>,
+    ) -> anyhow::Result<DocProcessor> {
+        let mut doc_processor = DocProcessor::new(
+            self.params.pipeline_id.index_id.clone(),
+            self.params.pipeline_id.source_id.clone(),
+            self.params.doc_mapper.clone(),
+            indexer_mailbox,
+        );
+        if let Some(dedup_settings) = &self.params.indexing_settings.dedup {
+            doc_processor = doc_processor.set_dedup_settings(dedup_settings)?;
+        }
+        doc_processor = doc_processor
+            .set_input_format(
+                self.params.source_config.input_format(),
+                self.params.source_config.csv_options.as_ref(),
+            )
+            .set_transform(&self.params.source_config.transform)?;
+        Ok(doc_processor)
+    }
+
     async fn terminate(&mut self) {
         self.kill_switch.kill();
         if let Some(handlers) = self.handles.take() {
@@ -357,6 +639,9 @@ impl IndexingPipeline {
                 handlers.uploader.kill(),
                 handlers.publisher.kill(),
             );
+            if let Some(parquet_sidecar_writer) = handlers.parquet_sidecar_writer_opt {
+                parquet_sidecar_writer.kill().await;
+            }
         }
     }
 }
@@ -376,7 +661,7 @@ impl Handler<Observe> for IndexingPipeline {
                 handles.uploader.observe(),
                 handles.publisher.observe(),
             );
-            self.statistics = self
+            let mut statistics = self
                 .previous_generations_statistics
                 .clone()
                 .add_actor_counters(
@@ -384,11 +669,19 @@ impl Handler<Observe> for IndexingPipeline {
                     &*indexer_counters,
                     &*uploader_counters,
                     &*publisher_counters,
-                )
+                );
+            for doc_processor_peer in &handles.doc_processor_peers {
+                let doc_processor_peer_counters = doc_processor_peer.observe().await;
+                statistics = statistics.add_doc_processor_counters(&*doc_processor_peer_counters);
+            }
+            self.statistics = statistics
                 .set_generation(self.statistics.generation)
-                .set_num_spawn_attempts(self.statistics.num_spawn_attempts);
+                .set_num_spawn_attempts(self.statistics.num_spawn_attempts)
+                .set_last_error(self.statistics.last_error.take());
+            self.export_statistics_metrics();
         }
-        ctx.schedule_self_msg(Duration::from_secs(1), Observe).await;
+        ctx.schedule_self_msg_coalesced(Duration::from_secs(1), Observe)
+            .await;
         Ok(())
     }
 }
@@ -406,16 +699,43 @@ impl Handler<Supervise> for IndexingPipeline {
             match self.healthcheck() {
                 Health::Healthy => {}
                 Health::FailureOrUnhealthy => {
+                    let failed_actors = self
+                        .supervisables()
+                        .into_iter()
+                        .filter(|supervisable| supervisable.health() == Health::FailureOrUnhealthy)
+                        .map(|supervisable| supervisable.name())
+                        .join(", ");
+                    self.statistics.last_error =
+                        Some(format!("Failed or unhealthy actors: {failed_actors}."));
+                    let pipeline_id = &self.params.pipeline_id;
+                    record_event(
+                        Event::new(EventType::PipelineRestart, "Indexing pipeline failed.")
+                            .with_index_id(&pipeline_id.index_id)
+                            .with_attribute("node_id", &pipeline_id.node_id)
+                            .with_attribute("source_id", &pipeline_id.source_id)
+                            .with_attribute("pipeline_ord", pipeline_id.pipeline_ord)
+                            .with_attribute("generation", self.generation()),
+                    );
                     self.terminate().await;
                     ctx.schedule_self_msg(quickwit_actors::HEARTBEAT, Spawn { retry_count: 0 })
                         .await;
                 }
-                Health::Success => {
-                    return Err(ActorExitStatus::Success);
-                }
+                Health::Success => match self.complete_backfill().await {
+                    Ok(true) => {
+                        self.terminate().await;
+                        ctx.schedule_self_msg(quickwit_actors::HEARTBEAT, Spawn { retry_count: 0 })
+                            .await;
+                    }
+                    Ok(false) => {
+                        return Err(ActorExitStatus::Success);
+                    }
+                    Err(error) => {
+                        error!(error=?error, "Failed to complete backfill, retrying.");
+                    }
+                },
             }
         }
-        ctx.schedule_self_msg(quickwit_actors::HEARTBEAT, Supervise)
+        ctx.schedule_self_msg_coalesced(quickwit_actors::HEARTBEAT, Supervise)
             .await;
         Ok(())
     }
@@ -441,6 +761,7 @@ impl Handler<Spawn> for IndexingPipeline {
                 info!(error = ?spawn_error, "Could not spawn pipeline, index might have been deleted.");
                 return Err(ActorExitStatus::Success);
             }
+            self.statistics.last_error = Some(format!("{spawn_error:#}"));
             let retry_delay = wait_duration_before_retry(spawn.retry_count);
             error!(error = ?spawn_error, retry_count = spawn.retry_count, retry_delay = ?retry_delay, "Error while spawning indexing pipeline, retrying after some time.");
             ctx.schedule_self_msg(
@@ -455,6 +776,41 @@ impl Handler<Spawn> for IndexingPipeline {
     }
 }
 
+#[async_trait]
+impl Handler<Restart> for IndexingPipeline {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Restart,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        // A pipeline without handles is already waiting to be respawned.
+        if self.handles.is_none() {
+            return Ok(());
+        }
+        let pipeline_id = &self.params.pipeline_id;
+        info!(
+            pipeline_id=?pipeline_id,
+            generation=self.generation(),
+            "Restarting indexing pipeline on request."
+        );
+        record_event(
+            Event::new(
+                EventType::PipelineRestart,
+                "Indexing pipeline restarted on request.",
+            )
+            .with_index_id(&pipeline_id.index_id)
+            .with_attribute("node_id", &pipeline_id.node_id)
+            .with_attribute("source_id", &pipeline_id.source_id)
+            .with_attribute("pipeline_ord", pipeline_id.pipeline_ord)
+            .with_attribute("generation", self.generation()),
+        );
+        self.terminate().await;
+        self.handle(Spawn::default(), ctx).await
+    }
+}
+
 pub struct IndexingPipelineParams {
     pub pipeline_id: IndexingPipelineId,
     pub doc_mapper: Arc<dyn DocMapper>,
@@ -467,17 +823,25 @@ pub struct IndexingPipelineParams {
     pub split_store: IndexingSplitStore,
     pub max_concurrent_split_uploads_index: usize,
     pub max_concurrent_split_uploads_merge: usize,
-    pub merge_planner_mailbox: Mailbox<MergePlanner>,
+    pub max_publish_batch_size: usize,
+    /// Merge planner notified of the published splits. `None` when the splits of the index are
+    /// merged by a remote merge node.
+    pub merge_planner_mailbox_opt: Option<Mailbox<MergePlanner>>,
+    /// Publish token handed out by the indexing scheduler with the assignment of the pipeline.
+    /// When `None`, the pipeline acquires a new token each time it is spawned.
+    pub publish_token_opt: Option<PublishToken>,
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
     use quickwit_actors::Universe;
-    use quickwit_config::{IndexingSettings, SourceParams};
+    use quickwit_common::metrics::IntGaugeVec;
+    use quickwit_config::{BackfillSourceParams, IndexingSettings, SourceParams, VecSourceParams};
     use quickwit_doc_mapper::default_doc_mapper_for_test;
+    use quickwit_metastore::checkpoint::{IndexCheckpoint, PartitionId, Position, PublishToken};
     use quickwit_metastore::{IndexMetadata, MetastoreError, MockMetastore};
     use quickwit_storage::RamStorage;
 
@@ -512,6 +876,14 @@ mod tests {
                     message: "MetastoreError Alarm".to_string(),
                 })
             });
+        metastore
+            .expect_acquire_publish_token()
+            .returning(|_, _, partition_set_id| {
+                Ok(PublishToken {
+                    partition_set_id: partition_set_id.to_string(),
+                    token: 1,
+                })
+            });
         metastore
             .expect_last_delete_opstamp()
             .returning(move |index_id| {
@@ -533,6 +905,7 @@ mod tests {
                     let checkpoint_delta = checkpoint_delta_opt.as_ref().unwrap();
                     index_id == "test-index"
                         && checkpoint_delta.source_id == "test-source"
+                        && checkpoint_delta.publish_token_opt.is_some()
                         && splits.len() == 1
                         && replaced_splits.is_empty()
                         && format!("{:?}", checkpoint_delta.source_delta)
@@ -553,6 +926,11 @@ mod tests {
         let source_config = SourceConfig {
             source_id: "test-source".to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::file(PathBuf::from("data/test_corpus.json")),
         };
@@ -572,7 +950,9 @@ mod tests {
             queues_dir_path: PathBuf::from("./queues"),
             max_concurrent_split_uploads_index: 4,
             max_concurrent_split_uploads_merge: 5,
-            merge_planner_mailbox,
+            max_publish_batch_size: 1,
+            merge_planner_mailbox_opt: Some(merge_planner_mailbox),
+            publish_token_opt: None,
         };
         let pipeline = IndexingPipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handler) = universe.spawn_builder().spawn(pipeline);
@@ -606,6 +986,14 @@ mod tests {
                     "ram:///indexes/test-index",
                 ))
             });
+        metastore
+            .expect_acquire_publish_token()
+            .returning(|_, _, partition_set_id| {
+                Ok(PublishToken {
+                    partition_set_id: partition_set_id.to_string(),
+                    token: 1,
+                })
+            });
         metastore
             .expect_last_delete_opstamp()
             .returning(move |index_id| {
@@ -644,6 +1032,11 @@ mod tests {
         let source_config = SourceConfig {
             source_id: "test-source".to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::file(PathBuf::from("data/test_corpus.json")),
         };
@@ -663,7 +1056,9 @@ mod tests {
             split_store,
             max_concurrent_split_uploads_index: 4,
             max_concurrent_split_uploads_merge: 5,
-            merge_planner_mailbox,
+            max_publish_batch_size: 1,
+            merge_planner_mailbox_opt: Some(merge_planner_mailbox),
+            publish_token_opt: None,
         };
         let pipeline = IndexingPipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handler) = universe.spawn_builder().spawn(pipeline);
@@ -672,6 +1067,204 @@ mod tests {
         assert_eq!(pipeline_statistics.generation, 1);
         assert_eq!(pipeline_statistics.num_spawn_attempts, 1);
         assert_eq!(pipeline_statistics.num_published_splits, 1);
+        assert_eq!(pipeline_statistics.num_pending_splits(), 0);
+        assert!(pipeline_statistics.total_size_splits > 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_indexing_pipeline_statistics_metrics() {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-metrics".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 1,
+        };
+        let source_config = SourceConfig {
+            source_id: "test-source".to_string(),
+            num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            enabled: true,
+            source_params: SourceParams::void(),
+        };
+        let storage = Arc::new(RamStorage::default());
+        let split_store = IndexingSplitStore::create_without_local_store(storage.clone());
+        let pipeline_params = IndexingPipelineParams {
+            pipeline_id,
+            doc_mapper: Arc::new(default_doc_mapper_for_test()),
+            source_config,
+            indexing_directory: IndexingDirectory::for_test().await,
+            indexing_settings: IndexingSettings::for_test(),
+            metastore: Arc::new(MockMetastore::default()),
+            queues_dir_path: PathBuf::from("./queues"),
+            storage,
+            split_store,
+            max_concurrent_split_uploads_index: 4,
+            max_concurrent_split_uploads_merge: 5,
+            max_publish_batch_size: 1,
+            merge_planner_mailbox_opt: None,
+            publish_token_opt: None,
+        };
+        let mut pipeline = IndexingPipeline::new(pipeline_params);
+        pipeline.statistics.num_docs = 10;
+        pipeline.statistics.num_invalid_docs = 2;
+        pipeline.statistics.num_local_splits = 3;
+        pipeline.statistics.num_published_splits = 1;
+        pipeline.statistics.total_size_splits = 1_000;
+        pipeline.export_statistics_metrics();
+
+        let labels = ["test-index-metrics", "test-source", "1"];
+        let gauge_value = |gauge_vec: &IntGaugeVec| gauge_vec.with_label_values(&labels).get();
+        assert_eq!(gauge_value(&INDEXER_METRICS.pipeline_num_docs), 10);
+        assert_eq!(gauge_value(&INDEXER_METRICS.pipeline_num_invalid_docs), 2);
+        assert_eq!(
+            gauge_value(&INDEXER_METRICS.pipeline_num_published_splits),
+            1
+        );
+        assert_eq!(
+            gauge_value(&INDEXER_METRICS.pipeline_uploaded_num_bytes),
+            1_000
+        );
+        assert_eq!(gauge_value(&INDEXER_METRICS.pipeline_pending_splits), 2);
+
+        pipeline.remove_statistics_metrics();
+        assert!(INDEXER_METRICS
+            .pipeline_num_docs
+            .remove_label_values(&labels)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_indexing_pipeline_backfill() -> anyhow::Result<()> {
+        let docs: Vec<String> = std::fs::read_to_string("data/test_corpus.json")?
+            .lines()
+            .take(4)
+            .map(|line| line.to_string())
+            .collect();
+        // The boundary of the backfill is already recorded, so the pipeline does not need to
+        // fetch the positions of the live source.
+        let index_checkpoint = Arc::new(Mutex::new(IndexCheckpoint::default()));
+        index_checkpoint
+            .lock()
+            .unwrap()
+            .try_apply_delta(IndexCheckpointDelta {
+                source_id: "test-source".to_string(),
+                source_delta: SourceCheckpointDelta::from_partition_delta(
+                    PartitionId::from(crate::source::BACKFILL_PHASE_PARTITION_ID),
+                    Position::Beginning,
+                    Position::from(1u64),
+                ),
+                publish_token_opt: None,
+            })
+            .unwrap();
+        let mut metastore = MockMetastore::default();
+        let index_checkpoint_clone = index_checkpoint.clone();
+        metastore
+            .expect_index_metadata()
+            .withf(|index_id| index_id == "test-index")
+            .returning(move |_| {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+                index_metadata.checkpoint = index_checkpoint_clone.lock().unwrap().clone();
+                Ok(index_metadata)
+            });
+        metastore
+            .expect_acquire_publish_token()
+            .returning(|_, _, partition_set_id| {
+                Ok(PublishToken {
+                    partition_set_id: partition_set_id.to_string(),
+                    token: 1,
+                })
+            });
+        metastore.expect_last_delete_opstamp().returning(|_| Ok(10));
+        metastore
+            .expect_stage_split()
+            .times(2)
+            .returning(|_, _| Ok(()));
+        let index_checkpoint_clone = index_checkpoint.clone();
+        metastore.expect_publish_splits().times(3).returning(
+            move |_, _, _, checkpoint_delta_opt| {
+                if let Some(checkpoint_delta) = checkpoint_delta_opt {
+                    index_checkpoint_clone
+                        .lock()
+                        .unwrap()
+                        .try_apply_delta(checkpoint_delta)
+                        .unwrap();
+                }
+                Ok(())
+            },
+        );
+        let universe = Universe::new();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let source_config = SourceConfig {
+            source_id: "test-source".to_string(),
+            num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            enabled: true,
+            source_params: SourceParams::Backfill(BackfillSourceParams {
+                historical: Box::new(SourceParams::Vec(VecSourceParams {
+                    docs: docs[..2].to_vec(),
+                    batch_num_docs: 2,
+                    partition: "historical".to_string(),
+                })),
+                live: Box::new(SourceParams::Vec(VecSourceParams {
+                    docs: docs[2..].to_vec(),
+                    batch_num_docs: 2,
+                    partition: "live".to_string(),
+                })),
+            }),
+        };
+        let storage = Arc::new(RamStorage::default());
+        let split_store = IndexingSplitStore::create_without_local_store(storage.clone());
+        let pipeline_params = IndexingPipelineParams {
+            pipeline_id,
+            doc_mapper: Arc::new(default_doc_mapper_for_test()),
+            source_config,
+            indexing_directory: IndexingDirectory::for_test().await,
+            indexing_settings: IndexingSettings::for_test(),
+            metastore: Arc::new(metastore),
+            queues_dir_path: PathBuf::from("./queues"),
+            storage,
+            split_store,
+            max_concurrent_split_uploads_index: 4,
+            max_concurrent_split_uploads_merge: 5,
+            max_publish_batch_size: 1,
+            merge_planner_mailbox_opt: None,
+            publish_token_opt: None,
+        };
+        let pipeline = IndexingPipeline::new(pipeline_params);
+        let (_pipeline_mailbox, pipeline_handler) = universe.spawn_builder().spawn(pipeline);
+        let (pipeline_exit_status, pipeline_statistics) = pipeline_handler.join().await;
+        assert!(pipeline_exit_status.is_success());
+        assert_eq!(pipeline_statistics.generation, 2);
+
+        let index_checkpoint = index_checkpoint.lock().unwrap();
+        let source_checkpoint = index_checkpoint.source_checkpoint("test-source").unwrap();
+        assert_eq!(
+            BackfillPhase::from_checkpoint(source_checkpoint),
+            BackfillPhase::Live
+        );
+        assert_eq!(
+            source_checkpoint.position_for_partition(&PartitionId::from("historical")),
+            Some(&Position::from(1u64))
+        );
+        assert_eq!(
+            source_checkpoint.position_for_partition(&PartitionId::from("live")),
+            Some(&Position::from(1u64))
+        );
         Ok(())
     }
 }
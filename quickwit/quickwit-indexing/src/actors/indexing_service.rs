@@ -20,34 +20,51 @@
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use byte_unit::Byte;
+use futures::future::join_all;
+use itertools::Itertools;
 use quickwit_actors::{
     Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Health, Mailbox, Observation,
     Supervisable,
 };
 use quickwit_common::fs::get_cache_directory_path;
 use quickwit_config::{
-    build_doc_mapper, IndexerConfig, SourceConfig, SourceParams, VecSourceParams,
+    build_doc_mapper, IndexerConfig, MaintenanceWindows, SourceConfig, SourceParams,
+    VecSourceParams,
 };
+use quickwit_doc_mapper::DocMapper;
 use quickwit_ingest_api::QUEUES_DIR_NAME;
-use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError};
+use quickwit_metastore::checkpoint::{PublishToken, SourceCheckpoint};
+use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError, SplitMetadata, SplitState};
 use quickwit_proto::{ServiceError, ServiceErrorCode};
-use quickwit_storage::{StorageError, StorageResolverError, StorageUriResolver};
+use quickwit_storage::{Storage, StorageError, StorageResolverError, StorageUriResolver};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
+use super::indexing_pipeline::Restart;
 use super::merge_pipeline::{MergePipeline, MergePipelineParams};
 use super::MergePlanner;
 use crate::models::{
-    DetachPipeline, IndexingDirectory, IndexingPipelineId, Observe, ObservePipeline,
+    ApplyIndexingPlan, ApplyIndexingPlanResponse, CheckpointResetTarget, DetachPipeline,
+    ForceMerge, GetSourceCheckpoint, IndexingDirectory, IndexingPipelineId, IndexingPipelineStatus,
+    MergeSplits, MergeSplitsResponse, NewSplits, Observe, ObservePipeline, ObservePipelines,
+    ReloadSourcePipelines, ReloadSourcePipelinesResponse, ResetSourceCheckpoint, RestartPipeline,
     ShutdownPipeline, ShutdownPipelines, SpawnMergePipeline, SpawnPipeline, SpawnPipelines,
     WeakIndexingDirectory,
 };
+use crate::scheduler::{is_node_local_source, requires_cluster_scheduling, IndexingTask};
+use crate::source::fetch_end_positions;
 use crate::split_store::{LocalSplitStore, SplitStoreQuota};
 use crate::{IndexingPipeline, IndexingPipelineParams, IndexingSplitStore, IndexingStatistics};
 
+/// Interval at which a remote merge node looks for the indexes designating it and their newly
+/// published splits.
+const REMOTE_MERGE_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
 /// Name of the indexing directory, usually located at `<data_dir_path>/indexing`.
 pub const INDEXING_DIR_NAME: &str = "indexing";
 
@@ -63,6 +80,8 @@ pub enum IndexingServiceError {
         source_id: String,
         pipeline_ord: usize,
     },
+    #[error("No merge pipeline for index `{index_id}` is running on this node.")]
+    MissingMergePipeline { index_id: String },
     #[error("Failed to resolve the storage `{0}`.")]
     StorageResolverError(#[from] StorageResolverError),
     #[error("Storage error `{0}`.")]
@@ -77,6 +96,7 @@ impl ServiceError for IndexingServiceError {
     fn status_code(&self) -> ServiceErrorCode {
         match self {
             Self::MissingPipeline { .. } => ServiceErrorCode::NotFound,
+            Self::MissingMergePipeline { .. } => ServiceErrorCode::NotFound,
             Self::PipelineAlreadyExists { .. } => ServiceErrorCode::BadRequest,
             Self::StorageResolverError(_) | Self::StorageError(_) => ServiceErrorCode::Internal,
             Self::MetastoreError(_) => ServiceErrorCode::Internal,
@@ -95,10 +115,13 @@ pub struct IndexingServiceState {
 type IndexId = String;
 type SourceId = String;
 
+/// Identifies the merge pipeline merging the splits produced by the indexing pipelines of a
+/// source on a given node. Remote merge pipelines merge the splits produced by other nodes.
 #[derive(Hash, Eq, PartialEq)]
 struct MergePipelineId {
     index_id: String,
     source_id: String,
+    node_id: String,
 }
 
 impl<'a> From<&'a IndexingPipelineId> for MergePipelineId {
@@ -106,6 +129,7 @@ impl<'a> From<&'a IndexingPipelineId> for MergePipelineId {
         MergePipelineId {
             index_id: pipeline_id.index_id.clone(),
             source_id: pipeline_id.source_id.clone(),
+            node_id: pipeline_id.node_id.clone(),
         }
     }
 }
@@ -125,7 +149,21 @@ pub struct IndexingService {
     indexing_directories: HashMap<(IndexId, SourceId), WeakIndexingDirectory>,
     local_split_store: Arc<LocalSplitStore>,
     max_concurrent_split_uploads: usize,
+    max_publish_batch_size: usize,
+    merge_windows: MaintenanceWindows,
+    merge_throughput_outside_windows: Option<Byte>,
     merge_pipeline_handles: HashMap<MergePipelineId, MergePipelineHandle>,
+    /// When enabled, the pipelines of the sources that are not node-local are only spawned
+    /// through the indexing plan.
+    enable_cluster_scheduling: bool,
+    /// Pipelines assigned to the node by the last indexing plan applied, with the publish token of
+    /// their assignment.
+    scheduled_pipelines: HashMap<IndexingPipelineId, Option<PublishToken>>,
+    /// When enabled, the node runs the merge pipelines of the indexes designating it as their
+    /// merge node.
+    enable_remote_merge: bool,
+    /// Remote merge pipelines needed according to the last refresh.
+    remote_merge_pipeline_ids: HashSet<MergePipelineId>,
 }
 
 impl IndexingService {
@@ -158,7 +196,14 @@ impl IndexingService {
             state: Default::default(),
             indexing_directories: HashMap::new(),
             max_concurrent_split_uploads: indexer_config.max_concurrent_split_uploads,
+            max_publish_batch_size: indexer_config.max_publish_batch_size,
+            merge_windows: indexer_config.merge_windows,
+            merge_throughput_outside_windows: indexer_config.merge_throughput_outside_windows,
             merge_pipeline_handles: HashMap::new(),
+            enable_cluster_scheduling: indexer_config.enable_cluster_scheduling,
+            scheduled_pipelines: HashMap::new(),
+            enable_remote_merge: indexer_config.enable_remote_merge,
+            remote_merge_pipeline_ids: HashSet::new(),
         })
     }
 
@@ -192,6 +237,47 @@ impl IndexingService {
         Ok(observation)
     }
 
+    async fn observe_pipelines(&self) -> Vec<IndexingPipelineStatus> {
+        let observe_futures = self.indexing_pipeline_handles.iter().map(
+            |(pipeline_id, pipeline_handle)| async move {
+                IndexingPipelineStatus {
+                    pipeline_id: pipeline_id.clone(),
+                    statistics: pipeline_handle.observe().await.state,
+                }
+            },
+        );
+        let mut pipeline_statuses = join_all(observe_futures).await;
+        pipeline_statuses.sort_by(|left, right| {
+            let left_id = &left.pipeline_id;
+            let right_id = &right.pipeline_id;
+            (&left_id.index_id, &left_id.source_id, left_id.pipeline_ord).cmp(&(
+                &right_id.index_id,
+                &right_id.source_id,
+                right_id.pipeline_ord,
+            ))
+        });
+        pipeline_statuses
+    }
+
+    async fn restart_pipeline(
+        &self,
+        ctx: &ActorContext<Self>,
+        pipeline_id: IndexingPipelineId,
+    ) -> Result<(), IndexingServiceError> {
+        let missing_pipeline_error = || IndexingServiceError::MissingPipeline {
+            index_id: pipeline_id.index_id.clone(),
+            source_id: pipeline_id.source_id.clone(),
+        };
+        let pipeline_handle = self
+            .indexing_pipeline_handles
+            .get(&pipeline_id)
+            .ok_or_else(missing_pipeline_error)?;
+        ctx.send_message(pipeline_handle.mailbox(), Restart)
+            .await
+            .map_err(|_| missing_pipeline_error())?;
+        Ok(())
+    }
+
     async fn spawn_pipeline(
         &mut self,
         ctx: &ActorContext<Self>,
@@ -206,8 +292,14 @@ impl IndexingService {
             pipeline_ord,
         };
         let index_metadata = self.index_metadata(ctx, &pipeline_id.index_id).await?;
-        self.spawn_pipeline_inner(ctx, pipeline_id.clone(), index_metadata, source_config)
-            .await?;
+        self.spawn_pipeline_inner(
+            ctx,
+            pipeline_id.clone(),
+            index_metadata,
+            source_config,
+            None,
+        )
+        .await?;
         Ok(pipeline_id)
     }
 
@@ -224,6 +316,14 @@ impl IndexingService {
             if !source_config.enabled {
                 continue;
             }
+            // The pipelines of scheduled sources are spawned by the indexing plan.
+            if self.enable_cluster_scheduling && !is_node_local_source(source_config) {
+                continue;
+            }
+            if requires_cluster_scheduling(source_config) {
+                error!(index_id=%index_id, source_id=%source_config.source_id, "Source requires cluster scheduling, which is disabled on this indexer. Skipping its pipelines.");
+                continue;
+            }
 
             let pipeline_ords = 0..source_config.num_pipelines().unwrap_or(1);
             for pipeline_ord in pipeline_ords {
@@ -242,6 +342,7 @@ impl IndexingService {
                     pipeline_id.clone(),
                     index_metadata.clone(),
                     source_config.clone(),
+                    None,
                 )
                 .await?;
                 pipeline_ids.push(pipeline_id);
@@ -257,7 +358,15 @@ impl IndexingService {
         pipeline_id: IndexingPipelineId,
         index_metadata: IndexMetadata,
         source_config: SourceConfig,
+        publish_token_opt: Option<PublishToken>,
     ) -> Result<(), IndexingServiceError> {
+        if !self.enable_cluster_scheduling && requires_cluster_scheduling(&source_config) {
+            return Err(IndexingServiceError::InvalidParams(anyhow::anyhow!(
+                "Source `{}` uses the `static` Kafka partition assignment, which requires the \
+                 indexers to enable cluster scheduling.",
+                source_config.source_id
+            )));
+        }
         if self.indexing_pipeline_handles.contains_key(&pipeline_id) {
             return Err(IndexingServiceError::PipelineAlreadyExists {
                 index_id: pipeline_id.index_id,
@@ -271,14 +380,6 @@ impl IndexingService {
             .await?;
         let storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
         let queues_dir_path = self.data_dir_path.join(QUEUES_DIR_NAME);
-        let merge_policy =
-            crate::merge_policy::merge_policy_from_settings(&index_metadata.indexing_settings);
-        let split_store = IndexingSplitStore::new(
-            storage.clone(),
-            merge_policy.clone(),
-            self.local_split_store.clone(),
-        );
-
         let doc_mapper = build_doc_mapper(
             &index_metadata.doc_mapping,
             &index_metadata.search_settings,
@@ -286,24 +387,32 @@ impl IndexingService {
         )
         .map_err(IndexingServiceError::InvalidParams)?;
 
-        let merge_pipeline_params = MergePipelineParams {
-            pipeline_id: pipeline_id.clone(),
-            doc_mapper: doc_mapper.clone(),
-            indexing_directory: indexing_directory.clone(),
-            metastore: self.metastore.clone(),
-            split_store: split_store.clone(),
-            merge_policy,
-            merge_max_io_num_bytes_per_sec: index_metadata
-                .indexing_settings
-                .resources
-                .max_merge_write_throughput,
-            max_concurrent_split_uploads: self.max_concurrent_split_uploads,
+        let is_merged_remotely = index_metadata
+            .indexing_settings
+            .merge_node_id
+            .as_ref()
+            .map(|merge_node_id| *merge_node_id != self.node_id)
+            .unwrap_or(false);
+        let (split_store, merge_planner_mailbox_opt) = if is_merged_remotely {
+            // The splits are downloaded from the storage by the merge node, there is no point in
+            // keeping them in the local split store.
+            let split_store = IndexingSplitStore::create_without_local_store(storage.clone());
+            (split_store, None)
+        } else {
+            let merge_pipeline_params = self.merge_pipeline_params(
+                pipeline_id.clone(),
+                &index_metadata,
+                doc_mapper.clone(),
+                indexing_directory.clone(),
+                storage.clone(),
+            );
+            let split_store = merge_pipeline_params.split_store.clone();
+            let merge_planner_mailbox = self
+                .get_or_create_merge_pipeline(merge_pipeline_params, ctx)
+                .await?;
+            (split_store, Some(merge_planner_mailbox))
         };
 
-        let merge_planner_mailbox = self
-            .get_or_create_merge_pipeline(merge_pipeline_params, ctx)
-            .await?;
-
         let max_concurrent_split_uploads_index = (self.max_concurrent_split_uploads / 2).max(1);
         let max_concurrent_split_uploads_merge =
             (self.max_concurrent_split_uploads - max_concurrent_split_uploads_index).max(1);
@@ -318,8 +427,10 @@ impl IndexingService {
             split_store,
             max_concurrent_split_uploads_index,
             max_concurrent_split_uploads_merge,
+            max_publish_batch_size: self.max_publish_batch_size,
             queues_dir_path,
-            merge_planner_mailbox,
+            merge_planner_mailbox_opt,
+            publish_token_opt,
         };
         let pipeline = IndexingPipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handle) = ctx.spawn_actor().spawn(pipeline);
@@ -329,6 +440,43 @@ impl IndexingService {
         Ok(())
     }
 
+    fn merge_pipeline_params(
+        &self,
+        pipeline_id: IndexingPipelineId,
+        index_metadata: &IndexMetadata,
+        doc_mapper: Arc<dyn DocMapper>,
+        indexing_directory: IndexingDirectory,
+        storage: Arc<dyn Storage>,
+    ) -> MergePipelineParams {
+        let merge_policy =
+            crate::merge_policy::merge_policy_from_settings(&index_metadata.indexing_settings);
+        let split_store = IndexingSplitStore::new(
+            storage.clone(),
+            merge_policy.clone(),
+            self.local_split_store.clone(),
+        );
+        MergePipelineParams {
+            pipeline_id,
+            doc_mapper,
+            indexing_directory,
+            metastore: self.metastore.clone(),
+            storage,
+            split_store,
+            merge_policy,
+            merge_max_io_num_bytes_per_sec: index_metadata
+                .indexing_settings
+                .resources
+                .max_merge_write_throughput,
+            merge_windows: self.merge_windows.clone(),
+            merge_max_io_num_bytes_per_sec_outside_windows: self.merge_throughput_outside_windows,
+            max_concurrent_split_uploads: self.max_concurrent_split_uploads,
+            parquet_sidecar_opt: index_metadata.indexing_settings.parquet_sidecar.clone(),
+            dedup_opt: index_metadata.indexing_settings.dedup.clone(),
+            hotcache_opt: index_metadata.indexing_settings.hotcache.clone(),
+            time_slicing_opt: index_metadata.indexing_settings.time_slicing.clone(),
+        }
+    }
+
     async fn spawn_merge_pipeline(
         &mut self,
         ctx: &ActorContext<Self>,
@@ -338,14 +486,366 @@ impl IndexingService {
         let source_config = SourceConfig {
             source_id: pipeline_id.source_id.clone(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::Vec(VecSourceParams::default()),
         };
-        self.spawn_pipeline_inner(ctx, pipeline_id.clone(), index_metadata, source_config)
-            .await?;
+        self.spawn_pipeline_inner(
+            ctx,
+            pipeline_id.clone(),
+            index_metadata,
+            source_config,
+            None,
+        )
+        .await?;
         Ok(pipeline_id)
     }
 
+    /// Shuts down the pipelines of an index, optionally restricted to a single source. Returns the
+    /// number of pipelines shut down.
+    async fn shutdown_pipelines(&mut self, index_id: &str, source_id_opt: Option<&str>) -> usize {
+        let pipelines_to_shutdown: Vec<IndexingPipelineId> = self
+            .indexing_pipeline_handles
+            .keys()
+            .filter(|pipeline_id| {
+                pipeline_id.index_id == index_id
+                    && source_id_opt
+                        .map(|source_id| pipeline_id.source_id == source_id)
+                        .unwrap_or(true)
+            })
+            .cloned()
+            .collect();
+        let mut num_shutdown_pipelines = 0;
+        for pipeline_id in pipelines_to_shutdown {
+            if let Some(pipeline_handle) = self.indexing_pipeline_handles.remove(&pipeline_id) {
+                pipeline_handle.quit().await;
+                self.state.num_running_pipelines -= 1;
+                num_shutdown_pipelines += 1;
+            }
+        }
+        num_shutdown_pipelines
+    }
+
+    async fn reload_source_pipelines(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        index_id: String,
+        source_id: String,
+    ) -> Result<ReloadSourcePipelinesResponse, IndexingServiceError> {
+        let index_metadata = self.index_metadata(ctx, &index_id).await?;
+        let num_shutdown_pipelines = self.shutdown_pipelines(&index_id, Some(&source_id)).await;
+        let mut num_spawned_pipelines = 0;
+
+        if let Some(source_config) = index_metadata
+            .sources
+            .get(&source_id)
+            .filter(|source_config| source_config.enabled)
+        {
+            let num_pipelines = source_config.num_pipelines().unwrap_or(1);
+            let pipeline_ords: Vec<(usize, Option<PublishToken>)> =
+                if self.enable_cluster_scheduling && !is_node_local_source(source_config) {
+                    // Only respawn the pipelines assigned to the node by the indexing plan.
+                    self.scheduled_pipelines
+                        .iter()
+                        .filter(|(pipeline_id, _)| {
+                            pipeline_id.index_id == index_id
+                                && pipeline_id.source_id == source_id
+                                && pipeline_id.pipeline_ord < num_pipelines
+                        })
+                        .map(|(pipeline_id, publish_token_opt)| {
+                            (pipeline_id.pipeline_ord, publish_token_opt.clone())
+                        })
+                        .sorted_by_key(|(pipeline_ord, _)| *pipeline_ord)
+                        .collect()
+                } else {
+                    (0..num_pipelines)
+                        .map(|pipeline_ord| (pipeline_ord, None))
+                        .collect()
+                };
+            for (pipeline_ord, publish_token_opt) in pipeline_ords {
+                let pipeline_id = IndexingPipelineId {
+                    index_id: index_id.clone(),
+                    source_id: source_id.clone(),
+                    node_id: self.node_id.clone(),
+                    pipeline_ord,
+                };
+                self.spawn_pipeline_inner(
+                    ctx,
+                    pipeline_id,
+                    index_metadata.clone(),
+                    source_config.clone(),
+                    publish_token_opt,
+                )
+                .await?;
+                num_spawned_pipelines += 1;
+            }
+        }
+        info!(
+            index_id=%index_id,
+            source_id=%source_id,
+            num_shutdown_pipelines=num_shutdown_pipelines,
+            num_spawned_pipelines=num_spawned_pipelines,
+            "Reloaded source pipelines."
+        );
+        Ok(ReloadSourcePipelinesResponse {
+            num_shutdown_pipelines,
+            num_spawned_pipelines,
+        })
+    }
+
+    async fn source_checkpoint(
+        &self,
+        ctx: &ActorContext<Self>,
+        index_id: &str,
+        source_id: &str,
+    ) -> Result<SourceCheckpoint, IndexingServiceError> {
+        let _protect_guard = ctx.protect_zone();
+        let source_checkpoint = self
+            .metastore
+            .source_checkpoint(index_id, source_id)
+            .await?;
+        Ok(source_checkpoint)
+    }
+
+    async fn reset_source_checkpoint(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        index_id: String,
+        source_id: String,
+        target: CheckpointResetTarget,
+    ) -> Result<SourceCheckpoint, IndexingServiceError> {
+        let index_metadata = self.index_metadata(ctx, &index_id).await?;
+        let source_config = index_metadata.sources.get(&source_id).ok_or_else(|| {
+            MetastoreError::SourceDoesNotExist {
+                source_id: source_id.clone(),
+            }
+        })?;
+        // The pipelines of a scheduled source may run on any indexer of the cluster and are
+        // respawned by the indexing plan: the source must be disabled first so that the indexing
+        // scheduler stops them.
+        if self.enable_cluster_scheduling
+            && !is_node_local_source(source_config)
+            && source_config.enabled
+        {
+            return Err(IndexingServiceError::InvalidParams(anyhow::anyhow!(
+                "Source `{}` of index `{}` is scheduled by the control plane and must be disabled \
+                 before resetting its checkpoint.",
+                source_id,
+                index_id
+            )));
+        }
+        let source_checkpoint = match target {
+            CheckpointResetTarget::Earliest => SourceCheckpoint::default(),
+            CheckpointResetTarget::Latest => {
+                let _protect_guard = ctx.protect_zone();
+                fetch_end_positions(&source_config.source_params)
+                    .await
+                    .map_err(IndexingServiceError::InvalidParams)?
+                    .into_iter()
+                    .collect()
+            }
+            CheckpointResetTarget::Position {
+                partition_id,
+                position,
+            } => {
+                let mut positions: Vec<_> = index_metadata
+                    .checkpoint
+                    .source_checkpoint(&source_id)
+                    .into_iter()
+                    .flat_map(|source_checkpoint| source_checkpoint.iter())
+                    .filter(|(other_partition_id, _)| *other_partition_id != partition_id)
+                    .collect();
+                positions.push((partition_id, position));
+                positions.into_iter().collect()
+            }
+        };
+        let num_pipelines = source_config.num_pipelines().unwrap_or(1);
+        let num_shutdown_pipelines = self.shutdown_pipelines(&index_id, Some(&source_id)).await;
+        {
+            let _protect_guard = ctx.protect_zone();
+            // The pipelines of the source may also run on other nodes. Acquiring a new publish
+            // token for each partition set of the source fences them: the checkpoint deltas they
+            // publish from now on fail with `StalePublishToken`, so they cannot publish on top of
+            // the new checkpoint. Once respawned, they acquire a new token and read the new
+            // checkpoint.
+            for pipeline_ord in 0..num_pipelines {
+                self.metastore
+                    .acquire_publish_token(&index_id, &source_id, &pipeline_ord.to_string())
+                    .await?;
+            }
+            if source_checkpoint.is_empty() {
+                self.metastore
+                    .reset_source_checkpoint(&index_id, &source_id)
+                    .await?;
+            } else {
+                self.metastore
+                    .update_source_checkpoint(&index_id, &source_id, source_checkpoint.clone())
+                    .await?;
+            }
+        }
+        info!(
+            index_id=%index_id,
+            source_id=%source_id,
+            num_shutdown_pipelines=num_shutdown_pipelines,
+            checkpoint=?source_checkpoint,
+            "Reset source checkpoint."
+        );
+        self.reload_source_pipelines(ctx, index_id, source_id)
+            .await?;
+        Ok(source_checkpoint)
+    }
+
+    async fn apply_indexing_plan(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        indexing_tasks: Vec<IndexingTask>,
+    ) -> ApplyIndexingPlanResponse {
+        let desired_pipelines: HashMap<IndexingPipelineId, Option<PublishToken>> = indexing_tasks
+            .into_iter()
+            .map(|indexing_task| {
+                let publish_token_opt =
+                    indexing_task
+                        .publish_token_opt
+                        .map(|publish_token| PublishToken {
+                            partition_set_id: indexing_task.pipeline_ord.to_string(),
+                            token: publish_token,
+                        });
+                let pipeline_id = IndexingPipelineId {
+                    index_id: indexing_task.index_id,
+                    source_id: indexing_task.source_id,
+                    node_id: self.node_id.clone(),
+                    pipeline_ord: indexing_task.pipeline_ord,
+                };
+                (pipeline_id, publish_token_opt)
+            })
+            .collect();
+        let mut response = ApplyIndexingPlanResponse::default();
+
+        // The pipelines whose assignment changed, i.e. whose publish token changed, are respawned
+        // with the new token.
+        let pipeline_ids_to_shutdown: Vec<IndexingPipelineId> = self
+            .scheduled_pipelines
+            .iter()
+            .filter(|(pipeline_id, publish_token_opt)| {
+                desired_pipelines.get(*pipeline_id) != Some(*publish_token_opt)
+            })
+            .map(|(pipeline_id, _)| pipeline_id.clone())
+            .collect();
+        for pipeline_id in pipeline_ids_to_shutdown {
+            if let Some(pipeline_handle) = self.indexing_pipeline_handles.remove(&pipeline_id) {
+                pipeline_handle.quit().await;
+                self.state.num_running_pipelines -= 1;
+                response.num_shutdown_pipelines += 1;
+            }
+        }
+        let pipeline_ids_to_spawn: Vec<IndexingPipelineId> = desired_pipelines
+            .keys()
+            .filter(|pipeline_id| !self.indexing_pipeline_handles.contains_key(pipeline_id))
+            .cloned()
+            .sorted_by(|left, right| {
+                (&left.index_id, &left.source_id, left.pipeline_ord).cmp(&(
+                    &right.index_id,
+                    &right.source_id,
+                    right.pipeline_ord,
+                ))
+            })
+            .collect();
+        let mut index_metadatas: HashMap<IndexId, IndexMetadata> = HashMap::new();
+
+        for pipeline_id in pipeline_ids_to_spawn {
+            if !index_metadatas.contains_key(&pipeline_id.index_id) {
+                match self.index_metadata(ctx, &pipeline_id.index_id).await {
+                    Ok(index_metadata) => {
+                        index_metadatas.insert(pipeline_id.index_id.clone(), index_metadata);
+                    }
+                    Err(error) => {
+                        warn!(index_id=%pipeline_id.index_id, error=?error, "Failed to fetch index metadata, skipping indexing task.");
+                        continue;
+                    }
+                }
+            }
+            let index_metadata = &index_metadatas[&pipeline_id.index_id];
+            let source_config = match index_metadata.sources.get(&pipeline_id.source_id) {
+                Some(source_config) => source_config.clone(),
+                None => {
+                    warn!(index_id=%pipeline_id.index_id, source_id=%pipeline_id.source_id, "Source does not exist, skipping indexing task.");
+                    continue;
+                }
+            };
+            let index_metadata = index_metadata.clone();
+            let publish_token_opt = desired_pipelines[&pipeline_id].clone();
+            if let Err(error) = self
+                .spawn_pipeline_inner(
+                    ctx,
+                    pipeline_id.clone(),
+                    index_metadata,
+                    source_config,
+                    publish_token_opt,
+                )
+                .await
+            {
+                error!(index_id=%pipeline_id.index_id, source_id=%pipeline_id.source_id, pipeline_ord=%pipeline_id.pipeline_ord, error=?error, "Failed to spawn indexing pipeline.");
+                continue;
+            }
+            response.num_spawned_pipelines += 1;
+        }
+        self.scheduled_pipelines = desired_pipelines;
+
+        if response.num_shutdown_pipelines > 0 || response.num_spawned_pipelines > 0 {
+            info!(
+                num_shutdown_pipelines = response.num_shutdown_pipelines,
+                num_spawned_pipelines = response.num_spawned_pipelines,
+                "Applied indexing plan."
+            );
+        }
+        response
+    }
+
+    async fn merge_splits(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        index_id: String,
+        split_ids_opt: Option<Vec<String>>,
+    ) -> Result<MergeSplitsResponse, IndexingServiceError> {
+        let merge_planner_mailboxes: Vec<Mailbox<MergePlanner>> = self
+            .merge_pipeline_handles
+            .iter()
+            .filter(|(merge_pipeline_id, _)| merge_pipeline_id.index_id == index_id)
+            .map(|(_, merge_pipeline_handle)| merge_pipeline_handle.mailbox.clone())
+            .collect();
+        if merge_planner_mailboxes.is_empty() {
+            return Err(IndexingServiceError::MissingMergePipeline { index_id });
+        }
+        let mut response = MergeSplitsResponse::default();
+
+        for merge_planner_mailbox in merge_planner_mailboxes {
+            let _protect_guard = ctx.protect_zone();
+            let force_merge = ForceMerge {
+                split_ids_opt: split_ids_opt.clone(),
+            };
+            match merge_planner_mailbox.ask(force_merge).await {
+                Ok(merge_operations) => response.merge_operations.extend(merge_operations),
+                Err(error) => {
+                    error!(index_id=%index_id, error=?error, "Failed to force merge operations.")
+                }
+            }
+        }
+        if let Some(split_ids) = split_ids_opt {
+            let merged_split_ids: HashSet<&String> =
+                response.merge_operations.values().flatten().collect();
+            response.skipped_split_ids = split_ids
+                .iter()
+                .filter(|split_id| !merged_split_ids.contains(split_id))
+                .cloned()
+                .collect();
+        }
+        Ok(response)
+    }
+
     async fn index_metadata(
         &self,
         ctx: &ActorContext<Self>,
@@ -391,16 +891,126 @@ impl IndexingService {
             .keys()
             .map(MergePipelineId::from)
             .collect();
+        let remote_merge_pipeline_ids = &self.remote_merge_pipeline_ids;
         self.merge_pipeline_handles
             .retain(|merge_pipeline_id, merge_pipeline_mailbox_handle| {
                 match merge_pipeline_mailbox_handle.handle.health() {
-                    Health::Healthy => needed_merge_pipeline_ids.contains(merge_pipeline_id),
+                    Health::Healthy => {
+                        needed_merge_pipeline_ids.contains(merge_pipeline_id)
+                            || remote_merge_pipeline_ids.contains(merge_pipeline_id)
+                    }
                     Health::FailureOrUnhealthy | Health::Success => false,
                 }
             });
         Ok(())
     }
 
+    /// Spawns the merge pipelines of the indexes designating this node as their merge node, one
+    /// per source and indexer, and feeds them with the immature splits published by the
+    /// indexers.
+    async fn refresh_remote_merge_pipelines(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), IndexingServiceError> {
+        let index_metadatas = {
+            let _protect_guard = ctx.protect_zone();
+            self.metastore.list_indexes_metadatas().await?
+        };
+        let mut remote_merge_pipeline_ids = HashSet::new();
+
+        for index_metadata in index_metadatas {
+            if index_metadata.indexing_settings.merge_node_id.as_ref() != Some(&self.node_id) {
+                continue;
+            }
+            let published_splits = {
+                let _protect_guard = ctx.protect_zone();
+                self.metastore
+                    .list_splits(&index_metadata.index_id, SplitState::Published, None, None)
+                    .await?
+            };
+            let merge_policy =
+                crate::merge_policy::merge_policy_from_settings(&index_metadata.indexing_settings);
+            let mut young_splits_per_pipeline: HashMap<IndexingPipelineId, Vec<SplitMetadata>> =
+                HashMap::new();
+            for split in published_splits {
+                let split_metadata = split.split_metadata;
+                // The splits produced by this node are merged by its local merge pipelines.
+                if split_metadata.node_id == self.node_id || merge_policy.is_mature(&split_metadata)
+                {
+                    continue;
+                }
+                let pipeline_id = IndexingPipelineId {
+                    index_id: split_metadata.index_id.clone(),
+                    source_id: split_metadata.source_id.clone(),
+                    node_id: split_metadata.node_id.clone(),
+                    pipeline_ord: 0,
+                };
+                young_splits_per_pipeline
+                    .entry(pipeline_id)
+                    .or_default()
+                    .push(split_metadata);
+            }
+            for (pipeline_id, young_splits) in young_splits_per_pipeline {
+                let merge_planner_mailbox = self
+                    .get_or_create_remote_merge_pipeline(ctx, pipeline_id.clone(), &index_metadata)
+                    .await?;
+                remote_merge_pipeline_ids.insert(MergePipelineId::from(&pipeline_id));
+                // The merge planner skips the splits it already knows about.
+                let _ = ctx
+                    .send_message(
+                        &merge_planner_mailbox,
+                        NewSplits {
+                            new_splits: young_splits,
+                        },
+                    )
+                    .await;
+            }
+            ctx.record_progress();
+        }
+        self.remote_merge_pipeline_ids = remote_merge_pipeline_ids;
+        Ok(())
+    }
+
+    async fn get_or_create_remote_merge_pipeline(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        pipeline_id: IndexingPipelineId,
+        index_metadata: &IndexMetadata,
+    ) -> Result<Mailbox<MergePlanner>, IndexingServiceError> {
+        let merge_pipeline_id = MergePipelineId::from(&pipeline_id);
+        if let Some(merge_pipeline_mailbox_handle) =
+            self.merge_pipeline_handles.get(&merge_pipeline_id)
+        {
+            return Ok(merge_pipeline_mailbox_handle.mailbox.clone());
+        }
+        info!(
+            index_id=%pipeline_id.index_id,
+            source_id=%pipeline_id.source_id,
+            node_id=%pipeline_id.node_id,
+            "Spawning remote merge pipeline."
+        );
+        let indexing_dir_path = self.data_dir_path.join(INDEXING_DIR_NAME);
+        let indexing_directory = self
+            .get_or_create_indexing_directory(&pipeline_id, indexing_dir_path)
+            .await?;
+        let storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
+        let doc_mapper = build_doc_mapper(
+            &index_metadata.doc_mapping,
+            &index_metadata.search_settings,
+            &index_metadata.indexing_settings,
+        )
+        .map_err(IndexingServiceError::InvalidParams)?;
+        let merge_pipeline_params = self.merge_pipeline_params(
+            pipeline_id,
+            index_metadata,
+            doc_mapper,
+            indexing_directory,
+            storage,
+        );
+        self.get_or_create_merge_pipeline(merge_pipeline_params, ctx)
+            .await
+    }
+
     async fn get_or_create_indexing_directory(
         &mut self,
         pipeline_id: &IndexingPipelineId,
@@ -477,36 +1087,92 @@ impl Handler<DetachPipeline> for IndexingService {
     }
 }
 
-#[derive(Debug)]
-struct SuperviseLoop;
-
 #[async_trait]
-impl Handler<SuperviseLoop> for IndexingService {
-    type Reply = ();
+impl Handler<ObservePipelines> for IndexingService {
+    type Reply = Vec<IndexingPipelineStatus>;
 
     async fn handle(
         &mut self,
-        _message: SuperviseLoop,
-        ctx: &ActorContext<Self>,
-    ) -> Result<(), ActorExitStatus> {
-        self.handle_supervise().await?;
-        ctx.schedule_self_msg(quickwit_actors::HEARTBEAT, SuperviseLoop)
-            .await;
-        Ok(())
+        _msg: ObservePipelines,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.observe_pipelines().await)
     }
 }
 
 #[async_trait]
-impl Actor for IndexingService {
-    type ObservableState = IndexingServiceState;
-
-    fn observable_state(&self) -> Self::ObservableState {
-        self.state.clone()
-    }
+impl Handler<RestartPipeline> for IndexingService {
+    type Reply = Result<(), IndexingServiceError>;
 
-    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
-        self.handle(SuperviseLoop, ctx).await
-    }
+    async fn handle(
+        &mut self,
+        msg: RestartPipeline,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: msg.index_id,
+            source_id: msg.source_id,
+            node_id: self.node_id.clone(),
+            pipeline_ord: msg.pipeline_ord,
+        };
+        Ok(self.restart_pipeline(ctx, pipeline_id).await)
+    }
+}
+
+#[derive(Debug)]
+struct SuperviseLoop;
+
+#[derive(Debug)]
+struct RefreshRemoteMergePipelines;
+
+#[async_trait]
+impl Handler<RefreshRemoteMergePipelines> for IndexingService {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: RefreshRemoteMergePipelines,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Err(error) = self.refresh_remote_merge_pipelines(ctx).await {
+            warn!(error=?error, "Failed to refresh remote merge pipelines.");
+        }
+        ctx.schedule_self_msg(REMOTE_MERGE_REFRESH_INTERVAL, RefreshRemoteMergePipelines)
+            .await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<SuperviseLoop> for IndexingService {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: SuperviseLoop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.handle_supervise().await?;
+        ctx.schedule_self_msg_coalesced(quickwit_actors::HEARTBEAT, SuperviseLoop)
+            .await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Actor for IndexingService {
+    type ObservableState = IndexingServiceState;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.state.clone()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        if self.enable_remote_merge {
+            self.handle(RefreshRemoteMergePipelines, ctx).await?;
+        }
+        self.handle(SuperviseLoop, ctx).await
+    }
 }
 
 #[async_trait]
@@ -572,31 +1238,82 @@ impl Handler<ShutdownPipelines> for IndexingService {
         message: ShutdownPipelines,
         _ctx: &ActorContext<Self>,
     ) -> Result<Self::Reply, ActorExitStatus> {
-        let source_filter_fn = |pipeline_id: &IndexingPipelineId| {
-            message
-                .source_id
-                .as_ref()
-                .map(|source_id| pipeline_id.source_id == *source_id)
-                .unwrap_or(true)
-        };
-        let pipelines_to_shutdown: Vec<IndexingPipelineId> = self
-            .indexing_pipeline_handles
-            .keys()
-            .filter(|pipeline_id| {
-                pipeline_id.index_id == message.index_id && source_filter_fn(pipeline_id)
-            })
-            .cloned()
-            .collect();
-        for pipeline_id in pipelines_to_shutdown {
-            if let Some(pipeline_handle) = self.indexing_pipeline_handles.remove(&pipeline_id) {
-                pipeline_handle.quit().await;
-                self.state.num_running_pipelines -= 1;
-            }
-        }
+        self.shutdown_pipelines(&message.index_id, message.source_id.as_deref())
+            .await;
         Ok(Ok(()))
     }
 }
 
+#[async_trait]
+impl Handler<ReloadSourcePipelines> for IndexingService {
+    type Reply = Result<ReloadSourcePipelinesResponse, IndexingServiceError>;
+    async fn handle(
+        &mut self,
+        message: ReloadSourcePipelines,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .reload_source_pipelines(ctx, message.index_id, message.source_id)
+            .await)
+    }
+}
+
+#[async_trait]
+impl Handler<GetSourceCheckpoint> for IndexingService {
+    type Reply = Result<SourceCheckpoint, IndexingServiceError>;
+
+    async fn handle(
+        &mut self,
+        msg: GetSourceCheckpoint,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .source_checkpoint(ctx, &msg.index_id, &msg.source_id)
+            .await)
+    }
+}
+
+#[async_trait]
+impl Handler<ResetSourceCheckpoint> for IndexingService {
+    type Reply = Result<SourceCheckpoint, IndexingServiceError>;
+
+    async fn handle(
+        &mut self,
+        msg: ResetSourceCheckpoint,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .reset_source_checkpoint(ctx, msg.index_id, msg.source_id, msg.target)
+            .await)
+    }
+}
+
+#[async_trait]
+impl Handler<ApplyIndexingPlan> for IndexingService {
+    type Reply = ApplyIndexingPlanResponse;
+    async fn handle(
+        &mut self,
+        message: ApplyIndexingPlan,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.apply_indexing_plan(ctx, message.indexing_tasks).await)
+    }
+}
+
+#[async_trait]
+impl Handler<MergeSplits> for IndexingService {
+    type Reply = Result<MergeSplitsResponse, IndexingServiceError>;
+    async fn handle(
+        &mut self,
+        message: MergeSplits,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .merge_splits(ctx, message.index_id, message.split_ids_opt)
+            .await)
+    }
+}
+
 #[async_trait]
 impl Handler<ShutdownPipeline> for IndexingService {
     type Reply = Result<(), IndexingServiceError>;
@@ -617,11 +1334,17 @@ impl Handler<ShutdownPipeline> for IndexingService {
 mod tests {
     use std::time::Duration;
 
-    use quickwit_actors::{ObservationType, Universe};
+    use quickwit_actors::{AskError, ObservationType, Universe};
     use quickwit_common::rand::append_random_suffix;
     use quickwit_common::uri::Uri;
-    use quickwit_config::{SourceConfig, VecSourceParams};
+    use quickwit_config::{
+        KafkaClientFeatures, KafkaFlavor, KafkaPartitionAssignment, KafkaSourceParams,
+        SourceConfig, VecSourceParams,
+    };
     use quickwit_ingest_api::init_ingest_api;
+    use quickwit_metastore::checkpoint::{
+        IndexCheckpointDelta, PartitionId, Position, SourceCheckpointDelta,
+    };
     use quickwit_metastore::quickwit_metastore_uri_resolver;
 
     use super::*;
@@ -672,6 +1395,11 @@ mod tests {
         let source_config_0 = SourceConfig {
             source_id: "test-indexing-service--source-0".to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::void(),
         };
@@ -708,6 +1436,35 @@ mod tests {
         assert_eq!(observation.generation, 1);
         assert_eq!(observation.num_spawn_attempts, 1);
 
+        // Test `observe_pipelines`.
+        let pipeline_statuses = indexing_server_mailbox.ask(ObservePipelines).await.unwrap();
+        assert_eq!(pipeline_statuses.len(), 1);
+        assert_eq!(pipeline_statuses[0].pipeline_id, pipeline_id_0);
+        assert_eq!(pipeline_statuses[0].statistics.generation, 1);
+        assert!(pipeline_statuses[0].statistics.last_error.is_none());
+
+        // Test `restart_pipeline`.
+        indexing_server_mailbox
+            .ask_for_res(RestartPipeline {
+                index_id: index_id.clone(),
+                source_id: source_config_0.source_id.clone(),
+                pipeline_ord: 0,
+            })
+            .await
+            .unwrap();
+        let restart_error = indexing_server_mailbox
+            .ask_for_res(RestartPipeline {
+                index_id: index_id.clone(),
+                source_id: source_config_0.source_id.clone(),
+                pipeline_ord: 1,
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            restart_error,
+            AskError::ErrorReply(IndexingServiceError::MissingPipeline { .. })
+        ));
+
         // Test `detach_pipeline`.
         let pipeline_handle = indexing_server_mailbox
             .ask_for_res(DetachPipeline {
@@ -731,6 +1488,11 @@ mod tests {
         let source_config_1 = SourceConfig {
             source_id: "test-indexing-service--source-1".to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::void(),
         };
@@ -753,6 +1515,11 @@ mod tests {
         let source_config_2 = SourceConfig {
             source_id: "test-indexing-service--source-2".to_string(),
             num_pipelines: 2,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::void(),
         };
@@ -813,6 +1580,123 @@ mod tests {
             0
         );
 
+        // Test `reload_source_pipelines`.
+        let reload_source_pipelines_msg = ReloadSourcePipelines {
+            index_id: index_id.clone(),
+            source_id: source_config_2.source_id.clone(),
+        };
+        let reload_response = indexing_server_mailbox
+            .ask_for_res(reload_source_pipelines_msg.clone())
+            .await
+            .unwrap();
+        assert_eq!(reload_response.num_shutdown_pipelines, 0);
+        assert_eq!(reload_response.num_spawned_pipelines, 2);
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            2
+        );
+        metastore
+            .toggle_source(&index_id, &source_config_2.source_id, false)
+            .await
+            .unwrap();
+        let reload_response = indexing_server_mailbox
+            .ask_for_res(reload_source_pipelines_msg)
+            .await
+            .unwrap();
+        assert_eq!(reload_response.num_shutdown_pipelines, 2);
+        assert_eq!(reload_response.num_spawned_pipelines, 0);
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            0
+        );
+
+        // Test `get_source_checkpoint` and `reset_source_checkpoint`.
+        let source_checkpoint = indexing_server_mailbox
+            .ask_for_res(GetSourceCheckpoint {
+                index_id: index_id.clone(),
+                source_id: source_config_2.source_id.clone(),
+            })
+            .await
+            .unwrap();
+        assert!(source_checkpoint.is_empty());
+
+        let stale_publish_token = metastore
+            .acquire_publish_token(&index_id, &source_config_2.source_id, "0")
+            .await
+            .unwrap();
+        let source_checkpoint = indexing_server_mailbox
+            .ask_for_res(ResetSourceCheckpoint {
+                index_id: index_id.clone(),
+                source_id: source_config_2.source_id.clone(),
+                target: CheckpointResetTarget::Position {
+                    partition_id: PartitionId::from("partition-1"),
+                    position: Position::from(42u64),
+                },
+            })
+            .await
+            .unwrap();
+        let expected_checkpoint: SourceCheckpoint =
+            [(PartitionId::from("partition-1"), Position::from(42u64))]
+                .into_iter()
+                .collect();
+        assert_eq!(source_checkpoint, expected_checkpoint);
+        assert_eq!(
+            metastore
+                .source_checkpoint(&index_id, &source_config_2.source_id)
+                .await
+                .unwrap(),
+            expected_checkpoint
+        );
+        // The pipelines of the source running elsewhere can no longer publish.
+        let checkpoint_delta = IndexCheckpointDelta {
+            source_id: source_config_2.source_id.clone(),
+            source_delta: SourceCheckpointDelta::from_partition_delta(
+                PartitionId::from("partition-1"),
+                Position::from(42u64),
+                Position::from(43u64),
+            ),
+            publish_token_opt: Some(stale_publish_token),
+        };
+        let publish_error = metastore
+            .publish_splits(&index_id, &[], &[], Some(checkpoint_delta))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            publish_error,
+            MetastoreError::StalePublishToken { .. }
+        ));
+        let source_checkpoint = indexing_server_mailbox
+            .ask_for_res(ResetSourceCheckpoint {
+                index_id: index_id.clone(),
+                source_id: source_config_2.source_id.clone(),
+                target: CheckpointResetTarget::Earliest,
+            })
+            .await
+            .unwrap();
+        assert!(source_checkpoint.is_empty());
+        assert!(metastore
+            .source_checkpoint(&index_id, &source_config_2.source_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let reset_error = indexing_server_mailbox
+            .ask_for_res(ResetSourceCheckpoint {
+                index_id: index_id.clone(),
+                source_id: source_config_2.source_id.clone(),
+                target: CheckpointResetTarget::Latest,
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            reset_error,
+            AskError::ErrorReply(IndexingServiceError::InvalidParams(_))
+        ));
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            0
+        );
+
         // Test `spawn_merge_pipeline`.
         indexing_server_mailbox
             .ask_for_res(SpawnMergePipeline {
@@ -825,10 +1709,38 @@ mod tests {
             1
         );
 
+        // Test `merge_splits`.
+        let merge_splits_response = indexing_server_mailbox
+            .ask_for_res(MergeSplits {
+                index_id: index_id.clone(),
+                split_ids_opt: Some(vec!["unknown-split".to_string()]),
+            })
+            .await
+            .unwrap();
+        assert!(merge_splits_response.merge_operations.is_empty());
+        assert_eq!(merge_splits_response.skipped_split_ids, ["unknown-split"]);
+
+        let merge_splits_error = indexing_server_mailbox
+            .ask_for_res(MergeSplits {
+                index_id: "unknown-index".to_string(),
+                split_ids_opt: None,
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            merge_splits_error,
+            AskError::ErrorReply(IndexingServiceError::MissingMergePipeline { .. })
+        ));
+
         // Test `supervise_pipelines`
         let source_config_3 = SourceConfig {
             source_id: "test-indexing-service--source-3".to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::Vec(VecSourceParams {
                 docs: Vec::new(),
@@ -853,4 +1765,302 @@ mod tests {
         }
         panic!("Sleep");
     }
+
+    #[tokio::test]
+    async fn test_indexing_service_rejects_static_kafka_partition_assignment() {
+        let metastore_uri = Uri::from_well_formed("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+        let index_id = append_random_suffix("test-indexing-service-static-kafka");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let indexer_config = IndexerConfig::for_test().unwrap();
+        assert!(!indexer_config.enable_cluster_scheduling);
+        let universe = Universe::new();
+        let indexing_server = IndexingService::new(
+            "test-node".to_string(),
+            temp_dir.path().to_path_buf(),
+            indexer_config,
+            metastore,
+            StorageUriResolver::for_test(),
+        )
+        .await
+        .unwrap();
+        let (indexing_server_mailbox, indexing_server_handle) =
+            universe.spawn_builder().spawn(indexing_server);
+
+        let source_config = SourceConfig {
+            source_id: "test-static-kafka-source".to_string(),
+            num_pipelines: 2,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            enabled: true,
+            source_params: SourceParams::Kafka(KafkaSourceParams {
+                topic: "test-topic".to_string(),
+                client_log_level: None,
+                client_params: serde_json::json!({"bootstrap.servers": "localhost:9092"}),
+                enable_backfill_mode: false,
+                flavor: KafkaFlavor::Kafka,
+                client_features: KafkaClientFeatures::default(),
+                partition_assignment: KafkaPartitionAssignment::Static,
+                enable_offset_commit: false,
+            }),
+        };
+        let spawn_error = indexing_server_mailbox
+            .ask_for_res(SpawnPipeline {
+                index_id,
+                pipeline_ord: 0,
+                source_config,
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            spawn_error,
+            AskError::ErrorReply(IndexingServiceError::InvalidParams(_))
+        ));
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_indexing_service_apply_indexing_plan() {
+        let metastore_uri = Uri::from_well_formed("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service-apply-indexing-plan");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let source_config = SourceConfig {
+            source_id: "test-indexing-service--source".to_string(),
+            num_pipelines: 3,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            enabled: true,
+            source_params: SourceParams::void(),
+        };
+        metastore
+            .add_source(&index_id, source_config.clone())
+            .await
+            .unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut indexer_config = IndexerConfig::for_test().unwrap();
+        indexer_config.enable_cluster_scheduling = true;
+        let universe = Universe::new();
+        let indexing_server = IndexingService::new(
+            "test-node".to_string(),
+            temp_dir.path().to_path_buf(),
+            indexer_config,
+            metastore.clone(),
+            StorageUriResolver::for_test(),
+        )
+        .await
+        .unwrap();
+        let (indexing_server_mailbox, indexing_server_handle) =
+            universe.spawn_builder().spawn(indexing_server);
+
+        // Scheduled sources are not spawned outside of the indexing plan.
+        indexing_server_mailbox
+            .ask_for_res(SpawnPipelines {
+                index_id: index_id.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            0
+        );
+        let indexing_task = |pipeline_ord: usize, publish_token: u64| IndexingTask {
+            index_id: index_id.clone(),
+            source_id: source_config.source_id.clone(),
+            pipeline_ord,
+            publish_token_opt: Some(publish_token),
+        };
+        let response = indexing_server_mailbox
+            .ask(ApplyIndexingPlan {
+                indexing_tasks: vec![indexing_task(0, 1), indexing_task(1, 1)],
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.num_shutdown_pipelines, 0);
+        assert_eq!(response.num_spawned_pipelines, 2);
+
+        let response = indexing_server_mailbox
+            .ask(ApplyIndexingPlan {
+                indexing_tasks: vec![indexing_task(1, 1), indexing_task(2, 1)],
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.num_shutdown_pipelines, 1);
+        assert_eq!(response.num_spawned_pipelines, 1);
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            2
+        );
+
+        // A task handed out with a new publish token was reassigned in the meantime: its pipeline
+        // is respawned with the new token.
+        let response = indexing_server_mailbox
+            .ask(ApplyIndexingPlan {
+                indexing_tasks: vec![indexing_task(1, 2), indexing_task(2, 1)],
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.num_shutdown_pipelines, 1);
+        assert_eq!(response.num_spawned_pipelines, 1);
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            2
+        );
+
+        // Tasks of unknown sources are skipped.
+        let response = indexing_server_mailbox
+            .ask(ApplyIndexingPlan {
+                indexing_tasks: vec![IndexingTask {
+                    index_id: index_id.clone(),
+                    source_id: "unknown-source".to_string(),
+                    pipeline_ord: 0,
+                    publish_token_opt: None,
+                }],
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.num_shutdown_pipelines, 2);
+        assert_eq!(response.num_spawned_pipelines, 0);
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            0
+        );
+
+        // The checkpoint of a scheduled source can only be reset once the source is disabled.
+        let reset_source_checkpoint_msg = ResetSourceCheckpoint {
+            index_id: index_id.clone(),
+            source_id: source_config.source_id.clone(),
+            target: CheckpointResetTarget::Earliest,
+        };
+        let reset_error = indexing_server_mailbox
+            .ask_for_res(reset_source_checkpoint_msg.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            reset_error,
+            AskError::ErrorReply(IndexingServiceError::InvalidParams(_))
+        ));
+        metastore
+            .toggle_source(&index_id, &source_config.source_id, false)
+            .await
+            .unwrap();
+        indexing_server_mailbox
+            .ask_for_res(reset_source_checkpoint_msg)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_indexing_service_remote_merge() {
+        let metastore_uri = Uri::from_well_formed("ram:///metastore".to_string());
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service-remote-merge");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let mut index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+        index_metadata.indexing_settings.merge_node_id = Some("merge-node".to_string());
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let other_index_id = append_random_suffix("test-indexing-service-remote-merge-other");
+        let other_index_uri = format!("ram:///indexes/{other_index_id}");
+        let other_index_metadata = IndexMetadata::for_test(&other_index_id, &other_index_uri);
+        metastore.create_index(other_index_metadata).await.unwrap();
+
+        for split_id in ["split-1", "split-2"] {
+            for index_id in [&index_id, &other_index_id] {
+                let split_metadata = SplitMetadata {
+                    split_id: split_id.to_string(),
+                    index_id: index_id.to_string(),
+                    source_id: "test-source".to_string(),
+                    node_id: "indexer-node".to_string(),
+                    num_docs: 10,
+                    ..Default::default()
+                };
+                metastore
+                    .stage_split(index_id, split_metadata)
+                    .await
+                    .unwrap();
+                metastore
+                    .publish_splits(index_id, &[split_id], &[], None)
+                    .await
+                    .unwrap();
+            }
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut indexer_config = IndexerConfig::for_test().unwrap();
+        indexer_config.enable_remote_merge = true;
+        let universe = Universe::new();
+        let indexing_server = IndexingService::new(
+            "merge-node".to_string(),
+            temp_dir.path().to_path_buf(),
+            indexer_config,
+            metastore.clone(),
+            StorageUriResolver::for_test(),
+        )
+        .await
+        .unwrap();
+        let (indexing_server_mailbox, indexing_server_handle) =
+            universe.spawn_builder().spawn(indexing_server);
+
+        // The merge node runs a merge pipeline for the splits of the indexer, but no indexing
+        // pipeline.
+        let merge_splits_response = indexing_server_mailbox
+            .ask_for_res(MergeSplits {
+                index_id: index_id.clone(),
+                split_ids_opt: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(merge_splits_response.merge_operations.len(), 1);
+        let merged_split_ids = merge_splits_response
+            .merge_operations
+            .values()
+            .next()
+            .unwrap();
+        assert_eq!(merged_split_ids.len(), 2);
+        assert_eq!(
+            indexing_server_handle.observe().await.num_running_pipelines,
+            0
+        );
+
+        // Indexes that do not designate the node are left to their indexers.
+        let merge_splits_error = indexing_server_mailbox
+            .ask_for_res(MergeSplits {
+                index_id: other_index_id,
+                split_ids_opt: None,
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            merge_splits_error,
+            AskError::ErrorReply(IndexingServiceError::MissingMergePipeline { .. })
+        ));
+    }
 }
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -25,13 +25,14 @@ use anyhow::Context;
 use async_trait::async_trait;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox};
 use quickwit_config::INGEST_API_SOURCE_ID;
-use quickwit_ingest_api::IngestApiService;
-use quickwit_metastore::Metastore;
+use quickwit_ingest_api::{ApplyQueueRetentionRequest, IngestApiService};
+use quickwit_metastore::{IndexMetadata, Metastore};
 use quickwit_proto::ingest_api::{DropQueueRequest, ListQueuesRequest};
 use serde::Serialize;
 use tracing::{debug, error, info, instrument};
 
 use super::IndexingService;
+use crate::metrics::INDEXER_METRICS;
 use crate::models::ShutdownPipelines;
 
 const RUN_INTERVAL: Duration = if cfg!(test) {
@@ -48,6 +49,10 @@ pub struct IngestApiGarbageCollectorCounters {
     pub num_deleted_queues: usize,
     /// The number of delete failures.
     pub num_delete_failures: usize,
+    /// The number of indexed records deleted from the queues once past their retention period.
+    pub num_truncated_records: u64,
+    /// The number of failures to apply the retention policy of a queue.
+    pub num_truncate_failures: usize,
 }
 
 #[derive(Debug)]
@@ -58,7 +63,10 @@ struct Loop;
 /// This actor has been introduced for Quickwit 0.3, in which indexes are
 /// deleted by the quickwit CLI without any communication with the (unique) indexing node.
 ///
-/// This actor is meant to be removed in the future.
+/// It also applies the retention policy of the queues of the existing indexes, defined by the
+/// `ingest_queue` indexing settings: the indexed records older than the retention period are
+/// deleted, while the queues without retention period are truncated as soon as their records are
+/// indexed.
 pub struct IngestApiGarbageCollector {
     metastore: Arc<dyn Metastore>,
     ingest_api_service: Mailbox<IngestApiService>,
@@ -110,14 +118,15 @@ impl IngestApiGarbageCollector {
             .collect();
         debug!(queues=?queues, "List ingest API queues.");
 
-        let index_ids: HashSet<String> = self
+        let index_metadatas: HashMap<String, IndexMetadata> = self
             .metastore
             .list_indexes_metadatas()
             .await
             .context("Failed to list queues")?
             .into_iter()
-            .map(|index_metadata| index_metadata.index_id)
+            .map(|index_metadata| (index_metadata.index_id.clone(), index_metadata))
             .collect();
+        let index_ids: HashSet<String> = index_metadatas.keys().cloned().collect();
         debug!(index_ids=?index_ids, metastore_uri=%self.metastore.uri(), "List indexes.");
 
         let queue_ids_to_delete = queues.difference(&index_ids);
@@ -126,15 +135,62 @@ impl IngestApiGarbageCollector {
             if let Err(delete_queue_error) = self.delete_queue(queue_id).await {
                 error!(error=?delete_queue_error, queue_id=%queue_id, "queue-delete-failure");
                 self.counters.num_delete_failures += 1;
+                INDEXER_METRICS.ingest_api_gc_delete_failures_total.inc();
             } else {
                 info!(queue_id=%queue_id, "queue-delete-success");
                 self.counters.num_deleted_queues += 1;
+                INDEXER_METRICS.ingest_api_gc_deleted_queues_total.inc();
+            }
+        }
+        for queue_id in queues.intersection(&index_ids) {
+            let index_metadata = &index_metadatas[queue_id];
+            match apply_queue_retention(&self.ingest_api_service, index_metadata).await {
+                Ok(num_truncated_records) => {
+                    debug!(
+                        queue_id=%queue_id,
+                        num_truncated_records=%num_truncated_records,
+                        "queue-truncate-success"
+                    );
+                    self.counters.num_truncated_records += num_truncated_records;
+                    INDEXER_METRICS
+                        .ingest_api_gc_truncated_records_total
+                        .inc_by(num_truncated_records);
+                }
+                Err(truncate_queue_error) => {
+                    error!(
+                        error=?truncate_queue_error,
+                        queue_id=%queue_id,
+                        "queue-truncate-failure"
+                    );
+                    self.counters.num_truncate_failures += 1;
+                    INDEXER_METRICS.ingest_api_gc_truncate_failures_total.inc();
+                }
             }
         }
         Ok(())
     }
 }
 
+/// Applies the retention policy of the queue of an index and returns the number of deleted records.
+pub(crate) async fn apply_queue_retention(
+    ingest_api_service: &Mailbox<IngestApiService>,
+    index_metadata: &IndexMetadata,
+) -> anyhow::Result<u64> {
+    let retention_period = index_metadata
+        .indexing_settings
+        .ingest_queue
+        .as_ref()
+        .map(|ingest_queue| ingest_queue.retention_period())
+        .transpose()?;
+    let num_truncated_records = ingest_api_service
+        .ask_for_res(ApplyQueueRetentionRequest {
+            queue_id: index_metadata.index_id.clone(),
+            retention_period,
+        })
+        .await?;
+    Ok(num_truncated_records)
+}
+
 #[async_trait]
 impl Actor for IngestApiGarbageCollector {
     type ObservableState = IngestApiGarbageCollectorCounters;
@@ -162,6 +218,7 @@ impl Handler<Loop> for IngestApiGarbageCollector {
     async fn handle(&mut self, _: Loop, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
         info!("ingest-api-garbage-collect-operation");
         self.counters.num_passes += 1;
+        INDEXER_METRICS.ingest_api_gc_passes_total.inc();
 
         if let Err(gc_err) = self.run_ingest_queues_gc().await {
             // We do not stop the actor here.
@@ -238,6 +295,8 @@ mod tests {
         let state_after_initialization = handler.process_pending_and_observe().await.state;
         assert_eq!(state_after_initialization.num_passes, 1);
         assert_eq!(state_after_initialization.num_deleted_queues, 0);
+        assert_eq!(state_after_initialization.num_truncated_records, 0);
+        assert_eq!(state_after_initialization.num_truncate_failures, 0);
 
         // 30 seconds later
         universe.simulate_time_shift(Duration::from_secs(30)).await;
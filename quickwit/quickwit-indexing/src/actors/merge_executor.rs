@@ -18,16 +18,21 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::BTreeSet;
-use std::ops::RangeInclusive;
-use std::path::Path;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use fail::fail_point;
+use fnv::FnvHasher;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_common::ignore_error_kind;
 use quickwit_common::io::IoControls;
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_directories::UnionDirectory;
@@ -36,6 +41,7 @@ use quickwit_doc_mapper::{DocMapper, QUICKWIT_TOKENIZER_MANAGER};
 use quickwit_metastore::{Metastore, SplitMetadata};
 use quickwit_proto::metastore_api::DeleteTask;
 use quickwit_proto::SearchRequest;
+use serde::{Deserialize, Serialize};
 use tantivy::directory::{DirectoryClone, MmapDirectory, RamDirectory};
 use tantivy::{Directory, Index, IndexMeta, SegmentId, SegmentReader, TantivyError};
 use tokio::runtime::Handle;
@@ -43,11 +49,48 @@ use tracing::{debug, info, instrument, warn};
 
 use crate::actors::Packager;
 use crate::controlled_directory::ControlledDirectory;
+use crate::dedup::{find_duplicate_docs, DocListQuery};
 use crate::merge_policy::MergeOperationType;
 use crate::models::{
     IndexedSplit, IndexedSplitBatch, IndexingPipelineId, MergeScratch, PublishLock,
     ScratchDirectory, SplitAttrs,
 };
+use crate::new_split_id;
+use crate::time_slicing::{
+    find_docs_outside_time_range, list_time_slice_ords, time_slice_range, KeepFilesDirectory,
+};
+
+/// Merges of at least this number of documents are run in steps, so that an interrupted merge
+/// can resume from the output of its completed steps.
+const RESUMABLE_MERGE_MIN_NUM_DOCS: u64 = 20_000_000;
+
+/// Maximum number of splits merged by each step of a resumable merge.
+const MERGE_STEP_MAX_NUM_SPLITS: usize = 4;
+
+/// Progress marker written in the directory of a merge step once it is complete.
+const MERGE_STEP_MARKER_FILE_NAME: &str = "merge-step.json";
+
+/// Progress directories older than this are assumed to belong to merges that will never resume.
+const MERGE_PROGRESS_TTL: Duration = Duration::from_secs(7 * 24 * 3_600);
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MergeExecutorCounters {
+    /// Number of merge and delete operations completed.
+    pub num_merge_operations: Arc<AtomicU64>,
+    /// Number of documents written by merges, including the intermediate steps of resumable
+    /// merges.
+    pub num_merged_docs: Arc<AtomicU64>,
+    /// Number of bytes written by merges, including the intermediate steps of resumable merges.
+    pub num_bytes_written: Arc<AtomicU64>,
+    /// Number of merge steps whose output, left by an interrupted merge, was reused.
+    pub num_resumed_merge_steps: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MergeStepMarker {
+    split_ids: Vec<String>,
+    num_docs: u64,
+}
 
 #[derive(Clone)]
 pub struct MergeExecutor {
@@ -56,17 +99,30 @@ pub struct MergeExecutor {
     doc_mapper: Arc<dyn DocMapper>,
     io_controls: IoControls,
     merge_packager_mailbox: Mailbox<Packager>,
+    /// Field holding the unique id of the documents. If set, merges drop the documents whose id
+    /// appears in another document of the merged splits.
+    dedup_field_opt: Option<String>,
+    /// Directory holding the output of the completed steps of resumable merges. If `None`,
+    /// merges are not resumable.
+    merge_progress_dir_opt: Option<PathBuf>,
+    resumable_merge_min_num_docs: u64,
+    /// Duration, in seconds, of the time slices. If set, merges produce one split per time slice
+    /// spanned by their documents.
+    time_slice_secs_opt: Option<i64>,
+    counters: MergeExecutorCounters,
 }
 
 #[async_trait]
 impl Actor for MergeExecutor {
-    type ObservableState = ();
+    type ObservableState = MergeExecutorCounters;
 
     fn runtime_handle(&self) -> Handle {
         RuntimeType::Blocking.get_runtime_handle()
     }
 
-    fn observable_state(&self) -> Self::ObservableState {}
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
 
     fn queue_capacity(&self) -> QueueCapacity {
         QueueCapacity::Bounded(1)
@@ -75,6 +131,21 @@ impl Actor for MergeExecutor {
     fn name(&self) -> String {
         "MergeExecutor".to_string()
     }
+
+    async fn initialize(&mut self, _ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        if let Some(merge_progress_dir) = &self.merge_progress_dir_opt {
+            tokio::fs::create_dir_all(merge_progress_dir)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to create merge progress directory `{}`.",
+                        merge_progress_dir.display()
+                    )
+                })?;
+            remove_stale_merge_progress_dirs(merge_progress_dir).await;
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -89,17 +160,23 @@ impl Handler<MergeScratch> for MergeExecutor {
     ) -> Result<(), ActorExitStatus> {
         let start = Instant::now();
         let merge_op = merge_scratch.merge_operation;
-        let indexed_split_opt: Option<IndexedSplit> = match merge_op.operation_type {
-            MergeOperationType::Merge => Some(
+        for split in merge_op.splits_as_slice() {
+            split
+                .check_format_compatibility()
+                .map_err(anyhow::Error::from)?;
+        }
+        let indexed_splits: Vec<IndexedSplit> = match merge_op.operation_type {
+            MergeOperationType::Merge | MergeOperationType::Upgrade => {
                 self.process_merge(
                     merge_op.merge_split_id.clone(),
                     merge_op.splits.clone(),
                     merge_scratch.tantivy_dirs,
                     merge_scratch.merge_scratch_directory,
+                    merge_op.operation_type == MergeOperationType::Upgrade,
                     ctx,
                 )
-                .await?,
-            ),
+                .await?
+            }
             MergeOperationType::DeleteAndMerge => {
                 assert_eq!(
                     merge_op.splits.len(),
@@ -116,11 +193,29 @@ impl Handler<MergeScratch> for MergeExecutor {
                     ctx,
                 )
                 .await?
+                .into_iter()
+                .collect()
             }
         };
-        if let Some(indexed_split) = indexed_split_opt {
+        if !indexed_splits.is_empty() {
+            let mut merged_num_docs = 0;
+            for indexed_split in &indexed_splits {
+                let num_bytes_written =
+                    directory_num_bytes(indexed_split.split_scratch_directory.path()).unwrap_or(0);
+                merged_num_docs += indexed_split.split_attrs.num_docs;
+                self.counters
+                    .num_bytes_written
+                    .fetch_add(num_bytes_written, Ordering::Relaxed);
+            }
+            self.counters
+                .num_merge_operations
+                .fetch_add(1, Ordering::Relaxed);
+            self.counters
+                .num_merged_docs
+                .fetch_add(merged_num_docs, Ordering::Relaxed);
             info!(
-                merged_num_docs = %indexed_split.split_attrs.num_docs,
+                merged_num_docs = %merged_num_docs,
+                num_merged_splits = indexed_splits.len(),
                 elapsed_secs = %start.elapsed().as_secs_f32(),
                 operation_type = %merge_op.operation_type,
                 "merge-operation-success"
@@ -129,7 +224,7 @@ impl Handler<MergeScratch> for MergeExecutor {
                 &self.merge_packager_mailbox,
                 IndexedSplitBatch {
                     batch_parent_span: merge_op.merge_parent_span.clone(),
-                    splits: vec![indexed_split],
+                    splits: indexed_splits,
                     checkpoint_delta: Default::default(),
                     publish_lock: PublishLock::default(),
                     merge_operation: Some(merge_op),
@@ -240,10 +335,28 @@ pub fn merge_split_attrs(
         .map(|split| split.delete_opstamp)
         .min()
         .unwrap_or(0);
+    // The merged split inherits the source and node of its input splits, which differ from the
+    // ones of the pipeline when the merge is executed on behalf of another pipeline (e.g. split
+    // compaction run by the janitor).
+    let pipeline_id = match splits.first() {
+        Some(first_split)
+            if splits.iter().all(|split| {
+                split.source_id == first_split.source_id && split.node_id == first_split.node_id
+            }) =>
+        {
+            IndexingPipelineId {
+                index_id: pipeline_id.index_id.clone(),
+                source_id: first_split.source_id.clone(),
+                node_id: first_split.node_id.clone(),
+                pipeline_ord: pipeline_id.pipeline_ord,
+            }
+        }
+        _ => pipeline_id.clone(),
+    };
     SplitAttrs {
         split_id: merge_split_id,
         partition_id,
-        pipeline_id: pipeline_id.clone(),
+        pipeline_id,
         replaced_split_ids,
         time_range,
         num_docs,
@@ -253,6 +366,92 @@ pub fn merge_split_attrs(
     }
 }
 
+/// Returns the name of the directory holding the progress of the merge of `splits`. It only
+/// depends on the set of merged splits so that a retried merge finds the progress of the previous
+/// attempt.
+fn merge_progress_key(splits: &[SplitMetadata]) -> String {
+    let split_ids: BTreeSet<&str> = splits.iter().map(|split| split.split_id()).collect();
+    let mut hasher = FnvHasher::default();
+    for split_id in split_ids {
+        split_id.hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Returns whether the step directory holds the complete output of the merge of `split_ids`.
+fn is_merge_step_completed(step_path: &Path, split_ids: &[String]) -> bool {
+    let marker_path = step_path.join(MERGE_STEP_MARKER_FILE_NAME);
+    let marker_json = match std::fs::read(&marker_path) {
+        Ok(marker_json) => marker_json,
+        Err(_) => return false,
+    };
+    let marker = match serde_json::from_slice::<MergeStepMarker>(&marker_json) {
+        Ok(marker) => marker,
+        Err(error) => {
+            warn!(marker_path=%marker_path.display(), error=?error, "invalid-merge-step-marker");
+            return false;
+        }
+    };
+    if marker.split_ids != split_ids {
+        return false;
+    }
+    // The files of the step may have been garbage collected by the final merge of a previous
+    // attempt that was interrupted before removing the progress directory.
+    match step_num_docs(step_path) {
+        Ok(num_docs) => num_docs == marker.num_docs,
+        Err(error) => {
+            warn!(step_path=%step_path.display(), error=?error, "invalid-merge-step");
+            false
+        }
+    }
+}
+
+fn step_num_docs(step_path: &Path) -> anyhow::Result<u64> {
+    let step_index = open_index(MmapDirectory::open(step_path)?)?;
+    let mut num_docs = 0;
+    for segment in step_index.searchable_segments()? {
+        num_docs += SegmentReader::open(&segment)?.num_docs() as u64;
+    }
+    Ok(num_docs)
+}
+
+/// Returns the total size of the files of a directory, ignoring its subdirectories.
+fn directory_num_bytes(dir_path: &Path) -> io::Result<u64> {
+    let mut num_bytes = 0;
+    for dir_entry in std::fs::read_dir(dir_path)? {
+        let metadata = dir_entry?.metadata()?;
+        if metadata.is_file() {
+            num_bytes += metadata.len();
+        }
+    }
+    Ok(num_bytes)
+}
+
+async fn remove_stale_merge_progress_dirs(merge_progress_dir: &Path) {
+    let mut read_dir = match tokio::fs::read_dir(merge_progress_dir).await {
+        Ok(read_dir) => read_dir,
+        Err(error) => {
+            warn!(error=?error, "failed-to-list-merge-progress-directories");
+            return;
+        }
+    };
+    let now = SystemTime::now();
+    while let Ok(Some(dir_entry)) = read_dir.next_entry().await {
+        let is_stale = dir_entry
+            .metadata()
+            .await
+            .and_then(|metadata| metadata.modified())
+            .map(|modified| now.duration_since(modified).unwrap_or_default() > MERGE_PROGRESS_TTL)
+            .unwrap_or(false);
+        if is_stale {
+            info!(path=%dir_entry.path().display(), "remove-stale-merge-progress-directory");
+            if let Err(error) = tokio::fs::remove_dir_all(dir_entry.path()).await {
+                warn!(path=%dir_entry.path().display(), error=?error, "failed-to-remove-merge-progress-directory");
+            }
+        }
+    }
+}
+
 fn max_merge_ops(splits: &[SplitMetadata]) -> usize {
     splits
         .iter()
@@ -275,18 +474,200 @@ impl MergeExecutor {
             doc_mapper,
             io_controls,
             merge_packager_mailbox,
+            dedup_field_opt: None,
+            merge_progress_dir_opt: None,
+            resumable_merge_min_num_docs: RESUMABLE_MERGE_MIN_NUM_DOCS,
+            time_slice_secs_opt: None,
+            counters: MergeExecutorCounters::default(),
         }
     }
 
+    pub fn set_dedup_field(mut self, doc_id_field_name: String) -> Self {
+        self.dedup_field_opt = Some(doc_id_field_name);
+        self
+    }
+
+    /// Makes large merges resumable: they are run in steps, the output of which is kept in
+    /// `merge_progress_dir` until the merge completes.
+    pub fn set_merge_progress_dir(mut self, merge_progress_dir: PathBuf) -> Self {
+        self.merge_progress_dir_opt = Some(merge_progress_dir);
+        self
+    }
+
+    /// Splits the output of merges by time slices of `slice_duration`.
+    pub fn set_time_slice(mut self, slice_duration: Duration) -> Self {
+        self.time_slice_secs_opt = Some(slice_duration.as_secs().max(1) as i64);
+        self
+    }
+
+    /// Returns the directory holding the progress of the merge of `splits` if the merge should
+    /// be run in resumable steps.
+    fn merge_progress_path(&self, splits: &[SplitMetadata]) -> Option<PathBuf> {
+        // Duplicate documents can only be detected over the whole set of merged splits.
+        if self.dedup_field_opt.is_some()
+            || splits.len() <= MERGE_STEP_MAX_NUM_SPLITS
+            || sum_num_docs(splits) < self.resumable_merge_min_num_docs
+        {
+            return None;
+        }
+        let merge_progress_dir = self.merge_progress_dir_opt.as_ref()?;
+        Some(merge_progress_dir.join(merge_progress_key(splits)))
+    }
+
+    /// Merges the splits by groups of at most `MERGE_STEP_MAX_NUM_SPLITS` and returns the
+    /// directories of the merged groups. The groups already merged by a previous attempt are
+    /// not merged again.
+    async fn run_merge_steps(
+        &self,
+        splits: &[SplitMetadata],
+        tantivy_dirs: Vec<Box<dyn Directory>>,
+        merge_progress_path: &Path,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<Vec<Box<dyn Directory>>> {
+        // The splits are sorted so that the groups of a retried merge are the same.
+        let mut splits_and_dirs: Vec<(&SplitMetadata, Box<dyn Directory>)> =
+            splits.iter().zip(tantivy_dirs).collect();
+        splits_and_dirs.sort_by(|(left, _), (right, _)| left.split_id().cmp(right.split_id()));
+
+        let mut step_dirs: Vec<Box<dyn Directory>> = Vec::new();
+        for (step_ord, step_splits_and_dirs) in splits_and_dirs
+            .chunks(MERGE_STEP_MAX_NUM_SPLITS)
+            .enumerate()
+        {
+            if step_splits_and_dirs.len() == 1 {
+                step_dirs.push(step_splits_and_dirs[0].1.clone());
+                continue;
+            }
+            let split_ids: Vec<String> = step_splits_and_dirs
+                .iter()
+                .map(|(split, _)| split.split_id().to_string())
+                .collect();
+            let step_path = merge_progress_path.join(format!("step-{}", step_ord));
+
+            if is_merge_step_completed(&step_path, &split_ids) {
+                info!(step_ord = step_ord, split_ids=?split_ids, "resume-merge-step");
+                self.counters
+                    .num_resumed_merge_steps
+                    .fetch_add(1, Ordering::Relaxed);
+            } else {
+                ignore_error_kind!(
+                    io::ErrorKind::NotFound,
+                    tokio::fs::remove_dir_all(&step_path).await
+                )?;
+                tokio::fs::create_dir_all(&step_path).await?;
+
+                let step_tantivy_dirs: Vec<Box<dyn Directory>> = step_splits_and_dirs
+                    .iter()
+                    .map(|(_, tantivy_dir)| tantivy_dir.clone())
+                    .collect();
+                let (union_index_meta, split_directories) =
+                    open_split_directories(&step_tantivy_dirs)?;
+                self.merge_split_directories(
+                    union_index_meta,
+                    split_directories,
+                    Vec::new(),
+                    None,
+                    false,
+                    None,
+                    &step_path,
+                    ctx,
+                )
+                .await?;
+                let num_docs = step_num_docs(&step_path)?;
+                let num_bytes_written = directory_num_bytes(&step_path)?;
+                // The marker is written last: a step without marker is merged again.
+                let marker = MergeStepMarker {
+                    split_ids,
+                    num_docs,
+                };
+                tokio::fs::write(
+                    step_path.join(MERGE_STEP_MARKER_FILE_NAME),
+                    serde_json::to_vec(&marker)?,
+                )
+                .await?;
+                self.counters
+                    .num_merged_docs
+                    .fetch_add(num_docs, Ordering::Relaxed);
+                self.counters
+                    .num_bytes_written
+                    .fetch_add(num_bytes_written, Ordering::Relaxed);
+                info!(
+                    step_ord = step_ord,
+                    num_docs = num_docs,
+                    "merge-step-success"
+                );
+            }
+            ctx.record_progress();
+            step_dirs.push(Box::new(MmapDirectory::open(&step_path)?));
+        }
+        Ok(step_dirs)
+    }
+
     async fn process_merge(
         &mut self,
         merge_split_id: String,
         splits: Vec<SplitMetadata>,
         tantivy_dirs: Vec<Box<dyn Directory>>,
         merge_scratch_directory: ScratchDirectory,
+        is_upgrade: bool,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<Vec<IndexedSplit>> {
+        let merge_progress_path_opt = self.merge_progress_path(&splits);
+        let tantivy_dirs = if let Some(merge_progress_path) = &merge_progress_path_opt {
+            self.run_merge_steps(&splits, tantivy_dirs, merge_progress_path, ctx)
+                .await?
+        } else {
+            tantivy_dirs
+        };
+        // An upgraded split is rewritten as is, it is not split by time slice.
+        let time_slices = if is_upgrade {
+            Vec::new()
+        } else {
+            self.list_time_slices(&tantivy_dirs)?
+        };
+        let indexed_splits = if time_slices.len() > 1 {
+            self.merge_time_slices(
+                merge_split_id,
+                &splits,
+                &tantivy_dirs,
+                time_slices,
+                &merge_scratch_directory,
+                ctx,
+            )
+            .await?
+        } else {
+            let indexed_split = self
+                .merge_splits(
+                    merge_split_id,
+                    &splits,
+                    &tantivy_dirs,
+                    merge_scratch_directory,
+                    is_upgrade,
+                    ctx,
+                )
+                .await?;
+            vec![indexed_split]
+        };
+        if let Some(merge_progress_path) = &merge_progress_path_opt {
+            // The merged splits no longer depend on the output of the merge steps.
+            drop(tantivy_dirs);
+            if let Err(error) = tokio::fs::remove_dir_all(merge_progress_path).await {
+                warn!(path=%merge_progress_path.display(), error=?error, "failed-to-remove-merge-progress-directory");
+            }
+        }
+        Ok(indexed_splits)
+    }
+
+    async fn merge_splits(
+        &self,
+        merge_split_id: String,
+        splits: &[SplitMetadata],
+        tantivy_dirs: &[Box<dyn Directory>],
+        merge_scratch_directory: ScratchDirectory,
+        is_upgrade: bool,
         ctx: &ActorContext<Self>,
     ) -> anyhow::Result<IndexedSplit> {
-        let (union_index_meta, split_directories) = open_split_directories(&tantivy_dirs)?;
+        let (union_index_meta, split_directories) = open_split_directories(tantivy_dirs)?;
         // TODO it would be nice if tantivy could let us run the merge in the current thread.
         fail_point!("before-merge-split");
         let controlled_directory = self
@@ -295,6 +676,8 @@ impl MergeExecutor {
                 split_directories,
                 Vec::new(),
                 None,
+                is_upgrade,
+                None,
                 merge_scratch_directory.path(),
                 ctx,
             )
@@ -306,7 +689,25 @@ impl MergeExecutor {
         let merged_index = open_index(controlled_directory.clone())?;
         ctx.record_progress();
 
-        let split_attrs = merge_split_attrs(merge_split_id, &self.pipeline_id, &splits);
+        let mut split_attrs = merge_split_attrs(merge_split_id, &self.pipeline_id, splits);
+        if is_upgrade {
+            // An upgraded split is rewritten as is, it does not undergo a merge.
+            split_attrs.num_merge_ops = max_merge_ops(splits);
+        }
+        if self.dedup_field_opt.is_some() {
+            // Duplicate documents may have been dropped by the merge.
+            let num_docs: u64 = merged_index
+                .searchable_segment_metas()?
+                .iter()
+                .map(|segment_meta| segment_meta.num_docs() as u64)
+                .sum();
+            if num_docs < split_attrs.num_docs {
+                split_attrs.uncompressed_docs_size_in_bytes =
+                    (num_docs as f32 * split_attrs.uncompressed_docs_size_in_bytes as f32
+                        / split_attrs.num_docs as f32) as u64;
+                split_attrs.num_docs = num_docs;
+            }
+        }
         Ok(IndexedSplit {
             split_attrs,
             index: merged_index,
@@ -315,6 +716,184 @@ impl MergeExecutor {
         })
     }
 
+    /// Returns the time ranges of the time slices holding the documents of the splits to merge,
+    /// or an empty list if time slicing is disabled.
+    fn list_time_slices(
+        &self,
+        tantivy_dirs: &[Box<dyn Directory>],
+    ) -> anyhow::Result<Vec<Range<i64>>> {
+        let (slice_secs, timestamp_field_name) = match (
+            self.time_slice_secs_opt,
+            self.doc_mapper.timestamp_field_name(),
+        ) {
+            (Some(slice_secs), Some(timestamp_field_name)) => (slice_secs, timestamp_field_name),
+            _ => return Ok(Vec::new()),
+        };
+        let (union_index_meta, split_directories) = open_split_directories(tantivy_dirs)?;
+        let shadowing_meta_json_directory = create_shadowing_meta_json_directory(union_index_meta)?;
+        let mut directory_stack: Vec<Box<dyn Directory>> =
+            vec![Box::new(shadowing_meta_json_directory)];
+        directory_stack.extend(split_directories.into_iter());
+        let union_index = open_index(UnionDirectory::union_of(directory_stack))?;
+        let timestamp_field = union_index
+            .schema()
+            .get_field(&timestamp_field_name)
+            .ok_or_else(|| {
+                TantivyError::SchemaError(format!(
+                    "Timestamp field `{}` does not exist",
+                    timestamp_field_name
+                ))
+            })?;
+        let time_slices = list_time_slice_ords(&union_index, timestamp_field, slice_secs)?
+            .into_iter()
+            .map(|slice_ord| time_slice_range(slice_ord, slice_secs))
+            .collect();
+        Ok(time_slices)
+    }
+
+    /// Merges the splits into one split per time slice, keeping only the documents of the slice
+    /// in each merged split.
+    ///
+    /// The time slices are bisected: each merge partitions the documents of its inputs between
+    /// the two halves of its time slices, so the documents are rewritten once per bisection level
+    /// instead of once per time slice.
+    async fn merge_time_slices(
+        &self,
+        merge_split_id: String,
+        splits: &[SplitMetadata],
+        tantivy_dirs: &[Box<dyn Directory>],
+        time_slices: Vec<Range<i64>>,
+        merge_scratch_directory: &ScratchDirectory,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<Vec<IndexedSplit>> {
+        let timestamp_field_name = self
+            .doc_mapper
+            .timestamp_field_name()
+            .expect("Time slicing requires a timestamp field.");
+        let merge_split_attrs = merge_split_attrs(merge_split_id, &self.pipeline_id, splits);
+        let mut indexed_splits = Vec::with_capacity(time_slices.len());
+
+        // Inputs left to partition, along with the scratch directory holding them, if any, and
+        // the ordinals of their time slices. The left halves are popped first so that the merged
+        // splits come out in time order.
+        let mut pending_partitions: Vec<(
+            Vec<Box<dyn Directory>>,
+            Option<ScratchDirectory>,
+            Range<usize>,
+        )> = vec![(tantivy_dirs.to_vec(), None, 0..time_slices.len())];
+
+        while let Some((input_dirs, _input_scratch_directory_opt, slice_ords)) =
+            pending_partitions.pop()
+        {
+            let time_range =
+                time_slices[slice_ords.start].start..time_slices[slice_ords.end - 1].end;
+            let split_scratch_directory = merge_scratch_directory
+                .named_temp_child(format!("time-slice-{}-", time_range.start))?;
+            let (union_index_meta, split_directories) = open_split_directories(&input_dirs)?;
+            let split_directories: Vec<Box<dyn Directory>> = split_directories
+                .into_iter()
+                .map(|split_directory| {
+                    Box::new(KeepFilesDirectory::new(split_directory)) as Box<dyn Directory>
+                })
+                .collect();
+            let controlled_directory = self
+                .merge_split_directories(
+                    union_index_meta,
+                    split_directories,
+                    Vec::new(),
+                    None,
+                    true,
+                    Some(time_range.clone()),
+                    split_scratch_directory.path(),
+                    ctx,
+                )
+                .await?;
+            let merged_index = open_index(controlled_directory.clone())?;
+            ctx.record_progress();
+
+            if slice_ords.len() > 1 {
+                let num_docs: u32 = merged_index
+                    .searchable_segment_metas()?
+                    .iter()
+                    .map(|segment_meta| segment_meta.num_docs())
+                    .sum();
+                // Deduplication may have dropped all the documents of these slices.
+                if num_docs == 0 {
+                    continue;
+                }
+                let middle_slice_ord = slice_ords.start + slice_ords.len() / 2;
+                for half_slice_ords in [
+                    middle_slice_ord..slice_ords.end,
+                    slice_ords.start..middle_slice_ord,
+                ] {
+                    let half_input_dirs: Vec<Box<dyn Directory>> = vec![Box::new(
+                        MmapDirectory::open(split_scratch_directory.path())?,
+                    )];
+                    pending_partitions.push((
+                        half_input_dirs,
+                        Some(split_scratch_directory.clone()),
+                        half_slice_ords,
+                    ));
+                }
+                continue;
+            }
+            let timestamp_field = merged_index
+                .schema()
+                .get_field(&timestamp_field_name)
+                .expect("The timestamp field was found in the splits to merge.");
+            let mut num_docs = 0;
+            let mut time_range_opt: Option<RangeInclusive<i64>> = None;
+            for segment in merged_index.searchable_segments()? {
+                let segment_reader = SegmentReader::open(&segment)?;
+                if segment_reader.num_docs() == 0 {
+                    continue;
+                }
+                num_docs += segment_reader.num_docs() as u64;
+                let timestamp_reader = timestamp_field_reader(timestamp_field, &segment_reader)?;
+                let (min_timestamp, max_timestamp) =
+                    (timestamp_reader.min_value(), timestamp_reader.max_value());
+                time_range_opt = Some(match time_range_opt {
+                    Some(time_range) => {
+                        min_timestamp.min(*time_range.start())
+                            ..=max_timestamp.max(*time_range.end())
+                    }
+                    None => min_timestamp..=max_timestamp,
+                });
+            }
+            // Deduplication may have dropped all the documents of the slice.
+            if num_docs == 0 {
+                continue;
+            }
+            let split_id = if indexed_splits.is_empty() {
+                merge_split_attrs.split_id.clone()
+            } else {
+                new_split_id()
+            };
+            let uncompressed_docs_size_in_bytes =
+                (num_docs as f32 * merge_split_attrs.uncompressed_docs_size_in_bytes as f32
+                    / merge_split_attrs.num_docs as f32) as u64;
+            let split_attrs = SplitAttrs {
+                split_id,
+                partition_id: merge_split_attrs.partition_id,
+                pipeline_id: merge_split_attrs.pipeline_id.clone(),
+                replaced_split_ids: merge_split_attrs.replaced_split_ids.clone(),
+                time_range: time_range_opt,
+                num_docs,
+                uncompressed_docs_size_in_bytes,
+                delete_opstamp: merge_split_attrs.delete_opstamp,
+                num_merge_ops: merge_split_attrs.num_merge_ops,
+            };
+            debug!(split_id=%split_attrs.split_id, time_slice=?time_range, num_docs=num_docs, "merge-time-slice-success");
+            indexed_splits.push(IndexedSplit {
+                split_attrs,
+                index: merged_index,
+                split_scratch_directory,
+                controlled_directory_opt: Some(controlled_directory),
+            });
+        }
+        Ok(indexed_splits)
+    }
+
     async fn process_delete_and_merge(
         &mut self,
         merge_split_id: String,
@@ -353,6 +932,8 @@ impl MergeExecutor {
                 split_directories,
                 delete_tasks,
                 Some(self.doc_mapper.clone()),
+                false,
+                None,
                 merge_scratch_directory.path(),
                 ctx,
             )
@@ -424,6 +1005,8 @@ impl MergeExecutor {
         split_directories: Vec<Box<dyn Directory>>,
         delete_tasks: Vec<DeleteTask>,
         doc_mapper_opt: Option<Arc<dyn DocMapper>>,
+        force_merge: bool,
+        time_slice_opt: Option<Range<i64>>,
         output_path: &Path,
         ctx: &ActorContext<MergeExecutor>,
     ) -> anyhow::Result<ControlledDirectory> {
@@ -476,14 +1059,71 @@ impl MergeExecutor {
             index_writer.commit()?;
         }
 
+        let mut num_duplicate_docs = 0;
+        if let Some(doc_id_field_name) = &self.dedup_field_opt {
+            let doc_id_field = union_index
+                .schema()
+                .get_field(doc_id_field_name)
+                .ok_or_else(|| {
+                    TantivyError::SchemaError(format!(
+                        "Dedup doc id field `{}` does not exist",
+                        doc_id_field_name
+                    ))
+                })?;
+            let duplicate_docs = find_duplicate_docs(&union_index, doc_id_field)?;
+            num_duplicate_docs = duplicate_docs.values().map(Vec::len).sum::<usize>();
+            if num_duplicate_docs > 0 {
+                debug!(
+                    num_duplicate_docs = num_duplicate_docs,
+                    "delete-duplicate-docs"
+                );
+                index_writer.delete_query(Box::new(DocListQuery::new(duplicate_docs)))?;
+                index_writer.commit()?;
+            }
+        }
+
+        let mut num_docs_outside_time_slice = 0;
+        if let Some(time_slice) = &time_slice_opt {
+            let timestamp_field_name = self
+                .doc_mapper
+                .timestamp_field_name()
+                .ok_or_else(|| anyhow!("Time slicing requires a timestamp field."))?;
+            let timestamp_field = union_index
+                .schema()
+                .get_field(&timestamp_field_name)
+                .ok_or_else(|| {
+                    TantivyError::SchemaError(format!(
+                        "Timestamp field `{}` does not exist",
+                        timestamp_field_name
+                    ))
+                })?;
+            let docs_outside_time_slice =
+                find_docs_outside_time_range(&union_index, timestamp_field, time_slice)?;
+            num_docs_outside_time_slice = docs_outside_time_slice.values().map(Vec::len).sum();
+            if num_docs_outside_time_slice > 0 {
+                debug!(
+                    num_docs_outside_time_slice = num_docs_outside_time_slice,
+                    "delete-docs-outside-time-slice"
+                );
+                index_writer.delete_query(Box::new(DocListQuery::new(docs_outside_time_slice)))?;
+                index_writer.commit()?;
+            }
+        }
+
         let segment_ids: Vec<SegmentId> = union_index
             .searchable_segment_metas()?
             .into_iter()
             .map(|segment_meta| segment_meta.id())
             .collect();
 
-        // A merge is useless if there is no delete and only one segment.
-        if num_delete_tasks == 0 && segment_ids.len() <= 1 {
+        // A merge is useless if there is no delete and only one segment, unless the segment must be
+        // rewritten with the current index format.
+        if !force_merge
+            && num_delete_tasks == 0
+            && num_duplicate_docs == 0
+            && num_docs_outside_time_slice == 0
+            && segment_ids.len() <= 1
+        {
             return Ok(output_directory);
         }
 
@@ -514,6 +1154,31 @@ mod tests {
     use crate::models::{IndexingPipelineId, ScratchDirectory};
     use crate::{get_tantivy_directory_from_split_bundle, new_split_id, TestSandbox};
 
+    #[test]
+    fn test_merge_split_attrs_inherits_source_and_node() {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "unknown".to_string(),
+            node_id: "unknown".to_string(),
+            pipeline_ord: 0,
+        };
+        let split_metadata = |split_id: &str, node_id: &str| SplitMetadata {
+            split_id: split_id.to_string(),
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: node_id.to_string(),
+            ..Default::default()
+        };
+        let splits = [split_metadata("a", "node-1"), split_metadata("b", "node-1")];
+        let split_attrs = merge_split_attrs(new_split_id(), &pipeline_id, &splits);
+        assert_eq!(split_attrs.pipeline_id.source_id, "test-source");
+        assert_eq!(split_attrs.pipeline_id.node_id, "node-1");
+
+        let splits = [split_metadata("a", "node-1"), split_metadata("b", "node-2")];
+        let split_attrs = merge_split_attrs(new_split_id(), &pipeline_id, &splits);
+        assert_eq!(split_attrs.pipeline_id, pipeline_id);
+    }
+
     #[tokio::test]
     async fn test_merge_executor() -> anyhow::Result<()> {
         let pipeline_id = IndexingPipelineId {
@@ -602,6 +1267,433 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_merge_executor_time_slicing() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-time-slicing".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: ts
+                type: datetime
+                input_formats:
+                - unix_timestamp
+                fast: true
+        "#;
+        let indexing_settings_yaml = "timestamp_field: ts";
+        let test_sandbox = TestSandbox::create(
+            &pipeline_id.index_id,
+            doc_mapping_yaml,
+            indexing_settings_yaml,
+            &["body"],
+            None,
+        )
+        .await?;
+        // Three splits spanning three days.
+        for timestamps in [
+            [86_400 - 10, 86_400 + 10],
+            [10, 20],
+            [86_400 + 20, 2 * 86_400 + 30],
+        ] {
+            let docs = timestamps
+                .into_iter()
+                .map(|timestamp| serde_json::json!({"body": "doc", "ts": timestamp}));
+            test_sandbox.add_documents(docs).await?;
+        }
+        let metastore = test_sandbox.metastore();
+        let split_metas: Vec<SplitMetadata> = metastore
+            .list_all_splits(&pipeline_id.index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        assert_eq!(split_metas.len(), 3);
+        let merge_scratch_directory = ScratchDirectory::for_test()?;
+        let downloaded_splits_directory =
+            merge_scratch_directory.named_temp_child("downloaded-splits-")?;
+        let mut tantivy_dirs: Vec<Box<dyn Directory>> = vec![];
+        for split_meta in &split_metas {
+            let split_filename = split_file(split_meta.split_id());
+            let dest_filepath = downloaded_splits_directory.path().join(&split_filename);
+            test_sandbox
+                .storage()
+                .copy_to_file(Path::new(&split_filename), &dest_filepath)
+                .await?;
+            tantivy_dirs.push(get_tantivy_directory_from_split_bundle(&dest_filepath).unwrap())
+        }
+        let merge_ops_inventory = Inventory::new();
+        let merge_operation =
+            merge_ops_inventory.track(MergeOperation::new_merge_operation(split_metas));
+        let merge_split_id = merge_operation.merge_split_id.clone();
+        let merge_scratch = MergeScratch {
+            merge_operation,
+            tantivy_dirs,
+            merge_scratch_directory,
+            downloaded_splits_directory,
+        };
+        let (merge_packager_mailbox, merge_packager_inbox) = create_test_mailbox();
+        let merge_executor = MergeExecutor::new(
+            pipeline_id,
+            metastore,
+            test_sandbox.doc_mapper(),
+            IoControls::default(),
+            merge_packager_mailbox,
+        )
+        .set_time_slice(Duration::from_secs(86_400));
+        let universe = Universe::new();
+        let (merge_executor_mailbox, merge_executor_handle) =
+            universe.spawn_builder().spawn(merge_executor);
+        merge_executor_mailbox.send_message(merge_scratch).await?;
+        let observation = merge_executor_handle.process_pending_and_observe().await;
+        assert_eq!(
+            observation
+                .state
+                .num_merge_operations
+                .load(Ordering::Relaxed),
+            1
+        );
+        assert_eq!(observation.state.num_merged_docs.load(Ordering::Relaxed), 6);
+        let packager_msgs: Vec<IndexedSplitBatch> = merge_packager_inbox.drain_for_test_typed();
+        assert_eq!(packager_msgs.len(), 1);
+        let splits = &packager_msgs[0].splits;
+        assert_eq!(splits.len(), 3);
+
+        let first_split_attrs = &splits[0].split_attrs;
+        assert_eq!(first_split_attrs.split_id, merge_split_id);
+        assert_eq!(first_split_attrs.num_docs, 3);
+        assert_eq!(first_split_attrs.time_range, Some(10..=86_400 - 10));
+        assert_eq!(first_split_attrs.replaced_split_ids.len(), 3);
+
+        let second_split_attrs = &splits[1].split_attrs;
+        assert_ne!(second_split_attrs.split_id, merge_split_id);
+        assert_eq!(second_split_attrs.num_docs, 2);
+        assert_eq!(
+            second_split_attrs.time_range,
+            Some(86_400 + 10..=86_400 + 20)
+        );
+        assert_eq!(second_split_attrs.replaced_split_ids.len(), 3);
+
+        let third_split_attrs = &splits[2].split_attrs;
+        assert_ne!(third_split_attrs.split_id, merge_split_id);
+        assert_ne!(third_split_attrs.split_id, second_split_attrs.split_id);
+        assert_eq!(third_split_attrs.num_docs, 1);
+        assert_eq!(
+            third_split_attrs.time_range,
+            Some(2 * 86_400 + 30..=2 * 86_400 + 30)
+        );
+
+        for (split, expected_num_docs) in splits.iter().zip([3, 2, 1]) {
+            let searcher = split.index.reader()?.searcher();
+            assert_eq!(searcher.segment_readers().len(), 1);
+            assert_eq!(searcher.num_docs(), expected_num_docs);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_executor_upgrade() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-upgrade".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox =
+            TestSandbox::create(&pipeline_id.index_id, doc_mapping_yaml, "", &["body"], None)
+                .await?;
+        test_sandbox
+            .add_documents(vec![
+                serde_json::json!({"body": "one"}),
+                serde_json::json!({"body": "two"}),
+            ])
+            .await?;
+        let metastore = test_sandbox.metastore();
+        let mut split_metas: Vec<SplitMetadata> = metastore
+            .list_all_splits(&pipeline_id.index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        assert_eq!(split_metas.len(), 1);
+        let merge_scratch_directory = ScratchDirectory::for_test()?;
+        let downloaded_splits_directory =
+            merge_scratch_directory.named_temp_child("downloaded-splits-")?;
+        let tantivy_dirs =
+            download_splits(&test_sandbox, &split_metas, &downloaded_splits_directory).await?;
+        let split_meta = split_metas.pop().unwrap();
+        let merge_ops_inventory = Inventory::new();
+        let merge_operation =
+            merge_ops_inventory.track(MergeOperation::new_upgrade_operation(split_meta.clone()));
+        let merge_scratch = MergeScratch {
+            merge_operation,
+            tantivy_dirs,
+            merge_scratch_directory,
+            downloaded_splits_directory,
+        };
+        let (merge_packager_mailbox, merge_packager_inbox) = create_test_mailbox();
+        let merge_executor = MergeExecutor::new(
+            pipeline_id,
+            metastore,
+            test_sandbox.doc_mapper(),
+            IoControls::default(),
+            merge_packager_mailbox,
+        );
+        let universe = Universe::new();
+        let (merge_executor_mailbox, merge_executor_handle) =
+            universe.spawn_builder().spawn(merge_executor);
+        merge_executor_mailbox.send_message(merge_scratch).await?;
+        merge_executor_handle.process_pending_and_observe().await;
+        let packager_msgs: Vec<IndexedSplitBatch> = merge_packager_inbox.drain_for_test_typed();
+        assert_eq!(packager_msgs.len(), 1);
+        let split_attrs_after_upgrade = &packager_msgs[0].splits[0].split_attrs;
+        assert_eq!(split_attrs_after_upgrade.num_docs, 2);
+        assert_eq!(
+            split_attrs_after_upgrade.replaced_split_ids,
+            vec![split_meta.split_id().to_string()]
+        );
+        assert_eq!(
+            split_attrs_after_upgrade.num_merge_ops,
+            split_meta.num_merge_ops
+        );
+        let reader = packager_msgs[0].splits[0].index.reader()?;
+        assert_eq!(reader.searcher().num_docs(), 2);
+        Ok(())
+    }
+
+    async fn download_splits(
+        test_sandbox: &TestSandbox,
+        split_metas: &[SplitMetadata],
+        downloaded_splits_directory: &ScratchDirectory,
+    ) -> anyhow::Result<Vec<Box<dyn Directory>>> {
+        let mut tantivy_dirs: Vec<Box<dyn Directory>> = vec![];
+        for split_meta in split_metas {
+            let split_filename = split_file(split_meta.split_id());
+            let dest_filepath = downloaded_splits_directory.path().join(&split_filename);
+            test_sandbox
+                .storage()
+                .copy_to_file(Path::new(&split_filename), &dest_filepath)
+                .await?;
+            tantivy_dirs.push(get_tantivy_directory_from_split_bundle(&dest_filepath).unwrap())
+        }
+        Ok(tantivy_dirs)
+    }
+
+    #[tokio::test]
+    async fn test_merge_executor_resumes_interrupted_merge() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-resume".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox =
+            TestSandbox::create(&pipeline_id.index_id, doc_mapping_yaml, "", &["body"], None)
+                .await?;
+        for split_ord in 0..6 {
+            let single_doc =
+                std::iter::once(serde_json::json!({ "body": format!("split {}", split_ord) }));
+            test_sandbox.add_documents(single_doc).await?;
+        }
+        let metastore = test_sandbox.metastore();
+        let split_metas: Vec<SplitMetadata> = metastore
+            .list_all_splits(&pipeline_id.index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        assert_eq!(split_metas.len(), 6);
+
+        let merge_progress_dir = tempfile::tempdir()?;
+        let (merge_packager_mailbox, merge_packager_inbox) = create_test_mailbox();
+        let mut merge_executor = MergeExecutor::new(
+            pipeline_id,
+            metastore,
+            test_sandbox.doc_mapper(),
+            IoControls::default(),
+            merge_packager_mailbox,
+        )
+        .set_merge_progress_dir(merge_progress_dir.path().to_path_buf());
+        merge_executor.resumable_merge_min_num_docs = 0;
+        let merge_progress_path = merge_executor.merge_progress_path(&split_metas).unwrap();
+
+        // The first attempt is interrupted after running the merge steps.
+        let universe = Universe::new();
+        let (merge_executor_mailbox, _merge_executor_inbox) = create_test_mailbox();
+        let (observable_state_tx, _observable_state_rx) =
+            tokio::sync::watch::channel(MergeExecutorCounters::default());
+        let ctx = ActorContext::for_test(&universe, merge_executor_mailbox, observable_state_tx);
+        let scratch_directory = ScratchDirectory::for_test()?;
+        let tantivy_dirs = download_splits(&test_sandbox, &split_metas, &scratch_directory).await?;
+        let step_dirs = merge_executor
+            .run_merge_steps(&split_metas, tantivy_dirs, &merge_progress_path, &ctx)
+            .await?;
+        assert_eq!(step_dirs.len(), 2);
+        assert!(merge_progress_path
+            .join("step-1")
+            .join(MERGE_STEP_MARKER_FILE_NAME)
+            .exists());
+        assert_eq!(
+            merge_executor
+                .counters
+                .num_merged_docs
+                .load(Ordering::Relaxed),
+            6
+        );
+        drop(step_dirs);
+
+        // The second attempt reuses the output of the steps.
+        let merge_scratch_directory = ScratchDirectory::for_test()?;
+        let downloaded_splits_directory =
+            merge_scratch_directory.named_temp_child("downloaded-splits-")?;
+        let tantivy_dirs =
+            download_splits(&test_sandbox, &split_metas, &downloaded_splits_directory).await?;
+        let merge_ops_inventory = Inventory::new();
+        let merge_operation =
+            merge_ops_inventory.track(MergeOperation::new_merge_operation(split_metas));
+        let merge_scratch = MergeScratch {
+            merge_operation,
+            tantivy_dirs,
+            merge_scratch_directory,
+            downloaded_splits_directory,
+        };
+        let (merge_executor_mailbox, merge_executor_handle) =
+            universe.spawn_builder().spawn(merge_executor);
+        merge_executor_mailbox.send_message(merge_scratch).await?;
+        let merge_executor_counters = merge_executor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(
+            merge_executor_counters
+                .num_resumed_merge_steps
+                .load(Ordering::Relaxed),
+            2
+        );
+        assert_eq!(
+            merge_executor_counters
+                .num_merge_operations
+                .load(Ordering::Relaxed),
+            1
+        );
+        assert_eq!(
+            merge_executor_counters
+                .num_merged_docs
+                .load(Ordering::Relaxed),
+            12
+        );
+        assert!(
+            merge_executor_counters
+                .num_bytes_written
+                .load(Ordering::Relaxed)
+                > 0
+        );
+        assert!(!merge_progress_path.exists());
+
+        let packager_msgs: Vec<IndexedSplitBatch> = merge_packager_inbox.drain_for_test_typed();
+        assert_eq!(packager_msgs.len(), 1);
+        assert_eq!(packager_msgs[0].splits[0].split_attrs.num_docs, 6);
+        let reader = packager_msgs[0].splits[0].index.reader()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 6);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_executor_drops_duplicate_docs() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index-dedup".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: event_id
+                type: text
+                tokenizer: raw
+              - name: body
+                type: text
+        "#;
+        let test_sandbox =
+            TestSandbox::create(&pipeline_id.index_id, doc_mapping_yaml, "", &["body"], None)
+                .await?;
+        for event_id in ["a", "b", "a", "c"] {
+            let single_doc = std::iter::once(
+                serde_json::json!({"event_id": event_id, "body": format!("event {}", event_id)}),
+            );
+            test_sandbox.add_documents(single_doc).await?;
+        }
+        let metastore = test_sandbox.metastore();
+        let split_metas: Vec<SplitMetadata> = metastore
+            .list_all_splits(&pipeline_id.index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        assert_eq!(split_metas.len(), 4);
+        let merge_scratch_directory = ScratchDirectory::for_test()?;
+        let downloaded_splits_directory =
+            merge_scratch_directory.named_temp_child("downloaded-splits-")?;
+        let mut tantivy_dirs: Vec<Box<dyn Directory>> = vec![];
+        for split_meta in &split_metas {
+            let split_filename = split_file(split_meta.split_id());
+            let dest_filepath = downloaded_splits_directory.path().join(&split_filename);
+            test_sandbox
+                .storage()
+                .copy_to_file(Path::new(&split_filename), &dest_filepath)
+                .await?;
+            tantivy_dirs.push(get_tantivy_directory_from_split_bundle(&dest_filepath).unwrap())
+        }
+        let merge_ops_inventory = Inventory::new();
+        let merge_operation =
+            merge_ops_inventory.track(MergeOperation::new_merge_operation(split_metas));
+        let merge_scratch = MergeScratch {
+            merge_operation,
+            tantivy_dirs,
+            merge_scratch_directory,
+            downloaded_splits_directory,
+        };
+        let (merge_packager_mailbox, merge_packager_inbox) = create_test_mailbox();
+        let merge_executor = MergeExecutor::new(
+            pipeline_id,
+            metastore,
+            test_sandbox.doc_mapper(),
+            IoControls::default(),
+            merge_packager_mailbox,
+        )
+        .set_dedup_field("event_id".to_string());
+        let universe = Universe::new();
+        let (merge_executor_mailbox, merge_executor_handle) =
+            universe.spawn_builder().spawn(merge_executor);
+        merge_executor_mailbox.send_message(merge_scratch).await?;
+        merge_executor_handle.process_pending_and_observe().await;
+        let packager_msgs: Vec<IndexedSplitBatch> = merge_packager_inbox.drain_for_test_typed();
+        assert_eq!(packager_msgs.len(), 1);
+        let split_attrs_after_merge = &packager_msgs[0].splits[0].split_attrs;
+        assert_eq!(split_attrs_after_merge.num_docs, 3);
+        let reader = packager_msgs[0].splits[0].index.reader()?;
+        let searcher = reader.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 3);
+        Ok(())
+    }
+
     #[test]
     fn test_combine_partition_ids_singleton_unchanged() {
         assert_eq!(combine_partition_ids_aux([17]), 17);
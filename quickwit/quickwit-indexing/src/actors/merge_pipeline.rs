@@ -28,8 +28,13 @@ use quickwit_actors::{
 };
 use quickwit_common::io::IoControls;
 use quickwit_common::KillSwitch;
+use quickwit_config::{
+    DedupSettings, HotcacheSettings, MaintenanceWindows, ParquetSidecarSettings,
+    TimeSlicingSettings,
+};
 use quickwit_doc_mapper::DocMapper;
 use quickwit_metastore::{Metastore, MetastoreError, SplitState};
+use quickwit_storage::Storage;
 use tokio::join;
 use tracing::{debug, error, info, instrument};
 
@@ -37,7 +42,9 @@ use crate::actors::indexing_pipeline::wait_duration_before_retry;
 use crate::actors::merge_split_downloader::MergeSplitDownloader;
 use crate::actors::publisher::PublisherType;
 use crate::actors::sequencer::Sequencer;
-use crate::actors::{MergeExecutor, MergePlanner, Packager, Publisher, Uploader, UploaderType};
+use crate::actors::{
+    MergeExecutor, MergePlanner, Packager, ParquetSidecarWriter, Publisher, Uploader, UploaderType,
+};
 use crate::merge_policy::MergePolicy;
 use crate::models::{IndexingDirectory, IndexingPipelineId, MergeStatistics, Observe};
 use crate::split_store::IndexingSplitStore;
@@ -50,6 +57,7 @@ pub struct MergePipelineHandles {
     pub merge_uploader: ActorHandle<Uploader>,
     pub merge_sequencer: ActorHandle<Sequencer<Publisher>>,
     pub merge_publisher: ActorHandle<Publisher>,
+    pub parquet_sidecar_writer_opt: Option<ActorHandle<ParquetSidecarWriter>>,
 }
 
 // Messages
@@ -68,6 +76,9 @@ pub struct MergePipeline {
     previous_generations_statistics: MergeStatistics,
     statistics: MergeStatistics,
     handles: Option<MergePipelineHandles>,
+    /// The IO controls shared by the split downloader and the merge executor. Their throughput
+    /// limit is updated as the merge windows open and close.
+    merge_io_controls_opt: Option<IoControls>,
     kill_switch: KillSwitch,
 }
 
@@ -99,6 +110,7 @@ impl MergePipeline {
             params,
             previous_generations_statistics: Default::default(),
             handles: None,
+            merge_io_controls_opt: None,
             kill_switch: KillSwitch::default(),
             statistics: MergeStatistics::default(),
             merge_planner_inbox,
@@ -112,7 +124,7 @@ impl MergePipeline {
 
     fn supervisables(&self) -> Vec<&dyn Supervisable> {
         if let Some(handles) = &self.handles {
-            let supervisables: Vec<&dyn Supervisable> = vec![
+            let mut supervisables: Vec<&dyn Supervisable> = vec![
                 &handles.merge_planner,
                 &handles.merge_split_downloader,
                 &handles.merge_executor,
@@ -121,6 +133,9 @@ impl MergePipeline {
                 &handles.merge_sequencer,
                 &handles.merge_publisher,
             ];
+            if let Some(parquet_sidecar_writer) = &handles.parquet_sidecar_writer_opt {
+                supervisables.push(parquet_sidecar_writer);
+            }
             supervisables
         } else {
             Vec::new()
@@ -155,6 +170,7 @@ impl MergePipeline {
                 healthy_actors=?healthy_actors,
                 failed_or_unhealthy_actors=?failure_or_unhealthy_actors,
                 success_actors=?success_actors,
+                killed_by=?self.kill_switch.killed_by(),
                 "Merge pipeline failure."
             );
             return Health::FailureOrUnhealthy;
@@ -184,11 +200,34 @@ impl MergePipeline {
         self.statistics.generation
     }
 
+    /// Returns the merge throughput limit in bytes per second, which depends on whether one of the
+    /// merge windows is currently open.
+    fn merge_throughput_limit(&self) -> f64 {
+        let max_merge_write_throughput: f64 = self
+            .params
+            .merge_max_io_num_bytes_per_sec
+            .as_ref()
+            .map(|bytes_per_sec| bytes_per_sec.get_bytes() as f64)
+            .unwrap_or(f64::INFINITY);
+        if self.params.merge_windows.is_open() {
+            return max_merge_write_throughput;
+        }
+        self.params
+            .merge_max_io_num_bytes_per_sec_outside_windows
+            .as_ref()
+            .map(|bytes_per_sec| bytes_per_sec.get_bytes() as f64)
+            .unwrap_or(f64::INFINITY)
+            .min(max_merge_write_throughput)
+    }
+
     // TODO: Should return an error saying whether we can retry or not.
     #[instrument(name="", level="info", skip_all, fields(index=%self.params.pipeline_id.index_id, gen=self.generation()))]
     async fn spawn_pipeline(&mut self, ctx: &ActorContext<Self>) -> anyhow::Result<()> {
         self.statistics.num_spawn_attempts += 1;
-        self.kill_switch = ctx.kill_switch().child();
+        self.kill_switch = ctx.kill_switch().named_child(&format!(
+            "merge-pipeline:{}:{}",
+            self.params.pipeline_id.index_id, self.params.pipeline_id.source_id
+        ));
 
         info!(
             index_id=%self.params.pipeline_id.index_id,
@@ -213,12 +252,30 @@ impl MergePipeline {
             .collect::<Vec<_>>();
 
         // Merge publisher
-        let merge_publisher = Publisher::new(
+        let mut merge_publisher = Publisher::new(
             PublisherType::MergePublisher,
             self.params.metastore.clone(),
             Some(self.merge_planner_mailbox.clone()),
             None,
         );
+        let parquet_sidecar_writer_handler_opt =
+            if let Some(parquet_sidecar) = &self.params.parquet_sidecar_opt {
+                let parquet_sidecar_writer = ParquetSidecarWriter::new(
+                    self.params.pipeline_id.index_id.clone(),
+                    parquet_sidecar.fields.clone(),
+                    self.params.storage.clone(),
+                    self.params.split_store.clone(),
+                    self.params.indexing_directory.scratch_directory().clone(),
+                );
+                let (parquet_sidecar_writer_mailbox, parquet_sidecar_writer_handler) = ctx
+                    .spawn_actor()
+                    .set_kill_switch(self.kill_switch.clone())
+                    .spawn(parquet_sidecar_writer);
+                merge_publisher.set_parquet_sidecar_writer_mailbox(parquet_sidecar_writer_mailbox);
+                Some(parquet_sidecar_writer_handler)
+            } else {
+                None
+            };
         let (merge_publisher_mailbox, merge_publisher_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -245,21 +302,21 @@ impl MergePipeline {
 
         // Merge Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let merge_packager = Packager::new("MergePackager", tag_fields, merge_uploader_mailbox);
+        let bloom_filter_fields = self.params.doc_mapper.bloom_filter_named_fields()?;
+        let merge_packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            bloom_filter_fields,
+            self.params.hotcache_opt.clone(),
+            merge_uploader_mailbox,
+        );
         let (merge_packager_mailbox, merge_packager_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
             .spawn(merge_packager);
 
-        let max_merge_write_throughput: f64 = self
-            .params
-            .merge_max_io_num_bytes_per_sec
-            .as_ref()
-            .map(|bytes_per_sec| bytes_per_sec.get_bytes() as f64)
-            .unwrap_or(f64::INFINITY);
-
         let split_downloader_io_controls = IoControls::default()
-            .set_throughput_limit(max_merge_write_throughput)
+            .set_throughput_limit(self.merge_throughput_limit())
             .set_index_and_component(
                 self.params.pipeline_id.index_id.as_str(),
                 "split_downloader_merge",
@@ -270,14 +327,31 @@ impl MergePipeline {
         let merge_executor_io_controls = split_downloader_io_controls
             .clone()
             .set_index_and_component(self.params.pipeline_id.index_id.as_str(), "merger");
+        self.merge_io_controls_opt = Some(merge_executor_io_controls.clone());
 
-        let merge_executor = MergeExecutor::new(
+        let time_slice_opt = self
+            .params
+            .time_slicing_opt
+            .as_ref()
+            .map(TimeSlicingSettings::slice_duration)
+            .transpose()?;
+        let mut merge_executor = MergeExecutor::new(
             self.params.pipeline_id.clone(),
             self.params.metastore.clone(),
             self.params.doc_mapper.clone(),
             merge_executor_io_controls,
             merge_packager_mailbox,
-        );
+        )
+        .set_merge_progress_dir(self.params.indexing_directory.merge_progress_path());
+        if let Some(dedup_settings) = &self.params.dedup_opt {
+            if dedup_settings.enforce_on_merge {
+                merge_executor =
+                    merge_executor.set_dedup_field(dedup_settings.doc_id_field.clone());
+            }
+        }
+        if let Some(slice_duration) = time_slice_opt {
+            merge_executor = merge_executor.set_time_slice(slice_duration);
+        }
         let (merge_executor_mailbox, merge_executor_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -295,12 +369,23 @@ impl MergePipeline {
             .spawn(merge_split_downloader);
 
         // Merge planner
-        let merge_planner = MergePlanner::new(
+        let mut merge_planner = MergePlanner::new(
             self.params.pipeline_id.clone(),
             published_splits,
             self.params.merge_policy.clone(),
             merge_split_downloader_mailbox,
         );
+        // Without a throughput to fall back to, merges are paused outside the merge windows.
+        if self
+            .params
+            .merge_max_io_num_bytes_per_sec_outside_windows
+            .is_none()
+        {
+            merge_planner = merge_planner.set_merge_windows(self.params.merge_windows.clone());
+        }
+        if let Some(slice_duration) = time_slice_opt {
+            merge_planner = merge_planner.set_time_slice(slice_duration);
+        }
         let (_, merge_planner_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -320,6 +405,7 @@ impl MergePipeline {
             merge_uploader: merge_uploader_handler,
             merge_sequencer: merge_sequencer_handler,
             merge_publisher: merge_publisher_handler,
+            parquet_sidecar_writer_opt: parquet_sidecar_writer_handler_opt,
         });
         Ok(())
     }
@@ -335,6 +421,9 @@ impl MergePipeline {
                 handlers.merge_uploader.kill(),
                 handlers.merge_publisher.kill(),
             );
+            if let Some(parquet_sidecar_writer) = handlers.parquet_sidecar_writer_opt {
+                parquet_sidecar_writer.kill().await;
+            }
         }
     }
 }
@@ -356,10 +445,14 @@ impl Handler<Observe> for MergePipeline {
                 .previous_generations_statistics
                 .clone()
                 .add_actor_counters(&*merge_uploader_counters, &*merge_publisher_counters)
+                // The merge executor does not process its mailbox while merging, but its counters
+                // are shared and updated as the merge progresses.
+                .add_merge_executor_counters(&handles.merge_executor.last_observation())
                 .set_generation(self.statistics.generation)
                 .set_num_spawn_attempts(self.statistics.num_spawn_attempts);
         }
-        ctx.schedule_self_msg(Duration::from_secs(1), Observe).await;
+        ctx.schedule_self_msg_coalesced(Duration::from_secs(1), Observe)
+            .await;
         Ok(())
     }
 }
@@ -373,6 +466,9 @@ impl Handler<Supervise> for MergePipeline {
         _: Supervise,
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
+        if let Some(merge_io_controls) = &self.merge_io_controls_opt {
+            merge_io_controls.update_throughput_limit(self.merge_throughput_limit());
+        }
         if self.handles.is_some() {
             match self.healthcheck() {
                 Health::Healthy => {}
@@ -386,7 +482,7 @@ impl Handler<Supervise> for MergePipeline {
                 }
             }
         }
-        ctx.schedule_self_msg(quickwit_actors::HEARTBEAT, Supervise)
+        ctx.schedule_self_msg_coalesced(quickwit_actors::HEARTBEAT, Supervise)
             .await;
         Ok(())
     }
@@ -432,10 +528,22 @@ pub struct MergePipelineParams {
     pub doc_mapper: Arc<dyn DocMapper>,
     pub indexing_directory: IndexingDirectory,
     pub metastore: Arc<dyn Metastore>,
+    pub storage: Arc<dyn Storage>,
     pub split_store: IndexingSplitStore,
     pub merge_policy: Arc<dyn MergePolicy>,
     pub max_concurrent_split_uploads: usize, //< TODO share with the indexing pipeline.
     pub merge_max_io_num_bytes_per_sec: Option<Byte>,
+    pub merge_windows: MaintenanceWindows,
+    pub merge_max_io_num_bytes_per_sec_outside_windows: Option<Byte>,
+    /// Settings of the Parquet sidecar files of the merged splits, if enabled for the index.
+    pub parquet_sidecar_opt: Option<ParquetSidecarSettings>,
+    /// Deduplication settings of the index, if enabled. Merges drop the duplicate documents only
+    /// if `enforce_on_merge` is set.
+    pub dedup_opt: Option<DedupSettings>,
+    /// Hotcache settings of the merged splits.
+    pub hotcache_opt: Option<HotcacheSettings>,
+    /// Time slicing settings of the merged splits, if enabled for the index.
+    pub time_slicing_opt: Option<TimeSlicingSettings>,
 }
 
 #[cfg(test)]
@@ -444,6 +552,7 @@ mod tests {
     use std::time::Duration;
 
     use quickwit_actors::{ActorExitStatus, Universe};
+    use quickwit_config::MaintenanceWindows;
     use quickwit_doc_mapper::default_doc_mapper_for_test;
     use quickwit_metastore::MockMetastore;
     use quickwit_storage::RamStorage;
@@ -474,10 +583,17 @@ mod tests {
             doc_mapper: Arc::new(default_doc_mapper_for_test()),
             indexing_directory: IndexingDirectory::for_test().await,
             metastore: Arc::new(metastore),
+            storage,
             split_store,
             merge_policy: default_merge_policy(),
             max_concurrent_split_uploads: 2,
             merge_max_io_num_bytes_per_sec: None,
+            merge_windows: MaintenanceWindows::default(),
+            merge_max_io_num_bytes_per_sec_outside_windows: None,
+            parquet_sidecar_opt: None,
+            dedup_opt: None,
+            hotcache_opt: None,
+            time_slicing_opt: None,
         };
         let pipeline = MergePipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handler) = universe.spawn_builder().spawn(pipeline);
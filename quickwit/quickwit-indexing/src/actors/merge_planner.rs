@@ -17,35 +17,63 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_config::MaintenanceWindows;
 use quickwit_metastore::SplitMetadata;
 use serde::Serialize;
-use tantivy::Inventory;
+use tantivy::{Inventory, TrackedObject};
 use tracing::info;
 
 use crate::actors::MergeSplitDownloader;
 use crate::merge_policy::MergeOperation;
 use crate::metrics::INDEXER_METRICS;
-use crate::models::{IndexingPipelineId, NewSplits};
+use crate::models::{ForceMerge, IndexingPipelineId, NewSplits};
+use crate::time_slicing::single_time_slice_ord;
 use crate::MergePolicy;
 
+/// Interval at which the merge planner checks whether a merge window has opened.
+const MERGE_WINDOWS_CHECK_INTERVAL: Duration = if cfg!(test) {
+    Duration::from_millis(100)
+} else {
+    Duration::from_secs(60)
+};
+
+/// Period during which the splits handed to a merge operation are not planned again. Remote merge
+/// pipelines are fed snapshots of the published splits that may predate the publish of a merge.
+const MERGED_SPLITS_RETENTION: Duration = Duration::from_secs(3_600);
+
+/// Splits are only merged with the splits of the same partition and, if time slicing is enabled,
+/// of the same time slice. Splits spanning several time slices are merged together.
+type MergeGroupKey = (u64, Option<i64>);
+
 /// The merge planner decides when to start a merge task.
 pub struct MergePlanner {
     pipeline_id: IndexingPipelineId,
     /// A young split is a split that has not reached maturity
     /// yet and can be candidate to merge operations.
-    partitioned_young_splits: HashMap<u64, Vec<SplitMetadata>>,
+    partitioned_young_splits: HashMap<MergeGroupKey, Vec<SplitMetadata>>,
     merge_policy: Arc<dyn MergePolicy>,
     merge_split_downloader_mailbox: Mailbox<MergeSplitDownloader>,
     /// Inventory of ongoing merge operations. If everything goes well,
     /// a merge operation is dropped after the publish of the merged split.
     /// Used for observability.
     ongoing_merge_operations_inventory: Inventory<MergeOperation>,
+    /// Splits handed to a merge operation, with the instant they were handed out. Once the merged
+    /// split is published, they are replaced and must not be planned again. A failed merge
+    /// operation restarts the merge pipeline, and with it the merge planner.
+    merged_split_ids: HashMap<String, Instant>,
+    /// Merge operations are only planned while one of these windows is open.
+    merge_windows: MaintenanceWindows,
+    /// Whether merge operations were withheld because all the merge windows were closed.
+    merges_paused: bool,
+    /// Duration, in seconds, of the time slices by which the splits are grouped.
+    time_slice_secs_opt: Option<i64>,
 }
 
 #[async_trait]
@@ -73,9 +101,12 @@ impl Actor for MergePlanner {
     }
 
     async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
-        let target_partition_ids = self.partitioned_young_splits.keys().cloned().collect_vec();
+        let target_group_keys = self.partitioned_young_splits.keys().cloned().collect_vec();
         self.handle(RefreshMetric, ctx).await?;
-        self.send_merge_ops(ctx, &target_partition_ids).await?;
+        if !self.merge_windows.is_empty() {
+            self.handle(CheckMergeWindows, ctx).await?;
+        }
+        self.send_merge_ops(ctx, &target_group_keys).await?;
         Ok(())
     }
 }
@@ -89,7 +120,12 @@ impl Handler<NewSplits> for MergePlanner {
         message: NewSplits,
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
-        let mut target_partition_ids = Vec::new();
+        let mut target_group_keys = Vec::new();
+        // Remote merge pipelines are periodically fed with all the published splits of their
+        // indexer, including the splits currently being merged or, if the snapshot predates the
+        // publish of the merged split, already merged.
+        self.merged_split_ids
+            .retain(|_, merged_at| merged_at.elapsed() < MERGED_SPLITS_RETENTION);
 
         let partitioned_new_young_splits = message
             .new_splits
@@ -108,31 +144,86 @@ impl Handler<NewSplits> for MergePlanner {
                 }
                 is_immature
             })
-            .group_by(|split| split.partition_id);
+            .group_by(|split| merge_group_key(split, self.time_slice_secs_opt));
 
-        for (partition_id, new_young_splits) in &partitioned_new_young_splits {
-            let young_splits = self
-                .partitioned_young_splits
-                .entry(partition_id)
-                .or_default();
+        for (group_key, new_young_splits) in &partitioned_new_young_splits {
+            let young_splits = self.partitioned_young_splits.entry(group_key).or_default();
             for new_young_split in new_young_splits {
                 // Due to the recycling of the mailbox of the merge planner, it is possible for
                 // a split already in store to be received.
                 let split_already_known = young_splits
                     .iter()
                     .any(|split| split.split_id() == new_young_split.split_id());
-                if split_already_known {
+                if split_already_known
+                    || self
+                        .merged_split_ids
+                        .contains_key(new_young_split.split_id())
+                {
                     continue;
                 }
                 young_splits.push(new_young_split);
             }
-            target_partition_ids.push(partition_id);
+            target_group_keys.push(group_key);
         }
-        self.send_merge_ops(ctx, &target_partition_ids).await?;
+        self.send_merge_ops(ctx, &target_group_keys).await?;
         Ok(())
     }
 }
 
+#[async_trait]
+impl Handler<ForceMerge> for MergePlanner {
+    /// Maps the ID of each merged split to the IDs of its input splits.
+    type Reply = BTreeMap<String, Vec<String>>;
+
+    async fn handle(
+        &mut self,
+        message: ForceMerge,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        let mut merge_operations = Vec::new();
+
+        for young_splits in self.partitioned_young_splits.values_mut() {
+            let (splits_to_merge, remaining_splits): (Vec<SplitMetadata>, Vec<SplitMetadata>) =
+                std::mem::take(young_splits).into_iter().partition(|split| {
+                    match &message.split_ids_opt {
+                        Some(split_ids) => split_ids
+                            .iter()
+                            .any(|split_id| split_id == split.split_id()),
+                        None => true,
+                    }
+                });
+            *young_splits = remaining_splits;
+
+            // A merge operation requires at least two splits.
+            if splits_to_merge.len() < 2 {
+                young_splits.extend(splits_to_merge);
+                continue;
+            }
+            merge_operations.push(MergeOperation::new_merge_operation(splits_to_merge));
+        }
+        let mut merged_split_ids = BTreeMap::new();
+
+        for merge_operation in merge_operations {
+            info!(merge_operation=?merge_operation, "Forced merge operation.");
+            merged_split_ids.insert(
+                merge_operation.merge_split_id.clone(),
+                merge_operation
+                    .splits
+                    .iter()
+                    .map(|split| split.split_id().to_string())
+                    .collect(),
+            );
+            let tracked_merge_operation = self.track_merge_operation(merge_operation);
+            ctx.send_message(
+                &self.merge_split_downloader_mailbox,
+                tracked_merge_operation,
+            )
+            .await?;
+        }
+        Ok(merged_split_ids)
+    }
+}
+
 impl MergePlanner {
     pub fn new(
         pipeline_id: IndexingPipelineId,
@@ -140,13 +231,14 @@ impl MergePlanner {
         merge_policy: Arc<dyn MergePolicy>,
         merge_split_downloader_mailbox: Mailbox<MergeSplitDownloader>,
     ) -> MergePlanner {
-        let mut partitioned_young_splits: HashMap<u64, Vec<SplitMetadata>> = HashMap::new();
+        let mut partitioned_young_splits: HashMap<MergeGroupKey, Vec<SplitMetadata>> =
+            HashMap::new();
         for split in published_splits {
             if !belongs_to_pipeline(&pipeline_id, &split) || merge_policy.is_mature(&split) {
                 continue;
             }
             partitioned_young_splits
-                .entry(split.partition_id)
+                .entry(merge_group_key(&split, None))
                 .or_default()
                 .push(split);
         }
@@ -156,23 +248,69 @@ impl MergePlanner {
             merge_policy,
             merge_split_downloader_mailbox,
             ongoing_merge_operations_inventory: Inventory::default(),
+            merged_split_ids: HashMap::new(),
+            merge_windows: MaintenanceWindows::default(),
+            merges_paused: false,
+            time_slice_secs_opt: None,
         }
     }
 
+    fn track_merge_operation(
+        &mut self,
+        merge_operation: MergeOperation,
+    ) -> TrackedObject<MergeOperation> {
+        let now = Instant::now();
+        for split in &merge_operation.splits {
+            self.merged_split_ids
+                .insert(split.split_id().to_string(), now);
+        }
+        self.ongoing_merge_operations_inventory
+            .track(merge_operation)
+    }
+
+    /// Restricts the planning of merge operations to the given windows. Forced merges are not
+    /// affected.
+    pub fn set_merge_windows(mut self, merge_windows: MaintenanceWindows) -> Self {
+        self.merge_windows = merge_windows;
+        self
+    }
+
+    /// Only merges together the splits of the same time slice of `slice_duration`, or the splits
+    /// spanning several time slices.
+    pub fn set_time_slice(mut self, slice_duration: Duration) -> Self {
+        let time_slice_secs_opt = Some(slice_duration.as_secs().max(1) as i64);
+        let mut partitioned_young_splits: HashMap<MergeGroupKey, Vec<SplitMetadata>> =
+            HashMap::new();
+        for split in self.partitioned_young_splits.into_values().flatten() {
+            partitioned_young_splits
+                .entry(merge_group_key(&split, time_slice_secs_opt))
+                .or_default()
+                .push(split);
+        }
+        self.partitioned_young_splits = partitioned_young_splits;
+        self.time_slice_secs_opt = time_slice_secs_opt;
+        self
+    }
+
     async fn send_merge_ops(
         &mut self,
         ctx: &ActorContext<Self>,
-        target_partition_ids: &[u64],
+        target_group_keys: &[MergeGroupKey],
     ) -> Result<(), ActorExitStatus> {
-        for partition_id in target_partition_ids {
-            if let Some(young_splits) = self.partitioned_young_splits.get_mut(partition_id) {
+        if !self.merge_windows.is_open() {
+            if !self.merges_paused {
+                info!(index_id=%self.pipeline_id.index_id, "Merge windows are closed, pausing merges.");
+                self.merges_paused = true;
+            }
+            return Ok(());
+        }
+        for group_key in target_group_keys {
+            if let Some(young_splits) = self.partitioned_young_splits.get_mut(group_key) {
                 let merge_operations = self.merge_policy.operations(young_splits);
 
                 for merge_operation in merge_operations {
                     info!(merge_operation=?merge_operation, "Planned merge operation.");
-                    let tracked_merge_operations = self
-                        .ongoing_merge_operations_inventory
-                        .track(merge_operation);
+                    let tracked_merge_operations = self.track_merge_operation(merge_operation);
                     ctx.send_message(
                         &self.merge_split_downloader_mailbox,
                         tracked_merge_operations,
@@ -185,6 +323,14 @@ impl MergePlanner {
     }
 }
 
+fn merge_group_key(split: &SplitMetadata, time_slice_secs_opt: Option<i64>) -> MergeGroupKey {
+    let time_slice_ord_opt = time_slice_secs_opt.and_then(|slice_secs| {
+        let time_range = split.time_range.as_ref()?;
+        single_time_slice_ord(time_range, slice_secs)
+    });
+    (split.partition_id, time_slice_ord_opt)
+}
+
 /// We can merge splits from the same (index_id, source_id, node_id).
 fn belongs_to_pipeline(pipeline_id: &IndexingPipelineId, split: &SplitMetadata) -> bool {
     pipeline_id.index_id == split.index_id
@@ -192,6 +338,30 @@ fn belongs_to_pipeline(pipeline_id: &IndexingPipelineId, split: &SplitMetadata)
         && pipeline_id.node_id == split.node_id
 }
 
+#[derive(Debug)]
+struct CheckMergeWindows;
+
+#[async_trait]
+impl Handler<CheckMergeWindows> for MergePlanner {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: CheckMergeWindows,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if self.merges_paused && self.merge_windows.is_open() {
+            info!(index_id=%self.pipeline_id.index_id, "Merge window is open, resuming merges.");
+            self.merges_paused = false;
+            let target_group_keys = self.partitioned_young_splits.keys().cloned().collect_vec();
+            self.send_merge_ops(ctx, &target_group_keys).await?;
+        }
+        ctx.schedule_self_msg(MERGE_WINDOWS_CHECK_INTERVAL, CheckMergeWindows)
+            .await;
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 struct RefreshMetric;
 
@@ -211,7 +381,7 @@ impl Handler<RefreshMetric> for MergePlanner {
                 self.pipeline_id.source_id.as_str(),
             ])
             .set(self.ongoing_merge_operations_inventory.list().len() as i64);
-        ctx.schedule_self_msg(quickwit_actors::HEARTBEAT, RefreshMetric)
+        ctx.schedule_self_msg_coalesced(quickwit_actors::HEARTBEAT, RefreshMetric)
             .await;
         Ok(())
     }
@@ -219,17 +389,20 @@ impl Handler<RefreshMetric> for MergePlanner {
 
 #[cfg(test)]
 mod tests {
+    use std::ops::RangeInclusive;
     use std::sync::Arc;
+    use std::time::Duration;
 
     use itertools::Itertools;
     use quickwit_actors::{create_mailbox, QueueCapacity, Universe};
     use quickwit_config::merge_policy_config::StableLogMergePolicyConfig;
+    use quickwit_config::{MaintenanceWindow, MaintenanceWindows};
     use quickwit_metastore::SplitMetadata;
     use tantivy::TrackedObject;
 
     use crate::actors::MergePlanner;
     use crate::merge_policy::{MergeOperation, StableLogMergePolicy};
-    use crate::models::{IndexingPipelineId, NewSplits};
+    use crate::models::{ForceMerge, IndexingPipelineId, NewSplits};
 
     fn split_metadata_for_test(
         split_id: &str,
@@ -330,6 +503,264 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_merge_planner_ignores_splits_being_merged() -> anyhow::Result<()> {
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            create_mailbox("MergeSplitDownloader".to_string(), QueueCapacity::Unbounded);
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let merge_policy = Arc::new(StableLogMergePolicy::new(
+            StableLogMergePolicyConfig {
+                min_level_num_docs: 10_000,
+                merge_factor: 3,
+                max_merge_factor: 5,
+            },
+            50_000,
+        ));
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            vec![],
+            merge_policy,
+            merge_split_downloader_mailbox,
+        );
+        let universe = Universe::new();
+        let (merge_planner_mailbox, _) = universe.spawn_builder().spawn(merge_planner);
+
+        let new_splits = vec![
+            split_metadata_for_test("1", 1, 1000),
+            split_metadata_for_test("2", 1, 1000),
+            split_metadata_for_test("3", 1, 1000),
+        ];
+        merge_planner_mailbox
+            .send_message(NewSplits {
+                new_splits: new_splits.clone(),
+            })
+            .await?;
+        let operations =
+            merge_split_downloader_inbox.drain_for_test_typed::<TrackedObject<MergeOperation>>();
+        assert_eq!(operations.len(), 1);
+
+        // The splits are published again while they are being merged.
+        merge_planner_mailbox
+            .send_message(NewSplits {
+                new_splits: new_splits.clone(),
+            })
+            .await?;
+        assert!(merge_split_downloader_inbox.drain_for_test().is_empty());
+
+        // The merge operation is over, but the splits are listed in a snapshot taken before the
+        // merged split was published: they are replaced and must not be planned again.
+        drop(operations);
+        merge_planner_mailbox
+            .send_message(NewSplits { new_splits })
+            .await?;
+        assert!(merge_split_downloader_inbox.drain_for_test().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_planner_groups_splits_by_time_slice() -> anyhow::Result<()> {
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            create_mailbox("MergeSplitDownloader".to_string(), QueueCapacity::Unbounded);
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let merge_policy = Arc::new(StableLogMergePolicy::new(
+            StableLogMergePolicyConfig {
+                min_level_num_docs: 10_000,
+                merge_factor: 3,
+                max_merge_factor: 5,
+            },
+            50_000,
+        ));
+        let split_metadata = |split_id: &str, time_range: RangeInclusive<i64>| SplitMetadata {
+            time_range: Some(time_range),
+            ..split_metadata_for_test(split_id, 1, 1000)
+        };
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            vec![
+                split_metadata("1", 0..=10),
+                split_metadata("2", 3_600..=3_610),
+            ],
+            merge_policy,
+            merge_split_downloader_mailbox,
+        )
+        .set_time_slice(Duration::from_secs(3_600));
+        let universe = Universe::new();
+        let (merge_planner_mailbox, _) = universe.spawn_builder().spawn(merge_planner);
+        assert!(merge_split_downloader_inbox.drain_for_test().is_empty());
+
+        merge_planner_mailbox
+            .send_message(NewSplits {
+                new_splits: vec![
+                    split_metadata("3", 20..=30),
+                    split_metadata("4", 3_590..=3_620),
+                    split_metadata("5", 3_620..=3_630),
+                ],
+            })
+            .await?;
+        // Split 4 spans two time slices, so none of them holds enough splits to be merged.
+        let operations =
+            merge_split_downloader_inbox.drain_for_test_typed::<TrackedObject<MergeOperation>>();
+        assert_eq!(operations.len(), 0);
+
+        merge_planner_mailbox
+            .send_message(NewSplits {
+                new_splits: vec![split_metadata("6", 40..=50)],
+            })
+            .await?;
+        let operations =
+            merge_split_downloader_inbox.drain_for_test_typed::<TrackedObject<MergeOperation>>();
+        assert_eq!(operations.len(), 1);
+        let merged_split_ids = operations[0]
+            .splits
+            .iter()
+            .map(|split| split.split_id())
+            .sorted()
+            .collect_vec();
+        assert_eq!(merged_split_ids, vec!["1", "3", "6"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_planner_outside_merge_windows() -> anyhow::Result<()> {
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            create_mailbox("MergeSplitDownloader".to_string(), QueueCapacity::Unbounded);
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let merge_policy = Arc::new(StableLogMergePolicy::new(
+            StableLogMergePolicyConfig {
+                min_level_num_docs: 10_000,
+                merge_factor: 3,
+                max_merge_factor: 5,
+            },
+            50_000,
+        ));
+        // This window opened once in 2000 and never again.
+        let merge_windows = MaintenanceWindows::new(vec![MaintenanceWindow {
+            start: "0 0 0 1 1 * 2000".to_string(),
+            duration: "1 hour".to_string(),
+        }]);
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            vec![],
+            merge_policy,
+            merge_split_downloader_mailbox,
+        )
+        .set_merge_windows(merge_windows);
+        let universe = Universe::new();
+
+        let (merge_planner_mailbox, _) = universe.spawn_builder().spawn(merge_planner);
+
+        let message = NewSplits {
+            new_splits: vec![
+                split_metadata_for_test("1_1", 1, 2500),
+                split_metadata_for_test("2_1", 1, 2000),
+                split_metadata_for_test("3_1", 1, 1500),
+            ],
+        };
+        merge_planner_mailbox.send_message(message).await?;
+        let merge_ops = merge_split_downloader_inbox.drain_for_test();
+        assert_eq!(merge_ops.len(), 0);
+
+        // Forced merges are not restricted by the merge windows.
+        let merged_split_ids = merge_planner_mailbox
+            .ask(ForceMerge {
+                split_ids_opt: None,
+            })
+            .await?;
+        assert_eq!(merged_split_ids.len(), 1);
+        let operations =
+            merge_split_downloader_inbox.drain_for_test_typed::<TrackedObject<MergeOperation>>();
+        assert_eq!(operations.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_planner_force_merge() -> anyhow::Result<()> {
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            create_mailbox("MergeSplitDownloader".to_string(), QueueCapacity::Unbounded);
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let merge_policy = Arc::new(StableLogMergePolicy::new(
+            StableLogMergePolicyConfig {
+                min_level_num_docs: 10_000,
+                merge_factor: 10,
+                max_merge_factor: 12,
+            },
+            50_000,
+        ));
+        let published_splits = vec![
+            split_metadata_for_test("1_1", 1, 100),
+            split_metadata_for_test("2_1", 1, 100),
+            split_metadata_for_test("3_1", 1, 100),
+            split_metadata_for_test("1_2", 2, 100),
+            split_metadata_for_test("2_2", 2, 100),
+            split_metadata_for_test("1_3", 3, 100),
+        ];
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            published_splits,
+            merge_policy,
+            merge_split_downloader_mailbox,
+        );
+        let universe = Universe::new();
+        let (merge_planner_mailbox, _) = universe.spawn_builder().spawn(merge_planner);
+        {
+            // "1_2" is alone in its partition, "unknown" does not exist.
+            let merged_split_ids = merge_planner_mailbox
+                .ask(ForceMerge {
+                    split_ids_opt: Some(vec![
+                        "1_1".to_string(),
+                        "3_1".to_string(),
+                        "1_2".to_string(),
+                        "unknown".to_string(),
+                    ]),
+                })
+                .await?;
+            assert_eq!(merged_split_ids.len(), 1);
+            let input_split_ids = merged_split_ids.values().next().unwrap();
+            assert_eq!(input_split_ids, &["1_1", "3_1"]);
+
+            let operations = merge_split_downloader_inbox
+                .drain_for_test_typed::<TrackedObject<MergeOperation>>();
+            assert_eq!(operations.len(), 1);
+        }
+        {
+            // Merges everything mergeable: "2_1" is now alone in partition 1, and so is "1_3" in
+            // partition 3.
+            let merged_split_ids = merge_planner_mailbox
+                .ask(ForceMerge {
+                    split_ids_opt: None,
+                })
+                .await?;
+            assert_eq!(merged_split_ids.len(), 1);
+            let input_split_ids = merged_split_ids.values().next().unwrap();
+            assert_eq!(input_split_ids, &["1_2", "2_2"]);
+
+            let operations = merge_split_downloader_inbox
+                .drain_for_test_typed::<TrackedObject<MergeOperation>>();
+            assert_eq!(operations.len(), 1);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -17,20 +17,31 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashSet;
 use std::path::Path;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_common::disk_space::available_disk_space;
 use quickwit_common::io::IoControls;
 use quickwit_metastore::SplitMetadata;
 use tantivy::{Directory, TrackedObject};
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn};
 
 use super::MergeExecutor;
 use crate::merge_policy::MergeOperation;
 use crate::models::{MergeScratch, ScratchDirectory};
 use crate::split_store::IndexingSplitStore;
 
+/// Delay before checking again whether there is enough disk space to download the splits of a
+/// deferred merge.
+const DISK_SPACE_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum number of times a merge deferred for lack of disk space is retried. The merge then
+/// fails, releasing its splits.
+const MAX_DISK_SPACE_RETRIES: usize = 30;
+
 #[derive(Clone)]
 pub struct MergeSplitDownloader {
     pub scratch_directory: ScratchDirectory,
@@ -52,20 +63,94 @@ impl Actor for MergeSplitDownloader {
     }
 }
 
+/// Merge operation deferred for lack of disk space.
+#[derive(Debug)]
+struct DeferredMergeOperation {
+    merge_operation: TrackedObject<MergeOperation>,
+    num_retries: usize,
+}
+
 #[async_trait]
 impl Handler<TrackedObject<MergeOperation>> for MergeSplitDownloader {
     type Reply = ();
 
+    async fn handle(
+        &mut self,
+        merge_operation: TrackedObject<MergeOperation>,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.process_merge_operation(merge_operation, 0, ctx).await
+    }
+}
+
+#[async_trait]
+impl Handler<DeferredMergeOperation> for MergeSplitDownloader {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        deferred_merge_operation: DeferredMergeOperation,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.process_merge_operation(
+            deferred_merge_operation.merge_operation,
+            deferred_merge_operation.num_retries,
+            ctx,
+        )
+        .await
+    }
+}
+
+impl MergeSplitDownloader {
     #[instrument(
         name = "merge_split_downloader",
         parent = merge_operation.merge_parent_span.id(),
         skip_all,
     )]
-    async fn handle(
+    async fn process_merge_operation(
         &mut self,
         merge_operation: TrackedObject<MergeOperation>,
+        num_retries: usize,
         ctx: &ActorContext<Self>,
     ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        let mut cached_split_ids = HashSet::new();
+        for split in merge_operation.splits_as_slice() {
+            if self.split_store.is_split_cached(split.split_id()).await {
+                cached_split_ids.insert(split.split_id());
+            }
+        }
+        let required_space =
+            projected_scratch_space(merge_operation.splits_as_slice(), &cached_split_ids);
+        let available_space = available_disk_space(self.scratch_directory.path())
+            .map_err(|error| anyhow::anyhow!(error))?;
+        if available_space < required_space {
+            if num_retries >= MAX_DISK_SPACE_RETRIES {
+                // Dropping the merge operation releases its splits.
+                return Err(anyhow::anyhow!(
+                    "Not enough disk space to download the splits to merge after {} retries ({} \
+                     bytes required, {} bytes available).",
+                    num_retries,
+                    required_space,
+                    available_space
+                )
+                .into());
+            }
+            warn!(
+                required_space = required_space,
+                available_space = available_space,
+                num_retries = num_retries,
+                "Not enough disk space to download the splits to merge, deferring merge."
+            );
+            // Rather than failing midway through the download, the merge is queued until
+            // enough disk space is freed.
+            let deferred_merge_operation = DeferredMergeOperation {
+                merge_operation,
+                num_retries: num_retries + 1,
+            };
+            ctx.schedule_self_msg(DISK_SPACE_RETRY_INTERVAL, deferred_merge_operation)
+                .await;
+            return Ok(());
+        }
         let merge_scratch_directory = self
             .scratch_directory
             .named_temp_child("merge-")
@@ -90,9 +175,7 @@ impl Handler<TrackedObject<MergeOperation>> for MergeSplitDownloader {
         ctx.send_message(&self.executor_mailbox, msg).await?;
         Ok(())
     }
-}
 
-impl MergeSplitDownloader {
     async fn download_splits(
         &self,
         splits: &[SplitMetadata],
@@ -129,6 +212,23 @@ impl MergeSplitDownloader {
     }
 }
 
+/// Returns the scratch space used by a merge: the downloaded splits and the merged split, which is
+/// roughly as large as the splits it is made of. The splits of the local split store are moved
+/// rather than downloaded, and take no extra space.
+fn projected_scratch_space(splits: &[SplitMetadata], cached_split_ids: &HashSet<&str>) -> u64 {
+    let merged_split_size = splits
+        .iter()
+        .map(|split| split.footer_offsets.end)
+        .fold(0u64, |total, split_size| total.saturating_add(split_size));
+    splits
+        .iter()
+        .filter(|split| !cached_split_ids.contains(split.split_id()))
+        .map(|split| split.footer_offsets.end)
+        .fold(merged_split_size, |total, split_size| {
+            total.saturating_add(split_size)
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use std::iter;
@@ -204,4 +304,105 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_projected_scratch_space() {
+        assert_eq!(projected_scratch_space(&[], &HashSet::new()), 0);
+        let splits = vec![
+            SplitMetadata {
+                split_id: "split-1".to_string(),
+                footer_offsets: 90..100,
+                ..Default::default()
+            },
+            SplitMetadata {
+                split_id: "split-2".to_string(),
+                footer_offsets: 150..200,
+                ..Default::default()
+            },
+        ];
+        assert_eq!(projected_scratch_space(&splits, &HashSet::new()), 600);
+        let cached_split_ids = HashSet::from_iter(["split-2"]);
+        assert_eq!(projected_scratch_space(&splits, &cached_split_ids), 400);
+        let huge_split = SplitMetadata {
+            footer_offsets: 0..u64::MAX,
+            ..Default::default()
+        };
+        assert_eq!(
+            projected_scratch_space(&[huge_split], &HashSet::new()),
+            u64::MAX
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_split_downloader_defers_merge_on_low_disk_space() -> anyhow::Result<()> {
+        let scratch_directory = ScratchDirectory::for_test()?;
+        let split_store = IndexingSplitStore::create_without_local_store(Arc::new(
+            RamStorageBuilder::default().build(),
+        ));
+        let universe = Universe::new();
+        let (merge_executor_mailbox, merge_executor_inbox) = create_test_mailbox();
+        let merge_split_downloader = MergeSplitDownloader {
+            scratch_directory,
+            split_store,
+            executor_mailbox: merge_executor_mailbox,
+            io_controls: IoControls::default(),
+        };
+        let (merge_split_downloader_mailbox, merge_split_downloader_handler) =
+            universe.spawn_builder().spawn(merge_split_downloader);
+        let splits_to_merge = vec![SplitMetadata {
+            split_id: new_split_id(),
+            footer_offsets: 0..u64::MAX / 2,
+            ..Default::default()
+        }];
+        let inventory = Inventory::new();
+        let merge_operation = inventory.track(MergeOperation::new_merge_operation(splits_to_merge));
+        merge_split_downloader_mailbox
+            .send_message(merge_operation)
+            .await?;
+        merge_split_downloader_handler
+            .process_pending_and_observe()
+            .await;
+        assert!(merge_executor_inbox.drain_for_test().is_empty());
+        // The merge operation is still tracked, waiting for disk space.
+        assert_eq!(inventory.list().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_merge_split_downloader_fails_after_max_disk_space_retries() -> anyhow::Result<()>
+    {
+        let scratch_directory = ScratchDirectory::for_test()?;
+        let split_store = IndexingSplitStore::create_without_local_store(Arc::new(
+            RamStorageBuilder::default().build(),
+        ));
+        let universe = Universe::new();
+        let (merge_executor_mailbox, merge_executor_inbox) = create_test_mailbox();
+        let merge_split_downloader = MergeSplitDownloader {
+            scratch_directory,
+            split_store,
+            executor_mailbox: merge_executor_mailbox,
+            io_controls: IoControls::default(),
+        };
+        let (merge_split_downloader_mailbox, merge_split_downloader_handler) =
+            universe.spawn_builder().spawn(merge_split_downloader);
+        let splits_to_merge = vec![SplitMetadata {
+            split_id: new_split_id(),
+            footer_offsets: 0..u64::MAX / 2,
+            ..Default::default()
+        }];
+        let inventory = Inventory::new();
+        let merge_operation = inventory.track(MergeOperation::new_merge_operation(splits_to_merge));
+        merge_split_downloader_mailbox
+            .send_message(DeferredMergeOperation {
+                merge_operation,
+                num_retries: MAX_DISK_SPACE_RETRIES,
+            })
+            .await?;
+        let (exit_status, _) = merge_split_downloader_handler.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Failure(_)));
+        assert!(merge_executor_inbox.drain_for_test().is_empty());
+        // The merge operation is released.
+        assert!(inventory.list().is_empty());
+        Ok(())
+    }
 }
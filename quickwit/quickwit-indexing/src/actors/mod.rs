@@ -26,6 +26,7 @@ mod indexer;
 mod indexing_service;
 mod ingest_api_garbage_collector;
 mod packager;
+mod parquet_sidecar_writer;
 mod publisher;
 mod sequencer;
 mod uploader;
@@ -39,15 +40,21 @@ mod merge_executor;
 mod merge_planner;
 mod merge_split_downloader;
 
-pub use self::doc_processor::{DocProcessor, DocProcessorCounters};
+pub use self::doc_processor::{
+    prepare_document, DocProcessor, DocProcessorCounters, PrepareDocumentError,
+};
 pub use self::index_serializer::IndexSerializer;
 pub use self::indexer::{Indexer, IndexerCounters};
+pub(crate) use self::ingest_api_garbage_collector::apply_queue_retention;
 pub use self::ingest_api_garbage_collector::{
     IngestApiGarbageCollector, IngestApiGarbageCollectorCounters,
 };
-pub use self::merge_executor::{combine_partition_ids, merge_split_attrs, MergeExecutor};
+pub use self::merge_executor::{
+    combine_partition_ids, merge_split_attrs, MergeExecutor, MergeExecutorCounters,
+};
 pub use self::merge_planner::MergePlanner;
 pub use self::merge_split_downloader::MergeSplitDownloader;
 pub use self::packager::Packager;
+pub use self::parquet_sidecar_writer::{ParquetSidecarWriter, ParquetSidecarWriterCounters};
 pub use self::publisher::{Publisher, PublisherCounters, PublisherType};
 pub use self::uploader::{SplitsUpdateMailbox, Uploader, UploaderCounters, UploaderType};
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -28,11 +28,16 @@ use fail::fail_point;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
-use quickwit_directories::write_hotcache;
+use quickwit_config::HotcacheSettings;
+use quickwit_directories::{write_hotcache, HotcacheOptions};
+use quickwit_doc_mapper::bloom_filter::{
+    BloomFilter, SplitBloomFilters, BLOOM_FILTER_FALSE_POSITIVE_RATE,
+};
 use quickwit_doc_mapper::tag_pruning::append_to_tag_set;
 use quickwit_doc_mapper::NamedField;
-use tantivy::schema::FieldType;
-use tantivy::{InvertedIndexReader, ReloadPolicy, SegmentMeta};
+use quickwit_metastore::SplitFieldStats;
+use tantivy::schema::{Field, FieldType};
+use tantivy::{InvertedIndexReader, ReloadPolicy, Searcher, SegmentMeta};
 use tokio::runtime::Handle;
 use tracing::{debug, info, instrument, warn};
 
@@ -65,18 +70,25 @@ pub struct Packager {
     uploader_mailbox: Mailbox<Uploader>,
     /// List of tag fields ([`Vec<NamedField>`]) defined in the index config.
     tag_fields: Vec<NamedField>,
+    /// List of bloom filter fields ([`Vec<NamedField>`]) defined in the index config.
+    bloom_filter_fields: Vec<NamedField>,
+    hotcache_options: HotcacheOptions,
 }
 
 impl Packager {
     pub fn new(
         actor_name: &'static str,
         tag_fields: Vec<NamedField>,
+        bloom_filter_fields: Vec<NamedField>,
+        hotcache_settings_opt: Option<HotcacheSettings>,
         uploader_mailbox: Mailbox<Uploader>,
     ) -> Packager {
         Packager {
             actor_name,
             uploader_mailbox,
             tag_fields,
+            bloom_filter_fields,
+            hotcache_options: hotcache_options(hotcache_settings_opt),
         }
     }
 
@@ -87,8 +99,14 @@ impl Packager {
     ) -> anyhow::Result<PackagedSplit> {
         let segment_metas = split.index.searchable_segment_metas()?;
         assert_eq!(segment_metas.len(), 1);
-        let packaged_split =
-            create_packaged_split(&segment_metas[..], split, &self.tag_fields, ctx)?;
+        let packaged_split = create_packaged_split(
+            &segment_metas[..],
+            split,
+            &self.tag_fields,
+            &self.bloom_filter_fields,
+            &self.hotcache_options,
+            ctx,
+        )?;
         Ok(packaged_split)
     }
 }
@@ -187,9 +205,30 @@ fn list_split_files(
     index_files
 }
 
-fn build_hotcache<W: io::Write>(split_path: &Path, out: &mut W) -> anyhow::Result<()> {
+fn hotcache_options(hotcache_settings_opt: Option<HotcacheSettings>) -> HotcacheOptions {
+    let hotcache_settings = if let Some(hotcache_settings) = hotcache_settings_opt {
+        hotcache_settings
+    } else {
+        return HotcacheOptions::default();
+    };
+    let field_names_opt = if hotcache_settings.fields.is_empty() {
+        None
+    } else {
+        Some(hotcache_settings.fields)
+    };
+    HotcacheOptions {
+        field_names_opt,
+        max_slice_num_bytes: hotcache_settings.max_slice_size.get_bytes() as usize,
+    }
+}
+
+fn build_hotcache<W: io::Write>(
+    split_path: &Path,
+    hotcache_options: &HotcacheOptions,
+    out: &mut W,
+) -> anyhow::Result<()> {
     let mmap_directory = tantivy::directory::MmapDirectory::open(split_path)?;
-    write_hotcache(mmap_directory, out)?;
+    write_hotcache(mmap_directory, hotcache_options, out)?;
     Ok(())
 }
 
@@ -203,21 +242,19 @@ fn build_hotcache<W: io::Write>(split_path: &Path, out: &mut W) -> anyhow::Resul
 ///
 /// Returns None may hurt split pruning and affects performance,
 /// but it does not affect Quickwit's result validity.
+/// Extracts the terms of a tag field, or returns `None` if the field has more than `max_terms`
+/// unique terms.
 fn try_extract_terms(
     named_field: &NamedField,
     inv_indexes: &[Arc<InvertedIndexReader>],
     max_terms: usize,
-) -> anyhow::Result<Vec<String>> {
+) -> anyhow::Result<Option<Vec<String>>> {
     let num_terms = inv_indexes
         .iter()
         .map(|inv_index| inv_index.terms().num_terms())
         .sum::<usize>();
     if num_terms > max_terms {
-        bail!(
-            "Number of unique terms for tag field {} > {}.",
-            named_field.name,
-            max_terms
-        );
+        return Ok(None);
     }
     let mut terms = Vec::with_capacity(num_terms);
     for inv_index in inv_indexes {
@@ -245,13 +282,15 @@ fn try_extract_terms(
             terms.push(term);
         }
     }
-    Ok(terms)
+    Ok(Some(terms))
 }
 
 fn create_packaged_split(
     segment_metas: &[SegmentMeta],
     split: IndexedSplit,
     tag_fields: &[NamedField],
+    bloom_filter_fields: &[NamedField],
+    hotcache_options: &HotcacheOptions,
     ctx: &ActorContext<Packager>,
 ) -> anyhow::Result<PackagedSplit> {
     info!(split_id = split.split_id(), "create-packaged-split");
@@ -275,9 +314,18 @@ fn create_packaged_split(
             .collect::<Result<Vec<_>, _>>()?;
 
         match try_extract_terms(named_field, &inverted_indexes, MAX_VALUES_PER_TAG_FIELD) {
-            Ok(terms) => {
+            Ok(Some(terms)) => {
                 append_to_tag_set(&named_field.name, &terms, &mut tags);
             }
+            // High-cardinality fields are expected, especially when all the keyword fields are
+            // tagged, so this is not worth a warning.
+            Ok(None) => {
+                debug!(
+                    field = %named_field.name,
+                    max_values = MAX_VALUES_PER_TAG_FIELD,
+                    "Too many unique values for tag field, no field values will be registered in the split metadata."
+                );
+            }
             Err(tag_extraction_error) => {
                 warn!(err=?tag_extraction_error,  "No field values will be registered in the split metadata.");
             }
@@ -286,9 +334,47 @@ fn create_packaged_split(
 
     ctx.record_progress();
 
+    let mut bloom_filters = SplitBloomFilters::default();
+    for named_field in bloom_filter_fields {
+        debug!(split_id = split.split_id(), field = %named_field.name, "build-bloom-filter");
+        let inverted_indexes = index_reader
+            .searcher()
+            .segment_readers()
+            .iter()
+            .map(|segment| segment.inverted_index(named_field.field))
+            .collect::<Result<Vec<_>, _>>()?;
+        match build_bloom_filter(&inverted_indexes) {
+            Ok(bloom_filter) => {
+                bloom_filters.insert(named_field.name.clone(), bloom_filter);
+            }
+            // Without a bloom filter, the split is simply never pruned on this field.
+            Err(bloom_filter_error) => {
+                warn!(err=?bloom_filter_error, field = %named_field.name, "Failed to build bloom filter.");
+            }
+        }
+        ctx.record_progress();
+    }
+    let bloom_filters_opt = if bloom_filters.is_empty() {
+        None
+    } else {
+        Some(bloom_filters)
+    };
+
+    debug!(split_id = split.split_id(), "compute-field-stats");
+    let field_stats = compute_field_stats(&index_reader.searcher()).unwrap_or_else(|error| {
+        // Field statistics are informative only, they are not worth failing the split for.
+        warn!(err=?error, "Failed to compute field statistics.");
+        BTreeMap::new()
+    });
+    ctx.record_progress();
+
     debug!(split_id = split.split_id(), "build-hotcache");
     let mut hotcache_bytes = Vec::new();
-    build_hotcache(split.split_scratch_directory.path(), &mut hotcache_bytes)?;
+    build_hotcache(
+        split.split_scratch_directory.path(),
+        hotcache_options,
+        &mut hotcache_bytes,
+    )?;
     ctx.record_progress();
 
     let packaged_split = PackagedSplit {
@@ -297,10 +383,72 @@ fn create_packaged_split(
         tags,
         split_files,
         hotcache_bytes,
+        bloom_filters_opt,
+        field_stats,
     };
     Ok(packaged_split)
 }
 
+/// Computes the number of bytes taken by each field in the split, its number of terms, and whether
+/// it is a fast field. Fields taking no space in the split are omitted.
+fn compute_field_stats(searcher: &Searcher) -> anyhow::Result<BTreeMap<String, SplitFieldStats>> {
+    let space_usage = searcher.space_usage()?;
+    let mut field_stats: HashMap<Field, SplitFieldStats> = HashMap::new();
+    for segment_space_usage in space_usage.segments() {
+        for (field, field_usage) in segment_space_usage.termdict().fields() {
+            field_stats.entry(*field).or_default().termdict_num_bytes += field_usage.total() as u64;
+        }
+        for (field, field_usage) in segment_space_usage.postings().fields() {
+            field_stats.entry(*field).or_default().postings_num_bytes += field_usage.total() as u64;
+        }
+        for (field, field_usage) in segment_space_usage.positions().fields() {
+            field_stats.entry(*field).or_default().positions_num_bytes +=
+                field_usage.total() as u64;
+        }
+        for (field, field_usage) in segment_space_usage.fast_fields().fields() {
+            field_stats.entry(*field).or_default().fast_field_num_bytes +=
+                field_usage.total() as u64;
+        }
+        for (field, field_usage) in segment_space_usage.fieldnorms().fields() {
+            field_stats.entry(*field).or_default().fieldnorms_num_bytes +=
+                field_usage.total() as u64;
+        }
+    }
+    let schema = searcher.schema();
+    let mut named_field_stats = BTreeMap::new();
+    for (field, mut stats) in field_stats {
+        if stats.total_num_bytes() == 0 {
+            continue;
+        }
+        let field_entry = schema.get_field_entry(field);
+        if field_entry.is_indexed() {
+            for segment_reader in searcher.segment_readers() {
+                stats.num_terms += segment_reader.inverted_index(field)?.terms().num_terms() as u64;
+            }
+        }
+        stats.is_fast = field_entry.is_fast();
+        named_field_stats.insert(field_entry.name().to_string(), stats);
+    }
+    Ok(named_field_stats)
+}
+
+/// Builds a bloom filter over the terms of a text field.
+fn build_bloom_filter(inv_indexes: &[Arc<InvertedIndexReader>]) -> anyhow::Result<BloomFilter> {
+    let num_terms = inv_indexes
+        .iter()
+        .map(|inv_index| inv_index.terms().num_terms())
+        .sum::<usize>();
+    let mut bloom_filter =
+        BloomFilter::with_num_values(num_terms, BLOOM_FILTER_FALSE_POSITIVE_RATE);
+    for inv_index in inv_indexes {
+        let mut terms_streamer = inv_index.terms().stream()?;
+        while let Some((term_data, _)) = terms_streamer.next() {
+            bloom_filter.insert(std::str::from_utf8(term_data)?);
+        }
+    }
+    Ok(bloom_filter)
+}
+
 /// Reads u64 from stored term data.
 fn u64_from_term_data(data: &[u8]) -> anyhow::Result<u64> {
     let u64_bytes: [u8; 8] = data[0..8]
@@ -425,7 +573,14 @@ mod tests {
                 "tag_str", "tag_many", "tag_u64", "tag_i64", "tag_f64", "tag_bool",
             ],
         );
-        let packager = Packager::new("TestPackager", tag_fields, mailbox);
+        let bloom_filter_fields = get_tag_fields(indexed_split.index.schema(), &["tag_many"]);
+        let packager = Packager::new(
+            "TestPackager",
+            tag_fields,
+            bloom_filter_fields,
+            None,
+            mailbox,
+        );
         let (packager_mailbox, packager_handle) = universe.spawn_builder().spawn(packager);
         packager_mailbox
             .send_message(IndexedSplitBatch {
@@ -461,7 +616,25 @@ mod tests {
                 "tag_u64:42"
             ]
         );
+        let bloom_filter = split
+            .bloom_filters_opt
+            .as_ref()
+            .unwrap()
+            .get("tag_many")
+            .unwrap();
+        assert!(bloom_filter.contains("many-1"));
+        assert!(bloom_filter.contains("many-9"));
         assert_eq!(split.split_attrs.time_range, Some(1628203589..=1628203640));
+        let text_stats = &split.field_stats["text"];
+        assert!(text_stats.postings_num_bytes > 0);
+        assert!(text_stats.positions_num_bytes > 0);
+        assert_eq!(text_stats.num_terms, 4);
+        assert!(!text_stats.is_fast);
+        assert_eq!(split.field_stats["tag_many"].num_terms, 9);
+        let timestamp_stats = &split.field_stats["timestamp"];
+        assert!(timestamp_stats.fast_field_num_bytes > 0);
+        assert_eq!(timestamp_stats.num_terms, 0);
+        assert!(timestamp_stats.is_fast);
         Ok(())
     }
 }
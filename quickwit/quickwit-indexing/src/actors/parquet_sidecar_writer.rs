@@ -0,0 +1,509 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use parquet::basic::{ConvertedType, Repetition, Type as PhysicalType};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::{Type as ParquetType, TypePtr};
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, QueueCapacity};
+use quickwit_common::runtimes::RuntimeType;
+use quickwit_common::{parquet_sidecar_file, split_file};
+use quickwit_metastore::SplitMetadata;
+use quickwit_storage::Storage;
+use serde::Serialize;
+use tantivy::schema::{Field, Schema, Type, Value};
+use tantivy::{Directory, DocAddress, Document, Index, IndexReader, ReloadPolicy};
+use tokio::runtime::Handle;
+use tracing::{info, warn};
+
+use crate::metrics::INDEXER_METRICS;
+use crate::models::{PublishedSplits, ScratchDirectory};
+use crate::split_store::{get_tantivy_directory_from_split_bundle, IndexingSplitStore};
+
+/// Maximum number of documents buffered in memory and written as one row group of a sidecar file.
+const ROW_GROUP_MAX_NUM_DOCS: usize = 100_000;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ParquetSidecarWriterCounters {
+    pub num_written_sidecars: u64,
+    pub num_deleted_sidecars: u64,
+    pub num_failed_sidecars: u64,
+}
+
+/// Writes a Parquet copy of some fields of the documents of each published split in the `parquet`
+/// directory of the index, and deletes the copies of the splits replaced by merges.
+///
+/// Sidecar files are derived from the splits: failing to write or delete one is logged and
+/// counted, but never interrupts the pipeline.
+#[derive(Clone)]
+pub struct ParquetSidecarWriter {
+    index_id: String,
+    field_names: Vec<String>,
+    storage: Arc<dyn Storage>,
+    split_store: IndexingSplitStore,
+    scratch_directory: ScratchDirectory,
+    counters: ParquetSidecarWriterCounters,
+}
+
+impl ParquetSidecarWriter {
+    pub fn new(
+        index_id: String,
+        field_names: Vec<String>,
+        storage: Arc<dyn Storage>,
+        split_store: IndexingSplitStore,
+        scratch_directory: ScratchDirectory,
+    ) -> Self {
+        Self {
+            index_id,
+            field_names,
+            storage,
+            split_store,
+            scratch_directory,
+            counters: ParquetSidecarWriterCounters::default(),
+        }
+    }
+
+    /// Writes the sidecar file of a split and returns its size in bytes. The split is read from
+    /// the local split store, where the uploader leaves the young splits, and only downloaded if
+    /// it is missing.
+    async fn write_sidecar(&self, split: &SplitMetadata) -> anyhow::Result<usize> {
+        let split_directory = self
+            .scratch_directory
+            .named_temp_child("parquet-sidecar-")?;
+        let cached_split_res = self
+            .split_store
+            .open_cached_split(split.split_id(), split_directory.path())
+            .await;
+        let tantivy_dir = match cached_split_res {
+            Ok(Some(tantivy_dir)) => tantivy_dir,
+            Ok(None) => self.download_split(split, split_directory.path()).await?,
+            Err(error) => {
+                warn!(index_id=%self.index_id, split_id=%split.split_id(), error=?error, "Failed to open cached split, downloading it.");
+                self.download_split(split, split_directory.path()).await?
+            }
+        };
+        let sidecar_bytes = build_sidecar(tantivy_dir, &self.field_names)?;
+        let num_bytes = sidecar_bytes.len();
+        let sidecar_file_name = parquet_sidecar_file(split.split_id());
+        self.storage
+            .put(Path::new(&sidecar_file_name), Box::new(sidecar_bytes))
+            .await?;
+        Ok(num_bytes)
+    }
+
+    async fn download_split(
+        &self,
+        split: &SplitMetadata,
+        download_dir_path: &Path,
+    ) -> anyhow::Result<Box<dyn Directory>> {
+        let split_file_name = split_file(split.split_id());
+        let split_path = download_dir_path.join(&split_file_name);
+        self.storage
+            .copy_to_file(Path::new(&split_file_name), &split_path)
+            .await?;
+        let tantivy_dir = get_tantivy_directory_from_split_bundle(&split_path)?;
+        Ok(tantivy_dir)
+    }
+
+    fn inc_sidecars_metric(&self, status: &str) {
+        INDEXER_METRICS
+            .parquet_sidecars_total
+            .with_label_values(&[self.index_id.as_str(), status])
+            .inc();
+    }
+}
+
+#[async_trait]
+impl Actor for ParquetSidecarWriter {
+    type ObservableState = ParquetSidecarWriterCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn runtime_handle(&self) -> Handle {
+        RuntimeType::Blocking.get_runtime_handle()
+    }
+
+    // The publisher must never wait for the sidecar files to be written.
+    fn queue_capacity(&self) -> QueueCapacity {
+        QueueCapacity::Unbounded
+    }
+
+    fn name(&self) -> String {
+        "ParquetSidecarWriter".to_string()
+    }
+}
+
+#[async_trait]
+impl Handler<PublishedSplits> for ParquetSidecarWriter {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        published_splits: PublishedSplits,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        // The sidecar files of the new splits are written before the ones of the replaced splits
+        // are deleted, so consumers may briefly see the documents of a merge twice but never miss
+        // them.
+        for split in &published_splits.new_splits {
+            let write_res = {
+                let _protect_guard = ctx.protect_zone();
+                self.write_sidecar(split).await
+            };
+            match write_res {
+                Ok(num_bytes) => {
+                    info!(index_id=%self.index_id, split_id=%split.split_id(), num_bytes=num_bytes, "write-parquet-sidecar");
+                    self.counters.num_written_sidecars += 1;
+                    self.inc_sidecars_metric("written");
+                }
+                Err(error) => {
+                    warn!(index_id=%self.index_id, split_id=%split.split_id(), error=?error, "Failed to write Parquet sidecar file.");
+                    self.counters.num_failed_sidecars += 1;
+                    self.inc_sidecars_metric("failed");
+                }
+            }
+        }
+        for split_id in &published_splits.replaced_split_ids {
+            let sidecar_file_name = parquet_sidecar_file(split_id);
+            let delete_res = ctx
+                .protect_future(self.storage.delete(Path::new(&sidecar_file_name)))
+                .await;
+            match delete_res {
+                Ok(()) => {
+                    self.counters.num_deleted_sidecars += 1;
+                    self.inc_sidecars_metric("deleted");
+                }
+                Err(error) => {
+                    warn!(index_id=%self.index_id, split_id=%split_id, error=?error, "Failed to delete Parquet sidecar file.");
+                    self.counters.num_failed_sidecars += 1;
+                    self.inc_sidecars_metric("failed");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the documents of a split and returns the content of its sidecar file: one optional
+/// column per field, holding the first value of the field in each document.
+fn build_sidecar(
+    tantivy_dir: Box<dyn Directory>,
+    field_names: &[String],
+) -> anyhow::Result<Vec<u8>> {
+    let index = Index::open(tantivy_dir)?;
+    let schema = index.schema();
+    let mut columns: Vec<SidecarColumn> = field_names
+        .iter()
+        .map(|field_name| SidecarColumn::new(&schema, field_name))
+        .collect::<anyhow::Result<_>>()?;
+    let mut parquet_fields = columns
+        .iter()
+        .map(SidecarColumn::parquet_field)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let parquet_schema = ParquetType::group_type_builder("sidecar")
+        .with_fields(&mut parquet_fields)
+        .build()?;
+    let properties = WriterProperties::builder().build();
+    let mut sidecar_bytes = Vec::new();
+    let mut writer = SerializedFileWriter::new(
+        &mut sidecar_bytes,
+        Arc::new(parquet_schema),
+        Arc::new(properties),
+    )?;
+    let index_reader: IndexReader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+    let searcher = index_reader.searcher();
+    let mut num_buffered_docs = 0;
+    for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+        for doc_id in segment_reader.doc_ids_alive() {
+            let doc = searcher.doc(DocAddress::new(segment_ord as u32, doc_id))?;
+            for column in &mut columns {
+                column.push(&doc);
+            }
+            num_buffered_docs += 1;
+            if num_buffered_docs == ROW_GROUP_MAX_NUM_DOCS {
+                write_row_group(&mut writer, &mut columns)?;
+                num_buffered_docs = 0;
+            }
+        }
+    }
+    if num_buffered_docs > 0 {
+        write_row_group(&mut writer, &mut columns)?;
+    }
+    writer.close()?;
+    Ok(sidecar_bytes)
+}
+
+fn write_row_group<W: Write + Send>(
+    writer: &mut SerializedFileWriter<W>,
+    columns: &mut [SidecarColumn],
+) -> anyhow::Result<()> {
+    let mut row_group_writer = writer.next_row_group()?;
+    for column in columns.iter_mut() {
+        let mut column_writer = row_group_writer
+            .next_column()?
+            .context("The Parquet schema has fewer columns than the sidecar.")?;
+        let def_levels = Some(&column.def_levels[..]);
+        match &column.values {
+            SidecarColumnValues::ByteArray(values) => column_writer
+                .typed::<ByteArrayType>()
+                .write_batch(values, def_levels, None)?,
+            SidecarColumnValues::Int64(values) => column_writer
+                .typed::<Int64Type>()
+                .write_batch(values, def_levels, None)?,
+            SidecarColumnValues::Double(values) => column_writer
+                .typed::<DoubleType>()
+                .write_batch(values, def_levels, None)?,
+            SidecarColumnValues::Bool(values) => column_writer
+                .typed::<BoolType>()
+                .write_batch(values, def_levels, None)?,
+        };
+        column_writer.close()?;
+        column.clear();
+    }
+    row_group_writer.close()?;
+    Ok(())
+}
+
+enum SidecarColumnValues {
+    ByteArray(Vec<ByteArray>),
+    Int64(Vec<i64>),
+    Double(Vec<f64>),
+    Bool(Vec<bool>),
+}
+
+/// Values of a field buffered before being written to a row group.
+struct SidecarColumn {
+    field: Field,
+    field_name: String,
+    value_type: Type,
+    /// Definition level of each buffered document: `1` if the document has a value, `0` if the
+    /// value is null. Only non-null values are buffered in `values`.
+    def_levels: Vec<i16>,
+    values: SidecarColumnValues,
+}
+
+impl SidecarColumn {
+    fn new(schema: &Schema, field_name: &str) -> anyhow::Result<Self> {
+        let field = schema
+            .get_field(field_name)
+            .with_context(|| format!("Field `{}` does not exist in the split.", field_name))?;
+        let value_type = schema.get_field_entry(field).field_type().value_type();
+        let values = match value_type {
+            Type::Str => SidecarColumnValues::ByteArray(Vec::new()),
+            Type::I64 | Type::U64 | Type::Date => SidecarColumnValues::Int64(Vec::new()),
+            Type::F64 => SidecarColumnValues::Double(Vec::new()),
+            Type::Bool => SidecarColumnValues::Bool(Vec::new()),
+            value_type => bail!(
+                "Field `{}` of type `{:?}` cannot be written to a Parquet sidecar file.",
+                field_name,
+                value_type
+            ),
+        };
+        Ok(Self {
+            field,
+            field_name: field_name.to_string(),
+            value_type,
+            def_levels: Vec::new(),
+            values,
+        })
+    }
+
+    fn parquet_field(&self) -> anyhow::Result<TypePtr> {
+        let (physical_type, converted_type) = match self.value_type {
+            Type::Str => (PhysicalType::BYTE_ARRAY, ConvertedType::UTF8),
+            // Parquet stores unsigned integers in signed physical columns.
+            Type::U64 => (PhysicalType::INT64, ConvertedType::UINT_64),
+            Type::Date => (PhysicalType::INT64, ConvertedType::TIMESTAMP_MICROS),
+            Type::F64 => (PhysicalType::DOUBLE, ConvertedType::NONE),
+            Type::Bool => (PhysicalType::BOOLEAN, ConvertedType::NONE),
+            _ => (PhysicalType::INT64, ConvertedType::NONE),
+        };
+        let parquet_field = ParquetType::primitive_type_builder(&self.field_name, physical_type)
+            .with_repetition(Repetition::OPTIONAL)
+            .with_converted_type(converted_type)
+            .build()?;
+        Ok(Arc::new(parquet_field))
+    }
+
+    /// Buffers the first value of the field in the document. Documents without a value for the
+    /// field get a null.
+    fn push(&mut self, doc: &Document) {
+        let has_value = match (&mut self.values, doc.get_first(self.field)) {
+            (SidecarColumnValues::ByteArray(values), Some(Value::Str(text))) => {
+                values.push(ByteArray::from(text.as_str()));
+                true
+            }
+            (SidecarColumnValues::Int64(values), Some(Value::I64(value))) => {
+                values.push(*value);
+                true
+            }
+            (SidecarColumnValues::Int64(values), Some(Value::U64(value))) => {
+                values.push(*value as i64);
+                true
+            }
+            (SidecarColumnValues::Int64(values), Some(Value::Date(date))) => {
+                values.push(date.into_timestamp_micros());
+                true
+            }
+            (SidecarColumnValues::Double(values), Some(Value::F64(value))) => {
+                values.push(*value);
+                true
+            }
+            (SidecarColumnValues::Bool(values), Some(Value::Bool(value))) => {
+                values.push(*value);
+                true
+            }
+            _ => false,
+        };
+        self.def_levels.push(if has_value { 1 } else { 0 });
+    }
+
+    fn clear(&mut self) {
+        self.def_levels.clear();
+        match &mut self.values {
+            SidecarColumnValues::ByteArray(values) => values.clear(),
+            SidecarColumnValues::Int64(values) => values.clear(),
+            SidecarColumnValues::Double(values) => values.clear(),
+            SidecarColumnValues::Bool(values) => values.clear(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Seek, Write};
+
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field as ParquetField;
+    use quickwit_actors::Universe;
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+    use crate::TestSandbox;
+
+    #[tokio::test]
+    async fn test_parquet_sidecar_writer_writes_sidecars() -> anyhow::Result<()> {
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: response_time
+                type: u64
+        "#;
+        let indexing_settings_yaml = r#"
+            parquet_sidecar:
+              fields: [body, response_time]
+        "#;
+        let test_sandbox = TestSandbox::create(
+            "test-parquet-sidecar",
+            doc_mapping_yaml,
+            indexing_settings_yaml,
+            &["body"],
+            None,
+        )
+        .await?;
+        let docs = vec![
+            serde_json::json!({"body": "info", "response_time": 12}),
+            serde_json::json!({"body": "error"}),
+        ];
+        test_sandbox.add_documents(docs).await?;
+        let splits = test_sandbox
+            .metastore()
+            .list_all_splits("test-parquet-sidecar")
+            .await?;
+        assert_eq!(splits.len(), 1);
+
+        let sidecar_file_name = parquet_sidecar_file(splits[0].split_id());
+        let sidecar_bytes = test_sandbox
+            .storage()
+            .get_all(Path::new(&sidecar_file_name))
+            .await?;
+        let mut sidecar_file = tempfile::tempfile()?;
+        sidecar_file.write_all(sidecar_bytes.as_slice())?;
+        sidecar_file.rewind()?;
+        let reader = SerializedFileReader::new(sidecar_file)?;
+        let mut rows: Vec<(String, Option<u64>)> = reader
+            .get_row_iter(None)?
+            .map(|row| {
+                let mut columns = row.get_column_iter();
+                let body = match columns.next() {
+                    Some((_, ParquetField::Str(body))) => body.clone(),
+                    field => panic!("Expected a string, got `{:?}`.", field),
+                };
+                let response_time = match columns.next() {
+                    Some((_, ParquetField::ULong(response_time))) => Some(*response_time),
+                    Some((_, ParquetField::Null)) => None,
+                    field => panic!("Expected a `u64` value, got `{:?}`.", field),
+                };
+                (body, response_time)
+            })
+            .collect();
+        rows.sort();
+        assert_eq!(
+            rows,
+            vec![("error".to_string(), None), ("info".to_string(), Some(12))]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parquet_sidecar_writer_deletes_replaced_sidecars() -> anyhow::Result<()> {
+        let storage = Arc::new(RamStorage::default());
+        let sidecar_file_name = parquet_sidecar_file("split-1");
+        storage
+            .put(Path::new(&sidecar_file_name), Box::new(b"sidecar".to_vec()))
+            .await?;
+        let parquet_sidecar_writer = ParquetSidecarWriter::new(
+            "test-index".to_string(),
+            vec!["body".to_string()],
+            storage.clone(),
+            IndexingSplitStore::create_without_local_store(storage.clone()),
+            ScratchDirectory::for_test()?,
+        );
+        let universe = Universe::new();
+        let (parquet_sidecar_writer_mailbox, parquet_sidecar_writer_handle) =
+            universe.spawn_builder().spawn(parquet_sidecar_writer);
+        parquet_sidecar_writer_mailbox
+            .send_message(PublishedSplits {
+                new_splits: Vec::new(),
+                replaced_split_ids: vec!["split-1".to_string()],
+            })
+            .await?;
+        let counters = parquet_sidecar_writer_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(counters.num_deleted_sidecars, 1);
+        assert_eq!(counters.num_failed_sidecars, 0);
+        assert!(!storage.exists(Path::new(&sidecar_file_name)).await?);
+        Ok(())
+    }
+}
@@ -22,19 +22,24 @@ use std::sync::Arc;
 use anyhow::Context;
 use async_trait::async_trait;
 use fail::fail_point;
-use quickwit_actors::{Actor, ActorContext, Handler, Mailbox, QueueCapacity};
-use quickwit_metastore::Metastore;
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_common::events::{record_event, Event, EventType};
+use quickwit_metastore::checkpoint::PublishToken;
+use quickwit_metastore::{Metastore, MetastoreError};
 use serde::Serialize;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
-use crate::actors::MergePlanner;
-use crate::models::{NewSplits, SplitsUpdate};
+use crate::actors::{MergePlanner, ParquetSidecarWriter};
+use crate::models::{NewSplits, PublishedSplits, SplitsUpdate};
 use crate::source::{SourceActor, SuggestTruncate};
 
 #[derive(Clone, Debug, Default, Serialize)]
 pub struct PublisherCounters {
     pub num_published_splits: u64,
     pub num_replace_operations: u64,
+    /// Number of replace operations rejected because the replaced splits were not published
+    /// anymore.
+    pub num_stale_replace_operations: u64,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -58,9 +63,60 @@ pub struct Publisher {
     metastore: Arc<dyn Metastore>,
     merge_planner_mailbox_opt: Option<Mailbox<MergePlanner>>,
     source_mailbox_opt: Option<Mailbox<SourceActor>>,
+    parquet_sidecar_writer_mailbox_opt: Option<Mailbox<ParquetSidecarWriter>>,
+    publish_token_opt: Option<PublishToken>,
+    max_publish_batch_size: usize,
+    pending_batch_opt: Option<PendingBatch>,
     counters: PublisherCounters,
 }
 
+/// Splits updates coalesced into a single update, waiting to be published.
+struct PendingBatch {
+    split_update: SplitsUpdate,
+    num_split_updates: usize,
+}
+
+impl PendingBatch {
+    /// Appends `split_update` to the batch if both updates can be published in the same
+    /// metastore call, and hands it back otherwise. Only updates publishing new splits, without
+    /// replacing any, can be coalesced.
+    fn try_append(&mut self, split_update: SplitsUpdate) -> Result<(), SplitsUpdate> {
+        let is_compatible = split_update.index_id == self.split_update.index_id
+            && split_update.publish_lock == self.split_update.publish_lock
+            && split_update.replaced_split_ids.is_empty()
+            && self.split_update.replaced_split_ids.is_empty()
+            && split_update.merge_operation.is_none()
+            && self.split_update.merge_operation.is_none();
+        if !is_compatible {
+            return Err(split_update);
+        }
+        let checkpoint_delta_opt = match (
+            &self.split_update.checkpoint_delta_opt,
+            &split_update.checkpoint_delta_opt,
+        ) {
+            (Some(checkpoint_delta), Some(other_checkpoint_delta))
+                if checkpoint_delta.source_id == other_checkpoint_delta.source_id =>
+            {
+                let mut checkpoint_delta = checkpoint_delta.clone();
+                if checkpoint_delta
+                    .source_delta
+                    .extend(other_checkpoint_delta.source_delta.clone())
+                    .is_err()
+                {
+                    return Err(split_update);
+                }
+                Some(checkpoint_delta)
+            }
+            (None, None) => None,
+            _ => return Err(split_update),
+        };
+        self.split_update.checkpoint_delta_opt = checkpoint_delta_opt;
+        self.split_update.new_splits.extend(split_update.new_splits);
+        self.num_split_updates += 1;
+        Ok(())
+    }
+}
+
 impl Publisher {
     pub fn new(
         publisher_type: PublisherType,
@@ -73,85 +129,109 @@ impl Publisher {
             metastore,
             merge_planner_mailbox_opt,
             source_mailbox_opt,
+            parquet_sidecar_writer_mailbox_opt: None,
+            publish_token_opt: None,
+            max_publish_batch_size: 1,
+            pending_batch_opt: None,
             counters: PublisherCounters::default(),
         }
     }
-}
-
-#[async_trait]
-impl Actor for Publisher {
-    type ObservableState = PublisherCounters;
 
-    fn observable_state(&self) -> Self::ObservableState {
-        self.counters.clone()
+    /// Sets the publish token attached to the checkpoint deltas published by the publisher. The
+    /// metastore rejects them as soon as another writer acquires a newer token for the same
+    /// partition set.
+    pub fn set_publish_token(&mut self, publish_token: PublishToken) {
+        self.publish_token_opt = Some(publish_token);
     }
 
-    fn name(&self) -> String {
-        self.publisher_type.actor_name().to_string()
+    /// Sets the mailbox of the actor writing the Parquet sidecar files of the published splits.
+    pub fn set_parquet_sidecar_writer_mailbox(
+        &mut self,
+        parquet_sidecar_writer_mailbox: Mailbox<ParquetSidecarWriter>,
+    ) {
+        self.parquet_sidecar_writer_mailbox_opt = Some(parquet_sidecar_writer_mailbox);
     }
 
-    fn queue_capacity(&self) -> QueueCapacity {
-        match self.publisher_type {
-            PublisherType::MainPublisher => QueueCapacity::Bounded(1),
-            PublisherType::MergePublisher => QueueCapacity::Unbounded,
-        }
+    /// Sets the maximum number of splits updates coalesced into a single `publish_splits`
+    /// metastore call. Updates received while the publisher is busy are batched together as long
+    /// as they only publish new splits.
+    pub fn set_max_publish_batch_size(&mut self, max_publish_batch_size: usize) {
+        self.max_publish_batch_size = max_publish_batch_size.max(1);
     }
 
-    async fn finalize(
-        &mut self,
-        _exit_status: &quickwit_actors::ActorExitStatus,
-        ctx: &ActorContext<Self>,
-    ) -> anyhow::Result<()> {
-        // The `garbage_collector` actor runs for ever.
-        // Periodically scheduling new messages for itself.
-        //
-        // The publisher actor being the last standing actor of the pipeline,
-        // its end of life should also means the end of life of never stopping actors.
-        // After all, when the publisher is stopped, there shouldn't be anything to process.
-        // It's fine if the merge planner is already dead.
-        if let Some(merge_planner_mailbox) = self.merge_planner_mailbox_opt.as_ref() {
-            let _ = ctx.send_exit_with_success(merge_planner_mailbox).await;
+    async fn publish_pending_batch(&mut self, ctx: &ActorContext<Self>) -> anyhow::Result<()> {
+        if let Some(pending_batch) = self.pending_batch_opt.take() {
+            self.publish(
+                pending_batch.split_update,
+                pending_batch.num_split_updates,
+                ctx,
+            )
+            .await?;
         }
         Ok(())
     }
-}
-
-#[async_trait]
-impl Handler<SplitsUpdate> for Publisher {
-    type Reply = ();
 
-    #[instrument(name="publisher", parent=split_update.parent_span.id(),  skip(self, ctx))]
-    async fn handle(
+    #[instrument(name="publisher", parent=split_update.parent_span.id(), skip(self, ctx))]
+    async fn publish(
         &mut self,
         split_update: SplitsUpdate,
+        num_split_updates: usize,
         ctx: &ActorContext<Self>,
-    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+    ) -> anyhow::Result<()> {
         fail_point!("publisher:before");
 
         let SplitsUpdate {
             index_id,
             new_splits,
             replaced_split_ids,
-            checkpoint_delta_opt,
+            mut checkpoint_delta_opt,
             publish_lock,
             merge_operation: _,
             parent_span: _,
         } = split_update;
 
+        if let Some(checkpoint_delta) = checkpoint_delta_opt.as_mut() {
+            checkpoint_delta.publish_token_opt = self.publish_token_opt.clone();
+        }
         let split_ids: Vec<&str> = new_splits.iter().map(|split| split.split_id()).collect();
 
         let replaced_split_ids_ref_vec: Vec<&str> =
             replaced_split_ids.iter().map(String::as_str).collect();
 
         if let Some(_guard) = publish_lock.acquire().await {
-            ctx.protect_future(self.metastore.publish_splits(
-                &index_id,
-                &split_ids[..],
-                &replaced_split_ids_ref_vec,
-                checkpoint_delta_opt.clone(),
-            ))
-            .await
-            .context("Failed to publish splits.")?;
+            let publish_res = ctx
+                .protect_future(self.metastore.publish_splits(
+                    &index_id,
+                    &split_ids[..],
+                    &replaced_split_ids_ref_vec,
+                    checkpoint_delta_opt.clone(),
+                ))
+                .await;
+            if let Err(error) = &publish_res {
+                record_event(
+                    Event::new(EventType::PublishFailure, "Failed to publish splits.")
+                        .with_index_id(&index_id)
+                        .with_attribute("publisher", self.publisher_type.actor_name())
+                        .with_attribute("split_ids", split_ids.join(","))
+                        .with_attribute("error", error),
+                );
+            }
+            if let Err(MetastoreError::ReplacedSplitsNotPublished {
+                split_ids: stale_split_ids,
+            }) = &publish_res
+            {
+                // Another writer replaced or deleted the input splits of the merge in the
+                // meantime: the merged splits are obsolete and remain staged until they are
+                // garbage collected.
+                warn!(
+                    new_splits=?split_ids,
+                    stale_split_ids=?stale_split_ids,
+                    "Discarding splits built from stale splits."
+                );
+                self.counters.num_stale_replace_operations += 1;
+                return Ok(());
+            }
+            publish_res.context("Failed to publish splits.")?;
         } else {
             // TODO: Remove the junk right away?
             info!(
@@ -160,7 +240,7 @@ impl Handler<SplitsUpdate> for Publisher {
             );
             return Ok(());
         }
-        info!(new_splits=?split_ids, checkpoint_delta=?checkpoint_delta_opt, "publish-new-splits");
+        info!(new_splits=?split_ids, checkpoint_delta=?checkpoint_delta_opt, num_split_updates=num_split_updates, "publish-new-splits");
         if let Some(source_mailbox) = self.source_mailbox_opt.as_ref() {
             if let Some(checkpoint) = checkpoint_delta_opt {
                 // We voluntarily do not log anything here.
@@ -178,6 +258,20 @@ impl Handler<SplitsUpdate> for Publisher {
             }
         }
 
+        if let Some(parquet_sidecar_writer_mailbox) =
+            self.parquet_sidecar_writer_mailbox_opt.as_ref()
+        {
+            let _ = ctx
+                .send_message(
+                    parquet_sidecar_writer_mailbox,
+                    PublishedSplits {
+                        new_splits: new_splits.clone(),
+                        replaced_split_ids: replaced_split_ids.clone(),
+                    },
+                )
+                .await;
+        }
+
         // The merge planner is not necessarily awake and this is not an error.
         // For instance, when a source reaches its end, and the last "new" split
         // has been packaged, the packager finalizer sends a message to the merge
@@ -189,7 +283,7 @@ impl Handler<SplitsUpdate> for Publisher {
         }
 
         if replaced_split_ids.is_empty() {
-            self.counters.num_published_splits += 1;
+            self.counters.num_published_splits += num_split_updates as u64;
         } else {
             self.counters.num_replace_operations += 1;
         }
@@ -198,13 +292,113 @@ impl Handler<SplitsUpdate> for Publisher {
     }
 }
 
+#[async_trait]
+impl Actor for Publisher {
+    type ObservableState = PublisherCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        self.publisher_type.actor_name().to_string()
+    }
+
+    fn queue_capacity(&self) -> QueueCapacity {
+        match self.publisher_type {
+            PublisherType::MainPublisher => QueueCapacity::Bounded(self.max_publish_batch_size),
+            PublisherType::MergePublisher => QueueCapacity::Unbounded,
+        }
+    }
+
+    async fn on_drained_messages(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.publish_pending_batch(ctx).await?;
+        Ok(())
+    }
+
+    async fn finalize(
+        &mut self,
+        exit_status: &ActorExitStatus,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        if matches!(
+            exit_status,
+            ActorExitStatus::Success | ActorExitStatus::Quit
+        ) {
+            self.publish_pending_batch(ctx).await?;
+        }
+        // The `garbage_collector` actor runs for ever.
+        // Periodically scheduling new messages for itself.
+        //
+        // The publisher actor being the last standing actor of the pipeline,
+        // its end of life should also means the end of life of never stopping actors.
+        // After all, when the publisher is stopped, there shouldn't be anything to process.
+        // It's fine if the merge planner is already dead.
+        if let Some(merge_planner_mailbox) = self.merge_planner_mailbox_opt.as_ref() {
+            let _ = ctx.send_exit_with_success(merge_planner_mailbox).await;
+        }
+        if let Some(parquet_sidecar_writer_mailbox) =
+            self.parquet_sidecar_writer_mailbox_opt.as_ref()
+        {
+            let _ = ctx
+                .send_exit_with_success(parquet_sidecar_writer_mailbox)
+                .await;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<SplitsUpdate> for Publisher {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        split_update: SplitsUpdate,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if self.max_publish_batch_size == 1 {
+            self.publish(split_update, 1, ctx).await?;
+            return Ok(());
+        }
+        // The pending batch is published once the mailbox is drained, unless it is full or
+        // the new update cannot be coalesced with it.
+        if let Some(pending_batch) = self.pending_batch_opt.as_mut() {
+            if let Err(split_update) = pending_batch.try_append(split_update) {
+                self.publish_pending_batch(ctx).await?;
+                self.pending_batch_opt = Some(PendingBatch {
+                    split_update,
+                    num_split_updates: 1,
+                });
+            }
+        } else {
+            self.pending_batch_opt = Some(PendingBatch {
+                split_update,
+                num_split_updates: 1,
+            });
+        }
+        let is_pending_batch_full = self
+            .pending_batch_opt
+            .as_ref()
+            .map(|pending_batch| pending_batch.num_split_updates >= self.max_publish_batch_size)
+            .unwrap_or(false);
+        if is_pending_batch_full {
+            self.publish_pending_batch(ctx).await?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use quickwit_actors::{create_test_mailbox, Universe};
+    use quickwit_actors::{create_test_mailbox, ActorExitStatus, ObservationType, Universe};
     use quickwit_metastore::checkpoint::{
         IndexCheckpointDelta, PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
     };
-    use quickwit_metastore::{MockMetastore, SplitMetadata};
+    use quickwit_metastore::{MetastoreError, MockMetastore, SplitMetadata};
     use tracing::Span;
 
     use super::*;
@@ -251,6 +445,7 @@ mod tests {
                 checkpoint_delta_opt: Some(IndexCheckpointDelta {
                     source_id: "source".to_string(),
                     source_delta: SourceCheckpointDelta::from(1..3),
+                    publish_token_opt: None,
                 }),
                 publish_lock: PublishLock::default(),
                 merge_operation: None,
@@ -281,6 +476,74 @@ mod tests {
         assert_eq!(merger_msgs[0].new_splits.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_publisher_coalesces_splits_updates() {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_publish_splits()
+            .withf(
+                |index_id, split_ids, replaced_split_ids, checkpoint_delta_opt| {
+                    let checkpoint_delta = checkpoint_delta_opt.as_ref().unwrap();
+                    index_id == "index"
+                        && split_ids[..] == ["split-1", "split-2", "split-3"]
+                        && replaced_split_ids.is_empty()
+                        && checkpoint_delta.source_delta == SourceCheckpointDelta::from(1..7)
+                },
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
+        let (source_mailbox, source_inbox) = create_test_mailbox();
+        let mut publisher = Publisher::new(
+            PublisherType::MainPublisher,
+            Arc::new(mock_metastore),
+            Some(merge_planner_mailbox),
+            Some(source_mailbox),
+        );
+        publisher.set_max_publish_batch_size(4);
+        let universe = Universe::new();
+        let (publisher_mailbox, publisher_handle) = universe.spawn_builder().spawn(publisher);
+        // Pausing the publisher lets the updates pile up in its mailbox, as they would while
+        // the publisher waits for the metastore.
+        publisher_handle.pause();
+        let publish_lock = PublishLock::default();
+        for (split_ord, checkpoint_delta) in [(1, 1..3), (2, 3..5), (3, 5..7)] {
+            publisher_mailbox
+                .send_message(SplitsUpdate {
+                    index_id: "index".to_string(),
+                    new_splits: vec![SplitMetadata::for_test(format!("split-{}", split_ord))],
+                    replaced_split_ids: Vec::new(),
+                    checkpoint_delta_opt: Some(IndexCheckpointDelta::for_test(
+                        "source",
+                        checkpoint_delta,
+                    )),
+                    publish_lock: publish_lock.clone(),
+                    merge_operation: None,
+                    parent_span: Span::none(),
+                })
+                .await
+                .unwrap();
+        }
+        publisher_handle.resume();
+        drop(publisher_mailbox);
+        let (exit_status, publisher_observation) = publisher_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        assert_eq!(publisher_observation.num_published_splits, 3);
+
+        let suggest_truncate_checkpoints = source_inbox.drain_for_test_typed::<SuggestTruncate>();
+        assert_eq!(suggest_truncate_checkpoints.len(), 1);
+        assert_eq!(
+            suggest_truncate_checkpoints[0]
+                .0
+                .position_for_partition(&PartitionId::default())
+                .unwrap(),
+            &Position::from(6u64)
+        );
+        let merge_planner_msgs = merge_planner_inbox.drain_for_test_typed::<NewSplits>();
+        assert_eq!(merge_planner_msgs.len(), 1);
+        assert_eq!(merge_planner_msgs[0].new_splits.len(), 3);
+    }
+
     #[tokio::test]
     async fn test_publisher_replace_operation() {
         let mut mock_metastore = MockMetastore::default();
@@ -297,12 +560,14 @@ mod tests {
             .times(1)
             .returning(|_, _, _, _| Ok(()));
         let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
-        let publisher = Publisher::new(
+        let (parquet_sidecar_writer_mailbox, parquet_sidecar_writer_inbox) = create_test_mailbox();
+        let mut publisher = Publisher::new(
             PublisherType::MainPublisher,
             Arc::new(mock_metastore),
             Some(merge_planner_mailbox),
             None,
         );
+        publisher.set_parquet_sidecar_writer_mailbox(parquet_sidecar_writer_mailbox);
         let universe = Universe::new();
         let (publisher_mailbox, publisher_handle) = universe.spawn_builder().spawn(publisher);
         let publisher_message = SplitsUpdate {
@@ -327,6 +592,52 @@ mod tests {
         let merge_planner_msgs = merge_planner_inbox.drain_for_test_typed::<NewSplits>();
         assert_eq!(merge_planner_msgs.len(), 1);
         assert_eq!(merge_planner_msgs[0].new_splits.len(), 1);
+        let parquet_sidecar_writer_msgs =
+            parquet_sidecar_writer_inbox.drain_for_test_typed::<PublishedSplits>();
+        assert_eq!(parquet_sidecar_writer_msgs.len(), 1);
+        assert_eq!(
+            parquet_sidecar_writer_msgs[0].replaced_split_ids,
+            ["split1", "split2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_publisher_discards_stale_replace_operation() {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_publish_splits()
+            .times(1)
+            .returning(|_, _, _, _| {
+                Err(MetastoreError::ReplacedSplitsNotPublished {
+                    split_ids: vec!["split1".to_string()],
+                })
+            });
+        let (merge_planner_mailbox, merge_planner_inbox) = create_test_mailbox();
+        let publisher = Publisher::new(
+            PublisherType::MergePublisher,
+            Arc::new(mock_metastore),
+            Some(merge_planner_mailbox),
+            None,
+        );
+        let universe = Universe::new();
+        let (publisher_mailbox, publisher_handle) = universe.spawn_builder().spawn(publisher);
+        publisher_mailbox
+            .send_message(SplitsUpdate {
+                index_id: "index".to_string(),
+                new_splits: vec![SplitMetadata::for_test("split3".to_string())],
+                replaced_split_ids: vec!["split1".to_string(), "split2".to_string()],
+                checkpoint_delta_opt: None,
+                publish_lock: PublishLock::default(),
+                merge_operation: None,
+                parent_span: Span::none(),
+            })
+            .await
+            .unwrap();
+        let publisher_observation = publisher_handle.process_pending_and_observe().await;
+        assert_eq!(publisher_observation.obs_type, ObservationType::Alive);
+        assert_eq!(publisher_observation.state.num_replace_operations, 0);
+        assert_eq!(publisher_observation.state.num_stale_replace_operations, 1);
+        assert!(merge_planner_inbox.drain_for_test().is_empty());
     }
 
     #[tokio::test]
@@ -366,4 +677,57 @@ mod tests {
         let merger_messages = merge_planner_inbox.drain_for_test();
         assert!(merger_messages.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_publisher_rejected_with_stale_publish_token() {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_publish_splits()
+            .withf(|_, _, _, checkpoint_delta_opt| {
+                let checkpoint_delta = checkpoint_delta_opt.as_ref().unwrap();
+                checkpoint_delta.publish_token_opt
+                    == Some(PublishToken {
+                        partition_set_id: "0".to_string(),
+                        token: 1,
+                    })
+            })
+            .times(1)
+            .returning(|_, _, _, _| {
+                Err(MetastoreError::StalePublishToken {
+                    source_id: "source".to_string(),
+                    partition_set_id: "0".to_string(),
+                    token: 1,
+                    latest_token: 2,
+                })
+            });
+        let (source_mailbox, source_inbox) = create_test_mailbox();
+        let mut publisher = Publisher::new(
+            PublisherType::MainPublisher,
+            Arc::new(mock_metastore),
+            None,
+            Some(source_mailbox),
+        );
+        publisher.set_publish_token(PublishToken {
+            partition_set_id: "0".to_string(),
+            token: 1,
+        });
+        let universe = Universe::new();
+        let (publisher_mailbox, publisher_handle) = universe.spawn_builder().spawn(publisher);
+        publisher_mailbox
+            .send_message(SplitsUpdate {
+                index_id: "index".to_string(),
+                new_splits: vec![SplitMetadata::for_test("split".to_string())],
+                replaced_split_ids: Vec::new(),
+                checkpoint_delta_opt: Some(IndexCheckpointDelta::for_test("source", 1..3)),
+                publish_lock: PublishLock::default(),
+                merge_operation: None,
+                parent_span: Span::none(),
+            })
+            .await
+            .unwrap();
+        let (exit_status, publisher_observation) = publisher_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Failure(_)));
+        assert_eq!(publisher_observation.num_published_splits, 0);
+        assert!(source_inbox.drain_for_test().is_empty());
+    }
 }
@@ -226,6 +226,7 @@ impl Uploader {
 pub struct UploaderCounters {
     pub num_staged_splits: Arc<AtomicU64>,
     pub num_uploaded_splits: Arc<AtomicU64>,
+    pub num_uploaded_bytes: Arc<AtomicU64>,
 }
 
 #[async_trait]
@@ -377,8 +378,11 @@ async fn stage_and_upload_split(
     let split_metadata = create_split_metadata(
         &packaged_split.split_attrs,
         packaged_split.tags.clone(),
+        packaged_split.field_stats.clone(),
         split_streamer.footer_range.start as u64..split_streamer.footer_range.end as u64,
     );
+    // The footer is at the end of the split file.
+    let split_num_bytes = split_streamer.footer_range.end as u64;
     let index_id = &packaged_split.split_attrs.pipeline_id.index_id.clone();
     metastore
         .stage_split(index_id, split_metadata.clone())
@@ -386,6 +390,13 @@ async fn stage_and_upload_split(
         .await?;
     counters.num_staged_splits.fetch_add(1, Ordering::SeqCst);
 
+    // The bloom filters are uploaded before the split is published so that searchers never
+    // see a published split whose bloom filters are missing.
+    if let Some(bloom_filters) = &packaged_split.bloom_filters_opt {
+        split_store
+            .store_bloom_filters(split_metadata.split_id(), bloom_filters)
+            .await?;
+    }
     split_store
         .store_split(
             &split_metadata,
@@ -394,18 +405,22 @@ async fn stage_and_upload_split(
         )
         .await?;
     counters.num_uploaded_splits.fetch_add(1, Ordering::SeqCst);
+    counters
+        .num_uploaded_bytes
+        .fetch_add(split_num_bytes, Ordering::SeqCst);
     Ok(split_metadata)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use std::time::{Duration, Instant};
 
     use quickwit_actors::{create_test_mailbox, ObservationType, Universe};
+    use quickwit_doc_mapper::bloom_filter::{BloomFilter, SplitBloomFilters};
     use quickwit_metastore::checkpoint::{IndexCheckpointDelta, SourceCheckpointDelta};
     use quickwit_metastore::MockMetastore;
-    use quickwit_storage::RamStorage;
+    use quickwit_storage::{RamStorage, Storage};
     use tokio::sync::oneshot;
 
     use super::*;
@@ -443,9 +458,14 @@ mod tests {
         );
         let (uploader_mailbox, uploader_handle) = universe.spawn_builder().spawn(uploader);
         let split_scratch_directory = ScratchDirectory::for_test()?;
+        let mut bloom_filter = BloomFilter::with_num_values(1, 0.01);
+        bloom_filter.insert("trace-1");
+        let mut bloom_filters = SplitBloomFilters::default();
+        bloom_filters.insert("trace_id".to_string(), bloom_filter);
         let checkpoint_delta_opt: Option<IndexCheckpointDelta> = Some(IndexCheckpointDelta {
             source_id: "test-source".to_string(),
             source_delta: SourceCheckpointDelta::from(3..15),
+            publish_token_opt: None,
         });
         uploader_mailbox
             .send_message(PackagedSplitBatch::new(
@@ -464,6 +484,8 @@ mod tests {
                     split_scratch_directory,
                     tags: Default::default(),
                     hotcache_bytes: vec![],
+                    bloom_filters_opt: Some(bloom_filters.clone()),
+                    field_stats: Default::default(),
                     split_files: vec![],
                 }],
                 checkpoint_delta_opt,
@@ -505,9 +527,25 @@ mod tests {
             SourceCheckpointDelta::from(3..15)
         );
         assert!(replaced_split_ids.is_empty());
-        let mut files = ram_storage.list_files().await;
+        let mut files: Vec<PathBuf> = ram_storage
+            .list_files()
+            .await?
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
         files.sort();
-        assert_eq!(&files, &[PathBuf::from("test-split.split")]);
+        assert_eq!(
+            &files,
+            &[
+                PathBuf::from("test-split.bloom"),
+                PathBuf::from("test-split.split")
+            ]
+        );
+        let bloom_filters_bytes = ram_storage.get_all(Path::new("test-split.bloom")).await?;
+        assert_eq!(
+            SplitBloomFilters::from_bytes(bloom_filters_bytes.as_slice())?,
+            bloom_filters
+        );
         Ok(())
     }
 
@@ -564,6 +602,8 @@ mod tests {
             tags: Default::default(),
             split_files: vec![],
             hotcache_bytes: vec![],
+            bloom_filters_opt: None,
+            field_stats: Default::default(),
         };
         let package_split_2 = PackagedSplit {
             split_attrs: SplitAttrs {
@@ -584,6 +624,8 @@ mod tests {
             tags: Default::default(),
             split_files: vec![],
             hotcache_bytes: vec![],
+            bloom_filters_opt: None,
+            field_stats: Default::default(),
         };
         uploader_mailbox
             .send_message(PackagedSplitBatch::new(
@@ -631,7 +673,12 @@ mod tests {
         );
         assert!(checkpoint_delta_opt.is_none());
 
-        let mut files = ram_storage.list_files().await;
+        let mut files: Vec<PathBuf> = ram_storage
+            .list_files()
+            .await?
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
         files.sort();
         assert_eq!(
             &files,
@@ -674,6 +721,7 @@ mod tests {
         let checkpoint_delta_opt: Option<IndexCheckpointDelta> = Some(IndexCheckpointDelta {
             source_id: "test-source".to_string(),
             source_delta: SourceCheckpointDelta::from(3..15),
+            publish_token_opt: None,
         });
         uploader_mailbox
             .send_message(PackagedSplitBatch::new(
@@ -692,6 +740,8 @@ mod tests {
                     split_scratch_directory,
                     tags: Default::default(),
                     hotcache_bytes: vec![],
+                    bloom_filters_opt: None,
+                    field_stats: Default::default(),
                     split_files: vec![],
                 }],
                 checkpoint_delta_opt,
@@ -0,0 +1,336 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversion of CSV and TSV records into JSON documents.
+
+use std::collections::HashMap;
+
+use csv::{ReaderBuilder, StringRecord};
+use csv_core::ReadRecordResult;
+use quickwit_config::{CsvOptions, SourceInputFormat};
+use quickwit_metastore::checkpoint::PartitionId;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tantivy::schema::{FieldType, Schema};
+
+use crate::doc_transform::{insert_field, split_field_path};
+
+/// Splits a stream of lines into CSV or TSV records. A record spans several lines when one of its
+/// quoted fields contains a newline.
+pub(crate) struct CsvRecordFramer {
+    reader: csv_core::Reader,
+}
+
+impl CsvRecordFramer {
+    pub fn new(input_format: SourceInputFormat, csv_options: &CsvOptions) -> Self {
+        let escape_opt = csv_options.escape.map(|escape| escape as u8);
+        let reader = csv_core::ReaderBuilder::new()
+            .delimiter(
+                csv_options
+                    .delimiter
+                    .unwrap_or_else(|| input_format.default_delimiter()) as u8,
+            )
+            .quote(csv_options.quote.unwrap_or('"') as u8)
+            .escape(escape_opt)
+            .double_quote(escape_opt.is_none())
+            .quoting(!csv_options.disable_quoting)
+            .build();
+        Self { reader }
+    }
+
+    /// Feeds the next line of the stream to the framer and returns whether it completes the
+    /// current record.
+    pub fn ends_record(&mut self, line: &[u8]) -> bool {
+        // The field values are not needed, the buffers are overwritten when full.
+        let mut field_bytes = [0u8; 1024];
+        let mut field_ends = [0usize; 64];
+        let mut input = line;
+        loop {
+            let (result, num_bytes_read, _, _) =
+                self.reader
+                    .read_record(input, &mut field_bytes, &mut field_ends);
+            input = &input[num_bytes_read..];
+            match result {
+                ReadRecordResult::Record => return true,
+                ReadRecordResult::InputEmpty | ReadRecordResult::End => return false,
+                ReadRecordResult::OutputFull | ReadRecordResult::OutputEndsFull => {
+                    // Feeding an empty input would signal the end of the stream.
+                    if input.is_empty() {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Resets the framer to the beginning of a record, when a new file is read.
+    pub fn reset(&mut self) {
+        self.reader.reset();
+    }
+}
+
+/// Converts CSV and TSV records into JSON documents, coercing their values to the types of the
+/// fields of the doc mapping.
+pub(crate) struct CsvDocParser {
+    delimiter: u8,
+    quote: u8,
+    escape_opt: Option<u8>,
+    quoting: bool,
+    columns: Vec<String>,
+    /// Columns read from the header of each partition, when the columns are not declared.
+    headers_per_partition: HashMap<PartitionId, Vec<String>>,
+    schema: Schema,
+}
+
+impl CsvDocParser {
+    pub fn new(input_format: SourceInputFormat, csv_options: &CsvOptions, schema: Schema) -> Self {
+        // The options are validated to be ASCII characters.
+        Self {
+            delimiter: csv_options
+                .delimiter
+                .unwrap_or_else(|| input_format.default_delimiter()) as u8,
+            quote: csv_options.quote.unwrap_or('"') as u8,
+            escape_opt: csv_options.escape.map(|escape| escape as u8),
+            quoting: !csv_options.disable_quoting,
+            columns: csv_options.columns.clone(),
+            headers_per_partition: HashMap::new(),
+            schema,
+        }
+    }
+
+    fn parse_record(&self, record_bytes: &[u8]) -> Result<StringRecord, String> {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .escape(self.escape_opt)
+            .double_quote(self.escape_opt.is_none())
+            .quoting(self.quoting)
+            .from_reader(record_bytes);
+        match reader.records().next() {
+            Some(Ok(record)) => Ok(record),
+            Some(Err(error)) => Err(error.to_string()),
+            None => Err("The record is empty.".to_string()),
+        }
+    }
+
+    /// Records the columns of the partition, read from the header of the file.
+    pub fn set_header(&mut self, partition_id: PartitionId, header: &[u8]) -> Result<(), String> {
+        let columns: Vec<String> = self
+            .parse_record(header)?
+            .iter()
+            .map(|column| column.trim().to_string())
+            .collect();
+        if columns.iter().any(|column| column.is_empty()) {
+            return Err("The header contains empty column names.".to_string());
+        }
+        self.headers_per_partition.insert(partition_id, columns);
+        Ok(())
+    }
+
+    /// Converts the record into a JSON document. Empty values are omitted.
+    pub fn to_json(
+        &self,
+        partition_id: &PartitionId,
+        record_bytes: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let columns = if self.columns.is_empty() {
+            self.headers_per_partition
+                .get(partition_id)
+                .ok_or_else(|| {
+                    format!("The header of partition `{}` is unknown.", partition_id.0)
+                })?
+        } else {
+            &self.columns
+        };
+        let record = self.parse_record(record_bytes)?;
+        if record.len() > columns.len() {
+            return Err(format!(
+                "The record has {} fields but only {} columns are declared.",
+                record.len(),
+                columns.len()
+            ));
+        }
+        let mut doc = JsonMap::new();
+        for (column, value) in columns.iter().zip(record.iter()) {
+            if value.is_empty() {
+                continue;
+            }
+            let json_value = self.coerce_value(column, value);
+            insert_field(&mut doc, &split_field_path(column), json_value)
+                .map_err(|error| error.to_string())?;
+        }
+        let doc_json =
+            serde_json::to_vec(&doc).expect("Serializing a JSON object should never fail.");
+        Ok(doc_json)
+    }
+
+    /// Converts the value to the type of the field of the column. Values that cannot be converted
+    /// are kept as strings and rejected by the doc mapper.
+    fn coerce_value(&self, column: &str, value: &str) -> JsonValue {
+        let field_type_opt = self
+            .schema
+            .get_field(column)
+            .map(|field| self.schema.get_field_entry(field).field_type());
+        let json_value_opt = match field_type_opt {
+            Some(FieldType::I64(_)) | Some(FieldType::Date(_)) => {
+                value.parse::<i64>().ok().map(JsonValue::from)
+            }
+            Some(FieldType::U64(_)) => value.parse::<u64>().ok().map(JsonValue::from),
+            Some(FieldType::F64(_)) => value.parse::<f64>().ok().map(JsonValue::from),
+            Some(FieldType::Bool(_)) => value.parse::<bool>().ok().map(JsonValue::from),
+            _ => None,
+        };
+        json_value_opt.unwrap_or_else(|| JsonValue::from(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tantivy::schema::{Schema, FAST, INDEXED, STORED, TEXT};
+
+    use super::*;
+
+    fn test_schema() -> Schema {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("body", TEXT | STORED);
+        schema_builder.add_i64_field("status", INDEXED | FAST);
+        schema_builder.add_f64_field("duration", FAST);
+        schema_builder.add_date_field("timestamp", INDEXED | FAST);
+        schema_builder.add_text_field("client.ip", TEXT);
+        schema_builder.build()
+    }
+
+    fn to_json_value(parser: &CsvDocParser, partition_id: &PartitionId, record: &str) -> JsonValue {
+        let doc_json = parser.to_json(partition_id, record.as_bytes()).unwrap();
+        serde_json::from_slice(&doc_json).unwrap()
+    }
+
+    #[test]
+    fn test_csv_doc_parser_declared_columns() {
+        let csv_options = CsvOptions {
+            columns: vec![
+                "timestamp".to_string(),
+                "status".to_string(),
+                "duration".to_string(),
+                "client.ip".to_string(),
+                "body".to_string(),
+            ],
+            ..Default::default()
+        };
+        let parser = CsvDocParser::new(SourceInputFormat::Csv, &csv_options, test_schema());
+        let partition_id = PartitionId::default();
+        assert_eq!(
+            to_json_value(
+                &parser,
+                &partition_id,
+                "1665000000,200,0.25,10.0.0.1,\"GET /, \"\"quoted\"\"\"\n"
+            ),
+            json!({
+                "timestamp": 1665000000,
+                "status": 200,
+                "duration": 0.25,
+                "client": {"ip": "10.0.0.1"},
+                "body": "GET /, \"quoted\""
+            })
+        );
+        // Empty and missing values are omitted, invalid values are kept as strings.
+        assert_eq!(
+            to_json_value(&parser, &partition_id, "2022-10-05T13:12:11Z,,n/a"),
+            json!({"timestamp": "2022-10-05T13:12:11Z", "duration": "n/a"})
+        );
+        assert!(parser
+            .to_json(&partition_id, b"1,2,3,4,5,6")
+            .unwrap_err()
+            .contains("6 fields"));
+    }
+
+    #[test]
+    fn test_csv_doc_parser_header() {
+        let csv_options = CsvOptions::default();
+        let mut parser = CsvDocParser::new(SourceInputFormat::Tsv, &csv_options, test_schema());
+        let partition_id = PartitionId::from("file.tsv");
+        assert!(parser
+            .to_json(&partition_id, b"200\thello")
+            .unwrap_err()
+            .contains("header"));
+        parser
+            .set_header(partition_id.clone(), b"status\tbody\n")
+            .unwrap();
+        assert_eq!(
+            to_json_value(&parser, &partition_id, "200\thello, world\n"),
+            json!({"status": 200, "body": "hello, world"})
+        );
+        assert!(parser
+            .to_json(&PartitionId::from("other.tsv"), b"200\thello")
+            .is_err());
+    }
+
+    #[test]
+    fn test_csv_doc_parser_options() {
+        let csv_options = CsvOptions {
+            columns: vec!["status".to_string(), "body".to_string()],
+            delimiter: Some(';'),
+            quote: Some('\''),
+            escape: Some('\\'),
+            disable_quoting: false,
+        };
+        let parser = CsvDocParser::new(SourceInputFormat::Csv, &csv_options, test_schema());
+        assert_eq!(
+            to_json_value(&parser, &PartitionId::default(), r"404;'it\'s; gone'"),
+            json!({"status": 404, "body": "it's; gone"})
+        );
+    }
+
+    #[test]
+    fn test_csv_record_framer() {
+        let mut framer = CsvRecordFramer::new(SourceInputFormat::Csv, &CsvOptions::default());
+        assert!(framer.ends_record(b"200,first\n"));
+        assert!(!framer.ends_record(b"404,\"multi\n"));
+        assert!(!framer.ends_record(b"line \"\"quoted\"\"\n"));
+        assert!(framer.ends_record(b"body\"\n"));
+        assert!(framer.ends_record(b"500,\"done\"\r\n"));
+        assert!(framer.ends_record(b"503,last\n"));
+
+        assert!(!framer.ends_record(b"404,\"unterminated\n"));
+        framer.reset();
+        assert!(framer.ends_record(b"200,first\n"));
+
+        let csv_options = CsvOptions {
+            disable_quoting: true,
+            ..Default::default()
+        };
+        let mut framer = CsvRecordFramer::new(SourceInputFormat::Tsv, &csv_options);
+        assert!(framer.ends_record(b"404\t\"not quoted\n"));
+
+        let parser = CsvDocParser::new(
+            SourceInputFormat::Csv,
+            &CsvOptions {
+                columns: vec!["status".to_string(), "body".to_string()],
+                ..Default::default()
+            },
+            test_schema(),
+        );
+        assert_eq!(
+            to_json_value(&parser, &PartitionId::default(), "404,\"multi\nline\"\n"),
+            json!({"status": 404, "body": "multi\nline"})
+        );
+    }
+}
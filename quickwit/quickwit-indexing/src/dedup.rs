@@ -0,0 +1,253 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Best-effort deduplication of the documents sharing the same id.
+//!
+//! Documents are deduplicated at two levels:
+//! - the doc processor drops the documents whose id was recently seen in the same partition;
+//! - optionally, the merge executor drops the documents whose id appears in another document of
+//! the merged splits.
+//! Neither level is exhaustive: duplicates that are far apart in the stream and never end up in
+//! the same merge are kept.
+
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+
+use lru::LruCache;
+use tantivy::query::{ConstScorer, Explanation, Query, Scorer, Weight};
+use tantivy::schema::{Field, IndexRecordOption, Value};
+use tantivy::{
+    DocId, DocSet, Document, Index, Score, Searcher, SegmentId, SegmentReader, TantivyError,
+    TERMINATED,
+};
+
+/// Remembers the ids of the most recently seen documents of each partition.
+pub(crate) struct DocIdDeduplicator {
+    doc_id_field: Field,
+    cache_capacity: NonZeroUsize,
+    seen_doc_ids_per_partition: HashMap<u64, LruCache<Vec<u8>, ()>>,
+}
+
+impl DocIdDeduplicator {
+    pub fn new(doc_id_field: Field, cache_capacity: NonZeroUsize) -> Self {
+        Self {
+            doc_id_field,
+            cache_capacity,
+            seen_doc_ids_per_partition: HashMap::new(),
+        }
+    }
+
+    /// Records the id of the document and returns whether it was already seen in the partition.
+    /// Documents without an id are never considered duplicates.
+    pub fn is_duplicate(&mut self, partition: u64, doc: &Document) -> bool {
+        let doc_id = match doc.get_first(self.doc_id_field).and_then(doc_id_bytes) {
+            Some(doc_id) => doc_id,
+            None => return false,
+        };
+        let cache_capacity = self.cache_capacity;
+        self.seen_doc_ids_per_partition
+            .entry(partition)
+            .or_insert_with(|| LruCache::new(cache_capacity))
+            .put(doc_id, ())
+            .is_some()
+    }
+}
+
+fn doc_id_bytes(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::Str(text) => Some(text.as_bytes().to_vec()),
+        Value::U64(val) => Some(val.to_be_bytes().to_vec()),
+        Value::I64(val) => Some(val.to_be_bytes().to_vec()),
+        _ => None,
+    }
+}
+
+/// Lists, for each segment of the index, the alive documents whose id was already seen in a
+/// previous document. The first document of each id is kept.
+pub(crate) fn find_duplicate_docs(
+    index: &Index,
+    doc_id_field: Field,
+) -> tantivy::Result<HashMap<SegmentId, Vec<DocId>>> {
+    let mut seen_doc_ids: HashSet<Vec<u8>> = HashSet::new();
+    let mut duplicate_docs: HashMap<SegmentId, Vec<DocId>> = HashMap::new();
+
+    for segment in index.searchable_segments()? {
+        let segment_reader = SegmentReader::open(&segment)?;
+        let inverted_index = segment_reader.inverted_index(doc_id_field)?;
+        let mut term_stream = inverted_index.terms().stream()?;
+        let mut segment_duplicate_docs = Vec::new();
+
+        while term_stream.advance() {
+            let mut postings = inverted_index
+                .read_postings_from_terminfo(term_stream.value(), IndexRecordOption::Basic)?;
+            let mut doc = postings.doc();
+            while doc != TERMINATED {
+                if !segment_reader.is_deleted(doc)
+                    && !seen_doc_ids.insert(term_stream.key().to_vec())
+                {
+                    segment_duplicate_docs.push(doc);
+                }
+                doc = postings.advance();
+            }
+        }
+        if !segment_duplicate_docs.is_empty() {
+            segment_duplicate_docs.sort_unstable();
+            segment_duplicate_docs.dedup();
+            duplicate_docs.insert(segment.id(), segment_duplicate_docs);
+        }
+    }
+    Ok(duplicate_docs)
+}
+
+/// Matches an explicit list of documents per segment. Used to delete the duplicate documents
+/// found by [`find_duplicate_docs`].
+#[derive(Clone, Debug)]
+pub(crate) struct DocListQuery {
+    docs: HashMap<SegmentId, Vec<DocId>>,
+}
+
+impl DocListQuery {
+    /// `docs` must be sorted in each segment.
+    pub fn new(docs: HashMap<SegmentId, Vec<DocId>>) -> Self {
+        Self { docs }
+    }
+}
+
+impl Query for DocListQuery {
+    fn weight(
+        &self,
+        _searcher: &Searcher,
+        _scoring_enabled: bool,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(self.clone()))
+    }
+}
+
+impl Weight for DocListQuery {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> tantivy::Result<Box<dyn Scorer>> {
+        let docs = self
+            .docs
+            .get(&reader.segment_id())
+            .cloned()
+            .unwrap_or_default();
+        Ok(Box::new(ConstScorer::new(DocListDocSet::new(docs), boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> tantivy::Result<Explanation> {
+        let is_match = self
+            .docs
+            .get(&reader.segment_id())
+            .map(|docs| docs.binary_search(&doc).is_ok())
+            .unwrap_or(false);
+        if !is_match {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({}) does not match",
+                doc
+            )));
+        }
+        Ok(Explanation::new("DocListQuery", 1.0))
+    }
+}
+
+struct DocListDocSet {
+    docs: Vec<DocId>,
+    cursor: usize,
+}
+
+impl DocListDocSet {
+    fn new(docs: Vec<DocId>) -> Self {
+        Self { docs, cursor: 0 }
+    }
+}
+
+impl DocSet for DocListDocSet {
+    fn advance(&mut self) -> DocId {
+        self.cursor += 1;
+        self.doc()
+    }
+
+    fn doc(&self) -> DocId {
+        self.docs.get(self.cursor).copied().unwrap_or(TERMINATED)
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.docs.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::Count;
+    use tantivy::query::TermQuery;
+    use tantivy::schema::{Schema, STRING};
+    use tantivy::{doc, Term};
+
+    use super::*;
+
+    #[test]
+    fn test_doc_id_deduplicator() {
+        let mut schema_builder = Schema::builder();
+        let doc_id_field = schema_builder.add_text_field("event_id", STRING);
+        let mut deduplicator = DocIdDeduplicator::new(doc_id_field, NonZeroUsize::new(2).unwrap());
+
+        assert!(!deduplicator.is_duplicate(0, &doc!(doc_id_field => "a")));
+        assert!(deduplicator.is_duplicate(0, &doc!(doc_id_field => "a")));
+        assert!(!deduplicator.is_duplicate(1, &doc!(doc_id_field => "a")));
+        assert!(!deduplicator.is_duplicate(0, &doc!()));
+        assert!(!deduplicator.is_duplicate(0, &doc!()));
+
+        // `a` is evicted from the cache of partition 0.
+        assert!(!deduplicator.is_duplicate(0, &doc!(doc_id_field => "b")));
+        assert!(!deduplicator.is_duplicate(0, &doc!(doc_id_field => "c")));
+        assert!(!deduplicator.is_duplicate(0, &doc!(doc_id_field => "a")));
+    }
+
+    #[test]
+    fn test_find_and_delete_duplicate_docs() -> anyhow::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let doc_id_field = schema_builder.add_text_field("event_id", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 3_000_000)?;
+        index_writer.add_document(doc!(doc_id_field => "a"))?;
+        index_writer.add_document(doc!(doc_id_field => "b"))?;
+        index_writer.add_document(doc!(doc_id_field => "a"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(doc_id_field => "b"))?;
+        index_writer.add_document(doc!(doc_id_field => "c"))?;
+        index_writer.commit()?;
+
+        let duplicate_docs = find_duplicate_docs(&index, doc_id_field)?;
+        let num_duplicate_docs: usize = duplicate_docs.values().map(Vec::len).sum();
+        assert_eq!(num_duplicate_docs, 2);
+
+        index_writer.delete_query(Box::new(DocListQuery::new(duplicate_docs)))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.num_docs(), 3);
+        for doc_id in ["a", "b", "c"] {
+            let term_query = TermQuery::new(
+                Term::from_field_text(doc_id_field, doc_id),
+                IndexRecordOption::Basic,
+            );
+            assert_eq!(searcher.search(&term_query, &Count)?, 1);
+        }
+        assert!(find_duplicate_docs(&index, doc_id_field)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,373 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Transformation of the documents of a source before they are parsed by the doc mapper.
+
+use std::str::FromStr;
+
+use quickwit_config::TransformOperation;
+use quickwit_doc_mapper::{DocParsingError, GrokPattern, StrptimeParser};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use time::format_description::well_known::Rfc3339;
+
+enum CompiledOperation {
+    Rename {
+        from: Vec<String>,
+        to: Vec<String>,
+    },
+    Drop {
+        fields: Vec<Vec<String>>,
+    },
+    ParseTimestamp {
+        field: Vec<String>,
+        parser: StrptimeParser,
+    },
+    DiscardIf {
+        field: Vec<String>,
+        equals: String,
+    },
+    Grok {
+        field: Vec<String>,
+        patterns: Vec<GrokPattern>,
+    },
+}
+
+/// Applies the transform operations of a source to its documents.
+pub(crate) struct DocTransform {
+    operations: Vec<CompiledOperation>,
+}
+
+impl DocTransform {
+    pub fn try_new(operations: &[TransformOperation]) -> anyhow::Result<Self> {
+        let operations = operations
+            .iter()
+            .map(|operation| {
+                let compiled_operation = match operation {
+                    TransformOperation::Rename { from, to } => CompiledOperation::Rename {
+                        from: split_field_path(from),
+                        to: split_field_path(to),
+                    },
+                    TransformOperation::Drop { fields } => CompiledOperation::Drop {
+                        fields: fields.iter().map(|field| split_field_path(field)).collect(),
+                    },
+                    TransformOperation::ParseTimestamp { field, format } => {
+                        CompiledOperation::ParseTimestamp {
+                            field: split_field_path(field),
+                            parser: StrptimeParser::from_str(format)
+                                .map_err(|error| anyhow::anyhow!(error))?,
+                        }
+                    }
+                    TransformOperation::DiscardIf { field, equals } => {
+                        CompiledOperation::DiscardIf {
+                            field: split_field_path(field),
+                            equals: equals.clone(),
+                        }
+                    }
+                    TransformOperation::Grok {
+                        field,
+                        patterns,
+                        pattern_definitions,
+                    } => CompiledOperation::Grok {
+                        field: split_field_path(field),
+                        patterns: patterns
+                            .iter()
+                            .map(|pattern| GrokPattern::new(pattern, pattern_definitions))
+                            .collect::<anyhow::Result<_>>()?,
+                    },
+                };
+                Ok(compiled_operation)
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { operations })
+    }
+
+    /// Transforms the JSON document. Returns `None` if the document is discarded.
+    pub fn apply(&self, doc_json: &[u8]) -> Result<Option<Vec<u8>>, DocParsingError> {
+        let mut doc = match serde_json::from_slice::<JsonValue>(doc_json) {
+            Ok(JsonValue::Object(doc)) => doc,
+            Ok(_) => {
+                return Err(DocParsingError::NotJsonObject(
+                    "The document is not a JSON object.".to_string(),
+                ))
+            }
+            Err(error) => return Err(DocParsingError::NotJsonObject(error.to_string())),
+        };
+        for operation in &self.operations {
+            match operation {
+                CompiledOperation::Rename { from, to } => {
+                    if let Some(value) = remove_field(&mut doc, from) {
+                        insert_field(&mut doc, to, value)?;
+                    }
+                }
+                CompiledOperation::Drop { fields } => {
+                    for field in fields {
+                        remove_field(&mut doc, field);
+                    }
+                }
+                CompiledOperation::ParseTimestamp { field, parser } => {
+                    if let Some(value) = get_field_mut(&mut doc, field) {
+                        *value = parse_timestamp(field, value, parser)?;
+                    }
+                }
+                CompiledOperation::DiscardIf { field, equals } => {
+                    let is_equal = match get_field_mut(&mut doc, field) {
+                        Some(JsonValue::String(value)) => value == equals,
+                        Some(value @ JsonValue::Number(_)) | Some(value @ JsonValue::Bool(_)) => {
+                            value.to_string() == *equals
+                        }
+                        _ => false,
+                    };
+                    if is_equal {
+                        return Ok(None);
+                    }
+                }
+                CompiledOperation::Grok { field, patterns } => {
+                    if let Some(value) = get_field_mut(&mut doc, field) {
+                        let text = value.as_str().map(str::to_string).ok_or_else(|| {
+                            DocParsingError::ValueError(
+                                field.join("."),
+                                "Expected a string.".to_string(),
+                            )
+                        })?;
+                        let captured_fields = patterns
+                            .iter()
+                            .find_map(|pattern| pattern.captures(&text))
+                            .ok_or_else(|| {
+                                DocParsingError::ValueError(
+                                    field.join("."),
+                                    "The value does not match any grok pattern.".to_string(),
+                                )
+                            })?;
+                        for (field_name, captured_value) in captured_fields {
+                            insert_field(&mut doc, &split_field_path(field_name), captured_value)?;
+                        }
+                    }
+                }
+            }
+        }
+        let transformed_doc_json =
+            serde_json::to_vec(&doc).expect("Serializing a JSON object should never fail.");
+        Ok(Some(transformed_doc_json))
+    }
+}
+
+pub(crate) fn split_field_path(field_path: &str) -> Vec<String> {
+    field_path.split('.').map(str::to_string).collect()
+}
+
+fn get_field_mut<'a>(
+    doc: &'a mut JsonMap<String, JsonValue>,
+    path: &[String],
+) -> Option<&'a mut JsonValue> {
+    let (last, parents) = path.split_last()?;
+    let mut object = doc;
+    for parent in parents {
+        object = object.get_mut(parent)?.as_object_mut()?;
+    }
+    object.get_mut(last)
+}
+
+fn remove_field(doc: &mut JsonMap<String, JsonValue>, path: &[String]) -> Option<JsonValue> {
+    let (last, parents) = path.split_last()?;
+    let mut object = doc;
+    for parent in parents {
+        object = object.get_mut(parent)?.as_object_mut()?;
+    }
+    object.remove(last)
+}
+
+pub(crate) fn insert_field(
+    doc: &mut JsonMap<String, JsonValue>,
+    path: &[String],
+    value: JsonValue,
+) -> Result<(), DocParsingError> {
+    let (last, parents) = match path.split_last() {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+    let mut object = doc;
+    for parent in parents {
+        object = object
+            .entry(parent.clone())
+            .or_insert_with(|| JsonValue::Object(JsonMap::new()))
+            .as_object_mut()
+            .ok_or_else(|| {
+                DocParsingError::ValueError(
+                    path.join("."),
+                    format!("Field `{parent}` is not an object."),
+                )
+            })?;
+    }
+    object.insert(last.clone(), value);
+    Ok(())
+}
+
+fn parse_timestamp(
+    path: &[String],
+    value: &JsonValue,
+    parser: &StrptimeParser,
+) -> Result<JsonValue, DocParsingError> {
+    let date_time_str = value.as_str().ok_or_else(|| {
+        DocParsingError::ValueError(path.join("."), "Expected a string.".to_string())
+    })?;
+    let date_time = parser
+        .parse_date_time(date_time_str)
+        .map_err(|error| DocParsingError::ValueError(path.join("."), error))?;
+    let rfc3339_date_time = date_time
+        .format(&Rfc3339)
+        .map_err(|error| DocParsingError::ValueError(path.join("."), error.to_string()))?;
+    Ok(JsonValue::String(rfc3339_date_time))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn transform(
+        operations_yaml: &str,
+        doc: JsonValue,
+    ) -> Result<Option<JsonValue>, DocParsingError> {
+        let operations: Vec<TransformOperation> = serde_yaml::from_str(operations_yaml).unwrap();
+        let doc_transform = DocTransform::try_new(&operations).unwrap();
+        let doc_json = serde_json::to_vec(&doc).unwrap();
+        let transformed_doc_json_opt = doc_transform.apply(&doc_json)?;
+        Ok(transformed_doc_json_opt
+            .map(|transformed_doc_json| serde_json::from_slice(&transformed_doc_json).unwrap()))
+    }
+
+    #[test]
+    fn test_doc_transform_rename_and_drop() {
+        let operations_yaml = r#"
+            - rename:
+                from: msg
+                to: body
+            - rename:
+                from: attributes.level
+                to: severity.text
+            - drop:
+                fields: [debug, attributes.unused, missing]
+        "#;
+        let doc = json!({
+            "msg": "hello",
+            "debug": true,
+            "attributes": {"level": "info", "unused": 1, "host": "localhost"}
+        });
+        let transformed_doc = transform(operations_yaml, doc).unwrap().unwrap();
+        assert_eq!(
+            transformed_doc,
+            json!({
+                "body": "hello",
+                "attributes": {"host": "localhost"},
+                "severity": {"text": "info"}
+            })
+        );
+        let doc = json!({"severity": "info", "attributes": {"level": "warn"}});
+        let error = transform(operations_yaml, doc).unwrap_err();
+        assert!(matches!(error, DocParsingError::ValueError(field, _) if field == "severity.text"));
+    }
+
+    #[test]
+    fn test_doc_transform_parse_timestamp() {
+        let operations_yaml = r#"
+            - parse_timestamp:
+                field: ts
+                format: "%Y-%m-%d %H:%M:%S"
+        "#;
+        let transformed_doc = transform(operations_yaml, json!({"ts": "2022-10-05 13:12:11"}))
+            .unwrap()
+            .unwrap();
+        assert_eq!(transformed_doc, json!({"ts": "2022-10-05T13:12:11Z"}));
+
+        let error = transform(operations_yaml, json!({"ts": "yesterday"})).unwrap_err();
+        assert!(matches!(error, DocParsingError::ValueError(field, _) if field == "ts"));
+
+        let error = transform(operations_yaml, json!({"ts": 1665000000})).unwrap_err();
+        assert!(matches!(error, DocParsingError::ValueError(field, _) if field == "ts"));
+    }
+
+    #[test]
+    fn test_doc_transform_discard_if() {
+        let operations_yaml = r#"
+            - discard_if:
+                field: level
+                equals: debug
+            - discard_if:
+                field: status
+                equals: "200"
+        "#;
+        assert!(transform(operations_yaml, json!({"level": "debug"}))
+            .unwrap()
+            .is_none());
+        assert!(transform(operations_yaml, json!({"status": 200}))
+            .unwrap()
+            .is_none());
+        assert!(
+            transform(operations_yaml, json!({"level": "info", "status": 500}))
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_doc_transform_grok() {
+        let operations_yaml = r#"
+            - grok:
+                field: message
+                patterns:
+                    - "%{SYSLOGLINE}"
+                    - "%{IP:client.ip} %{WORD:client.method} %{INT:status:int}"
+        "#;
+        let transformed_doc = transform(
+            operations_yaml,
+            json!({"message": "55.3.244.1 GET 200", "client": {"name": "curl"}}),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            transformed_doc,
+            json!({
+                "message": "55.3.244.1 GET 200",
+                "client": {"name": "curl", "ip": "55.3.244.1", "method": "GET"},
+                "status": 200
+            })
+        );
+        let error = transform(operations_yaml, json!({"message": "not a log line"})).unwrap_err();
+        assert!(matches!(error, DocParsingError::ValueError(field, _) if field == "message"));
+
+        let transformed_doc = transform(operations_yaml, json!({"body": "55.3.244.1 GET 200"}))
+            .unwrap()
+            .unwrap();
+        assert_eq!(transformed_doc, json!({"body": "55.3.244.1 GET 200"}));
+    }
+
+    #[test]
+    fn test_doc_transform_invalid_json() {
+        let doc_transform = DocTransform::try_new(&[]).unwrap();
+        assert!(matches!(
+            doc_transform.apply(b"{"),
+            Err(DocParsingError::NotJsonObject(_))
+        ));
+        assert!(matches!(
+            doc_transform.apply(b"[]"),
+            Err(DocParsingError::NotJsonObject(_))
+        ));
+    }
+}
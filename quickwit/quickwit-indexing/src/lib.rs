@@ -21,6 +21,7 @@ use std::sync::Arc;
 
 use itertools::Itertools;
 use quickwit_actors::{Mailbox, Universe};
+use quickwit_common::disk_space::start_disk_space_monitor;
 use quickwit_config::QuickwitConfig;
 use quickwit_ingest_api::{get_ingest_api_service, QUEUES_DIR_NAME};
 use quickwit_metastore::Metastore;
@@ -37,13 +38,18 @@ pub use crate::split_store::{get_tantivy_directory_from_split_bundle, IndexingSp
 
 pub mod actors;
 mod controlled_directory;
+mod csv_doc_parser;
+mod dedup;
+mod doc_transform;
 pub mod merge_policy;
 mod metrics;
 pub mod models;
+pub mod scheduler;
 pub mod source;
 mod split_store;
 #[cfg(any(test, feature = "testsuite"))]
 mod test_utils;
+mod time_slicing;
 
 #[cfg(any(test, feature = "testsuite"))]
 pub use test_utils::{mock_split, mock_split_meta, TestSandbox};
@@ -86,6 +92,13 @@ pub async fn start_indexing_service(
     }
     // Spawn Ingest Api garbage collector.
     let queues_dir_path = config.data_dir_path.join(QUEUES_DIR_NAME);
+    start_disk_space_monitor(
+        vec![
+            ("data", config.data_dir_path.clone()),
+            ("queues", queues_dir_path.clone()),
+        ],
+        config.indexer_config.min_free_disk_space.get_bytes() as u64,
+    );
     let ingest_api_service = get_ingest_api_service(&queues_dir_path).await?;
     let ingest_api_garbage_collector =
         IngestApiGarbageCollector::new(metastore, ingest_api_service, indexing_service.clone());
@@ -40,6 +40,8 @@ use crate::new_split_id;
 pub enum MergeOperationType {
     Merge,
     DeleteAndMerge,
+    /// Rewrites a single split with the split and tantivy index formats of the running version.
+    Upgrade,
 }
 
 impl fmt::Display for MergeOperationType {
@@ -81,6 +83,17 @@ impl MergeOperation {
         }
     }
 
+    pub fn new_upgrade_operation(split: SplitMetadata) -> Self {
+        let merge_split_id = new_split_id();
+        let merge_parent_span = info_span!("upgrade", merge_split_id=%merge_split_id, split_ids=?split.split_id(), typ=%MergeOperationType::Upgrade);
+        Self {
+            merge_parent_span,
+            merge_split_id,
+            splits: vec![split],
+            operation_type: MergeOperationType::Upgrade,
+        }
+    }
+
     pub fn splits_as_slice(&self) -> &[SplitMetadata] {
         self.splits.as_slice()
     }
@@ -320,7 +333,7 @@ pub mod tests {
             pipeline_ord: 0,
         };
         let split_attrs = merge_split_attrs(merged_split_id, &pipeline_id, splits);
-        create_split_metadata(&split_attrs, tags, 0..0)
+        create_split_metadata(&split_attrs, tags, Default::default(), 0..0)
     }
 
     fn apply_merge(
@@ -18,13 +18,32 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use once_cell::sync::Lazy;
-use quickwit_common::metrics::{new_counter_vec, new_gauge_vec, IntCounterVec, IntGaugeVec};
+use quickwit_common::metrics::{
+    new_counter, new_counter_vec, new_exponential_histogram_vec, new_gauge_vec, HistogramVec,
+    IntCounter, IntCounterVec, IntGaugeVec,
+};
 
 pub struct IndexerMetrics {
     pub processed_docs_total: IntCounterVec,
     pub processed_bytes: IntCounterVec,
     pub available_concurrent_upload_permits: IntGaugeVec,
     pub ongoing_merge_operations: IntGaugeVec,
+    pub pipeline_num_docs: IntGaugeVec,
+    pub pipeline_num_invalid_docs: IntGaugeVec,
+    pub pipeline_num_published_splits: IntGaugeVec,
+    pub pipeline_uploaded_num_bytes: IntGaugeVec,
+    pub pipeline_pending_splits: IntGaugeVec,
+    pub partition_docs_total: IntCounterVec,
+    pub partition_bytes_total: IntCounterVec,
+    pub partition_splits_total: IntCounterVec,
+    pub partition_split_num_docs: HistogramVec,
+    pub partition_split_num_bytes: HistogramVec,
+    pub parquet_sidecars_total: IntCounterVec,
+    pub ingest_api_gc_passes_total: IntCounter,
+    pub ingest_api_gc_deleted_queues_total: IntCounter,
+    pub ingest_api_gc_delete_failures_total: IntCounter,
+    pub ingest_api_gc_truncated_records_total: IntCounter,
+    pub ingest_api_gc_truncate_failures_total: IntCounter,
 }
 
 impl Default for IndexerMetrics {
@@ -56,6 +75,109 @@ impl Default for IndexerMetrics {
                 "quickwit_indexing",
                 &["index", "source"],
             ),
+            pipeline_num_docs: new_gauge_vec(
+                "pipeline_num_docs",
+                "Number of docs processed by an indexing pipeline, valid or not",
+                "quickwit_indexing",
+                &["index", "source", "pipeline_ord"],
+            ),
+            pipeline_num_invalid_docs: new_gauge_vec(
+                "pipeline_num_invalid_docs",
+                "Number of invalid docs (parsing errors, missing timestamps) processed by an \
+                 indexing pipeline",
+                "quickwit_indexing",
+                &["index", "source", "pipeline_ord"],
+            ),
+            pipeline_num_published_splits: new_gauge_vec(
+                "pipeline_num_published_splits",
+                "Number of splits published by an indexing pipeline",
+                "quickwit_indexing",
+                &["index", "source", "pipeline_ord"],
+            ),
+            pipeline_uploaded_num_bytes: new_gauge_vec(
+                "pipeline_uploaded_num_bytes",
+                "Number of bytes of the splits uploaded by an indexing pipeline",
+                "quickwit_indexing",
+                &["index", "source", "pipeline_ord"],
+            ),
+            pipeline_pending_splits: new_gauge_vec(
+                "pipeline_pending_splits",
+                "Number of splits created by an indexing pipeline and not published yet",
+                "quickwit_indexing",
+                &["index", "source", "pipeline_ord"],
+            ),
+            partition_docs_total: new_counter_vec(
+                "partition_docs_total",
+                "Number of docs indexed by partition of the indexes defining a partition key",
+                "quickwit_indexing",
+                &["index", "source", "partition"],
+            ),
+            partition_bytes_total: new_counter_vec(
+                "partition_bytes_total",
+                "Number of bytes of the docs indexed by partition of the indexes defining a \
+                 partition key",
+                "quickwit_indexing",
+                &["index", "source", "partition"],
+            ),
+            partition_splits_total: new_counter_vec(
+                "partition_splits_total",
+                "Number of splits created by the indexers by partition of the indexes defining a \
+                 partition key",
+                "quickwit_indexing",
+                &["index", "source", "partition"],
+            ),
+            partition_split_num_docs: new_exponential_histogram_vec(
+                "partition_split_num_docs",
+                "Number of docs of the splits created by the indexers by partition of the indexes \
+                 defining a partition key",
+                "quickwit_indexing",
+                &["index", "source", "partition"],
+                1_000.0,
+                4.0,
+                10,
+            ),
+            partition_split_num_bytes: new_exponential_histogram_vec(
+                "partition_split_num_bytes",
+                "Number of bytes of the docs of the splits created by the indexers by partition \
+                 of the indexes defining a partition key",
+                "quickwit_indexing",
+                &["index", "source", "partition"],
+                1_000_000.0,
+                4.0,
+                10,
+            ),
+            parquet_sidecars_total: new_counter_vec(
+                "parquet_sidecars_total",
+                "Number of Parquet sidecar files by index and status in [written, deleted, failed]",
+                "quickwit_indexing",
+                &["index", "status"],
+            ),
+            ingest_api_gc_passes_total: new_counter(
+                "ingest_api_gc_passes_total",
+                "Number of passes performed by the ingest API queues garbage collector",
+                "quickwit_indexing",
+            ),
+            ingest_api_gc_deleted_queues_total: new_counter(
+                "ingest_api_gc_deleted_queues_total",
+                "Number of ingest API queues of deleted indexes dropped by the garbage collector",
+                "quickwit_indexing",
+            ),
+            ingest_api_gc_delete_failures_total: new_counter(
+                "ingest_api_gc_delete_failures_total",
+                "Number of failures to drop the ingest API queue of a deleted index",
+                "quickwit_indexing",
+            ),
+            ingest_api_gc_truncated_records_total: new_counter(
+                "ingest_api_gc_truncated_records_total",
+                "Number of indexed records deleted from the ingest API queues once past their \
+                 retention period",
+                "quickwit_indexing",
+            ),
+            ingest_api_gc_truncate_failures_total: new_counter(
+                "ingest_api_gc_truncate_failures_total",
+                "Number of failures to apply the retention policy of an ingest API queue",
+                "quickwit_indexing",
+            ),
         }
     }
 }
@@ -20,6 +20,7 @@
 use std::fmt;
 use std::path::Path;
 
+use byte_unit::Byte;
 use quickwit_common::io::IoControls;
 use quickwit_metastore::checkpoint::IndexCheckpointDelta;
 use tantivy::directory::MmapDirectory;
@@ -80,6 +81,7 @@ impl IndexedSplitBuilder {
         last_delete_opstamp: u64,
         scratch_directory: ScratchDirectory,
         index_builder: IndexBuilder,
+        memory_budget: Byte,
         io_controls: IoControls,
     ) -> anyhow::Result<Self> {
         // We avoid intermediary merge, and instead merge all segments in the packager.
@@ -94,8 +96,10 @@ impl IndexedSplitBuilder {
 
         let controlled_directory = ControlledDirectory::new(box_mmap_directory, io_controls);
 
-        let index_writer =
-            index_builder.single_segment_index_writer(controlled_directory.clone(), 10_000_000)?;
+        let index_writer = index_builder.single_segment_index_writer(
+            controlled_directory.clone(),
+            memory_budget.get_bytes() as usize,
+        )?;
         Ok(Self {
             split_attrs: SplitAttrs {
                 pipeline_id,
@@ -28,6 +28,7 @@ use tokio::fs;
 use super::ScratchDirectory;
 
 const SCRATCH: &str = "scratch";
+const MERGE_PROGRESS: &str = "merge-progress";
 
 /// Root of an [`IndexingDirectory`].
 enum Root {
@@ -40,12 +41,13 @@ enum Root {
 
 /// An indexing directory is created in the data directory on the local file system for each index
 /// at the following location: `<data dir>/indexing/<index ID>/<source ID>`.
-/// The indexing directory consists of two directories:
+/// The indexing directory consists of three directories:
 /// - a scratch directory that stores temporary intermediate files
 /// - a cache directory that stores frequently accessed data structures
-/// While the scratch directory is emptied upon restart, the cache directory is not, and it is
-/// the responsability of the users of this folder to properly manage the lifecycle of the data
-/// that they write to it.
+/// - a merge progress directory that stores the output of the completed steps of large merges
+/// While the scratch directory is emptied upon restart, the other directories are not, and it is
+/// the responsability of the users of these folders to properly manage the lifecycle of the data
+/// that they write to them.
 #[derive(Clone)]
 pub struct IndexingDirectory {
     inner: Arc<InnerIndexingDirectory>,
@@ -110,6 +112,12 @@ impl IndexingDirectory {
         &self.inner.scratch_directory
     }
 
+    /// Returns the path of the directory storing the output of the completed steps of resumable
+    /// merges.
+    pub fn merge_progress_path(&self) -> PathBuf {
+        self.path().join(MERGE_PROGRESS)
+    }
+
     pub fn path(&self) -> &Path {
         match &self.inner.root {
             Root::Dir(root) => root,
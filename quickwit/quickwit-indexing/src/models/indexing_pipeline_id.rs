@@ -17,7 +17,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+use serde::Serialize;
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize)]
 pub struct IndexingPipelineId {
     pub index_id: String,
     pub source_id: String,
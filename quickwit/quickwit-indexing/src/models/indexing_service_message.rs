@@ -17,9 +17,14 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+
 use quickwit_config::SourceConfig;
+use quickwit_metastore::checkpoint::{PartitionId, Position};
+use serde::{Deserialize, Serialize};
 
-use super::IndexingPipelineId;
+use super::{IndexingPipelineId, IndexingStatistics};
+use crate::scheduler::IndexingTask;
 
 #[derive(Debug)]
 pub struct SpawnPipelines {
@@ -61,7 +66,108 @@ pub struct ObservePipeline {
     pub pipeline_id: IndexingPipelineId,
 }
 
+/// Observes the indexing pipelines running on the node.
+#[derive(Debug)]
+pub struct ObservePipelines;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct IndexingPipelineStatus {
+    #[serde(flatten)]
+    pub pipeline_id: IndexingPipelineId,
+    pub statistics: IndexingStatistics,
+}
+
+/// Restarts an indexing pipeline running on the node: its actors are terminated and a new
+/// generation of the pipeline is spawned.
+#[derive(Clone, Debug)]
+pub struct RestartPipeline {
+    pub index_id: String,
+    pub source_id: String,
+    pub pipeline_ord: usize,
+}
+
 #[derive(Debug)]
 pub struct SpawnMergePipeline {
     pub pipeline_id: IndexingPipelineId,
 }
+
+/// Shuts down the running pipelines of a source, then respawns them from the source config
+/// stored in the metastore if the source still exists and is enabled. This applies the changes
+/// made to a source config without restarting the indexing service.
+#[derive(Clone, Debug)]
+pub struct ReloadSourcePipelines {
+    pub index_id: String,
+    pub source_id: String,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ReloadSourcePipelinesResponse {
+    pub num_shutdown_pipelines: usize,
+    pub num_spawned_pipelines: usize,
+}
+
+/// Returns the checkpoint of a source, as recorded in the metastore.
+#[derive(Clone, Debug)]
+pub struct GetSourceCheckpoint {
+    pub index_id: String,
+    pub source_id: String,
+}
+
+/// Forcibly resets the checkpoint of a source, then reloads the pipelines of the source so that
+/// they resume from the new checkpoint. The positions of the checkpoint may move backward.
+///
+/// The publish tokens of the source are renewed beforehand, which prevents the pipelines of the
+/// source running on other nodes from publishing on top of the new checkpoint. The checkpoint of a
+/// source scheduled by the control plane can only be reset while the source is disabled.
+#[derive(Clone, Debug)]
+pub struct ResetSourceCheckpoint {
+    pub index_id: String,
+    pub source_id: String,
+    pub target: CheckpointResetTarget,
+}
+
+/// Positions a source checkpoint is reset to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CheckpointResetTarget {
+    /// Clears the checkpoint: the source is consumed again from the beginning.
+    Earliest,
+    /// Moves every partition to the position of its last record: the records not indexed yet are
+    /// skipped. Only supported by the sources able to report their end positions.
+    Latest,
+    /// Moves a single partition to the given position. The other partitions are left untouched.
+    Position {
+        partition_id: PartitionId,
+        position: Position,
+    },
+}
+
+/// Forces the merge of the splits of an index, regardless of the merge policy. See
+/// [`crate::models::ForceMerge`].
+#[derive(Debug)]
+pub struct MergeSplits {
+    pub index_id: String,
+    pub split_ids_opt: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MergeSplitsResponse {
+    /// Planned merge operations: maps the ID of each merged split to the IDs of its input splits.
+    pub merge_operations: BTreeMap<String, Vec<String>>,
+    /// IDs of the requested splits that were not merged because they are unknown to the merge
+    /// planners of this node, mature, already being merged, or alone in their partition.
+    pub skipped_split_ids: Vec<String>,
+}
+
+/// Applies the indexing tasks assigned to the node by the indexing plan: spawns the pipelines of
+/// the tasks that are not running and shuts down the pipelines of the previously assigned tasks
+/// that are no longer part of the plan. See [`crate::scheduler`].
+#[derive(Clone, Debug)]
+pub struct ApplyIndexingPlan {
+    pub indexing_tasks: Vec<IndexingTask>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ApplyIndexingPlanResponse {
+    pub num_shutdown_pipelines: usize,
+    pub num_spawned_pipelines: usize,
+}
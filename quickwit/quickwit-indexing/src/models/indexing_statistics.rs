@@ -46,6 +46,8 @@ pub struct IndexingStatistics {
     pub generation: usize,
     /// Number of successive pipeline spawn attempts.
     pub num_spawn_attempts: usize,
+    /// Last error that made the pipeline fail or prevented it from spawning.
+    pub last_error: Option<String>,
 }
 
 impl IndexingStatistics {
@@ -56,16 +58,30 @@ impl IndexingStatistics {
         uploader_counters: &UploaderCounters,
         publisher_counters: &PublisherCounters,
     ) -> Self {
-        self.num_docs += doc_processor_counters.num_processed_docs();
-        self.num_invalid_docs += doc_processor_counters.num_invalid_docs();
         self.num_local_splits += indexer_counters.num_splits_emitted;
-        self.total_bytes_processed += doc_processor_counters.overall_num_bytes;
         self.num_staged_splits += uploader_counters.num_staged_splits.load(Ordering::SeqCst);
         self.num_uploaded_splits += uploader_counters.num_uploaded_splits.load(Ordering::SeqCst);
+        self.total_size_splits += uploader_counters.num_uploaded_bytes.load(Ordering::SeqCst);
         self.num_published_splits += publisher_counters.num_published_splits;
+        self.add_doc_processor_counters(doc_processor_counters)
+    }
+
+    pub fn add_doc_processor_counters(
+        mut self,
+        doc_processor_counters: &DocProcessorCounters,
+    ) -> Self {
+        self.num_docs += doc_processor_counters.num_processed_docs();
+        self.num_invalid_docs += doc_processor_counters.num_invalid_docs();
+        self.total_bytes_processed += doc_processor_counters.overall_num_bytes;
         self
     }
 
+    /// Number of splits created by the indexer and not published yet.
+    pub fn num_pending_splits(&self) -> u64 {
+        self.num_local_splits
+            .saturating_sub(self.num_published_splits)
+    }
+
     pub fn set_num_spawn_attempts(mut self, num_spawn_attempts: usize) -> Self {
         self.num_spawn_attempts = num_spawn_attempts;
         self
@@ -75,4 +91,9 @@ impl IndexingStatistics {
         self.generation = generation;
         self
     }
+
+    pub fn set_last_error(mut self, last_error: Option<String>) -> Self {
+        self.last_error = last_error;
+        self
+    }
 }
@@ -23,3 +23,12 @@ use quickwit_metastore::SplitMetadata;
 pub struct NewSplits {
     pub new_splits: Vec<SplitMetadata>,
 }
+
+/// Asks the merge planner to merge splits right away, regardless of the merge policy.
+///
+/// If `split_ids_opt` is `None`, all the young splits known to the merge planner are merged,
+/// partition by partition.
+#[derive(Clone, Debug)]
+pub struct ForceMerge {
+    pub split_ids_opt: Option<Vec<String>>,
+}
@@ -21,7 +21,7 @@ use std::sync::atomic::Ordering;
 
 use serde::Serialize;
 
-use crate::actors::{PublisherCounters, UploaderCounters};
+use crate::actors::{MergeExecutorCounters, PublisherCounters, UploaderCounters};
 
 /// A Struct to hold all merge statistical data.
 #[derive(Clone, Debug, Default, Serialize)]
@@ -30,6 +30,12 @@ pub struct MergeStatistics {
     pub num_uploaded_splits: u64,
     /// Number of published splits
     pub num_published_splits: u64,
+    /// Number of documents written by the merge executor, updated as merge steps complete.
+    pub num_merged_docs: u64,
+    /// Number of bytes written by the merge executor, updated as merge steps complete.
+    pub num_merged_bytes: u64,
+    /// Number of merge steps resumed from the output of an interrupted merge.
+    pub num_resumed_merge_steps: u64,
     /// Pipeline generation.
     pub generation: usize,
     /// Number of successive pipeline spawn attempts.
@@ -47,6 +53,22 @@ impl MergeStatistics {
         self
     }
 
+    pub fn add_merge_executor_counters(
+        mut self,
+        merge_executor_counters: &MergeExecutorCounters,
+    ) -> Self {
+        self.num_merged_docs += merge_executor_counters
+            .num_merged_docs
+            .load(Ordering::SeqCst);
+        self.num_merged_bytes += merge_executor_counters
+            .num_bytes_written
+            .load(Ordering::SeqCst);
+        self.num_resumed_merge_steps += merge_executor_counters
+            .num_resumed_merge_steps
+            .load(Ordering::SeqCst);
+        self
+    }
+
     pub fn set_num_spawn_attempts(mut self, num_spawn_attempts: usize) -> Self {
         self.num_spawn_attempts = num_spawn_attempts;
         self
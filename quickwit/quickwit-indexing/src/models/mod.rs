@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod csv_header;
 mod indexed_split;
 mod indexing_directory;
 mod indexing_pipeline_id;
@@ -33,23 +34,27 @@ mod raw_doc_batch;
 mod scratch_directory;
 mod split_attrs;
 
+pub use csv_header::CsvHeader;
 pub use indexed_split::{
     CommitTrigger, IndexedSplit, IndexedSplitBatch, IndexedSplitBatchBuilder, IndexedSplitBuilder,
 };
 pub use indexing_directory::{IndexingDirectory, WeakIndexingDirectory};
 pub use indexing_pipeline_id::IndexingPipelineId;
 pub use indexing_service_message::{
-    DetachPipeline, ObservePipeline, ShutdownPipeline, ShutdownPipelines, SpawnMergePipeline,
-    SpawnPipeline, SpawnPipelines,
+    ApplyIndexingPlan, ApplyIndexingPlanResponse, CheckpointResetTarget, DetachPipeline,
+    GetSourceCheckpoint, IndexingPipelineStatus, MergeSplits, MergeSplitsResponse, ObservePipeline,
+    ObservePipelines, ReloadSourcePipelines, ReloadSourcePipelinesResponse, ResetSourceCheckpoint,
+    RestartPipeline, ShutdownPipeline, ShutdownPipelines, SpawnMergePipeline, SpawnPipeline,
+    SpawnPipelines,
 };
 pub use indexing_statistics::IndexingStatistics;
-pub use merge_planner_message::NewSplits;
+pub use merge_planner_message::{ForceMerge, NewSplits};
 pub use merge_scratch::MergeScratch;
 pub use merge_statistics::MergeStatistics;
 pub use packaged_split::{PackagedSplit, PackagedSplitBatch};
 pub use prepared_doc::{PreparedDoc, PreparedDocBatch};
 pub use publish_lock::{NewPublishLock, PublishLock};
-pub use publisher_message::SplitsUpdate;
+pub use publisher_message::{PublishedSplits, SplitsUpdate};
 pub use raw_doc_batch::RawDocBatch;
 pub use scratch_directory::ScratchDirectory;
 pub use split_attrs::{create_split_metadata, SplitAttrs};
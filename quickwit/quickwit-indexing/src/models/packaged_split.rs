@@ -17,10 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
 
+use quickwit_doc_mapper::bloom_filter::SplitBloomFilters;
 use quickwit_metastore::checkpoint::IndexCheckpointDelta;
+use quickwit_metastore::SplitFieldStats;
 use tantivy::TrackedObject;
 use tracing::Span;
 
@@ -33,6 +35,8 @@ pub struct PackagedSplit {
     pub tags: BTreeSet<String>,
     pub split_files: Vec<std::path::PathBuf>,
     pub hotcache_bytes: Vec<u8>,
+    pub bloom_filters_opt: Option<SplitBloomFilters>,
+    pub field_stats: BTreeMap<String, SplitFieldStats>,
 }
 
 impl PackagedSplit {
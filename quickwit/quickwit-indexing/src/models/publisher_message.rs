@@ -42,6 +42,13 @@ pub struct SplitsUpdate {
     pub parent_span: Span,
 }
 
+/// Splits published by a publisher, sent to the actors maintaining data derived from the splits.
+#[derive(Clone, Debug)]
+pub struct PublishedSplits {
+    pub new_splits: Vec<SplitMetadata>,
+    pub replaced_split_ids: Vec<String>,
+}
+
 impl fmt::Debug for SplitsUpdate {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let new_split_ids: String = self
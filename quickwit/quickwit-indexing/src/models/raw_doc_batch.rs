@@ -19,16 +19,19 @@
 
 use std::fmt;
 
+use bytes::Bytes;
 use quickwit_metastore::checkpoint::SourceCheckpointDelta;
 
+/// A batch of raw documents. The documents are `Bytes` so that they can share the buffer they
+/// were read into, for instance the buffer of a batch fetched from the ingest API queue.
 #[derive(Clone, Default)]
 pub struct RawDocBatch {
-    pub docs: Vec<String>,
+    pub docs: Vec<Bytes>,
     pub checkpoint_delta: SourceCheckpointDelta,
 }
 
 impl RawDocBatch {
-    pub fn new(docs: Vec<String>, checkpoint_delta: SourceCheckpointDelta) -> Self {
+    pub fn new(docs: Vec<Bytes>, checkpoint_delta: SourceCheckpointDelta) -> Self {
         RawDocBatch {
             docs,
             checkpoint_delta,
@@ -17,11 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::ops::{Range, RangeInclusive};
 
-use quickwit_metastore::SplitMetadata;
+use quickwit_metastore::{
+    SplitAccessStats, SplitFieldStats, SplitMetadata, SplitStorageTier, SPLIT_FORMAT_VERSION,
+};
 use time::OffsetDateTime;
 
 use crate::models::IndexingPipelineId;
@@ -82,6 +84,7 @@ impl fmt::Debug for SplitAttrs {
 pub fn create_split_metadata(
     split_attrs: &SplitAttrs,
     tags: BTreeSet<String>,
+    field_stats: BTreeMap<String, SplitFieldStats>,
     footer_offsets: Range<u64>,
 ) -> SplitMetadata {
     SplitMetadata {
@@ -98,5 +101,10 @@ pub fn create_split_metadata(
         footer_offsets,
         delete_opstamp: split_attrs.delete_opstamp,
         num_merge_ops: split_attrs.num_merge_ops,
+        split_format_version: SPLIT_FORMAT_VERSION,
+        tantivy_index_format_version: tantivy::INDEX_FORMAT_VERSION,
+        storage_tier: SplitStorageTier::Hot,
+        access_stats: SplitAccessStats::default(),
+        field_stats,
     }
 }
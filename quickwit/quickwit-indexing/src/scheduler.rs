@@ -0,0 +1,410 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Placement of the indexing pipelines on the indexers of a cluster.
+//!
+//! The indexing scheduler, run by the control plane, assigns each pipeline of the sources of the
+//! cluster to an indexer according to the expected throughput of the sources and the indexing
+//! capacity of the indexers. The resulting [`IndexingPlan`] is gossiped to the indexers, which
+//! apply their share of the plan. Node-local sources, such as the ingest API, are not scheduled:
+//! their pipelines keep running on every indexer.
+//!
+//! When an indexer leaves the cluster, the scheduler reassigns its pipelines to the other indexers
+//! once the [`FAILOVER_GRACE_PERIOD`] has elapsed, and the new pipelines resume from the source
+//! checkpoints stored in the metastore. Each time a task is assigned to an indexer, the scheduler
+//! acquires a new publish token for the task from the metastore and hands it to the indexer along
+//! with the task. The metastore rejects the splits published with an older token, so an indexer
+//! that lost a task, for instance because it was partitioned from the cluster, cannot publish for
+//! it anymore, whether or not it received the new plan.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use quickwit_config::{KafkaPartitionAssignment, SourceConfig, SourceParams};
+use quickwit_metastore::IndexMetadata;
+use serde::{Deserialize, Serialize};
+
+/// Chitchat key under which the indexers advertise their indexing capacity.
+pub const INDEXING_CAPACITY_KEY: &str = "indexing_capacity";
+
+/// Chitchat key under which the indexing scheduler publishes the indexing plan.
+pub const INDEXING_PLAN_KEY: &str = "indexing_plan";
+
+/// Period during which the pipelines of an indexer that left the cluster stay assigned to it.
+pub const FAILOVER_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Throughput assumed for the sources that do not declare an expected throughput.
+const DEFAULT_SOURCE_THROUGHPUT: u64 = 5_000_000; // 5MB
+
+/// An indexing pipeline to run on an indexer.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct IndexingTask {
+    pub index_id: String,
+    pub source_id: String,
+    pub pipeline_ord: usize,
+    /// Publish token acquired by the scheduler when the task was assigned to its indexer. The
+    /// partition set of the token is the pipeline ordinal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_token_opt: Option<u64>,
+}
+
+impl IndexingTask {
+    /// Returns the task stripped of its publish token.
+    pub fn without_publish_token(&self) -> IndexingTask {
+        IndexingTask {
+            publish_token_opt: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Assignment of the indexing tasks to the indexers, keyed by node ID.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IndexingPlan {
+    pub indexing_tasks: BTreeMap<String, Vec<IndexingTask>>,
+}
+
+impl IndexingPlan {
+    /// Returns the indexing tasks assigned to a node.
+    pub fn indexing_tasks_for_node(&self, node_id: &str) -> Vec<IndexingTask> {
+        self.indexing_tasks
+            .get(node_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn num_indexing_tasks(&self) -> usize {
+        self.indexing_tasks.values().map(Vec::len).sum()
+    }
+}
+
+/// An indexer available for scheduling.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexerNode {
+    pub node_id: String,
+    /// Indexing throughput (bytes per second) the indexer can sustain.
+    pub capacity: u64,
+}
+
+/// A source whose pipelines are placed by the indexing scheduler.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SchedulableSource {
+    pub index_id: String,
+    pub source_id: String,
+    pub num_pipelines: usize,
+    /// Expected throughput (bytes per second) of the source.
+    pub throughput: u64,
+}
+
+impl SchedulableSource {
+    /// Expected throughput of each pipeline of the source.
+    fn pipeline_throughput(&self) -> u64 {
+        (self.throughput / self.num_pipelines.max(1) as u64).max(1)
+    }
+}
+
+/// Returns whether the pipelines of a source read data local to the node, like the ingest API
+/// queues or a file. Such pipelines run on every indexer and are not scheduled.
+pub fn is_node_local_source(source_config: &SourceConfig) -> bool {
+    matches!(
+        source_config.source_params,
+        SourceParams::File(_) | SourceParams::IngestApi | SourceParams::Vec(_)
+    )
+}
+
+/// Returns whether the pipelines of a source must be placed by the indexing scheduler. A Kafka
+/// source with the `static` partition assignment derives the partitions of a pipeline from its
+/// ordinal: without the scheduler, every indexer would consume the same partitions.
+pub fn requires_cluster_scheduling(source_config: &SourceConfig) -> bool {
+    matches!(
+        &source_config.source_params,
+        SourceParams::Kafka(params)
+            if params.partition_assignment == KafkaPartitionAssignment::Static
+    )
+}
+
+/// Lists the enabled sources of the indexes that must be placed by the indexing scheduler.
+pub fn list_schedulable_sources(index_metadatas: &[IndexMetadata]) -> Vec<SchedulableSource> {
+    index_metadatas
+        .iter()
+        .flat_map(|index_metadata| {
+            index_metadata
+                .sources
+                .values()
+                .filter(|source_config| {
+                    source_config.enabled && !is_node_local_source(source_config)
+                })
+                .map(|source_config| SchedulableSource {
+                    index_id: index_metadata.index_id.clone(),
+                    source_id: source_config.source_id.clone(),
+                    num_pipelines: source_config.num_pipelines().unwrap_or(1),
+                    throughput: source_config
+                        .expected_throughput
+                        .map(|throughput| throughput.get_bytes() as u64)
+                        .unwrap_or(DEFAULT_SOURCE_THROUGHPUT),
+                })
+        })
+        .collect()
+}
+
+struct NodeLoad {
+    capacity: u64,
+    load: u64,
+    sources: HashSet<(String, String)>,
+}
+
+impl NodeLoad {
+    fn fits(&self, task_load: u64) -> bool {
+        self.load + task_load <= self.capacity
+    }
+
+    fn assign(&mut self, task: &IndexingTask, task_load: u64) {
+        self.load += task_load;
+        self.sources
+            .insert((task.index_id.clone(), task.source_id.clone()));
+    }
+
+    fn runs_source(&self, task: &IndexingTask) -> bool {
+        self.sources
+            .contains(&(task.index_id.clone(), task.source_id.clone()))
+    }
+}
+
+/// Builds the indexing plan placing the pipelines of `sources` on `indexers`.
+///
+/// Tasks of the previous plan stay on their indexer as long as it is still part of the cluster and
+/// has enough capacity, so that membership changes only move the tasks of the indexers that left
+/// the cluster or became overloaded. The remaining tasks are placed, heaviest first, on the
+/// indexers with enough capacity, preferring the indexers that do not already run a pipeline of
+/// the same source, then the indexer with the lowest relative load after assignment. Indexers may
+/// be assigned more than their capacity when the cluster is undersized.
+///
+/// The tasks of the returned plan carry no publish token: acquiring them is up to the caller.
+pub fn build_indexing_plan(
+    indexers: &[IndexerNode],
+    sources: &[SchedulableSource],
+    previous_plan: &IndexingPlan,
+) -> IndexingPlan {
+    let mut plan = IndexingPlan::default();
+    if indexers.is_empty() {
+        return plan;
+    }
+    let mut task_loads: BTreeMap<IndexingTask, u64> = BTreeMap::new();
+    for source in sources {
+        for pipeline_ord in 0..source.num_pipelines {
+            let task = IndexingTask {
+                index_id: source.index_id.clone(),
+                source_id: source.source_id.clone(),
+                pipeline_ord,
+                publish_token_opt: None,
+            };
+            task_loads.insert(task, source.pipeline_throughput());
+        }
+    }
+    let mut node_loads: HashMap<&str, NodeLoad> = indexers
+        .iter()
+        .map(|indexer| {
+            let node_load = NodeLoad {
+                capacity: indexer.capacity,
+                load: 0,
+                sources: HashSet::new(),
+            };
+            (indexer.node_id.as_str(), node_load)
+        })
+        .collect();
+
+    for (node_id, previous_tasks) in &previous_plan.indexing_tasks {
+        let node_load = match node_loads.get_mut(node_id.as_str()) {
+            Some(node_load) => node_load,
+            None => continue,
+        };
+        for previous_task in previous_tasks {
+            let task = previous_task.without_publish_token();
+            let task_load = match task_loads.get(&task) {
+                Some(task_load) => *task_load,
+                None => continue,
+            };
+            if node_load.fits(task_load) {
+                node_load.assign(&task, task_load);
+                task_loads.remove(&task);
+                plan.indexing_tasks
+                    .entry(node_id.clone())
+                    .or_default()
+                    .push(task);
+            }
+        }
+    }
+    let mut remaining_tasks: Vec<(IndexingTask, u64)> = task_loads.into_iter().collect();
+    // Heaviest tasks first, ties broken by task for determinism.
+    remaining_tasks.sort_by(|(left_task, left_load), (right_task, right_load)| {
+        right_load
+            .cmp(left_load)
+            .then_with(|| left_task.cmp(right_task))
+    });
+    for (task, task_load) in remaining_tasks {
+        let (node_id, node_load) = node_loads
+            .iter_mut()
+            .min_by(|(left_node_id, left), (right_node_id, right)| {
+                let left_ratio = (left.load + task_load) as u128 * right.capacity.max(1) as u128;
+                let right_ratio = (right.load + task_load) as u128 * left.capacity.max(1) as u128;
+                right
+                    .fits(task_load)
+                    .cmp(&left.fits(task_load))
+                    .then_with(|| left.runs_source(&task).cmp(&right.runs_source(&task)))
+                    .then_with(|| left_ratio.cmp(&right_ratio))
+                    .then_with(|| left_node_id.cmp(right_node_id))
+            })
+            .expect("The list of indexers should not be empty.");
+        node_load.assign(&task, task_load);
+        plan.indexing_tasks
+            .entry(node_id.to_string())
+            .or_default()
+            .push(task);
+    }
+    for tasks in plan.indexing_tasks.values_mut() {
+        tasks.sort();
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexer(node_id: &str, capacity: u64) -> IndexerNode {
+        IndexerNode {
+            node_id: node_id.to_string(),
+            capacity,
+        }
+    }
+
+    fn source(source_id: &str, num_pipelines: usize, throughput: u64) -> SchedulableSource {
+        SchedulableSource {
+            index_id: "test-index".to_string(),
+            source_id: source_id.to_string(),
+            num_pipelines,
+            throughput,
+        }
+    }
+
+    fn num_tasks_per_node(plan: &IndexingPlan) -> BTreeMap<&str, usize> {
+        plan.indexing_tasks
+            .iter()
+            .map(|(node_id, tasks)| (node_id.as_str(), tasks.len()))
+            .collect()
+    }
+
+    #[test]
+    fn test_build_indexing_plan_without_indexers() {
+        let sources = vec![source("source-1", 2, 10)];
+        let plan = build_indexing_plan(&[], &sources, &IndexingPlan::default());
+        assert_eq!(plan, IndexingPlan::default());
+    }
+
+    #[test]
+    fn test_build_indexing_plan_spreads_pipelines() {
+        let indexers = vec![indexer("node-1", 100), indexer("node-2", 100)];
+        let sources = vec![source("source-1", 2, 40), source("source-2", 2, 40)];
+        let plan = build_indexing_plan(&indexers, &sources, &IndexingPlan::default());
+        assert_eq!(plan.num_indexing_tasks(), 4);
+        assert_eq!(
+            num_tasks_per_node(&plan),
+            BTreeMap::from_iter([("node-1", 2), ("node-2", 2)])
+        );
+        // The pipelines of a source are placed on different indexers.
+        for tasks in plan.indexing_tasks.values() {
+            assert_ne!(tasks[0].source_id, tasks[1].source_id);
+        }
+    }
+
+    #[test]
+    fn test_build_indexing_plan_accounts_for_capacity() {
+        let indexers = vec![indexer("node-1", 30), indexer("node-2", 90)];
+        let sources = vec![source("source-1", 4, 40)];
+        let plan = build_indexing_plan(&indexers, &sources, &IndexingPlan::default());
+        assert_eq!(
+            num_tasks_per_node(&plan),
+            BTreeMap::from_iter([("node-1", 1), ("node-2", 3)])
+        );
+    }
+
+    #[test]
+    fn test_build_indexing_plan_is_stable_on_membership_changes() {
+        let sources = vec![source("source-1", 3, 30), source("source-2", 3, 30)];
+        let indexers = vec![indexer("node-1", 100), indexer("node-2", 100)];
+        let plan = build_indexing_plan(&indexers, &sources, &IndexingPlan::default());
+
+        // Adding an indexer does not move the tasks that fit on their indexer.
+        let indexers = vec![
+            indexer("node-1", 100),
+            indexer("node-2", 100),
+            indexer("node-3", 100),
+        ];
+        let new_plan = build_indexing_plan(&indexers, &sources, &plan);
+        assert_eq!(new_plan, plan);
+
+        // Removing an indexer only moves its tasks.
+        let indexers = vec![indexer("node-1", 100), indexer("node-3", 100)];
+        let new_plan = build_indexing_plan(&indexers, &sources, &plan);
+        assert_eq!(new_plan.num_indexing_tasks(), 6);
+        assert!(!new_plan.indexing_tasks.contains_key("node-2"));
+        assert_eq!(
+            new_plan.indexing_tasks_for_node("node-1"),
+            plan.indexing_tasks_for_node("node-1")
+        );
+        assert_eq!(
+            new_plan.indexing_tasks_for_node("node-3"),
+            plan.indexing_tasks_for_node("node-2")
+        );
+    }
+
+    #[test]
+    fn test_build_indexing_plan_ignores_publish_tokens() {
+        let indexers = vec![indexer("node-1", 100), indexer("node-2", 100)];
+        let sources = vec![source("source-1", 2, 40)];
+        let plan = build_indexing_plan(&indexers, &sources, &IndexingPlan::default());
+
+        // The tasks of the previous plan keep their indexer, without their publish token.
+        let mut previous_plan = plan.clone();
+        for task in previous_plan.indexing_tasks.values_mut().flatten() {
+            task.publish_token_opt = Some(1);
+        }
+        let new_plan = build_indexing_plan(&indexers, &sources, &previous_plan);
+        assert_eq!(new_plan, plan);
+    }
+
+    #[test]
+    fn test_build_indexing_plan_drops_removed_sources() {
+        let indexers = vec![indexer("node-1", 100)];
+        let sources = vec![source("source-1", 1, 10), source("source-2", 1, 10)];
+        let plan = build_indexing_plan(&indexers, &sources, &IndexingPlan::default());
+        assert_eq!(plan.num_indexing_tasks(), 2);
+
+        let new_plan = build_indexing_plan(&indexers, &sources[..1], &plan);
+        assert_eq!(
+            new_plan.indexing_tasks_for_node("node-1"),
+            vec![IndexingTask {
+                index_id: "test-index".to_string(),
+                source_id: "source-1".to_string(),
+                pipeline_ord: 0,
+                publish_token_opt: None,
+            }]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! A backfill source drains a historical source, then switches to a live source.
+//!
+//! Both sources share the checkpoint of the backfill source. Before the historical source is
+//! consumed, the positions the live source has reached are recorded in that checkpoint, so that
+//! the live source later resumes right after them, without gaps nor duplicates. The phase of the
+//! backfill is recorded in the checkpoint as well, in a dedicated partition.
+
+use anyhow::bail;
+use quickwit_config::{BackfillSourceParams, SourceConfig, SourceParams};
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+
+/// Partition of the checkpoint of a backfill source recording the phase of the backfill.
+pub const BACKFILL_PHASE_PARTITION_ID: &str = "_backfill-phase";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BackfillPhase {
+    /// The boundary between the historical and live data has not been recorded yet.
+    NotStarted,
+    /// The historical source is being consumed.
+    Historical,
+    /// The historical source is drained and the live source is being consumed.
+    Live,
+}
+
+impl BackfillPhase {
+    pub fn from_checkpoint(checkpoint: &SourceCheckpoint) -> Self {
+        let phase_partition_id = PartitionId::from(BACKFILL_PHASE_PARTITION_ID);
+        match checkpoint.position_for_partition(&phase_partition_id) {
+            None | Some(Position::Beginning) => BackfillPhase::NotStarted,
+            Some(position) if *position < BackfillPhase::Live.position() => {
+                BackfillPhase::Historical
+            }
+            Some(_) => BackfillPhase::Live,
+        }
+    }
+
+    fn position(self) -> Position {
+        match self {
+            BackfillPhase::NotStarted => Position::Beginning,
+            BackfillPhase::Historical => Position::from(1u64),
+            BackfillPhase::Live => Position::from(2u64),
+        }
+    }
+
+    /// Returns the checkpoint delta moving the backfill from this phase to the next one.
+    fn transition_delta(self) -> SourceCheckpointDelta {
+        let next_phase = match self {
+            BackfillPhase::NotStarted => BackfillPhase::Historical,
+            BackfillPhase::Historical | BackfillPhase::Live => BackfillPhase::Live,
+        };
+        SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from(BACKFILL_PHASE_PARTITION_ID),
+            self.position(),
+            next_phase.position(),
+        )
+    }
+}
+
+/// Returns the checkpoint delta starting the backfill: it records the current positions of the
+/// live source and moves the backfill to the historical phase.
+pub async fn fetch_backfill_start_delta(
+    params: &BackfillSourceParams,
+) -> anyhow::Result<SourceCheckpointDelta> {
+    let mut delta = BackfillPhase::NotStarted.transition_delta();
+    for (partition_id, position) in fetch_live_end_positions(params.live.as_ref()).await? {
+        delta.record_partition_delta(partition_id, Position::Beginning, position)?;
+    }
+    Ok(delta)
+}
+
+/// Returns the checkpoint delta ending the historical phase of the backfill.
+pub fn backfill_completion_delta() -> SourceCheckpointDelta {
+    BackfillPhase::Historical.transition_delta()
+}
+
+/// Returns the config of the source to consume during the given phase of the backfill.
+pub fn backfill_phase_source_config(
+    source_config: &SourceConfig,
+    params: &BackfillSourceParams,
+    phase: BackfillPhase,
+) -> SourceConfig {
+    let source_params = match phase {
+        BackfillPhase::NotStarted | BackfillPhase::Historical => params.historical.as_ref(),
+        BackfillPhase::Live => params.live.as_ref(),
+    };
+    SourceConfig {
+        source_params: source_params.clone(),
+        ..source_config.clone()
+    }
+}
+
+async fn fetch_live_end_positions(
+    live_params: &SourceParams,
+) -> anyhow::Result<Vec<(PartitionId, Position)>> {
+    match live_params {
+        SourceParams::Kafka(_) => super::fetch_end_positions(live_params).await,
+        _ => bail!("Backfill source parameter `live` must be a `kafka` source."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backfill_phases() {
+        let mut checkpoint = SourceCheckpoint::default();
+        assert_eq!(
+            BackfillPhase::from_checkpoint(&checkpoint),
+            BackfillPhase::NotStarted
+        );
+        checkpoint
+            .try_apply_delta(BackfillPhase::NotStarted.transition_delta())
+            .unwrap();
+        assert_eq!(
+            BackfillPhase::from_checkpoint(&checkpoint),
+            BackfillPhase::Historical
+        );
+        checkpoint
+            .try_apply_delta(backfill_completion_delta())
+            .unwrap();
+        assert_eq!(
+            BackfillPhase::from_checkpoint(&checkpoint),
+            BackfillPhase::Live
+        );
+        // Completing the historical phase twice is rejected by the checkpoint.
+        checkpoint
+            .try_apply_delta(backfill_completion_delta())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_backfill_phase_source_config() {
+        let params = BackfillSourceParams {
+            historical: Box::new(SourceParams::file("data/history.json")),
+            live: Box::new(SourceParams::void()),
+        };
+        let source_config = SourceConfig {
+            source_id: "backfill-source".to_string(),
+            num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            enabled: true,
+            source_params: SourceParams::Backfill(params.clone()),
+        };
+        let historical_config =
+            backfill_phase_source_config(&source_config, &params, BackfillPhase::Historical);
+        assert_eq!(historical_config.source_id, "backfill-source");
+        assert_eq!(historical_config.source_type(), "file");
+
+        let live_config =
+            backfill_phase_source_config(&source_config, &params, BackfillPhase::Live);
+        assert_eq!(live_config.source_id, "backfill-source");
+        assert_eq!(live_config.source_type(), "void");
+    }
+}
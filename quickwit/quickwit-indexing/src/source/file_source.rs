@@ -17,15 +17,18 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, io};
 
 use anyhow::Context;
 use async_trait::async_trait;
-use quickwit_actors::{ActorExitStatus, Mailbox};
-use quickwit_config::FileSourceParams;
+use bytes::Bytes;
+use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
+use quickwit_config::{FileCleanupPolicy, FileSourceParams, SourceConfig, SourceInputFormat};
 use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
 use serde::Serialize;
 use tokio::fs::File;
@@ -33,12 +36,18 @@ use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncSeekExt, BufReader};
 use tracing::info;
 
 use crate::actors::DocProcessor;
-use crate::models::RawDocBatch;
-use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
+use crate::csv_doc_parser::CsvRecordFramer;
+use crate::models::{CsvHeader, RawDocBatch};
+use crate::source::{
+    Source, SourceActor, SourceContext, SourceExecutionContext, TypedSourceFactory,
+};
 
 /// Number of bytes after which a new batch is cut.
 pub(crate) const BATCH_NUM_BYTES_LIMIT: u64 = 500_000u64;
 
+/// Interval at which a watched directory is listed when all its files have been read.
+const DIRECTORY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Default, Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct FileSourceCounters {
     pub previous_offset: u64,
@@ -51,6 +60,184 @@ pub struct FileSource {
     params: FileSourceParams,
     counters: FileSourceCounters,
     reader: BufReader<Box<dyn AsyncRead + Send + Sync + Unpin>>,
+    /// Set when `filepath` points to a directory.
+    directory_opt: Option<WatchedDirectory>,
+    /// Splits the lines into records for the `csv` and `tsv` input formats.
+    csv_framer_opt: Option<CsvRecordFramer>,
+    /// Whether the first record of each file is a CSV header.
+    infer_csv_header: bool,
+    /// Whether the next record read is the CSV header of the current file.
+    reads_csv_header: bool,
+    /// CSV header of the current file, read separately when the file is resumed past it.
+    pending_csv_header_opt: Option<Bytes>,
+}
+
+/// Read state of a file of a watched directory.
+#[derive(Clone, Copy, Debug, Default)]
+struct WatchedFile {
+    /// Offset up to which the file has been read.
+    offset: u64,
+    /// Size of the file when it was last opened.
+    num_bytes_opt: Option<u64>,
+}
+
+/// A directory whose files are read one after the other, in lexicographical order. Each file is a
+/// partition of the source.
+struct WatchedDirectory {
+    directory_path: PathBuf,
+    pattern_opt: Option<String>,
+    cleanup_policy: FileCleanupPolicy,
+    archive_dir_opt: Option<PathBuf>,
+    files: BTreeMap<PathBuf, WatchedFile>,
+    current_filepath_opt: Option<PathBuf>,
+    num_cleaned_up_files: u64,
+}
+
+impl WatchedDirectory {
+    /// Opens the first file of the directory that has unread data, seeked to its read offset.
+    async fn open_next_file(&mut self) -> anyhow::Result<Option<(File, u64)>> {
+        let mut entries = tokio::fs::read_dir(&self.directory_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to list directory `{}`.",
+                    self.directory_path.display()
+                )
+            })?;
+        let mut candidates = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            if let Some(pattern) = &self.pattern_opt {
+                let file_name = entry.file_name();
+                let is_match = file_name
+                    .to_str()
+                    .map(|file_name| matches_pattern(pattern, file_name))
+                    .unwrap_or(false);
+                if !is_match {
+                    continue;
+                }
+            }
+            candidates.push((entry.path(), metadata.len()));
+        }
+        candidates.sort();
+
+        for (filepath, num_bytes) in candidates {
+            let watched_file = self.files.entry(filepath.clone()).or_default();
+            // A file that has not grown since it was last read only holds an incomplete document.
+            if num_bytes <= watched_file.offset || watched_file.num_bytes_opt == Some(num_bytes) {
+                continue;
+            }
+            watched_file.num_bytes_opt = Some(num_bytes);
+            let offset = watched_file.offset;
+
+            let mut file = File::open(&filepath)
+                .await
+                .with_context(|| format!("Failed to open source file `{}`.", filepath.display()))?;
+            file.seek(SeekFrom::Start(offset)).await?;
+            info!(filepath=%filepath.display(), offset=%offset, "Reading file.");
+            self.current_filepath_opt = Some(filepath);
+            return Ok(Some((file, offset)));
+        }
+        Ok(None)
+    }
+
+    fn close_current_file(&mut self, offset: u64) {
+        if let Some(filepath) = self.current_filepath_opt.take() {
+            self.files.entry(filepath).or_default().offset = offset;
+        }
+    }
+
+    /// Deletes or archives the files that have been read entirely and whose content has been
+    /// published.
+    async fn cleanup_published_files(
+        &mut self,
+        checkpoint: &SourceCheckpoint,
+    ) -> anyhow::Result<()> {
+        let archive_dir_opt = match self.cleanup_policy {
+            FileCleanupPolicy::Keep => return Ok(()),
+            FileCleanupPolicy::Delete => None,
+            FileCleanupPolicy::Archive => self.archive_dir_opt.as_deref(),
+        };
+        let mut cleaned_up_filepaths = Vec::new();
+
+        for (filepath, watched_file) in &self.files {
+            if watched_file.offset == 0 || self.current_filepath_opt.as_ref() == Some(filepath) {
+                continue;
+            }
+            let partition_id = PartitionId::from(filepath.to_string_lossy().to_string());
+            let published_offset = match checkpoint.position_for_partition(&partition_id) {
+                Some(Position::Offset(offset_str)) => offset_str.parse::<u64>()?,
+                _ => continue,
+            };
+            if published_offset < watched_file.offset {
+                continue;
+            }
+            // The file may have been appended to since it was last read.
+            let num_bytes = match tokio::fs::metadata(filepath).await {
+                Ok(metadata) => metadata.len(),
+                Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => {
+                    cleaned_up_filepaths.push(filepath.clone());
+                    continue;
+                }
+                Err(io_error) => return Err(io_error.into()),
+            };
+            if num_bytes != watched_file.offset {
+                continue;
+            }
+            if let Some(archive_dir) = archive_dir_opt {
+                let file_name = filepath
+                    .file_name()
+                    .context("Watched file has no file name.")?;
+                tokio::fs::rename(filepath, archive_dir.join(file_name))
+                    .await
+                    .with_context(|| format!("Failed to archive file `{}`.", filepath.display()))?;
+            } else {
+                tokio::fs::remove_file(filepath)
+                    .await
+                    .with_context(|| format!("Failed to delete file `{}`.", filepath.display()))?;
+            }
+            info!(filepath=%filepath.display(), cleanup_policy=?self.cleanup_policy, "Cleaned up file.");
+            cleaned_up_filepaths.push(filepath.clone());
+            self.num_cleaned_up_files += 1;
+        }
+        for filepath in cleaned_up_filepaths {
+            self.files.remove(&filepath);
+        }
+        Ok(())
+    }
+}
+
+/// Matches a file name against a pattern supporting the `*` and `?` wildcards.
+fn matches_pattern(pattern: &str, file_name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let file_name: Vec<char> = file_name.chars().collect();
+    let mut pattern_idx = 0;
+    let mut file_name_idx = 0;
+    // Position of the last `*` seen in the pattern and of the file name character it was
+    // matched against, used for backtracking.
+    let mut star_opt: Option<(usize, usize)> = None;
+
+    while file_name_idx < file_name.len() {
+        if pattern_idx < pattern.len()
+            && (pattern[pattern_idx] == '?' || pattern[pattern_idx] == file_name[file_name_idx])
+        {
+            pattern_idx += 1;
+            file_name_idx += 1;
+        } else if pattern_idx < pattern.len() && pattern[pattern_idx] == '*' {
+            star_opt = Some((pattern_idx, file_name_idx));
+            pattern_idx += 1;
+        } else if let Some((star_idx, star_file_name_idx)) = star_opt {
+            pattern_idx = star_idx + 1;
+            file_name_idx = star_file_name_idx + 1;
+            star_opt = Some((star_idx, file_name_idx));
+        } else {
+            return false;
+        }
+    }
+    pattern[pattern_idx..].iter().all(|&c| c == '*')
 }
 
 impl fmt::Debug for FileSource {
@@ -66,27 +253,56 @@ impl Source for FileSource {
         doc_processor_mailbox: &Mailbox<DocProcessor>,
         ctx: &SourceContext,
     ) -> Result<Duration, ActorExitStatus> {
+        if let Some(directory) = &mut self.directory_opt {
+            if directory.current_filepath_opt.is_none() {
+                match directory.open_next_file().await? {
+                    Some((file, offset)) => {
+                        self.reader = BufReader::new(Box::new(file));
+                        if let Some(csv_framer) = &mut self.csv_framer_opt {
+                            csv_framer.reset();
+                        }
+                        self.counters.previous_offset = offset;
+                        self.counters.current_offset = offset;
+                        self.prepare_csv_header(offset).await?;
+                    }
+                    None => return Ok(DIRECTORY_POLL_INTERVAL),
+                }
+            }
+        }
+        if let Some(csv_header) = self.pending_csv_header_opt.take() {
+            self.send_csv_header(csv_header, doc_processor_mailbox, ctx)
+                .await?;
+        }
         // We collect batches of documents before sending them to the indexer.
         let limit_num_bytes = self.counters.previous_offset + BATCH_NUM_BYTES_LIMIT;
         let mut reached_eof = false;
         let mut doc_batch = RawDocBatch::default();
         while self.counters.current_offset < limit_num_bytes {
-            let mut doc_line = String::new();
-            let num_bytes = self
-                .reader
-                .read_line(&mut doc_line)
+            let doc = match self
+                .read_doc()
                 .await
-                .map_err(|io_err: io::Error| anyhow::anyhow!(io_err))?;
-            if num_bytes == 0 {
-                reached_eof = true;
-                break;
+                .map_err(|io_err: io::Error| anyhow::anyhow!(io_err))?
+            {
+                Some(doc) => doc,
+                None => {
+                    reached_eof = true;
+                    break;
+                }
+            };
+            let num_bytes = doc.len();
+            if self.reads_csv_header {
+                self.reads_csv_header = false;
+                self.counters.current_offset += num_bytes as u64;
+                self.send_csv_header(Bytes::from(doc), doc_processor_mailbox, ctx)
+                    .await?;
+                continue;
             }
-            doc_batch.docs.push(doc_line);
+            doc_batch.docs.push(Bytes::from(doc));
             self.counters.current_offset += num_bytes as u64;
             self.counters.num_lines_processed += 1;
         }
         if !doc_batch.docs.is_empty() {
-            if let Some(filepath) = &self.params.filepath {
+            if let Some(filepath) = self.current_filepath() {
                 let filepath_str = filepath
                     .to_str()
                     .context("Path is invalid utf-8")?
@@ -105,6 +321,12 @@ impl Source for FileSource {
             ctx.send_message(doc_processor_mailbox, doc_batch).await?;
         }
         if reached_eof {
+            if let Some(directory) = &mut self.directory_opt {
+                // The offset of the last batch sent, which excludes a header not followed by any
+                // record.
+                directory.close_current_file(self.counters.previous_offset);
+                return Ok(Duration::default());
+            }
             info!("EOF");
             ctx.send_exit_with_success(doc_processor_mailbox).await?;
             return Err(ActorExitStatus::Success);
@@ -117,8 +339,190 @@ impl Source for FileSource {
     }
 
     fn observable_state(&self) -> serde_json::Value {
-        serde_json::to_value(&self.counters).unwrap()
+        let mut observable_state = serde_json::to_value(&self.counters).unwrap();
+        if let Some(directory) = &self.directory_opt {
+            observable_state["current_filepath"] =
+                serde_json::json!(directory.current_filepath_opt);
+            observable_state["num_files"] = serde_json::json!(directory.files.len());
+            observable_state["num_cleaned_up_files"] =
+                serde_json::json!(directory.num_cleaned_up_files);
+        }
+        observable_state
+    }
+
+    async fn suggest_truncate(
+        &mut self,
+        checkpoint: SourceCheckpoint,
+        _ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        if let Some(directory) = &mut self.directory_opt {
+            directory.cleanup_published_files(&checkpoint).await?;
+        }
+        Ok(())
+    }
+}
+
+impl FileSource {
+    fn current_filepath(&self) -> Option<&Path> {
+        if let Some(directory) = &self.directory_opt {
+            return directory.current_filepath_opt.as_deref();
+        }
+        self.params.filepath.as_deref()
+    }
+
+    /// Reads the next document: a line, or a CSV record spanning several lines when one of its
+    /// quoted fields contains a newline. Returns `None` at the end of the file.
+    async fn read_doc(&mut self) -> io::Result<Option<String>> {
+        let mut doc = String::new();
+        loop {
+            let line_start = doc.len();
+            let num_bytes = self.reader.read_line(&mut doc).await?;
+            if num_bytes == 0 {
+                // The last document of a file in a watched directory may still be being written.
+                // It is read again once complete.
+                if doc.is_empty() || self.directory_opt.is_some() {
+                    return Ok(None);
+                }
+                return Ok(Some(doc));
+            }
+            if self.directory_opt.is_some() && !doc.ends_with('\n') {
+                return Ok(None);
+            }
+            let ends_doc = match &mut self.csv_framer_opt {
+                Some(csv_framer) => csv_framer.ends_record(doc[line_start..].as_bytes()),
+                None => true,
+            };
+            if ends_doc {
+                return Ok(Some(doc));
+            }
+        }
+    }
+
+    /// Prepares the reading of the CSV header of the current file, opened at `offset`.
+    async fn prepare_csv_header(&mut self, offset: u64) -> anyhow::Result<()> {
+        if !self.infer_csv_header {
+            return Ok(());
+        }
+        if offset == 0 {
+            self.reads_csv_header = true;
+            return Ok(());
+        }
+        let filepath_opt = self.current_filepath().map(Path::to_path_buf);
+        if let (Some(filepath), Some(csv_framer)) = (filepath_opt, &mut self.csv_framer_opt) {
+            self.pending_csv_header_opt = read_first_record(&filepath, csv_framer).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_csv_header(
+        &self,
+        header: Bytes,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<(), ActorExitStatus> {
+        let partition_id = if let Some(filepath) = self.current_filepath() {
+            let filepath_str = filepath.to_str().context("Path is invalid utf-8")?;
+            PartitionId::from(filepath_str)
+        } else {
+            PartitionId::default()
+        };
+        let csv_header = CsvHeader {
+            partition_id,
+            header,
+        };
+        ctx.send_message(doc_processor_mailbox, csv_header).await?;
+        Ok(())
+    }
+
+    async fn watch_directory(
+        source_id: String,
+        directory_path: PathBuf,
+        params: FileSourceParams,
+        checkpoint: SourceCheckpoint,
+        source_config: &SourceConfig,
+    ) -> anyhow::Result<FileSource> {
+        if let Some(archive_dir) = &params.archive_dir {
+            tokio::fs::create_dir_all(archive_dir)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to create archive directory `{}`.",
+                        archive_dir.display()
+                    )
+                })?;
+        }
+        let files: BTreeMap<PathBuf, WatchedFile> = checkpoint
+            .iter()
+            .filter_map(|(partition_id, position)| {
+                let filepath = PathBuf::from(partition_id.as_str());
+                if filepath.parent() != Some(directory_path.as_path()) {
+                    return None;
+                }
+                let offset = position.as_str().parse::<u64>().ok()?;
+                let watched_file = WatchedFile {
+                    offset,
+                    num_bytes_opt: None,
+                };
+                Some((filepath, watched_file))
+            })
+            .collect();
+        info!(directory=%directory_path.display(), num_files=files.len(), "Watching directory.");
+        let directory = WatchedDirectory {
+            directory_path,
+            pattern_opt: params.pattern.clone(),
+            cleanup_policy: params.cleanup_policy,
+            archive_dir_opt: params.archive_dir.clone(),
+            files,
+            current_filepath_opt: None,
+            num_cleaned_up_files: 0,
+        };
+        let file_source = FileSource {
+            source_id,
+            params,
+            counters: FileSourceCounters::default(),
+            reader: BufReader::new(Box::new(tokio::io::empty())),
+            directory_opt: Some(directory),
+            csv_framer_opt: csv_framer_opt(source_config),
+            infer_csv_header: source_config.infers_csv_header(),
+            reads_csv_header: false,
+            pending_csv_header_opt: None,
+        };
+        Ok(file_source)
+    }
+}
+
+/// Reads the first CSV record of the file, if it is complete.
+async fn read_first_record(
+    filepath: &Path,
+    csv_framer: &mut CsvRecordFramer,
+) -> anyhow::Result<Option<Bytes>> {
+    let file = File::open(filepath)
+        .await
+        .with_context(|| format!("Failed to open source file `{}`.", filepath.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut first_record = String::new();
+    let first_record_opt = loop {
+        let line_start = first_record.len();
+        reader.read_line(&mut first_record).await?;
+        if !first_record.ends_with('\n') {
+            break None;
+        }
+        if csv_framer.ends_record(first_record[line_start..].as_bytes()) {
+            break Some(Bytes::from(first_record));
+        }
+    };
+    // The framer is reused to read the file from its read offset.
+    csv_framer.reset();
+    Ok(first_record_opt)
+}
+
+fn csv_framer_opt(source_config: &SourceConfig) -> Option<CsvRecordFramer> {
+    let input_format = source_config.input_format();
+    if input_format == SourceInputFormat::Json {
+        return None;
     }
+    let csv_options = source_config.csv_options.clone().unwrap_or_default();
+    Some(CsvRecordFramer::new(input_format, &csv_options))
 }
 
 pub struct FileSourceFactory;
@@ -134,6 +538,22 @@ impl TypedSourceFactory for FileSourceFactory {
         params: FileSourceParams,
         checkpoint: SourceCheckpoint,
     ) -> anyhow::Result<FileSource> {
+        if let Some(filepath) = params.filepath.clone() {
+            let is_dir = tokio::fs::metadata(&filepath)
+                .await
+                .map(|metadata| metadata.is_dir())
+                .unwrap_or(false);
+            if is_dir {
+                return FileSource::watch_directory(
+                    ctx.source_config.source_id.clone(),
+                    filepath,
+                    params,
+                    checkpoint,
+                    &ctx.source_config,
+                )
+                .await;
+            }
+        }
         let mut offset = 0;
         let reader: Box<dyn AsyncRead + Send + Sync + Unpin> =
             if let Some(filepath) = &params.filepath {
@@ -152,7 +572,7 @@ impl TypedSourceFactory for FileSourceFactory {
                 // We cannot use the checkpoint.
                 Box::new(tokio::io::stdin())
             };
-        let file_source = FileSource {
+        let mut file_source = FileSource {
             source_id: ctx.source_config.source_id.clone(),
             counters: FileSourceCounters {
                 previous_offset: offset,
@@ -161,7 +581,13 @@ impl TypedSourceFactory for FileSourceFactory {
             },
             reader: BufReader::new(reader),
             params,
+            directory_opt: None,
+            csv_framer_opt: csv_framer_opt(&ctx.source_config),
+            infer_csv_header: ctx.source_config.infers_csv_header(),
+            reads_csv_header: false,
+            pending_csv_header_opt: None,
         };
+        file_source.prepare_csv_header(offset).await?;
         Ok(file_source)
     }
 }
@@ -172,9 +598,10 @@ mod tests {
     use std::path::PathBuf;
 
     use quickwit_actors::{create_test_mailbox, Command, Universe};
-    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_config::{SourceConfig, SourceInputFormat, SourceParams};
     use quickwit_metastore::checkpoint::{SourceCheckpoint, SourceCheckpointDelta};
     use quickwit_metastore::metastore_for_test;
+    use tokio::sync::watch;
 
     use super::*;
     use crate::source::SourceActor;
@@ -194,6 +621,11 @@ mod tests {
                 SourceConfig {
                     source_id: "test-file-source".to_string(),
                     num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
                     enabled: true,
                     source_params: SourceParams::File(params.clone()),
                 },
@@ -205,6 +637,7 @@ mod tests {
         let file_source_actor = SourceActor {
             source: Box::new(file_source),
             doc_processor_mailbox,
+            rate_limiter_opt: None,
         };
         let (_file_source_mailbox, file_source_handle) =
             universe.spawn_builder().spawn(file_source_actor);
@@ -257,6 +690,11 @@ mod tests {
                 SourceConfig {
                     source_id: "test-file-source".to_string(),
                     num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
                     enabled: true,
                     source_params: SourceParams::File(params.clone()),
                 },
@@ -268,6 +706,7 @@ mod tests {
         let file_source_actor = SourceActor {
             source: Box::new(source),
             doc_processor_mailbox,
+            rate_limiter_opt: None,
         };
         let (_file_source_mailbox, file_source_handle) =
             universe.spawn_builder().spawn(file_source_actor);
@@ -343,6 +782,11 @@ mod tests {
                 SourceConfig {
                     source_id: "test-file-source".to_string(),
                     num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
                     enabled: true,
                     source_params: SourceParams::File(params.clone()),
                 },
@@ -354,6 +798,7 @@ mod tests {
         let file_source_actor = SourceActor {
             source: Box::new(source),
             doc_processor_mailbox,
+            rate_limiter_opt: None,
         };
         let (_file_source_mailbox, file_source_handle) =
             universe.spawn_builder().spawn(file_source_actor);
@@ -368,7 +813,278 @@ mod tests {
             })
         );
         let indexer_messages: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
-        assert!(indexer_messages[0].docs[0].starts_with("2\n"));
+        assert!(indexer_messages[0].docs[0].starts_with(b"2\n"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_source_csv_header() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let mut temp_file = tempfile::NamedTempFile::new()?;
+        temp_file.write_all(b"status,body\n200,first\n404,second\n")?;
+        temp_file.flush()?;
+        let temp_file_path = temp_file.path().canonicalize()?;
+        let partition_id = PartitionId::from(temp_file_path.to_string_lossy().to_string());
+
+        for (resume_offset_opt, expected_docs) in [
+            (None, vec!["200,first\n", "404,second\n"]),
+            (Some(22u64), vec!["404,second\n"]),
+        ] {
+            let universe = Universe::new();
+            let (doc_processor_mailbox, doc_processor_inbox) = create_test_mailbox();
+            let params = FileSourceParams::file(&temp_file_path);
+            let mut checkpoint = SourceCheckpoint::default();
+            if let Some(resume_offset) = resume_offset_opt {
+                checkpoint.try_apply_delta(SourceCheckpointDelta::from_partition_delta(
+                    partition_id.clone(),
+                    Position::from(0u64),
+                    Position::from(resume_offset),
+                ))?;
+            }
+            let source = FileSourceFactory::typed_create_source(
+                SourceExecutionContext::for_test(
+                    metastore_for_test(),
+                    "test-index",
+                    PathBuf::from("./queues"),
+                    SourceConfig {
+                        source_id: "test-file-source".to_string(),
+                        num_pipelines: 1,
+                        expected_throughput: None,
+                        rate_limit_per_sec: None,
+                        transform: Vec::new(),
+                        input_format: Some(SourceInputFormat::Csv),
+                        csv_options: None,
+                        enabled: true,
+                        source_params: SourceParams::File(params.clone()),
+                    },
+                ),
+                params,
+                checkpoint,
+            )
+            .await?;
+            let file_source_actor = SourceActor {
+                source: Box::new(source),
+                doc_processor_mailbox,
+                rate_limiter_opt: None,
+            };
+            let (_file_source_mailbox, file_source_handle) =
+                universe.spawn_builder().spawn(file_source_actor);
+            let (actor_termination, _) = file_source_handle.join().await;
+            assert!(actor_termination.is_success());
+
+            let messages = doc_processor_inbox.drain_for_test();
+            let csv_header = messages[0].downcast_ref::<CsvHeader>().unwrap();
+            assert_eq!(csv_header.partition_id, partition_id);
+            assert_eq!(&csv_header.header[..], b"status,body\n");
+            let batch = messages[1].downcast_ref::<RawDocBatch>().unwrap();
+            assert_eq!(batch.docs, expected_docs);
+            let expected_from_position = resume_offset_opt
+                .map(Position::from)
+                .unwrap_or(Position::from(0u64));
+            assert_eq!(
+                batch.checkpoint_delta,
+                SourceCheckpointDelta::from_partition_delta(
+                    partition_id.clone(),
+                    expected_from_position,
+                    Position::from(33u64),
+                )
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_source_csv_multiline_record() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let mut temp_file = tempfile::NamedTempFile::new()?;
+        temp_file.write_all(b"status,\"body\n(multiline)\"\n200,\"first\nline\"\n404,second\n")?;
+        temp_file.flush()?;
+        let temp_file_path = temp_file.path().canonicalize()?;
+        let partition_id = PartitionId::from(temp_file_path.to_string_lossy().to_string());
+
+        for (resume_offset_opt, expected_docs) in [
+            (None, vec!["200,\"first\nline\"\n", "404,second\n"]),
+            (Some(43u64), vec!["404,second\n"]),
+        ] {
+            let universe = Universe::new();
+            let (doc_processor_mailbox, doc_processor_inbox) = create_test_mailbox();
+            let params = FileSourceParams::file(&temp_file_path);
+            let mut checkpoint = SourceCheckpoint::default();
+            if let Some(resume_offset) = resume_offset_opt {
+                checkpoint.try_apply_delta(SourceCheckpointDelta::from_partition_delta(
+                    partition_id.clone(),
+                    Position::from(0u64),
+                    Position::from(resume_offset),
+                ))?;
+            }
+            let source = FileSourceFactory::typed_create_source(
+                SourceExecutionContext::for_test(
+                    metastore_for_test(),
+                    "test-index",
+                    PathBuf::from("./queues"),
+                    SourceConfig {
+                        source_id: "test-file-source".to_string(),
+                        num_pipelines: 1,
+                        expected_throughput: None,
+                        rate_limit_per_sec: None,
+                        transform: Vec::new(),
+                        input_format: Some(SourceInputFormat::Csv),
+                        csv_options: None,
+                        enabled: true,
+                        source_params: SourceParams::File(params.clone()),
+                    },
+                ),
+                params,
+                checkpoint,
+            )
+            .await?;
+            let file_source_actor = SourceActor {
+                source: Box::new(source),
+                doc_processor_mailbox,
+                rate_limiter_opt: None,
+            };
+            let (_file_source_mailbox, file_source_handle) =
+                universe.spawn_builder().spawn(file_source_actor);
+            let (actor_termination, _) = file_source_handle.join().await;
+            assert!(actor_termination.is_success());
+
+            let messages = doc_processor_inbox.drain_for_test();
+            let csv_header = messages[0].downcast_ref::<CsvHeader>().unwrap();
+            assert_eq!(&csv_header.header[..], b"status,\"body\n(multiline)\"\n");
+            let batch = messages[1].downcast_ref::<RawDocBatch>().unwrap();
+            assert_eq!(batch.docs, expected_docs);
+            let expected_from_position = resume_offset_opt
+                .map(Position::from)
+                .unwrap_or(Position::from(0u64));
+            assert_eq!(
+                batch.checkpoint_delta,
+                SourceCheckpointDelta::from_partition_delta(
+                    partition_id.clone(),
+                    expected_from_position,
+                    Position::from(54u64),
+                )
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*", "app.log"));
+        assert!(matches_pattern("*.json", "app.json"));
+        assert!(matches_pattern("app-*.json", "app-2022-10-01.json"));
+        assert!(matches_pattern("app-?.log", "app-1.log"));
+        assert!(matches_pattern("*-*.log", "app-1.log"));
+        assert!(!matches_pattern("*.json", "app.json.gz"));
+        assert!(!matches_pattern("app-?.log", "app-12.log"));
+        assert!(!matches_pattern("app.log", "app.log.1"));
+    }
+
+    #[tokio::test]
+    async fn test_file_source_watch_directory() -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let temp_dir = tempfile::tempdir()?;
+        let directory_path = temp_dir.path().canonicalize()?;
+        std::fs::write(directory_path.join("a.json"), "1\n2\n")?;
+        std::fs::write(directory_path.join("b.txt"), "ignored\n")?;
+        std::fs::write(directory_path.join("c.json"), "3\n4")?;
+
+        let mut params = FileSourceParams::file(&directory_path);
+        params.pattern = Some("*.json".to_string());
+        params.cleanup_policy = FileCleanupPolicy::Delete;
+        let metastore = metastore_for_test();
+        let mut file_source = FileSourceFactory::typed_create_source(
+            SourceExecutionContext::for_test(
+                metastore,
+                "test-index",
+                PathBuf::from("./queues"),
+                SourceConfig {
+                    source_id: "test-file-source".to_string(),
+                    num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
+                    enabled: true,
+                    source_params: SourceParams::File(params.clone()),
+                },
+            ),
+            params,
+            SourceCheckpoint::default(),
+        )
+        .await?;
+        let (doc_processor_mailbox, doc_processor_inbox) = create_test_mailbox();
+        let (source_mailbox, _source_inbox) = create_test_mailbox::<SourceActor>();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(serde_json::Value::Null);
+        let ctx: SourceContext =
+            ActorContext::for_test(&universe, source_mailbox, observable_state_tx);
+
+        let a_filepath = directory_path.join("a.json");
+        let c_filepath = directory_path.join("c.json");
+        for _ in 0..2 {
+            let wait_duration = file_source
+                .emit_batches(&doc_processor_mailbox, &ctx)
+                .await
+                .unwrap();
+            assert_eq!(wait_duration, Duration::default());
+        }
+        // The last line of `c.json` is incomplete.
+        let wait_duration = file_source
+            .emit_batches(&doc_processor_mailbox, &ctx)
+            .await
+            .unwrap();
+        assert_eq!(wait_duration, DIRECTORY_POLL_INTERVAL);
+
+        let mut c_file = std::fs::OpenOptions::new().append(true).open(&c_filepath)?;
+        c_file.write_all(b"\n5\n")?;
+        c_file.flush()?;
+        file_source
+            .emit_batches(&doc_processor_mailbox, &ctx)
+            .await
+            .unwrap();
+
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 3);
+        assert_eq!(
+            batches[0].docs,
+            vec![Bytes::from("1\n"), Bytes::from("2\n")]
+        );
+        assert_eq!(
+            format!("{:?}", batches[0].checkpoint_delta),
+            format!(
+                "∆({}:{})",
+                a_filepath.display(),
+                "(00000000000000000000..00000000000000000004]"
+            )
+        );
+        assert_eq!(batches[1].docs, vec![Bytes::from("3\n")]);
+        assert_eq!(
+            &extract_position_delta(&batches[1].checkpoint_delta).unwrap(),
+            "00000000000000000000..00000000000000000002"
+        );
+        assert_eq!(
+            batches[2].docs,
+            vec![Bytes::from("4\n"), Bytes::from("5\n")]
+        );
+        assert_eq!(
+            &extract_position_delta(&batches[2].checkpoint_delta).unwrap(),
+            "00000000000000000002..00000000000000000006"
+        );
+
+        let mut checkpoint = SourceCheckpoint::default();
+        for batch in &batches[..2] {
+            checkpoint.try_apply_delta(batch.checkpoint_delta.clone())?;
+        }
+        file_source.suggest_truncate(checkpoint, &ctx).await?;
+        assert!(!a_filepath.exists());
+        assert!(c_filepath.exists());
+        assert!(directory_path.join("b.txt").exists());
+
+        let observable_state = file_source.observable_state();
+        assert_eq!(observable_state["num_lines_processed"], 5);
+        assert_eq!(observable_state["num_files"], 1);
+        assert_eq!(observable_state["num_cleaned_up_files"], 1);
         Ok(())
     }
 }
@@ -0,0 +1,324 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use google_cloud_auth::credentials::CredentialsFile;
+use google_cloud_default::WithAuthExt;
+use google_cloud_pubsub::client::{Client, ClientConfig};
+use google_cloud_pubsub::subscription::Subscription;
+use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
+use quickwit_config::PubSubSourceParams;
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::actors::DocProcessor;
+use crate::models::RawDocBatch;
+use crate::source::pending_acks::PendingAcks;
+use crate::source::{
+    Source, SourceActor, SourceContext, SourceExecutionContext, TypedSourceFactory,
+};
+
+const TARGET_BATCH_NUM_BYTES: u64 = 5_000_000;
+
+/// Maximum time spent pulling messages in a single call to `emit_batches`.
+const EMIT_BATCHES_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Wait time before pulling again when the subscription has no messages available.
+const PULL_COOL_DOWN: Duration = Duration::from_secs(1);
+
+/// Factory for instantiating a `GcpPubSubSource`.
+pub struct GcpPubSubSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for GcpPubSubSourceFactory {
+    type Source = GcpPubSubSource;
+    type Params = PubSubSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: PubSubSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        GcpPubSubSource::try_new(ctx, params, checkpoint).await
+    }
+}
+
+#[derive(Default)]
+pub struct GcpPubSubSourceState {
+    /// Position of the last message emitted, i.e. the number of messages emitted by the
+    /// pipelines sharing the partition of the source since its creation.
+    current_position: u64,
+    /// Number of bytes processed by the source.
+    pub num_bytes_processed: u64,
+    /// Number of messages processed by the source (including invalid messages).
+    pub num_messages_processed: u64,
+    /// Number of invalid messages, i.e., that were empty or could not be parsed.
+    pub num_invalid_messages: u64,
+    /// Number of messages acknowledged after being published.
+    pub num_acked_messages: u64,
+}
+
+/// A source pulling messages from a Google Cloud Pub/Sub subscription.
+///
+/// Pub/Sub messages have no position, so each indexing pipeline records the number of messages
+/// it has emitted in its own partition of the checkpoint. The messages are acknowledged only once
+/// the splits holding them are published: the ack IDs of each batch are kept in memory, keyed by
+/// the position of the batch, until the publisher suggests to truncate the source up to that
+/// position. Messages whose acknowledgement deadline expires before their publication, or that are
+/// in flight when the pipeline restarts, are redelivered and indexed twice. Setting the ack
+/// deadline of the subscription above the commit timeout of the index limits redeliveries.
+pub struct GcpPubSubSource {
+    ctx: Arc<SourceExecutionContext>,
+    subscription_name: String,
+    subscription: Subscription,
+    max_messages_per_pull: i32,
+    partition_id: PartitionId,
+    pending_acks: PendingAcks,
+    state: GcpPubSubSourceState,
+}
+
+impl fmt::Debug for GcpPubSubSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GcpPubSubSource {{ source_id: {}, subscription: {} }}",
+            self.ctx.source_config.source_id, self.subscription_name
+        )
+    }
+}
+
+pub(super) async fn create_subscription(
+    params: &PubSubSourceParams,
+) -> anyhow::Result<Subscription> {
+    let mut client_config = match &params.credentials_file {
+        Some(credentials_file) => {
+            let credentials = CredentialsFile::new_from_file(credentials_file.clone())
+                .await
+                .with_context(|| {
+                    format!("Failed to read GCP credentials file `{credentials_file}`.")
+                })?;
+            ClientConfig::default()
+                .with_credentials(credentials)
+                .await?
+        }
+        None => ClientConfig::default().with_auth().await?,
+    };
+    if let Some(project_id) = &params.project_id {
+        client_config.project_id = Some(project_id.clone());
+    }
+    let client = Client::new(client_config)
+        .await
+        .context("Failed to create Pub/Sub client.")?;
+    Ok(client.subscription(&params.subscription))
+}
+
+impl GcpPubSubSource {
+    /// Instantiates a new `GcpPubSubSource`.
+    pub async fn try_new(
+        ctx: Arc<SourceExecutionContext>,
+        params: PubSubSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self> {
+        let subscription = create_subscription(&params).await?;
+        let subscription_exists = subscription
+            .exists(None)
+            .await
+            .context("Failed to look up Pub/Sub subscription.")?;
+        if !subscription_exists {
+            bail!(
+                "Pub/Sub subscription `{}` does not exist.",
+                params.subscription
+            );
+        }
+        // Each pipeline records the messages it emits in its own partition.
+        let partition_id = PartitionId::from(format!("{}:{}", ctx.node_id, ctx.pipeline_ord));
+        let current_position = match checkpoint.position_for_partition(&partition_id) {
+            Some(Position::Offset(offset)) => offset
+                .parse::<u64>()
+                .with_context(|| format!("Failed to parse Pub/Sub position `{offset}`."))?,
+            _ => 0,
+        };
+        info!(
+            index_id=%ctx.index_id,
+            source_id=%ctx.source_config.source_id,
+            subscription=%params.subscription,
+            partition_id=%partition_id.as_str(),
+            current_position=%current_position,
+            "Starting Pub/Sub source."
+        );
+        let state = GcpPubSubSourceState {
+            current_position,
+            ..Default::default()
+        };
+        Ok(Self {
+            ctx,
+            subscription_name: params.subscription,
+            subscription,
+            max_messages_per_pull: params.max_messages_per_pull as i32,
+            partition_id,
+            pending_acks: PendingAcks::default(),
+            state,
+        })
+    }
+}
+
+#[async_trait]
+impl Source for GcpPubSubSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let mut batch_num_bytes = 0;
+        let mut docs = Vec::new();
+        let mut ack_ids = Vec::new();
+        let deadline = Instant::now() + EMIT_BATCHES_TIMEOUT;
+
+        while batch_num_bytes < TARGET_BATCH_NUM_BYTES && Instant::now() < deadline {
+            let messages = ctx
+                .protect_future(self.subscription.pull(self.max_messages_per_pull, None))
+                .await
+                .context("Failed to pull messages from Pub/Sub subscription.")?;
+            if messages.is_empty() {
+                break;
+            }
+            for message in messages {
+                ack_ids.push(message.ack_id().to_string());
+                let data = message.message.data;
+                let message_id = message.message.message_id;
+                batch_num_bytes += data.len() as u64;
+                self.state.num_bytes_processed += data.len() as u64;
+                self.state.num_messages_processed += 1;
+
+                match std::str::from_utf8(&data) {
+                    Ok(doc) if !doc.is_empty() => docs.push(Bytes::from(data)),
+                    Ok(_) => {
+                        warn!(
+                            subscription=%self.subscription_name,
+                            message_id=%message_id,
+                            "Message is empty."
+                        );
+                        self.state.num_invalid_messages += 1;
+                    }
+                    Err(error) => {
+                        warn!(
+                            subscription=%self.subscription_name,
+                            message_id=%message_id,
+                            error=?error,
+                            "Message contains invalid UTF-8 characters."
+                        );
+                        self.state.num_invalid_messages += 1;
+                    }
+                }
+            }
+            ctx.record_progress();
+        }
+        if ack_ids.is_empty() {
+            return Ok(PULL_COOL_DOWN);
+        }
+        let previous_position = if self.state.current_position == 0 {
+            Position::Beginning
+        } else {
+            Position::from(self.state.current_position)
+        };
+        let current_position = self.state.current_position + ack_ids.len() as u64;
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta
+            .record_partition_delta(
+                self.partition_id.clone(),
+                previous_position,
+                Position::from(current_position),
+            )
+            .context("Failed to record partition delta.")?;
+        self.state.current_position = current_position;
+        self.pending_acks.push(current_position, ack_ids);
+
+        let batch = RawDocBatch {
+            docs,
+            checkpoint_delta,
+        };
+        ctx.send_message(doc_processor_mailbox, batch).await?;
+        Ok(Duration::default())
+    }
+
+    async fn suggest_truncate(
+        &mut self,
+        checkpoint: SourceCheckpoint,
+        ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        let published_position = match checkpoint.position_for_partition(&self.partition_id) {
+            Some(Position::Offset(offset)) => offset.parse::<u64>()?,
+            _ => return Ok(()),
+        };
+        let ack_ids = self.pending_acks.drain_up_to(published_position);
+        if ack_ids.is_empty() {
+            return Ok(());
+        }
+        let num_ack_ids = ack_ids.len() as u64;
+        // If the acknowledgement fails, the messages are redelivered and indexed again.
+        ctx.protect_future(self.subscription.ack(ack_ids))
+            .await
+            .context("Failed to acknowledge Pub/Sub messages.")?;
+        self.state.num_acked_messages += num_ack_ids;
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "GcpPubSubSource{{source_id={}}}",
+            self.ctx.source_config.source_id
+        )
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        json!({
+            "index_id": self.ctx.index_id,
+            "source_id": self.ctx.source_config.source_id,
+            "subscription": self.subscription_name,
+            "partition_id": self.partition_id.as_str(),
+            "current_position": self.state.current_position,
+            "num_pending_acks": self.pending_acks.num_pending_acks(),
+            "num_bytes_processed": self.state.num_bytes_processed,
+            "num_messages_processed": self.state.num_messages_processed,
+            "num_invalid_messages": self.state.num_invalid_messages,
+            "num_acked_messages": self.state.num_acked_messages,
+        })
+    }
+}
+
+/// Checks whether we can establish a connection to the Pub/Sub service and find the subscription.
+pub(super) async fn check_connectivity(params: PubSubSourceParams) -> anyhow::Result<()> {
+    let subscription = create_subscription(&params).await?;
+    if !subscription.exists(None).await? {
+        bail!(
+            "Pub/Sub subscription `{}` does not exist.",
+            params.subscription
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,654 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::Cursor;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use async_compression::tokio::bufread::GzipDecoder;
+use async_trait::async_trait;
+use bytes::Bytes;
+use google_cloud_auth::credentials::CredentialsFile;
+use google_cloud_default::WithAuthExt;
+use google_cloud_pubsub::subscription::Subscription;
+use google_cloud_storage::client::{Client as StorageClient, ClientConfig as StorageClientConfig};
+use google_cloud_storage::http::objects::download::Range;
+use google_cloud_storage::http::objects::get::GetObjectRequest;
+use google_cloud_storage::http::Error as StorageError;
+use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
+use quickwit_config::{GcsNotificationsSourceParams, PubSubSourceParams};
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use tracing::{info, warn};
+
+use crate::actors::DocProcessor;
+use crate::models::RawDocBatch;
+use crate::source::gcp_pubsub_source::create_subscription;
+use crate::source::pending_acks::PendingAcks;
+use crate::source::{
+    Source, SourceActor, SourceContext, SourceExecutionContext, TypedSourceFactory,
+};
+
+const TARGET_BATCH_NUM_BYTES: u64 = 5_000_000;
+
+/// Maximum time spent reading objects in a single call to `emit_batches`.
+const EMIT_BATCHES_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Wait time before pulling again when the subscription has no notifications available.
+const PULL_COOL_DOWN: Duration = Duration::from_secs(1);
+
+/// Event type of the notifications sent when an object is created or overwritten.
+const OBJECT_FINALIZE_EVENT_TYPE: &str = "OBJECT_FINALIZE";
+
+/// Factory for instantiating a `GcsNotificationsSource`.
+pub struct GcsNotificationsSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for GcsNotificationsSourceFactory {
+    type Source = GcsNotificationsSource;
+    type Params = GcsNotificationsSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: GcsNotificationsSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        GcsNotificationsSource::try_new(ctx, params, checkpoint).await
+    }
+}
+
+#[derive(Default)]
+pub struct GcsNotificationsSourceState {
+    /// Position of the last document emitted, i.e. the number of documents emitted by the
+    /// pipelines sharing the partition of the source since its creation.
+    current_position: u64,
+    /// Position of the last document published.
+    published_position: u64,
+    /// Number of bytes processed by the source.
+    pub num_bytes_processed: u64,
+    /// Number of lines processed by the source (including invalid and empty lines).
+    pub num_lines_processed: u64,
+    /// Number of lines that could not be decoded as UTF-8.
+    pub num_invalid_lines: u64,
+    /// Number of objects read entirely by the source.
+    pub num_objects_processed: u64,
+    /// Number of objects deleted before the source could read them.
+    pub num_missing_objects: u64,
+    /// Number of messages that are not GCS notifications.
+    pub num_invalid_messages: u64,
+    /// Number of messages acknowledged.
+    pub num_acked_messages: u64,
+}
+
+/// Location of a GCS object.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct GcsObjectRef {
+    bucket: String,
+    name: String,
+}
+
+impl fmt::Display for GcsObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "gs://{}/{}", self.bucket, self.name)
+    }
+}
+
+/// Object referenced by a notification, waiting to be read. Each notification references a
+/// single object, so the notification is acknowledged once its object is read.
+#[derive(Debug, Eq, PartialEq)]
+struct PendingObject {
+    object: GcsObjectRef,
+    content_encoding_opt: Option<String>,
+    ack_id: String,
+}
+
+/// GCS object being read line by line.
+struct ObjectReader {
+    object: GcsObjectRef,
+    ack_id: String,
+    reader: Pin<Box<dyn AsyncBufRead + Send + Sync>>,
+}
+
+/// A source consuming a Pub/Sub subscription that receives GCS object-change notifications and
+/// indexing the newline-delimited JSON objects they reference. This is the GCP counterpart of the
+/// SQS source. Objects are downloaded entirely before being read. Objects whose name ends with
+/// `.gz` are decompressed, unless they are stored with the `gzip` content encoding, in which case
+/// GCS decompresses them while serving them.
+///
+/// Like the Pub/Sub source, each indexing pipeline records the number of documents it has emitted
+/// in its own partition of the checkpoint. A notification is acknowledged only once the splits
+/// holding the documents of its object are published. Notifications whose acknowledgement
+/// deadline expires before that, or that are in flight when the pipeline restarts, are
+/// redelivered and their objects indexed twice. Setting the ack deadline of the subscription above
+/// the commit timeout of the index limits redeliveries.
+pub struct GcsNotificationsSource {
+    ctx: Arc<SourceExecutionContext>,
+    subscription_name: String,
+    subscription: Subscription,
+    storage_client: StorageClient,
+    max_messages_per_pull: i32,
+    partition_id: PartitionId,
+    pending_objects: VecDeque<PendingObject>,
+    current_object_opt: Option<ObjectReader>,
+    pending_acks: PendingAcks,
+    state: GcsNotificationsSourceState,
+}
+
+impl fmt::Debug for GcsNotificationsSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GcsNotificationsSource {{ source_id: {}, subscription: {} }}",
+            self.ctx.source_config.source_id, self.subscription_name
+        )
+    }
+}
+
+fn pubsub_params(params: &GcsNotificationsSourceParams) -> PubSubSourceParams {
+    PubSubSourceParams {
+        subscription: params.subscription.clone(),
+        project_id: params.project_id.clone(),
+        credentials_file: params.credentials_file.clone(),
+        max_messages_per_pull: params.max_messages_per_pull,
+    }
+}
+
+async fn create_storage_client(
+    params: &GcsNotificationsSourceParams,
+) -> anyhow::Result<StorageClient> {
+    let client_config = match &params.credentials_file {
+        Some(credentials_file) => {
+            let credentials = CredentialsFile::new_from_file(credentials_file.clone())
+                .await
+                .with_context(|| {
+                    format!("Failed to read GCP credentials file `{credentials_file}`.")
+                })?;
+            StorageClientConfig::default()
+                .with_credentials(credentials)
+                .await?
+        }
+        None => StorageClientConfig::default().with_auth().await?,
+    };
+    Ok(StorageClient::new(client_config))
+}
+
+impl GcsNotificationsSource {
+    /// Instantiates a new `GcsNotificationsSource`.
+    pub async fn try_new(
+        ctx: Arc<SourceExecutionContext>,
+        params: GcsNotificationsSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self> {
+        let subscription = create_subscription(&pubsub_params(&params)).await?;
+        let subscription_exists = subscription
+            .exists(None)
+            .await
+            .context("Failed to look up Pub/Sub subscription.")?;
+        if !subscription_exists {
+            bail!(
+                "Pub/Sub subscription `{}` does not exist.",
+                params.subscription
+            );
+        }
+        let storage_client = create_storage_client(&params).await?;
+        // Each pipeline records the documents it emits in its own partition.
+        let partition_id = PartitionId::from(format!("{}:{}", ctx.node_id, ctx.pipeline_ord));
+        let current_position = match checkpoint.position_for_partition(&partition_id) {
+            Some(Position::Offset(offset)) => offset.parse::<u64>().with_context(|| {
+                format!("Failed to parse GCS notifications position `{offset}`.")
+            })?,
+            _ => 0,
+        };
+        info!(
+            index_id=%ctx.index_id,
+            source_id=%ctx.source_config.source_id,
+            subscription=%params.subscription,
+            partition_id=%partition_id.as_str(),
+            current_position=%current_position,
+            "Starting GCS notifications source."
+        );
+        let state = GcsNotificationsSourceState {
+            current_position,
+            published_position: current_position,
+            ..Default::default()
+        };
+        Ok(Self {
+            ctx,
+            subscription_name: params.subscription,
+            subscription,
+            storage_client,
+            max_messages_per_pull: params.max_messages_per_pull as i32,
+            partition_id,
+            pending_objects: VecDeque::new(),
+            current_object_opt: None,
+            pending_acks: PendingAcks::default(),
+            state,
+        })
+    }
+
+    /// Pulls a batch of notifications and queues the objects they reference.
+    async fn pull_notifications(&mut self, ctx: &SourceContext) -> anyhow::Result<()> {
+        let messages = ctx
+            .protect_future(self.subscription.pull(self.max_messages_per_pull, None))
+            .await
+            .context("Failed to pull messages from Pub/Sub subscription.")?;
+
+        let mut ignored_ack_ids = Vec::new();
+
+        for message in messages {
+            let ack_id = message.ack_id().to_string();
+            let notification =
+                match parse_notification(&message.message.attributes, &message.message.data) {
+                    Ok(notification) => notification,
+                    Err(error) => {
+                        // The message is not acknowledged, so that it ends up in the dead-letter
+                        // topic, if any, once delivered too many times.
+                        warn!(
+                            subscription=%self.subscription_name,
+                            message_id=%message.message.message_id,
+                            error=?error,
+                            "Message is not a GCS notification."
+                        );
+                        self.state.num_invalid_messages += 1;
+                        continue;
+                    }
+                };
+            match notification {
+                Some((object, content_encoding_opt)) => {
+                    self.pending_objects.push_back(PendingObject {
+                        object,
+                        content_encoding_opt,
+                        ack_id,
+                    });
+                }
+                // Notifications of other event types reference no objects to index.
+                None => ignored_ack_ids.push(ack_id),
+            }
+        }
+        self.ack_messages(ignored_ack_ids, ctx).await
+    }
+
+    /// Downloads the object, or returns `None` if it no longer exists.
+    async fn open_object(
+        &self,
+        pending_object: &PendingObject,
+        ctx: &SourceContext,
+    ) -> anyhow::Result<Option<Pin<Box<dyn AsyncBufRead + Send + Sync>>>> {
+        let request = GetObjectRequest {
+            bucket: pending_object.object.bucket.clone(),
+            object: pending_object.object.name.clone(),
+            ..Default::default()
+        };
+        let download_result = ctx
+            .protect_future(
+                self.storage_client
+                    .download_object(&request, &Range::default()),
+            )
+            .await;
+        let data = match download_result {
+            Ok(data) => data,
+            Err(StorageError::Response(error_response)) if error_response.code == 404 => {
+                return Ok(None);
+            }
+            Err(error) => {
+                return Err(anyhow::Error::new(error)
+                    .context(format!("Failed to get object `{}`.", pending_object.object)));
+            }
+        };
+        let reader = BufReader::new(Cursor::new(data));
+        if is_gzipped(
+            &pending_object.object,
+            pending_object.content_encoding_opt.as_deref(),
+        ) {
+            let mut decoder = GzipDecoder::new(reader);
+            decoder.multiple_members(true);
+            return Ok(Some(Box::pin(BufReader::new(decoder))));
+        }
+        Ok(Some(Box::pin(reader)))
+    }
+
+    /// Acknowledges the messages. Messages that fail to be acknowledged are delivered again.
+    async fn ack_messages(
+        &mut self,
+        ack_ids: Vec<String>,
+        ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        if ack_ids.is_empty() {
+            return Ok(());
+        }
+        let num_ack_ids = ack_ids.len() as u64;
+        ctx.protect_future(self.subscription.ack(ack_ids))
+            .await
+            .context("Failed to acknowledge Pub/Sub messages.")?;
+        self.state.num_acked_messages += num_ack_ids;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for GcsNotificationsSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let mut batch_num_bytes = 0;
+        let mut docs = Vec::new();
+        let mut ack_ids = Vec::new();
+        let mut is_idle = true;
+        let deadline = Instant::now() + EMIT_BATCHES_TIMEOUT;
+
+        while batch_num_bytes < TARGET_BATCH_NUM_BYTES && Instant::now() < deadline {
+            let object_reader = match &mut self.current_object_opt {
+                Some(object_reader) => object_reader,
+                None => {
+                    if self.pending_objects.is_empty() {
+                        self.pull_notifications(ctx).await?;
+                    }
+                    let pending_object = match self.pending_objects.pop_front() {
+                        Some(pending_object) => pending_object,
+                        None => break,
+                    };
+                    is_idle = false;
+
+                    match self.open_object(&pending_object, ctx).await? {
+                        Some(reader) => {
+                            self.current_object_opt = Some(ObjectReader {
+                                object: pending_object.object,
+                                ack_id: pending_object.ack_id,
+                                reader,
+                            });
+                        }
+                        None => {
+                            warn!(
+                                subscription=%self.subscription_name,
+                                object=%pending_object.object,
+                                "Object does not exist."
+                            );
+                            self.state.num_missing_objects += 1;
+                            ack_ids.push(pending_object.ack_id);
+                        }
+                    }
+                    ctx.record_progress();
+                    continue;
+                }
+            };
+            is_idle = false;
+
+            let mut line = Vec::new();
+            let num_bytes = ctx
+                .protect_future(object_reader.reader.read_until(b'\n', &mut line))
+                .await
+                .with_context(|| format!("Failed to read object `{}`.", object_reader.object))?;
+
+            if num_bytes == 0 {
+                self.state.num_objects_processed += 1;
+                if let Some(object_reader) = self.current_object_opt.take() {
+                    ack_ids.push(object_reader.ack_id);
+                }
+                continue;
+            }
+            batch_num_bytes += num_bytes as u64;
+            self.state.num_bytes_processed += num_bytes as u64;
+            self.state.num_lines_processed += 1;
+
+            match String::from_utf8(line) {
+                Ok(doc) if !doc.trim().is_empty() => docs.push(Bytes::from(doc)),
+                Ok(_) => {}
+                Err(error) => {
+                    warn!(
+                        object=%object_reader.object,
+                        error=?error,
+                        "Line contains invalid UTF-8 characters."
+                    );
+                    self.state.num_invalid_lines += 1;
+                }
+            }
+        }
+        if docs.is_empty() {
+            if ack_ids.is_empty() {
+                if is_idle {
+                    return Ok(PULL_COOL_DOWN);
+                }
+                return Ok(Duration::default());
+            }
+            // Documents emitted earlier from the objects of these notifications may still be
+            // waiting for publication.
+            if self.state.current_position > self.state.published_position {
+                self.pending_acks.push(self.state.current_position, ack_ids);
+            } else {
+                self.ack_messages(ack_ids, ctx).await?;
+            }
+            return Ok(Duration::default());
+        }
+        let previous_position = if self.state.current_position == 0 {
+            Position::Beginning
+        } else {
+            Position::from(self.state.current_position)
+        };
+        let current_position = self.state.current_position + docs.len() as u64;
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta
+            .record_partition_delta(
+                self.partition_id.clone(),
+                previous_position,
+                Position::from(current_position),
+            )
+            .context("Failed to record partition delta.")?;
+        self.state.current_position = current_position;
+        self.pending_acks.push(current_position, ack_ids);
+
+        let batch = RawDocBatch {
+            docs,
+            checkpoint_delta,
+        };
+        ctx.send_message(doc_processor_mailbox, batch).await?;
+        Ok(Duration::default())
+    }
+
+    async fn suggest_truncate(
+        &mut self,
+        checkpoint: SourceCheckpoint,
+        ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        let published_position = match checkpoint.position_for_partition(&self.partition_id) {
+            Some(Position::Offset(offset)) => offset.parse::<u64>()?,
+            _ => return Ok(()),
+        };
+        self.state.published_position = published_position;
+        let ack_ids = self.pending_acks.drain_up_to(published_position);
+        // If the acknowledgement fails, the notifications are redelivered and their objects
+        // indexed again.
+        self.ack_messages(ack_ids, ctx).await
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "GcsNotificationsSource{{source_id={}}}",
+            self.ctx.source_config.source_id
+        )
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        json!({
+            "index_id": self.ctx.index_id,
+            "source_id": self.ctx.source_config.source_id,
+            "subscription": self.subscription_name,
+            "partition_id": self.partition_id.as_str(),
+            "current_position": self.state.current_position,
+            "num_pending_objects": self.pending_objects.len(),
+            "num_pending_acks": self.pending_acks.num_pending_acks(),
+            "num_bytes_processed": self.state.num_bytes_processed,
+            "num_lines_processed": self.state.num_lines_processed,
+            "num_invalid_lines": self.state.num_invalid_lines,
+            "num_objects_processed": self.state.num_objects_processed,
+            "num_missing_objects": self.state.num_missing_objects,
+            "num_invalid_messages": self.state.num_invalid_messages,
+            "num_acked_messages": self.state.num_acked_messages,
+        })
+    }
+}
+
+/// Object resource sent as payload of the notifications in the `JSON_API_V1` format.
+#[derive(Deserialize)]
+struct ObjectResource {
+    #[serde(rename = "contentEncoding")]
+    content_encoding: Option<String>,
+}
+
+/// Extracts the object created and its content encoding, if known, from the attributes and the
+/// payload of a GCS notification. Returns `None` for the notifications of other event types.
+fn parse_notification(
+    attributes: &HashMap<String, String>,
+    data: &[u8],
+) -> anyhow::Result<Option<(GcsObjectRef, Option<String>)>> {
+    let event_type = attributes
+        .get("eventType")
+        .context("Message has no `eventType` attribute.")?;
+    if event_type != OBJECT_FINALIZE_EVENT_TYPE {
+        return Ok(None);
+    }
+    let bucket = attributes
+        .get("bucketId")
+        .context("Message has no `bucketId` attribute.")?;
+    let name = attributes
+        .get("objectId")
+        .context("Message has no `objectId` attribute.")?;
+    let content_encoding_opt =
+        if attributes.get("payloadFormat").map(String::as_str) == Some("JSON_API_V1") {
+            serde_json::from_slice::<ObjectResource>(data)
+                .context("Failed to parse object resource.")?
+                .content_encoding
+        } else {
+            None
+        };
+    let object = GcsObjectRef {
+        bucket: bucket.clone(),
+        name: name.clone(),
+    };
+    Ok(Some((object, content_encoding_opt)))
+}
+
+/// Objects stored with the `gzip` content encoding are served decompressed by GCS (decompressive
+/// transcoding), so only the other objects with the `.gz` extension are decompressed.
+fn is_gzipped(object: &GcsObjectRef, content_encoding_opt: Option<&str>) -> bool {
+    object.name.ends_with(".gz") && content_encoding_opt != Some("gzip")
+}
+
+/// Checks whether we can establish a connection to the Pub/Sub service and find the subscription.
+pub(super) async fn check_connectivity(params: GcsNotificationsSourceParams) -> anyhow::Result<()> {
+    let subscription = create_subscription(&pubsub_params(&params)).await?;
+    if !subscription.exists(None).await? {
+        bail!(
+            "Pub/Sub subscription `{}` does not exist.",
+            params.subscription
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification_attributes(
+        event_type: &str,
+        bucket: &str,
+        name: &str,
+    ) -> HashMap<String, String> {
+        HashMap::from([
+            ("eventType".to_string(), event_type.to_string()),
+            ("bucketId".to_string(), bucket.to_string()),
+            ("objectId".to_string(), name.to_string()),
+            ("payloadFormat".to_string(), "JSON_API_V1".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_parse_notification() {
+        {
+            let attributes =
+                notification_attributes("OBJECT_FINALIZE", "my-bucket", "logs/my app.ndjson");
+            let data = json!({
+                "kind": "storage#object",
+                "bucket": "my-bucket",
+                "name": "logs/my app.ndjson",
+                "size": "1024",
+            })
+            .to_string();
+            let (object, content_encoding_opt) = parse_notification(&attributes, data.as_bytes())
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                object,
+                GcsObjectRef {
+                    bucket: "my-bucket".to_string(),
+                    name: "logs/my app.ndjson".to_string(),
+                }
+            );
+            assert!(content_encoding_opt.is_none());
+        }
+        {
+            let attributes =
+                notification_attributes("OBJECT_FINALIZE", "my-bucket", "logs/app.ndjson.gz");
+            let data = json!({
+                "bucket": "my-bucket",
+                "name": "logs/app.ndjson.gz",
+                "contentEncoding": "gzip",
+            })
+            .to_string();
+            let (object, content_encoding_opt) = parse_notification(&attributes, data.as_bytes())
+                .unwrap()
+                .unwrap();
+            assert_eq!(content_encoding_opt.as_deref(), Some("gzip"));
+            assert!(!is_gzipped(&object, content_encoding_opt.as_deref()));
+            assert!(is_gzipped(&object, None));
+        }
+        {
+            let mut attributes =
+                notification_attributes("OBJECT_FINALIZE", "my-bucket", "logs/app.ndjson");
+            attributes.insert("payloadFormat".to_string(), "NONE".to_string());
+            let (object, content_encoding_opt) =
+                parse_notification(&attributes, b"").unwrap().unwrap();
+            assert_eq!(object.to_string(), "gs://my-bucket/logs/app.ndjson");
+            assert!(content_encoding_opt.is_none());
+        }
+        {
+            let attributes =
+                notification_attributes("OBJECT_DELETE", "my-bucket", "logs/app.ndjson");
+            assert!(parse_notification(&attributes, b"{}").unwrap().is_none());
+        }
+        {
+            parse_notification(&HashMap::new(), b"").unwrap_err();
+
+            let mut attributes =
+                notification_attributes("OBJECT_FINALIZE", "my-bucket", "logs/app.ndjson");
+            attributes.remove("objectId");
+            parse_notification(&attributes, b"{}").unwrap_err();
+        }
+    }
+}
@@ -23,15 +23,16 @@ use std::time::Duration;
 
 use async_trait::async_trait;
 use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
-use quickwit_ingest_api::{get_ingest_api_service, iter_doc_payloads, IngestApiService};
+use quickwit_ingest_api::{get_ingest_api_service, iter_docs, IngestApiService};
 use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
 use quickwit_proto::ingest_api::{
     CreateQueueIfNotExistsRequest, FetchRequest, FetchResponse, SuggestTruncateRequest,
 };
 use serde::Serialize;
+use tracing::warn;
 
 use super::{Source, SourceActor, SourceContext, TypedSourceFactory};
-use crate::actors::DocProcessor;
+use crate::actors::{apply_queue_retention, DocProcessor};
 use crate::models::RawDocBatch;
 use crate::source::SourceExecutionContext;
 
@@ -82,6 +83,21 @@ impl IngestApiSource {
         };
         ingest_api_service.ask_for_res(create_queue_req).await?;
 
+        // The retention policy of the queue must be in place before the first truncation. It is
+        // refreshed periodically by the `IngestApiGarbageCollector`.
+        match ctx.metastore.index_metadata(&ctx.index_id).await {
+            Ok(index_metadata) => {
+                apply_queue_retention(&ingest_api_service, &index_metadata).await?;
+            }
+            Err(error) => {
+                warn!(
+                    index_id=%ctx.index_id,
+                    error=?error,
+                    "Failed to apply ingest queue retention policy."
+                );
+            }
+        }
+
         let partition_id = PartitionId::from(ctx.index_id.clone());
         let previous_offset = if let Some(Position::Offset(offset_str)) =
             checkpoint.position_for_partition(&partition_id)
@@ -145,9 +161,7 @@ impl Source for IngestApiSource {
 
         // TODO use a timestamp (in the raw doc batch) given by at ingest time to be more accurate.
         let mut raw_doc_batch = RawDocBatch::default();
-        raw_doc_batch.docs.extend(
-            iter_doc_payloads(&doc_batch).map(|buff| String::from_utf8_lossy(buff).to_string()),
-        );
+        raw_doc_batch.docs.extend(iter_docs(&doc_batch));
         let current_offset = first_position + raw_doc_batch.docs.len() as u64 - 1;
         let partition_id = self.partition_id.clone();
         raw_doc_batch
@@ -165,7 +179,7 @@ impl Source for IngestApiSource {
     }
 
     async fn suggest_truncate(
-        &self,
+        &mut self,
         checkpoint: SourceCheckpoint,
         _ctx: &ActorContext<SourceActor>,
     ) -> anyhow::Result<()> {
@@ -217,10 +231,10 @@ mod tests {
     use quickwit_actors::{create_test_mailbox, Universe};
     use quickwit_common::rand::append_random_suffix;
     use quickwit_config::{SourceConfig, SourceParams, INGEST_API_SOURCE_ID};
-    use quickwit_ingest_api::{add_doc, init_ingest_api};
+    use quickwit_ingest_api::{init_ingest_api, DocBatchBuilder};
     use quickwit_metastore::checkpoint::{SourceCheckpoint, SourceCheckpointDelta};
     use quickwit_metastore::metastore_for_test;
-    use quickwit_proto::ingest_api::{DocBatch, IngestRequest};
+    use quickwit_proto::ingest_api::IngestRequest;
 
     use super::*;
     use crate::source::SourceActor;
@@ -229,22 +243,18 @@ mod tests {
         let mut doc_batches = vec![];
         let mut doc_id = 0usize;
         for _ in 0..num_batch {
-            let mut doc_batch = DocBatch {
-                index_id: index_id.clone(),
-                ..Default::default()
-            };
-            while doc_batch.doc_lens.len() < batch_size {
-                add_doc(
+            let mut doc_batch_builder = DocBatchBuilder::new(index_id.clone());
+            while doc_batch_builder.num_docs() < batch_size {
+                doc_batch_builder.add_doc(
                     format!(
                         "{:0>6} - The quick brown fox jumps over the lazy dog",
                         doc_id
                     )
                     .as_bytes(),
-                    &mut doc_batch,
                 );
                 doc_id += 1;
             }
-            doc_batches.push(doc_batch);
+            doc_batches.push(doc_batch_builder.build());
         }
         IngestRequest { doc_batches }
     }
@@ -253,6 +263,11 @@ mod tests {
         SourceConfig {
             source_id: INGEST_API_SOURCE_ID.to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::IngestApi,
         }
@@ -279,6 +294,7 @@ mod tests {
         let ingest_api_source_actor = SourceActor {
             source: Box::new(ingest_api_source),
             doc_processor_mailbox,
+            rate_limiter_opt: None,
         };
         let (_ingest_api_source_mailbox, ingest_api_source_handle) =
             universe.spawn_builder().spawn(ingest_api_source_actor);
@@ -303,7 +319,7 @@ mod tests {
         );
         let doc_batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
         assert_eq!(doc_batches.len(), 2);
-        assert!(doc_batches[1].docs[0].starts_with("038462"));
+        assert!(doc_batches[1].docs[0].starts_with(b"038462"));
         Ok(())
     }
 
@@ -337,6 +353,7 @@ mod tests {
         let ingest_api_source_actor = SourceActor {
             source: Box::new(ingest_api_source),
             doc_processor_mailbox,
+            rate_limiter_opt: None,
         };
         let (_ingest_api_source_mailbox, ingest_api_source_handle) =
             universe.spawn_builder().spawn(ingest_api_source_actor);
@@ -361,7 +378,7 @@ mod tests {
         );
         let doc_batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
         assert_eq!(doc_batches.len(), 1);
-        assert!(doc_batches[0].docs[0].starts_with("001201"));
+        assert!(doc_batches[0].docs[0].starts_with(b"001201"));
         Ok(())
     }
 
@@ -386,6 +403,7 @@ mod tests {
         let ingest_api_source_actor = SourceActor {
             source: Box::new(ingest_api_source),
             doc_processor_mailbox,
+            rate_limiter_opt: None,
         };
         let (_ingest_api_source_mailbox, ingest_api_source_handle) =
             universe.spawn_builder().spawn(ingest_api_source_actor);
@@ -410,7 +428,7 @@ mod tests {
         );
         let doc_batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
         assert_eq!(doc_batches.len(), 1);
-        assert!(doc_batches[0].docs[0].starts_with("000000"));
+        assert!(doc_batches[0].docs[0].starts_with(b"000000"));
         Ok(())
     }
 }
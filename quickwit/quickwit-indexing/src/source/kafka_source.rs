@@ -17,23 +17,27 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, bail, Context};
 use async_trait::async_trait;
+use bytes::Bytes;
 use itertools::Itertools;
 use oneshot;
-use quickwit_actors::{ActorExitStatus, Mailbox};
-use quickwit_config::KafkaSourceParams;
+use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
+use quickwit_config::{
+    KafkaClientFeatures, KafkaFlavor, KafkaPartitionAssignment, KafkaSourceParams,
+};
 use quickwit_metastore::checkpoint::{
     PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
 };
 use rdkafka::config::{ClientConfig, RDKafkaLogLevel};
 use rdkafka::consumer::{
-    BaseConsumer, Consumer, ConsumerContext, DefaultConsumerContext, Rebalance, RebalanceProtocol,
+    BaseConsumer, CommitMode, Consumer, ConsumerContext, DefaultConsumerContext, Rebalance,
+    RebalanceProtocol,
 };
 use rdkafka::error::KafkaError;
 use rdkafka::message::BorrowedMessage;
@@ -47,7 +51,9 @@ use tracing::{debug, info, warn};
 
 use crate::actors::DocProcessor;
 use crate::models::{NewPublishLock, PublishLock, RawDocBatch};
-use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
+use crate::source::{
+    Source, SourceActor, SourceContext, SourceExecutionContext, TypedSourceFactory,
+};
 
 /// Number of bytes after which we cut a new batch.
 ///
@@ -87,6 +93,7 @@ enum KafkaEvent {
         assignment_tx: oneshot::Sender<Vec<(i32, Offset)>>,
     },
     RevokePartitions {
+        partitions: Vec<i32>,
         ack_tx: oneshot::Sender<()>,
     },
     PartitionEOF(i32),
@@ -95,7 +102,7 @@ enum KafkaEvent {
 
 #[derive(Debug)]
 struct KafkaMessage {
-    doc_opt: Option<String>,
+    doc_opt: Option<Bytes>,
     payload_len: u64,
     partition: i32,
     offset: i64,
@@ -139,6 +146,9 @@ macro_rules! return_if_err {
 /// - Broker waits for ALL the consumers to ack the revoke notification (synchronization barrier).
 /// - Consumers receive new partition assignmennts.
 ///
+/// With the incremental cooperative protocol, the revoke and assign notifications only list the
+/// partitions that move, and consumers keep consuming the other partitions.
+///
 /// The API of the rebalance callback is better explained in the docs of `librdkafka`:
 /// <https://docs.confluent.io/2.0.0/clients/librdkafka/classRdKafka_1_1RebalanceCb.html>
 impl ConsumerContext for RdKafkaContext {
@@ -150,7 +160,7 @@ impl ConsumerContext for RdKafkaContext {
             let (ack_tx, ack_rx) = oneshot::channel();
             return_if_err!(
                 self.events_tx
-                    .blocking_send(KafkaEvent::RevokePartitions { ack_tx }),
+                    .blocking_send(KafkaEvent::RevokePartitions { partitions, ack_tx }),
                 "Failed to send revoke message to source."
             );
             return_if_err!(ack_rx.recv(), "Failed to receive revoke ack from source");
@@ -206,8 +216,8 @@ pub struct KafkaSourceState {
     pub assigned_partitions: HashMap<i32, PartitionId>,
     /// Offset for each partition of the last message received.
     pub current_positions: HashMap<i32, Position>,
-    /// Number of inactive partitions, i.e., that have reached EOF.
-    pub num_inactive_partitions: usize,
+    /// Inactive partitions, i.e., that have reached EOF.
+    pub inactive_partitions: HashSet<i32>,
     /// Number of bytes processed by the source.
     pub num_bytes_processed: u64,
     /// Number of messages processed by the source (including invalid messages).
@@ -224,6 +234,8 @@ pub struct KafkaSource {
     topic: String,
     state: KafkaSourceState,
     backfill_mode_enabled: bool,
+    offset_commit_enabled: bool,
+    partition_assignment: KafkaPartitionAssignment,
     events_rx: mpsc::Receiver<KafkaEvent>,
     consumer: Arc<RdKafkaConsumer>,
     poll_loop_jh: JoinHandle<()>,
@@ -245,21 +257,27 @@ impl KafkaSource {
     pub async fn try_new(
         ctx: Arc<SourceExecutionContext>,
         params: KafkaSourceParams,
-        _ignored_checkpoint: SourceCheckpoint,
+        checkpoint: SourceCheckpoint,
     ) -> anyhow::Result<Self> {
         let topic = params.topic.clone();
         let backfill_mode_enabled = params.enable_backfill_mode;
+        let offset_commit_enabled = params.enable_offset_commit;
+        let partition_assignment = params.partition_assignment;
 
         let (events_tx, events_rx) = mpsc::channel(100);
-        let consumer = create_consumer(
-            &ctx.index_id,
-            &ctx.source_config.source_id,
-            params,
-            events_tx.clone(),
-        )?;
-        consumer
-            .subscribe(&[&topic])
-            .with_context(|| format!("Failed to subscribe to topic `{topic}`."))?;
+        let consumer = create_consumer(&ctx, params, events_tx.clone())?;
+        let mut state = KafkaSourceState::default();
+
+        match partition_assignment {
+            // The positions of the partitions are fetched from the metastore when the consumer
+            // group assigns them, so the checkpoint is ignored.
+            KafkaPartitionAssignment::ConsumerGroup => consumer
+                .subscribe(&[&topic])
+                .with_context(|| format!("Failed to subscribe to topic `{topic}`."))?,
+            KafkaPartitionAssignment::Static => {
+                assign_static_partitions(&ctx, &consumer, &topic, &checkpoint, &mut state).await?
+            }
+        }
         let poll_loop_jh = spawn_consumer_poll_loop(consumer.clone(), events_tx);
         let publish_lock = PublishLock::default();
 
@@ -273,17 +291,17 @@ impl KafkaSource {
             source_id=%ctx.source_config.source_id,
             group_id=%consumer.client().context().group_id,
             topic=%topic,
+            partition_assignment=?partition_assignment,
             rebalance_protocol=%rebalance_protocol_str,
             "Starting Kafka source."
         );
-        let state = KafkaSourceState {
-            ..Default::default()
-        };
         Ok(KafkaSource {
             ctx,
             topic,
             state,
             backfill_mode_enabled,
+            offset_commit_enabled,
+            partition_assignment,
             events_rx,
             consumer,
             poll_loop_jh,
@@ -344,25 +362,7 @@ impl KafkaSource {
         partitions: &[i32],
         assignment_tx: oneshot::Sender<Vec<(i32, Offset)>>,
     ) -> anyhow::Result<()> {
-        let index_metadata = ctx
-            .protect_future(self.ctx.metastore.index_metadata(&self.ctx.index_id))
-            .await
-            .with_context(|| {
-                format!(
-                    "Failed to fetch index metadata for index `{}`.",
-                    self.ctx.index_id
-                )
-            })?;
-        let checkpoint = index_metadata
-            .checkpoint
-            .source_checkpoint(&self.ctx.source_config.source_id)
-            .cloned()
-            .unwrap_or_default();
-
-        self.state.assigned_partitions.clear();
-        self.state.current_positions.clear();
-        self.state.num_inactive_partitions = 0;
-
+        let checkpoint = self.fetch_source_checkpoint(ctx).await?;
         let mut next_offsets: Vec<(i32, Offset)> = Vec::with_capacity(partitions.len());
 
         for &partition in partitions {
@@ -371,19 +371,14 @@ impl KafkaSource {
                 .position_for_partition(&partition_id)
                 .cloned()
                 .unwrap_or(Position::Beginning);
-            let next_offset = match &current_position {
-                Position::Beginning => Offset::Beginning,
-                Position::Offset(offset_str) => {
-                    let offset: i64 = offset_str.parse().expect("Failed to parse checkpoint position to i64. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.");
-                    Offset::Offset(offset + 1)
-                }
-            };
+            let next_offset = next_offset_for_position(&current_position);
             self.state
                 .assigned_partitions
                 .insert(partition, partition_id);
             self.state
                 .current_positions
                 .insert(partition, current_position);
+            self.state.inactive_partitions.remove(&partition);
             next_offsets.push((partition, next_offset));
         }
         assignment_tx
@@ -397,9 +392,25 @@ impl KafkaSource {
         ctx: &SourceContext,
         doc_processor_mailbox: &Mailbox<DocProcessor>,
         batch: &mut BatchBuilder,
+        partitions: &[i32],
         ack_tx: oneshot::Sender<()>,
     ) -> anyhow::Result<()> {
         ctx.protect_future(self.publish_lock.kill()).await;
+
+        for partition in partitions {
+            self.state.assigned_partitions.remove(partition);
+            self.state.current_positions.remove(partition);
+        }
+        self.state.inactive_partitions.clear();
+
+        // With the incremental cooperative protocol, the consumer keeps the partitions that are
+        // not revoked. The work in progress on these partitions is discarded along with the
+        // publish lock, so we rewind them to their last published position. This must happen
+        // before acknowledging the revocation: the consumer does not fetch any new message until
+        // then.
+        if !self.state.assigned_partitions.is_empty() {
+            self.rewind_assigned_partitions(ctx).await?;
+        }
         ack_tx
             .send(())
             .map_err(|_| anyhow!("Consumer context was dropped."))?;
@@ -415,28 +426,79 @@ impl KafkaSource {
         Ok(())
     }
 
+    async fn rewind_assigned_partitions(&mut self, ctx: &SourceContext) -> anyhow::Result<()> {
+        let checkpoint = self.fetch_source_checkpoint(ctx).await?;
+        let mut seeks: Vec<(i32, Offset)> =
+            Vec::with_capacity(self.state.assigned_partitions.len());
+
+        for (&partition, partition_id) in &self.state.assigned_partitions {
+            let current_position = checkpoint
+                .position_for_partition(partition_id)
+                .cloned()
+                .unwrap_or(Position::Beginning);
+            seeks.push((partition, next_offset_for_position(&current_position)));
+            self.state
+                .current_positions
+                .insert(partition, current_position);
+        }
+        let consumer = self.consumer.clone();
+        let topic = self.topic.clone();
+        ctx.protect_future(spawn_blocking(move || {
+            seek_partitions(&consumer, &topic, seeks)
+        }))
+        .await??;
+        Ok(())
+    }
+
+    async fn fetch_source_checkpoint(
+        &self,
+        ctx: &SourceContext,
+    ) -> anyhow::Result<SourceCheckpoint> {
+        let index_metadata = ctx
+            .protect_future(self.ctx.metastore.index_metadata(&self.ctx.index_id))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to fetch index metadata for index `{}`.",
+                    self.ctx.index_id
+                )
+            })?;
+        let checkpoint = index_metadata
+            .checkpoint
+            .source_checkpoint(&self.ctx.source_config.source_id)
+            .cloned()
+            .unwrap_or_default();
+        Ok(checkpoint)
+    }
+
     fn process_partition_eof(&mut self, partition: i32) {
-        self.state.num_inactive_partitions += 1;
+        self.state.inactive_partitions.insert(partition);
 
         info!(
             topic=%self.topic,
             partition=%partition,
-            num_inactive_partitions=?self.state.num_inactive_partitions,
+            num_inactive_partitions=?self.state.inactive_partitions.len(),
             "Reached end of partition."
         );
     }
 
     fn should_exit(&self) -> bool {
+        // A pipeline may not get any partition with the static partition assignment.
+        if self.partition_assignment == KafkaPartitionAssignment::Static
+            && self.state.assigned_partitions.is_empty()
+        {
+            return self.backfill_mode_enabled;
+        }
         self.backfill_mode_enabled
             // This check ensures that we don't shutdown the source before the first partition assignment.
-            && self.state.num_inactive_partitions > 0
-            && self.state.num_inactive_partitions == self.state.assigned_partitions.len()
+            && !self.state.inactive_partitions.is_empty()
+            && self.state.inactive_partitions.len() == self.state.assigned_partitions.len()
     }
 }
 
 #[derive(Debug, Default)]
 struct BatchBuilder {
-    docs: Vec<String>,
+    docs: Vec<Bytes>,
     num_bytes: u64,
     checkpoint_delta: SourceCheckpointDelta,
 }
@@ -455,7 +517,7 @@ impl BatchBuilder {
         self.checkpoint_delta = SourceCheckpointDelta::default();
     }
 
-    fn push(&mut self, doc: String, num_bytes: u64) {
+    fn push(&mut self, doc: Bytes, num_bytes: u64) {
         self.docs.push(doc);
         self.num_bytes += num_bytes;
     }
@@ -491,7 +553,7 @@ impl Source for KafkaSource {
                     match event {
                         KafkaEvent::Message(message) => self.process_message(message, &mut batch).await?,
                         KafkaEvent::AssignPartitions { partitions, assignment_tx} => self.process_assign_partitions(ctx, &partitions, assignment_tx).await?,
-                        KafkaEvent::RevokePartitions { ack_tx } => self.process_revoke_partitions(ctx, doc_processor_mailbox, &mut batch, ack_tx).await?,
+                        KafkaEvent::RevokePartitions { partitions, ack_tx } => self.process_revoke_partitions(ctx, doc_processor_mailbox, &mut batch, &partitions, ack_tx).await?,
                         KafkaEvent::PartitionEOF(partition) => self.process_partition_eof(partition),
                         KafkaEvent::Error(error) => Err(ActorExitStatus::from(error))?,
                     }
@@ -536,6 +598,35 @@ impl Source for KafkaSource {
         Ok(())
     }
 
+    /// Commits the offsets of the published messages to the consumer group of the source, so
+    /// that lag-monitoring tools report the progress of the source. The committed offsets are
+    /// informational only: the source always resumes from the positions of its checkpoint.
+    async fn suggest_truncate(
+        &mut self,
+        checkpoint: SourceCheckpoint,
+        _ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        if !self.offset_commit_enabled {
+            return Ok(());
+        }
+        let offsets = offsets_to_commit(&self.state.assigned_partitions, &checkpoint);
+        if offsets.is_empty() {
+            return Ok(());
+        }
+        let mut topic_partition_list = TopicPartitionList::with_capacity(offsets.len());
+        for (partition, offset) in offsets {
+            topic_partition_list
+                .add_partition_offset(&self.topic, partition, offset)
+                .context("Failed to add partition offset.")?;
+        }
+        // Asynchronous commits do not block the source, and are retried with the next
+        // publication if they fail.
+        self.consumer
+            .commit(&topic_partition_list, CommitMode::Async)
+            .with_context(|| format!("Failed to commit offsets of topic `{}`.", self.topic))?;
+        Ok(())
+    }
+
     fn name(&self) -> String {
         format!(
             "KafkaSource{{source_id={}}}",
@@ -559,7 +650,7 @@ impl Source for KafkaSource {
             "topic": self.topic,
             "assigned_partitions": assigned_partitions,
             "current_positions": current_positions,
-            "num_inactive_partitions": self.state.num_inactive_partitions,
+            "num_inactive_partitions": self.state.inactive_partitions.len(),
             "num_bytes_processed": self.state.num_bytes_processed,
             "num_messages_processed": self.state.num_messages_processed,
             "num_invalid_messages": self.state.num_invalid_messages,
@@ -595,6 +686,141 @@ fn spawn_consumer_poll_loop(
     })
 }
 
+/// Returns the offset of the message following the position.
+fn next_offset_for_position(position: &Position) -> Offset {
+    match position {
+        Position::Beginning => Offset::Beginning,
+        Position::Offset(offset_str) => {
+            let offset: i64 = offset_str.parse().expect("Failed to parse checkpoint position to i64. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.");
+            Offset::Offset(offset + 1)
+        }
+    }
+}
+
+/// Returns the offsets to commit to the consumer group for the partitions assigned to the
+/// source, i.e. the offsets of the messages following the published positions. Kafka expects the
+/// offset of the next message to consume.
+fn offsets_to_commit(
+    assigned_partitions: &HashMap<i32, PartitionId>,
+    checkpoint: &SourceCheckpoint,
+) -> Vec<(i32, Offset)> {
+    assigned_partitions
+        .iter()
+        .filter_map(|(partition, partition_id)| {
+            match checkpoint.position_for_partition(partition_id) {
+                Some(position @ Position::Offset(_)) => {
+                    Some((*partition, next_offset_for_position(position)))
+                }
+                _ => None,
+            }
+        })
+        .sorted_by_key(|(partition, _)| *partition)
+        .collect()
+}
+
+/// Returns the partitions consumed by the pipeline `pipeline_ord` with the static partition
+/// assignment.
+fn static_partitions(num_partitions: i32, num_pipelines: usize, pipeline_ord: usize) -> Vec<i32> {
+    (0..num_partitions)
+        .filter(|partition| *partition as usize % num_pipelines == pipeline_ord)
+        .collect()
+}
+
+/// Assigns its share of the partitions of the topic to the pipeline, starting from the positions
+/// recorded in the checkpoint. Partitions added to the topic afterwards are only consumed once
+/// the pipeline restarts.
+async fn assign_static_partitions(
+    ctx: &SourceExecutionContext,
+    consumer: &Arc<RdKafkaConsumer>,
+    topic: &str,
+    checkpoint: &SourceCheckpoint,
+    state: &mut KafkaSourceState,
+) -> anyhow::Result<()> {
+    let num_partitions = fetch_num_partitions(consumer.clone(), topic.to_string()).await?;
+    let num_pipelines = ctx.source_config.num_pipelines;
+    let partitions = static_partitions(num_partitions, num_pipelines, ctx.pipeline_ord);
+
+    if partitions.is_empty() {
+        warn!(
+            topic=%topic,
+            num_partitions=%num_partitions,
+            num_pipelines=%num_pipelines,
+            pipeline_ord=%ctx.pipeline_ord,
+            "The source runs more pipelines than the topic has partitions: no partition assigned."
+        );
+    }
+    let mut tpl = TopicPartitionList::new();
+
+    for partition in partitions {
+        let partition_id = PartitionId::from(partition as i64);
+        let current_position = checkpoint
+            .position_for_partition(&partition_id)
+            .cloned()
+            .unwrap_or(Position::Beginning);
+        tpl.add_partition_offset(
+            topic,
+            partition,
+            next_offset_for_position(&current_position),
+        )?;
+        state.assigned_partitions.insert(partition, partition_id);
+        state.current_positions.insert(partition, current_position);
+    }
+    consumer
+        .assign(&tpl)
+        .with_context(|| format!("Failed to assign partitions of topic `{topic}`."))?;
+    info!(
+        topic=%topic,
+        partitions=%state.assigned_partitions.keys().sorted().join(","),
+        "Static partition assignment"
+    );
+    Ok(())
+}
+
+async fn fetch_num_partitions(
+    consumer: Arc<RdKafkaConsumer>,
+    topic: String,
+) -> anyhow::Result<i32> {
+    let timeout = Timeout::After(Duration::from_secs(5));
+    spawn_blocking(move || {
+        let cluster_metadata = consumer
+            .fetch_metadata(Some(&topic), timeout)
+            .with_context(|| format!("Failed to fetch metadata for topic `{topic}`."))?;
+        let num_partitions = cluster_metadata
+            .topics()
+            .iter()
+            .find(|topic_metadata| topic_metadata.name() == topic)
+            .map(|topic_metadata| topic_metadata.partitions().len())
+            .unwrap_or(0);
+        if num_partitions == 0 {
+            bail!("Topic `{topic}` does not exist or has no partitions.");
+        }
+        Ok(num_partitions as i32)
+    })
+    .await?
+}
+
+/// Moves the consumer position of the partitions. Seeking purges the messages of the partitions
+/// already fetched by the consumer.
+fn seek_partitions(
+    consumer: &RdKafkaConsumer,
+    topic: &str,
+    seeks: Vec<(i32, Offset)>,
+) -> anyhow::Result<()> {
+    for (partition, offset) in seeks {
+        consumer
+            .seek(
+                topic,
+                partition,
+                offset,
+                Timeout::After(Duration::from_secs(5)),
+            )
+            .with_context(|| {
+                format!("Failed to seek partition `{partition}` of topic `{topic}`.")
+            })?;
+    }
+    Ok(())
+}
+
 /// Returns the preceding `Position` for the offset.
 fn previous_position_for_offset(offset: i64) -> Position {
     if offset == 0 {
@@ -606,7 +832,7 @@ fn previous_position_for_offset(offset: i64) -> Position {
 
 /// Checks whether we can establish a connection to the Kafka broker.
 pub(super) async fn check_connectivity(params: KafkaSourceParams) -> anyhow::Result<()> {
-    let mut client_config = parse_client_params(params.client_params)?;
+    let mut client_config = create_client_config(&params)?;
 
     let consumer: BaseConsumer<DefaultConsumerContext> = client_config
         .set("group.id", "quickwit-connectivity-check".to_string())
@@ -634,18 +860,137 @@ pub(super) async fn check_connectivity(params: KafkaSourceParams) -> anyhow::Res
     Ok(())
 }
 
-/// Creates a new `KafkaSourceConsumer`.
-fn create_consumer(
-    index_id: &str,
-    source_id: &str,
+/// Returns the position of the last message of each non-empty partition of the topic. A backfill
+/// source records these positions as the boundary between its historical and live data.
+pub(super) async fn fetch_end_positions(
     params: KafkaSourceParams,
-    events_tx: mpsc::Sender<KafkaEvent>,
-) -> anyhow::Result<Arc<RdKafkaConsumer>> {
-    let mut client_config = parse_client_params(params.client_params)?;
+) -> anyhow::Result<Vec<(PartitionId, Position)>> {
+    let mut client_config = create_client_config(&params)?;
+
+    let consumer: BaseConsumer<DefaultConsumerContext> = client_config
+        .set("group.id", "quickwit-backfill-boundary".to_string())
+        .set_log_level(RDKafkaLogLevel::Error)
+        .create()?;
+    let topic = params.topic;
+    let timeout = Timeout::After(Duration::from_secs(5));
+
+    spawn_blocking(move || {
+        let cluster_metadata = consumer
+            .fetch_metadata(Some(&topic), timeout)
+            .with_context(|| format!("Failed to fetch metadata for topic `{topic}`."))?;
+        let partitions: Vec<i32> = cluster_metadata
+            .topics()
+            .iter()
+            .filter(|topic_metadata| topic_metadata.name() == topic)
+            .flat_map(|topic_metadata| topic_metadata.partitions())
+            .map(|partition_metadata| partition_metadata.id())
+            .collect();
+        if partitions.is_empty() {
+            bail!("Topic `{topic}` does not exist or has no partitions.");
+        }
+        let mut end_positions = Vec::with_capacity(partitions.len());
+
+        for partition in partitions {
+            let (_low_watermark, high_watermark) = consumer
+                .fetch_watermarks(&topic, partition, timeout)
+                .with_context(|| {
+                    format!(
+                        "Failed to fetch watermarks of partition `{partition}` of topic `{topic}`."
+                    )
+                })?;
+            if high_watermark > 0 {
+                end_positions.push((
+                    PartitionId::from(partition as i64),
+                    previous_position_for_offset(high_watermark),
+                ));
+            }
+        }
+        Ok(end_positions)
+    })
+    .await?
+}
+
+/// Creates the client configuration shared by the consumers of the source and the connectivity
+/// check: the client parameters, the defaults of the broker flavor, and the fetch settings.
+fn create_client_config(params: &KafkaSourceParams) -> anyhow::Result<ClientConfig> {
+    let mut client_config = parse_client_params(params.client_params.clone())?;
+    apply_flavor_defaults(&mut client_config, params.flavor);
+    apply_fetch_settings(&mut client_config, &params.client_features);
+    Ok(client_config)
+}
+
+/// Sets the client parameters required by the broker flavor, unless the user provided them.
+fn apply_flavor_defaults(client_config: &mut ClientConfig, flavor: KafkaFlavor) {
+    let defaults: &[(&str, &str)] = match flavor {
+        KafkaFlavor::Kafka => &[],
+        // <https://learn.microsoft.com/en-us/azure/event-hubs/apache-kafka-configurations>
+        KafkaFlavor::AzureEventHubs => &[
+            ("security.protocol", "SASL_SSL"),
+            ("sasl.mechanisms", "PLAIN"),
+            ("sasl.username", "$ConnectionString"),
+            ("socket.keepalive.enable", "true"),
+            ("metadata.max.age.ms", "180000"),
+        ],
+    };
+    for (key, value) in defaults {
+        if client_config.get(key).is_none() {
+            client_config.set(*key, *value);
+        }
+    }
+}
+
+fn apply_fetch_settings(client_config: &mut ClientConfig, client_features: &KafkaClientFeatures) {
+    if let Some(fetch_min_bytes) = client_features.fetch_min_bytes {
+        client_config.set("fetch.min.bytes", fetch_min_bytes.to_string());
+    }
+    if let Some(fetch_max_wait_ms) = client_features.fetch_max_wait_ms {
+        client_config.set("fetch.wait.max.ms", fetch_max_wait_ms.to_string());
+    }
+    if let Some(max_partition_fetch_bytes) = client_features.max_partition_fetch_bytes {
+        client_config.set(
+            "max.partition.fetch.bytes",
+            max_partition_fetch_bytes.to_string(),
+        );
+    }
+}
+
+/// Sets the consumer group parameters of a consumer of the source.
+fn apply_group_settings(
+    client_config: &mut ClientConfig,
+    ctx: &SourceExecutionContext,
+    client_features: &KafkaClientFeatures,
+) -> String {
+    let index_id = &ctx.index_id;
+    let source_id = &ctx.source_config.source_id;
 
     // Group ID is limited to 255 characters.
     let mut group_id = format!("quickwit-{index_id}-{source_id}");
     group_id.truncate(255);
+    client_config.set("group.id", &group_id);
+
+    if client_features.incremental_rebalancing {
+        client_config.set("partition.assignment.strategy", "cooperative-sticky");
+    }
+    if client_features.static_membership {
+        // The group instance ID must be unique within the group and stable across the restarts
+        // of the pipeline.
+        let node_id = &ctx.node_id;
+        let pipeline_ord = ctx.pipeline_ord;
+        let mut group_instance_id = format!("{group_id}-{node_id}-{pipeline_ord}");
+        group_instance_id.truncate(255);
+        client_config.set("group.instance.id", group_instance_id);
+    }
+    group_id
+}
+
+/// Creates a new `KafkaSourceConsumer`.
+fn create_consumer(
+    ctx: &SourceExecutionContext,
+    params: KafkaSourceParams,
+    events_tx: mpsc::Sender<KafkaEvent>,
+) -> anyhow::Result<Arc<RdKafkaConsumer>> {
+    let mut client_config = create_client_config(&params)?;
+    let group_id = apply_group_settings(&mut client_config, ctx, &params.client_features);
 
     let log_level = parse_client_log_level(params.client_log_level)?;
     let consumer: RdKafkaConsumer = client_config
@@ -654,7 +999,6 @@ fn create_consumer(
             "enable.partition.eof",
             params.enable_backfill_mode.to_string(),
         )
-        .set("group.id", &group_id)
         .set_log_level(log_level)
         .create_with_context(RdKafkaContext {
             group_id,
@@ -707,12 +1051,11 @@ fn parse_client_params(client_params: serde_json::Value) -> anyhow::Result<Clien
     Ok(client_config)
 }
 
-/// Converts the raw bytes of the message payload to a `String` skipping corrupted or empty
-/// messages.
-fn parse_message_payload(message: &BorrowedMessage) -> Option<String> {
+/// Copies the raw bytes of the message payload skipping corrupted or empty messages.
+fn parse_message_payload(message: &BorrowedMessage) -> Option<Bytes> {
     match message.payload_view::<str>() {
         Some(Ok(payload)) if !payload.is_empty() => {
-            let doc = payload.to_string();
+            let doc = Bytes::copy_from_slice(payload.as_bytes());
             return Some(doc);
         }
         Some(Ok(_)) => debug!(
@@ -741,6 +1084,148 @@ fn parse_message_payload(message: &BorrowedMessage) -> Option<String> {
     None
 }
 
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use quickwit_config::SourceConfig;
+    use quickwit_metastore::MockMetastore;
+
+    use super::*;
+
+    #[test]
+    fn test_create_client_config() {
+        {
+            let params = KafkaSourceParams {
+                topic: "my-topic".to_string(),
+                client_log_level: None,
+                client_params: json!({"bootstrap.servers": "localhost:9092"}),
+                enable_backfill_mode: false,
+                flavor: KafkaFlavor::Kafka,
+                client_features: KafkaClientFeatures::default(),
+                partition_assignment: KafkaPartitionAssignment::ConsumerGroup,
+                enable_offset_commit: false,
+            };
+            let client_config = create_client_config(&params).unwrap();
+            assert_eq!(
+                client_config.get("bootstrap.servers"),
+                Some("localhost:9092")
+            );
+            assert!(client_config.get("security.protocol").is_none());
+            assert!(client_config.get("fetch.min.bytes").is_none());
+        }
+        {
+            let params = KafkaSourceParams {
+                topic: "my-topic".to_string(),
+                client_log_level: None,
+                client_params: json!({
+                    "bootstrap.servers": "my-namespace.servicebus.windows.net:9093",
+                    "sasl.password": "Endpoint=sb://my-namespace.servicebus.windows.net/",
+                    "metadata.max.age.ms": 60000,
+                }),
+                enable_backfill_mode: false,
+                flavor: KafkaFlavor::AzureEventHubs,
+                client_features: KafkaClientFeatures {
+                    fetch_min_bytes: Some(1024),
+                    fetch_max_wait_ms: Some(250),
+                    max_partition_fetch_bytes: Some(4_194_304),
+                    ..Default::default()
+                },
+                partition_assignment: KafkaPartitionAssignment::ConsumerGroup,
+                enable_offset_commit: false,
+            };
+            let client_config = create_client_config(&params).unwrap();
+            assert_eq!(client_config.get("security.protocol"), Some("SASL_SSL"));
+            assert_eq!(client_config.get("sasl.mechanisms"), Some("PLAIN"));
+            assert_eq!(
+                client_config.get("sasl.username"),
+                Some("$ConnectionString")
+            );
+            assert_eq!(client_config.get("metadata.max.age.ms"), Some("60000"));
+            assert_eq!(client_config.get("fetch.min.bytes"), Some("1024"));
+            assert_eq!(client_config.get("fetch.wait.max.ms"), Some("250"));
+            assert_eq!(
+                client_config.get("max.partition.fetch.bytes"),
+                Some("4194304")
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply_group_settings() {
+        let mut source_config = SourceConfig::ingest_api_default();
+        source_config.source_id = "my-source".to_string();
+        let ctx = SourceExecutionContext::for_test(
+            Arc::new(MockMetastore::default()),
+            "my-index",
+            PathBuf::from("./queues"),
+            source_config,
+        );
+        {
+            let mut client_config = ClientConfig::new();
+            let group_id =
+                apply_group_settings(&mut client_config, &ctx, &KafkaClientFeatures::default());
+            assert_eq!(group_id, "quickwit-my-index-my-source");
+            assert_eq!(client_config.get("group.id"), Some(group_id.as_str()));
+            assert!(client_config.get("partition.assignment.strategy").is_none());
+            assert!(client_config.get("group.instance.id").is_none());
+        }
+        {
+            let mut client_config = ClientConfig::new();
+            let client_features = KafkaClientFeatures {
+                incremental_rebalancing: true,
+                static_membership: true,
+                ..Default::default()
+            };
+            apply_group_settings(&mut client_config, &ctx, &client_features);
+            assert_eq!(
+                client_config.get("partition.assignment.strategy"),
+                Some("cooperative-sticky")
+            );
+            assert_eq!(
+                client_config.get("group.instance.id"),
+                Some("quickwit-my-index-my-source-test-node-0")
+            );
+        }
+    }
+
+    #[test]
+    fn test_offsets_to_commit() {
+        let assigned_partitions = HashMap::from([
+            (0, PartitionId::from(0i64)),
+            (1, PartitionId::from(1i64)),
+            (2, PartitionId::from(2i64)),
+        ]);
+        let mut checkpoint = SourceCheckpoint::default();
+        let checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from(1i64),
+            Position::Beginning,
+            Position::from(41i64),
+        );
+        checkpoint.try_apply_delta(checkpoint_delta).unwrap();
+        let checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from(3i64),
+            Position::Beginning,
+            Position::from(9i64),
+        );
+        checkpoint.try_apply_delta(checkpoint_delta).unwrap();
+
+        assert!(offsets_to_commit(&HashMap::new(), &checkpoint).is_empty());
+        assert_eq!(
+            offsets_to_commit(&assigned_partitions, &checkpoint),
+            vec![(1, Offset::Offset(42))]
+        );
+    }
+
+    #[test]
+    fn test_static_partitions() {
+        assert_eq!(static_partitions(4, 1, 0), vec![0, 1, 2, 3]);
+        assert_eq!(static_partitions(5, 2, 0), vec![0, 2, 4]);
+        assert_eq!(static_partitions(5, 2, 1), vec![1, 3]);
+        assert!(static_partitions(2, 3, 2).is_empty());
+    }
+}
+
 #[cfg(all(test, feature = "kafka-broker-tests"))]
 mod kafka_broker_tests {
     use std::path::PathBuf;
@@ -760,9 +1245,16 @@ mod kafka_broker_tests {
     use crate::new_split_id;
     use crate::source::{quickwit_supported_sources, SourceActor};
 
+    /// The tests run against the broker listening on `localhost:9092` by default. Set
+    /// `QW_KAFKA_BOOTSTRAP_SERVERS` to run them against another Kafka-protocol compatible broker,
+    /// for instance the Redpanda broker of the Docker Compose setup (`localhost:19092`).
+    fn bootstrap_servers() -> String {
+        std::env::var("QW_KAFKA_BOOTSTRAP_SERVERS").unwrap_or_else(|_| "localhost:9092".to_string())
+    }
+
     fn create_admin_client() -> anyhow::Result<AdminClient<DefaultClientContext>> {
         let admin_client = ClientConfig::new()
-            .set("bootstrap.servers", "localhost:9092")
+            .set("bootstrap.servers", bootstrap_servers())
             .create()?;
         Ok(admin_client)
     }
@@ -809,7 +1301,7 @@ mod kafka_broker_tests {
         Q: ToBytes,
     {
         let producer: &FutureProducer = &ClientConfig::new()
-            .set("bootstrap.servers", "localhost:9092")
+            .set("bootstrap.servers", bootstrap_servers())
             .set("statistics.interval.ms", "500")
             .set("api.version.request", "true")
             .set("debug", "all")
@@ -851,14 +1343,23 @@ mod kafka_broker_tests {
         let source_config = SourceConfig {
             source_id: source_id.clone(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::Kafka(KafkaSourceParams {
                 topic: topic.to_string(),
                 client_log_level: None,
                 client_params: json!({
-                    "bootstrap.servers": "localhost:9092",
+                    "bootstrap.servers": bootstrap_servers(),
                 }),
                 enable_backfill_mode: true,
+                flavor: KafkaFlavor::Kafka,
+                client_features: KafkaClientFeatures::default(),
+                partition_assignment: KafkaPartitionAssignment::ConsumerGroup,
+                enable_offset_commit: false,
             }),
         };
         (source_id, source_config)
@@ -915,6 +1416,7 @@ mod kafka_broker_tests {
         let index_delta = IndexCheckpointDelta {
             source_id: source_id.to_string(),
             source_delta,
+            publish_token_opt: None,
         };
         metastore
             .publish_splits(index_id, &[&split_id], &[], Some(index_delta))
@@ -980,7 +1482,7 @@ mod kafka_broker_tests {
         assert_eq!(kafka_source.state.num_invalid_messages, 1);
 
         let message = KafkaMessage {
-            doc_opt: Some("test-doc".to_string()),
+            doc_opt: Some(Bytes::from_static(b"test-doc")),
             payload_len: 8,
             partition: 1,
             offset: 1,
@@ -1002,7 +1504,7 @@ mod kafka_broker_tests {
         assert_eq!(kafka_source.state.num_invalid_messages, 1);
 
         let message = KafkaMessage {
-            doc_opt: Some("test-doc".to_string()),
+            doc_opt: Some(Bytes::from_static(b"test-doc")),
             payload_len: 8,
             partition: 2,
             offset: 42,
@@ -1034,7 +1536,7 @@ mod kafka_broker_tests {
 
         // Message from unassigned partition
         let message = KafkaMessage {
-            doc_opt: Some("test-doc".to_string()),
+            doc_opt: Some(Bytes::from_static(b"test-doc")),
             payload_len: 8,
             partition: 3,
             offset: 42,
@@ -1072,7 +1574,7 @@ mod kafka_broker_tests {
         let mut kafka_source = KafkaSource::try_new(ctx, params, ignored_checkpoint)
             .await
             .unwrap();
-        kafka_source.state.num_inactive_partitions = 1;
+        kafka_source.state.inactive_partitions = HashSet::from_iter([1]);
 
         let universe = Universe::new();
         let (source_mailbox, _source_inbox) = create_test_mailbox();
@@ -1086,7 +1588,7 @@ mod kafka_broker_tests {
             .await
             .unwrap();
 
-        assert_eq!(kafka_source.state.num_inactive_partitions, 0);
+        assert!(kafka_source.state.inactive_partitions.is_empty());
 
         let expected_assigned_partitions =
             HashMap::from_iter([(1, PartitionId::from(1u64)), (2, PartitionId::from(2u64))]);
@@ -1108,6 +1610,60 @@ mod kafka_broker_tests {
         )
     }
 
+    #[tokio::test]
+    async fn test_kafka_source_static_partition_assignment() {
+        let admin_client = create_admin_client().unwrap();
+        let topic = append_random_suffix("test-kafka-source--static-partition-assignment--topic");
+        create_topic(&admin_client, &topic, 3).await.unwrap();
+
+        let metastore = metastore_for_test();
+        let index_id =
+            append_random_suffix("test-kafka-source--static-partition-assignment--index");
+        let (_source_id, mut source_config) = get_source_config(&topic);
+        source_config.num_pipelines = 2;
+
+        let mut params = if let SourceParams::Kafka(params) = source_config.clone().source_params {
+            params
+        } else {
+            unreachable!()
+        };
+        params.partition_assignment = KafkaPartitionAssignment::Static;
+
+        let ctx = SourceExecutionContext::for_test(
+            metastore,
+            &index_id,
+            PathBuf::from("./queues"),
+            source_config,
+        );
+        let mut checkpoint = SourceCheckpoint::default();
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta
+            .record_partition_delta(
+                PartitionId::from(2u64),
+                Position::Beginning,
+                Position::from(42u64),
+            )
+            .unwrap();
+        checkpoint.try_apply_delta(checkpoint_delta).unwrap();
+
+        let kafka_source = KafkaSource::try_new(ctx, params, checkpoint).await.unwrap();
+
+        let expected_assigned_partitions =
+            HashMap::from_iter([(0, PartitionId::from(0u64)), (2, PartitionId::from(2u64))]);
+        assert_eq!(
+            kafka_source.state.assigned_partitions,
+            expected_assigned_partitions
+        );
+        let expected_current_positions =
+            HashMap::from_iter([(0, Position::Beginning), (2, Position::from(42u64))]);
+        assert_eq!(
+            kafka_source.state.current_positions,
+            expected_current_positions
+        );
+        let assignment = kafka_source.consumer.assignment().unwrap();
+        assert_eq!(collect_partitions(&assignment, &topic), vec![0, 2]);
+    }
+
     #[tokio::test]
     async fn test_kafka_source_process_revoke_partitions() {
         let admin_client = create_admin_client().unwrap();
@@ -1142,14 +1698,18 @@ mod kafka_broker_tests {
         let (ack_tx, ack_rx) = oneshot::channel();
 
         let mut batch = BatchBuilder::default();
-        batch.push("test-doc".to_string(), 8);
+        batch.push(Bytes::from_static(b"test-doc"), 8);
+
+        kafka_source.state.assigned_partitions = HashMap::from_iter([(1, PartitionId::from(1u64))]);
+        kafka_source.state.current_positions = HashMap::from_iter([(1, Position::from(42u64))]);
+        kafka_source.state.inactive_partitions = HashSet::from_iter([1]);
 
         let publish_lock = kafka_source.publish_lock.clone();
         assert!(publish_lock.is_alive());
         assert_eq!(kafka_source.state.num_rebalances, 0);
 
         kafka_source
-            .process_revoke_partitions(&ctx, &indexer_mailbox, &mut batch, ack_tx)
+            .process_revoke_partitions(&ctx, &indexer_mailbox, &mut batch, &[1], ack_tx)
             .await
             .unwrap();
 
@@ -1157,6 +1717,10 @@ mod kafka_broker_tests {
         assert!(batch.docs.is_empty());
         assert!(publish_lock.is_dead());
 
+        assert!(kafka_source.state.assigned_partitions.is_empty());
+        assert!(kafka_source.state.current_positions.is_empty());
+        assert!(kafka_source.state.inactive_partitions.is_empty());
+
         assert_eq!(kafka_source.state.num_rebalances, 1);
 
         let indexer_messages: Vec<NewPublishLock> = indexer_inbox.drain_for_test_typed();
@@ -1194,7 +1758,7 @@ mod kafka_broker_tests {
         assert!(!kafka_source.should_exit());
 
         kafka_source.process_partition_eof(1);
-        assert_eq!(kafka_source.state.num_inactive_partitions, 1);
+        assert_eq!(kafka_source.state.inactive_partitions.len(), 1);
         assert!(kafka_source.should_exit());
 
         kafka_source.backfill_mode_enabled = false;
@@ -1232,6 +1796,7 @@ mod kafka_broker_tests {
             let source_actor = SourceActor {
                 source,
                 doc_processor_mailbox: doc_processor_mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_source_mailbox, source_handle) = universe.spawn_builder().spawn(source_actor);
             let (exit_status, exit_state) = source_handle.join().await;
@@ -1294,6 +1859,7 @@ mod kafka_broker_tests {
             let source_actor = SourceActor {
                 source,
                 doc_processor_mailbox: doc_processor_mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_source_mailbox, source_handle) = universe.spawn_builder().spawn(source_actor);
             let (exit_status, exit_state) = source_handle.join().await;
@@ -1366,6 +1932,7 @@ mod kafka_broker_tests {
             let source_actor = SourceActor {
                 source,
                 doc_processor_mailbox: doc_processor_mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_source_mailbox, source_handle) = universe.spawn_builder().spawn(source_actor);
             let (exit_status, exit_state) = source_handle.join().await;
@@ -1410,7 +1977,7 @@ mod kafka_broker_tests {
 
     #[tokio::test]
     async fn test_kafka_connectivity() {
-        let bootstrap_servers = "localhost:9092".to_string();
+        let bootstrap_servers = bootstrap_servers();
         let topic = append_random_suffix("test-kafka-connectivity-topic");
 
         let admin_client = create_admin_client().unwrap();
@@ -1422,6 +1989,10 @@ mod kafka_broker_tests {
             client_log_level: None,
             client_params: json!({ "bootstrap.servers": bootstrap_servers }),
             enable_backfill_mode: true,
+            flavor: KafkaFlavor::Kafka,
+            client_features: KafkaClientFeatures::default(),
+            partition_assignment: KafkaPartitionAssignment::ConsumerGroup,
+            enable_offset_commit: false,
         })
         .await
         .unwrap();
@@ -1433,6 +2004,10 @@ mod kafka_broker_tests {
             client_log_level: None,
             client_params: json!({ "bootstrap.servers": bootstrap_servers }),
             enable_backfill_mode: true,
+            flavor: KafkaFlavor::Kafka,
+            client_features: KafkaClientFeatures::default(),
+            partition_assignment: KafkaPartitionAssignment::ConsumerGroup,
+            enable_offset_commit: false,
         })
         .await
         .unwrap_err();
@@ -1445,6 +2020,10 @@ mod kafka_broker_tests {
                 "bootstrap.servers": "192.0.2.10:9092"
             }),
             enable_backfill_mode: true,
+            flavor: KafkaFlavor::Kafka,
+            client_features: KafkaClientFeatures::default(),
+            partition_assignment: KafkaPartitionAssignment::ConsumerGroup,
+            enable_offset_commit: false,
         })
         .await
         .unwrap_err();
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
 use std::time::Duration;
@@ -41,6 +41,7 @@ use tracing::{info, warn};
 
 use super::api::list_shards;
 use super::shard_consumer::{ShardConsumer, ShardConsumerHandle, ShardConsumerMessage};
+use super::shard_lineage::ShardLineage;
 use crate::actors::DocProcessor;
 use crate::models::RawDocBatch;
 use crate::source::kinesis::helpers::get_kinesis_client;
@@ -78,6 +79,8 @@ struct ShardConsumerState {
 pub struct KinesisSourceState {
     /// Pool of [`ShardConsumer`] managed by the source.
     shard_consumers: HashMap<ShardId, ShardConsumerState>,
+    /// Lineage of the shards, which holds back child shards until their parents are closed.
+    shard_lineage: ShardLineage,
     /// Number of bytes processed by the source.
     pub num_bytes_processed: u64,
     /// Number of records processed by the source (including invalid messages).
@@ -191,12 +194,27 @@ impl Source for KinesisSource {
                 None,
             ))
             .await?;
+        let listed_shard_ids: HashSet<ShardId> =
+            shards.iter().map(|shard| shard.shard_id.clone()).collect();
+
         for shard in shards {
-            self.spawn_shard_consumer(ctx, shard.shard_id);
+            // Parents that are no longer listed have expired and cannot be consumed anymore.
+            let parent_shard_ids = [shard.parent_shard_id, shard.adjacent_parent_shard_id]
+                .into_iter()
+                .flatten()
+                .filter(|parent_shard_id| listed_shard_ids.contains(parent_shard_id));
+            if self
+                .state
+                .shard_lineage
+                .add_shard(shard.shard_id.clone(), parent_shard_ids)
+            {
+                self.spawn_shard_consumer(ctx, shard.shard_id);
+            }
         }
         info!(
             stream_name = %self.stream_name,
             assigned_shards = %self.state.shard_consumers.keys().sorted().join(", "),
+            pending_shards = %self.state.shard_lineage.pending_shard_ids().iter().join(", "),
             "Starting Kinesis source."
         );
         Ok(())
@@ -219,17 +237,19 @@ impl Source for KinesisSource {
                 message_opt = self.shard_consumers_rx.recv() => {
                     // The source always carries a sender for this channel.
                     match message_opt.expect("Channel unexpectedly closed.") {
-                        ShardConsumerMessage::ChildShards(shard_ids) => {
-                            for shard_id in shard_ids {
-                                self.spawn_shard_consumer(ctx, shard_id);
+                        ShardConsumerMessage::ChildShards(children) => {
+                            for child in children {
+                                if self.state.shard_lineage.add_shard(child.shard_id.clone(), child.parent_shards) {
+                                    self.spawn_shard_consumer(ctx, child.shard_id);
+                                }
                             }
                         }
                         ShardConsumerMessage::Records { shard_id, records, lag_millis } => {
                             let num_records = records.len();
 
                             for (i, record) in records.into_iter().enumerate() {
-                                match String::from_utf8(record.data.to_vec()) {
-                                    Ok(doc) if !doc.is_empty() => docs.push(doc),
+                                match std::str::from_utf8(&record.data) {
+                                    Ok(doc) if !doc.is_empty() => docs.push(record.data.clone()),
                                     Ok(_) => {
                                         warn!(
                                             stream_name = %self.stream_name,
@@ -289,6 +309,11 @@ impl Source for KinesisSource {
                             );
                             self.state.shard_consumers.remove(&shard_id);
 
+                            // The records of the shard have all been received: its children
+                            // can be consumed without breaking the order of the records.
+                            for child_shard_id in self.state.shard_lineage.close_shard(&shard_id) {
+                                self.spawn_shard_consumer(ctx, child_shard_id);
+                            }
                         }
                         ShardConsumerMessage::ShardEOF(shard_id) => {
                             info!(
@@ -298,6 +323,10 @@ impl Source for KinesisSource {
                                 "Reached end of shard."
                             );
                             self.state.shard_consumers.remove(&shard_id);
+
+                            for child_shard_id in self.state.shard_lineage.close_shard(&shard_id) {
+                                self.spawn_shard_consumer(ctx, child_shard_id);
+                            }
                         }
                     }
                     ctx.record_progress();
@@ -314,7 +343,7 @@ impl Source for KinesisSource {
             };
             ctx.send_message(indexer_mailbox, batch).await?;
         }
-        if self.state.shard_consumers.is_empty() {
+        if self.state.shard_consumers.is_empty() && !self.state.shard_lineage.has_pending_shards() {
             info!(stream_name = %self.stream_name, "Reached end of stream.");
             ctx.send_exit_with_success(indexer_mailbox).await?;
             return Err(ActorExitStatus::Success);
@@ -339,6 +368,7 @@ impl Source for KinesisSource {
         json!({
             "stream_name": self.stream_name,
             "shard_consumer_positions": shard_consumer_positions,
+            "pending_shards": self.state.shard_lineage.pending_shard_ids(),
             "num_bytes_processed": self.state.num_bytes_processed,
             "num_records_processed": self.state.num_records_processed,
             "num_invalid_records": self.state.num_invalid_records,
@@ -437,6 +467,7 @@ mod tests {
             let actor = SourceActor {
                 source: Box::new(kinesis_source),
                 doc_processor_mailbox: doc_processor_mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_mailbox, handle) = universe.spawn_builder().spawn(actor);
             let (exit_status, exit_state) = handle.join().await;
@@ -491,6 +522,7 @@ mod tests {
             let actor = SourceActor {
                 source: Box::new(kinesis_source),
                 doc_processor_mailbox: doc_processor_mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_mailbox, handle) = universe.spawn_builder().spawn(actor);
             let (exit_status, exit_state) = handle.join().await;
@@ -562,6 +594,7 @@ mod tests {
             let actor = SourceActor {
                 source: Box::new(kinesis_source),
                 doc_processor_mailbox: doc_processor_mailbox.clone(),
+                rate_limiter_opt: None,
             };
             let (_mailbox, handle) = universe.spawn_builder().spawn(actor);
             let (exit_status, exit_state) = handle.join().await;
@@ -23,7 +23,7 @@ use std::time::Duration;
 use async_trait::async_trait;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Mailbox};
 use quickwit_aws::retry::RetryParams;
-use rusoto_kinesis::{KinesisClient, Record};
+use rusoto_kinesis::{ChildShard, KinesisClient, Record};
 use serde_json::json;
 use tokio::sync::mpsc;
 
@@ -33,8 +33,8 @@ use crate::source::SourceContext;
 #[derive(Debug)]
 pub(super) enum ShardConsumerMessage {
     /// The shard was the subject of a merge or a split and points to one (merge) or two (split)
-    /// children.
-    ChildShards(Vec<String>),
+    /// children along with their parents.
+    ChildShards(Vec<ChildShard>),
     Records {
         shard_id: String,
         records: Vec<Record>,
@@ -213,14 +213,13 @@ impl Handler<Loop> for ShardConsumer {
                 self.send_message(ctx, message).await?;
             }
             if let Some(children) = response.child_shards {
-                let shard_ids: Vec<String> = children
+                let children: Vec<ChildShard> = children
                     .into_iter()
                     // Filter out duplicate message when two shards are merged.
                     .filter(|child| child.parent_shards.first() == Some(&self.shard_id))
-                    .map(|child| child.shard_id)
                     .collect();
-                if !shard_ids.is_empty() {
-                    let message = ShardConsumerMessage::ChildShards(shard_ids);
+                if !children.is_empty() {
+                    let message = ShardConsumerMessage::ChildShards(children);
                     self.send_message(ctx, message).await?;
                 }
             }
@@ -442,7 +441,7 @@ mod tests {
 
             assert!(matches!(
                 &messages[0],
-                ShardConsumerMessage::ChildShards(shard_ids) if *shard_ids == vec![make_shard_id(2)]
+                ShardConsumerMessage::ChildShards(children) if children.iter().map(|child| child.shard_id.clone()).collect::<Vec<_>>() == vec![make_shard_id(2)]
             ));
             assert!(matches!(
                 &messages[1],
@@ -504,7 +503,7 @@ mod tests {
 
         assert!(matches!(
             &messages[0],
-            ShardConsumerMessage::ChildShards(shard_ids) if *shard_ids == vec![make_shard_id(1), make_shard_id(2)]
+            ShardConsumerMessage::ChildShards(children) if children.iter().map(|child| child.shard_id.clone()).collect::<Vec<_>>() == vec![make_shard_id(1), make_shard_id(2)]
         ));
         assert!(matches!(
             &messages[1],
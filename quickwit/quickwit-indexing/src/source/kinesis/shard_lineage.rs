@@ -0,0 +1,146 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+
+type ShardId = String;
+
+/// Tracks the lineage of the shards of a stream so that a child shard is consumed only after its
+/// parent shards are fully consumed. This preserves the order of the records sharing a partition
+/// key across resharding operations (splits and merges).
+#[derive(Debug, Default)]
+pub(super) struct ShardLineage {
+    /// Shards known to the source, whether they are pending, active, or closed.
+    known_shards: HashSet<ShardId>,
+    /// Shards waiting for their parent shards to be closed along with their open parents.
+    pending_shards: HashMap<ShardId, HashSet<ShardId>>,
+    /// Shards that have been fully consumed.
+    closed_shards: HashSet<ShardId>,
+}
+
+impl ShardLineage {
+    /// Registers a shard and returns whether it can be consumed right away, which is the case
+    /// when all its parents are closed. Parents unknown to the lineage must be omitted by the
+    /// caller, for instance when they have expired past the retention period of the stream.
+    /// Returns `false` if the shard was already registered.
+    pub fn add_shard(
+        &mut self,
+        shard_id: ShardId,
+        parent_shard_ids: impl IntoIterator<Item = ShardId>,
+    ) -> bool {
+        if !self.known_shards.insert(shard_id.clone()) {
+            return false;
+        }
+        let open_parent_shard_ids: HashSet<ShardId> = parent_shard_ids
+            .into_iter()
+            .filter(|parent_shard_id| !self.closed_shards.contains(parent_shard_id))
+            .collect();
+        if open_parent_shard_ids.is_empty() {
+            return true;
+        }
+        self.pending_shards.insert(shard_id, open_parent_shard_ids);
+        false
+    }
+
+    /// Marks a shard as closed and returns the sorted list of its children that can now be
+    /// consumed.
+    pub fn close_shard(&mut self, shard_id: &str) -> Vec<ShardId> {
+        self.closed_shards.insert(shard_id.to_string());
+
+        let mut ready_shard_ids = Vec::new();
+        for (child_shard_id, open_parent_shard_ids) in self.pending_shards.iter_mut() {
+            if open_parent_shard_ids.remove(shard_id) && open_parent_shard_ids.is_empty() {
+                ready_shard_ids.push(child_shard_id.clone());
+            }
+        }
+        for ready_shard_id in &ready_shard_ids {
+            self.pending_shards.remove(ready_shard_id);
+        }
+        ready_shard_ids.sort();
+        ready_shard_ids
+    }
+
+    /// Returns the sorted list of shards waiting for their parents to be closed.
+    pub fn pending_shard_ids(&self) -> Vec<&ShardId> {
+        let mut pending_shard_ids: Vec<&ShardId> = self.pending_shards.keys().collect();
+        pending_shard_ids.sort();
+        pending_shard_ids
+    }
+
+    pub fn has_pending_shards(&self) -> bool {
+        !self.pending_shards.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shard_ids(shard_ids: &[&str]) -> Vec<ShardId> {
+        shard_ids
+            .iter()
+            .map(|shard_id| shard_id.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_shard_lineage_split() {
+        let mut lineage = ShardLineage::default();
+        assert!(lineage.add_shard("shard-0".to_string(), Vec::new()));
+        assert!(!lineage.add_shard("shard-0".to_string(), Vec::new()));
+
+        assert!(!lineage.add_shard("shard-1".to_string(), shard_ids(&["shard-0"])));
+        assert!(!lineage.add_shard("shard-2".to_string(), shard_ids(&["shard-0"])));
+        assert_eq!(lineage.pending_shard_ids(), vec!["shard-1", "shard-2"]);
+        assert!(lineage.has_pending_shards());
+
+        assert_eq!(
+            lineage.close_shard("shard-0"),
+            shard_ids(&["shard-1", "shard-2"])
+        );
+        assert!(!lineage.has_pending_shards());
+        // Children reported again by another consumer are ignored.
+        assert!(!lineage.add_shard("shard-1".to_string(), shard_ids(&["shard-0"])));
+    }
+
+    #[test]
+    fn test_shard_lineage_merge() {
+        let mut lineage = ShardLineage::default();
+        assert!(lineage.add_shard("shard-0".to_string(), Vec::new()));
+        assert!(lineage.add_shard("shard-1".to_string(), Vec::new()));
+        assert!(!lineage.add_shard("shard-2".to_string(), shard_ids(&["shard-0", "shard-1"])));
+        assert!(lineage.close_shard("shard-1").is_empty());
+        assert_eq!(lineage.pending_shard_ids(), vec!["shard-2"]);
+        assert_eq!(lineage.close_shard("shard-0"), shard_ids(&["shard-2"]));
+        assert!(!lineage.has_pending_shards());
+    }
+
+    #[test]
+    fn test_shard_lineage_child_reported_after_parent_closed() {
+        let mut lineage = ShardLineage::default();
+        assert!(lineage.add_shard("shard-0".to_string(), Vec::new()));
+        assert!(lineage.add_shard("shard-1".to_string(), Vec::new()));
+        assert!(lineage.close_shard("shard-1").is_empty());
+        // The child of a merge is reported once its first parent is closed.
+        assert!(!lineage.add_shard("shard-2".to_string(), shard_ids(&["shard-0", "shard-1"])));
+        assert_eq!(lineage.close_shard("shard-0"), shard_ids(&["shard-2"]));
+        // Both parents are closed: the shard can be consumed right away.
+        assert!(lineage.add_shard("shard-3".to_string(), shard_ids(&["shard-0", "shard-1"])));
+    }
+}
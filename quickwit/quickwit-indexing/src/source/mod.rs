@@ -57,13 +57,31 @@
 //!   that file.
 //! - the kafka source: the partition id is a kafka topic partition id, and the position is a kafka
 //!   offset.
+mod backfill;
 mod file_source;
+#[cfg(feature = "gcp-pubsub")]
+mod gcp_pubsub_source;
+#[cfg(feature = "gcs-notifications")]
+mod gcs_notifications_source;
 mod ingest_api_source;
 #[cfg(feature = "kafka")]
 mod kafka_source;
 #[cfg(feature = "kinesis")]
 mod kinesis;
+#[cfg(feature = "mqtt")]
+mod mqtt_source;
+#[cfg(any(feature = "gcp-pubsub", feature = "sqs"))]
+mod pending_acks;
+#[cfg(feature = "postgres-cdc")]
+mod postgres_cdc_source;
+#[cfg(feature = "pulsar")]
+mod pulsar_source;
+mod push_source;
+mod rate_limiter;
 mod source_factory;
+#[cfg(feature = "sqs")]
+mod sqs_source;
+mod syslog_source;
 mod vec_source;
 mod void_source;
 
@@ -73,18 +91,40 @@ use std::time::Duration;
 
 use anyhow::bail;
 use async_trait::async_trait;
+pub use backfill::{
+    backfill_completion_delta, backfill_phase_source_config, fetch_backfill_start_delta,
+    BackfillPhase, BACKFILL_PHASE_PARTITION_ID,
+};
 pub use file_source::{FileSource, FileSourceFactory};
+#[cfg(feature = "gcp-pubsub")]
+pub use gcp_pubsub_source::{GcpPubSubSource, GcpPubSubSourceFactory};
+#[cfg(feature = "gcs-notifications")]
+pub use gcs_notifications_source::{GcsNotificationsSource, GcsNotificationsSourceFactory};
 #[cfg(feature = "kafka")]
 pub use kafka_source::{KafkaSource, KafkaSourceFactory};
 #[cfg(feature = "kinesis")]
 pub use kinesis::kinesis_source::{KinesisSource, KinesisSourceFactory};
+#[cfg(feature = "mqtt")]
+pub use mqtt_source::{MqttSource, MqttSourceFactory};
 use once_cell::sync::OnceCell;
+#[cfg(feature = "postgres-cdc")]
+pub use postgres_cdc_source::{PostgresCdcSource, PostgresCdcSourceFactory};
+#[cfg(feature = "pulsar")]
+pub use pulsar_source::{PulsarSource, PulsarSourceFactory};
+pub use push_source::{
+    get_push_source_sender, PushPipelineId, PushSource, PushSourceFactory, PushedDocs,
+};
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox};
+use quickwit_common::disk_space::is_disk_space_low;
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_config::{SourceConfig, SourceParams};
-use quickwit_metastore::checkpoint::SourceCheckpoint;
+use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
 use quickwit_metastore::Metastore;
+pub use rate_limiter::SourceRateLimiter;
 pub use source_factory::{SourceFactory, SourceLoader, TypedSourceFactory};
+#[cfg(feature = "sqs")]
+pub use sqs_source::{SqsSource, SqsSourceFactory};
+pub use syslog_source::{SyslogSource, SyslogSourceFactory};
 use tokio::runtime::Handle;
 use tracing::error;
 pub use vec_source::{VecSource, VecSourceFactory};
@@ -103,6 +143,10 @@ pub struct SourceExecutionContext {
     // Ingest API queues directory path.
     pub queues_dir_path: PathBuf,
     pub source_config: SourceConfig,
+    /// ID of the node running the indexing pipeline.
+    pub node_id: String,
+    /// Ordinal of the indexing pipeline among the pipelines of the source on the node.
+    pub pipeline_ord: usize,
 }
 
 impl SourceExecutionContext {
@@ -118,6 +162,8 @@ impl SourceExecutionContext {
             index_id: index_id.to_string(),
             queues_dir_path,
             source_config,
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
         })
     }
 }
@@ -178,6 +224,9 @@ pub trait Source: Send + Sync + 'static {
     /// This method is for instance useful for the ingest API, as it is possible
     /// to delete all message anterior to the checkpoint in the ingest API queue.
     ///
+    /// The Pub/Sub source also relies on it to acknowledge messages only once they are
+    /// published.
+    ///
     /// It is perfectly fine for implementation to ignore this function.
     /// For instance, message queue like kafka are meant to be shared by different
     /// client, and rely on a retention strategy to delete messages.
@@ -186,7 +235,7 @@ pub trait Source: Send + Sync + 'static {
     /// indexing pipeline, as truncation is just "a suggestion".
     /// The error will however be logged.
     async fn suggest_truncate(
-        &self,
+        &mut self,
         _checkpoint: SourceCheckpoint,
         _ctx: &ActorContext<SourceActor>,
     ) -> anyhow::Result<()> {
@@ -214,15 +263,21 @@ pub trait Source: Send + Sync + 'static {
 
 /// The SourceActor acts as a thin wrapper over a source trait object to execute.
 ///
-/// It mostly takes care of running a loop calling `emit_batches(...)`.
+/// It mostly takes care of running a loop calling `emit_batches(...)`, and of pausing that loop
+/// while the source exceeds its rate limit.
 pub struct SourceActor {
     pub source: Box<dyn Source>,
     pub doc_processor_mailbox: Mailbox<DocProcessor>,
+    /// Rate limiter fed by the doc processor with the batches emitted by the source.
+    pub rate_limiter_opt: Option<SourceRateLimiter>,
 }
 
 #[derive(Debug)]
 struct Loop;
 
+/// Interval at which a source paused because disk space is low checks whether it can resume.
+const LOW_DISK_SPACE_WAIT_DURATION: Duration = Duration::from_secs(1);
+
 #[async_trait]
 impl Actor for SourceActor {
     type ObservableState = serde_json::Value;
@@ -266,6 +321,18 @@ impl Handler<Loop> for SourceActor {
     type Reply = ();
 
     async fn handle(&mut self, _message: Loop, ctx: &SourceContext) -> Result<(), ActorExitStatus> {
+        if is_disk_space_low() {
+            ctx.schedule_self_msg(LOW_DISK_SPACE_WAIT_DURATION, Loop)
+                .await;
+            return Ok(());
+        }
+        if let Some(rate_limiter) = &self.rate_limiter_opt {
+            let rate_limit_wait_duration = rate_limiter.wait_duration();
+            if !rate_limit_wait_duration.is_zero() {
+                ctx.schedule_self_msg(rate_limit_wait_duration, Loop).await;
+                return Ok(());
+            }
+        }
         let wait_for = self
             .source
             .emit_batches(&self.doc_processor_mailbox, ctx)
@@ -284,10 +351,24 @@ pub fn quickwit_supported_sources() -> &'static SourceLoader {
     SOURCE_LOADER.get_or_init(|| {
         let mut source_factory = SourceLoader::default();
         source_factory.add_source("file", FileSourceFactory);
+        #[cfg(feature = "gcs-notifications")]
+        source_factory.add_source("gcs_notifications", GcsNotificationsSourceFactory);
         #[cfg(feature = "kafka")]
         source_factory.add_source("kafka", KafkaSourceFactory);
         #[cfg(feature = "kinesis")]
         source_factory.add_source("kinesis", KinesisSourceFactory);
+        #[cfg(feature = "mqtt")]
+        source_factory.add_source("mqtt", MqttSourceFactory);
+        #[cfg(feature = "postgres-cdc")]
+        source_factory.add_source("postgres-cdc", PostgresCdcSourceFactory);
+        #[cfg(feature = "gcp-pubsub")]
+        source_factory.add_source("pubsub", GcpPubSubSourceFactory);
+        #[cfg(feature = "pulsar")]
+        source_factory.add_source("pulsar", PulsarSourceFactory);
+        source_factory.add_source("push", PushSourceFactory);
+        #[cfg(feature = "sqs")]
+        source_factory.add_source("sqs", SqsSourceFactory);
+        source_factory.add_source("syslog", SyslogSourceFactory);
         source_factory.add_source("vec", VecSourceFactory);
         source_factory.add_source("void", VoidSourceFactory);
         source_factory.add_source("ingest-api", IngestApiSourceFactory);
@@ -296,7 +377,16 @@ pub fn quickwit_supported_sources() -> &'static SourceLoader {
 }
 
 pub async fn check_source_connectivity(source_config: &SourceConfig) -> anyhow::Result<()> {
-    match &source_config.source_params {
+    if let SourceParams::Backfill(params) = &source_config.source_params {
+        check_source_params_connectivity(&params.historical).await?;
+        check_source_params_connectivity(&params.live).await?;
+        return Ok(());
+    }
+    check_source_params_connectivity(&source_config.source_params).await
+}
+
+async fn check_source_params_connectivity(source_params: &SourceParams) -> anyhow::Result<()> {
+    match source_params {
         SourceParams::File(params) => {
             if let Some(filepath) = &params.filepath {
                 if !Path::new(filepath).exists() {
@@ -306,6 +396,17 @@ pub async fn check_source_connectivity(source_config: &SourceConfig) -> anyhow::
             Ok(())
         }
         #[allow(unused_variables)]
+        SourceParams::GcsNotifications(params) => {
+            #[cfg(not(feature = "gcs-notifications"))]
+            bail!("Quickwit binary was not compiled with the `gcs-notifications` feature.");
+
+            #[cfg(feature = "gcs-notifications")]
+            {
+                gcs_notifications_source::check_connectivity(params.clone()).await?;
+                Ok(())
+            }
+        }
+        #[allow(unused_variables)]
         SourceParams::Kafka(params) => {
             #[cfg(not(feature = "kafka"))]
             bail!("Quickwit binary was not compiled with the `kafka` feature.");
@@ -327,10 +428,86 @@ pub async fn check_source_connectivity(source_config: &SourceConfig) -> anyhow::
                 Ok(())
             }
         }
+        #[allow(unused_variables)]
+        SourceParams::Mqtt(params) => {
+            #[cfg(not(feature = "mqtt"))]
+            bail!("Quickwit binary was not compiled with the `mqtt` feature.");
+
+            #[cfg(feature = "mqtt")]
+            {
+                mqtt_source::check_connectivity(params.clone()).await?;
+                Ok(())
+            }
+        }
+        #[allow(unused_variables)]
+        SourceParams::PostgresCdc(params) => {
+            #[cfg(not(feature = "postgres-cdc"))]
+            bail!("Quickwit binary was not compiled with the `postgres-cdc` feature.");
+
+            #[cfg(feature = "postgres-cdc")]
+            {
+                postgres_cdc_source::check_connectivity(params.clone()).await?;
+                Ok(())
+            }
+        }
+        #[allow(unused_variables)]
+        SourceParams::PubSub(params) => {
+            #[cfg(not(feature = "gcp-pubsub"))]
+            bail!("Quickwit binary was not compiled with the `gcp-pubsub` feature.");
+
+            #[cfg(feature = "gcp-pubsub")]
+            {
+                gcp_pubsub_source::check_connectivity(params.clone()).await?;
+                Ok(())
+            }
+        }
+        #[allow(unused_variables)]
+        SourceParams::Pulsar(params) => {
+            #[cfg(not(feature = "pulsar"))]
+            bail!("Quickwit binary was not compiled with the `pulsar` feature.");
+
+            #[cfg(feature = "pulsar")]
+            {
+                pulsar_source::check_connectivity(params.clone()).await?;
+                Ok(())
+            }
+        }
+        #[allow(unused_variables)]
+        SourceParams::Sqs(params) => {
+            #[cfg(not(feature = "sqs"))]
+            bail!("Quickwit binary was not compiled with the `sqs` feature.");
+
+            #[cfg(feature = "sqs")]
+            {
+                sqs_source::check_connectivity(params.clone()).await?;
+                Ok(())
+            }
+        }
         _ => Ok(()),
     }
 }
 
+/// Returns the position of the last record of each non-empty partition of the source. Only Kafka
+/// sources are able to report these positions for now.
+pub async fn fetch_end_positions(
+    source_params: &SourceParams,
+) -> anyhow::Result<Vec<(PartitionId, Position)>> {
+    match source_params {
+        #[allow(unused_variables)]
+        SourceParams::Kafka(params) => {
+            #[cfg(not(feature = "kafka"))]
+            bail!("Quickwit binary was not compiled with the `kafka` feature.");
+
+            #[cfg(feature = "kafka")]
+            {
+                let end_positions = kafka_source::fetch_end_positions(params.clone()).await?;
+                Ok(end_positions)
+            }
+        }
+        _ => bail!("Only Kafka sources support fetching end positions."),
+    }
+}
+
 #[derive(Debug)]
 pub struct SuggestTruncate(pub SourceCheckpoint);
 
@@ -365,6 +542,11 @@ mod tests {
             let source_config = SourceConfig {
                 source_id: "void".to_string(),
                 num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
                 enabled: true,
                 source_params: SourceParams::void(),
             };
@@ -374,6 +556,11 @@ mod tests {
             let source_config = SourceConfig {
                 source_id: "vec".to_string(),
                 num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
                 enabled: true,
                 source_params: SourceParams::Vec(VecSourceParams::default()),
             };
@@ -383,6 +570,11 @@ mod tests {
             let source_config = SourceConfig {
                 source_id: "file".to_string(),
                 num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
                 enabled: true,
                 source_params: SourceParams::file("file-does-not-exist.json"),
             };
@@ -392,6 +584,11 @@ mod tests {
             let source_config = SourceConfig {
                 source_id: "file".to_string(),
                 num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
                 enabled: true,
                 source_params: SourceParams::file("data/test_corpus.json"),
             };
@@ -0,0 +1,648 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt, io};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bytes::{Buf, Bytes};
+use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
+use quickwit_config::MqttSourceParams;
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use rumqttc::v5::mqttbytes::v5::{Packet, Publish};
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{AsyncClient, Event, EventLoop, MqttOptions};
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use ulid::Ulid;
+
+use crate::actors::DocProcessor;
+use crate::models::RawDocBatch;
+use crate::source::{
+    Source, SourceActor, SourceContext, SourceExecutionContext, TypedSourceFactory,
+};
+
+const TARGET_BATCH_NUM_BYTES: u64 = 5_000_000;
+
+/// Maximum time spent collecting messages in a single call to `emit_batches`.
+const EMIT_BATCHES_TIMEOUT: Duration = Duration::from_secs(1);
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// Time waited before reconnecting to the broker after a connection error.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SPOOL_FILE_EXTENSION: &str = "spool";
+
+/// Factory for instantiating an `MqttSource`.
+pub struct MqttSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for MqttSourceFactory {
+    type Source = MqttSource;
+    type Params = MqttSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: MqttSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        MqttSource::try_new(ctx, params, checkpoint).await
+    }
+}
+
+/// A source subscribing to MQTT topics with QoS 1.
+///
+/// MQTT has no notion of offset: once a message is acknowledged, the broker will never deliver it
+/// again. The source therefore writes each batch of received messages to a spool in the queues
+/// directory before acknowledging them, and deletes the spooled batches once they are published.
+/// After a crash, the batches left in the spool are emitted again. Like the Pub/Sub source, each
+/// pipeline records the number of messages it has emitted in its own partition of the checkpoint.
+pub struct MqttSource {
+    ctx: Arc<SourceExecutionContext>,
+    client: AsyncClient,
+    event_loop_handle: JoinHandle<()>,
+    publish_rx: mpsc::Receiver<Publish>,
+    max_batch_num_messages: usize,
+    spool: MessageSpool,
+    /// Last positions of the spooled batches that remain to be emitted again.
+    batches_to_replay: VecDeque<u64>,
+    partition_id: PartitionId,
+    current_position: u64,
+    num_bytes_processed: u64,
+    num_messages_processed: u64,
+    num_replayed_messages: u64,
+}
+
+impl fmt::Debug for MqttSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MqttSource {{ source_id: {}, pipeline_ord: {} }}",
+            self.ctx.source_config.source_id, self.ctx.pipeline_ord
+        )
+    }
+}
+
+impl Drop for MqttSource {
+    fn drop(&mut self) {
+        self.event_loop_handle.abort();
+    }
+}
+
+fn mqtt_options(params: &MqttSourceParams, client_id: String) -> MqttOptions {
+    let mut mqtt_options = MqttOptions::new(client_id, params.host.clone(), params.port);
+    mqtt_options.set_keep_alive(KEEP_ALIVE);
+    if let Some(username) = &params.username {
+        mqtt_options.set_credentials(
+            username.clone(),
+            params.password.clone().unwrap_or_default(),
+        );
+    }
+    mqtt_options
+}
+
+fn spool_dir_path(ctx: &SourceExecutionContext) -> PathBuf {
+    ctx.queues_dir_path
+        .join("mqtt")
+        .join(&ctx.index_id)
+        .join(&ctx.source_config.source_id)
+        .join(ctx.pipeline_ord.to_string())
+}
+
+impl MqttSource {
+    /// Opens the spool of the pipeline and starts the event loop connecting to the broker.
+    pub async fn try_new(
+        ctx: Arc<SourceExecutionContext>,
+        params: MqttSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self> {
+        // Each pipeline records the messages it emits in its own partition.
+        let partition_id = PartitionId::from(format!("{}:{}", ctx.node_id, ctx.pipeline_ord));
+        let current_position = match checkpoint.position_for_partition(&partition_id) {
+            Some(Position::Offset(offset)) => offset
+                .parse::<u64>()
+                .with_context(|| format!("Failed to parse MQTT position `{offset}`."))?,
+            _ => 0,
+        };
+        let spool = MessageSpool::open(spool_dir_path(&ctx))
+            .await
+            .context("Failed to open MQTT spool.")?;
+        // Batches published before the crash may not have been truncated yet.
+        spool.truncate(current_position).await?;
+        let batches_to_replay: VecDeque<u64> = spool.list_batches().await?.into();
+
+        // The session outlives the connection so that the broker keeps the messages published
+        // while the pipeline is down, for up to the session expiry interval, and messages are
+        // acknowledged once they have been spooled. Without an expiry interval, MQTT v5 brokers
+        // discard the session on disconnection.
+        let client_id = format!(
+            "quickwit-{}-{}-{}-{}",
+            ctx.node_id, ctx.index_id, ctx.source_config.source_id, ctx.pipeline_ord
+        );
+        let mut mqtt_options = mqtt_options(&params, client_id);
+        mqtt_options.set_clean_start(false);
+        mqtt_options.set_session_expiry_interval(Some(params.session_expiry_interval_secs));
+        mqtt_options.set_manual_acks(true);
+        mqtt_options.set_receive_maximum(Some(
+            params.max_queued_messages.min(u16::MAX as usize) as u16
+        ));
+        let (client, event_loop) = AsyncClient::new(mqtt_options, params.max_queued_messages);
+        let (publish_tx, publish_rx) = mpsc::channel(params.max_queued_messages);
+        let event_loop_handle = tokio::spawn(run_event_loop(
+            event_loop,
+            client.clone(),
+            params.subscription_filters(),
+            publish_tx,
+        ));
+        info!(
+            index_id=%ctx.index_id,
+            source_id=%ctx.source_config.source_id,
+            pipeline_ord=%ctx.pipeline_ord,
+            partition_id=%partition_id.as_str(),
+            current_position=%current_position,
+            num_batches_to_replay=%batches_to_replay.len(),
+            "Starting MQTT source."
+        );
+        Ok(Self {
+            ctx,
+            client,
+            event_loop_handle,
+            publish_rx,
+            max_batch_num_messages: params.max_queued_messages,
+            spool,
+            batches_to_replay,
+            partition_id,
+            current_position,
+            num_bytes_processed: 0,
+            num_messages_processed: 0,
+            num_replayed_messages: 0,
+        })
+    }
+
+    async fn send_batch(
+        &mut self,
+        docs: Vec<Bytes>,
+        current_position: u64,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<(), ActorExitStatus> {
+        let previous_position = self.current_position;
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta
+            .record_partition_delta(
+                self.partition_id.clone(),
+                if previous_position == 0 {
+                    Position::Beginning
+                } else {
+                    Position::from(previous_position)
+                },
+                Position::from(current_position),
+            )
+            .context("Failed to record partition delta.")?;
+        self.num_bytes_processed += docs.iter().map(|doc| doc.len() as u64).sum::<u64>();
+        self.num_messages_processed += docs.len() as u64;
+        self.current_position = current_position;
+
+        let batch = RawDocBatch {
+            docs,
+            checkpoint_delta,
+        };
+        ctx.send_message(doc_processor_mailbox, batch).await?;
+        Ok(())
+    }
+
+    /// Emits the oldest batch left in the spool by a previous run of the pipeline.
+    async fn replay_batch(
+        &mut self,
+        last_position: u64,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<(), ActorExitStatus> {
+        let mut docs = ctx
+            .protect_future(self.spool.read_batch(last_position))
+            .await
+            .context("Failed to read MQTT spool.")?;
+        // The checkpoint may fall in the middle of the batch.
+        let first_position = (last_position + 1).saturating_sub(docs.len() as u64);
+        if first_position <= self.current_position {
+            let num_published_docs = (self.current_position + 1 - first_position) as usize;
+            docs.drain(..num_published_docs.min(docs.len()));
+        }
+        self.num_replayed_messages += docs.len() as u64;
+        self.send_batch(docs, last_position, doc_processor_mailbox, ctx)
+            .await
+    }
+}
+
+#[async_trait]
+impl Source for MqttSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        if let Some(last_position) = self.batches_to_replay.pop_front() {
+            self.replay_batch(last_position, doc_processor_mailbox, ctx)
+                .await?;
+            return Ok(Duration::default());
+        }
+        let mut batch_num_bytes = 0;
+        let mut publishes = Vec::new();
+        let deadline = Instant::now() + EMIT_BATCHES_TIMEOUT;
+
+        while batch_num_bytes < TARGET_BATCH_NUM_BYTES
+            && publishes.len() < self.max_batch_num_messages
+        {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let publish = match ctx
+                .protect_future(tokio::time::timeout(timeout, self.publish_rx.recv()))
+                .await
+            {
+                Ok(Some(publish)) => publish,
+                Ok(None) => {
+                    return Err(ActorExitStatus::from(anyhow::anyhow!(
+                        "MQTT event loop exited unexpectedly."
+                    )))
+                }
+                Err(_) => break,
+            };
+            batch_num_bytes += publish.payload.len() as u64;
+            publishes.push(publish);
+        }
+        if publishes.is_empty() {
+            return Ok(Duration::default());
+        }
+        let docs: Vec<Bytes> = publishes
+            .iter()
+            .map(|publish| publish.payload.clone())
+            .collect();
+        let current_position = self.current_position + docs.len() as u64;
+        ctx.protect_future(self.spool.append(current_position, &docs))
+            .await
+            .context("Failed to write MQTT spool.")?;
+        // The messages are safe in the spool: the broker can forget about them.
+        for publish in &publishes {
+            ctx.protect_future(self.client.ack(publish))
+                .await
+                .context("Failed to acknowledge MQTT message.")?;
+        }
+        self.send_batch(docs, current_position, doc_processor_mailbox, ctx)
+            .await?;
+        Ok(Duration::default())
+    }
+
+    async fn suggest_truncate(
+        &mut self,
+        checkpoint: SourceCheckpoint,
+        _ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        let published_position = match checkpoint.position_for_partition(&self.partition_id) {
+            Some(Position::Offset(offset)) => offset.parse::<u64>()?,
+            _ => return Ok(()),
+        };
+        self.spool.truncate(published_position).await?;
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "MqttSource{{source_id={}, pipeline_ord={}}}",
+            self.ctx.source_config.source_id, self.ctx.pipeline_ord
+        )
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        json!({
+            "index_id": self.ctx.index_id,
+            "source_id": self.ctx.source_config.source_id,
+            "pipeline_ord": self.ctx.pipeline_ord,
+            "partition_id": self.partition_id.as_str(),
+            "current_position": self.current_position,
+            "num_bytes_processed": self.num_bytes_processed,
+            "num_messages_processed": self.num_messages_processed,
+            "num_replayed_messages": self.num_replayed_messages,
+        })
+    }
+}
+
+/// Polls the connection to the broker, subscribing again every time the connection is
+/// established, and forwards the received messages to the source.
+async fn run_event_loop(
+    mut event_loop: EventLoop,
+    client: AsyncClient,
+    subscription_filters: Vec<String>,
+    publish_tx: mpsc::Sender<Publish>,
+) {
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("Connected to MQTT broker.");
+                for subscription_filter in &subscription_filters {
+                    if let Err(error) =
+                        client.try_subscribe(subscription_filter.clone(), QoS::AtLeastOnce)
+                    {
+                        warn!(error=?error, subscription_filter=%subscription_filter, "Failed to subscribe to MQTT topic.");
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                // The source has been dropped.
+                if publish_tx.send(publish).await.is_err() {
+                    return;
+                }
+            }
+            Ok(_) => {}
+            Err(error) => {
+                warn!(error=?error, "MQTT connection error.");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Batches of messages acknowledged to the broker but not published yet. Each batch is stored in
+/// its own file, named after the position of its last message, as a sequence of
+/// length-prefixed messages.
+struct MessageSpool {
+    dir_path: PathBuf,
+}
+
+impl MessageSpool {
+    async fn open(dir_path: PathBuf) -> io::Result<Self> {
+        tokio::fs::create_dir_all(&dir_path).await?;
+        Ok(Self { dir_path })
+    }
+
+    fn batch_path(&self, last_position: u64) -> PathBuf {
+        self.dir_path
+            .join(format!("{last_position:020}.{SPOOL_FILE_EXTENSION}"))
+    }
+
+    /// Writes a batch and syncs it to disk. The file is written under a temporary name first so
+    /// that a crash never leaves a partial batch behind.
+    async fn append(&self, last_position: u64, messages: &[Bytes]) -> io::Result<()> {
+        let num_bytes = messages.iter().map(|message| 4 + message.len()).sum();
+        let mut buffer = Vec::with_capacity(num_bytes);
+        for message in messages {
+            buffer.extend_from_slice(&(message.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(message);
+        }
+        let batch_path = self.batch_path(last_position);
+        let temp_path = batch_path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&temp_path).await?;
+        file.write_all(&buffer).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&temp_path, &batch_path).await
+    }
+
+    /// Returns the last positions of the spooled batches in increasing order.
+    async fn list_batches(&self) -> io::Result<Vec<u64>> {
+        let mut last_positions = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(&self.dir_path).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            if let Some(last_position) = parse_batch_path(&entry.path()) {
+                last_positions.push(last_position);
+            }
+        }
+        last_positions.sort_unstable();
+        Ok(last_positions)
+    }
+
+    async fn read_batch(&self, last_position: u64) -> io::Result<Vec<Bytes>> {
+        let buffer = tokio::fs::read(self.batch_path(last_position)).await?;
+        decode_messages(Bytes::from(buffer))
+    }
+
+    /// Deletes the batches whose messages are all at or before `position`.
+    async fn truncate(&self, position: u64) -> io::Result<()> {
+        for last_position in self.list_batches().await? {
+            if last_position > position {
+                break;
+            }
+            tokio::fs::remove_file(self.batch_path(last_position)).await?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_batch_path(path: &Path) -> Option<u64> {
+    if path.extension()? != SPOOL_FILE_EXTENSION {
+        return None;
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+fn decode_messages(mut buffer: Bytes) -> io::Result<Vec<Bytes>> {
+    let mut messages = Vec::new();
+    while buffer.has_remaining() {
+        if buffer.remaining() < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Truncated MQTT spool batch.",
+            ));
+        }
+        let message_len = buffer.get_u32_le() as usize;
+        if buffer.remaining() < message_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Truncated MQTT spool batch.",
+            ));
+        }
+        messages.push(buffer.split_to(message_len));
+    }
+    Ok(messages)
+}
+
+/// Checks that the broker accepts a connection with the configured credentials.
+pub(super) async fn check_connectivity(params: MqttSourceParams) -> anyhow::Result<()> {
+    let client_id = format!("quickwit-check-{}", Ulid::new());
+    let (_client, mut event_loop) = AsyncClient::new(mqtt_options(&params, client_id), 1);
+    tokio::time::timeout(CONNECTIVITY_TIMEOUT, async {
+        loop {
+            if let Event::Incoming(Packet::ConnAck(_)) = event_loop.poll().await? {
+                return Ok::<_, anyhow::Error>(());
+            }
+        }
+    })
+    .await
+    .with_context(|| {
+        format!(
+            "Timed out connecting to MQTT broker `{}:{}`.",
+            params.host, params.port
+        )
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::{create_test_mailbox, Universe};
+    use quickwit_common::rand::append_random_suffix;
+    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_metastore::metastore_for_test;
+
+    use super::*;
+    use crate::source::SuggestTruncate;
+
+    fn make_params() -> MqttSourceParams {
+        MqttSourceParams {
+            // Nothing listens on this port: the source only replays its spool.
+            host: "localhost".to_string(),
+            port: 1,
+            topics: vec!["sensors/+/temperature".to_string()],
+            share_group: None,
+            username: None,
+            password: None,
+            max_queued_messages: 100,
+            session_expiry_interval_secs: 3600,
+        }
+    }
+
+    fn make_checkpoint(position: u64) -> SourceCheckpoint {
+        let mut checkpoint = SourceCheckpoint::default();
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta
+            .record_partition_delta(
+                PartitionId::from("test-node:0"),
+                Position::Beginning,
+                Position::from(position),
+            )
+            .unwrap();
+        checkpoint.try_apply_delta(checkpoint_delta).unwrap();
+        checkpoint
+    }
+
+    #[tokio::test]
+    async fn test_message_spool() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let spool = MessageSpool::open(temp_dir.path().join("spool")).await?;
+        assert!(spool.list_batches().await?.is_empty());
+
+        spool
+            .append(2, &[Bytes::from_static(b"foo"), Bytes::from_static(b"")])
+            .await?;
+        spool.append(3, &[Bytes::from_static(b"bar")]).await?;
+        assert_eq!(spool.list_batches().await?, vec![2, 3]);
+        assert_eq!(
+            spool.read_batch(2).await?,
+            vec![Bytes::from_static(b"foo"), Bytes::from_static(b"")]
+        );
+        spool.truncate(1).await?;
+        assert_eq!(spool.list_batches().await?, vec![2, 3]);
+
+        spool.truncate(2).await?;
+        assert_eq!(spool.list_batches().await?, vec![3]);
+
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&10u32.to_le_bytes());
+        buffer.extend_from_slice(b"foo");
+        assert!(decode_messages(Bytes::from(buffer)).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mqtt_source_replays_spool() -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let temp_dir = tempfile::tempdir()?;
+        let index_id = append_random_suffix("test-mqtt-source");
+        let params = make_params();
+        let ctx = SourceExecutionContext::for_test(
+            metastore_for_test(),
+            &index_id,
+            temp_dir.path().to_path_buf(),
+            SourceConfig {
+                source_id: "test-mqtt-source".to_string(),
+                num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
+                enabled: true,
+                source_params: SourceParams::Mqtt(params.clone()),
+            },
+        );
+        let spool = MessageSpool::open(spool_dir_path(&ctx)).await?;
+        spool
+            .append(1, &[Bytes::from_static(br#"{"body": "foo"}"#)])
+            .await?;
+        spool
+            .append(
+                3,
+                &[
+                    Bytes::from_static(br#"{"body": "bar"}"#),
+                    Bytes::from_static(br#"{"body": "baz"}"#),
+                ],
+            )
+            .await?;
+        spool
+            .append(4, &[Bytes::from_static(br#"{"body": "qux"}"#)])
+            .await?;
+
+        // The first two messages have already been published.
+        let mqtt_source =
+            MqttSourceFactory::typed_create_source(ctx, params, make_checkpoint(2)).await?;
+        assert_eq!(spool.list_batches().await?, vec![3, 4]);
+
+        let (doc_processor_mailbox, doc_processor_inbox) = create_test_mailbox();
+        let mqtt_source_actor = SourceActor {
+            source: Box::new(mqtt_source),
+            doc_processor_mailbox,
+            rate_limiter_opt: None,
+        };
+        let (mqtt_source_mailbox, mqtt_source_handle) =
+            universe.spawn_builder().spawn(mqtt_source_actor);
+        let state = mqtt_source_handle.process_pending_and_observe().await;
+        assert_eq!(state["current_position"], 4);
+        assert_eq!(state["num_replayed_messages"], 2);
+
+        let batches = doc_processor_inbox.drain_for_test_typed::<RawDocBatch>();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(
+            batches[0].docs,
+            vec![Bytes::from_static(br#"{"body": "baz"}"#)]
+        );
+        assert_eq!(
+            batches[1].docs,
+            vec![Bytes::from_static(br#"{"body": "qux"}"#)]
+        );
+        assert_eq!(
+            format!("{:?}", batches[0].checkpoint_delta),
+            "∆(test-node:0:(00000000000000000002..00000000000000000003])"
+        );
+
+        mqtt_source_mailbox
+            .send_message(SuggestTruncate(make_checkpoint(4)))
+            .await?;
+        mqtt_source_handle.process_pending_and_observe().await;
+        assert!(spool.list_batches().await?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+/// Acknowledgements of the messages emitted by a source reading from a queue whose messages have
+/// no position of their own, waiting for the splits holding the messages to be published.
+///
+/// Such sources record the number of messages they have emitted as position in the checkpoint,
+/// so that the acknowledgements can be released when the publisher suggests to truncate the
+/// source up to a given position.
+#[derive(Debug, Default)]
+pub(crate) struct PendingAcks {
+    /// Acknowledgements keyed by the position of the last message of their batch.
+    acks_per_position: BTreeMap<u64, Vec<String>>,
+}
+
+impl PendingAcks {
+    pub fn push(&mut self, position: u64, acks: Vec<String>) {
+        self.acks_per_position
+            .entry(position)
+            .or_default()
+            .extend(acks);
+    }
+
+    /// Removes and returns the acknowledgements of the messages up to and including `position`.
+    pub fn drain_up_to(&mut self, position: u64) -> Vec<String> {
+        let unpublished_acks = self.acks_per_position.split_off(&(position + 1));
+        let published_acks = std::mem::replace(&mut self.acks_per_position, unpublished_acks);
+        published_acks.into_values().flatten().collect()
+    }
+
+    pub fn num_pending_acks(&self) -> usize {
+        self.acks_per_position.values().map(Vec::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_acks() {
+        let mut pending_acks = PendingAcks::default();
+        assert!(pending_acks.drain_up_to(10).is_empty());
+
+        pending_acks.push(2, vec!["ack-1".to_string(), "ack-2".to_string()]);
+        pending_acks.push(3, vec!["ack-3".to_string()]);
+        pending_acks.push(5, vec!["ack-4".to_string()]);
+        pending_acks.push(5, vec!["ack-5".to_string()]);
+        assert_eq!(pending_acks.num_pending_acks(), 5);
+
+        assert!(pending_acks.drain_up_to(1).is_empty());
+        assert_eq!(
+            pending_acks.drain_up_to(3),
+            vec![
+                "ack-1".to_string(),
+                "ack-2".to_string(),
+                "ack-3".to_string()
+            ]
+        );
+        assert_eq!(pending_acks.num_pending_acks(), 2);
+        assert!(pending_acks.drain_up_to(4).is_empty());
+        assert_eq!(
+            pending_acks.drain_up_to(5),
+            vec!["ack-4".to_string(), "ack-5".to_string()]
+        );
+        assert_eq!(pending_acks.num_pending_acks(), 0);
+    }
+}
@@ -0,0 +1,850 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fmt, mem};
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
+use quickwit_config::{PostgresCdcSourceParams, PostgresDecodingPlugin};
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use tracing::{info, warn};
+
+use crate::actors::DocProcessor;
+use crate::models::RawDocBatch;
+use crate::source::{
+    Source, SourceActor, SourceContext, SourceExecutionContext, TypedSourceFactory,
+};
+
+/// Maximum number of changes fetched by a call to `emit_batches`.
+const MAX_NUM_CHANGES_PER_BATCH: i64 = 10_000;
+
+/// Interval at which the source polls the replication slot once it has caught up with it.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Factory for instantiating a `PostgresCdcSource`.
+pub struct PostgresCdcSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for PostgresCdcSourceFactory {
+    type Source = PostgresCdcSource;
+    type Params = PostgresCdcSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: PostgresCdcSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        PostgresCdcSource::try_new(ctx, params, checkpoint).await
+    }
+}
+
+pub struct PostgresCdcSourceState {
+    /// Position of the commit of the last transaction emitted.
+    current_position: Position,
+    /// Position up to which the replication slot was advanced.
+    confirmed_position: Position,
+    /// Number of bytes processed by the source.
+    pub num_bytes_processed: u64,
+    /// Number of transactions processed by the source.
+    pub num_transactions_processed: u64,
+    /// Number of row changes processed by the source (including invalid changes).
+    pub num_changes_processed: u64,
+    /// Number of changes that could not be decoded.
+    pub num_invalid_changes: u64,
+}
+
+/// A source indexing the row changes of a Postgres database, decoded from a logical replication
+/// slot by the `wal2json` or the `pgoutput` plugin.
+///
+/// Each change is emitted as a JSON document holding the operation, the table and the values of
+/// the row. Changes are emitted transaction by transaction, and the position of a transaction in
+/// the checkpoint is the LSN of its commit. The source peeks at the changes of the slot without
+/// consuming them, and advances the slot once the splits holding the changes are published, so
+/// the changes not published yet are decoded again when a pipeline restarts. The source skips
+/// the transactions decoded again up to the position of the slot in the checkpoint.
+///
+/// As peeking always starts from the position of the slot, the source only fetches the changes
+/// following the ones it already peeked at since the slot was last advanced.
+pub struct PostgresCdcSource {
+    ctx: Arc<SourceExecutionContext>,
+    params: PostgresCdcSourceParams,
+    pool: PgPool,
+    partition_id: PartitionId,
+    /// Number of changes held by the slot before the changes that were not peeked at yet, since
+    /// the slot was last advanced.
+    num_peeked_changes: i64,
+    pgoutput_decoder: PgoutputDecoder,
+    state: PostgresCdcSourceState,
+}
+
+impl fmt::Debug for PostgresCdcSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PostgresCdcSource {{ source_id: {}, slot_name: {} }}",
+            self.ctx.source_config.source_id, self.params.slot_name
+        )
+    }
+}
+
+async fn connect_postgres(connection_uri: &str) -> anyhow::Result<PgPool> {
+    // A replication slot can only be used by one connection at a time.
+    PgPoolOptions::new()
+        .max_connections(1)
+        .connect(connection_uri)
+        .await
+        .context("Failed to connect to Postgres database.")
+}
+
+fn plugin_name(decoding_plugin: PostgresDecodingPlugin) -> &'static str {
+    match decoding_plugin {
+        PostgresDecodingPlugin::Wal2json => "wal2json",
+        PostgresDecodingPlugin::Pgoutput => "pgoutput",
+    }
+}
+
+impl PostgresCdcSource {
+    /// Instantiates a new `PostgresCdcSource`, creating its replication slot if it does not exist.
+    pub async fn try_new(
+        ctx: Arc<SourceExecutionContext>,
+        params: PostgresCdcSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self> {
+        let pool = connect_postgres(&params.connection_uri).await?;
+        let plugin = plugin_name(params.decoding_plugin);
+        let slot_plugin_opt: Option<(String,)> =
+            sqlx::query_as("SELECT plugin::text FROM pg_replication_slots WHERE slot_name = $1")
+                .bind(&params.slot_name)
+                .fetch_optional(&pool)
+                .await
+                .context("Failed to look up replication slot.")?;
+        match slot_plugin_opt {
+            Some((slot_plugin,)) if slot_plugin != plugin => {
+                bail!(
+                    "Replication slot `{}` uses the `{}` plugin, expected `{}`.",
+                    params.slot_name,
+                    slot_plugin,
+                    plugin
+                );
+            }
+            Some(_) => {}
+            None => {
+                info!(slot_name=%params.slot_name, plugin=%plugin, "Creating replication slot.");
+                sqlx::query("SELECT pg_create_logical_replication_slot($1, $2)")
+                    .bind(&params.slot_name)
+                    .bind(plugin)
+                    .execute(&pool)
+                    .await
+                    .with_context(|| {
+                        format!("Failed to create replication slot `{}`.", params.slot_name)
+                    })?;
+            }
+        }
+        let partition_id = PartitionId::from(params.slot_name.as_str());
+        let current_position = checkpoint
+            .position_for_partition(&partition_id)
+            .cloned()
+            .unwrap_or(Position::Beginning);
+        info!(
+            index_id=%ctx.index_id,
+            source_id=%ctx.source_config.source_id,
+            slot_name=%params.slot_name,
+            plugin=%plugin,
+            position=%current_position.as_str(),
+            "Starting Postgres CDC source."
+        );
+        let state = PostgresCdcSourceState {
+            current_position,
+            confirmed_position: Position::Beginning,
+            num_bytes_processed: 0,
+            num_transactions_processed: 0,
+            num_changes_processed: 0,
+            num_invalid_changes: 0,
+        };
+        Ok(Self {
+            ctx,
+            params,
+            pool,
+            partition_id,
+            num_peeked_changes: 0,
+            pgoutput_decoder: PgoutputDecoder::default(),
+            state,
+        })
+    }
+
+    /// Peeks at the changes of the slot following the first `num_skipped_changes` ones, up to the
+    /// end of the transaction holding the `upto_nchanges`-th change. Returns the LSN and the
+    /// decoded message of each of them.
+    async fn peek_changes(
+        &mut self,
+        num_skipped_changes: i64,
+        upto_nchanges: i64,
+    ) -> anyhow::Result<Vec<(String, anyhow::Result<DecodedMessage>)>> {
+        let changes = match self.params.decoding_plugin {
+            PostgresDecodingPlugin::Wal2json => {
+                let rows: Vec<(String, String)> = sqlx::query_as(
+                    "SELECT lsn::text, data FROM pg_logical_slot_peek_changes($1, NULL, $2, \
+                     'format-version', '2', 'include-transaction', 'true') OFFSET $3",
+                )
+                .bind(&self.params.slot_name)
+                .bind(upto_nchanges)
+                .bind(num_skipped_changes)
+                .fetch_all(&self.pool)
+                .await
+                .context("Failed to peek at the changes of the replication slot.")?;
+                rows.into_iter()
+                    .map(|(lsn, data)| {
+                        self.state.num_bytes_processed += data.len() as u64;
+                        let decoded_message_res = decode_wal2json_message(&data, &lsn);
+                        (lsn, decoded_message_res)
+                    })
+                    .collect()
+            }
+            PostgresDecodingPlugin::Pgoutput => {
+                let rows: Vec<(String, Vec<u8>)> = sqlx::query_as(
+                    "SELECT lsn::text, data FROM pg_logical_slot_peek_binary_changes($1, NULL, \
+                     $2, 'proto_version', '1', 'publication_names', $3) OFFSET $4",
+                )
+                .bind(&self.params.slot_name)
+                .bind(upto_nchanges)
+                .bind(self.params.publication_names.join(","))
+                .bind(num_skipped_changes)
+                .fetch_all(&self.pool)
+                .await
+                .context("Failed to peek at the changes of the replication slot.")?;
+                rows.into_iter()
+                    .map(|(lsn, data)| {
+                        self.state.num_bytes_processed += data.len() as u64;
+                        let decoded_message_res = self.pgoutput_decoder.decode(&data, &lsn);
+                        (lsn, decoded_message_res)
+                    })
+                    .collect()
+            }
+        };
+        Ok(changes)
+    }
+}
+
+#[async_trait]
+impl Source for PostgresCdcSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        // Peeking always starts from the position of the slot: the changes peeked at since then
+        // are skipped.
+        let upto_nchanges = self.num_peeked_changes + MAX_NUM_CHANGES_PER_BATCH;
+        let changes = ctx
+            .protect_future(self.peek_changes(self.num_peeked_changes, upto_nchanges))
+            .await?;
+        ctx.record_progress();
+
+        let num_fetched_changes = changes.len() as i64;
+        let mut num_committed_changes = 0;
+        let mut transaction_docs = Vec::new();
+        let mut num_transaction_changes = 0;
+        let mut docs = Vec::new();
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+
+        for (lsn, decoded_message_res) in changes {
+            num_transaction_changes += 1;
+            match decoded_message_res {
+                Ok(DecodedMessage::Change(doc)) => transaction_docs.push(doc),
+                Ok(DecodedMessage::Commit) => {
+                    num_committed_changes += mem::take(&mut num_transaction_changes);
+                    let transaction_docs = mem::take(&mut transaction_docs);
+                    let commit_position = position_from_lsn(&lsn)?;
+                    if commit_position <= self.state.current_position {
+                        continue;
+                    }
+                    self.state.num_transactions_processed += 1;
+                    self.state.num_changes_processed += transaction_docs.len() as u64;
+                    docs.extend(
+                        transaction_docs
+                            .iter()
+                            .map(|doc| Bytes::from(doc.to_string())),
+                    );
+                    checkpoint_delta
+                        .record_partition_delta(
+                            self.partition_id.clone(),
+                            self.state.current_position.clone(),
+                            commit_position.clone(),
+                        )
+                        .context("Failed to record partition delta.")?;
+                    self.state.current_position = commit_position;
+                }
+                Ok(DecodedMessage::Other) => {}
+                Err(error) => {
+                    warn!(lsn=%lsn, error=?error, "Failed to decode change.");
+                    self.state.num_changes_processed += 1;
+                    self.state.num_invalid_changes += 1;
+                }
+            }
+        }
+        self.num_peeked_changes += num_committed_changes;
+
+        let wait_for = if num_fetched_changes < MAX_NUM_CHANGES_PER_BATCH {
+            POLL_INTERVAL
+        } else {
+            Duration::default()
+        };
+        if checkpoint_delta.is_empty() {
+            return Ok(wait_for);
+        }
+        let batch = RawDocBatch {
+            docs,
+            checkpoint_delta,
+        };
+        ctx.send_message(doc_processor_mailbox, batch).await?;
+        Ok(wait_for)
+    }
+
+    async fn suggest_truncate(
+        &mut self,
+        checkpoint: SourceCheckpoint,
+        ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        let published_position = match checkpoint.position_for_partition(&self.partition_id) {
+            Some(published_position) if *published_position > self.state.confirmed_position => {
+                published_position.clone()
+            }
+            _ => return Ok(()),
+        };
+        let published_lsn = lsn_from_position(&published_position)?;
+        // If advancing the slot fails, the changes are decoded again and the source skips them.
+        ctx.protect_future(
+            sqlx::query("SELECT pg_replication_slot_advance($1, $2::pg_lsn)")
+                .bind(&self.params.slot_name)
+                .bind(&published_lsn)
+                .execute(&self.pool),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to advance replication slot `{}` to LSN `{}`.",
+                self.params.slot_name, published_lsn
+            )
+        })?;
+        self.state.confirmed_position = published_position;
+        // The slot no longer holds the changes published. The changes peeked at and not published
+        // yet are fetched again and skipped by position.
+        self.num_peeked_changes = 0;
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "PostgresCdcSource{{source_id={}}}",
+            self.ctx.source_config.source_id
+        )
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        let current_lsn_opt = lsn_from_position(&self.state.current_position).ok();
+        let confirmed_lsn_opt = lsn_from_position(&self.state.confirmed_position).ok();
+        json!({
+            "index_id": self.ctx.index_id,
+            "source_id": self.ctx.source_config.source_id,
+            "slot_name": self.params.slot_name,
+            "current_lsn": current_lsn_opt,
+            "confirmed_lsn": confirmed_lsn_opt,
+            "num_bytes_processed": self.state.num_bytes_processed,
+            "num_transactions_processed": self.state.num_transactions_processed,
+            "num_changes_processed": self.state.num_changes_processed,
+            "num_invalid_changes": self.state.num_invalid_changes,
+        })
+    }
+}
+
+/// Converts an LSN such as `16/B374D848` into a position. The LSN is converted into its 64-bit
+/// integer value so that the positions compare like the LSNs.
+fn position_from_lsn(lsn: &str) -> anyhow::Result<Position> {
+    let (high, low) = lsn
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Invalid LSN `{}`.", lsn))?;
+    let high = u32::from_str_radix(high, 16).with_context(|| format!("Invalid LSN `{}`.", lsn))?;
+    let low = u32::from_str_radix(low, 16).with_context(|| format!("Invalid LSN `{}`.", lsn))?;
+    Ok(Position::from(((high as u64) << 32) | low as u64))
+}
+
+fn lsn_from_position(position: &Position) -> anyhow::Result<String> {
+    let lsn: u64 = position
+        .as_str()
+        .parse()
+        .with_context(|| format!("Invalid LSN position `{}`.", position.as_str()))?;
+    Ok(format!("{:X}/{:X}", lsn >> 32, lsn as u32))
+}
+
+/// A message decoded from a replication slot.
+#[derive(Debug, PartialEq)]
+enum DecodedMessage {
+    /// A row change, as the JSON document to index.
+    Change(JsonValue),
+    /// The commit of the transaction of the changes decoded since the previous commit.
+    Commit,
+    /// Any other message, for instance the beginning of a transaction.
+    Other,
+}
+
+/// Builds the document indexed for a row change.
+fn change_doc(
+    operation: &str,
+    schema: &str,
+    table: &str,
+    lsn: &str,
+    row_opt: Option<JsonMap<String, JsonValue>>,
+    old_row_opt: Option<JsonMap<String, JsonValue>>,
+) -> JsonValue {
+    let mut doc = JsonMap::new();
+    doc.insert("op".to_string(), json!(operation));
+    doc.insert("schema".to_string(), json!(schema));
+    doc.insert("table".to_string(), json!(table));
+    doc.insert("lsn".to_string(), json!(lsn));
+    if let Some(row) = row_opt {
+        doc.insert("row".to_string(), JsonValue::Object(row));
+    }
+    if let Some(old_row) = old_row_opt {
+        doc.insert("old_row".to_string(), JsonValue::Object(old_row));
+    }
+    JsonValue::Object(doc)
+}
+
+/// Decodes a message of the `wal2json` plugin, in format version 2.
+fn decode_wal2json_message(data: &str, lsn: &str) -> anyhow::Result<DecodedMessage> {
+    let message: JsonValue = serde_json::from_str(data).context("Invalid wal2json message.")?;
+    let action = message
+        .get("action")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| anyhow!("wal2json message has no action."))?;
+    let operation = match action {
+        "I" => "insert",
+        "U" => "update",
+        "D" => "delete",
+        "C" => return Ok(DecodedMessage::Commit),
+        _ => return Ok(DecodedMessage::Other),
+    };
+    let schema = message
+        .get("schema")
+        .and_then(JsonValue::as_str)
+        .unwrap_or_default();
+    let table = message
+        .get("table")
+        .and_then(JsonValue::as_str)
+        .unwrap_or_default();
+    let row_opt = wal2json_row(&message, "columns")?;
+    let old_row_opt = wal2json_row(&message, "identity")?;
+    Ok(DecodedMessage::Change(change_doc(
+        operation,
+        schema,
+        table,
+        lsn,
+        row_opt,
+        old_row_opt,
+    )))
+}
+
+fn wal2json_row(
+    message: &JsonValue,
+    key: &str,
+) -> anyhow::Result<Option<JsonMap<String, JsonValue>>> {
+    let columns = match message.get(key).and_then(JsonValue::as_array) {
+        Some(columns) => columns,
+        None => return Ok(None),
+    };
+    let mut row = JsonMap::new();
+    for column in columns {
+        let name = column
+            .get("name")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| anyhow!("wal2json column has no name."))?;
+        let value = column.get("value").cloned().unwrap_or(JsonValue::Null);
+        row.insert(name.to_string(), value);
+    }
+    Ok(Some(row))
+}
+
+/// A relation described by the `pgoutput` plugin.
+#[derive(Debug)]
+struct Relation {
+    schema: String,
+    table: String,
+    columns: Vec<(String, u32)>,
+}
+
+/// Decodes the messages of the `pgoutput` plugin, in protocol version 1. The plugin describes
+/// each relation once, before its first change, so the decoder keeps track of the relations.
+#[derive(Default)]
+struct PgoutputDecoder {
+    relations: HashMap<u32, Relation>,
+}
+
+impl PgoutputDecoder {
+    fn decode(&mut self, data: &[u8], lsn: &str) -> anyhow::Result<DecodedMessage> {
+        let mut reader = PgoutputReader { data };
+        let operation = match reader.read_u8()? {
+            b'R' => {
+                let relation_id = reader.read_u32()?;
+                let schema = match reader.read_cstr()? {
+                    "" => "pg_catalog".to_string(),
+                    schema => schema.to_string(),
+                };
+                let table = reader.read_cstr()?.to_string();
+                let _replica_identity = reader.read_u8()?;
+                let num_columns = reader.read_u16()?;
+                let mut columns = Vec::with_capacity(num_columns as usize);
+                for _ in 0..num_columns {
+                    let _flags = reader.read_u8()?;
+                    let name = reader.read_cstr()?.to_string();
+                    let type_oid = reader.read_u32()?;
+                    let _type_modifier = reader.read_u32()?;
+                    columns.push((name, type_oid));
+                }
+                let relation = Relation {
+                    schema,
+                    table,
+                    columns,
+                };
+                self.relations.insert(relation_id, relation);
+                return Ok(DecodedMessage::Other);
+            }
+            b'C' => return Ok(DecodedMessage::Commit),
+            b'I' => "insert",
+            b'U' => "update",
+            b'D' => "delete",
+            _ => return Ok(DecodedMessage::Other),
+        };
+        let relation_id = reader.read_u32()?;
+        let relation = self
+            .relations
+            .get(&relation_id)
+            .ok_or_else(|| anyhow!("Unknown relation `{}`.", relation_id))?;
+        let mut row_opt = None;
+        let mut old_row_opt = None;
+        while !reader.data.is_empty() {
+            match reader.read_u8()? {
+                b'N' => row_opt = Some(reader.read_tuple(relation, false)?),
+                b'K' => old_row_opt = Some(reader.read_tuple(relation, true)?),
+                b'O' => old_row_opt = Some(reader.read_tuple(relation, false)?),
+                tuple_type => bail!("Unexpected tuple type `{}`.", tuple_type as char),
+            }
+        }
+        Ok(DecodedMessage::Change(change_doc(
+            operation,
+            &relation.schema,
+            &relation.table,
+            lsn,
+            row_opt,
+            old_row_opt,
+        )))
+    }
+}
+
+struct PgoutputReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> PgoutputReader<'a> {
+    fn read_bytes(&mut self, num_bytes: usize) -> anyhow::Result<&'a [u8]> {
+        if self.data.len() < num_bytes {
+            bail!("Truncated pgoutput message.");
+        }
+        let (bytes, data) = self.data.split_at(num_bytes);
+        self.data = data;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> anyhow::Result<u16> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> anyhow::Result<u32> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_cstr(&mut self) -> anyhow::Result<&'a str> {
+        let len = self
+            .data
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| anyhow!("Truncated pgoutput message."))?;
+        let bytes = self.read_bytes(len + 1)?;
+        std::str::from_utf8(&bytes[..len]).context("Invalid UTF-8 string in pgoutput message.")
+    }
+
+    /// Reads the values of a row. Values of unchanged TOASTed columns are not sent by Postgres,
+    /// and neither are the values of the columns of a key tuple that are not part of the key.
+    fn read_tuple(
+        &mut self,
+        relation: &Relation,
+        is_key: bool,
+    ) -> anyhow::Result<JsonMap<String, JsonValue>> {
+        let num_columns = self.read_u16()? as usize;
+        if num_columns != relation.columns.len() {
+            bail!(
+                "Tuple of relation `{}.{}` has {} columns, expected {}.",
+                relation.schema,
+                relation.table,
+                num_columns,
+                relation.columns.len()
+            );
+        }
+        let mut row = JsonMap::new();
+        for (name, type_oid) in &relation.columns {
+            match self.read_u8()? {
+                b'n' if is_key => {}
+                b'n' => {
+                    row.insert(name.clone(), JsonValue::Null);
+                }
+                b'u' => {}
+                b't' => {
+                    let len = self.read_u32()? as usize;
+                    let text = std::str::from_utf8(self.read_bytes(len)?)
+                        .context("Invalid UTF-8 value in pgoutput message.")?;
+                    row.insert(name.clone(), text_value_to_json(text, *type_oid));
+                }
+                value_kind => bail!("Unexpected column value kind `{}`.", value_kind as char),
+            }
+        }
+        Ok(row)
+    }
+}
+
+/// Converts the text representation of a value of the Postgres type `type_oid` into JSON. Values
+/// of types without a JSON counterpart are kept as strings.
+fn text_value_to_json(text: &str, type_oid: u32) -> JsonValue {
+    const BOOL_OID: u32 = 16;
+    const INT8_OID: u32 = 20;
+    const INT2_OID: u32 = 21;
+    const INT4_OID: u32 = 23;
+    const OID_OID: u32 = 26;
+    const JSON_OID: u32 = 114;
+    const FLOAT4_OID: u32 = 700;
+    const FLOAT8_OID: u32 = 701;
+    const NUMERIC_OID: u32 = 1700;
+    const JSONB_OID: u32 = 3802;
+
+    let json_value_opt = match type_oid {
+        BOOL_OID => Some(JsonValue::Bool(text == "t")),
+        INT8_OID | INT2_OID | INT4_OID | OID_OID => text.parse::<i64>().ok().map(JsonValue::from),
+        FLOAT4_OID | FLOAT8_OID | NUMERIC_OID => text
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(JsonValue::Number),
+        JSON_OID | JSONB_OID => serde_json::from_str(text).ok(),
+        _ => None,
+    };
+    json_value_opt.unwrap_or_else(|| JsonValue::String(text.to_string()))
+}
+
+/// Checks whether we can connect to the Postgres database and whether it is configured for
+/// logical decoding.
+pub(super) async fn check_connectivity(params: PostgresCdcSourceParams) -> anyhow::Result<()> {
+    let pool = connect_postgres(&params.connection_uri).await?;
+    let (wal_level,): (String,) = sqlx::query_as("SHOW wal_level")
+        .fetch_one(&pool)
+        .await
+        .context("Failed to fetch the `wal_level` of the Postgres database.")?;
+    if wal_level != "logical" {
+        bail!(
+            "Postgres database `wal_level` must be `logical` for logical decoding, got `{}`.",
+            wal_level
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_from_lsn() {
+        assert_eq!(
+            position_from_lsn("0/16B3748").unwrap(),
+            Position::from(0x16B3748u64)
+        );
+        assert_eq!(
+            position_from_lsn("16/B374D848").unwrap(),
+            Position::from(0x16_B374_D848u64)
+        );
+        assert!(position_from_lsn("1/FFFFFFFF").unwrap() < position_from_lsn("2/0").unwrap());
+        assert!(Position::Beginning < position_from_lsn("0/0").unwrap());
+        position_from_lsn("16B3748").unwrap_err();
+
+        let position = position_from_lsn("16/B374D848").unwrap();
+        assert_eq!(lsn_from_position(&position).unwrap(), "16/B374D848");
+        lsn_from_position(&Position::Beginning).unwrap_err();
+    }
+
+    #[test]
+    fn test_decode_wal2json_message() {
+        assert_eq!(
+            decode_wal2json_message(r#"{"action":"B"}"#, "0/1").unwrap(),
+            DecodedMessage::Other
+        );
+        assert_eq!(
+            decode_wal2json_message(r#"{"action":"C"}"#, "0/3").unwrap(),
+            DecodedMessage::Commit
+        );
+        let insert = r#"{"action":"I","schema":"public","table":"orders","columns":[
+            {"name":"id","type":"integer","value":1},
+            {"name":"status","type":"text","value":"paid"}]}"#;
+        assert_eq!(
+            decode_wal2json_message(insert, "0/2").unwrap(),
+            DecodedMessage::Change(json!({
+                "op": "insert",
+                "schema": "public",
+                "table": "orders",
+                "lsn": "0/2",
+                "row": {"id": 1, "status": "paid"},
+            }))
+        );
+        let delete = r#"{"action":"D","schema":"public","table":"orders","identity":[
+            {"name":"id","type":"integer","value":1}]}"#;
+        assert_eq!(
+            decode_wal2json_message(delete, "0/4").unwrap(),
+            DecodedMessage::Change(json!({
+                "op": "delete",
+                "schema": "public",
+                "table": "orders",
+                "lsn": "0/4",
+                "old_row": {"id": 1},
+            }))
+        );
+        decode_wal2json_message("not json", "0/5").unwrap_err();
+    }
+
+    fn relation_message() -> Vec<u8> {
+        let mut data = vec![b'R'];
+        data.extend_from_slice(&16385u32.to_be_bytes());
+        data.extend_from_slice(b"public\0orders\0");
+        data.push(b'd');
+        data.extend_from_slice(&3u16.to_be_bytes());
+        for (name, type_oid) in [("id", 23u32), ("paid", 16), ("details", 3802)] {
+            data.push(1);
+            data.extend_from_slice(name.as_bytes());
+            data.push(0);
+            data.extend_from_slice(&type_oid.to_be_bytes());
+            data.extend_from_slice(&u32::MAX.to_be_bytes());
+        }
+        data
+    }
+
+    fn tuple_data(values: &[Option<&str>]) -> Vec<u8> {
+        let mut data = (values.len() as u16).to_be_bytes().to_vec();
+        for value_opt in values {
+            match value_opt {
+                Some(value) => {
+                    data.push(b't');
+                    data.extend_from_slice(&(value.len() as u32).to_be_bytes());
+                    data.extend_from_slice(value.as_bytes());
+                }
+                None => data.push(b'n'),
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_pgoutput_decoder() {
+        let mut decoder = PgoutputDecoder::default();
+
+        let mut insert = vec![b'I'];
+        insert.extend_from_slice(&16385u32.to_be_bytes());
+        insert.push(b'N');
+        insert.extend(tuple_data(&[Some("1"), Some("f"), Some(r#"{"items":2}"#)]));
+        // The relation is unknown until it is described.
+        decoder.decode(&insert, "0/2").unwrap_err();
+
+        assert_eq!(
+            decoder.decode(&relation_message(), "0/1").unwrap(),
+            DecodedMessage::Other
+        );
+        assert_eq!(
+            decoder.decode(&insert, "0/2").unwrap(),
+            DecodedMessage::Change(json!({
+                "op": "insert",
+                "schema": "public",
+                "table": "orders",
+                "lsn": "0/2",
+                "row": {"id": 1, "paid": false, "details": {"items": 2}},
+            }))
+        );
+        let mut update = vec![b'U'];
+        update.extend_from_slice(&16385u32.to_be_bytes());
+        update.push(b'K');
+        update.extend(tuple_data(&[Some("1"), None, None]));
+        update.push(b'N');
+        update.extend(tuple_data(&[Some("2"), Some("t"), None]));
+        assert_eq!(
+            decoder.decode(&update, "0/3").unwrap(),
+            DecodedMessage::Change(json!({
+                "op": "update",
+                "schema": "public",
+                "table": "orders",
+                "lsn": "0/3",
+                "row": {"id": 2, "paid": true, "details": null},
+                "old_row": {"id": 1},
+            }))
+        );
+        let mut delete = vec![b'D'];
+        delete.extend_from_slice(&16385u32.to_be_bytes());
+        delete.push(b'K');
+        delete.extend(tuple_data(&[Some("2")]));
+        // The tuple does not match the relation.
+        decoder.decode(&delete, "0/4").unwrap_err();
+
+        let mut commit = vec![b'C', 0];
+        commit.extend_from_slice(&[0; 24]);
+        assert_eq!(
+            decoder.decode(&commit, "0/5").unwrap(),
+            DecodedMessage::Commit
+        );
+    }
+
+    #[test]
+    fn test_text_value_to_json() {
+        assert_eq!(text_value_to_json("t", 16), json!(true));
+        assert_eq!(text_value_to_json("-42", 20), json!(-42));
+        assert_eq!(text_value_to_json("1.5", 1700), json!(1.5));
+        assert_eq!(text_value_to_json("NaN", 701), json!("NaN"));
+        assert_eq!(text_value_to_json("[1,2]", 114), json!([1, 2]));
+        assert_eq!(
+            text_value_to_json("2022-10-14 12:00:00+00", 1184),
+            json!("2022-10-14 12:00:00+00")
+        );
+    }
+}
@@ -0,0 +1,458 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use pulsar::consumer::InitialPosition;
+use pulsar::message::proto::MessageIdData;
+use pulsar::{Consumer, ConsumerOptions, Pulsar, SubType, TokioExecutor};
+use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
+use quickwit_config::{PulsarSourceParams, PulsarSubscriptionType};
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use serde_json::json;
+use tokio::sync::Mutex;
+use tokio::time;
+use tracing::{info, warn};
+
+use crate::actors::DocProcessor;
+use crate::models::RawDocBatch;
+use crate::source::{
+    Source, SourceActor, SourceContext, SourceExecutionContext, TypedSourceFactory,
+};
+
+const TARGET_BATCH_NUM_BYTES: u64 = 5_000_000;
+
+/// Maximum time spent receiving messages in a single call to `emit_batches`.
+const EMIT_BATCHES_TIMEOUT: Duration = Duration::from_secs(1);
+
+type PulsarConsumer = Consumer<Vec<u8>, TokioExecutor>;
+
+/// Factory for instantiating a `PulsarSource`.
+pub struct PulsarSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for PulsarSourceFactory {
+    type Source = PulsarSource;
+    type Params = PulsarSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: PulsarSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        PulsarSource::try_new(ctx, params, checkpoint).await
+    }
+}
+
+#[derive(Default)]
+pub struct PulsarSourceState {
+    /// Positions of the last messages emitted, keyed by topic partition.
+    current_positions: BTreeMap<PartitionId, Position>,
+    /// Number of bytes processed by the source.
+    pub num_bytes_processed: u64,
+    /// Number of messages processed by the source (including invalid messages).
+    pub num_messages_processed: u64,
+    /// Number of invalid messages, i.e., that were empty or could not be parsed.
+    pub num_invalid_messages: u64,
+    /// Number of messages redelivered by Pulsar that had already been emitted.
+    pub num_skipped_messages: u64,
+}
+
+/// A source consuming Apache Pulsar topics through a subscription.
+///
+/// Each topic partition is a partition of the checkpoint, and the position of a message is its
+/// message ID. The subscription is acknowledged cumulatively, partition by partition, once the
+/// splits holding the messages are published, so Pulsar redelivers the messages that are not
+/// published yet when a pipeline restarts. The source skips the messages redelivered up to the
+/// position of their partition in the checkpoint.
+///
+/// With a `failover` subscription, the partitions are spread over the pipelines of the source, each
+/// of them recording positions only for the partitions it consumes. When a partition moves to
+/// another pipeline, the source fetches the position of the partition from the metastore.
+pub struct PulsarSource {
+    ctx: Arc<SourceExecutionContext>,
+    topics: Vec<String>,
+    subscription_name: String,
+    // Pulsar consumers are not `Sync`. We only access the consumer through `Mutex::get_mut`,
+    // which does not lock.
+    consumer: Mutex<PulsarConsumer>,
+    /// ID of the last message of each batch emitted, keyed by topic partition and position,
+    /// waiting for the publication of the batch to be acknowledged.
+    pending_acks: BTreeMap<PartitionId, BTreeMap<Position, MessageIdData>>,
+    state: PulsarSourceState,
+}
+
+impl fmt::Debug for PulsarSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PulsarSource {{ source_id: {}, subscription: {} }}",
+            self.ctx.source_config.source_id, self.subscription_name
+        )
+    }
+}
+
+async fn connect_pulsar(address: &str) -> anyhow::Result<Pulsar<TokioExecutor>> {
+    Pulsar::builder(address, TokioExecutor)
+        .build()
+        .await
+        .with_context(|| format!("Failed to connect to Pulsar cluster `{address}`."))
+}
+
+impl PulsarSource {
+    /// Instantiates a new `PulsarSource`.
+    pub async fn try_new(
+        ctx: Arc<SourceExecutionContext>,
+        params: PulsarSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self> {
+        let subscription_name = params.subscription_name.clone().unwrap_or_else(|| {
+            format!("quickwit-{}-{}", ctx.index_id, ctx.source_config.source_id)
+        });
+        let consumer_name = format!("quickwit-{}-{}", ctx.node_id, ctx.pipeline_ord);
+        let subscription_type = match params.subscription_type {
+            PulsarSubscriptionType::Exclusive => SubType::Exclusive,
+            PulsarSubscriptionType::Failover => SubType::Failover,
+        };
+        let pulsar = connect_pulsar(&params.address).await?;
+        let consumer: PulsarConsumer = pulsar
+            .consumer()
+            .with_topics(params.topics.clone())
+            .with_subscription(subscription_name.clone())
+            .with_subscription_type(subscription_type)
+            .with_consumer_name(consumer_name.clone())
+            .with_options(ConsumerOptions {
+                initial_position: InitialPosition::Earliest,
+                ..Default::default()
+            })
+            .build()
+            .await
+            .with_context(|| {
+                format!("Failed to subscribe to Pulsar subscription `{subscription_name}`.")
+            })?;
+        info!(
+            index_id=%ctx.index_id,
+            source_id=%ctx.source_config.source_id,
+            topics=?params.topics,
+            subscription=%subscription_name,
+            consumer=%consumer_name,
+            "Starting Pulsar source."
+        );
+        let state = PulsarSourceState {
+            current_positions: checkpoint.iter().collect(),
+            ..Default::default()
+        };
+        Ok(Self {
+            ctx,
+            topics: params.topics,
+            subscription_name,
+            consumer: Mutex::new(consumer),
+            pending_acks: BTreeMap::new(),
+            state,
+        })
+    }
+
+    /// Fetches the position of a partition that this pipeline did not consume so far from the
+    /// checkpoint of the source in the metastore. Another pipeline may have consumed it.
+    async fn fetch_partition_position(
+        &self,
+        partition_id: &PartitionId,
+        ctx: &SourceContext,
+    ) -> anyhow::Result<Position> {
+        let index_metadata = ctx
+            .protect_future(self.ctx.metastore.index_metadata(&self.ctx.index_id))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to fetch index metadata for index `{}`.",
+                    self.ctx.index_id
+                )
+            })?;
+        let position = index_metadata
+            .checkpoint
+            .source_checkpoint(&self.ctx.source_config.source_id)
+            .and_then(|checkpoint| checkpoint.position_for_partition(partition_id))
+            .cloned()
+            .unwrap_or(Position::Beginning);
+        Ok(position)
+    }
+}
+
+#[async_trait]
+impl Source for PulsarSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let mut batch_num_bytes = 0;
+        let mut docs = Vec::new();
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        let mut last_message_ids: BTreeMap<PartitionId, MessageIdData> = BTreeMap::new();
+        let deadline = time::Instant::now() + EMIT_BATCHES_TIMEOUT;
+
+        while batch_num_bytes < TARGET_BATCH_NUM_BYTES {
+            let message = match ctx
+                .protect_future(time::timeout_at(deadline, self.consumer.get_mut().next()))
+                .await
+            {
+                Ok(Some(message_res)) => {
+                    message_res.context("Failed to receive message from Pulsar.")?
+                }
+                Ok(None) => return Err(anyhow!("Pulsar consumer stream ended.").into()),
+                Err(_) => break,
+            };
+            ctx.record_progress();
+
+            let partition_id = PartitionId::from(message.topic.as_str());
+            let message_id = message.message_id().clone();
+            let current_position = position_from_message_id(&message_id);
+            let previous_position = match self.state.current_positions.get(&partition_id) {
+                Some(position) => position.clone(),
+                None => self.fetch_partition_position(&partition_id, ctx).await?,
+            };
+            if current_position <= previous_position {
+                self.state.num_skipped_messages += 1;
+                self.state
+                    .current_positions
+                    .insert(partition_id, previous_position);
+                continue;
+            }
+            let data = message.payload.data;
+            batch_num_bytes += data.len() as u64;
+            self.state.num_bytes_processed += data.len() as u64;
+            self.state.num_messages_processed += 1;
+
+            match std::str::from_utf8(&data) {
+                Ok(doc) if !doc.is_empty() => docs.push(Bytes::from(data)),
+                Ok(_) => {
+                    warn!(
+                        partition_id=%partition_id.as_str(),
+                        position=%current_position.as_str(),
+                        "Message is empty."
+                    );
+                    self.state.num_invalid_messages += 1;
+                }
+                Err(error) => {
+                    warn!(
+                        partition_id=%partition_id.as_str(),
+                        position=%current_position.as_str(),
+                        error=?error,
+                        "Message contains invalid UTF-8 characters."
+                    );
+                    self.state.num_invalid_messages += 1;
+                }
+            }
+            checkpoint_delta
+                .record_partition_delta(
+                    partition_id.clone(),
+                    previous_position,
+                    current_position.clone(),
+                )
+                .context("Failed to record partition delta.")?;
+            self.state
+                .current_positions
+                .insert(partition_id.clone(), current_position);
+            last_message_ids.insert(partition_id, message_id);
+        }
+        if checkpoint_delta.is_empty() {
+            return Ok(Duration::default());
+        }
+        for (partition_id, message_id) in last_message_ids {
+            self.pending_acks
+                .entry(partition_id)
+                .or_default()
+                .insert(position_from_message_id(&message_id), message_id);
+        }
+        let batch = RawDocBatch {
+            docs,
+            checkpoint_delta,
+        };
+        ctx.send_message(doc_processor_mailbox, batch).await?;
+        Ok(Duration::default())
+    }
+
+    async fn suggest_truncate(
+        &mut self,
+        checkpoint: SourceCheckpoint,
+        ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        for (partition_id, partition_pending_acks) in self.pending_acks.iter_mut() {
+            let published_position = match checkpoint.position_for_partition(partition_id) {
+                Some(published_position) => published_position,
+                None => continue,
+            };
+            let published_message_id_opt = partition_pending_acks
+                .range(..=published_position.clone())
+                .next_back()
+                .map(|(_, message_id)| message_id.clone());
+            partition_pending_acks.retain(|position, _| position > published_position);
+
+            if let Some(ack_message_id) = published_message_id_opt
+                .and_then(|message_id| cumulative_ack_message_id(&message_id))
+            {
+                // If the acknowledgement fails, Pulsar redelivers the messages and the source
+                // skips them.
+                ctx.protect_future(
+                    self.consumer
+                        .get_mut()
+                        .cumulative_ack_with_id(partition_id.as_str(), ack_message_id),
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to acknowledge messages of Pulsar topic `{}`.",
+                        partition_id.as_str()
+                    )
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "PulsarSource{{source_id={}}}",
+            self.ctx.source_config.source_id
+        )
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        let current_positions: Vec<(&str, &str)> = self
+            .state
+            .current_positions
+            .iter()
+            .map(|(partition_id, position)| (partition_id.as_str(), position.as_str()))
+            .collect();
+        json!({
+            "index_id": self.ctx.index_id,
+            "source_id": self.ctx.source_config.source_id,
+            "topics": self.topics,
+            "subscription": self.subscription_name,
+            "current_positions": current_positions,
+            "num_bytes_processed": self.state.num_bytes_processed,
+            "num_messages_processed": self.state.num_messages_processed,
+            "num_invalid_messages": self.state.num_invalid_messages,
+            "num_skipped_messages": self.state.num_skipped_messages,
+        })
+    }
+}
+
+/// Converts a message ID into a position. The components of the ID are zero-padded so that the
+/// positions compare like the message IDs within a topic partition.
+fn position_from_message_id(message_id: &MessageIdData) -> Position {
+    let batch_index = message_id.batch_index.unwrap_or(0).max(0);
+    Position::from(format!(
+        "{:0>20}:{:0>20}:{:0>10}",
+        message_id.ledger_id, message_id.entry_id, batch_index
+    ))
+}
+
+/// Returns the ID of the message to acknowledge cumulatively so that the messages up to
+/// `message_id`, and none after it, are acknowledged.
+fn cumulative_ack_message_id(message_id: &MessageIdData) -> Option<MessageIdData> {
+    match message_id.batch_index {
+        // Acknowledging a message of a batch acknowledges the whole entry, including the messages
+        // following it in the batch, so we acknowledge the previous entry instead.
+        Some(batch_index) if batch_index >= 0 => {
+            if message_id.entry_id == 0 {
+                return None;
+            }
+            Some(MessageIdData {
+                ledger_id: message_id.ledger_id,
+                entry_id: message_id.entry_id - 1,
+                partition: message_id.partition,
+                ..Default::default()
+            })
+        }
+        _ => Some(message_id.clone()),
+    }
+}
+
+/// Checks whether we can establish a connection to the Pulsar cluster and look up the topics.
+pub(super) async fn check_connectivity(params: PulsarSourceParams) -> anyhow::Result<()> {
+    let pulsar = connect_pulsar(&params.address).await?;
+
+    for topic in &params.topics {
+        pulsar
+            .lookup_partitioned_topic_number(topic.as_str())
+            .await
+            .with_context(|| format!("Failed to look up Pulsar topic `{topic}`."))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message_id(ledger_id: u64, entry_id: u64, batch_index: Option<i32>) -> MessageIdData {
+        MessageIdData {
+            ledger_id,
+            entry_id,
+            batch_index,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_position_from_message_id() {
+        assert_eq!(
+            position_from_message_id(&message_id(7, 42, None)),
+            Position::from("00000000000000000007:00000000000000000042:0000000000".to_string())
+        );
+        assert_eq!(
+            position_from_message_id(&message_id(7, 42, Some(3))),
+            Position::from("00000000000000000007:00000000000000000042:0000000003".to_string())
+        );
+        let message_ids = [
+            message_id(7, 9, None),
+            message_id(7, 10, Some(0)),
+            message_id(7, 10, Some(11)),
+            message_id(8, 0, None),
+            message_id(10, 2, None),
+        ];
+        for window in message_ids.windows(2) {
+            assert!(position_from_message_id(&window[0]) < position_from_message_id(&window[1]));
+        }
+        assert!(Position::Beginning < position_from_message_id(&message_id(0, 0, None)));
+    }
+
+    #[test]
+    fn test_cumulative_ack_message_id() {
+        assert_eq!(
+            cumulative_ack_message_id(&message_id(7, 42, None)),
+            Some(message_id(7, 42, None))
+        );
+        assert_eq!(
+            cumulative_ack_message_id(&message_id(7, 42, Some(3))),
+            Some(message_id(7, 41, None))
+        );
+        assert_eq!(cumulative_ack_message_id(&message_id(7, 0, Some(3))), None);
+    }
+}
@@ -0,0 +1,394 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bytes::Bytes;
+use once_cell::sync::OnceCell;
+use quickwit_actors::{ActorExitStatus, Mailbox};
+use quickwit_config::PushSourceParams;
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use serde_json::json;
+use tokio::sync::{mpsc, oneshot};
+use tracing::info;
+
+use crate::actors::DocProcessor;
+use crate::models::RawDocBatch;
+use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
+
+const TARGET_BATCH_NUM_BYTES: u64 = 5_000_000;
+
+/// Maximum time spent collecting pushed documents in a single call to `emit_batches`.
+const EMIT_BATCHES_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Identifies an indexing pipeline of a push source running on this node.
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct PushPipelineId {
+    pub index_id: String,
+    pub source_id: String,
+    pub pipeline_ord: usize,
+}
+
+/// Documents pushed into a pipeline. Once the documents have been handed over to the doc
+/// processor, the pipeline acknowledges them by sending its new position over `ack_tx`.
+pub struct PushedDocs {
+    pub docs: Vec<Bytes>,
+    pub ack_tx: oneshot::Sender<u64>,
+}
+
+type PushSourceSenders = HashMap<PushPipelineId, mpsc::Sender<PushedDocs>>;
+
+/// Senders feeding the push sources running on this node.
+static PUSH_SOURCE_SENDERS: OnceCell<Mutex<PushSourceSenders>> = OnceCell::new();
+
+fn push_source_senders() -> MutexGuard<'static, PushSourceSenders> {
+    PUSH_SOURCE_SENDERS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .expect("The lock should not be poisoned.")
+}
+
+/// Returns the sender feeding the pipeline `pipeline_id` if it is running on this node.
+///
+/// Sending waits while the queue of the pipeline is full, which is how producers are slowed down
+/// when the indexer cannot keep up.
+pub fn get_push_source_sender(pipeline_id: &PushPipelineId) -> Option<mpsc::Sender<PushedDocs>> {
+    push_source_senders().get(pipeline_id).cloned()
+}
+
+/// Factory for instantiating a `PushSource`.
+pub struct PushSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for PushSourceFactory {
+    type Source = PushSource;
+    type Params = PushSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: PushSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        PushSource::try_new(ctx, params, checkpoint)
+    }
+}
+
+/// A source receiving documents streamed by external producers over gRPC with the `PushDocs`
+/// method. Each pipeline registers a bounded queue that the gRPC service feeds. Documents are
+/// acknowledged once they have been sent to the doc processor, so a busy indexer stalls the
+/// stream instead of buffering an unbounded amount of documents. Like the Pub/Sub source, each
+/// pipeline records the number of documents it has emitted in its own partition of the
+/// checkpoint.
+pub struct PushSource {
+    ctx: Arc<SourceExecutionContext>,
+    pipeline_id: PushPipelineId,
+    docs_tx: mpsc::Sender<PushedDocs>,
+    docs_rx: mpsc::Receiver<PushedDocs>,
+    partition_id: PartitionId,
+    current_position: u64,
+    num_bytes_processed: u64,
+    num_docs_processed: u64,
+    num_requests_processed: u64,
+}
+
+impl fmt::Debug for PushSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PushSource {{ source_id: {}, pipeline_ord: {} }}",
+            self.pipeline_id.source_id, self.pipeline_id.pipeline_ord
+        )
+    }
+}
+
+impl Drop for PushSource {
+    fn drop(&mut self) {
+        let mut senders = push_source_senders();
+        // A respawned pipeline may already have replaced our sender.
+        if let Some(sender) = senders.get(&self.pipeline_id) {
+            if sender.same_channel(&self.docs_tx) {
+                senders.remove(&self.pipeline_id);
+            }
+        }
+    }
+}
+
+impl PushSource {
+    /// Creates the queue of the pipeline and registers it so that producers can push documents.
+    pub fn try_new(
+        ctx: Arc<SourceExecutionContext>,
+        params: PushSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self> {
+        let pipeline_id = PushPipelineId {
+            index_id: ctx.index_id.clone(),
+            source_id: ctx.source_config.source_id.clone(),
+            pipeline_ord: ctx.pipeline_ord,
+        };
+        // Each pipeline records the documents it emits in its own partition.
+        let partition_id = PartitionId::from(format!("{}:{}", ctx.node_id, ctx.pipeline_ord));
+        let current_position = match checkpoint.position_for_partition(&partition_id) {
+            Some(Position::Offset(offset)) => offset
+                .parse::<u64>()
+                .with_context(|| format!("Failed to parse push position `{offset}`."))?,
+            _ => 0,
+        };
+        let (docs_tx, docs_rx) = mpsc::channel(params.max_queued_batches);
+        push_source_senders().insert(pipeline_id.clone(), docs_tx.clone());
+
+        info!(
+            index_id=%pipeline_id.index_id,
+            source_id=%pipeline_id.source_id,
+            pipeline_ord=%pipeline_id.pipeline_ord,
+            partition_id=%partition_id.as_str(),
+            current_position=%current_position,
+            "Starting push source."
+        );
+        Ok(Self {
+            ctx,
+            pipeline_id,
+            docs_tx,
+            docs_rx,
+            partition_id,
+            current_position,
+            num_bytes_processed: 0,
+            num_docs_processed: 0,
+            num_requests_processed: 0,
+        })
+    }
+}
+
+#[async_trait]
+impl Source for PushSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let mut batch_num_bytes = 0;
+        let mut docs = Vec::new();
+        let mut pending_acks = Vec::new();
+        let deadline = Instant::now() + EMIT_BATCHES_TIMEOUT;
+
+        while batch_num_bytes < TARGET_BATCH_NUM_BYTES {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            // The source holds a sender, so the queue is never closed.
+            let pushed_docs = match ctx
+                .protect_future(tokio::time::timeout(timeout, self.docs_rx.recv()))
+                .await
+            {
+                Ok(Some(pushed_docs)) => pushed_docs,
+                Ok(None) | Err(_) => break,
+            };
+            batch_num_bytes += pushed_docs
+                .docs
+                .iter()
+                .map(|doc| doc.len() as u64)
+                .sum::<u64>();
+            pending_acks.push((pushed_docs.docs.len() as u64, pushed_docs.ack_tx));
+            docs.extend(pushed_docs.docs);
+        }
+        if pending_acks.is_empty() {
+            return Ok(Duration::default());
+        }
+        let previous_position = self.current_position;
+        let current_position = previous_position + docs.len() as u64;
+
+        if !docs.is_empty() {
+            let mut checkpoint_delta = SourceCheckpointDelta::default();
+            checkpoint_delta
+                .record_partition_delta(
+                    self.partition_id.clone(),
+                    if previous_position == 0 {
+                        Position::Beginning
+                    } else {
+                        Position::from(previous_position)
+                    },
+                    Position::from(current_position),
+                )
+                .context("Failed to record partition delta.")?;
+            self.num_bytes_processed += batch_num_bytes;
+            self.num_docs_processed += docs.len() as u64;
+
+            let batch = RawDocBatch {
+                docs,
+                checkpoint_delta,
+            };
+            ctx.send_message(doc_processor_mailbox, batch).await?;
+        }
+        self.current_position = current_position;
+        self.num_requests_processed += pending_acks.len() as u64;
+
+        let mut position = previous_position;
+        for (num_docs, ack_tx) in pending_acks {
+            position += num_docs;
+            // The producer may have closed its stream in the meantime.
+            let _ = ack_tx.send(position);
+        }
+        Ok(Duration::default())
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "PushSource{{source_id={}, pipeline_ord={}}}",
+            self.pipeline_id.source_id, self.pipeline_id.pipeline_ord
+        )
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        json!({
+            "index_id": self.ctx.index_id,
+            "source_id": self.pipeline_id.source_id,
+            "pipeline_ord": self.pipeline_id.pipeline_ord,
+            "partition_id": self.partition_id.as_str(),
+            "current_position": self.current_position,
+            "num_bytes_processed": self.num_bytes_processed,
+            "num_docs_processed": self.num_docs_processed,
+            "num_requests_processed": self.num_requests_processed,
+            "num_queued_requests": self.docs_tx.max_capacity() - self.docs_tx.capacity(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use quickwit_actors::{create_test_mailbox, Universe};
+    use quickwit_common::rand::append_random_suffix;
+    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_metastore::metastore_for_test;
+
+    use super::*;
+    use crate::source::SourceActor;
+
+    fn make_source_ctx(index_id: &str, params: PushSourceParams) -> Arc<SourceExecutionContext> {
+        SourceExecutionContext::for_test(
+            metastore_for_test(),
+            index_id,
+            PathBuf::from("./queues"),
+            SourceConfig {
+                source_id: "test-push-source".to_string(),
+                num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
+                enabled: true,
+                source_params: SourceParams::Push(params),
+            },
+        )
+    }
+
+    fn make_pipeline_id(index_id: &str) -> PushPipelineId {
+        PushPipelineId {
+            index_id: index_id.to_string(),
+            source_id: "test-push-source".to_string(),
+            pipeline_ord: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_push_source() -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let index_id = append_random_suffix("test-push-source");
+        let params = PushSourceParams::default();
+        let ctx = make_source_ctx(&index_id, params.clone());
+        let push_source =
+            PushSourceFactory::typed_create_source(ctx, params, SourceCheckpoint::default())
+                .await?;
+        let (doc_processor_mailbox, doc_processor_inbox) = create_test_mailbox();
+        let push_source_actor = SourceActor {
+            source: Box::new(push_source),
+            doc_processor_mailbox,
+            rate_limiter_opt: None,
+        };
+        let (_push_source_mailbox, push_source_handle) =
+            universe.spawn_builder().spawn(push_source_actor);
+
+        let sender = get_push_source_sender(&make_pipeline_id(&index_id)).unwrap();
+        let (first_ack_tx, first_ack_rx) = oneshot::channel();
+        sender
+            .send(PushedDocs {
+                docs: vec![
+                    Bytes::from_static(br#"{"body": "foo"}"#),
+                    Bytes::from_static(br#"{"body": "bar"}"#),
+                ],
+                ack_tx: first_ack_tx,
+            })
+            .await?;
+        let (second_ack_tx, second_ack_rx) = oneshot::channel();
+        sender
+            .send(PushedDocs {
+                docs: vec![Bytes::from_static(br#"{"body": "baz"}"#)],
+                ack_tx: second_ack_tx,
+            })
+            .await?;
+        assert_eq!(first_ack_rx.await?, 2);
+        assert_eq!(second_ack_rx.await?, 3);
+
+        let batches = doc_processor_inbox.drain_for_test_typed::<RawDocBatch>();
+        let docs: Vec<Bytes> = batches
+            .into_iter()
+            .flat_map(|batch| batch.docs.into_iter())
+            .collect();
+        assert_eq!(docs.len(), 3);
+        assert_eq!(docs[2], Bytes::from_static(br#"{"body": "baz"}"#));
+
+        let state = push_source_handle.process_pending_and_observe().await;
+        assert_eq!(state["current_position"], 3);
+        assert_eq!(state["num_docs_processed"], 3);
+        assert_eq!(state["num_requests_processed"], 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_source_resumes_from_checkpoint_and_unregisters() -> anyhow::Result<()> {
+        let index_id = append_random_suffix("test-push-source");
+        let params = PushSourceParams::default();
+        let ctx = make_source_ctx(&index_id, params.clone());
+        let mut checkpoint = SourceCheckpoint::default();
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta
+            .record_partition_delta(
+                PartitionId::from("test-node:0"),
+                Position::Beginning,
+                Position::from(42u64),
+            )
+            .unwrap();
+        checkpoint.try_apply_delta(checkpoint_delta).unwrap();
+
+        let pipeline_id = make_pipeline_id(&index_id);
+        let push_source = PushSource::try_new(ctx, params, checkpoint)?;
+        assert_eq!(push_source.current_position, 42);
+        assert!(get_push_source_sender(&pipeline_id).is_some());
+
+        drop(push_source);
+        assert!(get_push_source_sender(&pipeline_id).is_none());
+        Ok(())
+    }
+}
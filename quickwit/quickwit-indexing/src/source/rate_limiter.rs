@@ -0,0 +1,181 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use quickwit_config::SourceRateLimit;
+
+/// Token bucket refilled at a constant rate, up to one second worth of tokens.
+///
+/// Consumption is recorded after the fact, so the bucket can go into debt: the wait duration is
+/// then the time needed to pay it back.
+#[derive(Debug)]
+struct TokenBucket {
+    rate_per_sec: f64,
+    num_tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate_per_sec: u64, now: Instant) -> Self {
+        let rate_per_sec = rate_per_sec as f64;
+        Self {
+            rate_per_sec,
+            num_tokens: rate_per_sec,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.num_tokens =
+            (self.num_tokens + elapsed.as_secs_f64() * self.rate_per_sec).min(self.rate_per_sec);
+        self.refilled_at = now;
+    }
+
+    fn consume(&mut self, num_tokens: u64, now: Instant) {
+        self.refill(now);
+        self.num_tokens -= num_tokens as f64;
+    }
+
+    fn wait_duration(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.num_tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(-self.num_tokens / self.rate_per_sec)
+    }
+}
+
+#[derive(Debug)]
+struct RateLimiterState {
+    num_bytes_bucket_opt: Option<TokenBucket>,
+    num_docs_bucket_opt: Option<TokenBucket>,
+}
+
+/// Rate limiter shared by the source actor, which waits before emitting new batches, and the
+/// doc processor, which records the batches it receives.
+#[derive(Clone, Debug)]
+pub struct SourceRateLimiter {
+    inner: Arc<Mutex<RateLimiterState>>,
+}
+
+impl SourceRateLimiter {
+    pub fn new(rate_limit: &SourceRateLimit) -> Self {
+        Self::new_at(rate_limit, Instant::now())
+    }
+
+    fn new_at(rate_limit: &SourceRateLimit, now: Instant) -> Self {
+        let state = RateLimiterState {
+            num_bytes_bucket_opt: rate_limit
+                .num_bytes
+                .map(|num_bytes| TokenBucket::new(num_bytes.get_bytes() as u64, now)),
+            num_docs_bucket_opt: rate_limit
+                .num_docs
+                .map(|num_docs| TokenBucket::new(num_docs, now)),
+        };
+        Self {
+            inner: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Records a batch of `num_docs` documents totalling `num_bytes` bytes.
+    pub fn record(&self, num_docs: u64, num_bytes: u64) {
+        self.record_at(num_docs, num_bytes, Instant::now())
+    }
+
+    fn record_at(&self, num_docs: u64, num_bytes: u64, now: Instant) {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(bucket) = state.num_bytes_bucket_opt.as_mut() {
+            bucket.consume(num_bytes, now);
+        }
+        if let Some(bucket) = state.num_docs_bucket_opt.as_mut() {
+            bucket.consume(num_docs, now);
+        }
+    }
+
+    /// Returns how long the source must wait before emitting new batches.
+    pub fn wait_duration(&self) -> Duration {
+        self.wait_duration_at(Instant::now())
+    }
+
+    fn wait_duration_at(&self, now: Instant) -> Duration {
+        let mut state = self.inner.lock().unwrap();
+        let num_bytes_wait = state
+            .num_bytes_bucket_opt
+            .as_mut()
+            .map(|bucket| bucket.wait_duration(now))
+            .unwrap_or_default();
+        let num_docs_wait = state
+            .num_docs_bucket_opt
+            .as_mut()
+            .map(|bucket| bucket.wait_duration(now))
+            .unwrap_or_default();
+        num_bytes_wait.max(num_docs_wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byte_unit::Byte;
+
+    use super::*;
+
+    #[track_caller]
+    fn assert_wait_millis(rate_limiter: &SourceRateLimiter, now: Instant, expected_millis: u64) {
+        let wait_duration = rate_limiter.wait_duration_at(now);
+        let expected_duration = Duration::from_millis(expected_millis);
+        assert!(
+            (wait_duration.as_secs_f64() - expected_duration.as_secs_f64()).abs() < 1e-6,
+            "expected {expected_duration:?}, got {wait_duration:?}"
+        );
+    }
+
+    #[test]
+    fn test_source_rate_limiter() {
+        let now = Instant::now();
+        let rate_limit = SourceRateLimit {
+            num_bytes: Some(Byte::from_bytes(1_000)),
+            num_docs: Some(10),
+        };
+        let rate_limiter = SourceRateLimiter::new_at(&rate_limit, now);
+        assert_wait_millis(&rate_limiter, now, 0);
+
+        // The first second worth of tokens is available right away.
+        rate_limiter.record_at(10, 1_000, now);
+        assert_wait_millis(&rate_limiter, now, 0);
+
+        // Going into debt on bytes.
+        rate_limiter.record_at(1, 500, now);
+        assert_wait_millis(&rate_limiter, now, 500);
+        let later = now + Duration::from_millis(200);
+        assert_wait_millis(&rate_limiter, later, 300);
+
+        // Going further into debt on docs.
+        rate_limiter.record_at(9, 0, later);
+        assert_wait_millis(&rate_limiter, later, 800);
+
+        // The buckets do not accumulate more than one second worth of tokens.
+        let much_later = later + Duration::from_secs(60);
+        assert_wait_millis(&rate_limiter, much_later, 0);
+        rate_limiter.record_at(11, 0, much_later);
+        assert_wait_millis(&rate_limiter, much_later, 100);
+    }
+}
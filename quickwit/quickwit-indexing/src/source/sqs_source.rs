@@ -0,0 +1,748 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use async_compression::tokio::bufread::GzipDecoder;
+use async_trait::async_trait;
+use bytes::Bytes;
+use percent_encoding::percent_decode_str;
+use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
+use quickwit_aws::error::RusotoErrorWrapper;
+use quickwit_aws::region::sniff_aws_region_and_cache;
+use quickwit_aws::retry::{retry, RetryParams};
+use quickwit_aws::{get_credentials_provider, get_http_client};
+use quickwit_config::{RegionOrEndpoint, SqsSourceParams};
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use rusoto_core::{Region, RusotoError};
+use rusoto_s3::{GetObjectError, GetObjectRequest, S3Client, S3};
+use rusoto_sqs::{
+    DeleteMessageBatchRequest, DeleteMessageBatchRequestEntry, GetQueueAttributesRequest,
+    ReceiveMessageRequest, Sqs, SqsClient,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader};
+use tracing::{info, warn};
+
+use crate::actors::DocProcessor;
+use crate::models::RawDocBatch;
+use crate::source::pending_acks::PendingAcks;
+use crate::source::{
+    Source, SourceActor, SourceContext, SourceExecutionContext, TypedSourceFactory,
+};
+
+const TARGET_BATCH_NUM_BYTES: u64 = 5_000_000;
+
+/// Maximum time spent reading objects in a single call to `emit_batches`.
+const EMIT_BATCHES_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Maximum number of messages returned by a `ReceiveMessage` request, as imposed by SQS.
+const MAX_NUM_MESSAGES_PER_RECEIVE: i64 = 10;
+
+/// Maximum number of entries of a `DeleteMessageBatch` request, as imposed by SQS.
+const MAX_NUM_ENTRIES_PER_DELETE: usize = 10;
+
+/// Time a `ReceiveMessage` request waits for messages to arrive in an empty queue (long polling).
+const RECEIVE_WAIT_TIME_SECS: i64 = 1;
+
+/// Factory for instantiating an `SqsSource`.
+pub struct SqsSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for SqsSourceFactory {
+    type Source = SqsSource;
+    type Params = SqsSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: SqsSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        SqsSource::try_new(ctx, params, checkpoint).await
+    }
+}
+
+#[derive(Default)]
+pub struct SqsSourceState {
+    /// Position of the last document emitted, i.e. the number of documents emitted by the
+    /// pipelines sharing the partition of the source since its creation.
+    current_position: u64,
+    /// Position of the last document published.
+    published_position: u64,
+    /// Number of bytes processed by the source.
+    pub num_bytes_processed: u64,
+    /// Number of lines processed by the source (including invalid and empty lines).
+    pub num_lines_processed: u64,
+    /// Number of lines that could not be decoded as UTF-8.
+    pub num_invalid_lines: u64,
+    /// Number of objects read entirely by the source.
+    pub num_objects_processed: u64,
+    /// Number of objects deleted before the source could read them.
+    pub num_missing_objects: u64,
+    /// Number of messages that are not S3 event notifications.
+    pub num_invalid_messages: u64,
+    /// Number of messages deleted from the queue.
+    pub num_deleted_messages: u64,
+}
+
+/// Location of an S3 object.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct S3ObjectRef {
+    bucket: String,
+    key: String,
+}
+
+impl fmt::Display for S3ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.key)
+    }
+}
+
+/// S3 object waiting to be read. The receipt handle of a notification is attached to the last
+/// object it references, so that the notification is deleted once all its objects are read.
+struct PendingObject {
+    object: S3ObjectRef,
+    receipt_handle_opt: Option<String>,
+}
+
+/// S3 object being read line by line.
+struct ObjectReader {
+    object: S3ObjectRef,
+    receipt_handle_opt: Option<String>,
+    reader: Pin<Box<dyn AsyncBufRead + Send + Sync>>,
+}
+
+/// A source consuming an SQS queue that receives S3 event notifications and indexing the
+/// newline-delimited JSON objects they reference. Objects whose key ends with `.gz` or whose
+/// content encoding is `gzip` are decompressed on the fly.
+///
+/// Like Pub/Sub messages, SQS messages have no position, so each indexing pipeline records the
+/// number of documents it has emitted in its own partition of the checkpoint. A notification is
+/// deleted from the queue only once the splits holding the documents of all its objects are
+/// published. Notifications that become visible again before being deleted, or that are in flight
+/// when the pipeline restarts, are received again and their objects indexed twice. Setting the
+/// visibility timeout of the queue above the commit timeout of the index limits redeliveries.
+pub struct SqsSource {
+    ctx: Arc<SourceExecutionContext>,
+    queue_url: String,
+    sqs_client: SqsClient,
+    s3_client: S3Client,
+    retry_params: RetryParams,
+    partition_id: PartitionId,
+    pending_objects: VecDeque<PendingObject>,
+    current_object_opt: Option<ObjectReader>,
+    pending_acks: PendingAcks,
+    state: SqsSourceState,
+}
+
+impl fmt::Debug for SqsSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SqsSource {{ source_id: {}, queue_url: {} }}",
+            self.ctx.source_config.source_id, self.queue_url
+        )
+    }
+}
+
+fn get_region(region_or_endpoint: Option<RegionOrEndpoint>) -> anyhow::Result<Region> {
+    match region_or_endpoint {
+        Some(RegionOrEndpoint::Endpoint(endpoint)) => Ok(Region::Custom {
+            name: "Custom".to_string(),
+            endpoint,
+        }),
+        Some(RegionOrEndpoint::Region(region)) => region
+            .parse()
+            .with_context(|| format!("Failed to parse region: `{}`", region)),
+        None => sniff_aws_region_and_cache(),
+    }
+}
+
+fn get_sqs_client(region: Region) -> anyhow::Result<SqsClient> {
+    let http_client = get_http_client();
+    let credentials_provider = get_credentials_provider()?;
+    Ok(SqsClient::new_with(
+        http_client,
+        credentials_provider,
+        region,
+    ))
+}
+
+fn get_s3_client(region: Region) -> anyhow::Result<S3Client> {
+    let http_client = get_http_client();
+    let credentials_provider = get_credentials_provider()?;
+    Ok(S3Client::new_with(
+        http_client,
+        credentials_provider,
+        region,
+    ))
+}
+
+impl SqsSource {
+    /// Instantiates a new `SqsSource`.
+    pub async fn try_new(
+        ctx: Arc<SourceExecutionContext>,
+        params: SqsSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self> {
+        let region = get_region(params.region_or_endpoint)?;
+        let sqs_client = get_sqs_client(region.clone())?;
+        let s3_client = get_s3_client(region)?;
+        // Each pipeline records the documents it emits in its own partition.
+        let partition_id = PartitionId::from(format!("{}:{}", ctx.node_id, ctx.pipeline_ord));
+        let current_position = match checkpoint.position_for_partition(&partition_id) {
+            Some(Position::Offset(offset)) => offset
+                .parse::<u64>()
+                .with_context(|| format!("Failed to parse SQS position `{offset}`."))?,
+            _ => 0,
+        };
+        info!(
+            index_id=%ctx.index_id,
+            source_id=%ctx.source_config.source_id,
+            queue_url=%params.queue_url,
+            partition_id=%partition_id.as_str(),
+            current_position=%current_position,
+            "Starting SQS source."
+        );
+        let state = SqsSourceState {
+            current_position,
+            published_position: current_position,
+            ..Default::default()
+        };
+        Ok(Self {
+            ctx,
+            queue_url: params.queue_url,
+            sqs_client,
+            s3_client,
+            retry_params: RetryParams::default(),
+            partition_id,
+            pending_objects: VecDeque::new(),
+            current_object_opt: None,
+            pending_acks: PendingAcks::default(),
+            state,
+        })
+    }
+
+    /// Receives a batch of notifications and queues the objects they reference.
+    async fn receive_notifications(&mut self, ctx: &SourceContext) -> anyhow::Result<()> {
+        let request = ReceiveMessageRequest {
+            queue_url: self.queue_url.clone(),
+            max_number_of_messages: Some(MAX_NUM_MESSAGES_PER_RECEIVE),
+            wait_time_seconds: Some(RECEIVE_WAIT_TIME_SECS),
+            ..Default::default()
+        };
+        let response = ctx
+            .protect_future(retry(&self.retry_params, || async {
+                self.sqs_client
+                    .receive_message(request.clone())
+                    .await
+                    .map_err(RusotoErrorWrapper::from)
+            }))
+            .await
+            .context("Failed to receive messages from SQS queue.")?;
+
+        let mut empty_receipt_handles = Vec::new();
+
+        for message in response.messages.unwrap_or_default() {
+            let receipt_handle = match message.receipt_handle {
+                Some(receipt_handle) => receipt_handle,
+                None => continue,
+            };
+            let objects = match parse_notification(message.body.as_deref().unwrap_or_default()) {
+                Ok(objects) => objects,
+                Err(error) => {
+                    // The message is left in the queue, so that it ends up in the dead-letter
+                    // queue, if any, once received too many times.
+                    warn!(
+                        queue_url=%self.queue_url,
+                        message_id=?message.message_id,
+                        error=?error,
+                        "Message is not an S3 event notification."
+                    );
+                    self.state.num_invalid_messages += 1;
+                    continue;
+                }
+            };
+            // Test events and notifications of other event types reference no objects.
+            if objects.is_empty() {
+                empty_receipt_handles.push(receipt_handle);
+                continue;
+            }
+            let num_objects = objects.len();
+
+            for (object_idx, object) in objects.into_iter().enumerate() {
+                let receipt_handle_opt = if object_idx + 1 == num_objects {
+                    Some(receipt_handle.clone())
+                } else {
+                    None
+                };
+                self.pending_objects.push_back(PendingObject {
+                    object,
+                    receipt_handle_opt,
+                });
+            }
+        }
+        self.delete_messages(empty_receipt_handles, ctx).await
+    }
+
+    /// Opens the object for reading, or returns `None` if it no longer exists.
+    async fn open_object(
+        &self,
+        object: &S3ObjectRef,
+        ctx: &SourceContext,
+    ) -> anyhow::Result<Option<Pin<Box<dyn AsyncBufRead + Send + Sync>>>> {
+        let request = GetObjectRequest {
+            bucket: object.bucket.clone(),
+            key: object.key.clone(),
+            ..Default::default()
+        };
+        let get_object_result = ctx
+            .protect_future(retry(&self.retry_params, || async {
+                self.s3_client
+                    .get_object(request.clone())
+                    .await
+                    .map_err(RusotoErrorWrapper::from)
+            }))
+            .await;
+        let output = match get_object_result {
+            Ok(output) => output,
+            Err(RusotoErrorWrapper(RusotoError::Service(GetObjectError::NoSuchKey(_)))) => {
+                return Ok(None);
+            }
+            Err(error) => {
+                return Err(
+                    anyhow::Error::new(error).context(format!("Failed to get object `{object}`."))
+                );
+            }
+        };
+        let body: Pin<Box<dyn AsyncRead + Send + Sync>> = match output.body {
+            Some(body) => Box::pin(body.into_async_read()),
+            None => Box::pin(tokio::io::empty()),
+        };
+        let reader = BufReader::new(body);
+        let is_gzipped =
+            output.content_encoding.as_deref() == Some("gzip") || object.key.ends_with(".gz");
+        if is_gzipped {
+            let mut decoder = GzipDecoder::new(reader);
+            decoder.multiple_members(true);
+            return Ok(Some(Box::pin(BufReader::new(decoder))));
+        }
+        Ok(Some(Box::pin(reader)))
+    }
+
+    /// Deletes the messages from the queue. Messages that fail to be deleted are received again.
+    async fn delete_messages(
+        &mut self,
+        receipt_handles: Vec<String>,
+        ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        for chunk in receipt_handles.chunks(MAX_NUM_ENTRIES_PER_DELETE) {
+            let entries: Vec<DeleteMessageBatchRequestEntry> = chunk
+                .iter()
+                .enumerate()
+                .map(
+                    |(entry_idx, receipt_handle)| DeleteMessageBatchRequestEntry {
+                        id: entry_idx.to_string(),
+                        receipt_handle: receipt_handle.clone(),
+                    },
+                )
+                .collect();
+            let request = DeleteMessageBatchRequest {
+                queue_url: self.queue_url.clone(),
+                entries,
+            };
+            let response = ctx
+                .protect_future(retry(&self.retry_params, || async {
+                    self.sqs_client
+                        .delete_message_batch(request.clone())
+                        .await
+                        .map_err(RusotoErrorWrapper::from)
+                }))
+                .await
+                .context("Failed to delete messages from SQS queue.")?;
+
+            for failed_entry in &response.failed {
+                warn!(
+                    queue_url=%self.queue_url,
+                    code=%failed_entry.code,
+                    message=?failed_entry.message,
+                    "Failed to delete message from SQS queue."
+                );
+            }
+            self.state.num_deleted_messages += response.successful.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for SqsSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let mut batch_num_bytes = 0;
+        let mut docs = Vec::new();
+        let mut receipt_handles = Vec::new();
+        let deadline = Instant::now() + EMIT_BATCHES_TIMEOUT;
+
+        while batch_num_bytes < TARGET_BATCH_NUM_BYTES && Instant::now() < deadline {
+            let object_reader = match &mut self.current_object_opt {
+                Some(object_reader) => object_reader,
+                None => {
+                    if self.pending_objects.is_empty() {
+                        self.receive_notifications(ctx).await?;
+                    }
+                    let pending_object = match self.pending_objects.pop_front() {
+                        Some(pending_object) => pending_object,
+                        None => break,
+                    };
+                    match self.open_object(&pending_object.object, ctx).await? {
+                        Some(reader) => {
+                            self.current_object_opt = Some(ObjectReader {
+                                object: pending_object.object,
+                                receipt_handle_opt: pending_object.receipt_handle_opt,
+                                reader,
+                            });
+                        }
+                        None => {
+                            warn!(
+                                queue_url=%self.queue_url,
+                                object=%pending_object.object,
+                                "Object does not exist."
+                            );
+                            self.state.num_missing_objects += 1;
+                            receipt_handles.extend(pending_object.receipt_handle_opt);
+                        }
+                    }
+                    ctx.record_progress();
+                    continue;
+                }
+            };
+            let mut line = Vec::new();
+            let num_bytes = ctx
+                .protect_future(object_reader.reader.read_until(b'\n', &mut line))
+                .await
+                .with_context(|| format!("Failed to read object `{}`.", object_reader.object))?;
+
+            if num_bytes == 0 {
+                self.state.num_objects_processed += 1;
+                if let Some(object_reader) = self.current_object_opt.take() {
+                    receipt_handles.extend(object_reader.receipt_handle_opt);
+                }
+                continue;
+            }
+            batch_num_bytes += num_bytes as u64;
+            self.state.num_bytes_processed += num_bytes as u64;
+            self.state.num_lines_processed += 1;
+
+            match String::from_utf8(line) {
+                Ok(doc) if !doc.trim().is_empty() => docs.push(Bytes::from(doc)),
+                Ok(_) => {}
+                Err(error) => {
+                    warn!(
+                        object=%object_reader.object,
+                        error=?error,
+                        "Line contains invalid UTF-8 characters."
+                    );
+                    self.state.num_invalid_lines += 1;
+                }
+            }
+        }
+        if docs.is_empty() {
+            if receipt_handles.is_empty() {
+                return Ok(Duration::default());
+            }
+            // Documents emitted earlier from the objects of these notifications may still be
+            // waiting for publication.
+            if self.state.current_position > self.state.published_position {
+                self.pending_acks
+                    .push(self.state.current_position, receipt_handles);
+            } else {
+                self.delete_messages(receipt_handles, ctx).await?;
+            }
+            return Ok(Duration::default());
+        }
+        let previous_position = if self.state.current_position == 0 {
+            Position::Beginning
+        } else {
+            Position::from(self.state.current_position)
+        };
+        let current_position = self.state.current_position + docs.len() as u64;
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta
+            .record_partition_delta(
+                self.partition_id.clone(),
+                previous_position,
+                Position::from(current_position),
+            )
+            .context("Failed to record partition delta.")?;
+        self.state.current_position = current_position;
+        self.pending_acks.push(current_position, receipt_handles);
+
+        let batch = RawDocBatch {
+            docs,
+            checkpoint_delta,
+        };
+        ctx.send_message(doc_processor_mailbox, batch).await?;
+        Ok(Duration::default())
+    }
+
+    async fn suggest_truncate(
+        &mut self,
+        checkpoint: SourceCheckpoint,
+        ctx: &ActorContext<SourceActor>,
+    ) -> anyhow::Result<()> {
+        let published_position = match checkpoint.position_for_partition(&self.partition_id) {
+            Some(Position::Offset(offset)) => offset.parse::<u64>()?,
+            _ => return Ok(()),
+        };
+        self.state.published_position = published_position;
+        let receipt_handles = self.pending_acks.drain_up_to(published_position);
+        // If the deletion fails, the messages are received again and their objects indexed again.
+        self.delete_messages(receipt_handles, ctx).await
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "SqsSource{{source_id={}}}",
+            self.ctx.source_config.source_id
+        )
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        json!({
+            "index_id": self.ctx.index_id,
+            "source_id": self.ctx.source_config.source_id,
+            "queue_url": self.queue_url,
+            "partition_id": self.partition_id.as_str(),
+            "current_position": self.state.current_position,
+            "num_pending_objects": self.pending_objects.len(),
+            "num_pending_acks": self.pending_acks.num_pending_acks(),
+            "num_bytes_processed": self.state.num_bytes_processed,
+            "num_lines_processed": self.state.num_lines_processed,
+            "num_invalid_lines": self.state.num_invalid_lines,
+            "num_objects_processed": self.state.num_objects_processed,
+            "num_missing_objects": self.state.num_missing_objects,
+            "num_invalid_messages": self.state.num_invalid_messages,
+            "num_deleted_messages": self.state.num_deleted_messages,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct SnsNotification {
+    #[serde(rename = "Type")]
+    notification_type: String,
+    #[serde(rename = "Message")]
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct S3EventNotification {
+    #[serde(rename = "Records", default)]
+    records: Vec<S3EventRecord>,
+}
+
+#[derive(Deserialize)]
+struct S3EventRecord {
+    #[serde(rename = "eventName")]
+    event_name: String,
+    s3: S3Entity,
+}
+
+#[derive(Deserialize)]
+struct S3Entity {
+    bucket: S3BucketEntity,
+    object: S3ObjectEntity,
+}
+
+#[derive(Deserialize)]
+struct S3BucketEntity {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct S3ObjectEntity {
+    key: String,
+}
+
+/// Extracts the objects created from the body of an S3 event notification, delivered to the queue
+/// either directly or through an SNS topic.
+fn parse_notification(message_body: &str) -> anyhow::Result<Vec<S3ObjectRef>> {
+    let notification: S3EventNotification =
+        match serde_json::from_str::<SnsNotification>(message_body) {
+            Ok(sns_notification) if sns_notification.notification_type == "Notification" => {
+                serde_json::from_str(&sns_notification.message)
+            }
+            _ => serde_json::from_str(message_body),
+        }
+        .context("Failed to parse S3 event notification.")?;
+
+    notification
+        .records
+        .into_iter()
+        .filter(|record| record.event_name.starts_with("ObjectCreated:"))
+        .map(|record| {
+            Ok(S3ObjectRef {
+                bucket: record.s3.bucket.name,
+                key: decode_object_key(&record.s3.object.key)?,
+            })
+        })
+        .collect()
+}
+
+/// Decodes an object key of an S3 event notification, which is URL-encoded with spaces replaced by
+/// `+`.
+fn decode_object_key(encoded_key: &str) -> anyhow::Result<String> {
+    let key = percent_decode_str(&encoded_key.replace('+', " "))
+        .decode_utf8()
+        .with_context(|| format!("Object key `{encoded_key}` is not valid UTF-8."))?;
+    Ok(key.into_owned())
+}
+
+/// Checks whether we can establish a connection to the SQS service and find the queue.
+pub(super) async fn check_connectivity(params: SqsSourceParams) -> anyhow::Result<()> {
+    let region = get_region(params.region_or_endpoint)?;
+    let sqs_client = get_sqs_client(region)?;
+    let request = GetQueueAttributesRequest {
+        queue_url: params.queue_url.clone(),
+        attribute_names: None,
+    };
+    sqs_client
+        .get_queue_attributes(request)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to get attributes of SQS queue `{}`.",
+                params.queue_url
+            )
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s3_event_notification(event_name: &str, bucket: &str, key: &str) -> serde_json::Value {
+        json!({
+            "Records": [
+                {
+                    "eventVersion": "2.1",
+                    "eventSource": "aws:s3",
+                    "awsRegion": "us-east-1",
+                    "eventName": event_name,
+                    "s3": {
+                        "s3SchemaVersion": "1.0",
+                        "bucket": {
+                            "name": bucket,
+                            "arn": format!("arn:aws:s3:::{bucket}")
+                        },
+                        "object": {
+                            "key": key,
+                            "size": 1024
+                        }
+                    }
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_decode_object_key() {
+        assert_eq!(
+            decode_object_key("logs/app.ndjson").unwrap(),
+            "logs/app.ndjson"
+        );
+        assert_eq!(
+            decode_object_key("logs/my+app/2022%3D10/a%2Bb.ndjson.gz").unwrap(),
+            "logs/my app/2022=10/a+b.ndjson.gz"
+        );
+        decode_object_key("logs/%FF.ndjson").unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_notification() {
+        {
+            let message_body =
+                s3_event_notification("ObjectCreated:Put", "my-bucket", "logs/my+app.ndjson")
+                    .to_string();
+            let objects = parse_notification(&message_body).unwrap();
+            assert_eq!(
+                objects,
+                vec![S3ObjectRef {
+                    bucket: "my-bucket".to_string(),
+                    key: "logs/my app.ndjson".to_string(),
+                }]
+            );
+        }
+        {
+            let message_body =
+                s3_event_notification("ObjectRemoved:Delete", "my-bucket", "logs/app.ndjson")
+                    .to_string();
+            assert!(parse_notification(&message_body).unwrap().is_empty());
+        }
+        {
+            let s3_notification = s3_event_notification(
+                "ObjectCreated:CompleteMultipartUpload",
+                "my-bucket",
+                "logs/app.ndjson.gz",
+            );
+            let message_body = json!({
+                "Type": "Notification",
+                "MessageId": "22b80b92-fdea-4c2c-8f9d-bdfb0c7bf324",
+                "TopicArn": "arn:aws:sns:us-east-1:123456789012:my-topic",
+                "Message": s3_notification.to_string(),
+            })
+            .to_string();
+            let objects = parse_notification(&message_body).unwrap();
+            assert_eq!(
+                objects,
+                vec![S3ObjectRef {
+                    bucket: "my-bucket".to_string(),
+                    key: "logs/app.ndjson.gz".to_string(),
+                }]
+            );
+        }
+        {
+            let message_body = json!({
+                "Service": "Amazon S3",
+                "Event": "s3:TestEvent",
+                "Time": "2022-10-01T12:00:00.000Z",
+                "Bucket": "my-bucket",
+            })
+            .to_string();
+            assert!(parse_notification(&message_body).unwrap().is_empty());
+        }
+        {
+            parse_notification("not a notification").unwrap_err();
+            parse_notification(r#"{"Records": [{"eventName": "ObjectCreated:Put"}]}"#).unwrap_err();
+        }
+    }
+}
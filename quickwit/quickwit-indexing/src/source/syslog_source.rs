@@ -0,0 +1,855 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt, io};
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use quickwit_actors::{ActorExitStatus, Mailbox};
+use quickwit_config::{SyslogProtocol, SyslogSourceParams};
+use quickwit_ingest_api::{get_ingest_api_service, DocBatchBuilder, IngestApiService};
+use quickwit_metastore::checkpoint::{
+    PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+};
+use quickwit_proto::ingest_api::{CreateQueueIfNotExistsRequest, IngestRequest};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::actors::DocProcessor;
+use crate::models::RawDocBatch;
+use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
+
+const TARGET_BATCH_NUM_BYTES: u64 = 5_000_000;
+
+/// Maximum time spent collecting messages in a single call to `emit_batches`.
+const EMIT_BATCHES_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Maximum size of a UDP datagram.
+const MAX_DATAGRAM_NUM_BYTES: usize = 65_535;
+
+/// Maximum size of a TCP frame, whatever its framing.
+const MAX_TCP_FRAME_NUM_BYTES: usize = 1_000_000;
+/// Maximum number of bytes of the length prefix of an octet-counted frame, trailing space
+/// included.
+const MAX_FRAME_LEN_NUM_BYTES: u64 = 16;
+
+/// Value of the fields of a RFC 5424 message that are not provided.
+const NIL_VALUE: &str = "-";
+
+const RFC3164_MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Factory for instantiating a `SyslogSource`.
+pub struct SyslogSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for SyslogSourceFactory {
+    type Source = SyslogSource;
+    type Params = SyslogSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: SyslogSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self::Source> {
+        SyslogSource::try_new(ctx, params, checkpoint).await
+    }
+}
+
+#[derive(Default)]
+struct SyslogListenerCounters {
+    /// Number of frames received by the listener.
+    num_messages_received: AtomicU64,
+    /// Number of frames that are not syslog messages.
+    num_invalid_messages: AtomicU64,
+    /// Number of messages spilled to the ingest API queue of the index.
+    num_spilled_messages: AtomicU64,
+    /// Number of messages lost because they could not be spilled.
+    num_dropped_messages: AtomicU64,
+}
+
+/// Receives the frames read from the socket, parses them, and queues the resulting documents for
+/// the source. Documents that do not fit in the in-memory queue are spilled to the ingest API
+/// queue of the index.
+#[derive(Clone)]
+struct SyslogListener {
+    index_id: String,
+    queue_tx: mpsc::Sender<Bytes>,
+    ingest_api_service: Mailbox<IngestApiService>,
+    counters: Arc<SyslogListenerCounters>,
+}
+
+impl SyslogListener {
+    /// Returns `false` once the source has been dropped.
+    async fn handle_frame(&self, frame: &[u8]) -> bool {
+        self.counters
+            .num_messages_received
+            .fetch_add(1, Ordering::Relaxed);
+        let doc = if let Some(doc_json) = parse_syslog_message(frame) {
+            Bytes::from(doc_json.to_string())
+        } else {
+            self.counters
+                .num_invalid_messages
+                .fetch_add(1, Ordering::Relaxed);
+            return true;
+        };
+        match self.queue_tx.try_send(doc) {
+            Ok(()) => true,
+            Err(TrySendError::Full(doc)) => {
+                self.spill(doc).await;
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    async fn spill(&self, doc: Bytes) {
+        let mut doc_batch_builder = DocBatchBuilder::new(self.index_id.clone());
+        doc_batch_builder.add_doc(&doc);
+        let ingest_req = IngestRequest {
+            doc_batches: vec![doc_batch_builder.build()],
+        };
+        match self.ingest_api_service.ask_for_res(ingest_req).await {
+            Ok(_) => {
+                self.counters
+                    .num_spilled_messages
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            Err(error) => {
+                warn!(
+                    index_id=%self.index_id,
+                    error=?error,
+                    "Failed to spill syslog message to the ingest API queue."
+                );
+                self.counters
+                    .num_dropped_messages
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+async fn run_udp_listener(socket: UdpSocket, listener: SyslogListener) {
+    let mut buffer = vec![0u8; MAX_DATAGRAM_NUM_BYTES];
+    loop {
+        let num_bytes = match socket.recv(&mut buffer).await {
+            Ok(num_bytes) => num_bytes,
+            Err(error) => {
+                warn!(error=?error, "Failed to receive syslog datagram.");
+                continue;
+            }
+        };
+        if !listener.handle_frame(&buffer[..num_bytes]).await {
+            return;
+        }
+    }
+}
+
+async fn run_tcp_listener(tcp_listener: TcpListener, listener: SyslogListener) {
+    loop {
+        let (stream, peer_addr) = match tcp_listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                warn!(error=?error, "Failed to accept syslog connection.");
+                continue;
+            }
+        };
+        let listener = listener.clone();
+        tokio::spawn(async move {
+            if let Err(error) = handle_tcp_connection(stream, &listener).await {
+                warn!(peer_addr=%peer_addr, error=?error, "Syslog connection failed.");
+            }
+        });
+    }
+}
+
+async fn handle_tcp_connection(stream: TcpStream, listener: &SyslogListener) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut frame = Vec::new();
+    while read_tcp_frame(&mut reader, &mut frame).await? {
+        if !listener.handle_frame(&frame).await {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the next frame of a syslog TCP stream into `frame`. Frames are either prefixed with their
+/// length (octet counting) or terminated by a line feed (non-transparent framing), see RFC 6587.
+/// Returns `false` at the end of the stream.
+async fn read_tcp_frame<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    frame: &mut Vec<u8>,
+) -> io::Result<bool> {
+    frame.clear();
+    let first_byte = loop {
+        let buffer = reader.fill_buf().await?;
+        match buffer.first() {
+            None => return Ok(false),
+            Some(b'\r' | b'\n') => reader.consume(1),
+            Some(&byte) => break byte,
+        }
+    };
+    if first_byte.is_ascii_digit() {
+        let mut msg_len_bytes = Vec::new();
+        (&mut *reader)
+            .take(MAX_FRAME_LEN_NUM_BYTES)
+            .read_until(b' ', &mut msg_len_bytes)
+            .await?;
+        let msg_len = std::str::from_utf8(&msg_len_bytes)
+            .ok()
+            .and_then(|msg_len_str| msg_len_str.trim_end().parse::<usize>().ok())
+            .filter(|msg_len| *msg_len <= MAX_TCP_FRAME_NUM_BYTES)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid frame length."))?;
+        frame.resize(msg_len, 0);
+        reader.read_exact(frame).await?;
+    } else {
+        // The frame is read up to one byte past the limit to tell a frame of the maximum size
+        // from a longer one.
+        (&mut *reader)
+            .take(MAX_TCP_FRAME_NUM_BYTES as u64 + 1)
+            .read_until(b'\n', frame)
+            .await?;
+        if frame.len() > MAX_TCP_FRAME_NUM_BYTES && frame.last() != Some(&b'\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Frame exceeds the maximum frame size.",
+            ));
+        }
+        while matches!(frame.last(), Some(b'\r' | b'\n')) {
+            frame.pop();
+        }
+    }
+    Ok(true)
+}
+
+/// Parses a RFC 5424 or RFC 3164 syslog message into a JSON object. Returns `None` if the message
+/// does not start with a valid priority.
+fn parse_syslog_message(frame: &[u8]) -> Option<JsonValue> {
+    let text = String::from_utf8_lossy(frame);
+    let text = text.trim_end_matches(|c: char| matches!(c, '\r' | '\n' | '\0'));
+    let rest = text.strip_prefix('<')?;
+    let priority_end = rest.find('>')?;
+    if !(1..=3).contains(&priority_end) || !rest[..priority_end].bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let priority: u8 = rest[..priority_end].parse().ok()?;
+    if priority > 191 {
+        return None;
+    }
+    let rest = &rest[priority_end + 1..];
+    let mut doc = JsonMap::new();
+    doc.insert("facility".to_string(), json!(priority / 8));
+    doc.insert("severity".to_string(), json!(priority % 8));
+    if let Some(rest) = rest.strip_prefix("1 ") {
+        parse_rfc5424(rest, &mut doc)?;
+    } else {
+        parse_rfc3164(rest, &mut doc);
+    }
+    Some(JsonValue::Object(doc))
+}
+
+/// Parses `TIMESTAMP SP HOSTNAME SP APP-NAME SP PROCID SP MSGID SP STRUCTURED-DATA [SP MSG]`.
+fn parse_rfc5424(input: &str, doc: &mut JsonMap<String, JsonValue>) -> Option<()> {
+    doc.insert("version".to_string(), json!(1));
+    let mut rest = input;
+    for field_name in ["timestamp", "hostname", "app_name", "proc_id", "msg_id"] {
+        let (token, tail) = rest.split_once(' ')?;
+        if token != NIL_VALUE {
+            doc.insert(field_name.to_string(), json!(token));
+        }
+        rest = tail;
+    }
+    let (structured_data, rest) = parse_structured_data(rest)?;
+    if !structured_data.is_empty() {
+        doc.insert(
+            "structured_data".to_string(),
+            JsonValue::Object(structured_data),
+        );
+    }
+    let message = rest.strip_prefix(' ').unwrap_or(rest);
+    let message = message.strip_prefix('\u{feff}').unwrap_or(message);
+    if !message.is_empty() {
+        doc.insert("message".to_string(), json!(message));
+    }
+    Some(())
+}
+
+/// Parses the structured data elements `[SD-ID SP PARAM-NAME="PARAM-VALUE" ...]` of a RFC 5424
+/// message into an object keyed by SD-ID.
+fn parse_structured_data(input: &str) -> Option<(JsonMap<String, JsonValue>, &str)> {
+    let mut elements = JsonMap::new();
+    if let Some(rest) = input.strip_prefix(NIL_VALUE) {
+        return Some((elements, rest));
+    }
+    let mut rest = input;
+    while let Some(element) = rest.strip_prefix('[') {
+        let sd_id_end = element.find(|c: char| c == ' ' || c == ']')?;
+        let mut params = JsonMap::new();
+        let mut tail = &element[sd_id_end..];
+        loop {
+            if let Some(after_element) = tail.strip_prefix(']') {
+                tail = after_element;
+                break;
+            }
+            let (param_name, value_and_tail) = tail.strip_prefix(' ')?.split_once("=\"")?;
+            let (param_value, after_param) = parse_param_value(value_and_tail)?;
+            params.insert(param_name.to_string(), json!(param_value));
+            tail = after_param;
+        }
+        elements.insert(element[..sd_id_end].to_string(), JsonValue::Object(params));
+        rest = tail;
+    }
+    if elements.is_empty() {
+        return None;
+    }
+    Some((elements, rest))
+}
+
+/// Unescapes a quoted structured data parameter value and returns it along with what follows the
+/// closing quote.
+fn parse_param_value(input: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &input[idx + 1..])),
+            '\\' => match chars.next()? {
+                (_, escaped @ ('"' | '\\' | ']')) => value.push(escaped),
+                (_, other) => {
+                    value.push('\\');
+                    value.push(other);
+                }
+            },
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+/// Parses `TIMESTAMP SP HOSTNAME SP TAG[PID]: MSG`. That layout is only a convention, so the parts
+/// of the message that do not follow it are kept whole in `message`.
+fn parse_rfc3164(input: &str, doc: &mut JsonMap<String, JsonValue>) {
+    let mut message = input;
+    if let Some((timestamp, rest)) = split_rfc3164_timestamp(input) {
+        doc.insert("timestamp".to_string(), json!(timestamp));
+        message = rest;
+        if let Some((hostname, rest)) = rest.split_once(' ') {
+            doc.insert("hostname".to_string(), json!(hostname));
+            message = rest;
+            if let Some((app_name, proc_id_opt, rest)) = split_rfc3164_tag(rest) {
+                doc.insert("app_name".to_string(), json!(app_name));
+                if let Some(proc_id) = proc_id_opt {
+                    doc.insert("proc_id".to_string(), json!(proc_id));
+                }
+                message = rest;
+            }
+        }
+    }
+    doc.insert("message".to_string(), json!(message));
+}
+
+/// Splits a `Mmm dd hh:mm:ss` timestamp from the rest of the message.
+fn split_rfc3164_timestamp(input: &str) -> Option<(&str, &str)> {
+    let timestamp = input.get(..15)?;
+    if !timestamp.is_ascii() {
+        return None;
+    }
+    let rest = input.get(15..)?.strip_prefix(' ')?;
+    let bytes = timestamp.as_bytes();
+    let is_timestamp = RFC3164_MONTHS.contains(&&timestamp[..3])
+        && bytes[3] == b' '
+        && bytes[6] == b' '
+        && bytes[9] == b':'
+        && bytes[12] == b':'
+        && [4, 5, 7, 8, 10, 11, 13, 14]
+            .iter()
+            .all(|&idx| bytes[idx].is_ascii_digit() || (idx == 4 && bytes[idx] == b' '));
+    if !is_timestamp {
+        return None;
+    }
+    Some((timestamp, rest))
+}
+
+/// Splits a `TAG[PID]:` or `TAG:` prefix from the rest of the message.
+fn split_rfc3164_tag(input: &str) -> Option<(&str, Option<&str>, &str)> {
+    let tag_end = input.find(|c: char| c == '[' || c == ':' || c == ' ')?;
+    if tag_end == 0 {
+        return None;
+    }
+    let mut rest = &input[tag_end..];
+    let mut proc_id_opt = None;
+    if let Some(after_bracket) = rest.strip_prefix('[') {
+        let (proc_id, after_proc_id) = after_bracket.split_once(']')?;
+        proc_id_opt = Some(proc_id);
+        rest = after_proc_id;
+    }
+    let message = rest.strip_prefix(':')?;
+    Some((
+        &input[..tag_end],
+        proc_id_opt,
+        message.strip_prefix(' ').unwrap_or(message),
+    ))
+}
+
+/// A source listening for syslog messages over UDP or TCP.
+///
+/// Syslog senders do not replay messages, so the source is best-effort: the messages waiting in
+/// memory to be indexed are lost when the pipeline restarts. Once the in-memory queue is full,
+/// incoming messages are spilled to the ingest API queue of the index, which persists them on disk
+/// and lets the `_ingest-api` source index them. Like the Pub/Sub source, each pipeline records the
+/// number of messages it has emitted in its own partition of the checkpoint.
+pub struct SyslogSource {
+    ctx: Arc<SourceExecutionContext>,
+    protocol: SyslogProtocol,
+    local_addr: SocketAddr,
+    queue_rx: mpsc::Receiver<Bytes>,
+    listener_handle: JoinHandle<()>,
+    listener_counters: Arc<SyslogListenerCounters>,
+    partition_id: PartitionId,
+    current_position: u64,
+    num_bytes_processed: u64,
+    num_messages_processed: u64,
+}
+
+impl fmt::Debug for SyslogSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SyslogSource {{ source_id: {}, local_addr: {} }}",
+            self.ctx.source_config.source_id, self.local_addr
+        )
+    }
+}
+
+impl Drop for SyslogSource {
+    fn drop(&mut self) {
+        self.listener_handle.abort();
+    }
+}
+
+impl SyslogSource {
+    /// Binds the socket of the source and starts listening for messages.
+    pub async fn try_new(
+        ctx: Arc<SourceExecutionContext>,
+        params: SyslogSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<Self> {
+        let listen_addr: SocketAddr = params.listen_address.parse().with_context(|| {
+            format!(
+                "Failed to parse syslog listen address `{}`.",
+                params.listen_address
+            )
+        })?;
+        let ingest_api_service = get_ingest_api_service(ctx.queues_dir_path.as_path()).await?;
+        // Ensure a queue exists to spill the messages to.
+        let create_queue_req = CreateQueueIfNotExistsRequest {
+            queue_id: ctx.index_id.clone(),
+        };
+        ingest_api_service.ask_for_res(create_queue_req).await?;
+
+        let (queue_tx, queue_rx) = mpsc::channel(params.max_queued_messages);
+        let listener_counters = Arc::new(SyslogListenerCounters::default());
+        let listener = SyslogListener {
+            index_id: ctx.index_id.clone(),
+            queue_tx,
+            ingest_api_service,
+            counters: listener_counters.clone(),
+        };
+        let (local_addr, listener_handle) = match params.protocol {
+            SyslogProtocol::Udp => {
+                let socket = UdpSocket::bind(listen_addr)
+                    .await
+                    .with_context(|| format!("Failed to bind UDP socket to `{listen_addr}`."))?;
+                let local_addr = socket.local_addr()?;
+                (local_addr, tokio::spawn(run_udp_listener(socket, listener)))
+            }
+            SyslogProtocol::Tcp => {
+                let tcp_listener = TcpListener::bind(listen_addr)
+                    .await
+                    .with_context(|| format!("Failed to bind TCP socket to `{listen_addr}`."))?;
+                let local_addr = tcp_listener.local_addr()?;
+                (
+                    local_addr,
+                    tokio::spawn(run_tcp_listener(tcp_listener, listener)),
+                )
+            }
+        };
+        // Each pipeline records the messages it emits in its own partition.
+        let partition_id = PartitionId::from(format!("{}:{}", ctx.node_id, ctx.pipeline_ord));
+        let current_position = match checkpoint.position_for_partition(&partition_id) {
+            Some(Position::Offset(offset)) => offset
+                .parse::<u64>()
+                .with_context(|| format!("Failed to parse syslog position `{offset}`."))?,
+            _ => 0,
+        };
+        info!(
+            index_id=%ctx.index_id,
+            source_id=%ctx.source_config.source_id,
+            local_addr=%local_addr,
+            protocol=?params.protocol,
+            partition_id=%partition_id.as_str(),
+            current_position=%current_position,
+            "Starting syslog source."
+        );
+        Ok(Self {
+            ctx,
+            protocol: params.protocol,
+            local_addr,
+            queue_rx,
+            listener_handle,
+            listener_counters,
+            partition_id,
+            current_position,
+            num_bytes_processed: 0,
+            num_messages_processed: 0,
+        })
+    }
+}
+
+#[async_trait]
+impl Source for SyslogSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let mut batch_num_bytes = 0;
+        let mut docs = Vec::new();
+        let deadline = Instant::now() + EMIT_BATCHES_TIMEOUT;
+
+        while batch_num_bytes < TARGET_BATCH_NUM_BYTES {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let doc = match ctx
+                .protect_future(tokio::time::timeout(timeout, self.queue_rx.recv()))
+                .await
+            {
+                Ok(Some(doc)) => doc,
+                Ok(None) => return Err(anyhow!("Syslog listener stopped unexpectedly.").into()),
+                Err(_) => break,
+            };
+            batch_num_bytes += doc.len() as u64;
+            docs.push(doc);
+        }
+        if docs.is_empty() {
+            return Ok(Duration::default());
+        }
+        self.num_bytes_processed += batch_num_bytes;
+        self.num_messages_processed += docs.len() as u64;
+
+        let previous_position = if self.current_position == 0 {
+            Position::Beginning
+        } else {
+            Position::from(self.current_position)
+        };
+        let current_position = self.current_position + docs.len() as u64;
+        let mut checkpoint_delta = SourceCheckpointDelta::default();
+        checkpoint_delta
+            .record_partition_delta(
+                self.partition_id.clone(),
+                previous_position,
+                Position::from(current_position),
+            )
+            .context("Failed to record partition delta.")?;
+        self.current_position = current_position;
+
+        let batch = RawDocBatch {
+            docs,
+            checkpoint_delta,
+        };
+        ctx.send_message(doc_processor_mailbox, batch).await?;
+        Ok(Duration::default())
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "SyslogSource{{source_id={}}}",
+            self.ctx.source_config.source_id
+        )
+    }
+
+    fn observable_state(&self) -> serde_json::Value {
+        let counters = &self.listener_counters;
+        json!({
+            "index_id": self.ctx.index_id,
+            "source_id": self.ctx.source_config.source_id,
+            "local_addr": self.local_addr.to_string(),
+            "protocol": self.protocol,
+            "partition_id": self.partition_id.as_str(),
+            "current_position": self.current_position,
+            "num_bytes_processed": self.num_bytes_processed,
+            "num_messages_processed": self.num_messages_processed,
+            "num_messages_received": counters.num_messages_received.load(Ordering::Relaxed),
+            "num_invalid_messages": counters.num_invalid_messages.load(Ordering::Relaxed),
+            "num_spilled_messages": counters.num_spilled_messages.load(Ordering::Relaxed),
+            "num_dropped_messages": counters.num_dropped_messages.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use quickwit_actors::{create_test_mailbox, Universe};
+    use quickwit_common::rand::append_random_suffix;
+    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_ingest_api::{init_ingest_api, iter_doc_payloads};
+    use quickwit_metastore::metastore_for_test;
+    use quickwit_proto::ingest_api::FetchRequest;
+
+    use super::*;
+    use crate::source::SourceActor;
+
+    #[test]
+    fn test_parse_rfc5424_message() {
+        let frame = br#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="App\"lication\]" eventID="1011"][examplePriority@32473 class="high"] An application event log entry"#;
+        let doc = parse_syslog_message(frame).unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "facility": 20,
+                "severity": 5,
+                "version": 1,
+                "timestamp": "2003-10-11T22:14:15.003Z",
+                "hostname": "mymachine.example.com",
+                "app_name": "evntslog",
+                "msg_id": "ID47",
+                "structured_data": {
+                    "exampleSDID@32473": {
+                        "iut": "3",
+                        "eventSource": "App\"lication]",
+                        "eventID": "1011",
+                    },
+                    "examplePriority@32473": {"class": "high"},
+                },
+                "message": "An application event log entry",
+            })
+        );
+        let doc = parse_syslog_message(b"<34>1 - - su - - -\n").unwrap();
+        assert_eq!(
+            doc,
+            json!({"facility": 4, "severity": 2, "version": 1, "app_name": "su"})
+        );
+        assert!(parse_syslog_message(b"<34>1 - - su - - [unterminated").is_none());
+    }
+
+    #[test]
+    fn test_parse_rfc3164_message() {
+        let doc = parse_syslog_message(
+            b"<34>Oct 11 22:14:15 mymachine su[123]: 'su root' failed for lonvick on /dev/pts/8",
+        )
+        .unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "facility": 4,
+                "severity": 2,
+                "timestamp": "Oct 11 22:14:15",
+                "hostname": "mymachine",
+                "app_name": "su",
+                "proc_id": "123",
+                "message": "'su root' failed for lonvick on /dev/pts/8",
+            })
+        );
+        let doc = parse_syslog_message(b"<13>Feb  5 17:32:18 10.0.0.99 Use the BFG!").unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "facility": 1,
+                "severity": 5,
+                "timestamp": "Feb  5 17:32:18",
+                "hostname": "10.0.0.99",
+                "message": "Use the BFG!",
+            })
+        );
+        let doc = parse_syslog_message(b"<0>no timestamp here").unwrap();
+        assert_eq!(
+            doc,
+            json!({"facility": 0, "severity": 0, "message": "no timestamp here"})
+        );
+        assert!(parse_syslog_message(b"no priority").is_none());
+        assert!(parse_syslog_message(b"<192>invalid priority").is_none());
+        assert!(parse_syslog_message(b"<1a>invalid priority").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_tcp_frame() {
+        let mut stream: &[u8] = b"11 <34>1 - - -\n<13>first line\r\n\n<13>second line";
+        let mut frame = Vec::new();
+        assert!(read_tcp_frame(&mut stream, &mut frame).await.unwrap());
+        assert_eq!(frame, b"<34>1 - - -");
+        assert!(read_tcp_frame(&mut stream, &mut frame).await.unwrap());
+        assert_eq!(frame, b"<13>first line");
+        assert!(read_tcp_frame(&mut stream, &mut frame).await.unwrap());
+        assert_eq!(frame, b"<13>second line");
+        assert!(!read_tcp_frame(&mut stream, &mut frame).await.unwrap());
+
+        let mut stream: &[u8] = b"2000000 <34>1";
+        read_tcp_frame(&mut stream, &mut frame).await.unwrap_err();
+
+        let mut stream: &[u8] = &[b'1'; 1_000];
+        read_tcp_frame(&mut stream, &mut frame).await.unwrap_err();
+
+        let mut line = vec![b'a'; MAX_TCP_FRAME_NUM_BYTES];
+        line.push(b'\n');
+        let mut stream: &[u8] = &line;
+        assert!(read_tcp_frame(&mut stream, &mut frame).await.unwrap());
+        assert_eq!(frame.len(), MAX_TCP_FRAME_NUM_BYTES);
+
+        let line = vec![b'a'; MAX_TCP_FRAME_NUM_BYTES + 10];
+        let mut stream: &[u8] = &line;
+        read_tcp_frame(&mut stream, &mut frame).await.unwrap_err();
+    }
+
+    fn make_source_ctx(
+        index_id: &str,
+        queues_dir_path: &Path,
+        params: SyslogSourceParams,
+    ) -> Arc<SourceExecutionContext> {
+        SourceExecutionContext::for_test(
+            metastore_for_test(),
+            index_id,
+            queues_dir_path.to_path_buf(),
+            SourceConfig {
+                source_id: "test-syslog-source".to_string(),
+                num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
+                enabled: true,
+                source_params: SourceParams::Syslog(params),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_syslog_source_udp() -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let temp_dir = tempfile::tempdir()?;
+        init_ingest_api(&universe, temp_dir.path()).await?;
+        let index_id = append_random_suffix("test-syslog-source");
+        let params = SyslogSourceParams {
+            listen_address: "127.0.0.1:0".to_string(),
+            protocol: SyslogProtocol::Udp,
+            max_queued_messages: 10,
+        };
+        let ctx = make_source_ctx(&index_id, temp_dir.path(), params.clone());
+        let syslog_source =
+            SyslogSourceFactory::typed_create_source(ctx, params, SourceCheckpoint::default())
+                .await?;
+        let local_addr = syslog_source.local_addr;
+        let (doc_processor_mailbox, doc_processor_inbox) = create_test_mailbox();
+        let syslog_source_actor = SourceActor {
+            source: Box::new(syslog_source),
+            doc_processor_mailbox,
+            rate_limiter_opt: None,
+        };
+        let (_syslog_source_mailbox, syslog_source_handle) =
+            universe.spawn_builder().spawn(syslog_source_actor);
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await?;
+        socket
+            .send_to(b"<34>1 - host app - - - hello", local_addr)
+            .await?;
+        socket.send_to(b"not syslog", local_addr).await?;
+
+        let mut batches = Vec::new();
+        for _ in 0..100 {
+            batches = doc_processor_inbox.drain_for_test_typed::<RawDocBatch>();
+            if !batches.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].docs.len(), 1);
+        let doc: JsonValue = serde_json::from_slice(&batches[0].docs[0])?;
+        assert_eq!(doc["message"], "hello");
+        assert_eq!(doc["hostname"], "host");
+
+        let state = syslog_source_handle.process_pending_and_observe().await;
+        assert_eq!(state["current_position"], 1);
+        assert_eq!(state["num_messages_received"], 2);
+        assert_eq!(state["num_invalid_messages"], 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_syslog_listener_spills_to_ingest_api_queue() -> anyhow::Result<()> {
+        let universe = Universe::new();
+        let temp_dir = tempfile::tempdir()?;
+        let ingest_api_service = init_ingest_api(&universe, temp_dir.path()).await?;
+        let index_id = append_random_suffix("test-syslog-spill");
+        ingest_api_service
+            .ask_for_res(CreateQueueIfNotExistsRequest {
+                queue_id: index_id.clone(),
+            })
+            .await?;
+        let (queue_tx, mut queue_rx) = mpsc::channel(1);
+        let listener = SyslogListener {
+            index_id: index_id.clone(),
+            queue_tx,
+            ingest_api_service: ingest_api_service.clone(),
+            counters: Arc::new(SyslogListenerCounters::default()),
+        };
+        assert!(listener.handle_frame(b"<13>first").await);
+        assert!(listener.handle_frame(b"<13>second").await);
+        assert_eq!(
+            listener
+                .counters
+                .num_spilled_messages
+                .load(Ordering::Relaxed),
+            1
+        );
+        let queued_doc: JsonValue = serde_json::from_slice(&queue_rx.recv().await.unwrap())?;
+        assert_eq!(queued_doc["message"], "first");
+
+        let fetch_resp = ingest_api_service
+            .ask_for_res(FetchRequest {
+                index_id,
+                start_after: None,
+                num_bytes_limit: None,
+            })
+            .await?;
+        let doc_batch = fetch_resp.doc_batch.unwrap();
+        let spilled_docs: Vec<&[u8]> = iter_doc_payloads(&doc_batch).collect();
+        assert_eq!(spilled_docs.len(), 1);
+        let spilled_doc: JsonValue = serde_json::from_slice(spilled_docs[0])?;
+        assert_eq!(spilled_doc["message"], "second");
+
+        drop(queue_rx);
+        assert!(!listener.handle_frame(b"<13>third").await);
+        Ok(())
+    }
+}
@@ -22,6 +22,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use bytes::Bytes;
 use quickwit_actors::{ActorExitStatus, Mailbox};
 use quickwit_config::VecSourceParams;
 use quickwit_metastore::checkpoint::{
@@ -90,7 +91,7 @@ impl Source for VecSource {
             self.params.docs[self.next_item_idx..]
                 .iter()
                 .take(self.params.batch_num_docs)
-                .cloned(),
+                .map(|doc| Bytes::from(doc.clone())),
         );
         if doc_batch.docs.is_empty() {
             info!("Reached end of source.");
@@ -153,6 +154,11 @@ mod tests {
                 SourceConfig {
                     source_id: "test-vec-source".to_string(),
                     num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
                     enabled: true,
                     source_params: SourceParams::Vec(params.clone()),
                 },
@@ -164,6 +170,7 @@ mod tests {
         let vec_source_actor = SourceActor {
             source: Box::new(vec_source),
             doc_processor_mailbox,
+            rate_limiter_opt: None,
         };
         assert_eq!(
             vec_source_actor.name(),
@@ -210,6 +217,11 @@ mod tests {
                 SourceConfig {
                     source_id: "test-vec-source".to_string(),
                     num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
                     enabled: true,
                     source_params: SourceParams::Vec(params.clone()),
                 },
@@ -221,6 +233,7 @@ mod tests {
         let vec_source_actor = SourceActor {
             source: Box::new(vec_source),
             doc_processor_mailbox,
+            rate_limiter_opt: None,
         };
         let (_vec_source_mailbox, vec_source_handle) =
             universe.spawn_builder().spawn(vec_source_actor);
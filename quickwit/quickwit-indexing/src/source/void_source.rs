@@ -86,6 +86,11 @@ mod tests {
         let source_config = SourceConfig {
             source_id: "test-void-source".to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::void(),
         };
@@ -114,6 +119,11 @@ mod tests {
                 SourceConfig {
                     source_id: "test-void-source".to_string(),
                     num_pipelines: 1,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
                     enabled: true,
                     source_params: SourceParams::void(),
                 },
@@ -126,6 +136,7 @@ mod tests {
         let void_source_actor = SourceActor {
             source: Box::new(void_source),
             doc_processor_mailbox,
+            rate_limiter_opt: None,
         };
         let universe = Universe::new();
         let (_, void_source_handle) = universe.spawn_builder().spawn(void_source_actor);
@@ -27,6 +27,7 @@ use anyhow::Context;
 #[cfg(any(test, feature = "testsuite"))]
 use byte_unit::Byte;
 use quickwit_common::io::{IoControls, IoControlsAccess};
+use quickwit_doc_mapper::bloom_filter::SplitBloomFilters;
 use quickwit_metastore::SplitMetadata;
 use quickwit_storage::{PutPayload, Storage, StorageResult};
 use tantivy::directory::MmapDirectory;
@@ -179,6 +180,28 @@ impl IndexingSplitStore {
         Ok(())
     }
 
+    /// Stores the bloom filters of a split next to the split file.
+    #[instrument("store_bloom_filters", skip_all)]
+    pub async fn store_bloom_filters(
+        &self,
+        split_id: &str,
+        bloom_filters: &SplitBloomFilters,
+    ) -> anyhow::Result<()> {
+        let key = PathBuf::from(quickwit_common::bloom_filter_file(split_id));
+        self.inner
+            .remote_storage
+            .put(&key, Box::new(bloom_filters.to_bytes()))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed uploading key {} in bucket {}",
+                    key.display(),
+                    self.inner.remote_storage.uri()
+                )
+            })?;
+        Ok(())
+    }
+
     /// Gets a split from the split store, and makes it available to the given `output_path`.
     /// If the split is available in the local disk cache, then it will be moved
     /// from the cache to the `output_dir_path`.
@@ -227,6 +250,32 @@ impl IndexingSplitStore {
         get_tantivy_directory_from_split_bundle(&dest_filepath)
     }
 
+    /// Returns whether the split is in the local split store, in which case
+    /// [`Self::fetch_and_open_split`] does not download it.
+    pub async fn is_split_cached(&self, split_id: &str) -> bool {
+        self.inner.local_split_store.contains_split(split_id).await
+    }
+
+    /// Opens a split of the local split store without removing it from the store, unlike
+    /// [`Self::fetch_and_open_split`]. The files of the split are hard-linked into
+    /// `output_dir_path`. Returns `None` if the split is not in the local split store.
+    pub async fn open_cached_split(
+        &self,
+        split_id: &str,
+        output_dir_path: &Path,
+    ) -> StorageResult<Option<Box<dyn Directory>>> {
+        if let Some(split_path) = self
+            .inner
+            .local_split_store
+            .link_cached_split(split_id, output_dir_path)
+            .await?
+        {
+            let mmap_directory: Box<dyn Directory> = Box::new(MmapDirectory::open(&split_path)?);
+            return Ok(Some(mmap_directory));
+        }
+        Ok(None)
+    }
+
     pub fn downgrade(&self) -> WeakIndexingSplitStore {
         WeakIndexingSplitStore {
             inner: Arc::downgrade(&self.inner),
@@ -394,6 +394,47 @@ impl LocalSplitStore {
         }
     }
 
+    /// Returns whether the split is in the cache.
+    pub(super) async fn contains_split(&self, split_id: &str) -> bool {
+        let split_ulid = if let Ok(split_ulid) = Ulid::from_str(split_id) {
+            split_ulid
+        } else {
+            return false;
+        };
+        self.inner
+            .lock()
+            .await
+            .split_folders
+            .contains_key(&split_ulid)
+    }
+
+    /// Hard-links the files of a cached split into `output_dir_path`, leaving the split in the
+    /// cache. The linked files remain readable even if the split is evicted or moved out of the
+    /// cache in the meantime. Returns `None` if the split is not in the cache.
+    pub(super) async fn link_cached_split(
+        &self,
+        split_id: &str,
+        output_dir_path: &Path,
+    ) -> io::Result<Option<PathBuf>> {
+        let split_store_lock = self.inner.lock().await;
+        let split_ulid = if let Ok(split_ulid) = Ulid::from_str(split_id) {
+            split_ulid
+        } else {
+            return Ok(None);
+        };
+        if !split_store_lock.split_folders.contains_key(&split_ulid) {
+            return Ok(None);
+        }
+        let from_path = split_store_lock.split_path(split_ulid);
+        let to_full_path = output_dir_path.join(from_path.file_name().unwrap());
+        tokio::fs::create_dir(&to_full_path).await?;
+        let mut entries = tokio::fs::read_dir(&from_path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            tokio::fs::hard_link(entry.path(), to_full_path.join(entry.file_name())).await?;
+        }
+        Ok(Some(to_full_path))
+    }
+
     /// Tries to move a `split_folder` file into the cache.
     ///
     /// Move is not an image here. We are litterally moving the directory.
@@ -593,4 +634,27 @@ mod tests {
         assert!(split_path.exists());
         assert_eq!(split_path.parent().unwrap(), temp_dir_in.path());
     }
+
+    #[tokio::test]
+    async fn test_link_cached_split() -> anyhow::Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+        let split_id = "01GF5449X7DA53TK9F9W2ZJST2";
+        create_fake_split(cache_dir.path(), split_id, 15).await?;
+        let local_store =
+            LocalSplitStore::open(cache_dir.path().to_path_buf(), SplitStoreQuota::default())
+                .await?;
+        let output_dir = tempfile::tempdir()?;
+        assert!(local_store
+            .link_cached_split("01GF545472A06WY07SEHGCJF9P", output_dir.path())
+            .await?
+            .is_none());
+        let split_path = local_store
+            .link_cached_split(split_id, output_dir.path())
+            .await?
+            .unwrap();
+        assert_eq!(fs::read(split_path.join("splitdata")).await?, vec![0u8; 15]);
+        // The split stays in the cache.
+        assert!(local_store.inspect().await.contains_key(split_id));
+        Ok(())
+    }
 }
@@ -132,6 +132,11 @@ impl TestSandbox {
         let source_config = SourceConfig {
             source_id: self.index_id.clone(),
             num_pipelines: 0,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::Vec(VecSourceParams {
                 docs,
@@ -0,0 +1,213 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Time slicing of the merged splits.
+//!
+//! When time slicing is enabled, a merge produces one split per time slice spanned by the
+//! documents of its input splits. Slices are aligned on multiples of their duration since the Unix
+//! epoch and are identified by their ordinal, i.e. the number of slices elapsed since the epoch.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::ops::{Range, RangeInclusive};
+use std::path::Path;
+use std::sync::Arc;
+
+use quickwit_doc_mapper::fast_field_reader::timestamp_field_reader;
+use tantivy::directory::error::{DeleteError, OpenReadError, OpenWriteError};
+use tantivy::directory::{FileHandle, WatchCallback, WatchHandle, WritePtr};
+use tantivy::schema::Field;
+use tantivy::{Directory, DocId, Index, SegmentId, SegmentReader};
+
+/// Returns the ordinal of the time slice holding `timestamp`.
+pub(crate) fn time_slice_ord(timestamp: i64, slice_secs: i64) -> i64 {
+    timestamp.div_euclid(slice_secs)
+}
+
+/// Returns the time range, in seconds, covered by the time slice of ordinal `slice_ord`.
+pub(crate) fn time_slice_range(slice_ord: i64, slice_secs: i64) -> Range<i64> {
+    let start = slice_ord.saturating_mul(slice_secs);
+    start..start.saturating_add(slice_secs)
+}
+
+/// Returns the ordinal of the time slice holding the whole time range, or `None` if the time range
+/// spans several slices.
+pub(crate) fn single_time_slice_ord(
+    time_range: &RangeInclusive<i64>,
+    slice_secs: i64,
+) -> Option<i64> {
+    let start_slice_ord = time_slice_ord(*time_range.start(), slice_secs);
+    let end_slice_ord = time_slice_ord(*time_range.end(), slice_secs);
+    if start_slice_ord == end_slice_ord {
+        Some(start_slice_ord)
+    } else {
+        None
+    }
+}
+
+/// Lists the ordinals of the time slices holding at least one alive document of the index.
+pub(crate) fn list_time_slice_ords(
+    index: &Index,
+    timestamp_field: Field,
+    slice_secs: i64,
+) -> tantivy::Result<BTreeSet<i64>> {
+    let mut slice_ords = BTreeSet::new();
+
+    for segment in index.searchable_segments()? {
+        let segment_reader = SegmentReader::open(&segment)?;
+        if segment_reader.num_docs() == 0 {
+            continue;
+        }
+        let timestamp_reader = timestamp_field_reader(timestamp_field, &segment_reader)?;
+        let min_slice_ord = time_slice_ord(timestamp_reader.min_value(), slice_secs);
+        let max_slice_ord = time_slice_ord(timestamp_reader.max_value(), slice_secs);
+        // The bounds of the column also account for the deleted documents.
+        if min_slice_ord == max_slice_ord {
+            slice_ords.insert(min_slice_ord);
+            continue;
+        }
+        for doc in segment_reader.doc_ids_alive() {
+            slice_ords.insert(time_slice_ord(timestamp_reader.get(doc), slice_secs));
+        }
+    }
+    Ok(slice_ords)
+}
+
+/// Lists, for each segment of the index, the alive documents whose timestamp lies outside of
+/// `time_range`. The list is meant to be deleted with a `DocListQuery`.
+pub(crate) fn find_docs_outside_time_range(
+    index: &Index,
+    timestamp_field: Field,
+    time_range: &Range<i64>,
+) -> tantivy::Result<HashMap<SegmentId, Vec<DocId>>> {
+    let mut docs_outside_time_range = HashMap::new();
+
+    for segment in index.searchable_segments()? {
+        let segment_reader = SegmentReader::open(&segment)?;
+        let timestamp_reader = timestamp_field_reader(timestamp_field, &segment_reader)?;
+        let segment_docs: Vec<DocId> = segment_reader
+            .doc_ids_alive()
+            .filter(|doc| !time_range.contains(&timestamp_reader.get(*doc)))
+            .collect();
+        if !segment_docs.is_empty() {
+            docs_outside_time_range.insert(segment.id(), segment_docs);
+        }
+    }
+    Ok(docs_outside_time_range)
+}
+
+/// Wraps the directory of a split merged once per time slice and ignores the deletions of its
+/// files, which the garbage collection of the first merges would otherwise remove before the next
+/// ones read them.
+#[derive(Clone, Debug)]
+pub(crate) struct KeepFilesDirectory(Box<dyn Directory>);
+
+impl KeepFilesDirectory {
+    pub fn new(directory: Box<dyn Directory>) -> Self {
+        KeepFilesDirectory(directory)
+    }
+}
+
+impl Directory for KeepFilesDirectory {
+    fn get_file_handle(&self, path: &Path) -> Result<Arc<dyn FileHandle>, OpenReadError> {
+        self.0.get_file_handle(path)
+    }
+
+    fn delete(&self, _path: &Path) -> Result<(), DeleteError> {
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> Result<bool, OpenReadError> {
+        self.0.exists(path)
+    }
+
+    fn open_write(&self, path: &Path) -> Result<WritePtr, OpenWriteError> {
+        self.0.open_write(path)
+    }
+
+    fn atomic_read(&self, path: &Path) -> Result<Vec<u8>, OpenReadError> {
+        self.0.atomic_read(path)
+    }
+
+    fn atomic_write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.0.atomic_write(path, data)
+    }
+
+    fn watch(&self, watch_callback: WatchCallback) -> tantivy::Result<WatchHandle> {
+        self.0.watch(watch_callback)
+    }
+
+    fn sync_directory(&self) -> io::Result<()> {
+        self.0.sync_directory()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{Schema, FAST, INDEXED};
+    use tantivy::{doc, DateTime};
+
+    use super::*;
+    use crate::dedup::DocListQuery;
+
+    #[test]
+    fn test_time_slice_ord_and_range() {
+        assert_eq!(time_slice_ord(0, 3_600), 0);
+        assert_eq!(time_slice_ord(3_599, 3_600), 0);
+        assert_eq!(time_slice_ord(3_600, 3_600), 1);
+        assert_eq!(time_slice_ord(-1, 3_600), -1);
+        assert_eq!(time_slice_range(1, 3_600), 3_600..7_200);
+        assert_eq!(time_slice_range(-1, 3_600), -3_600..0);
+
+        assert_eq!(single_time_slice_ord(&(3_600..=7_199), 3_600), Some(1));
+        assert_eq!(single_time_slice_ord(&(3_599..=3_600), 3_600), None);
+    }
+
+    #[test]
+    fn test_list_time_slices_and_delete_docs_outside_time_range() -> anyhow::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let timestamp_field = schema_builder.add_date_field("ts", INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 3_000_000)?;
+        for timestamp in [10, 3_610, 20] {
+            index_writer
+                .add_document(doc!(timestamp_field => DateTime::from_timestamp_secs(timestamp)))?;
+        }
+        index_writer.commit()?;
+        index_writer.add_document(doc!(timestamp_field => DateTime::from_timestamp_secs(30)))?;
+        index_writer.commit()?;
+
+        let slice_ords = list_time_slice_ords(&index, timestamp_field, 3_600)?;
+        assert_eq!(slice_ords.into_iter().collect::<Vec<_>>(), vec![0, 1]);
+
+        let docs_outside_time_range =
+            find_docs_outside_time_range(&index, timestamp_field, &time_slice_range(0, 3_600))?;
+        let num_docs_outside_time_range: usize =
+            docs_outside_time_range.values().map(Vec::len).sum();
+        assert_eq!(num_docs_outside_time_range, 1);
+
+        index_writer.delete_query(Box::new(DocListQuery::new(docs_outside_time_range)))?;
+        index_writer.commit()?;
+        assert_eq!(index.reader()?.searcher().num_docs(), 3);
+
+        let slice_ords = list_time_slice_ords(&index, timestamp_field, 3_600)?;
+        assert_eq!(slice_ords.into_iter().collect::<Vec<_>>(), vec![0]);
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use quickwit_proto::ingest_api::IngestRequest;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+use crate::{DocBatchBuilder, IngestApiError};
+
+/// Action line of a bulk payload, in the format of the Elasticsearch bulk API.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all(deserialize = "lowercase"))]
+enum BulkAction {
+    Index(BulkActionMeta),
+    Create(BulkActionMeta),
+}
+
+impl BulkAction {
+    fn into_index(self) -> String {
+        match self {
+            BulkAction::Index(meta) => meta.index,
+            BulkAction::Create(meta) => meta.index,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct BulkActionMeta {
+    #[serde(alias = "_index")]
+    index: String,
+    #[serde(alias = "_id")]
+    #[serde(default)]
+    id: Option<String>,
+}
+
+/// Returns the non-empty lines of an NDJSON payload, trimmed.
+pub fn ndjson_lines(payload: &str) -> impl Iterator<Item = &str> {
+    payload.lines().filter_map(|line| {
+        let line_trimmed = line.trim();
+        if line_trimmed.is_empty() {
+            return None;
+        }
+        Some(line_trimmed)
+    })
+}
+
+fn invalid_bulk_payload(msg: String) -> IngestApiError {
+    IngestApiError::InvalidBulkPayload { msg }
+}
+
+/// Splits a bulk payload into one [`DocBatch`](quickwit_proto::ingest_api::DocBatch) per
+/// index.
+///
+/// The payload is an NDJSON sequence of action lines, each followed by the document to ingest,
+/// as in the Elasticsearch bulk API. Only the `index` and `create` actions are supported. The
+/// batches are ordered by first occurrence of their index in the payload, and the documents of
+/// an index keep their relative order.
+pub fn parse_bulk_payload(payload: &[u8]) -> crate::Result<IngestRequest> {
+    let payload_str = std::str::from_utf8(payload)
+        .map_err(|_| invalid_bulk_payload("Payload is not utf-8.".to_string()))?;
+    let mut doc_batch_builders: Vec<DocBatchBuilder> = Vec::new();
+    let mut doc_batch_ordinals: HashMap<String, usize> = HashMap::new();
+    let mut payload_lines = ndjson_lines(payload_str);
+
+    while let Some(action_str) = payload_lines.next() {
+        let action = serde_json::from_str::<BulkAction>(action_str)
+            .map_err(|error| invalid_bulk_payload(format!("Could not parse action: {error}")))?;
+        let source = payload_lines
+            .next()
+            .ok_or_else(|| invalid_bulk_payload("Expected source for the action.".to_string()))?;
+        serde_json::from_str::<IgnoredAny>(source)
+            .map_err(|error| invalid_bulk_payload(format!("Could not parse source: {error}")))?;
+
+        let index_id = action.into_index();
+        let ordinal = match doc_batch_ordinals.get(&index_id) {
+            Some(ordinal) => *ordinal,
+            None => {
+                let ordinal = doc_batch_builders.len();
+                doc_batch_ordinals.insert(index_id.clone(), ordinal);
+                doc_batch_builders.push(DocBatchBuilder::new(index_id));
+                ordinal
+            }
+        };
+        doc_batch_builders[ordinal].add_doc(source.as_bytes());
+    }
+    Ok(IngestRequest {
+        doc_batches: doc_batch_builders
+            .into_iter()
+            .map(DocBatchBuilder::build)
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iter_doc_payloads;
+
+    #[test]
+    fn test_deserialize_bulk_action() {
+        let json_str = r#"{ "create" : { "_index" : "test", "_id" : "2" } }"#;
+        let bulk_action = serde_json::from_str::<BulkAction>(json_str).unwrap();
+        assert_eq!(
+            bulk_action,
+            BulkAction::Create(BulkActionMeta {
+                index: "test".to_string(),
+                id: Some("2".to_string())
+            })
+        );
+
+        let json_str = r#"{ "index" : { "_index" : "test" } }"#;
+        let bulk_action = serde_json::from_str::<BulkAction>(json_str).unwrap();
+        assert_eq!(
+            bulk_action,
+            BulkAction::Index(BulkActionMeta {
+                index: "test".to_string(),
+                id: None
+            })
+        );
+
+        let json_str = r#"{ "delete" : { "_index" : "test", "_id" : "2" } }"#;
+        assert!(serde_json::from_str::<BulkAction>(json_str).is_err());
+    }
+
+    #[test]
+    fn test_parse_bulk_payload() {
+        let payload = r#"
+            { "create" : { "_index" : "index-1" } }
+            {"body": "doc-1"}
+            { "index" : { "_index" : "index-2" } }
+            {"body": "doc-2"}
+
+            { "create" : { "_index" : "index-1" } }
+            {"body": "doc-3"}
+        "#;
+        let ingest_req = parse_bulk_payload(payload.as_bytes()).unwrap();
+        assert_eq!(ingest_req.doc_batches.len(), 2);
+
+        let doc_batch = &ingest_req.doc_batches[0];
+        assert_eq!(doc_batch.index_id, "index-1");
+        let docs: Vec<_> = iter_doc_payloads(doc_batch).collect();
+        assert_eq!(
+            docs,
+            [&br#"{"body": "doc-1"}"#[..], &br#"{"body": "doc-3"}"#[..]]
+        );
+
+        let doc_batch = &ingest_req.doc_batches[1];
+        assert_eq!(doc_batch.index_id, "index-2");
+        let docs: Vec<_> = iter_doc_payloads(doc_batch).collect();
+        assert_eq!(docs, [&br#"{"body": "doc-2"}"#[..]]);
+    }
+
+    #[test]
+    fn test_parse_bulk_payload_invalid() {
+        let payload = r#"{ "delete" : { "_index" : "index-1" } }"#;
+        assert!(matches!(
+            parse_bulk_payload(payload.as_bytes()).unwrap_err(),
+            IngestApiError::InvalidBulkPayload { .. }
+        ));
+        let payload = r#"{ "create" : { "_index" : "index-1" } }"#;
+        assert!(matches!(
+            parse_bulk_payload(payload.as_bytes()).unwrap_err(),
+            IngestApiError::InvalidBulkPayload { .. }
+        ));
+        let payload = "{ \"create\" : { \"_index\" : \"index-1\" } }\n{\"body\": ";
+        assert!(matches!(
+            parse_bulk_payload(payload.as_bytes()).unwrap_err(),
+            IngestApiError::InvalidBulkPayload { .. }
+        ));
+        assert!(parse_bulk_payload(&[0xff, 0xfe]).is_err());
+    }
+}
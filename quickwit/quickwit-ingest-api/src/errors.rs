@@ -31,6 +31,10 @@ pub enum IngestApiError {
     IndexAlreadyExists { index_id: String },
     #[error("Ingest API service is down")]
     IngestAPIServiceDown,
+    #[error("Disk space is low, retry later.")]
+    DiskSpaceLow,
+    #[error("Invalid bulk payload: {msg}")]
+    InvalidBulkPayload { msg: String },
 }
 
 impl ServiceError for IngestApiError {
@@ -40,6 +44,8 @@ impl ServiceError for IngestApiError {
             IngestApiError::IndexDoesNotExist { .. } => ServiceErrorCode::NotFound,
             IngestApiError::IndexAlreadyExists { .. } => ServiceErrorCode::BadRequest,
             IngestApiError::IngestAPIServiceDown => ServiceErrorCode::Internal,
+            IngestApiError::DiskSpaceLow => ServiceErrorCode::Unavailable,
+            IngestApiError::InvalidBulkPayload { .. } => ServiceErrorCode::BadRequest,
         }
     }
 }
@@ -71,6 +77,8 @@ impl From<IngestApiError> for tonic::Status {
             IngestApiError::IndexDoesNotExist { .. } => tonic::Code::NotFound,
             IngestApiError::IndexAlreadyExists { .. } => tonic::Code::AlreadyExists,
             IngestApiError::IngestAPIServiceDown => tonic::Code::Internal,
+            IngestApiError::DiskSpaceLow => tonic::Code::Unavailable,
+            IngestApiError::InvalidBulkPayload { .. } => tonic::Code::InvalidArgument,
         };
         let message = error.to_string();
         tonic::Status::new(code, message)
@@ -19,9 +19,11 @@
 
 use std::collections::HashSet;
 use std::path::Path;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, QueueCapacity};
+use quickwit_common::disk_space::is_disk_space_low;
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_proto::ingest_api::{
     CreateQueueIfNotExistsRequest, CreateQueueRequest, DropQueueRequest, FetchRequest,
@@ -30,12 +32,35 @@ use quickwit_proto::ingest_api::{
 };
 
 use crate::metrics::INGEST_METRICS;
-use crate::{iter_doc_payloads, IngestApiError, Position, Queues};
+use crate::{iter_doc_payloads, IngestApiError, Position, QueueLag, Queues};
 
 pub struct IngestApiService {
     queues: Queues,
 }
 
+/// Requests the lag of the consumer of a queue.
+#[derive(Debug)]
+pub struct GetQueueLagRequest {
+    pub queue_id: String,
+}
+
+/// Deletes the records of a queue up to and including `up_to_position_included`, or all of them
+/// if `None`, whether they have been indexed or not.
+#[derive(Debug)]
+pub struct TruncateQueueRequest {
+    pub queue_id: String,
+    pub up_to_position_included: Option<u64>,
+}
+
+/// Sets the retention period of the indexed records of a queue, or lets them be deleted as soon as
+/// they are indexed if `None`, then deletes the indexed records older than the retention period.
+/// Replies with the number of deleted records.
+#[derive(Debug)]
+pub struct ApplyQueueRetentionRequest {
+    pub queue_id: String,
+    pub retention_period: Option<Duration>,
+}
+
 impl IngestApiService {
     pub fn with_queues_dir(queues_dir_path: &Path) -> crate::Result<Self> {
         let queues = Queues::open(queues_dir_path)?;
@@ -43,6 +68,9 @@ impl IngestApiService {
     }
 
     async fn ingest(&mut self, request: IngestRequest) -> crate::Result<IngestResponse> {
+        if is_disk_space_low() {
+            return Err(IngestApiError::DiskSpaceLow);
+        }
         // Check all indexes exist assuming existing queues always have a corresponding index.
         let first_non_existing_queue_opt = request
             .doc_batches
@@ -217,3 +245,48 @@ impl Handler<ListQueuesRequest> for IngestApiService {
         Ok(self.queues.list_queues())
     }
 }
+
+#[async_trait]
+impl Handler<GetQueueLagRequest> for IngestApiService {
+    type Reply = crate::Result<QueueLag>;
+    async fn handle(
+        &mut self,
+        request: GetQueueLagRequest,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.queues.queue_lag(&request.queue_id))
+    }
+}
+
+#[async_trait]
+impl Handler<TruncateQueueRequest> for IngestApiService {
+    type Reply = crate::Result<QueueLag>;
+    async fn handle(
+        &mut self,
+        request: TruncateQueueRequest,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        let up_to_position_included_opt = request.up_to_position_included.map(Position::from);
+        Ok(self
+            .queues
+            .truncate_queue(&request.queue_id, up_to_position_included_opt))
+    }
+}
+
+#[async_trait]
+impl Handler<ApplyQueueRetentionRequest> for IngestApiService {
+    type Reply = crate::Result<u64>;
+    async fn handle(
+        &mut self,
+        request: ApplyQueueRetentionRequest,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        if let Err(error) = self
+            .queues
+            .set_retention_period(&request.queue_id, request.retention_period)
+        {
+            return Ok(Err(error));
+        }
+        Ok(self.queues.truncate_expired_records(&request.queue_id))
+    }
+}
@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod bulk;
 mod errors;
 mod ingest_api_service;
 mod metrics;
@@ -27,13 +28,17 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context};
+pub use bulk::{ndjson_lines, parse_bulk_payload};
+use bytes::{Bytes, BytesMut};
 pub use errors::IngestApiError;
 use errors::Result;
-pub use ingest_api_service::IngestApiService;
+pub use ingest_api_service::{
+    ApplyQueueRetentionRequest, GetQueueLagRequest, IngestApiService, TruncateQueueRequest,
+};
 use metrics::INGEST_METRICS;
 use once_cell::sync::OnceCell;
 pub use position::Position;
-pub use queue::Queues;
+pub use queue::{QueueLag, Queues};
 use quickwit_actors::{Mailbox, Universe};
 use quickwit_proto::ingest_api::DocBatch;
 use tokio::sync::Mutex;
@@ -95,14 +100,53 @@ pub async fn start_ingest_api_service(
     init_ingest_api(universe, &queues_dir_path).await
 }
 
-/// Adds a document raw bytes to a [`DocBatch`]
-pub fn add_doc(payload: &[u8], fetch_resp: &mut DocBatch) -> usize {
-    fetch_resp.concat_docs.extend_from_slice(payload);
-    fetch_resp.doc_lens.push(payload.len() as u64);
-    INGEST_METRICS
-        .ingested_num_bytes
-        .inc_by(payload.len() as u64);
-    payload.len()
+/// Builds a [`DocBatch`] by appending the documents to a single buffer, which is then shared
+/// without copies by the consumers of the batch.
+#[derive(Debug)]
+pub struct DocBatchBuilder {
+    index_id: String,
+    concat_docs: BytesMut,
+    doc_lens: Vec<u64>,
+}
+
+impl DocBatchBuilder {
+    /// Creates a new builder for a batch of documents targeting `index_id`.
+    pub fn new(index_id: String) -> Self {
+        Self::with_capacity(index_id, 0)
+    }
+
+    /// Creates a new builder with a buffer preallocated for `capacity` bytes of documents.
+    pub fn with_capacity(index_id: String, capacity: usize) -> Self {
+        Self {
+            index_id,
+            concat_docs: BytesMut::with_capacity(capacity),
+            doc_lens: Vec::new(),
+        }
+    }
+
+    /// Adds a document raw bytes to the batch and returns its size in bytes.
+    pub fn add_doc(&mut self, payload: &[u8]) -> usize {
+        self.concat_docs.extend_from_slice(payload);
+        self.doc_lens.push(payload.len() as u64);
+        INGEST_METRICS
+            .ingested_num_bytes
+            .inc_by(payload.len() as u64);
+        payload.len()
+    }
+
+    /// Returns the number of documents in the batch.
+    pub fn num_docs(&self) -> usize {
+        self.doc_lens.len()
+    }
+
+    /// Builds the [`DocBatch`].
+    pub fn build(self) -> DocBatch {
+        DocBatch {
+            index_id: self.index_id,
+            concat_docs: self.concat_docs.freeze(),
+            doc_lens: self.doc_lens,
+        }
+    }
 }
 
 /// Returns an iterator over the document payloads within a doc_batch.
@@ -119,6 +163,21 @@ pub fn iter_doc_payloads(doc_batch: &DocBatch) -> impl Iterator<Item = &[u8]> {
         })
 }
 
+/// Returns an iterator over the documents within a doc_batch. The documents share the buffer of
+/// the batch.
+pub fn iter_docs(doc_batch: &DocBatch) -> impl Iterator<Item = Bytes> + '_ {
+    doc_batch
+        .doc_lens
+        .iter()
+        .cloned()
+        .scan(0, |current_offset, doc_num_bytes| {
+            let start = *current_offset;
+            let end = start + doc_num_bytes as usize;
+            *current_offset = end;
+            Some(doc_batch.concat_docs.slice(start..end))
+        })
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -158,4 +217,25 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[test]
+    fn test_doc_batch_builder() {
+        let mut doc_batch_builder = DocBatchBuilder::new("test-index".to_string());
+        assert_eq!(doc_batch_builder.add_doc(b"doc-1"), 5);
+        assert_eq!(doc_batch_builder.add_doc(b""), 0);
+        assert_eq!(doc_batch_builder.add_doc(b"doc-3"), 5);
+        assert_eq!(doc_batch_builder.num_docs(), 3);
+
+        let doc_batch = doc_batch_builder.build();
+        assert_eq!(doc_batch.index_id, "test-index");
+        assert_eq!(doc_batch.doc_lens, [5, 0, 5]);
+
+        let doc_payloads: Vec<&[u8]> = iter_doc_payloads(&doc_batch).collect();
+        assert_eq!(doc_payloads, [&b"doc-1"[..], b"", b"doc-3"]);
+
+        let docs: Vec<Bytes> = iter_docs(&doc_batch).collect();
+        assert_eq!(docs, ["doc-1", "", "doc-3"]);
+        // The documents point into the buffer of the batch.
+        assert_eq!(docs[2].as_ptr(), doc_batch.concat_docs[5..].as_ptr());
+    }
 }
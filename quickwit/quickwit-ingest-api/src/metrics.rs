@@ -18,12 +18,18 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use once_cell::sync::Lazy;
-use quickwit_common::metrics::{new_counter, new_gauge, IntCounter, IntGauge};
+use quickwit_common::metrics::{
+    new_counter, new_gauge, new_gauge_vec, IntCounter, IntGauge, IntGaugeVec,
+};
 
 pub struct IngestMetrics {
     pub ingested_num_bytes: IntCounter,
     pub ingested_num_docs: IntCounter,
     pub queue_count: IntGauge,
+    pub queue_disk_num_bytes: IntGauge,
+    pub queue_logical_num_bytes: IntGauge,
+    pub queue_lag_num_records: IntGaugeVec,
+    pub queue_lag_num_bytes: IntGaugeVec,
 }
 
 impl Default for IngestMetrics {
@@ -44,6 +50,29 @@ impl Default for IngestMetrics {
                 "Number of queues currently active",
                 "quickwit_ingest",
             ),
+            queue_disk_num_bytes: new_gauge(
+                "queue_disk_num_bytes",
+                "Size of the files of the queues on disk in bytes, after compression",
+                "quickwit_ingest",
+            ),
+            queue_logical_num_bytes: new_gauge(
+                "queue_logical_num_bytes",
+                "Size of the records stored in the files of the queues in bytes, before \
+                 compression",
+                "quickwit_ingest",
+            ),
+            queue_lag_num_records: new_gauge_vec(
+                "queue_lag_num_records",
+                "Number of records written to a queue but not indexed yet",
+                "quickwit_ingest",
+                &["index"],
+            ),
+            queue_lag_num_bytes: new_gauge_vec(
+                "queue_lag_num_bytes",
+                "Size of the records written to a queue but not indexed yet in bytes",
+                "quickwit_ingest",
+                &["index"],
+            ),
         }
     }
 }
@@ -17,22 +17,158 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
+use std::time::{Duration, Instant};
 
-use quickwit_proto::ingest_api::{DocBatch, FetchResponse, ListQueuesResponse};
-use rocksdb::{Direction, IteratorMode, WriteBatch, WriteOptions, DB};
+use quickwit_proto::ingest_api::{FetchResponse, ListQueuesResponse};
+use rocksdb::{
+    ColumnFamilyDescriptor, DBCompressionType, Direction, IteratorMode, WriteBatch, WriteOptions,
+    DB,
+};
+use serde::Serialize;
 use tracing::warn;
 
-use crate::{add_doc, Position};
+use crate::metrics::INGEST_METRICS;
+use crate::{DocBatchBuilder, Position};
 
 const FETCH_PAYLOAD_LIMIT: usize = 2_000_000; // 2MB
 
 const QUICKWIT_CF_PREFIX: &str = ".queue_";
 
+/// Writes to a queue with a retention period that occur within this interval of each other share
+/// the same entry of the write log.
+const WRITE_LOG_RESOLUTION: Duration = Duration::from_secs(1);
+
+/// Minimum interval between two updates of the queue size gauges, which query the properties of
+/// every column family.
+const SIZE_METRICS_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+
 pub struct Queues {
     db: DB,
     last_position_per_queue: HashMap<String, Option<Position>>,
+    consumer_state_per_queue: HashMap<String, ConsumerState>,
+    retention_per_queue: HashMap<String, QueueRetention>,
+    size_metrics_updated_at: Instant,
+}
+
+/// Tracks how far the records of a queue have been indexed.
+#[derive(Clone, Copy, Debug, Default)]
+struct ConsumerState {
+    /// Position of the last record reported as indexed via [`Queues::suggest_truncate`].
+    indexed_position_opt: Option<Position>,
+    /// Total size of the records written after `indexed_position_opt`.
+    pending_num_bytes: u64,
+}
+
+impl ConsumerState {
+    /// Serializes the consumer state as the pending number of bytes, followed by the indexed
+    /// position if any.
+    fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = self.pending_num_bytes.to_be_bytes().to_vec();
+        if let Some(indexed_position) = self.indexed_position_opt {
+            bytes.extend_from_slice(indexed_position.as_ref());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> crate::Result<ConsumerState> {
+        if bytes.len() != 8 && bytes.len() != 16 {
+            return Err(crate::IngestApiError::Corruption {
+                msg: format!("Invalid consumer state of {} bytes.", bytes.len()),
+            });
+        }
+        let (pending_num_bytes_bytes, indexed_position_bytes) = bytes.split_at(8);
+        let mut pending_num_bytes = [0u8; 8];
+        pending_num_bytes.copy_from_slice(pending_num_bytes_bytes);
+        let indexed_position_opt = if indexed_position_bytes.is_empty() {
+            None
+        } else {
+            Some(Position::try_from(indexed_position_bytes)?)
+        };
+        Ok(ConsumerState {
+            indexed_position_opt,
+            pending_num_bytes: u64::from_be_bytes(pending_num_bytes),
+        })
+    }
+}
+
+/// Retention of the indexed records of a queue: they are kept for `period` after being written so
+/// that they can be replayed, instead of being deleted as soon as they are indexed.
+///
+/// The write times of the records are tracked in memory only: after a restart, the records left in
+/// the queue are considered as written when the retention is set, so they are kept longer, never
+/// shorter, than the retention period.
+#[derive(Debug)]
+struct QueueRetention {
+    period: Duration,
+    /// Last position written to the queue at different points in time, in chronological order.
+    write_log: VecDeque<(Instant, Position)>,
+    /// Position of the last record older than the retention period.
+    expired_position_opt: Option<Position>,
+}
+
+impl QueueRetention {
+    fn new(period: Duration, last_position_opt: Option<Position>, now: Instant) -> Self {
+        let write_log = last_position_opt
+            .map(|last_position| (now, last_position))
+            .into_iter()
+            .collect();
+        Self {
+            period,
+            write_log,
+            expired_position_opt: None,
+        }
+    }
+
+    fn record_write(&mut self, last_position: Position, now: Instant) {
+        if let Some((write_instant, write_position)) = self.write_log.back_mut() {
+            if now.saturating_duration_since(*write_instant) < WRITE_LOG_RESOLUTION {
+                *write_position = last_position;
+                return;
+            }
+        }
+        self.write_log.push_back((now, last_position));
+    }
+
+    /// Returns the position of the last record written more than `period` ago, if any.
+    fn expired_position(&mut self, now: Instant) -> Option<Position> {
+        while let Some((write_instant, write_position)) = self.write_log.front().copied() {
+            if now.saturating_duration_since(write_instant) < self.period {
+                break;
+            }
+            self.expired_position_opt = Some(write_position);
+            self.write_log.pop_front();
+        }
+        self.expired_position_opt
+    }
+}
+
+/// Lag of the consumer of a queue: the records between the write head of the queue and the
+/// position up to which the records have been indexed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct QueueLag {
+    pub queue_id: String,
+    /// Position of the last record written to the queue.
+    pub write_position: Option<u64>,
+    /// Position of the last record indexed.
+    pub indexed_position: Option<u64>,
+    /// Number of records written but not indexed yet.
+    pub num_records: u64,
+    /// Size in bytes of the records written but not indexed yet.
+    pub num_bytes: u64,
+}
+
+/// Key of the default column family persisting the last position of a queue once all its records
+/// have been deleted by [`Queues::truncate_queue`].
+fn last_position_key(real_queue_id: &str) -> String {
+    format!("last_position{real_queue_id}")
+}
+
+/// Key of the default column family persisting the [`ConsumerState`] of a queue, so that it does
+/// not have to be rebuilt by scanning the queue when it is reopened.
+fn consumer_state_key(real_queue_id: &str) -> String {
+    format!("consumer_state{real_queue_id}")
 }
 
 fn default_rocks_db_options() -> rocksdb::Options {
@@ -41,9 +177,23 @@ fn default_rocks_db_options() -> rocksdb::Options {
     options.create_if_missing(true);
     options.set_max_open_files(512);
     options.set_max_total_wal_size(100_000_000);
+    // Records are compressed block by block when flushed into SST files. The records of a batch
+    // are stored next to each other, so they are compressed together.
+    options.set_compression_type(DBCompressionType::Zstd);
+    options.set_bottommost_compression_type(DBCompressionType::Zstd);
     options
 }
 
+/// Reads an integer property, such as `raw value size`, from the string representation of the
+/// aggregated table properties of a column family (`key1=value1; key2=value2; ...`).
+fn parse_table_property(table_properties: &str, property_name: &str) -> Option<u64> {
+    table_properties
+        .split(';')
+        .filter_map(|key_value| key_value.split_once('='))
+        .find(|(key, _)| key.trim() == property_name)
+        .and_then(|(_, value)| value.trim().parse().ok())
+}
+
 fn default_rocks_db_write_options() -> rocksdb::WriteOptions {
     let mut write_options = WriteOptions::default();
     write_options.set_sync(false);
@@ -65,8 +215,52 @@ fn next_position(db: &DB, queue_id: &str) -> crate::Result<Option<Position>> {
             Ok(Some(position))
         }
         Some(Err(error)) => Err(error.into()),
-        None => Ok(None),
+        None => {
+            // The queue may have been entirely truncated.
+            if let Some(position_bytes) = db.get(last_position_key(queue_id))? {
+                let position = Position::try_from(&*position_bytes)?;
+                return Ok(Some(position));
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Reads the persisted consumer state of a queue, or rebuilds it from the records the queue still
+/// holds if it was created before the consumer state was persisted.
+///
+/// Indexed records are truncated lazily, so the rebuilt indexed position may lag behind the
+/// actual one by a few records until the next truncation.
+fn consumer_state(
+    db: &DB,
+    queue_id: &str,
+    last_position_opt: Option<Position>,
+) -> crate::Result<ConsumerState> {
+    if let Some(consumer_state_bytes) = db.get(consumer_state_key(queue_id))? {
+        return ConsumerState::from_bytes(&consumer_state_bytes);
+    }
+    let cf = db
+        .cf_handle(queue_id)
+        .ok_or_else(|| crate::IngestApiError::Corruption {
+            msg: format!("RocksDB error: Missing column `{queue_id}`"),
+        })?;
+    let mut first_position_opt: Option<Position> = None;
+    let mut pending_num_bytes = 0;
+    for kv_res in db.full_iterator_cf(&cf, IteratorMode::Start) {
+        let (key, payload) = kv_res?;
+        if first_position_opt.is_none() {
+            first_position_opt = Some(Position::try_from(&*key)?);
+        }
+        pending_num_bytes += payload.len() as u64;
     }
+    let indexed_position_opt = match first_position_opt {
+        Some(first_position) => u64::from(first_position).checked_sub(1).map(Position::from),
+        None => last_position_opt,
+    };
+    Ok(ConsumerState {
+        indexed_position_opt,
+        pending_num_bytes,
+    })
 }
 
 impl Queues {
@@ -77,16 +271,35 @@ impl Queues {
         } else {
             Vec::new()
         };
-        let db = DB::open_cf(&options, queues_dir_path, &queue_ids)?;
+        // Queues created before compression was enabled are compressed as their files get
+        // compacted.
+        let cf_descriptors = queue_ids
+            .iter()
+            .map(|queue_id| ColumnFamilyDescriptor::new(queue_id, default_rocks_db_options()));
+        let db = DB::open_cf_descriptors(&options, queues_dir_path, cf_descriptors)?;
         let mut next_position_per_queue = HashMap::default();
+        let mut consumer_state_per_queue = HashMap::default();
         for queue_id in queue_ids {
+            if !queue_id.starts_with(QUICKWIT_CF_PREFIX) {
+                continue;
+            }
             let next_position = next_position(&db, &queue_id)?;
-            next_position_per_queue.insert(queue_id, next_position);
+            let consumer_state = consumer_state(&db, &queue_id, next_position)?;
+            next_position_per_queue.insert(queue_id.clone(), next_position);
+            consumer_state_per_queue.insert(queue_id, consumer_state);
         }
-        Ok(Queues {
+        let queues = Queues {
             db,
             last_position_per_queue: next_position_per_queue,
-        })
+            consumer_state_per_queue,
+            retention_per_queue: HashMap::default(),
+            size_metrics_updated_at: Instant::now(),
+        };
+        for real_queue_id in queues.last_position_per_queue.keys() {
+            queues.update_lag_metrics(real_queue_id);
+        }
+        queues.update_size_metrics();
+        Ok(queues)
     }
 
     pub fn queue_exists(&self, queue_id: &str) -> bool {
@@ -103,14 +316,31 @@ impl Queues {
         let real_queue_id = format!("{}{}", QUICKWIT_CF_PREFIX, queue_id);
         let cf_opts = default_rocks_db_options();
         self.db.create_cf(&real_queue_id, &cf_opts)?;
-        self.last_position_per_queue.insert(real_queue_id, None);
+        self.db.put(
+            consumer_state_key(&real_queue_id),
+            ConsumerState::default().to_bytes(),
+        )?;
+        self.last_position_per_queue
+            .insert(real_queue_id.clone(), None);
+        self.consumer_state_per_queue
+            .insert(real_queue_id, ConsumerState::default());
         Ok(())
     }
 
     pub fn drop_queue(&mut self, queue_id: &str) -> crate::Result<()> {
         let real_queue_id = format!("{}{}", QUICKWIT_CF_PREFIX, queue_id);
         self.db.drop_cf(&real_queue_id)?;
+        self.db.delete(last_position_key(&real_queue_id))?;
+        self.db.delete(consumer_state_key(&real_queue_id))?;
         self.last_position_per_queue.remove(&real_queue_id);
+        self.consumer_state_per_queue.remove(&real_queue_id);
+        self.retention_per_queue.remove(&real_queue_id);
+        let _ = INGEST_METRICS
+            .queue_lag_num_records
+            .remove_label_values(&[queue_id]);
+        let _ = INGEST_METRICS
+            .queue_lag_num_bytes
+            .remove_label_values(&[queue_id]);
         Ok(())
     }
 
@@ -127,14 +357,15 @@ impl Queues {
     /// In other words, truncating from a position, and fetching records starting
     /// earlier than this position can yield undefined result:
     /// the truncated records may or may not be returned.
+    ///
+    /// If the queue has a retention period, only the records older than the retention period are
+    /// removed.
     pub fn suggest_truncate(
         &mut self,
         queue_id: &str,
         up_to_offset_included: Position,
     ) -> crate::Result<()> {
         let real_queue_id = format!("{}{}", QUICKWIT_CF_PREFIX, queue_id);
-        let cf_ref = self.db.cf_handle(&real_queue_id).unwrap(); // FIXME
-                                                                 // We want to keep the last record.
         let last_position_opt = *self
             .last_position_per_queue
             .get(&real_queue_id)
@@ -149,21 +380,331 @@ impl Queues {
             return Ok(());
         };
 
-        // We make sure that we keep one record, in order to ensure we do not reset
-        // the position counter.
+        self.advance_indexed_position(&real_queue_id, up_to_offset_included.min(last_position))?;
+        self.update_lag_metrics(&real_queue_id);
+
+        let up_to_offset_included = match self.retention_per_queue.get_mut(&real_queue_id) {
+            Some(retention) => match retention.expired_position(Instant::now()) {
+                Some(expired_position) => up_to_offset_included.min(expired_position),
+                None => return Ok(()),
+            },
+            None => up_to_offset_included,
+        };
+        self.delete_records(&real_queue_id, up_to_offset_included, last_position)?;
+        Ok(())
+    }
+
+    /// Deletes the records of the queue up to and including `up_to_offset_included`, and returns
+    /// the number of deleted records.
+    ///
+    /// We make sure that we keep one record, in order to ensure we do not reset the position
+    /// counter.
+    fn delete_records(
+        &mut self,
+        real_queue_id: &str,
+        up_to_offset_included: Position,
+        last_position: Position,
+    ) -> crate::Result<u64> {
         let truncation_end_offset = if last_position > up_to_offset_included.inc() {
             up_to_offset_included.inc()
         } else {
             last_position
         };
-
+        let cf_ref = if let Some(cf_ref) = self.db.cf_handle(real_queue_id) {
+            cf_ref
+        } else {
+            return Ok(0);
+        };
+        let first_position_opt = match self
+            .db
+            .full_iterator_cf(&cf_ref, IteratorMode::Start)
+            .next()
+        {
+            Some(Ok((key, _))) => Some(Position::try_from(&*key)?),
+            Some(Err(error)) => return Err(error.into()),
+            None => None,
+        };
+        let num_deleted_records = first_position_opt
+            .map(|first_position| {
+                u64::from(truncation_end_offset).saturating_sub(u64::from(first_position))
+            })
+            .unwrap_or(0);
         self.db
             .delete_file_in_range_cf(&cf_ref, Position::default(), truncation_end_offset)?;
         self.db
             .delete_range_cf(&cf_ref, Position::default(), truncation_end_offset)?;
+        self.maybe_update_size_metrics(Instant::now());
+        Ok(num_deleted_records)
+    }
+
+    /// Sets the retention period of the indexed records of the queue. The indexed records are
+    /// deleted as soon as possible if `None`.
+    pub fn set_retention_period(
+        &mut self,
+        queue_id: &str,
+        retention_period_opt: Option<Duration>,
+    ) -> crate::Result<()> {
+        let real_queue_id = format!("{}{}", QUICKWIT_CF_PREFIX, queue_id);
+        let last_position_opt = *self
+            .last_position_per_queue
+            .get(&real_queue_id)
+            .ok_or_else(|| crate::IngestApiError::IndexDoesNotExist {
+                index_id: queue_id.to_string(),
+            })?;
+        match retention_period_opt {
+            Some(retention_period) => {
+                self.retention_per_queue
+                    .entry(real_queue_id)
+                    .and_modify(|retention| retention.period = retention_period)
+                    .or_insert_with(|| {
+                        QueueRetention::new(retention_period, last_position_opt, Instant::now())
+                    });
+            }
+            None => {
+                self.retention_per_queue.remove(&real_queue_id);
+            }
+        }
         Ok(())
     }
 
+    /// Deletes the indexed records of the queue that are older than its retention period, and
+    /// returns the number of deleted records. This is a no-op for a queue without retention
+    /// period, since its records are deleted as soon as they are indexed.
+    pub fn truncate_expired_records(&mut self, queue_id: &str) -> crate::Result<u64> {
+        self.truncate_expired_records_at(queue_id, Instant::now())
+    }
+
+    fn truncate_expired_records_at(&mut self, queue_id: &str, now: Instant) -> crate::Result<u64> {
+        let real_queue_id = format!("{}{}", QUICKWIT_CF_PREFIX, queue_id);
+        let last_position_opt = *self
+            .last_position_per_queue
+            .get(&real_queue_id)
+            .ok_or_else(|| crate::IngestApiError::IndexDoesNotExist {
+                index_id: queue_id.to_string(),
+            })?;
+        let last_position = if let Some(last_position) = last_position_opt {
+            last_position
+        } else {
+            return Ok(0);
+        };
+        let indexed_position = if let Some(indexed_position) = self
+            .consumer_state_per_queue
+            .get(&real_queue_id)
+            .and_then(|consumer_state| consumer_state.indexed_position_opt)
+        {
+            indexed_position
+        } else {
+            return Ok(0);
+        };
+        let expired_position = if let Some(expired_position) = self
+            .retention_per_queue
+            .get_mut(&real_queue_id)
+            .and_then(|retention| retention.expired_position(now))
+        {
+            expired_position
+        } else {
+            return Ok(0);
+        };
+        self.delete_records(
+            &real_queue_id,
+            indexed_position.min(expired_position),
+            last_position,
+        )
+    }
+
+    /// Truncates the queue up to and including `up_to_position_included`, or entirely if `None`.
+    ///
+    /// Unlike [`Queues::suggest_truncate`], the records are deleted whether they have been
+    /// indexed or not: this allows operators to drop the backlog of a queue after an incident.
+    /// The position counter of the queue is preserved.
+    pub fn truncate_queue(
+        &mut self,
+        queue_id: &str,
+        up_to_position_included_opt: Option<Position>,
+    ) -> crate::Result<QueueLag> {
+        let real_queue_id = format!("{}{}", QUICKWIT_CF_PREFIX, queue_id);
+        let last_position_opt = *self
+            .last_position_per_queue
+            .get(&real_queue_id)
+            .ok_or_else(|| crate::IngestApiError::IndexDoesNotExist {
+                index_id: queue_id.to_string(),
+            })?;
+        let last_position = if let Some(last_position) = last_position_opt {
+            last_position
+        } else {
+            return self.queue_lag(queue_id);
+        };
+        let truncation_position = up_to_position_included_opt
+            .map(|up_to_position_included| up_to_position_included.min(last_position))
+            .unwrap_or(last_position);
+        self.advance_indexed_position(&real_queue_id, truncation_position)?;
+
+        let cf_ref = self.db.cf_handle(&real_queue_id).ok_or_else(|| {
+            crate::IngestApiError::IndexDoesNotExist {
+                index_id: queue_id.to_string(),
+            }
+        })?;
+        let mut batch = WriteBatch::default();
+        if truncation_position == last_position {
+            // The queue is about to be emptied: its last position is persisted so that the
+            // position counter is not reset when the queue is reopened.
+            batch.put(last_position_key(&real_queue_id), last_position.as_ref());
+        }
+        batch.delete_range_cf(&cf_ref, Position::default(), truncation_position.inc());
+        let write_options = default_rocks_db_write_options();
+        self.db.write_opt(batch, &write_options)?;
+
+        self.update_lag_metrics(&real_queue_id);
+        self.update_size_metrics();
+        self.queue_lag(queue_id)
+    }
+
+    /// Returns the lag of the consumer of the queue.
+    pub fn queue_lag(&self, queue_id: &str) -> crate::Result<QueueLag> {
+        let real_queue_id = format!("{}{}", QUICKWIT_CF_PREFIX, queue_id);
+        self.real_queue_lag(&real_queue_id).ok_or_else(|| {
+            crate::IngestApiError::IndexDoesNotExist {
+                index_id: queue_id.to_string(),
+            }
+        })
+    }
+
+    fn real_queue_lag(&self, real_queue_id: &str) -> Option<QueueLag> {
+        let last_position_opt = *self.last_position_per_queue.get(real_queue_id)?;
+        let consumer_state = self
+            .consumer_state_per_queue
+            .get(real_queue_id)
+            .copied()
+            .unwrap_or_default();
+        let write_position: Option<u64> = last_position_opt.map(u64::from);
+        let indexed_position: Option<u64> = consumer_state.indexed_position_opt.map(u64::from);
+        let num_records = match (write_position, indexed_position) {
+            (Some(write_position), Some(indexed_position)) => {
+                write_position.saturating_sub(indexed_position)
+            }
+            (Some(write_position), None) => write_position + 1,
+            (None, _) => 0,
+        };
+        Some(QueueLag {
+            queue_id: real_queue_id
+                .strip_prefix(QUICKWIT_CF_PREFIX)
+                .unwrap_or(real_queue_id)
+                .to_string(),
+            write_position,
+            indexed_position,
+            num_records,
+            num_bytes: consumer_state.pending_num_bytes,
+        })
+    }
+
+    /// Moves the indexed position of the queue forward to `up_to_position_included`, and
+    /// subtracts the size of the records in between from the pending bytes. This must be called
+    /// before these records are deleted.
+    fn advance_indexed_position(
+        &mut self,
+        real_queue_id: &str,
+        up_to_position_included: Position,
+    ) -> crate::Result<()> {
+        let consumer_state = self
+            .consumer_state_per_queue
+            .entry(real_queue_id.to_string())
+            .or_default();
+        let start_position = match consumer_state.indexed_position_opt {
+            Some(indexed_position) if indexed_position >= up_to_position_included => {
+                return Ok(());
+            }
+            Some(indexed_position) => indexed_position.inc(),
+            None => Position::default(),
+        };
+        let cf_ref = if let Some(cf_ref) = self.db.cf_handle(real_queue_id) {
+            cf_ref
+        } else {
+            return Ok(());
+        };
+        let mut indexed_num_bytes = 0;
+        let full_it = self.db.full_iterator_cf(
+            &cf_ref,
+            IteratorMode::From(start_position.as_ref(), Direction::Forward),
+        );
+        for kv_res in full_it {
+            let (key, payload) = kv_res?;
+            if Position::try_from(&*key)? > up_to_position_included {
+                break;
+            }
+            indexed_num_bytes += payload.len() as u64;
+        }
+        consumer_state.indexed_position_opt = Some(up_to_position_included);
+        consumer_state.pending_num_bytes = consumer_state
+            .pending_num_bytes
+            .saturating_sub(indexed_num_bytes);
+        self.db.put_opt(
+            consumer_state_key(real_queue_id),
+            consumer_state.to_bytes(),
+            &default_rocks_db_write_options(),
+        )?;
+        Ok(())
+    }
+
+    /// Updates the gauges reporting the lag of the consumer of the queue.
+    fn update_lag_metrics(&self, real_queue_id: &str) {
+        if let Some(queue_lag) = self.real_queue_lag(real_queue_id) {
+            INGEST_METRICS
+                .queue_lag_num_records
+                .with_label_values(&[&queue_lag.queue_id])
+                .set(queue_lag.num_records as i64);
+            INGEST_METRICS
+                .queue_lag_num_bytes
+                .with_label_values(&[&queue_lag.queue_id])
+                .set(queue_lag.num_bytes as i64);
+        }
+    }
+
+    /// Updates the size gauges if they have not been updated for
+    /// [`SIZE_METRICS_UPDATE_INTERVAL`].
+    fn maybe_update_size_metrics(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.size_metrics_updated_at)
+            < SIZE_METRICS_UPDATE_INTERVAL
+        {
+            return;
+        }
+        self.size_metrics_updated_at = now;
+        self.update_size_metrics();
+    }
+
+    /// Updates the gauges reporting the size of the queues on disk and the size of the records
+    /// they hold, which measures the compression ratio of the queues.
+    fn update_size_metrics(&self) {
+        let mut disk_num_bytes = 0;
+        let mut logical_num_bytes = 0;
+        for real_queue_id in self.last_position_per_queue.keys() {
+            let cf_ref = if let Some(cf_ref) = self.db.cf_handle(real_queue_id) {
+                cf_ref
+            } else {
+                continue;
+            };
+            if let Ok(Some(sst_files_size)) = self
+                .db
+                .property_int_value_cf(&cf_ref, "rocksdb.total-sst-files-size")
+            {
+                disk_num_bytes += sst_files_size;
+            }
+            if let Ok(Some(table_properties)) = self
+                .db
+                .property_value_cf(&cf_ref, "rocksdb.aggregated-table-properties")
+            {
+                logical_num_bytes += parse_table_property(&table_properties, "raw key size")
+                    .unwrap_or_default()
+                    + parse_table_property(&table_properties, "raw value size").unwrap_or_default();
+            }
+        }
+        INGEST_METRICS
+            .queue_disk_num_bytes
+            .set(disk_num_bytes as i64);
+        INGEST_METRICS
+            .queue_logical_num_bytes
+            .set(logical_num_bytes as i64);
+    }
+
     // Append a single record to a target queue.
     #[cfg(test)]
     fn append(&mut self, queue_id: &str, record: &[u8]) -> crate::Result<()> {
@@ -198,15 +739,37 @@ impl Queues {
             .ok_or_else(column_does_not_exist)?;
 
         let mut batch = WriteBatch::default();
+        let mut num_bytes = 0;
         for record in records_it {
             batch.put_cf(&cf_ref, next_position.as_ref(), record);
             *last_position_opt = Some(next_position);
             next_position = next_position.inc();
+            num_bytes += record.len() as u64;
         }
+        let consumer_state = self
+            .consumer_state_per_queue
+            .entry(real_queue_id.clone())
+            .or_default();
+        let mut new_consumer_state = *consumer_state;
+        new_consumer_state.pending_num_bytes += num_bytes;
+        // The consumer state is persisted along with the records it accounts for.
+        batch.put(
+            consumer_state_key(&real_queue_id),
+            new_consumer_state.to_bytes(),
+        );
 
         let write_options = default_rocks_db_write_options();
         self.db.write_opt(batch, &write_options)?;
 
+        *consumer_state = new_consumer_state;
+        if let (Some(retention), Some(last_position)) = (
+            self.retention_per_queue.get_mut(&real_queue_id),
+            *last_position_opt,
+        ) {
+            retention.record_write(last_position, Instant::now());
+        }
+        self.update_lag_metrics(&real_queue_id);
+        self.maybe_update_size_metrics(Instant::now());
         Ok(())
     }
 
@@ -233,7 +796,7 @@ impl Queues {
             &cf,
             IteratorMode::From(start_position.as_ref(), Direction::Forward),
         );
-        let mut doc_batch = DocBatch::default();
+        let mut doc_batch_builder = DocBatchBuilder::new(String::new());
         let mut num_bytes = 0;
         let mut first_key_opt: Option<u64> = None;
         let size_limit = num_bytes_limit.unwrap_or(FETCH_PAYLOAD_LIMIT);
@@ -243,14 +806,14 @@ impl Queues {
             if first_key_opt.is_none() {
                 first_key_opt = Some(position.into());
             }
-            num_bytes += add_doc(&*payload, &mut doc_batch);
+            num_bytes += doc_batch_builder.add_doc(&*payload);
             if num_bytes > size_limit {
                 break;
             }
         }
         Ok(FetchResponse {
             first_position: first_key_opt,
-            doc_batch: Some(doc_batch),
+            doc_batch: Some(doc_batch_builder.build()),
         })
     }
 
@@ -263,7 +826,7 @@ impl Queues {
             }
         })?;
         let full_it = self.db.full_iterator_cf(&cf, IteratorMode::End);
-        let mut doc_batch = DocBatch::default();
+        let mut doc_batch_builder = DocBatchBuilder::new(String::new());
         let mut num_bytes = 0;
         let mut first_key_opt: Option<u64> = None;
         for kp_res in full_it {
@@ -272,14 +835,14 @@ impl Queues {
             if first_key_opt.is_none() {
                 first_key_opt = Some(position.into());
             }
-            num_bytes += add_doc(&*payload, &mut doc_batch);
+            num_bytes += doc_batch_builder.add_doc(&*payload);
             if num_bytes > FETCH_PAYLOAD_LIMIT {
                 break;
             }
         }
         Ok(FetchResponse {
             first_position: first_key_opt,
-            doc_batch: Some(doc_batch),
+            doc_batch: Some(doc_batch_builder.build()),
         })
     }
 
@@ -299,8 +862,9 @@ impl Queues {
 mod tests {
     use std::collections::HashSet;
     use std::ops::{Deref, DerefMut};
+    use std::time::{Duration, Instant};
 
-    use super::Queues;
+    use super::{parse_table_property, QueueLag, Queues};
     use crate::errors::IngestApiError;
     use crate::iter_doc_payloads;
     use crate::queue::Position;
@@ -397,6 +961,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_table_property() {
+        let table_properties = "# data blocks=3; # entries=1000; raw key size=8000; raw average \
+                                key size=8.000000; raw value size=120000; data block size=25000;";
+        assert_eq!(
+            parse_table_property(table_properties, "raw key size"),
+            Some(8_000)
+        );
+        assert_eq!(
+            parse_table_property(table_properties, "raw value size"),
+            Some(120_000)
+        );
+        assert_eq!(
+            parse_table_property(table_properties, "raw average key size"),
+            None
+        );
+        assert_eq!(parse_table_property(table_properties, "filter size"), None);
+    }
+
     #[test]
     fn test_simple() {
         let mut queues = QueuesForTest::default();
@@ -495,6 +1078,182 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_queue_lag() {
+        let mut queues = QueuesForTest::default();
+        queues.create_queue(TEST_QUEUE_ID).unwrap();
+        assert_eq!(
+            queues.queue_lag(TEST_QUEUE_ID).unwrap(),
+            QueueLag {
+                queue_id: TEST_QUEUE_ID.to_string(),
+                write_position: None,
+                indexed_position: None,
+                num_records: 0,
+                num_bytes: 0,
+            }
+        );
+        queues.append(TEST_QUEUE_ID, b"hello").unwrap();
+        queues.append(TEST_QUEUE_ID, b"happy").unwrap();
+        queues.append(TEST_QUEUE_ID, b"tax").unwrap();
+        let queue_lag = queues.queue_lag(TEST_QUEUE_ID).unwrap();
+        assert_eq!(queue_lag.write_position, Some(2));
+        assert_eq!(queue_lag.indexed_position, None);
+        assert_eq!(queue_lag.num_records, 3);
+        assert_eq!(queue_lag.num_bytes, 13);
+
+        queues
+            .suggest_truncate(TEST_QUEUE_ID, Position::from(0))
+            .unwrap();
+        let queue_lag = queues.queue_lag(TEST_QUEUE_ID).unwrap();
+        assert_eq!(queue_lag.indexed_position, Some(0));
+        assert_eq!(queue_lag.num_records, 2);
+        assert_eq!(queue_lag.num_bytes, 8);
+
+        queues.reload();
+        let queue_lag = queues.queue_lag(TEST_QUEUE_ID).unwrap();
+        assert_eq!(queue_lag.write_position, Some(2));
+        assert_eq!(queue_lag.indexed_position, Some(0));
+        assert_eq!(queue_lag.num_records, 2);
+        assert_eq!(queue_lag.num_bytes, 8);
+
+        let queue_err = queues.queue_lag(TEST_QUEUE_ID2).unwrap_err();
+        assert!(matches!(
+            queue_err,
+            IngestApiError::IndexDoesNotExist { .. }
+        ));
+    }
+
+    #[test]
+    fn test_truncate_queue() {
+        let mut queues = QueuesForTest::default();
+        queues.create_queue(TEST_QUEUE_ID).unwrap();
+        queues.append(TEST_QUEUE_ID, b"hello").unwrap();
+        queues.append(TEST_QUEUE_ID, b"happy").unwrap();
+        queues.append(TEST_QUEUE_ID, b"tax").unwrap();
+
+        let queue_lag = queues
+            .truncate_queue(TEST_QUEUE_ID, Some(Position::from(1)))
+            .unwrap();
+        assert_eq!(queue_lag.indexed_position, Some(1));
+        assert_eq!(queue_lag.num_records, 1);
+        assert_eq!(queue_lag.num_bytes, 3);
+        queues.fetch_test(TEST_QUEUE_ID, None, Some(2), &[&b"tax"[..]]);
+
+        let queue_lag = queues.truncate_queue(TEST_QUEUE_ID, None).unwrap();
+        assert_eq!(queue_lag.write_position, Some(2));
+        assert_eq!(queue_lag.indexed_position, Some(2));
+        assert_eq!(queue_lag.num_records, 0);
+        assert_eq!(queue_lag.num_bytes, 0);
+        queues.fetch_test(TEST_QUEUE_ID, None, None, &[]);
+
+        // The position counter survives the truncation of the entire queue.
+        queues.reload();
+        let queue_lag = queues.queue_lag(TEST_QUEUE_ID).unwrap();
+        assert_eq!(queue_lag.write_position, Some(2));
+        assert_eq!(queue_lag.num_records, 0);
+        queues.append(TEST_QUEUE_ID, b"fresh").unwrap();
+        queues.fetch_test(TEST_QUEUE_ID, None, Some(3), &[&b"fresh"[..]]);
+        assert_eq!(queues.queue_lag(TEST_QUEUE_ID).unwrap().num_records, 1);
+    }
+
+    #[test]
+    fn test_consumer_state_survives_reload() {
+        let mut queues = QueuesForTest::default();
+        queues.create_queue(TEST_QUEUE_ID).unwrap();
+        queues
+            .set_retention_period(TEST_QUEUE_ID, Some(Duration::from_secs(3600)))
+            .unwrap();
+        queues.append(TEST_QUEUE_ID, b"hello").unwrap();
+        queues.append(TEST_QUEUE_ID, b"happy").unwrap();
+        queues.append(TEST_QUEUE_ID, b"tax").unwrap();
+        queues
+            .suggest_truncate(TEST_QUEUE_ID, Position::from(1))
+            .unwrap();
+        // The indexed records are retained, yet the consumer state is restored as is.
+        queues.reload();
+        let queue_lag = queues.queue_lag(TEST_QUEUE_ID).unwrap();
+        assert_eq!(queue_lag.write_position, Some(2));
+        assert_eq!(queue_lag.indexed_position, Some(1));
+        assert_eq!(queue_lag.num_records, 1);
+        assert_eq!(queue_lag.num_bytes, 3);
+        queues.fetch_test(
+            TEST_QUEUE_ID,
+            None,
+            Some(0),
+            &[&b"hello"[..], &b"happy"[..], &b"tax"[..]],
+        );
+    }
+
+    #[test]
+    fn test_consumer_state_bytes() {
+        let consumer_state = ConsumerState::default();
+        let consumer_state_bytes = consumer_state.to_bytes();
+        assert_eq!(consumer_state_bytes.len(), 8);
+        let consumer_state = ConsumerState::from_bytes(&consumer_state_bytes).unwrap();
+        assert_eq!(consumer_state.indexed_position_opt, None);
+        assert_eq!(consumer_state.pending_num_bytes, 0);
+
+        let consumer_state = ConsumerState {
+            indexed_position_opt: Some(Position::from(42)),
+            pending_num_bytes: 1_000,
+        };
+        let consumer_state = ConsumerState::from_bytes(&consumer_state.to_bytes()).unwrap();
+        assert_eq!(
+            consumer_state.indexed_position_opt,
+            Some(Position::from(42))
+        );
+        assert_eq!(consumer_state.pending_num_bytes, 1_000);
+
+        ConsumerState::from_bytes(b"corrupted").unwrap_err();
+    }
+
+    #[test]
+    fn test_queue_retention() {
+        let mut queues = QueuesForTest::default();
+        queues.create_queue(TEST_QUEUE_ID).unwrap();
+        queues.append(TEST_QUEUE_ID, b"hello").unwrap();
+        queues
+            .set_retention_period(TEST_QUEUE_ID, Some(Duration::from_secs(3600)))
+            .unwrap();
+        queues.append(TEST_QUEUE_ID, b"happy").unwrap();
+        queues.append(TEST_QUEUE_ID, b"tax").unwrap();
+
+        // The indexed records are kept for replays.
+        queues
+            .suggest_truncate(TEST_QUEUE_ID, Position::from(1))
+            .unwrap();
+        assert_eq!(queues.queue_lag(TEST_QUEUE_ID).unwrap().num_records, 1);
+        queues.fetch_test(
+            TEST_QUEUE_ID,
+            None,
+            Some(0),
+            &[&b"hello"[..], &b"happy"[..], &b"tax"[..]],
+        );
+        assert_eq!(queues.truncate_expired_records(TEST_QUEUE_ID).unwrap(), 0);
+
+        // Once expired, only the indexed records are deleted.
+        let later = Instant::now() + Duration::from_secs(3600);
+        assert_eq!(
+            queues
+                .truncate_expired_records_at(TEST_QUEUE_ID, later)
+                .unwrap(),
+            2
+        );
+        queues.fetch_test(TEST_QUEUE_ID, None, Some(2), &[&b"tax"[..]]);
+
+        // Without retention period, the indexed records are deleted right away.
+        queues.set_retention_period(TEST_QUEUE_ID, None).unwrap();
+        queues.append(TEST_QUEUE_ID, b"fresh").unwrap();
+        queues
+            .suggest_truncate(TEST_QUEUE_ID, Position::from(2))
+            .unwrap();
+        queues.fetch_test(TEST_QUEUE_ID, None, Some(3), &[&b"fresh"[..]]);
+
+        queues
+            .set_retention_period("does-not-exist", None)
+            .unwrap_err();
+    }
+
     struct Record {
         queue_id: String,
         payload: Vec<u8>,
@@ -38,7 +38,7 @@ use tracing::debug;
 // OpenTelemetry to Jaeger Transformation
 // <https://opentelemetry.io/docs/reference/specification/trace/sdk_exporters/jaeger/>
 
-const TRACE_INDEX_ID: &str = "otel-trace";
+const TRACE_INDEX_ID: &str = "otel-traces";
 
 pub struct JaegerService {
     search_service: Arc<dyn SearchService>,
@@ -155,6 +155,10 @@ impl IntoSearchRequest for GetServicesRequest {
             sort_by_field: None,
             aggregation_request: None,
             snippet_fields: Vec::new(),
+            fetch_fields: Vec::new(),
+            debug: false,
+            role: None,
+            query_ast: None,
         }
     }
 }
@@ -198,6 +202,10 @@ impl IntoSearchRequest for GetOperationsRequest {
             sort_by_field: None,
             aggregation_request: None,
             snippet_fields: Vec::new(),
+            fetch_fields: Vec::new(),
+            debug: false,
+            role: None,
+            query_ast: None,
         }
     }
 }
@@ -251,6 +259,10 @@ impl IntoSearchRequest for FindTraceIDsRequest {
             sort_by_field: None,
             aggregation_request: None,
             snippet_fields: Vec::new(),
+            fetch_fields: Vec::new(),
+            debug: false,
+            role: None,
+            query_ast: None,
         }
     }
 }
@@ -302,6 +314,10 @@ impl IntoSearchRequest for FindTracesRequest {
             sort_by_field: None,
             aggregation_request: None,
             snippet_fields: Vec::new(),
+            fetch_fields: Vec::new(),
+            debug: false,
+            role: None,
+            query_ast: None,
         }
     }
 }
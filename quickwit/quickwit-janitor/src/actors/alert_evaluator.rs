@@ -0,0 +1,531 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use quickwit_actors::{Actor, ActorContext, Handler};
+use quickwit_config::{AlertConfig, IndexingSettings, StoredQuery};
+use quickwit_metastore::{AlertState, IndexMetadata, Metastore};
+use quickwit_proto::SearchRequest;
+use quickwit_search::SearchClientPool;
+use serde::Serialize;
+use time::OffsetDateTime;
+use tracing::{debug, error, info};
+
+use crate::alert_notification::{AlertEvent, AlertNotification, AlertNotifier};
+use crate::metrics::JANITOR_METRICS;
+
+const RUN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time allowed, on top of the commit timeout, for the splits to be uploaded and published.
+const PUBLISH_DELAY: Duration = Duration::from_secs(30);
+
+/// Returns how far behind the current time the alerts of an index are evaluated, so that the
+/// documents of an evaluation window are committed and published by the time it is evaluated.
+fn evaluation_lag_secs(indexing_settings: &IndexingSettings) -> i64 {
+    (indexing_settings.max_commit_timeout() + PUBLISH_DELAY).as_secs() as i64
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AlertEvaluatorCounters {
+    /// The number of passes over the stored queries.
+    pub num_passes: usize,
+
+    /// The number of alert evaluations.
+    pub num_evaluations: usize,
+
+    /// The number of failed alert evaluations.
+    pub num_failed_evaluations: usize,
+
+    /// The number of notifications delivered.
+    pub num_notifications: usize,
+
+    /// The number of notifications that could not be delivered.
+    pub num_failed_notifications: usize,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+/// An actor that periodically evaluates the alerts of the stored queries of all indexes and
+/// notifies their channels when they fire or resolve.
+///
+/// The state of each alert is persisted in the metastore before its channels are notified, so
+/// an evaluator restarting neither re-evaluates a time range nor notifies the same transition
+/// twice. In exchange, a notification lost because the node crashed right after persisting the
+/// state is not retried.
+pub struct AlertEvaluator {
+    metastore: Arc<dyn Metastore>,
+    search_client_pool: SearchClientPool,
+    notifier: Arc<dyn AlertNotifier>,
+    counters: AlertEvaluatorCounters,
+}
+
+impl AlertEvaluator {
+    pub fn new(
+        metastore: Arc<dyn Metastore>,
+        search_client_pool: SearchClientPool,
+        notifier: Arc<dyn AlertNotifier>,
+    ) -> Self {
+        Self {
+            metastore,
+            search_client_pool,
+            notifier,
+            counters: AlertEvaluatorCounters::default(),
+        }
+    }
+
+    /// Evaluates the alerts that are due. Should not return an error to prevent the actor from
+    /// crashing.
+    async fn evaluate_due_alerts(&mut self, now: i64) {
+        debug!("alert-evaluation-operation");
+        self.counters.num_passes += 1;
+
+        let index_metadatas = match self.metastore.list_indexes_metadatas().await {
+            Ok(metadatas) => metadatas,
+            Err(error) => {
+                error!(error=?error, "Failed to list indexes from the metastore.");
+                return;
+            }
+        };
+        for index_metadata in &index_metadatas {
+            let end_timestamp = now - evaluation_lag_secs(&index_metadata.indexing_settings);
+            for stored_query in index_metadata.stored_queries.values() {
+                let alert = match &stored_query.alert {
+                    Some(alert) if alert.enabled => alert,
+                    _ => continue,
+                };
+                let previous_state_opt = index_metadata.alert_states.get(&stored_query.name);
+                let evaluation_interval = match alert.evaluation_interval() {
+                    Ok(evaluation_interval) => evaluation_interval.as_secs() as i64,
+                    Err(error) => {
+                        error!(index_id=%index_metadata.index_id, stored_query=%stored_query.name, error=?error, "Invalid alert evaluation interval.");
+                        continue;
+                    }
+                };
+                let start_timestamp = match previous_state_opt {
+                    Some(previous_state) => previous_state.last_evaluation_timestamp,
+                    None => end_timestamp - evaluation_interval,
+                };
+                if end_timestamp < start_timestamp + evaluation_interval {
+                    continue;
+                }
+                self.evaluate_alert(
+                    index_metadata,
+                    stored_query,
+                    alert,
+                    previous_state_opt
+                        .map(|state| state.firing)
+                        .unwrap_or(false),
+                    start_timestamp,
+                    end_timestamp,
+                )
+                .await;
+            }
+        }
+    }
+
+    async fn evaluate_alert(
+        &mut self,
+        index_metadata: &IndexMetadata,
+        stored_query: &StoredQuery,
+        alert: &AlertConfig,
+        was_firing: bool,
+        start_timestamp: i64,
+        end_timestamp: i64,
+    ) {
+        let index_id = &index_metadata.index_id;
+        info!(index_id=%index_id, stored_query=%stored_query.name, start_timestamp=%start_timestamp, end_timestamp=%end_timestamp, "alert-evaluation-operation");
+        self.counters.num_evaluations += 1;
+        JANITOR_METRICS
+            .alert_evaluations_total
+            .with_label_values(&[index_id])
+            .inc();
+
+        let num_hits = match self
+            .count_hits(index_metadata, stored_query, start_timestamp, end_timestamp)
+            .await
+        {
+            Ok(num_hits) => num_hits,
+            Err(error) => {
+                error!(index_id=%index_id, stored_query=%stored_query.name, error=?error, "Failed to evaluate the alert.");
+                self.counters.num_failed_evaluations += 1;
+                JANITOR_METRICS
+                    .alert_evaluation_errors_total
+                    .with_label_values(&[index_id])
+                    .inc();
+                return;
+            }
+        };
+        let firing = alert.condition.is_met(num_hits);
+        let alert_state = AlertState {
+            name: stored_query.name.clone(),
+            last_evaluation_timestamp: end_timestamp,
+            last_num_hits: num_hits,
+            firing,
+        };
+        // The state is persisted first so that a transition is notified at most once.
+        if let Err(error) = self
+            .metastore
+            .update_alert_state(index_id, alert_state)
+            .await
+        {
+            error!(index_id=%index_id, stored_query=%stored_query.name, error=?error, "Failed to update the alert state.");
+            self.counters.num_failed_evaluations += 1;
+            JANITOR_METRICS
+                .alert_evaluation_errors_total
+                .with_label_values(&[index_id])
+                .inc();
+            return;
+        }
+        let event = match (was_firing, firing) {
+            (false, true) => AlertEvent::Fired,
+            (true, false) => AlertEvent::Resolved,
+            _ => return,
+        };
+        let notification = AlertNotification {
+            index_id: index_id.clone(),
+            stored_query_name: stored_query.name.clone(),
+            event,
+            condition: alert.condition.to_string(),
+            num_hits,
+            start_timestamp,
+            end_timestamp,
+        };
+        info!(index_id=%index_id, stored_query=%stored_query.name, event=?event, num_hits=%num_hits, "alert-notification-operation");
+        for channel in &alert.channels {
+            match self.notifier.notify(channel, &notification).await {
+                Ok(()) => {
+                    self.counters.num_notifications += 1;
+                    JANITOR_METRICS
+                        .alert_notifications_total
+                        .with_label_values(&[index_id])
+                        .inc();
+                }
+                Err(error) => {
+                    error!(index_id=%index_id, stored_query=%stored_query.name, error=?error, "Failed to notify the alert channel.");
+                    self.counters.num_failed_notifications += 1;
+                    JANITOR_METRICS
+                        .alert_notification_errors_total
+                        .with_label_values(&[index_id])
+                        .inc();
+                }
+            }
+        }
+    }
+
+    /// Counts the documents matching the stored query, rendered with the default values of its
+    /// parameters, within `[start_timestamp, end_timestamp)`.
+    async fn count_hits(
+        &self,
+        index_metadata: &IndexMetadata,
+        stored_query: &StoredQuery,
+        start_timestamp: i64,
+        end_timestamp: i64,
+    ) -> anyhow::Result<u64> {
+        if index_metadata.indexing_settings.timestamp_field.is_none() {
+            bail!(
+                "Index `{}` has no timestamp field to evaluate the alert over a time range.",
+                index_metadata.index_id
+            );
+        }
+        let query = stored_query.render(&Default::default())?;
+        let search_request = SearchRequest {
+            index_id: index_metadata.index_id.clone(),
+            query,
+            search_fields: stored_query.search_fields.clone(),
+            snippet_fields: Vec::new(),
+            fetch_fields: Vec::new(),
+            start_timestamp: Some(start_timestamp),
+            end_timestamp: Some(end_timestamp),
+            max_hits: 0,
+            start_offset: 0,
+            aggregation_request: None,
+            sort_order: None,
+            sort_by_field: None,
+            debug: false,
+            role: stored_query
+                .alert
+                .as_ref()
+                .and_then(|alert| alert.role.clone()),
+            query_ast: None,
+        };
+        let mut search_client = self
+            .search_client_pool
+            .clients()
+            .into_values()
+            .next()
+            .context("No searcher available to evaluate the alert.")?;
+        let search_response = search_client.root_search(search_request).await?;
+        Ok(search_response.num_hits)
+    }
+}
+
+#[async_trait]
+impl Actor for AlertEvaluator {
+    type ObservableState = AlertEvaluatorCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "AlertEvaluator".to_string()
+    }
+
+    async fn initialize(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle(Loop, ctx).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for AlertEvaluator {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Loop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        self.evaluate_due_alerts(now).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_config::{AlertChannel, AlertCondition, ThresholdOperator};
+    use quickwit_metastore::MockMetastore;
+    use quickwit_proto::SearchResponse;
+    use quickwit_search::MockSearchService;
+
+    use super::*;
+    use crate::alert_notification::MockAlertNotifier;
+
+    const NOW: i64 = 1_000_000;
+
+    /// Returns the current time at which the alerts are evaluated up to `NOW`.
+    fn now_with_lag() -> i64 {
+        NOW + evaluation_lag_secs(&make_index(None).indexing_settings)
+    }
+
+    fn make_index(alert_state_opt: Option<AlertState>) -> IndexMetadata {
+        let mut index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+        let stored_query = StoredQuery {
+            name: "errors".to_string(),
+            description: None,
+            query: "body:error".to_string(),
+            params: Vec::new(),
+            search_fields: Vec::new(),
+            max_hits: None,
+            sort_by_field: None,
+            aggregation_request: None,
+            alert: Some(AlertConfig {
+                evaluation_interval: "1m".to_string(),
+                condition: AlertCondition {
+                    operator: ThresholdOperator::GreaterThan,
+                    threshold: 10,
+                },
+                channels: vec![AlertChannel::Webhook {
+                    url: "https://hooks.example.com/alerts".to_string(),
+                }],
+                enabled: true,
+                role: None,
+            }),
+        };
+        index_metadata
+            .stored_queries
+            .insert(stored_query.name.clone(), stored_query);
+        if let Some(alert_state) = alert_state_opt {
+            index_metadata
+                .alert_states
+                .insert(alert_state.name.clone(), alert_state);
+        }
+        index_metadata
+    }
+
+    async fn make_alert_evaluator(
+        index_metadata: IndexMetadata,
+        num_hits_opt: Option<u64>,
+        expected_alert_state_opt: Option<AlertState>,
+        mock_notifier: MockAlertNotifier,
+    ) -> AlertEvaluator {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .returning(move || Ok(vec![index_metadata.clone()]));
+        mock_metastore
+            .expect_update_alert_state()
+            .times(usize::from(expected_alert_state_opt.is_some()))
+            .returning(move |index_id, alert_state| {
+                assert_eq!(index_id, "test-index");
+                assert_eq!(Some(alert_state), expected_alert_state_opt);
+                Ok(())
+            });
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(usize::from(num_hits_opt.is_some()))
+            .returning(move |search_request| {
+                assert_eq!(search_request.query, "body:error");
+                assert_eq!(search_request.start_timestamp, Some(NOW - 60));
+                assert_eq!(search_request.end_timestamp, Some(NOW));
+                assert_eq!(search_request.max_hits, 0);
+                Ok(SearchResponse {
+                    num_hits: num_hits_opt.unwrap(),
+                    ..Default::default()
+                })
+            });
+        let search_client_pool = SearchClientPool::from_mocks(vec![Arc::new(mock_search_service)])
+            .await
+            .unwrap();
+        AlertEvaluator::new(
+            Arc::new(mock_metastore),
+            search_client_pool,
+            Arc::new(mock_notifier),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_alert_evaluator_fires() {
+        let mut mock_notifier = MockAlertNotifier::new();
+        mock_notifier
+            .expect_notify()
+            .times(1)
+            .returning(|channel, notification| {
+                assert!(matches!(channel, AlertChannel::Webhook { .. }));
+                assert_eq!(notification.event, AlertEvent::Fired);
+                assert_eq!(notification.num_hits, 12);
+                assert_eq!(notification.condition, "num_hits > 10");
+                Ok(())
+            });
+        let expected_alert_state = AlertState {
+            name: "errors".to_string(),
+            last_evaluation_timestamp: NOW,
+            last_num_hits: 12,
+            firing: true,
+        };
+        let mut alert_evaluator = make_alert_evaluator(
+            make_index(None),
+            Some(12),
+            Some(expected_alert_state),
+            mock_notifier,
+        )
+        .await;
+        alert_evaluator.evaluate_due_alerts(now_with_lag()).await;
+        assert_eq!(alert_evaluator.counters.num_evaluations, 1);
+        assert_eq!(alert_evaluator.counters.num_notifications, 1);
+    }
+
+    #[tokio::test]
+    async fn test_alert_evaluator_does_not_notify_twice() {
+        let mut mock_notifier = MockAlertNotifier::new();
+        mock_notifier.expect_notify().never();
+        let previous_alert_state = AlertState {
+            name: "errors".to_string(),
+            last_evaluation_timestamp: NOW - 60,
+            last_num_hits: 12,
+            firing: true,
+        };
+        let expected_alert_state = AlertState {
+            last_evaluation_timestamp: NOW,
+            last_num_hits: 20,
+            ..previous_alert_state.clone()
+        };
+        let mut alert_evaluator = make_alert_evaluator(
+            make_index(Some(previous_alert_state)),
+            Some(20),
+            Some(expected_alert_state),
+            mock_notifier,
+        )
+        .await;
+        alert_evaluator.evaluate_due_alerts(now_with_lag()).await;
+        assert_eq!(alert_evaluator.counters.num_evaluations, 1);
+        assert_eq!(alert_evaluator.counters.num_notifications, 0);
+    }
+
+    #[tokio::test]
+    async fn test_alert_evaluator_resolves() {
+        let mut mock_notifier = MockAlertNotifier::new();
+        mock_notifier
+            .expect_notify()
+            .times(1)
+            .returning(|_, notification| {
+                assert_eq!(notification.event, AlertEvent::Resolved);
+                assert_eq!(notification.start_timestamp, NOW - 60);
+                assert_eq!(notification.end_timestamp, NOW);
+                Ok(())
+            });
+        let previous_alert_state = AlertState {
+            name: "errors".to_string(),
+            last_evaluation_timestamp: NOW - 60,
+            last_num_hits: 12,
+            firing: true,
+        };
+        let expected_alert_state = AlertState {
+            name: "errors".to_string(),
+            last_evaluation_timestamp: NOW,
+            last_num_hits: 3,
+            firing: false,
+        };
+        let mut alert_evaluator = make_alert_evaluator(
+            make_index(Some(previous_alert_state)),
+            Some(3),
+            Some(expected_alert_state),
+            mock_notifier,
+        )
+        .await;
+        alert_evaluator.evaluate_due_alerts(now_with_lag()).await;
+        assert_eq!(alert_evaluator.counters.num_notifications, 1);
+    }
+
+    #[tokio::test]
+    async fn test_alert_evaluator_skips_alerts_not_due() {
+        let mut mock_notifier = MockAlertNotifier::new();
+        mock_notifier.expect_notify().never();
+        let previous_alert_state = AlertState {
+            name: "errors".to_string(),
+            last_evaluation_timestamp: OffsetDateTime::now_utc().unix_timestamp() - 30,
+            last_num_hits: 0,
+            firing: false,
+        };
+        let alert_evaluator = make_alert_evaluator(
+            make_index(Some(previous_alert_state)),
+            None,
+            None,
+            mock_notifier,
+        )
+        .await;
+        let universe = Universe::new();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(alert_evaluator);
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_evaluations, 0);
+    }
+}
@@ -0,0 +1,294 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_actors::{
+    Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Mailbox, Supervisor,
+    SupervisorState,
+};
+use quickwit_common::io::IoControls;
+use quickwit_config::build_doc_mapper;
+use quickwit_indexing::actors::{
+    MergeExecutor, MergeSplitDownloader, Packager, ParquetSidecarWriter, Publisher, Uploader,
+    UploaderType,
+};
+use quickwit_indexing::merge_policy::MergeOperation;
+use quickwit_indexing::models::{IndexingDirectory, IndexingPipelineId};
+use quickwit_indexing::{IndexingSplitStore, PublisherType, SplitsUpdateMailbox};
+use quickwit_metastore::Metastore;
+use quickwit_storage::Storage;
+use serde::Serialize;
+use tantivy::TrackedObject;
+use tokio::join;
+use tracing::info;
+
+struct CompactionPipelineHandle {
+    pub downloader_mailbox: Mailbox<MergeSplitDownloader>,
+    pub downloader: ActorHandle<Supervisor<MergeSplitDownloader>>,
+    pub compaction_executor: ActorHandle<Supervisor<MergeExecutor>>,
+    pub packager: ActorHandle<Supervisor<Packager>>,
+    pub uploader: ActorHandle<Supervisor<Uploader>>,
+    pub publisher: ActorHandle<Supervisor<Publisher>>,
+    pub parquet_sidecar_writer_opt: Option<ActorHandle<Supervisor<ParquetSidecarWriter>>>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CompactionPipelineState {
+    pub downloader: SupervisorState,
+    pub compaction_executor: SupervisorState,
+    pub packager: SupervisorState,
+    pub uploader: SupervisorState,
+    pub publisher: SupervisorState,
+}
+
+/// A merge pipeline run by the janitor to compact the tiny splits of an index, independently of
+/// the indexing pipelines of the index. It is also used to rewrite the splits written with an
+/// outdated format when upgrading an index.
+pub struct CompactionPipeline {
+    index_id: String,
+    metastore: Arc<dyn Metastore>,
+    index_storage: Arc<dyn Storage>,
+    compaction_dir_path: PathBuf,
+    handles: Option<CompactionPipelineHandle>,
+    max_concurrent_split_uploads: usize,
+    state: CompactionPipelineState,
+}
+
+#[async_trait]
+impl Actor for CompactionPipeline {
+    type ObservableState = CompactionPipelineState;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.state.clone()
+    }
+
+    fn name(&self) -> String {
+        "CompactionPipeline".to_string()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.spawn_pipeline(ctx).await?;
+        self.handle(Observe, ctx).await?;
+        Ok(())
+    }
+
+    async fn finalize(
+        &mut self,
+        _exit_status: &ActorExitStatus,
+        _ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        if let Some(handles) = self.handles.take() {
+            join!(
+                handles.downloader.quit(),
+                handles.compaction_executor.quit(),
+                handles.packager.quit(),
+                handles.uploader.quit(),
+                handles.publisher.quit(),
+            );
+            if let Some(parquet_sidecar_writer) = handles.parquet_sidecar_writer_opt {
+                parquet_sidecar_writer.quit().await;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CompactionPipeline {
+    pub fn new(
+        index_id: String,
+        metastore: Arc<dyn Metastore>,
+        index_storage: Arc<dyn Storage>,
+        compaction_dir_path: PathBuf,
+        max_concurrent_split_uploads: usize,
+    ) -> Self {
+        Self {
+            index_id,
+            metastore,
+            index_storage,
+            compaction_dir_path,
+            handles: None,
+            max_concurrent_split_uploads,
+            state: CompactionPipelineState::default(),
+        }
+    }
+
+    async fn spawn_pipeline(&mut self, ctx: &ActorContext<Self>) -> anyhow::Result<()> {
+        info!(
+            index_id=%self.index_id,
+            root_dir=%self.compaction_dir_path.display(),
+            "Spawning compaction pipeline.",
+        );
+        let index_metadata = self.metastore.index_metadata(&self.index_id).await?;
+        let indexing_directory_path = self.compaction_dir_path.join(&self.index_id);
+        let indexing_directory = IndexingDirectory::create_in_dir(indexing_directory_path).await?;
+        let mut publisher = Publisher::new(
+            PublisherType::MergePublisher,
+            self.metastore.clone(),
+            None,
+            None,
+        );
+        let parquet_sidecar_writer_supervisor_handler_opt =
+            if let Some(parquet_sidecar) = &index_metadata.indexing_settings.parquet_sidecar {
+                let parquet_sidecar_writer = ParquetSidecarWriter::new(
+                    self.index_id.clone(),
+                    parquet_sidecar.fields.clone(),
+                    self.index_storage.clone(),
+                    indexing_directory.scratch_directory().clone(),
+                );
+                let (parquet_sidecar_writer_mailbox, parquet_sidecar_writer_supervisor_handler) =
+                    ctx.spawn_actor().supervise(parquet_sidecar_writer);
+                publisher.set_parquet_sidecar_writer_mailbox(parquet_sidecar_writer_mailbox);
+                Some(parquet_sidecar_writer_supervisor_handler)
+            } else {
+                None
+            };
+        let (publisher_mailbox, publisher_supervisor_handler) =
+            ctx.spawn_actor().supervise(publisher);
+        let split_store =
+            IndexingSplitStore::create_without_local_store(self.index_storage.clone());
+        let uploader = Uploader::new(
+            UploaderType::MergeUploader,
+            self.metastore.clone(),
+            split_store.clone(),
+            SplitsUpdateMailbox::Publisher(publisher_mailbox),
+            self.max_concurrent_split_uploads,
+        );
+        let (uploader_mailbox, uploader_supervisor_handler) = ctx.spawn_actor().supervise(uploader);
+
+        let doc_mapper = build_doc_mapper(
+            &index_metadata.doc_mapping,
+            &index_metadata.search_settings,
+            &index_metadata.indexing_settings,
+        )?;
+        let tag_fields = doc_mapper.tag_named_fields()?;
+        let bloom_filter_fields = doc_mapper.bloom_filter_named_fields()?;
+        let packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            bloom_filter_fields,
+            index_metadata.indexing_settings.hotcache.clone(),
+            uploader_mailbox,
+        );
+        let (packager_mailbox, packager_supervisor_handler) = ctx.spawn_actor().supervise(packager);
+        // The merged splits inherit the source and node of their input splits.
+        let index_pipeline_id = IndexingPipelineId {
+            index_id: self.index_id.to_string(),
+            node_id: "unknown".to_string(),
+            pipeline_ord: 0,
+            source_id: "unknown".to_string(),
+        };
+        let throughput_limit: f64 = index_metadata
+            .indexing_settings
+            .resources
+            .max_janitor_write_throughput
+            .as_ref()
+            .map(|bytes_per_sec| bytes_per_sec.get_bytes() as f64)
+            .unwrap_or(f64::INFINITY);
+        let compaction_executor_io_controls = IoControls::default()
+            .set_throughput_limit(throughput_limit)
+            .set_index_and_component(self.index_id.as_str(), "compactor");
+        let split_download_io_controls = compaction_executor_io_controls
+            .clone()
+            .set_index_and_component(self.index_id.as_str(), "split_downloader_compaction");
+        let compaction_executor = MergeExecutor::new(
+            index_pipeline_id,
+            self.metastore.clone(),
+            doc_mapper,
+            compaction_executor_io_controls,
+            packager_mailbox,
+        );
+        let (compaction_executor_mailbox, compaction_executor_supervisor_handler) =
+            ctx.spawn_actor().supervise(compaction_executor);
+        let merge_split_downloader = MergeSplitDownloader {
+            scratch_directory: indexing_directory.scratch_directory().clone(),
+            split_store,
+            executor_mailbox: compaction_executor_mailbox,
+            io_controls: split_download_io_controls,
+        };
+        let (downloader_mailbox, downloader_supervisor_handler) =
+            ctx.spawn_actor().supervise(merge_split_downloader);
+        self.handles = Some(CompactionPipelineHandle {
+            downloader_mailbox,
+            downloader: downloader_supervisor_handler,
+            compaction_executor: compaction_executor_supervisor_handler,
+            packager: packager_supervisor_handler,
+            uploader: uploader_supervisor_handler,
+            publisher: publisher_supervisor_handler,
+            parquet_sidecar_writer_opt: parquet_sidecar_writer_supervisor_handler_opt,
+        });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<TrackedObject<MergeOperation>> for CompactionPipeline {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        merge_operation: TrackedObject<MergeOperation>,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(handles) = &self.handles {
+            info!(index_id=%self.index_id, merge_operation=?merge_operation, "Planned compaction operation.");
+            ctx.send_message(&handles.downloader_mailbox, merge_operation)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Observe;
+
+#[async_trait]
+impl Handler<Observe> for CompactionPipeline {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Observe,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(handles) = &self.handles {
+            let (downloader, compaction_executor, packager, uploader, publisher) = join!(
+                handles.downloader.observe(),
+                handles.compaction_executor.observe(),
+                handles.packager.observe(),
+                handles.uploader.observe(),
+                handles.publisher.observe(),
+            );
+            self.state = CompactionPipelineState {
+                downloader: downloader.state,
+                compaction_executor: compaction_executor.state,
+                packager: packager.state,
+                uploader: uploader.state,
+                publisher: publisher.state,
+            }
+        }
+        // Supervisors supervise every `HEARTBEAT`. We can wait a bit more to observe supervisors.
+        ctx.schedule_self_msg_coalesced(Duration::from_secs(5), Observe)
+            .await;
+        Ok(())
+    }
+}
@@ -29,7 +29,8 @@ use quickwit_common::io::IoControls;
 use quickwit_common::KillSwitch;
 use quickwit_config::{build_doc_mapper, IndexingSettings};
 use quickwit_indexing::actors::{
-    MergeExecutor, MergeSplitDownloader, Packager, Publisher, Uploader, UploaderType,
+    MergeExecutor, MergeSplitDownloader, Packager, ParquetSidecarWriter, Publisher, Uploader,
+    UploaderType,
 };
 use quickwit_indexing::merge_policy::merge_policy_from_settings;
 use quickwit_indexing::models::{IndexingDirectory, IndexingPipelineId};
@@ -50,6 +51,7 @@ struct DeletePipelineHandle {
     pub packager: ActorHandle<Supervisor<Packager>>,
     pub uploader: ActorHandle<Supervisor<Uploader>>,
     pub publisher: ActorHandle<Supervisor<Publisher>>,
+    pub parquet_sidecar_writer_opt: Option<ActorHandle<Supervisor<ParquetSidecarWriter>>>,
 }
 
 /// A Struct to hold all statistical data about deletes.
@@ -124,12 +126,31 @@ impl DeleteTaskPipeline {
             "Spawning delete tasks pipeline.",
         );
         let index_metadata = self.metastore.index_metadata(&self.index_id).await?;
-        let publisher = Publisher::new(
+        let indexing_directory_path = self.delete_service_dir_path.join(&self.index_id);
+        let indexing_directory = IndexingDirectory::create_in_dir(indexing_directory_path).await?;
+        let mut publisher = Publisher::new(
             PublisherType::MergePublisher,
             self.metastore.clone(),
             None,
             None,
         );
+        let parquet_sidecar_writer_supervisor_handler_opt =
+            if let Some(parquet_sidecar) = &index_metadata.indexing_settings.parquet_sidecar {
+                let parquet_sidecar_writer = ParquetSidecarWriter::new(
+                    self.index_id.clone(),
+                    parquet_sidecar.fields.clone(),
+                    self.index_storage.clone(),
+                    indexing_directory.scratch_directory().clone(),
+                );
+                let (parquet_sidecar_writer_mailbox, parquet_sidecar_writer_supervisor_handler) =
+                    ctx.spawn_actor()
+                        .set_kill_switch(KillSwitch::default())
+                        .supervise(parquet_sidecar_writer);
+                publisher.set_parquet_sidecar_writer_mailbox(parquet_sidecar_writer_mailbox);
+                Some(parquet_sidecar_writer_supervisor_handler)
+            } else {
+                None
+            };
         let (publisher_mailbox, publisher_supervisor_handler) = ctx
             .spawn_actor()
             .set_kill_switch(KillSwitch::default())
@@ -154,7 +175,14 @@ impl DeleteTaskPipeline {
             &index_metadata.indexing_settings,
         )?;
         let tag_fields = doc_mapper.tag_named_fields()?;
-        let packager = Packager::new("MergePackager", tag_fields, uploader_mailbox);
+        let bloom_filter_fields = doc_mapper.bloom_filter_named_fields()?;
+        let packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            bloom_filter_fields,
+            index_metadata.indexing_settings.hotcache.clone(),
+            uploader_mailbox,
+        );
         let (packager_mailbox, packager_supervisor_handler) = ctx
             .spawn_actor()
             .set_kill_switch(KillSwitch::default())
@@ -189,8 +217,6 @@ impl DeleteTaskPipeline {
             .spawn_actor()
             .set_kill_switch(KillSwitch::default())
             .supervise(delete_executor);
-        let indexing_directory_path = self.delete_service_dir_path.join(&self.index_id);
-        let indexing_directory = IndexingDirectory::create_in_dir(indexing_directory_path).await?;
         let merge_split_downloader = MergeSplitDownloader {
             scratch_directory: indexing_directory.scratch_directory().clone(),
             split_store: split_store.clone(),
@@ -223,6 +249,7 @@ impl DeleteTaskPipeline {
             packager: packager_supervisor_handler,
             uploader: uploader_supervisor_handler,
             publisher: publisher_supervisor_handler,
+            parquet_sidecar_writer_opt: parquet_sidecar_writer_supervisor_handler_opt,
         });
         Ok(())
     }
@@ -265,7 +292,8 @@ impl Handler<Observe> for DeleteTaskPipeline {
             }
         }
         // Supervisors supervise every `HEARTBEAT`. We can wait a bit more to observe supervisors.
-        ctx.schedule_self_msg(Duration::from_secs(5), Observe).await;
+        ctx.schedule_self_msg_coalesced(Duration::from_secs(5), Observe)
+            .await;
         Ok(())
     }
 }
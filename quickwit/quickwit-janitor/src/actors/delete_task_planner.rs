@@ -53,9 +53,9 @@ const NUM_STALE_SPLITS_TO_FETCH: usize = 1000;
 ///
 /// Pseudo-algorithm for a given index:
 /// 1. Fetches the delete tasks and deduce the last `opstamp`.
-/// 2. Fetches the last `N` stale splits ordered by their `delete_opstamp`.
-///    A stale split is a split a `delete_opstamp` inferior to the last `opstamp`
-///    In theory, this works but... there is one difficulty:
+/// 2. Fetches the last `N` stale splits ordered by their `delete_opstamp`. A stale split is a split
+///    a `delete_opstamp` inferior to the last `opstamp` In theory, this works but... there is one
+///    difficulty:
 ///    - Delete operations do not run on immature splits and they are excluded after fetching stale
 ///      splits from the metastore as the metastore has no knowledge about the merge policy. If
 ///      there are more than `N` immature stale splits, the planner will plan no operations.
@@ -314,8 +314,8 @@ impl DeleteTaskPlanner {
         Ok(false)
     }
 
-    /// Fetches stale splits from [`Metastore`] and excludes immature splits and split already among
-    /// ongoing delete operations.
+    /// Fetches stale splits from [`Metastore`] and excludes immature splits, warm splits, and
+    /// splits already among ongoing delete operations.
     async fn get_relevant_stale_splits(
         &self,
         index_id: &str,
@@ -334,12 +334,12 @@ impl DeleteTaskPlanner {
             last_delete_opstamp = last_delete_opstamp,
             num_stale_splits_from_metastore = stale_splits.len()
         );
-        // Keep only mature splits and splits that are not already part of ongoing delete
-        // operations.
+        // Keep only mature hot splits that are not already part of ongoing delete operations.
         let ongoing_delete_operations = self.ongoing_delete_operations_inventory.list();
         let filtered_splits = stale_splits
             .into_iter()
             .filter(|stale_split| self.merge_policy.is_mature(&stale_split.split_metadata))
+            .filter(|stale_split| stale_split.split_metadata.storage_tier.is_hot())
             .filter(|stale_split| {
                 !ongoing_delete_operations.iter().any(|operation| {
                     operation
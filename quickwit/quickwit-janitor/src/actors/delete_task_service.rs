@@ -100,6 +100,8 @@ impl DeleteTaskService {
             .list_indexes_metadatas()
             .await?
             .into_iter()
+            // The splits of read-only indexes are never rewritten.
+            .filter(|index_metadata| !index_metadata.read_only)
             .map(|index_metadata| (index_metadata.index_id.clone(), index_metadata))
             .collect();
         let index_ids: HashSet<String> = index_metadata_by_index_id.keys().cloned().collect();
@@ -195,7 +197,8 @@ impl Handler<SuperviseLoop> for DeleteTaskService {
         if let Err(error) = result {
             error!("Delete task pipelines udpate failed: {}", error);
         }
-        ctx.schedule_self_msg(HEARTBEAT, SuperviseLoop).await;
+        ctx.schedule_self_msg_coalesced(HEARTBEAT, SuperviseLoop)
+            .await;
         Ok(())
     }
 }
@@ -25,10 +25,12 @@ use async_trait::async_trait;
 use futures::StreamExt;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, Handler};
+use quickwit_common::events::{record_event, Event, EventType};
+use quickwit_config::MaintenanceWindows;
 use quickwit_metastore::Metastore;
 use quickwit_storage::StorageUriResolver;
 use serde::Serialize;
-use tracing::{error, info};
+use tracing::{debug, error, info};
 
 use crate::garbage_collection::run_garbage_collect;
 
@@ -36,6 +38,7 @@ const RUN_INTERVAL: Duration = Duration::from_secs(60); // 1 minutes
 /// Staged files needs to be deleted if there was a failure.
 /// TODO ideally we want clean up all staged splits every time we restart the indexing pipeline, but
 /// the grace period strategy should do the job for the moment.
+/// This default can be overridden with the `staged_split_grace_period` janitor setting.
 const STAGED_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60 * 24); // 24 hours
 /// We cannot safely delete splits right away as a in-flight queries could actually
 /// have selected this split.
@@ -51,6 +54,8 @@ const MAX_CONCURRENT_STORAGE_REQUESTS: usize = if cfg!(test) { 2 } else { 10 };
 pub struct GarbageCollectorCounters {
     /// The number of passes the garbage collector has performed.
     pub num_passes: usize,
+    /// The number of passes skipped because the GC windows were closed.
+    pub num_skipped_passes: usize,
     /// The number of deleted files.
     pub num_deleted_files: usize,
     /// The number of bytes deleted.
@@ -71,6 +76,10 @@ pub struct GarbageCollector {
     metastore: Arc<dyn Metastore>,
     storage_resolver: StorageUriResolver,
     counters: GarbageCollectorCounters,
+    /// Garbage collection only runs while one of these windows is open.
+    gc_windows: MaintenanceWindows,
+    /// Period after which a staged split is garbage collected.
+    staged_grace_period: Duration,
 }
 
 impl GarbageCollector {
@@ -79,12 +88,29 @@ impl GarbageCollector {
             metastore,
             storage_resolver,
             counters: GarbageCollectorCounters::default(),
+            gc_windows: MaintenanceWindows::default(),
+            staged_grace_period: STAGED_GRACE_PERIOD,
         }
     }
 
+    pub fn set_gc_windows(mut self, gc_windows: MaintenanceWindows) -> Self {
+        self.gc_windows = gc_windows;
+        self
+    }
+
+    pub fn set_staged_grace_period(mut self, staged_grace_period: Duration) -> Self {
+        self.staged_grace_period = staged_grace_period;
+        self
+    }
+
     /// Gc Loop handler logic.
     /// Should not return an error to prevent the actor from crashing.
     async fn handle_inner(&mut self, ctx: &ActorContext<Self>) {
+        if !self.gc_windows.is_open() {
+            debug!("GC windows are closed, skipping garbage collection.");
+            self.counters.num_skipped_passes += 1;
+            return;
+        }
         info!("garbage-collect-operation");
         self.counters.num_passes += 1;
 
@@ -97,8 +123,11 @@ impl GarbageCollector {
         };
         info!(index_ids=%index_metadatas.iter().map(|im| &im.index_id).join(", "), "Garbage collecting indexes.");
 
+        // The split files of read-only indexes, such as indexes attached from a snapshot, are never
+        // deleted.
         let index_ids_to_storage_iter = index_metadatas
             .into_iter()
+            .filter(|index_metadata| !index_metadata.read_only)
             .filter_map(|index_metadata| {
                 match self.storage_resolver.resolve(&index_metadata.index_uri) {
                     Ok(storage) => Some((index_metadata.index_id, storage)),
@@ -113,12 +142,13 @@ impl GarbageCollector {
         let run_gc_tasks: Vec<_> = index_ids_to_storage_iter
             .map(|(index_id, storage)| {
                 let moved_metastore = self.metastore.clone();
+                let staged_grace_period = self.staged_grace_period;
                 async move {
                     let run_gc_result = run_garbage_collect(
                         &index_id,
                         storage,
                         moved_metastore,
-                        STAGED_GRACE_PERIOD,
+                        staged_grace_period,
                         DELETION_GRACE_PERIOD,
                         false,
                         Some(ctx),
@@ -141,6 +171,14 @@ impl GarbageCollector {
                 Err(error) => {
                     self.counters.num_failed_gc_run_on_index += 1;
                     error!(index_id=%index_id, error=?error, "Failed to run garbage collection on index.");
+                    record_event(
+                        Event::new(
+                            EventType::GarbageCollection,
+                            "Failed to run garbage collection on index.",
+                        )
+                        .with_index_id(&index_id)
+                        .with_attribute("error", format!("{:?}", error)),
+                    );
                     continue;
                 }
             };
@@ -152,11 +190,18 @@ impl GarbageCollector {
                     .collect();
                 info!(index_id=%index_id, deleted_files=?deleted_files, "gc-delete");
 
-                self.counters.num_deleted_files += deleted_file_entries.len();
-                self.counters.num_deleted_bytes += deleted_file_entries
+                let num_deleted_bytes = deleted_file_entries
                     .iter()
                     .map(|entry| entry.file_size_in_bytes as usize)
                     .sum::<usize>();
+                self.counters.num_deleted_files += deleted_file_entries.len();
+                self.counters.num_deleted_bytes += num_deleted_bytes;
+                record_event(
+                    Event::new(EventType::GarbageCollection, "Deleted files of index.")
+                        .with_index_id(&index_id)
+                        .with_attribute("num_deleted_files", deleted_file_entries.len())
+                        .with_attribute("num_deleted_bytes", num_deleted_bytes),
+                );
             }
         }
     }
@@ -203,10 +248,12 @@ mod tests {
     use std::path::Path;
 
     use quickwit_actors::Universe;
+    use quickwit_config::MaintenanceWindow;
     use quickwit_metastore::{
         IndexMetadata, MetastoreError, MockMetastore, Split, SplitMetadata, SplitState,
     };
     use quickwit_storage::MockStorage;
+    use time::OffsetDateTime;
 
     use super::*;
 
@@ -336,6 +383,80 @@ mod tests {
         assert_eq!(state_after_initialization.num_deleted_bytes, 60);
     }
 
+    #[tokio::test]
+    async fn test_garbage_collect_outside_gc_windows() {
+        let storage_resolver = StorageUriResolver::for_test();
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore.expect_list_indexes_metadatas().times(0);
+
+        // This window opened once in 2000 and never again.
+        let gc_windows = MaintenanceWindows::new(vec![MaintenanceWindow {
+            start: "0 0 0 1 1 * 2000".to_string(),
+            duration: "1 hour".to_string(),
+        }]);
+        let garbage_collect_actor =
+            GarbageCollector::new(Arc::new(mock_metastore), storage_resolver)
+                .set_gc_windows(gc_windows);
+        let universe = Universe::new();
+        let (_maibox, handler) = universe.spawn_builder().spawn(garbage_collect_actor);
+
+        let state_after_initialization = handler.process_pending_and_observe().await.state;
+        assert_eq!(state_after_initialization.num_passes, 0);
+        assert_eq!(state_after_initialization.num_skipped_passes, 1);
+    }
+
+    #[tokio::test]
+    async fn test_garbage_collect_respects_staged_grace_period() {
+        let storage_resolver = StorageUriResolver::for_test();
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .times(1)
+            .returning(|| {
+                Ok(vec![IndexMetadata::for_test(
+                    "test-index",
+                    "ram://indexes/test-index",
+                )])
+            });
+        mock_metastore.expect_list_splits().times(2).returning(
+            |index_id, split_state, _time_range, _tags| {
+                assert_eq!(index_id, "test-index");
+                let splits = match split_state {
+                    SplitState::Staged => {
+                        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+                        let mut splits = make_splits(&["a", "b"], SplitState::Staged);
+                        splits[0].update_timestamp = now_timestamp - 2 * 3600;
+                        splits[1].update_timestamp = now_timestamp;
+                        splits
+                    }
+                    SplitState::MarkedForDeletion => Vec::new(),
+                    _ => panic!("only Staged and MarkedForDeletion expected."),
+                };
+                Ok(splits)
+            },
+        );
+        mock_metastore
+            .expect_mark_splits_for_deletion()
+            .times(1)
+            .returning(|index_id, split_ids| {
+                assert_eq!(index_id, "test-index");
+                assert_eq!(split_ids, vec!["a"]);
+                Ok(())
+            });
+        mock_metastore.expect_delete_splits().times(0);
+
+        let garbage_collect_actor =
+            GarbageCollector::new(Arc::new(mock_metastore), storage_resolver)
+                .set_staged_grace_period(Duration::from_secs(3600));
+        let universe = Universe::new();
+        let (_maibox, handler) = universe.spawn_builder().spawn(garbage_collect_actor);
+
+        let state_after_initialization = handler.process_pending_and_observe().await.state;
+        assert_eq!(state_after_initialization.num_passes, 1);
+        assert_eq!(state_after_initialization.num_successful_gc_run_on_index, 1);
+        assert_eq!(state_after_initialization.num_deleted_files, 0);
+    }
+
     #[tokio::test]
     async fn test_garbage_collect_get_calls_repeatedly() {
         let storage_resolver = StorageUriResolver::for_test();
@@ -532,4 +653,32 @@ mod tests {
         assert_eq!(counters.num_failed_storage_resolution, 0);
         assert_eq!(counters.num_failed_gc_run_on_index, 1);
     }
+
+    #[tokio::test]
+    async fn test_garbage_collect_skips_read_only_indexes() {
+        let storage_resolver = StorageUriResolver::for_test();
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .times(1)
+            .returning(|| {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", "ram://indexes/test-index");
+                index_metadata.read_only = true;
+                Ok(vec![index_metadata])
+            });
+        mock_metastore.expect_list_splits().times(0);
+        mock_metastore.expect_mark_splits_for_deletion().times(0);
+        mock_metastore.expect_delete_splits().times(0);
+
+        let garbage_collect_actor =
+            GarbageCollector::new(Arc::new(mock_metastore), storage_resolver);
+        let universe = Universe::new();
+        let (_maibox, handle) = universe.spawn_builder().spawn(garbage_collect_actor);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_deleted_files, 0);
+        assert_eq!(counters.num_successful_gc_run_on_index, 0);
+    }
 }
@@ -0,0 +1,450 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler};
+use quickwit_cluster::Cluster;
+use quickwit_config::service::QuickwitService;
+use quickwit_indexing::scheduler::{
+    build_indexing_plan, list_schedulable_sources, IndexerNode, IndexingPlan, IndexingTask,
+    FAILOVER_GRACE_PERIOD, INDEXING_CAPACITY_KEY, INDEXING_PLAN_KEY,
+};
+use quickwit_metastore::{Metastore, MetastoreResult};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+const RUN_INTERVAL: Duration = if cfg!(test) {
+    Duration::from_millis(100)
+} else {
+    Duration::from_secs(10)
+};
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct IndexingSchedulerState {
+    /// The number of passes the indexing scheduler has performed.
+    pub num_passes: usize,
+    /// The number of times a new indexing plan was published.
+    pub num_published_plans: usize,
+    /// The number of indexers taking part in the last indexing plan.
+    pub num_indexers: usize,
+    /// The number of indexers whose pipelines were reassigned after they left the cluster.
+    pub num_failed_over_indexers: usize,
+    /// The last indexing plan published.
+    pub indexing_plan: IndexingPlan,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+/// An actor placing the indexing pipelines of the cluster on the indexers that enabled cluster
+/// scheduling. The indexing plan is rebuilt periodically, which accounts for membership changes
+/// and source updates, and gossiped to the indexers whenever it changes.
+pub struct IndexingScheduler {
+    cluster: Arc<Cluster>,
+    metastore: Arc<dyn Metastore>,
+    /// Last time each indexer was seen in the cluster.
+    indexers_last_seen: HashMap<String, (IndexerNode, Instant)>,
+    state: IndexingSchedulerState,
+}
+
+impl IndexingScheduler {
+    pub fn new(cluster: Arc<Cluster>, metastore: Arc<dyn Metastore>) -> Self {
+        Self {
+            cluster,
+            metastore,
+            indexers_last_seen: HashMap::new(),
+            state: IndexingSchedulerState::default(),
+        }
+    }
+
+    /// Returns the indexers taking part in the indexing plan: the live indexers and the indexers
+    /// that left the cluster less than the failover grace period ago. The pipelines of the latter
+    /// stay assigned to them so that a node missing a few heartbeats does not trigger a failover.
+    fn track_indexers(
+        &mut self,
+        live_indexers: Vec<IndexerNode>,
+        now: Instant,
+    ) -> Vec<IndexerNode> {
+        for indexer in live_indexers {
+            self.indexers_last_seen
+                .insert(indexer.node_id.clone(), (indexer, now));
+        }
+        let mut failed_indexer_ids = Vec::new();
+        self.indexers_last_seen.retain(|node_id, (_, last_seen)| {
+            if now.duration_since(*last_seen) < FAILOVER_GRACE_PERIOD {
+                return true;
+            }
+            failed_indexer_ids.push(node_id.clone());
+            false
+        });
+        for node_id in failed_indexer_ids {
+            warn!(node_id=%node_id, "Indexer left the cluster, reassigning its indexing pipelines.");
+            self.state.num_failed_over_indexers += 1;
+        }
+        let mut indexers: Vec<IndexerNode> = self
+            .indexers_last_seen
+            .values()
+            .map(|(indexer, _)| indexer.clone())
+            .collect();
+        indexers.sort_by(|left, right| left.node_id.cmp(&right.node_id));
+        indexers
+    }
+
+    /// Lists the indexers advertising their indexing capacity, i.e. the indexers that enabled
+    /// cluster scheduling.
+    async fn list_indexers(&self) -> Vec<IndexerNode> {
+        self
+            .cluster
+            .ready_members_key_value(INDEXING_CAPACITY_KEY)
+            .await
+            .into_iter()
+            .filter(|(member, _)| member.available_services.contains(&QuickwitService::Indexer))
+            .filter_map(|(member, capacity_str)| match capacity_str.parse() {
+                Ok(capacity) => Some(IndexerNode {
+                    node_id: member.node_unique_id,
+                    capacity,
+                }),
+                Err(_) => {
+                    warn!(node_id=%member.node_unique_id, capacity=%capacity_str, "Failed to parse indexing capacity, ignoring indexer.");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns whether the indexers apply the plan of this node, i.e. whether it is the ready
+    /// control plane with the lowest node ID. The other control planes must not schedule: the
+    /// publish tokens they would acquire would fence the pipelines of the plan applied by the
+    /// indexers.
+    async fn is_leading_control_plane(&self) -> bool {
+        self.cluster
+            .ready_members()
+            .await
+            .into_iter()
+            .filter(|member| {
+                member
+                    .available_services
+                    .contains(&QuickwitService::ControlPlane)
+            })
+            .min_by(|left, right| left.node_unique_id.cmp(&right.node_unique_id))
+            .map(|member| member.chitchat_id() == self.cluster.node_id.id)
+            .unwrap_or(false)
+    }
+
+    /// Hands the tasks of the plan the publish token of their assignment. The tasks that stay on
+    /// the same indexer keep the token of the previous plan. The tasks assigned to a new indexer
+    /// get a new token from the metastore, which prevents the indexer previously running the task
+    /// from publishing for it anymore.
+    async fn acquire_publish_tokens(
+        &self,
+        indexing_plan: &mut IndexingPlan,
+        ctx: &ActorContext<Self>,
+    ) -> MetastoreResult<()> {
+        let previous_publish_tokens: HashMap<(&str, IndexingTask), u64> = self
+            .state
+            .indexing_plan
+            .indexing_tasks
+            .iter()
+            .flat_map(|(node_id, tasks)| {
+                tasks.iter().filter_map(move |task| {
+                    task.publish_token_opt.map(|publish_token| {
+                        (
+                            (node_id.as_str(), task.without_publish_token()),
+                            publish_token,
+                        )
+                    })
+                })
+            })
+            .collect();
+        for (node_id, tasks) in indexing_plan.indexing_tasks.iter_mut() {
+            for task in tasks.iter_mut() {
+                if let Some(publish_token) =
+                    previous_publish_tokens.get(&(node_id.as_str(), task.without_publish_token()))
+                {
+                    task.publish_token_opt = Some(*publish_token);
+                    continue;
+                }
+                let publish_token = ctx
+                    .protect_future(self.metastore.acquire_publish_token(
+                        &task.index_id,
+                        &task.source_id,
+                        &task.pipeline_ord.to_string(),
+                    ))
+                    .await?;
+                task.publish_token_opt = Some(publish_token.token);
+            }
+        }
+        Ok(())
+    }
+
+    /// Loop handler logic.
+    /// Should not return an error to prevent the actor from crashing.
+    async fn handle_inner(&mut self, ctx: &ActorContext<Self>) {
+        self.state.num_passes += 1;
+
+        if !self.is_leading_control_plane().await {
+            return;
+        }
+        let live_indexers = self.list_indexers().await;
+        let indexers = self.track_indexers(live_indexers, Instant::now());
+        if indexers.is_empty() && self.state.indexing_plan == IndexingPlan::default() {
+            return;
+        }
+        let index_metadatas = match ctx
+            .protect_future(self.metastore.list_indexes_metadatas())
+            .await
+        {
+            Ok(index_metadatas) => index_metadatas,
+            Err(error) => {
+                error!(error=?error, "Failed to list indexes from the metastore.");
+                return;
+            }
+        };
+        let sources = list_schedulable_sources(&index_metadatas);
+        let mut indexing_plan = build_indexing_plan(&indexers, &sources, &self.state.indexing_plan);
+        self.state.num_indexers = indexers.len();
+
+        if let Err(error) = self.acquire_publish_tokens(&mut indexing_plan, ctx).await {
+            error!(error=?error, "Failed to acquire publish tokens.");
+            return;
+        }
+
+        if indexing_plan == self.state.indexing_plan {
+            return;
+        }
+        let indexing_plan_json = match serde_json::to_string(&indexing_plan) {
+            Ok(indexing_plan_json) => indexing_plan_json,
+            Err(error) => {
+                error!(error=?error, "Failed to serialize indexing plan.");
+                return;
+            }
+        };
+        info!(
+            num_indexers = indexers.len(),
+            num_indexing_tasks = indexing_plan.num_indexing_tasks(),
+            "Publishing new indexing plan."
+        );
+        self.cluster
+            .set_key_value(INDEXING_PLAN_KEY, indexing_plan_json)
+            .await;
+        self.state.indexing_plan = indexing_plan;
+        self.state.num_published_plans += 1;
+    }
+}
+
+#[async_trait]
+impl Actor for IndexingScheduler {
+    type ObservableState = IndexingSchedulerState;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.state.clone()
+    }
+
+    fn name(&self) -> String {
+        "IndexingScheduler".to_string()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle(Loop, ctx).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for IndexingScheduler {
+    type Reply = ();
+
+    async fn handle(&mut self, _: Loop, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle_inner(ctx).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use chitchat::transport::ChannelTransport;
+    use quickwit_actors::Universe;
+    use quickwit_cluster::{create_cluster_for_test, create_cluster_for_test_with_id};
+    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_metastore::checkpoint::PublishToken;
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_indexing_scheduler_publishes_indexing_plan() {
+        let transport = ChannelTransport::default();
+        let cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["control_plane", "indexer"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        cluster
+            .set_key_value(INDEXING_CAPACITY_KEY, 100_000_000u64)
+            .await;
+
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .returning(|| {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", "ram://indexes/test-index");
+                let source_config = SourceConfig {
+                    source_id: "test-source".to_string(),
+                    num_pipelines: 2,
+                    expected_throughput: None,
+                    rate_limit_per_sec: None,
+                    transform: Vec::new(),
+                    input_format: None,
+                    csv_options: None,
+                    enabled: true,
+                    source_params: SourceParams::void(),
+                };
+                index_metadata
+                    .sources
+                    .insert(source_config.source_id.clone(), source_config);
+                Ok(vec![index_metadata])
+            });
+        // The tokens are only acquired when the tasks are assigned, not on every pass.
+        mock_metastore
+            .expect_acquire_publish_token()
+            .times(2)
+            .returning(|index_id, source_id, partition_set_id| {
+                assert_eq!(index_id, "test-index");
+                assert_eq!(source_id, "test-source");
+                Ok(PublishToken {
+                    partition_set_id: partition_set_id.to_string(),
+                    token: 7,
+                })
+            });
+        let indexing_scheduler = IndexingScheduler::new(cluster.clone(), Arc::new(mock_metastore));
+        let universe = Universe::new();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(indexing_scheduler);
+
+        let state = handle.process_pending_and_observe().await.state;
+        assert_eq!(state.num_passes, 1);
+        assert_eq!(state.num_published_plans, 1);
+        assert_eq!(state.num_indexers, 1);
+        assert_eq!(state.indexing_plan.num_indexing_tasks(), 2);
+        assert!(state
+            .indexing_plan
+            .indexing_tasks
+            .values()
+            .flatten()
+            .all(|task| task.publish_token_opt == Some(7)));
+
+        let (_, indexing_plan_json) = cluster
+            .ready_members_key_value(INDEXING_PLAN_KEY)
+            .await
+            .pop()
+            .unwrap();
+        let indexing_plan: IndexingPlan = serde_json::from_str(&indexing_plan_json).unwrap();
+        assert_eq!(indexing_plan, state.indexing_plan);
+
+        // The plan is only published again when it changes.
+        universe.simulate_time_shift(RUN_INTERVAL).await;
+        let state = handle.process_pending_and_observe().await.state;
+        assert_eq!(state.num_passes, 2);
+        assert_eq!(state.num_published_plans, 1);
+    }
+
+    #[tokio::test]
+    async fn test_indexing_scheduler_leader_is_elected_among_control_planes() {
+        let transport = ChannelTransport::default();
+        // The janitor has the lowest node ID but does not run the indexing scheduler.
+        let janitor_services = HashSet::from([QuickwitService::Janitor]);
+        let janitor_cluster = Arc::new(
+            create_cluster_for_test_with_id(
+                1,
+                "test-cluster".to_string(),
+                Vec::new(),
+                &janitor_services,
+                &transport,
+                true,
+            )
+            .await
+            .unwrap(),
+        );
+        let control_plane_services = HashSet::from([QuickwitService::ControlPlane]);
+        let control_plane_cluster = Arc::new(
+            create_cluster_for_test_with_id(
+                2,
+                "test-cluster".to_string(),
+                vec![janitor_cluster.gossip_listen_addr.to_string()],
+                &control_plane_services,
+                &transport,
+                true,
+            )
+            .await
+            .unwrap(),
+        );
+        for cluster in [&janitor_cluster, &control_plane_cluster] {
+            cluster
+                .wait_for_members(|members| members.len() == 2, Duration::from_secs(10))
+                .await
+                .unwrap();
+        }
+        let control_plane_scheduler =
+            IndexingScheduler::new(control_plane_cluster, Arc::new(MockMetastore::default()));
+        assert!(control_plane_scheduler.is_leading_control_plane().await);
+
+        let janitor_scheduler =
+            IndexingScheduler::new(janitor_cluster, Arc::new(MockMetastore::default()));
+        assert!(!janitor_scheduler.is_leading_control_plane().await);
+    }
+
+    #[tokio::test]
+    async fn test_indexing_scheduler_fails_over_indexers_after_grace_period() {
+        let transport = ChannelTransport::default();
+        let cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["control_plane"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        let mut indexing_scheduler =
+            IndexingScheduler::new(cluster, Arc::new(MockMetastore::default()));
+        let indexer = |node_id: &str| IndexerNode {
+            node_id: node_id.to_string(),
+            capacity: 100,
+        };
+        let now = Instant::now();
+        let indexers =
+            indexing_scheduler.track_indexers(vec![indexer("node-1"), indexer("node-2")], now);
+        assert_eq!(indexers, vec![indexer("node-1"), indexer("node-2")]);
+
+        // `node-2` missed a few heartbeats: its pipelines stay assigned to it.
+        let indexers = indexing_scheduler
+            .track_indexers(vec![indexer("node-1")], now + FAILOVER_GRACE_PERIOD / 2);
+        assert_eq!(indexers, vec![indexer("node-1"), indexer("node-2")]);
+        assert_eq!(indexing_scheduler.state.num_failed_over_indexers, 0);
+
+        // `node-2` is still missing after the grace period: its pipelines are reassigned.
+        let indexers =
+            indexing_scheduler.track_indexers(vec![indexer("node-1")], now + FAILOVER_GRACE_PERIOD);
+        assert_eq!(indexers, vec![indexer("node-1")]);
+        assert_eq!(indexing_scheduler.state.num_failed_over_indexers, 1);
+    }
+}
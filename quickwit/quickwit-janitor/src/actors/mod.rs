@@ -17,12 +17,22 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod alert_evaluator;
+mod compaction_pipeline;
 mod delete_task_pipeline;
 mod delete_task_planner;
 mod delete_task_service;
 mod garbage_collector;
+mod indexing_scheduler;
 mod retention_policy_executor;
+mod split_compactor;
+mod split_tiering_executor;
 
+pub use alert_evaluator::AlertEvaluator;
+pub use compaction_pipeline::CompactionPipeline;
 pub use delete_task_service::DeleteTaskService;
 pub use garbage_collector::GarbageCollector;
+pub use indexing_scheduler::IndexingScheduler;
 pub use retention_policy_executor::RetentionPolicyExecutor;
+pub use split_compactor::SplitCompactor;
+pub use split_tiering_executor::SplitTieringExecutor;
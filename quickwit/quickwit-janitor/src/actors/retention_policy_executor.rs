@@ -28,6 +28,7 @@ use quickwit_metastore::{IndexMetadata, Metastore};
 use serde::Serialize;
 use tracing::{debug, error, info};
 
+use crate::metrics::JANITOR_METRICS;
 use crate::retention_policy_execution::run_execute_retention_policy;
 
 const RUN_INTERVAL: Duration = Duration::from_secs(60 * 60); // 1 hours
@@ -42,6 +43,9 @@ pub struct RetentionPolicyExecutorCounters {
 
     /// The number of expired splits.
     pub num_expired_splits: usize,
+
+    /// The number of bytes of the expired splits.
+    pub num_reclaimed_bytes: u64,
 }
 
 #[derive(Debug)]
@@ -204,7 +208,26 @@ impl Handler<Execute> for RetentionPolicyExecutor {
         )
         .await;
         match execution_result {
-            Ok(splits) => self.counters.num_expired_splits += splits.len(),
+            Ok(splits) => {
+                let num_reclaimed_bytes: u64 =
+                    splits.iter().map(|split| split.footer_offsets.end).sum();
+                info!(index_id=%message.index_id, num_expired_splits=%splits.len(), num_reclaimed_bytes=%num_reclaimed_bytes, "retention-policy-execute-operation-done");
+                self.counters.num_expired_splits += splits.len();
+                self.counters.num_reclaimed_bytes += num_reclaimed_bytes;
+
+                JANITOR_METRICS
+                    .retention_num_expired_splits_total
+                    .with_label_values(&[&message.index_id])
+                    .inc_by(splits.len() as u64);
+                JANITOR_METRICS
+                    .retention_reclaimed_bytes_total
+                    .with_label_values(&[&message.index_id])
+                    .inc_by(num_reclaimed_bytes);
+                JANITOR_METRICS
+                    .retention_last_run_reclaimed_bytes
+                    .with_label_values(&[&message.index_id])
+                    .set(num_reclaimed_bytes as i64);
+            }
             Err(error) => {
                 error!(index_id=%message.index_id, error=?error, "Failed to execute the retention policy on the index.")
             }
@@ -468,6 +491,7 @@ mod tests {
         let counters = handle.process_pending_and_observe().await.state;
         assert_eq!(counters.num_execution_passes, 2);
         assert_eq!(counters.num_expired_splits, 2);
+        assert_eq!(counters.num_reclaimed_bytes, 40);
 
         Ok(())
     }
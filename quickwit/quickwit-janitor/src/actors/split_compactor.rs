@@ -0,0 +1,293 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, ActorHandle, Handler};
+use quickwit_indexing::merge_policy::MergeOperation;
+use quickwit_metastore::{IndexMetadata, Metastore, SplitState};
+use quickwit_storage::StorageUriResolver;
+use serde::Serialize;
+use tantivy::Inventory;
+use time::OffsetDateTime;
+use tracing::{error, info};
+
+use super::compaction_pipeline::CompactionPipeline;
+use crate::split_compaction::plan_compaction_operations;
+
+pub const COMPACTION_DIR_NAME: &str = "compaction";
+
+const RUN_INTERVAL: Duration = Duration::from_secs(10 * 60); // 10 minutes
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SplitCompactorCounters {
+    /// The number of passes the split compactor has performed.
+    pub num_passes: usize,
+    /// The number of compaction operations scheduled.
+    pub num_scheduled_operations: usize,
+    /// The number of compaction pipelines currently running.
+    pub num_running_pipelines: usize,
+}
+
+struct CompactionPipelineEntry {
+    handle: ActorHandle<CompactionPipeline>,
+    /// Inventory of the ongoing compaction operations of the pipeline. An operation is dropped
+    /// after the publish of the compacted split.
+    ongoing_operations_inventory: Inventory<MergeOperation>,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+/// An actor detecting indexes with a pathological split distribution, i.e. a large number of tiny
+/// splits, and compacting them with a dedicated merge pipeline. Compaction pipelines are spawned on
+/// demand and shut down once they have no ongoing operation left.
+pub struct SplitCompactor {
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: StorageUriResolver,
+    data_dir_path: PathBuf,
+    max_concurrent_split_uploads: usize,
+    pipelines: HashMap<String, CompactionPipelineEntry>,
+    counters: SplitCompactorCounters,
+}
+
+impl SplitCompactor {
+    pub fn new(
+        metastore: Arc<dyn Metastore>,
+        storage_resolver: StorageUriResolver,
+        data_dir_path: PathBuf,
+        max_concurrent_split_uploads: usize,
+    ) -> Self {
+        Self {
+            metastore,
+            storage_resolver,
+            data_dir_path,
+            max_concurrent_split_uploads,
+            pipelines: HashMap::new(),
+            counters: SplitCompactorCounters::default(),
+        }
+    }
+
+    /// Loop handler logic.
+    /// Should not return an error to prevent the actor from crashing.
+    async fn handle_inner(&mut self, ctx: &ActorContext<Self>) {
+        self.counters.num_passes += 1;
+
+        let index_metadatas = match self.metastore.list_indexes_metadatas().await {
+            Ok(index_metadatas) => index_metadatas,
+            Err(error) => {
+                error!(error=?error, "Failed to list indexes from the metastore.");
+                return;
+            }
+        };
+        let mut active_index_ids = HashSet::new();
+
+        for index_metadata in index_metadatas {
+            // The splits of read-only indexes are never rewritten.
+            if index_metadata.read_only {
+                continue;
+            }
+            let index_id = index_metadata.index_id.clone();
+            match self.compact_index(index_metadata, ctx).await {
+                Ok(num_operations) if num_operations > 0 => {
+                    active_index_ids.insert(index_id);
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    error!(index_id=%index_id, error=?error, "Failed to compact index.");
+                }
+            }
+        }
+        // Shut down the pipelines of deleted or healthy indexes once their ongoing operations are
+        // complete.
+        let idle_index_ids: Vec<String> = self
+            .pipelines
+            .iter()
+            .filter(|(index_id, entry)| {
+                !active_index_ids.contains(*index_id)
+                    && entry.ongoing_operations_inventory.list().is_empty()
+            })
+            .map(|(index_id, _)| index_id.clone())
+            .collect();
+        for index_id in idle_index_ids {
+            if let Some(entry) = self.pipelines.remove(&index_id) {
+                info!(index_id=%index_id, "Shutting down idle compaction pipeline.");
+                entry.handle.quit().await;
+            }
+        }
+        self.counters.num_running_pipelines = self.pipelines.len();
+    }
+
+    /// Plans and schedules the compaction operations of an index. Returns the number of scheduled
+    /// operations.
+    async fn compact_index(
+        &mut self,
+        index_metadata: IndexMetadata,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<usize> {
+        let index_id = index_metadata.index_id;
+        let published_splits = ctx
+            .protect_future(self.metastore.list_splits(
+                &index_id,
+                SplitState::Published,
+                None,
+                None,
+            ))
+            .await?;
+        let ongoing_split_ids: HashSet<String> = self
+            .pipelines
+            .get(&index_id)
+            .map(|entry| {
+                entry
+                    .ongoing_operations_inventory
+                    .list()
+                    .iter()
+                    .flat_map(|merge_operation| merge_operation.splits_as_slice())
+                    .map(|split| split.split_id().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        let merge_operations =
+            plan_compaction_operations(published_splits, &ongoing_split_ids, now_timestamp);
+
+        if merge_operations.is_empty() {
+            return Ok(0);
+        }
+        info!(index_id=%index_id, num_operations=%merge_operations.len(), "Index has too many tiny splits, scheduling compaction.");
+
+        if !self.pipelines.contains_key(&index_id) {
+            let index_storage = self.storage_resolver.resolve(&index_metadata.index_uri)?;
+            let pipeline = CompactionPipeline::new(
+                index_id.clone(),
+                self.metastore.clone(),
+                index_storage,
+                self.data_dir_path.join(COMPACTION_DIR_NAME),
+                self.max_concurrent_split_uploads,
+            );
+            let (_pipeline_mailbox, pipeline_handle) = ctx.spawn_actor().spawn(pipeline);
+            let entry = CompactionPipelineEntry {
+                handle: pipeline_handle,
+                ongoing_operations_inventory: Inventory::default(),
+            };
+            self.pipelines.insert(index_id.clone(), entry);
+        }
+        let entry = self
+            .pipelines
+            .get(&index_id)
+            .expect("The compaction pipeline should have been spawned.");
+        let num_operations = merge_operations.len();
+
+        for merge_operation in merge_operations {
+            let tracked_merge_operation = entry.ongoing_operations_inventory.track(merge_operation);
+            ctx.send_message(entry.handle.mailbox(), tracked_merge_operation)
+                .await?;
+        }
+        self.counters.num_scheduled_operations += num_operations;
+        Ok(num_operations)
+    }
+}
+
+#[async_trait]
+impl Actor for SplitCompactor {
+    type ObservableState = SplitCompactorCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "SplitCompactor".to_string()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle(Loop, ctx).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for SplitCompactor {
+    type Reply = ();
+
+    async fn handle(&mut self, _: Loop, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle_inner(ctx).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_metastore::{IndexMetadata, MockMetastore, Split, SplitMetadata};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_split_compactor_ignores_healthy_indexes() {
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .times(1)
+            .returning(|| {
+                Ok(vec![IndexMetadata::for_test(
+                    "test-index",
+                    "ram://indexes/test-index",
+                )])
+            });
+        mock_metastore.expect_list_splits().times(1).returning(
+            |index_id, split_state, _time_range, _tags| {
+                assert_eq!(index_id, "test-index");
+                assert_eq!(split_state, SplitState::Published);
+                let splits = (0..10)
+                    .map(|split_ord| Split {
+                        split_metadata: SplitMetadata {
+                            split_id: format!("split-{split_ord}"),
+                            footer_offsets: 0..1_000,
+                            ..Default::default()
+                        },
+                        split_state: SplitState::Published,
+                        update_timestamp: 0,
+                        publish_timestamp: Some(0),
+                    })
+                    .collect();
+                Ok(splits)
+            },
+        );
+        let temp_dir = tempfile::tempdir().unwrap();
+        let split_compactor = SplitCompactor::new(
+            Arc::new(mock_metastore),
+            StorageUriResolver::for_test(),
+            temp_dir.path().to_path_buf(),
+            2,
+        );
+        let universe = Universe::new();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(split_compactor);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_scheduled_operations, 0);
+        assert_eq!(counters.num_running_pipelines, 0);
+    }
+}
@@ -0,0 +1,540 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler};
+use quickwit_common::uri::Uri;
+use quickwit_indexing::merge_policy::merge_policy_from_settings;
+use quickwit_metastore::{IndexMetadata, Metastore, Split, SplitState, SplitStorageTier};
+use quickwit_storage::{FilePayload, Storage, StorageUriResolver};
+use serde::Serialize;
+use time::OffsetDateTime;
+use tracing::{error, info, warn};
+
+use crate::metrics::JANITOR_METRICS;
+use crate::split_tiering::{plan_tiering_operations, TieringOperation};
+
+const RUN_INTERVAL: Duration = Duration::from_secs(60 * 60); // 1 hour
+
+/// Searchers may still read a split from its previous storage tier until they learn about its new
+/// location, so the previous copy of the split is only deleted after this grace period.
+const SOURCE_FILE_DELETION_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60); // 10 minutes
+
+/// Name of the directory, under the data directory, where the split files are downloaded before
+/// being uploaded to their target storage tier.
+pub const SPLIT_TIERING_DIR_NAME: &str = "split-tiering";
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SplitTieringExecutorCounters {
+    /// The number of passes the split tiering executor has performed.
+    pub num_passes: usize,
+    /// The number of splits moved to the warm storage tier.
+    pub num_demoted_splits: usize,
+    /// The number of splits moved back to the hot storage tier.
+    pub num_promoted_splits: usize,
+    /// The number of failed split moves.
+    pub num_failed_operations: usize,
+    /// The number of split copies deleted from their previous storage tier.
+    pub num_deleted_source_files: usize,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+/// An actor moving the splits of the indexes with a tiering policy between the index storage and
+/// a warm storage, based on the access statistics reported by the searchers.
+pub struct SplitTieringExecutor {
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: StorageUriResolver,
+    data_dir_path: PathBuf,
+    counters: SplitTieringExecutorCounters,
+}
+
+impl SplitTieringExecutor {
+    pub fn new(
+        metastore: Arc<dyn Metastore>,
+        storage_resolver: StorageUriResolver,
+        data_dir_path: PathBuf,
+    ) -> Self {
+        Self {
+            metastore,
+            storage_resolver,
+            data_dir_path,
+            counters: SplitTieringExecutorCounters::default(),
+        }
+    }
+
+    /// Loop handler logic.
+    /// Should not return an error to prevent the actor from crashing.
+    async fn handle_inner(&mut self, ctx: &ActorContext<Self>) {
+        self.counters.num_passes += 1;
+
+        let index_metadatas = match self.metastore.list_indexes_metadatas().await {
+            Ok(index_metadatas) => index_metadatas,
+            Err(error) => {
+                error!(error=?error, "Failed to list indexes from the metastore.");
+                return;
+            }
+        };
+        for index_metadata in index_metadatas {
+            // We only care about indexes with a tiering policy configured. The splits of
+            // read-only indexes are never moved.
+            if index_metadata.tiering_policy.is_none() || index_metadata.read_only {
+                continue;
+            }
+            let index_id = index_metadata.index_id.clone();
+            if let Err(error) = self.tier_index_splits(index_metadata, ctx).await {
+                error!(index_id=%index_id, error=?error, "Failed to move index splits between storage tiers.");
+            }
+        }
+    }
+
+    async fn tier_index_splits(
+        &mut self,
+        index_metadata: IndexMetadata,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let tiering_policy = index_metadata
+            .tiering_policy
+            .as_ref()
+            .expect("The index should have a tiering policy.");
+        let index_id = &index_metadata.index_id;
+        let published_splits = ctx
+            .protect_future(
+                self.metastore
+                    .list_splits(index_id, SplitState::Published, None, None),
+            )
+            .await?;
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        if let Err(error) = self
+            .delete_source_files(&index_metadata, &published_splits, now_timestamp, ctx)
+            .await
+        {
+            warn!(index_id=%index_id, error=?error, "Failed to delete splits from their previous storage tier.");
+        }
+        let merge_policy = merge_policy_from_settings(&index_metadata.indexing_settings);
+        let tiering_operations = plan_tiering_operations(
+            tiering_policy,
+            &published_splits,
+            &*merge_policy,
+            now_timestamp,
+        )?;
+        if tiering_operations.is_empty() {
+            return Ok(());
+        }
+        info!(index_id=%index_id, num_operations=%tiering_operations.len(), "Moving splits between storage tiers.");
+
+        for tiering_operation in tiering_operations {
+            let is_demotion = tiering_operation.is_demotion();
+            match self
+                .execute_tiering_operation(&index_metadata, &tiering_operation, ctx)
+                .await
+            {
+                Ok(()) => {
+                    if is_demotion {
+                        self.counters.num_demoted_splits += 1;
+                        JANITOR_METRICS
+                            .tiering_demoted_splits_total
+                            .with_label_values(&[index_id.as_str()])
+                            .inc();
+                    } else {
+                        self.counters.num_promoted_splits += 1;
+                        JANITOR_METRICS
+                            .tiering_promoted_splits_total
+                            .with_label_values(&[index_id.as_str()])
+                            .inc();
+                    }
+                }
+                Err(error) => {
+                    self.counters.num_failed_operations += 1;
+                    error!(index_id=%index_id, split_id=%tiering_operation.split_id, error=?error, "Failed to move split between storage tiers.");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the copies of the splits left in their previous storage tier once the grace period
+    /// has elapsed. The copies are found from the storage tiers recorded in the metastore, so the
+    /// copies left behind before a restart of the janitor are deleted too.
+    async fn delete_source_files(
+        &mut self,
+        index_metadata: &IndexMetadata,
+        published_splits: &[Split],
+        now_timestamp: i64,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let tiering_policy = index_metadata
+            .tiering_policy
+            .as_ref()
+            .expect("The index should have a tiering policy.");
+        let grace_period_secs = SOURCE_FILE_DELETION_GRACE_PERIOD.as_secs() as i64;
+        let storage_tiers = [
+            SplitStorageTier::Hot,
+            SplitStorageTier::Warm(tiering_policy.warm_storage_uri.clone()),
+        ];
+        for storage_tier in storage_tiers {
+            let source_split_paths: Vec<PathBuf> = published_splits
+                .iter()
+                .filter(|split| {
+                    let access_stats = &split.split_metadata.access_stats;
+                    split.split_metadata.storage_tier != storage_tier
+                        && access_stats.storage_tier_timestamp.map_or(
+                            false,
+                            |storage_tier_timestamp| {
+                                storage_tier_timestamp + grace_period_secs <= now_timestamp
+                            },
+                        )
+                })
+                .map(|split| PathBuf::from(quickwit_common::split_file(split.split_id())))
+                .collect();
+            if source_split_paths.is_empty() {
+                continue;
+            }
+            let storage_uri = storage_tier_uri(&storage_tier, &index_metadata.index_uri);
+            let storage = self.storage_resolver.resolve(&storage_uri)?;
+            let file_paths: HashSet<PathBuf> = ctx
+                .protect_future(storage.list_files())
+                .await?
+                .into_iter()
+                .map(|(file_path, _file_size_in_bytes)| file_path)
+                .collect();
+            let source_split_paths: Vec<&Path> = source_split_paths
+                .iter()
+                .filter(|split_path| file_paths.contains(*split_path))
+                .map(PathBuf::as_path)
+                .collect();
+            if source_split_paths.is_empty() {
+                continue;
+            }
+            ctx.protect_future(storage.bulk_delete(&source_split_paths))
+                .await?;
+            self.counters.num_deleted_source_files += source_split_paths.len();
+            info!(index_id=%index_metadata.index_id, storage=%storage_uri, num_files=%source_split_paths.len(), "Deleted splits from their previous storage tier.");
+        }
+        Ok(())
+    }
+
+    /// Copies the split file to its target storage tier and records its new location in the
+    /// metastore.
+    async fn execute_tiering_operation(
+        &self,
+        index_metadata: &IndexMetadata,
+        tiering_operation: &TieringOperation,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let source_storage_uri =
+            storage_tier_uri(&tiering_operation.source_tier, &index_metadata.index_uri);
+        let target_storage_uri =
+            storage_tier_uri(&tiering_operation.target_tier, &index_metadata.index_uri);
+        let source_storage = self.storage_resolver.resolve(&source_storage_uri)?;
+        let target_storage = self.storage_resolver.resolve(&target_storage_uri)?;
+
+        let split_path = PathBuf::from(quickwit_common::split_file(&tiering_operation.split_id));
+        // The split file is downloaded to the local disk and streamed from there rather than held
+        // in memory.
+        let download_dir_path = self.data_dir_path.join(SPLIT_TIERING_DIR_NAME);
+        tokio::fs::create_dir_all(&download_dir_path).await?;
+        let download_path = download_dir_path.join(&split_path);
+        let copy_result = self
+            .copy_split_file(
+                &*source_storage,
+                &*target_storage,
+                &split_path,
+                &download_path,
+                ctx,
+            )
+            .await;
+        if let Err(error) = tokio::fs::remove_file(&download_path).await {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                warn!(path=%download_path.display(), error=?error, "Failed to remove downloaded split file.");
+            }
+        }
+        copy_result?;
+        ctx.protect_future(self.metastore.update_split_storage_tier(
+            &index_metadata.index_id,
+            &tiering_operation.split_id,
+            tiering_operation.target_tier.clone(),
+        ))
+        .await?;
+        info!(
+            index_id=%index_metadata.index_id,
+            split_id=%tiering_operation.split_id,
+            source=%source_storage_uri,
+            target=%target_storage_uri,
+            "Moved split between storage tiers."
+        );
+        Ok(())
+    }
+
+    async fn copy_split_file(
+        &self,
+        source_storage: &dyn Storage,
+        target_storage: &dyn Storage,
+        split_path: &Path,
+        download_path: &Path,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        ctx.protect_future(source_storage.copy_to_file(split_path, download_path))
+            .await?;
+        let split_payload = FilePayload::open(download_path).await?;
+        ctx.protect_future(target_storage.put(split_path, Box::new(split_payload)))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Returns the URI of the storage holding the splits of a storage tier.
+fn storage_tier_uri(storage_tier: &SplitStorageTier, index_uri: &Uri) -> Uri {
+    storage_tier.storage_uri().unwrap_or(index_uri).clone()
+}
+
+#[async_trait]
+impl Actor for SplitTieringExecutor {
+    type ObservableState = SplitTieringExecutorCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "SplitTieringExecutor".to_string()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle(Loop, ctx).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for SplitTieringExecutor {
+    type Reply = ();
+
+    async fn handle(&mut self, _: Loop, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle_inner(ctx).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use quickwit_actors::Universe;
+    use quickwit_config::TieringPolicy;
+    use quickwit_metastore::{MockMetastore, Split, SplitMetadata};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_split_tiering_executor_demotes_idle_splits() {
+        let index_uri = Uri::from_well_formed("ram://indexes/test-index".to_string());
+        let warm_storage_uri = Uri::from_well_formed("ram://warm-indexes/test-index".to_string());
+        let warm_storage_tier = SplitStorageTier::Warm(warm_storage_uri.clone());
+
+        let storage_resolver = StorageUriResolver::for_test();
+        let index_storage = storage_resolver.resolve(&index_uri).unwrap();
+        index_storage
+            .put(Path::new("split-1.split"), Box::new(b"split-1".to_vec()))
+            .await
+            .unwrap();
+
+        let mut mock_metastore = MockMetastore::default();
+        let index_uri_clone = index_uri.clone();
+        let warm_storage_uri_clone = warm_storage_uri.clone();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .times(1)
+            .returning(move || {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", index_uri_clone.as_str());
+                index_metadata.tiering_policy = Some(TieringPolicy::new(
+                    warm_storage_uri_clone.clone(),
+                    "1 day".to_string(),
+                    NonZeroU64::new(10),
+                ));
+                Ok(vec![
+                    index_metadata,
+                    IndexMetadata::for_test("test-index-without-tiering", "ram://indexes/other"),
+                ])
+            });
+        mock_metastore.expect_list_splits().times(1).returning(
+            |index_id, split_state, _time_range, _tags| {
+                assert_eq!(index_id, "test-index");
+                assert_eq!(split_state, SplitState::Published);
+                let split = Split {
+                    split_metadata: SplitMetadata {
+                        split_id: "split-1".to_string(),
+                        num_docs: 10_000_000,
+                        ..Default::default()
+                    },
+                    split_state: SplitState::Published,
+                    update_timestamp: 0,
+                    publish_timestamp: Some(0),
+                };
+                Ok(vec![split])
+            },
+        );
+        let expected_storage_tier = warm_storage_tier.clone();
+        mock_metastore
+            .expect_update_split_storage_tier()
+            .times(1)
+            .returning(move |index_id, split_id, storage_tier| {
+                assert_eq!(index_id, "test-index");
+                assert_eq!(split_id, "split-1");
+                assert_eq!(storage_tier, expected_storage_tier);
+                Ok(())
+            });
+        let data_dir = tempfile::tempdir().unwrap();
+        let split_tiering_executor = SplitTieringExecutor::new(
+            Arc::new(mock_metastore),
+            storage_resolver.clone(),
+            data_dir.path().to_path_buf(),
+        );
+        let universe = Universe::new();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(split_tiering_executor);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_demoted_splits, 1);
+        assert_eq!(counters.num_promoted_splits, 0);
+        assert_eq!(counters.num_failed_operations, 0);
+
+        let warm_storage = storage_resolver.resolve(&warm_storage_uri).unwrap();
+        let split_data = warm_storage
+            .get_all(Path::new("split-1.split"))
+            .await
+            .unwrap();
+        assert_eq!(split_data.as_slice(), b"split-1");
+
+        // The copy in the index storage is only deleted after the grace period.
+        assert!(index_storage
+            .exists(Path::new("split-1.split"))
+            .await
+            .unwrap());
+        let download_dir_path = data_dir.path().join(SPLIT_TIERING_DIR_NAME);
+        assert!(!download_dir_path.join("split-1.split").exists());
+    }
+
+    #[tokio::test]
+    async fn test_split_tiering_executor_deletes_previous_tier_copies() {
+        let index_uri = Uri::from_well_formed("ram://indexes/test-index".to_string());
+        let warm_storage_uri = Uri::from_well_formed("ram://warm-indexes/test-index".to_string());
+        let warm_storage_tier = SplitStorageTier::Warm(warm_storage_uri.clone());
+
+        let storage_resolver = StorageUriResolver::for_test();
+        let index_storage = storage_resolver.resolve(&index_uri).unwrap();
+        let warm_storage = storage_resolver.resolve(&warm_storage_uri).unwrap();
+        for split_id in ["split-1", "split-2"] {
+            let split_path = PathBuf::from(quickwit_common::split_file(split_id));
+            index_storage
+                .put(&split_path, Box::new(split_id.as_bytes().to_vec()))
+                .await
+                .unwrap();
+            warm_storage
+                .put(&split_path, Box::new(split_id.as_bytes().to_vec()))
+                .await
+                .unwrap();
+        }
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+
+        let mut mock_metastore = MockMetastore::default();
+        let index_uri_clone = index_uri.clone();
+        let warm_storage_uri_clone = warm_storage_uri.clone();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .times(1)
+            .returning(move || {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", index_uri_clone.as_str());
+                index_metadata.tiering_policy = Some(TieringPolicy::new(
+                    warm_storage_uri_clone.clone(),
+                    "1 day".to_string(),
+                    NonZeroU64::new(10),
+                ));
+                Ok(vec![index_metadata])
+            });
+        mock_metastore.expect_list_splits().times(1).returning(
+            move |_index_id, _split_state, _time_range, _tags| {
+                // `split-1` was moved to the warm tier before the grace period, `split-2` within
+                // the grace period.
+                let splits = [
+                    (
+                        "split-1",
+                        now_timestamp - SOURCE_FILE_DELETION_GRACE_PERIOD.as_secs() as i64,
+                    ),
+                    ("split-2", now_timestamp),
+                ]
+                .into_iter()
+                .map(|(split_id, storage_tier_timestamp)| {
+                    let mut split_metadata = SplitMetadata {
+                        split_id: split_id.to_string(),
+                        num_docs: 10_000_000,
+                        storage_tier: warm_storage_tier.clone(),
+                        ..Default::default()
+                    };
+                    split_metadata.access_stats.storage_tier_timestamp =
+                        Some(storage_tier_timestamp);
+                    Split {
+                        split_metadata,
+                        split_state: SplitState::Published,
+                        update_timestamp: now_timestamp,
+                        publish_timestamp: Some(0),
+                    }
+                })
+                .collect();
+                Ok(splits)
+            },
+        );
+        mock_metastore.expect_update_split_storage_tier().never();
+        let data_dir = tempfile::tempdir().unwrap();
+        let split_tiering_executor = SplitTieringExecutor::new(
+            Arc::new(mock_metastore),
+            storage_resolver.clone(),
+            data_dir.path().to_path_buf(),
+        );
+        let universe = Universe::new();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(split_tiering_executor);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_deleted_source_files, 1);
+        assert_eq!(counters.num_failed_operations, 0);
+
+        assert!(!index_storage
+            .exists(Path::new("split-1.split"))
+            .await
+            .unwrap());
+        assert!(index_storage
+            .exists(Path::new("split-2.split"))
+            .await
+            .unwrap());
+        assert!(warm_storage
+            .exists(Path::new("split-1.split"))
+            .await
+            .unwrap());
+    }
+}
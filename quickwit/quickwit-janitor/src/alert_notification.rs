@@ -0,0 +1,185 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use quickwit_config::AlertChannel;
+use reqwest::redirect::Policy;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::json;
+
+const PAGERDUTY_EVENTS_API_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Transition of an alert notified to its channels.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertEvent {
+    /// The condition of the alert is met and was not met during the previous evaluation.
+    Fired,
+    /// The condition of the alert is no longer met.
+    Resolved,
+}
+
+/// Payload posted to webhooks and summarized in PagerDuty incidents.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct AlertNotification {
+    pub index_id: String,
+    pub stored_query_name: String,
+    pub event: AlertEvent,
+    pub condition: String,
+    pub num_hits: u64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+}
+
+impl AlertNotification {
+    /// Identifies the alert across its notifications. Used as PagerDuty deduplication key so
+    /// that the resolve event closes the incident opened by the trigger event.
+    fn alert_key(&self) -> String {
+        format!("{}/{}", self.index_id, self.stored_query_name)
+    }
+
+    fn summary(&self) -> String {
+        let state = match self.event {
+            AlertEvent::Fired => "fired",
+            AlertEvent::Resolved => "resolved",
+        };
+        format!(
+            "Alert `{}` {}: {} (num_hits = {}).",
+            self.alert_key(),
+            state,
+            self.condition,
+            self.num_hits
+        )
+    }
+}
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait AlertNotifier: Send + Sync + 'static {
+    /// Delivers a notification to a channel.
+    async fn notify(
+        &self,
+        channel: &AlertChannel,
+        notification: &AlertNotification,
+    ) -> anyhow::Result<()>;
+}
+
+/// Notifies webhooks and PagerDuty over HTTP.
+pub struct HttpAlertNotifier {
+    client: Client,
+}
+
+impl HttpAlertNotifier {
+    pub fn try_new() -> anyhow::Result<Self> {
+        let client = Client::builder()
+            .redirect(Policy::limited(3))
+            .timeout(Duration::from_secs(10))
+            .build()
+            .context("Failed to build alert notification HTTP client.")?;
+        Ok(HttpAlertNotifier { client })
+    }
+
+    async fn post_json(&self, url: &str, payload: &serde_json::Value) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(url)
+            .json(payload)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send alert notification to `{}`.", url))?;
+        if !response.status().is_success() {
+            bail!(
+                "Alert notification endpoint `{}` responded with status `{}`.",
+                url,
+                response.status()
+            );
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AlertNotifier for HttpAlertNotifier {
+    async fn notify(
+        &self,
+        channel: &AlertChannel,
+        notification: &AlertNotification,
+    ) -> anyhow::Result<()> {
+        match channel {
+            AlertChannel::Webhook { url } => {
+                let payload = serde_json::to_value(notification)?;
+                self.post_json(url, &payload).await
+            }
+            AlertChannel::PagerDuty { routing_key } => {
+                let payload = pagerduty_event(routing_key, notification);
+                self.post_json(PAGERDUTY_EVENTS_API_URL, &payload).await
+            }
+        }
+    }
+}
+
+/// Builds a PagerDuty Events API v2 event.
+fn pagerduty_event(routing_key: &str, notification: &AlertNotification) -> serde_json::Value {
+    let event_action = match notification.event {
+        AlertEvent::Fired => "trigger",
+        AlertEvent::Resolved => "resolve",
+    };
+    json!({
+        "routing_key": routing_key,
+        "event_action": event_action,
+        "dedup_key": notification.alert_key(),
+        "payload": {
+            "summary": notification.summary(),
+            "source": "quickwit",
+            "severity": "error",
+            "custom_details": notification,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pagerduty_event() {
+        let notification = AlertNotification {
+            index_id: "hdfs-logs".to_string(),
+            stored_query_name: "errors".to_string(),
+            event: AlertEvent::Resolved,
+            condition: "num_hits > 10".to_string(),
+            num_hits: 3,
+            start_timestamp: 1_000,
+            end_timestamp: 1_060,
+        };
+        let event = pagerduty_event("my-routing-key", &notification);
+        assert_eq!(event["routing_key"], "my-routing-key");
+        assert_eq!(event["event_action"], "resolve");
+        assert_eq!(event["dedup_key"], "hdfs-logs/errors");
+        assert_eq!(
+            event["payload"]["summary"],
+            "Alert `hdfs-logs/errors` resolved: num_hits > 10 (num_hits = 3)."
+        );
+        assert_eq!(event["payload"]["custom_details"]["event"], "resolved");
+    }
+}
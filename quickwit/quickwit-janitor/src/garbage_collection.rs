@@ -17,20 +17,23 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashSet;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
 use futures::{Future, StreamExt};
 use quickwit_actors::ActorContext;
+use quickwit_common::{parquet_sidecar_file, PARQUET_SIDECAR_DIR_NAME};
 use quickwit_metastore::{Metastore, MetastoreError, SplitMetadata, SplitState};
-use quickwit_storage::{Storage, StorageError};
+use quickwit_storage::{quickwit_storage_uri_resolver, Storage, StorageError, StorageErrorKind};
 use serde::Serialize;
 use thiserror::Error;
 use time::OffsetDateTime;
-use tracing::error;
+use tracing::{error, info, warn};
 
 use crate::actors::GarbageCollector;
+use crate::metrics::JANITOR_METRICS;
 
 const MAX_CONCURRENT_STORAGE_REQUESTS: usize = if cfg!(test) { 2 } else { 10 };
 
@@ -141,6 +144,22 @@ pub async fn run_garbage_collect(
     )
     .await?;
 
+    if !deletable_staged_splits.is_empty() {
+        let num_reaped_bytes: u64 = deletable_staged_splits
+            .iter()
+            .map(|split| split.footer_offsets.end)
+            .sum();
+        info!(index_id=%index_id, split_ids=?split_ids, num_bytes=%num_reaped_bytes, "Reaping splits stuck in the staged state.");
+        JANITOR_METRICS
+            .gc_num_reaped_staged_splits_total
+            .with_label_values(&[index_id])
+            .inc_by(deletable_staged_splits.len() as u64);
+        JANITOR_METRICS
+            .gc_reaped_staged_bytes_total
+            .with_label_values(&[index_id])
+            .inc_by(num_reaped_bytes);
+    }
+
     // We wait another 2 minutes until the split is actually deleted.
     let grace_period_deletion =
         OffsetDateTime::now_utc().unix_timestamp() - deletion_grace_period.as_secs() as i64;
@@ -167,6 +186,92 @@ pub async fn run_garbage_collect(
     Ok(deleted_files)
 }
 
+/// Lists the split files present on the storage that are not referenced by the metastore. Such
+/// files are left behind, for instance, when a node crashes while deleting splits.
+///
+/// Only the split files located at the root of the index directory and the Parquet sidecar files of
+/// the splits are considered. The storage is
+/// listed before the metastore: since a split is always staged before its file is uploaded, a
+/// split being uploaded concurrently cannot be reported as orphan.
+///
+/// * `index_id` - The target index id.
+/// * `storage` - The storage managing the target index.
+/// * `metastore` - The metastore managing the target index.
+pub async fn list_orphan_files(
+    index_id: &str,
+    storage: &dyn Storage,
+    metastore: &dyn Metastore,
+) -> anyhow::Result<Vec<FileEntry>> {
+    let candidate_file_entries: Vec<FileEntry> = storage
+        .list_files()
+        .await?
+        .into_iter()
+        .filter_map(|(path, file_size_in_bytes)| {
+            let file_name = path.to_str()?;
+            let is_split_file =
+                path.parent() == Some(Path::new("")) && file_name.ends_with(".split");
+            let is_bloom_filter_file =
+                path.parent() == Some(Path::new("")) && file_name.ends_with(".bloom");
+            let is_sidecar_file = path.parent() == Some(Path::new(PARQUET_SIDECAR_DIR_NAME))
+                && file_name.ends_with(".parquet");
+            if !is_split_file && !is_bloom_filter_file && !is_sidecar_file {
+                return None;
+            }
+            Some(FileEntry {
+                file_name: file_name.to_string(),
+                file_size_in_bytes,
+            })
+        })
+        .collect();
+    let referenced_file_names: HashSet<String> = metastore
+        .list_all_splits(index_id)
+        .await?
+        .iter()
+        .flat_map(|split| {
+            [
+                quickwit_common::split_file(split.split_id()),
+                quickwit_common::bloom_filter_file(split.split_id()),
+                parquet_sidecar_file(split.split_id()),
+            ]
+        })
+        .collect();
+    let orphan_file_entries = candidate_file_entries
+        .into_iter()
+        .filter(|file_entry| !referenced_file_names.contains(&file_entry.file_name))
+        .collect();
+    Ok(orphan_file_entries)
+}
+
+/// Deletes the orphan files returned by [`list_orphan_files`] from the storage.
+///
+/// * `storage` - The storage managing the target index.
+/// * `orphan_files` - The list of orphan files to delete.
+pub async fn delete_orphan_files(
+    storage: &dyn Storage,
+    orphan_files: Vec<FileEntry>,
+) -> anyhow::Result<Vec<FileEntry>> {
+    let paths: Vec<&Path> = orphan_files
+        .iter()
+        .map(|file_entry| Path::new(&file_entry.file_name))
+        .collect();
+    storage.bulk_delete(&paths).await?;
+    Ok(orphan_files)
+}
+
+/// Returns the storage holding the file of a split: the index storage, unless the split has been
+/// moved to a warm storage tier.
+fn split_storage(
+    split: &SplitMetadata,
+    index_storage: &Arc<dyn Storage>,
+) -> Result<Arc<dyn Storage>, StorageError> {
+    match split.storage_tier.storage_uri() {
+        Some(warm_storage_uri) => quickwit_storage_uri_resolver()
+            .resolve(warm_storage_uri)
+            .map_err(|error| StorageErrorKind::Service.with_error(error)),
+        None => Ok(index_storage.clone()),
+    }
+}
+
 /// Delete a list of splits from the storage and the metastore.
 /// It should leave the index and the metastore in good state.
 ///
@@ -193,7 +298,22 @@ pub async fn delete_splits_with_files(
                 let file_entry = FileEntry::from(&split);
                 let split_filename = quickwit_common::split_file(split.split_id());
                 let split_path = Path::new(&split_filename);
-                let delete_result = moved_storage.delete(split_path).await;
+                // The file of a warm split lives in the warm storage, its bloom filters remain in
+                // the index storage.
+                let delete_result = match split_storage(&split, &moved_storage) {
+                    Ok(split_storage) => split_storage.delete(split_path).await,
+                    Err(error) => Err(error),
+                };
+                if delete_result.is_ok() {
+                    // Leftover bloom filters are harmless and reported by the orphan files scan.
+                    let bloom_filter_filename = quickwit_common::bloom_filter_file(split.split_id());
+                    if let Err(error) = moved_storage
+                        .delete(Path::new(&bloom_filter_filename))
+                        .await
+                    {
+                        warn!(error = ?error, split_id = split.split_id(), "Failed to delete split bloom filters.");
+                    }
+                }
                 if let Some(ctx) = ctx_opt {
                     ctx.record_progress();
                 }
@@ -233,12 +353,13 @@ pub async fn delete_splits_with_files(
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
     use std::time::Duration;
 
     use quickwit_metastore::{metastore_for_test, IndexMetadata, SplitMetadata, SplitState};
     use quickwit_storage::storage_for_test;
 
-    use crate::run_garbage_collect;
+    use crate::{delete_orphan_files, list_orphan_files, run_garbage_collect};
 
     #[tokio::test]
     async fn test_run_gc_expires_stale_staged_splits_after_grace_period() {
@@ -341,6 +462,11 @@ mod tests {
             .mark_splits_for_deletion(index_id, &[split_id])
             .await
             .unwrap();
+        let bloom_filter_file = quickwit_common::bloom_filter_file(split_id);
+        storage
+            .put(Path::new(&bloom_filter_file), Box::new(b"bloom".to_vec()))
+            .await
+            .unwrap();
 
         assert_eq!(
             metastore
@@ -394,5 +520,67 @@ mod tests {
                 .len(),
             0
         );
+        assert!(!storage.exists(Path::new(&bloom_filter_file)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_delete_orphan_files() {
+        let storage = storage_for_test();
+        let metastore = metastore_for_test();
+
+        let index_id = "test-orphan-files--index";
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+        metastore.create_index(index_metadata).await.unwrap();
+
+        let split_metadata = SplitMetadata {
+            index_id: index_id.to_string(),
+            split_id: "referenced-split".to_string(),
+            ..Default::default()
+        };
+        metastore
+            .stage_split(index_id, split_metadata)
+            .await
+            .unwrap();
+        for (file_name, payload) in [
+            ("referenced-split.split", b"referenced".as_ref()),
+            ("orphan-split.split", b"orphan".as_ref()),
+            ("referenced-split.bloom", b"referenced".as_ref()),
+            ("orphan-split.bloom", b"orphan-bloom".as_ref()),
+            ("metastore.json", b"{}".as_ref()),
+            ("sub-dir/nested-split.split", b"nested".as_ref()),
+            ("parquet/referenced-split.parquet", b"referenced".as_ref()),
+            ("parquet/orphan-split.parquet", b"orphan-sidecar".as_ref()),
+        ] {
+            storage
+                .put(Path::new(file_name), Box::new(payload.to_vec()))
+                .await
+                .unwrap();
+        }
+        let mut orphan_files = list_orphan_files(index_id, &*storage, &*metastore)
+            .await
+            .unwrap();
+        orphan_files.sort_by(|left, right| left.file_name.cmp(&right.file_name));
+        assert_eq!(orphan_files.len(), 3);
+        assert_eq!(orphan_files[0].file_name, "orphan-split.bloom");
+        assert_eq!(orphan_files[0].file_size_in_bytes, 12);
+        assert_eq!(orphan_files[1].file_name, "orphan-split.split");
+        assert_eq!(orphan_files[1].file_size_in_bytes, 6);
+        assert_eq!(orphan_files[2].file_name, "parquet/orphan-split.parquet");
+        assert_eq!(orphan_files[2].file_size_in_bytes, 14);
+
+        delete_orphan_files(&*storage, orphan_files).await.unwrap();
+        assert!(!storage
+            .exists(Path::new("orphan-split.split"))
+            .await
+            .unwrap());
+        assert!(storage
+            .exists(Path::new("referenced-split.split"))
+            .await
+            .unwrap());
+        assert!(list_orphan_files(index_id, &*storage, &*metastore)
+            .await
+            .unwrap()
+            .is_empty());
     }
 }
@@ -19,24 +19,36 @@
 
 use quickwit_actors::{ActorHandle, Mailbox};
 
-use crate::actors::{DeleteTaskService, GarbageCollector, RetentionPolicyExecutor};
+use crate::actors::{
+    AlertEvaluator, DeleteTaskService, GarbageCollector, RetentionPolicyExecutor, SplitCompactor,
+    SplitTieringExecutor,
+};
 
 pub struct JanitorService {
     _garbage_collector_handle: ActorHandle<GarbageCollector>,
     _retention_policy_executor_handle: ActorHandle<RetentionPolicyExecutor>,
+    _split_compactor_handle: ActorHandle<SplitCompactor>,
+    _split_tiering_executor_handle: ActorHandle<SplitTieringExecutor>,
     delete_task_service_handle: ActorHandle<DeleteTaskService>,
+    _alert_evaluator_handle: ActorHandle<AlertEvaluator>,
 }
 
 impl JanitorService {
     pub fn new(
         garbage_collector_handle: ActorHandle<GarbageCollector>,
         retention_policy_executor_handle: ActorHandle<RetentionPolicyExecutor>,
+        split_compactor_handle: ActorHandle<SplitCompactor>,
+        split_tiering_executor_handle: ActorHandle<SplitTieringExecutor>,
         delete_task_service_handle: ActorHandle<DeleteTaskService>,
+        alert_evaluator_handle: ActorHandle<AlertEvaluator>,
     ) -> Self {
         Self {
             _garbage_collector_handle: garbage_collector_handle,
             _retention_policy_executor_handle: retention_policy_executor_handle,
+            _split_compactor_handle: split_compactor_handle,
+            _split_tiering_executor_handle: split_tiering_executor_handle,
             delete_task_service_handle,
+            _alert_evaluator_handle: alert_evaluator_handle,
         }
     }
 
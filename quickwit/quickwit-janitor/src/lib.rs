@@ -19,7 +19,8 @@
 
 use std::sync::Arc;
 
-use quickwit_actors::Universe;
+use quickwit_actors::{ActorHandle, Universe};
+use quickwit_cluster::Cluster;
 use quickwit_config::QuickwitConfig;
 use quickwit_metastore::Metastore;
 use quickwit_search::SearchClientPool;
@@ -27,18 +28,28 @@ use quickwit_storage::StorageUriResolver;
 use tracing::info;
 
 pub mod actors;
+mod alert_notification;
 pub mod error;
 mod garbage_collection;
 mod janitor_service;
 mod metrics;
 mod retention_policy_execution;
+mod split_compaction;
+mod split_tiering;
 
 pub use janitor_service::JanitorService;
 
+pub use self::alert_notification::{
+    AlertEvent, AlertNotification, AlertNotifier, HttpAlertNotifier,
+};
 pub use self::garbage_collection::{
-    delete_splits_with_files, run_garbage_collect, FileEntry, SplitDeletionError,
+    delete_orphan_files, delete_splits_with_files, list_orphan_files, run_garbage_collect,
+    FileEntry, SplitDeletionError,
+};
+use crate::actors::{
+    AlertEvaluator, DeleteTaskService, GarbageCollector, IndexingScheduler,
+    RetentionPolicyExecutor, SplitCompactor, SplitTieringExecutor,
 };
-use crate::actors::{DeleteTaskService, GarbageCollector, RetentionPolicyExecutor};
 
 pub async fn start_janitor_service(
     universe: &Universe,
@@ -48,13 +59,37 @@ pub async fn start_janitor_service(
     storage_uri_resolver: StorageUriResolver,
 ) -> anyhow::Result<JanitorService> {
     info!("Starting janitor service.");
-    let garbage_collector = GarbageCollector::new(metastore.clone(), storage_uri_resolver.clone());
+    let garbage_collector = GarbageCollector::new(metastore.clone(), storage_uri_resolver.clone())
+        .set_gc_windows(config.janitor_config.gc_windows.clone())
+        .set_staged_grace_period(config.janitor_config.staged_split_grace_period()?);
     let (_, garbage_collector_handle) = universe.spawn_builder().spawn(garbage_collector);
 
     let retention_policy_executor = RetentionPolicyExecutor::new(metastore.clone());
     let (_, retention_policy_executor_handle) =
         universe.spawn_builder().spawn(retention_policy_executor);
 
+    let split_compactor = SplitCompactor::new(
+        metastore.clone(),
+        storage_uri_resolver.clone(),
+        config.data_dir_path.clone(),
+        config.indexer_config.max_concurrent_split_uploads,
+    );
+    let (_, split_compactor_handle) = universe.spawn_builder().spawn(split_compactor);
+
+    let split_tiering_executor = SplitTieringExecutor::new(
+        metastore.clone(),
+        storage_uri_resolver.clone(),
+        config.data_dir_path.clone(),
+    );
+    let (_, split_tiering_executor_handle) = universe.spawn_builder().spawn(split_tiering_executor);
+
+    let alert_evaluator = AlertEvaluator::new(
+        metastore.clone(),
+        search_client_pool.clone(),
+        Arc::new(HttpAlertNotifier::try_new()?),
+    );
+    let (_, alert_evaluator_handle) = universe.spawn_builder().spawn(alert_evaluator);
+
     let delete_task_service = DeleteTaskService::new(
         metastore,
         search_client_pool,
@@ -67,6 +102,22 @@ pub async fn start_janitor_service(
     Ok(JanitorService::new(
         garbage_collector_handle,
         retention_policy_executor_handle,
+        split_compactor_handle,
+        split_tiering_executor_handle,
         delete_task_service_handle,
+        alert_evaluator_handle,
     ))
 }
+
+/// Starts the control plane service, which schedules the indexing pipelines across the indexers
+/// of the cluster.
+pub fn start_control_plane_service(
+    universe: &Universe,
+    cluster: Arc<Cluster>,
+    metastore: Arc<dyn Metastore>,
+) -> ActorHandle<IndexingScheduler> {
+    info!("Starting control plane service.");
+    let indexing_scheduler = IndexingScheduler::new(cluster, metastore);
+    let (_, indexing_scheduler_handle) = universe.spawn_builder().spawn(indexing_scheduler);
+    indexing_scheduler_handle
+}
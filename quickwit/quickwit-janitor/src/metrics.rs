@@ -18,10 +18,21 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use once_cell::sync::Lazy;
-use quickwit_common::metrics::{new_gauge_vec, IntGaugeVec};
+use quickwit_common::metrics::{new_counter_vec, new_gauge_vec, IntCounterVec, IntGaugeVec};
 
 pub struct JanitorMetrics {
     pub ongoing_num_delete_operations_total: IntGaugeVec,
+    pub retention_num_expired_splits_total: IntCounterVec,
+    pub retention_reclaimed_bytes_total: IntCounterVec,
+    pub retention_last_run_reclaimed_bytes: IntGaugeVec,
+    pub gc_num_reaped_staged_splits_total: IntCounterVec,
+    pub gc_reaped_staged_bytes_total: IntCounterVec,
+    pub alert_evaluations_total: IntCounterVec,
+    pub alert_evaluation_errors_total: IntCounterVec,
+    pub alert_notifications_total: IntCounterVec,
+    pub alert_notification_errors_total: IntCounterVec,
+    pub tiering_demoted_splits_total: IntCounterVec,
+    pub tiering_promoted_splits_total: IntCounterVec,
 }
 
 impl Default for JanitorMetrics {
@@ -33,6 +44,79 @@ impl Default for JanitorMetrics {
                 "quickwit_janitor",
                 &["index"],
             ),
+            retention_num_expired_splits_total: new_counter_vec(
+                "retention_num_expired_splits_total",
+                "Number of splits marked for deletion by the retention policy executor (per \
+                 index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            retention_reclaimed_bytes_total: new_counter_vec(
+                "retention_reclaimed_bytes_total",
+                "Number of bytes of the splits marked for deletion by the retention policy \
+                 executor (per index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            retention_last_run_reclaimed_bytes: new_gauge_vec(
+                "retention_last_run_reclaimed_bytes",
+                "Number of bytes of the splits marked for deletion during the last retention \
+                 policy execution (per index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            gc_num_reaped_staged_splits_total: new_counter_vec(
+                "gc_num_reaped_staged_splits_total",
+                "Number of splits stuck in the staged state past the grace period and marked for \
+                 deletion by the garbage collector (per index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            gc_reaped_staged_bytes_total: new_counter_vec(
+                "gc_reaped_staged_bytes_total",
+                "Number of bytes of the staged splits marked for deletion by the garbage \
+                 collector (per index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            alert_evaluations_total: new_counter_vec(
+                "alert_evaluations_total",
+                "Number of alert evaluations (per index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            alert_evaluation_errors_total: new_counter_vec(
+                "alert_evaluation_errors_total",
+                "Number of alert evaluations that failed to search the index or to persist the \
+                 alert state (per index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            alert_notifications_total: new_counter_vec(
+                "alert_notifications_total",
+                "Number of alert notifications delivered to their channels (per index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            alert_notification_errors_total: new_counter_vec(
+                "alert_notification_errors_total",
+                "Number of alert notifications that could not be delivered (per index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            tiering_demoted_splits_total: new_counter_vec(
+                "tiering_demoted_splits_total",
+                "Number of splits moved to the warm storage tier (per index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
+            tiering_promoted_splits_total: new_counter_vec(
+                "tiering_promoted_splits_total",
+                "Number of splits moved back from the warm storage tier to the index storage (per \
+                 index).",
+                "quickwit_janitor",
+                &["index"],
+            ),
         }
     }
 }
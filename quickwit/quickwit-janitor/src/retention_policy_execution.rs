@@ -31,6 +31,10 @@ use crate::actors::RetentionPolicyExecutor;
 /// only mark them as `MarkedForDeletion`. Actual split deletion
 /// is taken care of by the garbage collector.
 ///
+/// A split is expired if it is older than the retention period or if it is
+/// among the oldest splits that must be dropped for the index to fit in the
+/// retention max size.
+///
 /// * `index_id` - The target index id.
 /// * `metastore` - The metastore managing the target index.
 /// * `retention_policy` - The retention policy to used to evaluate the splits.
@@ -43,15 +47,25 @@ pub async fn run_execute_retention_policy(
 ) -> anyhow::Result<Vec<SplitMetadata>> {
     // Select published splits and filter for expiration.
     let current_date_time = OffsetDateTime::now_utc();
-    let expired_splits: Vec<SplitMetadata> = ctx
+    let published_splits = ctx
         .protect_future(metastore.list_splits(index_id, SplitState::Published, None, None))
-        .await?
-        .into_iter()
-        // TODO: possibly move this in DB query
-        .filter(|split| {
+        .await?;
+    // TODO: possibly move this in DB query
+    let (mut expired_splits, retained_splits): (Vec<Split>, Vec<Split>) =
+        published_splits.into_iter().partition(|split| {
             is_split_expired(current_date_time, split, retention_policy).unwrap_or(false)
-        })
-        .map(|meta| meta.split_metadata)
+        });
+    if let Some(max_index_num_bytes) = retention_policy.max_index_num_bytes()? {
+        let oversized_splits =
+            select_oversized_splits(retained_splits, max_index_num_bytes, retention_policy);
+        if !oversized_splits.is_empty() {
+            info!(index_id=%index_id, num_splits=%oversized_splits.len(), max_index_num_bytes=%max_index_num_bytes, "retention-policy-index-exceeds-max-size");
+        }
+        expired_splits.extend(oversized_splits);
+    }
+    let expired_splits: Vec<SplitMetadata> = expired_splits
+        .into_iter()
+        .map(|split| split.split_metadata)
         .collect();
 
     if expired_splits.is_empty() {
@@ -67,13 +81,58 @@ pub async fn run_execute_retention_policy(
     Ok(expired_splits)
 }
 
+/// Selects the oldest splits, according to the retention policy cutoff reference, that must be
+/// dropped for the total size of the remaining splits to fit in `max_index_num_bytes`.
+fn select_oversized_splits(
+    mut splits: Vec<Split>,
+    max_index_num_bytes: u64,
+    retention_policy: &RetentionPolicy,
+) -> Vec<Split> {
+    let mut index_num_bytes: u64 = splits
+        .iter()
+        .map(|split| split.split_metadata.footer_offsets.end)
+        .sum();
+    if index_num_bytes <= max_index_num_bytes {
+        return Vec::new();
+    }
+    splits.sort_by_key(|split| split_cutoff_timestamp(split, retention_policy));
+
+    let mut oversized_splits = Vec::new();
+    for split in splits {
+        if index_num_bytes <= max_index_num_bytes {
+            break;
+        }
+        index_num_bytes -= split.split_metadata.footer_offsets.end;
+        oversized_splits.push(split);
+    }
+    oversized_splits
+}
+
+/// Returns the timestamp of the split the retention policy is applied relatively to. Splits
+/// without time range sort first when the cutoff reference is the split timestamp field.
+fn split_cutoff_timestamp(split: &Split, retention_policy: &RetentionPolicy) -> Option<i64> {
+    match retention_policy.cutoff_reference {
+        RetentionPolicyCutoffReference::PublishTimestamp => {
+            Some(split.publish_timestamp.unwrap_or(split.update_timestamp))
+        }
+        RetentionPolicyCutoffReference::SplitTimestampField => split
+            .split_metadata
+            .time_range
+            .as_ref()
+            .map(|time_range| *time_range.end()),
+    }
+}
+
 /// Checks to see if a split is expired based on a retention policy.
 fn is_split_expired(
     current_date_time: OffsetDateTime,
     split: &Split,
     retention_policy: &RetentionPolicy,
 ) -> anyhow::Result<bool> {
-    let retention_period = retention_policy.retention_period()?;
+    let retention_period = match retention_policy.retention_period()? {
+        Some(retention_period) => retention_period,
+        None => return Ok(false),
+    };
     // The split `publish_timestamp` field does not exist in previous versions of Quickwit (< v0.4).
     // In order to stay backward compatible, we will use the `updated_timestamp` field
     // for splits generated from older version of Quickwit.
@@ -107,7 +166,7 @@ mod tests {
     use quickwit_metastore::{Split, SplitMetadata, SplitState};
     use time::OffsetDateTime;
 
-    use super::is_split_expired;
+    use super::{is_split_expired, select_oversized_splits};
 
     fn make_split(
         update_timestamp: i64,
@@ -208,4 +267,60 @@ mod tests {
         )
         .unwrap());
     }
+
+    #[test]
+    fn test_select_oversized_splits() {
+        let max_size_retention_policy = RetentionPolicy::with_max_index_size(
+            "50 B".to_string(),
+            RetentionPolicyCutoffReference::SplitTimestampField,
+            "hourly".to_string(),
+        );
+        let splits: Vec<Split> = [
+            ("b", 20..=30),
+            ("a", 0..=10),
+            ("d", 60..=70),
+            ("c", 40..=50),
+        ]
+        .into_iter()
+        .map(|(split_id, time_range)| {
+            let mut split = make_split(0, Some(0), Some(time_range));
+            split.split_metadata.split_id = split_id.to_string();
+            split
+        })
+        .collect();
+        // The index weighs 4 * 20 bytes.
+        let max_index_num_bytes = max_size_retention_policy
+            .max_index_num_bytes()
+            .unwrap()
+            .unwrap();
+        let oversized_splits = select_oversized_splits(
+            splits.clone(),
+            max_index_num_bytes,
+            &max_size_retention_policy,
+        );
+        let oversized_split_ids: Vec<&str> = oversized_splits
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        assert_eq!(oversized_split_ids, ["a", "b"]);
+
+        let oversized_splits = select_oversized_splits(splits, 80, &max_size_retention_policy);
+        assert!(oversized_splits.is_empty());
+    }
+
+    #[test]
+    fn test_is_split_expired_without_retention_period() {
+        let max_size_retention_policy = RetentionPolicy::with_max_index_size(
+            "1 GB".to_string(),
+            RetentionPolicyCutoffReference::PublishTimestamp,
+            "hourly".to_string(),
+        );
+        let split = make_split(0, Some(0), None);
+        assert!(!is_split_expired(
+            OffsetDateTime::now_utc(),
+            &split,
+            &max_size_retention_policy
+        )
+        .unwrap());
+    }
 }
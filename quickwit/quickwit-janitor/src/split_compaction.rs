@@ -0,0 +1,213 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use quickwit_indexing::merge_policy::MergeOperation;
+use quickwit_metastore::Split;
+
+/// Splits smaller than this size are considered tiny.
+const TINY_SPLIT_MAX_NUM_BYTES: u64 = 10_000_000; // 10MB
+
+/// An index is considered pathological when it holds at least this number of tiny splits.
+const MIN_NUM_TINY_SPLITS: usize = 100;
+
+/// Maximum number of splits merged by a single compaction operation.
+const MAX_NUM_SPLITS_PER_COMPACTION: usize = 25;
+
+/// Splits updated during this period are left to the merge pipeline of the indexer that produced
+/// them, which is likely still running.
+const IDLE_PERIOD: Duration = Duration::from_secs(60 * 60); // 1 hour
+
+/// Detects whether an index has a pathological split distribution, i.e. a large number of tiny
+/// splits, and plans the merge operations compacting its idle tiny splits.
+///
+/// Splits are merged with splits from the same source, node, and partition only, and by ascending
+/// time range start, to preserve the time locality of the merged splits.
+///
+/// * `published_splits` - The published splits of the index.
+/// * `ongoing_split_ids` - The IDs of the splits already undergoing a compaction.
+/// * `now_timestamp` - The current timestamp, used to tell idle splits apart.
+pub fn plan_compaction_operations(
+    published_splits: Vec<Split>,
+    ongoing_split_ids: &HashSet<String>,
+    now_timestamp: i64,
+) -> Vec<MergeOperation> {
+    let tiny_splits: Vec<Split> = published_splits
+        .into_iter()
+        .filter(|split| split.split_metadata.footer_offsets.end < TINY_SPLIT_MAX_NUM_BYTES)
+        // Warm splits are left untouched until they are promoted back to the index storage.
+        .filter(|split| split.split_metadata.storage_tier.is_hot())
+        .collect();
+    if tiny_splits.len() < MIN_NUM_TINY_SPLITS {
+        return Vec::new();
+    }
+    let mut idle_tiny_splits_by_pipeline: HashMap<(String, String, u64), Vec<Split>> =
+        HashMap::new();
+    for split in tiny_splits {
+        let is_idle = now_timestamp - split.update_timestamp >= IDLE_PERIOD.as_secs() as i64;
+        if !is_idle || ongoing_split_ids.contains(split.split_id()) {
+            continue;
+        }
+        let pipeline_key = (
+            split.split_metadata.source_id.clone(),
+            split.split_metadata.node_id.clone(),
+            split.split_metadata.partition_id,
+        );
+        idle_tiny_splits_by_pipeline
+            .entry(pipeline_key)
+            .or_default()
+            .push(split);
+    }
+    let mut merge_operations = Vec::new();
+
+    for mut splits in idle_tiny_splits_by_pipeline.into_values() {
+        splits.sort_by_key(|split| {
+            split
+                .split_metadata
+                .time_range
+                .as_ref()
+                .map(|time_range| *time_range.start())
+        });
+        for chunk in splits.chunks(MAX_NUM_SPLITS_PER_COMPACTION) {
+            // A merge operation requires at least two splits.
+            if chunk.len() < 2 {
+                continue;
+            }
+            let split_metadatas = chunk
+                .iter()
+                .map(|split| split.split_metadata.clone())
+                .collect();
+            merge_operations.push(MergeOperation::new_merge_operation(split_metadatas));
+        }
+    }
+    merge_operations
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{SplitMetadata, SplitState};
+
+    use super::*;
+
+    fn make_split(split_id: &str, node_id: &str, num_bytes: u64, update_timestamp: i64) -> Split {
+        Split {
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                index_id: "test-index".to_string(),
+                source_id: "test-source".to_string(),
+                node_id: node_id.to_string(),
+                footer_offsets: 0..num_bytes,
+                ..Default::default()
+            },
+            split_state: SplitState::Published,
+            update_timestamp,
+            publish_timestamp: Some(update_timestamp),
+        }
+    }
+
+    #[test]
+    fn test_plan_compaction_operations_healthy_index() {
+        let splits: Vec<Split> = (0..MIN_NUM_TINY_SPLITS - 1)
+            .map(|split_ord| make_split(&format!("split-{split_ord}"), "node-1", 1_000, 0))
+            .chain((0..100).map(|split_ord| {
+                make_split(
+                    &format!("large-split-{split_ord}"),
+                    "node-1",
+                    100_000_000,
+                    0,
+                )
+            }))
+            .collect();
+        let merge_operations = plan_compaction_operations(splits, &HashSet::new(), 1_000_000);
+        assert!(merge_operations.is_empty());
+    }
+
+    #[test]
+    fn test_plan_compaction_operations_pathological_index() {
+        let now_timestamp = 1_000_000;
+        let recent_timestamp = now_timestamp - 60;
+        let mut splits: Vec<Split> =
+            (0..60)
+                .map(|split_ord| make_split(&format!("node-1-{split_ord}"), "node-1", 1_000, 0))
+                .chain((0..3).map(|split_ord| {
+                    make_split(&format!("node-2-{split_ord}"), "node-2", 1_000, 0)
+                }))
+                .chain((0..40).map(|split_ord| {
+                    make_split(
+                        &format!("recent-{split_ord}"),
+                        "node-3",
+                        1_000,
+                        recent_timestamp,
+                    )
+                }))
+                .collect();
+        splits.push(make_split("ongoing", "node-2", 1_000, 0));
+
+        let ongoing_split_ids = HashSet::from_iter(["ongoing".to_string()]);
+        let mut merge_operations =
+            plan_compaction_operations(splits, &ongoing_split_ids, now_timestamp);
+        merge_operations.sort_by_key(|merge_operation| merge_operation.splits.len());
+
+        let num_splits_per_operation: Vec<usize> = merge_operations
+            .iter()
+            .map(|merge_operation| merge_operation.splits.len())
+            .collect();
+        // The last chunk of node-1 has 10 splits, node-2 has 3 idle splits, and node-3 splits are
+        // not idle.
+        assert_eq!(num_splits_per_operation, [3, 10, 25, 25]);
+
+        for merge_operation in &merge_operations {
+            let node_ids: HashSet<&str> = merge_operation
+                .splits
+                .iter()
+                .map(|split| split.node_id.as_str())
+                .collect();
+            assert_eq!(node_ids.len(), 1);
+            assert!(merge_operation
+                .splits
+                .iter()
+                .all(|split| split.split_id() != "ongoing"));
+        }
+    }
+
+    #[test]
+    fn test_plan_compaction_operations_does_not_mix_partitions() {
+        let splits: Vec<Split> = (0..MIN_NUM_TINY_SPLITS as u64)
+            .map(|split_ord| {
+                let mut split = make_split(&format!("split-{split_ord}"), "node-1", 1_000, 0);
+                split.split_metadata.partition_id = split_ord % 4;
+                split
+            })
+            .collect();
+        let merge_operations = plan_compaction_operations(splits, &HashSet::new(), 1_000_000);
+        assert_eq!(merge_operations.len(), 4);
+
+        for merge_operation in &merge_operations {
+            assert_eq!(merge_operation.splits.len(), 25);
+            let partition_ids: HashSet<u64> = merge_operation
+                .splits
+                .iter()
+                .map(|split| split.partition_id)
+                .collect();
+            assert_eq!(partition_ids.len(), 1);
+        }
+    }
+}
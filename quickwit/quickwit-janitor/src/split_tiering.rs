@@ -0,0 +1,238 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_config::TieringPolicy;
+use quickwit_indexing::merge_policy::MergePolicy;
+use quickwit_metastore::{Split, SplitStorageTier};
+
+/// Maximum number of splits moved between storage tiers for an index in a single pass.
+const MAX_NUM_OPERATIONS_PER_PASS: usize = 100;
+
+/// Move of a split file from one storage tier to another.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TieringOperation {
+    pub split_id: String,
+    pub source_tier: SplitStorageTier,
+    pub target_tier: SplitStorageTier,
+}
+
+impl TieringOperation {
+    pub fn is_demotion(&self) -> bool {
+        self.source_tier.is_hot()
+    }
+}
+
+/// Plans the moves of the published splits of an index between the hot and warm storage tiers:
+/// - hot splits not searched for the demotion period of the tiering policy are demoted to the warm
+///   storage. Immature splits are left alone since they are about to be merged.
+/// - warm splits searched at least `promote_after_num_searches` times since their demotion are
+///   promoted back to the index storage.
+///
+/// Least recently searched splits are demoted first, and most searched splits are promoted first.
+///
+/// * `tiering_policy` - The tiering policy of the index.
+/// * `published_splits` - The published splits of the index.
+/// * `merge_policy` - The merge policy of the index, used to tell mature splits apart.
+/// * `now_timestamp` - The current timestamp.
+pub fn plan_tiering_operations(
+    tiering_policy: &TieringPolicy,
+    published_splits: &[Split],
+    merge_policy: &dyn MergePolicy,
+    now_timestamp: i64,
+) -> anyhow::Result<Vec<TieringOperation>> {
+    let demotion_period_secs = tiering_policy.demotion_period()?.as_secs() as i64;
+    let warm_storage_tier = SplitStorageTier::Warm(tiering_policy.warm_storage_uri.clone());
+
+    let mut demotable_splits: Vec<&Split> = published_splits
+        .iter()
+        .filter(|split| {
+            split.split_metadata.storage_tier.is_hot()
+                && merge_policy.is_mature(&split.split_metadata)
+                && now_timestamp - split.split_metadata.last_activity_timestamp()
+                    >= demotion_period_secs
+        })
+        .collect();
+    demotable_splits.sort_by_key(|split| split.split_metadata.last_activity_timestamp());
+
+    let mut promotable_splits: Vec<&Split> = match tiering_policy.promote_after_num_searches {
+        Some(promote_after_num_searches) => published_splits
+            .iter()
+            .filter(|split| {
+                !split.split_metadata.storage_tier.is_hot()
+                    && split.split_metadata.access_stats.num_searches
+                        >= promote_after_num_searches.get()
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    promotable_splits
+        .sort_by_key(|split| std::cmp::Reverse(split.split_metadata.access_stats.num_searches));
+
+    let promotions = promotable_splits.into_iter().map(|split| TieringOperation {
+        split_id: split.split_id().to_string(),
+        source_tier: split.split_metadata.storage_tier.clone(),
+        target_tier: SplitStorageTier::Hot,
+    });
+    let demotions = demotable_splits.into_iter().map(|split| TieringOperation {
+        split_id: split.split_id().to_string(),
+        source_tier: SplitStorageTier::Hot,
+        target_tier: warm_storage_tier.clone(),
+    });
+    let tiering_operations = promotions
+        .chain(demotions)
+        .take(MAX_NUM_OPERATIONS_PER_PASS)
+        .collect();
+    Ok(tiering_operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use quickwit_common::uri::Uri;
+    use quickwit_config::IndexingSettings;
+    use quickwit_indexing::merge_policy::merge_policy_from_settings;
+    use quickwit_metastore::{SplitAccessStats, SplitMetadata, SplitState};
+
+    use super::*;
+
+    const NOW_TIMESTAMP: i64 = 1_000_000;
+    const DAY_SECS: i64 = 24 * 60 * 60;
+
+    fn make_split(
+        split_id: &str,
+        num_docs: usize,
+        storage_tier: SplitStorageTier,
+        num_searches: u64,
+        last_search_timestamp: Option<i64>,
+    ) -> Split {
+        Split {
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                index_id: "test-index".to_string(),
+                num_docs,
+                create_timestamp: 0,
+                storage_tier,
+                access_stats: SplitAccessStats {
+                    num_searches,
+                    last_search_timestamp,
+                    storage_tier_timestamp: None,
+                },
+                ..Default::default()
+            },
+            split_state: SplitState::Published,
+            update_timestamp: 0,
+            publish_timestamp: Some(0),
+        }
+    }
+
+    #[test]
+    fn test_plan_tiering_operations() {
+        let warm_storage_uri = Uri::from_well_formed("ram://warm-indexes/test-index".to_string());
+        let warm_storage_tier = SplitStorageTier::Warm(warm_storage_uri.clone());
+        let tiering_policy =
+            TieringPolicy::new(warm_storage_uri, "1 day".to_string(), NonZeroU64::new(10));
+        let merge_policy = merge_policy_from_settings(&IndexingSettings::default());
+        let mature_num_docs = 10_000_000;
+
+        let splits = vec![
+            make_split(
+                "never-searched",
+                mature_num_docs,
+                SplitStorageTier::Hot,
+                0,
+                None,
+            ),
+            make_split(
+                "searched-long-ago",
+                mature_num_docs,
+                SplitStorageTier::Hot,
+                3,
+                Some(NOW_TIMESTAMP - 3 * DAY_SECS),
+            ),
+            make_split(
+                "searched-recently",
+                mature_num_docs,
+                SplitStorageTier::Hot,
+                3,
+                Some(NOW_TIMESTAMP - 60),
+            ),
+            make_split("immature", 1_000, SplitStorageTier::Hot, 0, None),
+            make_split(
+                "warm-searched-often",
+                mature_num_docs,
+                warm_storage_tier.clone(),
+                10,
+                Some(NOW_TIMESTAMP - 60),
+            ),
+            make_split(
+                "warm-searched-rarely",
+                mature_num_docs,
+                warm_storage_tier.clone(),
+                9,
+                Some(NOW_TIMESTAMP - 60),
+            ),
+        ];
+        let tiering_operations =
+            plan_tiering_operations(&tiering_policy, &splits, &*merge_policy, NOW_TIMESTAMP)
+                .unwrap();
+        assert_eq!(
+            tiering_operations,
+            [
+                TieringOperation {
+                    split_id: "warm-searched-often".to_string(),
+                    source_tier: warm_storage_tier.clone(),
+                    target_tier: SplitStorageTier::Hot,
+                },
+                TieringOperation {
+                    split_id: "never-searched".to_string(),
+                    source_tier: SplitStorageTier::Hot,
+                    target_tier: warm_storage_tier.clone(),
+                },
+                TieringOperation {
+                    split_id: "searched-long-ago".to_string(),
+                    source_tier: SplitStorageTier::Hot,
+                    target_tier: warm_storage_tier,
+                },
+            ]
+        );
+        assert!(!tiering_operations[0].is_demotion());
+        assert!(tiering_operations[1].is_demotion());
+    }
+
+    #[test]
+    fn test_plan_tiering_operations_without_promotion() {
+        let warm_storage_uri = Uri::from_well_formed("ram://warm-indexes/test-index".to_string());
+        let warm_storage_tier = SplitStorageTier::Warm(warm_storage_uri.clone());
+        let tiering_policy = TieringPolicy::new(warm_storage_uri, "1 day".to_string(), None);
+        let merge_policy = merge_policy_from_settings(&IndexingSettings::default());
+
+        let splits = vec![make_split(
+            "warm-searched-often",
+            10_000_000,
+            warm_storage_tier,
+            1_000,
+            Some(NOW_TIMESTAMP - 60),
+        )];
+        let tiering_operations =
+            plan_tiering_operations(&tiering_policy, &splits, &*merge_policy, NOW_TIMESTAMP)
+                .unwrap();
+        assert!(tiering_operations.is_empty());
+    }
+}
@@ -24,8 +24,9 @@ use byte_unit::Byte;
 use quickwit_common::uri::Uri;
 use quickwit_config::merge_policy_config::{MergePolicyConfig, StableLogMergePolicyConfig};
 use quickwit_config::{
-    DocMapping, IndexingResources, IndexingSettings, KafkaSourceParams, RetentionPolicy,
-    RetentionPolicyCutoffReference, SearchSettings, SourceConfig, SourceParams,
+    DocMapping, IndexingResources, IndexingSettings, KafkaClientFeatures, KafkaFlavor,
+    KafkaPartitionAssignment, KafkaSourceParams, RetentionPolicy, RetentionPolicyCutoffReference,
+    SearchSettings, SourceConfig, SourceParams,
 };
 use quickwit_doc_mapper::{ModeType, SortOrder};
 
@@ -141,6 +142,9 @@ pub(crate) fn sample_index_metadata_for_regression() -> IndexMetadata {
             .into_iter()
             .map(|tag_field| tag_field.to_string())
             .collect::<BTreeSet<String>>(),
+        tag_keyword_fields: false,
+        bloom_filter_fields: BTreeSet::new(),
+        dynamic_all: false,
         store_source: true,
         mode: ModeType::Dynamic,
         dynamic_mapping: None,
@@ -174,16 +178,26 @@ pub(crate) fn sample_index_metadata_for_regression() -> IndexMetadata {
     };
     let search_settings = SearchSettings {
         default_search_fields: vec!["message".to_string()],
+        ..Default::default()
     };
     let kafka_source = SourceConfig {
         source_id: "kafka-source".to_string(),
         num_pipelines: 2,
+        expected_throughput: None,
+        rate_limit_per_sec: None,
+        transform: Vec::new(),
+        input_format: None,
+        csv_options: None,
         enabled: true,
         source_params: SourceParams::Kafka(KafkaSourceParams {
             topic: "kafka-topic".to_string(),
             client_log_level: None,
             client_params: serde_json::json!({}),
             enable_backfill_mode: false,
+            flavor: KafkaFlavor::Kafka,
+            client_features: KafkaClientFeatures::default(),
+            partition_assignment: KafkaPartitionAssignment::ConsumerGroup,
+            enable_offset_commit: false,
         }),
     };
     let mut sources = HashMap::default();
@@ -193,13 +207,18 @@ pub(crate) fn sample_index_metadata_for_regression() -> IndexMetadata {
         index_id: "my-index".to_string(),
         index_uri: Uri::from_well_formed("s3://quickwit-indexes/my-index".to_string()),
         checkpoint,
+        publish_tokens: Default::default(),
         doc_mapping,
         indexing_settings,
         retention_policy,
+        read_only: false,
         search_settings,
         sources,
+        stored_queries: Default::default(),
+        alert_states: Default::default(),
         create_timestamp: 1789,
         update_timestamp: 1789,
+        tiering_policy: None,
     }
 }
 
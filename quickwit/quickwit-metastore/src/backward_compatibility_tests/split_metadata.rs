@@ -17,7 +17,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use crate::SplitMetadata;
+use quickwit_common::uri::Uri;
+
+use crate::{SplitAccessStats, SplitFieldStats, SplitMetadata, SplitStorageTier};
 
 /// Creates a split metadata object that will be
 /// used to check for non-regression
@@ -36,6 +38,30 @@ pub(crate) fn sample_split_metadata_for_regression() -> SplitMetadata {
         tags: ["234".to_string(), "aaa".to_string()].into_iter().collect(),
         footer_offsets: 1000..2000,
         num_merge_ops: 3,
+        split_format_version: 1,
+        tantivy_index_format_version: 5,
+        storage_tier: SplitStorageTier::Warm(Uri::from_well_formed(
+            "s3://warm-bucket/my-index".to_string(),
+        )),
+        access_stats: SplitAccessStats {
+            num_searches: 42,
+            last_search_timestamp: Some(1_000_000),
+            storage_tier_timestamp: None,
+        },
+        field_stats: [(
+            "body".to_string(),
+            SplitFieldStats {
+                termdict_num_bytes: 100,
+                postings_num_bytes: 200,
+                positions_num_bytes: 300,
+                fast_field_num_bytes: 0,
+                fieldnorms_num_bytes: 40,
+                num_terms: 12,
+                is_fast: false,
+            },
+        )]
+        .into_iter()
+        .collect(),
     }
 }
 
@@ -164,12 +164,33 @@ impl IndexCheckpoint {
         Ok(true)
     }
 
+    /// Returns the first partition of the delta that starts after the position reached in the
+    /// checkpoint, if any. Applying such a delta would skip some positions.
+    pub fn find_gap(&self, delta: &IndexCheckpointDelta) -> Option<IncompatibleCheckpointDelta> {
+        self.per_source
+            .get(&delta.source_id)
+            .and_then(|source_checkpoint| source_checkpoint.find_gap(&delta.source_delta))
+    }
+
     /// Resets the checkpoint of the source identified by `source_id`. Returns whether a mutation
     /// occurred.
     pub(crate) fn reset_source(&mut self, source_id: &str) -> bool {
         self.per_source.remove(source_id).is_some()
     }
 
+    /// Replaces the checkpoint of the source identified by `source_id`. Returns whether a
+    /// mutation occurred.
+    pub(crate) fn update_source(
+        &mut self,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> bool {
+        let previous_checkpoint_opt = self
+            .per_source
+            .insert(source_id.to_string(), source_checkpoint.clone());
+        previous_checkpoint_opt != Some(source_checkpoint)
+    }
+
     /// Returns the checkpoint associated to a given source.
     ///
     /// All registered source have an associated checkpoint (that is possibly empty).
@@ -180,6 +201,11 @@ impl IndexCheckpoint {
         self.per_source.get(source_id)
     }
 
+    /// Returns the IDs of the sources that have a checkpoint.
+    pub fn source_ids(&self) -> impl Iterator<Item = &str> + '_ {
+        self.per_source.keys().map(String::as_str)
+    }
+
     /// Adds a new source. If the source was already here, this
     /// method returns successfully and does not override the existing checkpoint.
     pub fn add_source(&mut self, source_id: &str) {
@@ -293,6 +319,26 @@ impl SourceCheckpoint {
             .map(|(partition_id, position)| (partition_id.clone(), position.clone()))
     }
 
+    /// Returns the first partition of the delta that starts after the position reached in the
+    /// checkpoint, if any. Partitions absent from the checkpoint never have a gap.
+    pub fn find_gap(&self, delta: &SourceCheckpointDelta) -> Option<IncompatibleCheckpointDelta> {
+        delta
+            .per_partition
+            .iter()
+            .find_map(|(partition_id, partition_delta)| {
+                let position = self.per_partition.get(partition_id)?;
+                if *position < partition_delta.from {
+                    Some(IncompatibleCheckpointDelta {
+                        partition_id: partition_id.clone(),
+                        current_position: position.clone(),
+                        delta_position_from: partition_delta.from.clone(),
+                    })
+                } else {
+                    None
+                }
+            })
+    }
+
     fn check_compatibility(
         &self,
         delta: &SourceCheckpointDelta,
@@ -389,10 +435,28 @@ pub struct SourceCheckpointDelta {
     per_partition: BTreeMap<PartitionId, PartitionDelta>,
 }
 
+/// Fencing token handed out by the metastore to the single writer allowed to advance the
+/// checkpoint of a given partition set of a source.
+///
+/// Acquiring a new token for a partition set invalidates the previous ones, so that a stale
+/// writer cannot publish anymore once a new writer took over.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishToken {
+    /// Identifies the set of partitions of the source owned by the writer.
+    pub partition_set_id: String,
+    /// Monotonically increasing token value.
+    pub token: u64,
+}
+
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IndexCheckpointDelta {
     pub source_id: String,
     pub source_delta: SourceCheckpointDelta,
+    /// Fencing token of the writer publishing the delta, if any. The metastore rejects the
+    /// delta if a newer token was acquired for the same partition set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish_token_opt: Option<PublishToken>,
 }
 
 impl IndexCheckpointDelta {
@@ -405,6 +469,7 @@ impl IndexCheckpointDelta {
         IndexCheckpointDelta {
             source_id: source_id.to_string(),
             source_delta: SourceCheckpointDelta::from(pos_range),
+            publish_token_opt: None,
         }
     }
 }
@@ -522,6 +587,11 @@ impl SourceCheckpointDelta {
         Ok(())
     }
 
+    /// Returns the partitions covered by the checkpoint delta, in increasing order.
+    pub fn partitions(&self) -> impl Iterator<Item = &PartitionId> + '_ {
+        self.per_partition.keys()
+    }
+
     /// Returns the number of partitions covered by the checkpoint delta.
     pub fn num_partitions(&self) -> usize {
         self.per_partition.len()
@@ -716,6 +786,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_checkpoint_find_gap() {
+        let mut checkpoint = SourceCheckpoint::default();
+        checkpoint
+            .try_apply_delta(SourceCheckpointDelta::from(0..5))
+            .unwrap();
+        assert!(checkpoint
+            .find_gap(&SourceCheckpointDelta::from(5..8))
+            .is_none());
+        assert!(checkpoint
+            .find_gap(&SourceCheckpointDelta::from(3..8))
+            .is_none());
+        assert_eq!(
+            checkpoint.find_gap(&SourceCheckpointDelta::from(7..8)),
+            Some(IncompatibleCheckpointDelta {
+                partition_id: PartitionId::default(),
+                current_position: Position::from(4u64),
+                delta_position_from: Position::from(6u64),
+            })
+        );
+        let new_partition_delta = SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from("new"),
+            Position::from(42u64),
+            Position::from(43u64),
+        );
+        assert!(checkpoint.find_gap(&new_partition_delta).is_none());
+    }
+
     #[test]
     fn test_position_u64() {
         let pos = Position::from(4u64);
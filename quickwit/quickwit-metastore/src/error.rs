@@ -39,6 +39,9 @@ pub enum MetastoreError {
     #[error("Index `{index_id}` does not exist.")]
     IndexDoesNotExist { index_id: String },
 
+    #[error("Index `{index_id}` is read-only.")]
+    IndexReadOnly { index_id: String },
+
     /// Any generic internal error.
     /// The message can be helpful to users, but the detail of the error
     /// are judged uncoverable and not useful for error handling.
@@ -60,9 +63,29 @@ pub enum MetastoreError {
     #[error("Splits `{split_ids:?}` are not staged.")]
     SplitsNotStaged { split_ids: Vec<String> },
 
-    #[error("Publish checkpoint delta overlaps with the current checkpoint: {0:?}.")]
+    #[error(
+        "Stale writer: replaced splits `{split_ids:?}` are not published. Another writer may have \
+         replaced them."
+    )]
+    ReplacedSplitsNotPublished { split_ids: Vec<String> },
+
+    #[error("Stale writer: publish checkpoint delta overlaps with the current checkpoint: {0:?}.")]
     IncompatibleCheckpointDelta(#[from] IncompatibleCheckpointDelta),
 
+    #[error("Publish checkpoint delta leaves a gap after the current checkpoint: {0:?}.")]
+    CheckpointDeltaGap(IncompatibleCheckpointDelta),
+
+    #[error(
+        "Publish token `{token}` of partition set `{partition_set_id}` of source `{source_id}` is \
+         stale: a writer acquired token `{latest_token}` since."
+    )]
+    StalePublishToken {
+        source_id: String,
+        partition_set_id: String,
+        token: u64,
+        latest_token: u64,
+    },
+
     #[error("Source `{source_id}` of type `{source_type}` already exists.")]
     SourceAlreadyExists {
         source_id: String,
@@ -72,6 +95,9 @@ pub enum MetastoreError {
     #[error("Source `{source_id}` does not exist.")]
     SourceDoesNotExist { source_id: String },
 
+    #[error("Stored query `{name}` does not exist.")]
+    StoredQueryDoesNotExist { name: String },
+
     #[error("Database error: `{message}`.")]
     DbError { message: String },
 
@@ -104,18 +130,23 @@ impl ServiceError for MetastoreError {
     fn status_code(&self) -> ServiceErrorCode {
         match self {
             Self::ConnectionError { .. } => ServiceErrorCode::Internal,
+            Self::CheckpointDeltaGap(_) => ServiceErrorCode::BadRequest,
             Self::Forbidden { .. } => ServiceErrorCode::Internal,
             Self::IncompatibleCheckpointDelta(_) => ServiceErrorCode::BadRequest,
             Self::IndexAlreadyExists { .. } => ServiceErrorCode::BadRequest,
             Self::IndexDoesNotExist { .. } => ServiceErrorCode::BadRequest,
+            Self::IndexReadOnly { .. } => ServiceErrorCode::BadRequest,
             Self::InternalError { .. } => ServiceErrorCode::Internal,
             Self::InvalidManifest { .. } => ServiceErrorCode::Internal,
             Self::Io { .. } => ServiceErrorCode::Internal,
+            Self::ReplacedSplitsNotPublished { .. } => ServiceErrorCode::BadRequest,
             Self::SourceAlreadyExists { .. } => ServiceErrorCode::BadRequest,
             Self::SourceDoesNotExist { .. } => ServiceErrorCode::BadRequest,
+            Self::StoredQueryDoesNotExist { .. } => ServiceErrorCode::NotFound,
             Self::SplitsDoNotExist { .. } => ServiceErrorCode::BadRequest,
             Self::SplitsNotDeletable { .. } => ServiceErrorCode::BadRequest,
             Self::SplitsNotStaged { .. } => ServiceErrorCode::BadRequest,
+            Self::StalePublishToken { .. } => ServiceErrorCode::BadRequest,
             Self::DbError { .. } => ServiceErrorCode::Internal,
             Self::JsonDeserializeError { .. } => ServiceErrorCode::Internal,
             Self::JsonSerializeError { .. } => ServiceErrorCode::Internal,
@@ -45,13 +45,19 @@ pub use metastore::grpc_metastore::{GrpcMetastoreAdapter, MetastoreGrpcClient};
 pub use metastore::postgresql_metastore::PostgresqlMetastore;
 #[cfg(any(test, feature = "testsuite"))]
 pub use metastore::MockMetastore;
-pub use metastore::{file_backed_metastore, IndexMetadata, Metastore};
+pub use metastore::{
+    file_backed_metastore, AlertState, FaultInjectingMetastore, IndexFreshness,
+    IndexFreshnessMetastore, IndexMetadata, Metastore, MetastoreFaults, INDEX_FRESHNESS_KEY_PREFIX,
+};
 pub use metastore_resolver::{
     quickwit_metastore_uri_resolver, MetastoreFactory, MetastoreUriResolver,
 };
 use quickwit_common::is_disjoint;
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
-pub use split_metadata::{Split, SplitMetadata, SplitState};
+pub use split_metadata::{
+    Split, SplitAccessStats, SplitFieldStats, SplitFormatError, SplitMetadata, SplitState,
+    SplitStorageTier, OLDEST_SUPPORTED_SPLIT_FORMAT_VERSION, SPLIT_FORMAT_VERSION,
+};
 pub(crate) use split_metadata_version::VersionedSplitMetadata;
 
 /// Returns `true` if the split time range is included in `time_range_opt`.
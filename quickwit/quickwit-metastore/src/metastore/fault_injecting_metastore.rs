@@ -0,0 +1,631 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_common::uri::Uri;
+use quickwit_config::{SourceConfig, StoredQuery};
+use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken, SourceCheckpoint};
+use crate::{
+    AlertState, IndexMetadata, Metastore, MetastoreError, MetastoreResult, Split, SplitMetadata,
+    SplitState, SplitStorageTier,
+};
+
+/// Faults injected by a [`FaultInjectingMetastore`] into the requests it forwards.
+///
+/// Rates are probabilities in `[0, 1]` drawn independently for each request. A request suffers at
+/// most one fault, so the sum of the rates must not exceed 1.
+#[derive(Clone, Debug, Default)]
+pub struct MetastoreFaults {
+    /// Latency added to every request.
+    pub latency: Duration,
+    /// Rate of requests failing with a connection error before reaching the underlying
+    /// metastore.
+    pub error_rate: f64,
+    /// Rate of write requests failing with a connection error after the underlying metastore
+    /// applied them, as when a connection drops before the response is received.
+    pub post_commit_error_rate: f64,
+}
+
+impl MetastoreFaults {
+    fn validate(&self) {
+        for (name, rate) in [
+            ("error_rate", self.error_rate),
+            ("post_commit_error_rate", self.post_commit_error_rate),
+        ] {
+            assert!(
+                (0.0..=1.0).contains(&rate),
+                "`{name}` must be in [0, 1], got `{rate}`."
+            );
+        }
+        assert!(
+            self.error_rate + self.post_commit_error_rate <= 1.0,
+            "The sum of the fault rates must not exceed 1."
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Fault {
+    Error,
+    PostCommitError,
+}
+
+fn fault_error(operation: &str) -> MetastoreError {
+    MetastoreError::ConnectionError {
+        message: format!("Injected fault for `{operation}` request."),
+    }
+}
+
+macro_rules! inject_faults {
+    ($self:ident, read, $method_name:ident, $expr:expr) => {
+        match $self.draw_fault(false).await {
+            Some(_) => return Err(fault_error(stringify!($method_name))),
+            None => return $expr,
+        }
+    };
+    ($self:ident, write, $method_name:ident, $expr:expr) => {
+        match $self.draw_fault(true).await {
+            Some(Fault::Error) => return Err(fault_error(stringify!($method_name))),
+            Some(Fault::PostCommitError) => {
+                let _ = $expr;
+                return Err(fault_error(stringify!($method_name)));
+            }
+            None => return $expr,
+        }
+    };
+}
+
+/// Metastore wrapper injecting latency and connection errors into the requests forwarded to the
+/// underlying metastore.
+///
+/// Faults are drawn from a seeded random number generator, so a given sequence of requests
+/// always suffers the same faults. This metastore is meant to exercise the retry and recovery
+/// logic of the indexing pipelines and the janitor in tests and chaos runs.
+pub struct FaultInjectingMetastore {
+    underlying: Box<dyn Metastore>,
+    faults: Mutex<MetastoreFaults>,
+    rng: Mutex<StdRng>,
+    num_injected_faults: AtomicU64,
+}
+
+impl FaultInjectingMetastore {
+    /// Creates a new [`FaultInjectingMetastore`] wrapping `metastore`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fault rates are invalid.
+    pub fn new(metastore: Box<dyn Metastore>, faults: MetastoreFaults, seed: u64) -> Self {
+        faults.validate();
+        Self {
+            underlying: metastore,
+            faults: Mutex::new(faults),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+            num_injected_faults: AtomicU64::new(0),
+        }
+    }
+
+    /// Replaces the injected faults, for instance to let the metastore recover in a chaos run.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fault rates are invalid.
+    pub fn set_faults(&self, faults: MetastoreFaults) {
+        faults.validate();
+        *self.faults.lock().unwrap() = faults;
+    }
+
+    /// Returns the number of requests that failed because of an injected fault.
+    pub fn num_injected_faults(&self) -> u64 {
+        self.num_injected_faults.load(Ordering::Relaxed)
+    }
+
+    /// Draws the fault of a request, then waits for the latency to elapse.
+    async fn draw_fault(&self, is_write: bool) -> Option<Fault> {
+        let (latency, fault_opt) = {
+            let faults = self.faults.lock().unwrap();
+            let sample = self.rng.lock().unwrap().gen::<f64>();
+            let fault_opt = if sample < faults.error_rate {
+                Some(Fault::Error)
+            } else if is_write && sample < faults.error_rate + faults.post_commit_error_rate {
+                Some(Fault::PostCommitError)
+            } else {
+                None
+            };
+            (faults.latency, fault_opt)
+        };
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        if fault_opt.is_some() {
+            self.num_injected_faults.fetch_add(1, Ordering::Relaxed);
+        }
+        fault_opt
+    }
+}
+
+#[async_trait]
+impl Metastore for FaultInjectingMetastore {
+    fn uri(&self) -> &Uri {
+        self.underlying.uri()
+    }
+
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        self.underlying.check_connectivity().await
+    }
+
+    // Index API
+
+    async fn create_index(&self, index_metadata: IndexMetadata) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            create_index,
+            self.underlying.create_index(index_metadata).await
+        );
+    }
+
+    async fn index_exists(&self, index_id: &str) -> MetastoreResult<bool> {
+        inject_faults!(
+            self,
+            read,
+            index_exists,
+            self.underlying.index_exists(index_id).await
+        );
+    }
+
+    async fn index_metadata(&self, index_id: &str) -> MetastoreResult<IndexMetadata> {
+        inject_faults!(
+            self,
+            read,
+            index_metadata,
+            self.underlying.index_metadata(index_id).await
+        );
+    }
+
+    async fn list_indexes_metadatas(&self) -> MetastoreResult<Vec<IndexMetadata>> {
+        inject_faults!(
+            self,
+            read,
+            list_indexes_metadatas,
+            self.underlying.list_indexes_metadatas().await
+        );
+    }
+
+    async fn delete_index(&self, index_id: &str) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            delete_index,
+            self.underlying.delete_index(index_id).await
+        );
+    }
+
+    // Split API
+
+    async fn stage_split(
+        &self,
+        index_id: &str,
+        split_metadata: SplitMetadata,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            stage_split,
+            self.underlying.stage_split(index_id, split_metadata).await
+        );
+    }
+
+    async fn publish_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        replaced_split_ids: &[&'a str],
+        checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            publish_splits,
+            self.underlying
+                .publish_splits(
+                    index_id,
+                    split_ids,
+                    replaced_split_ids,
+                    checkpoint_delta_opt,
+                )
+                .await
+        );
+    }
+
+    async fn list_splits(
+        &self,
+        index_id: &str,
+        split_state: SplitState,
+        time_range: Option<Range<i64>>,
+        tags: Option<TagFilterAst>,
+    ) -> MetastoreResult<Vec<Split>> {
+        inject_faults!(
+            self,
+            read,
+            list_splits,
+            self.underlying
+                .list_splits(index_id, split_state, time_range, tags)
+                .await
+        );
+    }
+
+    async fn list_all_splits(&self, index_id: &str) -> MetastoreResult<Vec<Split>> {
+        inject_faults!(
+            self,
+            read,
+            list_all_splits,
+            self.underlying.list_all_splits(index_id).await
+        );
+    }
+
+    async fn mark_splits_for_deletion<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            mark_splits_for_deletion,
+            self.underlying
+                .mark_splits_for_deletion(index_id, split_ids)
+                .await
+        );
+    }
+
+    async fn delete_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            delete_splits,
+            self.underlying.delete_splits(index_id, split_ids).await
+        );
+    }
+
+    async fn record_split_searches(
+        &self,
+        index_id: &str,
+        num_searches_per_split: HashMap<String, u64>,
+        search_timestamp: i64,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            record_split_searches,
+            self.underlying
+                .record_split_searches(index_id, num_searches_per_split, search_timestamp)
+                .await
+        );
+    }
+
+    async fn update_split_storage_tier(
+        &self,
+        index_id: &str,
+        split_id: &str,
+        storage_tier: SplitStorageTier,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            update_split_storage_tier,
+            self.underlying
+                .update_split_storage_tier(index_id, split_id, storage_tier)
+                .await
+        );
+    }
+
+    // Source API
+
+    async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            add_source,
+            self.underlying.add_source(index_id, source).await
+        );
+    }
+
+    async fn update_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            update_source,
+            self.underlying.update_source(index_id, source).await
+        );
+    }
+
+    async fn toggle_source(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        enable: bool,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            toggle_source,
+            self.underlying
+                .toggle_source(index_id, source_id, enable)
+                .await
+        );
+    }
+
+    async fn reset_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            reset_source_checkpoint,
+            self.underlying
+                .reset_source_checkpoint(index_id, source_id)
+                .await
+        );
+    }
+
+    async fn update_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            update_source_checkpoint,
+            self.underlying
+                .update_source_checkpoint(index_id, source_id, source_checkpoint)
+                .await
+        );
+    }
+
+    async fn acquire_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        partition_set_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        inject_faults!(
+            self,
+            write,
+            acquire_publish_token,
+            self.underlying
+                .acquire_publish_token(index_id, source_id, partition_set_id)
+                .await
+        );
+    }
+
+    async fn delete_source(&self, index_id: &str, source_id: &str) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            delete_source,
+            self.underlying.delete_source(index_id, source_id).await
+        );
+    }
+
+    // Stored queries API
+
+    async fn put_stored_query(
+        &self,
+        index_id: &str,
+        stored_query: StoredQuery,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            put_stored_query,
+            self.underlying
+                .put_stored_query(index_id, stored_query)
+                .await
+        );
+    }
+
+    async fn delete_stored_query(&self, index_id: &str, name: &str) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            delete_stored_query,
+            self.underlying.delete_stored_query(index_id, name).await
+        );
+    }
+
+    async fn update_alert_state(
+        &self,
+        index_id: &str,
+        alert_state: AlertState,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            update_alert_state,
+            self.underlying
+                .update_alert_state(index_id, alert_state)
+                .await
+        );
+    }
+
+    // Delete tasks API
+
+    async fn create_delete_task(&self, delete_query: DeleteQuery) -> MetastoreResult<DeleteTask> {
+        inject_faults!(
+            self,
+            write,
+            create_delete_task,
+            self.underlying.create_delete_task(delete_query).await
+        );
+    }
+
+    async fn list_delete_tasks(
+        &self,
+        index_id: &str,
+        opstamp_start: u64,
+    ) -> MetastoreResult<Vec<DeleteTask>> {
+        inject_faults!(
+            self,
+            read,
+            list_delete_tasks,
+            self.underlying
+                .list_delete_tasks(index_id, opstamp_start)
+                .await
+        );
+    }
+
+    async fn last_delete_opstamp(&self, index_id: &str) -> MetastoreResult<u64> {
+        inject_faults!(
+            self,
+            read,
+            last_delete_opstamp,
+            self.underlying.last_delete_opstamp(index_id).await
+        );
+    }
+
+    async fn update_splits_delete_opstamp<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        delete_opstamp: u64,
+    ) -> MetastoreResult<()> {
+        inject_faults!(
+            self,
+            write,
+            update_splits_delete_opstamp,
+            self.underlying
+                .update_splits_delete_opstamp(index_id, split_ids, delete_opstamp)
+                .await
+        );
+    }
+
+    async fn list_stale_splits(
+        &self,
+        index_id: &str,
+        delete_opstamp: u64,
+        num_splits: usize,
+    ) -> MetastoreResult<Vec<Split>> {
+        inject_faults!(
+            self,
+            read,
+            list_stale_splits,
+            self.underlying
+                .list_stale_splits(index_id, delete_opstamp, num_splits)
+                .await
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+    use crate::FileBackedMetastore;
+
+    #[tokio::test]
+    async fn test_fault_injecting_metastore_errors() {
+        let faults = MetastoreFaults {
+            error_rate: 1.0,
+            ..Default::default()
+        };
+        let metastore = FaultInjectingMetastore::new(
+            Box::new(FileBackedMetastore::for_test(Arc::new(
+                RamStorage::default(),
+            ))),
+            faults,
+            0,
+        );
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+        let error = metastore.create_index(index_metadata).await.unwrap_err();
+        assert!(matches!(error, MetastoreError::ConnectionError { .. }));
+        assert_eq!(metastore.num_injected_faults(), 1);
+
+        metastore.set_faults(MetastoreFaults::default());
+        assert!(!metastore.index_exists("test-index").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_fault_injecting_metastore_post_commit_errors() {
+        let faults = MetastoreFaults {
+            post_commit_error_rate: 1.0,
+            ..Default::default()
+        };
+        let metastore = FaultInjectingMetastore::new(
+            Box::new(FileBackedMetastore::for_test(Arc::new(
+                RamStorage::default(),
+            ))),
+            faults,
+            0,
+        );
+        let index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+        let error = metastore.create_index(index_metadata).await.unwrap_err();
+        assert!(matches!(error, MetastoreError::ConnectionError { .. }));
+        // The write was applied and reads do not suffer post-commit errors.
+        assert!(metastore.index_exists("test-index").await.unwrap());
+        assert_eq!(metastore.num_injected_faults(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fault_injecting_metastore_is_deterministic() {
+        let faults = MetastoreFaults {
+            error_rate: 0.5,
+            ..Default::default()
+        };
+        let mut outcomes = Vec::new();
+        for _ in 0..2 {
+            let metastore = FaultInjectingMetastore::new(
+                Box::new(FileBackedMetastore::for_test(Arc::new(
+                    RamStorage::default(),
+                ))),
+                faults.clone(),
+                42,
+            );
+            let mut metastore_outcomes = Vec::new();
+            for _ in 0..32 {
+                metastore_outcomes.push(metastore.list_indexes_metadatas().await.is_ok());
+            }
+            assert!(metastore_outcomes.contains(&true));
+            assert!(metastore_outcomes.contains(&false));
+            outcomes.push(metastore_outcomes);
+        }
+        assert_eq!(outcomes[0], outcomes[1]);
+    }
+}
@@ -26,16 +26,16 @@ use std::fmt::Debug;
 use std::ops::Range;
 
 use itertools::Itertools;
-use quickwit_config::SourceConfig;
+use quickwit_config::{SourceConfig, StoredQuery};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
-use crate::checkpoint::IndexCheckpointDelta;
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken, SourceCheckpoint};
 use crate::{
-    split_tag_filter, split_time_range_filter, IndexMetadata, MetastoreError, MetastoreResult,
-    Split, SplitMetadata, SplitState,
+    split_tag_filter, split_time_range_filter, AlertState, IndexMetadata, MetastoreError,
+    MetastoreResult, Split, SplitMetadata, SplitState, SplitStorageTier,
 };
 
 /// A `FileBackedIndex` object carries an index metadata and its split metadata.
@@ -207,6 +207,7 @@ impl FileBackedIndex {
         split_ids: &[&str],
         deletable_states: &[SplitState],
     ) -> MetastoreResult<bool> {
+        self.metadata.check_writable()?;
         let mut is_modified = false;
         let mut split_not_found_ids = Vec::new();
         let mut non_deletable_split_ids = Vec::new();
@@ -309,11 +310,45 @@ impl FileBackedIndex {
         replaced_split_ids: &[&'a str],
         checkpoint_delta_opt: Option<IndexCheckpointDelta>,
     ) -> MetastoreResult<()> {
+        // The splits of a read-only index are published once, when the index is attached, and
+        // are never replaced afterwards.
+        if !replaced_split_ids.is_empty() || checkpoint_delta_opt.is_some() {
+            self.metadata.check_writable()?;
+        }
         if let Some(checkpoint_delta) = checkpoint_delta_opt {
-            self.metadata.checkpoint.try_apply_delta(checkpoint_delta)?;
+            self.metadata.try_apply_checkpoint_delta(checkpoint_delta)?;
         }
         self.mark_splits_as_published_helper(split_ids)?;
-        self.mark_splits_for_deletion(replaced_split_ids, &[SplitState::Published])?;
+        if !replaced_split_ids.is_empty() {
+            self.check_replaced_splits(replaced_split_ids)?;
+            self.mark_splits_for_deletion(replaced_split_ids, &[SplitState::Published])?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the splits replaced by a publish are published. Otherwise, the writer worked
+    /// on a stale view of the index: the splits were replaced or deleted in the meantime.
+    fn check_replaced_splits(&self, replaced_split_ids: &[&str]) -> MetastoreResult<()> {
+        let mut split_not_found_ids = Vec::new();
+        let mut split_not_published_ids = Vec::new();
+
+        for &split_id in replaced_split_ids {
+            match self.splits.get(split_id) {
+                Some(split) if split.split_state == SplitState::Published => {}
+                Some(_) => split_not_published_ids.push(split_id.to_string()),
+                None => split_not_found_ids.push(split_id.to_string()),
+            }
+        }
+        if !split_not_found_ids.is_empty() {
+            return Err(MetastoreError::SplitsDoNotExist {
+                split_ids: split_not_found_ids,
+            });
+        }
+        if !split_not_published_ids.is_empty() {
+            return Err(MetastoreError::ReplacedSplitsNotPublished {
+                split_ids: split_not_published_ids,
+            });
+        }
         Ok(())
     }
 
@@ -365,6 +400,7 @@ impl FileBackedIndex {
 
     /// Deletes multiple splits.
     pub(crate) fn delete_splits(&mut self, split_ids: &[&str]) -> MetastoreResult<()> {
+        self.metadata.check_writable()?;
         let mut split_not_found_ids = Vec::new();
         let mut split_not_deletable_ids = Vec::new();
 
@@ -393,11 +429,61 @@ impl FileBackedIndex {
         Ok(())
     }
 
+    /// Adds reported searches to the access statistics of the splits. Returns whether a mutation
+    /// occurred.
+    pub(crate) fn record_split_searches(
+        &mut self,
+        num_searches_per_split: HashMap<String, u64>,
+        search_timestamp: i64,
+    ) -> MetastoreResult<bool> {
+        let mut mutation_occurred = false;
+        for (split_id, num_searches) in num_searches_per_split {
+            // Splits deleted since the searches were reported are ignored.
+            if let Some(split) = self.splits.get_mut(&split_id) {
+                split
+                    .split_metadata
+                    .access_stats
+                    .record_searches(num_searches, search_timestamp);
+                mutation_occurred = true;
+            }
+        }
+        Ok(mutation_occurred)
+    }
+
+    /// Updates the storage tier of a split and resets its number of searches. Returns whether a
+    /// mutation occurred.
+    pub(crate) fn update_split_storage_tier(
+        &mut self,
+        split_id: &str,
+        storage_tier: SplitStorageTier,
+    ) -> MetastoreResult<bool> {
+        let split =
+            self.splits
+                .get_mut(split_id)
+                .ok_or_else(|| MetastoreError::SplitsDoNotExist {
+                    split_ids: vec![split_id.to_string()],
+                })?;
+        if split.split_metadata.storage_tier == storage_tier {
+            return Ok(false);
+        }
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        split.split_metadata.storage_tier = storage_tier;
+        split.split_metadata.access_stats.num_searches = 0;
+        split.split_metadata.access_stats.storage_tier_timestamp = Some(now_timestamp);
+        split.update_timestamp = now_timestamp;
+        Ok(true)
+    }
+
     /// Adds a source. Returns whether a mutation occurred.
     pub(crate) fn add_source(&mut self, source: SourceConfig) -> MetastoreResult<bool> {
         self.metadata.add_source(source)
     }
 
+    /// Replaces the config of a source. Returns whether a mutation occurred.
+    pub(crate) fn update_source(&mut self, source: SourceConfig) -> MetastoreResult<bool> {
+        self.metadata.update_source(source)
+    }
+
     pub(crate) fn toggle_source(&mut self, source_id: &str, enable: bool) -> MetastoreResult<bool> {
         self.metadata.toggle_source(source_id, enable)
     }
@@ -407,11 +493,47 @@ impl FileBackedIndex {
         self.metadata.delete_source(source_id)
     }
 
+    /// Adds or replaces a stored query. Returns whether a mutation occurred.
+    pub(crate) fn put_stored_query(&mut self, stored_query: StoredQuery) -> MetastoreResult<bool> {
+        self.metadata.put_stored_query(stored_query)
+    }
+
+    /// Deletes a stored query. Returns whether a mutation occurred.
+    pub(crate) fn delete_stored_query(&mut self, name: &str) -> MetastoreResult<bool> {
+        self.metadata.delete_stored_query(name)
+    }
+
+    /// Replaces the evaluation state of the alert of a stored query. Returns whether a mutation
+    /// occurred.
+    pub(crate) fn update_alert_state(&mut self, alert_state: AlertState) -> MetastoreResult<bool> {
+        self.metadata.update_alert_state(alert_state)
+    }
+
     /// Resets the checkpoint of a source. Returns whether a mutation occurred.
     pub(crate) fn reset_source_checkpoint(&mut self, source_id: &str) -> MetastoreResult<bool> {
         Ok(self.metadata.checkpoint.reset_source(source_id))
     }
 
+    /// Replaces the checkpoint of a source. Returns whether a mutation occurred.
+    pub(crate) fn update_source_checkpoint(
+        &mut self,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> MetastoreResult<bool> {
+        self.metadata
+            .update_source_checkpoint(source_id, source_checkpoint)
+    }
+
+    /// Acquires a new publish token for a partition set of a source.
+    pub(crate) fn acquire_publish_token(
+        &mut self,
+        source_id: &str,
+        partition_set_id: &str,
+    ) -> PublishToken {
+        self.metadata
+            .acquire_publish_token(source_id, partition_set_id)
+    }
+
     /// Creates [`DeleteTask`] from a [`DeleteQuery`].
     pub(crate) fn create_delete_task(
         &mut self,
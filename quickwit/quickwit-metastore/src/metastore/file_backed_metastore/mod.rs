@@ -35,7 +35,7 @@ use async_trait::async_trait;
 use futures::future::try_join_all;
 use itertools::Itertools;
 use quickwit_common::uri::Uri;
-use quickwit_config::SourceConfig;
+use quickwit_config::{SourceConfig, StoredQuery};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 use quickwit_storage::Storage;
@@ -48,9 +48,10 @@ use self::store_operations::{
     delete_index, fetch_and_build_indexes_states, fetch_index, index_exists, put_index,
     put_indexes_states,
 };
-use crate::checkpoint::IndexCheckpointDelta;
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken, SourceCheckpoint};
 use crate::{
-    IndexMetadata, Metastore, MetastoreError, MetastoreResult, Split, SplitMetadata, SplitState,
+    AlertState, IndexMetadata, Metastore, MetastoreError, MetastoreResult, Split, SplitMetadata,
+    SplitState, SplitStorageTier,
 };
 
 /// State of an index tracked by the metastore.
@@ -439,12 +440,44 @@ impl Metastore for FileBackedMetastore {
         Ok(())
     }
 
+    async fn record_split_searches(
+        &self,
+        index_id: &str,
+        num_searches_per_split: HashMap<String, u64>,
+        search_timestamp: i64,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.record_split_searches(num_searches_per_split, search_timestamp)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn update_split_storage_tier(
+        &self,
+        index_id: &str,
+        split_id: &str,
+        storage_tier: SplitStorageTier,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.update_split_storage_tier(split_id, storage_tier)
+        })
+        .await?;
+        Ok(())
+    }
+
     async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
         self.mutate(index_id, |index| index.add_source(source))
             .await?;
         Ok(())
     }
 
+    async fn update_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| index.update_source(source))
+            .await?;
+        Ok(())
+    }
+
     async fn toggle_source(
         &self,
         index_id: &str,
@@ -462,6 +495,32 @@ impl Metastore for FileBackedMetastore {
         Ok(())
     }
 
+    async fn put_stored_query(
+        &self,
+        index_id: &str,
+        stored_query: StoredQuery,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| index.put_stored_query(stored_query))
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_stored_query(&self, index_id: &str, name: &str) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| index.delete_stored_query(name))
+            .await?;
+        Ok(())
+    }
+
+    async fn update_alert_state(
+        &self,
+        index_id: &str,
+        alert_state: AlertState,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| index.update_alert_state(alert_state))
+            .await?;
+        Ok(())
+    }
+
     async fn reset_source_checkpoint(
         &self,
         index_id: &str,
@@ -472,6 +531,34 @@ impl Metastore for FileBackedMetastore {
         Ok(())
     }
 
+    async fn update_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.update_source_checkpoint(source_id, source_checkpoint)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn acquire_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        partition_set_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        let mut publish_token_opt = None;
+        self.mutate(index_id, |index| {
+            publish_token_opt = Some(index.acquire_publish_token(source_id, partition_set_id));
+            Ok(true)
+        })
+        .await?;
+        Ok(publish_token_opt.expect("The mutation should have acquired a publish token."))
+    }
+
     /// -------------------------------------------------------------------------------
     /// Read-only accessors
 
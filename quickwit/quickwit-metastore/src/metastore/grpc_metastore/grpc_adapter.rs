@@ -26,14 +26,17 @@ use quickwit_common::extract_time_range;
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::metastore_api_service_server::{self as grpc};
 use quickwit_proto::metastore_api::{
-    AddSourceRequest, CreateIndexRequest, CreateIndexResponse, DeleteIndexRequest,
-    DeleteIndexResponse, DeleteQuery, DeleteSourceRequest, DeleteSplitsRequest, DeleteTask,
-    IndexMetadataRequest, IndexMetadataResponse, LastDeleteOpstampRequest,
-    LastDeleteOpstampResponse, ListAllSplitsRequest, ListDeleteTasksRequest,
-    ListDeleteTasksResponse, ListIndexesMetadatasRequest, ListIndexesMetadatasResponse,
-    ListSplitsRequest, ListSplitsResponse, ListStaleSplitsRequest, MarkSplitsForDeletionRequest,
-    PublishSplitsRequest, ResetSourceCheckpointRequest, SourceResponse, SplitResponse,
-    StageSplitRequest, ToggleSourceRequest, UpdateSplitsDeleteOpstampRequest,
+    AcquirePublishTokenRequest, AcquirePublishTokenResponse, AddSourceRequest, CreateIndexRequest,
+    CreateIndexResponse, DeleteIndexRequest, DeleteIndexResponse, DeleteQuery, DeleteSourceRequest,
+    DeleteSplitsRequest, DeleteStoredQueryRequest, DeleteTask, IndexMetadataRequest,
+    IndexMetadataResponse, LastDeleteOpstampRequest, LastDeleteOpstampResponse,
+    ListAllSplitsRequest, ListDeleteTasksRequest, ListDeleteTasksResponse,
+    ListIndexesMetadatasRequest, ListIndexesMetadatasResponse, ListSplitsRequest,
+    ListSplitsResponse, ListStaleSplitsRequest, MarkSplitsForDeletionRequest, PublishSplitsRequest,
+    PutStoredQueryRequest, RecordSplitSearchesRequest, ResetSourceCheckpointRequest,
+    SourceResponse, SplitResponse, StageSplitRequest, StoredQueryResponse, ToggleSourceRequest,
+    UpdateAlertStateRequest, UpdateSourceCheckpointRequest, UpdateSourceRequest,
+    UpdateSplitStorageTierRequest, UpdateSplitsDeleteOpstampRequest,
     UpdateSplitsDeleteOpstampResponse,
 };
 use quickwit_proto::tonic;
@@ -275,6 +278,53 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
         Ok(tonic::Response::new(delete_splits_reply))
     }
 
+    async fn record_split_searches(
+        &self,
+        request: tonic::Request<RecordSplitSearchesRequest>,
+    ) -> Result<tonic::Response<SplitResponse>, tonic::Status> {
+        let record_split_searches_request = request.into_inner();
+        let num_searches_per_split = serde_json::from_str(
+            &record_split_searches_request.num_searches_per_split_serialized_json,
+        )
+        .map_err(|error| MetastoreError::JsonDeserializeError {
+            name: "NumSearchesPerSplit".to_string(),
+            message: error.to_string(),
+        })?;
+        let record_split_searches_reply = self
+            .0
+            .record_split_searches(
+                &record_split_searches_request.index_id,
+                num_searches_per_split,
+                record_split_searches_request.search_timestamp,
+            )
+            .await
+            .map(|_| SplitResponse {})?;
+        Ok(tonic::Response::new(record_split_searches_reply))
+    }
+
+    async fn update_split_storage_tier(
+        &self,
+        request: tonic::Request<UpdateSplitStorageTierRequest>,
+    ) -> Result<tonic::Response<SplitResponse>, tonic::Status> {
+        let update_split_storage_tier_request = request.into_inner();
+        let storage_tier =
+            serde_json::from_str(&update_split_storage_tier_request.storage_tier_serialized_json)
+                .map_err(|error| MetastoreError::JsonDeserializeError {
+                name: "SplitStorageTier".to_string(),
+                message: error.to_string(),
+            })?;
+        let update_split_storage_tier_reply = self
+            .0
+            .update_split_storage_tier(
+                &update_split_storage_tier_request.index_id,
+                &update_split_storage_tier_request.split_id,
+                storage_tier,
+            )
+            .await
+            .map(|_| SplitResponse {})?;
+        Ok(tonic::Response::new(update_split_storage_tier_reply))
+    }
+
     async fn add_source(
         &self,
         request: tonic::Request<AddSourceRequest>,
@@ -293,6 +343,26 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
         Ok(tonic::Response::new(add_source_reply))
     }
 
+    async fn update_source(
+        &self,
+        request: tonic::Request<UpdateSourceRequest>,
+    ) -> Result<tonic::Response<SourceResponse>, tonic::Status> {
+        let update_source_request = request.into_inner();
+        let source_config = serde_json::from_str(
+            &update_source_request.source_config_serialized_json,
+        )
+        .map_err(|error| MetastoreError::JsonDeserializeError {
+            name: "SourceConfig".to_string(),
+            message: error.to_string(),
+        })?;
+        let update_source_reply = self
+            .0
+            .update_source(&update_source_request.index_id, source_config)
+            .await
+            .map(|_| SourceResponse {})?;
+        Ok(tonic::Response::new(update_source_reply))
+    }
+
     async fn toggle_source(
         &self,
         request: tonic::Request<ToggleSourceRequest>,
@@ -339,6 +409,99 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
         Ok(tonic::Response::new(reply))
     }
 
+    async fn update_source_checkpoint(
+        &self,
+        request: tonic::Request<UpdateSourceCheckpointRequest>,
+    ) -> Result<tonic::Response<SourceResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let source_checkpoint = serde_json::from_str(&request.source_checkpoint_serialized_json)
+            .map_err(|error| MetastoreError::JsonDeserializeError {
+                name: "SourceCheckpoint".to_string(),
+                message: error.to_string(),
+            })?;
+        let reply = self
+            .0
+            .update_source_checkpoint(&request.index_id, &request.source_id, source_checkpoint)
+            .await
+            .map(|_| SourceResponse {})?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    async fn acquire_publish_token(
+        &self,
+        request: tonic::Request<AcquirePublishTokenRequest>,
+    ) -> Result<tonic::Response<AcquirePublishTokenResponse>, tonic::Status> {
+        let request = request.into_inner();
+        let publish_token = self
+            .0
+            .acquire_publish_token(
+                &request.index_id,
+                &request.source_id,
+                &request.partition_set_id,
+            )
+            .await?;
+        let reply = AcquirePublishTokenResponse {
+            publish_token: publish_token.token,
+        };
+        Ok(tonic::Response::new(reply))
+    }
+
+    async fn put_stored_query(
+        &self,
+        request: tonic::Request<PutStoredQueryRequest>,
+    ) -> Result<tonic::Response<StoredQueryResponse>, tonic::Status> {
+        let put_stored_query_request = request.into_inner();
+        let stored_query = serde_json::from_str(
+            &put_stored_query_request.stored_query_serialized_json,
+        )
+        .map_err(|error| MetastoreError::JsonDeserializeError {
+            name: "StoredQuery".to_string(),
+            message: error.to_string(),
+        })?;
+        let put_stored_query_reply = self
+            .0
+            .put_stored_query(&put_stored_query_request.index_id, stored_query)
+            .await
+            .map(|_| StoredQueryResponse {})?;
+        Ok(tonic::Response::new(put_stored_query_reply))
+    }
+
+    async fn delete_stored_query(
+        &self,
+        request: tonic::Request<DeleteStoredQueryRequest>,
+    ) -> Result<tonic::Response<StoredQueryResponse>, tonic::Status> {
+        let delete_stored_query_request = request.into_inner();
+        let delete_stored_query_reply = self
+            .0
+            .delete_stored_query(
+                &delete_stored_query_request.index_id,
+                &delete_stored_query_request.stored_query_name,
+            )
+            .await
+            .map(|_| StoredQueryResponse {})?;
+        Ok(tonic::Response::new(delete_stored_query_reply))
+    }
+
+    async fn update_alert_state(
+        &self,
+        request: tonic::Request<UpdateAlertStateRequest>,
+    ) -> Result<tonic::Response<StoredQueryResponse>, tonic::Status> {
+        let update_alert_state_request = request.into_inner();
+        let alert_state = serde_json::from_str(
+            &update_alert_state_request.alert_state_serialized_json,
+        )
+        .map_err(|error| MetastoreError::JsonDeserializeError {
+            name: "AlertState".to_string(),
+            message: error.to_string(),
+        })?;
+        let update_alert_state_reply = self
+            .0
+            .update_alert_state(&update_alert_state_request.index_id, alert_state)
+            .await
+            .map(|_| StoredQueryResponse {})?;
+        Ok(tonic::Response::new(update_alert_state_reply))
+    }
+
     async fn last_delete_opstamp(
         &self,
         request: tonic::Request<LastDeleteOpstampRequest>,
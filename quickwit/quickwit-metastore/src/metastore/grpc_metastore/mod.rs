@@ -19,7 +19,7 @@
 
 mod grpc_adapter;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::net::SocketAddr;
 use std::ops::Range;
@@ -32,16 +32,18 @@ use itertools::Itertools;
 use quickwit_cluster::ClusterMember;
 use quickwit_common::uri::Uri as QuickwitUri;
 use quickwit_config::service::QuickwitService;
-use quickwit_config::SourceConfig;
+use quickwit_config::{SourceConfig, StoredQuery};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::metastore_api_service_client::MetastoreApiServiceClient;
 use quickwit_proto::metastore_api::{
-    AddSourceRequest, CreateIndexRequest, DeleteIndexRequest, DeleteQuery, DeleteSourceRequest,
-    DeleteSplitsRequest, DeleteTask, IndexMetadataRequest, LastDeleteOpstampRequest,
-    ListAllSplitsRequest, ListDeleteTasksRequest, ListIndexesMetadatasRequest, ListSplitsRequest,
-    ListStaleSplitsRequest, MarkSplitsForDeletionRequest, PublishSplitsRequest,
-    ResetSourceCheckpointRequest, StageSplitRequest, ToggleSourceRequest,
-    UpdateSplitsDeleteOpstampRequest,
+    AcquirePublishTokenRequest, AddSourceRequest, CreateIndexRequest, DeleteIndexRequest,
+    DeleteQuery, DeleteSourceRequest, DeleteSplitsRequest, DeleteStoredQueryRequest, DeleteTask,
+    IndexMetadataRequest, LastDeleteOpstampRequest, ListAllSplitsRequest, ListDeleteTasksRequest,
+    ListIndexesMetadatasRequest, ListSplitsRequest, ListStaleSplitsRequest,
+    MarkSplitsForDeletionRequest, PublishSplitsRequest, PutStoredQueryRequest,
+    RecordSplitSearchesRequest, ResetSourceCheckpointRequest, StageSplitRequest,
+    ToggleSourceRequest, UpdateAlertStateRequest, UpdateSourceCheckpointRequest,
+    UpdateSourceRequest, UpdateSplitStorageTierRequest, UpdateSplitsDeleteOpstampRequest,
 };
 use quickwit_proto::tonic::transport::{Channel, Endpoint};
 use quickwit_proto::tonic::Status;
@@ -54,9 +56,10 @@ use tower::timeout::error::Elapsed;
 use tower::timeout::Timeout;
 use tracing::{error, info};
 
-use crate::checkpoint::IndexCheckpointDelta;
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken, SourceCheckpoint};
 use crate::{
-    IndexMetadata, Metastore, MetastoreError, MetastoreResult, Split, SplitMetadata, SplitState,
+    AlertState, IndexMetadata, Metastore, MetastoreError, MetastoreResult, Split, SplitMetadata,
+    SplitState, SplitStorageTier,
 };
 
 const CLIENT_TIMEOUT_DURATION: Duration = if cfg!(test) {
@@ -373,6 +376,60 @@ impl Metastore for MetastoreGrpcClient {
         Ok(())
     }
 
+    /// Records the searches received by splits of a given index.
+    async fn record_split_searches(
+        &self,
+        index_id: &str,
+        num_searches_per_split: HashMap<String, u64>,
+        search_timestamp: i64,
+    ) -> MetastoreResult<()> {
+        let num_searches_per_split_serialized_json = serde_json::to_string(&num_searches_per_split)
+            .map_err(|error| MetastoreError::JsonSerializeError {
+                name: "NumSearchesPerSplit".to_string(),
+                message: error.to_string(),
+            })?;
+        let request = RecordSplitSearchesRequest {
+            index_id: index_id.to_string(),
+            num_searches_per_split_serialized_json,
+            search_timestamp,
+        };
+        self.0
+            .clone()
+            .record_split_searches(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    /// Updates the storage tier of a split of a given index.
+    async fn update_split_storage_tier(
+        &self,
+        index_id: &str,
+        split_id: &str,
+        storage_tier: SplitStorageTier,
+    ) -> MetastoreResult<()> {
+        let storage_tier_serialized_json =
+            serde_json::to_string(&storage_tier).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    name: "SplitStorageTier".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let request = UpdateSplitStorageTierRequest {
+            index_id: index_id.to_string(),
+            split_id: split_id.to_string(),
+            storage_tier_serialized_json,
+        };
+        self.0
+            .clone()
+            .update_split_storage_tier(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
     /// Adds a source to a given index.
     async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
         let source_config_serialized_json =
@@ -393,6 +450,26 @@ impl Metastore for MetastoreGrpcClient {
         Ok(())
     }
 
+    /// Replaces the config of a source of a given index.
+    async fn update_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
+        let source_config_serialized_json =
+            serde_json::to_string(&source).map_err(|error| MetastoreError::JsonSerializeError {
+                name: "SourceConfig".to_string(),
+                message: error.to_string(),
+            })?;
+        let request = UpdateSourceRequest {
+            index_id: index_id.to_string(),
+            source_config_serialized_json,
+        };
+        self.0
+            .clone()
+            .update_source(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
     /// Toggles the source `enabled` field value.
     async fn toggle_source(
         &self,
@@ -448,6 +525,125 @@ impl Metastore for MetastoreGrpcClient {
         Ok(())
     }
 
+    /// Replaces a source checkpoint.
+    async fn update_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> MetastoreResult<()> {
+        let source_checkpoint_serialized_json =
+            serde_json::to_string(&source_checkpoint).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    name: "SourceCheckpoint".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let request = UpdateSourceCheckpointRequest {
+            index_id: index_id.to_string(),
+            source_id: source_id.to_string(),
+            source_checkpoint_serialized_json,
+        };
+        self.0
+            .clone()
+            .update_source_checkpoint(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    /// Acquires a new publish token for a partition set of a source.
+    async fn acquire_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        partition_set_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        let request = AcquirePublishTokenRequest {
+            index_id: index_id.to_string(),
+            source_id: source_id.to_string(),
+            partition_set_id: partition_set_id.to_string(),
+        };
+        let response = self
+            .0
+            .clone()
+            .acquire_publish_token(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(PublishToken {
+            partition_set_id: partition_set_id.to_string(),
+            token: response.publish_token,
+        })
+    }
+
+    /// Adds or replaces a stored query of a given index.
+    async fn put_stored_query(
+        &self,
+        index_id: &str,
+        stored_query: StoredQuery,
+    ) -> MetastoreResult<()> {
+        let stored_query_serialized_json =
+            serde_json::to_string(&stored_query).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    name: "StoredQuery".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let request = PutStoredQueryRequest {
+            index_id: index_id.to_string(),
+            stored_query_serialized_json,
+        };
+        self.0
+            .clone()
+            .put_stored_query(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    /// Deletes a stored query of a given index.
+    async fn delete_stored_query(&self, index_id: &str, name: &str) -> MetastoreResult<()> {
+        let request = DeleteStoredQueryRequest {
+            index_id: index_id.to_string(),
+            stored_query_name: name.to_string(),
+        };
+        self.0
+            .clone()
+            .delete_stored_query(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    /// Updates the evaluation state of the alert of a stored query of a given index.
+    async fn update_alert_state(
+        &self,
+        index_id: &str,
+        alert_state: AlertState,
+    ) -> MetastoreResult<()> {
+        let alert_state_serialized_json = serde_json::to_string(&alert_state).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                name: "AlertState".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let request = UpdateAlertStateRequest {
+            index_id: index_id.to_string(),
+            alert_state_serialized_json,
+        };
+        self.0
+            .clone()
+            .update_alert_state(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
     async fn last_delete_opstamp(&self, index_id: &str) -> MetastoreResult<u64> {
         let request = LastDeleteOpstampRequest {
             index_id: index_id.to_string(),
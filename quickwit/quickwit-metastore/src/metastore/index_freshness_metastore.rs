@@ -0,0 +1,598 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use quickwit_cluster::Cluster;
+use quickwit_common::uri::Uri;
+use quickwit_config::{SourceConfig, StoredQuery};
+use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken, SourceCheckpoint};
+use crate::{
+    split_tag_filter, split_time_range_filter, AlertState, IndexMetadata, Metastore,
+    MetastoreResult, Split, SplitMetadata, SplitState, SplitStorageTier,
+};
+
+/// Prefix of the cluster keys under which nodes gossip the freshness of the indexes they
+/// update. The full key is `index_freshness:{index_id}`.
+pub const INDEX_FRESHNESS_KEY_PREFIX: &str = "index_freshness:";
+
+/// Maximum age of a cached list of published splits. Changes made by processes that do not
+/// gossip (the CLI, for instance) become visible to searchers after at most this duration.
+const SPLIT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Coarse freshness of an index as seen by the node that gossips it. Any change made by the node
+/// to the set of published splits of the index results in a different value.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IndexFreshness {
+    /// Timestamp (ms) of the latest change made by the node to the published splits.
+    pub last_publish_timestamp_millis: i64,
+    /// Number of splits published by the node since it started.
+    pub num_published_splits: u64,
+    /// Number of splits unpublished, i.e. replaced or marked for deletion, by the node since it
+    /// started.
+    pub num_unpublished_splits: u64,
+}
+
+fn index_freshness_key(index_id: &str) -> String {
+    format!("{INDEX_FRESHNESS_KEY_PREFIX}{index_id}")
+}
+
+/// Freshness values gossiped by the ready members of the cluster for an index, keyed by chitchat
+/// node ID.
+type FreshnessView = BTreeMap<String, String>;
+
+struct CachedPublishedSplits {
+    freshness_view: FreshnessView,
+    fetched_at: Instant,
+    splits: Vec<Split>,
+}
+
+/// Metastore wrapper that gossips the freshness of the indexes whose published splits are updated
+/// through it.
+///
+/// When the split cache is enabled, the wrapper also serves the published splits of an index
+/// from a local cache as long as the freshness gossiped by the cluster for that index has not
+/// changed since the splits were fetched, saving a metastore round trip per search.
+#[derive(Clone)]
+pub struct IndexFreshnessMetastore {
+    underlying: Arc<dyn Metastore>,
+    cluster: Arc<Cluster>,
+    local_freshness: Arc<Mutex<HashMap<String, IndexFreshness>>>,
+    split_cache_opt: Option<Arc<Mutex<HashMap<String, CachedPublishedSplits>>>>,
+}
+
+impl IndexFreshnessMetastore {
+    /// Creates a metastore that gossips the freshness of the indexes it updates.
+    pub fn new(underlying: Arc<dyn Metastore>, cluster: Arc<Cluster>) -> Self {
+        Self {
+            underlying,
+            cluster,
+            local_freshness: Default::default(),
+            split_cache_opt: None,
+        }
+    }
+
+    /// Returns a metastore sharing the same gossiped state that also caches published splits.
+    /// Meant for searchers, which can tolerate a view lagging by the gossip propagation delay.
+    pub fn with_split_cache(&self) -> Self {
+        Self {
+            underlying: self.underlying.clone(),
+            cluster: self.cluster.clone(),
+            local_freshness: self.local_freshness.clone(),
+            split_cache_opt: Some(Default::default()),
+        }
+    }
+
+    async fn report_update(
+        &self,
+        index_id: &str,
+        num_published_splits: usize,
+        num_unpublished_splits: usize,
+    ) {
+        let index_freshness = {
+            let mut local_freshness_guard = self.local_freshness.lock().unwrap();
+            let index_freshness = local_freshness_guard
+                .entry(index_id.to_string())
+                .or_default();
+            let now_millis = (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64;
+            // Keeps the timestamp strictly increasing so that every update changes the value.
+            index_freshness.last_publish_timestamp_millis =
+                now_millis.max(index_freshness.last_publish_timestamp_millis + 1);
+            index_freshness.num_published_splits += num_published_splits as u64;
+            index_freshness.num_unpublished_splits += num_unpublished_splits as u64;
+            *index_freshness
+        };
+        let index_freshness_json = serde_json::to_string(&index_freshness)
+            .expect("`IndexFreshness` should be JSON serializable.");
+        self.cluster
+            .set_key_value(index_freshness_key(index_id), index_freshness_json)
+            .await;
+        if let Some(split_cache) = &self.split_cache_opt {
+            split_cache.lock().unwrap().remove(index_id);
+        }
+    }
+
+    async fn freshness_view(&self, index_id: &str) -> FreshnessView {
+        self.cluster
+            .ready_members_key_value(&index_freshness_key(index_id))
+            .await
+            .into_iter()
+            .map(|(member, value)| (member.chitchat_id(), value))
+            .collect()
+    }
+
+    async fn list_published_splits_cached(
+        &self,
+        split_cache: &Mutex<HashMap<String, CachedPublishedSplits>>,
+        index_id: &str,
+        time_range: Option<Range<i64>>,
+        tags: Option<TagFilterAst>,
+    ) -> MetastoreResult<Vec<Split>> {
+        let filter_splits = |splits: &[Split]| -> Vec<Split> {
+            splits
+                .iter()
+                .filter(|split| split_time_range_filter(split, time_range.as_ref()))
+                .filter(|split| split_tag_filter(split, tags.as_ref()))
+                .cloned()
+                .collect()
+        };
+        // The view must be captured before fetching the splits: an update racing with the fetch
+        // then changes the view and invalidates the entry.
+        let freshness_view = self.freshness_view(index_id).await;
+        let cached_splits_opt = split_cache
+            .lock()
+            .unwrap()
+            .get(index_id)
+            .filter(|cached_splits| {
+                cached_splits.freshness_view == freshness_view
+                    && cached_splits.fetched_at.elapsed() < SPLIT_CACHE_TTL
+            })
+            .map(|cached_splits| filter_splits(&cached_splits.splits));
+        if let Some(cached_splits) = cached_splits_opt {
+            return Ok(cached_splits);
+        }
+        let splits = self
+            .underlying
+            .list_splits(index_id, SplitState::Published, None, None)
+            .await?;
+        let filtered_splits = filter_splits(&splits);
+        split_cache.lock().unwrap().insert(
+            index_id.to_string(),
+            CachedPublishedSplits {
+                freshness_view,
+                fetched_at: Instant::now(),
+                splits,
+            },
+        );
+        Ok(filtered_splits)
+    }
+}
+
+#[async_trait]
+impl Metastore for IndexFreshnessMetastore {
+    fn uri(&self) -> &Uri {
+        self.underlying.uri()
+    }
+
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        self.underlying.check_connectivity().await
+    }
+
+    // Index API
+
+    async fn create_index(&self, index_metadata: IndexMetadata) -> MetastoreResult<()> {
+        self.underlying.create_index(index_metadata).await
+    }
+
+    async fn index_exists(&self, index_id: &str) -> MetastoreResult<bool> {
+        self.underlying.index_exists(index_id).await
+    }
+
+    async fn index_metadata(&self, index_id: &str) -> MetastoreResult<IndexMetadata> {
+        self.underlying.index_metadata(index_id).await
+    }
+
+    async fn list_indexes_metadatas(&self) -> MetastoreResult<Vec<IndexMetadata>> {
+        self.underlying.list_indexes_metadatas().await
+    }
+
+    async fn delete_index(&self, index_id: &str) -> MetastoreResult<()> {
+        self.underlying.delete_index(index_id).await?;
+        self.report_update(index_id, 0, 0).await;
+        Ok(())
+    }
+
+    // Split API
+
+    async fn stage_split(
+        &self,
+        index_id: &str,
+        split_metadata: SplitMetadata,
+    ) -> MetastoreResult<()> {
+        self.underlying.stage_split(index_id, split_metadata).await
+    }
+
+    async fn publish_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        replaced_split_ids: &[&'a str],
+        checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .publish_splits(
+                index_id,
+                split_ids,
+                replaced_split_ids,
+                checkpoint_delta_opt,
+            )
+            .await?;
+        if !split_ids.is_empty() || !replaced_split_ids.is_empty() {
+            self.report_update(index_id, split_ids.len(), replaced_split_ids.len())
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn list_splits(
+        &self,
+        index_id: &str,
+        split_state: SplitState,
+        time_range: Option<Range<i64>>,
+        tags: Option<TagFilterAst>,
+    ) -> MetastoreResult<Vec<Split>> {
+        if let (Some(split_cache), SplitState::Published) = (&self.split_cache_opt, split_state) {
+            return self
+                .list_published_splits_cached(split_cache, index_id, time_range, tags)
+                .await;
+        }
+        self.underlying
+            .list_splits(index_id, split_state, time_range, tags)
+            .await
+    }
+
+    async fn list_all_splits(&self, index_id: &str) -> MetastoreResult<Vec<Split>> {
+        self.underlying.list_all_splits(index_id).await
+    }
+
+    async fn mark_splits_for_deletion<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .mark_splits_for_deletion(index_id, split_ids)
+            .await?;
+        if !split_ids.is_empty() {
+            self.report_update(index_id, 0, split_ids.len()).await;
+        }
+        Ok(())
+    }
+
+    async fn delete_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.underlying.delete_splits(index_id, split_ids).await
+    }
+
+    async fn record_split_searches(
+        &self,
+        index_id: &str,
+        num_searches_per_split: HashMap<String, u64>,
+        search_timestamp: i64,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .record_split_searches(index_id, num_searches_per_split, search_timestamp)
+            .await
+    }
+
+    async fn update_split_storage_tier(
+        &self,
+        index_id: &str,
+        split_id: &str,
+        storage_tier: SplitStorageTier,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .update_split_storage_tier(index_id, split_id, storage_tier)
+            .await?;
+        // The split is now searched from another storage: this is reported as a split replacing
+        // itself so that searchers stop serving it from their cache.
+        self.report_update(index_id, 1, 1).await;
+        Ok(())
+    }
+
+    // Source API
+
+    async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
+        self.underlying.add_source(index_id, source).await
+    }
+
+    async fn update_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
+        self.underlying.update_source(index_id, source).await
+    }
+
+    async fn toggle_source(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        enable: bool,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .toggle_source(index_id, source_id, enable)
+            .await
+    }
+
+    async fn reset_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .reset_source_checkpoint(index_id, source_id)
+            .await
+    }
+
+    async fn update_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .update_source_checkpoint(index_id, source_id, source_checkpoint)
+            .await
+    }
+
+    async fn acquire_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        partition_set_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        self.underlying
+            .acquire_publish_token(index_id, source_id, partition_set_id)
+            .await
+    }
+
+    async fn delete_source(&self, index_id: &str, source_id: &str) -> MetastoreResult<()> {
+        self.underlying.delete_source(index_id, source_id).await
+    }
+
+    // Stored queries API
+
+    async fn put_stored_query(
+        &self,
+        index_id: &str,
+        stored_query: StoredQuery,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .put_stored_query(index_id, stored_query)
+            .await
+    }
+
+    async fn delete_stored_query(&self, index_id: &str, name: &str) -> MetastoreResult<()> {
+        self.underlying.delete_stored_query(index_id, name).await
+    }
+
+    async fn update_alert_state(
+        &self,
+        index_id: &str,
+        alert_state: AlertState,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .update_alert_state(index_id, alert_state)
+            .await
+    }
+
+    // Delete tasks API
+
+    async fn create_delete_task(&self, delete_query: DeleteQuery) -> MetastoreResult<DeleteTask> {
+        self.underlying.create_delete_task(delete_query).await
+    }
+
+    async fn list_delete_tasks(
+        &self,
+        index_id: &str,
+        opstamp_start: u64,
+    ) -> MetastoreResult<Vec<DeleteTask>> {
+        self.underlying
+            .list_delete_tasks(index_id, opstamp_start)
+            .await
+    }
+
+    async fn last_delete_opstamp(&self, index_id: &str) -> MetastoreResult<u64> {
+        self.underlying.last_delete_opstamp(index_id).await
+    }
+
+    async fn update_splits_delete_opstamp<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+        delete_opstamp: u64,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .update_splits_delete_opstamp(index_id, split_ids, delete_opstamp)
+            .await
+    }
+
+    async fn list_stale_splits(
+        &self,
+        index_id: &str,
+        delete_opstamp: u64,
+        num_splits: usize,
+    ) -> MetastoreResult<Vec<Split>> {
+        self.underlying
+            .list_stale_splits(index_id, delete_opstamp, num_splits)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chitchat::transport::ChannelTransport;
+    use quickwit_cluster::create_cluster_for_test;
+
+    use super::*;
+    use crate::MockMetastore;
+
+    fn mock_split(split_id: &str, time_range: Range<i64>) -> Split {
+        Split {
+            split_state: SplitState::Published,
+            update_timestamp: 0,
+            publish_timestamp: None,
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                time_range: Some(time_range),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_index_freshness_metastore_caches_published_splits() {
+        let transport = ChannelTransport::default();
+        let cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["searcher"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        let mut mock_metastore = MockMetastore::new();
+        mock_metastore
+            .expect_list_splits()
+            .withf(|index_id, split_state, time_range, tags| {
+                index_id == "test-index"
+                    && *split_state == SplitState::Published
+                    && time_range.is_none()
+                    && tags.is_none()
+            })
+            .times(1)
+            .returning(|_, _, _, _| {
+                Ok(vec![
+                    mock_split("split-1", 0..10),
+                    mock_split("split-2", 10..20),
+                ])
+            });
+        mock_metastore
+            .expect_list_splits()
+            .withf(|_, split_state, _, _| *split_state == SplitState::Staged)
+            .times(2)
+            .returning(|_, _, _, _| Ok(Vec::new()));
+        let metastore =
+            IndexFreshnessMetastore::new(Arc::new(mock_metastore), cluster).with_split_cache();
+
+        let splits = metastore
+            .list_splits("test-index", SplitState::Published, None, None)
+            .await
+            .unwrap();
+        assert_eq!(splits.len(), 2);
+
+        let splits = metastore
+            .list_splits("test-index", SplitState::Published, Some(12..15), None)
+            .await
+            .unwrap();
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].split_id(), "split-2");
+
+        for _ in 0..2 {
+            metastore
+                .list_splits("test-index", SplitState::Staged, None, None)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_index_freshness_metastore_invalidates_cache_on_gossiped_update() {
+        let transport = ChannelTransport::default();
+        let searcher_cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["searcher"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        let indexer_cluster = Arc::new(
+            create_cluster_for_test(
+                vec![searcher_cluster.gossip_listen_addr.to_string()],
+                &["indexer"],
+                &transport,
+                true,
+            )
+            .await
+            .unwrap(),
+        );
+        let mut searcher_mock_metastore = MockMetastore::new();
+        searcher_mock_metastore
+            .expect_list_splits()
+            .times(2)
+            .returning(|_, _, _, _| Ok(vec![mock_split("split-1", 0..10)]));
+        let searcher_metastore =
+            IndexFreshnessMetastore::new(Arc::new(searcher_mock_metastore), searcher_cluster)
+                .with_split_cache();
+
+        let mut indexer_mock_metastore = MockMetastore::new();
+        indexer_mock_metastore
+            .expect_publish_splits()
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let indexer_metastore =
+            IndexFreshnessMetastore::new(Arc::new(indexer_mock_metastore), indexer_cluster);
+
+        searcher_metastore
+            .list_splits("test-index", SplitState::Published, None, None)
+            .await
+            .unwrap();
+        searcher_metastore
+            .list_splits("test-index", SplitState::Published, None, None)
+            .await
+            .unwrap();
+
+        indexer_metastore
+            .publish_splits("test-index", &["split-2"], &[], None)
+            .await
+            .unwrap();
+        let key = index_freshness_key("test-index");
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while searcher_metastore
+                .cluster
+                .ready_members_key_value(&key)
+                .await
+                .is_empty()
+            {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        searcher_metastore
+            .list_splits("test-index", SplitState::Published, None, None)
+            .await
+            .unwrap();
+    }
+}
@@ -18,20 +18,34 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use itertools::Itertools;
 use quickwit_common::uri::Uri;
 use quickwit_config::{
     DocMapping, IndexingSettings, IndexingSettingsLegacy, RetentionPolicy, SearchSettings,
-    SourceConfig,
+    SourceConfig, StoredQuery, TieringPolicy,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::checkpoint::IndexCheckpoint;
+use crate::checkpoint::{IndexCheckpoint, IndexCheckpointDelta, PublishToken, SourceCheckpoint};
 use crate::split_metadata::utc_now_timestamp;
 use crate::{MetastoreError, MetastoreResult};
 
+/// Evaluation state of the alert of a stored query. It is persisted so that the alert is neither
+/// evaluated twice over the same time range nor notified twice after a restart.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AlertState {
+    /// Name of the stored query.
+    pub name: String,
+    /// End of the time range covered by the last evaluation (Unix timestamp in seconds).
+    pub last_evaluation_timestamp: i64,
+    /// Number of hits of the last evaluation.
+    pub last_num_hits: u64,
+    /// Whether the condition of the alert was met during the last evaluation.
+    pub firing: bool,
+}
+
 /// An index metadata carries all meta data about an index.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(into = "VersionedIndexMetadata")]
@@ -44,6 +58,8 @@ pub struct IndexMetadata {
     /// Checkpoint relative to a source or a set of sources. It expresses up to which point
     /// documents have been indexed.
     pub checkpoint: IndexCheckpoint,
+    /// Latest publish tokens handed out, keyed by source ID and partition set ID.
+    pub publish_tokens: BTreeMap<String, BTreeMap<String, u64>>,
     /// Describes how ingested JSON documents are indexed.
     pub doc_mapping: DocMapping,
     /// Configures various indexing settings such as commit timeout, max split size, indexing
@@ -53,8 +69,18 @@ pub struct IndexMetadata {
     pub search_settings: SearchSettings,
     /// Data sources keyed by their `source_id`.
     pub sources: HashMap<String, SourceConfig>,
+    /// Stored queries keyed by their name.
+    pub stored_queries: BTreeMap<String, StoredQuery>,
+    /// Evaluation states of the alerts of the stored queries, keyed by stored query name.
+    pub alert_states: BTreeMap<String, AlertState>,
     /// An optional retention policy which will be applied to the splits of the index.
     pub retention_policy: Option<RetentionPolicy>,
+    /// An optional tiering policy moving the splits of the index between the index storage and
+    /// a warm storage according to how often they are searched.
+    pub tiering_policy: Option<TieringPolicy>,
+    /// Read-only indexes, such as indexes attached from a snapshot, accept no sources, and their
+    /// split files are never modified nor deleted.
+    pub read_only: bool,
     /// Time at which the index was created.
     pub create_timestamp: i64,
     /// Time at which the index was last updated.
@@ -141,24 +167,41 @@ impl IndexMetadata {
                 r#"attributes.server"#.to_string(),
                 r#"attributes.server\.status"#.to_string(),
             ],
+            ..Default::default()
         };
         let now_timestamp = utc_now_timestamp();
         Self {
             index_id: index_id.to_string(),
             index_uri,
             checkpoint: Default::default(),
+            publish_tokens: Default::default(),
             doc_mapping,
             indexing_settings,
             search_settings,
             sources: Default::default(),
+            stored_queries: Default::default(),
+            alert_states: Default::default(),
             retention_policy: None, // TODO
+            tiering_policy: None,
+            read_only: false,
             create_timestamp: now_timestamp,
             update_timestamp: now_timestamp,
         }
     }
 
+    /// Returns an error if the index is read-only.
+    pub(crate) fn check_writable(&self) -> MetastoreResult<()> {
+        if self.read_only {
+            return Err(MetastoreError::IndexReadOnly {
+                index_id: self.index_id.clone(),
+            });
+        }
+        Ok(())
+    }
+
     /// Adds a source to the index. Returns whether the index was modified (true).
     pub(crate) fn add_source(&mut self, source: SourceConfig) -> MetastoreResult<bool> {
+        self.check_writable()?;
         let entry = self.sources.entry(source.source_id.clone());
         let source_id = source.source_id.clone();
         if let Entry::Occupied(_) = entry {
@@ -172,6 +215,18 @@ impl IndexMetadata {
         Ok(true)
     }
 
+    /// Replaces the config of a source. Returns whether the index was modified.
+    pub(crate) fn update_source(&mut self, source: SourceConfig) -> MetastoreResult<bool> {
+        let current_source = self.sources.get_mut(&source.source_id).ok_or_else(|| {
+            MetastoreError::SourceDoesNotExist {
+                source_id: source.source_id.clone(),
+            }
+        })?;
+        let mutation_occurred = *current_source != source;
+        *current_source = source;
+        Ok(mutation_occurred)
+    }
+
     pub(crate) fn toggle_source(&mut self, source_id: &str, enable: bool) -> MetastoreResult<bool> {
         let source =
             self.sources
@@ -192,8 +247,107 @@ impl IndexMetadata {
                 source_id: source_id.to_string(),
             })?;
         self.checkpoint.remove_source(source_id);
+        self.publish_tokens.remove(source_id);
+        Ok(true)
+    }
+
+    /// Replaces the checkpoint of a source. Returns whether the index was modified.
+    pub(crate) fn update_source_checkpoint(
+        &mut self,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> MetastoreResult<bool> {
+        if !self.sources.contains_key(source_id) {
+            return Err(MetastoreError::SourceDoesNotExist {
+                source_id: source_id.to_string(),
+            });
+        }
+        Ok(self.checkpoint.update_source(source_id, source_checkpoint))
+    }
+
+    /// Adds or replaces a stored query. Returns whether the index was modified.
+    pub(crate) fn put_stored_query(&mut self, stored_query: StoredQuery) -> MetastoreResult<bool> {
+        let previous_stored_query_opt = self
+            .stored_queries
+            .insert(stored_query.name.clone(), stored_query.clone());
+        Ok(previous_stored_query_opt.as_ref() != Some(&stored_query))
+    }
+
+    /// Deletes a stored query and the state of its alert. Returns whether the index was modified
+    /// (true).
+    pub(crate) fn delete_stored_query(&mut self, name: &str) -> MetastoreResult<bool> {
+        self.stored_queries.remove(name).ok_or_else(|| {
+            MetastoreError::StoredQueryDoesNotExist {
+                name: name.to_string(),
+            }
+        })?;
+        self.alert_states.remove(name);
         Ok(true)
     }
+
+    /// Replaces the evaluation state of the alert of a stored query. Returns whether the index was
+    /// modified.
+    pub(crate) fn update_alert_state(&mut self, alert_state: AlertState) -> MetastoreResult<bool> {
+        if !self.stored_queries.contains_key(&alert_state.name) {
+            return Err(MetastoreError::StoredQueryDoesNotExist {
+                name: alert_state.name,
+            });
+        }
+        let previous_alert_state_opt = self
+            .alert_states
+            .insert(alert_state.name.clone(), alert_state.clone());
+        Ok(previous_alert_state_opt.as_ref() != Some(&alert_state))
+    }
+
+    /// Hands out a new publish token for the partition set `partition_set_id` of the source
+    /// `source_id`, invalidating the tokens previously handed out for the same partition set.
+    pub(crate) fn acquire_publish_token(
+        &mut self,
+        source_id: &str,
+        partition_set_id: &str,
+    ) -> PublishToken {
+        let latest_token = self
+            .publish_tokens
+            .entry(source_id.to_string())
+            .or_default()
+            .entry(partition_set_id.to_string())
+            .or_default();
+        *latest_token += 1;
+        PublishToken {
+            partition_set_id: partition_set_id.to_string(),
+            token: *latest_token,
+        }
+    }
+
+    /// Applies a checkpoint delta, after checking that the writer publishing it still holds the
+    /// latest publish token of its partition set, if it carries one, and that the delta is
+    /// consecutive with the checkpoint. Returns whether a mutation occurred.
+    pub(crate) fn try_apply_checkpoint_delta(
+        &mut self,
+        checkpoint_delta: IndexCheckpointDelta,
+    ) -> MetastoreResult<bool> {
+        if let Some(publish_token) = &checkpoint_delta.publish_token_opt {
+            let latest_token = self
+                .publish_tokens
+                .get(&checkpoint_delta.source_id)
+                .and_then(|tokens| tokens.get(&publish_token.partition_set_id))
+                .copied()
+                .unwrap_or(0);
+            if publish_token.token != latest_token {
+                return Err(MetastoreError::StalePublishToken {
+                    source_id: checkpoint_delta.source_id.clone(),
+                    partition_set_id: publish_token.partition_set_id.clone(),
+                    token: publish_token.token,
+                    latest_token,
+                });
+            }
+        }
+        if let Some(gap) = self.checkpoint.find_gap(&checkpoint_delta) {
+            return Err(MetastoreError::CheckpointDeltaGap(gap));
+        }
+        let mutation_occurred = self.checkpoint.try_apply_delta(checkpoint_delta)?;
+        Ok(mutation_occurred)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -246,6 +400,9 @@ pub(crate) struct IndexMetadataV2 {
     pub index_id: String,
     pub index_uri: String,
     pub checkpoint: IndexCheckpoint,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub publish_tokens: BTreeMap<String, BTreeMap<String, u64>>,
     pub doc_mapping: DocMapping,
     #[serde(default)]
     pub indexing_settings: IndexingSettings,
@@ -254,8 +411,20 @@ pub(crate) struct IndexMetadataV2 {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub sources: Vec<SourceConfig>,
     #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stored_queries: Vec<StoredQuery>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alert_states: Vec<AlertState>,
+    #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retention_policy: Option<RetentionPolicy>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tiering_policy: Option<TieringPolicy>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
     #[serde(default = "utc_now_timestamp")]
     pub create_timestamp: i64,
     #[serde(default = "utc_now_timestamp")]
@@ -269,15 +438,22 @@ impl From<IndexMetadata> for IndexMetadataV2 {
             .into_values()
             .sorted_by(|left, right| left.source_id.cmp(&right.source_id))
             .collect();
+        let stored_queries = index_metadata.stored_queries.into_values().collect();
+        let alert_states = index_metadata.alert_states.into_values().collect();
         Self {
             index_id: index_metadata.index_id,
             index_uri: index_metadata.index_uri.into_string(),
             checkpoint: index_metadata.checkpoint,
+            publish_tokens: index_metadata.publish_tokens,
             doc_mapping: index_metadata.doc_mapping,
             indexing_settings: index_metadata.indexing_settings,
             search_settings: index_metadata.search_settings,
             sources,
+            stored_queries,
+            alert_states,
             retention_policy: index_metadata.retention_policy,
+            tiering_policy: index_metadata.tiering_policy,
+            read_only: index_metadata.read_only,
             create_timestamp: index_metadata.create_timestamp,
             update_timestamp: index_metadata.update_timestamp,
         }
@@ -295,11 +471,16 @@ impl From<IndexMetadataV1> for IndexMetadata {
             index_id: v1.index_id,
             index_uri: Uri::from_well_formed(v1.index_uri),
             checkpoint: v1.checkpoint,
+            publish_tokens: Default::default(),
             doc_mapping: v1.doc_mapping,
             indexing_settings: v1.indexing_settings.into(),
             search_settings: v1.search_settings,
             sources,
+            stored_queries: Default::default(),
+            alert_states: Default::default(),
             retention_policy: v1.retention_policy,
+            tiering_policy: None,
+            read_only: false,
             create_timestamp: v1.create_timestamp,
             update_timestamp: v1.update_timestamp,
         }
@@ -313,15 +494,30 @@ impl From<IndexMetadataV2> for IndexMetadata {
             .into_iter()
             .map(|source| (source.source_id.clone(), source))
             .collect();
+        let stored_queries = v2
+            .stored_queries
+            .into_iter()
+            .map(|stored_query| (stored_query.name.clone(), stored_query))
+            .collect();
+        let alert_states = v2
+            .alert_states
+            .into_iter()
+            .map(|alert_state| (alert_state.name.clone(), alert_state))
+            .collect();
         Self {
             index_id: v2.index_id,
             index_uri: Uri::from_well_formed(v2.index_uri),
             checkpoint: v2.checkpoint,
+            publish_tokens: v2.publish_tokens,
             doc_mapping: v2.doc_mapping,
             indexing_settings: v2.indexing_settings,
             search_settings: v2.search_settings,
             sources,
+            stored_queries,
+            alert_states,
             retention_policy: v2.retention_policy,
+            tiering_policy: v2.tiering_policy,
+            read_only: v2.read_only,
             create_timestamp: v2.create_timestamp,
             update_timestamp: v2.update_timestamp,
         }
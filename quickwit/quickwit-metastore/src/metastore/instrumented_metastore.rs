@@ -17,17 +17,21 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::ops::Range;
 
 use async_trait::async_trait;
 use concat_idents::concat_idents;
 use quickwit_common::uri::Uri;
-use quickwit_config::SourceConfig;
+use quickwit_config::{SourceConfig, StoredQuery};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 
-use crate::checkpoint::IndexCheckpointDelta;
-use crate::{IndexMetadata, Metastore, MetastoreResult, Split, SplitMetadata, SplitState};
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken, SourceCheckpoint};
+use crate::{
+    AlertState, IndexMetadata, Metastore, MetastoreResult, Split, SplitMetadata, SplitState,
+    SplitStorageTier,
+};
 
 macro_rules! instrument {
     ($method_name:ident, $expr:expr, $($label:expr),*) => {
@@ -218,6 +222,36 @@ impl Metastore for InstrumentedMetastore {
         );
     }
 
+    async fn record_split_searches(
+        &self,
+        index_id: &str,
+        num_searches_per_split: HashMap<String, u64>,
+        search_timestamp: i64,
+    ) -> MetastoreResult<()> {
+        instrument!(
+            record_split_searches,
+            self.underlying
+                .record_split_searches(index_id, num_searches_per_split, search_timestamp)
+                .await,
+            index_id
+        );
+    }
+
+    async fn update_split_storage_tier(
+        &self,
+        index_id: &str,
+        split_id: &str,
+        storage_tier: SplitStorageTier,
+    ) -> MetastoreResult<()> {
+        instrument!(
+            update_split_storage_tier,
+            self.underlying
+                .update_split_storage_tier(index_id, split_id, storage_tier)
+                .await,
+            index_id
+        );
+    }
+
     // Source API
 
     async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
@@ -230,6 +264,16 @@ impl Metastore for InstrumentedMetastore {
         );
     }
 
+    async fn update_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
+        let source_id = source.source_id.clone();
+        instrument!(
+            update_source,
+            self.underlying.update_source(index_id, source).await,
+            index_id,
+            source_id.as_ref()
+        );
+    }
+
     async fn toggle_source(
         &self,
         index_id: &str,
@@ -261,6 +305,38 @@ impl Metastore for InstrumentedMetastore {
         );
     }
 
+    async fn update_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> MetastoreResult<()> {
+        instrument!(
+            update_source_checkpoint,
+            self.underlying
+                .update_source_checkpoint(index_id, source_id, source_checkpoint)
+                .await,
+            index_id,
+            source_id
+        );
+    }
+
+    async fn acquire_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        partition_set_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        instrument!(
+            acquire_publish_token,
+            self.underlying
+                .acquire_publish_token(index_id, source_id, partition_set_id)
+                .await,
+            index_id,
+            source_id
+        );
+    }
+
     async fn delete_source(&self, index_id: &str, source_id: &str) -> MetastoreResult<()> {
         instrument!(
             delete_source,
@@ -270,6 +346,48 @@ impl Metastore for InstrumentedMetastore {
         );
     }
 
+    // Stored queries API
+    async fn put_stored_query(
+        &self,
+        index_id: &str,
+        stored_query: StoredQuery,
+    ) -> MetastoreResult<()> {
+        let name = stored_query.name.clone();
+        instrument!(
+            put_stored_query,
+            self.underlying
+                .put_stored_query(index_id, stored_query)
+                .await,
+            index_id,
+            name.as_ref()
+        );
+    }
+
+    async fn delete_stored_query(&self, index_id: &str, name: &str) -> MetastoreResult<()> {
+        instrument!(
+            delete_stored_query,
+            self.underlying.delete_stored_query(index_id, name).await,
+            index_id,
+            name
+        );
+    }
+
+    async fn update_alert_state(
+        &self,
+        index_id: &str,
+        alert_state: AlertState,
+    ) -> MetastoreResult<()> {
+        let name = alert_state.name.clone();
+        instrument!(
+            update_alert_state,
+            self.underlying
+                .update_alert_state(index_id, alert_state)
+                .await,
+            index_id,
+            name.as_ref()
+        );
+    }
+
     // Delete tasks API
     async fn create_delete_task(&self, delete_query: DeleteQuery) -> MetastoreResult<DeleteTask> {
         let index_id = delete_query.index_id.clone();
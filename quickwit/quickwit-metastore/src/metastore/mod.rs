@@ -17,8 +17,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod fault_injecting_metastore;
 pub mod file_backed_metastore;
 pub mod grpc_metastore;
+mod index_freshness_metastore;
 mod index_metadata;
 mod instrumented_metastore;
 #[cfg(feature = "postgres")]
@@ -26,17 +28,24 @@ pub mod postgresql_metastore;
 #[cfg(feature = "postgres")]
 mod postgresql_model;
 
+use std::collections::HashMap;
 use std::ops::Range;
 
 use async_trait::async_trait;
-pub use index_metadata::IndexMetadata;
+pub use fault_injecting_metastore::{FaultInjectingMetastore, MetastoreFaults};
+pub use index_freshness_metastore::{
+    IndexFreshness, IndexFreshnessMetastore, INDEX_FRESHNESS_KEY_PREFIX,
+};
+pub use index_metadata::{AlertState, IndexMetadata};
 use quickwit_common::uri::Uri;
-use quickwit_config::SourceConfig;
+use quickwit_config::{SourceConfig, StoredQuery};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 
-use crate::checkpoint::IndexCheckpointDelta;
-use crate::{MetastoreError, MetastoreResult, Split, SplitMetadata, SplitState};
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken, SourceCheckpoint};
+use crate::{
+    AlertState, MetastoreError, MetastoreResult, Split, SplitMetadata, SplitState, SplitStorageTier,
+};
 
 /// Metastore meant to manage Quickwit's indexes, their splits and delete tasks.
 ///
@@ -149,6 +158,16 @@ pub trait Metastore: Send + Sync + 'static {
     ///
     /// This method can be used to advance the checkpoint, by supplying an empty array for
     /// `split_ids`.
+    ///
+    /// The publish is rejected if the writer worked on a stale view of the index: the replaced
+    /// splits must be published ([`ReplacedSplitsNotPublished`]), and the checkpoint delta must be
+    /// consecutive with the checkpoint for the partitions it already holds
+    /// ([`IncompatibleCheckpointDelta`] if they overlap, [`CheckpointDeltaGap`] if some positions
+    /// would be skipped).
+    ///
+    /// [`ReplacedSplitsNotPublished`]: crate::MetastoreError::ReplacedSplitsNotPublished
+    /// [`IncompatibleCheckpointDelta`]: crate::MetastoreError::IncompatibleCheckpointDelta
+    /// [`CheckpointDeltaGap`]: crate::MetastoreError::CheckpointDeltaGap
     async fn publish_splits<'a>(
         &self,
         index_id: &str,
@@ -195,6 +214,26 @@ pub trait Metastore: Send + Sync + 'static {
     async fn delete_splits<'a>(&self, index_id: &str, split_ids: &[&'a str])
         -> MetastoreResult<()>;
 
+    /// Adds the searches reported by a searcher to the access statistics of splits, setting
+    /// their last search timestamp to `search_timestamp`. Splits that do not exist anymore are
+    /// ignored.
+    async fn record_split_searches(
+        &self,
+        index_id: &str,
+        num_searches_per_split: HashMap<String, u64>,
+        search_timestamp: i64,
+    ) -> MetastoreResult<()>;
+
+    /// Records that the file of a split was moved to the storage tier `storage_tier` and resets
+    /// the number of searches of the split, which counts the searches received in its current
+    /// tier. An error will occur if the split does not exist.
+    async fn update_split_storage_tier(
+        &self,
+        index_id: &str,
+        split_id: &str,
+        storage_tier: SplitStorageTier,
+    ) -> MetastoreResult<()>;
+
     // Source API
 
     /// Adds a new source. Fails with
@@ -204,6 +243,13 @@ pub trait Metastore: Send + Sync + 'static {
     /// If a checkpoint is already registered for the source, it is kept.
     async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()>;
 
+    /// Replaces the config of an existing source. Fails with
+    /// [`SourceDoesNotExist`](crate::MetastoreError::SourceDoesNotExist) if the specified source
+    /// does not exist.
+    ///
+    /// The checkpoint associated to the source is kept.
+    async fn update_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()>;
+
     /// Enables or Disables a source.
     /// Fails with `SourceDoesNotExist` error if the specified source doesn't exist.
     async fn toggle_source(
@@ -217,6 +263,49 @@ pub trait Metastore: Send + Sync + 'static {
     async fn reset_source_checkpoint(&self, index_id: &str, source_id: &str)
         -> MetastoreResult<()>;
 
+    /// Replaces the checkpoint of a source identified by `index_id` and `source_id`. Contrary to
+    /// the checkpoint deltas applied on publish, positions are allowed to move backward.
+    async fn update_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> MetastoreResult<()>;
+
+    /// Returns the checkpoint of a source identified by `index_id` and `source_id`. Fails with
+    /// [`SourceDoesNotExist`](crate::MetastoreError::SourceDoesNotExist) if the index has neither
+    /// a source nor a checkpoint registered under `source_id`.
+    async fn source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<SourceCheckpoint> {
+        let index_metadata = self.index_metadata(index_id).await?;
+        if let Some(source_checkpoint) = index_metadata.checkpoint.source_checkpoint(source_id) {
+            return Ok(source_checkpoint.clone());
+        }
+        if index_metadata.sources.contains_key(source_id) {
+            return Ok(SourceCheckpoint::default());
+        }
+        Err(MetastoreError::SourceDoesNotExist {
+            source_id: source_id.to_string(),
+        })
+    }
+
+    /// Acquires a new publish token for the partition set `partition_set_id` of the source
+    /// identified by `index_id` and `source_id`.
+    ///
+    /// The token acts as a fencing token: once acquired, publishing a checkpoint delta carrying
+    /// a token previously handed out for the same partition set fails with
+    /// [`StalePublishToken`](crate::MetastoreError::StalePublishToken). This guarantees that a
+    /// single writer advances the checkpoint of a given partition set at a time.
+    async fn acquire_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        partition_set_id: &str,
+    ) -> MetastoreResult<PublishToken>;
+
     /// Deletes a source. Fails with
     /// [`SourceDoesNotExist`](crate::MetastoreError::SourceDoesNotExist) if the specified source
     /// does not exist.
@@ -225,6 +314,29 @@ pub trait Metastore: Send + Sync + 'static {
     /// If the checkpoint is missing, this does not trigger an error.
     async fn delete_source(&self, index_id: &str, source_id: &str) -> MetastoreResult<()>;
 
+    // Stored queries API
+
+    /// Adds a stored query to an index, replacing the stored query with the same name if any.
+    async fn put_stored_query(
+        &self,
+        index_id: &str,
+        stored_query: StoredQuery,
+    ) -> MetastoreResult<()>;
+
+    /// Deletes a stored query. Fails with
+    /// [`StoredQueryDoesNotExist`](crate::MetastoreError::StoredQueryDoesNotExist) if the
+    /// specified stored query does not exist.
+    async fn delete_stored_query(&self, index_id: &str, name: &str) -> MetastoreResult<()>;
+
+    /// Replaces the evaluation state of the alert of a stored query. Fails with
+    /// [`StoredQueryDoesNotExist`](crate::MetastoreError::StoredQueryDoesNotExist) if the
+    /// specified stored query does not exist.
+    async fn update_alert_state(
+        &self,
+        index_id: &str,
+        alert_state: AlertState,
+    ) -> MetastoreResult<()>;
+
     // Delete tasks API
 
     /// Creates a new [`DeleteTask`] from a [`DeleteQuery`].
@@ -28,22 +28,23 @@ use std::time::Duration;
 use async_trait::async_trait;
 use itertools::Itertools;
 use quickwit_common::uri::Uri;
-use quickwit_config::SourceConfig;
+use quickwit_config::{SourceConfig, StoredQuery};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 use sqlx::migrate::Migrator;
 use sqlx::postgres::{PgConnectOptions, PgDatabaseError, PgPoolOptions};
 use sqlx::{ConnectOptions, Pool, Postgres, Row, Transaction};
+use time::OffsetDateTime;
 use tokio::sync::Mutex;
 use tracing::log::LevelFilter;
 use tracing::{debug, error, instrument, warn};
 
-use crate::checkpoint::IndexCheckpointDelta;
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken, SourceCheckpoint};
 use crate::metastore::instrumented_metastore::InstrumentedMetastore;
 use crate::metastore::postgresql_model::{self, Index, IndexIdSplitIdRow};
 use crate::{
-    IndexMetadata, Metastore, MetastoreError, MetastoreFactory, MetastoreResolverError,
-    MetastoreResult, Split, SplitMetadata, SplitState,
+    AlertState, IndexMetadata, Metastore, MetastoreError, MetastoreFactory, MetastoreResolverError,
+    MetastoreResult, Split, SplitMetadata, SplitState, SplitStorageTier,
 };
 
 static MIGRATOR: Migrator = sqlx::migrate!("migrations/postgresql");
@@ -145,6 +146,30 @@ async fn index_metadata(
         .index_metadata()
 }
 
+/// Same as [`index_metadata`] but also locks the index row until the end of the transaction, so
+/// that concurrent read-modify-write cycles on the index metadata are serialized.
+async fn index_metadata_for_update(
+    tx: &mut Transaction<'_, Postgres>,
+    index_id: &str,
+) -> MetastoreResult<IndexMetadata> {
+    let index_opt: Option<Index> = sqlx::query_as::<_, Index>(
+        r#"
+        SELECT *
+        FROM indexes
+        WHERE index_id = $1
+        FOR UPDATE
+    "#,
+    )
+    .bind(index_id)
+    .fetch_optional(tx)
+    .await?;
+    index_opt
+        .ok_or_else(|| MetastoreError::IndexDoesNotExist {
+            index_id: index_id.to_string(),
+        })?
+        .index_metadata()
+}
+
 /// Publishes mutiple splits.
 /// Returns the IDs of the splits successfully published.
 #[instrument(skip(tx))]
@@ -199,6 +224,66 @@ async fn update_index_update_timestamp(
     Ok(())
 }
 
+/// Applies `mutate_fn` to the metadata of the given splits and persists the result. The split rows
+/// are locked until the end of the transaction. Returns the IDs of the splits that were found.
+#[instrument(skip(tx, mutate_fn))]
+async fn mutate_splits_metadata<M: FnMut(&mut SplitMetadata)>(
+    tx: &mut Transaction<'_, Postgres>,
+    index_id: &str,
+    split_ids: &[&str],
+    mut mutate_fn: M,
+) -> MetastoreResult<Vec<String>> {
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT split_id, split_metadata_json
+        FROM splits
+        WHERE
+                index_id = $1
+            AND split_id = ANY($2)
+        FOR UPDATE
+    "#,
+    )
+    .bind(index_id)
+    .bind(split_ids)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut mutated_split_ids = Vec::with_capacity(rows.len());
+    for (split_id, split_metadata_json) in rows {
+        let mut split_metadata: SplitMetadata = serde_json::from_str(&split_metadata_json)
+            .map_err(|err| MetastoreError::InternalError {
+                message: format!(
+                    "Failed to deserialize split metadata. index_id=`{index_id}`, \
+                     split_id=`{split_id}`."
+                ),
+                cause: err.to_string(),
+            })?;
+        mutate_fn(&mut split_metadata);
+        let split_metadata_json = serde_json::to_string(&split_metadata).map_err(|err| {
+            MetastoreError::InternalError {
+                message: "Failed to serialize split metadata.".to_string(),
+                cause: err.to_string(),
+            }
+        })?;
+        sqlx::query(
+            r#"
+            UPDATE splits
+            SET split_metadata_json = $1
+            WHERE
+                    index_id = $2
+                AND split_id = $3
+        "#,
+        )
+        .bind(split_metadata_json)
+        .bind(index_id)
+        .bind(&split_id)
+        .execute(&mut *tx)
+        .await?;
+        mutated_split_ids.push(split_id);
+    }
+    Ok(mutated_split_ids)
+}
+
 /// Marks multiple splits for deletion.
 /// Returns the IDs of the splits successfully marked for deletion.
 #[instrument(skip(tx))]
@@ -431,7 +516,7 @@ async fn mutate_index_metadata<E, M: FnOnce(&mut IndexMetadata) -> Result<bool,
 where
     MetastoreError: From<E>,
 {
-    let mut index_metadata = index_metadata(tx, index_id).await?;
+    let mut index_metadata = index_metadata_for_update(tx, index_id).await?;
     let mutation_occurred = mutate_fn(&mut index_metadata)?;
     if !mutation_occurred {
         return Ok(mutation_occurred);
@@ -506,6 +591,14 @@ impl Metastore for PostgresqlMetastore {
     #[instrument(skip(self))]
     async fn delete_index(&self, index_id: &str) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
+            // The splits of a read-only index cannot be deleted beforehand: they are deleted along
+            // with the index, and their files are left untouched.
+            if index_metadata(tx, index_id).await?.read_only {
+                sqlx::query("DELETE FROM splits WHERE index_id = $1")
+                    .bind(index_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
             let query_res = sqlx::query("DELETE FROM indexes WHERE index_id = $1")
                 .bind(index_id)
                 .execute(tx)
@@ -570,9 +663,14 @@ impl Metastore for PostgresqlMetastore {
         checkpoint_delta_opt: Option<IndexCheckpointDelta>,
     ) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
+            // The splits of a read-only index are published once, when the index is attached, and
+            // are never replaced afterwards.
+            if !replaced_split_ids.is_empty() || checkpoint_delta_opt.is_some() {
+                index_metadata(tx, index_id).await?.check_writable()?;
+            }
             if let Some(checkpoint_delta) = checkpoint_delta_opt {
                 mutate_index_metadata(tx, index_id, |index_metadata| {
-                    index_metadata.checkpoint.try_apply_delta(checkpoint_delta)
+                    index_metadata.try_apply_checkpoint_delta(checkpoint_delta)
                 })
                 .await?;
             }
@@ -610,7 +708,7 @@ impl Metastore for PostgresqlMetastore {
                 });
             }
             if marked_split_ids.len() != replaced_split_ids.len() {
-                let non_deletable_split_ids = replaced_split_ids
+                let not_published_split_ids = replaced_split_ids
                     .iter()
                     .filter(|replaced_split_id| {
                         marked_split_ids
@@ -619,8 +717,8 @@ impl Metastore for PostgresqlMetastore {
                     })
                     .map(|split_id| split_id.to_string())
                     .collect();
-                return Err(MetastoreError::SplitsNotDeletable {
-                    split_ids: non_deletable_split_ids,
+                return Err(MetastoreError::ReplacedSplitsNotPublished {
+                    split_ids: not_published_split_ids,
                 });
             }
             Ok(())
@@ -654,6 +752,7 @@ impl Metastore for PostgresqlMetastore {
         split_ids: &[&'a str],
     ) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
+            index_metadata(tx, index_id).await?.check_writable()?;
             let marked_split_ids: Vec<String> = mark_splits_for_deletion(
                 tx,
                 index_id,
@@ -689,6 +788,7 @@ impl Metastore for PostgresqlMetastore {
         split_ids: &[&'a str],
     ) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
+            index_metadata(tx, index_id).await?.check_writable()?;
             let deletable_states = [
                 SplitState::Staged.as_str(),
                 SplitState::MarkedForDeletion.as_str(),
@@ -725,6 +825,67 @@ impl Metastore for PostgresqlMetastore {
         })
     }
 
+    #[instrument(skip(self, num_searches_per_split))]
+    async fn record_split_searches(
+        &self,
+        index_id: &str,
+        num_searches_per_split: HashMap<String, u64>,
+        search_timestamp: i64,
+    ) -> MetastoreResult<()> {
+        if num_searches_per_split.is_empty() {
+            return Ok(());
+        }
+        run_with_tx!(self.connection_pool, tx, {
+            let split_ids: Vec<&str> = num_searches_per_split
+                .keys()
+                .map(|split_id| split_id.as_str())
+                .collect();
+            // Splits deleted since the searches were reported are ignored.
+            mutate_splits_metadata(tx, index_id, &split_ids, |split_metadata| {
+                let num_searches = num_searches_per_split
+                    .get(&split_metadata.split_id)
+                    .copied()
+                    .unwrap_or(0);
+                split_metadata
+                    .access_stats
+                    .record_searches(num_searches, search_timestamp);
+            })
+            .await?;
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn update_split_storage_tier(
+        &self,
+        index_id: &str,
+        split_id: &str,
+        storage_tier: SplitStorageTier,
+    ) -> MetastoreResult<()> {
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        run_with_tx!(self.connection_pool, tx, {
+            let mutated_split_ids =
+                mutate_splits_metadata(tx, index_id, &[split_id], |split_metadata| {
+                    split_metadata.storage_tier = storage_tier.clone();
+                    split_metadata.access_stats.num_searches = 0;
+                    split_metadata.access_stats.storage_tier_timestamp = Some(now_timestamp);
+                })
+                .await?;
+            if !mutated_split_ids.is_empty() {
+                return Ok(());
+            }
+            // If no split was found, maybe the index itself does not exist in the first place.
+            if index_opt(tx, index_id).await?.is_none() {
+                return Err(MetastoreError::IndexDoesNotExist {
+                    index_id: index_id.to_string(),
+                });
+            }
+            Err(MetastoreError::SplitsDoNotExist {
+                split_ids: vec![split_id.to_string()],
+            })
+        })
+    }
+
     #[instrument(skip(self))]
     async fn index_metadata(&self, index_id: &str) -> MetastoreResult<IndexMetadata> {
         run_with_tx!(self.connection_pool, tx, {
@@ -743,6 +904,17 @@ impl Metastore for PostgresqlMetastore {
         })
     }
 
+    #[instrument(skip(self, source), fields(source_id=source.source_id.as_str()))]
+    async fn update_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                index_metadata.update_source(source)
+            })
+            .await?;
+            Ok(())
+        })
+    }
+
     #[instrument(skip(self))]
     async fn toggle_source(
         &self,
@@ -770,6 +942,47 @@ impl Metastore for PostgresqlMetastore {
         })
     }
 
+    #[instrument(skip(self, stored_query), fields(name=stored_query.name.as_str()))]
+    async fn put_stored_query(
+        &self,
+        index_id: &str,
+        stored_query: StoredQuery,
+    ) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                index_metadata.put_stored_query(stored_query)
+            })
+            .await?;
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_stored_query(&self, index_id: &str, name: &str) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                index_metadata.delete_stored_query(name)
+            })
+            .await?;
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self, alert_state), fields(name=alert_state.name.as_str()))]
+    async fn update_alert_state(
+        &self,
+        index_id: &str,
+        alert_state: AlertState,
+    ) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                index_metadata.update_alert_state(alert_state)
+            })
+            .await?;
+            Ok(())
+        })
+    }
+
     #[instrument(skip(self))]
     async fn reset_source_checkpoint(
         &self,
@@ -785,6 +998,41 @@ impl Metastore for PostgresqlMetastore {
         })
     }
 
+    #[instrument(skip(self, source_checkpoint))]
+    async fn update_source_checkpoint(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        source_checkpoint: SourceCheckpoint,
+    ) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                index_metadata.update_source_checkpoint(source_id, source_checkpoint)
+            })
+            .await?;
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn acquire_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+        partition_set_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        run_with_tx!(self.connection_pool, tx, {
+            let mut publish_token_opt = None;
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                publish_token_opt =
+                    Some(index_metadata.acquire_publish_token(source_id, partition_set_id));
+                Ok::<_, MetastoreError>(true)
+            })
+            .await?;
+            Ok(publish_token_opt.expect("The mutation should have acquired a publish token."))
+        })
+    }
+
     fn uri(&self) -> &Uri {
         &self.uri
     }
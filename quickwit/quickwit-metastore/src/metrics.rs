@@ -66,12 +66,22 @@ pub struct MetastoreMetrics {
     pub delete_splits_requests_total: IntCounterVec,
     pub delete_splits_errors_total: IntCounterVec,
     pub delete_splits_duration_seconds: HistogramVec,
+    pub record_split_searches_requests_total: IntCounterVec,
+    pub record_split_searches_errors_total: IntCounterVec,
+    pub record_split_searches_duration_seconds: HistogramVec,
+    pub update_split_storage_tier_requests_total: IntCounterVec,
+    pub update_split_storage_tier_errors_total: IntCounterVec,
+    pub update_split_storage_tier_duration_seconds: HistogramVec,
 
     // Source API
     pub add_source_requests_total: IntCounterVec,
     pub add_source_errors_total: IntCounterVec,
     pub add_source_duration_seconds: HistogramVec,
 
+    pub update_source_requests_total: IntCounterVec,
+    pub update_source_errors_total: IntCounterVec,
+    pub update_source_duration_seconds: HistogramVec,
+
     pub toggle_source_requests_total: IntCounterVec,
     pub toggle_source_errors_total: IntCounterVec,
     pub toggle_source_duration_seconds: HistogramVec,
@@ -80,10 +90,31 @@ pub struct MetastoreMetrics {
     pub reset_source_checkpoint_errors_total: IntCounterVec,
     pub reset_source_checkpoint_duration_seconds: HistogramVec,
 
+    pub update_source_checkpoint_requests_total: IntCounterVec,
+    pub update_source_checkpoint_errors_total: IntCounterVec,
+    pub update_source_checkpoint_duration_seconds: HistogramVec,
+
+    pub acquire_publish_token_requests_total: IntCounterVec,
+    pub acquire_publish_token_errors_total: IntCounterVec,
+    pub acquire_publish_token_duration_seconds: HistogramVec,
+
     pub delete_source_requests_total: IntCounterVec,
     pub delete_source_errors_total: IntCounterVec,
     pub delete_source_duration_seconds: HistogramVec,
 
+    // Stored queries API
+    pub put_stored_query_requests_total: IntCounterVec,
+    pub put_stored_query_errors_total: IntCounterVec,
+    pub put_stored_query_duration_seconds: HistogramVec,
+
+    pub delete_stored_query_requests_total: IntCounterVec,
+    pub delete_stored_query_errors_total: IntCounterVec,
+    pub delete_stored_query_duration_seconds: HistogramVec,
+
+    pub update_alert_state_requests_total: IntCounterVec,
+    pub update_alert_state_errors_total: IntCounterVec,
+    pub update_alert_state_duration_seconds: HistogramVec,
+
     // Delete tasks API
     pub create_delete_task_requests_total: IntCounterVec,
     pub create_delete_task_errors_total: IntCounterVec,
@@ -317,6 +348,42 @@ impl Default for MetastoreMetrics {
                 "quickwit_metastore",
                 &["index", "error"],
             ),
+            record_split_searches_requests_total: new_counter_vec(
+                "record_split_searches_requests_total",
+                "Number of record split searches requests",
+                "quickwit_metastore",
+                &["index"],
+            ),
+            record_split_searches_errors_total: new_counter_vec(
+                "record_split_searches_errors_total",
+                "Number of failed record split searches requests",
+                "quickwit_metastore",
+                &["index"],
+            ),
+            record_split_searches_duration_seconds: new_histogram_vec(
+                "record_split_searches_duration_seconds",
+                "Duration of record split searches requests",
+                "quickwit_metastore",
+                &["index", "error"],
+            ),
+            update_split_storage_tier_requests_total: new_counter_vec(
+                "update_split_storage_tier_requests_total",
+                "Number of update split storage tier requests",
+                "quickwit_metastore",
+                &["index"],
+            ),
+            update_split_storage_tier_errors_total: new_counter_vec(
+                "update_split_storage_tier_errors_total",
+                "Number of failed update split storage tier requests",
+                "quickwit_metastore",
+                &["index"],
+            ),
+            update_split_storage_tier_duration_seconds: new_histogram_vec(
+                "update_split_storage_tier_duration_seconds",
+                "Duration of update split storage tier requests",
+                "quickwit_metastore",
+                &["index", "error"],
+            ),
 
             add_source_requests_total: new_counter_vec(
                 "add_source_requests_total",
@@ -337,6 +404,25 @@ impl Default for MetastoreMetrics {
                 &["index", "source", "error"],
             ),
 
+            update_source_requests_total: new_counter_vec(
+                "update_source_requests_total",
+                "Number of update source requests",
+                "quickwit_metastore",
+                &["index", "source"],
+            ),
+            update_source_errors_total: new_counter_vec(
+                "update_source_errors_total",
+                "Number of failed update source requests",
+                "quickwit_metastore",
+                &["index", "source"],
+            ),
+            update_source_duration_seconds: new_histogram_vec(
+                "update_source_duration_seconds",
+                "Duration of update source requests",
+                "quickwit_metastore",
+                &["index", "source", "error"],
+            ),
+
             toggle_source_requests_total: new_counter_vec(
                 "toggle_source_requests_total",
                 "Number of toggle source requests",
@@ -375,6 +461,44 @@ impl Default for MetastoreMetrics {
                 &["index", "source", "error"],
             ),
 
+            update_source_checkpoint_requests_total: new_counter_vec(
+                "update_source_checkpoint_requests_total",
+                "Number of update source checkpoint requests",
+                "quickwit_metastore",
+                &["index", "source"],
+            ),
+            update_source_checkpoint_errors_total: new_counter_vec(
+                "update_source_checkpoint_errors_total",
+                "Number of failed update source checkpoint requests",
+                "quickwit_metastore",
+                &["index", "source"],
+            ),
+            update_source_checkpoint_duration_seconds: new_histogram_vec(
+                "update_source_checkpoint_duration_seconds",
+                "Duration of update source checkpoint requests",
+                "quickwit_metastore",
+                &["index", "source", "error"],
+            ),
+
+            acquire_publish_token_requests_total: new_counter_vec(
+                "acquire_publish_token_requests_total",
+                "Number of acquire publish token requests",
+                "quickwit_metastore",
+                &["index", "source"],
+            ),
+            acquire_publish_token_errors_total: new_counter_vec(
+                "acquire_publish_token_errors_total",
+                "Number of failed acquire publish token requests",
+                "quickwit_metastore",
+                &["index", "source"],
+            ),
+            acquire_publish_token_duration_seconds: new_histogram_vec(
+                "acquire_publish_token_duration_seconds",
+                "Duration of acquire publish token requests",
+                "quickwit_metastore",
+                &["index", "source", "error"],
+            ),
+
             delete_source_requests_total: new_counter_vec(
                 "delete_source_requests_total",
                 "Number of delete source requests",
@@ -394,6 +518,63 @@ impl Default for MetastoreMetrics {
                 &["index", "source", "error"],
             ),
 
+            put_stored_query_requests_total: new_counter_vec(
+                "put_stored_query_requests_total",
+                "Number of put stored query requests",
+                "quickwit_metastore",
+                &["index", "stored_query"],
+            ),
+            put_stored_query_errors_total: new_counter_vec(
+                "put_stored_query_errors_total",
+                "Number of failed put stored query requests",
+                "quickwit_metastore",
+                &["index", "stored_query"],
+            ),
+            put_stored_query_duration_seconds: new_histogram_vec(
+                "put_stored_query_duration_seconds",
+                "Duration of put stored query requests",
+                "quickwit_metastore",
+                &["index", "stored_query", "error"],
+            ),
+
+            delete_stored_query_requests_total: new_counter_vec(
+                "delete_stored_query_requests_total",
+                "Number of delete stored query requests",
+                "quickwit_metastore",
+                &["index", "stored_query"],
+            ),
+            delete_stored_query_errors_total: new_counter_vec(
+                "delete_stored_query_errors_total",
+                "Number of failed delete stored query requests",
+                "quickwit_metastore",
+                &["index", "stored_query"],
+            ),
+            delete_stored_query_duration_seconds: new_histogram_vec(
+                "delete_stored_query_duration_seconds",
+                "Duration of delete stored query requests",
+                "quickwit_metastore",
+                &["index", "stored_query", "error"],
+            ),
+
+            update_alert_state_requests_total: new_counter_vec(
+                "update_alert_state_requests_total",
+                "Number of update alert state requests",
+                "quickwit_metastore",
+                &["index", "stored_query"],
+            ),
+            update_alert_state_errors_total: new_counter_vec(
+                "update_alert_state_errors_total",
+                "Number of failed update alert state requests",
+                "quickwit_metastore",
+                &["index", "stored_query"],
+            ),
+            update_alert_state_duration_seconds: new_histogram_vec(
+                "update_alert_state_duration_seconds",
+                "Duration of update alert state requests",
+                "quickwit_metastore",
+                &["index", "stored_query", "error"],
+            ),
+
             create_delete_task_requests_total: new_counter_vec(
                 "create_delete_task_requests_total",
                 "Number of create delete task requests",
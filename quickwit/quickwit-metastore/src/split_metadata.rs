@@ -17,16 +17,133 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::ops::{Range, RangeInclusive};
 use std::str::FromStr;
 
+use quickwit_common::uri::Uri;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use time::OffsetDateTime;
 
 use crate::VersionedSplitMetadata;
 
+/// Version of the split file format, i.e. the layout of the split bundle and of its hotcache,
+/// written by this version of Quickwit. It must be bumped on every change of the split file format.
+pub const SPLIT_FORMAT_VERSION: u32 = 1;
+
+/// Oldest split file format version that this version of Quickwit can open. Splits written with an
+/// older format must be rewritten with `quickwit split upgrade` before upgrading Quickwit.
+pub const OLDEST_SUPPORTED_SPLIT_FORMAT_VERSION: u32 = 0;
+
+/// Error returned when a split cannot be opened by this version of Quickwit.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum SplitFormatError {
+    #[error(
+        "Split `{split_id}` was written with the split format version {split_format_version}, \
+         which is no longer supported (oldest supported version: {}). Rewrite the split with \
+         `quickwit split upgrade` using a previous version of Quickwit.",
+        OLDEST_SUPPORTED_SPLIT_FORMAT_VERSION
+    )]
+    Outdated {
+        split_id: String,
+        split_format_version: u32,
+    },
+    #[error(
+        "Split `{split_id}` was written by a more recent version of Quickwit (split format \
+         version {split_format_version}, tantivy index format version \
+         {tantivy_index_format_version}) and cannot be opened by this version. Upgrade Quickwit."
+    )]
+    TooRecent {
+        split_id: String,
+        split_format_version: u32,
+        tantivy_index_format_version: u32,
+    },
+}
+
+/// Storage tier holding the file of a split.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitStorageTier {
+    /// The split file is stored in the index storage.
+    #[default]
+    Hot,
+    /// The split file was moved by the tiering policy of the index to the warm storage located at
+    /// the given URI.
+    Warm(Uri),
+}
+
+impl SplitStorageTier {
+    /// Returns whether the split file is stored in the index storage.
+    pub fn is_hot(&self) -> bool {
+        matches!(self, SplitStorageTier::Hot)
+    }
+
+    /// Returns the URI of the storage holding the split file if it is not the index storage.
+    pub fn storage_uri(&self) -> Option<&Uri> {
+        match self {
+            SplitStorageTier::Hot => None,
+            SplitStorageTier::Warm(storage_uri) => Some(storage_uri),
+        }
+    }
+}
+
+/// Coarse access statistics of a split, reported periodically by the searchers.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SplitAccessStats {
+    /// Number of searches received by the split since it was moved to its current storage tier.
+    pub num_searches: u64,
+    /// Time of the last report of searches of the split, if any.
+    pub last_search_timestamp: Option<i64>,
+    /// Time the split was moved to its current storage tier, if it was ever moved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_tier_timestamp: Option<i64>,
+}
+
+impl SplitAccessStats {
+    /// Adds `num_searches` searches reported at `search_timestamp`.
+    pub fn record_searches(&mut self, num_searches: u64, search_timestamp: i64) {
+        self.num_searches += num_searches;
+        self.last_search_timestamp = Some(
+            self.last_search_timestamp
+                .map_or(search_timestamp, |last_search_timestamp| {
+                    last_search_timestamp.max(search_timestamp)
+                }),
+        );
+    }
+}
+
+/// Statistics of a field of a split, computed when the split is packaged.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SplitFieldStats {
+    /// Number of bytes of the term dictionary of the field.
+    pub termdict_num_bytes: u64,
+    /// Number of bytes of the postings lists of the field.
+    pub postings_num_bytes: u64,
+    /// Number of bytes of the term positions of the field.
+    pub positions_num_bytes: u64,
+    /// Number of bytes of the fast field column of the field.
+    pub fast_field_num_bytes: u64,
+    /// Number of bytes of the fieldnorms of the field.
+    pub fieldnorms_num_bytes: u64,
+    /// Number of terms of the field, summed over the segments of the split.
+    pub num_terms: u64,
+    /// Whether the field is a fast field.
+    pub is_fast: bool,
+}
+
+impl SplitFieldStats {
+    /// Returns the number of bytes taken by the field in the split, excluding the doc store.
+    pub fn total_num_bytes(&self) -> u64 {
+        self.termdict_num_bytes
+            + self.postings_num_bytes
+            + self.positions_num_bytes
+            + self.fast_field_num_bytes
+            + self.fieldnorms_num_bytes
+    }
+}
+
 /// Carries split metadata.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Split {
@@ -99,7 +216,8 @@ pub struct SplitMetadata {
 
     /// Set of unique tags values of form `{field_name}:{field_value}`.
     /// The set is filled at indexing with values from each field registered
-    /// in the [`DocMapping`](quickwit_config::DocMapping) `tag_fields` attribute and only when
+    /// in the [`DocMapping`](quickwit_config::DocMapping) `tag_fields` attribute (or, when
+    /// `tag_keyword_fields` is set, from each keyword field) and only when
     /// cardinality of a given field is less or equal to [`MAX_VALUES_PER_TAG_FIELD`].
     /// An additional special tag of the form `{field_name}!` is added to the set
     /// to indicate that this field `field_name` was indeed registered in `tag_fields`.
@@ -122,6 +240,23 @@ pub struct SplitMetadata {
     /// Number of merge operations that was involved to create
     /// this split.
     pub num_merge_ops: usize,
+
+    /// Version of the split file format the split was written with. Splits written before the
+    /// format version was recorded have the version 0.
+    pub split_format_version: u32,
+
+    /// Version of the tantivy index format the split was written with, or 0 if unknown.
+    pub tantivy_index_format_version: u32,
+
+    /// Storage tier holding the split file.
+    pub storage_tier: SplitStorageTier,
+
+    /// Searches received by the split.
+    pub access_stats: SplitAccessStats,
+
+    /// Statistics of the fields of the split, keyed by field name. Splits packaged before field
+    /// statistics were recorded have none.
+    pub field_stats: BTreeMap<String, SplitFieldStats>,
 }
 
 impl SplitMetadata {
@@ -149,6 +284,42 @@ impl SplitMetadata {
         &self.split_id
     }
 
+    /// Checks that the split can be opened by this version of Quickwit.
+    pub fn check_format_compatibility(&self) -> Result<(), SplitFormatError> {
+        if self.split_format_version < OLDEST_SUPPORTED_SPLIT_FORMAT_VERSION {
+            return Err(SplitFormatError::Outdated {
+                split_id: self.split_id.clone(),
+                split_format_version: self.split_format_version,
+            });
+        }
+        if self.split_format_version > SPLIT_FORMAT_VERSION
+            || self.tantivy_index_format_version > tantivy::INDEX_FORMAT_VERSION
+        {
+            return Err(SplitFormatError::TooRecent {
+                split_id: self.split_id.clone(),
+                split_format_version: self.split_format_version,
+                tantivy_index_format_version: self.tantivy_index_format_version,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the time of the last reported search of the split or, if the split was never
+    /// searched, the time it was created.
+    pub fn last_activity_timestamp(&self) -> i64 {
+        self.access_stats
+            .last_search_timestamp
+            .unwrap_or(self.create_timestamp)
+    }
+
+    /// Returns whether the split was written with an older split or tantivy index format than the
+    /// ones written by this version of Quickwit, and can be rewritten with `quickwit split
+    /// upgrade`.
+    pub fn is_format_outdated(&self) -> bool {
+        self.split_format_version < SPLIT_FORMAT_VERSION
+            || self.tantivy_index_format_version < tantivy::INDEX_FORMAT_VERSION
+    }
+
     #[cfg(any(test, feature = "testsuite"))]
     /// Returns an instance of `SplitMetadata` for testing.
     pub fn for_test(split_id: String) -> Self {
@@ -216,3 +387,31 @@ pub fn utc_now_timestamp() -> i64 {
         OffsetDateTime::now_utc().unix_timestamp()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_metadata_check_format_compatibility() {
+        let mut split_metadata = SplitMetadata::for_test("split".to_string());
+        split_metadata.check_format_compatibility().unwrap();
+        assert!(split_metadata.is_format_outdated());
+
+        split_metadata.split_format_version = SPLIT_FORMAT_VERSION;
+        split_metadata.tantivy_index_format_version = tantivy::INDEX_FORMAT_VERSION;
+        split_metadata.check_format_compatibility().unwrap();
+        assert!(!split_metadata.is_format_outdated());
+
+        split_metadata.split_format_version = SPLIT_FORMAT_VERSION + 1;
+        assert!(matches!(
+            split_metadata.check_format_compatibility().unwrap_err(),
+            SplitFormatError::TooRecent { .. }
+        ));
+
+        split_metadata.split_format_version = SPLIT_FORMAT_VERSION;
+        split_metadata.tantivy_index_format_version = tantivy::INDEX_FORMAT_VERSION + 1;
+        let error = split_metadata.check_format_compatibility().unwrap_err();
+        assert!(error.to_string().contains("Upgrade Quickwit"));
+    }
+}
@@ -17,13 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Range, RangeInclusive};
 
 use serde::{Deserialize, Serialize};
 
 use crate::split_metadata::utc_now_timestamp;
-use crate::{SplitMetadata, SplitState};
+use crate::{SplitAccessStats, SplitFieldStats, SplitMetadata, SplitState, SplitStorageTier};
 
 #[derive(Clone, Eq, PartialEq, Debug, Deserialize)]
 struct SplitMetadataV0 {
@@ -86,6 +86,11 @@ impl From<SplitMetadataAndFooterV0> for SplitMetadata {
             tags: v0.split_metadata.tags,
             index_id: "".to_string(),
             num_merge_ops: 0,
+            split_format_version: 0,
+            tantivy_index_format_version: 0,
+            storage_tier: SplitStorageTier::Hot,
+            access_stats: SplitAccessStats::default(),
+            field_stats: BTreeMap::new(),
         }
     }
 }
@@ -146,6 +151,24 @@ pub(crate) struct SplitMetadataV1 {
 
     #[serde(default)]
     num_merge_ops: usize,
+
+    /// Version of the split file format the split was written with.
+    #[serde(default)]
+    pub split_format_version: u32,
+
+    /// Version of the tantivy index format the split was written with, or 0 if unknown.
+    #[serde(default)]
+    pub tantivy_index_format_version: u32,
+
+    #[serde(default)]
+    pub storage_tier: SplitStorageTier,
+
+    #[serde(default)]
+    pub access_stats: SplitAccessStats,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_stats: BTreeMap<String, SplitFieldStats>,
 }
 
 impl From<SplitMetadataV1> for SplitMetadata {
@@ -180,6 +203,11 @@ impl From<SplitMetadataV1> for SplitMetadata {
             tags: v1.tags,
             footer_offsets: v1.footer_offsets,
             num_merge_ops: v1.num_merge_ops,
+            split_format_version: v1.split_format_version,
+            tantivy_index_format_version: v1.tantivy_index_format_version,
+            storage_tier: v1.storage_tier,
+            access_stats: v1.access_stats,
+            field_stats: v1.field_stats,
         }
     }
 }
@@ -200,6 +228,11 @@ impl From<SplitMetadata> for SplitMetadataV1 {
             tags: split.tags,
             footer_offsets: split.footer_offsets,
             num_merge_ops: split.num_merge_ops,
+            split_format_version: split.split_format_version,
+            tantivy_index_format_version: split.tantivy_index_format_version,
+            storage_tier: split.storage_tier,
+            access_stats: split.access_stats,
+            field_stats: split.field_stats,
         }
     }
 }
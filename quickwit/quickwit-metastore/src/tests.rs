@@ -19,12 +19,13 @@
 
 #[cfg(test)]
 pub mod test_suite {
-    use std::collections::{BTreeSet, HashSet};
+    use std::collections::{BTreeSet, HashMap, HashSet};
 
     use async_trait::async_trait;
     use itertools::Itertools;
     use quickwit_common::rand::append_random_suffix;
-    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_common::uri::Uri;
+    use quickwit_config::{SourceConfig, SourceParams, StoredQuery};
     use quickwit_doc_mapper::tag_pruning::{no_tag, tag, TagFilterAst};
     use quickwit_proto::metastore_api::DeleteQuery;
     use time::OffsetDateTime;
@@ -32,7 +33,10 @@ pub mod test_suite {
     use tracing::{error, info};
 
     use crate::checkpoint::{IndexCheckpointDelta, PartitionId, Position, SourceCheckpoint};
-    use crate::{IndexMetadata, Metastore, MetastoreError, SplitMetadata, SplitState};
+    use crate::{
+        AlertState, IndexMetadata, Metastore, MetastoreError, SplitAccessStats, SplitMetadata,
+        SplitState, SplitStorageTier,
+    };
 
     #[async_trait]
     pub trait DefaultForTest {
@@ -48,6 +52,11 @@ pub mod test_suite {
     }
 
     async fn cleanup_index(metastore: &dyn Metastore, index_id: &str) {
+        // The splits of a read-only index are deleted along with the index.
+        if metastore.index_metadata(index_id).await.unwrap().read_only {
+            metastore.delete_index(index_id).await.unwrap();
+            return;
+        }
         // List all splits.
         let all_splits = metastore.list_all_splits(index_id).await.unwrap();
 
@@ -93,6 +102,11 @@ pub mod test_suite {
             let source = SourceConfig {
                 source_id: source_id.clone(),
                 num_pipelines: 1,
+                expected_throughput: None,
+                rate_limit_per_sec: None,
+                transform: Vec::new(),
+                input_format: None,
+                csv_options: None,
                 enabled: true,
                 source_params: SourceParams::void(),
             };
@@ -170,6 +184,11 @@ pub mod test_suite {
         let source = SourceConfig {
             source_id: source_id.to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::void(),
         };
@@ -218,6 +237,228 @@ pub mod test_suite {
         cleanup_index(&metastore, &index_metadata.index_id).await;
     }
 
+    pub async fn test_metastore_update_source_checkpoint<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-update-source-checkpoint");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let source_id = format!("{index_id}--source");
+        let source = SourceConfig {
+            source_id: source_id.clone(),
+            num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            enabled: true,
+            source_params: SourceParams::void(),
+        };
+        metastore.add_source(&index_id, source).await.unwrap();
+        metastore
+            .publish_splits(
+                &index_id,
+                &[],
+                &[],
+                Some(IndexCheckpointDelta::for_test(&source_id, 0..10)),
+            )
+            .await
+            .unwrap();
+
+        // Move the checkpoint backward, which publishing a checkpoint delta does not allow.
+        let source_checkpoint: SourceCheckpoint = [(PartitionId::default(), Position::from(4u64))]
+            .into_iter()
+            .collect();
+        metastore
+            .update_source_checkpoint(&index_id, &source_id, source_checkpoint.clone())
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        assert_eq!(
+            index_metadata.checkpoint.source_checkpoint(&source_id),
+            Some(&source_checkpoint)
+        );
+
+        assert!(matches!(
+            metastore
+                .update_source_checkpoint(
+                    "index-does-not-exist",
+                    &source_id,
+                    source_checkpoint.clone()
+                )
+                .await
+                .unwrap_err(),
+            MetastoreError::IndexDoesNotExist { .. }
+        ));
+        assert!(matches!(
+            metastore
+                .update_source_checkpoint(&index_id, "source-does-not-exist", source_checkpoint)
+                .await
+                .unwrap_err(),
+            MetastoreError::SourceDoesNotExist { .. }
+        ));
+        let index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        assert!(index_metadata
+            .checkpoint
+            .source_checkpoint("source-does-not-exist")
+            .is_none());
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_source_checkpoint<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-source-checkpoint");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let source_id = format!("{index_id}--source");
+        assert!(matches!(
+            metastore
+                .source_checkpoint(&index_id, &source_id)
+                .await
+                .unwrap_err(),
+            MetastoreError::SourceDoesNotExist { .. }
+        ));
+        let source = SourceConfig {
+            source_id: source_id.clone(),
+            num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            enabled: true,
+            source_params: SourceParams::void(),
+        };
+        metastore.add_source(&index_id, source).await.unwrap();
+        assert!(metastore
+            .source_checkpoint(&index_id, &source_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        metastore
+            .publish_splits(
+                &index_id,
+                &[],
+                &[],
+                Some(IndexCheckpointDelta::for_test(&source_id, 0..10)),
+            )
+            .await
+            .unwrap();
+        let expected_checkpoint: SourceCheckpoint =
+            [(PartitionId::default(), Position::from(9u64))]
+                .into_iter()
+                .collect();
+        assert_eq!(
+            metastore
+                .source_checkpoint(&index_id, &source_id)
+                .await
+                .unwrap(),
+            expected_checkpoint
+        );
+        assert!(matches!(
+            metastore
+                .source_checkpoint("index-does-not-exist", &source_id)
+                .await
+                .unwrap_err(),
+            MetastoreError::IndexDoesNotExist { .. }
+        ));
+        cleanup_index(&metastore, &index_id).await;
+    }
+
+    pub async fn test_metastore_acquire_publish_token<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-metastore-acquire-publish-token");
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(&index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let source_id = format!("{index_id}--source");
+        let source = SourceConfig {
+            source_id: source_id.clone(),
+            num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            enabled: true,
+            source_params: SourceParams::void(),
+        };
+        metastore.add_source(&index_id, source).await.unwrap();
+
+        let stale_token = metastore
+            .acquire_publish_token(&index_id, &source_id, "0")
+            .await
+            .unwrap();
+        let latest_token = metastore
+            .acquire_publish_token(&index_id, &source_id, "0")
+            .await
+            .unwrap();
+        assert!(latest_token.token > stale_token.token);
+
+        // Tokens of distinct partition sets are independent.
+        let other_token = metastore
+            .acquire_publish_token(&index_id, &source_id, "1")
+            .await
+            .unwrap();
+
+        let mut checkpoint_delta = IndexCheckpointDelta::for_test(&source_id, 0..10);
+        checkpoint_delta.publish_token_opt = Some(stale_token);
+        assert!(matches!(
+            metastore
+                .publish_splits(&index_id, &[], &[], Some(checkpoint_delta))
+                .await
+                .unwrap_err(),
+            MetastoreError::StalePublishToken { .. }
+        ));
+        let mut checkpoint_delta = IndexCheckpointDelta::for_test(&source_id, 0..10);
+        checkpoint_delta.publish_token_opt = Some(latest_token);
+        metastore
+            .publish_splits(&index_id, &[], &[], Some(checkpoint_delta))
+            .await
+            .unwrap();
+
+        let mut checkpoint_delta = IndexCheckpointDelta::for_test(&source_id, 10..20);
+        checkpoint_delta.publish_token_opt = Some(other_token);
+        metastore
+            .publish_splits(&index_id, &[], &[], Some(checkpoint_delta))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            metastore
+                .acquire_publish_token("index-does-not-exist", &source_id, "0")
+                .await
+                .unwrap_err(),
+            MetastoreError::IndexDoesNotExist { .. }
+        ));
+        cleanup_index(&metastore, &index_id).await;
+    }
+
     pub async fn test_metastore_toggle_source<MetastoreToTest: Metastore + DefaultForTest>() {
         let metastore = MetastoreToTest::default_for_test().await;
 
@@ -234,6 +475,11 @@ pub mod test_suite {
         let source = SourceConfig {
             source_id: source_id.to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::void(),
         };
@@ -254,14 +500,212 @@ pub mod test_suite {
         let source = index_metadata.sources.get(source_id).unwrap();
         assert_eq!(source.enabled, false);
 
-        // Enable source.
+        // Enable source.
+        metastore
+            .toggle_source(index_id, &source.source_id, true)
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(index_id).await.unwrap();
+        let source = index_metadata.sources.get(source_id).unwrap();
+        assert_eq!(source.enabled, true);
+
+        cleanup_index(&metastore, &index_metadata.index_id).await;
+    }
+
+    pub async fn test_metastore_update_source<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = "test-metastore-update-source";
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let source_id = "test-metastore-update-source--void-source-id";
+        let source = SourceConfig {
+            source_id: source_id.to_string(),
+            num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            enabled: true,
+            source_params: SourceParams::void(),
+        };
+        assert!(matches!(
+            metastore
+                .update_source(index_id, source.clone())
+                .await
+                .unwrap_err(),
+            MetastoreError::SourceDoesNotExist { .. }
+        ));
+        metastore
+            .add_source(index_id, source.clone())
+            .await
+            .unwrap();
+
+        let updated_source = SourceConfig {
+            num_pipelines: 2,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
+            ..source
+        };
+        metastore
+            .update_source(index_id, updated_source.clone())
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(index_id).await.unwrap();
+        assert_eq!(
+            index_metadata.sources.get(source_id).unwrap(),
+            &updated_source
+        );
+        assert!(index_metadata
+            .checkpoint
+            .source_checkpoint(source_id)
+            .is_some());
+
+        cleanup_index(&metastore, &index_metadata.index_id).await;
+    }
+
+    pub async fn test_metastore_put_and_delete_stored_query<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = "test-metastore-put-and-delete-stored-query";
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let stored_query = StoredQuery {
+            name: "errors".to_string(),
+            description: None,
+            query: "body:error".to_string(),
+            params: Vec::new(),
+            search_fields: Vec::new(),
+            max_hits: None,
+            sort_by_field: None,
+            aggregation_request: None,
+            alert: None,
+        };
+        metastore
+            .put_stored_query(index_id, stored_query.clone())
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(index_id).await.unwrap();
+        assert_eq!(
+            index_metadata.stored_queries.get("errors").unwrap(),
+            &stored_query
+        );
+
+        let updated_stored_query = StoredQuery {
+            max_hits: Some(5),
+            ..stored_query
+        };
+        metastore
+            .put_stored_query(index_id, updated_stored_query.clone())
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(index_id).await.unwrap();
+        assert_eq!(index_metadata.stored_queries.len(), 1);
+        assert_eq!(
+            index_metadata.stored_queries.get("errors").unwrap(),
+            &updated_stored_query
+        );
+
+        metastore
+            .delete_stored_query(index_id, "errors")
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(index_id).await.unwrap();
+        assert!(index_metadata.stored_queries.is_empty());
+
+        assert!(matches!(
+            metastore
+                .delete_stored_query(index_id, "errors")
+                .await
+                .unwrap_err(),
+            MetastoreError::StoredQueryDoesNotExist { .. }
+        ));
+        assert!(matches!(
+            metastore
+                .put_stored_query("index-does-not-exist", updated_stored_query)
+                .await
+                .unwrap_err(),
+            MetastoreError::IndexDoesNotExist { .. }
+        ));
+
+        cleanup_index(&metastore, &index_metadata.index_id).await;
+    }
+
+    pub async fn test_metastore_update_alert_state<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = "test-metastore-update-alert-state";
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+
+        metastore
+            .create_index(index_metadata.clone())
+            .await
+            .unwrap();
+
+        let alert_state = AlertState {
+            name: "errors".to_string(),
+            last_evaluation_timestamp: 1_000,
+            last_num_hits: 12,
+            firing: true,
+        };
+        assert!(matches!(
+            metastore
+                .update_alert_state(index_id, alert_state.clone())
+                .await
+                .unwrap_err(),
+            MetastoreError::StoredQueryDoesNotExist { .. }
+        ));
+
+        let stored_query = StoredQuery {
+            name: "errors".to_string(),
+            description: None,
+            query: "body:error".to_string(),
+            params: Vec::new(),
+            search_fields: Vec::new(),
+            max_hits: None,
+            sort_by_field: None,
+            aggregation_request: None,
+            alert: None,
+        };
+        metastore
+            .put_stored_query(index_id, stored_query)
+            .await
+            .unwrap();
+        metastore
+            .update_alert_state(index_id, alert_state.clone())
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(index_id).await.unwrap();
+        assert_eq!(
+            index_metadata.alert_states.get("errors").unwrap(),
+            &alert_state
+        );
+
         metastore
-            .toggle_source(index_id, &source.source_id, true)
+            .delete_stored_query(index_id, "errors")
             .await
             .unwrap();
         let index_metadata = metastore.index_metadata(index_id).await.unwrap();
-        let source = index_metadata.sources.get(source_id).unwrap();
-        assert_eq!(source.enabled, true);
+        assert!(index_metadata.alert_states.is_empty());
 
         cleanup_index(&metastore, &index_metadata.index_id).await;
     }
@@ -277,6 +721,11 @@ pub mod test_suite {
         let source = SourceConfig {
             source_id: source_id.to_string(),
             num_pipelines: 1,
+            expected_throughput: None,
+            rate_limit_per_sec: None,
+            transform: Vec::new(),
+            input_format: None,
+            csv_options: None,
             enabled: true,
             source_params: SourceParams::void(),
         };
@@ -932,6 +1381,121 @@ pub mod test_suite {
 
             cleanup_index(&metastore, index_id).await;
         }
+
+        // Publish a split with a checkpoint delta leaving a gap
+        {
+            metastore
+                .create_index(index_metadata.clone())
+                .await
+                .unwrap();
+
+            metastore
+                .stage_split(index_id, split_metadata_1.clone())
+                .await
+                .unwrap();
+
+            metastore
+                .stage_split(index_id, split_metadata_2.clone())
+                .await
+                .unwrap();
+
+            metastore
+                .publish_splits(
+                    index_id,
+                    &[split_id_1],
+                    &[],
+                    {
+                        let offsets = 0..5;
+                        IndexCheckpointDelta::for_test(source_id, offsets)
+                    }
+                    .into(),
+                )
+                .await
+                .unwrap();
+
+            let publish_error = metastore
+                .publish_splits(
+                    index_id,
+                    &[split_id_2],
+                    &[],
+                    {
+                        let offsets = 10..12;
+                        IndexCheckpointDelta::for_test(source_id, offsets)
+                    }
+                    .into(),
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                publish_error,
+                MetastoreError::CheckpointDeltaGap(_)
+            ));
+
+            cleanup_index(&metastore, index_id).await;
+        }
+
+        // Replace a split of a read-only index
+        {
+            let mut read_only_index_metadata = index_metadata.clone();
+            read_only_index_metadata.read_only = true;
+            metastore
+                .create_index(read_only_index_metadata)
+                .await
+                .unwrap();
+
+            metastore
+                .stage_split(index_id, split_metadata_1.clone())
+                .await
+                .unwrap();
+            metastore
+                .stage_split(index_id, split_metadata_2.clone())
+                .await
+                .unwrap();
+
+            // The splits of a read-only index are published when it is attached.
+            metastore
+                .publish_splits(index_id, &[split_id_1], &[], None)
+                .await
+                .unwrap();
+
+            let publish_error = metastore
+                .publish_splits(index_id, &[split_id_2], &[split_id_1], None)
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                publish_error,
+                MetastoreError::IndexReadOnly { .. }
+            ));
+
+            let publish_error = metastore
+                .publish_splits(
+                    index_id,
+                    &[split_id_2],
+                    &[],
+                    {
+                        let offsets = 0..5;
+                        IndexCheckpointDelta::for_test(source_id, offsets)
+                    }
+                    .into(),
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                publish_error,
+                MetastoreError::IndexReadOnly { .. }
+            ));
+
+            let split = metastore
+                .list_all_splits(index_id)
+                .await
+                .unwrap()
+                .into_iter()
+                .find(|split| split.split_id() == split_id_1)
+                .unwrap();
+            assert_eq!(split.split_state, SplitState::Published);
+
+            cleanup_index(&metastore, index_id).await;
+        }
     }
 
     pub async fn test_metastore_replace_splits<MetastoreToTest: Metastore + DefaultForTest>() {
@@ -1142,7 +1706,7 @@ pub mod test_suite {
                 .await
                 .unwrap_err();
             assert!(
-                matches!(error, MetastoreError::SplitsNotDeletable { split_ids } if split_ids == vec![split_id_1.clone()])
+                matches!(error, MetastoreError::ReplacedSplitsNotPublished { split_ids } if split_ids == vec![split_id_1.clone()])
             );
 
             cleanup_index(&metastore, &index_id).await;
@@ -1256,6 +1820,29 @@ pub mod test_suite {
 
             cleanup_index(&metastore, index_id).await;
         }
+
+        // Mark a split of a read-only index for deletion
+        {
+            let mut read_only_index_metadata = index_metadata.clone();
+            read_only_index_metadata.read_only = true;
+            metastore
+                .create_index(read_only_index_metadata)
+                .await
+                .unwrap();
+
+            metastore
+                .stage_split(index_id, split_metadata_1.clone())
+                .await
+                .unwrap();
+
+            let result = metastore
+                .mark_splits_for_deletion(index_id, &[split_id_1])
+                .await
+                .unwrap_err();
+            assert!(matches!(result, MetastoreError::IndexReadOnly { .. }));
+
+            cleanup_index(&metastore, index_id).await;
+        }
     }
 
     pub async fn test_metastore_delete_splits<MetastoreToTest: Metastore + DefaultForTest>() {
@@ -1388,6 +1975,165 @@ pub mod test_suite {
 
             cleanup_index(&metastore, index_id).await;
         }
+
+        {
+            info!("Delete a staged split of a read-only index");
+            let mut read_only_index_metadata = index_metadata.clone();
+            read_only_index_metadata.read_only = true;
+            metastore
+                .create_index(read_only_index_metadata)
+                .await
+                .unwrap();
+
+            metastore
+                .stage_split(index_id, split_metadata_1.clone())
+                .await
+                .unwrap();
+
+            let metastore_err = metastore
+                .delete_splits(index_id, &[split_id_1])
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                metastore_err,
+                MetastoreError::IndexReadOnly { .. }
+            ));
+
+            cleanup_index(&metastore, index_id).await;
+            assert!(!metastore.index_exists(index_id).await.unwrap());
+        }
+    }
+
+    pub async fn test_metastore_split_access_stats_and_storage_tier<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = "split-access-stats-and-storage-tier-index";
+        let index_uri = format!("ram://indexes/{index_id}");
+        let index_metadata = IndexMetadata::for_test(index_id, &index_uri);
+
+        let split_id_1 = "split-access-stats-and-storage-tier-index-one";
+        let split_metadata_1 = SplitMetadata {
+            split_id: split_id_1.to_string(),
+            index_id: index_id.to_string(),
+            ..Default::default()
+        };
+        let split_id_2 = "split-access-stats-and-storage-tier-index-two";
+        let split_metadata_2 = SplitMetadata {
+            split_id: split_id_2.to_string(),
+            index_id: index_id.to_string(),
+            ..Default::default()
+        };
+        let warm_storage_tier = SplitStorageTier::Warm(Uri::from_well_formed(
+            "ram://warm-indexes/my-index".to_string(),
+        ));
+
+        {
+            info!("Update the storage tier of a split on a non-existent index");
+            let metastore_err = metastore
+                .update_split_storage_tier(
+                    "non-existent-index",
+                    "non-existent-split",
+                    warm_storage_tier.clone(),
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                metastore_err,
+                MetastoreError::IndexDoesNotExist { .. }
+            ));
+        }
+
+        metastore.create_index(index_metadata).await.unwrap();
+        metastore
+            .stage_split(index_id, split_metadata_1)
+            .await
+            .unwrap();
+        metastore
+            .stage_split(index_id, split_metadata_2)
+            .await
+            .unwrap();
+        {
+            info!("Update the storage tier of a non-existent split");
+            let metastore_err = metastore
+                .update_split_storage_tier(
+                    index_id,
+                    "non-existent-split",
+                    warm_storage_tier.clone(),
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                metastore_err,
+                MetastoreError::SplitsDoNotExist { .. }
+            ));
+        }
+        {
+            info!("Record split searches");
+            let num_searches_per_split = HashMap::from_iter([
+                (split_id_1.to_string(), 3),
+                ("non-existent-split".to_string(), 5),
+            ]);
+            metastore
+                .record_split_searches(index_id, num_searches_per_split, 1_000)
+                .await
+                .unwrap();
+            let num_searches_per_split = HashMap::from_iter([(split_id_1.to_string(), 2)]);
+            metastore
+                .record_split_searches(index_id, num_searches_per_split, 2_000)
+                .await
+                .unwrap();
+
+            let splits = metastore.list_all_splits(index_id).await.unwrap();
+            let split_1 = splits
+                .iter()
+                .find(|split| split.split_id() == split_id_1)
+                .unwrap();
+            assert_eq!(split_1.split_metadata.access_stats.num_searches, 5);
+            assert_eq!(
+                split_1.split_metadata.access_stats.last_search_timestamp,
+                Some(2_000)
+            );
+            let split_2 = splits
+                .iter()
+                .find(|split| split.split_id() == split_id_2)
+                .unwrap();
+            assert_eq!(
+                split_2.split_metadata.access_stats,
+                SplitAccessStats::default()
+            );
+        }
+        {
+            info!("Update the storage tier of a split");
+            metastore
+                .update_split_storage_tier(index_id, split_id_1, warm_storage_tier.clone())
+                .await
+                .unwrap();
+
+            let splits = metastore.list_all_splits(index_id).await.unwrap();
+            let split_1 = splits
+                .iter()
+                .find(|split| split.split_id() == split_id_1)
+                .unwrap();
+            assert_eq!(split_1.split_metadata.storage_tier, warm_storage_tier);
+            assert_eq!(split_1.split_metadata.access_stats.num_searches, 0);
+            assert_eq!(
+                split_1.split_metadata.access_stats.last_search_timestamp,
+                Some(2_000)
+            );
+            assert!(split_1
+                .split_metadata
+                .access_stats
+                .storage_tier_timestamp
+                .is_some());
+            let split_2 = splits
+                .iter()
+                .find(|split| split.split_id() == split_id_2)
+                .unwrap();
+            assert_eq!(split_2.split_metadata.storage_tier, SplitStorageTier::Hot);
+        }
+        cleanup_index(&metastore, index_id).await;
     }
 
     pub async fn test_metastore_list_all_splits<MetastoreToTest: Metastore + DefaultForTest>() {
@@ -2721,18 +3467,60 @@ macro_rules! metastore_test_suite {
                 crate::tests::test_suite::test_metastore_add_source::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_update_source_checkpoint() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_update_source_checkpoint::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_source_checkpoint() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_source_checkpoint::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_acquire_publish_token() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_acquire_publish_token::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             async fn test_metastore_toggle_source() {
                 let _ = tracing_subscriber::fmt::try_init();
                 crate::tests::test_suite::test_metastore_toggle_source::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_update_source() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_update_source::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             async fn test_metastore_delete_source() {
                 let _ = tracing_subscriber::fmt::try_init();
                 crate::tests::test_suite::test_metastore_delete_source::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_put_and_delete_stored_query() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_put_and_delete_stored_query::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_update_alert_state() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_update_alert_state::<$metastore_type>().await;
+            }
+
+            #[tokio::test]
+            async fn test_metastore_split_access_stats_and_storage_tier() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_split_access_stats_and_storage_tier::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             async fn test_metastore_reset_checkpoint() {
                 let _ = tracing_subscriber::fmt::try_init();
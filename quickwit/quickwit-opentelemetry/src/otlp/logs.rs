@@ -17,14 +17,61 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
+
 use async_trait::async_trait;
+use quickwit_actors::Mailbox;
+use quickwit_ingest_api::{DocBatchBuilder, IngestApiService};
+use quickwit_proto::ingest_api::IngestRequest;
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::logs_service_server::LogsService;
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::{
     ExportLogsServiceRequest, ExportLogsServiceResponse,
 };
+use quickwit_proto::opentelemetry::proto::common::v1::any_value::Value as OtlpValue;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use tracing::error;
+
+use super::{extract_attributes, to_json_value};
 
-#[derive(Default, Clone)]
-pub struct OtlpGrpcLogsService {}
+/// Index receiving the logs, unless the request designates another one with the
+/// [`LOGS_INDEX_HEADER`] metadata header.
+const LOGS_INDEX_ID: &str = "otel-logs";
+
+/// gRPC metadata header designating the index receiving the logs of a request.
+const LOGS_INDEX_HEADER: &str = "qw-otel-logs-index";
+
+/// Receives the logs exported by OpenTelemetry clients and collectors, and ingests them into an
+/// index through the ingest API. The log records are mapped to the doc mapping of the
+/// `otel-logs` tutorial index.
+#[derive(Clone)]
+pub struct OtlpGrpcLogsService {
+    ingest_api_service: Mailbox<IngestApiService>,
+}
+
+impl OtlpGrpcLogsService {
+    pub fn new(ingest_api_service: Mailbox<IngestApiService>) -> Self {
+        Self { ingest_api_service }
+    }
+}
+
+type Base64 = String;
+
+#[derive(Debug, Serialize)]
+struct LogRecord {
+    /// Timestamp of the log record in milliseconds.
+    timestamp: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    severity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    attributes: HashMap<String, JsonValue>,
+    resource: HashMap<String, JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_id: Option<Base64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    span_id: Option<Base64>,
+}
 
 #[async_trait]
 impl LogsService for OtlpGrpcLogsService {
@@ -32,11 +79,196 @@ impl LogsService for OtlpGrpcLogsService {
         &self,
         request: tonic::Request<ExportLogsServiceRequest>,
     ) -> Result<tonic::Response<ExportLogsServiceResponse>, tonic::Status> {
-        let request = request.into_inner();
-        for resource_log in request.resource_logs {
-            println!("{:?}", resource_log);
+        let index_id = match request.metadata().get(LOGS_INDEX_HEADER) {
+            Some(header_value) => header_value
+                .to_str()
+                .map_err(|_| {
+                    tonic::Status::invalid_argument(format!(
+                        "Header `{LOGS_INDEX_HEADER}` must be a valid index ID."
+                    ))
+                })?
+                .to_string(),
+            None => LOGS_INDEX_ID.to_string(),
+        };
+        let log_records = parse_log_records(request.into_inner());
+        if log_records.is_empty() {
+            return Ok(tonic::Response::new(ExportLogsServiceResponse::default()));
+        }
+        let mut doc_batch_builder = DocBatchBuilder::new(index_id.clone());
+        for log_record in log_records {
+            let log_record_json =
+                serde_json::to_vec(&log_record).expect("Log record should be JSON serializable.");
+            doc_batch_builder.add_doc(&log_record_json);
+        }
+        let ingest_request = IngestRequest {
+            doc_batches: vec![doc_batch_builder.build()],
+        };
+        if let Err(error) = self.ingest_api_service.ask_for_res(ingest_request).await {
+            error!(index_id=%index_id, error=?error, "Failed to ingest logs.");
+            return Err(tonic::Status::internal(format!(
+                "Failed to ingest logs into index `{index_id}`: {error}."
+            )));
         }
-        let response = ExportLogsServiceResponse::default();
-        Ok(tonic::Response::new(response))
+        Ok(tonic::Response::new(ExportLogsServiceResponse::default()))
+    }
+}
+
+fn parse_log_records(request: ExportLogsServiceRequest) -> Vec<LogRecord> {
+    let mut log_records = Vec::new();
+    for resource_log in request.resource_logs {
+        let resource = resource_log
+            .resource
+            .map(|resource| extract_attributes(resource.attributes))
+            .unwrap_or_default();
+        for scope_log in resource_log.scope_logs {
+            for log_record in scope_log.log_records {
+                // The observed timestamp is set when the time of the event is unknown.
+                let timestamp_nanos = if log_record.time_unix_nano != 0 {
+                    log_record.time_unix_nano
+                } else {
+                    log_record.observed_time_unix_nano
+                };
+                let severity = if !log_record.severity_text.is_empty() {
+                    Some(log_record.severity_text)
+                } else {
+                    severity_text_for_number(log_record.severity_number).map(str::to_string)
+                };
+                let body =
+                    log_record
+                        .body
+                        .and_then(|body| body.value)
+                        .and_then(|body| match body {
+                            OtlpValue::StringValue(body) => Some(body),
+                            body => to_json_value(body).map(|body| body.to_string()),
+                        });
+                let trace_id = if !log_record.trace_id.is_empty() {
+                    Some(base64::encode(log_record.trace_id))
+                } else {
+                    None
+                };
+                let span_id = if !log_record.span_id.is_empty() {
+                    Some(base64::encode(log_record.span_id))
+                } else {
+                    None
+                };
+                log_records.push(LogRecord {
+                    timestamp: (timestamp_nanos / 1_000_000) as i64,
+                    severity,
+                    body,
+                    attributes: extract_attributes(log_record.attributes),
+                    resource: resource.clone(),
+                    trace_id,
+                    span_id,
+                });
+            }
+        }
+    }
+    log_records
+}
+
+/// Returns the short name of a severity number, as defined by the OpenTelemetry log data model:
+/// <https://github.com/open-telemetry/opentelemetry-specification/blob/main/specification/logs/data-model.md#displaying-severity>
+fn severity_text_for_number(severity_number: i32) -> Option<&'static str> {
+    let severity_text = match severity_number {
+        1..=4 => "TRACE",
+        5..=8 => "DEBUG",
+        9..=12 => "INFO",
+        13..=16 => "WARN",
+        17..=20 => "ERROR",
+        21..=24 => "FATAL",
+        _ => return None,
+    };
+    Some(severity_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::opentelemetry::proto::common::v1::{AnyValue, KeyValue, KeyValueList};
+    use quickwit_proto::opentelemetry::proto::logs::v1::{
+        LogRecord as OtlpLogRecord, ResourceLogs, ScopeLogs,
+    };
+    use quickwit_proto::opentelemetry::proto::resource::v1::Resource;
+    use serde_json::json;
+
+    use super::*;
+
+    fn string_value(value: &str) -> Option<AnyValue> {
+        Some(AnyValue {
+            value: Some(OtlpValue::StringValue(value.to_string())),
+        })
+    }
+
+    #[test]
+    fn test_parse_log_records() {
+        let request = ExportLogsServiceRequest {
+            resource_logs: vec![ResourceLogs {
+                resource: Some(Resource {
+                    attributes: vec![KeyValue {
+                        key: "service.name".to_string(),
+                        value: string_value("payments"),
+                    }],
+                    dropped_attributes_count: 0,
+                }),
+                scope_logs: vec![ScopeLogs {
+                    scope: None,
+                    log_records: vec![
+                        OtlpLogRecord {
+                            time_unix_nano: 1_666_000_000_123_456_789,
+                            severity_number: 17,
+                            body: string_value("Payment declined."),
+                            attributes: vec![KeyValue {
+                                key: "card.type".to_string(),
+                                value: string_value("visa"),
+                            }],
+                            trace_id: vec![1; 16],
+                            span_id: vec![2; 8],
+                            ..Default::default()
+                        },
+                        OtlpLogRecord {
+                            observed_time_unix_nano: 1_666_000_001_000_000_000,
+                            severity_text: "notice".to_string(),
+                            body: Some(AnyValue {
+                                value: Some(OtlpValue::KvlistValue(KeyValueList {
+                                    values: vec![KeyValue {
+                                        key: "amount".to_string(),
+                                        value: Some(AnyValue {
+                                            value: Some(OtlpValue::IntValue(42)),
+                                        }),
+                                    }],
+                                })),
+                            }),
+                            ..Default::default()
+                        },
+                    ],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        };
+        let log_records: Vec<JsonValue> = parse_log_records(request)
+            .iter()
+            .map(|log_record| serde_json::to_value(log_record).unwrap())
+            .collect();
+        assert_eq!(
+            log_records,
+            vec![
+                json!({
+                    "timestamp": 1_666_000_000_123i64,
+                    "severity": "ERROR",
+                    "body": "Payment declined.",
+                    "attributes": {"card.type": "visa"},
+                    "resource": {"service.name": "payments"},
+                    "trace_id": "AQEBAQEBAQEBAQEBAQEBAQ==",
+                    "span_id": "AgICAgICAgI=",
+                }),
+                json!({
+                    "timestamp": 1_666_000_001_000i64,
+                    "severity": "notice",
+                    "body": r#"{"amount":42}"#,
+                    "attributes": {},
+                    "resource": {"service.name": "payments"},
+                }),
+            ]
+        );
     }
 }
@@ -20,5 +20,86 @@
 mod logs;
 mod trace;
 
+use std::collections::HashMap;
+
 pub use logs::OtlpGrpcLogsService;
-pub use trace::OtlpGrpcTraceService;
+use quickwit_proto::opentelemetry::proto::common::v1::any_value::Value as OtlpValue;
+use quickwit_proto::opentelemetry::proto::common::v1::KeyValue;
+use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+pub use trace::{OtlpGrpcTraceService, OTEL_TRACES_INDEX_ID};
+
+fn extract_attributes(attributes: Vec<KeyValue>) -> HashMap<String, JsonValue> {
+    let mut attrs = HashMap::new();
+    for attribute in attributes {
+        // Filtering out empty attribute values is fine according to the OTel spec: <https://github.com/open-telemetry/opentelemetry-specification/tree/main/specification/common#attribute>
+        if let Some(value) = attribute
+            .value
+            .and_then(|value| value.value)
+            .and_then(to_json_value)
+        {
+            attrs.insert(attribute.key, value);
+        }
+    }
+    attrs
+}
+
+/// Extracts the attributes into a map whose keys follow the OpenTelemetry semantic conventions:
+/// the attributes holding a list of key-value pairs are flattened into dotted keys, so that
+/// `{"http": {"method": "GET"}}` becomes `{"http.method": "GET"}`.
+fn flatten_attributes(attributes: Vec<KeyValue>) -> HashMap<String, JsonValue> {
+    let mut attrs = HashMap::new();
+    flatten_attributes_into("", attributes, &mut attrs);
+    attrs
+}
+
+fn flatten_attributes_into(
+    prefix: &str,
+    attributes: Vec<KeyValue>,
+    attrs: &mut HashMap<String, JsonValue>,
+) {
+    for attribute in attributes {
+        let key = format!("{prefix}{}", attribute.key);
+        match attribute.value.and_then(|value| value.value) {
+            Some(OtlpValue::KvlistValue(kvlist)) => {
+                flatten_attributes_into(&format!("{key}."), kvlist.values, attrs);
+            }
+            Some(value) => {
+                if let Some(value) = to_json_value(value) {
+                    attrs.insert(key, value);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn extract_value(attributes: Vec<KeyValue>, key: &str) -> Option<OtlpValue> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.key == key)
+        .and_then(|attribute| attribute.value.clone())
+        .and_then(|value| value.value)
+}
+
+fn to_json_value(value: OtlpValue) -> Option<JsonValue> {
+    match value {
+        OtlpValue::StringValue(value) => Some(JsonValue::String(value)),
+        OtlpValue::BoolValue(value) => Some(JsonValue::Bool(value)),
+        OtlpValue::IntValue(value) => Some(JsonValue::Number(JsonNumber::from(value))),
+        OtlpValue::DoubleValue(value) => JsonNumber::from_f64(value).map(JsonValue::Number),
+        OtlpValue::ArrayValue(array) => {
+            let values = array
+                .values
+                .into_iter()
+                .filter_map(|value| value.value.and_then(to_json_value))
+                .collect();
+            Some(JsonValue::Array(values))
+        }
+        OtlpValue::KvlistValue(kvlist) => {
+            let object: JsonMap<String, JsonValue> =
+                extract_attributes(kvlist.values).into_iter().collect();
+            Some(JsonValue::Object(object))
+        }
+        OtlpValue::BytesValue(bytes) => Some(JsonValue::String(base64::encode(bytes))),
+    }
+}
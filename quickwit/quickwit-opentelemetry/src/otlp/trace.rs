@@ -22,21 +22,30 @@ use std::collections::HashMap;
 use async_trait::async_trait;
 use base64;
 use quickwit_actors::Mailbox;
-use quickwit_ingest_api::IngestApiService;
-use quickwit_proto::ingest_api::{DocBatch, IngestRequest};
+use quickwit_ingest_api::{DocBatchBuilder, IngestApiService};
+use quickwit_proto::ingest_api::IngestRequest;
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceService;
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::{
     ExportTraceServiceRequest, ExportTraceServiceResponse,
 };
 use quickwit_proto::opentelemetry::proto::common::v1::any_value::Value as OtlpValue;
-use quickwit_proto::opentelemetry::proto::common::v1::KeyValue;
 use quickwit_proto::opentelemetry::proto::trace::v1::Status;
 use serde::Serialize;
-use serde_json::{Number as JsonNumber, Value as JsonValue};
-use tracing::{error, warn};
+use serde_json::Value as JsonValue;
+use tracing::error;
 
-const TRACE_INDEX_ID: &str = "otel-trace";
+use super::{extract_value, flatten_attributes};
 
+/// Index receiving the spans, unless the request designates another one with the
+/// [`TRACES_INDEX_HEADER`] metadata header. The index is created with a built-in doc mapping
+/// when the OTLP service is enabled.
+pub const OTEL_TRACES_INDEX_ID: &str = "otel-traces";
+
+/// gRPC metadata header designating the index receiving the spans of a request.
+const TRACES_INDEX_HEADER: &str = "qw-otel-traces-index";
+
+/// Receives the spans exported by OpenTelemetry clients and collectors, and ingests them into an
+/// index through the ingest API, one document per span.
 #[derive(Clone)]
 pub struct OtlpGrpcTraceService {
     ingest_api_service: Mailbox<IngestApiService>,
@@ -61,7 +70,9 @@ struct Span {
     span_name: String,
     span_start_timestamp_nanos: i64,
     span_end_timestamp_nanos: i64,
+    span_duration_millis: u64,
     span_attributes: HashMap<String, JsonValue>,
+    resource_attributes: HashMap<String, JsonValue>,
     span_dropped_attributes_count: u64,
     span_dropped_events_count: u64,
     span_dropped_links_count: u64,
@@ -90,128 +101,118 @@ impl TraceService for OtlpGrpcTraceService {
         &self,
         request: tonic::Request<ExportTraceServiceRequest>,
     ) -> Result<tonic::Response<ExportTraceServiceResponse>, tonic::Status> {
-        let request = request.into_inner();
-        let mut doc_batch = DocBatch {
-            index_id: TRACE_INDEX_ID.to_string(),
-            ..Default::default()
+        let index_id = match request.metadata().get(TRACES_INDEX_HEADER) {
+            Some(header_value) => header_value
+                .to_str()
+                .map_err(|_| {
+                    tonic::Status::invalid_argument(format!(
+                        "Header `{TRACES_INDEX_HEADER}` must be a valid index ID."
+                    ))
+                })?
+                .to_string(),
+            None => OTEL_TRACES_INDEX_ID.to_string(),
         };
-        for resource_span in request.resource_spans {
-            // println!("Resource: {:?}", resource_span.resource);
-            let service_name = match resource_span
-                .resource
-                .and_then(|resource| extract_value(resource.attributes, "service.name"))
-            {
-                Some(OtlpValue::StringValue(service_name)) => Some(service_name),
-                _ => None,
-            };
-            for scope_span in resource_span.scope_spans {
-                // println!("\tScope: {:?}", scope_span.scope);
-                for span in scope_span.spans {
-                    // println!("\t\tSpan: {:?}", span);
-                    let trace_id = base64::encode(span.trace_id);
-                    let span_id = base64::encode(span.span_id);
-                    let parent_span_id = if !span.parent_span_id.is_empty() {
-                        Some(base64::encode(span.parent_span_id))
-                    } else {
-                        None
-                    };
-                    let span_name = if !span.name.is_empty() {
-                        span.name
-                    } else {
-                        "unknown".to_string()
-                    };
-                    let span_start_timestamp_nanos = span.start_time_unix_nano as i64;
-                    let span_end_timestamp_nanos = span.end_time_unix_nano as i64;
-                    let span_attributes = extract_attributes(span.attributes);
-                    // for event in span.events {
-                    //     let event = Event {
-                    //         // trace_id: &trace_id,
-                    //         // service_name: &service_name,
-                    //         // span_id: &span_id,
-                    //         // span_kind: to_span_kind(span.kind),
-                    //         // span_name: &span_name,
-                    //         // span_attributes: &span_attributes,
-                    //         event_timestamp_nanos: event.time_unix_nano as i64,
-                    //         event_name: event.name,
-                    //         event_attributes: extract_attributes(event.attributes),
-                    //         event_dropped_attributes_count: event.dropped_attributes_count as
-                    // u64,     };
-                    // }
-                    let span = Span {
-                        trace_id,
-                        trace_state: span.trace_state,
-                        service_name: service_name.clone(),
-                        span_id,
-                        span_kind: to_span_kind(span.kind),
-                        span_name,
-                        span_start_timestamp_nanos,
-                        span_end_timestamp_nanos,
-                        span_attributes,
-                        span_dropped_attributes_count: span.dropped_attributes_count as u64,
-                        span_dropped_events_count: span.dropped_events_count as u64,
-                        span_dropped_links_count: span.dropped_links_count as u64,
-                        span_status: span.status,
-                        parent_span_id,
-                    };
-                    let span_json = serde_json::to_vec(&span).expect("");
-                    let span_json_len = span_json.len() as u64;
-                    doc_batch.concat_docs.extend_from_slice(&span_json);
-                    doc_batch.doc_lens.push(span_json_len);
-                }
-            }
+        let spans = parse_spans(request.into_inner());
+        if spans.is_empty() {
+            return Ok(tonic::Response::new(ExportTraceServiceResponse::default()));
+        }
+        let mut doc_batch_builder = DocBatchBuilder::new(index_id.clone());
+        for span in spans {
+            let span_json = serde_json::to_vec(&span).expect("Span should be JSON serializable.");
+            doc_batch_builder.add_doc(&span_json);
         }
         let ingest_request = IngestRequest {
-            doc_batches: vec![doc_batch],
+            doc_batches: vec![doc_batch_builder.build()],
         };
-        // TODO: return appropriate tonic status
         if let Err(error) = self.ingest_api_service.ask_for_res(ingest_request).await {
-            error!(error=?error, "Failed to ingest trace");
+            error!(index_id=%index_id, error=?error, "Failed to ingest spans.");
+            return Err(tonic::Status::internal(format!(
+                "Failed to ingest spans into index `{index_id}`: {error}."
+            )));
         }
-        let response = ExportTraceServiceResponse::default();
-        Ok(tonic::Response::new(response))
+        Ok(tonic::Response::new(ExportTraceServiceResponse::default()))
     }
 }
 
-#[derive(Debug, Serialize)]
-struct SpanKind {
-    id: i32,
-    name: &'static str,
-}
+/// Maps the spans of a request to documents. Following the OpenTelemetry semantic conventions,
+/// the span and resource attributes are flattened into dotted keys, such as `http.method`, and
+/// the `service.name` resource attribute is promoted to the `service_name` field.
+fn parse_spans(request: ExportTraceServiceRequest) -> Vec<Span> {
+    let mut spans = Vec::new();
+    for resource_span in request.resource_spans {
+        let resource_attributes = resource_span
+            .resource
+            .map(|resource| resource.attributes)
+            .unwrap_or_default();
+        let service_name = match extract_value(resource_attributes.clone(), "service.name") {
+            Some(OtlpValue::StringValue(service_name)) => Some(service_name),
+            _ => None,
+        };
+        let resource_attributes = flatten_attributes(resource_attributes);
 
-fn extract_attributes(attributes: Vec<KeyValue>) -> HashMap<String, JsonValue> {
-    let mut attrs = HashMap::new();
-    for attribute in attributes {
-        // Filtering out empty attribute values is fine according to the OTel spec: <https://github.com/open-telemetry/opentelemetry-specification/tree/main/specification/common#attribute>
-        if let Some(value) = attribute
-            .value
-            .and_then(|value| value.value)
-            .and_then(to_json_value)
-        {
-            attrs.insert(attribute.key, value);
+        for scope_span in resource_span.scope_spans {
+            for span in scope_span.spans {
+                let trace_id = base64::encode(span.trace_id);
+                let span_id = base64::encode(span.span_id);
+                let parent_span_id = if !span.parent_span_id.is_empty() {
+                    Some(base64::encode(span.parent_span_id))
+                } else {
+                    None
+                };
+                let span_name = if !span.name.is_empty() {
+                    span.name
+                } else {
+                    "unknown".to_string()
+                };
+                let span_start_timestamp_nanos = span.start_time_unix_nano as i64;
+                let span_end_timestamp_nanos = span.end_time_unix_nano as i64;
+                let span_duration_millis = span
+                    .end_time_unix_nano
+                    .saturating_sub(span.start_time_unix_nano)
+                    / 1_000_000;
+                let span_attributes = flatten_attributes(span.attributes);
+                // for event in span.events {
+                //     let event = Event {
+                //         // trace_id: &trace_id,
+                //         // service_name: &service_name,
+                //         // span_id: &span_id,
+                //         // span_kind: to_span_kind(span.kind),
+                //         // span_name: &span_name,
+                //         // span_attributes: &span_attributes,
+                //         event_timestamp_nanos: event.time_unix_nano as i64,
+                //         event_name: event.name,
+                //         event_attributes: extract_attributes(event.attributes),
+                //         event_dropped_attributes_count: event.dropped_attributes_count as
+                // u64,     };
+                // }
+                spans.push(Span {
+                    trace_id,
+                    trace_state: span.trace_state,
+                    service_name: service_name.clone(),
+                    span_id,
+                    span_kind: to_span_kind(span.kind),
+                    span_name,
+                    span_start_timestamp_nanos,
+                    span_end_timestamp_nanos,
+                    span_duration_millis,
+                    span_attributes,
+                    resource_attributes: resource_attributes.clone(),
+                    span_dropped_attributes_count: span.dropped_attributes_count as u64,
+                    span_dropped_events_count: span.dropped_events_count as u64,
+                    span_dropped_links_count: span.dropped_links_count as u64,
+                    span_status: span.status,
+                    parent_span_id,
+                });
+            }
         }
     }
-    attrs
+    spans
 }
 
-fn extract_value(attributes: Vec<KeyValue>, key: &str) -> Option<OtlpValue> {
-    attributes
-        .iter()
-        .find(|attribute| attribute.key == key)
-        .and_then(|attribute| attribute.value.clone())
-        .and_then(|value| value.value)
-}
-
-fn to_json_value(value: OtlpValue) -> Option<JsonValue> {
-    match value {
-        OtlpValue::StringValue(value) => Some(JsonValue::String(value)),
-        OtlpValue::BoolValue(value) => Some(JsonValue::Bool(value)),
-        OtlpValue::IntValue(value) => Some(JsonValue::Number(JsonNumber::from(value))),
-        OtlpValue::DoubleValue(value) => JsonNumber::from_f64(value).map(JsonValue::Number),
-        OtlpValue::ArrayValue(_) | OtlpValue::BytesValue(_) | OtlpValue::KvlistValue(_) => {
-            warn!(value=?value, "Skipping unsupported OTLP value type");
-            None
-        }
-    }
+#[derive(Debug, Serialize)]
+struct SpanKind {
+    id: i32,
+    name: &'static str,
 }
 
 fn to_span_kind(id: i32) -> SpanKind {
@@ -222,7 +223,96 @@ fn to_span_kind(id: i32) -> SpanKind {
         3 => "client",
         4 => "producer",
         5 => "consumer",
-        _ => panic!("Unknown span kind: `{id}`."),
+        _ => "unknown",
     };
     SpanKind { id, name }
 }
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::opentelemetry::proto::common::v1::{AnyValue, KeyValue, KeyValueList};
+    use quickwit_proto::opentelemetry::proto::resource::v1::Resource;
+    use quickwit_proto::opentelemetry::proto::trace::v1::{
+        ResourceSpans, ScopeSpans, Span as OtlpSpan,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn key_value(key: &str, value: OtlpValue) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: Some(AnyValue { value: Some(value) }),
+        }
+    }
+
+    #[test]
+    fn test_parse_spans() {
+        let request = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: Some(Resource {
+                    attributes: vec![
+                        key_value(
+                            "service.name",
+                            OtlpValue::StringValue("payments".to_string()),
+                        ),
+                        key_value(
+                            "host",
+                            OtlpValue::KvlistValue(KeyValueList {
+                                values: vec![key_value(
+                                    "name",
+                                    OtlpValue::StringValue("node-1".to_string()),
+                                )],
+                            }),
+                        ),
+                    ],
+                    dropped_attributes_count: 0,
+                }),
+                scope_spans: vec![ScopeSpans {
+                    scope: None,
+                    spans: vec![OtlpSpan {
+                        trace_id: vec![1; 16],
+                        span_id: vec![2; 8],
+                        name: "charge".to_string(),
+                        kind: 2,
+                        start_time_unix_nano: 1_666_000_000_000_000_000,
+                        end_time_unix_nano: 1_666_000_000_250_000_000,
+                        attributes: vec![
+                            key_value("http.method", OtlpValue::StringValue("POST".to_string())),
+                            key_value(
+                                "http",
+                                OtlpValue::KvlistValue(KeyValueList {
+                                    values: vec![key_value(
+                                        "status_code",
+                                        OtlpValue::IntValue(200),
+                                    )],
+                                }),
+                            ),
+                        ],
+                        ..Default::default()
+                    }],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        };
+        let spans = parse_spans(request);
+        assert_eq!(spans.len(), 1);
+
+        let span = serde_json::to_value(&spans[0]).unwrap();
+        assert_eq!(span["trace_id"], json!("AQEBAQEBAQEBAQEBAQEBAQ=="));
+        assert_eq!(span["span_id"], json!("AgICAgICAgI="));
+        assert_eq!(span["service_name"], json!("payments"));
+        assert_eq!(span["span_kind"], json!({"id": 2, "name": "server"}));
+        assert_eq!(span["span_duration_millis"], json!(250));
+        assert_eq!(
+            span["span_attributes"],
+            json!({"http.method": "POST", "http.status_code": 200})
+        );
+        assert_eq!(
+            span["resource_attributes"],
+            json!({"service.name": "payments", "host.name": "node-1"})
+        );
+        assert_eq!(span["parent_span_id"], JsonValue::Null);
+    }
+}
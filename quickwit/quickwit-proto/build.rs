@@ -26,7 +26,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let protos = find_protos("protos/quickwit");
 
     let mut prost_config = prost_build::Config::default();
-    prost_config.protoc_arg("--experimental_allow_proto3_optional");
+    prost_config
+        .protoc_arg("--experimental_allow_proto3_optional")
+        .bytes(&[
+            "DocBatch.concat_docs",
+            "BulkIngestRequest.payload",
+            "PushDocsRequest.concat_docs",
+        ]);
 
     tonic_build::configure()
         .type_attribute(".", "#[derive(Serialize, Deserialize)]")
@@ -22,6 +22,7 @@
 mod quickwit;
 mod quickwit_ingest_api;
 mod quickwit_metastore_api;
+mod quickwit_push_api;
 
 pub mod ingest_api {
     pub use crate::quickwit_ingest_api::*;
@@ -31,6 +32,10 @@ pub mod metastore_api {
     pub use crate::quickwit_metastore_api::*;
 }
 
+pub mod push_api {
+    pub use crate::quickwit_push_api::*;
+}
+
 pub mod jaeger {
     pub mod api_v2 {
             include!("jaeger.api_v2.rs");
@@ -116,6 +121,10 @@ pub enum ServiceErrorCode {
     MethodNotAllowed,
     UnsupportedMediaType,
     BadRequest,
+    Unauthorized,
+    Forbidden,
+    Unavailable,
+    TooManyRequests,
 }
 
 impl ServiceErrorCode {
@@ -126,6 +135,10 @@ impl ServiceErrorCode {
             ServiceErrorCode::BadRequest => tonic::Code::InvalidArgument,
             ServiceErrorCode::MethodNotAllowed => tonic::Code::InvalidArgument,
             ServiceErrorCode::UnsupportedMediaType => tonic::Code::InvalidArgument,
+            ServiceErrorCode::Unauthorized => tonic::Code::Unauthenticated,
+            ServiceErrorCode::Forbidden => tonic::Code::PermissionDenied,
+            ServiceErrorCode::Unavailable => tonic::Code::Unavailable,
+            ServiceErrorCode::TooManyRequests => tonic::Code::ResourceExhausted,
         }
     }
     pub fn to_http_status_code(self) -> http::StatusCode {
@@ -135,6 +148,10 @@ impl ServiceErrorCode {
             ServiceErrorCode::BadRequest => http::StatusCode::BAD_REQUEST,
             ServiceErrorCode::MethodNotAllowed => http::StatusCode::METHOD_NOT_ALLOWED,
             ServiceErrorCode::UnsupportedMediaType => http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ServiceErrorCode::Unauthorized => http::StatusCode::UNAUTHORIZED,
+            ServiceErrorCode::Forbidden => http::StatusCode::FORBIDDEN,
+            ServiceErrorCode::Unavailable => http::StatusCode::SERVICE_UNAVAILABLE,
+            ServiceErrorCode::TooManyRequests => http::StatusCode::TOO_MANY_REQUESTS,
         }
     }
 }
@@ -169,6 +186,7 @@ impl From<SearchStreamRequest> for SearchRequest {
             query: item.query,
             search_fields: item.search_fields,
             snippet_fields: item.snippet_fields,
+            fetch_fields: Vec::new(),
             start_timestamp: item.start_timestamp,
             end_timestamp: item.end_timestamp,
             max_hits: 0,
@@ -176,6 +194,9 @@ impl From<SearchStreamRequest> for SearchRequest {
             sort_by_field: None,
             sort_order: None,
             aggregation_request: None,
+            debug: false,
+            role: item.role,
+            query_ast: None,
         }
     }
 }
@@ -39,6 +39,20 @@ pub struct SearchRequest {
     /// Fields to extract snippet on
     #[prost(string, repeated, tag="12")]
     pub snippet_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Stored fields returned in the hits. All the stored fields are returned when empty.
+    #[prost(string, repeated, tag="13")]
+    pub fetch_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// When set, the response contains the timings of the search of each split.
+    #[prost(bool, tag="14")]
+    pub debug: bool,
+    /// Role of the user issuing the search. The fields denied to this role in the
+    /// index search settings are hidden from the request.
+    #[prost(string, optional, tag="15")]
+    pub role: ::core::option::Option<::prost::alloc::string::String>,
+    /// JSON serialized query AST. When set, it is searched instead of `query`,
+    /// which must be empty.
+    #[prost(string, optional, tag="16")]
+    pub query_ast: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -59,6 +73,28 @@ pub struct SearchResponse {
     /// Serialized aggregation response
     #[prost(string, optional, tag="5")]
     pub aggregation: ::core::option::Option<::prost::alloc::string::String>,
+    /// Timings of the search of each split, returned in debug mode.
+    #[prost(message, repeated, tag="6")]
+    pub split_timings: ::prost::alloc::vec::Vec<SplitSearchTimings>,
+}
+/// Durations of the steps of the leaf search of a split, in microseconds.
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SplitSearchTimings {
+    #[prost(string, tag="1")]
+    pub split_id: ::prost::alloc::string::String,
+    /// Time spent fetching the split footer, zero if it was cached.
+    #[prost(uint64, tag="2")]
+    pub footer_fetch_micros: u64,
+    /// Time spent opening the split from its hotcache.
+    #[prost(uint64, tag="3")]
+    pub hotcache_load_micros: u64,
+    /// Time spent downloading the data required by the query.
+    #[prost(uint64, tag="4")]
+    pub warmup_micros: u64,
+    /// Time spent executing the query on the warmed-up data.
+    #[prost(uint64, tag="5")]
+    pub collection_micros: u64,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -105,6 +141,10 @@ pub struct SplitIdAndFooterOffsets {
     /// The offset of the end of the footer in split bundle. The footer contains the file bundle metada and the hotcache.
     #[prost(uint64, tag="3")]
     pub split_footer_end: u64,
+    /// URI of the storage holding the split file when it is not stored in the index storage, for
+    /// instance when the split was moved to the warm storage tier of the index. Empty otherwise.
+    #[prost(string, tag="4")]
+    pub storage_uri: ::prost::alloc::string::String,
 }
 /// / Hits returned by a FetchDocRequest.
 /// /
@@ -193,6 +233,9 @@ pub struct LeafSearchResponse {
     /// json serialized intermediate aggregation_result.
     #[prost(string, optional, tag="5")]
     pub intermediate_aggregation_result: ::core::option::Option<::prost::alloc::string::String>,
+    /// Timings of the search of each split, returned in debug mode.
+    #[prost(message, repeated, tag="6")]
+    pub split_timings: ::prost::alloc::vec::Vec<SplitSearchTimings>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -256,6 +299,10 @@ pub struct SearchStreamRequest {
     /// Fields to extract snippet on.
     #[prost(string, repeated, tag="10")]
     pub snippet_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Role of the user issuing the search. The fields denied to this role in the
+    /// index search settings are hidden from the request.
+    #[prost(string, optional, tag="11")]
+    pub role: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -30,12 +30,45 @@ pub struct IngestRequest {
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BulkIngestRequest {
+    #[prost(bytes="bytes", tag="1")]
+    pub payload: ::prost::bytes::Bytes,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct IngestResponse {
     #[prost(uint64, tag="1")]
     pub num_docs_for_processing: u64,
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IngestStreamRequest {
+    #[prost(message, repeated, tag="1")]
+    pub doc_batches: ::prost::alloc::vec::Vec<DocBatch>,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IngestStreamAck {
+    /// / Number of requests of the stream ingested so far.
+    #[prost(uint64, tag="1")]
+    pub num_acked_requests: u64,
+    /// / Number of documents of the stream ingested so far.
+    #[prost(uint64, tag="2")]
+    pub num_docs_for_processing: u64,
+    /// / Write position of each queue targeted by the stream so far.
+    #[prost(message, repeated, tag="3")]
+    pub queue_positions: ::prost::alloc::vec::Vec<QueuePosition>,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueuePosition {
+    #[prost(string, tag="1")]
+    pub queue_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="2")]
+    pub position: u64,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct FetchRequest {
     #[prost(string, tag="1")]
     pub index_id: ::prost::alloc::string::String,
@@ -57,8 +90,8 @@ pub struct FetchResponse {
 pub struct DocBatch {
     #[prost(string, tag="1")]
     pub index_id: ::prost::alloc::string::String,
-    #[prost(bytes="vec", tag="2")]
-    pub concat_docs: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes="bytes", tag="2")]
+    pub concat_docs: ::prost::bytes::Bytes,
     #[prost(uint64, repeated, tag="3")]
     pub doc_lens: ::prost::alloc::vec::Vec<u64>,
 }
@@ -194,6 +227,66 @@ pub mod ingest_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        //// Ingests documents destined for several indexes in their queues.
+        ////
+        //// The payload follows the format of the Elasticsearch bulk API: each
+        //// document is preceded by an `index` or `create` action line naming
+        //// its index. The delivery guarantees are the same as `Ingest`.
+        pub async fn bulk_ingest(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BulkIngestRequest>,
+        ) -> Result<tonic::Response<super::IngestResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_ingest_api.IngestAPIService/BulkIngest",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        //// Ingests a continuous stream of document batches over a single call.
+        ////
+        //// The requests of the stream are ingested in order, one at a time. The
+        //// server acknowledges them every `INGEST_STREAM_ACK_INTERVAL` requests
+        //// and once the client closes the stream. Each ack reports the write
+        //// position of the queues targeted by the stream so far: the documents of
+        //// the acknowledged requests are stored in their queues at or before these
+        //// positions.
+        ////
+        //// Upon error, the error is sent in lieu of the next ack and the stream is
+        //// closed. The client should resend the requests following the last
+        //// acknowledged request to get at least once delivery.
+        pub async fn ingest_stream(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<
+                Message = super::IngestStreamRequest,
+            >,
+        ) -> Result<
+            tonic::Response<tonic::codec::Streaming<super::IngestStreamAck>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_ingest_api.IngestAPIService/IngestStream",
+            );
+            self.inner.streaming(request.into_streaming_request(), path, codec).await
+        }
         //// Fetches record from a given queue.
         ////
         //// Records are returned in order.
@@ -267,6 +360,37 @@ pub mod ingest_api_service_server {
             &self,
             request: tonic::Request<super::IngestRequest>,
         ) -> Result<tonic::Response<super::IngestResponse>, tonic::Status>;
+        //// Ingests documents destined for several indexes in their queues.
+        ////
+        //// The payload follows the format of the Elasticsearch bulk API: each
+        //// document is preceded by an `index` or `create` action line naming
+        //// its index. The delivery guarantees are the same as `Ingest`.
+        async fn bulk_ingest(
+            &self,
+            request: tonic::Request<super::BulkIngestRequest>,
+        ) -> Result<tonic::Response<super::IngestResponse>, tonic::Status>;
+        ///Server streaming response type for the IngestStream method.
+        type IngestStreamStream: futures_core::Stream<
+                Item = Result<super::IngestStreamAck, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        //// Ingests a continuous stream of document batches over a single call.
+        ////
+        //// The requests of the stream are ingested in order, one at a time. The
+        //// server acknowledges them every `INGEST_STREAM_ACK_INTERVAL` requests
+        //// and once the client closes the stream. Each ack reports the write
+        //// position of the queues targeted by the stream so far: the documents of
+        //// the acknowledged requests are stored in their queues at or before these
+        //// positions.
+        ////
+        //// Upon error, the error is sent in lieu of the next ack and the stream is
+        //// closed. The client should resend the requests following the last
+        //// acknowledged request to get at least once delivery.
+        async fn ingest_stream(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::IngestStreamRequest>>,
+        ) -> Result<tonic::Response<Self::IngestStreamStream>, tonic::Status>;
         //// Fetches record from a given queue.
         ////
         //// Records are returned in order.
@@ -388,6 +512,89 @@ pub mod ingest_api_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/quickwit_ingest_api.IngestAPIService/BulkIngest" => {
+                    #[allow(non_camel_case_types)]
+                    struct BulkIngestSvc<T: IngestApiService>(pub Arc<T>);
+                    impl<
+                        T: IngestApiService,
+                    > tonic::server::UnaryService<super::BulkIngestRequest>
+                    for BulkIngestSvc<T> {
+                        type Response = super::IngestResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BulkIngestRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).bulk_ingest(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = BulkIngestSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_ingest_api.IngestAPIService/IngestStream" => {
+                    #[allow(non_camel_case_types)]
+                    struct IngestStreamSvc<T: IngestApiService>(pub Arc<T>);
+                    impl<
+                        T: IngestApiService,
+                    > tonic::server::StreamingService<super::IngestStreamRequest>
+                    for IngestStreamSvc<T> {
+                        type Response = super::IngestStreamAck;
+                        type ResponseStream = T::IngestStreamStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::IngestStreamRequest>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).ingest_stream(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = IngestStreamSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/quickwit_ingest_api.IngestAPIService/Fetch" => {
                     #[allow(non_camel_case_types)]
                     struct FetchSvc<T: IngestApiService>(pub Arc<T>);
@@ -108,6 +108,26 @@ pub struct DeleteSplitsRequest {
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RecordSplitSearchesRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub num_searches_per_split_serialized_json: ::prost::alloc::string::String,
+    #[prost(int64, tag="3")]
+    pub search_timestamp: i64,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateSplitStorageTierRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub split_id: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub storage_tier_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SplitResponse {
 }
 #[derive(Serialize, Deserialize)]
@@ -120,6 +140,14 @@ pub struct AddSourceRequest {
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateSourceRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub source_config_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ToggleSourceRequest {
     #[prost(string, tag="1")]
     pub index_id: ::prost::alloc::string::String,
@@ -146,8 +174,66 @@ pub struct ResetSourceCheckpointRequest {
 }
 #[derive(Serialize, Deserialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateSourceCheckpointRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub source_id: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub source_checkpoint_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SourceResponse {
 }
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AcquirePublishTokenRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub source_id: ::prost::alloc::string::String,
+    #[prost(string, tag="3")]
+    pub partition_set_id: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AcquirePublishTokenResponse {
+    #[prost(uint64, tag="1")]
+    pub publish_token: u64,
+}
+// /
+// / Stored queries.
+// /
+
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PutStoredQueryRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub stored_query_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteStoredQueryRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub stored_query_name: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateAlertStateRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub alert_state_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StoredQueryResponse {
+}
 // /
 // / Delete tasks.
 // /
@@ -506,6 +592,46 @@ pub mod metastore_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Records the searches received by splits.
+        pub async fn record_split_searches(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RecordSplitSearchesRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/record_split_searches",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Updates the storage tier of a split.
+        pub async fn update_split_storage_tier(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateSplitStorageTierRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/update_split_storage_tier",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Adds source.
         pub async fn add_source(
             &mut self,
@@ -526,6 +652,26 @@ pub mod metastore_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Updates source.
+        pub async fn update_source(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateSourceRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/update_source",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Toggles source.
         pub async fn toggle_source(
             &mut self,
@@ -586,6 +732,109 @@ pub mod metastore_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Updates source checkpoint.
+        pub async fn update_source_checkpoint(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateSourceCheckpointRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/update_source_checkpoint",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Acquires a new publish token for a partition set of a source.
+        pub async fn acquire_publish_token(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AcquirePublishTokenRequest>,
+        ) -> Result<
+            tonic::Response<super::AcquirePublishTokenResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/acquire_publish_token",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Adds or replaces a stored query.
+        pub async fn put_stored_query(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PutStoredQueryRequest>,
+        ) -> Result<tonic::Response<super::StoredQueryResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/put_stored_query",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Deletes a stored query.
+        pub async fn delete_stored_query(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteStoredQueryRequest>,
+        ) -> Result<tonic::Response<super::StoredQueryResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/delete_stored_query",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Updates the evaluation state of the alert of a stored query.
+        pub async fn update_alert_state(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateAlertStateRequest>,
+        ) -> Result<tonic::Response<super::StoredQueryResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/update_alert_state",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Gets last opstamp for a given `index_id`.
         pub async fn last_delete_opstamp(
             &mut self,
@@ -748,11 +997,26 @@ pub mod metastore_api_service_server {
             &self,
             request: tonic::Request<super::DeleteSplitsRequest>,
         ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Records the searches received by splits.
+        async fn record_split_searches(
+            &self,
+            request: tonic::Request<super::RecordSplitSearchesRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Updates the storage tier of a split.
+        async fn update_split_storage_tier(
+            &self,
+            request: tonic::Request<super::UpdateSplitStorageTierRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
         /// Adds source.
         async fn add_source(
             &self,
             request: tonic::Request<super::AddSourceRequest>,
         ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Updates source.
+        async fn update_source(
+            &self,
+            request: tonic::Request<super::UpdateSourceRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
         /// Toggles source.
         async fn toggle_source(
             &self,
@@ -768,6 +1032,34 @@ pub mod metastore_api_service_server {
             &self,
             request: tonic::Request<super::ResetSourceCheckpointRequest>,
         ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Updates source checkpoint.
+        async fn update_source_checkpoint(
+            &self,
+            request: tonic::Request<super::UpdateSourceCheckpointRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Acquires a new publish token for a partition set of a source.
+        async fn acquire_publish_token(
+            &self,
+            request: tonic::Request<super::AcquirePublishTokenRequest>,
+        ) -> Result<
+            tonic::Response<super::AcquirePublishTokenResponse>,
+            tonic::Status,
+        >;
+        /// Adds or replaces a stored query.
+        async fn put_stored_query(
+            &self,
+            request: tonic::Request<super::PutStoredQueryRequest>,
+        ) -> Result<tonic::Response<super::StoredQueryResponse>, tonic::Status>;
+        /// Deletes a stored query.
+        async fn delete_stored_query(
+            &self,
+            request: tonic::Request<super::DeleteStoredQueryRequest>,
+        ) -> Result<tonic::Response<super::StoredQueryResponse>, tonic::Status>;
+        /// Updates the evaluation state of the alert of a stored query.
+        async fn update_alert_state(
+            &self,
+            request: tonic::Request<super::UpdateAlertStateRequest>,
+        ) -> Result<tonic::Response<super::StoredQueryResponse>, tonic::Status>;
         /// Gets last opstamp for a given `index_id`.
         async fn last_delete_opstamp(
             &self,
@@ -1254,6 +1546,86 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/quickwit_metastore_api.MetastoreApiService/record_split_searches" => {
+                    #[allow(non_camel_case_types)]
+                    struct record_split_searchesSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::RecordSplitSearchesRequest>
+                    for record_split_searchesSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RecordSplitSearchesRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).record_split_searches(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = record_split_searchesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/update_split_storage_tier" => {
+                    #[allow(non_camel_case_types)]
+                    struct update_split_storage_tierSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::UpdateSplitStorageTierRequest>
+                    for update_split_storage_tierSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateSplitStorageTierRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).update_split_storage_tier(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = update_split_storage_tierSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/quickwit_metastore_api.MetastoreApiService/add_source" => {
                     #[allow(non_camel_case_types)]
                     struct add_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
@@ -1292,6 +1664,44 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/quickwit_metastore_api.MetastoreApiService/update_source" => {
+                    #[allow(non_camel_case_types)]
+                    struct update_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::UpdateSourceRequest>
+                    for update_sourceSvc<T> {
+                        type Response = super::SourceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateSourceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).update_source(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = update_sourceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/quickwit_metastore_api.MetastoreApiService/toggle_source" => {
                     #[allow(non_camel_case_types)]
                     struct toggle_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
@@ -1414,6 +1824,208 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/quickwit_metastore_api.MetastoreApiService/update_source_checkpoint" => {
+                    #[allow(non_camel_case_types)]
+                    struct update_source_checkpointSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::UpdateSourceCheckpointRequest>
+                    for update_source_checkpointSvc<T> {
+                        type Response = super::SourceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateSourceCheckpointRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).update_source_checkpoint(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = update_source_checkpointSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/acquire_publish_token" => {
+                    #[allow(non_camel_case_types)]
+                    struct acquire_publish_tokenSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::AcquirePublishTokenRequest>
+                    for acquire_publish_tokenSvc<T> {
+                        type Response = super::AcquirePublishTokenResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AcquirePublishTokenRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).acquire_publish_token(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = acquire_publish_tokenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/put_stored_query" => {
+                    #[allow(non_camel_case_types)]
+                    struct put_stored_querySvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::PutStoredQueryRequest>
+                    for put_stored_querySvc<T> {
+                        type Response = super::StoredQueryResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PutStoredQueryRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).put_stored_query(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = put_stored_querySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/delete_stored_query" => {
+                    #[allow(non_camel_case_types)]
+                    struct delete_stored_querySvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::DeleteStoredQueryRequest>
+                    for delete_stored_querySvc<T> {
+                        type Response = super::StoredQueryResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteStoredQueryRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).delete_stored_query(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = delete_stored_querySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/update_alert_state" => {
+                    #[allow(non_camel_case_types)]
+                    struct update_alert_stateSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::UpdateAlertStateRequest>
+                    for update_alert_stateSvc<T> {
+                        type Response = super::StoredQueryResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateAlertStateRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).update_alert_state(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = update_alert_stateSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/quickwit_metastore_api.MetastoreApiService/last_delete_opstamp" => {
                     #[allow(non_camel_case_types)]
                     struct last_delete_opstampSvc<T: MetastoreApiService>(pub Arc<T>);
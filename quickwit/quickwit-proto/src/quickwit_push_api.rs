@@ -0,0 +1,296 @@
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PushDocsRequest {
+    #[prost(string, tag="1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag="2")]
+    pub source_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag="3")]
+    pub pipeline_ord: u64,
+    #[prost(bytes="bytes", tag="4")]
+    pub concat_docs: ::prost::bytes::Bytes,
+    #[prost(uint64, repeated, tag="5")]
+    pub doc_lens: ::prost::alloc::vec::Vec<u64>,
+}
+#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PushDocsResponse {
+    /// / Number of documents of the acknowledged request.
+    #[prost(uint64, tag="1")]
+    pub num_docs: u64,
+    /// / Position of the pipeline after the documents of the acknowledged request.
+    #[prost(uint64, tag="2")]
+    pub position: u64,
+}
+/// Generated client implementations.
+pub mod push_api_service_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    #[derive(Debug, Clone)]
+    pub struct PushApiServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl PushApiServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: std::convert::TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> PushApiServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> PushApiServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            PushApiServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        //// Streams documents directly into a running indexing pipeline of a `push` source.
+        ////
+        //// The pipeline is designated by the first request of the stream. Subsequent
+        //// requests must target the same pipeline.
+        ////
+        //// Each request is acknowledged by exactly one response, sent once its documents
+        //// have been handed over to the indexer. The server stops reading the stream while
+        //// the indexer is busy, so that producers are slowed down to the indexing throughput.
+        ////
+        //// Documents acknowledged but not yet committed in a split are lost if the indexer
+        //// restarts: the client should resend them to get at least once delivery.
+        pub async fn push_docs(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::PushDocsRequest>,
+        ) -> Result<
+            tonic::Response<tonic::codec::Streaming<super::PushDocsResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_push_api.PushAPIService/PushDocs",
+            );
+            self.inner.streaming(request.into_streaming_request(), path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod push_api_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    ///Generated trait containing gRPC methods that should be implemented for use with PushApiServiceServer.
+    #[async_trait]
+    pub trait PushApiService: Send + Sync + 'static {
+        ///Server streaming response type for the PushDocs method.
+        type PushDocsStream: futures_core::Stream<
+                Item = Result<super::PushDocsResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        //// Streams documents directly into a running indexing pipeline of a `push` source.
+        ////
+        //// The pipeline is designated by the first request of the stream. Subsequent
+        //// requests must target the same pipeline.
+        ////
+        //// Each request is acknowledged by exactly one response, sent once its documents
+        //// have been handed over to the indexer. The server stops reading the stream while
+        //// the indexer is busy, so that producers are slowed down to the indexing throughput.
+        ////
+        //// Documents acknowledged but not yet committed in a split are lost if the indexer
+        //// restarts: the client should resend them to get at least once delivery.
+        async fn push_docs(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::PushDocsRequest>>,
+        ) -> Result<tonic::Response<Self::PushDocsStream>, tonic::Status>;
+    }
+    #[derive(Debug)]
+    pub struct PushApiServiceServer<T: PushApiService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: PushApiService> PushApiServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for PushApiServiceServer<T>
+    where
+        T: PushApiService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/quickwit_push_api.PushAPIService/PushDocs" => {
+                    #[allow(non_camel_case_types)]
+                    struct PushDocsSvc<T: PushApiService>(pub Arc<T>);
+                    impl<
+                        T: PushApiService,
+                    > tonic::server::StreamingService<super::PushDocsRequest>
+                    for PushDocsSvc<T> {
+                        type Response = super::PushDocsResponse;
+                        type ResponseStream = T::PushDocsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::PushDocsRequest>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).push_docs(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PushDocsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: PushApiService> Clone for PushApiServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+            }
+        }
+    }
+    impl<T: PushApiService> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: PushApiService> tonic::server::NamedService for PushApiServiceServer<T> {
+        const NAME: &'static str = "quickwit_push_api.PushAPIService";
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+
+use crate::collector::merge_intermediate_aggregation_results;
+use crate::SearchError;
+
+/// Temporary file holding the intermediate aggregation results of the splits searched by a leaf
+/// search, so that they do not have to be kept in memory until all the splits are searched.
+///
+/// The results are written as length-prefixed JSON records. The file is anonymous and removed by
+/// the OS once dropped.
+pub(crate) struct AggregationSpillFile {
+    writer: BufWriter<File>,
+    num_results: usize,
+}
+
+impl AggregationSpillFile {
+    /// Creates a spill file in `spill_dir`, creating the directory if necessary.
+    pub fn create(spill_dir: &Path) -> io::Result<Self> {
+        std::fs::create_dir_all(spill_dir)?;
+        let file = tempfile::tempfile_in(spill_dir)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            num_results: 0,
+        })
+    }
+
+    /// Appends the serialized intermediate aggregation result of a split to the file.
+    pub fn spill(&mut self, intermediate_aggregation_result: &str) -> io::Result<()> {
+        let num_bytes = intermediate_aggregation_result.len() as u64;
+        self.writer.write_all(&num_bytes.to_le_bytes())?;
+        self.writer
+            .write_all(intermediate_aggregation_result.as_bytes())?;
+        self.num_results += 1;
+        crate::SEARCH_METRICS
+            .aggregation_spilled_num_bytes_total
+            .inc_by(num_bytes + 8);
+        Ok(())
+    }
+
+    /// Reads the spilled results back one at a time and merges them as they are read, so that
+    /// only the merged result and a single split result are held in memory at any point.
+    ///
+    /// Fails with [`SearchError::MemoryBudgetExceeded`] when the merged result, measured by the
+    /// size of its serialization, outgrows `max_merged_num_bytes`.
+    pub fn merge(self, max_merged_num_bytes: usize) -> crate::Result<Option<String>> {
+        let num_results = self.num_results;
+        let mut file = self.writer.into_inner().map_err(|error| {
+            SearchError::InternalError(format!(
+                "Failed to flush aggregation spill file: {}",
+                error.error()
+            ))
+        })?;
+        file.seek(SeekFrom::Start(0)).map_err(spill_io_error)?;
+        let mut reader = BufReader::new(file);
+        let mut merged_result_opt: Option<IntermediateAggregationResults> = None;
+        let mut buffer = Vec::new();
+        // Merging a result grows the merged result by about the size of the result at most, so
+        // the merged result is only measured once the results merged since it was last measured
+        // may have made it outgrow the limit.
+        let mut merged_num_bytes_upper_bound = 0;
+        for _ in 0..num_results {
+            let mut num_bytes = [0u8; 8];
+            reader.read_exact(&mut num_bytes).map_err(spill_io_error)?;
+            buffer.resize(u64::from_le_bytes(num_bytes) as usize, 0);
+            reader.read_exact(&mut buffer).map_err(spill_io_error)?;
+            merged_num_bytes_upper_bound += buffer.len();
+            let result: IntermediateAggregationResults = serde_json::from_slice(&buffer)?;
+            merge_intermediate_aggregation_results(&mut merged_result_opt, result);
+
+            if merged_num_bytes_upper_bound > max_merged_num_bytes {
+                let merged_num_bytes = merged_result_opt
+                    .as_ref()
+                    .map(serde_json::to_vec)
+                    .transpose()?
+                    .map_or(0, |merged_result_json| merged_result_json.len());
+                if merged_num_bytes > max_merged_num_bytes {
+                    return Err(SearchError::MemoryBudgetExceeded {
+                        requested_num_bytes: merged_num_bytes,
+                        available_num_bytes: max_merged_num_bytes,
+                        budget_num_bytes: max_merged_num_bytes,
+                    });
+                }
+                merged_num_bytes_upper_bound = merged_num_bytes;
+            }
+        }
+        let merged_result = merged_result_opt
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        Ok(merged_result)
+    }
+}
+
+fn spill_io_error(error: io::Error) -> SearchError {
+    SearchError::InternalError(format!("Failed to read aggregation spill file: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregation_spill_file_without_results() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spill_dir = temp_dir.path().join("aggregation-spill");
+        let spill_file = AggregationSpillFile::create(&spill_dir).unwrap();
+        assert!(spill_dir.is_dir());
+        assert_eq!(spill_file.num_results, 0);
+        assert!(spill_file.merge(0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_aggregation_spill_file_invalid_result() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut spill_file = AggregationSpillFile::create(temp_dir.path()).unwrap();
+        spill_file.spill("not an aggregation result").unwrap();
+        assert_eq!(spill_file.num_results, 1);
+        assert!(spill_file.merge(usize::MAX).is_err());
+    }
+}
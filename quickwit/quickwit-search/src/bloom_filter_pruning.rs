@@ -0,0 +1,216 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use futures::{stream, StreamExt};
+use lru::LruCache;
+use once_cell::sync::Lazy;
+use quickwit_doc_mapper::bloom_filter::SplitBloomFilters;
+use quickwit_doc_mapper::tag_pruning::TagFilterAst;
+use quickwit_doc_mapper::DocMapper;
+use quickwit_metastore::SplitMetadata;
+use quickwit_storage::{Storage, StorageErrorKind};
+use tracing::{debug, warn};
+
+use crate::SEARCH_METRICS;
+
+/// Maximum size of the bloom filters kept in memory by the search planner.
+const BLOOM_FILTER_CACHE_CAPACITY_IN_BYTES: usize = 500_000_000;
+
+/// Maximum number of bloom filter files fetched concurrently.
+const MAX_CONCURRENT_BLOOM_FILTER_FETCHES: usize = 100;
+
+static BLOOM_FILTER_CACHE: Lazy<BloomFilterCache> =
+    Lazy::new(|| BloomFilterCache::with_capacity_in_bytes(BLOOM_FILTER_CACHE_CAPACITY_IN_BYTES));
+
+/// Caches the deserialized bloom filters of splits. Splits are immutable, so entries never need
+/// to be invalidated.
+struct BloomFilterCache {
+    capacity_in_bytes: usize,
+    inner: Mutex<BloomFilterCacheInner>,
+}
+
+struct BloomFilterCacheInner {
+    num_bytes: usize,
+    lru_cache: LruCache<String, Arc<SplitBloomFilters>>,
+}
+
+impl BloomFilterCache {
+    fn with_capacity_in_bytes(capacity_in_bytes: usize) -> Self {
+        BloomFilterCache {
+            capacity_in_bytes,
+            inner: Mutex::new(BloomFilterCacheInner {
+                num_bytes: 0,
+                lru_cache: LruCache::unbounded(),
+            }),
+        }
+    }
+
+    fn get(&self, split_id: &str) -> Option<Arc<SplitBloomFilters>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.lru_cache.get(split_id).cloned()
+    }
+
+    fn put(&self, split_id: String, bloom_filters: Arc<SplitBloomFilters>) {
+        let num_bytes = bloom_filters.num_bytes();
+        if num_bytes > self.capacity_in_bytes {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(previous_bloom_filters) = inner.lru_cache.put(split_id, bloom_filters) {
+            inner.num_bytes -= previous_bloom_filters.num_bytes();
+        }
+        inner.num_bytes += num_bytes;
+        while inner.num_bytes > self.capacity_in_bytes {
+            if let Some((_, evicted_bloom_filters)) = inner.lru_cache.pop_lru() {
+                inner.num_bytes -= evicted_bloom_filters.num_bytes();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+/// Removes the splits that, according to their bloom filters, definitely contain no document
+/// matching the query, represented by the tag filter extracted from it.
+///
+/// This only kicks in when the query filters on a field listed in the doc mapping
+/// `bloom_filter_fields`. Splits whose bloom filters cannot be fetched are kept.
+pub(crate) async fn prune_splits_with_bloom_filters(
+    tag_filter_ast_opt: Option<TagFilterAst>,
+    doc_mapper: &dyn DocMapper,
+    index_storage: Arc<dyn Storage>,
+    split_metadatas: Vec<SplitMetadata>,
+) -> crate::Result<Vec<SplitMetadata>> {
+    let bloom_filter_field_names = doc_mapper.bloom_filter_field_names();
+    if bloom_filter_field_names.is_empty() {
+        return Ok(split_metadatas);
+    }
+    let tag_filter_ast = match tag_filter_ast_opt {
+        Some(tag_filter_ast)
+            if references_any_field(&tag_filter_ast, &bloom_filter_field_names) =>
+        {
+            tag_filter_ast
+        }
+        _ => return Ok(split_metadatas),
+    };
+    let num_splits = split_metadatas.len();
+    let relevant_split_metadatas: Vec<SplitMetadata> = stream::iter(split_metadatas)
+        .map(|split_metadata| {
+            let index_storage = index_storage.clone();
+            async move {
+                let bloom_filters =
+                    get_split_bloom_filters(split_metadata.split_id(), &*index_storage).await;
+                (split_metadata, bloom_filters)
+            }
+        })
+        .buffered(MAX_CONCURRENT_BLOOM_FILTER_FETCHES)
+        .filter_map(|(split_metadata, bloom_filters_opt)| {
+            let might_match = bloom_filters_opt
+                .map(|bloom_filters| bloom_filters.might_match(&tag_filter_ast))
+                .unwrap_or(true);
+            async move { might_match.then_some(split_metadata) }
+        })
+        .collect()
+        .await;
+    let num_pruned_splits = num_splits - relevant_split_metadatas.len();
+    debug!(
+        num_splits = num_splits,
+        num_pruned_splits = num_pruned_splits,
+        "prune-splits-with-bloom-filters"
+    );
+    SEARCH_METRICS
+        .splits_pruned_by_bloom_filters_total
+        .inc_by(num_pruned_splits as u64);
+    Ok(relevant_split_metadatas)
+}
+
+/// Returns true if the tag filter tests the value of one of the given fields.
+fn references_any_field(tag_filter_ast: &TagFilterAst, field_names: &BTreeSet<String>) -> bool {
+    match tag_filter_ast {
+        TagFilterAst::And(children) | TagFilterAst::Or(children) => children
+            .iter()
+            .any(|child| references_any_field(child, field_names)),
+        TagFilterAst::Tag { tag, .. } => tag
+            .split_once(':')
+            .map(|(field_name, _)| field_names.contains(field_name))
+            .unwrap_or(false),
+    }
+}
+
+/// Returns the bloom filters of a split, from the cache or from the storage, or `None` if they
+/// could not be fetched. Splits created before bloom filters were configured have no bloom
+/// filters file, which is cached as an empty set of bloom filters.
+async fn get_split_bloom_filters(
+    split_id: &str,
+    index_storage: &dyn Storage,
+) -> Option<Arc<SplitBloomFilters>> {
+    if let Some(bloom_filters) = BLOOM_FILTER_CACHE.get(split_id) {
+        return Some(bloom_filters);
+    }
+    let bloom_filter_file = quickwit_common::bloom_filter_file(split_id);
+    let bloom_filters = match index_storage.get_all(Path::new(&bloom_filter_file)).await {
+        Ok(bytes) => match SplitBloomFilters::from_bytes(bytes.as_slice()) {
+            Ok(bloom_filters) => bloom_filters,
+            Err(error) => {
+                warn!(error = ?error, split_id = split_id, "Failed to deserialize split bloom filters.");
+                return None;
+            }
+        },
+        Err(error) if error.kind() == StorageErrorKind::DoesNotExist => {
+            SplitBloomFilters::default()
+        }
+        Err(error) => {
+            warn!(error = ?error, split_id = split_id, "Failed to fetch split bloom filters.");
+            return None;
+        }
+    };
+    let bloom_filters = Arc::new(bloom_filters);
+    BLOOM_FILTER_CACHE.put(split_id.to_string(), bloom_filters.clone());
+    Some(bloom_filters)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_doc_mapper::bloom_filter::BloomFilter;
+
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter_cache_evicts_least_recently_used_entries() {
+        let mut bloom_filters = SplitBloomFilters::default();
+        bloom_filters.insert(
+            "trace_id".to_string(),
+            BloomFilter::with_num_values(1, 0.01),
+        );
+        let bloom_filters = Arc::new(bloom_filters);
+        let num_bytes = bloom_filters.num_bytes();
+        let cache = BloomFilterCache::with_capacity_in_bytes(2 * num_bytes);
+        cache.put("split-1".to_string(), bloom_filters.clone());
+        cache.put("split-2".to_string(), bloom_filters.clone());
+        assert!(cache.get("split-1").is_some());
+        cache.put("split-3".to_string(), bloom_filters);
+        assert!(cache.get("split-1").is_some());
+        assert!(cache.get("split-2").is_none());
+        assert!(cache.get("split-3").is_some());
+    }
+}
@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use quickwit_proto::{
     FetchDocsRequest, FetchDocsResponse, LeafSearchRequest, LeafSearchResponse,
@@ -25,13 +26,14 @@ use quickwit_proto::{
 use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::watch;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::debug;
 
 use crate::retry::search::LeafSearchRetryPolicy;
 use crate::retry::search_stream::{LeafSearchStreamRetryPolicy, SuccessfullSplitIds};
 use crate::retry::{retry_client, DefaultRetryPolicy, RetryPolicy};
-use crate::{SearchClientPool, SearchError, SearchServiceClient};
+use crate::{SearchClientPool, SearchError, SearchServiceClient, SEARCH_METRICS};
 
 /// Client that executes placed requests (Request, `SearchServiceClient`) and provides
 /// retry policies for `FetchDocsRequest`, `LeafSearchRequest` and `LeafSearchStreamRequest`
@@ -39,12 +41,26 @@ use crate::{SearchClientPool, SearchError, SearchServiceClient};
 #[derive(Clone)]
 pub struct ClusterClient {
     client_pool: SearchClientPool,
+    leaf_search_hedging_percentage_opt: Option<u8>,
 }
 
 impl ClusterClient {
     /// Instantiates [`ClusterClient`].
     pub fn new(client_pool: SearchClientPool) -> Self {
-        Self { client_pool }
+        Self {
+            client_pool,
+            leaf_search_hedging_percentage_opt: None,
+        }
+    }
+
+    /// Hedges the leaf search requests of the given percentage of the splits of a search. See
+    /// [`ClusterClient::multi_leaf_search`].
+    pub fn with_leaf_search_hedging(
+        mut self,
+        leaf_search_hedging_percentage_opt: Option<u8>,
+    ) -> Self {
+        self.leaf_search_hedging_percentage_opt = leaf_search_hedging_percentage_opt;
+        self
     }
 
     /// Fetches docs with retry on another node client.
@@ -96,6 +112,102 @@ impl ClusterClient {
         response_res
     }
 
+    /// Executes the leaf search requests placed on their clients and returns the responses in the
+    /// same order.
+    ///
+    /// When hedging is enabled, the requests still running once all the splits but the hedged
+    /// percentage have been searched are also sent to another node, and the first successful
+    /// response wins. This bounds the tail latency of a search when one node degrades.
+    pub async fn multi_leaf_search(
+        &self,
+        placed_requests: Vec<(LeafSearchRequest, SearchServiceClient)>,
+    ) -> crate::Result<Vec<LeafSearchResponse>> {
+        let num_splits: usize = placed_requests
+            .iter()
+            .map(|(request, _)| request.split_offsets.len())
+            .sum();
+        let hedging_threshold = match self.leaf_search_hedging_percentage_opt {
+            Some(hedging_percentage) => hedging_threshold(num_splits, hedging_percentage),
+            // The threshold is never reached.
+            None => num_splits + 1,
+        };
+        let (hedging_tx, hedging_rx) = watch::channel(hedging_threshold == 0);
+        let num_requests = placed_requests.len();
+        let mut leaf_search_futures: FuturesUnordered<_> = placed_requests
+            .into_iter()
+            .enumerate()
+            .map(|(request_ord, (request, client))| {
+                let num_request_splits = request.split_offsets.len();
+                let hedging_rx = hedging_rx.clone();
+                async move {
+                    let response_res = self.hedged_leaf_search(request, client, hedging_rx).await;
+                    (request_ord, num_request_splits, response_res)
+                }
+            })
+            .collect();
+        let mut responses: Vec<Option<LeafSearchResponse>> = vec![None; num_requests];
+        let mut num_searched_splits = 0;
+
+        while let Some((request_ord, num_request_splits, response_res)) =
+            leaf_search_futures.next().await
+        {
+            responses[request_ord] = Some(response_res?);
+            num_searched_splits += num_request_splits;
+
+            if num_searched_splits >= hedging_threshold {
+                let _ = hedging_tx.send(true);
+            }
+        }
+        Ok(responses.into_iter().flatten().collect())
+    }
+
+    /// Leaf search with retry on another node client, also sent to another node once
+    /// `hedging_rx` turns true if the first request is still running.
+    async fn hedged_leaf_search(
+        &self,
+        request: LeafSearchRequest,
+        client: SearchServiceClient,
+        mut hedging_rx: watch::Receiver<bool>,
+    ) -> crate::Result<LeafSearchResponse> {
+        let hedged_client_opt = request.split_offsets.first().and_then(|split_offsets| {
+            retry_client(&self.client_pool, &client, &split_offsets.split_id).ok()
+        });
+        let leaf_search_future = self.leaf_search(request.clone(), client);
+        tokio::pin!(leaf_search_future);
+
+        let hedged_client = match hedged_client_opt {
+            Some(hedged_client) => hedged_client,
+            // There is no other node to hedge the request on.
+            None => return leaf_search_future.await,
+        };
+        tokio::select! {
+            response_res = &mut leaf_search_future => return response_res,
+            _ = wait_for_hedging(&mut hedging_rx) => {}
+        }
+        debug!(
+            "Leaf search request is slow. Hedge request {:?} with {:?}",
+            request, hedged_client
+        );
+        SEARCH_METRICS.leaf_search_hedged_requests_total.inc();
+        let hedged_leaf_search_future = self.leaf_search(request, hedged_client);
+        tokio::pin!(hedged_leaf_search_future);
+
+        tokio::select! {
+            response_res = &mut leaf_search_future => {
+                if response_res.is_ok() {
+                    return response_res;
+                }
+                hedged_leaf_search_future.await
+            }
+            response_res = &mut hedged_leaf_search_future => {
+                if response_res.is_ok() {
+                    return response_res;
+                }
+                leaf_search_future.await
+            }
+        }
+    }
+
     /// Leaf search stream with retry on another node client.
     pub async fn leaf_search_stream(
         &self,
@@ -146,6 +258,21 @@ impl ClusterClient {
     }
 }
 
+// Returns the number of splits to search before hedging the requests still running.
+fn hedging_threshold(num_splits: usize, hedging_percentage: u8) -> usize {
+    let hedging_percentage = hedging_percentage.min(100) as usize;
+    num_splits * (100 - hedging_percentage) / 100
+}
+
+// Waits until hedging is triggered, or forever if it never is.
+async fn wait_for_hedging(hedging_rx: &mut watch::Receiver<bool>) {
+    while !*hedging_rx.borrow() {
+        if hedging_rx.changed().await.is_err() {
+            futures::future::pending::<()>().await;
+        }
+    }
+}
+
 // Merge initial leaf search results with results obtained from a retry.
 fn merge_leaf_search_results(
     initial_response_result: crate::Result<LeafSearchResponse>,
@@ -156,6 +283,9 @@ fn merge_leaf_search_results(
             initial_response
                 .partial_hits
                 .append(&mut retry_response.partial_hits);
+            initial_response
+                .split_timings
+                .append(&mut retry_response.split_timings);
             let intermediate_aggregation_result = initial_response
                 .intermediate_aggregation_result
                 .map(|res1_str| {
@@ -179,6 +309,7 @@ fn merge_leaf_search_results(
                     + retry_response.num_attempted_splits,
                 failed_splits: retry_response.failed_splits,
                 partial_hits: initial_response.partial_hits,
+                split_timings: initial_response.split_timings,
             };
             Ok(merged_response)
         }
@@ -246,6 +377,7 @@ mod tests {
                 split_id: split_id.to_string(),
                 split_footer_end: 100,
                 split_footer_start: 0,
+                storage_uri: String::new(),
             }],
             ..Default::default()
         }
@@ -271,11 +403,13 @@ mod tests {
                     split_id: "split_1".to_string(),
                     split_footer_start: 0,
                     split_footer_end: 100,
+                    storage_uri: String::new(),
                 },
                 SplitIdAndFooterOffsets {
                     split_id: "split_2".to_string(),
                     split_footer_start: 0,
                     split_footer_end: 100,
+                    storage_uri: String::new(),
                 },
             ],
         }
@@ -292,6 +426,7 @@ mod tests {
             fast_field: "fast".to_string(),
             output_format: 0,
             partition_by_field: None,
+            role: None,
         };
         LeafSearchStreamRequest {
             request: Some(search_request),
@@ -302,11 +437,13 @@ mod tests {
                     split_id: "split_1".to_string(),
                     split_footer_start: 0,
                     split_footer_end: 100,
+                    storage_uri: String::new(),
                 },
                 SplitIdAndFooterOffsets {
                     split_id: "split_2".to_string(),
                     split_footer_start: 0,
                     split_footer_end: 100,
+                    storage_uri: String::new(),
                 },
             ],
         }
@@ -549,4 +686,67 @@ mod tests {
         assert!(results[0].is_ok());
         Ok(())
     }
+
+    #[test]
+    fn test_hedging_threshold() {
+        assert_eq!(hedging_threshold(0, 10), 0);
+        assert_eq!(hedging_threshold(100, 10), 90);
+        assert_eq!(hedging_threshold(15, 10), 13);
+        assert_eq!(hedging_threshold(100, 100), 0);
+    }
+
+    fn echo_leaf_search_response(request: LeafSearchRequest) -> crate::Result<LeafSearchResponse> {
+        let partial_hits = request
+            .split_offsets
+            .iter()
+            .map(|split_offsets| mock_partial_hit(&split_offsets.split_id, 1, 1))
+            .collect();
+        Ok(LeafSearchResponse {
+            num_hits: request.split_offsets.len() as u64,
+            partial_hits,
+            failed_splits: Vec::new(),
+            num_attempted_splits: request.split_offsets.len() as u64,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_cluster_client_multi_leaf_search_with_hedging() -> anyhow::Result<()> {
+        let mut mock_service_1 = MockSearchService::new();
+        mock_service_1
+            .expect_leaf_search()
+            .returning(echo_leaf_search_response);
+        let mut mock_service_2 = MockSearchService::new();
+        mock_service_2
+            .expect_leaf_search()
+            .returning(echo_leaf_search_response);
+        let client_pool =
+            SearchClientPool::from_mocks(vec![Arc::new(mock_service_1), Arc::new(mock_service_2)])
+                .await?;
+        let client_hashmap = client_pool.clients();
+        let first_client = client_hashmap
+            .get(&"127.0.0.1:20000".parse::<SocketAddr>()?)
+            .unwrap()
+            .clone();
+        let second_client = client_hashmap
+            .get(&"127.0.0.1:20010".parse::<SocketAddr>()?)
+            .unwrap()
+            .clone();
+        let mut first_request = mock_leaf_search_request();
+        let second_request = LeafSearchRequest {
+            split_offsets: first_request.split_offsets.split_off(1),
+            ..first_request.clone()
+        };
+        let cluster_client = ClusterClient::new(client_pool).with_leaf_search_hedging(Some(100));
+        let responses = cluster_client
+            .multi_leaf_search(vec![
+                (first_request, first_client),
+                (second_request, second_client),
+            ])
+            .await?;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].partial_hits[0].split_id, "split_1");
+        assert_eq!(responses[1].partial_hits[0].split_id, "split_2");
+        Ok(())
+    }
 }
@@ -247,6 +247,7 @@ impl SegmentCollector for QuickwitSegmentCollector {
             partial_hits,
             failed_splits: vec![],
             num_attempted_splits: 1,
+            split_timings: Vec::new(),
         })
     }
 }
@@ -372,30 +373,23 @@ impl Collector for QuickwitCollector {
 
 /// Merges a set of Leaf Results.
 fn merge_leaf_responses(
-    leaf_responses: Vec<LeafSearchResponse>,
+    mut leaf_responses: Vec<LeafSearchResponse>,
     max_hits: usize,
 ) -> tantivy::Result<LeafSearchResponse> {
     // Optimization: No merging needed if there is only one result.
     if leaf_responses.len() == 1 {
         return Ok(leaf_responses.into_iter().next().unwrap_or_default()); //< default is actually never called
     }
-    let intermediate_aggregation_results = leaf_responses
-        .iter()
-        .flat_map(|leaf_response| {
-            leaf_response
-                .intermediate_aggregation_result
-                .as_ref()
-                .map(|res| serde_json::from_str(res))
-        })
-        .collect::<Result<Vec<IntermediateAggregationResults>, _>>()?;
-
-    let intermediate_aggregation_result =
-        intermediate_aggregation_results
-            .into_iter()
-            .reduce(|mut res1, res2| {
-                res1.merge_fruits(res2);
-                res1
-            });
+    // The intermediate aggregation results are deserialized and merged one at a time, and their
+    // serialized form is released as we go, so that the buckets of all the responses are never
+    // held in memory at the same time.
+    let mut intermediate_aggregation_result = None;
+    for leaf_response in &mut leaf_responses {
+        if let Some(res) = leaf_response.intermediate_aggregation_result.take() {
+            let res: IntermediateAggregationResults = serde_json::from_str(&res)?;
+            merge_intermediate_aggregation_results(&mut intermediate_aggregation_result, res);
+        }
+    }
 
     let num_attempted_splits = leaf_responses
         .iter()
@@ -410,6 +404,11 @@ fn merge_leaf_responses(
         .flat_map(|leaf_response| leaf_response.failed_splits.iter())
         .cloned()
         .collect_vec();
+    let split_timings = leaf_responses
+        .iter()
+        .flat_map(|leaf_response| leaf_response.split_timings.iter())
+        .cloned()
+        .collect_vec();
     let all_partial_hits: Vec<PartialHit> = leaf_responses
         .into_iter()
         .flat_map(|leaf_response| leaf_response.partial_hits)
@@ -425,9 +424,22 @@ fn merge_leaf_responses(
         partial_hits: top_k_partial_hits,
         failed_splits,
         num_attempted_splits,
+        split_timings,
     })
 }
 
+/// Merges an intermediate aggregation result into the results merged so far.
+pub(crate) fn merge_intermediate_aggregation_results(
+    merged_res_opt: &mut Option<IntermediateAggregationResults>,
+    res: IntermediateAggregationResults,
+) {
+    if let Some(merged_res) = merged_res_opt.as_mut() {
+        merged_res.merge_fruits(res);
+    } else {
+        *merged_res_opt = Some(res);
+    }
+}
+
 /// Mutates partial_hits so that it contains the top-num_hitso hits,
 /// and so that these elements are sorted.
 ///
@@ -20,7 +20,7 @@
 use quickwit_doc_mapper::QueryParserError;
 use quickwit_metastore::MetastoreError;
 use quickwit_proto::{tonic, ServiceError, ServiceErrorCode};
-use quickwit_storage::StorageResolverError;
+use quickwit_storage::{StorageError, StorageResolverError};
 use serde::{Deserialize, Serialize};
 use tantivy::TantivyError;
 use thiserror::Error;
@@ -42,6 +42,24 @@ pub enum SearchError {
     InvalidArgument(String),
     #[error("Invalid query: {0}")]
     InvalidQuery(String),
+    #[error("Stored query `{name}` of index `{index_id}` does not exist.")]
+    StoredQueryDoesNotExist { index_id: String, name: String },
+    #[error("Incompatible split: {0}")]
+    IncompatibleSplit(String),
+    #[error(
+        "Search memory budget exceeded: the request requires an estimated {requested_num_bytes} \
+         bytes per split but only {available_num_bytes} bytes out of {budget_num_bytes} are \
+         available."
+    )]
+    MemoryBudgetExceeded {
+        requested_num_bytes: usize,
+        available_num_bytes: usize,
+        budget_num_bytes: usize,
+    },
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
 }
 
 impl ServiceError for SearchError {
@@ -53,6 +71,17 @@ impl ServiceError for SearchError {
             SearchError::InvalidQuery(_) => ServiceErrorCode::BadRequest,
             SearchError::InvalidArgument(_) => ServiceErrorCode::BadRequest,
             SearchError::InvalidAggregationRequest(_) => ServiceErrorCode::BadRequest,
+            SearchError::StoredQueryDoesNotExist { .. } => ServiceErrorCode::NotFound,
+            SearchError::IncompatibleSplit(_) => ServiceErrorCode::Internal,
+            // A request that does not fit in the whole budget will never succeed.
+            SearchError::MemoryBudgetExceeded {
+                requested_num_bytes,
+                budget_num_bytes,
+                ..
+            } if requested_num_bytes > budget_num_bytes => ServiceErrorCode::BadRequest,
+            SearchError::MemoryBudgetExceeded { .. } => ServiceErrorCode::Unavailable,
+            SearchError::TooManyRequests(_) => ServiceErrorCode::TooManyRequests,
+            SearchError::Forbidden(_) => ServiceErrorCode::Forbidden,
         }
     }
 }
@@ -104,6 +133,12 @@ impl From<MetastoreError> for SearchError {
     }
 }
 
+impl From<StorageError> for SearchError {
+    fn from(storage_error: StorageError) -> SearchError {
+        SearchError::InternalError(format!("Storage error: {}", storage_error))
+    }
+}
+
 impl From<JoinError> for SearchError {
     fn from(join_error: JoinError) -> SearchError {
         SearchError::InternalError(format!("Spawned task in root join failed: {}", join_error))
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 
 use anyhow::{Context, Ok};
@@ -27,8 +27,9 @@ use quickwit_doc_mapper::DocMapper;
 use quickwit_proto::{FetchDocsResponse, PartialHit, SearchRequest, SplitIdAndFooterOffsets};
 use quickwit_storage::Storage;
 use tantivy::query::{Query, QueryParserError};
-use tantivy::schema::{Field, Value};
+use tantivy::schema::{Field, FieldValue, Schema, Value};
 use tantivy::{ReloadPolicy, Score, Searcher, SnippetGenerator, Term};
+use tokio::sync::Semaphore;
 use tracing::error;
 
 use crate::leaf::open_index_with_caches;
@@ -106,6 +107,11 @@ async fn fetch_docs_to_map(
 /// This function takes a list of partial hits (possibly from different splits)
 /// and the storage associated to an index, fetches the document from
 /// the split document stores, and returns the full hits.
+///
+/// The estimated memory of the documents is reserved from the searcher fetch docs memory budget
+/// for the whole request before fetching any of them, and held until the hits are assembled. A
+/// request therefore never waits for memory while holding some, which could deadlock concurrent
+/// requests.
 pub async fn fetch_docs(
     searcher_context: Arc<SearcherContext>,
     partial_hits: Vec<PartialHit>,
@@ -119,8 +125,20 @@ pub async fn fetch_docs(
         .map(GlobalDocAddress::from_partial_hit)
         .collect();
 
+    let fetch_docs_memory_semaphore = &searcher_context.fetch_docs_memory_semaphore;
+    let num_reserved_permits = fetch_docs_num_permits(
+        &searcher_context,
+        partial_hits
+            .len()
+            .saturating_mul(DEFAULT_DOC_NUM_BYTES_ESTIMATE),
+    );
+    let _memory_permit = fetch_docs_memory_semaphore
+        .acquire_many(num_reserved_permits)
+        .await
+        .expect("The fetch docs memory semaphore should never be closed.");
+
     let mut global_doc_addr_to_doc_json = fetch_docs_to_map(
-        searcher_context,
+        searcher_context.clone(),
         global_doc_addrs,
         index_storage,
         splits,
@@ -148,8 +166,24 @@ pub async fn fetch_docs(
     Ok(FetchDocsResponse { hits })
 }
 
+/// Returns the number of permits of the fetch docs memory semaphore covering `num_bytes`. It is
+/// capped by the budget so that a single request fetching wide documents can still proceed.
+fn fetch_docs_num_permits(searcher_context: &SearcherContext, num_bytes: usize) -> u32 {
+    let memory_budget = (searcher_context
+        .searcher_config
+        .fetch_docs_memory_budget
+        .get_bytes() as usize)
+        .min(Semaphore::MAX_PERMITS);
+    num_bytes
+        .clamp(1, memory_budget.max(1))
+        .min(u32::MAX as usize) as u32
+}
+
 const NUM_CONCURRENT_REQUESTS: usize = 10;
 
+/// Estimated size of a fetched document. It matches the size of a doc store block.
+const DEFAULT_DOC_NUM_BYTES_ESTIMATE: usize = 16_384;
+
 /// A struct for holding a fetched document's content and snippet.
 #[derive(Debug)]
 struct Document {
@@ -157,7 +191,30 @@ struct Document {
     snippet_json: Option<String>,
 }
 
+/// Resolves the fields requested with `fetch_fields` against the split schema. Returns `None`
+/// when all the stored fields should be returned.
+fn resolve_fetch_fields(
+    schema: &Schema,
+    search_request_opt: Option<&SearchRequest>,
+) -> Option<HashSet<Field>> {
+    let search_request = search_request_opt?;
+    if search_request.fetch_fields.is_empty() {
+        return None;
+    }
+    // Fields missing from the split schema are simply absent from the hits.
+    let fetch_fields = search_request
+        .fetch_fields
+        .iter()
+        .filter_map(|field_name| schema.get_field(field_name))
+        .collect();
+    Some(fetch_fields)
+}
+
 /// Fetching docs from a specific split.
+///
+/// Documents are fetched concurrently. Only the fields requested with `fetch_fields` are converted
+/// to JSON and returned. The doc store compresses the stored fields of a document together, so the
+/// fields that are not requested are still decompressed.
 #[tracing::instrument(skip(global_doc_addrs, index_storage, split, searcher_context))]
 async fn fetch_docs_in_split(
     searcher_context: Arc<SearcherContext>,
@@ -180,58 +237,57 @@ async fn fetch_docs_in_split(
         .reload_policy(ReloadPolicy::Manual)
         .try_into()?;
     let searcher = Arc::new(index_reader.searcher());
-    let fields_snippet_generator_opt =
-        if let (Some(doc_mapper), Some(search_request)) = (doc_mapper_opt, search_request_opt) {
+    let fields_snippet_generator_opt = match (doc_mapper_opt, search_request_opt) {
+        (Some(doc_mapper), Some(search_request)) if !search_request.snippet_fields.is_empty() => {
             Some(create_fields_snippet_generator(&searcher, doc_mapper, search_request).await?)
-        } else {
-            None
-        };
+        }
+        _ => None,
+    };
+    let fetch_fields_opt = Arc::new(resolve_fetch_fields(&searcher.schema(), search_request_opt));
 
     let doc_futures = global_doc_addrs.into_iter().map(|global_doc_addr| {
         let searcher = searcher.clone();
         let fields_snippet_generator_opt_clone = fields_snippet_generator_opt.clone();
+        let fetch_fields_opt = fetch_fields_opt.clone();
         async move {
             let doc = searcher
                 .doc_async(global_doc_addr.doc_addr)
                 .await
                 .context("searcher-doc-async")?;
-            let content_json = searcher.schema().to_json(&doc);
-            if fields_snippet_generator_opt_clone.is_none() {
-                return Ok((
-                    global_doc_addr,
-                    Document {
-                        content_json,
-                        snippet_json: None,
-                    },
-                ));
-            }
 
-            let fields_snippet_generator_clone = fields_snippet_generator_opt_clone.unwrap();
-            if fields_snippet_generator_clone.is_empty() {
-                return Ok((
-                    global_doc_addr,
-                    Document {
-                        content_json,
-                        snippet_json: None,
-                    },
-                ));
-            }
-
-            let mut snippets = HashMap::new();
-            for (field, field_values) in doc.get_sorted_field_values() {
-                let field_name = searcher.schema().get_field_name(field);
-                if let Some(values) = fields_snippet_generator_clone
-                    .snippets_from_field_values(field_name, field_values)
-                {
-                    snippets.insert(field_name, values);
+            let snippet_json_opt = match &fields_snippet_generator_opt_clone {
+                Some(fields_snippet_generator) if !fields_snippet_generator.is_empty() => {
+                    let mut snippets = HashMap::new();
+                    for (field, field_values) in doc.get_sorted_field_values() {
+                        let field_name = searcher.schema().get_field_name(field);
+                        if let Some(values) = fields_snippet_generator
+                            .snippets_from_field_values(field_name, field_values)
+                        {
+                            snippets.insert(field_name, values);
+                        }
+                    }
+                    Some(serde_json::to_string(&snippets)?)
                 }
-            }
-            let snippet_json = serde_json::to_string(&snippets)?;
+                _ => None,
+            };
+            let content_json = if let Some(fetch_fields) = fetch_fields_opt.as_ref() {
+                let fetched_field_values: Vec<FieldValue> = doc
+                    .field_values()
+                    .iter()
+                    .filter(|field_value| fetch_fields.contains(&field_value.field()))
+                    .cloned()
+                    .collect();
+                searcher
+                    .schema()
+                    .to_json(&tantivy::Document::from(fetched_field_values))
+            } else {
+                searcher.schema().to_json(&doc)
+            };
             Ok((
                 global_doc_addr,
                 Document {
                     content_json,
-                    snippet_json: Some(snippet_json),
+                    snippet_json: snippet_json_opt,
                 },
             ))
         }
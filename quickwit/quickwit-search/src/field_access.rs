@@ -0,0 +1,387 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_config::{FieldAccessPolicy, SearchSettings};
+use quickwit_doc_mapper::{
+    extract_query_fields, extract_search_request_query_fields, QueryFields, DYNAMIC_ALL_FIELD_NAME,
+    SOURCE_FIELD_NAME,
+};
+use quickwit_proto::{Hit, SearchRequest, SearchStreamRequest};
+use serde_json::Value as JsonValue;
+
+use crate::SearchError;
+
+/// Returns the field access policy restricting the searches issued with the role `role_opt`.
+///
+/// Searches issued without a role, or on an index without field access policies, are not
+/// restricted. On an index with field access policies, searches issued with a role that has no
+/// policy are rejected.
+pub(crate) fn field_access_policy_for_role<'a>(
+    search_settings: &'a SearchSettings,
+    role_opt: Option<&str>,
+) -> crate::Result<Option<&'a FieldAccessPolicy>> {
+    if search_settings.field_access_policies.is_empty() {
+        return Ok(None);
+    }
+    // Searches issued without a role are denied rather than left unrestricted.
+    let role = role_opt.ok_or_else(|| {
+        SearchError::Forbidden(
+            "The index defines field access policies: searches must be issued with a role."
+                .to_string(),
+        )
+    })?;
+    search_settings
+        .field_access_policies
+        .iter()
+        .find(|field_access_policy| field_access_policy.role == role)
+        .map(Some)
+        .ok_or_else(|| {
+            SearchError::Forbidden(format!("Role `{}` has no access to the index.", role))
+        })
+}
+
+/// Rejects the search requests targeting fields denied by the policy. When the query relies on
+/// the default search fields, the denied ones are removed from the search fields of the request.
+pub(crate) fn restrict_search_request(
+    field_access_policy: &FieldAccessPolicy,
+    default_search_fields: &[String],
+    search_request: &mut SearchRequest,
+) -> crate::Result<()> {
+    let query_fields = extract_search_request_query_fields(search_request)?;
+    let requested_fields = query_fields
+        .field_names
+        .iter()
+        .chain(&search_request.search_fields)
+        .chain(&search_request.snippet_fields)
+        .chain(&search_request.fetch_fields)
+        .chain(&search_request.sort_by_field);
+    for field_name in requested_fields {
+        check_field_access(field_access_policy, field_name)?;
+    }
+    if let Some(aggregation_request) = &search_request.aggregation_request {
+        let aggregation_json: JsonValue = serde_json::from_str(aggregation_request)
+            .map_err(|err| SearchError::InvalidAggregationRequest(err.to_string()))?;
+        let mut aggregation_field_names = Vec::new();
+        collect_aggregation_field_names(&aggregation_json, &mut aggregation_field_names);
+        for field_name in aggregation_field_names {
+            check_field_access(field_access_policy, field_name)?;
+        }
+    }
+    restrict_search_fields(
+        field_access_policy,
+        default_search_fields,
+        &query_fields,
+        &mut search_request.search_fields,
+    )
+}
+
+/// Same as [`restrict_search_request`] for search stream requests.
+pub(crate) fn restrict_search_stream_request(
+    field_access_policy: &FieldAccessPolicy,
+    default_search_fields: &[String],
+    search_stream_request: &mut SearchStreamRequest,
+) -> crate::Result<()> {
+    let query_fields = extract_query_fields(&search_stream_request.query)?;
+    let requested_fields = query_fields
+        .field_names
+        .iter()
+        .chain(&search_stream_request.search_fields)
+        .chain(&search_stream_request.snippet_fields)
+        .chain(Some(&search_stream_request.fast_field))
+        .chain(&search_stream_request.partition_by_field);
+    for field_name in requested_fields {
+        check_field_access(field_access_policy, field_name)?;
+    }
+    restrict_search_fields(
+        field_access_policy,
+        default_search_fields,
+        &query_fields,
+        &mut search_stream_request.search_fields,
+    )
+}
+
+/// Strips the fields denied by the policy from a hit, including from its source document.
+pub(crate) fn redact_hit(
+    field_access_policy: &FieldAccessPolicy,
+    hit: &mut Hit,
+) -> crate::Result<()> {
+    if field_access_policy.denied_fields.is_empty() {
+        return Ok(());
+    }
+    let mut doc_json: JsonValue = serde_json::from_str(&hit.json)?;
+    for denied_field in &field_access_policy.denied_fields {
+        remove_field_path(&mut doc_json, denied_field);
+        if let Some(source_json) = doc_json.get_mut(SOURCE_FIELD_NAME) {
+            remove_field_path(source_json, denied_field);
+        }
+    }
+    hit.json = serde_json::to_string(&doc_json)?;
+    Ok(())
+}
+
+fn check_field_access(
+    field_access_policy: &FieldAccessPolicy,
+    field_name: &str,
+) -> crate::Result<()> {
+    // The `_dynamic_all` field indexes the values of all the dynamic fields, denied ones included.
+    let is_denied = field_access_policy.is_denied(field_name)
+        || (field_name == DYNAMIC_ALL_FIELD_NAME && !field_access_policy.denied_fields.is_empty());
+    if is_denied {
+        return Err(SearchError::Forbidden(format!(
+            "Field `{}` is denied to role `{}`.",
+            field_name, field_access_policy.role
+        )));
+    }
+    Ok(())
+}
+
+/// Pins the search fields of a query relying on the default search fields to the default search
+/// fields that are not denied by the policy.
+fn restrict_search_fields(
+    field_access_policy: &FieldAccessPolicy,
+    default_search_fields: &[String],
+    query_fields: &QueryFields,
+    search_fields: &mut Vec<String>,
+) -> crate::Result<()> {
+    if !query_fields.uses_default_search_fields
+        || !search_fields.is_empty()
+        || field_access_policy.denied_fields.is_empty()
+    {
+        return Ok(());
+    }
+    let allowed_search_fields: Vec<String> = default_search_fields
+        .iter()
+        .filter(|field_name| !field_access_policy.is_denied(field_name))
+        .cloned()
+        .collect();
+    if allowed_search_fields.is_empty() {
+        return Err(SearchError::Forbidden(format!(
+            "All the default search fields are denied to role `{}`. The query must target fields \
+             explicitly.",
+            field_access_policy.role
+        )));
+    }
+    *search_fields = allowed_search_fields;
+    Ok(())
+}
+
+/// Collects the fields aggregated by an aggregation request.
+fn collect_aggregation_field_names<'a>(json_value: &'a JsonValue, field_names: &mut Vec<&'a str>) {
+    match json_value {
+        JsonValue::Object(json_obj) => {
+            for (key, value) in json_obj {
+                match value {
+                    JsonValue::String(field_name) if key == "field" => field_names.push(field_name),
+                    _ => collect_aggregation_field_names(value, field_names),
+                }
+            }
+        }
+        JsonValue::Array(values) => {
+            for value in values {
+                collect_aggregation_field_names(value, field_names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Removes the field `field_path`, a possibly nested field path such as `user.email`, from a
+/// JSON document.
+fn remove_field_path(json_value: &mut JsonValue, field_path: &str) {
+    match json_value {
+        JsonValue::Object(json_obj) => {
+            json_obj.remove(field_path);
+            for (dot_idx, _) in field_path.match_indices('.') {
+                if let Some(child_json_value) = json_obj.get_mut(&field_path[..dot_idx]) {
+                    remove_field_path(child_json_value, &field_path[dot_idx + 1..]);
+                }
+            }
+        }
+        JsonValue::Array(values) => {
+            for value in values {
+                remove_field_path(value, field_path);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn analyst_policy() -> FieldAccessPolicy {
+        FieldAccessPolicy {
+            role: "analyst".to_string(),
+            denied_fields: vec!["user.email".to_string(), "client_ip".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_field_access_policy_for_role() {
+        let mut search_settings = SearchSettings::default();
+        assert!(
+            field_access_policy_for_role(&search_settings, Some("analyst"))
+                .unwrap()
+                .is_none()
+        );
+
+        search_settings.field_access_policies = vec![analyst_policy()];
+        let search_error = field_access_policy_for_role(&search_settings, None).unwrap_err();
+        assert!(matches!(search_error, SearchError::Forbidden(_)));
+        assert_eq!(
+            field_access_policy_for_role(&search_settings, Some("analyst")).unwrap(),
+            Some(&analyst_policy())
+        );
+        let search_error =
+            field_access_policy_for_role(&search_settings, Some("intern")).unwrap_err();
+        assert!(matches!(search_error, SearchError::Forbidden(_)));
+    }
+
+    #[test]
+    fn test_restrict_search_request() {
+        let default_search_fields = vec!["body".to_string(), "client_ip".to_string()];
+        {
+            let mut search_request = SearchRequest {
+                query: "severity:ERROR AND timeout".to_string(),
+                ..Default::default()
+            };
+            restrict_search_request(
+                &analyst_policy(),
+                &default_search_fields,
+                &mut search_request,
+            )
+            .unwrap();
+            assert_eq!(search_request.search_fields, vec!["body".to_string()]);
+        }
+        {
+            let mut search_request = SearchRequest {
+                query: "user.email:foo@bar.com".to_string(),
+                ..Default::default()
+            };
+            let search_error = restrict_search_request(
+                &analyst_policy(),
+                &default_search_fields,
+                &mut search_request,
+            )
+            .unwrap_err();
+            assert_eq!(
+                search_error.to_string(),
+                "Forbidden: Field `user.email` is denied to role `analyst`."
+            );
+        }
+        {
+            let mut search_request = SearchRequest {
+                query: "severity:ERROR".to_string(),
+                sort_by_field: Some("client_ip".to_string()),
+                ..Default::default()
+            };
+            restrict_search_request(
+                &analyst_policy(),
+                &default_search_fields,
+                &mut search_request,
+            )
+            .unwrap_err();
+        }
+        {
+            let mut search_request = SearchRequest {
+                query: "severity:ERROR".to_string(),
+                aggregation_request: Some(
+                    json!({
+                        "severities": {
+                            "terms": { "field": "severity" },
+                            "aggs": { "ips": { "terms": { "field": "client_ip" } } }
+                        }
+                    })
+                    .to_string(),
+                ),
+                ..Default::default()
+            };
+            let search_error = restrict_search_request(
+                &analyst_policy(),
+                &default_search_fields,
+                &mut search_request,
+            )
+            .unwrap_err();
+            assert_eq!(
+                search_error.to_string(),
+                "Forbidden: Field `client_ip` is denied to role `analyst`."
+            );
+        }
+        {
+            let mut search_request = SearchRequest {
+                query: "timeout".to_string(),
+                ..Default::default()
+            };
+            restrict_search_request(
+                &analyst_policy(),
+                &["client_ip".to_string()],
+                &mut search_request,
+            )
+            .unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_restrict_search_stream_request() {
+        let mut search_stream_request = SearchStreamRequest {
+            query: "severity:ERROR".to_string(),
+            fast_field: "client_ip".to_string(),
+            ..Default::default()
+        };
+        restrict_search_stream_request(&analyst_policy(), &[], &mut search_stream_request)
+            .unwrap_err();
+
+        search_stream_request.fast_field = "timestamp".to_string();
+        restrict_search_stream_request(&analyst_policy(), &[], &mut search_stream_request).unwrap();
+    }
+
+    #[test]
+    fn test_redact_hit() {
+        let mut hit = Hit {
+            json: json!({
+                "body": "timeout",
+                "client_ip": "10.0.0.1",
+                "user": { "id": 1, "email": "foo@bar.com" },
+                "_source": {
+                    "body": "timeout",
+                    "client_ip": "10.0.0.1",
+                    "user": [{ "id": 1, "email": "foo@bar.com" }]
+                }
+            })
+            .to_string(),
+            ..Default::default()
+        };
+        redact_hit(&analyst_policy(), &mut hit).unwrap();
+        let doc_json: JsonValue = serde_json::from_str(&hit.json).unwrap();
+        assert_eq!(
+            doc_json,
+            json!({
+                "body": "timeout",
+                "user": { "id": 1 },
+                "_source": {
+                    "body": "timeout",
+                    "user": [{ "id": 1 }]
+                }
+            })
+        );
+    }
+}
@@ -20,34 +20,45 @@
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use anyhow::Context;
 use futures::future::try_join_all;
 use futures::Future;
 use itertools::{Either, Itertools};
+use quickwit_common::uri::Uri;
 use quickwit_directories::{CachingDirectory, HotDirectory, StorageDirectory};
 use quickwit_doc_mapper::{DocMapper, QUICKWIT_TOKENIZER_MANAGER};
 use quickwit_proto::{
     LeafSearchResponse, SearchRequest, SplitIdAndFooterOffsets, SplitSearchError,
+    SplitSearchTimings,
 };
 use quickwit_storage::{
-    wrap_storage_with_long_term_cache, BundleStorage, MemorySizedCache, OwnedBytes, Storage,
+    quickwit_storage_uri_resolver, wrap_storage_with_long_term_cache, BundleStorage,
+    MemorySizedCache, OwnedBytes, Storage,
 };
 use tantivy::collector::Collector;
 use tantivy::directory::FileSlice;
 use tantivy::error::AsyncIoError;
 use tantivy::query::Query;
-use tantivy::schema::{Cardinality, Field, FieldType};
+use tantivy::schema::{Cardinality, Field, FieldType, Schema};
 use tantivy::{Index, ReloadPolicy, Searcher, Term};
+use tokio::sync::Semaphore;
 use tokio::task::spawn_blocking;
 use tracing::*;
 
+use crate::aggregation_spill::AggregationSpillFile;
 use crate::collector::{make_collector_for_split, make_merge_collector};
+use crate::memory_budget::{
+    estimate_aggregation_result_num_bytes, estimate_split_search_num_bytes, SearchMemoryAccountant,
+    SearchMemoryReservation,
+};
 use crate::service::SearcherContext;
 use crate::SearchError;
 
-async fn get_split_footer_from_cache_or_fetch(
+pub(crate) async fn get_split_footer_from_cache_or_fetch(
     index_storage: Arc<dyn Storage>,
     split_and_footer_offsets: &SplitIdAndFooterOffsets,
     footer_cache: &MemorySizedCache<String>,
@@ -82,24 +93,79 @@ async fn get_split_footer_from_cache_or_fetch(
     Ok(footer_data_opt)
 }
 
+/// Returns the storage holding the file of the given split: the index storage, unless the split
+/// has been moved to a warm storage tier.
+fn resolve_split_storage(
+    index_storage: Arc<dyn Storage>,
+    split_and_footer_offsets: &SplitIdAndFooterOffsets,
+) -> anyhow::Result<Arc<dyn Storage>> {
+    if split_and_footer_offsets.storage_uri.is_empty() {
+        return Ok(index_storage);
+    }
+    let storage_uri = Uri::from_str(&split_and_footer_offsets.storage_uri)?;
+    let split_storage = quickwit_storage_uri_resolver().resolve(&storage_uri)?;
+    Ok(split_storage)
+}
+
 /// Opens a `tantivy::Index` for the given split with several cache layers:
 /// - A split footer cache given by `SearcherContext.split_footer_cache`.
 /// - A fast fields cache given by `SearcherContext.storage_long_term_cache`.
 /// - An ephemeral unbounded cache directory whose lifetime is tied to the returned `Index`.
+///
+/// If the split is in the local split cache, it is opened from its memory-mapped file instead.
 pub(crate) async fn open_index_with_caches(
     searcher_context: &Arc<SearcherContext>,
     index_storage: Arc<dyn Storage>,
     split_and_footer_offsets: &SplitIdAndFooterOffsets,
     ephemeral_unbounded_cache: bool,
 ) -> anyhow::Result<Index> {
+    open_index_with_caches_and_timings(
+        searcher_context,
+        index_storage,
+        split_and_footer_offsets,
+        ephemeral_unbounded_cache,
+        &mut SplitSearchTimings::default(),
+    )
+    .await
+}
+
+/// Same as [`open_index_with_caches`], recording the time spent fetching the footer of the split
+/// and opening it from its hotcache in `split_timings`.
+async fn open_index_with_caches_and_timings(
+    searcher_context: &Arc<SearcherContext>,
+    index_storage: Arc<dyn Storage>,
+    split_and_footer_offsets: &SplitIdAndFooterOffsets,
+    ephemeral_unbounded_cache: bool,
+    split_timings: &mut SplitSearchTimings,
+) -> anyhow::Result<Index> {
+    let index_storage = resolve_split_storage(index_storage, split_and_footer_offsets)?;
+    if let Some(split_cache) = searcher_context.split_cache_opt.as_ref() {
+        if let Some(split_directory) =
+            split_cache.split_directory(&split_and_footer_offsets.split_id)
+        {
+            let hotcache_load_start = Instant::now();
+            let mut index = Index::open(split_directory)?;
+            index.set_tokenizers(QUICKWIT_TOKENIZER_MANAGER.clone());
+            split_timings.hotcache_load_micros = hotcache_load_start.elapsed().as_micros() as u64;
+            return Ok(index);
+        }
+        split_cache.record_search(
+            &split_and_footer_offsets.split_id,
+            split_and_footer_offsets.split_footer_end,
+            index_storage.clone(),
+        );
+    }
     let split_file = PathBuf::from(format!("{}.split", split_and_footer_offsets.split_id));
+    let footer_fetch_start = Instant::now();
     let footer_data = get_split_footer_from_cache_or_fetch(
         index_storage.clone(),
         split_and_footer_offsets,
         &searcher_context.split_footer_cache,
     )
     .await?;
+    split_timings.footer_fetch_micros = footer_fetch_start.elapsed().as_micros() as u64;
 
+    let hotcache_load_start = Instant::now();
     let (hotcache_bytes, bundle_storage) = BundleStorage::open_from_split_data(
         index_storage,
         split_file,
@@ -118,6 +184,7 @@ pub(crate) async fn open_index_with_caches(
     };
     let mut index = Index::open(hot_directory)?;
     index.set_tokenizers(QUICKWIT_TOKENIZER_MANAGER.clone());
+    split_timings.hotcache_load_micros = hotcache_load_start.elapsed().as_micros() as u64;
     Ok(index)
 }
 
@@ -165,6 +232,25 @@ pub(crate) async fn warmup(
     Ok(())
 }
 
+/// Adds the warmup fields declared in the search settings of the index to the fast fields warmed
+/// up by a search. Their fast field data ends up in the searcher fast field cache, which outlives
+/// the search. Fields missing from the split schema, or not fast in it, are ignored.
+fn add_warmup_field_names(
+    split_schema: &Schema,
+    doc_mapper: &dyn DocMapper,
+    fast_field_names: &mut HashSet<String>,
+) {
+    for warmup_field_name in doc_mapper.warmup_field_names() {
+        let is_fast = split_schema
+            .get_field(&warmup_field_name)
+            .map(|field| split_schema.get_field_entry(field).is_fast())
+            .unwrap_or(false);
+        if is_fast {
+            fast_field_names.insert(warmup_field_name);
+        }
+    }
+}
+
 async fn warm_up_term_dict_fields(
     searcher: &Searcher,
     term_dict_field_names: &HashSet<String>,
@@ -323,7 +409,23 @@ async fn leaf_search_single_split(
     doc_mapper: Arc<dyn DocMapper>,
 ) -> crate::Result<LeafSearchResponse> {
     let split_id = split.split_id.to_string();
-    let index = open_index_with_caches(searcher_context, storage, &split, true).await?;
+    let mut split_timings = SplitSearchTimings {
+        split_id: split_id.clone(),
+        ..Default::default()
+    };
+    // The footer of a split is cached the first time the split is opened by the searcher.
+    let is_first_open = searcher_context
+        .split_footer_cache
+        .get(&split.split_id)
+        .is_none();
+    let index = open_index_with_caches_and_timings(
+        searcher_context,
+        storage,
+        &split,
+        true,
+        &mut split_timings,
+    )
+    .await?;
     let split_schema = index.schema();
     let quickwit_collector = make_collector_for_split(
         split_id.clone(),
@@ -331,32 +433,91 @@ async fn leaf_search_single_split(
         search_request,
         &split_schema,
     )?;
+    let mut fast_field_names = quickwit_collector.fast_field_names();
+    let term_dict_field_names = quickwit_collector.term_dict_field_names();
+    if is_first_open {
+        add_warmup_field_names(&split_schema, doc_mapper.as_ref(), &mut fast_field_names);
+    }
     let query = doc_mapper.query(split_schema, search_request)?;
     let reader = index
         .reader_builder()
         .reload_policy(ReloadPolicy::Manual)
         .try_into()?;
     let searcher = reader.searcher();
+    let warmup_start = Instant::now();
     warmup(
         &searcher,
         &query,
-        &quickwit_collector.fast_field_names(),
-        &quickwit_collector.term_dict_field_names(),
+        &fast_field_names,
+        &term_dict_field_names,
         quickwit_collector.requires_scoring(),
     )
     .await?;
-    let leaf_search_response = crate::run_cpu_intensive(move || {
+    split_timings.warmup_micros = warmup_start.elapsed().as_micros() as u64;
+    let (mut leaf_search_response, collection_duration) = crate::run_cpu_intensive(move || {
         let span = info_span!( "search", split_id = %split.split_id);
         let _span_guard = span.enter();
-        searcher.search(&query, &quickwit_collector)
+        let collection_start = Instant::now();
+        searcher
+            .search(&query, &quickwit_collector)
+            .map(|leaf_search_response| (leaf_search_response, collection_start.elapsed()))
     })
     .await
     .map_err(|_| {
         crate::SearchError::InternalError(format!("Leaf search panicked. split={}", split_id))
     })??;
+    split_timings.collection_micros = collection_duration.as_micros() as u64;
+    if search_request.debug {
+        leaf_search_response.split_timings.push(split_timings);
+    }
     Ok(leaf_search_response)
 }
 
+/// Moves the intermediate aggregation result of a split search response to the spill file.
+async fn spill_aggregation_result(
+    aggregation_spill_file: Arc<Mutex<AggregationSpillFile>>,
+    mut leaf_search_response: LeafSearchResponse,
+) -> crate::Result<LeafSearchResponse> {
+    if let Some(intermediate_aggregation_result) =
+        leaf_search_response.intermediate_aggregation_result.take()
+    {
+        spawn_blocking(move || {
+            aggregation_spill_file
+                .lock()
+                .expect("The aggregation spill file lock should never be poisoned.")
+                .spill(&intermediate_aggregation_result)
+        })
+        .await
+        .context("Failed to spill aggregation result.")?
+        .map_err(|error| {
+            SearchError::InternalError(format!("Failed to spill aggregation result: {}", error))
+        })?;
+    }
+    Ok(leaf_search_response)
+}
+
+/// Reserves the memory holding the partial results of the splits until they are merged, then the
+/// memory required to search as many splits concurrently as possible, up to
+/// `max_num_concurrent_splits`. Returns the reservations along with the number of splits that can
+/// be searched concurrently.
+fn reserve_leaf_search_memory(
+    search_memory_accountant: &SearchMemoryAccountant,
+    split_results_num_bytes: usize,
+    split_search_num_bytes: usize,
+    max_num_concurrent_splits: usize,
+) -> crate::Result<(Vec<SearchMemoryReservation<'_>>, usize)> {
+    let mut memory_reservations = Vec::with_capacity(2);
+    if split_results_num_bytes > 0 {
+        let (memory_reservation, _) =
+            search_memory_accountant.try_reserve_units(split_results_num_bytes, 1)?;
+        memory_reservations.push(memory_reservation);
+    }
+    let (memory_reservation, num_concurrent_splits) = search_memory_accountant
+        .try_reserve_units(split_search_num_bytes, max_num_concurrent_splits)?;
+    memory_reservations.push(memory_reservation);
+    Ok((memory_reservations, num_concurrent_splits))
+}
+
 /// `leaf` step of search.
 ///
 /// The leaf search collects all kind of information, and returns a set of
@@ -370,13 +531,89 @@ pub async fn leaf_search(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<dyn DocMapper>,
 ) -> Result<LeafSearchResponse, SearchError> {
+    // The number of splits searched concurrently for this request is lowered when the memory left
+    // in the searcher budget does not allow to search as many splits as the searcher could.
+    let split_search_num_bytes = estimate_split_search_num_bytes(request)?;
+    let max_num_concurrent_splits = splits
+        .len()
+        .min(
+            searcher_context
+                .searcher_config
+                .max_num_concurrent_split_searches,
+        )
+        .max(1);
+    let aggregation_spill_dir_opt = request.aggregation_request.as_ref().and(
+        searcher_context
+            .searcher_config
+            .aggregation_spill_dir
+            .as_ref(),
+    );
+    // The partial aggregation results of the splits are held in memory until they are merged.
+    let aggregation_result_num_bytes = estimate_aggregation_result_num_bytes(request)?;
+    let reservation_res = reserve_leaf_search_memory(
+        &searcher_context.search_memory_accountant,
+        aggregation_result_num_bytes.saturating_mul(splits.len()),
+        split_search_num_bytes,
+        max_num_concurrent_splits,
+    );
+    let (_memory_reservations, num_concurrent_splits, aggregation_spill_file_opt) =
+        match (reservation_res, aggregation_spill_dir_opt) {
+            (Ok((memory_reservations, num_concurrent_splits)), _) => {
+                (memory_reservations, num_concurrent_splits, None)
+            }
+            // The partial aggregation results of the splits do not fit in the memory left in the
+            // budget: they are spilled to disk as the splits are searched, then read back and
+            // merged one at a time. Only the merged result and the result being merged are then
+            // held in memory, and the merge fails if the merged result outgrows its reservation.
+            (Err(SearchError::MemoryBudgetExceeded { .. }), Some(aggregation_spill_dir)) => {
+                let (memory_reservations, num_concurrent_splits) = reserve_leaf_search_memory(
+                    &searcher_context.search_memory_accountant,
+                    aggregation_result_num_bytes.saturating_mul(2),
+                    split_search_num_bytes,
+                    max_num_concurrent_splits,
+                )?;
+                let aggregation_spill_file = AggregationSpillFile::create(aggregation_spill_dir)
+                    .map_err(|error| {
+                        SearchError::InternalError(format!(
+                            "Failed to create aggregation spill file in `{}`: {}",
+                            aggregation_spill_dir.display(),
+                            error
+                        ))
+                    })?;
+                crate::SEARCH_METRICS
+                    .leaf_search_aggregation_spills_total
+                    .inc();
+                info!(
+                    index_id = %request.index_id,
+                    num_splits = splits.len(),
+                    "Spilling partial aggregation results to disk."
+                );
+                (
+                    memory_reservations,
+                    num_concurrent_splits,
+                    Some(Arc::new(Mutex::new(aggregation_spill_file))),
+                )
+            }
+            (Err(error), _) => return Err(error),
+        };
+    let request_split_semaphore = Semaphore::new(num_concurrent_splits);
+    searcher_context
+        .split_access_tracker
+        .record_searches(&request.index_id, splits);
+
     let leaf_search_single_split_futures: Vec<_> = splits
         .iter()
         .map(|split| {
             let doc_mapper_clone = doc_mapper.clone();
             let index_storage_clone = index_storage.clone();
             let searcher_context_clone = searcher_context.clone();
+            let request_split_semaphore = &request_split_semaphore;
+            let aggregation_spill_file_opt = aggregation_spill_file_opt.as_ref();
             async move {
+                let _request_split_permit = request_split_semaphore
+                    .acquire()
+                    .await
+                    .expect("The request split semaphore should never be closed.");
                 let _leaf_split_search_permit = searcher_context_clone.leaf_search_split_semaphore
                     .acquire()
                     .await
@@ -394,6 +631,17 @@ pub async fn leaf_search(
                 )
                 .await;
                 timer.observe_duration();
+                let leaf_search_single_split_res =
+                    match (leaf_search_single_split_res, aggregation_spill_file_opt) {
+                        (Ok(leaf_search_response), Some(aggregation_spill_file)) => {
+                            spill_aggregation_result(
+                                aggregation_spill_file.clone(),
+                                leaf_search_response,
+                            )
+                            .await
+                        }
+                        (leaf_search_single_split_res, _) => leaf_search_single_split_res,
+                    };
                 leaf_search_single_split_res.map_err(|err| (split.split_id.clone(), err))
             }
         })
@@ -423,6 +671,20 @@ pub async fn leaf_search(
             .await
             .context("Failed to merge split search responses.")??;
 
+    if let Some(aggregation_spill_file) = aggregation_spill_file_opt {
+        let aggregation_spill_file = Arc::try_unwrap(aggregation_spill_file)
+            .ok()
+            .and_then(|aggregation_spill_file| aggregation_spill_file.into_inner().ok())
+            .ok_or_else(|| {
+                SearchError::InternalError("Aggregation spill file is still in use.".to_string())
+            })?;
+        merged_search_response.intermediate_aggregation_result =
+            spawn_blocking(move || aggregation_spill_file.merge(aggregation_result_num_bytes))
+                .instrument(info_span!("merge_spilled_aggregation_results"))
+                .await
+                .context("Failed to merge spilled aggregation results.")??;
+    }
+
     merged_search_response
         .failed_splits
         .extend(errors.iter().map(|(split_id, err)| SplitSearchError {
@@ -21,20 +21,28 @@
 #![warn(missing_docs)]
 #![allow(clippy::bool_assert_comparison)]
 
+mod aggregation_spill;
+mod bloom_filter_pruning;
 mod client;
 mod cluster_client;
 mod collector;
 mod error;
 mod fetch_docs;
+mod field_access;
 mod filters;
 mod leaf;
+mod memory_budget;
 mod rendezvous_hasher;
 mod retry;
 mod root;
 mod search_client_pool;
+mod search_quotas;
 mod search_response_rest;
 mod search_stream;
 mod service;
+mod split_access_stats;
+mod split_cache;
+mod split_warmup;
 mod thread_pool;
 
 mod metrics;
@@ -42,31 +50,42 @@ mod metrics;
 mod tests;
 
 use metrics::SEARCH_METRICS;
-use quickwit_common::extract_time_range;
-use root::validate_request;
+use quickwit_common::{extract_time_range, split_file};
+use root::{validate_fetch_fields, validate_request};
 use service::SearcherContext;
+use split_cache::SPLIT_CACHE_DIR_NAME;
 
 /// Refer to this as `crate::Result<T>`.
 pub type Result<T> = std::result::Result<T, SearchError>;
 
 use std::cmp::Reverse;
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Context;
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
 use itertools::Itertools;
+use quickwit_cluster::Cluster;
+use quickwit_config::service::QuickwitService;
 use quickwit_config::{build_doc_mapper, QuickwitConfig, SearcherConfig};
-use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
+use quickwit_directories::read_split_footer;
+use quickwit_doc_mapper::tag_pruning::extract_tags_from_search_request;
 use quickwit_doc_mapper::DocMapper;
+pub use quickwit_doc_mapper::{BoolQuery, QueryAst};
 use quickwit_metastore::{Metastore, SplitMetadata, SplitState};
-use quickwit_proto::{PartialHit, SearchRequest, SearchResponse, SplitIdAndFooterOffsets};
-use quickwit_storage::StorageUriResolver;
+use quickwit_proto::{
+    PartialHit, SearchRequest, SearchResponse, SearchStreamRequest, SplitIdAndFooterOffsets,
+};
+use quickwit_storage::{Storage, StorageUriResolver};
 use serde_json::Value as JsonValue;
 use tantivy::aggregation::agg_req::Aggregations;
 use tantivy::aggregation::agg_result::AggregationResults;
 use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tantivy::DocAddress;
 
+use crate::bloom_filter_pruning::prune_splits_with_bloom_filters;
 pub use crate::client::SearchServiceClient;
 pub use crate::cluster_client::ClusterClient;
 pub use crate::error::{parse_grpc_error, SearchError};
@@ -74,9 +93,13 @@ use crate::fetch_docs::fetch_docs;
 use crate::leaf::leaf_search;
 pub use crate::root::{jobs_to_leaf_request, root_search, SearchJob};
 pub use crate::search_client_pool::{create_search_service_client, SearchClientPool};
+pub use crate::search_quotas::{SearchQuotaPermit, SearchQuotas};
 pub use crate::search_response_rest::SearchResponseRest;
+use crate::search_stream::leaf_search_stream;
 pub use crate::search_stream::root_search_stream;
 pub use crate::service::{MockSearchService, SearchService, SearchServiceImpl};
+use crate::split_access_stats::SplitAccessStatsReporter;
+use crate::split_warmup::SplitWarmer;
 use crate::thread_pool::run_cpu_intensive;
 
 /// GlobalDocAddress serves as a hit address.
@@ -110,6 +133,11 @@ fn extract_split_and_footer_offsets(split_metadata: &SplitMetadata) -> SplitIdAn
         split_id: split_metadata.split_id.clone(),
         split_footer_start: split_metadata.footer_offsets.start as u64,
         split_footer_end: split_metadata.footer_offsets.end as u64,
+        storage_uri: split_metadata
+            .storage_tier
+            .storage_uri()
+            .map(|storage_uri| storage_uri.to_string())
+            .unwrap_or_default(),
     }
 }
 
@@ -120,7 +148,7 @@ async fn list_relevant_splits(
 ) -> crate::Result<Vec<SplitMetadata>> {
     let time_range_opt =
         extract_time_range(search_request.start_timestamp, search_request.end_timestamp);
-    let tags_filter = extract_tags_from_query(&search_request.query)?;
+    let tags_filter = extract_tags_from_search_request(search_request)?;
     let split_metas = metastore
         .list_splits(
             &search_request.index_id,
@@ -129,10 +157,18 @@ async fn list_relevant_splits(
             tags_filter,
         )
         .await?;
-    Ok(split_metas
+    let split_metadatas = split_metas
         .into_iter()
         .map(|metadata| metadata.split_metadata)
-        .collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    // Splits written with an unsupported format are never opened, to avoid returning wrong
+    // results or crashing the searchers.
+    for split_metadata in &split_metadatas {
+        split_metadata
+            .check_format_compatibility()
+            .map_err(|error| SearchError::IncompatibleSplit(error.to_string()))?;
+    }
+    Ok(split_metadatas)
 }
 
 /// Converts a `LeafHit` into a `Hit`.
@@ -175,9 +211,50 @@ pub async fn single_node_search(
     let start_instant = tokio::time::Instant::now();
     let index_metadata = metastore.index_metadata(&search_request.index_id).await?;
     let index_storage = storage_resolver.resolve(&index_metadata.index_uri)?;
+    let doc_mapper = build_doc_mapper(
+        &index_metadata.doc_mapping,
+        &index_metadata.search_settings,
+        &index_metadata.indexing_settings,
+    )
+    .map_err(|err| {
+        SearchError::InternalError(format!("Failed to build doc mapper. Cause: {}", err))
+    })?;
     let metas = list_relevant_splits(search_request, metastore).await?;
+    let metas = prune_splits_with_bloom_filters(
+        extract_tags_from_search_request(&search_request)?,
+        &*doc_mapper,
+        index_storage.clone(),
+        metas,
+    )
+    .await?;
     let split_metadata: Vec<SplitIdAndFooterOffsets> =
         metas.iter().map(extract_split_and_footer_offsets).collect();
+    search_splits(
+        start_instant,
+        search_request,
+        index_storage,
+        split_metadata,
+        doc_mapper,
+    )
+    .await
+}
+
+/// Performs a search on the current node, restricted to the given splits. Unlike
+/// [`single_node_search`], the splits are not pruned based on the time range and tags of the
+/// request.
+pub async fn single_node_search_splits(
+    search_request: &SearchRequest,
+    metastore: &dyn Metastore,
+    storage_resolver: StorageUriResolver,
+    splits: &[SplitMetadata],
+) -> crate::Result<SearchResponse> {
+    let start_instant = tokio::time::Instant::now();
+    let index_metadata = metastore.index_metadata(&search_request.index_id).await?;
+    let index_storage = storage_resolver.resolve(&index_metadata.index_uri)?;
+    let split_metadata: Vec<SplitIdAndFooterOffsets> = splits
+        .iter()
+        .map(extract_split_and_footer_offsets)
+        .collect();
     let doc_mapper = build_doc_mapper(
         &index_metadata.doc_mapping,
         &index_metadata.search_settings,
@@ -186,8 +263,167 @@ pub async fn single_node_search(
     .map_err(|err| {
         SearchError::InternalError(format!("Failed to build doc mapper. Cause: {}", err))
     })?;
+    search_splits(
+        start_instant,
+        search_request,
+        index_storage,
+        split_metadata,
+        doc_mapper,
+    )
+    .await
+}
+
+/// Performs a search stream on the current node.
+/// See also `[root_search_stream]`.
+///
+/// Each item of the returned stream holds the values extracted from one split.
+pub async fn single_node_search_stream(
+    search_stream_request: SearchStreamRequest,
+    metastore: &dyn Metastore,
+    storage_resolver: StorageUriResolver,
+) -> crate::Result<impl futures::Stream<Item = crate::Result<Bytes>>> {
+    let (index_storage, doc_mapper, split_metadata) =
+        prepare_single_node_search_stream(&search_stream_request, metastore, storage_resolver)
+            .await?;
+    let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
+    let leaf_stream = leaf_search_stream(
+        searcher_context,
+        search_stream_request,
+        index_storage,
+        split_metadata,
+        doc_mapper,
+    )
+    .await;
+    Ok(leaf_stream.map_ok(|leaf_response| Bytes::from(leaf_response.data)))
+}
 
+/// Streams the values of several fast fields of the documents matching the request, split by
+/// split. Each item holds the values of one split in the CSV format, one chunk per fast field in
+/// the order of `fast_fields`. The chunks of a split list the values of the same documents in the
+/// same order, so that they can be zipped into rows. The `fast_field` of the request is ignored.
+pub async fn single_node_search_stream_fields(
+    search_stream_request: SearchStreamRequest,
+    fast_fields: Vec<String>,
+    metastore: &dyn Metastore,
+    storage_resolver: StorageUriResolver,
+) -> crate::Result<impl futures::Stream<Item = crate::Result<Vec<Bytes>>>> {
+    let (index_storage, doc_mapper, split_metadata) =
+        prepare_single_node_search_stream(&search_stream_request, metastore, storage_resolver)
+            .await?;
+    let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
+    let split_stream = futures::stream::iter(split_metadata).then(move |split| {
+        let searcher_context = searcher_context.clone();
+        let search_stream_request = search_stream_request.clone();
+        let fast_fields = fast_fields.clone();
+        let index_storage = index_storage.clone();
+        let doc_mapper = doc_mapper.clone();
+        async move {
+            let mut field_chunks = Vec::with_capacity(fast_fields.len());
+            for fast_field in fast_fields {
+                let field_request = SearchStreamRequest {
+                    fast_field,
+                    ..search_stream_request.clone()
+                };
+                let field_chunk: Vec<u8> = leaf_search_stream(
+                    searcher_context.clone(),
+                    field_request,
+                    index_storage.clone(),
+                    vec![split.clone()],
+                    doc_mapper.clone(),
+                )
+                .await
+                .map_ok(|leaf_response| leaf_response.data)
+                .try_concat()
+                .await?;
+                field_chunks.push(Bytes::from(field_chunk));
+            }
+            crate::Result::Ok(field_chunks)
+        }
+    });
+    Ok(split_stream)
+}
+
+/// Resolves the storage, the doc mapper, and the relevant splits of a search stream request.
+async fn prepare_single_node_search_stream(
+    search_stream_request: &SearchStreamRequest,
+    metastore: &dyn Metastore,
+    storage_resolver: StorageUriResolver,
+) -> crate::Result<(
+    Arc<dyn Storage>,
+    Arc<dyn DocMapper>,
+    Vec<SplitIdAndFooterOffsets>,
+)> {
+    let search_request = SearchRequest::from(search_stream_request.clone());
+    let index_metadata = metastore.index_metadata(&search_request.index_id).await?;
+    let index_storage = storage_resolver.resolve(&index_metadata.index_uri)?;
+    let doc_mapper = build_doc_mapper(
+        &index_metadata.doc_mapping,
+        &index_metadata.search_settings,
+        &index_metadata.indexing_settings,
+    )
+    .map_err(|err| {
+        SearchError::InternalError(format!("Failed to build doc mapper. Cause: {}", err))
+    })?;
+    let metas = list_relevant_splits(&search_request, metastore).await?;
+    let metas = prune_splits_with_bloom_filters(
+        extract_tags_from_search_request(&search_request)?,
+        &*doc_mapper,
+        index_storage.clone(),
+        metas,
+    )
+    .await?;
+    let split_metadata: Vec<SplitIdAndFooterOffsets> =
+        metas.iter().map(extract_split_and_footer_offsets).collect();
+
+    // Validates the query by effectively building it against the current schema.
+    doc_mapper.query(doc_mapper.schema(), &search_request)?;
+    Ok((index_storage, doc_mapper, split_metadata))
+}
+
+/// Performs a search directly against split files, without any metastore.
+///
+/// The split footer offsets, usually stored in the metastore, are read from the split files
+/// themselves. Since split time ranges and tags are not known either, no split pruning occurs.
+/// This is meant for debugging and forensics on splits copied out of a cluster.
+pub async fn offline_search(
+    search_request: &SearchRequest,
+    storage: Arc<dyn Storage>,
+    split_ids: &[String],
+    doc_mapper: Arc<dyn DocMapper>,
+) -> crate::Result<SearchResponse> {
+    let start_instant = tokio::time::Instant::now();
+    let mut split_metadata = Vec::with_capacity(split_ids.len());
+    for split_id in split_ids {
+        let split_file = PathBuf::from(split_file(split_id));
+        let file_len = storage.file_num_bytes(&split_file).await?;
+        let (split_footer, _) = read_split_footer(storage.clone(), &split_file).await?;
+        split_metadata.push(SplitIdAndFooterOffsets {
+            split_id: split_id.clone(),
+            split_footer_start: file_len - split_footer.len() as u64,
+            split_footer_end: file_len,
+            storage_uri: String::new(),
+        });
+    }
+    search_splits(
+        start_instant,
+        search_request,
+        storage,
+        split_metadata,
+        doc_mapper,
+    )
+    .await
+}
+
+/// Runs the leaf search and fetch docs phases on the current node for the given splits.
+async fn search_splits(
+    start_instant: tokio::time::Instant,
+    search_request: &SearchRequest,
+    index_storage: Arc<dyn Storage>,
+    split_metadata: Vec<SplitIdAndFooterOffsets>,
+    doc_mapper: Arc<dyn DocMapper>,
+) -> crate::Result<SearchResponse> {
     validate_request(search_request)?;
+    validate_fetch_fields(&doc_mapper.schema(), search_request)?;
 
     // Validates the query by effectively building it against the current schema.
     doc_mapper.query(doc_mapper.schema(), search_request)?;
@@ -202,12 +438,14 @@ pub async fn single_node_search(
     .await
     .context("Failed to perform leaf search.")?;
 
-    let doc_mapper_opt = if !search_request.snippet_fields.is_empty() {
+    let needs_search_request =
+        !search_request.snippet_fields.is_empty() || !search_request.fetch_fields.is_empty();
+    let doc_mapper_opt = if needs_search_request {
         Some(doc_mapper.clone())
     } else {
         None
     };
-    let search_request_opt = if !search_request.snippet_fields.is_empty() {
+    let search_request_opt = if needs_search_request {
         Some(search_request)
     } else {
         None
@@ -250,23 +488,60 @@ pub async fn single_node_search(
             .iter()
             .map(|error| format!("{:?}", error))
             .collect_vec(),
+        split_timings: leaf_search_response.split_timings,
     })
 }
 
 /// Starts a search node, aka a `searcher`.
+///
+/// If the node runs the searcher service, it also prefetches the hotcache and footer of the
+/// splits newly published in the cluster.
 pub async fn start_searcher_service(
     quickwit_config: &QuickwitConfig,
+    cluster: Arc<Cluster>,
     metastore: Arc<dyn Metastore>,
     storage_uri_resolver: StorageUriResolver,
     search_client_pool: SearchClientPool,
 ) -> anyhow::Result<Arc<dyn SearchService>> {
-    let cluster_client = ClusterClient::new(search_client_pool.clone());
+    let cluster_client = ClusterClient::new(search_client_pool.clone()).with_leaf_search_hedging(
+        quickwit_config
+            .searcher_config
+            .leaf_search_hedging_percentage,
+    );
+    let mut searcher_config = quickwit_config.searcher_config.clone();
+    if let Some(split_cache_config) = searcher_config.split_cache.as_mut() {
+        split_cache_config
+            .root_path
+            .get_or_insert_with(|| quickwit_config.data_dir_path.join(SPLIT_CACHE_DIR_NAME));
+    }
     let search_service = Arc::new(SearchServiceImpl::new(
-        metastore,
-        storage_uri_resolver,
+        metastore.clone(),
+        storage_uri_resolver.clone(),
         cluster_client,
-        search_client_pool,
-        quickwit_config.searcher_config.clone(),
+        search_client_pool.clone(),
+        searcher_config,
     ));
+    if quickwit_config
+        .enabled_services
+        .contains(&QuickwitService::Searcher)
+    {
+        SplitAccessStatsReporter::new(
+            metastore.clone(),
+            search_service
+                .searcher_context()
+                .split_access_tracker
+                .clone(),
+        )
+        .spawn();
+        SplitWarmer::new(
+            cluster,
+            metastore,
+            storage_uri_resolver,
+            search_service.searcher_context(),
+            search_client_pool,
+            quickwit_config.grpc_advertise_addr,
+        )
+        .spawn();
+    }
     Ok(search_service)
 }
@@ -0,0 +1,313 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use quickwit_proto::SearchRequest;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::SearchError;
+
+/// Estimated number of bytes taken by a bucket of a bucket aggregation, including its key.
+const BUCKET_NUM_BYTES: usize = 128;
+/// Estimated number of bytes taken by the state of a metric aggregation.
+const METRIC_NUM_BYTES: usize = 64;
+/// Estimated number of bytes taken by a partial hit kept in the top K heap.
+const PARTIAL_HIT_NUM_BYTES: usize = 128;
+/// Estimated number of bytes required to collect a split, aggregations and hits aside.
+const BASE_SPLIT_NUM_BYTES: usize = 64 * 1024;
+/// Number of buckets assumed for the histograms whose bounds are not known in advance.
+const DEFAULT_HISTOGRAM_NUM_BUCKETS: usize = 1_000;
+
+/// Keeps track of the memory reserved by the leaf searches running on a searcher and rejects the
+/// reservations that would exceed the searcher memory budget.
+pub struct SearchMemoryAccountant {
+    budget_num_bytes: usize,
+    reserved_num_bytes: AtomicUsize,
+}
+
+impl SearchMemoryAccountant {
+    pub fn new(budget_num_bytes: usize) -> Self {
+        Self {
+            budget_num_bytes,
+            reserved_num_bytes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of bytes currently reserved.
+    pub fn reserved_num_bytes(&self) -> usize {
+        self.reserved_num_bytes.load(Ordering::Acquire)
+    }
+
+    /// Reserves `unit_num_bytes` for as many units as possible, up to `max_num_units`, and
+    /// returns the reservation along with the number of units it covers. Fails if not even a
+    /// single unit fits in the memory left in the budget.
+    pub fn try_reserve_units(
+        &self,
+        unit_num_bytes: usize,
+        max_num_units: usize,
+    ) -> Result<(SearchMemoryReservation<'_>, usize), SearchError> {
+        let unit_num_bytes = unit_num_bytes.max(1);
+        let mut reserved_num_bytes = self.reserved_num_bytes.load(Ordering::Acquire);
+        loop {
+            let available_num_bytes = self.budget_num_bytes.saturating_sub(reserved_num_bytes);
+            let num_units = (available_num_bytes / unit_num_bytes).min(max_num_units);
+            if num_units == 0 {
+                return Err(SearchError::MemoryBudgetExceeded {
+                    requested_num_bytes: unit_num_bytes,
+                    available_num_bytes,
+                    budget_num_bytes: self.budget_num_bytes,
+                });
+            }
+            let num_bytes = num_units * unit_num_bytes;
+            match self.reserved_num_bytes.compare_exchange_weak(
+                reserved_num_bytes,
+                reserved_num_bytes + num_bytes,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    crate::SEARCH_METRICS
+                        .search_memory_reserved_num_bytes
+                        .add(num_bytes as i64);
+                    let reservation = SearchMemoryReservation {
+                        accountant: self,
+                        num_bytes,
+                    };
+                    return Ok((reservation, num_units));
+                }
+                Err(current_reserved_num_bytes) => {
+                    reserved_num_bytes = current_reserved_num_bytes;
+                }
+            }
+        }
+    }
+}
+
+/// Memory reserved from a [`SearchMemoryAccountant`], released on drop.
+pub struct SearchMemoryReservation<'a> {
+    accountant: &'a SearchMemoryAccountant,
+    num_bytes: usize,
+}
+
+impl<'a> Drop for SearchMemoryReservation<'a> {
+    fn drop(&mut self) {
+        self.accountant
+            .reserved_num_bytes
+            .fetch_sub(self.num_bytes, Ordering::AcqRel);
+        crate::SEARCH_METRICS
+            .search_memory_reserved_num_bytes
+            .sub(self.num_bytes as i64);
+    }
+}
+
+/// Estimates the number of bytes required to collect a single split for the given request.
+pub fn estimate_split_search_num_bytes(search_request: &SearchRequest) -> crate::Result<usize> {
+    let num_hits = search_request
+        .start_offset
+        .saturating_add(search_request.max_hits) as usize;
+    let num_bytes =
+        BASE_SPLIT_NUM_BYTES.saturating_add(num_hits.saturating_mul(PARTIAL_HIT_NUM_BYTES));
+    Ok(num_bytes.saturating_add(estimate_aggregation_result_num_bytes(search_request)?))
+}
+
+/// Estimates the number of bytes taken by the partial aggregation results of a single split for
+/// the given request, or 0 if the request has no aggregations.
+pub fn estimate_aggregation_result_num_bytes(
+    search_request: &SearchRequest,
+) -> crate::Result<usize> {
+    let aggregation_request = match search_request.aggregation_request.as_ref() {
+        Some(aggregation_request) => aggregation_request,
+        None => return Ok(0),
+    };
+    let aggregations: JsonMap<String, JsonValue> = serde_json::from_str(aggregation_request)
+        .map_err(|error| SearchError::InvalidAggregationRequest(error.to_string()))?;
+    Ok(estimate_aggregations_num_bytes(&aggregations))
+}
+
+fn estimate_aggregations_num_bytes(aggregations: &JsonMap<String, JsonValue>) -> usize {
+    aggregations
+        .values()
+        .filter_map(JsonValue::as_object)
+        .map(estimate_aggregation_num_bytes)
+        .fold(0, usize::saturating_add)
+}
+
+fn estimate_aggregation_num_bytes(aggregation: &JsonMap<String, JsonValue>) -> usize {
+    let sub_aggregations_num_bytes = aggregation
+        .get("aggs")
+        .or_else(|| aggregation.get("aggregations"))
+        .and_then(JsonValue::as_object)
+        .map(estimate_aggregations_num_bytes)
+        .unwrap_or(0);
+    let bucket_num_bytes = BUCKET_NUM_BYTES.saturating_add(sub_aggregations_num_bytes);
+    let num_buckets_opt = aggregation
+        .iter()
+        .filter(|(key, _)| key.as_str() != "aggs" && key.as_str() != "aggregations")
+        .find_map(|(kind, params)| estimate_num_buckets(kind, params));
+    match num_buckets_opt {
+        Some(num_buckets) => num_buckets.saturating_mul(bucket_num_bytes),
+        None => METRIC_NUM_BYTES,
+    }
+}
+
+/// Estimates the number of buckets returned by a split for a bucket aggregation, or returns
+/// `None` for metric aggregations.
+fn estimate_num_buckets(kind: &str, params: &JsonValue) -> Option<usize> {
+    let get_usize = |key: &str| params.get(key).and_then(JsonValue::as_u64);
+    match kind {
+        "terms" => {
+            let num_buckets = get_usize("split_size")
+                .or_else(|| get_usize("shard_size"))
+                .or_else(|| get_usize("segment_size"))
+                .unwrap_or_else(|| {
+                    let size = get_usize("size").unwrap_or(10);
+                    size.saturating_mul(3) / 2 + 10
+                });
+            Some(num_buckets as usize)
+        }
+        "range" => {
+            let num_ranges = params
+                .get("ranges")
+                .and_then(JsonValue::as_array)
+                .map(Vec::len)
+                .unwrap_or(0);
+            Some(num_ranges + 2)
+        }
+        "histogram" => {
+            let interval = params
+                .get("interval")
+                .and_then(JsonValue::as_f64)
+                .filter(|interval| *interval > 0.0);
+            let bounds = params
+                .get("hard_bounds")
+                .or_else(|| params.get("extended_bounds"))
+                .and_then(|bounds| {
+                    Some((bounds.get("min")?.as_f64()?, bounds.get("max")?.as_f64()?))
+                });
+            let num_buckets = match (interval, bounds) {
+                (Some(interval), Some((min, max))) if max >= min => {
+                    ((max - min) / interval).min(usize::MAX as f64) as usize + 1
+                }
+                _ => DEFAULT_HISTOGRAM_NUM_BUCKETS,
+            };
+            Some(num_buckets)
+        }
+        "date_histogram" => Some(DEFAULT_HISTOGRAM_NUM_BUCKETS),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_request_with_aggregation(aggregation: serde_json::Value) -> SearchRequest {
+        SearchRequest {
+            max_hits: 0,
+            aggregation_request: Some(aggregation.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_estimate_split_search_num_bytes_without_aggregations() {
+        let search_request = SearchRequest {
+            max_hits: 10,
+            start_offset: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            estimate_split_search_num_bytes(&search_request).unwrap(),
+            BASE_SPLIT_NUM_BYTES + 20 * PARTIAL_HIT_NUM_BYTES
+        );
+        assert_eq!(
+            estimate_aggregation_result_num_bytes(&search_request).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_estimate_split_search_num_bytes_with_aggregations() {
+        let search_request = search_request_with_aggregation(serde_json::json!({
+            "hosts": {
+                "terms": { "field": "host", "split_size": 100 },
+                "aggs": {
+                    "avg_latency": { "avg": { "field": "latency" } }
+                }
+            },
+            "latencies": {
+                "histogram": {
+                    "field": "latency",
+                    "interval": 10.0,
+                    "hard_bounds": { "min": 0.0, "max": 1000.0 }
+                }
+            }
+        }));
+        assert_eq!(
+            estimate_split_search_num_bytes(&search_request).unwrap(),
+            BASE_SPLIT_NUM_BYTES
+                + 100 * (BUCKET_NUM_BYTES + METRIC_NUM_BYTES)
+                + 101 * BUCKET_NUM_BYTES
+        );
+        assert_eq!(
+            estimate_aggregation_result_num_bytes(&search_request).unwrap(),
+            100 * (BUCKET_NUM_BYTES + METRIC_NUM_BYTES) + 101 * BUCKET_NUM_BYTES
+        );
+        let search_request = search_request_with_aggregation(serde_json::json!({
+            "hosts": { "terms": { "field": "host", "size": 1_000_000_000u64 } }
+        }));
+        assert!(
+            estimate_split_search_num_bytes(&search_request).unwrap()
+                > 1_000_000_000 * BUCKET_NUM_BYTES
+        );
+        let search_request = SearchRequest {
+            aggregation_request: Some("[]".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            estimate_split_search_num_bytes(&search_request).unwrap_err(),
+            SearchError::InvalidAggregationRequest(_)
+        ));
+    }
+
+    #[test]
+    fn test_search_memory_accountant() {
+        let accountant = SearchMemoryAccountant::new(1_000);
+        let (reservation, num_units) = accountant.try_reserve_units(300, 10).unwrap();
+        assert_eq!(num_units, 3);
+        assert_eq!(accountant.reserved_num_bytes(), 900);
+
+        let error = accountant.try_reserve_units(200, 1).err().unwrap();
+        assert!(matches!(
+            error,
+            SearchError::MemoryBudgetExceeded {
+                requested_num_bytes: 200,
+                available_num_bytes: 100,
+                budget_num_bytes: 1_000,
+            }
+        ));
+        drop(reservation);
+        assert_eq!(accountant.reserved_num_bytes(), 0);
+
+        let (_reservation, num_units) = accountant.try_reserve_units(200, 2).unwrap();
+        assert_eq!(num_units, 2);
+        assert_eq!(accountant.reserved_num_bytes(), 400);
+    }
+}
@@ -28,6 +28,18 @@ pub struct SearchMetrics {
     pub leaf_searches_splits_total: IntCounter,
     pub leaf_search_split_duration_secs: Histogram,
     pub active_search_threads_count: IntGauge,
+    pub split_warmups_total: IntCounter,
+    pub splits_pruned_by_bloom_filters_total: IntCounter,
+    pub split_cache_hits_total: IntCounter,
+    pub split_cache_misses_total: IntCounter,
+    pub split_cache_downloads_total: IntCounter,
+    pub split_cache_num_bytes: IntGauge,
+    pub split_cache_num_splits: IntGauge,
+    pub search_memory_reserved_num_bytes: IntGauge,
+    pub leaf_search_aggregation_spills_total: IntCounter,
+    pub aggregation_spilled_num_bytes_total: IntCounter,
+    pub leaf_search_hedged_requests_total: IntCounter,
+    pub search_quota_rejections_total: IntCounter,
 }
 
 impl Default for SearchMetrics {
@@ -49,6 +61,70 @@ impl Default for SearchMetrics {
                 "Number of threads in use in the CPU thread pool",
                 "quickwit_search",
             ),
+            split_warmups_total: new_counter(
+                "split_warmups_total",
+                "Number of newly published splits whose hotcache and footer were prefetched.",
+                "quickwit_search",
+            ),
+            splits_pruned_by_bloom_filters_total: new_counter(
+                "splits_pruned_by_bloom_filters_total",
+                "Number of splits skipped by the search planner because their bloom filters rule \
+                 out the query.",
+                "quickwit_search",
+            ),
+            split_cache_hits_total: new_counter(
+                "split_cache_hits_total",
+                "Number of splits opened from the local split cache.",
+                "quickwit_search",
+            ),
+            split_cache_misses_total: new_counter(
+                "split_cache_misses_total",
+                "Number of splits opened from the index storage because they were not in the \
+                 local split cache.",
+                "quickwit_search",
+            ),
+            split_cache_downloads_total: new_counter(
+                "split_cache_downloads_total",
+                "Number of splits downloaded to the local split cache.",
+                "quickwit_search",
+            ),
+            split_cache_num_bytes: new_gauge(
+                "split_cache_num_bytes",
+                "Size in bytes of the splits in the local split cache.",
+                "quickwit_search",
+            ),
+            split_cache_num_splits: new_gauge(
+                "split_cache_num_splits",
+                "Number of splits in the local split cache.",
+                "quickwit_search",
+            ),
+            search_memory_reserved_num_bytes: new_gauge(
+                "search_memory_reserved_num_bytes",
+                "Estimated number of bytes reserved by the leaf searches in progress.",
+                "quickwit_search",
+            ),
+            leaf_search_aggregation_spills_total: new_counter(
+                "leaf_search_aggregation_spills_total",
+                "Number of leaf searches that spilled the partial aggregation results of their \
+                 splits to disk because the search memory budget could not hold them.",
+                "quickwit_search",
+            ),
+            aggregation_spilled_num_bytes_total: new_counter(
+                "aggregation_spilled_num_bytes_total",
+                "Number of bytes of partial aggregation results spilled to disk by the leaf \
+                 searches.",
+                "quickwit_search",
+            ),
+            leaf_search_hedged_requests_total: new_counter(
+                "leaf_search_hedged_requests_total",
+                "Number of slow leaf search requests sent again to another searcher.",
+                "quickwit_search",
+            ),
+            search_quota_rejections_total: new_counter(
+                "search_quota_rejections_total",
+                "Number of search requests rejected because the index reached its search quotas.",
+                "quickwit_search",
+            ),
         }
     }
 }
@@ -108,11 +108,13 @@ mod tests {
             split_id: "split_1".to_string(),
             split_footer_end: 100,
             split_footer_start: 0,
+            storage_uri: String::new(),
         };
         let split_2 = SplitIdAndFooterOffsets {
             split_id: "split_2".to_string(),
             split_footer_end: 100,
             split_footer_start: 0,
+            storage_uri: String::new(),
         };
         let retry_policy = LeafSearchStreamRetryPolicy {};
         let request = LeafSearchStreamRequest {
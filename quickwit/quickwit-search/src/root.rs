@@ -23,25 +23,30 @@ use std::collections::{HashMap, HashSet};
 use futures::future::try_join_all;
 use itertools::Itertools;
 use quickwit_config::build_doc_mapper;
+use quickwit_doc_mapper::tag_pruning::extract_tags_from_search_request;
 use quickwit_metastore::{Metastore, SplitMetadata};
 use quickwit_proto::{
     FetchDocsRequest, FetchDocsResponse, LeafSearchRequest, LeafSearchResponse, PartialHit,
     SearchRequest, SearchResponse, SplitIdAndFooterOffsets,
 };
+use quickwit_storage::StorageUriResolver;
 use tantivy::aggregation::agg_req::Aggregations;
 use tantivy::aggregation::agg_result::AggregationResults;
 use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tantivy::collector::Collector;
+use tantivy::schema::Schema;
 use tantivy::TantivyError;
 use tokio::task::spawn_blocking;
 use tracing::{debug, error, instrument};
 
+use crate::bloom_filter_pruning::prune_splits_with_bloom_filters;
 use crate::cluster_client::ClusterClient;
 use crate::collector::make_merge_collector;
+use crate::field_access::{field_access_policy_for_role, redact_hit, restrict_search_request};
 use crate::search_client_pool::Job;
 use crate::{
     extract_split_and_footer_offsets, list_relevant_splits, SearchClientPool, SearchError,
-    SearchServiceClient,
+    SearchQuotas, SearchServiceClient,
 };
 
 /// SearchJob to be assigned to search clients by the [`SearchClientPool`].
@@ -133,21 +138,55 @@ pub(crate) fn validate_request(search_request: &SearchRequest) -> crate::Result<
     Ok(())
 }
 
+/// Validates that the fields requested with `fetch_fields` exist in the schema.
+pub(crate) fn validate_fetch_fields(
+    schema: &Schema,
+    search_request: &SearchRequest,
+) -> crate::Result<()> {
+    for fetch_field in &search_request.fetch_fields {
+        if schema.get_field(fetch_field).is_none() {
+            return Err(SearchError::InvalidArgument(format!(
+                "Unknown fetch field: `{}`",
+                fetch_field
+            )));
+        }
+    }
+    Ok(())
+}
+
 /// Performs a distributed search.
 /// 1. Sends leaf request over gRPC to multiple leaf nodes.
 /// 2. Merges the search results.
 /// 3. Sends fetch docs requests to multiple leaf nodes.
 /// 4. Builds the response with docs and returns.
-#[instrument(skip(search_request, cluster_client, client_pool, metastore))]
+#[instrument(skip_all)]
 pub async fn root_search(
     search_request: &SearchRequest,
     metastore: &dyn Metastore,
+    storage_resolver: &StorageUriResolver,
     cluster_client: &ClusterClient,
     client_pool: &SearchClientPool,
+    search_quotas: &SearchQuotas,
 ) -> crate::Result<SearchResponse> {
     let start_instant = tokio::time::Instant::now();
 
     let index_metadata = metastore.index_metadata(&search_request.index_id).await?;
+    let field_access_policy_opt = field_access_policy_for_role(
+        &index_metadata.search_settings,
+        search_request.role.as_deref(),
+    )?;
+    let mut search_request = search_request.clone();
+    if let Some(field_access_policy) = field_access_policy_opt {
+        restrict_search_request(
+            field_access_policy,
+            &index_metadata.search_settings.default_search_fields,
+            &mut search_request,
+        )?;
+    }
+    let search_request = &search_request;
+
+    let _search_quota_permit =
+        search_quotas.try_admit(&index_metadata.index_id, &index_metadata.search_settings)?;
 
     let doc_mapper = build_doc_mapper(
         &index_metadata.doc_mapping,
@@ -159,6 +198,7 @@ pub async fn root_search(
     })?;
 
     validate_request(search_request)?;
+    validate_fetch_fields(&doc_mapper.schema(), search_request)?;
 
     // Validates the query by effectively building it against the current schema.
     doc_mapper.query(doc_mapper.schema(), search_request)?;
@@ -169,6 +209,24 @@ pub async fn root_search(
 
     let split_metadatas: Vec<SplitMetadata> =
         list_relevant_splits(search_request, metastore).await?;
+    let split_metadatas = if doc_mapper.bloom_filter_field_names().is_empty() {
+        split_metadatas
+    } else {
+        let index_storage = storage_resolver.resolve(&index_metadata.index_uri)?;
+        prune_splits_with_bloom_filters(
+            extract_tags_from_search_request(search_request)?,
+            &*doc_mapper,
+            index_storage,
+            split_metadatas,
+        )
+        .await?
+    };
+
+    let scanned_num_bytes: u64 = split_metadatas
+        .iter()
+        .map(|split_metadata| split_metadata.footer_offsets.end)
+        .sum();
+    search_quotas.record_scanned_bytes(&index_metadata.index_id, scanned_num_bytes);
 
     let split_offsets_map: HashMap<String, SplitIdAndFooterOffsets> = split_metadatas
         .iter()
@@ -183,26 +241,29 @@ pub async fn root_search(
     let jobs: Vec<SearchJob> = split_metadatas.iter().map(SearchJob::from).collect();
     let assigned_leaf_search_jobs = client_pool.assign_jobs(jobs, &HashSet::default())?;
     debug!(assigned_leaf_search_jobs=?assigned_leaf_search_jobs, "Assigned leaf search jobs.");
-    let leaf_search_responses: Vec<LeafSearchResponse> = try_join_all(
-        assigned_leaf_search_jobs
-            .into_iter()
-            .map(|(client, client_jobs)| {
-                let leaf_request = jobs_to_leaf_request(
-                    search_request,
-                    &doc_mapper_str,
-                    index_metadata.index_uri.as_ref(),
-                    client_jobs,
-                );
-                cluster_client.leaf_search(leaf_request, client)
-            }),
-    )
-    .await?;
+    let placed_leaf_requests = assigned_leaf_search_jobs
+        .into_iter()
+        .map(|(client, client_jobs)| {
+            let leaf_request = jobs_to_leaf_request(
+                search_request,
+                &doc_mapper_str,
+                index_metadata.index_uri.as_ref(),
+                client_jobs,
+            );
+            (leaf_request, client)
+        })
+        .collect();
+    let leaf_search_responses: Vec<LeafSearchResponse> = cluster_client
+        .multi_leaf_search(placed_leaf_requests)
+        .await?;
 
     // Creates a collector which merges responses into one
     let merge_collector = make_merge_collector(search_request)?;
 
     // Merging is a cpu-bound task.
     // It should be executed by Tokio's blocking threads.
+    // Unlike the leaf searches, the root search does not spill partial aggregation results: the
+    // results of all the leaf searches are held in memory while they are merged.
 
     // Wrap into result for merge_fruits
     let leaf_search_responses: Vec<tantivy::Result<LeafSearchResponse>> =
@@ -246,7 +307,9 @@ pub async fn root_search(
                     .map(|fetch_doc_job| fetch_doc_job.into())
                     .collect();
 
-                let fetch_docs_req = if search_request.snippet_fields.is_empty() {
+                let fetch_docs_req = if search_request.snippet_fields.is_empty()
+                    && search_request.fetch_fields.is_empty()
+                {
                     FetchDocsRequest {
                         partial_hits,
                         index_id: search_request.index_id.to_string(),
@@ -279,6 +342,12 @@ pub async fn root_search(
         .map(|leaf_hit: quickwit_proto::LeafHit| crate::convert_leaf_hit(leaf_hit, &*doc_mapper))
         .collect::<crate::Result<_>>()?;
 
+    if let Some(field_access_policy) = field_access_policy_opt {
+        for hit in &mut hits {
+            redact_hit(field_access_policy, hit)?;
+        }
+    }
+
     hits.sort_unstable_by_key(|hit| {
         Reverse(
             hit.partial_hit
@@ -308,6 +377,7 @@ pub async fn root_search(
         hits,
         elapsed_time_micros: elapsed.as_micros() as u64,
         errors: vec![],
+        split_timings: leaf_search_response.split_timings,
     })
 }
 
@@ -348,9 +418,17 @@ fn assign_client_fetch_doc_tasks(
 }
 
 // Measure the cost associated to searching in a given split metadata.
-fn compute_split_cost(_split_metadata: &SplitMetadata) -> u32 {
+/// Cost of searching a split stored in the warm tier, relative to a split stored in the hot tier.
+/// Warm storages are typically slower, so we spread warm splits more evenly across searchers.
+const WARM_SPLIT_COST: u32 = 5;
+
+fn compute_split_cost(split_metadata: &SplitMetadata) -> u32 {
     // TODO: Have a smarter cost, by smoothing the number of docs.
-    1
+    if split_metadata.storage_tier.is_hot() {
+        1
+    } else {
+        WARM_SPLIT_COST
+    }
 }
 
 /// Builds a [`LeafSearchRequest`] from a list of [`SearchJob`].
@@ -378,7 +456,7 @@ mod tests {
 
     use quickwit_indexing::mock_split;
     use quickwit_metastore::{IndexMetadata, MockMetastore, SplitState};
-    use quickwit_proto::SplitSearchError;
+    use quickwit_proto::{ServiceError, SplitSearchError};
 
     use super::*;
     use crate::MockSearchService;
@@ -492,8 +570,15 @@ mod tests {
         ])
         .await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await?;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         assert_eq!(search_response.num_hits, 5);
         assert_eq!(search_response.hits.len(), 0);
         Ok(())
@@ -550,13 +635,96 @@ mod tests {
         );
         let client_pool = SearchClientPool::from_mocks(vec![Arc::new(mock_search_service)]).await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await?;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         assert_eq!(search_response.num_hits, 3);
         assert_eq!(search_response.hits.len(), 3);
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_root_search_rejects_requests_beyond_index_quotas() -> anyhow::Result<()> {
+        let search_request = quickwit_proto::SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "test".to_string(),
+            search_fields: vec!["body".to_string()],
+            max_hits: 10,
+            ..Default::default()
+        };
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+                index_metadata.search_settings.max_scanned_bytes_per_minute =
+                    Some(byte_unit::Byte::from_bytes(1));
+                Ok(index_metadata)
+            });
+        metastore.expect_list_splits().returning(
+            |_index_id: &str, _split_state: SplitState, _time_range: Option<Range<i64>>, _tags| {
+                Ok(vec![mock_split("split1")])
+            },
+        );
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_leaf_search().returning(
+            |_leaf_search_req: quickwit_proto::LeafSearchRequest| {
+                Ok(quickwit_proto::LeafSearchResponse {
+                    num_hits: 1,
+                    partial_hits: vec![mock_partial_hit("split1", 1, 1)],
+                    failed_splits: Vec::new(),
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            },
+        );
+        mock_search_service.expect_fetch_docs().returning(
+            |fetch_docs_req: quickwit_proto::FetchDocsRequest| {
+                Ok(quickwit_proto::FetchDocsResponse {
+                    hits: get_doc_for_fetch_req(fetch_docs_req),
+                })
+            },
+        );
+        let client_pool = SearchClientPool::from_mocks(vec![Arc::new(mock_search_service)]).await?;
+        let cluster_client = ClusterClient::new(client_pool.clone());
+        let search_quotas = SearchQuotas::default();
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &search_quotas,
+        )
+        .await?;
+        assert_eq!(search_response.num_hits, 1);
+
+        // The first search exhausted the scanned bytes quota of the index.
+        let search_error = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &search_quotas,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(search_error, SearchError::TooManyRequests(_)));
+        assert_eq!(
+            search_error.status_code().to_http_status_code(),
+            http::StatusCode::TOO_MANY_REQUESTS
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_root_search_multiple_splits() -> anyhow::Result<()> {
         let search_request = quickwit_proto::SearchRequest {
@@ -630,8 +798,15 @@ mod tests {
         ])
         .await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await?;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         assert_eq!(search_response.num_hits, 3);
         assert_eq!(search_response.hits.len(), 3);
         Ok(())
@@ -737,8 +912,15 @@ mod tests {
         ])
         .await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await?;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         assert_eq!(search_response.num_hits, 3);
         assert_eq!(search_response.hits.len(), 3);
         Ok(())
@@ -861,8 +1043,15 @@ mod tests {
         ])
         .await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await?;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         assert_eq!(search_response.num_hits, 3);
         assert_eq!(search_response.hits.len(), 3);
         Ok(())
@@ -934,8 +1123,15 @@ mod tests {
         let client_pool =
             SearchClientPool::from_mocks(vec![Arc::new(mock_search_service1)]).await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await?;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         assert_eq!(search_response.num_hits, 1);
         assert_eq!(search_response.hits.len(), 1);
         Ok(())
@@ -993,8 +1189,15 @@ mod tests {
         let client_pool =
             SearchClientPool::from_mocks(vec![Arc::new(mock_search_service1)]).await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await;
         assert!(search_response.is_err());
         Ok(())
     }
@@ -1075,8 +1278,15 @@ mod tests {
         ])
         .await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await?;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         assert_eq!(search_response.num_hits, 1);
         assert_eq!(search_response.hits.len(), 1);
         Ok(())
@@ -1148,8 +1358,15 @@ mod tests {
         ])
         .await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await?;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         assert_eq!(search_response.num_hits, 1);
         assert_eq!(search_response.hits.len(), 1);
         Ok(())
@@ -1188,8 +1405,10 @@ mod tests {
                 ..Default::default()
             },
             &metastore,
+            &StorageUriResolver::for_test(),
             &cluster_client,
             &client_pool,
+            &SearchQuotas::default(),
         )
         .await
         .is_err());
@@ -1206,8 +1425,10 @@ mod tests {
                 ..Default::default()
             },
             &metastore,
+            &StorageUriResolver::for_test(),
             &cluster_client,
             &client_pool,
+            &SearchQuotas::default(),
         )
         .await
         .is_err());
@@ -1264,8 +1485,15 @@ mod tests {
         let client_pool =
             SearchClientPool::from_mocks(vec![Arc::new(MockSearchService::new())]).await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await;
         assert!(search_response.is_err());
         assert_eq!(
             search_response.unwrap_err().to_string(),
@@ -1304,8 +1532,15 @@ mod tests {
         let client_pool =
             SearchClientPool::from_mocks(vec![Arc::new(MockSearchService::new())]).await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await;
         assert!(search_response.is_err());
         assert_eq!(
             search_response.unwrap_err().to_string(),
@@ -1322,8 +1557,15 @@ mod tests {
             ..Default::default()
         };
 
-        let search_response =
-            root_search(&search_request, &metastore, &cluster_client, &client_pool).await;
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            &cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await;
         assert!(search_response.is_err());
         assert_eq!(
             search_response.unwrap_err().to_string(),
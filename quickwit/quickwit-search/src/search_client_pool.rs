@@ -193,6 +193,14 @@ impl SearchClientPool {
     }
 }
 
+/// Maximum load of a node relative to the average load of the nodes. Jobs are assigned to the
+/// node with the highest affinity for their split as long as its load remains below this bound,
+/// and spill over to the next nodes in the rendez-vous order otherwise.
+///
+/// A low value balances the load more evenly across the nodes, a high value favors split
+/// affinity, and therefore the hit rate of the per-node split caches.
+const MAX_LOAD_FACTOR: f64 = 1.25;
+
 fn job_order_key<J: Job>(job: &J) -> (Reverse<u32>, &str) {
     (Reverse(job.cost()), job.split_id())
 }
@@ -215,6 +223,11 @@ impl SearchClientPool {
     /// Assign the given job to the clients.
     /// Returns a list of pair (SocketAddr, Vec<Job>)
     ///
+    /// Jobs are assigned using rendez-vous hashing with bounded loads: a given split is
+    /// consistently routed to the same node, so that the split caches of the searchers only hold
+    /// the splits they are responsible for, unless this node is already too loaded compared to
+    /// the others (see [`MAX_LOAD_FACTOR`]).
+    ///
     /// When exclude_addresses filters all clients it is ignored.
     pub fn assign_jobs<J: Job>(
         &self,
@@ -261,18 +274,27 @@ impl SearchClientPool {
             job_order_key(left).cmp(&job_order_key(right))
         });
 
+        let total_load: u64 = jobs.iter().map(|job| job.cost() as u64).sum();
+        let max_load = total_load as f64 * MAX_LOAD_FACTOR / nodes.len() as f64;
+
         for job in jobs {
             sort_by_rendez_vous_hash(&mut nodes, job.split_id());
-            // choose one of the the first two nodes based on least loaded
-            let chosen_node_index: usize = if nodes.len() >= 2 {
-                if nodes[0].load > nodes[1].load {
-                    1
-                } else {
-                    0
-                }
-            } else {
-                0
-            };
+            // Choose the node with the highest affinity that can take the job without exceeding
+            // the max load. An idle node always accepts a job, however large. If all the nodes
+            // are saturated, fall back to the least loaded one.
+            let chosen_node_index: usize = nodes
+                .iter()
+                .position(|node| {
+                    node.load == 0 || (node.load + job.cost() as u64) as f64 <= max_load
+                })
+                .unwrap_or_else(|| {
+                    nodes
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, node)| node.load)
+                        .map(|(node_index, _)| node_index)
+                        .expect("The list of nodes should not be empty.")
+                });
 
             // update node load for next round
             nodes[chosen_node_index].load += job.cost() as u64;
@@ -319,18 +341,14 @@ impl SearchClientPool {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
-    use std::net::SocketAddr;
-    use std::sync::Arc;
     use std::time::Duration;
 
     use chitchat::transport::{ChannelTransport, Transport};
-    use itertools::Itertools;
     use quickwit_cluster::{create_cluster_for_test, grpc_addr_from_listen_addr_for_test, Cluster};
 
-    use super::create_search_service_client;
+    use super::*;
     use crate::root::SearchJob;
-    use crate::SearchClientPool;
+    use crate::MockSearchService;
 
     async fn create_cluster_simple_for_test(
         transport: &dyn Transport,
@@ -418,4 +436,69 @@ mod tests {
         );
         Ok(())
     }
+
+    fn assigned_addr_per_split(
+        assigned_jobs: &[(SearchServiceClient, Vec<SearchJob>)],
+    ) -> HashMap<String, SocketAddr> {
+        assigned_jobs
+            .iter()
+            .flat_map(|(client, jobs)| {
+                jobs.iter()
+                    .map(|job| (job.split_id().to_string(), client.grpc_addr()))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_client_pool_assign_jobs_split_affinity() -> anyhow::Result<()> {
+        let client_pool = SearchClientPool::from_mocks(vec![
+            Arc::new(MockSearchService::new()),
+            Arc::new(MockSearchService::new()),
+            Arc::new(MockSearchService::new()),
+        ])
+        .await?;
+        let split_ids: Vec<String> = (0..30)
+            .map(|split_ord| format!("split{split_ord}"))
+            .collect();
+        // Zero-cost jobs never saturate the nodes, so they are routed to their preferred node
+        // regardless of the other jobs of the request.
+        let jobs = split_ids
+            .iter()
+            .map(|split_id| SearchJob::for_test(split_id, 0))
+            .collect();
+        let assigned_jobs = client_pool.assign_jobs(jobs, &HashSet::default())?;
+        let assigned_addrs = assigned_addr_per_split(&assigned_jobs);
+        assert_eq!(assigned_jobs.len(), 3);
+
+        for split_id in &split_ids[..10] {
+            let jobs = vec![SearchJob::for_test(split_id, 0)];
+            let assigned_jobs = client_pool.assign_jobs(jobs, &HashSet::default())?;
+            assert_eq!(
+                assigned_addr_per_split(&assigned_jobs),
+                HashMap::from_iter([(split_id.clone(), assigned_addrs[split_id])])
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_client_pool_assign_jobs_bounded_load() -> anyhow::Result<()> {
+        let client_pool = SearchClientPool::from_mocks(vec![
+            Arc::new(MockSearchService::new()),
+            Arc::new(MockSearchService::new()),
+            Arc::new(MockSearchService::new()),
+        ])
+        .await?;
+        let jobs = (0..100)
+            .map(|split_ord| SearchJob::for_test(&format!("split{split_ord}"), 1))
+            .collect();
+        let assigned_jobs = client_pool.assign_jobs(jobs, &HashSet::default())?;
+        assert_eq!(assigned_jobs.len(), 3);
+        // 100 jobs * 1.25 / 3 nodes
+        let max_load = 41;
+        for (_, jobs) in &assigned_jobs {
+            assert!(jobs.len() <= max_load);
+        }
+        Ok(())
+    }
 }
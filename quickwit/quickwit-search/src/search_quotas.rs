@@ -0,0 +1,220 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use quickwit_config::SearchSettings;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::SearchError;
+
+/// Length of the windows over which the bytes scanned for an index are accounted.
+const SCANNED_BYTES_WINDOW: Duration = Duration::from_secs(60);
+
+/// Enforces on a root searcher the per-index quotas defined in the search settings of the
+/// indexes: the number of concurrent searches and the number of bytes scanned per minute.
+#[derive(Clone, Default)]
+pub struct SearchQuotas {
+    per_index: Arc<Mutex<HashMap<String, IndexQuotaState>>>,
+}
+
+struct IndexQuotaState {
+    /// Semaphore bounding the concurrent searches on the index, along with its number of
+    /// permits.
+    concurrency_limit_opt: Option<(usize, Arc<Semaphore>)>,
+    window_start: Instant,
+    window_scanned_num_bytes: u64,
+}
+
+impl IndexQuotaState {
+    fn new() -> Self {
+        Self {
+            concurrency_limit_opt: None,
+            window_start: Instant::now(),
+            window_scanned_num_bytes: 0,
+        }
+    }
+
+    fn roll_window(&mut self) {
+        if self.window_start.elapsed() >= SCANNED_BYTES_WINDOW {
+            self.window_start = Instant::now();
+            self.window_scanned_num_bytes = 0;
+        }
+    }
+}
+
+/// Admission of a search by [`SearchQuotas`]. The search counts as running until the permit is
+/// dropped.
+pub struct SearchQuotaPermit {
+    _concurrency_permit_opt: Option<OwnedSemaphorePermit>,
+}
+
+impl SearchQuotas {
+    /// Admits a search on the index, or rejects it with [`SearchError::TooManyRequests`] if the
+    /// index has reached one of its quotas.
+    pub fn try_admit(
+        &self,
+        index_id: &str,
+        search_settings: &SearchSettings,
+    ) -> crate::Result<SearchQuotaPermit> {
+        if search_settings.max_concurrent_searches.is_none()
+            && search_settings.max_scanned_bytes_per_minute.is_none()
+        {
+            return Ok(SearchQuotaPermit {
+                _concurrency_permit_opt: None,
+            });
+        }
+        let mut per_index = self.per_index.lock().unwrap();
+        let state = per_index
+            .entry(index_id.to_string())
+            .or_insert_with(IndexQuotaState::new);
+
+        if let Some(max_scanned_bytes_per_minute) = search_settings.max_scanned_bytes_per_minute {
+            state.roll_window();
+            let max_scanned_num_bytes = max_scanned_bytes_per_minute.get_bytes() as u64;
+            if state.window_scanned_num_bytes >= max_scanned_num_bytes {
+                crate::SEARCH_METRICS.search_quota_rejections_total.inc();
+                return Err(SearchError::TooManyRequests(format!(
+                    "Index `{index_id}` exceeded its quota of {max_scanned_num_bytes} bytes \
+                     scanned per minute."
+                )));
+            }
+        }
+        let concurrency_permit_opt = match search_settings.max_concurrent_searches {
+            Some(max_concurrent_searches) => {
+                // The semaphore is replaced when the limit is updated. Searches admitted by the
+                // previous semaphore are then no longer accounted for.
+                let semaphore = match &state.concurrency_limit_opt {
+                    Some((num_permits, semaphore)) if *num_permits == max_concurrent_searches => {
+                        semaphore.clone()
+                    }
+                    _ => {
+                        let semaphore = Arc::new(Semaphore::new(max_concurrent_searches));
+                        state.concurrency_limit_opt =
+                            Some((max_concurrent_searches, semaphore.clone()));
+                        semaphore
+                    }
+                };
+                let concurrency_permit = semaphore.try_acquire_owned().map_err(|_| {
+                    crate::SEARCH_METRICS.search_quota_rejections_total.inc();
+                    SearchError::TooManyRequests(format!(
+                        "Index `{index_id}` exceeded its quota of {max_concurrent_searches} \
+                         concurrent searches."
+                    ))
+                })?;
+                Some(concurrency_permit)
+            }
+            None => None,
+        };
+        Ok(SearchQuotaPermit {
+            _concurrency_permit_opt: concurrency_permit_opt,
+        })
+    }
+
+    /// Records the number of bytes of split files scanned by a search on the index.
+    pub fn record_scanned_bytes(&self, index_id: &str, num_bytes: u64) {
+        if let Some(state) = self.per_index.lock().unwrap().get_mut(index_id) {
+            state.roll_window();
+            state.window_scanned_num_bytes += num_bytes;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byte_unit::Byte;
+
+    use super::*;
+
+    #[test]
+    fn test_search_quotas_without_limits() {
+        let search_quotas = SearchQuotas::default();
+        let search_settings = SearchSettings::default();
+        let _permits: Vec<SearchQuotaPermit> = (0..100)
+            .map(|_| {
+                search_quotas
+                    .try_admit("test-index", &search_settings)
+                    .unwrap()
+            })
+            .collect();
+        search_quotas.record_scanned_bytes("test-index", u64::MAX);
+        search_quotas
+            .try_admit("test-index", &search_settings)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_search_quotas_concurrent_searches() {
+        let search_quotas = SearchQuotas::default();
+        let search_settings = SearchSettings {
+            max_concurrent_searches: Some(2),
+            ..Default::default()
+        };
+        let permit_1 = search_quotas
+            .try_admit("test-index", &search_settings)
+            .unwrap();
+        let _permit_2 = search_quotas
+            .try_admit("test-index", &search_settings)
+            .unwrap();
+        let error = search_quotas
+            .try_admit("test-index", &search_settings)
+            .err()
+            .unwrap();
+        assert!(matches!(error, SearchError::TooManyRequests(_)));
+        // Other indexes have their own quotas.
+        search_quotas
+            .try_admit("other-index", &search_settings)
+            .unwrap();
+        drop(permit_1);
+        search_quotas
+            .try_admit("test-index", &search_settings)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_quotas_scanned_bytes_per_minute() {
+        tokio::time::pause();
+        let search_quotas = SearchQuotas::default();
+        let search_settings = SearchSettings {
+            max_scanned_bytes_per_minute: Some(Byte::from_bytes(1_000)),
+            ..Default::default()
+        };
+        search_quotas
+            .try_admit("test-index", &search_settings)
+            .unwrap();
+        search_quotas.record_scanned_bytes("test-index", 600);
+        search_quotas
+            .try_admit("test-index", &search_settings)
+            .unwrap();
+        search_quotas.record_scanned_bytes("test-index", 600);
+        let error = search_quotas
+            .try_admit("test-index", &search_settings)
+            .err()
+            .unwrap();
+        assert!(matches!(error, SearchError::TooManyRequests(_)));
+
+        tokio::time::advance(SCANNED_BYTES_WINDOW).await;
+        search_quotas
+            .try_admit("test-index", &search_settings)
+            .unwrap();
+    }
+}
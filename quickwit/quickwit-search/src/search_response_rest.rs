@@ -17,12 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
 
 use quickwit_common::truncate_str;
-use quickwit_proto::SearchResponse;
+use quickwit_proto::{SearchResponse, SplitSearchTimings};
 use serde::Serialize;
-use serde_json::Value as JsonValue;
+use serde_json::{Map as JsonMap, Value as JsonValue};
 
 use crate::error::SearchError;
 
@@ -44,6 +45,9 @@ pub struct SearchResponseRest {
     /// Aggregations.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aggregations: Option<JsonValue>,
+    /// Timings of the search of each split, returned in debug mode.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub split_timings: Vec<SplitSearchTimings>,
 }
 
 impl TryFrom<SearchResponse> for SearchResponseRest {
@@ -86,6 +90,93 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
                 .map(|agg| serde_json::from_str(&agg))
                 .transpose()
                 .map_err(|err| SearchError::InternalError(err.to_string()))?,
+            split_timings: search_response.split_timings,
         })
     }
 }
+
+impl SearchResponseRest {
+    /// Reshapes the hits into flat objects. Each entry of the projection maps the name of an
+    /// output field to the dot-separated path of the value selected in the hit, e.g.
+    /// `service: resource.service.name`. Paths that select no value are left out of the hit.
+    pub fn project_hits(&mut self, projection: &HashMap<String, String>) -> crate::Result<()> {
+        let mut projection_paths: Vec<(&str, Vec<&str>)> = Vec::with_capacity(projection.len());
+        for (output_field_name, path) in projection {
+            let path_segments: Vec<&str> = path.split('.').collect();
+            if output_field_name.is_empty()
+                || path_segments.iter().any(|segment| segment.is_empty())
+            {
+                return Err(SearchError::InvalidArgument(format!(
+                    "Invalid projection `{output_field_name}: {path}`: output field names and \
+                     path segments must not be empty."
+                )));
+            }
+            projection_paths.push((output_field_name.as_str(), path_segments));
+        }
+        for hit in &mut self.hits {
+            let mut projected_hit = JsonMap::with_capacity(projection_paths.len());
+            for (output_field_name, path_segments) in &projection_paths {
+                if let Some(value) = select_path(hit, path_segments) {
+                    projected_hit.insert(output_field_name.to_string(), value.clone());
+                }
+            }
+            *hit = JsonValue::Object(projected_hit);
+        }
+        Ok(())
+    }
+}
+
+fn select_path<'a>(value: &'a JsonValue, path_segments: &[&str]) -> Option<&'a JsonValue> {
+    path_segments
+        .iter()
+        .try_fold(value, |value, segment| value.as_object()?.get(*segment))
+        .filter(|value| !value.is_null())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_project_hits() {
+        let mut search_response_rest = SearchResponseRest {
+            num_hits: 2,
+            hits: vec![
+                json!({
+                    "resource": {"service": {"name": "payments"}},
+                    "severity_text": "ERROR",
+                    "body": {"message": "Payment declined."},
+                }),
+                json!({
+                    "resource": {"service": {"name": "checkout"}},
+                    "body": "Order placed.",
+                }),
+            ],
+            snippets: None,
+            elapsed_time_micros: 0,
+            errors: Vec::new(),
+            aggregations: None,
+            split_timings: Vec::new(),
+        };
+        let projection = HashMap::from_iter([
+            ("service".to_string(), "resource.service.name".to_string()),
+            ("level".to_string(), "severity_text".to_string()),
+            ("message".to_string(), "body.message".to_string()),
+        ]);
+        search_response_rest.project_hits(&projection).unwrap();
+        assert_eq!(
+            search_response_rest.hits,
+            vec![
+                json!({"service": "payments", "level": "ERROR", "message": "Payment declined."}),
+                json!({"service": "checkout"}),
+            ]
+        );
+
+        let projection =
+            HashMap::from_iter([("service".to_string(), "resource..name".to_string())]);
+        let error = search_response_rest.project_hits(&projection).unwrap_err();
+        assert!(matches!(error, SearchError::InvalidArgument(_)));
+    }
+}
@@ -86,6 +86,9 @@ async fn leaf_search_results_stream(
     splits: Vec<SplitIdAndFooterOffsets>,
     doc_mapper: Arc<dyn DocMapper>,
 ) -> impl futures::Stream<Item = crate::Result<LeafSearchStreamResponse>> + Sync + Send + 'static {
+    searcher_context
+        .split_access_tracker
+        .record_searches(&request.index_id, &splits);
     let max_num_concurrent_split_streams = searcher_context
         .searcher_config
         .max_num_concurrent_split_streams;
@@ -487,6 +490,7 @@ mod tests {
             fast_field: "ts".to_string(),
             output_format: 0,
             partition_by_field: None,
+            role: None,
         };
         let splits = test_sandbox.metastore().list_all_splits(index_id).await?;
         let splits_offsets = splits
@@ -495,6 +499,7 @@ mod tests {
                 split_id: split_meta.split_id().to_string(),
                 split_footer_start: split_meta.split_metadata.footer_offsets.start,
                 split_footer_end: split_meta.split_metadata.footer_offsets.end,
+                storage_uri: String::new(),
             })
             .collect();
         let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
@@ -567,6 +572,7 @@ mod tests {
             fast_field: "ts".to_string(),
             output_format: 0,
             partition_by_field: None,
+            role: None,
         };
         let splits = test_sandbox.metastore().list_all_splits(index_id).await?;
         let splits_offsets = splits
@@ -575,6 +581,7 @@ mod tests {
                 split_id: split_meta.split_id().to_string(),
                 split_footer_start: split_meta.split_metadata.footer_offsets.start,
                 split_footer_end: split_meta.split_metadata.footer_offsets.end,
+                storage_uri: String::new(),
             })
             .collect();
         let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
@@ -624,6 +631,7 @@ mod tests {
             fast_field: "app".to_string(),
             output_format: 0,
             partition_by_field: None,
+            role: None,
         };
         let splits = test_sandbox.metastore().list_all_splits(index_id).await?;
         let splits_offsets = splits
@@ -632,6 +640,7 @@ mod tests {
                 split_id: split_meta.split_id().to_string(),
                 split_footer_start: split_meta.split_metadata.footer_offsets.start,
                 split_footer_end: split_meta.split_metadata.footer_offsets.end,
+                storage_uri: String::new(),
             })
             .collect();
         let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
@@ -723,6 +732,7 @@ mod tests {
             fast_field: "fast_field".to_string(),
             output_format: 1,
             partition_by_field: Some(String::from("partition_by_fast_field")),
+            role: None,
         };
         let splits = test_sandbox.metastore().list_all_splits(index_id).await?;
         let splits_offsets = splits
@@ -731,6 +741,7 @@ mod tests {
                 split_id: split_meta.split_id().to_string(),
                 split_footer_start: split_meta.split_metadata.footer_offsets.start,
                 split_footer_end: split_meta.split_metadata.footer_offsets.end,
+                storage_uri: String::new(),
             })
             .collect();
         let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
@@ -22,28 +22,57 @@ use std::collections::HashSet;
 use bytes::Bytes;
 use futures::{StreamExt, TryStreamExt};
 use quickwit_config::build_doc_mapper;
+use quickwit_doc_mapper::tag_pruning::extract_tags_from_search_request;
 use quickwit_metastore::Metastore;
 use quickwit_proto::{LeafSearchStreamRequest, SearchRequest, SearchStreamRequest};
+use quickwit_storage::StorageUriResolver;
 use tokio_stream::StreamMap;
 use tracing::*;
 
+use crate::bloom_filter_pruning::prune_splits_with_bloom_filters;
 use crate::cluster_client::ClusterClient;
+use crate::field_access::{field_access_policy_for_role, restrict_search_stream_request};
 use crate::root::SearchJob;
-use crate::{list_relevant_splits, SearchClientPool, SearchError, SearchServiceClient};
+use crate::{
+    list_relevant_splits, SearchClientPool, SearchError, SearchQuotas, SearchServiceClient,
+};
 
 /// Perform a distributed search stream.
-#[instrument(skip(metastore, cluster_client, client_pool))]
+#[instrument(skip(
+    metastore,
+    storage_resolver,
+    cluster_client,
+    client_pool,
+    search_quotas
+))]
 pub async fn root_search_stream(
-    search_stream_request: SearchStreamRequest,
+    mut search_stream_request: SearchStreamRequest,
     metastore: &dyn Metastore,
+    storage_resolver: &StorageUriResolver,
     cluster_client: ClusterClient,
     client_pool: &SearchClientPool,
+    search_quotas: &SearchQuotas,
 ) -> crate::Result<impl futures::Stream<Item = crate::Result<Bytes>>> {
     // TODO: building a search request should not be necessary for listing splits.
     // This needs some refactoring: relevant splits, metadata_map, jobs...
 
+    let index_metadata = metastore
+        .index_metadata(&search_stream_request.index_id)
+        .await?;
+    if let Some(field_access_policy) = field_access_policy_for_role(
+        &index_metadata.search_settings,
+        search_stream_request.role.as_deref(),
+    )? {
+        restrict_search_stream_request(
+            field_access_policy,
+            &index_metadata.search_settings.default_search_fields,
+            &mut search_stream_request,
+        )?;
+    }
+    let search_quota_permit =
+        search_quotas.try_admit(&index_metadata.index_id, &index_metadata.search_settings)?;
+
     let search_request = SearchRequest::from(search_stream_request.clone());
-    let index_metadata = metastore.index_metadata(&search_request.index_id).await?;
     let split_metadatas = list_relevant_splits(&search_request, metastore).await?;
     let doc_mapper = build_doc_mapper(
         &index_metadata.doc_mapping,
@@ -57,6 +86,25 @@ pub async fn root_search_stream(
     // Validates the query by effectively building it against the current schema.
     doc_mapper.query(doc_mapper.schema(), &search_request)?;
 
+    let split_metadatas = if doc_mapper.bloom_filter_field_names().is_empty() {
+        split_metadatas
+    } else {
+        let index_storage = storage_resolver.resolve(&index_metadata.index_uri)?;
+        prune_splits_with_bloom_filters(
+            extract_tags_from_search_request(&search_request)?,
+            &*doc_mapper,
+            index_storage,
+            split_metadatas,
+        )
+        .await?
+    };
+
+    let scanned_num_bytes: u64 = split_metadatas
+        .iter()
+        .map(|split_metadata| split_metadata.footer_offsets.end)
+        .sum();
+    search_quotas.record_scanned_bytes(&index_metadata.index_id, scanned_num_bytes);
+
     let doc_mapper_str = serde_json::to_string(&doc_mapper).map_err(|err| {
         SearchError::InternalError(format!("Failed to serialize doc mapper: Cause {}", err))
     })?;
@@ -81,7 +129,11 @@ pub async fn root_search_stream(
         stream_map.insert(leaf_ord, leaf_stream);
     }
     Ok(stream_map
-        .map(|(_leaf_ord, result)| result)
+        .map(move |(_leaf_ord, result)| {
+            // The search counts as running until the stream is dropped.
+            let _search_quota_permit = &search_quota_permit;
+            result
+        })
         .map_ok(|leaf_response| Bytes::from(leaf_response.data)))
 }
 
@@ -124,6 +176,7 @@ mod tests {
             fast_field: "timestamp".to_string(),
             output_format: OutputFormat::Csv as i32,
             partition_by_field: None,
+            role: None,
         };
         let mut metastore = MockMetastore::new();
         metastore
@@ -159,17 +212,97 @@ mod tests {
         let client_pool = SearchClientPool::from_mocks(vec![Arc::new(mock_search_service)]).await?;
 
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let result: Vec<Bytes> =
-            root_search_stream(request, &metastore, cluster_client, &client_pool)
-                .await?
-                .try_collect()
-                .await?;
+        let result: Vec<Bytes> = root_search_stream(
+            request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?
+        .try_collect()
+        .await?;
         assert_eq!(result.len(), 2);
         assert_eq!(&result[0], &b"123"[..]);
         assert_eq!(&result[1], &b"456"[..]);
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_root_search_stream_enforces_search_quotas() -> anyhow::Result<()> {
+        let request = quickwit_proto::SearchStreamRequest {
+            index_id: "test-index".to_string(),
+            query: "test".to_string(),
+            search_fields: vec!["body".to_string()],
+            snippet_fields: vec![],
+            start_timestamp: None,
+            end_timestamp: None,
+            fast_field: "timestamp".to_string(),
+            output_format: OutputFormat::Csv as i32,
+            partition_by_field: None,
+            role: None,
+        };
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+                index_metadata.search_settings.max_concurrent_searches = Some(1);
+                Ok(index_metadata)
+            });
+        metastore.expect_list_splits().returning(
+            |_index_id: &str, _split_state: SplitState, _time_range: Option<Range<i64>>, _tags| {
+                Ok(vec![mock_split("split1")])
+            },
+        );
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_leaf_search_stream().returning(
+            |_leaf_search_req: quickwit_proto::LeafSearchStreamRequest| {
+                let (_result_sender, result_receiver) = tokio::sync::mpsc::unbounded_channel();
+                Ok(UnboundedReceiverStream::new(result_receiver))
+            },
+        );
+        let client_pool = SearchClientPool::from_mocks(vec![Arc::new(mock_search_service)]).await?;
+        let search_quotas = SearchQuotas::default();
+
+        let stream = root_search_stream(
+            request.clone(),
+            &metastore,
+            &StorageUriResolver::for_test(),
+            ClusterClient::new(client_pool.clone()),
+            &client_pool,
+            &search_quotas,
+        )
+        .await?;
+        // The first search stream is still running.
+        let search_error = root_search_stream(
+            request.clone(),
+            &metastore,
+            &StorageUriResolver::for_test(),
+            ClusterClient::new(client_pool.clone()),
+            &client_pool,
+            &search_quotas,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(search_error, SearchError::TooManyRequests(_)));
+
+        drop(stream);
+        root_search_stream(
+            request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            ClusterClient::new(client_pool.clone()),
+            &client_pool,
+            &search_quotas,
+        )
+        .await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_root_search_stream_single_split_partitionned() -> anyhow::Result<()> {
         let request = quickwit_proto::SearchStreamRequest {
@@ -182,6 +315,7 @@ mod tests {
             fast_field: "timestamp".to_string(),
             output_format: OutputFormat::Csv as i32,
             partition_by_field: Some("timestamp".to_string()),
+            role: None,
         };
         let mut metastore = MockMetastore::new();
         metastore
@@ -216,7 +350,15 @@ mod tests {
         drop(result_sender);
         let client_pool = SearchClientPool::from_mocks(vec![Arc::new(mock_search_service)]).await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let stream = root_search_stream(request, &metastore, cluster_client, &client_pool).await?;
+        let stream = root_search_stream(
+            request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         let result: Vec<_> = stream.try_collect().await?;
         assert_eq!(result.len(), 2);
         assert_eq!(&result[0], &b"123"[..]);
@@ -236,6 +378,7 @@ mod tests {
             fast_field: "timestamp".to_string(),
             output_format: OutputFormat::Csv as i32,
             partition_by_field: None,
+            role: None,
         };
         let mut metastore = MockMetastore::new();
         metastore
@@ -278,7 +421,15 @@ mod tests {
         drop(result_sender);
         let client_pool = SearchClientPool::from_mocks(vec![Arc::new(mock_search_service)]).await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let stream = root_search_stream(request, &metastore, cluster_client, &client_pool).await?;
+        let stream = root_search_stream(
+            request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         let result: Result<Vec<_>, SearchError> = stream.try_collect().await;
         assert_eq!(result.is_err(), true);
         assert_eq!(result.unwrap_err().to_string(), "Internal error: `error`.");
@@ -316,10 +467,13 @@ mod tests {
                 fast_field: "timestamp".to_string(),
                 output_format: OutputFormat::Csv as i32,
                 partition_by_field: Some("timestamp".to_string()),
+                role: None,
             },
             &metastore,
+            &StorageUriResolver::for_test(),
             ClusterClient::new(client_pool.clone()),
             &client_pool,
+            &SearchQuotas::default(),
         )
         .await
         .is_err());
@@ -335,10 +489,13 @@ mod tests {
                 fast_field: "timestamp".to_string(),
                 output_format: OutputFormat::Csv as i32,
                 partition_by_field: Some("timestamp".to_string()),
+                role: None,
             },
             &metastore,
+            &StorageUriResolver::for_test(),
             ClusterClient::new(client_pool.clone()),
-            &client_pool
+            &client_pool,
+            &SearchQuotas::default(),
         )
         .await
         .is_err());
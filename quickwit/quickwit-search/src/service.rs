@@ -19,11 +19,13 @@
 
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use quickwit_common::events::{record_event, Event, EventType};
 use quickwit_common::uri::Uri;
-use quickwit_config::SearcherConfig;
+use quickwit_config::{SearcherConfig, SplitCacheConfig};
 use quickwit_doc_mapper::DocMapper;
 use quickwit_metastore::Metastore;
 use quickwit_proto::{
@@ -34,10 +36,19 @@ use quickwit_proto::{
 use quickwit_storage::{Cache, MemorySizedCache, QuickwitCache, StorageUriResolver};
 use tokio::sync::Semaphore;
 use tokio_stream::wrappers::UnboundedReceiverStream;
-use tracing::info;
+use tracing::{error, info, warn};
 
+use crate::memory_budget::SearchMemoryAccountant;
 use crate::search_stream::{leaf_search_stream, root_search_stream};
-use crate::{fetch_docs, leaf_search, root_search, ClusterClient, SearchClientPool, SearchError};
+use crate::split_access_stats::SplitAccessTracker;
+use crate::split_cache::SplitCache;
+use crate::{
+    fetch_docs, leaf_search, root_search, ClusterClient, SearchClientPool, SearchError,
+    SearchQuotas,
+};
+
+/// Root search requests slower than this threshold are recorded as slow query events.
+const SLOW_QUERY_THRESHOLD: Duration = Duration::from_secs(5);
 
 #[derive(Clone)]
 /// The search service implementation.
@@ -47,6 +58,7 @@ pub struct SearchServiceImpl {
     cluster_client: ClusterClient,
     client_pool: SearchClientPool,
     searcher_context: Arc<SearcherContext>,
+    search_quotas: SearchQuotas,
 }
 
 /// Trait representing a search service.
@@ -72,6 +84,7 @@ pub trait SearchService: 'static + Send + Sync {
     /// it to other nodes.
     /// - it should be applied on the given subset of splits
     /// - hit content is not fetched, and we instead return a so-called `PartialHit`.
+    /// - field access policies are not enforced: they are applied by the root search.
     async fn leaf_search(&self, request: LeafSearchRequest) -> crate::Result<LeafSearchResponse>;
 
     /// Fetches the documents contents from the document store.
@@ -107,8 +120,13 @@ impl SearchServiceImpl {
             cluster_client,
             client_pool,
             searcher_context,
+            search_quotas: SearchQuotas::default(),
         }
     }
+
+    pub(crate) fn searcher_context(&self) -> Arc<SearcherContext> {
+        self.searcher_context.clone()
+    }
 }
 
 fn deserialize_doc_mapper(doc_mapper_str: &str) -> crate::Result<Arc<dyn DocMapper>> {
@@ -124,11 +142,22 @@ impl SearchService for SearchServiceImpl {
         let search_result = root_search(
             &search_request,
             self.metastore.as_ref(),
+            &self.storage_uri_resolver,
             &self.cluster_client,
             &self.client_pool,
+            &self.search_quotas,
         )
         .await?;
-
+        let elapsed = Duration::from_micros(search_result.elapsed_time_micros);
+        if elapsed > SLOW_QUERY_THRESHOLD {
+            record_event(
+                Event::new(EventType::SlowQuery, "Slow search request.")
+                    .with_index_id(&search_request.index_id)
+                    .with_attribute("query", &search_request.query)
+                    .with_attribute("elapsed_millis", elapsed.as_millis())
+                    .with_attribute("num_hits", search_result.num_hits),
+            );
+        }
         Ok(search_result)
     }
 
@@ -191,8 +220,10 @@ impl SearchService for SearchServiceImpl {
         let data = root_search_stream(
             stream_request,
             self.metastore.as_ref(),
+            &self.storage_uri_resolver,
             self.cluster_client.clone(),
             &self.client_pool,
+            &self.search_quotas,
         )
         .await?;
         Ok(Box::pin(data))
@@ -232,10 +263,18 @@ pub struct SearcherContext {
     pub leaf_search_split_semaphore: Semaphore,
     /// Counting semaphore to limit concurrent split stream requests.
     pub split_stream_semaphore: Semaphore,
+    /// Counting semaphore, in bytes, to limit the memory used by the documents being fetched.
+    pub fetch_docs_memory_semaphore: Semaphore,
+    /// Accountant of the memory estimated to be used by the leaf searches in progress.
+    pub search_memory_accountant: SearchMemoryAccountant,
     /// Split footer cache.
     pub split_footer_cache: MemorySizedCache<String>,
     /// Fast fields cache.
     pub fast_fields_cache: Arc<dyn Cache>,
+    /// Local cache of the most searched splits, if enabled.
+    pub split_cache_opt: Option<Arc<SplitCache>>,
+    /// Searches received by each split, reported periodically to the metastore.
+    pub split_access_tracker: Arc<SplitAccessTracker>,
 }
 
 impl SearcherContext {
@@ -249,15 +288,48 @@ impl SearcherContext {
             Semaphore::new(searcher_config.max_num_concurrent_split_searches);
         let split_stream_semaphore =
             Semaphore::new(searcher_config.max_num_concurrent_split_streams);
+        let fetch_docs_memory_semaphore = Semaphore::new(
+            (searcher_config.fetch_docs_memory_budget.get_bytes() as usize)
+                .min(Semaphore::MAX_PERMITS),
+        );
+        let search_memory_accountant =
+            SearchMemoryAccountant::new(searcher_config.search_memory_budget.get_bytes() as usize);
         let fast_field_cache_capacity =
             searcher_config.fast_field_cache_capacity.get_bytes() as usize;
         let storage_long_term_cache = Arc::new(QuickwitCache::new(fast_field_cache_capacity));
+        let split_cache_opt = searcher_config
+            .split_cache
+            .as_ref()
+            .and_then(open_split_cache)
+            .map(Arc::new);
         Self {
             searcher_config,
             split_footer_cache: global_split_footer_cache,
             leaf_search_split_semaphore,
             split_stream_semaphore,
+            fetch_docs_memory_semaphore,
+            search_memory_accountant,
             fast_fields_cache: storage_long_term_cache,
+            split_cache_opt,
+            split_access_tracker: Arc::new(SplitAccessTracker::default()),
+        }
+    }
+}
+
+/// Opens the split cache, falling back to searching the splits from the index storage only if
+/// the cache cannot be opened.
+fn open_split_cache(split_cache_config: &SplitCacheConfig) -> Option<SplitCache> {
+    let root_path = if let Some(root_path) = split_cache_config.root_path.clone() {
+        root_path
+    } else {
+        warn!("The split cache is disabled because its root path is not set.");
+        return None;
+    };
+    match SplitCache::open(root_path, split_cache_config) {
+        Ok(split_cache) => Some(split_cache),
+        Err(error) => {
+            error!(error=?error, "Failed to open the split cache, the split cache is disabled.");
+            None
         }
     }
 }
@@ -0,0 +1,177 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use quickwit_metastore::Metastore;
+use quickwit_proto::SplitIdAndFooterOffsets;
+use tantivy::time::OffsetDateTime;
+use tracing::warn;
+
+/// Interval at which the searches recorded by a searcher are reported to the metastore.
+const REPORT_INTERVAL: Duration = if cfg!(test) {
+    Duration::from_secs(1)
+} else {
+    Duration::from_secs(5 * 60)
+};
+
+/// Counts the searches received by each split since the last report, per index.
+#[derive(Default)]
+pub struct SplitAccessTracker {
+    num_searches_per_index: Mutex<HashMap<String, HashMap<String, u64>>>,
+}
+
+impl SplitAccessTracker {
+    /// Records one search of each of the given splits of an index.
+    pub fn record_searches(&self, index_id: &str, splits: &[SplitIdAndFooterOffsets]) {
+        if splits.is_empty() {
+            return;
+        }
+        let mut num_searches_per_index = self
+            .num_searches_per_index
+            .lock()
+            .expect("The lock should never be poisoned.");
+        let num_searches_per_split = num_searches_per_index
+            .entry(index_id.to_string())
+            .or_default();
+        for split in splits {
+            *num_searches_per_split
+                .entry(split.split_id.clone())
+                .or_default() += 1;
+        }
+    }
+
+    /// Takes the searches recorded since the last call.
+    fn take(&self) -> HashMap<String, HashMap<String, u64>> {
+        let mut num_searches_per_index = self
+            .num_searches_per_index
+            .lock()
+            .expect("The lock should never be poisoned.");
+        std::mem::take(&mut *num_searches_per_index)
+    }
+}
+
+/// Periodically reports the searches recorded by a [`SplitAccessTracker`] to the metastore, where
+/// they feed the access statistics used to move splits between storage tiers.
+pub(crate) struct SplitAccessStatsReporter {
+    metastore: Arc<dyn Metastore>,
+    split_access_tracker: Arc<SplitAccessTracker>,
+}
+
+impl SplitAccessStatsReporter {
+    pub fn new(
+        metastore: Arc<dyn Metastore>,
+        split_access_tracker: Arc<SplitAccessTracker>,
+    ) -> Self {
+        Self {
+            metastore,
+            split_access_tracker,
+        }
+    }
+
+    /// Spawns a task that periodically reports the recorded searches.
+    pub fn spawn(self) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REPORT_INTERVAL);
+            // The first tick completes immediately.
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                self.report().await;
+            }
+        });
+    }
+
+    async fn report(&self) {
+        let search_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        for (index_id, num_searches_per_split) in self.split_access_tracker.take() {
+            // Failed reports are dropped: access statistics are best-effort.
+            if let Err(error) = self
+                .metastore
+                .record_split_searches(&index_id, num_searches_per_split, search_timestamp)
+                .await
+            {
+                warn!(index_id=%index_id, error=?error, "Failed to report split searches.");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::MockMetastore;
+
+    use super::*;
+
+    fn split_offsets(split_id: &str) -> SplitIdAndFooterOffsets {
+        SplitIdAndFooterOffsets {
+            split_id: split_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_split_access_tracker() {
+        let split_access_tracker = SplitAccessTracker::default();
+        split_access_tracker.record_searches("index-1", &[]);
+        assert!(split_access_tracker.take().is_empty());
+
+        split_access_tracker.record_searches(
+            "index-1",
+            &[split_offsets("split-1"), split_offsets("split-2")],
+        );
+        split_access_tracker.record_searches("index-1", &[split_offsets("split-1")]);
+        split_access_tracker.record_searches("index-2", &[split_offsets("split-3")]);
+
+        let num_searches_per_index = split_access_tracker.take();
+        assert_eq!(num_searches_per_index.len(), 2);
+        assert_eq!(
+            num_searches_per_index["index-1"],
+            HashMap::from_iter([("split-1".to_string(), 2), ("split-2".to_string(), 1)])
+        );
+        assert_eq!(
+            num_searches_per_index["index-2"],
+            HashMap::from_iter([("split-3".to_string(), 1)])
+        );
+        assert!(split_access_tracker.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_split_access_stats_reporter() {
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_record_split_searches()
+            .withf(|index_id, num_searches_per_split, _search_timestamp| {
+                index_id == "index-1"
+                    && num_searches_per_split == &HashMap::from_iter([("split-1".to_string(), 1)])
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let split_access_tracker = Arc::new(SplitAccessTracker::default());
+        split_access_tracker.record_searches("index-1", &[split_offsets("split-1")]);
+
+        let reporter =
+            SplitAccessStatsReporter::new(Arc::new(metastore), split_access_tracker.clone());
+        reporter.report().await;
+        // Nothing left to report.
+        reporter.report().await;
+    }
+}
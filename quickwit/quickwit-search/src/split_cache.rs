@@ -0,0 +1,413 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use lru::LruCache;
+use quickwit_common::split_file;
+use quickwit_config::SplitCacheConfig;
+use quickwit_directories::BundleDirectory;
+use quickwit_storage::Storage;
+use tantivy::directory::MmapDirectory;
+use tantivy::Directory;
+use tracing::{info, warn};
+
+use crate::metrics::SEARCH_METRICS;
+
+/// Name of the split cache directory when no root path is configured, relative to the data
+/// directory.
+pub(crate) const SPLIT_CACHE_DIR_NAME: &str = "searcher-split-cache";
+
+/// Maximum number of not yet cached splits for which the number of searches is tracked.
+const MAX_NUM_TRACKED_SPLITS: usize = 100_000;
+
+/// Keeps the most searched splits on the local disk of the searcher and serves them through
+/// memory-mapped files.
+///
+/// A split is downloaded in the background once it has been searched
+/// `num_searches_before_caching` times. The least recently searched splits are evicted when the
+/// cache exceeds `max_num_bytes` or `max_num_splits`.
+pub struct SplitCache {
+    root_path: PathBuf,
+    mmap_directory: MmapDirectory,
+    max_num_bytes: u64,
+    max_num_splits: usize,
+    num_searches_before_caching: usize,
+    inner: Mutex<InnerSplitCache>,
+}
+
+struct InnerSplitCache {
+    /// Splits available on disk, in LRU order, along with their size in bytes.
+    cached_splits: LruCache<String, u64>,
+    num_bytes: u64,
+    /// Number of searches received by the splits that are not cached yet.
+    num_searches_per_split: LruCache<String, usize>,
+    /// Splits being downloaded.
+    downloading_splits: HashSet<String>,
+}
+
+impl InnerSplitCache {
+    /// Removes the least recently searched splits until the cache fits its limits and returns
+    /// their IDs.
+    fn evict(&mut self, max_num_bytes: u64, max_num_splits: usize) -> Vec<String> {
+        let mut evicted_split_ids = Vec::new();
+        while self.num_bytes > max_num_bytes || self.cached_splits.len() > max_num_splits {
+            if let Some((split_id, num_bytes)) = self.cached_splits.pop_lru() {
+                self.num_bytes -= num_bytes;
+                evicted_split_ids.push(split_id);
+            } else {
+                break;
+            }
+        }
+        evicted_split_ids
+    }
+
+    fn update_metrics(&self) {
+        SEARCH_METRICS
+            .split_cache_num_bytes
+            .set(self.num_bytes as i64);
+        SEARCH_METRICS
+            .split_cache_num_splits
+            .set(self.cached_splits.len() as i64);
+    }
+}
+
+impl SplitCache {
+    /// Opens the split cache located in `root_path`, reusing the splits cached by a previous
+    /// run of the searcher.
+    pub fn open(root_path: PathBuf, split_cache_config: &SplitCacheConfig) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&root_path).with_context(|| {
+            format!(
+                "Failed to create split cache directory `{}`.",
+                root_path.display()
+            )
+        })?;
+        let mut cached_splits = LruCache::unbounded();
+        let mut num_bytes = 0;
+
+        for dir_entry_res in std::fs::read_dir(&root_path)? {
+            let dir_entry = dir_entry_res?;
+            if !dir_entry.file_type()?.is_file() {
+                continue;
+            }
+            let path = dir_entry.path();
+            let file_name = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .unwrap_or_default();
+            if let Some(split_id) = file_name.strip_suffix(".split") {
+                let split_num_bytes = dir_entry.metadata()?.len();
+                cached_splits.put(split_id.to_string(), split_num_bytes);
+                num_bytes += split_num_bytes;
+            } else if file_name.ends_with(".split.tmp") {
+                // Leftover of a download interrupted by a restart.
+                std::fs::remove_file(&path)?;
+            } else {
+                warn!(path=%path.display(), "Ignoring unknown file in split cache directory.");
+            }
+        }
+        let mmap_directory = MmapDirectory::open(&root_path)?;
+        let split_cache = SplitCache {
+            root_path,
+            mmap_directory,
+            max_num_bytes: split_cache_config.max_num_bytes.get_bytes() as u64,
+            max_num_splits: split_cache_config.max_num_splits,
+            num_searches_before_caching: split_cache_config.num_searches_before_caching,
+            inner: Mutex::new(InnerSplitCache {
+                cached_splits,
+                num_bytes,
+                num_searches_per_split: LruCache::new(
+                    NonZeroUsize::new(MAX_NUM_TRACKED_SPLITS).unwrap(),
+                ),
+                downloading_splits: HashSet::new(),
+            }),
+        };
+        let evicted_split_ids = {
+            let mut inner = split_cache.inner.lock().unwrap();
+            let evicted_split_ids =
+                inner.evict(split_cache.max_num_bytes, split_cache.max_num_splits);
+            inner.update_metrics();
+            evicted_split_ids
+        };
+        for split_id in evicted_split_ids {
+            split_cache.delete_split_file(&split_id);
+        }
+        info!(
+            root_path=%split_cache.root_path.display(),
+            num_splits=split_cache.inner.lock().unwrap().cached_splits.len(),
+            "Opened split cache."
+        );
+        Ok(split_cache)
+    }
+
+    /// Returns the directory of the split if it is cached on disk.
+    pub fn split_directory(&self, split_id: &str) -> Option<BundleDirectory> {
+        if self
+            .inner
+            .lock()
+            .unwrap()
+            .cached_splits
+            .get(split_id)
+            .is_none()
+        {
+            SEARCH_METRICS.split_cache_misses_total.inc();
+            return None;
+        }
+        let split_path = PathBuf::from(split_file(split_id));
+        let split_directory_res = self
+            .mmap_directory
+            .open_read(&split_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file_slice| Ok(BundleDirectory::open_split(file_slice)?));
+        match split_directory_res {
+            Ok(split_directory) => {
+                SEARCH_METRICS.split_cache_hits_total.inc();
+                Some(split_directory)
+            }
+            Err(error) => {
+                // The split was evicted in the meantime or its file is corrupted.
+                warn!(split_id=%split_id, error=?error, "Failed to open cached split.");
+                SEARCH_METRICS.split_cache_misses_total.inc();
+                self.remove_split(split_id);
+                None
+            }
+        }
+    }
+
+    /// Records a search on a split that is not cached, and downloads the split in the
+    /// background once it has been searched `num_searches_before_caching` times.
+    pub fn record_search(
+        self: &Arc<Self>,
+        split_id: &str,
+        split_num_bytes: u64,
+        storage: Arc<dyn Storage>,
+    ) {
+        if !self.record_search_and_admit(split_id, split_num_bytes) {
+            return;
+        }
+        let split_cache = self.clone();
+        let split_id = split_id.to_string();
+        tokio::spawn(async move {
+            if let Err(error) = split_cache.download_split(&split_id, &*storage).await {
+                warn!(split_id=%split_id, error=?error, "Failed to download split to the split cache.");
+            }
+            split_cache
+                .inner
+                .lock()
+                .unwrap()
+                .downloading_splits
+                .remove(&split_id);
+        });
+    }
+
+    /// Increments the number of searches of the split and returns whether the split should be
+    /// downloaded. If so, the split is marked as being downloaded.
+    fn record_search_and_admit(&self, split_id: &str, split_num_bytes: u64) -> bool {
+        if self.max_num_splits == 0 || split_num_bytes > self.max_num_bytes {
+            return false;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if inner.cached_splits.contains(split_id) || inner.downloading_splits.contains(split_id) {
+            return false;
+        }
+        let num_searches =
+            if let Some(num_searches) = inner.num_searches_per_split.get_mut(split_id) {
+                *num_searches += 1;
+                *num_searches
+            } else {
+                inner.num_searches_per_split.put(split_id.to_string(), 1);
+                1
+            };
+        if num_searches < self.num_searches_before_caching {
+            return false;
+        }
+        inner.num_searches_per_split.pop(split_id);
+        inner.downloading_splits.insert(split_id.to_string());
+        true
+    }
+
+    async fn download_split(&self, split_id: &str, storage: &dyn Storage) -> anyhow::Result<()> {
+        let split_file = split_file(split_id);
+        let split_path = self.root_path.join(&split_file);
+        let tmp_split_path = self.root_path.join(format!("{split_file}.tmp"));
+
+        if let Err(error) = storage
+            .copy_to_file(Path::new(&split_file), &tmp_split_path)
+            .await
+        {
+            let _ = tokio::fs::remove_file(&tmp_split_path).await;
+            return Err(error.into());
+        }
+        let split_num_bytes = tokio::fs::metadata(&tmp_split_path).await?.len();
+        tokio::fs::rename(&tmp_split_path, &split_path).await?;
+
+        let evicted_split_ids = {
+            let mut inner = self.inner.lock().unwrap();
+            inner
+                .cached_splits
+                .put(split_id.to_string(), split_num_bytes);
+            inner.num_bytes += split_num_bytes;
+            let evicted_split_ids = inner.evict(self.max_num_bytes, self.max_num_splits);
+            inner.update_metrics();
+            evicted_split_ids
+        };
+        for evicted_split_id in evicted_split_ids {
+            self.delete_split_file(&evicted_split_id);
+        }
+        SEARCH_METRICS.split_cache_downloads_total.inc();
+        Ok(())
+    }
+
+    fn remove_split(&self, split_id: &str) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(split_num_bytes) = inner.cached_splits.pop(split_id) {
+                inner.num_bytes -= split_num_bytes;
+            }
+            inner.update_metrics();
+        }
+        self.delete_split_file(split_id);
+    }
+
+    /// Deletes the file of an evicted split. Searches still reading the split keep their
+    /// memory map, which remains valid until it is dropped.
+    fn delete_split_file(&self, split_id: &str) {
+        let split_path = self.root_path.join(split_file(split_id));
+        if let Err(error) = std::fs::remove_file(&split_path) {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                warn!(split_path=%split_path.display(), error=?error, "Failed to delete cached split.");
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn cached_split_ids(&self) -> Vec<String> {
+        let inner = self.inner.lock().unwrap();
+        let mut split_ids: Vec<String> = inner
+            .cached_splits
+            .iter()
+            .map(|(split_id, _)| split_id.clone())
+            .collect();
+        split_ids.sort();
+        split_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byte_unit::Byte;
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+
+    fn split_cache_config(max_num_bytes: u64, max_num_splits: usize) -> SplitCacheConfig {
+        SplitCacheConfig {
+            root_path: None,
+            max_num_bytes: Byte::from_bytes(max_num_bytes as u128),
+            max_num_splits,
+            num_searches_before_caching: 2,
+        }
+    }
+
+    #[test]
+    fn test_split_cache_admission() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let split_cache =
+            SplitCache::open(temp_dir.path().to_path_buf(), &split_cache_config(100, 10)).unwrap();
+        assert!(!split_cache.record_search_and_admit("split-1", 10));
+        assert!(split_cache.record_search_and_admit("split-1", 10));
+        // The split is being downloaded.
+        assert!(!split_cache.record_search_and_admit("split-1", 10));
+        // The split is larger than the cache.
+        assert!(!split_cache.record_search_and_admit("split-2", 101));
+        assert!(!split_cache.record_search_and_admit("split-2", 101));
+    }
+
+    #[tokio::test]
+    async fn test_split_cache_eviction() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = RamStorage::builder()
+            .put("split-1.split", &[0u8; 40])
+            .put("split-2.split", &[0u8; 40])
+            .put("split-3.split", &[0u8; 40])
+            .build();
+        let split_cache =
+            SplitCache::open(temp_dir.path().to_path_buf(), &split_cache_config(100, 10)).unwrap();
+        split_cache
+            .download_split("split-1", &storage)
+            .await
+            .unwrap();
+        split_cache
+            .download_split("split-2", &storage)
+            .await
+            .unwrap();
+        assert_eq!(split_cache.cached_split_ids(), vec!["split-1", "split-2"]);
+
+        // Touch `split-1` so that `split-2` becomes the least recently used split.
+        split_cache
+            .inner
+            .lock()
+            .unwrap()
+            .cached_splits
+            .get("split-1");
+        split_cache
+            .download_split("split-3", &storage)
+            .await
+            .unwrap();
+        assert_eq!(split_cache.cached_split_ids(), vec!["split-1", "split-3"]);
+        assert!(temp_dir.path().join("split-1.split").exists());
+        assert!(!temp_dir.path().join("split-2.split").exists());
+        assert!(temp_dir.path().join("split-3.split").exists());
+    }
+
+    #[tokio::test]
+    async fn test_split_cache_reopen() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = RamStorage::builder()
+            .put("split-1.split", &[0u8; 40])
+            .put("split-2.split", &[0u8; 40])
+            .build();
+        {
+            let split_cache =
+                SplitCache::open(temp_dir.path().to_path_buf(), &split_cache_config(100, 10))
+                    .unwrap();
+            split_cache
+                .download_split("split-1", &storage)
+                .await
+                .unwrap();
+            split_cache
+                .download_split("split-2", &storage)
+                .await
+                .unwrap();
+        }
+        std::fs::write(temp_dir.path().join("split-3.split.tmp"), b"partial").unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), b"not a split").unwrap();
+
+        let split_cache =
+            SplitCache::open(temp_dir.path().to_path_buf(), &split_cache_config(100, 1)).unwrap();
+        assert_eq!(split_cache.cached_split_ids().len(), 1);
+        assert!(!temp_dir.path().join("split-3.split.tmp").exists());
+        // Files that do not belong to the cache are left untouched.
+        assert!(temp_dir.path().join("notes.txt").exists());
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use quickwit_cluster::Cluster;
+use quickwit_metastore::{Metastore, SplitState, INDEX_FRESHNESS_KEY_PREFIX};
+use quickwit_storage::{Storage, StorageUriResolver};
+use tracing::{debug, error, warn};
+
+use crate::leaf::get_split_footer_from_cache_or_fetch;
+use crate::metrics::SEARCH_METRICS;
+use crate::rendezvous_hasher::sort_by_rendez_vous_hash;
+use crate::service::SearcherContext;
+use crate::{extract_split_and_footer_offsets, SearchClientPool};
+
+/// Interval at which the freshness gossiped for each index is checked.
+const FRESHNESS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval at which the list of indexes is refreshed from the metastore.
+const INDEX_LIST_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+struct IndexWarmupState {
+    index_storage: Arc<dyn Storage>,
+    freshness_view: Vec<(String, String)>,
+    known_split_ids: Option<HashSet<String>>,
+}
+
+/// Prefetches the hotcache and footer of newly published splits into the split footer cache so
+/// that the first query hitting a fresh split does not pay for fetching them.
+///
+/// New splits are detected when the freshness gossiped for their index changes. Each searcher
+/// only warms up the splits it has the highest rendezvous affinity with, i.e. the splits that root
+/// searches preferably route to it.
+pub(crate) struct SplitWarmer {
+    cluster: Arc<Cluster>,
+    metastore: Arc<dyn Metastore>,
+    storage_uri_resolver: StorageUriResolver,
+    searcher_context: Arc<SearcherContext>,
+    search_client_pool: SearchClientPool,
+    self_grpc_addr: SocketAddr,
+    indexes: HashMap<String, IndexWarmupState>,
+}
+
+impl SplitWarmer {
+    pub fn new(
+        cluster: Arc<Cluster>,
+        metastore: Arc<dyn Metastore>,
+        storage_uri_resolver: StorageUriResolver,
+        searcher_context: Arc<SearcherContext>,
+        search_client_pool: SearchClientPool,
+        self_grpc_addr: SocketAddr,
+    ) -> Self {
+        Self {
+            cluster,
+            metastore,
+            storage_uri_resolver,
+            searcher_context,
+            search_client_pool,
+            self_grpc_addr,
+            indexes: HashMap::new(),
+        }
+    }
+
+    /// Spawns a task that periodically warms up newly published splits.
+    pub fn spawn(mut self) {
+        tokio::spawn(async move {
+            let mut last_index_list_refresh_opt: Option<Instant> = None;
+            let mut interval = tokio::time::interval(FRESHNESS_POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                if last_index_list_refresh_opt
+                    .map(|last_refresh| last_refresh.elapsed() >= INDEX_LIST_REFRESH_INTERVAL)
+                    .unwrap_or(true)
+                {
+                    if let Err(error) = self.refresh_indexes().await {
+                        error!(error=?error, "Failed to refresh the list of indexes to warm up.");
+                    }
+                    last_index_list_refresh_opt = Some(Instant::now());
+                }
+                self.warm_up_new_splits().await;
+            }
+        });
+    }
+
+    async fn refresh_indexes(&mut self) -> anyhow::Result<()> {
+        let indexes_metadatas = self.metastore.list_indexes_metadatas().await?;
+        let index_ids: HashSet<&str> = indexes_metadatas
+            .iter()
+            .map(|index_metadata| index_metadata.index_id.as_str())
+            .collect();
+        self.indexes
+            .retain(|index_id, _| index_ids.contains(index_id.as_str()));
+        for index_metadata in &indexes_metadatas {
+            if self.indexes.contains_key(&index_metadata.index_id) {
+                continue;
+            }
+            let index_storage = self
+                .storage_uri_resolver
+                .resolve(&index_metadata.index_uri)?;
+            self.indexes.insert(
+                index_metadata.index_id.clone(),
+                IndexWarmupState {
+                    index_storage,
+                    freshness_view: Vec::new(),
+                    known_split_ids: None,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if this searcher is the one root searches preferably route the split to.
+    fn has_highest_affinity(&self, split_id: &str) -> bool {
+        let mut grpc_addrs: Vec<SocketAddr> =
+            self.search_client_pool.clients().into_keys().collect();
+        sort_by_rendez_vous_hash(&mut grpc_addrs, split_id);
+        grpc_addrs.first() == Some(&self.self_grpc_addr)
+    }
+
+    async fn warm_up_new_splits(&mut self) {
+        let index_ids: Vec<String> = self.indexes.keys().cloned().collect();
+        for index_id in index_ids {
+            if let Err(error) = self.warm_up_index_new_splits(&index_id).await {
+                warn!(index_id=%index_id, error=?error, "Failed to warm up new splits.");
+            }
+        }
+    }
+
+    async fn warm_up_index_new_splits(&mut self, index_id: &str) -> anyhow::Result<()> {
+        let mut freshness_view: Vec<(String, String)> = self
+            .cluster
+            .ready_members_key_value(&format!("{INDEX_FRESHNESS_KEY_PREFIX}{index_id}"))
+            .await
+            .into_iter()
+            .map(|(member, value)| (member.chitchat_id(), value))
+            .collect();
+        freshness_view.sort();
+
+        let index_state = match self.indexes.get(index_id) {
+            Some(index_state) => index_state,
+            None => return Ok(()),
+        };
+        if index_state.known_split_ids.is_some() && index_state.freshness_view == freshness_view {
+            return Ok(());
+        }
+        let splits = self
+            .metastore
+            .list_splits(index_id, SplitState::Published, None, None)
+            .await?;
+        let new_splits: Vec<_> = match &index_state.known_split_ids {
+            // The splits published before the index was first seen are not considered new.
+            Some(known_split_ids) => splits
+                .iter()
+                .filter(|split| !known_split_ids.contains(split.split_id()))
+                .filter(|split| self.has_highest_affinity(split.split_id()))
+                .collect(),
+            None => Vec::new(),
+        };
+        for split in new_splits {
+            let split_and_footer_offsets = extract_split_and_footer_offsets(&split.split_metadata);
+            debug!(index_id=%index_id, split_id=%split.split_id(), "Warming up split.");
+            match get_split_footer_from_cache_or_fetch(
+                index_state.index_storage.clone(),
+                &split_and_footer_offsets,
+                &self.searcher_context.split_footer_cache,
+            )
+            .await
+            {
+                Ok(_) => SEARCH_METRICS.split_warmups_total.inc(),
+                Err(error) => {
+                    warn!(index_id=%index_id, split_id=%split.split_id(), error=?error, "Failed to warm up split.")
+                }
+            }
+        }
+        let known_split_ids = splits
+            .iter()
+            .map(|split| split.split_id().to_string())
+            .collect();
+        if let Some(index_state) = self.indexes.get_mut(index_id) {
+            index_state.freshness_view = freshness_view;
+            index_state.known_split_ids = Some(known_split_ids);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use chitchat::transport::ChannelTransport;
+    use quickwit_cluster::{create_cluster_for_test, grpc_addr_from_listen_addr_for_test};
+    use quickwit_common::uri::Uri;
+    use quickwit_config::SearcherConfig;
+    use quickwit_metastore::{IndexMetadata, MockMetastore, Split, SplitMetadata};
+
+    use super::*;
+
+    fn mock_split(split_id: &str) -> Split {
+        Split {
+            split_state: SplitState::Published,
+            update_timestamp: 0,
+            publish_timestamp: None,
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                footer_offsets: 5..10,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_warmer_warms_up_new_splits() {
+        let transport = ChannelTransport::default();
+        let cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["searcher"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        let self_grpc_addr = grpc_addr_from_listen_addr_for_test(cluster.gossip_listen_addr);
+        let search_client_pool = SearchClientPool::for_addrs(&[self_grpc_addr])
+            .await
+            .unwrap();
+
+        let index_uri = "ram:///indexes/test-index";
+        let storage_uri_resolver = StorageUriResolver::for_test();
+        let index_storage = storage_uri_resolver
+            .resolve(&Uri::from_well_formed(index_uri.to_string()))
+            .unwrap();
+        for split_id in ["split-1", "split-2"] {
+            index_storage
+                .put(
+                    Path::new(&format!("{split_id}.split")),
+                    Box::new(b"0123456789".to_vec()),
+                )
+                .await
+                .unwrap();
+        }
+        let mut mock_metastore = MockMetastore::new();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .returning(move || Ok(vec![IndexMetadata::for_test("test-index", index_uri)]));
+        let num_list_splits_calls = Arc::new(AtomicUsize::new(0));
+        let num_list_splits_calls_clone = num_list_splits_calls.clone();
+        mock_metastore
+            .expect_list_splits()
+            .returning(move |_, _, _, _| {
+                if num_list_splits_calls_clone.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Ok(vec![mock_split("split-1")])
+                } else {
+                    Ok(vec![mock_split("split-1"), mock_split("split-2")])
+                }
+            });
+        let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
+        let mut split_warmer = SplitWarmer::new(
+            cluster.clone(),
+            Arc::new(mock_metastore),
+            storage_uri_resolver,
+            searcher_context.clone(),
+            search_client_pool,
+            self_grpc_addr,
+        );
+        split_warmer.refresh_indexes().await.unwrap();
+
+        // The splits published before the index is first seen are not warmed up.
+        split_warmer.warm_up_new_splits().await;
+        assert_eq!(num_list_splits_calls.load(Ordering::SeqCst), 1);
+        let split_footer_cache = &searcher_context.split_footer_cache;
+        assert!(split_footer_cache.get(&"split-1".to_string()).is_none());
+
+        // The freshness is unchanged: the metastore is not queried.
+        split_warmer.warm_up_new_splits().await;
+        assert_eq!(num_list_splits_calls.load(Ordering::SeqCst), 1);
+
+        cluster
+            .set_key_value(format!("{INDEX_FRESHNESS_KEY_PREFIX}test-index"), "1")
+            .await;
+        split_warmer.warm_up_new_splits().await;
+        assert_eq!(num_list_splits_calls.load(Ordering::SeqCst), 2);
+        assert!(split_footer_cache.get(&"split-1".to_string()).is_none());
+        assert_eq!(
+            split_footer_cache
+                .get(&"split-2".to_string())
+                .unwrap()
+                .as_slice(),
+            b"56789"
+        );
+    }
+}
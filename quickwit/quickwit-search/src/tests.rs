@@ -21,6 +21,7 @@ use std::collections::BTreeSet;
 
 use assert_json_diff::{assert_json_eq, assert_json_include};
 use quickwit_config::SearcherConfig;
+use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
 use quickwit_doc_mapper::DefaultDocMapper;
 use quickwit_indexing::TestSandbox;
 use quickwit_proto::{LeafHit, SearchRequest, SortOrder};
@@ -28,7 +29,11 @@ use serde_json::json;
 use tantivy::time::OffsetDateTime;
 
 use super::*;
-use crate::single_node_search;
+use crate::bloom_filter_pruning::prune_splits_with_bloom_filters;
+use crate::{
+    offline_search, single_node_search, single_node_search_splits, single_node_search_stream,
+    single_node_search_stream_fields,
+};
 
 #[tokio::test]
 async fn test_single_node_simple() -> anyhow::Result<()> {
@@ -77,6 +82,270 @@ async fn test_single_node_simple() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_search_query_ast() -> anyhow::Result<()> {
+    let index_id = "single-node-search-query-ast";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+                record: position
+              - name: path
+                type: text
+                tokenizer: raw
+        "#;
+    let test_sandbox =
+        TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"], None).await?;
+    let docs = vec![
+        json!({"body": r#"The server said "goodbye" and left."#, "path": r#"C:\Users\snoopy"#}),
+        json!({"body": "The server said hello.", "path": "/home/snoopy"}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+
+    for (query_ast, expected_num_hits) in [
+        (QueryAst::text(r#"said "goodbye""#), 1),
+        (QueryAst::term("path", r#"C:\Users\snoopy"#), 1),
+        (
+            QueryAst::text("server").and(QueryAst::term("path", "/home/snoopy").negate()),
+            1,
+        ),
+        (QueryAst::text("server"), 2),
+    ] {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query_ast: Some(serde_json::to_string(&query_ast)?),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let single_node_result = single_node_search(
+            &search_request,
+            &*test_sandbox.metastore(),
+            test_sandbox.storage_uri_resolver(),
+        )
+        .await?;
+        assert_eq!(
+            single_node_result.num_hits, expected_num_hits,
+            "query_ast: {query_ast:?}"
+        );
+    }
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "server".to_string(),
+        query_ast: Some(serde_json::to_string(&QueryAst::text("server"))?),
+        max_hits: 10,
+        ..Default::default()
+    };
+    assert!(single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await
+    .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_offline_search() -> anyhow::Result<()> {
+    let index_id = "offline-search";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+              - name: body
+                type: text
+        "#;
+    let test_sandbox =
+        TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"], None).await?;
+    let docs = vec![
+        json!({"title": "snoopy", "body": "Snoopy is an anthropomorphic beagle[5] in the comic strip..."}),
+        json!({"title": "beagle", "body": "The beagle is a breed of small scent hound, similar in appearance to the much larger foxhound."}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+    let split_ids: Vec<String> = test_sandbox
+        .metastore()
+        .list_all_splits(index_id)
+        .await?
+        .into_iter()
+        .map(|split| split.split_id().to_string())
+        .collect();
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "beagle".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let search_response = offline_search(
+        &search_request,
+        test_sandbox.storage(),
+        &split_ids,
+        test_sandbox.doc_mapper(),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 2);
+    assert_eq!(search_response.hits.len(), 2);
+    assert!(search_response.errors.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_search_splits() -> anyhow::Result<()> {
+    let index_id = "single-node-search-splits";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+    let test_sandbox =
+        TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"], None).await?;
+    test_sandbox
+        .add_documents(vec![json!({"body": "first split"})])
+        .await?;
+    let first_splits = test_sandbox.metastore().list_all_splits(index_id).await?;
+    test_sandbox
+        .add_documents(vec![json!({"body": "second split"})])
+        .await?;
+    let new_splits: Vec<SplitMetadata> = test_sandbox
+        .metastore()
+        .list_all_splits(index_id)
+        .await?
+        .into_iter()
+        .filter(|split| {
+            first_splits
+                .iter()
+                .all(|first_split| first_split.split_id() != split.split_id())
+        })
+        .map(|split| split.split_metadata)
+        .collect();
+    assert_eq!(new_splits.len(), 1);
+
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "split".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let search_response = single_node_search_splits(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+        &new_splits,
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 1);
+    let hit_json: serde_json::Value = serde_json::from_str(&search_response.hits[0].json)?;
+    assert_json_include!(actual: hit_json, expected: json!({"body": "second split"}));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_search_stream() -> anyhow::Result<()> {
+    let index_id = "single-node-search-stream";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: response_time
+                type: i64
+                fast: true
+        "#;
+    let test_sandbox =
+        TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"], None).await?;
+    let docs = vec![
+        json!({"body": "info", "response_time": 12}),
+        json!({"body": "error", "response_time": 500}),
+        json!({"body": "info", "response_time": 7}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+    let search_stream_request = SearchStreamRequest {
+        index_id: index_id.to_string(),
+        query: "info".to_string(),
+        fast_field: "response_time".to_string(),
+        output_format: quickwit_proto::OutputFormat::Csv as i32,
+        ..Default::default()
+    };
+    let stream = single_node_search_stream(
+        search_stream_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    let chunks: Vec<Bytes> = stream.try_collect().await?;
+    let values: BTreeSet<String> = chunks
+        .iter()
+        .flat_map(|chunk| std::str::from_utf8(chunk).unwrap().lines())
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(values, BTreeSet::from(["12".to_string(), "7".to_string()]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_search_stream_fields() -> anyhow::Result<()> {
+    let index_id = "single-node-search-stream-fields";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: response_time
+                type: i64
+                fast: true
+              - name: status_code
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox =
+        TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"], None).await?;
+    test_sandbox
+        .add_documents(vec![
+            json!({"body": "info", "response_time": 12, "status_code": 200}),
+            json!({"body": "error", "response_time": 500, "status_code": 500}),
+        ])
+        .await?;
+    test_sandbox
+        .add_documents(vec![
+            json!({"body": "info", "response_time": 7, "status_code": 204}),
+        ])
+        .await?;
+    let search_stream_request = SearchStreamRequest {
+        index_id: index_id.to_string(),
+        query: "info".to_string(),
+        output_format: quickwit_proto::OutputFormat::Csv as i32,
+        ..Default::default()
+    };
+    let stream = single_node_search_stream_fields(
+        search_stream_request,
+        vec!["response_time".to_string(), "status_code".to_string()],
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    let split_chunks: Vec<Vec<Bytes>> = stream.try_collect().await?;
+    assert_eq!(split_chunks.len(), 2);
+    let rows: BTreeSet<(String, String)> = split_chunks
+        .iter()
+        .flat_map(|field_chunks| {
+            assert_eq!(field_chunks.len(), 2);
+            let response_times = std::str::from_utf8(&field_chunks[0]).unwrap().lines();
+            let status_codes = std::str::from_utf8(&field_chunks[1]).unwrap().lines();
+            response_times
+                .zip(status_codes)
+                .map(|(response_time, status_code)| {
+                    (response_time.to_string(), status_code.to_string())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        BTreeSet::from([
+            ("12".to_string(), "200".to_string()),
+            ("7".to_string(), "204".to_string())
+        ])
+    );
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_search_with_snippet() -> anyhow::Result<()> {
     let index_id = "single-node-with-snippet";
@@ -130,6 +399,73 @@ async fn test_single_search_with_snippet() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_search_with_fetch_fields() -> anyhow::Result<()> {
+    let index_id = "single-node-with-fetch-fields";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+              - name: body
+                type: text
+              - name: url
+                type: text
+                tokenizer: raw
+        "#;
+    let test_sandbox =
+        TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"], None).await?;
+    let docs = vec![
+        json!({"title": "snoopy", "body": "Snoopy is an anthropomorphic beagle.", "url": "/snoopy"}),
+        json!({"title": "beagle", "body": "The beagle is a breed of small scent hound.", "url": "/beagle"}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "beagle".to_string(),
+        snippet_fields: vec!["body".to_string()],
+        fetch_fields: vec!["title".to_string(), "url".to_string()],
+        max_hits: 10,
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.hits.len(), 2);
+    for hit in &single_node_result.hits {
+        let hit_json: serde_json::Value = serde_json::from_str(&hit.json)?;
+        let hit_obj = hit_json.as_object().unwrap();
+        assert!(hit_obj.contains_key("title"));
+        assert!(hit_obj.contains_key("url"));
+        assert!(!hit_obj.contains_key("body"));
+        // Snippets are still computed on fields that are not fetched.
+        let snippet_json: serde_json::Value = serde_json::from_str(hit.snippet.as_ref().unwrap())?;
+        assert!(snippet_json["body"][0]
+            .as_str()
+            .unwrap()
+            .contains("<b>beagle</b>"));
+    }
+
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "beagle".to_string(),
+        fetch_fields: vec!["unknown".to_string()],
+        max_hits: 10,
+        ..Default::default()
+    };
+    let search_error = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(search_error.to_string().contains("Unknown fetch field"));
+    Ok(())
+}
+
 async fn slop_search_and_check(
     test_sandbox: &TestSandbox,
     index_id: &str,
@@ -273,6 +609,64 @@ async fn test_single_node_several_splits() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_search_debug_split_timings() -> anyhow::Result<()> {
+    let index_id = "single-node-search-debug-split-timings";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+    let test_sandbox =
+        TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"], None).await?;
+    for _ in 0..2 {
+        test_sandbox
+            .add_documents(vec![
+                json!({"body": "The beagle is a breed of small scent hound."}),
+            ])
+            .await?;
+    }
+    let mut search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "beagle".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.num_hits, 2);
+    assert!(single_node_result.split_timings.is_empty());
+
+    search_request.debug = true;
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.num_hits, 2);
+    let mut split_ids: Vec<String> = single_node_result
+        .split_timings
+        .iter()
+        .map(|split_timings| split_timings.split_id.clone())
+        .collect();
+    split_ids.sort();
+    let mut expected_split_ids: Vec<String> = test_sandbox
+        .metastore()
+        .list_all_splits(index_id)
+        .await?
+        .into_iter()
+        .map(|split| split.split_id().to_string())
+        .collect();
+    expected_split_ids.sort();
+    assert_eq!(split_ids, expected_split_ids);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_filtering() -> anyhow::Result<()> {
     let index_id = "single-node-filtering";
@@ -621,6 +1015,127 @@ async fn test_single_node_split_pruning_by_tags() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_split_pruning_by_bloom_filters() -> anyhow::Result<()> {
+    let doc_mapping_yaml = r#"
+            bloom_filter_fields:
+              - trace_id
+            field_mappings:
+              - name: trace_id
+                type: text
+                tokenizer: raw
+              - name: body
+                type: text
+        "#;
+    let index_id = "single-node-pruning-by-bloom-filters";
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[], None).await?;
+    for split_ord in 0..3 {
+        let docs = (0..10).map(|doc_ord| {
+            json!({"body": "span", "trace_id": format!("trace{}x{}", split_ord, doc_ord)})
+        });
+        test_sandbox.add_documents(docs).await?;
+    }
+    let split_metadatas = list_relevant_splits(
+        &SearchRequest {
+            index_id: index_id.to_string(),
+            query: "trace_id:trace1x7".to_string(),
+            ..Default::default()
+        },
+        &*test_sandbox.metastore(),
+    )
+    .await?;
+    assert_eq!(split_metadatas.len(), 3);
+
+    for (query, expected_num_splits) in [
+        ("trace_id:trace1x7", 1),
+        ("trace_id:trace1x7 OR trace_id:trace2x3", 2),
+        ("trace_id:trace9x9", 0),
+        ("body:span", 3),
+        ("body:span AND NOT trace_id:trace1x7", 3),
+    ] {
+        let relevant_split_metadatas = prune_splits_with_bloom_filters(
+            extract_tags_from_query(query)?,
+            &*test_sandbox.doc_mapper(),
+            test_sandbox.storage(),
+            split_metadatas.clone(),
+        )
+        .await?;
+        assert_eq!(
+            relevant_split_metadatas.len(),
+            expected_num_splits,
+            "{}",
+            query
+        );
+    }
+
+    let search_response = single_node_search(
+        &SearchRequest {
+            index_id: index_id.to_string(),
+            query: "trace_id:trace1x7".to_string(),
+            max_hits: 10,
+            ..Default::default()
+        },
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_split_pruning_by_keyword_field_tags() -> anyhow::Result<()> {
+    let doc_mapping_yaml = r#"
+            tag_keyword_fields: true
+            field_mappings:
+              - name: tenant_id
+                type: text
+                tokenizer: raw
+              - name: body
+                type: text
+        "#;
+    let index_id = "single-node-pruning-by-keyword-field-tags";
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[], None).await?;
+    for tenant_id in ["acme", "globex"] {
+        let mut docs = vec![];
+        for i in 0..10 {
+            docs.push(json!({"body": format!("content num #{}", i + 1), "tenant_id": tenant_id}));
+        }
+        test_sandbox.add_documents(docs).await?;
+    }
+
+    let selected_splits = list_relevant_splits(
+        &SearchRequest {
+            index_id: index_id.to_string(),
+            query: "tenant_id:acme AND body:content".to_string(),
+            ..Default::default()
+        },
+        &*test_sandbox.metastore(),
+    )
+    .await?;
+    assert_eq!(selected_splits.len(), 1);
+    assert_eq!(
+        selected_splits[0]
+            .tags
+            .iter()
+            .map(|tag| tag.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["tenant_id!", "tenant_id:acme"]
+    );
+
+    let selected_splits = list_relevant_splits(
+        &SearchRequest {
+            index_id: index_id.to_string(),
+            query: "tenant_id:initech".to_string(),
+            ..Default::default()
+        },
+        &*test_sandbox.metastore(),
+    )
+    .await?;
+    assert!(selected_splits.is_empty());
+    Ok(())
+}
+
 const DYNAMIC_TEST_INDEX_ID: &str = "search_dynamic_mode";
 
 async fn test_search_dynamic_util(test_sandbox: &TestSandbox, query: &str) -> Vec<u32> {
@@ -635,6 +1150,7 @@ async fn test_search_dynamic_util(test_sandbox: &TestSandbox, query: &str) -> Ve
             split_id: split_meta.split_id().to_string(),
             split_footer_start: split_meta.split_metadata.footer_offsets.start,
             split_footer_end: split_meta.split_metadata.footer_offsets.end,
+            storage_uri: String::new(),
         })
         .collect();
     let request = quickwit_proto::SearchRequest {
@@ -912,6 +1428,131 @@ async fn test_single_node_aggregation() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_leaf_search_spills_aggregation_results() -> anyhow::Result<()> {
+    let index_id = "leaf-search-aggregation-spill";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: color
+                type: text
+                tokenizer: raw
+                fast: true
+        "#;
+    let test_sandbox =
+        TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["color"], None).await?;
+    test_sandbox
+        .add_documents(vec![json!({"color": "blue"}), json!({"color": "green"})])
+        .await?;
+    test_sandbox
+        .add_documents(vec![json!({"color": "blue"}), json!({"color": "white"})])
+        .await?;
+    test_sandbox
+        .add_documents(vec![json!({"color": "blue"})])
+        .await?;
+    let splits_offsets: Vec<SplitIdAndFooterOffsets> = test_sandbox
+        .metastore()
+        .list_all_splits(index_id)
+        .await?
+        .iter()
+        .map(|split| extract_split_and_footer_offsets(&split.split_metadata))
+        .collect();
+    assert_eq!(splits_offsets.len(), 3);
+    // The buckets of the terms aggregation of a split are estimated to take 20_480 bytes, and
+    // searching a split 86_272 bytes: the budget can hold a split search and two split results,
+    // but not a split search and the results of the three splits.
+    let agg_req = json!({
+        "colors": { "terms": { "field": "color", "size": 100 } }
+    });
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "*".to_string(),
+        search_fields: vec!["color".to_string()],
+        max_hits: 2,
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let temp_dir = tempfile::tempdir()?;
+    let mut searcher_config = SearcherConfig {
+        search_memory_budget: byte_unit::Byte::from_bytes(130_000),
+        ..Default::default()
+    };
+    {
+        let searcher_context = Arc::new(SearcherContext::new(searcher_config.clone()));
+        let error = leaf_search(
+            searcher_context,
+            &search_request,
+            test_sandbox.storage(),
+            &splits_offsets,
+            test_sandbox.doc_mapper(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, SearchError::MemoryBudgetExceeded { .. }));
+    }
+    searcher_config.aggregation_spill_dir = Some(temp_dir.path().to_path_buf());
+    {
+        // Spilling does not help when the budget cannot even hold the merged result.
+        let searcher_context = Arc::new(SearcherContext::new(SearcherConfig {
+            search_memory_budget: byte_unit::Byte::from_bytes(100_000),
+            ..searcher_config.clone()
+        }));
+        let error = leaf_search(
+            searcher_context,
+            &search_request,
+            test_sandbox.storage(),
+            &splits_offsets,
+            test_sandbox.doc_mapper(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, SearchError::MemoryBudgetExceeded { .. }));
+    }
+    let searcher_context = Arc::new(SearcherContext::new(searcher_config));
+    let leaf_search_response = leaf_search(
+        searcher_context.clone(),
+        &search_request,
+        test_sandbox.storage(),
+        &splits_offsets,
+        test_sandbox.doc_mapper(),
+    )
+    .await?;
+    assert_eq!(
+        searcher_context
+            .search_memory_accountant
+            .reserved_num_bytes(),
+        0
+    );
+    assert!(leaf_search_response.failed_splits.is_empty());
+    assert_eq!(leaf_search_response.num_hits, 5);
+    assert_eq!(leaf_search_response.partial_hits.len(), 2);
+
+    let intermediate_aggregation_result: IntermediateAggregationResults = serde_json::from_str(
+        &leaf_search_response
+            .intermediate_aggregation_result
+            .unwrap(),
+    )?;
+    let aggregations: Aggregations = serde_json::from_value(agg_req)?;
+    let aggregation_result: AggregationResults =
+        intermediate_aggregation_result.into_final_bucket_result(aggregations)?;
+    let aggregation_result_json = serde_json::to_value(&aggregation_result)?;
+    assert_eq!(
+        aggregation_result_json["colors"]["buckets"][0]["key"],
+        "blue"
+    );
+    assert_eq!(
+        aggregation_result_json["colors"]["buckets"][0]["doc_count"],
+        3
+    );
+    assert_eq!(
+        aggregation_result_json["colors"]["buckets"]
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_aggregation_missing_fast_field() -> anyhow::Result<()> {
     let index_id = "single-node-agg-2";
@@ -975,6 +1616,49 @@ async fn test_single_node_aggregation_missing_fast_field() -> anyhow::Result<()>
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_aggregation_exceeding_memory_budget() -> anyhow::Result<()> {
+    let index_id = "single-node-agg-3";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: color
+                type: text
+                fast: true
+        "#;
+    let test_sandbox =
+        TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["color"], None).await?;
+    let docs = vec![json!({"color": "blue"}), json!({"color": "white"})];
+    test_sandbox.add_documents(docs).await?;
+    let agg_req = r#"
+ {
+   "colors": {
+     "terms": {
+       "field": "color",
+       "size": 1000000000
+     }
+   }
+ }"#;
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "*".to_string(),
+        search_fields: vec!["color".to_string()],
+        max_hits: 2,
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let search_error = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(search_error
+        .to_string()
+        .contains("Search memory budget exceeded"));
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_with_ip_field() -> anyhow::Result<()> {
     let index_id = "single-node-with-ip-field";
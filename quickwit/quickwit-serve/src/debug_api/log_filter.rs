@@ -0,0 +1,58 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use once_cell::sync::OnceCell;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use super::rest_handler::DebugApiError;
+
+/// Handle reloading the log filter of the process.
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+static LOG_FILTER_HANDLE: OnceCell<LogFilterHandle> = OnceCell::new();
+
+/// Registers the handle used by the log filter endpoint to change the log filter at runtime. This
+/// must be called once by the binary setting up logging.
+pub fn set_log_filter_handle(log_filter_handle: LogFilterHandle) {
+    if LOG_FILTER_HANDLE.set(log_filter_handle).is_err() {
+        tracing::warn!("Log filter handle is already set.");
+    }
+}
+
+fn log_filter_handle() -> Result<&'static LogFilterHandle, DebugApiError> {
+    LOG_FILTER_HANDLE.get().ok_or_else(|| {
+        DebugApiError::Unavailable("the log filter cannot be changed at runtime.".to_string())
+    })
+}
+
+pub(crate) fn current_log_filter() -> Result<String, DebugApiError> {
+    log_filter_handle()?
+        .with_current(|env_filter| env_filter.to_string())
+        .map_err(|error| DebugApiError::Internal(error.to_string()))
+}
+
+/// Replaces the log filter, for instance with `quickwit=debug,quickwit_search=trace`. See the
+/// [`EnvFilter`] documentation for the directives syntax.
+pub(crate) fn set_log_filter(log_filter: &str) -> Result<(), DebugApiError> {
+    let env_filter = EnvFilter::try_new(log_filter)
+        .map_err(|error| DebugApiError::InvalidRequest(error.to_string()))?;
+    log_filter_handle()?
+        .reload(env_filter)
+        .map_err(|error| DebugApiError::Internal(error.to_string()))
+}
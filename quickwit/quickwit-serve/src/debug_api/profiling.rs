@@ -0,0 +1,132 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::rest_handler::DebugApiError;
+
+/// Output format of a CPU profile.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CpuProfileFormat {
+    /// Flamegraph rendered as an SVG image.
+    Flamegraph,
+    /// Protobuf-encoded profile readable by `go tool pprof`.
+    Pprof,
+}
+
+impl Default for CpuProfileFormat {
+    fn default() -> Self {
+        CpuProfileFormat::Flamegraph
+    }
+}
+
+impl CpuProfileFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Flamegraph => "image/svg+xml",
+            Self::Pprof => "application/octet-stream",
+        }
+    }
+}
+
+/// Samples the stacks of all the threads of the process for `duration`.
+#[cfg(feature = "pprof")]
+pub(crate) async fn build_cpu_profile(
+    duration: Duration,
+    format: CpuProfileFormat,
+) -> Result<Vec<u8>, DebugApiError> {
+    use pprof::protos::Message;
+
+    /// Sampling frequency of the CPU profiler in Hz.
+    const CPU_PROFILE_FREQUENCY: i32 = 99;
+
+    let profiler_guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(CPU_PROFILE_FREQUENCY)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        // Fails if another profile is being collected.
+        .map_err(|error| DebugApiError::Unavailable(error.to_string()))?;
+    tokio::time::sleep(duration).await;
+    let report = profiler_guard
+        .report()
+        .build()
+        .map_err(|error| DebugApiError::Internal(error.to_string()))?;
+    let mut profile = Vec::new();
+    match format {
+        CpuProfileFormat::Flamegraph => report
+            .flamegraph(&mut profile)
+            .map_err(|error| DebugApiError::Internal(error.to_string()))?,
+        CpuProfileFormat::Pprof => report
+            .pprof()
+            .map_err(|error| DebugApiError::Internal(error.to_string()))?
+            .encode(&mut profile)
+            .map_err(|error| DebugApiError::Internal(error.to_string()))?,
+    }
+    Ok(profile)
+}
+
+#[cfg(not(feature = "pprof"))]
+pub(crate) async fn build_cpu_profile(
+    _duration: Duration,
+    _format: CpuProfileFormat,
+) -> Result<Vec<u8>, DebugApiError> {
+    Err(DebugApiError::Unavailable(
+        "Quickwit was built without the `pprof` feature.".to_string(),
+    ))
+}
+
+/// Dumps a jemalloc heap profile, which can be rendered with `jeprof`, for instance as a
+/// flamegraph with `jeprof --collapsed <quickwit-binary> <profile> | flamegraph.pl`.
+#[cfg(feature = "jemalloc-profiling")]
+pub(crate) async fn dump_heap_profile() -> Result<Vec<u8>, DebugApiError> {
+    use std::ffi::CString;
+
+    use quickwit_common::rand::append_random_suffix;
+
+    // SAFETY: `opt.prof` is a boolean option.
+    let is_profiling_active: bool = unsafe { tikv_jemalloc_ctl::raw::read(b"opt.prof\0") }
+        .map_err(|error| DebugApiError::Unavailable(error.to_string()))?;
+    if !is_profiling_active {
+        return Err(DebugApiError::Unavailable(
+            "heap profiling is not active. Start Quickwit with the environment variable \
+             `MALLOC_CONF=prof:true`."
+                .to_string(),
+        ));
+    }
+    let dump_path = std::env::temp_dir().join(append_random_suffix("quickwit-heap-profile"));
+    let dump_path_cstr = CString::new(dump_path.to_string_lossy().as_bytes())
+        .map_err(|error| DebugApiError::Internal(error.to_string()))?;
+    // SAFETY: `prof.dump` expects a pointer to a NUL-terminated file path, which outlives the
+    // call.
+    unsafe { tikv_jemalloc_ctl::raw::write(b"prof.dump\0", dump_path_cstr.as_ptr()) }
+        .map_err(|error| DebugApiError::Internal(error.to_string()))?;
+    let profile_res = tokio::fs::read(&dump_path).await;
+    let _ = tokio::fs::remove_file(&dump_path).await;
+    profile_res.map_err(|error| DebugApiError::Internal(error.to_string()))
+}
+
+#[cfg(not(feature = "jemalloc-profiling"))]
+pub(crate) async fn dump_heap_profile() -> Result<Vec<u8>, DebugApiError> {
+    Err(DebugApiError::Unavailable(
+        "Quickwit was built without the `jemalloc-profiling` feature.".to_string(),
+    ))
+}
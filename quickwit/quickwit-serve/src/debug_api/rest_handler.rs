@@ -0,0 +1,338 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use hyper::header::CONTENT_TYPE;
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+use super::log_filter::{current_log_filter, set_log_filter};
+use super::profiling::{build_cpu_profile, dump_heap_profile, CpuProfileFormat};
+use crate::format::FormatError;
+use crate::Format;
+
+/// Maximum duration of a CPU profile.
+const MAX_CPU_PROFILE_DURATION: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Error)]
+pub enum DebugApiError {
+    #[error("Admin endpoints are disabled. Set `admin_token` in the node config to enable them.")]
+    AdminEndpointsDisabled,
+    #[error("Missing or invalid admin token.")]
+    InvalidAdminToken,
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("Not available: {0}")]
+    Unavailable(String),
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl ServiceError for DebugApiError {
+    fn status_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::AdminEndpointsDisabled => ServiceErrorCode::NotFound,
+            Self::InvalidAdminToken => ServiceErrorCode::Unauthorized,
+            Self::InvalidRequest(_) => ServiceErrorCode::BadRequest,
+            Self::Unavailable(_) => ServiceErrorCode::Unavailable,
+            Self::Internal(_) => ServiceErrorCode::Internal,
+        }
+    }
+}
+
+/// Debug handlers, reserved to administrators.
+pub fn debug_handlers(
+    admin_token_opt: Option<String>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    cpu_profile_handler(admin_token_opt.clone())
+        .or(heap_profile_handler(admin_token_opt.clone()))
+        .or(get_log_filter_handler(admin_token_opt.clone()))
+        .or(put_log_filter_handler(admin_token_opt))
+}
+
+/// Checks the `Authorization: Bearer <admin_token>` header of a request.
+fn check_admin_token(
+    admin_token_opt: Option<&str>,
+    authorization_opt: Option<&str>,
+) -> Result<(), DebugApiError> {
+    let admin_token = admin_token_opt.ok_or(DebugApiError::AdminEndpointsDisabled)?;
+    let bearer_token = authorization_opt
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .ok_or(DebugApiError::InvalidAdminToken)?;
+    if !constant_time_eq(bearer_token.as_bytes(), admin_token.as_bytes()) {
+        return Err(DebugApiError::InvalidAdminToken);
+    }
+    Ok(())
+}
+
+/// Compares two byte strings in a time that does not depend on the position of their first
+/// differing byte, so that the admin token cannot be guessed by timing the responses.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+    left.iter()
+        .zip(right)
+        .fold(0u8, |diff, (left_byte, right_byte)| {
+            diff | (left_byte ^ right_byte)
+        })
+        == 0
+}
+
+pub(crate) fn admin_filter(
+    admin_token_opt: Option<String>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |authorization_opt: Option<String>| {
+            let check_res =
+                check_admin_token(admin_token_opt.as_deref(), authorization_opt.as_deref());
+            async move { check_res.map_err(|error| warp::reject::custom(AdminRejection(error))) }
+        })
+        .untuple_one()
+}
+
+#[derive(Debug)]
+struct AdminRejection(DebugApiError);
+
+impl warp::reject::Reject for AdminRejection {}
+
+/// Turns the rejections of the admin filter into error replies. Other rejections are propagated.
+pub(crate) async fn recover_admin_rejection(rejection: Rejection) -> Result<Response, Rejection> {
+    if let Some(AdminRejection(error)) = rejection.find::<AdminRejection>() {
+        let format_error = FormatError {
+            code: error.status_code(),
+            error: error.to_string(),
+        };
+        return Ok(Format::default()
+            .make_reply_for_err(format_error)
+            .into_response());
+    }
+    Err(rejection)
+}
+
+/// This struct represents the QueryString passed to the CPU profile endpoint.
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct CpuProfileQueryString {
+    /// Duration of the profile in seconds.
+    #[serde(default = "default_cpu_profile_duration_secs")]
+    pub seconds: u64,
+    /// Output format of the profile: `flamegraph` (SVG) or `pprof` (protobuf).
+    #[serde(default)]
+    pub format: CpuProfileFormat,
+}
+
+fn default_cpu_profile_duration_secs() -> u64 {
+    30
+}
+
+fn cpu_profile_handler(
+    admin_token_opt: Option<String>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("debug" / "pprof" / "profile")
+        .and(warp::get())
+        .and(
+            admin_filter(admin_token_opt)
+                .and(serde_qs::warp::query(serde_qs::Config::default()))
+                .and_then(get_cpu_profile)
+                .recover(recover_admin_rejection)
+                .unify(),
+        )
+}
+
+async fn get_cpu_profile(query: CpuProfileQueryString) -> Result<Response, Infallible> {
+    let duration = Duration::from_secs(query.seconds);
+    if duration.is_zero() || duration > MAX_CPU_PROFILE_DURATION {
+        return Ok(make_error_reply(DebugApiError::InvalidRequest(format!(
+            "The profile duration must be between 1 and {} seconds.",
+            MAX_CPU_PROFILE_DURATION.as_secs()
+        ))));
+    }
+    let reply = match build_cpu_profile(duration, query.format).await {
+        Ok(profile) => make_profile_reply(query.format.content_type(), profile),
+        Err(error) => make_error_reply(error),
+    };
+    Ok(reply)
+}
+
+fn heap_profile_handler(
+    admin_token_opt: Option<String>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("debug" / "pprof" / "heap")
+        .and(warp::get())
+        .and(
+            admin_filter(admin_token_opt)
+                .and_then(get_heap_profile)
+                .recover(recover_admin_rejection)
+                .unify(),
+        )
+}
+
+async fn get_heap_profile() -> Result<Response, Infallible> {
+    let reply = match dump_heap_profile().await {
+        Ok(profile) => make_profile_reply("application/octet-stream", profile),
+        Err(error) => make_error_reply(error),
+    };
+    Ok(reply)
+}
+
+/// Body of the log filter endpoints.
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct LogFilter {
+    /// Log filter directives, for instance `quickwit=debug,tantivy=info`.
+    filter: String,
+}
+
+fn get_log_filter_handler(
+    admin_token_opt: Option<String>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("debug" / "log-filter").and(warp::get()).and(
+        admin_filter(admin_token_opt)
+            .and_then(get_log_filter)
+            .recover(recover_admin_rejection)
+            .unify(),
+    )
+}
+
+async fn get_log_filter() -> Result<Response, Infallible> {
+    let log_filter_res = current_log_filter().map(|filter| LogFilter { filter });
+    Ok(Format::default()
+        .make_rest_reply_non_serializable_error(log_filter_res)
+        .into_response())
+}
+
+fn put_log_filter_handler(
+    admin_token_opt: Option<String>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("debug" / "log-filter").and(warp::put()).and(
+        admin_filter(admin_token_opt)
+            .and(warp::body::json())
+            .and_then(put_log_filter)
+            .recover(recover_admin_rejection)
+            .unify(),
+    )
+}
+
+async fn put_log_filter(log_filter: LogFilter) -> Result<Response, Infallible> {
+    let log_filter_res = set_log_filter(&log_filter.filter).map(|_| log_filter);
+    Ok(Format::default()
+        .make_rest_reply_non_serializable_error(log_filter_res)
+        .into_response())
+}
+
+fn make_profile_reply(content_type: &'static str, profile: Vec<u8>) -> Response {
+    warp::reply::with_header(profile, CONTENT_TYPE, content_type).into_response()
+}
+
+fn make_error_reply(error: DebugApiError) -> Response {
+    Format::default()
+        .make_reply_for_err(FormatError::wrap(error))
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_admin_token() {
+        assert!(matches!(
+            check_admin_token(None, Some("Bearer secret")),
+            Err(DebugApiError::AdminEndpointsDisabled)
+        ));
+        assert!(matches!(
+            check_admin_token(Some("secret"), None),
+            Err(DebugApiError::InvalidAdminToken)
+        ));
+        assert!(matches!(
+            check_admin_token(Some("secret"), Some("Bearer wrong")),
+            Err(DebugApiError::InvalidAdminToken)
+        ));
+        assert!(matches!(
+            check_admin_token(Some("secret"), Some("secret")),
+            Err(DebugApiError::InvalidAdminToken)
+        ));
+        assert!(matches!(
+            check_admin_token(Some("secret"), Some("Bearer secret2")),
+            Err(DebugApiError::InvalidAdminToken)
+        ));
+        check_admin_token(Some("secret"), Some("Bearer secret")).unwrap();
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"secret", b""));
+    }
+
+    #[test]
+    fn test_debug_api_error_status_codes() {
+        assert!(matches!(
+            DebugApiError::Unavailable("heap profiling".to_string()).status_code(),
+            ServiceErrorCode::Unavailable
+        ));
+        assert!(matches!(
+            DebugApiError::InvalidAdminToken.status_code(),
+            ServiceErrorCode::Unauthorized
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_debug_handlers_require_admin_token() {
+        let handlers = debug_handlers(Some("secret".to_string())).recover(crate::rest::recover_fn);
+        let resp = warp::test::request()
+            .path("/debug/pprof/heap")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 401);
+
+        let resp = warp::test::request()
+            .path("/debug/pprof/profile?seconds=0")
+            .header("authorization", "Bearer secret")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/debug/log-filter")
+            .method("PUT")
+            .header("authorization", "Bearer secret")
+            .json(&serde_json::json!({"filter": "quickwit=notalevel"}))
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let handlers = debug_handlers(None).recover(crate::rest::recover_fn);
+        let resp = warp::test::request()
+            .path("/debug/pprof/heap")
+            .header("authorization", "Bearer secret")
+            .reply(&handlers)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
+}
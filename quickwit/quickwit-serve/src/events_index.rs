@@ -0,0 +1,211 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use quickwit_actors::Mailbox;
+use quickwit_common::events::{install_event_sink, Event, EVENTS_INDEX_ID};
+use quickwit_common::uri::Uri;
+use quickwit_config::{
+    DocMapping, IndexingSettings, RetentionPolicy, SearchSettings, SourceConfig,
+};
+use quickwit_ingest_api::{DocBatchBuilder, IngestApiService};
+use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError};
+use quickwit_proto::ingest_api::{CreateQueueIfNotExistsRequest, IngestRequest};
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Events recorded while a batch is being sent are buffered and sent in the next batch.
+const EVENTS_BATCH_DELAY: Duration = Duration::from_secs(1);
+
+/// Document written in the events index.
+#[derive(Serialize)]
+struct EventDoc<'a> {
+    node_id: &'a str,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Creates the events index if it does not exist yet and starts writing the events recorded by
+/// the node into it through the ingest API.
+pub(crate) async fn start_events_index_writer(
+    node_id: String,
+    default_index_root_uri: &Uri,
+    metastore: Arc<dyn Metastore>,
+    ingest_api_service: Mailbox<IngestApiService>,
+) -> anyhow::Result<()> {
+    create_events_index_if_not_exists(default_index_root_uri, metastore.as_ref()).await?;
+    ingest_api_service
+        .ask_for_res(CreateQueueIfNotExistsRequest {
+            queue_id: EVENTS_INDEX_ID.to_string(),
+        })
+        .await
+        .context("Failed to create the events index queue.")?;
+    if let Some(event_rx) = install_event_sink() {
+        tokio::spawn(events_writing_loop(node_id, event_rx, ingest_api_service));
+    }
+    Ok(())
+}
+
+async fn create_events_index_if_not_exists(
+    default_index_root_uri: &Uri,
+    metastore: &dyn Metastore,
+) -> anyhow::Result<()> {
+    match metastore.index_metadata(EVENTS_INDEX_ID).await {
+        Ok(_) => return Ok(()),
+        Err(MetastoreError::IndexDoesNotExist { .. }) => {}
+        Err(error) => return Err(error.into()),
+    }
+    let index_uri = default_index_root_uri.join(EVENTS_INDEX_ID)?;
+    let index_metadata = events_index_metadata(index_uri);
+    match metastore.create_index(index_metadata).await {
+        // Another indexer may have created the index in the meantime.
+        Ok(()) | Err(MetastoreError::IndexAlreadyExists { .. }) => {}
+        Err(error) => return Err(error.into()),
+    }
+    info!(index_id = EVENTS_INDEX_ID, "Created events index.");
+    Ok(())
+}
+
+/// Builds the metadata of the events index. The index bypasses the validation of user index
+/// configs: its ID starts with an underscore, which user index IDs cannot.
+fn events_index_metadata(index_uri: Uri) -> IndexMetadata {
+    let doc_mapping: DocMapping = serde_json::from_value(json!({
+        "field_mappings": [
+            {
+                "name": "timestamp",
+                "type": "datetime",
+                "input_formats": ["unix_timestamp"],
+                "precision": "seconds",
+                "fast": true
+            },
+            {"name": "node_id", "type": "text", "tokenizer": "raw"},
+            {"name": "event_type", "type": "text", "tokenizer": "raw"},
+            {"name": "index_id", "type": "text", "tokenizer": "raw"},
+            {"name": "message", "type": "text"},
+            {"name": "attributes", "type": "json"}
+        ],
+        "tag_fields": ["event_type"]
+    }))
+    .expect("The events index doc mapping should be valid.");
+    let indexing_settings: IndexingSettings = serde_json::from_value(json!({
+        "timestamp_field": "timestamp",
+        "commit_timeout_secs": 60
+    }))
+    .expect("The events index indexing settings should be valid.");
+    let search_settings = SearchSettings {
+        default_search_fields: vec!["message".to_string()],
+        ..Default::default()
+    };
+    let retention_policy: RetentionPolicy = serde_json::from_value(json!({
+        "period": "30 days",
+        "cutoff_reference": "split_timestamp_field",
+        "schedule": "daily"
+    }))
+    .expect("The events index retention policy should be valid.");
+    let ingest_api_source_config = SourceConfig::ingest_api_default();
+    let sources = HashMap::from([(
+        ingest_api_source_config.source_id.clone(),
+        ingest_api_source_config,
+    )]);
+    let now_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+    IndexMetadata {
+        index_id: EVENTS_INDEX_ID.to_string(),
+        index_uri,
+        checkpoint: Default::default(),
+        publish_tokens: Default::default(),
+        sources,
+        stored_queries: Default::default(),
+        alert_states: Default::default(),
+        doc_mapping,
+        indexing_settings,
+        search_settings,
+        retention_policy: Some(retention_policy),
+        read_only: false,
+        create_timestamp: now_timestamp,
+        update_timestamp: now_timestamp,
+        tiering_policy: None,
+    }
+}
+
+async fn events_writing_loop(
+    node_id: String,
+    mut event_rx: mpsc::Receiver<Event>,
+    ingest_api_service: Mailbox<IngestApiService>,
+) {
+    while let Some(event) = event_rx.recv().await {
+        let mut doc_batch_builder = DocBatchBuilder::new(EVENTS_INDEX_ID.to_string());
+        add_event_doc(&node_id, &event, &mut doc_batch_builder);
+        tokio::time::sleep(EVENTS_BATCH_DELAY).await;
+        while let Ok(event) = event_rx.try_recv() {
+            add_event_doc(&node_id, &event, &mut doc_batch_builder);
+        }
+        let ingest_req = IngestRequest {
+            doc_batches: vec![doc_batch_builder.build()],
+        };
+        // Failing to write events must not be recorded as an event itself.
+        if let Err(error) = ingest_api_service.ask_for_res(ingest_req).await {
+            warn!(error = ?error, "Failed to write events into the events index.");
+        }
+    }
+}
+
+fn add_event_doc(node_id: &str, event: &Event, doc_batch_builder: &mut DocBatchBuilder) {
+    let event_doc = EventDoc { node_id, event };
+    let event_json = serde_json::to_vec(&event_doc).expect("Events should be serializable.");
+    doc_batch_builder.add_doc(&event_json);
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_common::events::EventType;
+    use quickwit_config::build_doc_mapper;
+    use quickwit_ingest_api::iter_doc_payloads;
+
+    use super::*;
+
+    #[test]
+    fn test_events_index_metadata() {
+        let index_uri = Uri::from_well_formed(format!("ram:///indexes/{}", EVENTS_INDEX_ID));
+        let index_metadata = events_index_metadata(index_uri);
+        let doc_mapper = build_doc_mapper(
+            &index_metadata.doc_mapping,
+            &index_metadata.search_settings,
+            &index_metadata.indexing_settings,
+        )
+        .unwrap();
+
+        let event = Event::new(EventType::PipelineRestart, "Indexing pipeline failed.")
+            .with_index_id("hdfs-logs")
+            .with_attribute("source_id", "kafka-source");
+        let mut doc_batch_builder = DocBatchBuilder::new(EVENTS_INDEX_ID.to_string());
+        add_event_doc("node-1", &event, &mut doc_batch_builder);
+        let doc_batch = doc_batch_builder.build();
+        let event_json = iter_doc_payloads(&doc_batch).next().unwrap();
+        doc_mapper.doc_from_json_bytes(event_json).unwrap();
+    }
+}
@@ -24,15 +24,20 @@ use anyhow::Context;
 use quickwit_config::service::QuickwitService;
 use quickwit_jaeger::JaegerService;
 use quickwit_metastore::GrpcMetastoreAdapter;
-use quickwit_opentelemetry::otlp::OtlpGrpcTraceService;
+use quickwit_opentelemetry::otlp::{OtlpGrpcLogsService, OtlpGrpcTraceService};
+use quickwit_proto::ingest_api::ingest_api_service_server::IngestApiServiceServer;
 use quickwit_proto::jaeger::storage::v1::span_reader_plugin_server::SpanReaderPluginServer;
 use quickwit_proto::metastore_api::metastore_api_service_server::MetastoreApiServiceServer;
+use quickwit_proto::opentelemetry::proto::collector::logs::v1::logs_service_server::LogsServiceServer;
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceServiceServer;
+use quickwit_proto::push_api::push_api_service_server::PushApiServiceServer;
 use quickwit_proto::search_service_server::SearchServiceServer;
 use quickwit_proto::tonic;
 use tonic::transport::Server;
 use tracing::*;
 
+use crate::ingest_api::GrpcIngestApiAdapter;
+use crate::push_api::GrpcPushApiAdapter;
 use crate::search_api::GrpcSearchAdapter;
 use crate::QuickwitServices;
 
@@ -53,6 +58,24 @@ pub(crate) async fn start_grpc_server(
     } else {
         None
     };
+    // Mount gRPC ingest API service if `QuickwitService::Indexer` is enabled on node.
+    let ingest_api_service = if let Some(ingest_api_service) = &services.ingest_api_service {
+        enabled_grpc_services.insert("ingest-api");
+        let grpc_ingest_api_service = GrpcIngestApiAdapter::new(
+            ingest_api_service.clone(),
+            services.index_auto_creator.clone(),
+        );
+        Some(IngestApiServiceServer::new(grpc_ingest_api_service))
+    } else {
+        None
+    };
+    // Mount gRPC push API service if `QuickwitService::Indexer` is enabled on node.
+    let push_api_service = if services.services.contains(&QuickwitService::Indexer) {
+        enabled_grpc_services.insert("push-api");
+        Some(PushApiServiceServer::new(GrpcPushApiAdapter::default()))
+    } else {
+        None
+    };
     // Mount gRPC OpenTelemetry OTLP trace service if `QuickwitService::Indexer` is enabled on node.
     let enable_opentelemetry_otlp_service = services
         .config
@@ -72,6 +95,21 @@ pub(crate) async fn start_grpc_server(
     } else {
         None
     };
+    // Mount gRPC OpenTelemetry OTLP logs service if `QuickwitService::Indexer` is enabled on node.
+    let otlp_logs_service = if enable_opentelemetry_otlp_service
+        && services.services.contains(&QuickwitService::Indexer)
+    {
+        enabled_grpc_services.insert("otlp-logs");
+        let ingest_api_service = services
+            .ingest_api_service
+            .clone()
+            .context("Failed to instantiate OTLP logs service: the ingest API is disabled.")?;
+        Some(LogsServiceServer::new(OtlpGrpcLogsService::new(
+            ingest_api_service,
+        )))
+    } else {
+        None
+    };
     // Mount gRPC search service if `QuickwitService::Searcher` is enabled on node.
     let search_service = if services.services.contains(&QuickwitService::Searcher) {
         enabled_grpc_services.insert("search");
@@ -94,6 +132,9 @@ pub(crate) async fn start_grpc_server(
         };
     let server_router = server
         .add_optional_service(metastore_service)
+        .add_optional_service(ingest_api_service)
+        .add_optional_service(push_api_service)
+        .add_optional_service(otlp_logs_service)
         .add_optional_service(otlp_trace_service)
         .add_optional_service(search_service)
         .add_optional_service(jaeger_service);
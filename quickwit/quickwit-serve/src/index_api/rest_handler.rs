@@ -21,12 +21,18 @@ use std::convert::Infallible;
 use std::sync::Arc;
 
 use quickwit_actors::Mailbox;
-use quickwit_config::IndexConfig;
-use quickwit_core::IndexService;
+use quickwit_cluster::Cluster;
+use quickwit_common::uri::Uri;
+use quickwit_config::service::QuickwitService;
+use quickwit_config::{IndexConfig, StoredQuery};
+use quickwit_core::{IndexService, SimulatedDoc};
 use quickwit_indexing::models::SpawnPipelines;
 use quickwit_indexing::IndexingService;
+use quickwit_proto::ServiceErrorCode;
 use quickwit_search::SearchError;
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use tracing::info;
 use warp::{Filter, Rejection};
 
@@ -36,11 +42,22 @@ use crate::{require, with_arg};
 pub fn index_management_handlers(
     index_service: Arc<IndexService>,
     indexer_service: Option<Mailbox<IndexingService>>,
+    cluster: Arc<Cluster>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     get_index_metadata_handler(index_service.clone())
         .or(get_indexes_metadatas_handler(index_service.clone()))
         .or(get_all_splits_handler(index_service.clone()))
-        .or(create_index_handler(index_service.clone(), indexer_service))
+        .or(get_field_stats_handler(index_service.clone()))
+        .or(create_index_handler(
+            index_service.clone(),
+            indexer_service,
+            cluster,
+        ))
+        .or(attach_index_handler(index_service.clone()))
+        .or(get_stored_queries_handler(index_service.clone()))
+        .or(put_stored_query_handler(index_service.clone()))
+        .or(delete_stored_query_handler(index_service.clone()))
+        .or(simulate_ingest_handler(index_service.clone()))
         .or(delete_index_handler(index_service))
 }
 
@@ -89,6 +106,24 @@ fn get_all_splits_handler(
         .and_then(get_all_splits)
 }
 
+async fn get_field_stats(
+    index_id: String,
+    index_service: Arc<IndexService>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!(index_id = %index_id, "get-field-stats");
+    let field_stats = index_service.get_field_stats(&index_id).await;
+    Ok(Format::default().make_rest_reply_non_serializable_error(field_stats))
+}
+
+fn get_field_stats_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "field-stats")
+        .and(warp::get())
+        .and(warp::path::end().map(move || index_service.clone()))
+        .and_then(get_field_stats)
+}
+
 async fn get_indexes_metadatas(
     index_service: Arc<IndexService>,
 ) -> Result<impl warp::Reply, Infallible> {
@@ -103,11 +138,13 @@ async fn get_indexes_metadatas(
 fn create_index_handler(
     index_service: Arc<IndexService>,
     indexer_service: Option<Mailbox<IndexingService>>,
+    cluster: Arc<Cluster>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     warp::path!("indexes")
         .and(warp::post())
         .and(json_body())
         .and(require(indexer_service))
+        .and(with_arg(cluster))
         .and(warp::path::end().map(move || index_service.clone()))
         .and_then(create_index)
 }
@@ -120,9 +157,29 @@ fn json_body<T: DeserializeOwned + Send>(
 async fn create_index(
     index_config: IndexConfig,
     indexer_service: Mailbox<IndexingService>,
+    cluster: Arc<Cluster>,
     index_service: Arc<IndexService>,
 ) -> Result<impl warp::Reply, Infallible> {
     info!(index_id = %index_config.index_id, "create-index");
+    if let Some(merge_node_id) = &index_config.indexing_settings.merge_node_id {
+        // The indexers stop merging the splits of the index as soon as their pipelines are
+        // spawned: a merge node missing from the cluster would leave the splits unmerged.
+        let is_merge_node_member = cluster.ready_members().await.iter().any(|member| {
+            member.node_unique_id == *merge_node_id
+                && member
+                    .available_services
+                    .contains(&QuickwitService::Indexer)
+        });
+        if !is_merge_node_member {
+            return Ok(Format::default().make_reply_for_err(FormatError {
+                code: ServiceErrorCode::BadRequest,
+                error: format!(
+                    "Merge node `{}` is not an indexer of the cluster.",
+                    merge_node_id
+                ),
+            }));
+        }
+    }
     let index_metadata = index_service.create_index(index_config, false).await;
 
     if let Ok(ref index_metadata) = index_metadata {
@@ -139,6 +196,138 @@ async fn create_index(
     Ok(Format::default().make_rest_reply_non_serializable_error(index_metadata))
 }
 
+/// Body of the attach index endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AttachIndexRequest {
+    /// URI of a snapshot or of a manifest file.
+    manifest_uri: Uri,
+    /// ID of the attached index. Defaults to the ID of the snapshotted index.
+    #[serde(default)]
+    index_id: Option<String>,
+}
+
+fn attach_index_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("indexes" / "attach")
+        .and(warp::post())
+        .and(json_body())
+        .and(warp::path::end().map(move || index_service.clone()))
+        .and_then(attach_index)
+}
+
+async fn attach_index(
+    attach_index_request: AttachIndexRequest,
+    index_service: Arc<IndexService>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!(manifest_uri = %attach_index_request.manifest_uri, "attach-index");
+    // The attached index is read-only: unlike index creation, no pipeline is spawned.
+    let index_metadata = index_service
+        .attach_index(
+            &attach_index_request.manifest_uri,
+            attach_index_request.index_id,
+        )
+        .await
+        .map_err(SearchError::from);
+    Ok(Format::default().make_rest_reply_non_serializable_error(index_metadata))
+}
+
+fn get_stored_queries_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "stored-queries")
+        .and(warp::get())
+        .and(warp::path::end().map(move || index_service.clone()))
+        .and_then(get_stored_queries)
+}
+
+async fn get_stored_queries(
+    index_id: String,
+    index_service: Arc<IndexService>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!(index_id = %index_id, "get-stored-queries");
+    let stored_queries = index_service.list_stored_queries(&index_id).await;
+    Ok(Format::default().make_rest_reply_non_serializable_error(stored_queries))
+}
+
+fn put_stored_query_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "stored-queries")
+        .and(warp::post())
+        .and(json_body())
+        .and(warp::path::end().map(move || index_service.clone()))
+        .and_then(put_stored_query)
+}
+
+async fn put_stored_query(
+    index_id: String,
+    stored_query: StoredQuery,
+    index_service: Arc<IndexService>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!(index_id = %index_id, stored_query = %stored_query.name, "put-stored-query");
+    let stored_query = index_service
+        .put_stored_query(&index_id, stored_query)
+        .await;
+    Ok(Format::default().make_rest_reply_non_serializable_error(stored_query))
+}
+
+fn delete_stored_query_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "stored-queries" / String)
+        .and(warp::delete())
+        .and(warp::path::end().map(move || index_service.clone()))
+        .and_then(delete_stored_query)
+}
+
+async fn delete_stored_query(
+    index_id: String,
+    stored_query_name: String,
+    index_service: Arc<IndexService>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!(index_id = %index_id, stored_query = %stored_query_name, "delete-stored-query");
+    let delete_res = index_service
+        .delete_stored_query(&index_id, &stored_query_name)
+        .await;
+    Ok(Format::default().make_rest_reply_non_serializable_error(delete_res))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SimulateIngestRequest {
+    docs: Vec<JsonValue>,
+}
+
+#[derive(Serialize)]
+struct SimulateIngestResponse {
+    docs: Vec<SimulatedDoc>,
+}
+
+fn simulate_ingest_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "_simulate")
+        .and(warp::post())
+        .and(json_body())
+        .and(warp::path::end().map(move || index_service.clone()))
+        .and_then(simulate_ingest)
+}
+
+async fn simulate_ingest(
+    index_id: String,
+    simulate_ingest_request: SimulateIngestRequest,
+    index_service: Arc<IndexService>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!(index_id = %index_id, num_docs = simulate_ingest_request.docs.len(), "simulate-ingest");
+    let simulate_res = index_service
+        .simulate_ingest(&index_id, simulate_ingest_request.docs)
+        .await
+        .map(|docs| SimulateIngestResponse { docs });
+    Ok(Format::default().make_rest_reply_non_serializable_error(simulate_res))
+}
+
 fn delete_index_handler(
     index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
@@ -162,14 +351,25 @@ mod tests {
     use std::ops::Range;
 
     use assert_json_diff::assert_json_include;
+    use chitchat::transport::ChannelTransport;
+    use quickwit_actors::create_test_mailbox;
+    use quickwit_cluster::create_cluster_for_test;
     use quickwit_common::uri::Uri;
     use quickwit_indexing::mock_split;
-    use quickwit_metastore::{IndexMetadata, MockMetastore, SplitState};
+    use quickwit_metastore::{IndexMetadata, MockMetastore, SplitFieldStats, SplitState};
     use quickwit_storage::StorageUriResolver;
 
     use super::*;
     use crate::recover_fn;
 
+    async fn cluster_for_test() -> Arc<Cluster> {
+        let transport = ChannelTransport::default();
+        let cluster = create_cluster_for_test(Vec::new(), &["indexer"], &transport, true)
+            .await
+            .unwrap();
+        Arc::new(cluster)
+    }
+
     #[tokio::test]
     async fn test_rest_get_index() -> anyhow::Result<()> {
         let mut metastore = MockMetastore::new();
@@ -186,8 +386,12 @@ mod tests {
             StorageUriResolver::for_test(),
             Uri::from_well_formed("ram:///indexes".to_string()),
         );
-        let index_management_handler =
-            super::index_management_handlers(Arc::new(index_service), None).recover(recover_fn);
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            None,
+            cluster_for_test().await,
+        )
+        .recover(recover_fn);
         let resp = warp::test::request()
             .path("/indexes/test-index")
             .reply(&index_management_handler)
@@ -216,8 +420,12 @@ mod tests {
             StorageUriResolver::for_test(),
             Uri::from_well_formed("ram:///indexes".to_string()),
         );
-        let index_management_handler =
-            super::index_management_handlers(Arc::new(index_service), None).recover(recover_fn);
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            None,
+            cluster_for_test().await,
+        )
+        .recover(recover_fn);
         let resp = warp::test::request()
             .path("/indexes/quickwit-demo-index/splits")
             .reply(&index_management_handler)
@@ -249,8 +457,12 @@ mod tests {
             StorageUriResolver::for_test(),
             Uri::from_well_formed("ram:///indexes".to_string()),
         );
-        let index_management_handler =
-            super::index_management_handlers(Arc::new(index_service), None).recover(recover_fn);
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            None,
+            cluster_for_test().await,
+        )
+        .recover(recover_fn);
         let resp = warp::test::request()
             .path("/indexes")
             .reply(&index_management_handler)
@@ -298,8 +510,12 @@ mod tests {
             StorageUriResolver::for_test(),
             Uri::new("file:///default-index-uri".to_string()),
         );
-        let index_management_handler =
-            super::index_management_handlers(Arc::new(index_service), None).recover(recover_fn);
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            None,
+            cluster_for_test().await,
+        )
+        .recover(recover_fn);
         let resp = warp::test::request()
             .path("/indexes/quickwit-demo-index")
             .method("DELETE")
@@ -314,7 +530,195 @@ mod tests {
         assert_json_include!(actual: resp_json, expected: expected_response_json);
         Ok(())
     }
-    //
+    #[tokio::test]
+    async fn test_rest_get_field_stats() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_splits()
+            .withf(|index_id, split_state, _time_range, _tags| {
+                index_id == "quickwit-demo-index" && *split_state == SplitState::Published
+            })
+            .returning(|_index_id, _split_state, _time_range, _tags| {
+                let mut split = mock_split("split_1");
+                split.split_metadata.field_stats.insert(
+                    "body".to_string(),
+                    SplitFieldStats {
+                        termdict_num_bytes: 10,
+                        postings_num_bytes: 20,
+                        num_terms: 3,
+                        ..Default::default()
+                    },
+                );
+                Ok(vec![split, mock_split("split_2")])
+            });
+        let index_service = IndexService::new(
+            Arc::new(metastore),
+            StorageUriResolver::for_test(),
+            Uri::from_well_formed("ram:///indexes".to_string()),
+        );
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            None,
+            cluster_for_test().await,
+        )
+        .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes/quickwit-demo-index/field-stats")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: serde_json::Value = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "num_splits": 1,
+            "num_splits_without_stats": 1,
+            "fields": {
+                "body": {
+                    "num_splits": 1,
+                    "total_num_bytes": 30,
+                    "min_cardinality": 3,
+                    "max_cardinality": 3,
+                    "num_fast_splits": 0,
+                }
+            }
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_put_stored_query() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_put_stored_query()
+            .withf(|index_id, stored_query| {
+                index_id == "quickwit-demo-index" && stored_query.name == "errors"
+            })
+            .times(1)
+            .returning(|_index_id, _stored_query| Ok(()));
+        let index_service = IndexService::new(
+            Arc::new(metastore),
+            StorageUriResolver::for_test(),
+            Uri::from_well_formed("ram:///indexes".to_string()),
+        );
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            None,
+            cluster_for_test().await,
+        )
+        .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes/quickwit-demo-index/stored-queries")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"name": "errors", "query": "level:{{level}}", "params": [{"name": "level", "default": "error"}]}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: serde_json::Value = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "name": "errors",
+            "query": "level:{{level}}",
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+
+        // Undeclared parameters are rejected before reaching the metastore.
+        let resp = warp::test::request()
+            .path("/indexes/quickwit-demo-index/stored-queries")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"name": "errors", "query": "level:{{level}}"}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_simulate_ingest() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .withf(|index_id| index_id == "quickwit-demo-index")
+            .returning(|_index_id: &str| {
+                let mut index_metadata =
+                    IndexMetadata::for_test("quickwit-demo-index", "ram:///indexes/test-index");
+                index_metadata.doc_mapping = serde_json::from_str(
+                    r#"{
+                        "field_mappings": [
+                            {"name": "timestamp", "type": "i64", "fast": true},
+                            {"name": "body", "type": "text", "stored": true}
+                        ]
+                    }"#,
+                )
+                .unwrap();
+                index_metadata.search_settings.default_search_fields = vec!["body".to_string()];
+                Ok(index_metadata)
+            });
+        let index_service = IndexService::new(
+            Arc::new(metastore),
+            StorageUriResolver::for_test(),
+            Uri::from_well_formed("ram:///indexes".to_string()),
+        );
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            None,
+            cluster_for_test().await,
+        )
+        .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes/quickwit-demo-index/_simulate")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"docs": [{"timestamp": 1, "body": "hello"}, {"body": "no timestamp"}]}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: serde_json::Value = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "docs": [{
+                "doc": {"timestamp": [1], "body": ["hello"]},
+                "timestamp": 1,
+            }, {}]
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+        let error = resp_json["docs"][1]["error"].as_str().unwrap();
+        assert!(error.contains("timestamp"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_create_index_with_unknown_merge_node() -> anyhow::Result<()> {
+        let metastore = MockMetastore::new();
+        let index_service = IndexService::new(
+            Arc::new(metastore),
+            StorageUriResolver::for_test(),
+            Uri::from_well_formed("ram:///indexes".to_string()),
+        );
+        let (indexer_service_mailbox, _indexer_service_inbox) = create_test_mailbox();
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            Some(indexer_service_mailbox),
+            cluster_for_test().await,
+        )
+        .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes")
+            .method("POST")
+            .json(&true)
+            .body(
+                r#"{"version": 0, "index_id": "hdfs-logs", "doc_mapping": {"field_mappings":[]}, "indexing_settings": {"merge_node_id": "unknown-node"}}"#,
+            )
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let resp_json: serde_json::Value = serde_json::from_slice(resp.body())?;
+        assert!(resp_json["error"]
+            .as_str()
+            .unwrap()
+            .contains("unknown-node"));
+        Ok(())
+    }
+
     // #[tokio::test]
     // async fn test_rest_create_index() -> anyhow::Result<()> {
     //     let mut metastore = MockMetastore::new();
@@ -19,4 +19,8 @@
 
 mod rest_handler;
 
-pub use rest_handler::indexing_get_handler;
+pub use rest_handler::{
+    get_source_checkpoint_handler, indexing_get_handler, indexing_pipelines_handler,
+    merge_splits_handler, reload_source_handler, reset_source_checkpoint_handler,
+    restart_pipeline_handler,
+};
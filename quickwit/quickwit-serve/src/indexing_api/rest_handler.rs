@@ -21,12 +21,57 @@ use std::convert::Infallible;
 
 use quickwit_actors::Mailbox;
 use quickwit_indexing::actors::IndexingService;
-use quickwit_indexing::models::Observe;
+use quickwit_indexing::models::{
+    CheckpointResetTarget, GetSourceCheckpoint, MergeSplits, Observe, ObservePipelines,
+    ReloadSourcePipelines, ResetSourceCheckpoint, RestartPipeline,
+};
+use quickwit_metastore::checkpoint::{PartitionId, Position};
+use serde::Deserialize;
 use warp::{Filter, Rejection};
 
-use crate::format::Format;
+use crate::format::{Format, FormatError};
 use crate::require;
 
+/// Body of a merge request.
+#[derive(Deserialize, Debug, Eq, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MergeSplitsRequest {
+    /// IDs of the splits to merge. If omitted, all the splits that can be merged are merged.
+    #[serde(default)]
+    pub split_ids: Option<Vec<String>>,
+}
+
+/// Body of a source checkpoint reset request.
+#[derive(Deserialize, Debug, Eq, PartialEq)]
+#[serde(tag = "target", rename_all = "snake_case")]
+pub enum ResetSourceCheckpointRequest {
+    /// Clears the checkpoint.
+    Earliest,
+    /// Moves every partition to the position of its last record.
+    Latest,
+    /// Moves the partition `partition_id` to `position`.
+    Position {
+        partition_id: String,
+        position: String,
+    },
+}
+
+impl From<ResetSourceCheckpointRequest> for CheckpointResetTarget {
+    fn from(reset_request: ResetSourceCheckpointRequest) -> Self {
+        match reset_request {
+            ResetSourceCheckpointRequest::Earliest => CheckpointResetTarget::Earliest,
+            ResetSourceCheckpointRequest::Latest => CheckpointResetTarget::Latest,
+            ResetSourceCheckpointRequest::Position {
+                partition_id,
+                position,
+            } => CheckpointResetTarget::Position {
+                partition_id: PartitionId::from(partition_id),
+                position: Position::from(position),
+            },
+        }
+    }
+}
+
 async fn indexing_endpoint(
     indexing_service_mailbox: Mailbox<IndexingService>,
 ) -> Result<impl warp::Reply, Infallible> {
@@ -45,3 +90,337 @@ pub fn indexing_get_handler(
         .and(require(indexing_service_mailbox_opt))
         .and_then(indexing_endpoint)
 }
+
+fn indexing_pipelines_filter() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("indexing" / "pipelines").and(warp::get())
+}
+
+async fn indexing_pipelines_endpoint(
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let pipeline_statuses = indexing_service_mailbox.ask(ObservePipelines).await;
+    Ok(Format::PrettyJson.make_rest_reply_non_serializable_error(pipeline_statuses))
+}
+
+/// Lists the indexing pipelines running on this node along with their statistics, generation,
+/// number of spawn attempts, and last error.
+pub fn indexing_pipelines_handler(
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    indexing_pipelines_filter()
+        .and(require(indexing_service_mailbox_opt))
+        .and_then(indexing_pipelines_endpoint)
+}
+
+fn restart_pipeline_filter(
+) -> impl Filter<Extract = (String, String, usize), Error = Rejection> + Clone {
+    warp::path!("indexing" / "pipelines" / String / String / usize / "restart").and(warp::post())
+}
+
+async fn restart_pipeline_endpoint(
+    index_id: String,
+    source_id: String,
+    pipeline_ord: usize,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let restart_pipeline = RestartPipeline {
+        index_id,
+        source_id,
+        pipeline_ord,
+    };
+    let restart_pipeline_response = indexing_service_mailbox
+        .ask_for_res(restart_pipeline)
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply(restart_pipeline_response))
+}
+
+/// Terminates the actors of an indexing pipeline running on this node and spawns a new
+/// generation of the pipeline.
+pub fn restart_pipeline_handler(
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    restart_pipeline_filter()
+        .and(require(indexing_service_mailbox_opt))
+        .and_then(restart_pipeline_endpoint)
+}
+
+fn merge_splits_filter(
+) -> impl Filter<Extract = (String, MergeSplitsRequest), Error = Rejection> + Clone {
+    warp::path!(String / "merge")
+        .and(warp::post())
+        .and(warp::body::json())
+}
+
+async fn merge_splits_endpoint(
+    index_id: String,
+    merge_splits_request: MergeSplitsRequest,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let merge_splits = MergeSplits {
+        index_id,
+        split_ids_opt: merge_splits_request.split_ids,
+    };
+    let merge_splits_response = indexing_service_mailbox
+        .ask_for_res(merge_splits)
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply(merge_splits_response))
+}
+
+/// Forces the merge of the splits of an index on this node, regardless of the merge policy.
+pub fn merge_splits_handler(
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    merge_splits_filter()
+        .and(require(indexing_service_mailbox_opt))
+        .and_then(merge_splits_endpoint)
+}
+
+fn reload_source_filter() -> impl Filter<Extract = (String, String), Error = Rejection> + Clone {
+    warp::path!(String / "sources" / String / "reload").and(warp::post())
+}
+
+async fn reload_source_endpoint(
+    index_id: String,
+    source_id: String,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let reload_source_pipelines = ReloadSourcePipelines {
+        index_id,
+        source_id,
+    };
+    let reload_source_response = indexing_service_mailbox
+        .ask_for_res(reload_source_pipelines)
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply(reload_source_response))
+}
+
+/// Restarts the pipelines of a source on this node so that the source config stored in the
+/// metastore takes effect immediately.
+pub fn reload_source_handler(
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    reload_source_filter()
+        .and(require(indexing_service_mailbox_opt))
+        .and_then(reload_source_endpoint)
+}
+
+fn get_source_checkpoint_filter(
+) -> impl Filter<Extract = (String, String), Error = Rejection> + Clone {
+    warp::path!(String / "sources" / String / "checkpoint").and(warp::get())
+}
+
+async fn get_source_checkpoint_endpoint(
+    index_id: String,
+    source_id: String,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let get_source_checkpoint = GetSourceCheckpoint {
+        index_id,
+        source_id,
+    };
+    let source_checkpoint = indexing_service_mailbox
+        .ask_for_res(get_source_checkpoint)
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply(source_checkpoint))
+}
+
+/// Returns the checkpoint of a source, i.e. the last position indexed for each partition.
+pub fn get_source_checkpoint_handler(
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    get_source_checkpoint_filter()
+        .and(require(indexing_service_mailbox_opt))
+        .and_then(get_source_checkpoint_endpoint)
+}
+
+fn reset_source_checkpoint_filter(
+) -> impl Filter<Extract = (String, String, ResetSourceCheckpointRequest), Error = Rejection> + Clone
+{
+    warp::path!(String / "sources" / String / "checkpoint" / "reset")
+        .and(warp::post())
+        .and(warp::body::json())
+}
+
+async fn reset_source_checkpoint_endpoint(
+    index_id: String,
+    source_id: String,
+    reset_request: ResetSourceCheckpointRequest,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let reset_source_checkpoint = ResetSourceCheckpoint {
+        index_id,
+        source_id,
+        target: reset_request.into(),
+    };
+    let source_checkpoint = indexing_service_mailbox
+        .ask_for_res(reset_source_checkpoint)
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply(source_checkpoint))
+}
+
+/// Forcibly resets the checkpoint of a source, then reloads the pipelines of the source on this
+/// node so that they resume from the new checkpoint.
+pub fn reset_source_checkpoint_handler(
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    reset_source_checkpoint_filter()
+        .and(require(indexing_service_mailbox_opt))
+        .and_then(reset_source_checkpoint_endpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reload_source_filter() {
+        let (index_id, source_id) = warp::test::request()
+            .path("/my-index/sources/my-source/reload")
+            .method("POST")
+            .filter(&reload_source_filter())
+            .await
+            .unwrap();
+        assert_eq!(index_id, "my-index");
+        assert_eq!(source_id, "my-source");
+
+        warp::test::request()
+            .path("/my-index/sources/my-source/reload")
+            .method("GET")
+            .filter(&reload_source_filter())
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_source_checkpoint_filters() {
+        let (index_id, source_id) = warp::test::request()
+            .path("/my-index/sources/my-source/checkpoint")
+            .method("GET")
+            .filter(&get_source_checkpoint_filter())
+            .await
+            .unwrap();
+        assert_eq!(index_id, "my-index");
+        assert_eq!(source_id, "my-source");
+
+        let (index_id, source_id, reset_request) = warp::test::request()
+            .path("/my-index/sources/my-source/checkpoint/reset")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"target": "latest"}"#)
+            .filter(&reset_source_checkpoint_filter())
+            .await
+            .unwrap();
+        assert_eq!(index_id, "my-index");
+        assert_eq!(source_id, "my-source");
+        assert_eq!(reset_request, ResetSourceCheckpointRequest::Latest);
+
+        let (_, _, reset_request) = warp::test::request()
+            .path("/my-index/sources/my-source/checkpoint/reset")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"target": "position", "partition_id": "0", "position": "42"}"#)
+            .filter(&reset_source_checkpoint_filter())
+            .await
+            .unwrap();
+        assert_eq!(
+            CheckpointResetTarget::from(reset_request),
+            CheckpointResetTarget::Position {
+                partition_id: PartitionId::from("0"),
+                position: Position::from("42"),
+            }
+        );
+
+        let rejection = warp::test::request()
+            .path("/my-index/sources/my-source/checkpoint/reset")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"target": "beginning"}"#)
+            .filter(&reset_source_checkpoint_filter())
+            .await
+            .unwrap_err();
+        assert!(rejection
+            .find::<warp::filters::body::BodyDeserializeError>()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_indexing_pipelines_filter() {
+        warp::test::request()
+            .path("/indexing/pipelines")
+            .method("GET")
+            .filter(&indexing_pipelines_filter())
+            .await
+            .unwrap();
+
+        warp::test::request()
+            .path("/indexing/pipelines")
+            .method("POST")
+            .filter(&indexing_pipelines_filter())
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_restart_pipeline_filter() {
+        let (index_id, source_id, pipeline_ord) = warp::test::request()
+            .path("/indexing/pipelines/my-index/my-source/1/restart")
+            .method("POST")
+            .filter(&restart_pipeline_filter())
+            .await
+            .unwrap();
+        assert_eq!(index_id, "my-index");
+        assert_eq!(source_id, "my-source");
+        assert_eq!(pipeline_ord, 1);
+
+        warp::test::request()
+            .path("/indexing/pipelines/my-index/my-source/not-an-ord/restart")
+            .method("POST")
+            .filter(&restart_pipeline_filter())
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_merge_splits_filter() {
+        let (index_id, merge_splits_request) = warp::test::request()
+            .path("/my-index/merge")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"split_ids": ["split-1", "split-2"]}"#)
+            .filter(&merge_splits_filter())
+            .await
+            .unwrap();
+        assert_eq!(index_id, "my-index");
+        assert_eq!(
+            merge_splits_request.split_ids,
+            Some(vec!["split-1".to_string(), "split-2".to_string()])
+        );
+
+        let (_, merge_splits_request) = warp::test::request()
+            .path("/my-index/merge")
+            .method("POST")
+            .json(&true)
+            .body("{}")
+            .filter(&merge_splits_filter())
+            .await
+            .unwrap();
+        assert_eq!(merge_splits_request, MergeSplitsRequest::default());
+
+        let rejection = warp::test::request()
+            .path("/my-index/merge")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"splits": ["split-1"]}"#)
+            .filter(&merge_splits_filter())
+            .await
+            .unwrap_err();
+        assert!(rejection
+            .find::<warp::filters::body::BodyDeserializeError>()
+            .is_some());
+    }
+}
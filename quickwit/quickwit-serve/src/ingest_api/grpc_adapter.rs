@@ -17,21 +17,47 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeSet;
+use std::pin::Pin;
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use futures::Stream;
 use quickwit_actors::Mailbox;
-use quickwit_ingest_api::IngestApiService;
+use quickwit_ingest_api::{parse_bulk_payload, GetQueueLagRequest, IngestApiService};
 use quickwit_proto::ingest_api::{
-    ingest_api_service_server as grpc, FetchRequest, FetchResponse, IngestRequest, IngestResponse,
+    ingest_api_service_server as grpc, BulkIngestRequest, FetchRequest, FetchResponse,
+    IngestRequest, IngestResponse, IngestStreamAck, IngestStreamRequest, QueuePosition,
     TailRequest,
 };
-use quickwit_proto::{convert_to_grpc_result, tonic};
+use quickwit_proto::{convert_to_grpc_result, tonic, ServiceError};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::format::FormatError;
+use crate::ingest_api::index_auto_creator::{ingest_docs, IndexAutoCreator};
+
+/// Number of requests of an ingest stream covered by each ack. The last ack of a stream may cover
+/// fewer requests.
+const INGEST_STREAM_ACK_INTERVAL: u64 = 16;
+
+type IngestStreamAckResult = Result<IngestStreamAck, tonic::Status>;
 
 #[derive(Clone)]
-pub struct GrpcIngestApiAdapter(Mailbox<IngestApiService>);
+pub struct GrpcIngestApiAdapter {
+    ingest_api_service: Mailbox<IngestApiService>,
+    index_auto_creator_opt: Option<Arc<IndexAutoCreator>>,
+}
 
-impl From<Mailbox<IngestApiService>> for GrpcIngestApiAdapter {
-    fn from(ingest_api_service: Mailbox<IngestApiService>) -> Self {
-        GrpcIngestApiAdapter(ingest_api_service)
+impl GrpcIngestApiAdapter {
+    pub fn new(
+        ingest_api_service: Mailbox<IngestApiService>,
+        index_auto_creator_opt: Option<Arc<IndexAutoCreator>>,
+    ) -> Self {
+        GrpcIngestApiAdapter {
+            ingest_api_service,
+            index_auto_creator_opt,
+        }
     }
 }
 
@@ -42,16 +68,56 @@ impl grpc::IngestApiService for GrpcIngestApiAdapter {
         request: tonic::Request<IngestRequest>,
     ) -> Result<tonic::Response<IngestResponse>, tonic::Status> {
         let ingest_req = request.into_inner();
-        let ingest_reply = self.0.ask_for_res(ingest_req).await;
+        let ingest_reply = ingest_docs(
+            ingest_req,
+            &self.ingest_api_service,
+            self.index_auto_creator_opt.as_deref(),
+        )
+        .await;
+        convert_to_grpc_result(ingest_reply)
+    }
+
+    async fn bulk_ingest(
+        &self,
+        request: tonic::Request<BulkIngestRequest>,
+    ) -> Result<tonic::Response<IngestResponse>, tonic::Status> {
+        let bulk_ingest_req = request.into_inner();
+        let ingest_req =
+            parse_bulk_payload(&bulk_ingest_req.payload).map_err(tonic::Status::from)?;
+        let ingest_reply = ingest_docs(
+            ingest_req,
+            &self.ingest_api_service,
+            self.index_auto_creator_opt.as_deref(),
+        )
+        .await;
         convert_to_grpc_result(ingest_reply)
     }
 
+    type IngestStreamStream = Pin<Box<dyn Stream<Item = IngestStreamAckResult> + Send>>;
+
+    async fn ingest_stream(
+        &self,
+        request: tonic::Request<tonic::Streaming<IngestStreamRequest>>,
+    ) -> Result<tonic::Response<Self::IngestStreamStream>, tonic::Status> {
+        let request_stream = request.into_inner();
+        // The stream is not read while an ack waits for the client to consume it.
+        let (acks_tx, acks_rx) = mpsc::channel(1);
+        tokio::spawn(ingest_requests(
+            request_stream,
+            self.ingest_api_service.clone(),
+            self.index_auto_creator_opt.clone(),
+            acks_tx,
+        ));
+        let response_stream: Self::IngestStreamStream = Box::pin(ReceiverStream::new(acks_rx));
+        Ok(tonic::Response::new(response_stream))
+    }
+
     async fn fetch(
         &self,
         request: tonic::Request<FetchRequest>,
     ) -> Result<tonic::Response<FetchResponse>, tonic::Status> {
         let fetch_req = request.into_inner();
-        let fetch_reply = self.0.ask_for_res(fetch_req).await;
+        let fetch_reply = self.ingest_api_service.ask_for_res(fetch_req).await;
         convert_to_grpc_result(fetch_reply)
     }
 
@@ -60,7 +126,171 @@ impl grpc::IngestApiService for GrpcIngestApiAdapter {
         request: tonic::Request<TailRequest>,
     ) -> Result<tonic::Response<FetchResponse>, tonic::Status> {
         let tail_req = request.into_inner();
-        let tail_reply = self.0.ask_for_res(tail_req).await;
+        let tail_reply = self.ingest_api_service.ask_for_res(tail_req).await;
         convert_to_grpc_result(tail_reply)
     }
 }
+
+/// Ingests the requests of the stream one at a time until the stream ends or fails, and sends an
+/// ack every `INGEST_STREAM_ACK_INTERVAL` requests and once the stream ends.
+async fn ingest_requests(
+    mut request_stream: tonic::Streaming<IngestStreamRequest>,
+    ingest_api_service: Mailbox<IngestApiService>,
+    index_auto_creator_opt: Option<Arc<IndexAutoCreator>>,
+    acks_tx: mpsc::Sender<IngestStreamAckResult>,
+) {
+    let mut queue_ids = BTreeSet::new();
+    let mut num_acked_requests = 0;
+    let mut num_docs_for_processing = 0;
+    let mut num_pending_requests = 0;
+
+    loop {
+        let request = match request_stream.message().await {
+            Ok(Some(request)) => request,
+            Ok(None) => break,
+            Err(status) => {
+                let _ = acks_tx.send(Err(status)).await;
+                return;
+            }
+        };
+        queue_ids.extend(
+            request
+                .doc_batches
+                .iter()
+                .map(|doc_batch| doc_batch.index_id.clone()),
+        );
+        let ingest_req = IngestRequest {
+            doc_batches: request.doc_batches,
+        };
+        match ingest_docs(
+            ingest_req,
+            &ingest_api_service,
+            index_auto_creator_opt.as_deref(),
+        )
+        .await
+        {
+            Ok(ingest_response) => {
+                num_docs_for_processing += ingest_response.num_docs_for_processing;
+            }
+            Err(error) => {
+                let _ = acks_tx.send(Err(error.grpc_error())).await;
+                return;
+            }
+        }
+        num_pending_requests += 1;
+
+        if num_pending_requests == INGEST_STREAM_ACK_INTERVAL {
+            num_acked_requests += num_pending_requests;
+            num_pending_requests = 0;
+            let ack = make_ack(
+                &ingest_api_service,
+                &queue_ids,
+                num_acked_requests,
+                num_docs_for_processing,
+            )
+            .await;
+            let is_err = ack.is_err();
+            // The client may have closed the stream.
+            if acks_tx.send(ack).await.is_err() || is_err {
+                return;
+            }
+        }
+    }
+    if num_pending_requests > 0 {
+        num_acked_requests += num_pending_requests;
+        let ack = make_ack(
+            &ingest_api_service,
+            &queue_ids,
+            num_acked_requests,
+            num_docs_for_processing,
+        )
+        .await;
+        let _ = acks_tx.send(ack).await;
+    }
+}
+
+async fn make_ack(
+    ingest_api_service: &Mailbox<IngestApiService>,
+    queue_ids: &BTreeSet<String>,
+    num_acked_requests: u64,
+    num_docs_for_processing: u64,
+) -> IngestStreamAckResult {
+    let mut queue_positions = Vec::with_capacity(queue_ids.len());
+
+    for queue_id in queue_ids {
+        let queue_lag = ingest_api_service
+            .ask_for_res(GetQueueLagRequest {
+                queue_id: queue_id.clone(),
+            })
+            .await
+            .map_err(|error| FormatError::wrap(error).grpc_error())?;
+        if let Some(write_position) = queue_lag.write_position {
+            queue_positions.push(QueuePosition {
+                queue_id: queue_id.clone(),
+                position: write_position,
+            });
+        }
+    }
+    Ok(IngestStreamAck {
+        num_acked_requests,
+        num_docs_for_processing,
+        queue_positions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_ingest_api::{init_ingest_api, DocBatchBuilder};
+    use quickwit_proto::ingest_api::CreateQueueIfNotExistsRequest;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_make_ack() {
+        let universe = Universe::new();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ingest_api_service = init_ingest_api(&universe, temp_dir.path()).await.unwrap();
+        for queue_id in ["queue-1", "queue-2"] {
+            ingest_api_service
+                .ask_for_res(CreateQueueIfNotExistsRequest {
+                    queue_id: queue_id.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        let mut doc_batch_builder = DocBatchBuilder::new("queue-1".to_string());
+        for doc in ["{\"a\":1}", "{\"a\":2}", "{\"a\":3}"] {
+            doc_batch_builder.add_doc(doc.as_bytes());
+        }
+        ingest_api_service
+            .ask_for_res(IngestRequest {
+                doc_batches: vec![doc_batch_builder.build()],
+            })
+            .await
+            .unwrap();
+
+        let queue_ids: BTreeSet<String> = ["queue-1".to_string(), "queue-2".to_string()]
+            .into_iter()
+            .collect();
+        let ack = make_ack(&ingest_api_service, &queue_ids, 2, 3)
+            .await
+            .unwrap();
+        assert_eq!(ack.num_acked_requests, 2);
+        assert_eq!(ack.num_docs_for_processing, 3);
+        // Empty queues have no write position.
+        assert_eq!(
+            ack.queue_positions,
+            vec![QueuePosition {
+                queue_id: "queue-1".to_string(),
+                position: 2,
+            }]
+        );
+
+        let queue_ids: BTreeSet<String> = ["queue-3".to_string()].into_iter().collect();
+        let status = make_ack(&ingest_api_service, &queue_ids, 1, 1)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Context;
+use quickwit_actors::Mailbox;
+use quickwit_config::{IndexConfig, IndexTemplateConfig};
+use quickwit_core::{IndexService, IndexServiceError};
+use quickwit_indexing::actors::IndexingService;
+use quickwit_indexing::models::SpawnPipelines;
+use quickwit_ingest_api::IngestApiService;
+use quickwit_metastore::MetastoreError;
+use quickwit_proto::ingest_api::{
+    CreateQueueIfNotExistsRequest, IngestRequest, IngestResponse, QueueExistsRequest,
+};
+use quickwit_proto::ServiceErrorCode;
+use quickwit_storage::load_file;
+use tracing::info;
+
+use crate::format::FormatError;
+
+/// Creates the indexes missing on ingest from the index templates of the node, when
+/// `auto_create_indexes` is enabled.
+pub struct IndexAutoCreator {
+    index_templates: Vec<(IndexTemplateConfig, IndexConfig)>,
+    index_service: Arc<IndexService>,
+    indexing_service: Mailbox<IndexingService>,
+    ingest_api_service: Mailbox<IngestApiService>,
+}
+
+impl IndexAutoCreator {
+    /// Loads the index configs of the templates.
+    pub async fn load(
+        index_template_configs: &[IndexTemplateConfig],
+        index_service: Arc<IndexService>,
+        indexing_service: Mailbox<IndexingService>,
+        ingest_api_service: Mailbox<IngestApiService>,
+    ) -> anyhow::Result<Self> {
+        let mut index_templates = Vec::with_capacity(index_template_configs.len());
+        for index_template_config in index_template_configs {
+            let index_config_uri = &index_template_config.index_config_uri;
+            let file_content = load_file(index_config_uri).await?;
+            let index_config = IndexConfig::load(index_config_uri, file_content.as_slice())
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to load the index config of index template `{}`.",
+                        index_template_config.template_id
+                    )
+                })?;
+            index_templates.push((index_template_config.clone(), index_config));
+        }
+        Ok(Self {
+            index_templates,
+            index_service,
+            indexing_service,
+            ingest_api_service,
+        })
+    }
+
+    /// Creates the indexes targeted by the request that do not exist yet and match a template.
+    /// The indexes matching no template are left missing, so that the ingest request fails.
+    pub async fn create_missing_indexes(
+        &self,
+        ingest_req: &IngestRequest,
+    ) -> Result<(), FormatError> {
+        let index_ids: HashSet<&str> = ingest_req
+            .doc_batches
+            .iter()
+            .map(|doc_batch| doc_batch.index_id.as_str())
+            .collect();
+        for index_id in index_ids {
+            let queue_exists = self
+                .ingest_api_service
+                .ask_for_res(QueueExistsRequest {
+                    queue_id: index_id.to_string(),
+                })
+                .await
+                .map_err(FormatError::wrap)?;
+            if queue_exists {
+                continue;
+            }
+            if let Some((index_template_config, template_index_config)) = self
+                .index_templates
+                .iter()
+                .find(|(index_template_config, _)| index_template_config.matches(index_id))
+            {
+                let index_config = index_config_from_template(template_index_config, index_id)
+                    .map_err(|error| FormatError {
+                        code: ServiceErrorCode::BadRequest,
+                        error: format!("{error:#}"),
+                    })?;
+                self.create_index(index_config, &index_template_config.template_id)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_index(
+        &self,
+        index_config: IndexConfig,
+        template_id: &str,
+    ) -> Result<(), FormatError> {
+        let index_id = index_config.index_id.clone();
+        match self.index_service.create_index(index_config, false).await {
+            Ok(_) => {
+                info!(index_id = %index_id, template_id = %template_id, "Created index from template.");
+            }
+            // Another request may have created the index in the meantime.
+            Err(IndexServiceError::MetastoreError(MetastoreError::IndexAlreadyExists {
+                ..
+            })) => {}
+            Err(error) => return Err(FormatError::wrap(error)),
+        }
+        // Spawning the pipelines is idempotent, and leaves the scheduled sources to the indexing
+        // plan if cluster scheduling is enabled.
+        self.indexing_service
+            .ask_for_res(SpawnPipelines {
+                index_id: index_id.clone(),
+            })
+            .await
+            .map_err(FormatError::wrap)?;
+        self.ingest_api_service
+            .ask_for_res(CreateQueueIfNotExistsRequest { queue_id: index_id })
+            .await
+            .map_err(FormatError::wrap)?;
+        Ok(())
+    }
+}
+
+/// Ingests the documents of a request, creating the missing indexes beforehand if the index
+/// auto-creation is enabled.
+pub(crate) async fn ingest_docs(
+    ingest_req: IngestRequest,
+    ingest_api_service: &Mailbox<IngestApiService>,
+    index_auto_creator_opt: Option<&IndexAutoCreator>,
+) -> Result<IngestResponse, FormatError> {
+    if let Some(index_auto_creator) = index_auto_creator_opt {
+        index_auto_creator
+            .create_missing_indexes(&ingest_req)
+            .await?;
+    }
+    ingest_api_service
+        .ask_for_res(ingest_req)
+        .await
+        .map_err(FormatError::wrap)
+}
+
+/// Builds the config of an index created from a template: the index gets the ID it is created
+/// for, and the default index URI.
+fn index_config_from_template(
+    template_index_config: &IndexConfig,
+    index_id: &str,
+) -> anyhow::Result<IndexConfig> {
+    let mut index_config = template_index_config.clone();
+    index_config.index_id = index_id.to_string();
+    index_config.index_uri = None;
+    index_config.validate()?;
+    Ok(index_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_common::uri::Uri;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_index_config_from_template() {
+        let index_config_uri =
+            Uri::from_well_formed("file:///config/logs-template.yaml".to_string());
+        let index_config_yaml = r#"
+            version: 0
+            index_id: logs-template
+            index_uri: s3://indexes/logs-template
+            doc_mapping:
+              field_mappings:
+                - name: body
+                  type: text
+            search_settings:
+              default_search_fields: [body]
+        "#;
+        let template_index_config =
+            IndexConfig::load(&index_config_uri, index_config_yaml.as_bytes())
+                .await
+                .unwrap();
+        let index_config = index_config_from_template(&template_index_config, "logs-app").unwrap();
+        assert_eq!(index_config.index_id, "logs-app");
+        assert!(index_config.index_uri.is_none());
+        assert_eq!(index_config.doc_mapping.field_mappings.len(), 1);
+        assert_eq!(
+            index_config.search_settings.default_search_fields,
+            ["body".to_string()]
+        );
+
+        index_config_from_template(&template_index_config, "logs/app").unwrap_err();
+    }
+}
@@ -18,6 +18,9 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 mod grpc_adapter;
+mod index_auto_creator;
 mod rest_handler;
 
-pub use rest_handler::{elastic_bulk_handler, ingest_handler, tail_handler, BulkApiError};
+pub use grpc_adapter::GrpcIngestApiAdapter;
+pub use index_auto_creator::IndexAutoCreator;
+pub use rest_handler::{elastic_bulk_handler, ingest_handler, queue_handlers, tail_handler};
@@ -17,20 +17,26 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
 use std::convert::Infallible;
+use std::sync::Arc;
 
 use bytes::Bytes;
 use quickwit_actors::Mailbox;
-use quickwit_ingest_api::{add_doc, IngestApiService};
-use quickwit_proto::ingest_api::{DocBatch, IngestRequest, TailRequest};
+use quickwit_ingest_api::{
+    ndjson_lines, parse_bulk_payload, DocBatchBuilder, GetQueueLagRequest, IngestApiService,
+    TruncateQueueRequest,
+};
+use quickwit_proto::ingest_api::{IngestRequest, TailRequest};
+use quickwit_proto::ServiceErrorCode;
 use serde::Deserialize;
-use serde_json::Value;
 use thiserror::Error;
-use warp::{reject, Filter, Rejection};
+use warp::reply::Response;
+use warp::{reject, Filter, Rejection, Reply};
 
+use crate::debug_api::{admin_filter, recover_admin_rejection};
 use crate::format::FormatError;
-use crate::{require, Format};
+use crate::ingest_api::index_auto_creator::{ingest_docs, IndexAutoCreator};
+use crate::{require, with_arg, Format};
 
 #[derive(Debug, Error)]
 #[error("Body is not utf-8.")]
@@ -46,90 +52,45 @@ impl warp::reject::Reject for IngestApiServiceUnavailable {}
 
 const CONTENT_LENGTH_LIMIT: u64 = 10_000_000; // 10M
 
-#[derive(Debug, Error)]
-pub enum BulkApiError {
-    #[error("Could not parse action `{0}`.")]
-    InvalidAction(String),
-    #[error("Could not parse the source `{0}`.")]
-    InvalidSource(String),
-}
-
-impl warp::reject::Reject for BulkApiError {}
-
-#[derive(Clone, Debug, Deserialize, PartialEq)]
-#[serde(rename_all(deserialize = "lowercase"))]
-enum BulkAction {
-    Index(BulkActionMeta),
-    Create(BulkActionMeta),
-}
-
-impl BulkAction {
-    fn into_index(self) -> String {
-        match self {
-            BulkAction::Index(meta) => meta.index,
-            BulkAction::Create(meta) => meta.index,
-        }
-    }
-}
-
-#[derive(Clone, Debug, Deserialize, PartialEq)]
-struct BulkActionMeta {
-    #[serde(alias = "_index")]
-    index: String,
-    #[serde(alias = "_id")]
-    id: String,
-}
-
 pub fn ingest_handler(
     ingest_api_mailbox_opt: Option<Mailbox<IngestApiService>>,
+    index_auto_creator_opt: Option<Arc<IndexAutoCreator>>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     ingest_filter()
         .and(require(ingest_api_mailbox_opt))
+        .and(with_arg(index_auto_creator_opt))
         .and_then(ingest)
 }
 
-fn ingest_filter() -> impl Filter<Extract = (String, String), Error = Rejection> + Clone {
+fn ingest_filter() -> impl Filter<Extract = (String, Bytes), Error = Rejection> + Clone {
     warp::path!(String / "ingest")
         .and(warp::post())
         .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
-        .and(warp::body::bytes().and_then(|body: Bytes| async move {
-            if let Ok(body_str) = std::str::from_utf8(&*body) {
-                Ok(body_str.to_string())
-            } else {
-                Err(reject::custom(InvalidUtf8))
-            }
-        }))
-}
-
-fn lines(body: &str) -> impl Iterator<Item = &str> {
-    body.lines().filter_map(|line| {
-        let line_trimmed = line.trim();
-        if line_trimmed.is_empty() {
-            return None;
-        }
-        Some(line_trimmed)
-    })
+        .and(warp::body::bytes())
 }
 
 async fn ingest(
     index_id: String,
-    payload: String,
+    payload: Bytes,
     ingest_api_mailbox: Mailbox<IngestApiService>,
-) -> Result<impl warp::Reply, Infallible> {
-    let mut doc_batch = DocBatch {
-        index_id,
-        ..Default::default()
-    };
-    for doc_payload in lines(&payload) {
-        add_doc(doc_payload.as_bytes(), &mut doc_batch);
+    index_auto_creator_opt: Option<Arc<IndexAutoCreator>>,
+) -> Result<impl warp::Reply, Rejection> {
+    // The documents are validated and split in place, and copied once into the buffer of the
+    // batch, which is then shared down to the indexing pipelines.
+    let payload_str = std::str::from_utf8(&payload).map_err(|_| reject::custom(InvalidUtf8))?;
+    let mut doc_batch_builder = DocBatchBuilder::with_capacity(index_id, payload.len());
+    for doc_payload in ndjson_lines(payload_str) {
+        doc_batch_builder.add_doc(doc_payload.as_bytes());
     }
     let ingest_req = IngestRequest {
-        doc_batches: vec![doc_batch],
+        doc_batches: vec![doc_batch_builder.build()],
     };
-    let ingest_resp = ingest_api_mailbox
-        .ask_for_res(ingest_req)
-        .await
-        .map_err(FormatError::wrap);
+    let ingest_resp = ingest_docs(
+        ingest_req,
+        &ingest_api_mailbox,
+        index_auto_creator_opt.as_deref(),
+    )
+    .await;
     Ok(Format::PrettyJson.make_rest_reply(ingest_resp))
 }
 
@@ -156,84 +117,152 @@ async fn tail_endpoint(
     Ok(Format::PrettyJson.make_rest_reply(tail_res))
 }
 
-fn elastic_bulk_filter() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+/// Queue management handlers: the lag of the queue of an index, and the truncation of its
+/// records, which is reserved to administrators.
+pub fn queue_handlers(
+    ingest_api_mailbox_opt: Option<Mailbox<IngestApiService>>,
+    admin_token_opt: Option<String>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    queue_lag_handler(ingest_api_mailbox_opt.clone()).or(truncate_queue_handler(
+        ingest_api_mailbox_opt,
+        admin_token_opt,
+    ))
+}
+
+fn queue_lag_handler(
+    ingest_api_mailbox_opt: Option<Mailbox<IngestApiService>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!(String / "queue")
+        .and(warp::get())
+        .and(require(ingest_api_mailbox_opt))
+        .and_then(queue_lag_endpoint)
+}
+
+async fn queue_lag_endpoint(
+    index_id: String,
+    ingest_api_service: Mailbox<IngestApiService>,
+) -> Result<impl warp::Reply, Infallible> {
+    let queue_lag_res = ingest_api_service
+        .ask_for_res(GetQueueLagRequest { queue_id: index_id })
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson.make_rest_reply(queue_lag_res))
+}
+
+/// Body of the truncate queue endpoint.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TruncateQueueBody {
+    /// Position up to which the records are deleted, included. All the records are deleted if
+    /// missing.
+    #[serde(default)]
+    up_to_position: Option<u64>,
+}
+
+fn truncate_queue_handler(
+    ingest_api_mailbox_opt: Option<Mailbox<IngestApiService>>,
+    admin_token_opt: Option<String>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    warp::path!(String / "queue" / "truncate")
+        .and(warp::post())
+        .and(
+            admin_filter(admin_token_opt)
+                .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
+                .and(warp::body::bytes())
+                .and(require(ingest_api_mailbox_opt))
+                .and_then(truncate_queue_endpoint)
+                .recover(recover_admin_rejection)
+                .unify(),
+        )
+}
+
+async fn truncate_queue_endpoint(
+    index_id: String,
+    body: Bytes,
+    ingest_api_service: Mailbox<IngestApiService>,
+) -> Result<Response, Infallible> {
+    // The body is optional: all the records of the queue are deleted if it is empty.
+    let body_res = if body.iter().all(u8::is_ascii_whitespace) {
+        Ok(TruncateQueueBody::default())
+    } else {
+        serde_json::from_slice::<TruncateQueueBody>(&body)
+    };
+    let truncate_queue_body = match body_res {
+        Ok(truncate_queue_body) => truncate_queue_body,
+        Err(error) => {
+            let format_error = FormatError {
+                code: ServiceErrorCode::BadRequest,
+                error: format!("Invalid truncate queue request: {error}"),
+            };
+            return Ok(Format::PrettyJson
+                .make_reply_for_err(format_error)
+                .into_response());
+        }
+    };
+    let truncate_queue_req = TruncateQueueRequest {
+        queue_id: index_id,
+        up_to_position_included: truncate_queue_body.up_to_position,
+    };
+    let queue_lag_res = ingest_api_service
+        .ask_for_res(truncate_queue_req)
+        .await
+        .map_err(FormatError::wrap);
+    Ok(Format::PrettyJson
+        .make_rest_reply(queue_lag_res)
+        .into_response())
+}
+
+fn elastic_bulk_filter() -> impl Filter<Extract = (Bytes,), Error = Rejection> + Clone {
     warp::path!("_bulk")
         .and(warp::post())
         .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
-        .and(warp::body::bytes().and_then(|body: Bytes| async move {
-            if let Ok(body_str) = std::str::from_utf8(&*body) {
-                Ok(body_str.to_string())
-            } else {
-                Err(reject::custom(InvalidUtf8))
-            }
-        }))
+        .and(warp::body::bytes())
 }
 
 pub fn elastic_bulk_handler(
     ingest_api_mailbox_opt: Option<Mailbox<IngestApiService>>,
+    index_auto_creator_opt: Option<Arc<IndexAutoCreator>>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     elastic_bulk_filter()
         .and(require(ingest_api_mailbox_opt))
+        .and(with_arg(index_auto_creator_opt))
         .and_then(elastic_ingest)
 }
 
 async fn elastic_ingest(
-    payload: String,
+    payload: Bytes,
     ingest_api_mailbox: Mailbox<IngestApiService>,
-) -> Result<impl warp::Reply, Rejection> {
-    let mut batches = HashMap::new();
-    let mut payload_lines = lines(&payload);
-
-    while let Some(json_str) = payload_lines.next() {
-        let action = serde_json::from_str::<BulkAction>(json_str)
-            .map_err(|e| BulkApiError::InvalidAction(e.to_string()))?;
-        let source = payload_lines
-            .next()
-            .ok_or_else(|| {
-                BulkApiError::InvalidSource("Expected source for the action.".to_string())
-            })
-            .and_then(|source| {
-                serde_json::from_str::<Value>(source)
-                    .map_err(|err| BulkApiError::InvalidSource(err.to_string()))
-            })?;
-
-        let index_id = action.into_index();
-        let doc_batch = batches.entry(index_id.clone()).or_insert(DocBatch {
-            index_id,
-            ..Default::default()
-        });
-
-        add_doc(source.to_string().as_bytes(), doc_batch);
-    }
-
-    let ingest_req = IngestRequest {
-        doc_batches: batches.into_iter().map(|(_, batch)| batch).collect(),
+    index_auto_creator_opt: Option<Arc<IndexAutoCreator>>,
+) -> Result<impl warp::Reply, Infallible> {
+    // The documents are split into one batch per index, each destined for the queue of its
+    // index.
+    let ingest_resp = match parse_bulk_payload(&payload) {
+        Ok(ingest_req) => {
+            ingest_docs(
+                ingest_req,
+                &ingest_api_mailbox,
+                index_auto_creator_opt.as_deref(),
+            )
+            .await
+        }
+        Err(error) => Err(FormatError::wrap(error)),
     };
-    let ingest_resp = ingest_api_mailbox
-        .ask_for_res(ingest_req)
-        .await
-        .map_err(FormatError::wrap);
     Ok(Format::PrettyJson.make_rest_reply(ingest_resp))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{BulkAction, BulkActionMeta};
+    use super::TruncateQueueBody;
 
     #[test]
-    fn test_deserialize() {
-        let json_str = r#"{ "create" : { "_index" : "test", "_id" : "2" } }"#;
-        let bulk_object = serde_json::from_str::<BulkAction>(json_str).unwrap();
-        assert_eq!(
-            bulk_object,
-            BulkAction::Create(BulkActionMeta {
-                index: "test".to_string(),
-                id: "2".to_string()
-            })
-        );
-
-        let json_str = r#"{ "delete" : { "_index" : "test", "_id" : "2" } }"#;
-        assert!(serde_json::from_str::<BulkAction>(json_str).is_err());
+    fn test_deserialize_truncate_queue_body() {
+        let body = serde_json::from_str::<TruncateQueueBody>(r#"{"up_to_position": 42}"#).unwrap();
+        assert_eq!(body.up_to_position, Some(42));
+
+        let body = serde_json::from_str::<TruncateQueueBody>("{}").unwrap();
+        assert_eq!(body.up_to_position, None);
+
+        serde_json::from_str::<TruncateQueueBody>(r#"{"up_to": 42}"#).unwrap_err();
     }
 
     // TODO: find a way to refactor/mock IngestApiService for testing the endpoint.
@@ -25,12 +25,16 @@ mod grpc;
 mod rest;
 
 mod cluster_api;
+mod debug_api;
 mod delete_task_api;
+mod events_index;
 mod health_check_api;
 mod index_api;
 mod indexing_api;
 mod ingest_api;
 mod node_info_handler;
+mod otel_traces_index;
+mod push_api;
 mod search_api;
 #[cfg(test)]
 mod test_utils;
@@ -52,10 +56,14 @@ use quickwit_config::service::QuickwitService;
 use quickwit_config::QuickwitConfig;
 use quickwit_core::IndexService;
 use quickwit_indexing::actors::IndexingService;
+use quickwit_indexing::models::ApplyIndexingPlan;
+use quickwit_indexing::scheduler::{IndexingPlan, INDEXING_CAPACITY_KEY, INDEXING_PLAN_KEY};
 use quickwit_indexing::start_indexing_service;
 use quickwit_ingest_api::{start_ingest_api_service, IngestApiService};
-use quickwit_janitor::{start_janitor_service, JanitorService};
-use quickwit_metastore::{quickwit_metastore_uri_resolver, Metastore, MetastoreGrpcClient};
+use quickwit_janitor::{start_control_plane_service, start_janitor_service, JanitorService};
+use quickwit_metastore::{
+    quickwit_metastore_uri_resolver, IndexFreshnessMetastore, Metastore, MetastoreGrpcClient,
+};
 use quickwit_search::{start_searcher_service, SearchClientPool, SearchService};
 use quickwit_storage::quickwit_storage_uri_resolver;
 use serde::{Deserialize, Serialize};
@@ -63,7 +71,11 @@ use tracing::{error, warn};
 use warp::{Filter, Rejection};
 
 pub use crate::args::ServeArgs;
+pub use crate::debug_api::{set_log_filter_handle, LogFilterHandle};
+use crate::events_index::start_events_index_writer;
+use crate::ingest_api::IndexAutoCreator;
 pub use crate::metrics::SERVE_METRICS;
+use crate::otel_traces_index::create_otel_traces_index_if_not_exists;
 #[cfg(test)]
 use crate::rest::recover_fn;
 
@@ -73,6 +85,12 @@ const READYNESS_REPORTING_INTERVAL: Duration = if cfg!(any(test, feature = "test
     Duration::from_secs(10)
 };
 
+const INDEXING_PLAN_POLLING_INTERVAL: Duration = if cfg!(any(test, feature = "testsuite")) {
+    Duration::from_millis(25)
+} else {
+    Duration::from_secs(10)
+};
+
 struct QuickwitServices {
     pub config: Arc<QuickwitConfig>,
     pub build_info: Arc<QuickwitBuildInfo>,
@@ -83,10 +101,11 @@ struct QuickwitServices {
     /// the root requests.
     pub search_service: Arc<dyn SearchService>,
     pub indexer_service: Option<Mailbox<IndexingService>>,
-    #[allow(dead_code)] // TODO remove
     pub janitor_service: Option<JanitorService>,
     pub ingest_api_service: Option<Mailbox<IngestApiService>>,
     pub index_service: Arc<IndexService>,
+    /// Creates the missing indexes on ingest if `auto_create_indexes` is enabled.
+    pub index_auto_creator: Option<Arc<IndexAutoCreator>>,
     pub services: HashSet<QuickwitService>,
 }
 
@@ -131,6 +150,12 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
         .await?;
         Arc::new(metastore_client)
     };
+    // Gossip the freshness of the indexes updated by this node so that searchers can serve the
+    // published splits of unchanged indexes from their cache.
+    let index_freshness_metastore = IndexFreshnessMetastore::new(metastore, cluster.clone());
+    let search_metastore: Arc<dyn Metastore> =
+        Arc::new(index_freshness_metastore.with_split_cache());
+    let metastore: Arc<dyn Metastore> = Arc::new(index_freshness_metastore);
 
     check_cluster_configuration(
         &config.enabled_services,
@@ -150,6 +175,27 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
         if config.enabled_services.contains(&QuickwitService::Indexer) {
             let ingest_api_service =
                 start_ingest_api_service(&universe, &config.data_dir_path).await?;
+            // The events index is created before the indexing service starts so that the
+            // pipeline of its ingest API source is spawned along with the others.
+            if config.indexer_config.enable_events_index {
+                start_events_index_writer(
+                    config.node_id.clone(),
+                    &config.default_index_root_uri,
+                    metastore.clone(),
+                    ingest_api_service.clone(),
+                )
+                .await?;
+            }
+            // Likewise, the index receiving the spans of the OTLP trace service is created with
+            // its built-in doc mapping before the indexing service starts.
+            if config.indexer_config.enable_opentelemetry_otlp_service {
+                create_otel_traces_index_if_not_exists(
+                    &config.default_index_root_uri,
+                    metastore.as_ref(),
+                    &ingest_api_service,
+                )
+                .await?;
+            }
             let indexing_service = start_indexing_service(
                 &universe,
                 &config,
@@ -157,6 +203,19 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
                 storage_resolver.clone(),
             )
             .await?;
+            if config.indexer_config.enable_cluster_scheduling {
+                cluster
+                    .set_key_value(
+                        INDEXING_CAPACITY_KEY,
+                        config.indexer_config.indexing_capacity.get_bytes(),
+                    )
+                    .await;
+                tokio::spawn(indexing_plan_applying_task(
+                    cluster.clone(),
+                    config.node_id.clone(),
+                    indexing_service.clone(),
+                ));
+            }
             (Some(ingest_api_service), Some(indexing_service))
         } else {
             (None, None)
@@ -179,9 +238,17 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
         None
     };
 
+    if config
+        .enabled_services
+        .contains(&QuickwitService::ControlPlane)
+    {
+        start_control_plane_service(&universe, cluster.clone(), metastore.clone());
+    }
+
     let search_service: Arc<dyn SearchService> = start_searcher_service(
         &config,
-        metastore.clone(),
+        cluster.clone(),
+        search_metastore,
         storage_resolver.clone(),
         search_client_pool,
     )
@@ -194,6 +261,22 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
         config.default_index_root_uri.clone(),
     ));
 
+    let index_auto_creator = match (&ingest_api_service, &indexer_service) {
+        (Some(ingest_api_service), Some(indexer_service))
+            if config.indexer_config.auto_create_indexes =>
+        {
+            let index_auto_creator = IndexAutoCreator::load(
+                &config.indexer_config.index_templates,
+                index_service.clone(),
+                indexer_service.clone(),
+                ingest_api_service.clone(),
+            )
+            .await?;
+            Some(Arc::new(index_auto_creator))
+        }
+        _ => None,
+    };
+
     let grpc_listen_addr = config.grpc_listen_addr;
     let rest_listen_addr = config.rest_listen_addr;
     let services = config.enabled_services.clone();
@@ -207,6 +290,7 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
         janitor_service,
         ingest_api_service,
         index_service,
+        index_auto_creator,
         services,
     };
     let grpc_server = grpc::start_grpc_server(grpc_listen_addr, &quickwit_services);
@@ -244,6 +328,51 @@ async fn node_readyness_reporting_task(cluster: Arc<Cluster>, metastore: Arc<dyn
     }
 }
 
+/// Applies the indexing tasks assigned to the node by the indexing plan published by the control
+/// plane every 10 seconds (25 ms for tests).
+///
+/// The node keeps running the tasks of the last plan applied when it cannot read the plan anymore,
+/// for instance because it is partitioned from the cluster: the tasks carry publish tokens, so the
+/// pipelines of the tasks reassigned to other indexers in the meantime cannot publish anymore.
+async fn indexing_plan_applying_task(
+    cluster: Arc<Cluster>,
+    node_id: String,
+    indexing_service: Mailbox<IndexingService>,
+) {
+    let mut interval = tokio::time::interval(INDEXING_PLAN_POLLING_INTERVAL);
+    loop {
+        interval.tick().await;
+        // If several control planes publish an indexing plan, the one with the lowest node ID wins.
+        let indexing_plan_json_opt = cluster
+            .ready_members_key_value(INDEXING_PLAN_KEY)
+            .await
+            .into_iter()
+            .filter(|(member, _)| {
+                member
+                    .available_services
+                    .contains(&QuickwitService::ControlPlane)
+            })
+            .min_by(|(left, _), (right, _)| left.node_unique_id.cmp(&right.node_unique_id))
+            .map(|(_, indexing_plan_json)| indexing_plan_json);
+        let indexing_plan: IndexingPlan = match indexing_plan_json_opt
+            .map(|indexing_plan_json| serde_json::from_str(&indexing_plan_json))
+        {
+            Some(Ok(indexing_plan)) => indexing_plan,
+            Some(Err(error)) => {
+                error!(error=?error, "Failed to deserialize indexing plan.");
+                continue;
+            }
+            None => continue,
+        };
+        let apply_indexing_plan = ApplyIndexingPlan {
+            indexing_tasks: indexing_plan.indexing_tasks_for_node(&node_id),
+        };
+        if let Err(error) = indexing_service.ask(apply_indexing_plan).await {
+            error!(error=?error, "Failed to apply indexing plan.");
+        }
+    }
+}
+
 /// Displays some warnings if the cluster runs a file-backed metastore or serves file-backed
 /// indexes.
 async fn check_cluster_configuration(
@@ -0,0 +1,177 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use quickwit_actors::Mailbox;
+use quickwit_common::uri::Uri;
+use quickwit_config::{DocMapping, IndexingSettings, SearchSettings, SourceConfig};
+use quickwit_ingest_api::IngestApiService;
+use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError};
+use quickwit_opentelemetry::otlp::OTEL_TRACES_INDEX_ID;
+use quickwit_proto::ingest_api::CreateQueueIfNotExistsRequest;
+use serde_json::json;
+use tracing::info;
+
+/// Creates the index receiving the spans of the OTLP trace service and its ingest API queue if
+/// they do not exist yet. The index has a built-in doc mapping with one document per span.
+pub(crate) async fn create_otel_traces_index_if_not_exists(
+    default_index_root_uri: &Uri,
+    metastore: &dyn Metastore,
+    ingest_api_service: &Mailbox<IngestApiService>,
+) -> anyhow::Result<()> {
+    match metastore.index_metadata(OTEL_TRACES_INDEX_ID).await {
+        Ok(_) => {}
+        Err(MetastoreError::IndexDoesNotExist { .. }) => {
+            let index_uri = default_index_root_uri.join(OTEL_TRACES_INDEX_ID)?;
+            let index_metadata = otel_traces_index_metadata(index_uri);
+            match metastore.create_index(index_metadata).await {
+                // Another indexer may have created the index in the meantime.
+                Ok(()) | Err(MetastoreError::IndexAlreadyExists { .. }) => {}
+                Err(error) => return Err(error.into()),
+            }
+            info!(
+                index_id = OTEL_TRACES_INDEX_ID,
+                "Created OTLP traces index."
+            );
+        }
+        Err(error) => return Err(error.into()),
+    }
+    ingest_api_service
+        .ask_for_res(CreateQueueIfNotExistsRequest {
+            queue_id: OTEL_TRACES_INDEX_ID.to_string(),
+        })
+        .await
+        .context("Failed to create the OTLP traces index queue.")?;
+    Ok(())
+}
+
+/// Builds the metadata of the OTLP traces index. The trace and span IDs, the service name, and
+/// the span duration are fast fields, so that spans can be grouped and sorted by them.
+fn otel_traces_index_metadata(index_uri: Uri) -> IndexMetadata {
+    let doc_mapping: DocMapping = serde_json::from_value(json!({
+        "mode": "lenient",
+        "field_mappings": [
+            {"name": "trace_id", "type": "text", "tokenizer": "raw", "fast": true},
+            {"name": "trace_state", "type": "text", "indexed": false},
+            {"name": "service_name", "type": "text", "tokenizer": "raw", "fast": true},
+            {"name": "span_id", "type": "text", "tokenizer": "raw", "fast": true},
+            {"name": "span_kind", "type": "json", "tokenizer": "raw"},
+            {"name": "span_name", "type": "text", "tokenizer": "raw"},
+            {
+                "name": "span_start_timestamp_nanos",
+                "type": "datetime",
+                "input_formats": ["unix_timestamp"],
+                "precision": "milliseconds",
+                "fast": true
+            },
+            {
+                "name": "span_end_timestamp_nanos",
+                "type": "datetime",
+                "input_formats": ["unix_timestamp"],
+                "precision": "milliseconds",
+                "fast": true
+            },
+            {"name": "span_duration_millis", "type": "u64", "fast": true},
+            {"name": "span_attributes", "type": "json", "tokenizer": "raw"},
+            {"name": "resource_attributes", "type": "json", "tokenizer": "raw"},
+            {"name": "span_dropped_attributes_count", "type": "u64", "indexed": false},
+            {"name": "span_dropped_events_count", "type": "u64", "indexed": false},
+            {"name": "span_dropped_links_count", "type": "u64", "indexed": false},
+            {"name": "span_status", "type": "json", "indexed": false},
+            {"name": "parent_span_id", "type": "text", "tokenizer": "raw"}
+        ]
+    }))
+    .expect("The OTLP traces index doc mapping should be valid.");
+    let indexing_settings: IndexingSettings = serde_json::from_value(json!({
+        "timestamp_field": "span_start_timestamp_nanos",
+        "commit_timeout_secs": 10
+    }))
+    .expect("The OTLP traces index indexing settings should be valid.");
+    let search_settings = SearchSettings {
+        default_search_fields: vec!["span_name".to_string()],
+        ..Default::default()
+    };
+    let ingest_api_source_config = SourceConfig::ingest_api_default();
+    let sources = HashMap::from([(
+        ingest_api_source_config.source_id.clone(),
+        ingest_api_source_config,
+    )]);
+    let now_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+    IndexMetadata {
+        index_id: OTEL_TRACES_INDEX_ID.to_string(),
+        index_uri,
+        checkpoint: Default::default(),
+        publish_tokens: Default::default(),
+        sources,
+        stored_queries: Default::default(),
+        alert_states: Default::default(),
+        doc_mapping,
+        indexing_settings,
+        search_settings,
+        retention_policy: None,
+        read_only: false,
+        create_timestamp: now_timestamp,
+        update_timestamp: now_timestamp,
+        tiering_policy: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::build_doc_mapper;
+
+    use super::*;
+
+    #[test]
+    fn test_otel_traces_index_metadata() {
+        let index_uri = Uri::from_well_formed(format!("ram:///indexes/{}", OTEL_TRACES_INDEX_ID));
+        let index_metadata = otel_traces_index_metadata(index_uri);
+        let doc_mapper = build_doc_mapper(
+            &index_metadata.doc_mapping,
+            &index_metadata.search_settings,
+            &index_metadata.indexing_settings,
+        )
+        .unwrap();
+        let span_json = json!({
+            "trace_id": "AQEBAQEBAQEBAQEBAQEBAQ==",
+            "trace_state": "",
+            "service_name": "payments",
+            "span_id": "AgICAgICAgI=",
+            "span_kind": {"id": 2, "name": "server"},
+            "span_name": "charge",
+            "span_start_timestamp_nanos": 1_666_000_000_000_000_000i64,
+            "span_end_timestamp_nanos": 1_666_000_000_250_000_000i64,
+            "span_duration_millis": 250,
+            "span_attributes": {"http.method": "POST", "http.status_code": 200},
+            "resource_attributes": {"service.name": "payments"},
+            "span_dropped_attributes_count": 0,
+            "span_dropped_events_count": 0,
+            "span_dropped_links_count": 0,
+            "span_status": null,
+            "parent_span_id": null
+        });
+        doc_mapper.doc_from_json(span_json.to_string()).unwrap();
+    }
+}
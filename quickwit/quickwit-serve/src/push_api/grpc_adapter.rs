@@ -0,0 +1,178 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use quickwit_indexing::source::{get_push_source_sender, PushPipelineId, PushedDocs};
+use quickwit_proto::push_api::{
+    push_api_service_server as grpc, PushDocsRequest, PushDocsResponse,
+};
+use quickwit_proto::tonic;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Maximum number of requests of a stream handed over to the pipeline and waiting for their
+/// acknowledgement. Beyond that, the stream is not read until the client consumes the responses.
+const MAX_PENDING_ACKS: usize = 16;
+
+type PendingAck = Result<(u64, oneshot::Receiver<u64>), tonic::Status>;
+
+#[derive(Clone, Default)]
+pub struct GrpcPushApiAdapter;
+
+#[async_trait]
+impl grpc::PushApiService for GrpcPushApiAdapter {
+    type PushDocsStream =
+        Pin<Box<dyn Stream<Item = Result<PushDocsResponse, tonic::Status>> + Send>>;
+
+    async fn push_docs(
+        &self,
+        request: tonic::Request<tonic::Streaming<PushDocsRequest>>,
+    ) -> Result<tonic::Response<Self::PushDocsStream>, tonic::Status> {
+        let mut request_stream = request.into_inner();
+        let first_request = match request_stream.message().await? {
+            Some(first_request) => first_request,
+            None => {
+                let response_stream: Self::PushDocsStream = Box::pin(futures::stream::empty());
+                return Ok(tonic::Response::new(response_stream));
+            }
+        };
+        let pipeline_id = PushPipelineId {
+            index_id: first_request.index_id.clone(),
+            source_id: first_request.source_id.clone(),
+            pipeline_ord: first_request.pipeline_ord as usize,
+        };
+        let docs_tx = get_push_source_sender(&pipeline_id).ok_or_else(|| {
+            tonic::Status::not_found(format!(
+                "Pipeline #{} of push source `{}` of index `{}` is not running on this node.",
+                pipeline_id.pipeline_ord, pipeline_id.source_id, pipeline_id.index_id
+            ))
+        })?;
+        let (pending_acks_tx, pending_acks_rx) = mpsc::channel(MAX_PENDING_ACKS);
+        tokio::spawn(forward_requests(
+            first_request,
+            request_stream,
+            pipeline_id,
+            docs_tx,
+            pending_acks_tx,
+        ));
+        let response_stream: Self::PushDocsStream = Box::pin(
+            ReceiverStream::new(pending_acks_rx).then(|pending_ack: PendingAck| async move {
+                let (num_docs, ack_rx) = pending_ack?;
+                let position = ack_rx.await.map_err(|_| {
+                    tonic::Status::unavailable(
+                        "The pipeline stopped before acknowledging the documents.",
+                    )
+                })?;
+                Ok(PushDocsResponse { num_docs, position })
+            }),
+        );
+        Ok(tonic::Response::new(response_stream))
+    }
+}
+
+/// Hands the documents of the requests over to the pipeline until the stream ends or fails.
+/// Sending to the pipeline waits while its queue is full, and so does reading the next request.
+async fn forward_requests(
+    first_request: PushDocsRequest,
+    mut request_stream: tonic::Streaming<PushDocsRequest>,
+    pipeline_id: PushPipelineId,
+    docs_tx: mpsc::Sender<PushedDocs>,
+    pending_acks_tx: mpsc::Sender<PendingAck>,
+) {
+    let mut request_opt = Some(first_request);
+
+    while let Some(request) = request_opt {
+        let pending_ack = forward_request(request, &pipeline_id, &docs_tx).await;
+        let is_err = pending_ack.is_err();
+        // The client may have closed the stream.
+        if pending_acks_tx.send(pending_ack).await.is_err() || is_err {
+            return;
+        }
+        request_opt = match request_stream.message().await {
+            Ok(request_opt) => request_opt,
+            Err(status) => {
+                let _ = pending_acks_tx.send(Err(status)).await;
+                return;
+            }
+        };
+    }
+}
+
+async fn forward_request(
+    request: PushDocsRequest,
+    pipeline_id: &PushPipelineId,
+    docs_tx: &mpsc::Sender<PushedDocs>,
+) -> PendingAck {
+    if request.index_id != pipeline_id.index_id
+        || request.source_id != pipeline_id.source_id
+        || request.pipeline_ord as usize != pipeline_id.pipeline_ord
+    {
+        return Err(tonic::Status::invalid_argument(
+            "All the requests of a stream must target the same pipeline.",
+        ));
+    }
+    let docs = split_docs(request.concat_docs, &request.doc_lens)?;
+    let num_docs = docs.len() as u64;
+    let (ack_tx, ack_rx) = oneshot::channel();
+    docs_tx
+        .send(PushedDocs { docs, ack_tx })
+        .await
+        .map_err(|_| tonic::Status::unavailable("The pipeline has stopped."))?;
+    Ok((num_docs, ack_rx))
+}
+
+fn split_docs(concat_docs: Bytes, doc_lens: &[u64]) -> Result<Vec<Bytes>, tonic::Status> {
+    let total_num_bytes: u64 = doc_lens.iter().sum();
+    if total_num_bytes != concat_docs.len() as u64 {
+        return Err(tonic::Status::invalid_argument(format!(
+            "The document lengths add up to {} bytes, but the payload has {} bytes.",
+            total_num_bytes,
+            concat_docs.len()
+        )));
+    }
+    let mut docs = Vec::with_capacity(doc_lens.len());
+    let mut start = 0;
+
+    for doc_len in doc_lens {
+        let end = start + *doc_len as usize;
+        docs.push(concat_docs.slice(start..end));
+        start = end;
+    }
+    Ok(docs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_docs() {
+        let concat_docs = Bytes::from_static(b"{\"a\":1}{}{\"b\":2}");
+        let docs = split_docs(concat_docs.clone(), &[7, 2, 7]).unwrap();
+        assert_eq!(docs, vec![&b"{\"a\":1}"[..], &b"{}"[..], &b"{\"b\":2}"[..]]);
+        assert!(split_docs(Bytes::new(), &[]).unwrap().is_empty());
+
+        let status = split_docs(concat_docs, &[7, 2]).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -26,14 +26,21 @@ use tracing::{error, info};
 use warp::{redirect, Filter, Rejection, Reply};
 
 use crate::cluster_api::cluster_handler;
+use crate::debug_api::debug_handlers;
 use crate::delete_task_api::delete_task_api_handlers;
 use crate::format::FormatError;
 use crate::health_check_api::health_check_handlers;
 use crate::index_api::index_management_handlers;
-use crate::indexing_api::indexing_get_handler;
-use crate::ingest_api::{elastic_bulk_handler, ingest_handler, tail_handler};
+use crate::indexing_api::{
+    get_source_checkpoint_handler, indexing_get_handler, indexing_pipelines_handler,
+    merge_splits_handler, reload_source_handler, reset_source_checkpoint_handler,
+    restart_pipeline_handler,
+};
+use crate::ingest_api::{elastic_bulk_handler, ingest_handler, queue_handlers, tail_handler};
 use crate::node_info_handler::node_info_handler;
-use crate::search_api::{search_get_handler, search_post_handler, search_stream_handler};
+use crate::search_api::{
+    search_get_handler, search_post_handler, search_stream_handler, search_template_handler,
+};
 use crate::ui_handler::ui_handler;
 use crate::{Format, QuickwitServices};
 
@@ -58,6 +65,24 @@ pub(crate) async fn start_rest_server(
         .or(indexing_get_handler(
             quickwit_services.indexer_service.clone(),
         ))
+        .or(merge_splits_handler(
+            quickwit_services.indexer_service.clone(),
+        ))
+        .or(reload_source_handler(
+            quickwit_services.indexer_service.clone(),
+        ))
+        .or(get_source_checkpoint_handler(
+            quickwit_services.indexer_service.clone(),
+        ))
+        .or(reset_source_checkpoint_handler(
+            quickwit_services.indexer_service.clone(),
+        ))
+        .or(indexing_pipelines_handler(
+            quickwit_services.indexer_service.clone(),
+        ))
+        .or(restart_pipeline_handler(
+            quickwit_services.indexer_service.clone(),
+        ))
         .or(search_get_handler(quickwit_services.search_service.clone()))
         .or(search_post_handler(
             quickwit_services.search_service.clone(),
@@ -65,14 +90,27 @@ pub(crate) async fn start_rest_server(
         .or(search_stream_handler(
             quickwit_services.search_service.clone(),
         ))
-        .or(ingest_handler(quickwit_services.ingest_api_service.clone()))
+        .or(search_template_handler(
+            quickwit_services.metastore.clone(),
+            quickwit_services.search_service.clone(),
+        ))
+        .or(ingest_handler(
+            quickwit_services.ingest_api_service.clone(),
+            quickwit_services.index_auto_creator.clone(),
+        ))
         .or(tail_handler(quickwit_services.ingest_api_service.clone()))
+        .or(queue_handlers(
+            quickwit_services.ingest_api_service.clone(),
+            quickwit_services.config.admin_token_opt.clone(),
+        ))
         .or(elastic_bulk_handler(
             quickwit_services.ingest_api_service.clone(),
+            quickwit_services.index_auto_creator.clone(),
         ))
         .or(index_management_handlers(
             quickwit_services.index_service.clone(),
             quickwit_services.indexer_service.clone(),
+            quickwit_services.cluster.clone(),
         ))
         .or(delete_task_api_handlers(
             quickwit_services.metastore.clone(),
@@ -89,6 +127,9 @@ pub(crate) async fn start_rest_server(
         .or(redirect_root_to_ui_route)
         .or(ui_handler())
         .or(metrics_service)
+        .or(debug_handlers(
+            quickwit_services.config.admin_token_opt.clone(),
+        ))
         .with(request_counter)
         .recover(recover_fn);
 
@@ -165,11 +206,6 @@ fn get_status_with_error(rejection: Rejection) -> FormatError {
             code: ServiceErrorCode::BadRequest,
             error: error.to_string(),
         }
-    } else if let Some(error) = rejection.find::<crate::ingest_api::BulkApiError>() {
-        FormatError {
-            code: ServiceErrorCode::BadRequest,
-            error: error.to_string(),
-        }
     } else {
         error!("REST server error: {:?}", rejection);
         FormatError {
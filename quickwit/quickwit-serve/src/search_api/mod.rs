@@ -21,7 +21,9 @@ mod grpc_adapter;
 mod rest_handler;
 
 pub use self::grpc_adapter::GrpcSearchAdapter;
-pub use self::rest_handler::{search_get_handler, search_post_handler, search_stream_handler};
+pub use self::rest_handler::{
+    search_get_handler, search_post_handler, search_stream_handler, search_template_handler,
+};
 
 #[cfg(test)]
 mod tests {
@@ -36,8 +38,9 @@ mod tests {
     use quickwit_proto::{tonic, OutputFormat};
     use quickwit_search::{
         root_search_stream, ClusterClient, MockSearchService, SearchClientPool, SearchError,
-        SearchService,
+        SearchQuotas, SearchService,
     };
+    use quickwit_storage::StorageUriResolver;
     use tokio_stream::wrappers::UnboundedReceiverStream;
     use tonic::transport::Server;
 
@@ -71,6 +74,7 @@ mod tests {
             fast_field: "timestamp".to_string(),
             output_format: OutputFormat::Csv as i32,
             partition_by_field: None,
+            role: None,
         };
         let mut metastore = MockMetastore::new();
         metastore
@@ -120,7 +124,15 @@ mod tests {
         start_test_server(grpc_addr, Arc::new(mock_search_service)).await?;
         let client_pool = SearchClientPool::for_addrs(&[grpc_addr]).await?;
         let cluster_client = ClusterClient::new(client_pool.clone());
-        let stream = root_search_stream(request, &metastore, cluster_client, &client_pool).await?;
+        let stream = root_search_stream(
+            request,
+            &metastore,
+            &StorageUriResolver::for_test(),
+            cluster_client,
+            &client_pool,
+            &SearchQuotas::default(),
+        )
+        .await?;
         let result: Result<Vec<_>, SearchError> = stream.try_collect().await;
         assert!(result.is_err());
         assert_eq!(
@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::convert::{Infallible, TryFrom};
 use std::sync::Arc;
 
@@ -24,6 +25,7 @@ use futures::stream::StreamExt;
 use hyper::header::HeaderValue;
 use hyper::HeaderMap;
 use quickwit_doc_mapper::{SortByField, SortOrder};
+use quickwit_metastore::Metastore;
 use quickwit_proto::{OutputFormat, ServiceError, SortOrder as ProtoSortOrder};
 use quickwit_search::{SearchError, SearchResponseRest, SearchService};
 use serde::{de, Deserialize, Deserializer};
@@ -93,6 +95,10 @@ pub struct SearchRequestQueryString {
     #[serde(default)]
     #[serde(deserialize_with = "from_simple_list")]
     pub snippet_fields: Option<Vec<String>>,
+    /// Stored fields returned in the hits. All the stored fields are returned if not set.
+    #[serde(default)]
+    #[serde(deserialize_with = "from_simple_list")]
+    pub fetch_fields: Option<Vec<String>>,
     /// If set, restrict search to documents with a `timestamp >= start_timestamp`.
     pub start_timestamp: Option<i64>,
     /// If set, restrict search to documents with a `timestamp < end_timestamp``.
@@ -114,10 +120,17 @@ pub struct SearchRequestQueryString {
     #[serde(deserialize_with = "sort_by_field_mini_dsl")]
     #[serde(default)]
     sort_by_field: Option<SortByField>,
+    /// If set, the response contains the timings of the search of each split.
+    #[serde(default)]
+    pub debug: bool,
+    /// Reshapes the hits into flat objects, mapping output field names to dot-separated paths
+    /// into the hits, e.g. `projection[service]=resource.service.name`.
+    #[serde(default)]
+    pub projection: HashMap<String, String>,
 }
 
-fn get_proto_search_by(search_request: &SearchRequestQueryString) -> (Option<i32>, Option<String>) {
-    if let Some(sort_by_field) = &search_request.sort_by_field {
+fn get_proto_search_by(sort_by_field_opt: Option<&SortByField>) -> (Option<i32>, Option<String>) {
+    if let Some(sort_by_field) = sort_by_field_opt {
         let sort_order = match sort_by_field.order {
             SortOrder::Asc => ProtoSortOrder::Asc as i32,
             SortOrder::Desc => ProtoSortOrder::Desc as i32,
@@ -131,14 +144,17 @@ fn get_proto_search_by(search_request: &SearchRequestQueryString) -> (Option<i32
 async fn search_endpoint(
     index_id: String,
     search_request: SearchRequestQueryString,
+    role_opt: Option<String>,
     search_service: &dyn SearchService,
 ) -> Result<SearchResponseRest, SearchError> {
-    let (sort_order, sort_by_field) = get_proto_search_by(&search_request);
+    let (sort_order, sort_by_field) = get_proto_search_by(search_request.sort_by_field.as_ref());
+    let projection = search_request.projection;
     let search_request = quickwit_proto::SearchRequest {
         index_id,
         query: search_request.query,
         search_fields: search_request.search_fields.unwrap_or_default(),
         snippet_fields: search_request.snippet_fields.unwrap_or_default(),
+        fetch_fields: search_request.fetch_fields.unwrap_or_default(),
         start_timestamp: search_request.start_timestamp,
         end_timestamp: search_request.end_timestamp,
         max_hits: search_request.max_hits,
@@ -148,12 +164,27 @@ async fn search_endpoint(
             .map(|agg| serde_json::to_string(&agg).expect("could not serialize serde_json::Value")),
         sort_order,
         sort_by_field,
+        debug: search_request.debug,
+        role: role_opt,
+        query_ast: None,
     };
     let search_response = search_service.root_search(search_request).await?;
-    let search_response_rest = SearchResponseRest::try_from(search_response)?;
+    let mut search_response_rest = SearchResponseRest::try_from(search_response)?;
+    if !projection.is_empty() {
+        search_response_rest.project_hits(&projection)?;
+    }
     Ok(search_response_rest)
 }
 
+/// Header carrying the role of the user issuing a search. It is meant to be set by an
+/// authenticating proxy, which restricts the fields the user can access according to the field
+/// access policies of the index. The role is trusted as is: it is not authenticated.
+const ROLE_HEADER_NAME: &str = "x-quickwit-role";
+
+fn role_filter() -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Clone {
+    warp::header::optional::<String>(ROLE_HEADER_NAME)
+}
+
 fn search_get_filter(
 ) -> impl Filter<Extract = (String, SearchRequestQueryString), Error = Rejection> + Clone {
     warp::path!(String / "search")
@@ -172,12 +203,14 @@ fn search_post_filter(
 async fn search(
     index_id: String,
     search_request: SearchRequestQueryString,
+    role_opt: Option<String>,
     search_service: Arc<dyn SearchService>,
 ) -> Result<impl warp::Reply, Infallible> {
-    info!(index_id = %index_id, request =? search_request, "search");
-    Ok(search_request
-        .format
-        .make_rest_reply(search_endpoint(index_id, search_request, &*search_service).await))
+    info!(index_id = %index_id, request =? search_request, role = ?role_opt, "search");
+    let format = search_request.format;
+    Ok(format.make_rest_reply(
+        search_endpoint(index_id, search_request, role_opt, &*search_service).await,
+    ))
 }
 
 /// REST GET search handler.
@@ -187,6 +220,7 @@ pub fn search_get_handler(
     search_service: Arc<dyn SearchService>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     search_get_filter()
+        .and(role_filter())
         .and(with_arg(search_service))
         .and_then(search)
 }
@@ -198,14 +232,146 @@ pub fn search_post_handler(
     search_service: Arc<dyn SearchService>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     search_post_filter()
+        .and(role_filter())
         .and(with_arg(search_service))
         .and_then(search)
 }
 
+/// This struct represents the QueryString passed to the search template REST API.
+#[derive(Deserialize, Debug, Eq, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SearchTemplateRequestQueryString {
+    /// Values of the parameters of the stored query, e.g. `params[service]=payments`.
+    #[serde(default)]
+    pub params: HashMap<String, String>,
+    /// If set, restrict search to documents with a `timestamp >= start_timestamp`.
+    pub start_timestamp: Option<i64>,
+    /// If set, restrict search to documents with a `timestamp < end_timestamp``.
+    pub end_timestamp: Option<i64>,
+    /// Maximum number of hits to return. Overrides the `max_hits` of the stored query, which
+    /// defaults to 20.
+    pub max_hits: Option<u64>,
+    /// First hit to return.
+    #[serde(default)]
+    pub start_offset: u64,
+    /// The output format.
+    #[serde(default)]
+    pub format: Format,
+    /// Reshapes the hits into flat objects, mapping output field names to dot-separated paths
+    /// into the hits.
+    #[serde(default)]
+    pub projection: HashMap<String, String>,
+}
+
+async fn search_template_endpoint(
+    index_id: String,
+    stored_query_name: String,
+    search_request: SearchTemplateRequestQueryString,
+    role_opt: Option<String>,
+    metastore: &dyn Metastore,
+    search_service: &dyn SearchService,
+) -> Result<SearchResponseRest, SearchError> {
+    let index_metadata = metastore.index_metadata(&index_id).await?;
+    let stored_query = index_metadata
+        .stored_queries
+        .get(&stored_query_name)
+        .ok_or_else(|| SearchError::StoredQueryDoesNotExist {
+            index_id: index_id.clone(),
+            name: stored_query_name.clone(),
+        })?;
+    let query = stored_query
+        .render(&search_request.params)
+        .map_err(|error| SearchError::InvalidArgument(error.to_string()))?;
+    let sort_by_field_opt = stored_query.sort_by_field.clone().map(SortByField::from);
+    let (sort_order, sort_by_field) = get_proto_search_by(sort_by_field_opt.as_ref());
+    let projection = search_request.projection;
+    let search_request = quickwit_proto::SearchRequest {
+        index_id,
+        query,
+        search_fields: stored_query.search_fields.clone(),
+        snippet_fields: Vec::new(),
+        fetch_fields: Vec::new(),
+        start_timestamp: search_request.start_timestamp,
+        end_timestamp: search_request.end_timestamp,
+        max_hits: search_request
+            .max_hits
+            .or(stored_query.max_hits)
+            .unwrap_or_else(default_max_hits),
+        start_offset: search_request.start_offset,
+        aggregation_request: stored_query.aggregation_request.clone(),
+        sort_order,
+        sort_by_field,
+        debug: false,
+        role: role_opt,
+        query_ast: None,
+    };
+    let search_response = search_service.root_search(search_request).await?;
+    let mut search_response_rest = SearchResponseRest::try_from(search_response)?;
+    if !projection.is_empty() {
+        search_response_rest.project_hits(&projection)?;
+    }
+    Ok(search_response_rest)
+}
+
+fn search_template_get_filter(
+) -> impl Filter<Extract = (String, String, SearchTemplateRequestQueryString), Error = Rejection> + Clone
+{
+    warp::path!(String / "search" / "template" / String)
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+}
+
+fn search_template_post_filter(
+) -> impl Filter<Extract = (String, String, SearchTemplateRequestQueryString), Error = Rejection> + Clone
+{
+    warp::path!(String / "search" / "template" / String)
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::body::json())
+}
+
+async fn search_template(
+    index_id: String,
+    stored_query_name: String,
+    search_request: SearchTemplateRequestQueryString,
+    role_opt: Option<String>,
+    metastore: Arc<dyn Metastore>,
+    search_service: Arc<dyn SearchService>,
+) -> Result<impl warp::Reply, Infallible> {
+    info!(index_id = %index_id, stored_query = %stored_query_name, request =? search_request, "search-template");
+    let format = search_request.format;
+    Ok(format.make_rest_reply(
+        search_template_endpoint(
+            index_id,
+            stored_query_name,
+            search_request,
+            role_opt,
+            &*metastore,
+            &*search_service,
+        )
+        .await,
+    ))
+}
+
+/// REST GET and POST handlers executing the stored queries of an index.
+pub fn search_template_handler(
+    metastore: Arc<dyn Metastore>,
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    search_template_get_filter()
+        .or(search_template_post_filter())
+        .unify()
+        .and(role_filter())
+        .and(with_arg(metastore))
+        .and(with_arg(search_service))
+        .and_then(search_template)
+}
+
 pub fn search_stream_handler(
     search_service: Arc<dyn SearchService>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
     search_stream_filter()
+        .and(role_filter())
         .and(with_arg(search_service))
         .and_then(search_stream)
 }
@@ -244,6 +410,7 @@ struct SearchStreamRequestQueryString {
 async fn search_stream_endpoint(
     index_id: String,
     search_request: SearchStreamRequestQueryString,
+    role_opt: Option<String>,
     search_service: &dyn SearchService,
 ) -> Result<hyper::Body, SearchError> {
     let request = quickwit_proto::SearchStreamRequest {
@@ -256,6 +423,7 @@ async fn search_stream_endpoint(
         fast_field: search_request.fast_field,
         output_format: search_request.output_format as i32,
         partition_by_field: search_request.partition_by_field,
+        role: role_opt,
     };
     let mut data = search_service.root_search_stream(request).await?;
     let (mut sender, body) = hyper::Body::channel();
@@ -310,15 +478,17 @@ fn make_streaming_reply(result: Result<hyper::Body, SearchError>) -> impl Reply
 async fn search_stream(
     index_id: String,
     request: SearchStreamRequestQueryString,
+    role_opt: Option<String>,
     search_service: Arc<dyn SearchService>,
 ) -> Result<impl warp::Reply, Infallible> {
-    info!(index_id=%index_id,request=?request, "search_stream");
+    info!(index_id=%index_id,request=?request, role=?role_opt, "search_stream");
     let content_type = match request.output_format {
         OutputFormat::ClickHouseRowBinary => "application/octet-stream",
         OutputFormat::Csv => "text/csv",
     };
-    let reply =
-        make_streaming_reply(search_stream_endpoint(index_id, request, &*search_service).await);
+    let reply = make_streaming_reply(
+        search_stream_endpoint(index_id, request, role_opt, &*search_service).await,
+    );
     let reply_with_header = reply::with_header(reply, CONTENT_TYPE, content_type);
     Ok(reply_with_header)
 }
@@ -335,6 +505,8 @@ mod tests {
     use assert_json_diff::{assert_json_eq, assert_json_include};
     use bytes::Bytes;
     use mockall::predicate;
+    use quickwit_config::StoredQuery;
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
     use quickwit_search::{MockSearchService, SearchError};
     use serde_json::json;
 
@@ -360,6 +532,7 @@ mod tests {
             elapsed_time_micros: 0u64,
             errors: Vec::new(),
             aggregations: None,
+            split_timings: Vec::new(),
         };
         let search_response_json: serde_json::Value = serde_json::to_value(&search_response)?;
         let expected_search_response_json: serde_json::Value = json!({
@@ -482,6 +655,25 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_rest_search_api_route_fetch_fields() {
+        let rest_search_api_filter = search_get_filter();
+        let (_, req) = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&fetch_fields=title,url")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(
+            &req,
+            &super::SearchRequestQueryString {
+                query: "*".to_string(),
+                max_hits: 20,
+                fetch_fields: Some(vec!["title".to_string(), "url".to_string()]),
+                ..Default::default()
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_rest_search_api_route_sort_by() {
         let rest_search_api_filter = search_get_filter();
@@ -566,7 +758,7 @@ mod tests {
         assert_eq!(resp.status(), 400);
         let resp_json: serde_json::Value = serde_json::from_slice(resp.body())?;
         let exp_resp_json = serde_json::json!({
-            "error": "unknown field `end_unix_timestamp`, expected one of `query`, `aggs`, `search_field`, `snippet_fields`, `start_timestamp`, `end_timestamp`, `max_hits`, `start_offset`, `format`, `sort_by_field`"
+            "error": "unknown field `end_unix_timestamp`, expected one of `query`, `aggs`, `search_field`, `snippet_fields`, `fetch_fields`, `start_timestamp`, `end_timestamp`, `max_hits`, `start_offset`, `format`, `sort_by_field`"
         });
         assert_eq!(resp_json, exp_resp_json);
         Ok(())
@@ -615,6 +807,44 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_search_api_projection() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_root_search().returning(|_| {
+            Ok(quickwit_proto::SearchResponse {
+                hits: vec![quickwit_proto::Hit {
+                    json: r#"{"resource": {"service": {"name": "payments"}}, "body": "foo"}"#
+                        .to_string(),
+                    partial_hit: None,
+                    snippet: None,
+                }],
+                num_hits: 1,
+                ..Default::default()
+            })
+        });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search?query=*&projection[service]=resource.service.name&\
+                 projection[message]=body",
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: serde_json::Value = serde_json::from_slice(resp.body())?;
+        assert_eq!(
+            resp_json["hits"],
+            json!([{"service": "payments", "message": "foo"}])
+        );
+
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&projection[service]=")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rest_search_api_start_offset_and_num_hits_parameter() -> anyhow::Result<()> {
         let mut mock_search_service = MockSearchService::new();
@@ -638,6 +868,106 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_search_api_role_header() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    search_request.role.as_deref() == Some("analyst")
+                },
+            ))
+            .returning(|_| {
+                Err(SearchError::Forbidden(
+                    "Field `user.email` is denied to role `analyst`.".to_string(),
+                ))
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=user.email:foo")
+            .header("x-quickwit-role", "analyst")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 403);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_template() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_index_metadata().returning(|index_id| {
+            let mut index_metadata =
+                IndexMetadata::for_test(index_id, "ram:///indexes/quickwit-demo-index");
+            let stored_query: StoredQuery = serde_json::from_value(json!({
+                "name": "errors-by-owner",
+                "query": "owner:{{owner}} AND body:{{level}}",
+                "params": [{"name": "owner"}, {"name": "level", "default": "error"}],
+                "max_hits": 5,
+            }))
+            .unwrap();
+            index_metadata
+                .stored_queries
+                .insert(stored_query.name.clone(), stored_query);
+            Ok(index_metadata)
+        });
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    search_request.query == "owner:acme AND body:error"
+                        && search_request.max_hits == 5
+                        && search_request.start_timestamp == Some(10)
+                },
+            ))
+            .times(2)
+            .returning(|_| Ok(Default::default()));
+        let search_template_handler =
+            search_template_handler(Arc::new(metastore), Arc::new(mock_search_service))
+                .recover(recover_fn);
+        let resp = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search/template/errors-by-owner?params[owner]=acme&\
+                 start_timestamp=10",
+            )
+            .reply(&search_template_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search/template/errors-by-owner")
+            .json(&true)
+            .body(r#"{"params": {"owner": "acme"}, "start_timestamp": 10}"#)
+            .reply(&search_template_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        // Missing required parameter.
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search/template/errors-by-owner")
+            .reply(&search_template_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        // Parameter values cannot alter the structure of the query.
+        let resp = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search/template/errors-by-owner?params[owner]=acme%20OR%20*",
+            )
+            .reply(&search_template_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search/template/does-not-exist")
+            .reply(&search_template_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rest_search_api_with_index_does_not_exist() -> anyhow::Result<()> {
         let mut mock_search_service = MockSearchService::new();
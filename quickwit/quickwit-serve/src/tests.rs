@@ -64,6 +64,7 @@ async fn test_standalone_server_no_indexer() -> anyhow::Result<()> {
             query: "*".to_string(),
             search_fields: Vec::new(),
             snippet_fields: Vec::new(),
+            fetch_fields: Vec::new(),
             start_timestamp: None,
             end_timestamp: None,
             aggregation_request: None,
@@ -71,6 +72,9 @@ async fn test_standalone_server_no_indexer() -> anyhow::Result<()> {
             sort_by_field: None,
             sort_order: None,
             start_offset: 0,
+            debug: false,
+            role: None,
+            query_ast: None,
         })
         .await;
     assert!(search_result.is_ok());
@@ -109,6 +113,10 @@ async fn test_multi_nodes_cluster() -> anyhow::Result<()> {
             sort_order: None,
             start_offset: 0,
             snippet_fields: Vec::new(),
+            fetch_fields: Vec::new(),
+            debug: false,
+            role: None,
+            query_ast: None,
         })
         .await;
     assert!(search_result.is_ok());
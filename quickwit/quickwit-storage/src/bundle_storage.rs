@@ -255,6 +255,16 @@ impl Storage for BundleStorage {
         Ok(file_range.end - file_range.start as u64)
     }
 
+    async fn list_files(&self) -> StorageResult<Vec<(PathBuf, u64)>> {
+        let files = self
+            .metadata
+            .files
+            .iter()
+            .map(|(path, file_range)| (path.clone(), file_range.end - file_range.start))
+            .collect();
+        Ok(files)
+    }
+
     fn uri(&self) -> &Uri {
         self.storage.uri()
     }
@@ -18,7 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::ops::Range;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -90,6 +90,10 @@ impl Storage for StorageWithCache {
         self.storage.file_num_bytes(path).await
     }
 
+    async fn list_files(&self) -> StorageResult<Vec<(PathBuf, u64)>> {
+        self.storage.list_files().await
+    }
+
     fn uri(&self) -> &Uri {
         self.storage.uri()
     }
@@ -182,6 +182,10 @@ impl<T: Storage> Storage for DebouncedStorage<T> {
     async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
         self.underlying.file_num_bytes(path).await
     }
+
+    async fn list_files(&self) -> StorageResult<Vec<(PathBuf, u64)>> {
+        self.underlying.list_files().await
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,393 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_common::uri::Uri;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::io::AsyncWriteExt;
+
+use crate::storage::{BulkDeleteError, SendableAsync};
+use crate::{OwnedBytes, PutPayload, Storage, StorageError, StorageErrorKind, StorageResult};
+
+/// Faults injected by a [`FaultInjectingStorage`] into the requests it forwards.
+///
+/// Rates are probabilities in `[0, 1]` drawn independently for each request. A request suffers
+/// at most one of throttling, error, or partial read, so the sum of the rates must not exceed 1.
+#[derive(Clone, Debug, Default)]
+pub struct StorageFaults {
+    /// Latency added to every request.
+    pub latency: Duration,
+    /// Upper bound of an additional latency drawn uniformly for every request.
+    pub latency_jitter: Duration,
+    /// Rate of requests failing with a [`StorageErrorKind::Io`] error.
+    pub error_rate: f64,
+    /// Rate of read requests (`copy_to`, `get_slice`, `get_all`) failing after transferring only
+    /// part of the file. `copy_to` writes the first half of the file to its output before
+    /// failing.
+    pub partial_read_rate: f64,
+    /// Rate of requests rejected with a [`StorageErrorKind::Service`] error, as object storages
+    /// do when throttling clients.
+    pub throttling_rate: f64,
+}
+
+impl StorageFaults {
+    fn validate(&self) {
+        for (name, rate) in [
+            ("error_rate", self.error_rate),
+            ("partial_read_rate", self.partial_read_rate),
+            ("throttling_rate", self.throttling_rate),
+        ] {
+            assert!(
+                (0.0..=1.0).contains(&rate),
+                "`{name}` must be in [0, 1], got `{rate}`."
+            );
+        }
+        assert!(
+            self.error_rate + self.partial_read_rate + self.throttling_rate <= 1.0,
+            "The sum of the fault rates must not exceed 1."
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Fault {
+    Error,
+    PartialRead,
+    Throttling,
+}
+
+/// Storage wrapper injecting latency, errors, partial reads, and throttling into the requests
+/// forwarded to the underlying storage.
+///
+/// Faults are drawn from a seeded random number generator, so a given sequence of requests
+/// always suffers the same faults. This storage is meant to exercise retry logic in tests and
+/// chaos runs.
+pub struct FaultInjectingStorage {
+    storage: Arc<dyn Storage>,
+    faults: Mutex<StorageFaults>,
+    rng: Mutex<StdRng>,
+    num_injected_faults: AtomicU64,
+}
+
+impl fmt::Debug for FaultInjectingStorage {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("FaultInjectingStorage")
+            .field("uri", self.storage.uri())
+            .field("faults", &*self.faults.lock().unwrap())
+            .finish()
+    }
+}
+
+impl FaultInjectingStorage {
+    /// Creates a new [`FaultInjectingStorage`] wrapping `storage`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fault rates are invalid.
+    pub fn new(storage: Arc<dyn Storage>, faults: StorageFaults, seed: u64) -> Self {
+        faults.validate();
+        Self {
+            storage,
+            faults: Mutex::new(faults),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+            num_injected_faults: AtomicU64::new(0),
+        }
+    }
+
+    /// Replaces the injected faults, for instance to let the storage recover in a chaos run.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fault rates are invalid.
+    pub fn set_faults(&self, faults: StorageFaults) {
+        faults.validate();
+        *self.faults.lock().unwrap() = faults;
+    }
+
+    /// Returns the number of requests that failed because of an injected fault.
+    pub fn num_injected_faults(&self) -> u64 {
+        self.num_injected_faults.load(Ordering::Relaxed)
+    }
+
+    /// Draws the latency and the fault for a request, then waits for the latency to elapse.
+    /// Always draws the same number of random values so that faults remain reproducible.
+    async fn draw_fault(&self, is_read: bool) -> Option<Fault> {
+        let (latency, fault_opt) = {
+            let faults = self.faults.lock().unwrap();
+            let mut rng = self.rng.lock().unwrap();
+            let latency = faults.latency + faults.latency_jitter.mul_f64(rng.gen::<f64>());
+            let sample = rng.gen::<f64>();
+            let fault_opt = if sample < faults.throttling_rate {
+                Some(Fault::Throttling)
+            } else if sample < faults.throttling_rate + faults.error_rate {
+                Some(Fault::Error)
+            } else if is_read
+                && sample < faults.throttling_rate + faults.error_rate + faults.partial_read_rate
+            {
+                Some(Fault::PartialRead)
+            } else {
+                None
+            };
+            (latency, fault_opt)
+        };
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        if fault_opt.is_some() {
+            self.num_injected_faults.fetch_add(1, Ordering::Relaxed);
+        }
+        fault_opt
+    }
+
+    /// Injects the faults of a request that does not read file content.
+    async fn inject_faults(&self, operation: &str, path: &Path) -> StorageResult<()> {
+        match self.draw_fault(false).await {
+            Some(fault) => Err(fault_error(fault, operation, path)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn fault_error(fault: Fault, operation: &str, path: &Path) -> StorageError {
+    match fault {
+        Fault::Error => StorageErrorKind::Io.with_error(anyhow::anyhow!(
+            "Injected error for `{operation}` request on `{}`.",
+            path.display()
+        )),
+        Fault::PartialRead => StorageErrorKind::Io.with_error(anyhow::anyhow!(
+            "Injected partial read for `{operation}` request on `{}`: unexpected end of stream.",
+            path.display()
+        )),
+        Fault::Throttling => StorageErrorKind::Service.with_error(anyhow::anyhow!(
+            "Injected throttling for `{operation}` request on `{}`: slow down.",
+            path.display()
+        )),
+    }
+}
+
+#[async_trait]
+impl Storage for FaultInjectingStorage {
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        self.storage.check_connectivity().await
+    }
+
+    async fn put(&self, path: &Path, payload: Box<dyn PutPayload>) -> StorageResult<()> {
+        self.inject_faults("put", path).await?;
+        self.storage.put(path, payload).await
+    }
+
+    async fn copy_to(&self, path: &Path, output: &mut dyn SendableAsync) -> StorageResult<()> {
+        match self.draw_fault(true).await {
+            Some(Fault::PartialRead) => {
+                let payload_bytes = self.storage.get_all(path).await?;
+                output
+                    .write_all(&payload_bytes[..payload_bytes.len() / 2])
+                    .await?;
+                output.flush().await?;
+                Err(fault_error(Fault::PartialRead, "copy_to", path))
+            }
+            Some(fault) => Err(fault_error(fault, "copy_to", path)),
+            None => self.storage.copy_to(path, output).await,
+        }
+    }
+
+    async fn get_slice(&self, path: &Path, range: Range<usize>) -> StorageResult<OwnedBytes> {
+        match self.draw_fault(true).await {
+            Some(fault) => Err(fault_error(fault, "get_slice", path)),
+            None => self.storage.get_slice(path, range).await,
+        }
+    }
+
+    async fn get_all(&self, path: &Path) -> StorageResult<OwnedBytes> {
+        match self.draw_fault(true).await {
+            Some(fault) => Err(fault_error(fault, "get_all", path)),
+            None => self.storage.get_all(path).await,
+        }
+    }
+
+    async fn delete(&self, path: &Path) -> StorageResult<()> {
+        self.inject_faults("delete", path).await?;
+        self.storage.delete(path).await
+    }
+
+    async fn bulk_delete<'a>(&self, paths: &[&'a Path]) -> Result<(), BulkDeleteError> {
+        let first_path = paths.first().copied().unwrap_or_else(|| Path::new(""));
+        if let Err(error) = self.inject_faults("bulk_delete", first_path).await {
+            return Err(BulkDeleteError {
+                error: Some(error),
+                unattempted: paths.iter().map(|path| path.to_path_buf()).collect(),
+                ..Default::default()
+            });
+        }
+        self.storage.bulk_delete(paths).await
+    }
+
+    async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
+        self.inject_faults("file_num_bytes", path).await?;
+        self.storage.file_num_bytes(path).await
+    }
+
+    async fn list_files(&self) -> StorageResult<Vec<(PathBuf, u64)>> {
+        self.inject_faults("list_files", Path::new("")).await?;
+        self.storage.list_files().await
+    }
+
+    fn uri(&self) -> &Uri {
+        self.storage.uri()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RamStorageBuilder;
+
+    fn ram_storage() -> Arc<dyn Storage> {
+        Arc::new(RamStorageBuilder::default().put("foo", b"abcdefgh").build())
+    }
+
+    #[tokio::test]
+    async fn test_fault_injecting_storage_no_faults() {
+        let storage = FaultInjectingStorage::new(ram_storage(), StorageFaults::default(), 0);
+        let payload = storage.get_all(Path::new("foo")).await.unwrap();
+        assert_eq!(payload.as_slice(), b"abcdefgh");
+        let payload = storage.get_slice(Path::new("foo"), 2..4).await.unwrap();
+        assert_eq!(payload.as_slice(), b"cd");
+        storage
+            .put(Path::new("bar"), Box::new(b"ijkl".to_vec()))
+            .await
+            .unwrap();
+        assert_eq!(storage.file_num_bytes(Path::new("bar")).await.unwrap(), 4);
+        assert_eq!(storage.num_injected_faults(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_fault_injecting_storage_errors() {
+        let faults = StorageFaults {
+            error_rate: 1.0,
+            ..Default::default()
+        };
+        let storage = FaultInjectingStorage::new(ram_storage(), faults, 0);
+        let error = storage.get_all(Path::new("foo")).await.unwrap_err();
+        assert_eq!(error.kind(), StorageErrorKind::Io);
+        let error = storage
+            .put(Path::new("bar"), Box::new(b"ijkl".to_vec()))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), StorageErrorKind::Io);
+        let bulk_delete_error = storage
+            .bulk_delete(&[Path::new("foo"), Path::new("bar")])
+            .await
+            .unwrap_err();
+        assert!(bulk_delete_error.error.is_some());
+        assert_eq!(bulk_delete_error.unattempted.len(), 2);
+        assert_eq!(storage.num_injected_faults(), 3);
+
+        storage.set_faults(StorageFaults::default());
+        storage.get_all(Path::new("foo")).await.unwrap();
+        assert!(!storage.exists(Path::new("bar")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_fault_injecting_storage_throttling() {
+        let faults = StorageFaults {
+            throttling_rate: 1.0,
+            ..Default::default()
+        };
+        let storage = FaultInjectingStorage::new(ram_storage(), faults, 0);
+        let error = storage.delete(Path::new("foo")).await.unwrap_err();
+        assert_eq!(error.kind(), StorageErrorKind::Service);
+    }
+
+    #[tokio::test]
+    async fn test_fault_injecting_storage_partial_reads() {
+        let faults = StorageFaults {
+            partial_read_rate: 1.0,
+            ..Default::default()
+        };
+        let storage = FaultInjectingStorage::new(ram_storage(), faults, 0);
+        let mut output = Vec::new();
+        let error = storage
+            .copy_to(Path::new("foo"), &mut output)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), StorageErrorKind::Io);
+        assert_eq!(output, b"abcd");
+        storage.get_slice(Path::new("foo"), 0..2).await.unwrap_err();
+        // Partial reads only affect read requests.
+        storage.file_num_bytes(Path::new("foo")).await.unwrap();
+        assert_eq!(storage.num_injected_faults(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fault_injecting_storage_is_deterministic() {
+        let faults = StorageFaults {
+            error_rate: 0.5,
+            ..Default::default()
+        };
+        let mut outcomes = Vec::new();
+        for _ in 0..2 {
+            let storage = FaultInjectingStorage::new(ram_storage(), faults.clone(), 42);
+            let mut storage_outcomes = Vec::new();
+            for _ in 0..32 {
+                storage_outcomes.push(storage.get_all(Path::new("foo")).await.is_ok());
+            }
+            assert!(storage_outcomes.contains(&true));
+            assert!(storage_outcomes.contains(&false));
+            outcomes.push(storage_outcomes);
+        }
+        assert_eq!(outcomes[0], outcomes[1]);
+    }
+
+    #[tokio::test]
+    async fn test_fault_injecting_storage_latency() {
+        tokio::time::pause();
+        let faults = StorageFaults {
+            latency: Duration::from_millis(100),
+            latency_jitter: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let storage = FaultInjectingStorage::new(ram_storage(), faults, 0);
+        let start = tokio::time::Instant::now();
+        storage.get_all(Path::new("foo")).await.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100));
+        assert!(elapsed <= Duration::from_millis(150));
+    }
+
+    #[test]
+    #[should_panic(expected = "The sum of the fault rates must not exceed 1.")]
+    fn test_fault_injecting_storage_invalid_rates() {
+        let faults = StorageFaults {
+            error_rate: 0.6,
+            throttling_rate: 0.6,
+            ..Default::default()
+        };
+        FaultInjectingStorage::new(ram_storage(), faults, 0);
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! io_uring implementation of the local file operations of [`crate::LocalFileStorage`].
+//!
+//! io_uring submissions must be issued from a `tokio-uring` runtime, which cannot be nested
+//! into the regular tokio runtime. The operations are therefore executed on a dedicated thread
+//! running a `tokio-uring` runtime, and their results are sent back through a channel.
+//!
+//! The availability of io_uring is detected once at runtime: when the kernel does not support
+//! it or forbids it (seccomp profiles of some container runtimes, for instance), the local file
+//! storage falls back to the regular `tokio::fs` implementation.
+
+use std::future::Future;
+use std::io::{self, ErrorKind};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use rusoto_core::ByteStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio_uring::buf::IoBuf;
+use tokio_uring::fs::File;
+use tracing::{info, warn};
+
+/// Size of the buffers used to read and copy files.
+const BUFFER_NUM_BYTES: usize = 1 << 20; // 1MiB
+
+type Task = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()>>> + Send>;
+
+struct IoUringExecutor {
+    task_tx: mpsc::UnboundedSender<Task>,
+}
+
+static IO_URING_EXECUTOR: Lazy<Option<IoUringExecutor>> = Lazy::new(start_io_uring_executor);
+
+/// Starts the thread running the `tokio-uring` runtime. Returns `None` if io_uring is not
+/// available on this host.
+fn start_io_uring_executor() -> Option<IoUringExecutor> {
+    let (task_tx, mut task_rx) = mpsc::unbounded_channel::<Task>();
+    let (init_tx, init_rx) = std::sync::mpsc::channel::<io::Result<()>>();
+    let spawn_res = std::thread::Builder::new()
+        .name("quickwit-io-uring".to_string())
+        .spawn(move || {
+            let runtime = match tokio_uring::Runtime::new(&tokio_uring::builder()) {
+                Ok(runtime) => runtime,
+                Err(error) => {
+                    let _ = init_tx.send(Err(error));
+                    return;
+                }
+            };
+            let _ = init_tx.send(Ok(()));
+            runtime.block_on(async move {
+                while let Some(task) = task_rx.recv().await {
+                    tokio_uring::spawn(task());
+                }
+            });
+        });
+    if let Err(error) = spawn_res {
+        warn!(error=?error, "Failed to spawn io_uring thread, falling back to `tokio::fs`.");
+        return None;
+    }
+    match init_rx.recv() {
+        Ok(Ok(())) => {
+            info!("Using io_uring for local file storage operations.");
+            Some(IoUringExecutor { task_tx })
+        }
+        Ok(Err(error)) => {
+            warn!(error=?error, "io_uring is not available, falling back to `tokio::fs`.");
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Returns whether io_uring is available on this host.
+pub(crate) fn is_io_uring_available() -> bool {
+    IO_URING_EXECUTOR.is_some()
+}
+
+/// Runs the future built by `task_fn` on the io_uring thread and returns its output.
+async fn execute<F, Fut, T>(task_fn: F) -> io::Result<T>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = io::Result<T>> + 'static,
+    T: Send + 'static,
+{
+    let executor = IO_URING_EXECUTOR
+        .as_ref()
+        .ok_or_else(|| io::Error::new(ErrorKind::Unsupported, "io_uring is not available."))?;
+    let (result_tx, result_rx) = oneshot::channel();
+    let task: Task = Box::new(move || {
+        Box::pin(async move {
+            let _ = result_tx.send(task_fn().await);
+        })
+    });
+    executor
+        .task_tx
+        .send(task)
+        .map_err(|_| io::Error::new(ErrorKind::Other, "The io_uring thread has stopped."))?;
+    result_rx
+        .await
+        .map_err(|_| io::Error::new(ErrorKind::Other, "The io_uring task was dropped."))?
+}
+
+/// Reads `buffer.capacity()` bytes at most from `file` at offset `pos` into `buffer`, which is
+/// cleared first.
+async fn read_at(file: &File, mut buffer: Vec<u8>, pos: u64) -> (io::Result<usize>, Vec<u8>) {
+    buffer.clear();
+    file.read_at(buffer, pos).await
+}
+
+/// Writes the entire `buffer` into `file` at offset `pos` and gives the buffer back.
+async fn write_all_at<T: IoBuf>(file: &File, mut buffer: T, mut pos: u64) -> (io::Result<()>, T) {
+    let mut num_bytes_written = 0;
+    while num_bytes_written < buffer.bytes_init() {
+        let (write_res, slice) = file.write_at(buffer.slice(num_bytes_written..), pos).await;
+        buffer = slice.into_inner();
+        match write_res {
+            Ok(0) => {
+                let error = io::Error::new(ErrorKind::WriteZero, "Failed to write whole buffer.");
+                return (Err(error), buffer);
+            }
+            Ok(num_bytes) => {
+                num_bytes_written += num_bytes;
+                pos += num_bytes as u64;
+            }
+            Err(error) => return (Err(error), buffer),
+        }
+    }
+    (Ok(()), buffer)
+}
+
+/// Reads the bytes of the file located at `path` within `range`.
+pub(crate) async fn read_slice(path: PathBuf, range: Range<usize>) -> io::Result<Vec<u8>> {
+    execute(move || async move {
+        let file = File::open(&path).await?;
+        let mut content_bytes = Vec::with_capacity(range.len());
+        let mut buffer = Vec::with_capacity(BUFFER_NUM_BYTES.min(range.len()));
+        while content_bytes.len() < range.len() {
+            let pos = (range.start + content_bytes.len()) as u64;
+            let (read_res, read_buffer) = read_at(&file, buffer, pos).await;
+            buffer = read_buffer;
+            let num_bytes = read_res?;
+            if num_bytes == 0 {
+                let _ = file.close().await;
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "Failed to fill whole buffer.",
+                ));
+            }
+            let num_bytes_to_copy = num_bytes.min(range.len() - content_bytes.len());
+            content_bytes.extend_from_slice(&buffer[..num_bytes_to_copy]);
+        }
+        file.close().await?;
+        Ok(content_bytes)
+    })
+    .await
+}
+
+/// Reads the entire file located at `path`.
+pub(crate) async fn read_all(path: PathBuf) -> io::Result<Vec<u8>> {
+    let num_bytes = tokio::fs::metadata(&path).await?.len() as usize;
+    read_slice(path, 0..num_bytes).await
+}
+
+/// Copies the file located at `src_path` to `dst_path` and returns the number of bytes copied.
+pub(crate) async fn copy_file(src_path: PathBuf, dst_path: PathBuf) -> io::Result<u64> {
+    execute(move || async move {
+        let src_file = File::open(&src_path).await?;
+        let dst_file = File::create(&dst_path).await?;
+        let mut buffer = Vec::with_capacity(BUFFER_NUM_BYTES);
+        let mut pos = 0;
+        loop {
+            let (read_res, read_buffer) = read_at(&src_file, buffer, pos).await;
+            let num_bytes = read_res?;
+            if num_bytes == 0 {
+                break;
+            }
+            let (write_res, write_buffer) = write_all_at(&dst_file, read_buffer, pos).await;
+            write_res?;
+            buffer = write_buffer;
+            pos += num_bytes as u64;
+        }
+        src_file.close().await?;
+        dst_file.close().await?;
+        Ok(pos)
+    })
+    .await
+}
+
+/// Writes the bytes of `byte_stream` into a new file located at `path`.
+pub(crate) async fn write_stream(path: PathBuf, byte_stream: ByteStream) -> io::Result<()> {
+    execute(move || async move {
+        let file = File::create(&path).await?;
+        let mut byte_stream = byte_stream;
+        let mut pos = 0;
+        while let Some(chunk_res) = byte_stream.next().await {
+            let chunk = chunk_res?;
+            let chunk_num_bytes = chunk.len() as u64;
+            write_all_at(&file, chunk, pos).await.0?;
+            pos += chunk_num_bytes;
+        }
+        file.close().await?;
+        Ok(())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_io_uring_file_operations() {
+        if !is_io_uring_available() {
+            return;
+        }
+        let tempdir = tempfile::tempdir().unwrap();
+        let src_path = tempdir.path().join("src");
+        let dst_path = tempdir.path().join("dst");
+        let content_bytes: Vec<u8> = (0..3 * BUFFER_NUM_BYTES + 17)
+            .map(|idx| (idx % 251) as u8)
+            .collect();
+
+        let byte_stream = ByteStream::from(content_bytes.clone());
+        write_stream(src_path.clone(), byte_stream).await.unwrap();
+        assert_eq!(read_all(src_path.clone()).await.unwrap(), content_bytes);
+
+        let range = BUFFER_NUM_BYTES - 3..2 * BUFFER_NUM_BYTES + 5;
+        assert_eq!(
+            read_slice(src_path.clone(), range.clone()).await.unwrap(),
+            &content_bytes[range]
+        );
+        let error = read_slice(src_path.clone(), 0..content_bytes.len() + 1)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        let num_bytes = copy_file(src_path, dst_path.clone()).await.unwrap();
+        assert_eq!(num_bytes, content_bytes.len() as u64);
+        assert_eq!(tokio::fs::read(dst_path).await.unwrap(), content_bytes);
+    }
+}
@@ -29,6 +29,7 @@
 //! etc.
 //!
 //! - The `BundleStorage` bundles together multiple files into a single file.
+//! - The `FaultInjectingStorage` injects latency and failures into the requests of another storage.
 mod cache;
 mod debouncer;
 mod metrics;
@@ -42,6 +43,9 @@ pub use self::storage::Storage;
 
 mod bundle_storage;
 mod error;
+mod fault_injecting_storage;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod io_uring;
 mod local_file_storage;
 mod object_storage;
 mod payload;
@@ -57,6 +61,7 @@ pub use self::bundle_storage::{BundleStorage, BundleStorageFileOffsets};
 #[cfg(any(test, feature = "testsuite"))]
 pub use self::cache::MockCache;
 pub use self::cache::{wrap_storage_with_long_term_cache, Cache, MemorySizedCache, QuickwitCache};
+pub use self::fault_injecting_storage::{FaultInjectingStorage, StorageFaults};
 pub use self::local_file_storage::{LocalFileStorage, LocalFileStorageFactory};
 #[cfg(feature = "azure")]
 pub use self::object_storage::{AzureBlobStorage, AzureBlobStorageFactory};
@@ -64,7 +69,7 @@ pub use self::object_storage::{
     MultiPartPolicy, S3CompatibleObjectStorage, S3CompatibleObjectStorageFactory,
 };
 pub use self::ram_storage::{RamStorage, RamStorageBuilder};
-pub use self::split::{SplitPayload, SplitPayloadBuilder};
+pub use self::split::{FilePayload, SplitPayload, SplitPayloadBuilder};
 #[cfg(any(test, feature = "testsuite"))]
 pub use self::storage::MockStorage;
 #[cfg(any(test, feature = "testsuite"))]
@@ -232,6 +237,30 @@ pub(crate) mod test_suite {
         Ok(())
     }
 
+    async fn test_list_files(storage: &mut dyn Storage) -> anyhow::Result<()> {
+        let test_paths = [
+            Path::new("list_files/file"),
+            Path::new("list_files/dir/file"),
+        ];
+        for (payload_len, test_path) in test_paths.iter().enumerate() {
+            storage
+                .put(test_path, Box::new(vec![0u8; payload_len + 1]))
+                .await?;
+        }
+        let files = storage.list_files().await?;
+        for (payload_len, test_path) in test_paths.iter().enumerate() {
+            assert!(files.contains(&(test_path.to_path_buf(), payload_len as u64 + 1)));
+        }
+        for test_path in test_paths {
+            storage.delete(test_path).await?;
+        }
+        let files = storage.list_files().await?;
+        assert!(!files
+            .iter()
+            .any(|(path, _)| path.starts_with(Path::new("list_files"))));
+        Ok(())
+    }
+
     async fn test_delete_missing_file(storage: &mut dyn Storage) -> anyhow::Result<()> {
         let test_path = Path::new("missing_file");
         assert!(!storage.exists(test_path).await.unwrap());
@@ -284,6 +313,7 @@ pub(crate) mod test_suite {
             .await
             .context("write_and_delete_with_separator")?;
         test_file_size(storage).await.context("file_size")?;
+        test_list_files(storage).await.context("list_files")?;
         test_delete_missing_file(storage)
             .await
             .context("delete_missing_file")?;
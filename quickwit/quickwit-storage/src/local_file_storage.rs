@@ -127,6 +127,35 @@ fn delete_all_dirs<'a>(root: &'a Path, path: &'a Path) -> BoxFuture<'a, std::io:
     .boxed()
 }
 
+/// Lists recursively the files located under the `{root}/{path}` directory and appends them along
+/// with their sizes to `files`. The paths are relative to the `{root}` directory.
+fn list_files_recursively<'a>(
+    root: &'a Path,
+    path: PathBuf,
+    files: &'a mut Vec<(PathBuf, u64)>,
+) -> BoxFuture<'a, std::io::Result<()>> {
+    async move {
+        let mut entries = match fs::read_dir(root.join(&path)).await {
+            Ok(entries) => entries,
+            // The storage root directory is created lazily.
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let entry_path = path.join(entry.file_name());
+            let metadata = entry.metadata().await?;
+
+            if metadata.is_dir() {
+                list_files_recursively(root, entry_path, files).await?;
+            } else if metadata.is_file() {
+                files.push((entry_path, metadata.len()));
+            }
+        }
+        Ok(())
+    }
+    .boxed()
+}
+
 #[async_trait]
 impl Storage for LocalFileStorage {
     async fn check_connectivity(&self) -> anyhow::Result<()> {
@@ -147,6 +176,12 @@ impl Storage for LocalFileStorage {
             fs::create_dir_all(parent_dir).await?;
         }
 
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if crate::io_uring::is_io_uring_available() {
+            let byte_stream = payload.byte_stream().await?;
+            crate::io_uring::write_stream(full_path, byte_stream).await?;
+            return Ok(());
+        }
         let mut reader = payload.byte_stream().await?.into_async_read();
         let mut f = tokio::fs::File::create(full_path).await?;
         tokio::io::copy(&mut reader, &mut f).await?;
@@ -161,8 +196,26 @@ impl Storage for LocalFileStorage {
         Ok(())
     }
 
+    async fn copy_to_file(&self, path: &Path, output_path: &Path) -> StorageResult<()> {
+        let full_path = self.root.join(path);
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if crate::io_uring::is_io_uring_available() {
+            crate::io_uring::copy_file(full_path, output_path.to_path_buf()).await?;
+            return Ok(());
+        }
+        let mut file = fs::File::open(&full_path).await?;
+        let mut output_file = fs::File::create(output_path).await?;
+        tokio::io::copy(&mut file, &mut output_file).await?;
+        Ok(())
+    }
+
     async fn get_slice(&self, path: &Path, range: Range<usize>) -> StorageResult<OwnedBytes> {
         let full_path = self.root.join(path);
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if crate::io_uring::is_io_uring_available() {
+            let content_bytes = crate::io_uring::read_slice(full_path, range).await?;
+            return Ok(OwnedBytes::new(content_bytes));
+        }
         let mut file = fs::File::open(full_path).await?;
         file.seek(SeekFrom::Start(range.start as u64)).await?;
         let mut content_bytes: Vec<u8> = vec![0u8; range.len()];
@@ -238,7 +291,15 @@ impl Storage for LocalFileStorage {
 
     async fn get_all(&self, path: &Path) -> StorageResult<OwnedBytes> {
         let full_path = self.root.join(path);
-        let content_bytes = fs::read(full_path).await.map_err(|err| {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        let content_bytes_res = if crate::io_uring::is_io_uring_available() {
+            crate::io_uring::read_all(full_path).await
+        } else {
+            fs::read(full_path).await
+        };
+        #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+        let content_bytes_res = fs::read(full_path).await;
+        let content_bytes = content_bytes_res.map_err(|err| {
             StorageError::from(err).add_context(format!(
                 "Failed to read file {}/{}",
                 self.uri(),
@@ -274,6 +335,12 @@ impl Storage for LocalFileStorage {
             }
         }
     }
+
+    async fn list_files(&self) -> StorageResult<Vec<(PathBuf, u64)>> {
+        let mut files = Vec::new();
+        list_files_recursively(&self.root, PathBuf::new(), &mut files).await?;
+        Ok(files)
+    }
 }
 
 /// A File storage resolver
@@ -431,6 +431,27 @@ impl Storage for AzureBlobStorage {
         }
     }
 
+    async fn list_files(&self) -> StorageResult<Vec<(PathBuf, u64)>> {
+        let mut list_blobs_builder = self.container_client.list_blobs();
+        // Restricts the listing to the blobs located under the storage prefix.
+        if !self.prefix.as_os_str().is_empty() {
+            list_blobs_builder =
+                list_blobs_builder.prefix(format!("{}/", self.prefix.to_string_lossy()));
+        }
+        let mut list_blobs_stream = list_blobs_builder.into_stream();
+        let mut files = Vec::new();
+
+        while let Some(list_blobs_result) = list_blobs_stream.next().await {
+            let list_blobs_response = list_blobs_result.map_err(AzureErrorWrapper::from)?;
+            for blob in list_blobs_response.blobs.blobs {
+                if let Ok(relative_path) = Path::new(&blob.name).strip_prefix(&self.prefix) {
+                    files.push((relative_path.to_path_buf(), blob.properties.content_length));
+                }
+            }
+        }
+        Ok(files)
+    }
+
     fn uri(&self) -> &Uri {
         &self.uri
     }
@@ -735,6 +735,43 @@ impl Storage for S3CompatibleObjectStorage {
         }
     }
 
+    async fn list_files(&self) -> StorageResult<Vec<(PathBuf, u64)>> {
+        // Restricts the listing to the objects located under the storage prefix.
+        let prefix_opt = if self.prefix.as_os_str().is_empty() {
+            None
+        } else {
+            Some(format!("{}/", self.prefix.to_string_lossy()))
+        };
+        let mut files = Vec::new();
+        let mut continuation_token_opt: Option<String> = None;
+        loop {
+            let list_objects_req = ListObjectsV2Request {
+                bucket: self.bucket.clone(),
+                prefix: prefix_opt.clone(),
+                continuation_token: continuation_token_opt.take(),
+                ..Default::default()
+            };
+            let list_objects_output = retry(&self.retry_params, || async {
+                self.s3_client
+                    .list_objects_v2(list_objects_req.clone())
+                    .await
+                    .map_err(RusotoErrorWrapper::from)
+            })
+            .await?;
+            for object in list_objects_output.contents.unwrap_or_default() {
+                if let Some(key) = object.key {
+                    let num_bytes = object.size.unwrap_or(0).max(0) as u64;
+                    files.push((self.relative_path(&key), num_bytes));
+                }
+            }
+            continuation_token_opt = list_objects_output.next_continuation_token;
+            if continuation_token_opt.is_none() {
+                break;
+            }
+        }
+        Ok(files)
+    }
+
     fn uri(&self) -> &Uri {
         &self.uri
     }
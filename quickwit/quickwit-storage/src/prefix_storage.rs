@@ -98,6 +98,20 @@ impl Storage for PrefixStorage {
     async fn file_num_bytes(&self, path: &Path) -> crate::StorageResult<u64> {
         self.storage.file_num_bytes(&self.prefix.join(path)).await
     }
+
+    async fn list_files(&self) -> crate::StorageResult<Vec<(PathBuf, u64)>> {
+        let files = self
+            .storage
+            .list_files()
+            .await?
+            .into_iter()
+            .filter_map(|(path, num_bytes)| {
+                let relative_path = path.strip_prefix(&self.prefix).ok()?.to_path_buf();
+                Some((relative_path, num_bytes))
+            })
+            .collect();
+        Ok(files)
+    }
 }
 
 /// Creates a [`PrefixStorage`] using an underlying storage and a prefix.
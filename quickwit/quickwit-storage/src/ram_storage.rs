@@ -74,11 +74,6 @@ impl RamStorage {
     async fn get_data(&self, path: &Path) -> Option<OwnedBytes> {
         self.files.read().await.get(path).cloned()
     }
-
-    /// Returns the list of files that are present in the RamStorage.
-    pub async fn list_files(&self) -> Vec<PathBuf> {
-        self.files.read().await.keys().cloned().collect()
-    }
 }
 
 #[async_trait]
@@ -148,6 +143,17 @@ impl Storage for RamStorage {
             Err(StorageErrorKind::DoesNotExist.with_error(err))
         }
     }
+
+    async fn list_files(&self) -> StorageResult<Vec<(PathBuf, u64)>> {
+        let files = self
+            .files
+            .read()
+            .await
+            .iter()
+            .map(|(path, file_bytes)| (path.clone(), file_bytes.len() as u64))
+            .collect();
+        Ok(files)
+    }
 }
 
 /// Builder to create a prepopulated [`RamStorage`]. This is mostly useful for tests.
@@ -72,12 +72,25 @@ impl PutPayload for SplitPayload {
     }
 }
 
+/// Payload streaming the content of a local file, so that it does not have to be loaded in
+/// memory to be uploaded.
 #[derive(Clone)]
-struct FilePayload {
+pub struct FilePayload {
     len: u64,
     path: PathBuf,
 }
 
+impl FilePayload {
+    /// Creates a payload holding the whole content of the file at `path`.
+    pub async fn open(path: &Path) -> io::Result<Self> {
+        let len = tokio::fs::metadata(path).await?.len();
+        Ok(Self {
+            len,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
 #[async_trait]
 impl PutPayload for FilePayload {
     fn len(&self) -> u64 {
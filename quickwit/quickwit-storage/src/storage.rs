@@ -123,6 +123,10 @@ pub trait Storage: Send + Sync + 'static {
     /// Returns a file size.
     async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64>;
 
+    /// Lists all the files of the storage along with their sizes. The returned paths are
+    /// relative to the storage root.
+    async fn list_files(&self) -> StorageResult<Vec<(PathBuf, u64)>>;
+
     /// Returns an URI identifying the storage
     fn uri(&self) -> &Uri;
 }
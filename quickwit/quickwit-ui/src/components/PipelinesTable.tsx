@@ -0,0 +1,78 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+import { Button, Paper, Table, TableBody, TableCell, TableContainer, TableHead, TableRow, Tooltip } from "@mui/material";
+import { IndexingPipelineStatus } from "../utils/models";
+
+const PipelinesTable = ({ pipelines, onRestart }: Readonly<{
+  pipelines: IndexingPipelineStatus[],
+  onRestart: (pipeline: IndexingPipelineStatus) => void,
+}>) => {
+  return (
+    <TableContainer component={Paper}>
+      <Table sx={{ minWidth: 650 }} aria-label="Pipelines">
+        <TableHead>
+          <TableRow>
+            <TableCell align="left">Index ID</TableCell>
+            <TableCell align="left">Source ID</TableCell>
+            <TableCell align="left">Node ID</TableCell>
+            <TableCell align="right">Ord</TableCell>
+            <TableCell align="right">Docs</TableCell>
+            <TableCell align="right">Invalid docs</TableCell>
+            <TableCell align="right">Bytes processed</TableCell>
+            <TableCell align="right">Published splits</TableCell>
+            <TableCell align="right">Generation</TableCell>
+            <TableCell align="left">Last error</TableCell>
+            <TableCell align="left"></TableCell>
+          </TableRow>
+        </TableHead>
+        <TableBody>
+          {pipelines.map((pipeline) => (
+            <TableRow
+              key={`${pipeline.index_id}/${pipeline.source_id}/${pipeline.pipeline_ord}`}
+              sx={{ '&:last-child td, &:last-child th': { border: 0 } }}
+            >
+              <TableCell component="th" scope="row">{pipeline.index_id}</TableCell>
+              <TableCell align="left">{pipeline.source_id}</TableCell>
+              <TableCell align="left">{pipeline.node_id}</TableCell>
+              <TableCell align="right">{pipeline.pipeline_ord}</TableCell>
+              <TableCell align="right">{pipeline.statistics.num_docs}</TableCell>
+              <TableCell align="right">{pipeline.statistics.num_invalid_docs}</TableCell>
+              <TableCell align="right">{pipeline.statistics.total_bytes_processed}</TableCell>
+              <TableCell align="right">{pipeline.statistics.num_published_splits}</TableCell>
+              <TableCell align="right">{pipeline.statistics.generation}</TableCell>
+              <TableCell align="left">
+                {pipeline.statistics.last_error &&
+                  <Tooltip title={pipeline.statistics.last_error}>
+                    <span>{pipeline.statistics.last_error.substring(0, 40)}</span>
+                  </Tooltip>
+                }
+              </TableCell>
+              <TableCell align="left">
+                <Button size="small" onClick={() => onRestart(pipeline)}>Restart</Button>
+              </TableCell>
+            </TableRow>
+          ))}
+        </TableBody>
+      </Table>
+    </TableContainer>
+  );
+};
+
+export default PipelinesTable;
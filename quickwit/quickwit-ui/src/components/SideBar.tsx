@@ -31,6 +31,7 @@ import { CodeSSlash } from "@styled-icons/remix-line/CodeSSlash"
 import { GroupWork } from '@styled-icons/material-outlined/GroupWork';
 import { Database } from '@styled-icons/feather/Database';
 import { Settings } from '@styled-icons/feather/Settings';
+import { Activity } from '@styled-icons/feather/Activity';
 import { useLocalStorage } from '../providers/LocalStorageProvider';
 import { toUrlSearchRequestParams } from '../utils/urls';
 import { APP_BAR_HEIGHT_PX } from './LayoutUtils';
@@ -89,6 +90,7 @@ const SideBar = () => {
           <Typography variant="body1">Admin</Typography>
         </ListSubheader>
           <ListItemLink to="/indexes" primary={<Typography variant="body1">Indexes</Typography>} icon={<Database size="18px" />} />
+          <ListItemLink to="/pipelines" primary={<Typography variant="body1">Pipelines</Typography>} icon={<Activity size="18px" />} />
           <ListItemLink to="/cluster" primary={<Typography variant="body1">Cluster</Typography>} icon={<GroupWork size="18px" />} />
         <ListItemLink to="/node-info" primary={<Typography variant="body1">Node info</Typography>} icon={<Settings size="18px" />} />
       </List>
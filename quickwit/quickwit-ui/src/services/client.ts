@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-import { Cluster, Index, IndexMetadata, QuickwitBuildInfo, SearchRequest, SearchResponse, SplitMetadata } from "../utils/models";
+import { Cluster, Index, IndexingPipelineStatus, IndexMetadata, QuickwitBuildInfo, SearchRequest, SearchResponse, SplitMetadata } from "../utils/models";
 import { serializeSortByField } from "../utils/urls";
 
 export class Client {
@@ -52,6 +52,14 @@ export class Client {
     return await this.fetch(`${this.apiRoot()}version`, this.defaultGetRequestParams());
   }
 
+  async indexingPipelines(): Promise<Array<IndexingPipelineStatus>> {
+    return await this.fetch(`${this.apiRoot()}indexing/pipelines`, this.defaultGetRequestParams());
+  }
+
+  async restartIndexingPipeline(indexId: string, sourceId: string, pipelineOrd: number): Promise<void> {
+    return await this.fetch(`${this.apiRoot()}indexing/pipelines/${indexId}/${sourceId}/${pipelineOrd}/restart`, { method: "POST" });
+  }
+
   // eslint-disable-next-line
   async config(): Promise<Record<string, any>> {
     return await this.fetch(`${this.apiRoot()}config`, this.defaultGetRequestParams());
@@ -253,3 +253,25 @@ export type NodeId = {
   grpc_address: string,
   self: boolean,
 }
+
+export type IndexingStatistics = {
+  num_docs: number,
+  num_invalid_docs: number,
+  num_local_splits: number,
+  num_staged_splits: number,
+  num_uploaded_splits: number,
+  num_published_splits: number,
+  total_bytes_processed: number,
+  total_size_splits: number,
+  generation: number,
+  num_spawn_attempts: number,
+  last_error: string | null,
+}
+
+export type IndexingPipelineStatus = {
+  index_id: string,
+  source_id: string,
+  node_id: string,
+  pipeline_ord: number,
+  statistics: IndexingStatistics,
+}
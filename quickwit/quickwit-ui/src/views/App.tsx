@@ -29,6 +29,7 @@ import { FullBoxContainer } from '../components/LayoutUtils';
 import { LocalStorageProvider } from '../providers/LocalStorageProvider';
 import ClusterView from './ClusterView';
 import NodeInfoView from './NodeInfoView';
+import PipelinesView from './PipelinesView';
 
 function App() {
   return (
@@ -43,6 +44,7 @@ function App() {
             <Route path="search" element={<SearchView />} />
             <Route path="indexes" element={<IndexesView />} />
             <Route path="indexes/:indexId" element={<IndexView />} />
+            <Route path="pipelines" element={<PipelinesView />} />
             <Route path="cluster" element={<ClusterView />} />
             <Route path="node-info" element={<NodeInfoView />} />
           </Routes>
@@ -0,0 +1,78 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+import { render, unmountComponentAtNode } from "react-dom";
+import { waitFor } from "@testing-library/react";
+import { screen } from '@testing-library/dom';
+import PipelinesView from './PipelinesView';
+import { act } from "react-dom/test-utils";
+import { Client } from "../services/client";
+
+jest.mock('../services/client');
+const mockedUsedNavigate = jest.fn();
+jest.mock('react-router-dom', () => ({
+  ...jest.requireActual('react-router-dom'),
+  useNavigate: () => mockedUsedNavigate,
+}));
+
+let container = null;
+beforeEach(() => {
+  // setup a DOM element as a render target
+  container = document.createElement("div");
+  document.body.appendChild(container);
+});
+
+afterEach(() => {
+  // cleanup on exiting
+  unmountComponentAtNode(container);
+  container.remove();
+  container = null;
+});
+
+test('renders PipelinesView', async () => {
+  const pipelines = [
+    {
+      "index_id": "otel-logs",
+      "source_id": "kafka-source",
+      "node_id": "node-green-uCdq",
+      "pipeline_ord": 0,
+      "statistics": {
+        "num_docs": 1234,
+        "num_invalid_docs": 2,
+        "num_local_splits": 1,
+        "num_staged_splits": 1,
+        "num_uploaded_splits": 1,
+        "num_published_splits": 1,
+        "total_bytes_processed": 56789,
+        "total_size_splits": 4096,
+        "generation": 1,
+        "num_spawn_attempts": 1,
+        "last_error": null
+      }
+    }
+  ];
+  Client.prototype.indexingPipelines.mockImplementation(() => Promise.resolve(pipelines));
+
+  await act(async () => {
+    render(<PipelinesView />, container);
+  });
+
+  await waitFor(() => expect(screen.getByText(/kafka-source/)).toBeInTheDocument());
+  expect(screen.getByText(/56789/)).toBeInTheDocument();
+});
@@ -0,0 +1,95 @@
+// Copyright (C) 2022 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+import { Box, Typography } from '@mui/material';
+import { useCallback, useEffect, useMemo, useState } from 'react';
+import ApiUrlFooter from '../components/ApiUrlFooter';
+import { ViewUnderAppBarBox, FullBoxContainer, QBreadcrumbs } from '../components/LayoutUtils';
+import Loader from '../components/Loader';
+import PipelinesTable from '../components/PipelinesTable';
+import ErrorResponseDisplay from '../components/ResponseErrorDisplay';
+import { Client } from '../services/client';
+import { IndexingPipelineStatus, ResponseError } from '../utils/models';
+
+// Pipeline statistics are refreshed at this interval.
+const REFRESH_INTERVAL_MS = 5000;
+
+function PipelinesView() {
+  const [loading, setLoading] = useState(false);
+  const [pipelines, setPipelines] = useState<null | IndexingPipelineStatus[]>(null);
+  const [responseError, setResponseError] = useState<ResponseError | null>(null);
+  const quickwitClient = useMemo(() => new Client(), []);
+
+  const fetchPipelines = useCallback(() => {
+    quickwitClient.indexingPipelines().then(
+      (pipelines) => {
+        setResponseError(null);
+        setLoading(false);
+        setPipelines(pipelines);
+      },
+      (error) => {
+        setLoading(false);
+        setResponseError(error);
+      }
+    );
+  }, [quickwitClient]);
+
+  useEffect(() => {
+    setLoading(true);
+    fetchPipelines();
+    const interval = setInterval(fetchPipelines, REFRESH_INTERVAL_MS);
+    return () => clearInterval(interval);
+  }, [fetchPipelines]);
+
+  const restartPipeline = (pipeline: IndexingPipelineStatus) => {
+    quickwitClient.restartIndexingPipeline(pipeline.index_id, pipeline.source_id, pipeline.pipeline_ord).then(
+      () => fetchPipelines(),
+      (error) => setResponseError(error)
+    );
+  }
+
+  const renderResult = () => {
+    if (responseError !== null) {
+      return ErrorResponseDisplay(responseError);
+    }
+    if (loading || pipelines == null) {
+      return <Loader />;
+    }
+    if (pipelines.length === 0) {
+      return <Box>There are no indexing pipelines running on this node.</Box>
+    }
+    return <PipelinesTable pipelines={pipelines} onRestart={restartPipeline} />
+  }
+
+  return (
+    <ViewUnderAppBarBox>
+      <FullBoxContainer>
+        <QBreadcrumbs aria-label="breadcrumb">
+          <Typography color="text.primary">Pipelines</Typography>
+        </QBreadcrumbs>
+        <FullBoxContainer sx={{ px: 0 }}>
+          { renderResult() }
+        </FullBoxContainer>
+      </FullBoxContainer>
+      { ApiUrlFooter('api/v1/indexing/pipelines') }
+    </ViewUnderAppBarBox>
+  );
+}
+
+export default PipelinesView;